@@ -1,26 +1,68 @@
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::{
-    gio,
+    gdk, gio,
     glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
 };
 
+use std::{io::Write, time::Instant};
+
 use crate::{
+    command_line::{self, CommandLineArgs},
     config::{APP_ID, PKGDATADIR, PROFILE, VERSION},
+    core::{
+        format_latency, note_uri_heading, BackgroundAction, BackgroundMode, DueReminders, Journal,
+        JournalEvent, StartupMilestone, StartupTimeline,
+    },
+    dbus_service::{self, FoundNote, MethodError, NotesBackend},
+    debug_window::DebugWindow,
+    error_log_dialog::ErrorLogDialog,
+    model::{ErrorEntry, ErrorLog, Note, NoteId},
+    preferences_window::PreferencesWindow,
+    quick_capture::{Capture, QuickCapture},
+    session::{ReadOnly, Session},
+    spawn,
+    utils::{self, background, palette::Palette},
     window::Window,
 };
 
+/// How often to check if a reminder of a note is due
+const REMINDER_CHECK_INTERVAL_SECS: u32 = 60;
+/// Actions that need the notes, so they can't run while the notebook is prepared
+const SESSION_ACTIONS: [&str; 7] = [
+    "lock",
+    "preferences",
+    "quick-capture",
+    "undo-merge",
+    "undo-delete-tag",
+    "undo-link-update",
+    "show-links-to-check",
+];
+
 mod imp {
     use super::*;
     use glib::WeakRef;
     use once_cell::unsync::OnceCell;
+    use std::cell::{Cell, RefCell};
 
     #[derive(Debug)]
     pub struct Application {
         pub window: OnceCell<WeakRef<Window>>,
         pub settings: gio::Settings,
+        /// Session loaded without the main window, to save quick captures
+        pub headless_session: RefCell<Option<Session>>,
+        pub due_reminders: RefCell<DueReminders<NoteId>>,
+        pub reminder_source_id: RefCell<Option<glib::SourceId>>,
+        pub error_log: ErrorLog,
+        pub journal: Journal,
+        /// Whether the debug mode is enabled with `--debug` until the app quits
+        pub is_debug_forced: Cell<bool>,
+        pub background_mode: RefCell<BackgroundMode>,
+        /// Whether the app is held, so it keeps running while the main window is hidden
+        pub is_held_in_background: Cell<bool>,
+        pub startup_timeline: RefCell<StartupTimeline>,
     }
 
     #[glib::object_subclass]
@@ -33,6 +75,15 @@ mod imp {
             Self {
                 window: OnceCell::new(),
                 settings: gio::Settings::new(APP_ID),
+                headless_session: RefCell::default(),
+                due_reminders: RefCell::default(),
+                reminder_source_id: RefCell::default(),
+                error_log: ErrorLog::new(),
+                journal: Journal::new(),
+                is_debug_forced: Cell::default(),
+                background_mode: RefCell::default(),
+                is_held_in_background: Cell::default(),
+                startup_timeline: RefCell::new(StartupTimeline::new(Instant::now())),
             }
         }
     }
@@ -45,18 +96,48 @@ mod imp {
 
             if let Some(window) = self.window.get() {
                 let window = window.upgrade().unwrap();
+                // It may be hidden while the app runs in the background
+                self.background_mode.borrow_mut().activate();
                 window.present();
                 return;
             }
 
-            let window = Window::new(obj);
-            self.window
-                .set(window.downgrade())
-                .expect("Window already set.");
-
+            obj.create_main_window();
             obj.main_window().present();
         }
 
+        fn handle_local_options(&self, obj: &Self::Type, options: &glib::VariantDict) -> i32 {
+            if options.contains(command_line::VERSION_OPTION) {
+                if let Err(err) = writeln!(std::io::stdout(), "Noteworthy {}", VERSION) {
+                    log::warn!("Failed to write version: {:?}", err);
+                }
+                return 0;
+            }
+
+            self.parent_handle_local_options(obj, options)
+        }
+
+        fn command_line(
+            &self,
+            obj: &Self::Type,
+            command_line: &gio::ApplicationCommandLine,
+        ) -> i32 {
+            let args = CommandLineArgs::from_options(&command_line.options_dict());
+            log::info!("Handling command line `{:?}`", args);
+
+            obj.handle_command_line_args(args, command_line);
+
+            0
+        }
+
+        fn open(&self, obj: &Self::Type, files: &[gio::File], _hint: &str) {
+            obj.activate();
+
+            for file in files {
+                obj.open_note_uri(&file.uri());
+            }
+        }
+
         fn startup(&self, obj: &Self::Type) {
             self.parent_startup(obj);
 
@@ -64,6 +145,11 @@ mod imp {
 
             obj.setup_gactions();
             obj.setup_accels();
+            obj.setup_journal();
+            obj.setup_edit_profiler();
+            obj.setup_palette();
+            obj.setup_dbus_service();
+            obj.setup_background_mode();
         }
     }
 
@@ -79,12 +165,20 @@ glib::wrapper! {
 
 impl Application {
     pub fn new() -> Self {
-        glib::Object::new(&[
+        let app: Self = glib::Object::new(&[
             ("application-id", &Some(APP_ID)),
-            ("flags", &gio::ApplicationFlags::empty()),
+            (
+                "flags",
+                &(gio::ApplicationFlags::HANDLES_COMMAND_LINE
+                    | gio::ApplicationFlags::HANDLES_OPEN),
+            ),
             ("resource-base-path", &Some("/io/github/seadve/Noteworthy/")),
         ])
-        .expect("Application initialization failed...")
+        .expect("Application initialization failed...");
+
+        CommandLineArgs::add_main_options(&app);
+
+        app
     }
 
     pub fn run(&self) {
@@ -103,6 +197,601 @@ impl Application {
         self.imp().window.get().unwrap().upgrade().unwrap()
     }
 
+    /// Errors of syncing and saving that are shown to the user
+    pub fn error_log(&self) -> ErrorLog {
+        self.imp().error_log.clone()
+    }
+
+    /// Recent events of the notes and syncs, shown in the debug information window
+    pub fn journal(&self) -> Journal {
+        self.imp().journal.clone()
+    }
+
+    /// Log when `milestone` of the cold start is first reached, and all of them once they are
+    pub fn reach_startup_milestone(&self, milestone: StartupMilestone) {
+        let mut timeline = self.imp().startup_timeline.borrow_mut();
+
+        if let Some(elapsed) = timeline.reach(milestone, Instant::now()) {
+            log::info!(
+                "Startup: {} after {}",
+                milestone.name(),
+                format_latency(elapsed)
+            );
+
+            if timeline.is_complete() {
+                log::info!("Startup timeline: {}", timeline.to_line());
+            }
+        }
+    }
+
+    /// Whether the debug information window can be shown and the journal is written to a file,
+    /// from the setting or `--debug`
+    pub fn is_debug_mode(&self) -> bool {
+        self.imp().is_debug_forced.get() || self.settings().boolean("debug-mode")
+    }
+
+    /// The session of the main window, or the one loaded without it when only a quick
+    /// capture is being saved
+    pub fn session(&self) -> Session {
+        if let Some(window) = self.window().filter(Window::has_session) {
+            return window.session().clone();
+        }
+
+        self.imp()
+            .headless_session
+            .borrow()
+            .clone()
+            .expect("No session is loaded")
+    }
+
+    fn window(&self) -> Option<Window> {
+        self.imp().window.get().and_then(|window| window.upgrade())
+    }
+
+    fn create_main_window(&self) {
+        let window = Window::new(self);
+        self.imp()
+            .window
+            .set(window.downgrade())
+            .expect("Window already set.");
+
+        window.run_with_session(clone!(@weak self as obj => move |_| {
+            obj.start_reminder_scheduler();
+        }));
+    }
+
+    /// Enable or disable the actions that need the notes, like while the notebook is cloned
+    pub fn set_session_actions_enabled(&self, is_enabled: bool) {
+        for name in SESSION_ACTIONS {
+            match self
+                .lookup_action(name)
+                .and_then(|action| action.downcast::<gio::SimpleAction>().ok())
+            {
+                Some(action) => action.set_enabled(is_enabled),
+                None => log::warn!("App action `{}` does not exist", name),
+            }
+        }
+    }
+
+    /// This is run in the primary instance, even when the arguments are given to another one.
+    fn handle_command_line_args(
+        &self,
+        args: CommandLineArgs,
+        command_line: &gio::ApplicationCommandLine,
+    ) {
+        if args.is_quit {
+            if self.imp().window.get().is_some() {
+                self.quit_really();
+            } else {
+                self.quit();
+            }
+            return;
+        }
+
+        if args.is_debug && !self.imp().is_debug_forced.get() {
+            self.imp().is_debug_forced.set(true);
+            self.update_debug_mode();
+        }
+
+        if args.is_quick_capture {
+            self.activate_action("quick-capture", None);
+            return;
+        }
+
+        if args.is_background && self.window().is_none() && self.start_in_background() {
+            return;
+        }
+
+        self.activate();
+
+        let window = self.main_window();
+
+        if let Some(title) = args.new_note_title {
+            window.run_with_session(move |session| {
+                session.create_note_with_title(&title);
+            });
+        }
+
+        // Links to notes, like from a browser, go through `open` as they do when the app is
+        // asked to open them over D-Bus
+        let (note_uris, files): (Vec<_>, Vec<_>) = args
+            .files
+            .iter()
+            .map(|arg| command_line.create_file_for_arg(arg))
+            .partition(|file| NoteId::from_uri(&file.uri()).is_some());
+
+        if !note_uris.is_empty() {
+            self.open(&note_uris, "");
+        }
+
+        for file in files {
+            window.run_with_session(move |session| {
+                spawn!(clone!(@weak session => async move {
+                    if let Err(err) = session.open_file(&file).await {
+                        log::error!("Failed to open `{}`: {:?}", file.uri(), err);
+                    }
+                }));
+            });
+        }
+    }
+
+    /// Create the main window without showing it, as when started on login. Returns whether it
+    /// is kept hidden, which it is not when the app may not run in the background or the notes
+    /// are not set up yet.
+    fn start_in_background(&self) -> bool {
+        if !utils::default_notes_dir().exists() {
+            return false;
+        }
+
+        let action = self
+            .imp()
+            .background_mode
+            .borrow_mut()
+            .start_in_background();
+        if action == BackgroundAction::PresentWindow {
+            return false;
+        }
+
+        log::info!("Starting in the background");
+        self.create_main_window();
+        true
+    }
+
+    /// Select the note at `uri`, like `noteworthy://note/Note`, once the notes are loaded, and
+    /// scroll to its heading in `noteworthy://note/Note#Heading`
+    fn open_note_uri(&self, uri: &str) {
+        let note_id = match NoteId::from_uri(uri) {
+            Some(note_id) => note_id,
+            None => {
+                log::warn!("Cannot open `{}`, which is not a link to a note", uri);
+                return;
+            }
+        };
+
+        let heading = note_uri_heading(uri);
+
+        let window = self.main_window();
+        window.run_with_session(clone!(@weak window => move |session| {
+            match session.note_manager().note_list().find_by_id(&note_id) {
+                Some(note) => match heading {
+                    Some(ref heading) => session.select_note_heading(note, heading),
+                    None => session.set_selected_note(Some(note)),
+                },
+                None => {
+                    log::warn!("Cannot find linked note `{}`", note_id);
+                    let toast = adw::Toast::new(&gettext("The linked note no longer exists"));
+                    window.add_toast(&toast);
+                }
+            }
+        }));
+    }
+
+    fn show_quick_capture(&self) {
+        let quick_capture = QuickCapture::new();
+        quick_capture.set_application(Some(self));
+        quick_capture.set_transient_for(self.window().as_ref());
+
+        quick_capture.connect_captured(clone!(@weak self as obj => move |quick_capture| {
+            obj.save_capture(quick_capture.capture());
+        }));
+
+        quick_capture.present();
+    }
+
+    fn save_capture(&self, capture: Capture) {
+        // The notes have to be set up in the main window first
+        if self.window().is_none() && !utils::default_notes_dir().exists() {
+            self.activate();
+        }
+
+        if let Some(window) = self.window() {
+            window.run_with_session(clone!(@weak window => move |session| {
+                let res = session.note_manager().create_note_with_content(
+                    &capture.title,
+                    &capture.content,
+                    &capture.tag_names,
+                );
+
+                match res {
+                    Ok(_) => window.add_toast(&adw::Toast::new(&gettext("Note captured"))),
+                    Err(err) => {
+                        if !session.report_read_only(&err) {
+                            log::error!("Failed to save quick capture: {:?}", err);
+                        }
+                    }
+                }
+            }));
+            return;
+        }
+
+        // Keep running until the note is committed, even without any window open
+        let hold_guard = self.hold();
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.save_capture_headless(&capture).await {
+                log::error!("Failed to save quick capture: {:?}", err);
+            }
+            obj.imp().headless_session.replace(None);
+            drop(hold_guard);
+        }));
+    }
+
+    /// Load the notes without presenting the main window, then only save and commit the
+    /// captured note. The other changes are left for the next sync, which also pushes it.
+    async fn save_capture_headless(&self, capture: &Capture) -> anyhow::Result<()> {
+        let notes_folder = gio::File::for_path(&utils::default_notes_dir());
+        let session = Session::for_existing(&notes_folder);
+        self.imp().headless_session.replace(Some(session.clone()));
+
+        session.load().await?;
+
+        let note_manager = session.note_manager();
+        let note = note_manager.create_note_with_content(
+            &capture.title,
+            &capture.content,
+            &capture.tag_names,
+        )?;
+
+        note_manager.commit_note(&note).await?;
+
+        Ok(())
+    }
+
+    /// Check for due reminders now, which includes the ones that were missed while the app
+    /// was closed, then every [`REMINDER_CHECK_INTERVAL_SECS`]
+    fn start_reminder_scheduler(&self) {
+        let imp = self.imp();
+
+        if imp.reminder_source_id.borrow().is_some() {
+            return;
+        }
+
+        self.notify_due_reminders();
+
+        let source_id = glib::timeout_add_seconds_local(
+            REMINDER_CHECK_INTERVAL_SECS,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                obj.notify_due_reminders();
+                glib::Continue(true)
+            }),
+        );
+        imp.reminder_source_id.replace(Some(source_id));
+    }
+
+    fn notify_due_reminders(&self) {
+        let note_list = self.session().note_manager().note_list();
+
+        let reminders = note_list
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+            .filter_map(|note| {
+                let reminder = note.metadata().reminder()?;
+                Some((note.id(), reminder))
+            });
+        let due_note_ids = self
+            .imp()
+            .due_reminders
+            .borrow_mut()
+            .take_due(reminders, chrono::Utc::now());
+
+        for note_id in &due_note_ids {
+            if let Some(note) = note_list.find_by_id(note_id) {
+                self.send_reminder_notification(&note);
+            }
+        }
+    }
+
+    fn send_reminder_notification(&self, note: &Note) {
+        let uri = note.file().uri();
+        let target = uri.as_str().to_variant();
+
+        // The title would show what the hidden notes are about
+        let title = note.metadata().title();
+        let body = if self.session().is_locked() {
+            gettext("A note has a reminder")
+        } else if title.is_empty() {
+            gettext("Untitled Note")
+        } else {
+            title
+        };
+
+        let notification = gio::Notification::new(&gettext("Reminder"));
+        notification.set_body(Some(&body));
+        notification.set_default_action_and_target_value("app.open-reminder", Some(&target));
+        notification.add_button_with_target_value(
+            &gettext("Dismiss"),
+            "app.dismiss-reminder",
+            Some(&target),
+        );
+
+        log::info!("Sending reminder for `{}`", uri);
+        self.send_notification(Some(&format!("reminder-{}", uri)), &notification);
+    }
+
+    /// Clear the reminder of the note at `uri`, as it is either opened or dismissed from the
+    /// notification. This also marks the note as unsaved.
+    fn complete_reminder(&self, uri: String, is_open_note: bool) {
+        // The app may have been started only to activate the notification
+        self.activate();
+
+        self.main_window().run_with_session(move |session| {
+            let file = gio::File::for_uri(&uri);
+
+            if let Some(note) = session.note_manager().note_for_file(&file) {
+                note.metadata().set_reminder(None);
+
+                if is_open_note {
+                    session.set_selected_note(Some(note));
+                }
+            } else {
+                log::warn!("Cannot find note with reminder at `{}`", uri);
+            }
+        });
+    }
+
+    fn show_error_log_dialog(&self) {
+        let dialog = ErrorLogDialog::new(&self.error_log());
+        dialog.set_transient_for(self.window().as_ref());
+        dialog.present();
+    }
+
+    fn show_debug_window(&self) {
+        let window = DebugWindow::new(&self.journal());
+        window.set_transient_for(self.window().as_ref());
+        window.present();
+    }
+
+    /// Record the errors of the error log in the journal too, and follow the debug mode
+    fn setup_journal(&self) {
+        let journal = self.journal();
+
+        self.error_log().connect_items_changed(
+            clone!(@weak journal => move |error_log, position, _, added| {
+                // Only the newest error is added at once
+                if added == 0 {
+                    return;
+                }
+
+                let entry = error_log
+                    .item(position + added - 1)
+                    .and_then(|item| item.downcast::<ErrorEntry>().ok());
+
+                if let Some(entry) = entry {
+                    journal.record(JournalEvent::Error {
+                        message: entry.message(),
+                        detail: entry.detail(),
+                    });
+                }
+            }),
+        );
+
+        self.settings().connect_changed(
+            Some("debug-mode"),
+            clone!(@weak self as obj => move |_, _| {
+                obj.update_debug_mode();
+            }),
+        );
+
+        self.update_debug_mode();
+    }
+
+    fn setup_edit_profiler(&self) {
+        self.settings().connect_changed(
+            Some("profile-editing"),
+            clone!(@weak self as obj => move |_, _| {
+                obj.update_edit_profiler();
+            }),
+        );
+
+        self.update_edit_profiler();
+    }
+
+    /// Apply the palette of the settings to the widgets, following the style as it changes
+    fn setup_palette(&self) {
+        let provider = gtk::CssProvider::new();
+        match gdk::Display::default() {
+            Some(display) => gtk::StyleContext::add_provider_for_display(
+                &display,
+                &provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            ),
+            None => log::warn!("No display to apply the palette to"),
+        }
+
+        self.settings().connect_changed(
+            Some("color-palette"),
+            clone!(@weak provider => move |_, _| update_palette(&provider)),
+        );
+
+        let style_manager = adw::StyleManager::default();
+        style_manager
+            .connect_dark_notify(clone!(@weak provider => move |_| update_palette(&provider)));
+        style_manager.connect_high_contrast_notify(
+            clone!(@weak provider => move |_| update_palette(&provider)),
+        );
+
+        update_palette(&provider);
+    }
+
+    fn update_edit_profiler(&self) {
+        let is_forced =
+            utils::is_profile_env_value(std::env::var(utils::PROFILE_ENV_VAR).ok().as_deref());
+        utils::EditProfiler::default()
+            .set_is_enabled(is_forced || self.settings().boolean("profile-editing"));
+    }
+
+    fn update_debug_mode(&self) {
+        let is_debug_mode = self.is_debug_mode();
+
+        let file_path = is_debug_mode.then(|| {
+            let mut path = glib::user_cache_dir();
+            path.push("noteworthy");
+            path.push("journal.log");
+            path
+        });
+        log::info!("Journal file set to `{:?}`", file_path);
+        self.journal().set_file_path(file_path);
+
+        if let Some(action) = self.lookup_action("show-debug-info") {
+            action
+                .downcast::<gio::SimpleAction>()
+                .unwrap()
+                .set_enabled(is_debug_mode);
+        }
+    }
+
+    /// Follow the settings to run in the background and start on login, asking if the app may
+    fn setup_background_mode(&self) {
+        let settings = self.settings();
+
+        settings.connect_changed(
+            Some("run-in-background"),
+            clone!(@weak self as obj => move |settings, key| {
+                let is_enabled = settings.boolean(key);
+                let was_active = obj.imp().background_mode.borrow().is_active();
+
+                let action = obj.imp().background_mode.borrow_mut().set_enabled(is_enabled);
+                obj.handle_background_action(action);
+
+                if was_active && !is_enabled && settings.boolean("autostart") {
+                    spawn!(async move {
+                        if let Err(err) = background::remove_autostart().await {
+                            log::error!("Failed to remove autostart: {:?}", err);
+                        }
+                    });
+                }
+            }),
+        );
+        settings.connect_changed(
+            Some("autostart"),
+            clone!(@weak self as obj => move |_, _| {
+                let action = obj.imp().background_mode.borrow_mut().autostart_changed();
+                obj.handle_background_action(action);
+            }),
+        );
+
+        let action = self
+            .imp()
+            .background_mode
+            .borrow_mut()
+            .set_enabled(settings.boolean("run-in-background"));
+        self.handle_background_action(action);
+    }
+
+    fn handle_background_action(&self, action: BackgroundAction) {
+        match action {
+            BackgroundAction::RequestPermission => {
+                let is_autostart = self.settings().boolean("autostart");
+                spawn!(clone!(@weak self as obj => async move {
+                    let is_granted = match background::request_background(is_autostart).await {
+                        Ok(is_granted) => is_granted,
+                        Err(err) => {
+                            log::error!("Failed to request running in the background: {:?}", err);
+                            false
+                        }
+                    };
+                    log::info!("Running in the background allowed: {}", is_granted);
+
+                    let action =
+                        obj.imp().background_mode.borrow_mut().permission_answered(is_granted);
+                    obj.handle_background_action(action);
+                }));
+            }
+            BackgroundAction::Refuse { is_window_hidden } => {
+                if let Err(err) = self.settings().set_boolean("run-in-background", false) {
+                    log::error!("Failed to turn off running in the background: {:?}", err);
+                }
+
+                if let Some(window) = self.window() {
+                    if is_window_hidden {
+                        window.present();
+                    }
+                    window.add_toast(&adw::Toast::new(&gettext(
+                        "Noteworthy is not allowed to run in the background",
+                    )));
+                }
+            }
+            BackgroundAction::PresentWindow => {
+                if let Some(window) = self.window() {
+                    window.present();
+                }
+            }
+            // Closing and quitting are handled where the main window is closed
+            BackgroundAction::None | BackgroundAction::HideWindow | BackgroundAction::Quit => {}
+        }
+
+        self.update_background_hold();
+    }
+
+    /// Hold the app while it runs in the background, so it is not quit once the main window is
+    /// hidden, and it can be quit from the main menu instead
+    fn update_background_hold(&self) {
+        let imp = self.imp();
+        let is_active = imp.background_mode.borrow().is_active();
+
+        if let Some(action) = self.lookup_action("quit-really") {
+            action
+                .downcast::<gio::SimpleAction>()
+                .unwrap()
+                .set_enabled(is_active);
+        }
+
+        if imp.is_held_in_background.replace(is_active) == is_active {
+            return;
+        }
+
+        if is_active {
+            self.hold();
+        } else {
+            self.release();
+        }
+    }
+
+    /// Whether the main window that is closed only has to be hidden, as the app runs in the
+    /// background
+    pub fn hides_main_window_on_close(&self) -> bool {
+        let action = self.imp().background_mode.borrow_mut().close_window();
+        action == BackgroundAction::HideWindow
+    }
+
+    /// Save the notes and quit, even while the app runs in the background
+    fn quit_really(&self) {
+        self.imp().background_mode.borrow_mut().quit_really();
+        self.update_background_hold();
+        self.activate_action("quit", None);
+    }
+
+    fn show_preferences_window(&self) {
+        let main_window = self.main_window();
+        main_window.run_with_session(clone!(@weak main_window => move |session| {
+            let note_manager = session.note_manager();
+            let window =
+                PreferencesWindow::new(&note_manager.notebook_settings(), &note_manager.repository());
+            window.set_transient_for(Some(&main_window));
+            window.present();
+        }));
+    }
+
     fn show_about_dialog(&self) {
         let dialog = gtk::AboutDialog::builder()
             .transient_for(&self.main_window())
@@ -125,21 +814,211 @@ impl Application {
     fn setup_gactions(&self) {
         let action_quit = gio::SimpleAction::new("quit", None);
         action_quit.connect_activate(clone!(@weak self as obj => move |_, _| {
-            // This is needed to trigger the delete event and saving the window state
-            obj.main_window().close();
-            obj.quit();
+            // This is needed to trigger the delete event and saving the window state, and the
+            // app can only quit once the notes are saved and the window is gone
+            let window = obj.main_window();
+            window.connect_destroy(clone!(@weak obj => move |_| obj.quit()));
+            // It is never shown when started in the background, and can't be closed unless it
+            // is realized
+            if !window.is_realized() {
+                window.realize();
+            }
+            window.close();
         }));
         self.add_action(&action_quit);
 
+        let action_quit_really = gio::SimpleAction::new("quit-really", None);
+        action_quit_really.set_enabled(false);
+        action_quit_really.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.quit_really();
+        }));
+        self.add_action(&action_quit_really);
+
         let action_about = gio::SimpleAction::new("about", None);
         action_about.connect_activate(clone!(@weak self as obj => move |_, _| {
             obj.show_about_dialog();
         }));
         self.add_action(&action_about);
+
+        let action_lock = gio::SimpleAction::new("lock", None);
+        action_lock.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.main_window().run_with_session(|session| session.lock());
+        }));
+        self.add_action(&action_lock);
+
+        let action_preferences = gio::SimpleAction::new("preferences", None);
+        action_preferences.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.show_preferences_window();
+        }));
+        self.add_action(&action_preferences);
+
+        let action_show_error_log = gio::SimpleAction::new("show-error-log", None);
+        action_show_error_log.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.show_error_log_dialog();
+        }));
+        self.add_action(&action_show_error_log);
+
+        let action_show_debug_info = gio::SimpleAction::new("show-debug-info", None);
+        action_show_debug_info.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.show_debug_window();
+        }));
+        self.add_action(&action_show_debug_info);
+
+        let action_quick_capture = gio::SimpleAction::new("quick-capture", None);
+        action_quick_capture.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.show_quick_capture();
+        }));
+        self.add_action(&action_quick_capture);
+
+        let action_undo_merge = gio::SimpleAction::new("undo-merge", None);
+        action_undo_merge.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.main_window().run_with_session(|session| session.undo_merge());
+        }));
+        self.add_action(&action_undo_merge);
+
+        let action_undo_delete_tag = gio::SimpleAction::new("undo-delete-tag", None);
+        action_undo_delete_tag.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.main_window()
+                .run_with_session(|session| session.undo_delete_tag());
+        }));
+        self.add_action(&action_undo_delete_tag);
+
+        let action_undo_link_update = gio::SimpleAction::new("undo-link-update", None);
+        action_undo_link_update.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.main_window()
+                .run_with_session(|session| session.undo_link_update());
+        }));
+        self.add_action(&action_undo_link_update);
+
+        let action_show_links_to_check = gio::SimpleAction::new("show-links-to-check", None);
+        action_show_links_to_check.connect_activate(clone!(@weak self as obj => move |_, _| {
+            obj.main_window()
+                .run_with_session(|session| session.show_attention_dialog());
+        }));
+        self.add_action(&action_show_links_to_check);
+
+        let action_open_uri = gio::SimpleAction::new("open-uri", Some(glib::VariantTy::STRING));
+        action_open_uri.connect_activate(|_, param| {
+            let uri = param.unwrap().get::<String>().unwrap();
+            if let Err(err) =
+                gio::AppInfo::launch_default_for_uri(&uri, gio::AppLaunchContext::NONE)
+            {
+                log::error!("Failed to open `{}`: {:?}", uri, err);
+            }
+        });
+        self.add_action(&action_open_uri);
+
+        let action_open_reminder =
+            gio::SimpleAction::new("open-reminder", Some(glib::VariantTy::STRING));
+        action_open_reminder.connect_activate(clone!(@weak self as obj => move |_, param| {
+            let uri = param.unwrap().get::<String>().unwrap();
+            obj.complete_reminder(uri, true);
+        }));
+        self.add_action(&action_open_reminder);
+
+        let action_dismiss_reminder =
+            gio::SimpleAction::new("dismiss-reminder", Some(glib::VariantTy::STRING));
+        action_dismiss_reminder.connect_activate(clone!(@weak self as obj => move |_, param| {
+            let uri = param.unwrap().get::<String>().unwrap();
+            obj.complete_reminder(uri, false);
+        }));
+        self.add_action(&action_dismiss_reminder);
+    }
+
+    /// Export the interface for scripts, which is only done in the primary instance
+    fn setup_dbus_service(&self) {
+        let (connection, object_path) = match (self.dbus_connection(), self.dbus_object_path()) {
+            (Some(connection), Some(object_path)) => (connection, object_path),
+            _ => return,
+        };
+
+        match dbus_service::register(&connection, &object_path) {
+            Ok(_) => log::info!("Exported DBus interface at `{}`", object_path),
+            Err(err) => log::error!("Failed to export DBus interface: {:?}", err),
+        }
+    }
+
+    /// The session of the main window once its notes are loaded, which the DBus methods are
+    /// run on
+    fn dbus_session(&self) -> Result<Session, MethodError> {
+        let session = self
+            .window()
+            .filter(Window::is_session_ready)
+            .map(|window| window.session().clone())
+            .ok_or(MethodError::NoSession)?;
+
+        // The notes would be revealed without the PIN
+        if session.is_locked() {
+            return Err(MethodError::Locked);
+        }
+
+        Ok(session)
     }
 
     fn setup_accels(&self) {
         self.set_accels_for_action("app.quit", &["<Control>q"]);
+        self.set_accels_for_action("app.lock", &["<Control><Shift>l"]);
+        self.set_accels_for_action("app.preferences", &["<Control>comma"]);
+        self.set_accels_for_action("app.show-debug-info", &["<Control><Shift>d"]);
+    }
+}
+
+fn update_palette(provider: &gtk::CssProvider) {
+    let palette = Palette::current();
+    log::info!("Palette set to `{:?}`", palette);
+    provider.load_from_data(palette.css().as_bytes());
+}
+
+impl NotesBackend for Application {
+    fn create_note(&self, title: &str, body: &str, tags: &[String]) -> Result<String, MethodError> {
+        let session = self.dbus_session()?;
+        let note = session
+            .note_manager()
+            .create_note_with_content(title, body, tags)
+            .map_err(|err| {
+                if err.is::<ReadOnly>() {
+                    MethodError::ReadOnly
+                } else {
+                    MethodError::InvalidArgs(err.to_string())
+                }
+            })?;
+        Ok(note.id().to_string())
+    }
+
+    fn search_notes(&self, query: &str) -> Result<Vec<FoundNote>, MethodError> {
+        let session = self.dbus_session()?;
+
+        let found_notes = session
+            .search_notes(query)
+            .iter()
+            .map(|note| {
+                let metadata = note.metadata();
+                let buffer = note.buffer();
+                let (start_iter, end_iter) = buffer.bounds();
+                let content = buffer.text(&start_iter, &end_iter, true);
+
+                FoundNote {
+                    id: note.id().to_string(),
+                    title: metadata.title(),
+                    snippet: dbus_service::snippet(&content, query),
+                    modified: metadata.last_modified().to_local().to_rfc3339(),
+                }
+            })
+            .collect();
+
+        Ok(found_notes)
+    }
+
+    fn open_note(&self, id: &str) -> Result<(), MethodError> {
+        let session = self.dbus_session()?;
+        let note = NoteId::parse(id)
+            .and_then(|note_id| session.note_manager().note_list().find_by_id(&note_id))
+            .ok_or_else(|| MethodError::NoteNotFound(id.to_string()))?;
+
+        session.set_selected_note(Some(note));
+        self.main_window().present();
+
+        Ok(())
     }
 }
 
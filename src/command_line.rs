@@ -0,0 +1,203 @@
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, VariantTy},
+    prelude::*,
+};
+
+const NEW_NOTE_OPTION: &str = "new-note";
+const QUIT_OPTION: &str = "quit";
+const QUICK_CAPTURE_OPTION: &str = "quick-capture";
+const DEBUG_OPTION: &str = "debug";
+pub const BACKGROUND_OPTION: &str = "background";
+pub const VERSION_OPTION: &str = "version";
+// Equivalent to G_OPTION_REMAINING
+const REMAINING_OPTION: &str = "";
+
+/// What to do on launch, based on the options given on the command line
+#[derive(Debug, Default, PartialEq)]
+pub struct CommandLineArgs {
+    /// Title of the note to create, given by `--new-note TITLE`
+    pub new_note_title: Option<String>,
+    /// Notes to open, as they are given on the command line
+    pub files: Vec<String>,
+    pub is_quit: bool,
+    /// Whether to only show the quick capture dialog, without the main window
+    pub is_quick_capture: bool,
+    /// Whether to enable the debug mode until the app quits, regardless of the setting
+    pub is_debug: bool,
+    /// Whether to start without showing the main window, as it is when started on login
+    pub is_background: bool,
+}
+
+impl CommandLineArgs {
+    /// Register the options handled by [`CommandLineArgs::from_options`] to `app`
+    pub fn add_main_options(app: &impl IsA<gio::Application>) {
+        app.add_main_option(
+            NEW_NOTE_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::String,
+            &gettext("Create a note with the given title"),
+            Some("TITLE"),
+        );
+        app.add_main_option(
+            QUIT_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Quit the running instance"),
+            None,
+        );
+        app.add_main_option(
+            QUICK_CAPTURE_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Write a note without opening the main window"),
+            None,
+        );
+        app.add_main_option(
+            DEBUG_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Show debug information and write it to the cache folder"),
+            None,
+        );
+        app.add_main_option(
+            BACKGROUND_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Start in the background if it is enabled, without opening the main window"),
+            None,
+        );
+        app.add_main_option(
+            VERSION_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            &gettext("Show the version of the app"),
+            None,
+        );
+        app.add_main_option(
+            REMAINING_OPTION,
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::StringArray,
+            &gettext("Notes to open"),
+            Some("FILE…"),
+        );
+    }
+
+    pub fn from_options(options: &glib::VariantDict) -> Self {
+        let new_note_title = options
+            .lookup_value(NEW_NOTE_OPTION, Some(VariantTy::STRING))
+            .and_then(|value| value.get::<String>())
+            .filter(|title| !title.trim().is_empty());
+
+        let files = options
+            .lookup_value(REMAINING_OPTION, Some(VariantTy::STRING_ARRAY))
+            .and_then(|value| value.get::<Vec<String>>())
+            .unwrap_or_default();
+
+        Self {
+            new_note_title,
+            files,
+            is_quit: options.contains(QUIT_OPTION),
+            is_quick_capture: options.contains(QUICK_CAPTURE_OPTION),
+            is_debug: options.contains(DEBUG_OPTION),
+            is_background: options.contains(BACKGROUND_OPTION),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let options = glib::VariantDict::new(None);
+        assert_eq!(
+            CommandLineArgs::from_options(&options),
+            CommandLineArgs::default()
+        );
+    }
+
+    #[test]
+    fn new_note() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(NEW_NOTE_OPTION, &"Groceries".to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert_eq!(args.new_note_title.as_deref(), Some("Groceries"));
+        assert!(args.files.is_empty());
+        assert!(!args.is_quit);
+    }
+
+    #[test]
+    fn new_note_empty_title() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(NEW_NOTE_OPTION, &"  ".to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert_eq!(args.new_note_title, None);
+    }
+
+    #[test]
+    fn files() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(
+            REMAINING_OPTION,
+            &vec!["a.md".to_string(), "/home/user/b.md".to_string()].to_variant(),
+        );
+
+        let args = CommandLineArgs::from_options(&options);
+        assert_eq!(args.files, vec!["a.md", "/home/user/b.md"]);
+        assert_eq!(args.new_note_title, None);
+    }
+
+    #[test]
+    fn quit() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(QUIT_OPTION, &true.to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert!(args.is_quit);
+        assert!(!args.is_quick_capture);
+    }
+
+    #[test]
+    fn quick_capture() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(QUICK_CAPTURE_OPTION, &true.to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert!(args.is_quick_capture);
+        assert!(!args.is_quit);
+    }
+
+    #[test]
+    fn debug() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(DEBUG_OPTION, &true.to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert!(args.is_debug);
+        assert!(!args.is_quick_capture);
+        assert_eq!(args.new_note_title, None);
+    }
+
+    #[test]
+    fn background() {
+        let options = glib::VariantDict::new(None);
+        options.insert_value(BACKGROUND_OPTION, &true.to_variant());
+
+        let args = CommandLineArgs::from_options(&options);
+        assert!(args.is_background);
+        assert!(!args.is_quit);
+        assert!(args.files.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    is_code_fence,
+    vault_import::{destination_of, is_external, percent_decode},
+    DATA_FILE_NAME,
+};
+
+/// `![Label](path)` and `[Label](<path with spaces>)`
+static RE_MARKDOWN_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"!?\[[^\[\]\n]*\]\((?:<([^<>\n]+)>|([^()\s]+))\)").unwrap());
+
+/// A file in the notes directory that is not a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentFile {
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+}
+
+impl AttachmentFile {
+    pub fn file_name(&self) -> String {
+        file_name_of(&self.path)
+    }
+}
+
+/// The files directly in `directory` that can be attachments, sorted by file name. Notes,
+/// which are told apart by their `.md` extension, the data file, hidden files, and folders
+/// are left out.
+pub fn attachment_files(directory: &Path) -> io::Result<Vec<AttachmentFile>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let file_name = file_name_of(&path);
+
+        if file_name.starts_with('.')
+            || file_name == DATA_FILE_NAME
+            || is_note_file_name(&file_name)
+        {
+            continue;
+        }
+
+        files.push(AttachmentFile {
+            path,
+            size: metadata.len(),
+        });
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Names of the files linked by the Markdown links of `content`, outside of code blocks.
+/// External links and links to notes are left out.
+pub fn linked_file_names(content: &str) -> Vec<String> {
+    let mut file_names = Vec::new();
+    let mut is_in_code_block = false;
+
+    for line in content.split('\n') {
+        if is_code_fence(line) {
+            is_in_code_block = !is_in_code_block;
+            continue;
+        }
+
+        if is_in_code_block {
+            continue;
+        }
+
+        for captures in RE_MARKDOWN_LINK.captures_iter(line) {
+            let destination = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .unwrap()
+                .as_str();
+
+            if is_external(destination) {
+                continue;
+            }
+
+            let decoded = percent_decode(destination.split('#').next().unwrap_or_default());
+            let file_name = file_name_of(Path::new(&decoded));
+
+            if !file_name.is_empty()
+                && !is_note_file_name(&file_name)
+                && !file_names.contains(&file_name)
+            {
+                file_names.push(file_name);
+            }
+        }
+    }
+
+    file_names
+}
+
+/// A Markdown link to the attachment named `file_name`, shown in the note when `is_embedded`,
+/// like a picture
+pub fn markdown_reference(file_name: &str, is_embedded: bool) -> String {
+    let label = Path::new(file_name)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+
+    format!(
+        "{}[{}]({})",
+        if is_embedded { "!" } else { "" },
+        label,
+        destination_of(file_name)
+    )
+}
+
+/// Which notes use each attachment, either by having it in their attachments or by linking
+/// to it in their content
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AttachmentReferences {
+    /// Indices of the notes by the file name of the attachment
+    notes_by_file_name: HashMap<String, Vec<usize>>,
+}
+
+impl AttachmentReferences {
+    /// Find the attachments used by `notes`, which are the files of the attachments and the
+    /// content of each note
+    pub fn scan(notes: &[(Vec<PathBuf>, String)]) -> Self {
+        let mut notes_by_file_name: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, (attachment_files, content)) in notes.iter().enumerate() {
+            let file_names = attachment_files
+                .iter()
+                .map(|path| file_name_of(path))
+                .chain(linked_file_names(content));
+
+            for file_name in file_names {
+                let indices = notes_by_file_name.entry(file_name).or_default();
+
+                if indices.last() != Some(&index) {
+                    indices.push(index);
+                }
+            }
+        }
+
+        Self { notes_by_file_name }
+    }
+
+    /// Indices of the notes using the attachment named `file_name`, in the order they were
+    /// scanned. It is an orphan when there are none.
+    pub fn referencing(&self, file_name: &str) -> &[usize] {
+        self.notes_by_file_name
+            .get(file_name)
+            .map_or(&[], |indices| indices.as_slice())
+    }
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn is_note_file_name(file_name: &str) -> bool {
+    file_name.to_lowercase().ends_with(".md")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "noteworthy-attachment-references-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn orphans(files: &[AttachmentFile], references: &AttachmentReferences) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.file_name())
+            .filter(|file_name| references.referencing(file_name).is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn linked_file_names_in_content() {
+        let content = "# Trip\n\
+            ![Camera](Camera.png) and [the recording](<Audio Recording.ogg>)\n\
+            [Site](https://example.com/map.png) [Other note](Other.md) [Heading](#trip)\n\
+            ![Nested](attachments/Scan.pdf) ![Again](Camera.png)\n\
+            ```\n\
+            ![In code](Code.png)\n\
+            ```\n\
+            ![After code](OtherFile.txt#page=2)";
+
+        assert_eq!(
+            linked_file_names(content),
+            vec![
+                "Camera.png",
+                "Audio Recording.ogg",
+                "Scan.pdf",
+                "OtherFile.txt"
+            ]
+        );
+        assert!(linked_file_names("No links [here]\n").is_empty());
+    }
+
+    #[test]
+    fn percent_encoded_link() {
+        assert_eq!(
+            linked_file_names("![Scan](attachments/Scan%202.png)"),
+            vec!["Scan 2.png"]
+        );
+    }
+
+    #[test]
+    fn markdown_reference_links_back() {
+        assert_eq!(
+            markdown_reference("Camera.png", true),
+            "![Camera](Camera.png)"
+        );
+        assert_eq!(
+            markdown_reference("Audio Recording.ogg", false),
+            "[Audio Recording](<Audio Recording.ogg>)"
+        );
+
+        for (file_name, is_embedded) in [("Camera.png", true), ("Other File.pdf", false)] {
+            let reference = markdown_reference(file_name, is_embedded);
+            assert_eq!(linked_file_names(&reference), vec![file_name]);
+        }
+    }
+
+    #[test]
+    fn referencing() {
+        let notes = vec![
+            (
+                vec![PathBuf::from("/notes/Camera.png")],
+                "![Camera](Camera.png)".to_string(),
+            ),
+            (
+                Vec::new(),
+                "See ![it](Camera.png) and [this](Notes.txt)".to_string(),
+            ),
+            (
+                vec![PathBuf::from("/notes/AudioRecording.ogg")],
+                String::new(),
+            ),
+        ];
+        let references = AttachmentReferences::scan(&notes);
+
+        assert_eq!(references.referencing("Camera.png"), &[0, 1]);
+        assert_eq!(references.referencing("Notes.txt"), &[1]);
+        assert_eq!(references.referencing("AudioRecording.ogg"), &[2]);
+        assert!(references.referencing("Other.png").is_empty());
+    }
+
+    #[test]
+    fn orphans_in_directory() {
+        let dir = temp_dir("orphans");
+        fs::write(dir.join("Note.md"), "![Camera](Camera.png)").unwrap();
+        fs::write(dir.join("Camera.png"), [1, 2, 3]).unwrap();
+        fs::write(dir.join("AudioRecording.ogg"), [1]).unwrap();
+        fs::write(dir.join("Orphan.png"), [1, 2]).unwrap();
+        fs::write(dir.join("Unused File.pdf"), b"").unwrap();
+        fs::write(dir.join(DATA_FILE_NAME), "").unwrap();
+        fs::write(dir.join(".hidden.png"), b"").unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::create_dir_all(dir.join("Folder")).unwrap();
+
+        let files = attachment_files(&dir).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                AttachmentFile {
+                    path: dir.join("AudioRecording.ogg"),
+                    size: 1
+                },
+                AttachmentFile {
+                    path: dir.join("Camera.png"),
+                    size: 3
+                },
+                AttachmentFile {
+                    path: dir.join("Orphan.png"),
+                    size: 2
+                },
+                AttachmentFile {
+                    path: dir.join("Unused File.pdf"),
+                    size: 0
+                },
+            ]
+        );
+
+        let notes = vec![
+            (Vec::new(), "![Camera](Camera.png)".to_string()),
+            (
+                vec![dir.join("AudioRecording.ogg")],
+                "```\n![Not used](Orphan.png)\n```".to_string(),
+            ),
+        ];
+        let references = AttachmentReferences::scan(&notes);
+        assert_eq!(
+            orphans(&files, &references),
+            vec!["Orphan.png", "Unused File.pdf"]
+        );
+
+        // Every attachment is an orphan without notes
+        assert_eq!(
+            orphans(&files, &AttachmentReferences::scan(&[])).len(),
+            files.len()
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
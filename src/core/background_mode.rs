@@ -0,0 +1,275 @@
+/// Whether the app was allowed to run in the background, as answered by the Background portal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Permission {
+    Unknown,
+    Requested,
+    Granted,
+    Denied,
+}
+
+/// What the app has to do after a change of [`BackgroundMode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundAction {
+    None,
+    /// Ask if the app may run in the background, starting it on login too if it is wanted
+    RequestPermission,
+    /// Running in the background was denied, so the setting has to be turned off, and the
+    /// main window shown again if it is hidden
+    Refuse {
+        is_window_hidden: bool,
+    },
+    HideWindow,
+    PresentWindow,
+    /// Save the notes and quit
+    Quit,
+}
+
+/// Keeps track of whether closing the main window only hides it, so the reminders and syncs
+/// keep running, or quits the app
+#[derive(Debug)]
+pub struct BackgroundMode {
+    is_enabled: bool,
+    permission: Permission,
+    is_window_hidden: bool,
+    is_quitting: bool,
+}
+
+impl Default for BackgroundMode {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            permission: Permission::Unknown,
+            is_window_hidden: false,
+            is_quitting: false,
+        }
+    }
+}
+
+impl BackgroundMode {
+    /// Whether the app keeps running without its main window, which is only once it is both
+    /// enabled and allowed, and until it really quits
+    pub fn is_active(&self) -> bool {
+        self.is_enabled && self.permission == Permission::Granted && !self.is_quitting
+    }
+
+    /// The setting to run in the background is turned on or off
+    pub fn set_enabled(&mut self, is_enabled: bool) -> BackgroundAction {
+        self.is_enabled = is_enabled;
+
+        if !is_enabled {
+            return self.show_hidden_window();
+        }
+
+        match self.permission {
+            Permission::Requested | Permission::Granted => BackgroundAction::None,
+            // It may be allowed since it was last denied
+            Permission::Unknown | Permission::Denied => {
+                self.permission = Permission::Requested;
+                BackgroundAction::RequestPermission
+            }
+        }
+    }
+
+    /// The setting to start on login is changed, which is asked along with the permission
+    pub fn autostart_changed(&mut self) -> BackgroundAction {
+        if !self.is_enabled {
+            return BackgroundAction::None;
+        }
+
+        self.permission = Permission::Requested;
+        BackgroundAction::RequestPermission
+    }
+
+    /// The answer of the request for [`BackgroundAction::RequestPermission`]
+    pub fn permission_answered(&mut self, is_granted: bool) -> BackgroundAction {
+        if is_granted {
+            self.permission = Permission::Granted;
+            return BackgroundAction::None;
+        }
+
+        self.permission = Permission::Denied;
+
+        if !self.is_enabled {
+            return BackgroundAction::None;
+        }
+
+        self.is_enabled = false;
+        let is_window_hidden = self.is_window_hidden;
+        self.is_window_hidden = false;
+        BackgroundAction::Refuse { is_window_hidden }
+    }
+
+    /// The app is started on login. The main window is only kept hidden if the app may run in
+    /// the background, or may be once it is asked.
+    pub fn start_in_background(&mut self) -> BackgroundAction {
+        if self.is_enabled && self.permission != Permission::Denied {
+            self.is_window_hidden = true;
+            return BackgroundAction::None;
+        }
+
+        BackgroundAction::PresentWindow
+    }
+
+    /// The app is activated again, like from the app grid or a notification
+    pub fn activate(&mut self) -> BackgroundAction {
+        self.is_window_hidden = false;
+        BackgroundAction::PresentWindow
+    }
+
+    /// The main window is asked to close
+    pub fn close_window(&mut self) -> BackgroundAction {
+        if self.is_active() {
+            self.is_window_hidden = true;
+            BackgroundAction::HideWindow
+        } else {
+            BackgroundAction::Quit
+        }
+    }
+
+    /// The app is asked to quit even while it runs in the background
+    pub fn quit_really(&mut self) -> BackgroundAction {
+        self.is_quitting = true;
+        BackgroundAction::Quit
+    }
+
+    fn show_hidden_window(&mut self) -> BackgroundAction {
+        if self.is_window_hidden {
+            self.is_window_hidden = false;
+            BackgroundAction::PresentWindow
+        } else {
+            BackgroundAction::None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn active() -> BackgroundMode {
+        let mut mode = BackgroundMode::default();
+        assert_eq!(mode.set_enabled(true), BackgroundAction::RequestPermission);
+        assert_eq!(mode.permission_answered(true), BackgroundAction::None);
+        assert!(mode.is_active());
+        mode
+    }
+
+    #[test]
+    fn close_without_background_mode() {
+        let mut mode = BackgroundMode::default();
+        assert!(!mode.is_active());
+        assert_eq!(mode.close_window(), BackgroundAction::Quit);
+        assert!(!mode.is_window_hidden);
+    }
+
+    #[test]
+    fn close_with_background_mode() {
+        let mut mode = active();
+        assert_eq!(mode.close_window(), BackgroundAction::HideWindow);
+        assert!(mode.is_window_hidden);
+        assert!(mode.is_active());
+    }
+
+    #[test]
+    fn close_while_permission_is_requested() {
+        let mut mode = BackgroundMode::default();
+        mode.set_enabled(true);
+        assert!(!mode.is_active());
+        assert_eq!(mode.close_window(), BackgroundAction::Quit);
+    }
+
+    #[test]
+    fn activate_presents_hidden_window() {
+        let mut mode = active();
+        mode.close_window();
+
+        assert_eq!(mode.activate(), BackgroundAction::PresentWindow);
+        assert!(!mode.is_window_hidden);
+        assert_eq!(mode.close_window(), BackgroundAction::HideWindow);
+    }
+
+    #[test]
+    fn quit_really() {
+        let mut mode = active();
+        mode.close_window();
+
+        assert_eq!(mode.quit_really(), BackgroundAction::Quit);
+        assert!(!mode.is_active());
+        assert_eq!(mode.close_window(), BackgroundAction::Quit);
+    }
+
+    #[test]
+    fn denied() {
+        let mut mode = BackgroundMode::default();
+        mode.set_enabled(true);
+
+        assert_eq!(
+            mode.permission_answered(false),
+            BackgroundAction::Refuse {
+                is_window_hidden: false
+            }
+        );
+        assert!(!mode.is_active());
+        assert_eq!(mode.close_window(), BackgroundAction::Quit);
+
+        // Asked again, as it may have been allowed in the settings of the desktop since
+        assert_eq!(mode.set_enabled(true), BackgroundAction::RequestPermission);
+    }
+
+    #[test]
+    fn denied_while_started_in_background() {
+        let mut mode = BackgroundMode::default();
+        mode.set_enabled(true);
+        assert_eq!(mode.start_in_background(), BackgroundAction::None);
+        assert!(mode.is_window_hidden);
+
+        assert_eq!(
+            mode.permission_answered(false),
+            BackgroundAction::Refuse {
+                is_window_hidden: true
+            }
+        );
+        assert!(!mode.is_window_hidden);
+    }
+
+    #[test]
+    fn start_in_background() {
+        let mut mode = BackgroundMode::default();
+        assert_eq!(mode.start_in_background(), BackgroundAction::PresentWindow);
+        assert!(!mode.is_window_hidden);
+
+        let mut mode = active();
+        assert_eq!(mode.start_in_background(), BackgroundAction::None);
+        assert!(mode.is_window_hidden);
+        assert_eq!(mode.activate(), BackgroundAction::PresentWindow);
+    }
+
+    #[test]
+    fn disable_presents_hidden_window() {
+        let mut mode = active();
+        mode.close_window();
+
+        assert_eq!(mode.set_enabled(false), BackgroundAction::PresentWindow);
+        assert!(!mode.is_active());
+        assert_eq!(mode.set_enabled(false), BackgroundAction::None);
+
+        // Already allowed, so it is not asked again
+        assert_eq!(mode.set_enabled(true), BackgroundAction::None);
+        assert!(mode.is_active());
+    }
+
+    #[test]
+    fn autostart_changed() {
+        let mut mode = BackgroundMode::default();
+        assert_eq!(mode.autostart_changed(), BackgroundAction::None);
+
+        let mut mode = active();
+        assert_eq!(
+            mode.autostart_changed(),
+            BackgroundAction::RequestPermission
+        );
+        assert!(!mode.is_active());
+        mode.permission_answered(true);
+        assert!(mode.is_active());
+    }
+}
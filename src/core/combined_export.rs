@@ -0,0 +1,359 @@
+use gettextrs::gettext;
+
+use super::{is_code_fence, DateTime};
+
+/// Deepest heading level of Markdown
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// Kind of document that notes are exported to together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombinedFormat {
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl CombinedFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// A note to put in the combined document, taken from the note as it was when chosen
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombinedNote {
+    pub title: String,
+    pub last_modified: DateTime,
+    pub tag_names: Vec<String>,
+    pub content: String,
+}
+
+/// `notes` from the least to the most recently modified, like minutes of meetings
+pub fn sorted_by_date(notes: &[CombinedNote]) -> Vec<CombinedNote> {
+    let mut notes = notes.to_vec();
+    notes.sort_by_key(|note| note.last_modified);
+    notes
+}
+
+/// Move the item at `from` to be at `to`, shifting the ones in between, as when dragged
+pub fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) {
+    if from >= items.len() {
+        return;
+    }
+
+    let item = items.remove(from);
+    items.insert(to.min(items.len()), item);
+}
+
+/// One Markdown document of `notes` in their order, each under a level 1 heading of its
+/// title. `on_progress` is called with the number of notes added so far.
+pub fn combine_notes(
+    notes: &[CombinedNote],
+    include_metadata: bool,
+    mut on_progress: impl FnMut(usize),
+) -> String {
+    let mut sections = Vec::with_capacity(notes.len());
+
+    for (index, note) in notes.iter().enumerate() {
+        let mut blocks = vec![format!("# {}", note.title.trim())];
+
+        if include_metadata {
+            blocks.push(metadata_line(note));
+        }
+
+        let content = shift_headings(note.content.trim(), 1);
+        if !content.is_empty() {
+            blocks.push(content);
+        }
+
+        sections.push(blocks.join("\n\n"));
+        on_progress(index + 1);
+    }
+
+    if sections.is_empty() {
+        return String::new();
+    }
+
+    let mut document = sections.join("\n\n");
+    document.push('\n');
+    document
+}
+
+/// A page named `title` with the rendered `body`, to open the combined notes in a browser
+pub fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        title
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+        body
+    )
+}
+
+/// The date and tags of `note` in italics
+fn metadata_line(note: &CombinedNote) -> String {
+    let modified = note.last_modified.to_local().format("%Y-%m-%d %H:%M");
+    let mut line = gettext!("Modified {}", modified);
+
+    if !note.tag_names.is_empty() {
+        line.push_str(" · ");
+        line.push_str(&gettext!("Tags: {}", note.tag_names.join(", ")));
+    }
+
+    format!("*{}*", line)
+}
+
+/// Make the headings of `content` `n_levels` deeper, so they are under the heading of the
+/// note. Setext headings become ATX ones, and the ones that would be deeper than the deepest
+/// level stay at it. Lines in fenced code blocks are left as they are.
+fn shift_headings(content: &str, n_levels: usize) -> String {
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+    let mut shifted = String::with_capacity(content.len());
+    let mut is_in_code_block = false;
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+
+        if is_code_fence(line) {
+            is_in_code_block = !is_in_code_block;
+        } else if !is_in_code_block {
+            if let Some(level) = atx_heading_level(line) {
+                let text = line.trim_start_matches(' ');
+                let indent = &line[..line.len() - text.len()];
+                shifted.push_str(indent);
+                shifted.push_str(&heading_marker(level, n_levels));
+                shifted.push_str(&text[level..]);
+                continue;
+            }
+
+            let setext_level = lines
+                .get(index)
+                .and_then(|next_line| setext_underline_level(next_line))
+                .filter(|_| is_paragraph_line(line));
+            if let Some(level) = setext_level {
+                let line_ending = &lines[index][lines[index].trim_end().len()..];
+                shifted.push_str(&heading_marker(level, n_levels));
+                shifted.push(' ');
+                shifted.push_str(line.trim());
+                shifted.push_str(line_ending);
+                index += 1;
+                continue;
+            }
+        }
+
+        shifted.push_str(line);
+    }
+
+    shifted
+}
+
+fn heading_marker(level: usize, n_levels: usize) -> String {
+    "#".repeat((level + n_levels).min(MAX_HEADING_LEVEL))
+}
+
+/// Level of the heading if `line` is like `## Heading`
+pub fn atx_heading_level(line: &str) -> Option<usize> {
+    let text = line.trim_start_matches(' ');
+    if line.len() - text.len() > 3 {
+        return None;
+    }
+
+    let level = text.chars().take_while(|c| *c == '#').count();
+    let rest = &text[level..];
+    let is_heading = (1..=MAX_HEADING_LEVEL).contains(&level)
+        && (rest.is_empty() || rest.starts_with(char::is_whitespace));
+
+    is_heading.then(|| level)
+}
+
+/// Level of the heading above if `line` is the `===` or `---` under a setext heading
+fn setext_underline_level(line: &str) -> Option<usize> {
+    let underline = line.trim_start_matches(' ');
+    if line.len() - underline.len() > 3 {
+        return None;
+    }
+
+    let underline = underline.trim_end();
+    if underline.is_empty() {
+        None
+    } else if underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Whether `line` can be the text of a setext heading, and not a list item, quote, or other
+/// block whose next `---` is a thematic break
+fn is_paragraph_line(line: &str) -> bool {
+    let text = line.trim_start_matches(' ');
+    let trimmed = text.trim();
+
+    !trimmed.is_empty()
+        && line.len() - text.len() <= 3
+        && atx_heading_level(line).is_none()
+        && setext_underline_level(line).is_none()
+        && !trimmed.starts_with(|c| matches!(c, '-' | '*' | '+' | '>' | '|'))
+        && !trimmed
+            .split_once(|c| c == '.' || c == ')')
+            .map_or(false, |(number, _)| {
+                !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+            })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn note(title: &str, timestamp: i64, content: &str) -> CombinedNote {
+        CombinedNote {
+            title: title.to_string(),
+            last_modified: DateTime::from_timestamp(timestamp),
+            tag_names: Vec::new(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn shift_atx_headings() {
+        assert_eq!(
+            shift_headings("# Agenda\nText\n## Item\n", 1),
+            "## Agenda\nText\n### Item\n"
+        );
+        assert_eq!(shift_headings("#\n  # Indented", 1), "##\n  ## Indented");
+        assert_eq!(shift_headings("# Title", 2), "### Title");
+    }
+
+    #[test]
+    fn shift_headings_at_deepest_level() {
+        assert_eq!(
+            shift_headings("##### Five\n###### Six", 1),
+            "###### Five\n###### Six"
+        );
+    }
+
+    #[test]
+    fn shift_headings_ignores_non_headings() {
+        let content = "#tag and #another\n    # Indented code\n####### Seven\nText # not";
+        assert_eq!(shift_headings(content, 1), content);
+    }
+
+    #[test]
+    fn shift_headings_ignores_code_blocks() {
+        let content = "```sh\n# comment\n```\n# Heading\n~~~\n## Also code\n~~~\n";
+        assert_eq!(
+            shift_headings(content, 1),
+            "```sh\n# comment\n```\n## Heading\n~~~\n## Also code\n~~~\n"
+        );
+    }
+
+    #[test]
+    fn shift_setext_headings() {
+        assert_eq!(
+            shift_headings("Title\n=====\nText\n\nSection\n---\n", 1),
+            "## Title\nText\n\n### Section\n"
+        );
+        assert_eq!(shift_headings("Title\r\n===\r\n", 1), "## Title\r\n");
+
+        // Thematic breaks stay as they are
+        let content = "Text\n\n---\n- Item\n---\n> Quote\n---\n1. First\n---\n";
+        assert_eq!(shift_headings(content, 1), content);
+    }
+
+    #[test]
+    fn sort_by_date() {
+        let notes = vec![
+            note("Third", 300, ""),
+            note("First", 100, ""),
+            note("Second", 200, ""),
+        ];
+
+        let titles = sorted_by_date(&notes)
+            .into_iter()
+            .map(|note| note.title)
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["First", "Second", "Third"]);
+    }
+
+    #[test]
+    fn move_items() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+
+        move_item(&mut items, 0, 2);
+        assert_eq!(items, ['b', 'c', 'a', 'd']);
+
+        move_item(&mut items, 3, 0);
+        assert_eq!(items, ['d', 'b', 'c', 'a']);
+
+        move_item(&mut items, 1, 10);
+        assert_eq!(items, ['d', 'c', 'a', 'b']);
+
+        move_item(&mut items, 10, 0);
+        assert_eq!(items, ['d', 'c', 'a', 'b']);
+    }
+
+    #[test]
+    fn combine() {
+        let notes = vec![
+            note("Monday", 100, "# Agenda\n\n- Budget\n\n"),
+            note("Tuesday", 200, ""),
+            note("Wednesday", 300, "Short one"),
+        ];
+
+        let mut progress = Vec::new();
+        let document = combine_notes(&notes, false, |n_done| progress.push(n_done));
+
+        assert_eq!(
+            document,
+            "# Monday\n\n## Agenda\n\n- Budget\n\n# Tuesday\n\n# Wednesday\n\nShort one\n"
+        );
+        assert_eq!(progress, [1, 2, 3]);
+    }
+
+    #[test]
+    fn combine_with_metadata() {
+        let mut monday = note("Monday", 100, "Minutes");
+        monday.tag_names = vec!["Work".to_string(), "Meeting".to_string()];
+        let tuesday = note("Tuesday", 200, "More minutes");
+
+        let date = |timestamp| {
+            DateTime::from_timestamp(timestamp)
+                .to_local()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+
+        assert_eq!(
+            combine_notes(&[monday, tuesday], true, |_| {}),
+            format!(
+                "# Monday\n\n*Modified {} · Tags: Work, Meeting*\n\nMinutes\n\n\
+                 # Tuesday\n\n*Modified {}*\n\nMore minutes\n",
+                date(100),
+                date(200)
+            )
+        );
+    }
+
+    #[test]
+    fn html_page() {
+        assert_eq!(
+            html_document("Q&A <Notes>", "<h1>Title</h1>\n"),
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>Q&amp;A &lt;Notes&gt;</title>\n</head>\n<body>\n<h1>Title</h1>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn combine_nothing() {
+        assert_eq!(combine_notes(&[], true, |_| unreachable!()), "");
+    }
+}
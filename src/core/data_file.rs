@@ -0,0 +1,528 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use std::collections::HashSet;
+
+use super::{DailyWordCounts, DateTime, ExtraFields, NotebookSettingsData};
+
+/// Name of the data file, relative to the notebook directory
+pub const DATA_FILE_NAME: &str = "data.nwty";
+
+/// Version of the data file format written by this version of the app. Version 2 added the
+/// recently deleted tags.
+pub const DATA_FILE_VERSION: u32 = 2;
+
+/// How long a deleted tag can be restored before it is purged
+pub const DELETED_TAG_RETENTION_DAYS: i64 = 30;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// A search saved by the user, shown in the sidebar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearchData {
+    pub name: String,
+    /// Text of the [`SearchQuery`](super::SearchQuery), which is parsed when it is shown
+    pub query: String,
+}
+
+/// A tag that was deleted recently, kept so it can be restored on the notes it was removed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletedTagData {
+    pub name: String,
+    pub deleted: DateTime,
+    /// Ids of the notes that had the tag when it was deleted
+    pub note_ids: Vec<String>,
+}
+
+impl DeletedTagData {
+    /// Whether it was deleted more than [`DELETED_TAG_RETENTION_DAYS`] before `now`, so it
+    /// can no longer be restored
+    pub fn is_expired(&self, now: DateTime) -> bool {
+        let age = now.to_local().timestamp() - self.deleted.to_local().timestamp();
+        age > DELETED_TAG_RETENTION_DAYS * SECONDS_PER_DAY
+    }
+
+    /// Invalid entries are dropped rather than losing the rest of the data file
+    fn deserialize_or_default<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Self>, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        Ok(serde_yaml::from_value(value).unwrap_or_else(|err| {
+            log::warn!("Discarding invalid deleted tags: {:?}", err);
+            Vec::new()
+        }))
+    }
+}
+
+/// Plain data of the notebook-wide data file, which is synced along with the notes.
+///
+/// It is always written in a canonical form, so two devices with the same tags write the same
+/// file no matter the order the tags were created in, and syncing does not conflict on it.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataFile {
+    /// Version of the format the file was written in. Files from before it was added have
+    /// none, which is read as `0`.
+    pub version: u32,
+    /// Names of every tag of the notebook
+    pub tag_list: Vec<String>,
+    /// Order the tags are shown in, when it is not the order of `tag_list`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tag_order: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub saved_searches: Vec<SavedSearchData>,
+    /// Tags deleted in the last [`DELETED_TAG_RETENTION_DAYS`], which can still be restored
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "DeletedTagData::deserialize_or_default"
+    )]
+    pub deleted_tags: Vec<DeletedTagData>,
+    /// Words written in the notebook on each day, for the daily word goal
+    #[serde(
+        skip_serializing_if = "DailyWordCounts::is_empty",
+        deserialize_with = "DailyWordCounts::deserialize_or_default"
+    )]
+    pub daily_word_counts: DailyWordCounts,
+    #[serde(
+        rename = "notebook-settings",
+        deserialize_with = "NotebookSettingsData::deserialize_or_default"
+    )]
+    pub notebook_settings: NotebookSettingsData,
+    /// Fields added by newer versions of the app, kept so saving doesn't lose them
+    #[serde(flatten)]
+    pub extra_fields: ExtraFields,
+}
+
+impl DataFile {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_yaml::from_slice(bytes)?)
+    }
+
+    /// Serialize with the tags and saved searches sorted by name, the deleted tags from the
+    /// oldest, the current format version, and LF-only line
+    /// endings, so the same data is always written as the same bytes
+    pub fn to_canonical_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut canonical = self.clone();
+        canonical.version = DATA_FILE_VERSION;
+        canonical.tag_list = sorted_tag_names(self.tag_list.iter().cloned());
+        canonical.tag_order = self.ordered_tag_names();
+        if canonical.tag_order == canonical.tag_list {
+            canonical.tag_order.clear();
+        }
+        canonical.saved_searches = sorted_saved_searches(self.saved_searches.iter().cloned());
+        canonical.deleted_tags = sorted_deleted_tags(self.deleted_tags.iter().cloned());
+
+        // Strings with line breaks are written escaped, so this only changes the separators
+        let mut text = serde_yaml::to_string(&canonical)?.replace("\r\n", "\n");
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        Ok(text.into_bytes())
+    }
+
+    /// Combine the files written on two devices. No tag, saved search, or deleted tag of either
+    /// is lost, the
+    /// larger word count of each day is kept, and the notebook settings that were changed last
+    /// are kept, preferring `self` when unknown.
+    pub fn union(&self, other: &Self) -> Self {
+        let notebook_settings = if other
+            .notebook_settings
+            .is_newer_than(&self.notebook_settings)
+        {
+            other.notebook_settings.clone()
+        } else {
+            self.notebook_settings.clone()
+        };
+
+        let mut extra_fields = other.extra_fields.clone();
+        for (key, value) in &self.extra_fields.0 {
+            extra_fields.0.insert(key.clone(), value.clone());
+        }
+
+        Self {
+            version: self.version.max(other.version),
+            tag_list: sorted_tag_names(self.tag_list.iter().chain(&other.tag_list).cloned()),
+            tag_order: unique_tag_names(self.tag_order.iter().chain(&other.tag_order).cloned()),
+            saved_searches: sorted_saved_searches(
+                self.saved_searches
+                    .iter()
+                    .chain(&other.saved_searches)
+                    .cloned(),
+            ),
+            deleted_tags: sorted_deleted_tags(
+                self.deleted_tags.iter().chain(&other.deleted_tags).cloned(),
+            ),
+            daily_word_counts: self.daily_word_counts.union(&other.daily_word_counts),
+            notebook_settings,
+            extra_fields,
+        }
+    }
+
+    /// Names of every tag in the order they are shown in. Tags missing from `tag_order`, such
+    /// as ones created by an older version of the app, come last.
+    pub fn ordered_tag_names(&self) -> Vec<String> {
+        let tag_list = sorted_tag_names(self.tag_list.iter().cloned());
+
+        let ordered = self
+            .tag_order
+            .iter()
+            .filter(|name| tag_list.binary_search(*name).is_ok())
+            .chain(&tag_list)
+            .cloned();
+        unique_tag_names(ordered)
+    }
+}
+
+/// Keep the first of each name, in the order they come in
+fn unique_tag_names(names: impl Iterator<Item = String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names.filter(|name| seen.insert(name.clone())).collect()
+}
+
+/// Sorted by their bytes rather than the locale, so every device agrees on the order
+fn sorted_tag_names(names: impl Iterator<Item = String>) -> Vec<String> {
+    let mut names = names.collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Sorted by name, with one search of each name. Of searches with the same name, like ones
+/// edited on two devices, the one with the last query is kept, so every device keeps the same.
+fn sorted_saved_searches(
+    saved_searches: impl Iterator<Item = SavedSearchData>,
+) -> Vec<SavedSearchData> {
+    let mut saved_searches = saved_searches.collect::<Vec<_>>();
+    saved_searches.sort_unstable_by(|a, b| a.name.cmp(&b.name).then_with(|| b.query.cmp(&a.query)));
+    saved_searches.dedup_by(|a, b| a.name == b.name);
+    saved_searches
+}
+
+/// Sorted from the oldest deletion, with their note ids sorted, and one of each deletion, like
+/// one synced from another device
+pub fn sorted_deleted_tags(
+    deleted_tags: impl Iterator<Item = DeletedTagData>,
+) -> Vec<DeletedTagData> {
+    let mut deleted_tags = deleted_tags
+        .map(|mut deleted_tag| {
+            deleted_tag.note_ids.sort_unstable();
+            deleted_tag.note_ids.dedup();
+            deleted_tag
+        })
+        .collect::<Vec<_>>();
+    deleted_tags.sort_unstable_by(|a, b| {
+        (a.deleted, &a.name, &a.note_ids).cmp(&(b.deleted, &b.name, &b.note_ids))
+    });
+    deleted_tags.dedup_by(|a, b| a.deleted == b.deleted && a.name == b.name);
+    deleted_tags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_harness::{fixture, fixtures, snapshot_settings};
+
+    fn data_file(tag_names: &[&str]) -> DataFile {
+        DataFile {
+            tag_list: tag_names.iter().map(|name| name.to_string()).collect(),
+            ..DataFile::default()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data = data_file(&["Work", "Home", "Ä", "a"]);
+        data.notebook_settings.note_template = "# Today\r\n\r\n- ".to_string();
+
+        let bytes = data.to_canonical_bytes().unwrap();
+        let loaded = DataFile::parse(&bytes).unwrap();
+
+        assert_eq!(loaded.version, DATA_FILE_VERSION);
+        assert_eq!(loaded.tag_list, ["Home", "Work", "a", "Ä"]);
+        assert_eq!(loaded.notebook_settings, data.notebook_settings);
+
+        // Writing what was read gives back the same bytes
+        assert_eq!(loaded.to_canonical_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn canonical_regardless_of_tag_order() {
+        let a = data_file(&["B", "A", "C"]).to_canonical_bytes().unwrap();
+        let b = data_file(&["C", "B", "A"]).to_canonical_bytes().unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn canonical_format() {
+        let mut data = data_file(&["B", "A"]);
+        data.notebook_settings.note_template = "Line\r\nLine".to_string();
+
+        let text = String::from_utf8(data.to_canonical_bytes().unwrap()).unwrap();
+        assert!(text.starts_with("---\nversion: 2\ntag_list:\n  - A\n  - B\n"));
+        assert!(text.ends_with('\n'));
+        assert!(!text.contains('\r'));
+    }
+
+    #[test]
+    fn tag_order() {
+        let mut data = data_file(&["B", "C", "A"]);
+        data.tag_order = data.tag_list.clone();
+
+        let bytes = data.to_canonical_bytes().unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(
+            text.contains("\ntag_list:\n  - A\n  - B\n  - C\ntag_order:\n  - B\n  - C\n  - A\n")
+        );
+
+        let loaded = DataFile::parse(&bytes).unwrap();
+        assert_eq!(loaded.ordered_tag_names(), ["B", "C", "A"]);
+
+        // Not written at all when it is the order of the names
+        data.tag_order = vec!["A".into(), "B".into(), "C".into()];
+        let text = String::from_utf8(data.to_canonical_bytes().unwrap()).unwrap();
+        assert!(!text.contains("tag_order"));
+    }
+
+    #[test]
+    fn ordered_tag_names_with_missing_and_removed_tags() {
+        let mut data = data_file(&["D", "A", "C", "B"]);
+        data.tag_order = vec!["C".into(), "Removed".into(), "A".into(), "C".into()];
+        assert_eq!(data.ordered_tag_names(), ["C", "A", "B", "D"]);
+    }
+
+    #[test]
+    fn parse_without_version() {
+        // Written before the version was added
+        let data = DataFile::parse(b"---\ntag_list:\n  - B\n  - A\n").unwrap();
+
+        assert_eq!(data.version, 0);
+        assert_eq!(data.tag_list, ["B", "A"]);
+        assert_eq!(data.notebook_settings, NotebookSettingsData::default());
+    }
+
+    #[test]
+    fn parse_with_invalid_notebook_settings() {
+        let data = DataFile::parse(b"---\ntag_list:\n  - A\nnotebook-settings: [1, 2]\n").unwrap();
+
+        // The tags are not lost along with the settings
+        assert_eq!(data.tag_list, ["A"]);
+        assert_eq!(data.notebook_settings, NotebookSettingsData::default());
+    }
+
+    #[test]
+    fn parse_keeps_extra_fields() {
+        let data = DataFile::parse(b"---\nversion: 2\ntag_list: []\nfuture: true\n").unwrap();
+        let text = String::from_utf8(data.to_canonical_bytes().unwrap()).unwrap();
+        assert!(text.contains("\nfuture: true\n"));
+    }
+
+    #[test]
+    fn union_divergent_files() {
+        let ours = DataFile::parse(
+            b"---\ntag_list:\n  - Work\n  - Ideas\nnotebook-settings:\n  version: 1\n  modified: \"2022-03-01T10:00:00+00:00\"\n  note_template: Ours\n",
+        )
+        .unwrap();
+        let theirs = DataFile::parse(
+            b"---\nversion: 1\ntag_list:\n  - Home\n  - Work\nnotebook-settings:\n  version: 1\n  modified: \"2022-03-02T10:00:00+00:00\"\n  note_template: Theirs\n",
+        )
+        .unwrap();
+
+        let merged = ours.union(&theirs);
+        assert_eq!(merged.version, 1);
+        assert_eq!(merged.tag_list, ["Home", "Ideas", "Work"]);
+        assert_eq!(merged.notebook_settings.note_template, "Theirs");
+        assert_eq!(merged.ordered_tag_names(), ["Home", "Ideas", "Work"]);
+
+        // Both devices end up writing the same file
+        assert_eq!(
+            merged.to_canonical_bytes().unwrap(),
+            theirs.union(&ours).to_canonical_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn daily_word_counts() {
+        let data = DataFile::parse(
+            b"---\ntag_list: []\ndaily_word_counts:\n  2022-03-02: 5\n  2022-03-01: 420\n",
+        )
+        .unwrap();
+
+        let text = String::from_utf8(data.to_canonical_bytes().unwrap()).unwrap();
+        assert!(text.contains("\ndaily_word_counts:\n  2022-03-01: 420\n  2022-03-02: 5\n"));
+
+        let theirs =
+            DataFile::parse(b"---\ntag_list: []\ndaily_word_counts:\n  2022-03-02: 30\n").unwrap();
+        let merged = data.union(&theirs);
+        assert_eq!(
+            merged.daily_word_counts.to_csv(),
+            "date,words\n2022-03-01,420\n2022-03-02,30\n"
+        );
+
+        // The tags are not lost along with invalid counts
+        let data =
+            DataFile::parse(b"---\ntag_list:\n  - A\ndaily_word_counts:\n  yesterday: many\n")
+                .unwrap();
+        assert_eq!(data.tag_list, ["A"]);
+        assert!(data.daily_word_counts.is_empty());
+
+        // Not written at all when there are none
+        let text = String::from_utf8(data_file(&["A"]).to_canonical_bytes().unwrap()).unwrap();
+        assert!(!text.contains("daily_word_counts"));
+    }
+
+    fn saved_search(name: &str, query: &str) -> SavedSearchData {
+        SavedSearchData {
+            name: name.to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn saved_searches() {
+        let mut data = data_file(&["Work"]);
+        data.saved_searches = vec![
+            saved_search("Work", "tag:work is:pinned"),
+            saved_search("Recent", "after:7d"),
+        ];
+
+        let bytes = data.to_canonical_bytes().unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.contains(
+            "\nsaved_searches:\n  - name: Recent\n    query: \"after:7d\"\n  - name: Work\n    query: \"tag:work is:pinned\"\n"
+        ));
+
+        let loaded = DataFile::parse(&bytes).unwrap();
+        assert_eq!(
+            loaded.saved_searches,
+            [
+                saved_search("Recent", "after:7d"),
+                saved_search("Work", "tag:work is:pinned"),
+            ]
+        );
+
+        // Not written at all when there are none
+        let text = String::from_utf8(data_file(&["Work"]).to_canonical_bytes().unwrap()).unwrap();
+        assert!(!text.contains("saved_searches"));
+    }
+
+    #[test]
+    fn union_saved_searches() {
+        let mut ours = data_file(&[]);
+        ours.saved_searches = vec![
+            saved_search("Pinned", "is:pinned"),
+            saved_search("Work", "tag:work"),
+        ];
+        let mut theirs = data_file(&[]);
+        theirs.saved_searches = vec![
+            saved_search("Recent", "after:7d"),
+            saved_search("Work", "tag:work is:pinned"),
+        ];
+
+        let merged = ours.union(&theirs);
+        assert_eq!(
+            merged.saved_searches,
+            [
+                saved_search("Pinned", "is:pinned"),
+                saved_search("Recent", "after:7d"),
+                saved_search("Work", "tag:work is:pinned"),
+            ]
+        );
+        assert_eq!(merged, theirs.union(&ours));
+    }
+
+    fn deleted_tag(name: &str, deleted: &str, note_ids: &[&str]) -> DeletedTagData {
+        DeletedTagData {
+            name: name.to_string(),
+            deleted: DateTime::parse(deleted).unwrap(),
+            note_ids: note_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn deleted_tags() {
+        let mut data = data_file(&["Home"]);
+        data.deleted_tags = vec![
+            deleted_tag("Work", "2022-03-02T10:00:00+00:00", &["Note 2", "Note 1"]),
+            deleted_tag("Ideas", "2022-03-01T10:00:00+00:00", &[]),
+        ];
+
+        let bytes = data.to_canonical_bytes().unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.contains(
+            "\ndeleted_tags:\n  - name: Ideas\n    deleted: \"2022-03-01T10:00:00+00:00\"\n    note_ids: []\n  - name: Work\n    deleted: \"2022-03-02T10:00:00+00:00\"\n    note_ids:\n      - Note 1\n      - Note 2\n"
+        ));
+
+        let loaded = DataFile::parse(&bytes).unwrap();
+        assert_eq!(loaded.deleted_tags.len(), 2);
+        assert_eq!(loaded.deleted_tags[1].note_ids, ["Note 1", "Note 2"]);
+
+        // The tags are not lost along with invalid deleted tags
+        let data = DataFile::parse(b"---\ntag_list:\n  - A\ndeleted_tags:\n  - name: B\n").unwrap();
+        assert_eq!(data.tag_list, ["A"]);
+        assert!(data.deleted_tags.is_empty());
+
+        // Not written at all when there are none
+        let text = String::from_utf8(data_file(&["A"]).to_canonical_bytes().unwrap()).unwrap();
+        assert!(!text.contains("deleted_tags"));
+    }
+
+    #[test]
+    fn union_deleted_tags() {
+        let mut ours = data_file(&[]);
+        ours.deleted_tags = vec![
+            deleted_tag("Work", "2022-03-02T10:00:00+00:00", &["Note 1"]),
+            deleted_tag("Work", "2022-02-01T10:00:00+00:00", &["Note 2"]),
+        ];
+        let mut theirs = data_file(&[]);
+        theirs.deleted_tags = vec![
+            deleted_tag("Work", "2022-03-02T10:00:00+00:00", &["Note 1"]),
+            deleted_tag("Home", "2022-03-03T10:00:00+00:00", &["Note 3"]),
+        ];
+
+        let merged = ours.union(&theirs);
+        let names = merged
+            .deleted_tags
+            .iter()
+            .map(|deleted_tag| deleted_tag.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Work", "Work", "Home"]);
+        assert_eq!(merged, theirs.union(&ours));
+    }
+
+    #[test]
+    fn deleted_tag_expiry() {
+        let deleted = deleted_tag("Work", "2022-03-01T10:00:00+00:00", &[]);
+        let at = |text| DateTime::parse(text).unwrap();
+
+        assert!(!deleted.is_expired(at("2022-03-01T10:00:00+00:00")));
+        assert!(!deleted.is_expired(at("2022-03-31T10:00:00+00:00")));
+        assert!(deleted.is_expired(at("2022-03-31T10:00:01+00:00")));
+        assert!(deleted.is_expired(at("2023-01-01T00:00:00+00:00")));
+    }
+
+    #[test]
+    fn fixtures_written_canonically() {
+        for (name, text) in fixtures("data") {
+            let bytes = DataFile::parse(text.as_bytes())
+                .unwrap()
+                .to_canonical_bytes()
+                .unwrap();
+
+            // Written the same once it is in the current format
+            let loaded = DataFile::parse(&bytes).unwrap();
+            assert_eq!(loaded.to_canonical_bytes().unwrap(), bytes, "`{}`", name);
+
+            let text = String::from_utf8(bytes).unwrap();
+            snapshot_settings().bind(|| insta::assert_snapshot!(format!("data-{}", name), text));
+        }
+
+        // Sections that can't be read don't lose the others
+        let data = DataFile::parse(fixture("data/invalid-sections.nwty").as_bytes()).unwrap();
+        assert_eq!(data.tag_list, ["Work"]);
+        assert!(data.deleted_tags.is_empty());
+        assert!(data.daily_word_counts.is_empty());
+        assert_eq!(data.notebook_settings, NotebookSettingsData::default());
+    }
+}
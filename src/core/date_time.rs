@@ -1,14 +1,23 @@
-use chrono::Local;
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, SecondsFormat, TimeZone, Utc, Weekday};
+use gettextrs::{gettext, ngettext};
 use gtk::glib;
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
-/// A boxed [`DateTime<Local>`](chrono::DateTime<Local>)
-#[derive(
-    Debug, Clone, Copy, glib::Boxed, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
-)]
+use std::fmt;
+
+/// A boxed instant, kept in UTC so it is compared by when it happened, whatever offset it
+/// was written with. Only shown in local time.
+///
+/// It is written in RFC 3339 in UTC, like `2022-01-31T13:30:00+00:00`, with the fraction of
+/// the second only when there is one. Besides that, any offset, a date only like
+/// `2022-01-31`, which is the start of that day in local time, and seconds since the Unix
+/// epoch are read, as notes written by older versions and other apps have those.
+#[derive(Debug, Clone, Copy, glib::Boxed, PartialEq, Eq, PartialOrd, Ord)]
 #[boxed_type(name = "NwtyDateTime")]
-#[serde(transparent)]
-pub struct DateTime(chrono::DateTime<Local>);
+pub struct DateTime(chrono::DateTime<Utc>);
 
 impl Default for DateTime {
     fn default() -> Self {
@@ -18,27 +27,443 @@ impl Default for DateTime {
 
 impl DateTime {
     pub fn now() -> Self {
-        Self(Local::now())
+        Self(Utc::now())
+    }
+
+    /// From the seconds since the Unix epoch, like the times of git commits
+    pub fn from_timestamp(seconds: i64) -> Self {
+        Self(Utc.timestamp(seconds, 0))
+    }
+
+    /// Read `text` in any of the formats [`DateTime`] accepts
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(text) {
+            return Some(Self(date_time.with_timezone(&Utc)));
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            let start = date.and_hms(0, 0, 0);
+            // Midnight is skipped in the few timezones that change their offset then
+            let date_time = Local.from_local_datetime(&start).earliest().map_or_else(
+                || Utc.from_utc_datetime(&start),
+                |start| start.with_timezone(&Utc),
+            );
+            return Some(Self(date_time));
+        }
+
+        text.parse().ok().and_then(timestamp)
+    }
+
+    /// The format it is written in, which [`DateTime::parse`] reads back to the same instant
+    pub fn to_rfc3339(self) -> String {
+        self.0.to_rfc3339_opts(SecondsFormat::AutoSi, false)
+    }
+
+    pub fn to_local(self) -> chrono::DateTime<Local> {
+        self.0.with_timezone(&Local)
     }
 
     pub fn fuzzy_display(&self) -> String {
         let now = Local::now();
+        let local = self.to_local();
 
-        let is_today = now.date() == self.0.date();
-        let duration = now.signed_duration_since(self.0);
+        let is_today = now.date() == local.date();
+        let duration = now.signed_duration_since(local);
 
         let hours_difference = duration.num_hours();
         let week_difference = duration.num_weeks();
 
         if is_today {
-            self.0.format("%I∶%M") // 08:10
+            local.format("%I∶%M") // 08:10
         } else if hours_difference <= 30 {
-            self.0.format("yesterday")
+            local.format("yesterday")
         } else if week_difference <= 52 {
-            self.0.format("%b %d") // Sep 03
+            local.format("%b %d") // Sep 03
         } else {
-            self.0.format("%b %d %Y") // Sep 03 1920
+            local.format("%b %d %Y") // Sep 03 1920
         }
         .to_string()
     }
+
+    /// Shown like "Just now", "12∶45", "Yesterday", "Tue", or "Mar 3, 2021", the further it
+    /// is from now
+    pub fn format_relative(&self) -> String {
+        format_relative(self.to_local().into(), Local::now().into())
+    }
+
+    /// How long ago it was, like "2 minutes ago", for times within the last day. Older times
+    /// are shown like [`DateTime::fuzzy_display`].
+    pub fn relative_display(&self) -> String {
+        let minutes = Local::now().signed_duration_since(self.0).num_minutes();
+
+        if minutes < 1 {
+            gettext("just now")
+        } else if minutes < 60 {
+            ngettext!("{} minute ago", "{} minutes ago", minutes as u32, minutes)
+        } else if minutes < 24 * 60 {
+            let hours = minutes / 60;
+            ngettext!("{} hour ago", "{} hours ago", hours as u32, hours)
+        } else {
+            self.fuzzy_display()
+        }
+    }
+}
+
+/// Always written in the format of [`DateTime::to_rfc3339`], so the dates read in another
+/// format are rewritten in it the next time their file is saved. Nothing has to be migrated
+/// before that, as they are read the same.
+impl Serialize for DateTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DateTimeVisitor)
+    }
+}
+
+struct DateTimeVisitor;
+
+impl<'de> Visitor<'de> for DateTimeVisitor {
+    type Value = DateTime;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a date in RFC 3339, a date like `2022-01-31`, or a Unix timestamp")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<DateTime, E> {
+        DateTime::parse(text).ok_or_else(|| E::invalid_value(Unexpected::Str(text), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, seconds: i64) -> Result<DateTime, E> {
+        timestamp(seconds).ok_or_else(|| E::invalid_value(Unexpected::Signed(seconds), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, seconds: u64) -> Result<DateTime, E> {
+        i64::try_from(seconds)
+            .ok()
+            .and_then(timestamp)
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(seconds), &self))
+    }
+}
+
+/// The seconds since the Unix epoch, unless they are too far from it to be a date
+fn timestamp(seconds: i64) -> Option<DateTime> {
+    Utc.timestamp_opt(seconds, 0).single().map(DateTime)
+}
+
+/// Times in the future, like from a device with its clock ahead, are taken as just now
+fn format_relative(
+    time: chrono::DateTime<FixedOffset>,
+    now: chrono::DateTime<FixedOffset>,
+) -> String {
+    if now.signed_duration_since(time).num_minutes() < 1 {
+        return gettext("Just now");
+    }
+
+    // By the dates on the clock at each time, so a change of offset, like for daylight saving
+    // time, does not move the time to another day
+    let date = time.naive_local().date();
+    let days_difference = now
+        .naive_local()
+        .date()
+        .signed_duration_since(date)
+        .num_days();
+
+    match days_difference {
+        0 => time.format("%H∶%M").to_string(),
+        1 => gettext("Yesterday"),
+        2..=6 => weekday_name(date.weekday()),
+        _ => {
+            // Translators: Month, day and year, like "Mar 3, 2021"
+            gettext!(
+                "{} {}, {}",
+                month_name(date.month()),
+                date.day(),
+                date.year()
+            )
+        }
+    }
+}
+
+fn weekday_name(weekday: Weekday) -> String {
+    match weekday {
+        Weekday::Mon => gettext("Mon"),
+        Weekday::Tue => gettext("Tue"),
+        Weekday::Wed => gettext("Wed"),
+        Weekday::Thu => gettext("Thu"),
+        Weekday::Fri => gettext("Fri"),
+        Weekday::Sat => gettext("Sat"),
+        Weekday::Sun => gettext("Sun"),
+    }
+}
+
+fn month_name(month: u32) -> String {
+    match month {
+        1 => gettext("Jan"),
+        2 => gettext("Feb"),
+        3 => gettext("Mar"),
+        4 => gettext("Apr"),
+        5 => gettext("May"),
+        6 => gettext("Jun"),
+        7 => gettext("Jul"),
+        8 => gettext("Aug"),
+        9 => gettext("Sep"),
+        10 => gettext("Oct"),
+        11 => gettext("Nov"),
+        _ => gettext("Dec"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(offset_hours: i32, date_time: &str) -> chrono::DateTime<FixedOffset> {
+        FixedOffset::east(offset_hours * 3600)
+            .datetime_from_str(date_time, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+    }
+
+    /// Offsets of the timezones in use, in minutes, including the ones that are not whole
+    /// hours
+    const OFFSET_MINUTES: [i32; 9] = [
+        -12 * 60,
+        -9 * 60 - 30,
+        -5 * 60,
+        0,
+        60,
+        5 * 60 + 30,
+        5 * 60 + 45,
+        8 * 60,
+        14 * 60,
+    ];
+
+    /// Instants spread from before the Unix epoch to far after now, some with a fraction of a
+    /// second
+    fn instants() -> impl Iterator<Item = chrono::DateTime<Utc>> {
+        (-2_000_000_000_i64..5_000_000_000)
+            .step_by(7_777_777)
+            .enumerate()
+            .map(|(i, seconds)| {
+                let nanos = match i % 3 {
+                    0 => 0,
+                    1 => 250_000_000,
+                    _ => 123_456_789,
+                };
+                Utc.timestamp(seconds, nanos)
+            })
+    }
+
+    fn from_yaml(yaml: &str) -> Result<DateTime, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    #[test]
+    fn round_trip() {
+        for instant in instants() {
+            for offset_minutes in OFFSET_MINUTES {
+                let offset = FixedOffset::east(offset_minutes * 60);
+                let text = instant.with_timezone(&offset).to_rfc3339();
+
+                let date_time = DateTime::parse(&text).unwrap();
+                assert_eq!(date_time.0, instant, "`{}` read wrong", text);
+
+                let written = serde_yaml::to_string(&date_time).unwrap();
+                assert_eq!(from_yaml(&written).unwrap(), date_time, "`{}`", written);
+                assert_eq!(DateTime::parse(&date_time.to_rfc3339()), Some(date_time));
+            }
+        }
+    }
+
+    #[test]
+    fn written_format() {
+        let date_time = DateTime::parse("2022-01-31T14:30:00+01:00").unwrap();
+        assert_eq!(date_time.to_rfc3339(), "2022-01-31T13:30:00+00:00");
+        assert_eq!(
+            serde_yaml::to_value(&date_time).unwrap(),
+            serde_yaml::Value::from("2022-01-31T13:30:00+00:00")
+        );
+
+        let date_time = DateTime::parse("2022-01-31T14:30:00.25Z").unwrap();
+        assert_eq!(date_time.to_rfc3339(), "2022-01-31T14:30:00.250+00:00");
+    }
+
+    #[test]
+    fn legacy_formats() {
+        let expected = DateTime::parse("2022-01-02T02:00:00Z").unwrap();
+
+        // How older versions wrote it, in local time with nanoseconds
+        assert_eq!(
+            from_yaml("\"2022-01-02T10:00:00.000000000+08:00\"").unwrap(),
+            expected
+        );
+        assert_eq!(from_yaml("2022-01-02T02:00:00+00:00").unwrap(), expected);
+        assert_eq!(from_yaml("1641088800").unwrap(), expected);
+        assert_eq!(from_yaml("\"1641088800\"").unwrap(), expected);
+        assert_eq!(from_yaml("0").unwrap(), DateTime::from_timestamp(0));
+        assert_eq!(
+            from_yaml("-86400").unwrap(),
+            DateTime::from_timestamp(-86400)
+        );
+
+        let start_of_day = Local
+            .from_local_datetime(&NaiveDate::from_ymd(2022, 1, 31).and_hms(0, 0, 0))
+            .earliest()
+            .unwrap();
+        assert_eq!(from_yaml("2022-01-31").unwrap().to_local(), start_of_day);
+        assert_eq!(
+            from_yaml("\" 2022-01-31 \"").unwrap().to_local(),
+            start_of_day
+        );
+
+        // Rewritten in the current format
+        let written = serde_yaml::to_value(&from_yaml("1641088800").unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_yaml::Value::from("2022-01-02T02:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for yaml in [
+            "last tuesday",
+            "\"\"",
+            "2022-13-01",
+            "\"2022-01-31T25:00:00+00:00\"",
+            "true",
+            "1.5",
+            "[2022]",
+            "99999999999999999999",
+        ] {
+            assert!(from_yaml(yaml).is_err(), "`{}` was read", yaml);
+        }
+        assert_eq!(DateTime::parse("9223372036854775807"), None);
+    }
+
+    #[test]
+    fn ordering_across_offsets() {
+        // Later on the clock, but earlier as it is further east
+        let east = DateTime::parse("2022-01-01T10:00:00+08:00").unwrap();
+        let west = DateTime::parse("2022-01-01T03:00:00+00:00").unwrap();
+        assert!(east < west);
+        assert!("2022-01-01T10:00:00+08:00" > "2022-01-01T03:00:00+00:00");
+
+        // The same instant with any offset
+        let same = DateTime::parse("2021-12-31T21:00:00-05:00").unwrap();
+        assert_eq!(same, DateTime::parse("2022-01-01T02:00:00Z").unwrap());
+        assert_eq!(same.cmp(&east), std::cmp::Ordering::Equal);
+
+        let instants = instants().collect::<Vec<_>>();
+        for (a, b) in instants.iter().zip(instants.iter().skip(1).rev()) {
+            for (offset_a, offset_b) in OFFSET_MINUTES.iter().zip(OFFSET_MINUTES.iter().rev()) {
+                let a_text = a
+                    .with_timezone(&FixedOffset::east(offset_a * 60))
+                    .to_rfc3339();
+                let b_text = b
+                    .with_timezone(&FixedOffset::east(offset_b * 60))
+                    .to_rfc3339();
+                assert_eq!(
+                    DateTime::parse(&a_text).cmp(&DateTime::parse(&b_text)),
+                    a.cmp(b),
+                    "`{}` and `{}`",
+                    a_text,
+                    b_text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn format_relative_just_now() {
+        let now = at(0, "2021-03-03 12:45:30");
+
+        assert_eq!(format_relative(now, now), "Just now");
+        assert_eq!(
+            format_relative(at(0, "2021-03-03 12:44:31"), now),
+            "Just now"
+        );
+        // Ahead of this device
+        assert_eq!(
+            format_relative(at(0, "2021-03-03 14:00:00"), now),
+            "Just now"
+        );
+        assert_eq!(
+            format_relative(at(0, "2022-01-01 00:00:00"), now),
+            "Just now"
+        );
+    }
+
+    #[test]
+    fn format_relative_today() {
+        let now = at(0, "2021-03-03 12:45:30");
+
+        assert_eq!(format_relative(at(0, "2021-03-03 12:44:30"), now), "12∶44");
+        assert_eq!(format_relative(at(0, "2021-03-03 00:00:00"), now), "00∶00");
+    }
+
+    #[test]
+    fn format_relative_midnight() {
+        let now = at(0, "2021-03-04 00:00:00");
+
+        assert_eq!(
+            format_relative(at(0, "2021-03-03 23:59:00"), now),
+            "Yesterday"
+        );
+        assert_eq!(format_relative(at(0, "2021-03-02 23:59:59"), now), "Tue");
+    }
+
+    #[test]
+    fn format_relative_last_week() {
+        let now = at(0, "2021-03-10 09:00:00");
+
+        assert_eq!(
+            format_relative(at(0, "2021-03-09 09:00:00"), now),
+            "Yesterday"
+        );
+        assert_eq!(format_relative(at(0, "2021-03-08 23:00:00"), now), "Mon");
+        assert_eq!(format_relative(at(0, "2021-03-04 00:00:00"), now), "Thu");
+        assert_eq!(
+            format_relative(at(0, "2021-03-03 23:59:59"), now),
+            "Mar 3, 2021"
+        );
+    }
+
+    #[test]
+    fn format_relative_year_boundary() {
+        let now = at(0, "2022-01-01 00:30:00");
+
+        assert_eq!(
+            format_relative(at(0, "2021-12-31 23:30:00"), now),
+            "Yesterday"
+        );
+        assert_eq!(format_relative(at(0, "2021-12-27 12:00:00"), now), "Mon");
+        assert_eq!(
+            format_relative(at(0, "2020-12-31 12:00:00"), now),
+            "Dec 31, 2020"
+        );
+    }
+
+    #[test]
+    fn format_relative_daylight_saving_time() {
+        // The clock went from 02:00 to 03:00, so an hour later it is still the same day
+        assert_eq!(
+            format_relative(at(1, "2021-03-28 01:30:00"), at(2, "2021-03-28 03:30:00")),
+            "01∶30"
+        );
+        // Back from 03:00 to 02:00, so only an hour passed since the day before
+        assert_eq!(
+            format_relative(at(2, "2021-10-30 23:30:00"), at(1, "2021-10-31 00:30:00")),
+            "Yesterday"
+        );
+        assert_eq!(
+            format_relative(at(2, "2021-10-31 02:30:00"), at(1, "2021-10-31 02:10:00")),
+            "02∶30"
+        );
+    }
 }
@@ -0,0 +1,231 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+/// How much of their content notes must share to be near duplicates, from 0 to 1
+pub const NEAR_DUPLICATE_THRESHOLD: f64 = 0.8;
+
+/// Words in a row that are compared at a time to measure how much content is shared
+const SHINGLE_WORDS: usize = 4;
+
+/// `content` with its whitespace collapsed into single spaces, so notes only differing in
+/// their line breaks or indentation are the same.
+///
+/// It is the body of a note without its front matter, so notes with the same body but other
+/// titles or tags are also the same.
+pub fn normalize(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn content_hash(content: &str) -> u64 {
+    hash(&normalize(content))
+}
+
+/// Indices of the `contents` that are the same once normalized, in groups of at least two.
+/// Empty contents are never duplicates.
+pub fn duplicate_groups(contents: &[impl AsRef<str>]) -> Vec<Vec<usize>> {
+    let mut indices_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut hashes = Vec::new();
+
+    for (index, content) in contents.iter().enumerate() {
+        if content.as_ref().trim().is_empty() {
+            continue;
+        }
+
+        let hash = content_hash(content.as_ref());
+        let indices = indices_by_hash.entry(hash).or_default();
+        if indices.is_empty() {
+            hashes.push(hash);
+        }
+        indices.push(index);
+    }
+
+    // In the order the first of each group appears
+    hashes
+        .into_iter()
+        .filter_map(|hash| indices_by_hash.remove(&hash))
+        .filter(|indices| indices.len() > 1)
+        .collect()
+}
+
+/// Like [`duplicate_groups`], but also grouping the `contents` that share at least
+/// `threshold` of their shingles, which are their words taken [`SHINGLE_WORDS`] at a time.
+///
+/// Every pair of contents is compared, so this is much slower.
+pub fn near_duplicate_groups(contents: &[impl AsRef<str>], threshold: f64) -> Vec<Vec<usize>> {
+    let shingles = contents
+        .iter()
+        .map(|content| shingles(&normalize(content.as_ref())))
+        .collect::<Vec<_>>();
+
+    // Each index points to another in its group, up to the first of the group
+    let mut parents = (0..contents.len()).collect::<Vec<_>>();
+
+    for a in 0..shingles.len() {
+        for b in (a + 1)..shingles.len() {
+            if shingles[a].is_empty() || similarity(&shingles[a], &shingles[b]) < threshold {
+                continue;
+            }
+
+            let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+            parents[root_a.max(root_b)] = root_a.min(root_b);
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root = HashMap::new();
+
+    for index in 0..parents.len() {
+        let root = root(&mut parents, index);
+        let group_index = *group_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group_index].push(index);
+    }
+
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// The first index of the group of `index`, pointing the indices on the way closer to it
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// Hashes of the words of `normalized` taken [`SHINGLE_WORDS`] at a time, or of all of them
+/// when there are fewer
+fn shingles(normalized: &str) -> HashSet<u64> {
+    if normalized.is_empty() {
+        return HashSet::new();
+    }
+
+    let words = normalized.split(' ').collect::<Vec<_>>();
+
+    if words.len() < SHINGLE_WORDS {
+        return HashSet::from([hash(&words)]);
+    }
+
+    words
+        .windows(SHINGLE_WORDS)
+        .map(|shingle| hash(&shingle))
+        .collect()
+}
+
+/// Jaccard index of `a` and `b`, the shingles they share out of all of them
+fn similarity(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let n_shared = a.intersection(b).count();
+    let n_all = a.len() + b.len() - n_shared;
+
+    if n_all == 0 {
+        return 0.0;
+    }
+
+    n_shared as f64 / n_all as f64
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GROCERIES: &str = "# Groceries\n\n- Milk\n- Eggs\n- Bread\n";
+
+    #[test]
+    fn normalize_whitespace() {
+        assert_eq!(
+            normalize("  # Groceries\r\n\n- Milk\t\n  - Eggs  "),
+            "# Groceries - Milk - Eggs"
+        );
+        assert_eq!(
+            content_hash(GROCERIES),
+            content_hash("# Groceries\n- Milk\n- Eggs\n- Bread")
+        );
+        assert_ne!(
+            content_hash(GROCERIES),
+            content_hash("# Groceries\n- Milk\n- Bread\n- Eggs")
+        );
+    }
+
+    #[test]
+    fn groups() {
+        let contents = [
+            GROCERIES,
+            "Other",
+            "",
+            "# Groceries\n- Milk\n- Eggs\n- Bread",
+            " ",
+            "other\n",
+            "Other\n\n",
+            GROCERIES,
+        ];
+
+        assert_eq!(duplicate_groups(&contents), [vec![0, 3, 7], vec![1, 6]]);
+        assert!(duplicate_groups(&["A", "B"]).is_empty());
+    }
+
+    #[test]
+    fn near_groups() {
+        let long_text = "The quick brown fox jumps over the lazy dog while the cat sleeps on \
+            the warm windowsill and the birds sing outside in the tall green trees";
+        let contents = [
+            long_text.to_string(),
+            "Something else entirely".to_string(),
+            format!("{} today", long_text),
+            long_text.replace("cat", "kitten"),
+            String::new(),
+            String::new(),
+            "Something else entirely".to_string(),
+        ];
+
+        assert_eq!(
+            near_duplicate_groups(&contents, NEAR_DUPLICATE_THRESHOLD),
+            [vec![0, 2], vec![1, 6]]
+        );
+        // A changed word changes every shingle it is in
+        assert_eq!(
+            near_duplicate_groups(&contents, 0.5),
+            [vec![0, 2, 3], vec![1, 6]]
+        );
+    }
+
+    #[test]
+    fn near_groups_are_transitive() {
+        let words = (0..40).map(|n| format!("word{}", n)).collect::<Vec<_>>();
+        let contents = [
+            words[..36].join(" "),
+            words[2..38].join(" "),
+            words[4..40].join(" "),
+        ];
+
+        // The first and the last are only grouped through the middle one
+        assert!(
+            similarity(&shingles(&contents[0]), &shingles(&contents[2])) < NEAR_DUPLICATE_THRESHOLD
+        );
+        assert_eq!(
+            near_duplicate_groups(&contents, NEAR_DUPLICATE_THRESHOLD),
+            [vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn similarity_of_shingles() {
+        let a = shingles(&normalize("one two three four five"));
+        let b = shingles(&normalize("one two three four six"));
+
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert!((similarity(&a, &b) - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(similarity(&a, &HashSet::new()), 0.0);
+        assert_eq!(shingles("short text").len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+use std::path::{Path, PathBuf};
+
+/// Name of the file in the root of the notes with the patterns of the files to leave out
+pub const EXCLUSIONS_FILE_NAME: &str = ".nwtyignore";
+
+/// Written when the notes have no exclusions file yet
+pub const DEFAULT_EXCLUSIONS: &str = "\
+# Files that are neither loaded as notes nor committed, in the syntax of .gitignore
+*.tmp
+.#*
+*~
+";
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid pattern `{pattern}` on line {line}")]
+pub struct PatternError {
+    pub line: usize,
+    pub pattern: String,
+    #[source]
+    source: globset::Error,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    matcher: GlobMatcher,
+    is_negated: bool,
+    is_dir_only: bool,
+}
+
+impl Rule {
+    fn parse(pattern: &str) -> Result<Self, globset::Error> {
+        let (is_negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let (is_dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+
+        // A pattern with a slash before its end is relative to the root, otherwise it matches
+        // at any depth
+        let glob = match pattern.strip_prefix('/') {
+            Some(pattern) => pattern.to_string(),
+            None if pattern.contains('/') => pattern.to_string(),
+            None => format!("**/{}", pattern),
+        };
+
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()?
+            .compile_matcher();
+
+        Ok(Self {
+            matcher,
+            is_negated,
+            is_dir_only,
+        })
+    }
+}
+
+/// Files that are left out of the notes and the commits, from patterns in the syntax of
+/// `.gitignore`
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    rules: Vec<Rule>,
+}
+
+impl Exclusions {
+    /// The exclusions of the patterns in `text`, one per line. The invalid ones are skipped,
+    /// and returned as errors, so the others are still used.
+    pub fn parse(text: &str) -> (Self, Vec<PatternError>) {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let pattern = line.trim_end();
+
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+
+            match Rule::parse(pattern) {
+                Ok(rule) => rules.push(rule),
+                Err(source) => errors.push(PatternError {
+                    line: index + 1,
+                    pattern: pattern.to_string(),
+                    source,
+                }),
+            }
+        }
+
+        (Self { rules }, errors)
+    }
+
+    /// Whether the file at `path`, relative to the root of the notes, is left out. Like in
+    /// `.gitignore`, everything in an excluded directory is, even if a later pattern includes
+    /// it again.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+
+        let mut ancestor = PathBuf::new();
+        let mut components = path.components().peekable();
+
+        while let Some(component) = components.next() {
+            ancestor.push(component);
+
+            if components.peek().is_none() {
+                return self.matches(&ancestor, is_dir);
+            }
+
+            if self.matches(&ancestor, true) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// The pattern that excludes only the file at `path`, relative to the root of the notes,
+    /// with the characters that are special in patterns escaped
+    pub fn path_pattern(path: &Path) -> String {
+        let mut pattern = String::from("/");
+
+        for character in path.to_string_lossy().chars() {
+            if matches!(character, '\\' | '*' | '?' | '[' | ']' | '{' | '}') {
+                pattern.push('\\');
+            }
+            pattern.push(character);
+        }
+
+        pattern
+    }
+
+    /// Whether the last pattern that matches `path` excludes it
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.is_dir_only) && rule.matcher.is_match(path))
+            .map_or(false, |rule| !rule.is_negated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exclusions(text: &str) -> Exclusions {
+        let (exclusions, errors) = Exclusions::parse(text);
+        assert!(errors.is_empty(), "{:?}", errors);
+        exclusions
+    }
+
+    fn is_excluded(exclusions: &Exclusions, path: &str) -> bool {
+        exclusions.is_excluded(Path::new(path), false)
+    }
+
+    #[test]
+    fn default_patterns() {
+        let exclusions = exclusions(DEFAULT_EXCLUSIONS);
+
+        assert!(is_excluded(&exclusions, "Draft.tmp"));
+        assert!(is_excluded(&exclusions, ".#Note.md"));
+        assert!(is_excluded(&exclusions, "Note.md~"));
+        assert!(is_excluded(&exclusions, "scripts/build.tmp"));
+
+        assert!(!is_excluded(&exclusions, "Note.md"));
+        assert!(!is_excluded(&exclusions, "tmp.md"));
+        assert!(!is_excluded(&exclusions, EXCLUSIONS_FILE_NAME));
+    }
+
+    #[test]
+    fn nested_paths() {
+        let exclusions = exclusions("archive-raw/\n/scripts\ndocs/*.md\n*.log\n");
+
+        // Everything in a directory, at any depth
+        assert!(is_excluded(&exclusions, "archive-raw/Old.md"));
+        assert!(is_excluded(&exclusions, "archive-raw/2021/03/Old.md"));
+        assert!(is_excluded(&exclusions, "notes/archive-raw/Old.md"));
+        assert!(exclusions.is_excluded(Path::new("archive-raw"), true));
+        assert!(!is_excluded(&exclusions, "archive-raw"));
+
+        // Only in the root
+        assert!(is_excluded(&exclusions, "scripts/sync.sh"));
+        assert!(!is_excluded(&exclusions, "notes/scripts/sync.sh"));
+
+        // Wildcards don't match slashes
+        assert!(is_excluded(&exclusions, "docs/Guide.md"));
+        assert!(!is_excluded(&exclusions, "docs/guides/Guide.md"));
+        assert!(!is_excluded(&exclusions, "notes/docs/Guide.md"));
+
+        assert!(is_excluded(&exclusions, "a/b/c/debug.log"));
+    }
+
+    #[test]
+    fn negation() {
+        let exclusions = exclusions("*.md\n!Keep.md\nprivate/\n!private/Shared.md\n");
+
+        assert!(is_excluded(&exclusions, "Note.md"));
+        assert!(!is_excluded(&exclusions, "Keep.md"));
+        assert!(!is_excluded(&exclusions, "notes/Keep.md"));
+
+        // Nothing in an excluded directory can be included again
+        assert!(is_excluded(&exclusions, "private/Shared.md"));
+    }
+
+    #[test]
+    fn path_pattern() {
+        let pattern = Exclusions::path_pattern(Path::new("drafts/Rome.md"));
+        assert_eq!(pattern, "/drafts/Rome.md");
+        let path_exclusions = exclusions(&pattern);
+        assert!(is_excluded(&path_exclusions, "drafts/Rome.md"));
+        assert!(!is_excluded(&path_exclusions, "Rome.md"));
+        assert!(!is_excluded(&path_exclusions, "notes/drafts/Rome.md"));
+
+        let pattern = Exclusions::path_pattern(Path::new("[Draft] *Rome?.md"));
+        assert_eq!(pattern, "/\\[Draft\\] \\*Rome\\?.md");
+        let path_exclusions = exclusions(&pattern);
+        assert!(is_excluded(&path_exclusions, "[Draft] *Rome?.md"));
+        assert!(!is_excluded(&path_exclusions, "D Rome1.md"));
+        assert!(!is_excluded(&path_exclusions, "[Draft] My Rome!.md"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        let (exclusions, errors) = Exclusions::parse("# Comment\n\n*.tmp\nnotes/[abc.md\n*~\n");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+        assert_eq!(errors[0].pattern, "notes/[abc.md");
+
+        // The others are still used
+        assert!(is_excluded(&exclusions, "Draft.tmp"));
+        assert!(is_excluded(&exclusions, "Note.md~"));
+        assert!(!is_excluded(&exclusions, "# Comment"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(!is_excluded(&Exclusions::default(), "Note.md"));
+        assert!(!is_excluded(&exclusions(""), "Note.md"));
+    }
+}
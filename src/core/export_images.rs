@@ -0,0 +1,428 @@
+use gettextrs::gettext;
+use gtk::{gio, glib};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use super::vault_import::{is_external, percent_decode, unique_file_name};
+
+/// Images up to this size, in bytes, are put in the HTML that is copied, and larger ones are
+/// linked to, as apps that paste it may not take large data
+pub const MAX_COPIED_IMAGE_SIZE: u64 = 1024 * 1024;
+
+/// Largest width and height, in pixels, that images are put in PDFs at
+pub const MAX_PDF_IMAGE_SIZE: i32 = 2048;
+
+/// Drawn like a box in the apps that paste HTML, which leave out stylesheets
+const PLACEHOLDER_STYLE: &str =
+    "display: inline-block; padding: 1em; border: 1px dashed #999; color: #666;";
+
+/// `<img src="…" alt="…">`, as rendered by [`markdown_to_html`](super::markdown_to_html)
+static RE_IMAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<img src="([^"]*)" alt="([^"]*)">"#).unwrap());
+
+/// How the images of exported HTML are kept showing outside of the notes folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageEmbedding {
+    /// In the HTML as data URIs, so it is a single file
+    DataUri,
+    /// Copied to the folder `dir_name` next to the HTML file
+    CopyAlongside { dir_name: String },
+    /// As data URIs when smaller than `max_size` bytes, and linked to with `file://` otherwise
+    DataUriUpTo { max_size: u64 },
+}
+
+/// What the source of an image of the HTML is replaced with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    Url(String),
+    /// The file of the image doesn't exist, so a box with its name is shown in its place
+    Missing,
+}
+
+/// The HTML to export, with the files to write along with it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExportedHtml {
+    pub html: String,
+    /// Files to copy from their path to the one relative to the HTML file
+    pub copies: Vec<(PathBuf, String)>,
+    /// Sources of the images whose file is missing
+    pub missing_images: Vec<String>,
+}
+
+/// The sources of the images of `html`, each once, in the order they are shown
+pub fn image_sources(html: &str) -> Vec<String> {
+    let mut sources = Vec::new();
+
+    for captures in RE_IMAGE.captures_iter(html) {
+        let source = unescape(&captures[1]);
+
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+
+    sources
+}
+
+/// `html` with the source of each of its images replaced with the one in `sources`, and the
+/// missing ones replaced with a placeholder. Images that are not in `sources` are kept as is.
+pub fn rewrite_image_sources(html: &str, sources: &HashMap<String, ImageSource>) -> String {
+    RE_IMAGE
+        .replace_all(html, |captures: &Captures| {
+            let source = unescape(&captures[1]);
+
+            match sources.get(&source) {
+                Some(ImageSource::Url(url)) => {
+                    format!("<img src=\"{}\" alt=\"{}\">", escape(url), &captures[2])
+                }
+                Some(ImageSource::Missing) => {
+                    let alt = unescape(&captures[2]);
+                    let label = if alt.trim().is_empty() {
+                        file_name_of(&source)
+                    } else {
+                        alt
+                    };
+
+                    format!(
+                        "<span class=\"missing-image\" style=\"{}\">{}</span>",
+                        PLACEHOLDER_STYLE,
+                        escape(&gettext!("Missing image: {}", label))
+                    )
+                }
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// The file of the image at `source` in a note of `notes_dir`, or `None` if it is not in
+/// the folder, like an image on the web
+pub fn image_path(source: &str, notes_dir: &Path) -> Option<PathBuf> {
+    if is_external(source) || source.starts_with("data:") {
+        return None;
+    }
+
+    let decoded = percent_decode(source.split('#').next().unwrap_or_default());
+    if decoded.is_empty() {
+        return None;
+    }
+
+    let path = notes_dir.join(decoded);
+    let is_in_notes_dir = path.starts_with(notes_dir)
+        && !path
+            .components()
+            .any(|component| component == Component::ParentDir);
+    is_in_notes_dir.then_some(path)
+}
+
+/// Folder that the images are copied to, next to the HTML file named `file_name`, like
+/// browsers do when saving a page
+pub fn images_dir_name(file_name: &str) -> String {
+    let stem = Path::new(file_name).file_stem().map_or_else(
+        || file_name.to_string(),
+        |stem| stem.to_string_lossy().to_string(),
+    );
+    format!("{}_files", stem)
+}
+
+/// `width` by `height` scaled down to fit in `max_width` by `max_height`, keeping its aspect
+/// ratio. Sizes that already fit are kept as is.
+pub fn fitted_size(width: f64, height: f64, max_width: f64, max_height: f64) -> (f64, f64) {
+    if width <= 0.0 || height <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let scale = (max_width / width).min(max_height / height).min(1.0);
+    (width * scale, height * scale)
+}
+
+/// `html`, rendered from notes in `notes_dir`, with its images showing where it is exported
+/// as told by `embedding`. This reads the images, so it is done off the main thread.
+pub fn embed_images(html: &str, notes_dir: &Path, embedding: &ImageEmbedding) -> ExportedHtml {
+    let mut sources = HashMap::new();
+    let mut copies = Vec::new();
+    let mut used_file_names = HashSet::new();
+    let mut missing_images = Vec::new();
+
+    for source in image_sources(html) {
+        let path = match image_path(&source, notes_dir) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let size = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => {
+                log::warn!("Image `{}` to export is missing", path.display());
+                missing_images.push(source.clone());
+                sources.insert(source, ImageSource::Missing);
+                continue;
+            }
+        };
+
+        let image_source = match embedding {
+            ImageEmbedding::CopyAlongside { dir_name } => {
+                let file_name = unique_file_name(&path, &used_file_names);
+                used_file_names.insert(file_name.clone());
+
+                let copy_path = format!("{}/{}", dir_name, file_name);
+                copies.push((path, copy_path.clone()));
+                ImageSource::Url(encode_path(&copy_path))
+            }
+            ImageEmbedding::DataUriUpTo { max_size } if size >= *max_size => {
+                ImageSource::Url(gio::File::for_path(&path).uri().to_string())
+            }
+            ImageEmbedding::DataUri | ImageEmbedding::DataUriUpTo { .. } => match data_uri(&path) {
+                Ok(uri) => ImageSource::Url(uri),
+                Err(err) => {
+                    log::warn!("Failed to read image `{}`: {:?}", path.display(), err);
+                    missing_images.push(source.clone());
+                    ImageSource::Missing
+                }
+            },
+        };
+        sources.insert(source, image_source);
+    }
+
+    ExportedHtml {
+        html: rewrite_image_sources(html, &sources),
+        copies,
+        missing_images,
+    }
+}
+
+fn data_uri(path: &Path) -> io::Result<String> {
+    let data = fs::read(path)?;
+    let (content_type, _) = gio::content_type_guess(Some(path), &data);
+    let mime_type = gio::content_type_get_mime_type(&content_type).map_or_else(
+        || "application/octet-stream".to_string(),
+        |mime| mime.to_string(),
+    );
+
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type,
+        glib::base64_encode(&data)
+    ))
+}
+
+fn file_name_of(source: &str) -> String {
+    let decoded = percent_decode(source.split('#').next().unwrap_or_default());
+    Path::new(&decoded).file_name().map_or_else(
+        || decoded.clone(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Escape the characters of `path` that would change the meaning of a URL
+fn encode_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::core::markdown_to_html;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "noteworthy-export-images-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sources_of_images() {
+        let html = markdown_to_html(
+            "![Camera](Camera.png) [Link](Link.png)\n\n\
+            ![Again](Camera.png) ![Scan](<Scan 2.png>) ![Tom & Jerry](a&b.png)\n\n\
+            ```\n![In code](Code.png)\n```",
+        );
+
+        assert_eq!(
+            image_sources(&html),
+            vec!["Camera.png", "Scan 2.png", "a&b.png"]
+        );
+        assert!(image_sources("<p>No images</p>").is_empty());
+    }
+
+    #[test]
+    fn rewrite_sources() {
+        let html =
+            markdown_to_html("![Camera](Camera.png) ![](Scan.png)\n\n![Web](https://a.b/c.png)");
+        let sources = [
+            (
+                "Camera.png".to_string(),
+                ImageSource::Url("images/Camera.png".to_string()),
+            ),
+            ("Scan.png".to_string(), ImageSource::Missing),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        assert_eq!(
+            rewrite_image_sources(&html, &sources),
+            format!(
+                "<p><img src=\"images/Camera.png\" alt=\"Camera\"> \
+                <span class=\"missing-image\" style=\"{}\">Missing image: Scan.png</span></p>\n\
+                <p><img src=\"https://a.b/c.png\" alt=\"Web\"></p>\n",
+                PLACEHOLDER_STYLE
+            )
+        );
+
+        // Kept as is without sources
+        assert_eq!(rewrite_image_sources(&html, &HashMap::new()), html);
+    }
+
+    #[test]
+    fn rewrite_escaped() {
+        let html = markdown_to_html("![<Tom> & \"Jerry\"](a&b.png)");
+        let sources = [(
+            "a&b.png".to_string(),
+            ImageSource::Url("data:image/png;base64,AA==".to_string()),
+        )]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        assert_eq!(
+            rewrite_image_sources(&html, &sources),
+            "<p><img src=\"data:image/png;base64,AA==\" alt=\"&lt;Tom&gt; &amp; &quot;Jerry&quot;\"></p>\n"
+        );
+
+        let sources = [("a&b.png".to_string(), ImageSource::Missing)]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        assert!(rewrite_image_sources(&html, &sources)
+            .contains(">Missing image: &lt;Tom&gt; &amp; &quot;Jerry&quot;</span>"));
+    }
+
+    #[test]
+    fn paths_of_images() {
+        let notes_dir = Path::new("/home/user/Notes");
+
+        assert_eq!(
+            image_path("Camera.png", notes_dir),
+            Some(notes_dir.join("Camera.png"))
+        );
+        assert_eq!(
+            image_path("Scan%202.png#page=2", notes_dir),
+            Some(notes_dir.join("Scan 2.png"))
+        );
+        assert_eq!(
+            image_path("/home/user/Notes/Camera.png", notes_dir),
+            Some(notes_dir.join("Camera.png"))
+        );
+
+        // Only the ones in the notes folder
+        assert_eq!(image_path("https://example.com/a.png", notes_dir), None);
+        assert_eq!(image_path("data:image/png;base64,AA==", notes_dir), None);
+        assert_eq!(image_path("../Secret.png", notes_dir), None);
+        assert_eq!(image_path("/etc/Secret.png", notes_dir), None);
+        assert_eq!(image_path("", notes_dir), None);
+    }
+
+    #[test]
+    fn images_dir_names() {
+        assert_eq!(images_dir_name("Notes.html"), "Notes_files");
+        assert_eq!(images_dir_name("Trip 2022.html"), "Trip 2022_files");
+        assert_eq!(images_dir_name("Notes"), "Notes_files");
+    }
+
+    #[test]
+    fn fitted_sizes() {
+        assert_eq!(fitted_size(100.0, 50.0, 400.0, 400.0), (100.0, 50.0));
+        assert_eq!(fitted_size(800.0, 400.0, 400.0, 400.0), (400.0, 200.0));
+        assert_eq!(fitted_size(400.0, 800.0, 400.0, 300.0), (150.0, 300.0));
+        assert_eq!(fitted_size(0.0, 10.0, 400.0, 400.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn embed() {
+        let dir = temp_dir("embed");
+        fs::write(dir.join("Small.png"), [1, 2, 3]).unwrap();
+        fs::write(dir.join("Large.png"), [0; 16]).unwrap();
+        let html = markdown_to_html("![Small](Small.png) ![Large](Large.png) ![Gone](Gone.png)");
+
+        let exported = embed_images(&html, &dir, &ImageEmbedding::DataUriUpTo { max_size: 8 });
+        assert!(exported.html.contains(";base64,AQID\" alt=\"Small\">"));
+        assert!(exported.html.contains(&format!(
+            "<img src=\"{}\" alt=\"Large\">",
+            gio::File::for_path(dir.join("Large.png")).uri()
+        )));
+        assert!(exported.html.contains(">Missing image: Gone</span>"));
+        assert!(exported.copies.is_empty());
+        assert_eq!(exported.missing_images, vec!["Gone.png"]);
+
+        let exported = embed_images(&html, &dir, &ImageEmbedding::DataUri);
+        assert_eq!(exported.html.matches(";base64,").count(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn copy_alongside() {
+        let dir = temp_dir("copy-alongside");
+        fs::create_dir_all(dir.join("Folder")).unwrap();
+        fs::write(dir.join("Camera Roll.png"), [1]).unwrap();
+        fs::write(dir.join("Folder/Camera Roll.png"), [2]).unwrap();
+        let html = markdown_to_html(
+            "![A](<Camera Roll.png>) ![B](<Folder/Camera Roll.png>) ![Gone](Gone.png)",
+        );
+
+        let exported = embed_images(
+            &html,
+            &dir,
+            &ImageEmbedding::CopyAlongside {
+                dir_name: "Notes_files".to_string(),
+            },
+        );
+        assert_eq!(
+            exported.copies,
+            vec![
+                (
+                    dir.join("Camera Roll.png"),
+                    "Notes_files/Camera Roll.png".to_string()
+                ),
+                (
+                    dir.join("Folder/Camera Roll.png"),
+                    "Notes_files/Camera Roll-2.png".to_string()
+                ),
+            ]
+        );
+        assert!(exported
+            .html
+            .contains("<img src=\"Notes_files/Camera%20Roll-2.png\" alt=\"B\">"));
+        assert_eq!(exported.missing_images, vec!["Gone.png"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
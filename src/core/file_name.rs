@@ -0,0 +1,134 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use std::collections::HashSet;
+
+/// Used when nothing of the given stem is left
+const FALLBACK_STEM: &str = "Note";
+
+/// `stem` with only ASCII letters, numbers, `-` and `_`, so it is the same file on every
+/// filesystem. Accents are dropped and anything else is turned into a dash.
+pub fn safe_file_stem(stem: &str) -> String {
+    let mut safe_stem = String::with_capacity(stem.len());
+
+    for c in stem.nfd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            safe_stem.push(c);
+        } else if !safe_stem.is_empty() && !safe_stem.ends_with('-') {
+            safe_stem.push('-');
+        }
+    }
+
+    let safe_stem = safe_stem.trim_end_matches('-');
+
+    if safe_stem.is_empty() {
+        FALLBACK_STEM.to_string()
+    } else {
+        safe_stem.to_string()
+    }
+}
+
+/// `extension` if it is safe to use, like those of [`safe_file_stem`]
+pub fn safe_file_extension(extension: &str) -> Option<String> {
+    if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Some(extension.to_string())
+    } else {
+        None
+    }
+}
+
+/// A file name from `stem` and `extension` that is not in `existing_names`, which have to be
+/// in lowercase. A number is added to the stem if needed, as the names are compared regardless
+/// of case, like in case-insensitive filesystems.
+pub fn unique_file_name(
+    stem: &str,
+    extension: Option<&str>,
+    existing_names: &HashSet<String>,
+) -> String {
+    let file_name = |stem: &str| match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    };
+    let is_taken = |file_name: &str| existing_names.contains(&file_name.to_lowercase());
+
+    let first = file_name(stem);
+
+    if !is_taken(&first) {
+        return first;
+    }
+
+    (2..)
+        .map(|n| file_name(&format!("{}-{}", stem, n)))
+        .find(|file_name| !is_taken(file_name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn safe_file_stems() {
+        assert_eq!(safe_file_stem("Note"), "Note");
+        assert_eq!(safe_file_stem("Audio_Recording-2"), "Audio_Recording-2");
+        assert_eq!(safe_file_stem("Meeting notes"), "Meeting-notes");
+        assert_eq!(safe_file_stem("  a / b:c  "), "a-b-c");
+        assert_eq!(safe_file_stem("../../etc"), "etc");
+        assert_eq!(safe_file_stem(""), "Note");
+        assert_eq!(safe_file_stem("日記"), "Note");
+    }
+
+    #[test]
+    fn safe_file_stems_unicode() {
+        // Composed and decomposed forms give the same stem
+        assert_eq!(safe_file_stem("Caf\u{e9}"), "Cafe");
+        assert_eq!(safe_file_stem("Cafe\u{301}"), "Cafe");
+        assert_eq!(safe_file_stem("\u{c5}ngstr\u{f6}m"), "Angstrom");
+        assert_eq!(safe_file_stem("A\u{30a}ngstro\u{308}m"), "Angstrom");
+    }
+
+    #[test]
+    fn safe_file_stems_emoji() {
+        assert_eq!(safe_file_stem("🎉 Party 🎉"), "Party");
+        assert_eq!(safe_file_stem("Plan 📝 2024"), "Plan-2024");
+        assert_eq!(safe_file_stem("🇯🇵 Trip"), "Trip");
+        assert_eq!(safe_file_stem("👩‍👩‍👧"), "Note");
+        assert_eq!(safe_file_stem("❤️"), "Note");
+    }
+
+    #[test]
+    fn safe_file_extensions() {
+        assert_eq!(safe_file_extension("md"), Some("md".into()));
+        assert_eq!(safe_file_extension("OGG"), Some("OGG".into()));
+        assert_eq!(safe_file_extension(""), None);
+        assert_eq!(safe_file_extension("tar.gz"), None);
+        assert_eq!(safe_file_extension("m\u{e9}"), None);
+    }
+
+    #[test]
+    fn unique_file_names() {
+        let existing_names = HashSet::from([
+            "note.md".to_string(),
+            "note-2.md".to_string(),
+            "camera".to_string(),
+        ]);
+
+        assert_eq!(
+            unique_file_name("Other", Some("md"), &existing_names),
+            "Other.md"
+        );
+        assert_eq!(unique_file_name("Note", None, &existing_names), "Note");
+        // Only differs in case from existing files
+        assert_eq!(
+            unique_file_name("Note", Some("md"), &existing_names),
+            "Note-3.md"
+        );
+        assert_eq!(
+            unique_file_name("NOTE", Some("MD"), &existing_names),
+            "NOTE-3.MD"
+        );
+        assert_eq!(
+            unique_file_name("Camera", None, &existing_names),
+            "Camera-2"
+        );
+    }
+}
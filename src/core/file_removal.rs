@@ -0,0 +1,166 @@
+use gtk::{gio, glib, prelude::*};
+
+use std::{fmt::Debug, future::Future, pin::Pin};
+
+pub type TrashFuture = Pin<Box<dyn Future<Output = Result<(), glib::Error>>>>;
+
+/// How the files of a note are removed when it is deleted permanently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionMode {
+    /// Move them to the Trash, and fail where there is none
+    Trash,
+    /// Move them to the Trash, or delete them where there is none. Only use this after telling
+    /// the user that the files may be deleted.
+    TrashOrDelete,
+    /// Delete them right away, so they can't be restored with the file manager
+    Delete,
+}
+
+/// What happened to a file that was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileRemoval {
+    Trashed,
+    Deleted,
+    /// There was no file to remove, like for a note that was never saved
+    NotFound,
+}
+
+/// Where trashed files are moved to
+pub trait Trash: Debug {
+    fn trash(&self, file: &gio::File) -> TrashFuture;
+}
+
+/// The Trash of the desktop, where the file manager can restore the files from
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DesktopTrash;
+
+impl Trash for DesktopTrash {
+    fn trash(&self, file: &gio::File) -> TrashFuture {
+        file.trash_future(glib::PRIORITY_DEFAULT_IDLE)
+    }
+}
+
+/// Remove `file` the way `mode` says
+pub async fn remove_file(
+    trash: &dyn Trash,
+    file: &gio::File,
+    mode: DeletionMode,
+) -> Result<FileRemoval, glib::Error> {
+    if mode != DeletionMode::Delete {
+        match trash.trash(file).await {
+            Ok(()) => return Ok(FileRemoval::Trashed),
+            Err(err) if err.matches(gio::IOErrorEnum::NotFound) => {
+                return Ok(FileRemoval::NotFound)
+            }
+            Err(err) if mode == DeletionMode::TrashOrDelete => {
+                log::warn!(
+                    "Failed to trash `{}`, deleting it instead: {:?}",
+                    file.uri(),
+                    err
+                );
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    match file.delete_future(glib::PRIORITY_DEFAULT_IDLE).await {
+        Ok(()) => Ok(FileRemoval::Deleted),
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => Ok(FileRemoval::NotFound),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        cell::Cell,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    /// Like a file system without a Trash
+    #[derive(Debug, Default)]
+    struct UnsupportedTrash {
+        n_calls: Cell<u32>,
+    }
+
+    impl Trash for UnsupportedTrash {
+        fn trash(&self, _file: &gio::File) -> TrashFuture {
+            self.n_calls.set(self.n_calls.get() + 1);
+
+            Box::pin(async {
+                Err(glib::Error::new(
+                    gio::IOErrorEnum::NotSupported,
+                    "Trashing is not supported",
+                ))
+            })
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "noteworthy-file-removal-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("Note.md");
+        fs::write(&path, "Content").unwrap();
+        path
+    }
+
+    fn remove(
+        trash: &dyn Trash,
+        path: &Path,
+        mode: DeletionMode,
+    ) -> Result<FileRemoval, glib::Error> {
+        let file = gio::File::for_path(path);
+        glib::MainContext::new().block_on(remove_file(trash, &file, mode))
+    }
+
+    #[test]
+    fn trash_or_delete_falls_back() {
+        let path = temp_file("fall-back");
+        let trash = UnsupportedTrash::default();
+
+        let removal = remove(&trash, &path, DeletionMode::TrashOrDelete).unwrap();
+        assert_eq!(removal, FileRemoval::Deleted);
+        assert_eq!(trash.n_calls.get(), 1);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn trash_without_fallback() {
+        let path = temp_file("no-fall-back");
+
+        let err = remove(&UnsupportedTrash::default(), &path, DeletionMode::Trash).unwrap_err();
+        assert!(err.matches(gio::IOErrorEnum::NotSupported));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn delete_skips_trash() {
+        let path = temp_file("delete");
+        let trash = UnsupportedTrash::default();
+
+        let removal = remove(&trash, &path, DeletionMode::Delete).unwrap();
+        assert_eq!(removal, FileRemoval::Deleted);
+        assert_eq!(trash.n_calls.get(), 0);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = temp_file("missing");
+        fs::remove_file(&path).unwrap();
+
+        for mode in [DeletionMode::Trash, DeletionMode::Delete] {
+            let removal = remove(&DesktopTrash, &path, mode).unwrap();
+            assert_eq!(removal, FileRemoval::NotFound);
+        }
+    }
+}
@@ -0,0 +1,514 @@
+//! Editing the front matter of a note as key and value properties, or as raw YAML
+
+use gettextrs::gettext;
+use serde_yaml::{Mapping, Value};
+
+use super::{MetadataData, NoteColor};
+
+/// Keys of the fields of [`MetadataData`], in the order they are stored
+const KNOWN_KEYS: &[&str] = &[
+    "title",
+    "tag_list",
+    "attachment_list",
+    "last_modified",
+    "is_pinned",
+    "is_trashed",
+    "color",
+    "reminder",
+    "gist_id",
+    "language",
+    "created",
+];
+/// Keys that are removed from the front matter when their value is empty
+const OPTIONAL_KEYS: &[&str] = &["color", "reminder", "gist_id", "language", "created"];
+const DATE_KEYS: &[&str] = &["last_modified", "reminder", "created"];
+
+/// Value of a property, which is how it is edited
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    Boolean(bool),
+    /// A date in RFC 3339, like `2022-01-31T14:30:00+01:00`
+    Date(String),
+    List(Vec<String>),
+    /// Anything that is none of the above, like numbers and mappings, edited as YAML
+    Yaml(String),
+}
+
+/// A field of the front matter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub key: String,
+    pub value: PropertyValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyError {
+    /// Key of the invalid property, or `None` if the error is not about a single one
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl PropertyError {
+    fn new(key: Option<&str>, message: String) -> Self {
+        Self {
+            key: key.map(str::to_string),
+            message,
+        }
+    }
+}
+
+/// The properties of `data`, which are the fields known to [`MetadataData`] followed by the
+/// extra ones, in the order they are stored
+pub fn properties(data: &MetadataData) -> Vec<Property> {
+    let mut mapping = match serde_yaml::to_value(data) {
+        Ok(Value::Mapping(mapping)) => mapping,
+        other => {
+            log::error!("Metadata is not serialized into a mapping: {:?}", other);
+            Mapping::new()
+        }
+    };
+
+    let mut properties = Vec::new();
+
+    for key in KNOWN_KEYS {
+        // Optional fields are skipped when not set, but they can still be set here
+        let value = mapping
+            .remove(&Value::from(*key))
+            .unwrap_or_else(|| Value::String(String::new()));
+
+        let value = if DATE_KEYS.contains(key) {
+            PropertyValue::Date(value.as_str().unwrap_or_default().to_string())
+        } else {
+            property_value(value)
+        };
+
+        properties.push(Property {
+            key: key.to_string(),
+            value,
+        });
+    }
+
+    for (key, value) in mapping {
+        let key = match key {
+            Value::String(key) => key,
+            other => yaml_text(&other),
+        };
+
+        properties.push(Property {
+            key,
+            value: property_value(value),
+        });
+    }
+
+    properties
+}
+
+/// Inverse of [`properties`]. The type of each value is checked, the dates must be valid and
+/// the title must not be empty.
+pub fn apply(properties: &[Property]) -> Result<MetadataData, Vec<PropertyError>> {
+    let mut mapping = Mapping::new();
+    let mut errors = Vec::new();
+
+    for property in properties {
+        match property_to_value(property) {
+            Ok(Some(value)) => {
+                mapping.insert(Value::from(property.key.as_str()), value);
+            }
+            Ok(None) => {}
+            Err(message) => errors.push(PropertyError::new(Some(&property.key), message)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    serde_yaml::from_value(Value::Mapping(mapping))
+        .map_err(|err| vec![PropertyError::new(None, err.to_string())])
+}
+
+/// The front matter of `data` as YAML, without the document start
+pub fn to_yaml(data: &MetadataData) -> String {
+    match serde_yaml::to_string(data) {
+        Ok(yaml) => document_content(&yaml).to_string(),
+        Err(err) => {
+            log::error!("Failed to serialize metadata: {:?}", err);
+            String::new()
+        }
+    }
+}
+
+/// Inverse of [`to_yaml`]. Like [`apply`], the title must not be empty.
+pub fn from_yaml(yaml: &str) -> Result<MetadataData, PropertyError> {
+    let data: MetadataData =
+        serde_yaml::from_str(yaml).map_err(|err| PropertyError::new(None, err.to_string()))?;
+
+    if data.title.trim().is_empty() {
+        return Err(PropertyError::new(
+            Some("title"),
+            gettext("Title cannot be empty"),
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Items of a list edited as text, which are separated by commas
+pub fn list_from_text(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Inverse of [`list_from_text`]
+pub fn list_to_text(items: &[String]) -> String {
+    items.join(", ")
+}
+
+fn property_value(value: Value) -> PropertyValue {
+    match value {
+        Value::String(text) => PropertyValue::Text(text),
+        Value::Bool(boolean) => PropertyValue::Boolean(boolean),
+        Value::Sequence(ref items) if items.iter().all(Value::is_string) => PropertyValue::List(
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+        ),
+        other => PropertyValue::Yaml(yaml_text(&other)),
+    }
+}
+
+/// The value that is stored for the property, or `None` if it is not stored at all
+fn property_to_value(property: &Property) -> Result<Option<Value>, String> {
+    let key = property.key.as_str();
+    let is_optional = OPTIONAL_KEYS.contains(&key);
+
+    let value = match property.value {
+        PropertyValue::Text(ref text) => {
+            if key == "title" && text.trim().is_empty() {
+                return Err(gettext("Title cannot be empty"));
+            }
+
+            if is_optional && text.trim().is_empty() {
+                return Ok(None);
+            }
+
+            if key == "color" {
+                let name = text.trim();
+                if !NoteColor::ALL.into_iter().any(|color| color.name() == name) {
+                    return Err(gettext("Unknown color, expected one like “red” or “blue”"));
+                }
+                return Ok(Some(Value::from(name)));
+            }
+
+            Value::String(text.clone())
+        }
+        PropertyValue::Boolean(boolean) => Value::Bool(boolean),
+        PropertyValue::Date(ref text) => {
+            let text = text.trim();
+
+            if text.is_empty() {
+                if is_optional {
+                    return Ok(None);
+                }
+                return Err(gettext("Date cannot be empty"));
+            }
+
+            if chrono::DateTime::parse_from_rfc3339(text).is_err() {
+                return Err(gettext(
+                    "Invalid date, expected one like “2022-01-31T14:30:00+01:00”",
+                ));
+            }
+
+            Value::String(text.to_string())
+        }
+        PropertyValue::List(ref items) => Value::Sequence(
+            items
+                .iter()
+                .map(|item| Value::from(item.as_str()))
+                .collect(),
+        ),
+        PropertyValue::Yaml(ref yaml) => {
+            if yaml.trim().is_empty() {
+                Value::Null
+            } else {
+                serde_yaml::from_str(yaml).map_err(|err| err.to_string())?
+            }
+        }
+    };
+
+    Ok(Some(value))
+}
+
+fn yaml_text(value: &Value) -> String {
+    serde_yaml::to_string(value)
+        .map(|yaml| document_content(&yaml).trim_end().to_string())
+        .unwrap_or_default()
+}
+
+/// `yaml` without the `---` that starts documents serialized by `serde_yaml`
+fn document_content(yaml: &str) -> &str {
+    yaml.strip_prefix("---\n").unwrap_or(yaml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::ExtraFields;
+
+    fn data() -> MetadataData {
+        let mut extra_fields = Mapping::new();
+        extra_fields.insert("author".into(), "Someone".into());
+        extra_fields.insert("draft".into(), true.into());
+        extra_fields.insert("rating".into(), 4_u64.into());
+
+        MetadataData {
+            title: "Title".to_string(),
+            tag_list: vec!["A".to_string(), "B".to_string()],
+            last_modified: serde_yaml::from_str("\"2022-01-02T10:00:00+08:00\"").unwrap(),
+            is_pinned: true,
+            extra_fields: ExtraFields(extra_fields),
+            ..MetadataData::default()
+        }
+    }
+
+    fn value<'a>(properties: &'a [Property], key: &str) -> &'a PropertyValue {
+        &properties
+            .iter()
+            .find(|property| property.key == key)
+            .unwrap()
+            .value
+    }
+
+    fn set_value(properties: &mut [Property], key: &str, value: PropertyValue) {
+        properties
+            .iter_mut()
+            .find(|property| property.key == key)
+            .unwrap()
+            .value = value;
+    }
+
+    #[test]
+    fn properties_types() {
+        let properties = properties(&data());
+
+        assert_eq!(
+            value(&properties, "title"),
+            &PropertyValue::Text("Title".to_string())
+        );
+        assert_eq!(
+            value(&properties, "tag_list"),
+            &PropertyValue::List(vec!["A".to_string(), "B".to_string()])
+        );
+        assert_eq!(
+            value(&properties, "attachment_list"),
+            &PropertyValue::Yaml("[]".to_string())
+        );
+        match value(&properties, "last_modified") {
+            PropertyValue::Date(text) => assert_eq!(
+                chrono::DateTime::parse_from_rfc3339(text).unwrap(),
+                chrono::DateTime::parse_from_rfc3339("2022-01-02T10:00:00+08:00").unwrap()
+            ),
+            other => panic!("Expected a date, got {:?}", other),
+        }
+        assert_eq!(
+            value(&properties, "is_pinned"),
+            &PropertyValue::Boolean(true)
+        );
+        assert_eq!(
+            value(&properties, "color"),
+            &PropertyValue::Text(String::new())
+        );
+        assert_eq!(
+            value(&properties, "reminder"),
+            &PropertyValue::Date(String::new())
+        );
+        assert_eq!(
+            value(&properties, "author"),
+            &PropertyValue::Text("Someone".to_string())
+        );
+        assert_eq!(value(&properties, "draft"), &PropertyValue::Boolean(true));
+        assert_eq!(
+            value(&properties, "rating"),
+            &PropertyValue::Yaml("4".to_string())
+        );
+    }
+
+    #[test]
+    fn properties_order() {
+        let keys = properties(&data())
+            .into_iter()
+            .map(|property| property.key)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                "title",
+                "tag_list",
+                "attachment_list",
+                "last_modified",
+                "is_pinned",
+                "is_trashed",
+                "color",
+                "reminder",
+                "gist_id",
+                "language",
+                "created",
+                "author",
+                "draft",
+                "rating",
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_unchanged() {
+        let data = data();
+        assert_eq!(apply(&properties(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn apply_changed() {
+        let mut properties = properties(&data());
+        set_value(
+            &mut properties,
+            "title",
+            PropertyValue::Text("New".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "color",
+            PropertyValue::Text("green".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "reminder",
+            PropertyValue::Date("2022-01-03T01:30:00Z".to_string()),
+        );
+        set_value(&mut properties, "draft", PropertyValue::Boolean(false));
+        set_value(
+            &mut properties,
+            "rating",
+            PropertyValue::Yaml("{ stars: 5 }".to_string()),
+        );
+
+        let data = apply(&properties).unwrap();
+        assert_eq!(data.title, "New");
+        assert_eq!(data.color, NoteColor::Green);
+        assert!(data.reminder.is_some());
+        assert_eq!(
+            data.extra_fields.0.get(&"draft".into()),
+            Some(&Value::Bool(false))
+        );
+        assert!(data
+            .extra_fields
+            .0
+            .get(&"rating".into())
+            .unwrap()
+            .is_mapping());
+    }
+
+    #[test]
+    fn apply_errors() {
+        let mut properties = properties(&data());
+        set_value(
+            &mut properties,
+            "title",
+            PropertyValue::Text(" ".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "last_modified",
+            PropertyValue::Date("yesterday".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "color",
+            PropertyValue::Text("teal".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "rating",
+            PropertyValue::Yaml("[unclosed".to_string()),
+        );
+
+        let keys = apply(&properties)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.key.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["title", "last_modified", "color", "rating"]);
+    }
+
+    #[test]
+    fn apply_empty_date() {
+        let mut properties = properties(&data());
+        set_value(
+            &mut properties,
+            "last_modified",
+            PropertyValue::Date(String::new()),
+        );
+        assert!(apply(&properties).is_err());
+
+        // Optional, so it is only removed
+        set_value(
+            &mut properties,
+            "last_modified",
+            PropertyValue::Date("2022-01-02T10:00:00+08:00".to_string()),
+        );
+        set_value(
+            &mut properties,
+            "reminder",
+            PropertyValue::Date(String::new()),
+        );
+        assert_eq!(apply(&properties).unwrap().reminder, None);
+    }
+
+    #[test]
+    fn apply_wrong_known_type() {
+        let mut properties = properties(&data());
+        set_value(
+            &mut properties,
+            "attachment_list",
+            PropertyValue::Yaml("Not a list".to_string()),
+        );
+
+        let errors = apply(&properties).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, None);
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let data = data();
+        let yaml = to_yaml(&data);
+        assert!(yaml.starts_with("title: Title\n"));
+        assert!(yaml.contains("\nauthor: Someone\n"));
+        assert_eq!(from_yaml(&yaml).unwrap(), data);
+    }
+
+    #[test]
+    fn yaml_errors() {
+        let err = from_yaml("title: [unclosed").unwrap_err();
+        assert_eq!(err.key, None);
+        assert!(!err.message.is_empty());
+
+        let err = from_yaml("title: Title\nis_pinned: maybe").unwrap_err();
+        assert_eq!(err.key, None);
+
+        let err = from_yaml("title: ''").unwrap_err();
+        assert_eq!(err.key.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn list_text() {
+        assert_eq!(list_from_text("A, B ,,C "), ["A", "B", "C"]);
+        assert!(list_from_text(" ").is_empty());
+        assert_eq!(list_to_text(&["A".to_string(), "B".to_string()]), "A, B");
+    }
+}
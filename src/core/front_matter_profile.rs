@@ -0,0 +1,300 @@
+use gtk::glib;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_yaml::{Mapping, Value};
+
+use std::mem;
+
+use super::MetadataData;
+
+/// Keys of the standard profile, with the keys of [`MetadataData`] they are read as
+const STANDARD_KEYS: &[(&str, &str)] = &[
+    ("modified", "last_modified"),
+    ("tags", "tag_list"),
+    ("pinned", "is_pinned"),
+    ("trashed", "is_trashed"),
+];
+
+/// How the front matter of notes is written. Notes written in either profile are read the
+/// same, so it can be changed at any time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "NwtyFrontMatterProfile")]
+pub enum FrontMatterProfile {
+    /// The fields as they are named in [`MetadataData`], like `last_modified` and `tag_list`
+    Noteworthy,
+    /// The fields that other Markdown apps and static site generators read, like `modified`
+    /// and `tags`. The fields only Noteworthy uses are still written when they are set.
+    StandardMarkdown,
+}
+
+impl Default for FrontMatterProfile {
+    fn default() -> Self {
+        Self::Noteworthy
+    }
+}
+
+impl FrontMatterProfile {
+    pub const ALL: [Self; 2] = [Self::Noteworthy, Self::StandardMarkdown];
+
+    /// Name used when stored in the notebook settings
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Noteworthy => "noteworthy",
+            Self::StandardMarkdown => "standard_markdown",
+        }
+    }
+
+    /// Inverse of [`FrontMatterProfile::name`]. Unknown names, which may be from a newer
+    /// version, fall back to [`FrontMatterProfile::Noteworthy`].
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+            .unwrap_or_else(|| {
+                log::warn!(
+                    "Unknown front matter profile `{}`, falling back to noteworthy",
+                    name
+                );
+                Self::Noteworthy
+            })
+    }
+
+    /// The fields of the front matter of `data`, in the order they are written
+    pub fn fields(self, data: &MetadataData) -> anyhow::Result<Mapping> {
+        let fields = match serde_yaml::to_value(data)? {
+            Value::Mapping(fields) => fields,
+            other => anyhow::bail!("Metadata is not serialized into a mapping: {:?}", other),
+        };
+
+        Ok(match self {
+            Self::Noteworthy => fields,
+            Self::StandardMarkdown => standard_fields(fields),
+        })
+    }
+}
+
+impl Serialize for FrontMatterProfile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for FrontMatterProfile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::from_name(&name))
+    }
+}
+
+/// Rename the fields of the standard profile in `fields` to the ones of [`MetadataData`], so
+/// notes written in either profile are read the same. A field is kept as it is when the one it
+/// would be renamed to is also there, or when its value can't be read as it, like a date written
+/// in words, so it is not lost when the note is saved.
+pub fn read_standard_fields(fields: &mut Mapping) {
+    let renames = STANDARD_KEYS
+        .iter()
+        .filter(|(standard_key, key)| is_readable_as(fields, standard_key, key))
+        .collect::<Vec<_>>();
+
+    if renames.is_empty() {
+        return;
+    }
+
+    // Collected again instead of removed, which would change the order of the other fields
+    *fields = mem::take(fields)
+        .into_iter()
+        .map(|(key, value)| {
+            let rename = renames
+                .iter()
+                .find(|(standard_key, _)| key.as_str() == Some(*standard_key));
+
+            match rename {
+                Some((_, key)) => (Value::from(*key), value),
+                None => (key, value),
+            }
+        })
+        .collect();
+}
+
+/// Whether the field `standard_key` of `fields` can be read as `key` of [`MetadataData`]
+fn is_readable_as(fields: &Mapping, standard_key: &str, key: &str) -> bool {
+    if fields.contains_key(&Value::from(key)) {
+        return false;
+    }
+
+    let value = match fields.get(&Value::from(standard_key)) {
+        Some(value) => value.clone(),
+        None => return false,
+    };
+
+    let mut field = Mapping::new();
+    field.insert(Value::from(key), value);
+    serde_yaml::from_value::<MetadataData>(Value::Mapping(field)).is_ok()
+}
+
+/// `fields` of the Noteworthy profile written in the standard one. The flags are only
+/// written when they are set, and the fields only Noteworthy uses come after the others.
+fn standard_fields(fields: Mapping) -> Mapping {
+    let mut standard = Mapping::new();
+
+    for key in ["title", "created"] {
+        if let Some(value) = fields.get(&Value::from(key)) {
+            standard.insert(Value::from(key), value.clone());
+        }
+    }
+
+    for (standard_key, key) in STANDARD_KEYS {
+        match fields.get(&Value::from(*key)) {
+            None | Some(Value::Bool(false)) => (),
+            Some(value) => {
+                standard.insert(Value::from(*standard_key), value.clone());
+            }
+        }
+    }
+
+    for (key, value) in fields {
+        let is_written = key.as_str().map_or(false, |key| {
+            key == "title" || key == "created" || STANDARD_KEYS.iter().any(|(_, k)| *k == key)
+        });
+        let is_no_attachments = key.as_str() == Some("attachment_list")
+            && value.as_sequence().map_or(false, Vec::is_empty);
+
+        // Fields of other apps with the same keys, like a `modified` that was not a valid
+        // date, are replaced by the ones that are written
+        if is_written || is_no_attachments || standard.contains_key(&key) {
+            continue;
+        }
+
+        standard.insert(key, value);
+    }
+
+    standard
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::core::{DateTime, ExtraFields};
+
+    fn data() -> MetadataData {
+        let mut extra_fields = Mapping::new();
+        extra_fields.insert("author".into(), "Someone".into());
+
+        MetadataData {
+            title: "Title".to_string(),
+            tag_list: vec!["A".to_string(), "B".to_string()],
+            created: Some(serde_yaml::from_str("\"2022-01-01T10:00:00+08:00\"").unwrap()),
+            last_modified: serde_yaml::from_str("\"2022-01-02T10:00:00+08:00\"").unwrap(),
+            is_pinned: true,
+            extra_fields: ExtraFields(extra_fields),
+            ..MetadataData::default()
+        }
+    }
+
+    fn keys(fields: &Mapping) -> Vec<&str> {
+        fields.iter().filter_map(|(key, _)| key.as_str()).collect()
+    }
+
+    #[test]
+    fn name() {
+        for profile in FrontMatterProfile::ALL {
+            assert_eq!(FrontMatterProfile::from_name(profile.name()), profile);
+        }
+
+        assert_eq!(
+            FrontMatterProfile::from_name("unknown"),
+            FrontMatterProfile::Noteworthy
+        );
+    }
+
+    #[test]
+    fn noteworthy_fields() {
+        let fields = FrontMatterProfile::Noteworthy.fields(&data()).unwrap();
+        assert_eq!(
+            keys(&fields),
+            [
+                "title",
+                "tag_list",
+                "attachment_list",
+                "last_modified",
+                "is_pinned",
+                "is_trashed",
+                "created",
+                "author",
+            ]
+        );
+    }
+
+    #[test]
+    fn standard_fields() {
+        let fields = FrontMatterProfile::StandardMarkdown
+            .fields(&data())
+            .unwrap();
+        assert_eq!(
+            keys(&fields),
+            ["title", "created", "modified", "tags", "pinned", "author"]
+        );
+        assert_eq!(
+            fields.get(&"tags".into()),
+            Some(&Value::Sequence(vec!["A".into(), "B".into()]))
+        );
+
+        let modified: DateTime =
+            serde_yaml::from_value(fields.get(&"modified".into()).unwrap().clone()).unwrap();
+        assert_eq!(modified, data().last_modified);
+
+        // Fields only Noteworthy uses are kept when they are set
+        let data = MetadataData {
+            is_pinned: false,
+            is_trashed: true,
+            created: None,
+            gist_id: Some("aa5a315d61ae9438b18d".to_string()),
+            ..data()
+        };
+        let fields = FrontMatterProfile::StandardMarkdown.fields(&data).unwrap();
+        assert_eq!(
+            keys(&fields),
+            ["title", "modified", "tags", "trashed", "gist_id", "author"]
+        );
+    }
+
+    #[test]
+    fn standard_fields_replace_other_fields() {
+        let mut extra_fields = Mapping::new();
+        extra_fields.insert("modified".into(), "last tuesday".into());
+
+        let data = MetadataData {
+            extra_fields: ExtraFields(extra_fields),
+            ..data()
+        };
+        let fields = FrontMatterProfile::StandardMarkdown.fields(&data).unwrap();
+        assert_ne!(
+            fields.get(&"modified".into()),
+            Some(&Value::from("last tuesday"))
+        );
+    }
+
+    #[test]
+    fn read_standard() {
+        let mut fields: Mapping = serde_yaml::from_str(
+            "title: A\nmodified: \"2022-01-02T10:00:00+08:00\"\ntags: [A]\npinned: true\n",
+        )
+        .unwrap();
+        read_standard_fields(&mut fields);
+        assert_eq!(
+            keys(&fields),
+            ["title", "last_modified", "tag_list", "is_pinned"]
+        );
+
+        // Kept when they can't be read, or when the ones they would be read as are there
+        let mut fields: Mapping = serde_yaml::from_str(
+            "title: A\nmodified: early 2022\ntags: A, B\nis_pinned: false\npinned: true\n",
+        )
+        .unwrap();
+        read_standard_fields(&mut fields);
+        assert_eq!(
+            keys(&fields),
+            ["title", "modified", "tags", "is_pinned", "pinned"]
+        );
+    }
+}
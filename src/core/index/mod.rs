@@ -0,0 +1,838 @@
+mod tokenize;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    ops::{Bound, Range},
+    path::{Path, PathBuf},
+};
+
+pub use self::tokenize::query_words;
+
+use self::tokenize::{tokenize, Token};
+use super::{FileStamp, IndexReconciliation};
+
+pub const SEARCH_INDEX_FILE_NAME: &str = "search-index.nwty";
+
+/// Changed whenever the tokens change, so an index written by an older version is not used
+const VERSION: u32 = 1;
+
+/// How quickly more occurrences of a word stop raising the score, as in BM25
+const K1: f64 = 1.2;
+/// How much longer notes are penalized for having more words, as in BM25
+const B: f64 = 0.75;
+/// Added to the score for each two words of the query that are next to each other in a note
+const PHRASE_BONUS: f64 = 1.0;
+
+/// Removed notes that are kept before compacting, so it is not done on every edit
+const MIN_TOMBSTONES_TO_COMPACT: usize = 64;
+
+/// An indexed note, by its file name in the notes directory
+#[derive(Debug, Clone, PartialEq)]
+struct Document {
+    file_name: String,
+    n_words: usize,
+    /// Of the file the text was read from, or `None` when it was indexed from unsaved changes,
+    /// so it is indexed again on the next load
+    stamp: Option<FileStamp>,
+}
+
+/// Where a word is in a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Occurrence {
+    /// Index of the word among the words of the note
+    position: u32,
+    /// Byte offset in the text
+    offset: u32,
+    len: u32,
+}
+
+impl Occurrence {
+    fn range(&self) -> Range<usize> {
+        let start = self.offset as usize;
+        start..start + self.len as usize
+    }
+}
+
+/// The occurrences of a word in the note in a slot
+#[derive(Debug, Clone, PartialEq)]
+struct Posting {
+    slot: u32,
+    occurrences: Vec<Occurrence>,
+}
+
+/// A note that has every word of a query, with where the words are in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexMatch {
+    pub file_name: String,
+    /// Higher for notes where the words are more frequent and rarer in the other notes
+    pub score: f64,
+    /// Byte ranges of the words in the text, in order
+    pub offsets: Vec<Range<usize>>,
+}
+
+/// Occurrences of a word of a query in a note
+#[derive(Debug, Default)]
+struct Hits {
+    n_occurrences: usize,
+    positions: Vec<u32>,
+    offsets: Vec<Range<usize>>,
+}
+
+/// As the index is written to a file
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    directory: PathBuf,
+    documents: Vec<DocumentFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DocumentFile {
+    file_name: String,
+    stamp: Option<FileStamp>,
+    /// The words in order as `offset,len,word`, separated by spaces
+    words: String,
+}
+
+/// Which notes have each word, with where they are in them, so the content of the notes
+/// can be searched without reading it
+///
+/// A note that is indexed again or removed leaves a tombstone, which queries skip, until
+/// there are enough of them to compact the index.
+#[derive(Debug, Clone)]
+pub struct SearchIndex {
+    directory: PathBuf,
+    /// By slot, with `None` for the tombstones
+    documents: Vec<Option<Document>>,
+    slots: HashMap<String, u32>,
+    /// By word, in increasing order of slot
+    postings: BTreeMap<String, Vec<Posting>>,
+    n_tombstones: usize,
+    /// Words of the indexed notes
+    n_words: usize,
+    /// Words of the tombstones, which are still in the postings
+    n_dead_words: usize,
+}
+
+impl SearchIndex {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            documents: Vec::new(),
+            slots: HashMap::new(),
+            postings: BTreeMap::new(),
+            n_tombstones: 0,
+            n_words: 0,
+            n_dead_words: 0,
+        }
+    }
+
+    /// Parse an index of the notes in `directory`. It fails if it is of another version or
+    /// directory, as if it was corrupt.
+    pub fn parse(bytes: &[u8], directory: &Path) -> anyhow::Result<Self> {
+        let file: IndexFile = serde_yaml::from_slice(bytes)?;
+
+        anyhow::ensure!(
+            file.version == VERSION,
+            "Index is of version {}, expected {}",
+            file.version,
+            VERSION
+        );
+        anyhow::ensure!(
+            file.directory == directory,
+            "Index is of `{}`, expected `{}`",
+            file.directory.display(),
+            directory.display()
+        );
+
+        let mut index = Self::new(directory);
+
+        for document in file.documents {
+            let tokens = document
+                .words
+                .split(' ')
+                .filter(|word| !word.is_empty())
+                .map(parse_token)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| anyhow::anyhow!("Invalid words of `{}`", document.file_name))?;
+
+            index.remove(&document.file_name);
+            index.insert_tokens(document.file_name, tokens, document.stamp);
+        }
+
+        Ok(index)
+    }
+
+    pub fn serialize(&self) -> anyhow::Result<String> {
+        let mut words_by_slot = vec![Vec::new(); self.documents.len()];
+
+        for (word, postings) in &self.postings {
+            for posting in postings {
+                if self.documents[posting.slot as usize].is_some() {
+                    let words = &mut words_by_slot[posting.slot as usize];
+                    words.extend(
+                        posting
+                            .occurrences
+                            .iter()
+                            .map(|occurrence| (occurrence, word)),
+                    );
+                }
+            }
+        }
+
+        let documents = self
+            .documents
+            .iter()
+            .zip(words_by_slot)
+            .filter_map(|(document, mut words)| {
+                let document = document.as_ref()?;
+
+                words.sort_by_key(|(occurrence, _)| occurrence.position);
+                let words = words
+                    .iter()
+                    .map(|(occurrence, word)| {
+                        format!("{},{},{}", occurrence.offset, occurrence.len, word)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                Some(DocumentFile {
+                    file_name: document.file_name.clone(),
+                    stamp: document.stamp,
+                    words,
+                })
+            })
+            .collect();
+
+        let file = IndexFile {
+            version: VERSION,
+            directory: self.directory.clone(),
+            documents,
+        };
+
+        Ok(serde_yaml::to_string(&file)?)
+    }
+
+    /// Index `text`, the content of the note in `file_name`, in place of what it was indexed
+    /// with before. `stamp` is of the file it was read from, if it is saved.
+    pub fn insert(&mut self, file_name: &str, text: &str, stamp: Option<FileStamp>) {
+        self.remove(file_name);
+        self.insert_tokens(file_name.to_string(), tokenize(text), stamp);
+    }
+
+    /// Leave a tombstone in place of the note in `file_name`, if it is indexed
+    pub fn remove(&mut self, file_name: &str) {
+        let slot = match self.slots.remove(file_name) {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        if let Some(document) = self.documents[slot as usize].take() {
+            self.n_tombstones += 1;
+            self.n_words -= document.n_words;
+            self.n_dead_words += document.n_words;
+        }
+
+        if self.n_tombstones >= MIN_TOMBSTONES_TO_COMPACT
+            && (self.n_dead_words > self.n_words || self.n_tombstones > self.slots.len())
+        {
+            self.compact();
+        }
+    }
+
+    /// Remove the notes whose file name `f` returns false for, returning whether there were
+    /// any
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) -> bool {
+        let removed = self
+            .slots
+            .keys()
+            .filter(|file_name| !f(file_name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for file_name in &removed {
+            self.remove(file_name);
+        }
+
+        !removed.is_empty()
+    }
+
+    /// Drop the tombstones from the postings
+    pub fn compact(&mut self) {
+        if self.n_tombstones == 0 {
+            return;
+        }
+
+        let mut new_slots = vec![None; self.documents.len()];
+        let mut documents = Vec::with_capacity(self.slots.len());
+
+        for (slot, document) in std::mem::take(&mut self.documents).into_iter().enumerate() {
+            if let Some(document) = document {
+                new_slots[slot] = Some(documents.len() as u32);
+                documents.push(Some(document));
+            }
+        }
+
+        self.postings.retain(|_, postings| {
+            *postings = std::mem::take(postings)
+                .into_iter()
+                .filter_map(|posting| {
+                    let slot = new_slots[posting.slot as usize]?;
+                    Some(Posting { slot, ..posting })
+                })
+                .collect();
+            !postings.is_empty()
+        });
+
+        self.slots = documents
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, document)| {
+                let document = document.as_ref()?;
+                Some((document.file_name.clone(), slot as u32))
+            })
+            .collect();
+        self.documents = documents;
+        self.n_tombstones = 0;
+        self.n_dead_words = 0;
+    }
+
+    pub fn contains(&self, file_name: &str) -> bool {
+        self.slots.contains_key(file_name)
+    }
+
+    /// Set the stamp of the file of the note in `file_name` if it was indexed from unsaved
+    /// changes, like once they are saved. A note indexed from its file keeps the stamp of
+    /// that file, so it is indexed again if the file changed since.
+    pub fn set_missing_stamp(&mut self, file_name: &str, stamp: FileStamp) {
+        if let Some(&slot) = self.slots.get(file_name) {
+            if let Some(ref mut document) = self.documents[slot as usize] {
+                document.stamp.get_or_insert(stamp);
+            }
+        }
+    }
+
+    /// Compare the notes with `stamps`, the ones of the note files that exist now by file
+    /// name. Notes indexed from unsaved changes are stale.
+    pub fn reconcile(&self, stamps: &HashMap<String, FileStamp>) -> IndexReconciliation {
+        let mut reconciliation = IndexReconciliation::default();
+
+        for document in self.documents.iter().flatten() {
+            match stamps.get(&document.file_name) {
+                Some(stamp) if Some(*stamp) == document.stamp => (),
+                Some(_) => reconciliation.stale.push(document.file_name.clone()),
+                None => reconciliation.removed.push(document.file_name.clone()),
+            }
+        }
+
+        reconciliation.added = stamps
+            .keys()
+            .filter(|file_name| !self.slots.contains_key(*file_name))
+            .cloned()
+            .collect();
+
+        reconciliation.stale.sort();
+        reconciliation.added.sort();
+        reconciliation.removed.sort();
+
+        reconciliation
+    }
+
+    /// The notes with every word of `text`, the last one as the start of a word since it
+    /// may not be fully typed yet. The best match is first, ranked like BM25 with a bonus
+    /// for the words that are next to each other as in `text`.
+    pub fn query(&self, text: &str) -> Vec<IndexMatch> {
+        let words = query_words(text);
+
+        let n_documents = self.slots.len();
+        if words.is_empty() || n_documents == 0 {
+            return Vec::new();
+        }
+
+        let average_n_words = self.n_words as f64 / n_documents as f64;
+
+        let hits = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| self.hits(word, i == words.len() - 1))
+            .collect::<Vec<_>>();
+
+        let rarest_hits = hits.iter().min_by_key(|hits| hits.len()).unwrap();
+
+        let mut matches = rarest_hits
+            .keys()
+            .filter(|slot| hits.iter().all(|hits| hits.contains_key(slot)))
+            .map(|slot| {
+                let document = self.documents[*slot as usize].as_ref().unwrap();
+                let length_norm = 1.0 - B + B * document.n_words as f64 / average_n_words;
+
+                let mut score = 0.0;
+                let mut offsets = Vec::new();
+
+                for word_hits in &hits {
+                    let n_with_word = word_hits.len() as f64;
+                    let idf =
+                        (1.0 + (n_documents as f64 - n_with_word + 0.5) / (n_with_word + 0.5)).ln();
+
+                    let slot_hits = &word_hits[slot];
+                    let frequency = slot_hits.n_occurrences as f64;
+                    score += idf * frequency * (K1 + 1.0) / (frequency + K1 * length_norm);
+
+                    offsets.extend(slot_hits.offsets.iter().cloned());
+                }
+
+                for pair in hits.windows(2) {
+                    let next_positions = &pair[1][slot].positions;
+                    let is_adjacent = pair[0][slot]
+                        .positions
+                        .iter()
+                        .any(|position| next_positions.binary_search(&(position + 1)).is_ok());
+
+                    if is_adjacent {
+                        score += PHRASE_BONUS;
+                    }
+                }
+
+                offsets.sort_by_key(|range| (range.start, range.end));
+                offsets.dedup();
+
+                IndexMatch {
+                    file_name: document.file_name.clone(),
+                    score,
+                    offsets,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.file_name.cmp(&b.file_name))
+        });
+
+        matches
+    }
+
+    /// The occurrences of `word` by slot in the indexed notes, or of the words starting with
+    /// it if `is_prefix`
+    fn hits(&self, word: &str, is_prefix: bool) -> HashMap<u32, Hits> {
+        let mut hits: HashMap<u32, Hits> = HashMap::new();
+
+        let postings = self
+            .postings
+            .range::<str, _>((Bound::Included(word), Bound::Unbounded))
+            .take_while(|(indexed_word, _)| {
+                if is_prefix {
+                    indexed_word.starts_with(word)
+                } else {
+                    indexed_word.as_str() == word
+                }
+            })
+            .flat_map(|(_, postings)| postings);
+
+        for posting in postings {
+            if self.documents[posting.slot as usize].is_none() {
+                continue;
+            }
+
+            let slot_hits = hits.entry(posting.slot).or_default();
+            slot_hits.n_occurrences += posting.occurrences.len();
+            for occurrence in &posting.occurrences {
+                slot_hits.positions.push(occurrence.position);
+                slot_hits.offsets.push(occurrence.range());
+            }
+        }
+
+        for slot_hits in hits.values_mut() {
+            slot_hits.positions.sort_unstable();
+        }
+
+        hits
+    }
+
+    fn insert_tokens(&mut self, file_name: String, tokens: Vec<Token>, stamp: Option<FileStamp>) {
+        let slot = self.documents.len() as u32;
+        let n_words = tokens.len();
+
+        let mut occurrences_by_word: HashMap<String, Vec<Occurrence>> = HashMap::new();
+        for (position, token) in tokens.into_iter().enumerate() {
+            occurrences_by_word
+                .entry(token.word)
+                .or_default()
+                .push(Occurrence {
+                    position: position as u32,
+                    offset: token.offset as u32,
+                    len: token.len as u32,
+                });
+        }
+
+        // The slot is the last one, so the postings stay in order
+        for (word, occurrences) in occurrences_by_word {
+            self.postings
+                .entry(word)
+                .or_default()
+                .push(Posting { slot, occurrences });
+        }
+
+        self.documents.push(Some(Document {
+            file_name: file_name.clone(),
+            n_words,
+            stamp,
+        }));
+        self.slots.insert(file_name, slot);
+        self.n_words += n_words;
+    }
+}
+
+/// Whether `content` has every word of `text`, the last one as the start of a word, as
+/// [`SearchIndex::query`] matches it
+pub fn contains_words(content: &str, text: &str) -> bool {
+    let words = query_words(text);
+
+    let (last_word, other_words) = match words.split_last() {
+        Some(split) => split,
+        None => return true,
+    };
+
+    let content_words = tokenize(content)
+        .into_iter()
+        .map(|token| token.word)
+        .collect::<Vec<_>>();
+
+    other_words.iter().all(|word| content_words.contains(word))
+        && content_words
+            .iter()
+            .any(|content_word| content_word.starts_with(last_word.as_str()))
+}
+
+/// Parse a token of [`DocumentFile::words`]
+fn parse_token(text: &str) -> Option<Token> {
+    let mut parts = text.splitn(3, ',');
+    let offset = parts.next()?.parse().ok()?;
+    let len = parts.next()?.parse().ok()?;
+    let word = parts.next()?.to_string();
+
+    Some(Token { word, offset, len })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{collections::BTreeSet, time::Duration};
+
+    /// Every word of the indexed notes by file name, in order, with where they are
+    fn snapshot(index: &SearchIndex) -> BTreeMap<String, Vec<(u32, String, Range<usize>)>> {
+        let mut snapshot = BTreeMap::new();
+
+        for (word, postings) in &index.postings {
+            for posting in postings {
+                if let Some(ref document) = index.documents[posting.slot as usize] {
+                    let words: &mut Vec<_> =
+                        snapshot.entry(document.file_name.clone()).or_default();
+                    for occurrence in &posting.occurrences {
+                        words.push((occurrence.position, word.clone(), occurrence.range()));
+                    }
+                }
+            }
+        }
+
+        for document in index.documents.iter().flatten() {
+            snapshot.entry(document.file_name.clone()).or_default();
+        }
+        for words in snapshot.values_mut() {
+            words.sort_by_key(|(position, ..)| *position);
+        }
+
+        snapshot
+    }
+
+    fn file_names(matches: &[IndexMatch]) -> Vec<&str> {
+        matches
+            .iter()
+            .map(|index_match| index_match.file_name.as_str())
+            .collect()
+    }
+
+    fn stamp(modified_secs: u64, size: u64) -> FileStamp {
+        FileStamp {
+            modified: Duration::from_secs(modified_secs),
+            size,
+        }
+    }
+
+    /// Deterministic, so a failure can be reproduced
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn test_index() -> SearchIndex {
+        let mut index = SearchIndex::new("/home/user/Notes");
+        index.insert(
+            "Groceries.md",
+            "# Groceries\n\n- [ ] Milk\n- [x] Eggs\n- [ ] Bread",
+            None,
+        );
+        index.insert(
+            "Recipes.md",
+            "Pancakes need milk, eggs, and flour. More milk makes them thinner.",
+            None,
+        );
+        index.insert("Journal.md", "Bought milk on the way home", None);
+        index
+    }
+
+    #[test]
+    fn query() {
+        let index = test_index();
+
+        // Shorter notes are better, unless the word is more frequent in a longer one
+        assert_eq!(
+            file_names(&index.query("milk")),
+            ["Groceries.md", "Recipes.md", "Journal.md"]
+        );
+        assert_eq!(
+            file_names(&index.query("EGGS milk")),
+            ["Groceries.md", "Recipes.md"]
+        );
+        assert!(index.query("milk butter").is_empty());
+        assert!(index.query("  ").is_empty());
+    }
+
+    #[test]
+    fn query_prefix() {
+        let index = test_index();
+
+        // Only the last word, which may still be typed
+        assert_eq!(file_names(&index.query("pan")), ["Recipes.md"]);
+        assert_eq!(file_names(&index.query("bought mi")), ["Journal.md"]);
+        assert!(index.query("pan milk").is_empty());
+    }
+
+    #[test]
+    fn query_phrase() {
+        let mut index = SearchIndex::new("/home/user/Notes");
+        index.insert("a.md", "green tea and black coffee", None);
+        index.insert("b.md", "black tea and green coffee", None);
+
+        let matches = index.query("green tea");
+        assert_eq!(file_names(&matches), ["a.md", "b.md"]);
+        assert!(matches[0].score > matches[1].score);
+    }
+
+    #[test]
+    fn query_offsets() {
+        let text = "Café & crème, then more café";
+        let mut index = SearchIndex::new("/home/user/Notes");
+        index.insert("a.md", text, None);
+
+        let matches = index.query("CAFÉ cr");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0]
+                .offsets
+                .iter()
+                .map(|range| &text[range.clone()])
+                .collect::<Vec<_>>(),
+            ["Café", "crème", "café"]
+        );
+    }
+
+    #[test]
+    fn tombstones() {
+        let mut index = test_index();
+
+        index.insert("Journal.md", "Nothing bought today", None);
+        assert_eq!(
+            file_names(&index.query("milk")),
+            ["Groceries.md", "Recipes.md"]
+        );
+        assert_eq!(file_names(&index.query("bought")), ["Journal.md"]);
+
+        index.remove("Recipes.md");
+        assert!(!index.contains("Recipes.md"));
+        assert_eq!(file_names(&index.query("milk")), ["Groceries.md"]);
+        assert!(index.query("pancakes").is_empty());
+
+        assert!(index.retain(|file_name| file_name != "Groceries.md"));
+        assert!(!index.retain(|file_name| file_name != "Groceries.md"));
+        assert!(index.query("milk").is_empty());
+        assert_eq!(index.n_tombstones, 3);
+
+        index.compact();
+        assert_eq!(index.n_tombstones, 0);
+        assert_eq!(index.documents.len(), 1);
+        assert!(index.postings.keys().all(|word| word != "milk"));
+        assert_eq!(file_names(&index.query("bought")), ["Journal.md"]);
+    }
+
+    #[test]
+    fn compacts_tombstones() {
+        let mut index = SearchIndex::new("/home/user/Notes");
+        index.insert("other.md", "untouched words", None);
+
+        for i in 0..1_000 {
+            index.insert("edited.md", &format!("edit number {}", i), None);
+        }
+
+        assert!(index.documents.len() < MIN_TOMBSTONES_TO_COMPACT * 2);
+        assert_eq!(file_names(&index.query("number 999")), ["edited.md"]);
+        assert!(index.query("number 998").is_empty());
+        assert_eq!(file_names(&index.query("untouched")), ["other.md"]);
+    }
+
+    #[test]
+    fn incremental_matches_rebuild() {
+        const FILE_NAMES: [&str; 6] = ["a.md", "b.md", "c.md", "d.md", "e.md", "f.md"];
+        const WORDS: [&str; 10] = [
+            "milk",
+            "Milk",
+            "mild",
+            "eggs",
+            "égg",
+            "[link](dest)",
+            "don't",
+            "3.14",
+            "東京",
+            "\n```\n",
+        ];
+
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut index = SearchIndex::new("/home/user/Notes");
+        let mut texts = BTreeMap::new();
+
+        for step in 0..2_000 {
+            let file_name = FILE_NAMES[rng.below(FILE_NAMES.len())];
+
+            match rng.below(10) {
+                0 => {
+                    index.remove(file_name);
+                    texts.remove(file_name);
+                }
+                1 => {
+                    let removed = FILE_NAMES[rng.below(FILE_NAMES.len())];
+                    index.retain(|file_name| file_name != removed);
+                    texts.remove(removed);
+                }
+                _ => {
+                    let n_words = rng.below(12);
+                    let text = (0..n_words)
+                        .map(|_| WORDS[rng.below(WORDS.len())])
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    index.insert(file_name, &text, None);
+                    texts.insert(file_name, text);
+                }
+            }
+
+            let mut rebuilt = SearchIndex::new("/home/user/Notes");
+            for (file_name, text) in &texts {
+                rebuilt.insert(file_name, text, None);
+            }
+
+            assert_eq!(snapshot(&index), snapshot(&rebuilt), "At step {}", step);
+            for query in ["milk", "mil", "eggs milk", "don't", "東", "link", "dest"] {
+                assert_eq!(
+                    index.query(query),
+                    rebuilt.query(query),
+                    "At step {} querying `{}`",
+                    step,
+                    query
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let directory = Path::new("/home/user/Notes");
+
+        let mut index = test_index();
+        index.set_missing_stamp("Groceries.md", stamp(10, 200));
+        index.insert("Journal.md", "Tea, then more tea", Some(stamp(20, 30)));
+        // Indexed from its file, so it keeps the stamp of that file
+        index.set_missing_stamp("Journal.md", stamp(25, 30));
+        index.insert("Empty.md", "", Some(stamp(30, 0)));
+
+        let serialized = index.serialize().unwrap();
+        let parsed = SearchIndex::parse(serialized.as_bytes(), directory).unwrap();
+
+        assert_eq!(snapshot(&parsed), snapshot(&index));
+        assert_eq!(parsed.n_tombstones, 0);
+        for query in ["milk", "tea", "eggs milk", "gro"] {
+            assert_eq!(parsed.query(query), index.query(query));
+        }
+
+        let stamps = [
+            ("Groceries.md".to_string(), stamp(10, 200)),
+            ("Journal.md".to_string(), stamp(21, 30)),
+            ("Recipes.md".to_string(), stamp(5, 60)),
+            ("New.md".to_string(), stamp(40, 10)),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        assert_eq!(parsed.reconcile(&stamps), index.reconcile(&stamps));
+        assert_eq!(
+            parsed.reconcile(&stamps),
+            IndexReconciliation {
+                // Indexed without a stamp, from unsaved changes
+                stale: vec!["Journal.md".to_string(), "Recipes.md".to_string()],
+                added: vec!["New.md".to_string()],
+                removed: vec!["Empty.md".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let directory = Path::new("/home/user/Notes");
+        let serialized = test_index().serialize().unwrap();
+
+        assert!(SearchIndex::parse(serialized.as_bytes(), Path::new("/other")).is_err());
+        assert!(SearchIndex::parse(
+            serialized
+                .replace(&format!("version: {}", VERSION), "version: 0")
+                .as_bytes(),
+            directory
+        )
+        .is_err());
+        assert!(SearchIndex::parse(b"not: [an index", directory).is_err());
+
+        let invalid_words = format!(
+            "version: {}\ndirectory: /home/user/Notes\ndocuments:\n  - file_name: a.md\n    stamp: ~\n    words: \"0,4\"\n",
+            VERSION
+        );
+        assert!(SearchIndex::parse(invalid_words.as_bytes(), directory).is_err());
+    }
+
+    #[test]
+    fn words() {
+        assert!(contains_words("Eggs, milk and bread", "MILK bre"));
+        assert!(contains_words("Eggs, milk and bread", ""));
+        assert!(!contains_words("Eggs, milk and bread", "mi eggs"));
+        assert!(!contains_words("[Label](milk.md)", "milk"));
+
+        let matched = test_index()
+            .query("eggs mi")
+            .into_iter()
+            .map(|index_match| index_match.file_name)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            matched,
+            ["Groceries.md".to_string(), "Recipes.md".to_string()]
+                .into_iter()
+                .collect()
+        );
+    }
+}
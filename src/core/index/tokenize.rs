@@ -0,0 +1,229 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use std::ops::Range;
+
+/// Markdown that is not words of the note: fence lines, link destinations, reference
+/// definitions, task boxes, autolinks, HTML tags, and entities
+static RE_MARKDOWN_SYNTAX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"(?m)^ {0,3}(?:`{3,}|~{3,}).*$",
+        r"|\]\([^)\n]*\)",
+        r"|^ {0,3}\[[^\]\n]+\]:.*$",
+        r"|^[ \t]*[-*+][ \t]+\[[ xX]\]",
+        r"|<[A-Za-z][A-Za-z0-9+.-]*:[^>\s]*>",
+        r"|</?[A-Za-z][A-Za-z0-9-]*(?:\s[^>\n]*)?/?>",
+        r"|&(?:[A-Za-z][A-Za-z0-9]*|#[0-9]+|#[xX][0-9A-Fa-f]+);",
+    ))
+    .unwrap()
+});
+
+/// A word of a text as it is indexed, with where it is in the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Normalized, so it is found however it is written
+    pub word: String,
+    /// Byte offset in the text
+    pub offset: usize,
+    /// Length in bytes in the text, which may differ from the normalized word
+    pub len: usize,
+}
+
+/// The words of the Markdown `text`, without its syntax, like link destinations and HTML
+/// tags. Words are split like Unicode word boundaries, where an apostrophe between letters
+/// and a point or comma between digits don't split them, and each ideograph is a word.
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    for syntax in RE_MARKDOWN_SYNTAX.find_iter(text) {
+        push_words(text, start..syntax.start(), &mut tokens);
+        start = syntax.end();
+    }
+    push_words(text, start..text.len(), &mut tokens);
+
+    tokens
+}
+
+/// The normalized words of `text` as it is searched, which is not Markdown
+pub fn query_words(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    push_words(text, 0..text.len(), &mut tokens);
+    tokens.into_iter().map(|token| token.word).collect()
+}
+
+/// Push the words of `text` in `range` to `tokens`
+fn push_words(text: &str, range: Range<usize>, tokens: &mut Vec<Token>) {
+    let chars = text[range.clone()]
+        .char_indices()
+        .map(|(offset, c)| (range.start + offset, c))
+        .collect::<Vec<_>>();
+    let end_of = |index: usize| chars.get(index).map_or(range.end, |(offset, _)| *offset);
+
+    let mut index = 0;
+    while index < chars.len() {
+        let (start, c) = chars[index];
+
+        if is_ideograph(c) {
+            index += 1;
+            tokens.push(token(text, start..end_of(index)));
+            continue;
+        }
+
+        if !is_word_char(c) {
+            index += 1;
+            continue;
+        }
+
+        index += 1;
+        while let Some(&(_, c)) = chars.get(index) {
+            if is_word_char(c) && !is_ideograph(c) {
+                index += 1;
+                continue;
+            }
+
+            let previous = chars[index - 1].1;
+            let next = chars.get(index + 1).map(|(_, c)| *c);
+            if next.map_or(false, |next| is_joined(previous, c, next)) {
+                index += 2;
+                continue;
+            }
+
+            break;
+        }
+
+        tokens.push(token(text, start..end_of(index)));
+    }
+}
+
+fn token(text: &str, range: Range<usize>) -> Token {
+    Token {
+        word: normalize(&text[range.clone()]),
+        offset: range.start,
+        len: range.len(),
+    }
+}
+
+/// In compatibility form and lowercase, so `Ｆｕｌｌ` and `full` are the same word
+fn normalize(word: &str) -> String {
+    word.nfkc().flat_map(char::to_lowercase).collect()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c)
+}
+
+/// Whether `c` between `previous` and `next` is part of one word, like in `don't` and
+/// `3.14`
+fn is_joined(previous: char, c: char, next: char) -> bool {
+    match c {
+        '\'' | '’' => previous.is_alphabetic() && next.is_alphabetic() && !is_ideograph(next),
+        '.' | ',' => previous.is_numeric() && next.is_numeric(),
+        _ => false,
+    }
+}
+
+/// Whether `c` is written without spaces between words, so each is a word of its own
+fn is_ideograph(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{309F}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        tokenize(text).into_iter().map(|token| token.word).collect()
+    }
+
+    #[test]
+    fn offsets() {
+        let text = "Hello, Wörld!";
+        let tokens = tokenize(text);
+
+        assert_eq!(
+            tokens,
+            [
+                Token {
+                    word: "hello".to_string(),
+                    offset: 0,
+                    len: 5,
+                },
+                Token {
+                    word: "wörld".to_string(),
+                    offset: 7,
+                    len: 6,
+                },
+            ]
+        );
+        assert_eq!(&text[tokens[1].offset..][..tokens[1].len], "Wörld");
+    }
+
+    #[test]
+    fn word_boundaries() {
+        assert_eq!(words("don't stop"), ["don't", "stop"]);
+        assert_eq!(
+            words("it’s 3.14 or 1,000."),
+            ["it’s", "3.14", "or", "1,000"]
+        );
+        assert_eq!(words("'quoted' end."), ["quoted", "end"]);
+        assert_eq!(
+            words("snake_case kebab-case"),
+            ["snake", "case", "kebab", "case"]
+        );
+        assert_eq!(words("e-mail@example.com"), ["e", "mail", "example", "com"]);
+    }
+
+    #[test]
+    fn unicode() {
+        // Combining marks stay in the word
+        assert_eq!(words("नमस्ते दुनिया"), ["नमस्ते", "दुनिया"]);
+        assert_eq!(words("cafe\u{301} crème"), ["café", "crème"]);
+        // Full-width and ligatures are the same as plain letters
+        assert_eq!(words("Ｆｕｌｌ ﬁle"), ["full", "file"]);
+        assert_eq!(words("ΣΊΣΥΦΟΣ"), ["σίσυφοσ"]);
+        // Each ideograph is a word, but katakana is not
+        assert_eq!(words("東京タワーへ"), ["東", "京", "タワー", "へ"]);
+    }
+
+    #[test]
+    fn markdown_syntax() {
+        assert_eq!(
+            words("# Title\n\n**Bold** and _em_ with `code` > quote"),
+            ["title", "bold", "and", "em", "with", "code", "quote"]
+        );
+        assert_eq!(
+            words("[Label](https://example.com/path \"Hint\") ![Alt](image.png)"),
+            ["label", "alt"]
+        );
+        assert_eq!(
+            words("See [[Groceries|the list]] #work"),
+            ["see", "groceries", "the", "list", "work"]
+        );
+        assert_eq!(
+            words("<https://example.com> <span class=\"x\">Inside</span> a&nbsp;b"),
+            ["inside", "a", "b"]
+        );
+        assert_eq!(
+            words("- [x] Done\n- [ ] Todo\n[ref]: https://example.com\n"),
+            ["done", "todo"]
+        );
+        assert_eq!(words("```rust\nfn main() {}\n```\n"), ["fn", "main"]);
+    }
+
+    #[test]
+    fn query() {
+        assert_eq!(query_words("  Milk, EGGS "), ["milk", "eggs"]);
+        // Not Markdown, so nothing is left out
+        assert_eq!(query_words("[a](b)"), ["a", "b"]);
+        assert!(query_words(" - ").is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// How many entries are kept in memory, the oldest are removed first
+const MAX_ENTRIES: usize = 500;
+/// Size of the journal file before it is moved to `<file>.1` and a new one is started
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Something that happened to the notes or the repository, for diagnosing issues
+#[derive(Debug, Clone, PartialEq)]
+pub enum JournalEvent {
+    NoteCreated {
+        id: String,
+    },
+    NoteSaved {
+        id: String,
+        bytes: u64,
+    },
+    NoteDeleted {
+        id: String,
+    },
+    SyncStarted,
+    /// Sent from the thread pulling, once the changes of the remote are merged
+    Pulled {
+        files: usize,
+    },
+    /// Sent from the thread pushing, once the remote accepted the changes
+    Pushed,
+    SyncFinished {
+        pushed: bool,
+        pulled: usize,
+    },
+    Error {
+        message: String,
+        detail: String,
+    },
+}
+
+impl JournalEvent {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::NoteCreated { .. } => "NoteCreated",
+            Self::NoteSaved { .. } => "NoteSaved",
+            Self::NoteDeleted { .. } => "NoteDeleted",
+            Self::SyncStarted => "SyncStarted",
+            Self::Pulled { .. } => "Pulled",
+            Self::Pushed => "Pushed",
+            Self::SyncFinished { .. } => "SyncFinished",
+            Self::Error { .. } => "Error",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::NoteCreated { id } | Self::NoteDeleted { id } => vec![("id", id.clone())],
+            Self::NoteSaved { id, bytes } => vec![("id", id.clone()), ("bytes", bytes.to_string())],
+            Self::SyncStarted | Self::Pushed => Vec::new(),
+            Self::Pulled { files } => vec![("files", files.to_string())],
+            Self::SyncFinished { pushed, pulled } => {
+                vec![
+                    ("pushed", pushed.to_string()),
+                    ("pulled", pulled.to_string()),
+                ]
+            }
+            Self::Error { message, detail } => {
+                vec![("message", message.clone()), ("detail", detail.clone())]
+            }
+        }
+    }
+}
+
+/// An event with when it was recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub event: JournalEvent,
+}
+
+impl JournalEntry {
+    pub fn new(event: JournalEvent) -> Self {
+        Self {
+            timestamp: chrono::Utc::now(),
+            event,
+        }
+    }
+
+    /// The entry on a single line, as the timestamp, the name of the event, then its fields as
+    /// `key=value`. Values are quoted when they have spaces or characters that need escaping.
+    pub fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} {}",
+            self.timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            self.event.name()
+        );
+
+        for (key, value) in self.event.fields() {
+            line.push(' ');
+            line.push_str(key);
+            line.push('=');
+            line.push_str(&quote_value(&value));
+        }
+
+        line
+    }
+}
+
+fn quote_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '=' || c == '\\');
+
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Keeps the last `capacity` items, dropping the oldest when full
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "RingBuffer must have a capacity");
+
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add `item` as the newest, returning the oldest one if it had to be dropped
+    pub fn push(&mut self, item: T) -> Option<T> {
+        let dropped = if self.items.len() == self.capacity {
+            self.items.pop_front()
+        } else {
+            None
+        };
+
+        self.items.push_back(item);
+        dropped
+    }
+
+    /// Oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.items.iter()
+    }
+}
+
+impl<T> Default for RingBuffer<T> {
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES)
+    }
+}
+
+/// Appends lines to a file, moving it to `<file>.1` once it is larger than `max_bytes`, so
+/// at most two files are kept
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn rotated_path(&self) -> PathBuf {
+        let mut file_name = self.path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".1");
+        self.path.with_file_name(file_name)
+    }
+
+    pub fn append_line(&self, line: &str) -> std::io::Result<()> {
+        let size = fs::metadata(&self.path).map_or(0, |metadata| metadata.len());
+
+        if size > 0 && size + line.len() as u64 + 1 > self.max_bytes {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+/// Sends events to a [`Journal`] from any thread, without waiting for them to be recorded
+#[derive(Debug, Clone)]
+pub struct JournalSender(glib::Sender<JournalEvent>);
+
+impl JournalSender {
+    pub fn send(&self, event: JournalEvent) {
+        if let Err(err) = self.0.send(event) {
+            log::warn!("Failed to send event to the journal: {:?}", err.0);
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct Journal {
+        pub entries: RefCell<RingBuffer<JournalEntry>>,
+        pub file: RefCell<Option<RotatingFile>>,
+        pub sender: OnceCell<JournalSender>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Journal {
+        const NAME: &'static str = "NwtyJournal";
+        type Type = super::Journal;
+    }
+
+    impl ObjectImpl for Journal {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("recorded", &[], <()>::static_type().into()).build()]
+            });
+            SIGNALS.as_ref()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Recent events of the notes and the repository, and optionally a file with them
+    pub struct Journal(ObjectSubclass<imp::Journal>);
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create Journal.")
+    }
+
+    /// Sender to record events from other threads. They are recorded in the default main
+    /// context, so this must be first called on the main thread.
+    pub fn sender(&self) -> JournalSender {
+        self.imp()
+            .sender
+            .get_or_init(|| {
+                let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT_IDLE);
+
+                receiver.attach(
+                    None,
+                    clone!(@weak self as obj => @default-return Continue(false), move |event| {
+                        obj.record(event);
+                        Continue(true)
+                    }),
+                );
+
+                JournalSender(sender)
+            })
+            .clone()
+    }
+
+    /// Record `event` now. This must be called on the main thread, use [`Journal::sender`]
+    /// on others.
+    pub fn record(&self, event: JournalEvent) {
+        let entry = JournalEntry::new(event);
+        let imp = self.imp();
+
+        if let Some(ref file) = *imp.file.borrow() {
+            if let Err(err) = file.append_line(&entry.to_line()) {
+                log::warn!(
+                    "Failed to write to journal file `{}`: {:?}",
+                    file.path().display(),
+                    err
+                );
+            }
+        }
+
+        imp.entries.borrow_mut().push(entry);
+        self.emit_by_name::<()>("recorded", &[]);
+    }
+
+    /// Also write the events recorded from now on to `path`, or stop writing them when `None`
+    pub fn set_file_path(&self, path: Option<PathBuf>) {
+        self.imp()
+            .file
+            .replace(path.map(|path| RotatingFile::new(path, MAX_FILE_BYTES)));
+    }
+
+    /// Oldest first
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.imp().entries.borrow().iter().cloned().collect()
+    }
+
+    pub fn last_entry(&self) -> Option<JournalEntry> {
+        self.imp().entries.borrow().iter().next_back().cloned()
+    }
+
+    pub fn connect_recorded<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("recorded", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the setting with `key` holds something that must not be shared
+pub fn is_secret_setting(key: &str) -> bool {
+    key.contains("token") || key.contains("password")
+}
+
+/// Text to paste in a bug report, with the version, the `settings` as key and value pairs
+/// without the secret ones, and the `entries` oldest first
+pub fn diagnostics_report(
+    version: &str,
+    settings: &[(String, String)],
+    entries: &[JournalEntry],
+) -> String {
+    let mut report = format!("Noteworthy {}\n\nSettings:\n", version);
+
+    for (key, value) in settings {
+        if !is_secret_setting(key) {
+            report.push_str(&format!("  {}: {}\n", key, value));
+        }
+    }
+
+    report.push_str("\nEvents:\n");
+    for entry in entries {
+        report.push_str(&entry.to_line());
+        report.push('\n');
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    use std::{cell::Cell, rc::Rc};
+
+    fn entry(event: JournalEvent) -> JournalEntry {
+        JournalEntry {
+            timestamp: chrono::Utc.ymd(2022, 3, 14).and_hms_milli(9, 26, 53, 589),
+            event,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("noteworthy-journal-test-{}", name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest() {
+        let mut buffer = RingBuffer::new(3);
+        assert_eq!(buffer.iter().count(), 0);
+
+        assert_eq!(buffer.push(1), None);
+        assert_eq!(buffer.push(2), None);
+        assert_eq!(buffer.push(3), None);
+        assert_eq!(buffer.iter().count(), 3);
+
+        assert_eq!(buffer.push(4), Some(1));
+        assert_eq!(buffer.push(5), Some(2));
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(buffer.iter().next_back(), Some(&5));
+    }
+
+    #[test]
+    fn ring_buffer_capacity_one() {
+        let mut buffer = RingBuffer::new(1);
+        assert_eq!(buffer.push("a"), None);
+        assert_eq!(buffer.push("b"), Some("a"));
+        assert_eq!(buffer.iter().collect::<Vec<_>>(), vec![&"b"]);
+    }
+
+    #[test]
+    fn line_without_fields() {
+        assert_eq!(
+            entry(JournalEvent::SyncStarted).to_line(),
+            "2022-03-14T09:26:53.589Z SyncStarted"
+        );
+    }
+
+    #[test]
+    fn line_with_fields() {
+        let line = entry(JournalEvent::NoteSaved {
+            id: "Note-1234".into(),
+            bytes: 2048,
+        })
+        .to_line();
+        assert_eq!(
+            line,
+            "2022-03-14T09:26:53.589Z NoteSaved id=Note-1234 bytes=2048"
+        );
+
+        let line = entry(JournalEvent::SyncFinished {
+            pushed: true,
+            pulled: 3,
+        })
+        .to_line();
+        assert_eq!(
+            line,
+            "2022-03-14T09:26:53.589Z SyncFinished pushed=true pulled=3"
+        );
+    }
+
+    #[test]
+    fn line_quotes_values() {
+        let line = entry(JournalEvent::Error {
+            message: "Failed to sync notes".into(),
+            detail: "remote said \"no\"\nat C:\\repo".into(),
+        })
+        .to_line();
+        assert_eq!(
+            line,
+            r#"2022-03-14T09:26:53.589Z Error message="Failed to sync notes" detail="remote said \"no\"\nat C:\\repo""#
+        );
+        assert!(!line.contains('\n'));
+
+        let line = entry(JournalEvent::NoteCreated { id: "".into() }).to_line();
+        assert!(line.ends_with(r#"id="""#));
+    }
+
+    #[test]
+    fn record() {
+        let journal = Journal::new();
+        let n_recorded = Rc::new(Cell::new(0));
+        journal.connect_recorded(clone!(@strong n_recorded => move |_| {
+            n_recorded.set(n_recorded.get() + 1);
+        }));
+
+        journal.record(JournalEvent::SyncStarted);
+        journal.record(JournalEvent::Pushed);
+        assert_eq!(n_recorded.get(), 2);
+
+        let events = journal
+            .entries()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![JournalEvent::SyncStarted, JournalEvent::Pushed]
+        );
+        assert_eq!(journal.last_entry().unwrap().event, JournalEvent::Pushed);
+    }
+
+    #[test]
+    fn record_max_entries() {
+        let journal = Journal::new();
+
+        for files in 0..MAX_ENTRIES + 5 {
+            journal.record(JournalEvent::Pulled { files });
+        }
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].event, JournalEvent::Pulled { files: 5 });
+    }
+
+    #[test]
+    fn send_from_other_thread() {
+        let journal = Journal::new();
+        let sender = journal.sender();
+
+        std::thread::spawn(move || sender.send(JournalEvent::Pulled { files: 2 }))
+            .join()
+            .unwrap();
+        assert!(journal.entries().is_empty());
+
+        let ctx = glib::MainContext::default();
+        while ctx.pending() {
+            ctx.iteration(false);
+        }
+
+        assert_eq!(
+            journal.last_entry().unwrap().event,
+            JournalEvent::Pulled { files: 2 }
+        );
+    }
+
+    #[test]
+    fn rotating_file() {
+        let dir = temp_dir("rotating-file");
+        let file = RotatingFile::new(dir.join("journal.log"), 20);
+
+        file.append_line("0123456789").unwrap();
+        assert!(!file.rotated_path().exists());
+
+        // Would be 22 bytes with this line
+        file.append_line("abcdefghij").unwrap();
+        assert_eq!(
+            fs::read_to_string(file.rotated_path()).unwrap(),
+            "0123456789\n"
+        );
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "abcdefghij\n");
+        assert_eq!(file.rotated_path(), dir.join("journal.log.1"));
+    }
+
+    #[test]
+    fn record_to_file() {
+        let dir = temp_dir("record-to-file");
+        let path = dir.join("cache").join("journal.log");
+
+        let journal = Journal::new();
+        journal.record(JournalEvent::SyncStarted);
+        journal.set_file_path(Some(path.clone()));
+        journal.record(JournalEvent::NoteCreated { id: "A".into() });
+        journal.set_file_path(None);
+        journal.record(JournalEvent::Pushed);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        assert!(content.trim_end().ends_with("NoteCreated id=A"));
+    }
+
+    #[test]
+    fn diagnostics_without_secrets() {
+        let settings = vec![
+            ("lock-timeout".to_string(), "5".to_string()),
+            ("remote-password".to_string(), "'hunter2'".to_string()),
+            ("publish-token".to_string(), "'ghp_1234'".to_string()),
+        ];
+        let entries = vec![
+            entry(JournalEvent::SyncStarted),
+            entry(JournalEvent::Pulled { files: 1 }),
+        ];
+
+        let report = diagnostics_report("1.0", &settings, &entries);
+        assert!(report.starts_with("Noteworthy 1.0\n"));
+        assert!(report.contains("  lock-timeout: 5\n"));
+        assert!(!report.contains("hunter2"));
+        assert!(!report.contains("ghp_1234"));
+        assert!(report.find("SyncStarted").unwrap() < report.find("Pulled files=1").unwrap());
+    }
+}
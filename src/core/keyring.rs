@@ -0,0 +1,124 @@
+use gtk::{gio, glib};
+
+use std::collections::HashMap;
+
+use crate::config::APP_ID;
+
+const APPLICATION_ATTRIBUTE: &str = "application";
+const KIND_ATTRIBUTE: &str = "kind";
+const REMOTE_ATTRIBUTE: &str = "remote";
+
+/// A secret of the app in the keyring, which is found by the attributes `application` set to
+/// the app id and `kind`.
+///
+/// Items stored by other tools, such as with
+/// `secret-tool store --label=Noteworthy application <app id> kind gist-token`, are found too,
+/// as the name of the schema is not matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secret<'a> {
+    /// GitHub token for publishing gists
+    GistToken,
+    /// What the user entered when the remote at this URL rejected the credentials, which is
+    /// also stored as the `remote` attribute
+    RemoteCredential(&'a str),
+    /// Text of the [`PinHash`](super::PinHash) of the PIN asked to unlock the notes
+    LockPinHash,
+}
+
+impl<'a> Secret<'a> {
+    fn kind(self) -> &'static str {
+        match self {
+            Self::GistToken => "gist-token",
+            Self::RemoteCredential(_) => "remote-credential",
+            Self::LockPinHash => "lock-pin-hash",
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Self::GistToken => "Noteworthy GitHub Token".to_string(),
+            Self::RemoteCredential(remote_url) => format!("Noteworthy: {}", remote_url),
+            Self::LockPinHash => "Noteworthy Lock PIN".to_string(),
+        }
+    }
+
+    fn attributes(self) -> HashMap<&'static str, &'a str> {
+        let mut attributes = HashMap::from([
+            (APPLICATION_ATTRIBUTE, APP_ID),
+            (KIND_ATTRIBUTE, self.kind()),
+        ]);
+
+        if let Self::RemoteCredential(remote_url) = self {
+            attributes.insert(REMOTE_ATTRIBUTE, remote_url);
+        }
+
+        attributes
+    }
+}
+
+/// Look up `secret` in the keyring, or `None` if it is not stored.
+///
+/// This blocks until the keyring responds, which may need to be unlocked first.
+pub fn lookup(secret: Secret<'_>) -> Result<Option<String>, glib::Error> {
+    let value = libsecret::password_lookup_sync(
+        Some(&schema()),
+        secret.attributes(),
+        gio::Cancellable::NONE,
+    )?;
+
+    Ok(value.map(String::from).filter(|value| !value.is_empty()))
+}
+
+/// Store `value` as `secret` in the keyring, replacing the one before.
+///
+/// This blocks until the keyring responds, which may need to be unlocked first.
+pub fn store(secret: Secret<'_>, value: &str) -> Result<(), glib::Error> {
+    libsecret::password_store_sync(
+        Some(&schema()),
+        secret.attributes(),
+        None,
+        &secret.label(),
+        value,
+        gio::Cancellable::NONE,
+    )
+}
+
+/// Remove `secret` from the keyring, if it is stored.
+///
+/// This blocks until the keyring responds, which may need to be unlocked first.
+pub fn clear(secret: Secret<'_>) -> Result<(), glib::Error> {
+    libsecret::password_clear_sync(Some(&schema()), secret.attributes(), gio::Cancellable::NONE)
+}
+
+fn schema() -> libsecret::Schema {
+    libsecret::Schema::new(
+        APP_ID,
+        libsecret::SchemaFlags::DONT_MATCH_NAME,
+        HashMap::from([
+            (
+                APPLICATION_ATTRIBUTE,
+                libsecret::SchemaAttributeType::String,
+            ),
+            (KIND_ATTRIBUTE, libsecret::SchemaAttributeType::String),
+            (REMOTE_ATTRIBUTE, libsecret::SchemaAttributeType::String),
+        ]),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        let attributes = Secret::GistToken.attributes();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes["application"], APP_ID);
+        assert_eq!(attributes["kind"], "gist-token");
+
+        let attributes = Secret::RemoteCredential("git@github.com:SeaDve/test.git").attributes();
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(attributes["kind"], "remote-credential");
+        assert_eq!(attributes["remote"], "git@github.com:SeaDve/test.git");
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+/// Upper bounds of the buckets in microseconds, the last one taking the rest
+const BUCKET_BOUNDS_MICROS: [u64; 12] = [
+    50,
+    100,
+    250,
+    500,
+    1_000,
+    2_000,
+    4_000,
+    8_000,
+    16_000,
+    32_000,
+    64_000,
+    u64::MAX,
+];
+
+/// How long something took each time it was measured, counted in buckets so it takes the
+/// same memory however many times that is
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; BUCKET_BOUNDS_MICROS.len()],
+    n_samples: u64,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, duration: Duration) {
+        let micros = duration.as_micros();
+        let index = BUCKET_BOUNDS_MICROS
+            .iter()
+            .position(|bound| micros <= u128::from(*bound))
+            .unwrap_or(BUCKET_BOUNDS_MICROS.len() - 1);
+
+        self.counts[index] += 1;
+        self.n_samples += 1;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn len(&self) -> u64 {
+        self.n_samples
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_samples == 0
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The time at most `fraction` of the measurements took, like `0.95` for the 95th
+    /// percentile. It is the bound of the bucket it is in, or the longest time when that is
+    /// shorter, so it is never below the exact one.
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let rank = ((fraction.clamp(0.0, 1.0) * self.n_samples as f64).ceil() as u64).max(1);
+
+        let mut n_samples = 0;
+        for (count, bound) in self.counts.iter().zip(BUCKET_BOUNDS_MICROS) {
+            n_samples += count;
+
+            if n_samples >= rank {
+                return Some(Duration::from_micros(bound).min(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+
+    /// The counts of the buckets that are not empty, like `≤0.05 ms: 3, ≤0.10 ms: 1`
+    pub fn to_line(&self) -> String {
+        self.counts
+            .iter()
+            .zip(BUCKET_BOUNDS_MICROS)
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, bound)| {
+                if bound == u64::MAX {
+                    format!(
+                        ">{}: {}",
+                        format_latency(Duration::from_micros(
+                            BUCKET_BOUNDS_MICROS[BUCKET_BOUNDS_MICROS.len() - 2]
+                        )),
+                        count
+                    )
+                } else {
+                    format!(
+                        "≤{}: {}",
+                        format_latency(Duration::from_micros(bound)),
+                        count
+                    )
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `duration` in milliseconds, precise enough for the time of a handler, like `0.25 ms`
+pub fn format_latency(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn micros(micros: u64) -> Duration {
+        Duration::from_micros(micros)
+    }
+
+    #[test]
+    fn empty() {
+        let histogram = LatencyHistogram::default();
+        assert!(histogram.is_empty());
+        assert_eq!(histogram.percentile(0.5), None);
+        assert_eq!(histogram.to_line(), "");
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..90 {
+            histogram.record(micros(30));
+        }
+        for _ in 0..9 {
+            histogram.record(micros(700));
+        }
+        histogram.record(micros(3_500));
+
+        assert_eq!(histogram.len(), 100);
+        assert_eq!(histogram.max(), micros(3_500));
+        assert_eq!(histogram.percentile(0.5), Some(micros(50)));
+        assert_eq!(histogram.percentile(0.9), Some(micros(50)));
+        assert_eq!(histogram.percentile(0.95), Some(micros(1_000)));
+        // Not above the longest time
+        assert_eq!(histogram.percentile(1.0), Some(micros(3_500)));
+        assert_eq!(histogram.percentile(0.0), Some(micros(50)));
+    }
+
+    #[test]
+    fn percentile_of_single_measurement() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(micros(20));
+        assert_eq!(histogram.percentile(0.5), Some(micros(20)));
+
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_secs(1));
+        assert_eq!(histogram.percentile(0.95), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn bucket_bounds() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(micros(50));
+        histogram.record(micros(51));
+        histogram.record(micros(2_000));
+        histogram.record(Duration::from_millis(100));
+
+        assert_eq!(
+            histogram.to_line(),
+            "≤0.05 ms: 1, ≤0.10 ms: 1, ≤2.00 ms: 1, >64.00 ms: 1"
+        );
+    }
+
+    #[test]
+    fn latency_format() {
+        assert_eq!(format_latency(micros(250)), "0.25 ms");
+        assert_eq!(format_latency(Duration::from_millis(16)), "16.00 ms");
+    }
+}
@@ -0,0 +1,141 @@
+use similar::{Algorithm, DiffTag};
+
+use std::time::{Duration, Instant};
+
+use super::{combined_export::atx_heading_level, is_code_fence};
+
+/// Past it, the changed lines are not the fewest possible, so comparing them while typing in a
+/// note that changed a lot stays fast
+const DIFF_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How a line of a note differs from when it was last saved, shown next to it in the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeMark {
+    /// The line is new
+    Added,
+    /// The line replaces lines that were there
+    Modified,
+    /// Lines were removed right before this one
+    Removed,
+}
+
+/// The mark of each line of `current`, by its number, telling how it differs from `saved`.
+/// Unchanged lines have none. The lines are the ones of a text buffer, so there is one more
+/// than line breaks.
+pub fn changed_lines(saved: &str, current: &str) -> Vec<Option<ChangeMark>> {
+    let saved_lines = saved.split('\n').collect::<Vec<_>>();
+    let current_lines = current.split('\n').collect::<Vec<_>>();
+    let mut marks = vec![None; current_lines.len()];
+
+    let ops = similar::capture_diff_slices_deadline(
+        Algorithm::Myers,
+        &saved_lines,
+        &current_lines,
+        Some(Instant::now() + DIFF_TIMEOUT),
+    );
+
+    for op in ops {
+        let (tag, _, current_range) = op.as_tag_tuple();
+
+        match tag {
+            DiffTag::Equal => (),
+            DiffTag::Insert => marks[current_range].fill(Some(ChangeMark::Added)),
+            DiffTag::Replace => marks[current_range].fill(Some(ChangeMark::Modified)),
+            DiffTag::Delete => {
+                // Removed at the end, after the last line
+                let line = current_range.start.min(marks.len() - 1);
+                marks[line].get_or_insert(ChangeMark::Removed);
+            }
+        }
+    }
+
+    marks
+}
+
+/// The level of each line of `text` that is a heading like `## Heading`, by its number.
+/// Lines in code blocks are not headings, even when they start with `#`.
+pub fn heading_levels(text: &str) -> Vec<Option<usize>> {
+    let mut is_in_code_block = false;
+
+    text.split('\n')
+        .map(|line| {
+            if is_code_fence(line) {
+                is_in_code_block = !is_in_code_block;
+                return None;
+            }
+
+            if is_in_code_block {
+                None
+            } else {
+                atx_heading_level(line)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ChangeMark::*;
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(changed_lines("Milk\nEggs", "Milk\nEggs"), [None, None]);
+        assert_eq!(changed_lines("", ""), [None]);
+    }
+
+    #[test]
+    fn added_lines() {
+        assert_eq!(
+            changed_lines("Milk\nBread", "Milk\nEggs\nButter\nBread"),
+            [None, Some(Added), Some(Added), None]
+        );
+        // The empty line of an empty note is kept after the new one
+        assert_eq!(changed_lines("", "Milk\n"), [Some(Added), None]);
+    }
+
+    #[test]
+    fn modified_lines() {
+        assert_eq!(
+            changed_lines("Milk\nEggs\nBread", "Milk\nSix eggs\nBread"),
+            [None, Some(Modified), None]
+        );
+    }
+
+    #[test]
+    fn removed_lines() {
+        assert_eq!(
+            changed_lines("Milk\nEggs\nButter\nBread", "Milk\nBread"),
+            [None, Some(Removed)]
+        );
+
+        // After the last line
+        assert_eq!(changed_lines("Milk\nEggs", "Milk"), [Some(Removed)]);
+        assert_eq!(changed_lines("Milk\nEggs", ""), [Some(Modified)]);
+    }
+
+    #[test]
+    fn removed_then_added() {
+        assert_eq!(
+            changed_lines("Milk\nEggs\nBread", "Eggs\nBread\nButter"),
+            [Some(Removed), None, Some(Added)]
+        );
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(
+            heading_levels("# Groceries\nMilk\n## Dairy\n#hashtag\n   ### Indented\n####"),
+            [Some(1), None, Some(2), None, Some(3), Some(4)]
+        );
+    }
+
+    #[test]
+    fn headings_in_code() {
+        assert_eq!(
+            heading_levels("```sh\n# Comment\n```\n# Heading"),
+            [None, None, None, Some(1)]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::ops::Range;
+
+/// Schemes of the links found, which are matched case-insensitively
+const SCHEMES: &[&str] = &["https://", "http://", "mailto:", "file://"];
+/// Not part of a link when at its end, as it is more likely punctuation of the sentence
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ':', ';', '!', '?', '\'', '"', '*', '_', '~'];
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+/// A link found in some text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLink {
+    /// Byte range of the link in the text
+    pub range: Range<usize>,
+    /// What the link opens. This is the text of the link, with `mailto:` prepended to emails
+    /// written without it.
+    pub uri: String,
+}
+
+/// The `http(s)://`, `mailto:` and `file://` links and emails in `text`, in order
+///
+/// Domains without a scheme, like `example.com`, are not links, as they are too often
+/// just words with a dot.
+pub fn find_links(text: &str) -> Vec<TextLink> {
+    let mut links = Vec::new();
+
+    let mut start = 0;
+    while start < text.len() {
+        match scheme_link_at(text, start) {
+            Some(end) => {
+                links.push(TextLink {
+                    range: start..end,
+                    uri: text[start..end].to_string(),
+                });
+                start = end;
+            }
+            None => start += next_char_len(text, start),
+        }
+    }
+
+    let mut emails = Vec::new();
+    for (at_index, _) in text.match_indices('@') {
+        let is_in_link = links.iter().any(|link| link.range.contains(&at_index));
+        let is_in_email = emails
+            .iter()
+            .any(|email: &TextLink| email.range.contains(&at_index));
+
+        if is_in_link || is_in_email {
+            continue;
+        }
+
+        if let Some(range) = email_around(text, at_index) {
+            emails.push(TextLink {
+                uri: format!("mailto:{}", &text[range.clone()]),
+                range,
+            });
+        }
+    }
+
+    links.extend(emails);
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// Whether `line` opens or closes a fenced code block, in which links are not detected
+pub fn is_code_fence(line: &str) -> bool {
+    let line = line.trim_start_matches(' ');
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// The end of the link with a scheme starting at `start`, if there is one
+fn scheme_link_at(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start..];
+    let scheme = SCHEMES.iter().find(|scheme| {
+        rest.get(..scheme.len())
+            .map_or(false, |prefix| prefix.eq_ignore_ascii_case(scheme))
+    })?;
+
+    // Part of a word, like `xhttp://`, or of another scheme, like `svn+http://`
+    let is_word_before = text[..start].chars().next_back().map_or(false, |c| {
+        c.is_alphanumeric() || matches!(c, '+' | '-' | '.')
+    });
+    if is_word_before {
+        return None;
+    }
+
+    let body_start = start + scheme.len();
+    let body_len = text[body_start..]
+        .find(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '`'))
+        .unwrap_or(text.len() - body_start);
+    let body = trim_link_end(&text[body_start..body_start + body_len]);
+
+    let is_valid = match *scheme {
+        "mailto:" => body
+            .split_once('@')
+            .map_or(false, |(user, host)| !user.is_empty() && !host.is_empty()),
+        "file://" => body.starts_with('/') && body.len() > 1,
+        // Hosts start with a letter or digit, or `[` for IPv6 addresses
+        _ => body
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_alphanumeric() || c == '['),
+    };
+    if !is_valid {
+        return None;
+    }
+
+    Some(body_start + body.len())
+}
+
+/// Remove what is more likely around the link than part of it, like the period ending the
+/// sentence or the parenthesis closing the one it is in
+fn trim_link_end(mut link: &str) -> &str {
+    while let Some(last) = link.chars().next_back() {
+        let is_unbalanced_bracket = BRACKETS.iter().any(|&(open, close)| {
+            last == close && link.matches(close).count() > link.matches(open).count()
+        });
+
+        if TRAILING_PUNCTUATION.contains(&last) || is_unbalanced_bracket {
+            link = &link[..link.len() - last.len_utf8()];
+        } else {
+            break;
+        }
+    }
+
+    link
+}
+
+/// The range of the email with its `@` at `at_index`, if it is a valid one
+fn email_around(text: &str, at_index: usize) -> Option<Range<usize>> {
+    let is_local_char = |c: char| c.is_alphanumeric() || "._%+-".contains(c);
+    let is_domain_char = |c: char| c.is_alphanumeric() || c == '-' || c == '.';
+
+    let local_start = text[..at_index]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_local_char(c))
+        .last()
+        .map_or(at_index, |(index, _)| index);
+    // Dots can't start the local part, so these are likely punctuation
+    let local_start = local_start + text[local_start..at_index].len()
+        - text[local_start..at_index].trim_start_matches('.').len();
+
+    // Parts of something else, like `user:pass@host` or paths
+    let is_other_before = text[..local_start]
+        .chars()
+        .next_back()
+        .map_or(false, |c| matches!(c, '/' | ':' | '@'));
+    if local_start == at_index || is_other_before {
+        return None;
+    }
+
+    let domain_start = at_index + 1;
+    let domain_len = text[domain_start..]
+        .find(|c: char| !is_domain_char(c))
+        .unwrap_or(text.len() - domain_start);
+    let domain = text[domain_start..domain_start + domain_len].trim_end_matches(&['.', '-'][..]);
+
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let is_valid_domain = labels.len() >= 2
+        && labels
+            .iter()
+            .all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+        && labels.last().map_or(false, |tld| {
+            tld.chars().count() >= 2 && tld.chars().all(char::is_alphabetic)
+        });
+    if !is_valid_domain {
+        return None;
+    }
+
+    Some(local_start..domain_start + domain.len())
+}
+
+fn next_char_len(text: &str, index: usize) -> usize {
+    text[index..].chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn link_texts(text: &str) -> Vec<&str> {
+        find_links(text)
+            .into_iter()
+            .map(|link| &text[link.range])
+            .collect()
+    }
+
+    #[test]
+    fn boundaries() {
+        let cases: &[(&str, &[&str])] = &[
+            // Plain
+            ("https://example.com", &["https://example.com"]),
+            ("http://example.com/a/b", &["http://example.com/a/b"]),
+            ("See https://example.com here", &["https://example.com"]),
+            ("HTTPS://EXAMPLE.COM", &["HTTPS://EXAMPLE.COM"]),
+            (
+                "https://example.com/search?q=a+b&lang=en#top",
+                &["https://example.com/search?q=a+b&lang=en#top"],
+            ),
+            ("https://example.com:8080/", &["https://example.com:8080/"]),
+            ("http://[::1]:8080/x", &["http://[::1]:8080/x"]),
+            ("https://例え.jp/パス", &["https://例え.jp/パス"]),
+            (
+                "https://a.com and http://b.org",
+                &["https://a.com", "http://b.org"],
+            ),
+            // Trailing punctuation
+            ("Go to https://example.com.", &["https://example.com"]),
+            ("https://example.com, then", &["https://example.com"]),
+            ("Really https://example.com?!", &["https://example.com"]),
+            ("https://example.com/a.b...", &["https://example.com/a.b"]),
+            ("https://example.com/path/;", &["https://example.com/path/"]),
+            ("'https://example.com'", &["https://example.com"]),
+            ("\"https://example.com\"", &["https://example.com"]),
+            ("**https://example.com**", &["https://example.com"]),
+            ("_https://example.com_", &["https://example.com"]),
+            ("https://example.com/?q=1.", &["https://example.com/?q=1"]),
+            // Brackets
+            ("(https://example.com)", &["https://example.com"]),
+            ("(see https://example.com).", &["https://example.com"]),
+            (
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                &["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+            ),
+            (
+                "(https://en.wikipedia.org/wiki/Rust_(programming_language))",
+                &["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+            ),
+            (
+                "[Example](https://example.com/a)",
+                &["https://example.com/a"],
+            ),
+            ("[https://example.com]", &["https://example.com"]),
+            ("{https://example.com}", &["https://example.com"]),
+            ("https://example.com/a[1]", &["https://example.com/a[1]"]),
+            ("<https://example.com>", &["https://example.com"]),
+            ("`https://example.com`", &["https://example.com"]),
+            // Word boundaries
+            ("xhttps://example.com", &[]),
+            ("svn+https://example.com", &[]),
+            ("1https://example.com", &[]),
+            // Not links
+            ("https://", &[]),
+            ("https:// example.com", &[]),
+            ("https://.", &[]),
+            ("https://-", &[]),
+            ("example.com", &[]),
+            ("www.example.com", &[]),
+            ("ftp://example.com", &[]),
+            ("http:/example.com", &[]),
+            ("Just text.", &[]),
+            ("", &[]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(&link_texts(text), expected, "for `{}`", text);
+        }
+    }
+
+    #[test]
+    fn mailto_and_file() {
+        let cases: &[(&str, &[&str])] = &[
+            ("mailto:me@example.com", &["mailto:me@example.com"]),
+            (
+                "Mail mailto:me@example.com?subject=Hi.",
+                &["mailto:me@example.com?subject=Hi"],
+            ),
+            ("mailto:", &[]),
+            ("mailto:me", &[]),
+            ("mailto:@example.com", &[]),
+            (
+                "file:///home/me/Notes/a.md",
+                &["file:///home/me/Notes/a.md"],
+            ),
+            ("(file:///home/me/a.md)", &["file:///home/me/a.md"]),
+            ("file://", &[]),
+            ("file:///", &[]),
+            ("file://host/a", &[]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(&link_texts(text), expected, "for `{}`", text);
+        }
+    }
+
+    #[test]
+    fn emails() {
+        let cases: &[(&str, &[&str])] = &[
+            ("me@example.com", &["me@example.com"]),
+            ("Mail me@example.com.", &["me@example.com"]),
+            (
+                "(first.last+tag@mail.example.org)",
+                &["first.last+tag@mail.example.org"],
+            ),
+            ("<me@example.com>", &["me@example.com"]),
+            ("...me@example.com", &["me@example.com"]),
+            ("me@example.com-", &["me@example.com"]),
+            ("a@b.co and c@d.io", &["a@b.co", "c@d.io"]),
+            // Not emails
+            ("@example.com", &[]),
+            ("me@", &[]),
+            ("me@localhost", &[]),
+            ("me@example.c", &[]),
+            ("me@example.123", &[]),
+            ("me@-example.com", &[]),
+            ("me@example..com", &[]),
+            ("@mention", &[]),
+            ("me@@example.com", &[]),
+            ("/path/me@example.com", &[]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(&link_texts(text), expected, "for `{}`", text);
+        }
+    }
+
+    #[test]
+    fn emails_not_in_links() {
+        assert_eq!(
+            link_texts("https://me@example.com/x and you@example.com"),
+            ["https://me@example.com/x", "you@example.com"]
+        );
+        assert_eq!(
+            link_texts("mailto:me@example.com"),
+            ["mailto:me@example.com"]
+        );
+    }
+
+    #[test]
+    fn uris() {
+        let links = find_links("me@example.com https://example.com");
+        assert_eq!(links[0].uri, "mailto:me@example.com");
+        assert_eq!(links[0].range, 0..14);
+        assert_eq!(links[1].uri, "https://example.com");
+        assert_eq!(links[1].range, 15..34);
+    }
+
+    #[test]
+    fn code_fence() {
+        assert!(is_code_fence("```"));
+        assert!(is_code_fence("```rust"));
+        assert!(is_code_fence("  ~~~"));
+        assert!(!is_code_fence("``"));
+        assert!(!is_code_fence("Text ```"));
+        assert!(!is_code_fence("`code`"));
+    }
+}
@@ -0,0 +1,36 @@
+/// Number of notes added to the list at once while loading, so the first ones are shown
+/// before the rest are read
+pub const LOAD_BATCH_SIZE: usize = 50;
+
+/// Fraction from 0 to 1 of the notes that are loaded, once `n_enumerated` files were found
+/// in the notes directory and `n_processed` of them were read. Finding no files means there is
+/// nothing left to load.
+pub fn load_progress(n_processed: usize, n_enumerated: usize) -> f64 {
+    if n_enumerated == 0 {
+        return 1.0;
+    }
+
+    (n_processed as f64 / n_enumerated as f64).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fraction() {
+        assert_eq!(load_progress(0, 4), 0.0);
+        assert_eq!(load_progress(1, 4), 0.25);
+        assert_eq!(load_progress(4, 4), 1.0);
+    }
+
+    #[test]
+    fn nothing_to_load() {
+        assert_eq!(load_progress(0, 0), 1.0);
+    }
+
+    #[test]
+    fn never_above_one() {
+        assert_eq!(load_progress(5, 4), 1.0);
+    }
+}
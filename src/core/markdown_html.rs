@@ -0,0 +1,742 @@
+use gtk::TextDirection;
+
+use super::{base_direction, find_links};
+
+/// Render the markdown of a note as HTML, to paste it with its formatting into other apps
+///
+/// This covers the common parts of markdown: headings, paragraphs, emphasis, code, links,
+/// images, lists, quotes, and rules. Anything else, including raw HTML, is kept as text.
+///
+/// The blocks in a right-to-left language, and the ones in a left-to-right one inside them,
+/// have a `dir` attribute, so they read in the right direction in a page.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let lines = markdown.lines().collect::<Vec<_>>();
+    let mut html = String::with_capacity(markdown.len() + markdown.len() / 4);
+    push_blocks(&lines, TextDirection::Ltr, &mut html);
+    html
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListKind {
+    Unordered,
+    Ordered,
+}
+
+struct ListItem<'a> {
+    kind: ListKind,
+    number: u32,
+    text: &'a str,
+}
+
+/// Push the blocks of `lines`, which are in an element of `direction`
+fn push_blocks(lines: &[&str], direction: TextDirection, html: &mut String) {
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+
+        if line.is_empty() {
+            i += 1;
+        } else if let Some(fence) = code_fence(line) {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with(fence))
+                .map_or(lines.len(), |n| i + 1 + n);
+
+            // Code reads from the left even in a right-to-left note
+            let language = line[fence.len()..].trim();
+            html.push_str(&format!(
+                "<pre{}>",
+                dir_attribute(Some(TextDirection::Ltr), direction)
+            ));
+            if language.is_empty() {
+                html.push_str("<code>");
+            } else {
+                html.push_str(&format!("<code class=\"language-{}\">", escape(language)));
+            }
+            for line in &lines[i + 1..end] {
+                html.push_str(&escape(line));
+                html.push('\n');
+            }
+            html.push_str("</code></pre>\n");
+
+            i = end + 1;
+        } else if let Some((level, text)) = heading(line) {
+            html.push_str(&format!(
+                "<h{}{}>",
+                level,
+                dir_attribute(base_direction(text), direction)
+            ));
+            push_inline(text, html);
+            html.push_str(&format!("</h{}>\n", level));
+
+            i += 1;
+        } else if is_thematic_break(line) {
+            html.push_str("<hr>\n");
+
+            i += 1;
+        } else if line.starts_with('>') {
+            let end = lines[i..]
+                .iter()
+                .position(|line| !line.trim_start().starts_with('>'))
+                .map_or(lines.len(), |n| i + n);
+
+            let quoted_lines = lines[i..end]
+                .iter()
+                .map(|line| {
+                    let line = &line.trim_start()[1..];
+                    line.strip_prefix(' ').unwrap_or(line)
+                })
+                .collect::<Vec<_>>();
+
+            let quote_direction = base_direction(&quoted_lines.join("\n"));
+            html.push_str(&format!(
+                "<blockquote{}>\n",
+                dir_attribute(quote_direction, direction)
+            ));
+            push_blocks(&quoted_lines, quote_direction.unwrap_or(direction), html);
+            html.push_str("</blockquote>\n");
+
+            i = end;
+        } else if let Some(item) = list_item(line) {
+            i = push_list(lines, i, item.kind, item.number, direction, html);
+        } else {
+            let end = lines[i + 1..]
+                .iter()
+                .position(|line| {
+                    let line = line.trim_start();
+                    line.is_empty() || starts_block(line)
+                })
+                .map_or(lines.len(), |n| i + 1 + n);
+
+            let text = lines[i..end]
+                .iter()
+                .map(|line| line.trim_start())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            html.push_str(&format!(
+                "<p{}>",
+                dir_attribute(base_direction(&text), direction)
+            ));
+            push_inline(&text, html);
+            html.push_str("</p>\n");
+
+            i = end;
+        }
+    }
+}
+
+/// Push the list starting at `start`, in an element of `direction`, and return the index of
+/// the line after it
+fn push_list(
+    lines: &[&str],
+    start: usize,
+    kind: ListKind,
+    number: u32,
+    direction: TextDirection,
+    html: &mut String,
+) -> usize {
+    let mut items: Vec<String> = Vec::new();
+    let mut i = start;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        match list_item(trimmed) {
+            Some(item) if item.kind == kind => items.push(item.text.to_string()),
+            // An indented line continues the item before it
+            None if !trimmed.is_empty() && trimmed.len() < line.len() && !starts_block(trimmed) => {
+                let last_item = items.last_mut().unwrap();
+                last_item.push('\n');
+                last_item.push_str(trimmed);
+            }
+            _ => break,
+        }
+
+        i += 1;
+    }
+
+    // The markers are on the side of the first item
+    let item_texts = items.iter().map(|item| task_item(item)).collect::<Vec<_>>();
+    let list_direction = item_texts.iter().find_map(|(_, text)| base_direction(text));
+    let list_dir_attribute = dir_attribute(list_direction, direction);
+    let list_direction = list_direction.unwrap_or(direction);
+
+    match kind {
+        ListKind::Unordered => html.push_str(&format!("<ul{}>\n", list_dir_attribute)),
+        ListKind::Ordered if number == 1 => {
+            html.push_str(&format!("<ol{}>\n", list_dir_attribute));
+        }
+        ListKind::Ordered => html.push_str(&format!(
+            "<ol{} start=\"{}\">\n",
+            list_dir_attribute, number
+        )),
+    }
+
+    for (is_checked, text) in item_texts {
+        html.push_str(&format!(
+            "<li{}>",
+            dir_attribute(base_direction(text), list_direction)
+        ));
+
+        match is_checked {
+            Some(false) => html.push_str("<input type=\"checkbox\" disabled> "),
+            Some(true) => html.push_str("<input type=\"checkbox\" checked disabled> "),
+            None => (),
+        }
+        push_inline(text, html);
+
+        html.push_str("</li>\n");
+    }
+
+    match kind {
+        ListKind::Unordered => html.push_str("</ul>\n"),
+        ListKind::Ordered => html.push_str("</ol>\n"),
+    }
+
+    i
+}
+
+/// Whether the list item `item` is a checked or unchecked task, and its text without the
+/// checkbox, which isn't part of its direction
+fn task_item(item: &str) -> (Option<bool>, &str) {
+    if let Some(text) = item.strip_prefix("[ ] ") {
+        (Some(false), text)
+    } else if let Some(text) = item
+        .strip_prefix("[x] ")
+        .or_else(|| item.strip_prefix("[X] "))
+    {
+        (Some(true), text)
+    } else {
+        (None, item)
+    }
+}
+
+/// The `dir` attribute of an element of `direction` in one of `parent_direction`, which is
+/// only needed when they differ
+fn dir_attribute(
+    direction: Option<TextDirection>,
+    parent_direction: TextDirection,
+) -> &'static str {
+    match direction {
+        Some(direction) if direction != parent_direction => match direction {
+            TextDirection::Rtl => " dir=\"rtl\"",
+            _ => " dir=\"ltr\"",
+        },
+        _ => "",
+    }
+}
+
+/// Whether `line`, without its indentation, starts a block that ends a paragraph
+fn starts_block(line: &str) -> bool {
+    code_fence(line).is_some()
+        || heading(line).is_some()
+        || is_thematic_break(line)
+        || line.starts_with('>')
+        || list_item(line).is_some()
+}
+
+fn code_fence(line: &str) -> Option<&'static str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|fence| line.starts_with(fence))
+}
+
+/// The level and the text of an ATX heading, like `## Heading`
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+
+    // The closing `#`s, like in `## Heading ##`, are not part of the text
+    let text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    if without_closing.is_empty() || without_closing.ends_with(' ') {
+        Some((level, without_closing.trim_end()))
+    } else {
+        Some((level, text))
+    }
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+
+    match chars.next() {
+        Some(first @ ('-' | '*' | '_')) => {
+            let rest = chars.collect::<Vec<_>>();
+            rest.len() >= 2 && rest.iter().all(|c| *c == first)
+        }
+        _ => false,
+    }
+}
+
+fn list_item(line: &str) -> Option<ListItem<'_>> {
+    fn item_text(rest: &str) -> Option<&str> {
+        if rest.is_empty() {
+            Some("")
+        } else {
+            rest.strip_prefix(' ').map(str::trim_start)
+        }
+    }
+
+    if let Some(rest) = line.strip_prefix(&['-', '*', '+'][..]) {
+        return Some(ListItem {
+            kind: ListKind::Unordered,
+            number: 1,
+            text: item_text(rest)?,
+        });
+    }
+
+    let n_digits = line.chars().take_while(char::is_ascii_digit).count();
+    if !(1..=9).contains(&n_digits) {
+        return None;
+    }
+
+    let rest = line[n_digits..].strip_prefix(&['.', ')'][..])?;
+    Some(ListItem {
+        kind: ListKind::Ordered,
+        number: line[..n_digits].parse().ok()?,
+        text: item_text(rest)?,
+    })
+}
+
+fn push_inline(text: &str, html: &mut String) {
+    let links = find_links(text);
+    let mut pos = 0;
+
+    while pos < text.len() {
+        let rest = &text[pos..];
+
+        if let Some(link) = links.iter().find(|link| link.range.start == pos) {
+            html.push_str(&format!("<a href=\"{}\">", escape(&link.uri)));
+            html.push_str(&escape(&text[link.range.clone()]));
+            html.push_str("</a>");
+            pos = link.range.end;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap();
+        let is_after_word = text[..pos]
+            .chars()
+            .next_back()
+            .map_or(false, char::is_alphanumeric);
+
+        let n_consumed = match c {
+            '\\' => push_escaped(rest, html),
+            '`' => Some(push_code_span(rest, html)),
+            '[' | '!' => push_link(rest, html),
+            '<' => push_autolink(rest, html),
+            // Underscores in words, like in `snake_case`, are not emphasis
+            '_' if is_after_word => None,
+            '*' | '_' | '~' => Some(push_emphasis(rest, html)),
+            ' ' => push_hard_break(rest, html),
+            _ => None,
+        };
+
+        match n_consumed {
+            Some(n_consumed) => pos += n_consumed,
+            None => {
+                html.push_str(&escape(&rest[..c.len_utf8()]));
+                pos += c.len_utf8();
+            }
+        }
+    }
+}
+
+/// A backslash before a newline is a line break, and before punctuation makes it literal
+fn push_escaped(rest: &str, html: &mut String) -> Option<usize> {
+    let escaped = rest[1..].chars().next()?;
+
+    if escaped == '\n' {
+        html.push_str("<br>\n");
+    } else if escaped.is_ascii_punctuation() {
+        html.push_str(&escape(&rest[1..2]));
+    } else {
+        return None;
+    }
+
+    Some(2)
+}
+
+/// Two or more spaces before a newline are a line break
+fn push_hard_break(rest: &str, html: &mut String) -> Option<usize> {
+    let n_spaces = rest.chars().take_while(|c| *c == ' ').count();
+
+    if n_spaces >= 2 && rest[n_spaces..].starts_with('\n') {
+        html.push_str("<br>\n");
+        Some(n_spaces + 1)
+    } else {
+        None
+    }
+}
+
+fn push_code_span(rest: &str, html: &mut String) -> usize {
+    let n_ticks = rest.chars().take_while(|c| *c == '`').count();
+    let ticks = &rest[..n_ticks];
+
+    let closing = rest[n_ticks..].match_indices(ticks).find(|(index, _)| {
+        let after = &rest[n_ticks + index + n_ticks..];
+        !after.starts_with('`')
+    });
+
+    match closing {
+        Some((index, _)) => {
+            let code = rest[n_ticks..n_ticks + index].replace('\n', " ");
+            let code = match code
+                .strip_prefix(' ')
+                .and_then(|code| code.strip_suffix(' '))
+            {
+                Some(stripped) if !stripped.trim().is_empty() => stripped,
+                _ => code.as_str(),
+            };
+
+            html.push_str("<code>");
+            html.push_str(&escape(code));
+            html.push_str("</code>");
+
+            n_ticks + index + n_ticks
+        }
+        None => {
+            html.push_str(ticks);
+            n_ticks
+        }
+    }
+}
+
+/// Links like `[text](url)` and images like `![alt](url)`
+fn push_link(rest: &str, html: &mut String) -> Option<usize> {
+    let is_image = rest.starts_with("![");
+    let label_start = if is_image { 2 } else { 1 };
+
+    if !is_image && !rest.starts_with('[') {
+        return None;
+    }
+
+    let label_end = label_start + closing_bracket(&rest[label_start..], '[', ']')?;
+    let label = &rest[label_start..label_end];
+
+    let destination_start = label_end + 1;
+    if !rest[destination_start..].starts_with('(') {
+        return None;
+    }
+    let destination_end =
+        destination_start + 1 + closing_bracket(&rest[destination_start + 1..], '(', ')')?;
+
+    // The title, like in `[text](url "title")`, is not kept. Destinations with spaces, like
+    // the ones of attachments, are in angle brackets.
+    let destination = rest[destination_start + 1..destination_end].trim();
+    let url = match destination
+        .strip_prefix('<')
+        .and_then(|destination| destination.split_once('>'))
+    {
+        Some((url, _)) => url,
+        None => destination.split_whitespace().next().unwrap_or_default(),
+    };
+
+    if !is_safe_url(url) {
+        return None;
+    }
+
+    if is_image {
+        html.push_str(&format!(
+            "<img src=\"{}\" alt=\"{}\">",
+            escape(url),
+            escape(label)
+        ));
+    } else {
+        html.push_str(&format!("<a href=\"{}\">", escape(url)));
+        push_inline(label, html);
+        html.push_str("</a>");
+    }
+
+    Some(destination_end + 1)
+}
+
+/// Links like `<https://example.com>` and `<name@example.com>`
+fn push_autolink(rest: &str, html: &mut String) -> Option<usize> {
+    let end = rest.find('>')?;
+    let inner = &rest[1..end];
+
+    if inner.is_empty() || inner.contains(char::is_whitespace) || !is_safe_url(inner) {
+        return None;
+    }
+
+    let href = if inner.contains(':') {
+        inner.to_string()
+    } else if inner.contains('@') {
+        format!("mailto:{}", inner)
+    } else {
+        return None;
+    };
+
+    html.push_str(&format!(
+        "<a href=\"{}\">{}</a>",
+        escape(&href),
+        escape(inner)
+    ));
+
+    Some(end + 1)
+}
+
+/// `*em*`, `_em_`, `**strong**`, `__strong__`, and `~~strikethrough~~`. When there is no
+/// closing delimiter, the opening one is kept as text.
+fn push_emphasis(rest: &str, html: &mut String) -> usize {
+    let delimiter = rest.chars().next().unwrap();
+    let n_run = rest.chars().take_while(|c| *c == delimiter).count();
+
+    let (n_delimiters, tag) = match (delimiter, n_run) {
+        ('~', 1) => {
+            html.push('~');
+            return 1;
+        }
+        ('~', _) => (2, "del"),
+        (_, 1) => (1, "em"),
+        _ => (2, "strong"),
+    };
+
+    let body = &rest[n_delimiters..];
+    let closing = body
+        .match_indices(&rest[..n_delimiters])
+        .map(|(index, _)| {
+            // Close with the end of a longer run, like in `***both***`
+            let n_extra = body[index + n_delimiters..]
+                .chars()
+                .take_while(|c| *c == delimiter)
+                .count();
+            index + n_extra
+        })
+        .find(|index| {
+            let inner = &body[..*index];
+            let after = &body[index + n_delimiters..];
+
+            !inner.is_empty()
+                && !inner.starts_with(char::is_whitespace)
+                && !inner.ends_with(char::is_whitespace)
+                // A single delimiter does not close with one of a double, like in `*a **b***`
+                && (n_delimiters == 2 || !inner.ends_with(delimiter))
+                && (delimiter != '_' || !after.starts_with(char::is_alphanumeric))
+        });
+
+    match closing {
+        Some(index) => {
+            html.push_str(&format!("<{}>", tag));
+            push_inline(&body[..index], html);
+            html.push_str(&format!("</{}>", tag));
+            n_delimiters + index + n_delimiters
+        }
+        None => {
+            html.push_str(&rest[..n_run]);
+            n_run
+        }
+    }
+}
+
+/// Index of the bracket that closes the one before `text`, skipping nested pairs
+fn closing_bracket(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+
+    for (index, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some(index);
+            }
+            depth -= 1;
+        }
+    }
+
+    None
+}
+
+/// Links that run code when opened are kept as text
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim_start().to_lowercase();
+    !url.starts_with("javascript:") && !url.starts_with("vbscript:")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headings_and_paragraphs() {
+        assert_eq!(
+            markdown_to_html("# Title\n\nFirst line\nsecond line\n\n### Sub ###"),
+            "<h1>Title</h1>\n<p>First line\nsecond line</p>\n<h3>Sub</h3>\n"
+        );
+        assert_eq!(markdown_to_html("#NotHeading"), "<p>#NotHeading</p>\n");
+        assert_eq!(markdown_to_html("## C#"), "<h2>C#</h2>\n");
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(
+            markdown_to_html("*a* _b_ **c** __d__ ~~e~~ ***f***"),
+            "<p><em>a</em> <em>b</em> <strong>c</strong> <strong>d</strong> <del>e</del> <strong><em>f</em></strong></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("**bold *and italic***"),
+            "<p><strong>bold <em>and italic</em></strong></p>\n"
+        );
+    }
+
+    #[test]
+    fn emphasis_kept_as_text() {
+        assert_eq!(
+            markdown_to_html("snake_case_name"),
+            "<p>snake_case_name</p>\n"
+        );
+        assert_eq!(markdown_to_html("2 * 3 * 4"), "<p>2 * 3 * 4</p>\n");
+        assert_eq!(markdown_to_html("**unclosed"), "<p>**unclosed</p>\n");
+        assert_eq!(markdown_to_html("~single~"), "<p>~single~</p>\n");
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            markdown_to_html("Run `cargo <build>` or ``a ` b``"),
+            "<p>Run <code>cargo &lt;build&gt;</code> or <code>a ` b</code></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("```rust\nfn main() {}\n\n// <end>\n```\nAfter"),
+            "<pre><code class=\"language-rust\">fn main() {}\n\n// &lt;end&gt;\n</code></pre>\n<p>After</p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("~~~\n*not emphasis*"),
+            "<pre><code>*not emphasis*\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            markdown_to_html("[The *site*](https://example.com \"Title\")"),
+            "<p><a href=\"https://example.com\">The <em>site</em></a></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("![A \"cat\"](cat.png)"),
+            "<p><img src=\"cat.png\" alt=\"A &quot;cat&quot;\"></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("![Scan](<Scan 2.png> \"Title\")"),
+            "<p><img src=\"Scan 2.png\" alt=\"Scan\"></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("<https://example.com> <me@example.com>"),
+            "<p><a href=\"https://example.com\">https://example.com</a> <a href=\"mailto:me@example.com\">me@example.com</a></p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("See https://example.com/a_b."),
+            "<p>See <a href=\"https://example.com/a_b\">https://example.com/a_b</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn links_kept_as_text() {
+        assert_eq!(markdown_to_html("[[Wiki link]]"), "<p>[[Wiki link]]</p>\n");
+        assert_eq!(
+            markdown_to_html("[no destination]"),
+            "<p>[no destination]</p>\n"
+        );
+        assert_eq!(
+            markdown_to_html("[click](javascript:alert(1))"),
+            "<p>[click](javascript:alert(1))</p>\n"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            markdown_to_html("\\*not em\\* & <b>raw</b>"),
+            "<p>*not em* &amp; &lt;b&gt;raw&lt;/b&gt;</p>\n"
+        );
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(
+            markdown_to_html("one  \ntwo\\\nthree"),
+            "<p>one<br>\ntwo<br>\nthree</p>\n"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            markdown_to_html("- a\n* b\n  continued\n\n3. c\n4) d"),
+            "<ul>\n<li>a</li>\n<li>b\ncontinued</li>\n</ul>\n<ol start=\"3\">\n<li>c</li>\n<li>d</li>\n</ol>\n"
+        );
+        assert_eq!(
+            markdown_to_html("- [ ] todo\n- [x] done"),
+            "<ul>\n<li><input type=\"checkbox\" disabled> todo</li>\n<li><input type=\"checkbox\" checked disabled> done</li>\n</ul>\n"
+        );
+        assert_eq!(markdown_to_html("-not a list"), "<p>-not a list</p>\n");
+    }
+
+    #[test]
+    fn paragraph_ended_by_block() {
+        assert_eq!(
+            markdown_to_html("Text\n- item\n# Heading"),
+            "<p>Text</p>\n<ul>\n<li>item</li>\n</ul>\n<h1>Heading</h1>\n"
+        );
+    }
+
+    #[test]
+    fn quotes_and_rules() {
+        assert_eq!(
+            markdown_to_html("> # Quoted\n> text\n\n---\n* * *"),
+            "<blockquote>\n<h1>Quoted</h1>\n<p>text</p>\n</blockquote>\n<hr>\n<hr>\n"
+        );
+    }
+
+    #[test]
+    fn right_to_left_blocks() {
+        assert_eq!(
+            markdown_to_html("# קניות\n\nחלב ו**לחם**\n\nMilk"),
+            "<h1 dir=\"rtl\">קניות</h1>\n<p dir=\"rtl\">חלב ו<strong>לחם</strong></p>\n<p>Milk</p>\n"
+        );
+        // Code reads from the left in a right-to-left quote
+        assert_eq!(
+            markdown_to_html("> مرحبا\n> ```\n> let a = 1;\n> ```"),
+            "<blockquote dir=\"rtl\">\n<p>مرحبا</p>\n<pre dir=\"ltr\"><code>let a = 1;\n</code></pre>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn right_to_left_lists() {
+        assert_eq!(
+            markdown_to_html("- [x] חלב\n- Milk\n- 42"),
+            "<ul dir=\"rtl\">\n<li><input type=\"checkbox\" checked disabled> חלב</li>\n<li dir=\"ltr\">Milk</li>\n<li>42</li>\n</ul>\n"
+        );
+        assert_eq!(
+            markdown_to_html("3. 2022\n4. قائمة"),
+            "<ol dir=\"rtl\" start=\"3\">\n<li>2022</li>\n<li>قائمة</li>\n</ol>\n"
+        );
+    }
+}
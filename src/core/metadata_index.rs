@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::MetadataData;
+
+pub const METADATA_INDEX_FILE_NAME: &str = "index.nwty";
+
+/// Changed whenever the entries change, so an index written by an older version is not used
+const VERSION: u32 = 2;
+
+/// When a note file was last modified and its size, to tell whether it changed since it was
+/// indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub modified: Duration,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn for_metadata(metadata: &fs::Metadata) -> Option<Self> {
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+
+        Some(Self {
+            modified,
+            size: metadata.len(),
+        })
+    }
+}
+
+/// What is known of a note file without reading it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub stamp: FileStamp,
+    /// Where the content starts in the file, after the front matter
+    pub body_offset: usize,
+    pub metadata: MetadataData,
+}
+
+/// How the note files in the notes directory differ from the ones in a [`MetadataIndex`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IndexReconciliation {
+    /// Indexed files that changed since, so they have to be parsed again
+    pub stale: Vec<String>,
+    /// Files that are not indexed
+    pub added: Vec<String>,
+    /// Indexed files that no longer exist
+    pub removed: Vec<String>,
+}
+
+impl IndexReconciliation {
+    pub fn is_empty(&self) -> bool {
+        self.stale.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The metadata of the notes of a notes directory by file name, so they can be shown at
+/// startup before their files are read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataIndex {
+    version: u32,
+    directory: PathBuf,
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+impl MetadataIndex {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            version: VERSION,
+            directory: directory.into(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Parse an index of the notes in `directory`. It fails if it is of another version or
+    /// directory, as if it was corrupt.
+    pub fn parse(bytes: &[u8], directory: &Path) -> anyhow::Result<Self> {
+        let index: Self = serde_yaml::from_slice(bytes)?;
+
+        anyhow::ensure!(
+            index.version == VERSION,
+            "Index is of version {}, expected {}",
+            index.version,
+            VERSION
+        );
+        anyhow::ensure!(
+            index.directory == directory,
+            "Index is of `{}`, expected `{}`",
+            index.directory.display(),
+            directory.display()
+        );
+
+        Ok(index)
+    }
+
+    pub fn serialize(&self) -> anyhow::Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    pub fn insert(&mut self, file_name: impl Into<String>, entry: IndexEntry) {
+        self.entries.insert(file_name.into(), entry);
+    }
+
+    /// The file names and entries, ordered by file name
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &IndexEntry)> {
+        self.entries
+            .iter()
+            .map(|(file_name, entry)| (file_name.as_str(), entry))
+    }
+
+    /// Compare the entries with `stamps`, the ones of the note files that exist now by file
+    /// name
+    pub fn reconcile(&self, stamps: &HashMap<String, FileStamp>) -> IndexReconciliation {
+        let mut reconciliation = IndexReconciliation::default();
+
+        for (file_name, entry) in &self.entries {
+            match stamps.get(file_name) {
+                Some(stamp) if *stamp == entry.stamp => (),
+                Some(_) => reconciliation.stale.push(file_name.clone()),
+                None => reconciliation.removed.push(file_name.clone()),
+            }
+        }
+
+        reconciliation.added = stamps
+            .keys()
+            .filter(|file_name| !self.entries.contains_key(*file_name))
+            .cloned()
+            .collect();
+        reconciliation.added.sort();
+
+        reconciliation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::core::{DateTime, ExtraFields};
+
+    fn stamp(modified_secs: u64, size: u64) -> FileStamp {
+        FileStamp {
+            modified: Duration::from_secs(modified_secs),
+            size,
+        }
+    }
+
+    fn entry(title: &str, stamp: FileStamp) -> IndexEntry {
+        let mut extra_fields = serde_yaml::Mapping::new();
+        extra_fields.insert("source".into(), "Other app".into());
+
+        IndexEntry {
+            stamp,
+            body_offset: 120,
+            metadata: MetadataData {
+                title: title.to_string(),
+                tag_list: vec!["Work".to_string(), "Ideas".to_string()],
+                last_modified: DateTime::from_timestamp(1_650_000_000),
+                is_pinned: true,
+                extra_fields: ExtraFields(extra_fields),
+                ..MetadataData::default()
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let directory = Path::new("/home/user/Notes");
+
+        let mut index = MetadataIndex::new(directory);
+        index.insert("a.md", entry("A", stamp(10, 200)));
+        index.insert(
+            "b.md",
+            IndexEntry {
+                stamp: FileStamp {
+                    modified: Duration::new(20, 123_456_789),
+                    size: 0,
+                },
+                body_offset: 0,
+                metadata: MetadataData {
+                    is_trashed: true,
+                    ..MetadataData::default()
+                },
+            },
+        );
+
+        let serialized = index.serialize().unwrap();
+        let parsed = MetadataIndex::parse(serialized.as_bytes(), directory).unwrap();
+        assert_eq!(parsed, index);
+
+        let file_names = parsed
+            .entries()
+            .map(|(file_name, _)| file_name)
+            .collect::<Vec<_>>();
+        assert_eq!(file_names, ["a.md", "b.md"]);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let directory = Path::new("/home/user/Notes");
+
+        assert!(MetadataIndex::parse(b"", directory).is_err());
+        assert!(MetadataIndex::parse(b"version: [", directory).is_err());
+
+        let other_directory = MetadataIndex::new("/home/user/Other").serialize().unwrap();
+        assert!(MetadataIndex::parse(other_directory.as_bytes(), directory).is_err());
+
+        let mut older = MetadataIndex::new(directory);
+        older.version = VERSION - 1;
+        let older = older.serialize().unwrap();
+        assert!(MetadataIndex::parse(older.as_bytes(), directory).is_err());
+    }
+
+    #[test]
+    fn reconcile() {
+        let mut index = MetadataIndex::new("/home/user/Notes");
+        index.insert("same.md", entry("Same", stamp(10, 100)));
+        index.insert("modified.md", entry("Modified", stamp(10, 100)));
+        index.insert("resized.md", entry("Resized", stamp(10, 100)));
+        index.insert("removed.md", entry("Removed", stamp(10, 100)));
+
+        let stamps = HashMap::from([
+            ("same.md".to_string(), stamp(10, 100)),
+            ("modified.md".to_string(), stamp(11, 100)),
+            ("resized.md".to_string(), stamp(10, 101)),
+            ("b-added.md".to_string(), stamp(12, 100)),
+            ("a-added.md".to_string(), stamp(12, 100)),
+        ]);
+
+        let reconciliation = index.reconcile(&stamps);
+        assert_eq!(reconciliation.stale, ["modified.md", "resized.md"]);
+        assert_eq!(reconciliation.added, ["a-added.md", "b-added.md"]);
+        assert_eq!(reconciliation.removed, ["removed.md"]);
+        assert!(!reconciliation.is_empty());
+
+        let stamps = HashMap::from([("same.md".to_string(), stamp(10, 100))]);
+        let mut index = MetadataIndex::new("/home/user/Notes");
+        index.insert("same.md", entry("Same", stamp(10, 100)));
+        assert!(index.reconcile(&stamps).is_empty());
+    }
+}
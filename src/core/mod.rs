@@ -1,21 +1,133 @@
+mod attachment_references;
 mod audio_player;
 mod audio_player_handler;
 mod audio_recorder;
 mod audio_recording;
+mod background_mode;
 mod clock_time;
+mod combined_export;
+mod data_file;
 mod date_time;
+mod duplicates;
+mod exclusions;
+mod export_images;
+mod file_name;
+mod file_removal;
 mod file_type;
+pub mod front_matter;
+mod front_matter_profile;
+mod index;
+mod journal;
+pub mod keyring;
+mod latency_histogram;
+mod line_marks;
+mod link_detection;
+mod load_progress;
+mod markdown_html;
+mod metadata_index;
+mod note_color;
+mod note_data;
+mod note_merge;
 mod note_repository;
+mod note_uri;
+mod notebook_settings_data;
+mod pin_hash;
 mod point;
+pub mod publisher;
+mod recovery;
+mod reminder;
+mod review_period;
+mod revision_cache;
+mod save_batch;
+mod search_query;
+mod site_export;
+mod startup_timeline;
+mod tag_bundle;
+mod tag_set;
+mod tag_suggestions;
+mod text_direction;
+mod title_heading;
+mod trash_size;
+mod vault_import;
+mod version_diff;
+mod wiki_link;
+mod writing_stats;
 
 pub use self::{
+    attachment_references::{
+        attachment_files, markdown_reference, AttachmentFile, AttachmentReferences,
+    },
     audio_player::{AudioPlayer, PlaybackState},
     audio_player_handler::AudioPlayerHandler,
     audio_recorder::AudioRecorder,
     audio_recording::AudioRecording,
+    background_mode::{BackgroundAction, BackgroundMode},
     clock_time::ClockTime,
+    combined_export::{
+        combine_notes, html_document, move_item, sorted_by_date, CombinedFormat, CombinedNote,
+    },
+    data_file::{
+        sorted_deleted_tags, DataFile, DeletedTagData, SavedSearchData, DATA_FILE_NAME,
+        DELETED_TAG_RETENTION_DAYS,
+    },
     date_time::DateTime,
+    duplicates::{duplicate_groups, near_duplicate_groups, NEAR_DUPLICATE_THRESHOLD},
+    exclusions::{Exclusions, DEFAULT_EXCLUSIONS, EXCLUSIONS_FILE_NAME},
+    export_images::{
+        embed_images, fitted_size, image_path, image_sources, images_dir_name, ExportedHtml,
+        ImageEmbedding, MAX_COPIED_IMAGE_SIZE, MAX_PDF_IMAGE_SIZE,
+    },
+    file_name::{safe_file_extension, safe_file_stem, unique_file_name},
+    file_removal::{remove_file, DeletionMode, DesktopTrash, FileRemoval, Trash, TrashFuture},
     file_type::FileType,
-    note_repository::{NoteRepository, SyncState},
+    front_matter_profile::FrontMatterProfile,
+    index::{contains_words, query_words, IndexMatch, SearchIndex, SEARCH_INDEX_FILE_NAME},
+    journal::{diagnostics_report, Journal, JournalEvent},
+    latency_histogram::{format_latency, LatencyHistogram},
+    line_marks::{changed_lines, heading_levels, ChangeMark},
+    link_detection::{find_links, is_code_fence},
+    load_progress::{load_progress, LOAD_BATCH_SIZE},
+    markdown_html::markdown_to_html,
+    metadata_index::{
+        FileStamp, IndexEntry, IndexReconciliation, MetadataIndex, METADATA_INDEX_FILE_NAME,
+    },
+    note_color::NoteColor,
+    note_data::{
+        body_offset, content_at, AttachmentData, ExtraFields, MetadataData, NoteData, NoteSortKey,
+        ParseFallback, DEFAULT_LANGUAGE, PLAIN_TEXT_LANGUAGE,
+    },
+    note_merge::NoteMerge,
+    note_repository::{
+        retry_once_on_auth_failure, AheadBehind, ChangedFile, CloneCancelled, CloneProgress,
+        CredentialKind, DiffHunk, DiskUsage, FileChange, FileRevision, NoteRepository,
+        RemoteStatus, RepoOps, RepositoryError, SyncState,
+    },
+    note_uri::{
+        convert_note_uris, note_id_from_uri, note_uri, note_uri_heading, note_uri_markdown_link,
+        NoteLinkStyle,
+    },
+    notebook_settings_data::{NotebookSettingsData, NOTEBOOK_SETTINGS_VERSION},
+    pin_hash::PinHash,
     point::Point,
+    recovery::{read_recovered_notes, reconcile, RecoveredNote, RecoveryWriter},
+    reminder::{DueReminders, Reminder},
+    review_period::{day_in, day_title, DateRange, ReviewPeriod},
+    revision_cache::RevisionCache,
+    save_batch::{write_batch, NotesNotSaved, MAX_WRITES_IN_FLIGHT},
+    search_query::{QueryError, QueryErrorKind, SearchQuery, SearchTarget},
+    site_export::{build_site, write_site, SiteExportCancelled, SiteNote},
+    startup_timeline::{StartupMilestone, StartupTimeline},
+    tag_bundle::{
+        plan_import, write_bundle, BundleNote, CollisionPolicy, TagBundle, WrittenBundle,
+        BUNDLE_EXTENSION,
+    },
+    tag_set::{is_same_tag_name, normalize_tag_name, TagSet},
+    tag_suggestions::{scanned_part, suggest_tags},
+    text_direction::base_direction,
+    title_heading::{first_heading, heading_line, with_heading},
+    trash_size::{trash_only_attachments, trash_size},
+    vault_import::{convert_vault, read_vault, UnresolvedLink, VaultImportOptions},
+    version_diff::{diff_lines, DiffLine, LineChange},
+    wiki_link::{retarget_wiki_links, wiki_link_targets},
+    writing_stats::{DailyWordCounts, EditWordCount},
 };
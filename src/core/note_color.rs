@@ -0,0 +1,131 @@
+use gettextrs::gettext;
+use gtk::glib;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Color label of a note. It is only visual and never affects the order of the notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "NwtyNoteColor")]
+pub enum NoteColor {
+    None,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Pink,
+    Brown,
+}
+
+impl Default for NoteColor {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl NoteColor {
+    pub const ALL: [Self; 9] = [
+        Self::None,
+        Self::Red,
+        Self::Orange,
+        Self::Yellow,
+        Self::Green,
+        Self::Blue,
+        Self::Purple,
+        Self::Pink,
+        Self::Brown,
+    ];
+
+    pub fn is_none(self) -> bool {
+        self == Self::None
+    }
+
+    /// Name used when stored in the front matter of a note
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Red => "red",
+            Self::Orange => "orange",
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Purple => "purple",
+            Self::Pink => "pink",
+            Self::Brown => "brown",
+        }
+    }
+
+    /// Inverse of [`NoteColor::name`]. Unknown names, which may be from a newer version,
+    /// fall back to [`NoteColor::None`].
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|color| color.name() == name)
+            .unwrap_or_else(|| {
+                log::warn!("Unknown note color `{}`, falling back to none", name);
+                Self::None
+            })
+    }
+
+    pub fn display_name(self) -> String {
+        match self {
+            Self::None => gettext("No Color"),
+            Self::Red => gettext("Red"),
+            Self::Orange => gettext("Orange"),
+            Self::Yellow => gettext("Yellow"),
+            Self::Green => gettext("Green"),
+            Self::Blue => gettext("Blue"),
+            Self::Purple => gettext("Purple"),
+            Self::Pink => gettext("Pink"),
+            Self::Brown => gettext("Brown"),
+        }
+    }
+}
+
+impl Serialize for NoteColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for NoteColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::from_name(&name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn name() {
+        for color in NoteColor::ALL {
+            assert_eq!(NoteColor::from_name(color.name()), color);
+        }
+    }
+
+    #[test]
+    fn unknown_name() {
+        assert_eq!(NoteColor::from_name("teal"), NoteColor::None);
+        assert_eq!(NoteColor::from_name(""), NoteColor::None);
+    }
+
+    #[test]
+    fn serialize() {
+        assert_eq!(
+            serde_yaml::to_string(&NoteColor::Blue).unwrap(),
+            "---\nblue\n"
+        );
+    }
+
+    #[test]
+    fn deserialize() {
+        let color: NoteColor = serde_yaml::from_str("green").unwrap();
+        assert_eq!(color, NoteColor::Green);
+
+        let color: NoteColor = serde_yaml::from_str("magenta").unwrap();
+        assert_eq!(color, NoteColor::None);
+    }
+}
@@ -0,0 +1,1032 @@
+use gray_matter::{engine::YAML, Matter};
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use std::{cmp::Ordering, fmt, mem, path::PathBuf};
+
+use super::{front_matter_profile, DateTime, FrontMatterProfile, NoteColor, Reminder};
+
+const FRONT_MATTER_DELIMITER: &str = "---\n";
+
+/// Language that notes are highlighted as, unless they choose another
+pub const DEFAULT_LANGUAGE: &str = "markdown";
+/// Language of the notes that are not highlighted, as there is no such language to choose
+pub const PLAIN_TEXT_LANGUAGE: &str = "plain";
+
+/// Plain data of an attachment as stored in the front matter of a note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentData {
+    // TODO Use relative path instead of absolute path
+    pub file: PathBuf,
+    pub created: DateTime,
+    pub title: String,
+}
+
+impl Default for AttachmentData {
+    fn default() -> Self {
+        Self {
+            file: std::env::temp_dir(),
+            created: DateTime::default(),
+            title: String::default(),
+        }
+    }
+}
+
+/// Plain data of the metadata of a note as stored in its front matter
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataData {
+    pub title: String,
+    pub tag_list: Vec<String>,
+    pub attachment_list: Vec<AttachmentData>,
+    pub last_modified: DateTime,
+    pub is_pinned: bool,
+    pub is_trashed: bool,
+    #[serde(skip_serializing_if = "is_no_color")]
+    pub color: NoteColor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminder: Option<Reminder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gist_id: Option<String>,
+    /// Id of the language the content is highlighted as, like `yaml`, or
+    /// [`PLAIN_TEXT_LANGUAGE`]. Markdown when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// When the note was created, which is not known for the notes created before it was
+    /// recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime>,
+    #[serde(flatten)]
+    pub extra_fields: ExtraFields,
+}
+
+impl MetadataData {
+    /// Serialize into the front matter of a note file in `profile`, which the content
+    /// directly follows. Dates that were read in a legacy format, like a Unix timestamp, are
+    /// written in the current one.
+    pub fn serialize(&self, profile: FrontMatterProfile) -> anyhow::Result<String> {
+        let mut text = serde_yaml::to_string(&profile.fields(self)?)?;
+        text.push_str(FRONT_MATTER_DELIMITER);
+        Ok(text)
+    }
+}
+
+/// Fields of the front matter that are not known, like ones added by hand or by other apps.
+/// They are kept as is, so saving the note doesn't lose them.
+#[derive(Debug, Default, Clone, PartialEq, glib::Boxed, Serialize, Deserialize)]
+#[boxed_type(name = "NwtyExtraFields")]
+#[serde(transparent)]
+pub struct ExtraFields(pub serde_yaml::Mapping);
+
+// Takes a reference as required by `skip_serializing_if`
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_no_color(color: &NoteColor) -> bool {
+    color.is_none()
+}
+
+/// Plain data of a note, which is the front matter and the content after it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoteData {
+    pub metadata: MetadataData,
+    pub content: String,
+}
+
+impl NoteData {
+    /// Parse the contents of a note file
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let parsed_entity = Matter::<YAML>::new().parse(text);
+        let pod = parsed_entity
+            .data
+            .ok_or_else(|| anyhow::anyhow!("ParsedEntity.data not found in `{}`", text))?;
+
+        let mut fields: Mapping = pod.deserialize()?;
+        front_matter_profile::read_standard_fields(&mut fields);
+
+        Ok(Self {
+            metadata: serde_yaml::from_value(Value::Mapping(fields))?,
+            content: parsed_entity.content,
+        })
+    }
+
+    /// Parse the contents of a note file that may be from another app, which never fails.
+    /// Titles that are numbers or booleans are used as text, and fields of the wrong type are
+    /// reset. A file without front matter, or with one that is never closed or is not a
+    /// mapping, is all content, so its text is kept when it is saved. Each of these is
+    /// returned as a fallback. Like [`NoteData::parse`], the content is trimmed.
+    ///
+    /// When the last modified date is missing or invalid, it is left as now, for the caller to
+    /// set it to the one of the file.
+    pub fn parse_tolerant(text: &str) -> (Self, Vec<ParseFallback>) {
+        let mut fallbacks = Vec::new();
+
+        let fields = match split_front_matter(text) {
+            Ok((front_matter, content)) => match front_matter_fields(front_matter) {
+                Ok(mut fields) => {
+                    front_matter_profile::read_standard_fields(&mut fields);
+                    Some((fields, content))
+                }
+                Err(error) => {
+                    fallbacks.push(ParseFallback::MalformedFrontMatter(error));
+                    None
+                }
+            },
+            Err(fallback) => {
+                fallbacks.push(fallback);
+                None
+            }
+        };
+
+        let (mut fields, content) = match fields {
+            Some(fields) => fields,
+            None => {
+                fallbacks.push(ParseFallback::MissingLastModified);
+                let note_data = Self {
+                    metadata: MetadataData::default(),
+                    content: text.trim().to_string(),
+                };
+                return (note_data, fallbacks);
+            }
+        };
+
+        let title = take_field(&mut fields, "title")
+            .map_or_else(String::new, |value| title_from_value(value, &mut fallbacks));
+        let last_modified = take_field(&mut fields, "last_modified");
+
+        // Find the fields of the wrong type one at a time, so only those are reset
+        if serde_yaml::from_value::<MetadataData>(Value::Mapping(fields.clone())).is_err() {
+            fields = fields
+                .into_iter()
+                .filter(|(key, value)| {
+                    let mut field = Mapping::new();
+                    field.insert(key.clone(), value.clone());
+
+                    match serde_yaml::from_value::<MetadataData>(Value::Mapping(field)) {
+                        Ok(_) => true,
+                        Err(err) => {
+                            fallbacks.push(ParseFallback::InvalidField {
+                                key: value_text(key),
+                                error: err.to_string(),
+                            });
+                            false
+                        }
+                    }
+                })
+                .collect();
+        }
+
+        let mut metadata = serde_yaml::from_value::<MetadataData>(Value::Mapping(fields))
+            .unwrap_or_else(|err| {
+                log::error!(
+                    "Failed to deserialize metadata with valid fields: {:?}",
+                    err
+                );
+                MetadataData::default()
+            });
+        metadata.title = title;
+
+        match last_modified {
+            None | Some(Value::Null) => fallbacks.push(ParseFallback::MissingLastModified),
+            Some(value) => match serde_yaml::from_value(value.clone()) {
+                Ok(last_modified) => metadata.last_modified = last_modified,
+                Err(_) => {
+                    fallbacks.push(ParseFallback::InvalidLastModified(value_text(&value)));
+                }
+            },
+        }
+
+        let note_data = Self {
+            metadata,
+            content: content.trim().to_string(),
+        };
+        (note_data, fallbacks)
+    }
+
+    /// Serialize into the contents of a note file with the front matter in `profile`, which
+    /// can be parsed back with [`NoteData::parse`]
+    pub fn serialize(&self, profile: FrontMatterProfile) -> anyhow::Result<String> {
+        let mut text = self.metadata.serialize(profile)?;
+        text.push_str(&self.content);
+        Ok(text)
+    }
+}
+
+/// Where the content starts in `text`, the contents of a note file that
+/// [`NoteData::parse_tolerant`] parsed with `fallbacks`, so it can be taken again with
+/// [`content_at`] without parsing the front matter
+pub fn body_offset(text: &str, fallbacks: &[ParseFallback]) -> usize {
+    if fallbacks.iter().any(ParseFallback::is_all_content) {
+        return 0;
+    }
+
+    split_front_matter(text).map_or(0, |(_, content)| text.len() - content.len())
+}
+
+/// The content of the note file `text` that starts at `body_offset`, trimmed like
+/// [`NoteData::parse_tolerant`], or `None` if it can't start there
+pub fn content_at(text: &str, body_offset: usize) -> Option<&str> {
+    text.get(body_offset..).map(str::trim)
+}
+
+/// Why a part of a note file is not used as it is, found by [`NoteData::parse_tolerant`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseFallback {
+    /// The file doesn't start with front matter
+    NoFrontMatter,
+    /// The front matter is never closed, so the opening `---` is a horizontal rule
+    UnterminatedFrontMatter,
+    /// The front matter is not a YAML mapping, with the error of parsing it
+    MalformedFrontMatter(String),
+    /// The title is a number or boolean, and is used as this text
+    CoercedTitle(String),
+    /// The title is neither text nor a number or boolean, like a list, so it is empty
+    InvalidTitle(String),
+    /// There is no last modified date
+    MissingLastModified,
+    /// The last modified date is not in RFC 3339, like `2022-01-31T14:30:00+01:00`
+    InvalidLastModified(String),
+    /// A field known to Noteworthy with a value of the wrong type, which is reset
+    InvalidField { key: String, error: String },
+}
+
+impl ParseFallback {
+    /// Whether the whole file is used as the content
+    pub fn is_all_content(&self) -> bool {
+        matches!(
+            self,
+            Self::NoFrontMatter | Self::UnterminatedFrontMatter | Self::MalformedFrontMatter(_)
+        )
+    }
+
+    /// Whether the last modified date has to be taken from elsewhere
+    pub fn is_last_modified(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingLastModified | Self::InvalidLastModified(_)
+        )
+    }
+}
+
+impl fmt::Display for ParseFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoFrontMatter => write!(f, "no front matter, using whole file as content"),
+            Self::UnterminatedFrontMatter => {
+                write!(f, "unterminated front matter, using whole file as content")
+            }
+            Self::MalformedFrontMatter(error) => write!(
+                f,
+                "malformed front matter, using whole file as content: {}",
+                error
+            ),
+            Self::CoercedTitle(title) => write!(f, "title coerced to string `{}`", title),
+            Self::InvalidTitle(value) => write!(f, "invalid title `{}`, using empty title", value),
+            Self::MissingLastModified => write!(f, "missing last_modified, using file mtime"),
+            Self::InvalidLastModified(value) => {
+                write!(f, "invalid last_modified `{}`, using file mtime", value)
+            }
+            Self::InvalidField { key, error } => {
+                write!(f, "invalid field `{}`, using default: {}", key, error)
+            }
+        }
+    }
+}
+
+/// The front matter and the content after it, or why there is no front matter
+fn split_front_matter(text: &str) -> Result<(&str, &str), ParseFallback> {
+    let rest = text
+        .strip_prefix(FRONT_MATTER_DELIMITER)
+        .or_else(|| text.strip_prefix("---\r\n"))
+        .ok_or(ParseFallback::NoFrontMatter)?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Ok((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    Err(ParseFallback::UnterminatedFrontMatter)
+}
+
+/// The fields of `front_matter`, where an empty one has none
+fn front_matter_fields(front_matter: &str) -> Result<Mapping, String> {
+    if front_matter.trim().is_empty() {
+        return Ok(Mapping::new());
+    }
+
+    match serde_yaml::from_str(front_matter).map_err(|err| err.to_string())? {
+        Value::Mapping(fields) => Ok(fields),
+        Value::Null => Ok(Mapping::new()),
+        other => Err(format!(
+            "expected a mapping, found `{}`",
+            value_text(&other)
+        )),
+    }
+}
+
+/// Remove the field `key` from `fields`, keeping the others in their order, which
+/// [`Mapping::remove`] doesn't, so the fields of other apps are saved where they were
+fn take_field(fields: &mut Mapping, key: &str) -> Option<Value> {
+    let key = Value::from(key);
+    let value = fields.get(&key).cloned()?;
+
+    *fields = mem::take(fields)
+        .into_iter()
+        .filter(|(other_key, _)| *other_key != key)
+        .collect();
+    Some(value)
+}
+
+fn title_from_value(value: Value, fallbacks: &mut Vec<ParseFallback>) -> String {
+    let title = match value {
+        Value::String(title) => return title,
+        Value::Number(number) => number.to_string(),
+        Value::Bool(boolean) => boolean.to_string(),
+        other => {
+            fallbacks.push(ParseFallback::InvalidTitle(value_text(&other)));
+            return String::new();
+        }
+    };
+
+    fallbacks.push(ParseFallback::CoercedTitle(title.clone()));
+    title
+}
+
+/// `value` as YAML on one line, to be shown in warnings
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|yaml| {
+                yaml.trim_start_matches("---")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Order of the notes in the sidebar. Pinned notes come first, then the most recently modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteSortKey {
+    pub is_pinned: bool,
+    pub last_modified: DateTime,
+}
+
+impl Ord for NoteSortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .is_pinned
+            .cmp(&self.is_pinned)
+            .then_with(|| other.last_modified.cmp(&self.last_modified))
+    }
+}
+
+impl PartialOrd for NoteSortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use chrono::NaiveDate;
+
+    use std::fmt::Write;
+
+    use crate::test_harness::{fixture, fixtures, snapshot_settings, MetadataBuilder};
+
+    const NOTE_TEXT: &str = "---
+title: A title
+tag_list:
+  - A
+  - B
+attachment_list:
+  - file: /home/user/a.png
+    created: \"2022-01-01T15:30:00+08:00\"
+    title: Picture
+last_modified: \"2022-01-02T10:00:00+08:00\"
+is_pinned: true
+is_trashed: false
+color: purple
+reminder: \"2022-01-03T01:30:00Z\"
+gist_id: aa5a315d61ae9438b18d
+language: yaml
+author: Someone
+rating: 4
+aliases:
+  - Other title
+---
+# Heading
+
+Some content
+";
+
+    #[test]
+    fn parse() {
+        let note_data = NoteData::parse(NOTE_TEXT).unwrap();
+
+        let metadata = &note_data.metadata;
+        assert_eq!(metadata.title, "A title");
+        assert_eq!(metadata.tag_list, vec!["A", "B"]);
+        assert_eq!(metadata.attachment_list.len(), 1);
+        assert_eq!(
+            metadata.attachment_list[0].file,
+            PathBuf::from("/home/user/a.png")
+        );
+        assert_eq!(metadata.attachment_list[0].title, "Picture");
+        assert!(metadata.is_pinned);
+        assert!(!metadata.is_trashed);
+        assert_eq!(metadata.color, NoteColor::Purple);
+        assert_eq!(
+            metadata.reminder,
+            Some(serde_yaml::from_str("\"2022-01-03T01:30:00Z\"").unwrap())
+        );
+        assert_eq!(metadata.gist_id.as_deref(), Some("aa5a315d61ae9438b18d"));
+        assert_eq!(metadata.language.as_deref(), Some("yaml"));
+
+        let extra_fields = &metadata.extra_fields.0;
+        assert_eq!(extra_fields.len(), 3);
+        assert_eq!(extra_fields.get(&"author".into()), Some(&"Someone".into()));
+        assert_eq!(extra_fields.get(&"rating".into()), Some(&4_u64.into()));
+        assert!(extra_fields.get(&"aliases".into()).unwrap().is_sequence());
+
+        assert!(note_data.content.starts_with("# Heading\n\nSome content"));
+    }
+
+    #[test]
+    fn parse_missing_fields() {
+        let note_data = NoteData::parse("---\ntitle: Only title\n---\nContent").unwrap();
+        assert_eq!(note_data.metadata.title, "Only title");
+        assert!(note_data.metadata.tag_list.is_empty());
+        assert!(note_data.metadata.attachment_list.is_empty());
+        assert!(!note_data.metadata.is_pinned);
+        assert_eq!(note_data.metadata.color, NoteColor::None);
+        assert_eq!(note_data.metadata.reminder, None);
+        assert_eq!(note_data.metadata.gist_id, None);
+        assert_eq!(note_data.metadata.language, None);
+        assert!(note_data.metadata.extra_fields.0.is_empty());
+        assert_eq!(note_data.content, "Content");
+    }
+
+    #[test]
+    fn parse_unknown_color() {
+        let note_data = NoteData::parse("---\ntitle: A\ncolor: teal\n---\nContent").unwrap();
+        assert_eq!(note_data.metadata.title, "A");
+        assert_eq!(note_data.metadata.color, NoteColor::None);
+    }
+
+    #[test]
+    fn parse_without_front_matter() {
+        assert!(NoteData::parse("Just content").is_err());
+    }
+
+    /// Text of the fixture `name` in `tests/fixtures/notes/malformed`
+    fn malformed(name: &str) -> String {
+        fixture(&format!("notes/malformed/{}.md", name))
+    }
+
+    fn parse_fixture(name: &str) -> (NoteData, Vec<ParseFallback>) {
+        NoteData::parse_tolerant(&malformed(name))
+    }
+
+    #[test]
+    fn parse_tolerant_valid() {
+        let (note_data, fallbacks) = NoteData::parse_tolerant(NOTE_TEXT);
+        assert!(fallbacks.is_empty());
+        assert_eq!(note_data, NoteData::parse(NOTE_TEXT).unwrap());
+    }
+
+    #[test]
+    fn parse_tolerant_fixtures() {
+        for (name, text) in fixtures("notes/malformed") {
+            let (note_data, fallbacks) = NoteData::parse_tolerant(&text);
+            assert!(!fallbacks.is_empty(), "`{}` has no fallbacks", name);
+
+            // Nothing of the file is lost when it is saved then loaded again
+            let saved = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+            assert_eq!(
+                NoteData::parse(&saved).unwrap(),
+                note_data,
+                "`{}` changed when saved",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn parse_tolerant_coerced_title() {
+        let (note_data, fallbacks) = parse_fixture("title-number");
+        assert_eq!(note_data.metadata.title, "123");
+        assert_eq!(fallbacks, [ParseFallback::CoercedTitle("123".to_string())]);
+        assert_eq!(note_data.content, "Content");
+
+        let (note_data, fallbacks) = parse_fixture("title-boolean");
+        assert_eq!(note_data.metadata.title, "true");
+        assert_eq!(fallbacks, [ParseFallback::CoercedTitle("true".to_string())]);
+
+        let (note_data, fallbacks) = parse_fixture("title-list");
+        assert_eq!(note_data.metadata.title, "");
+        assert_eq!(
+            fallbacks,
+            [ParseFallback::InvalidTitle("- First - Second".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_tolerant_last_modified() {
+        let (note_data, fallbacks) = parse_fixture("modified-null");
+        assert_eq!(note_data.metadata.title, "Null date");
+        assert_eq!(fallbacks, [ParseFallback::MissingLastModified]);
+
+        let (note_data, fallbacks) = parse_fixture("modified-invalid");
+        assert_eq!(note_data.metadata.title, "Invalid date");
+        assert_eq!(
+            fallbacks,
+            [ParseFallback::InvalidLastModified(
+                "last tuesday".to_string()
+            )]
+        );
+        assert!(fallbacks[0].is_last_modified());
+
+        // Fields of other apps are kept as they are
+        let (note_data, fallbacks) = parse_fixture("modified-missing");
+        assert_eq!(fallbacks, [ParseFallback::MissingLastModified]);
+        let extra_fields = &note_data.metadata.extra_fields.0;
+        assert_eq!(
+            extra_fields.get(&"modified".into()),
+            Some(&"early 2022".into())
+        );
+        assert_eq!(extra_fields.get(&"draft".into()), Some(&true.into()));
+    }
+
+    #[test]
+    fn parse_tolerant_invalid_fields() {
+        let (note_data, fallbacks) = parse_fixture("invalid-fields");
+
+        let metadata = &note_data.metadata;
+        assert_eq!(metadata.title, "Wrong types");
+        assert!(metadata.tag_list.is_empty());
+        assert!(!metadata.is_pinned);
+        assert_eq!(
+            metadata.last_modified,
+            serde_yaml::from_str("\"2022-01-02T10:00:00+08:00\"").unwrap()
+        );
+        assert_eq!(
+            metadata.extra_fields.0.get(&"author".into()),
+            Some(&"Someone".into())
+        );
+
+        let keys = fallbacks
+            .iter()
+            .map(|fallback| match fallback {
+                ParseFallback::InvalidField { key, .. } => key.as_str(),
+                other => panic!("Unexpected fallback {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["tag_list", "is_pinned"]);
+    }
+
+    #[test]
+    fn parse_tolerant_whole_file_as_content() {
+        for name in [
+            "unterminated",
+            "invalid-yaml",
+            "not-mapping",
+            "no-front-matter",
+        ] {
+            let text = malformed(name);
+            let (note_data, fallbacks) = NoteData::parse_tolerant(&text);
+
+            assert_eq!(note_data.content, text.trim(), "`{}` lost content", name);
+            assert_eq!(note_data.metadata.title, "");
+            assert_eq!(fallbacks.len(), 2, "`{}`: {:?}", name, fallbacks);
+            assert!(fallbacks[1].is_last_modified());
+        }
+
+        let (_, fallbacks) = parse_fixture("unterminated");
+        assert_eq!(fallbacks[0], ParseFallback::UnterminatedFrontMatter);
+        let (_, fallbacks) = parse_fixture("no-front-matter");
+        assert_eq!(fallbacks[0], ParseFallback::NoFrontMatter);
+        let (_, fallbacks) = parse_fixture("not-mapping");
+        assert!(matches!(
+            fallbacks[0],
+            ParseFallback::MalformedFrontMatter(_)
+        ));
+    }
+
+    #[test]
+    fn parse_tolerant_empty_front_matter() {
+        let (note_data, fallbacks) = parse_fixture("empty-front-matter");
+        assert_eq!(note_data.metadata.title, "");
+        assert_eq!(note_data.content, "Content");
+        assert_eq!(fallbacks, [ParseFallback::MissingLastModified]);
+    }
+
+    #[test]
+    fn content_at_body_offset() {
+        let (note_data, fallbacks) = NoteData::parse_tolerant(NOTE_TEXT);
+        let offset = body_offset(NOTE_TEXT, &fallbacks);
+        assert!(NOTE_TEXT[..offset].ends_with("---\n"));
+        assert_eq!(
+            content_at(NOTE_TEXT, offset),
+            Some(note_data.content.as_str())
+        );
+
+        for name in [
+            "no-front-matter",
+            "unterminated",
+            "invalid-yaml",
+            "title-list",
+        ] {
+            let text = malformed(name);
+            let (note_data, fallbacks) = NoteData::parse_tolerant(&text);
+            let offset = body_offset(&text, &fallbacks);
+            assert_eq!(content_at(&text, offset), Some(note_data.content.as_str()));
+        }
+
+        // Saved by Noteworthy, so the content directly follows the front matter
+        let text = NoteData::parse(NOTE_TEXT)
+            .unwrap()
+            .serialize(FrontMatterProfile::Noteworthy)
+            .unwrap();
+        let front_matter = note_data
+            .metadata
+            .serialize(FrontMatterProfile::Noteworthy)
+            .unwrap();
+        assert_eq!(body_offset(&text, &[]), front_matter.len());
+
+        assert_eq!(content_at("Short", 10), None);
+        assert_eq!(content_at("Émoji", 1), None);
+    }
+
+    #[test]
+    fn serialize() {
+        let note_data = NoteData {
+            metadata: MetadataData {
+                title: "Title".to_string(),
+                tag_list: vec!["A".to_string()],
+                ..MetadataData::default()
+            },
+            content: "Content".to_string(),
+        };
+
+        let text = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+        assert!(text.starts_with("---\ntitle: Title\ntag_list:\n  - A\n"));
+        assert!(text.ends_with("\n---\nContent"));
+        assert!(!text.contains("color"));
+        assert!(!text.contains("reminder"));
+        assert!(!text.contains("gist_id"));
+        assert!(!text.contains("language"));
+    }
+
+    #[test]
+    fn serialize_extra_fields() {
+        let mut extra_fields = serde_yaml::Mapping::new();
+        extra_fields.insert("author".into(), "Someone".into());
+        extra_fields.insert("draft".into(), true.into());
+
+        let note_data = NoteData {
+            metadata: MetadataData {
+                title: "Title".to_string(),
+                extra_fields: ExtraFields(extra_fields),
+                ..MetadataData::default()
+            },
+            content: String::new(),
+        };
+
+        let text = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+        assert!(text.contains("\nauthor: Someone\ndraft: true\n"));
+        assert!(!text.contains("extra_fields"));
+        assert_eq!(NoteData::parse(&text).unwrap(), note_data);
+    }
+
+    #[test]
+    fn serialize_color() {
+        let note_data = NoteData {
+            metadata: MetadataData {
+                color: NoteColor::Orange,
+                ..MetadataData::default()
+            },
+            content: String::new(),
+        };
+
+        let text = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+        assert!(text.contains("\ncolor: orange\n"));
+    }
+
+    #[test]
+    fn serialize_then_parse() {
+        let note_data = NoteData::parse(NOTE_TEXT).unwrap();
+        let text = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+        assert_eq!(NoteData::parse(&text).unwrap(), note_data);
+    }
+
+    #[test]
+    fn serialize_standard_markdown() {
+        let note_data = NoteData::parse(NOTE_TEXT).unwrap();
+        let text = note_data
+            .serialize(FrontMatterProfile::StandardMarkdown)
+            .unwrap();
+
+        assert!(text.starts_with("---\ntitle: A title\nmodified: "));
+        assert!(text.contains("\ntags:\n  - A\n  - B\npinned: true\n"));
+        assert!(!text.contains("trashed"));
+        assert!(!text.contains("last_modified"));
+        assert!(!text.contains("tag_list"));
+        assert!(text.contains("\ncolor: purple\n"));
+        assert!(text.contains("\nauthor: Someone\n"));
+        assert!(text.ends_with("\n---\n# Heading\n\nSome content"));
+    }
+
+    #[test]
+    fn serialize_then_parse_profiles() {
+        let mut note_data = NoteData::parse(NOTE_TEXT).unwrap();
+        note_data.metadata.created =
+            Some(serde_yaml::from_str("\"2021-12-31T08:00:00+08:00\"").unwrap());
+
+        for profile in FrontMatterProfile::ALL {
+            let text = note_data.serialize(profile).unwrap();
+            assert_eq!(NoteData::parse(&text).unwrap(), note_data, "{:?}", profile);
+
+            let (parsed, fallbacks) = NoteData::parse_tolerant(&text);
+            assert!(fallbacks.is_empty(), "{:?}: {:?}", profile, fallbacks);
+            assert_eq!(parsed, note_data, "{:?}", profile);
+        }
+
+        // Converted from one profile to the other and back without changes
+        let standard = note_data
+            .serialize(FrontMatterProfile::StandardMarkdown)
+            .unwrap();
+        let noteworthy = NoteData::parse(&standard)
+            .unwrap()
+            .serialize(FrontMatterProfile::Noteworthy)
+            .unwrap();
+        assert_eq!(
+            noteworthy,
+            note_data.serialize(FrontMatterProfile::Noteworthy).unwrap()
+        );
+        assert_eq!(
+            NoteData::parse(&noteworthy)
+                .unwrap()
+                .serialize(FrontMatterProfile::StandardMarkdown)
+                .unwrap(),
+            standard
+        );
+    }
+
+    #[test]
+    fn parse_standard_markdown_from_other_app() {
+        let text = "---\ntitle: Other app\ncreated: 2022-01-01T10:00:00+08:00\nmodified: 2022-01-02T10:00:00+08:00\ntags: [Work]\ntrashed: true\ndraft: true\n---\nContent";
+
+        let (note_data, fallbacks) = NoteData::parse_tolerant(text);
+        assert!(fallbacks.is_empty());
+        assert_eq!(note_data, NoteData::parse(text).unwrap());
+
+        let metadata = &note_data.metadata;
+        assert_eq!(
+            metadata.created,
+            Some(serde_yaml::from_str("\"2022-01-01T10:00:00+08:00\"").unwrap())
+        );
+        assert_eq!(
+            metadata.last_modified,
+            serde_yaml::from_str("\"2022-01-02T10:00:00+08:00\"").unwrap()
+        );
+        assert_eq!(metadata.tag_list, ["Work"]);
+        assert!(!metadata.is_pinned);
+        assert!(metadata.is_trashed);
+        assert_eq!(metadata.extra_fields.0.len(), 1);
+        assert_eq!(
+            metadata.extra_fields.0.get(&"draft".into()),
+            Some(&true.into())
+        );
+    }
+
+    #[test]
+    fn legacy_dates_rewritten() {
+        let text = "---\ntitle: Old\nlast_modified: 1641088800\nattachment_list:\n  - file: /home/user/a.png\n    created: 2022-01-01\n---\nContent";
+        let note_data = NoteData::parse(text).unwrap();
+        assert_eq!(
+            note_data.metadata.last_modified,
+            DateTime::parse("2022-01-02T10:00:00+08:00").unwrap()
+        );
+
+        let saved = note_data.serialize(FrontMatterProfile::Noteworthy).unwrap();
+        assert!(saved.contains("last_modified: \"2022-01-02T02:00:00+00:00\"\n"));
+        assert!(!saved.contains("1641088800"));
+        assert!(!saved.contains("created: 2022-01-01\n"));
+        assert_eq!(NoteData::parse(&saved).unwrap(), note_data);
+    }
+
+    #[test]
+    fn sort_key() {
+        let older: DateTime = serde_yaml::from_str("\"2022-01-01T10:00:00+08:00\"").unwrap();
+        let newer: DateTime = serde_yaml::from_str("\"2022-01-02T10:00:00+08:00\"").unwrap();
+
+        let pinned_older = NoteSortKey {
+            is_pinned: true,
+            last_modified: older,
+        };
+        let unpinned_newer = NoteSortKey {
+            is_pinned: false,
+            last_modified: newer,
+        };
+        let unpinned_older = NoteSortKey {
+            is_pinned: false,
+            last_modified: older,
+        };
+
+        let mut keys = vec![unpinned_older, unpinned_newer, pinned_older];
+        keys.sort();
+        assert_eq!(keys, vec![pinned_older, unpinned_newer, unpinned_older]);
+    }
+
+    /// Fixtures with dates without a time, which are read in the local timezone, so they are
+    /// written differently depending on where the tests run
+    const LOCAL_TIME_FIXTURES: &[&str] = &["date-only"];
+
+    /// Every fixture of notes parsed like when loading them, with the last modified date set
+    /// like the one of the file when it is missing
+    fn parsed_fixtures() -> Vec<(String, NoteData)> {
+        let file_modified = DateTime::parse("2022-02-01T00:00:00Z").unwrap();
+
+        ["current", "foreign", "legacy", "malformed"]
+            .into_iter()
+            .flat_map(|dir| fixtures(&format!("notes/{}", dir)))
+            .map(|(name, text)| {
+                let (mut note_data, fallbacks) = NoteData::parse_tolerant(&text);
+                if fallbacks.iter().any(ParseFallback::is_last_modified) {
+                    note_data.metadata.last_modified = file_modified;
+                }
+                (name, note_data)
+            })
+            .collect()
+    }
+
+    fn extra_keys(metadata: &MetadataData) -> Vec<&str> {
+        metadata
+            .extra_fields
+            .0
+            .iter()
+            .filter_map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn serialize_permutations() {
+        for profile in FrontMatterProfile::ALL {
+            let mut snapshot = String::new();
+
+            for (name, metadata) in MetadataBuilder::permutations() {
+                let note_data = NoteData {
+                    metadata,
+                    content: String::new(),
+                };
+                let text = note_data.serialize(profile).unwrap();
+                assert_eq!(
+                    NoteData::parse(&text).unwrap(),
+                    note_data,
+                    "`{}` changed when written in {:?}",
+                    name,
+                    profile
+                );
+
+                writeln!(snapshot, "=== {} ===\n{}", name, text).unwrap();
+            }
+
+            snapshot_settings()
+                .bind(|| insta::assert_snapshot!(format!("metadata-{}", profile.name()), snapshot));
+        }
+    }
+
+    #[test]
+    fn fixtures_written_in_profiles() {
+        for profile in FrontMatterProfile::ALL {
+            let mut snapshot = String::new();
+
+            for (name, note_data) in parsed_fixtures() {
+                let text = note_data.serialize(profile).unwrap();
+
+                // In the standard profile, fields of other apps with its keys are replaced
+                if profile == FrontMatterProfile::Noteworthy {
+                    assert_eq!(
+                        NoteData::parse(&text).unwrap(),
+                        note_data,
+                        "`{}` changed when saved",
+                        name
+                    );
+                }
+
+                // Loading and saving again doesn't change the file anymore
+                let (loaded, _) = NoteData::parse_tolerant(&text);
+                assert_eq!(
+                    loaded.serialize(profile).unwrap(),
+                    text,
+                    "`{}` changed when saved again in {:?}",
+                    name,
+                    profile
+                );
+
+                if !LOCAL_TIME_FIXTURES.contains(&name.as_str()) {
+                    writeln!(snapshot, "=== {} ===\n{}\n", name, text).unwrap();
+                }
+            }
+
+            snapshot_settings()
+                .bind(|| insta::assert_snapshot!(format!("notes-{}", profile.name()), snapshot));
+        }
+    }
+
+    #[test]
+    fn current_fixtures_written_as_is() {
+        for profile in FrontMatterProfile::ALL {
+            let name = profile.name().replace('_', "-");
+            let text = fixture(&format!("notes/current/{}.md", name));
+
+            let (note_data, fallbacks) = NoteData::parse_tolerant(&text);
+            assert!(fallbacks.is_empty(), "`{}`: {:?}", name, fallbacks);
+
+            // The line break at the end of the file is trimmed from the content
+            assert_eq!(note_data.serialize(profile).unwrap(), text.trim_end());
+        }
+    }
+
+    #[test]
+    fn legacy_fixtures() {
+        for (name, text) in fixtures("notes/legacy") {
+            let (note_data, fallbacks) = NoteData::parse_tolerant(&text);
+            assert!(fallbacks.is_empty(), "`{}`: {:?}", name, fallbacks);
+            assert_eq!(note_data, NoteData::parse(&text).unwrap(), "`{}`", name);
+        }
+
+        let parse = |name| NoteData::parse(&fixture(&format!("notes/legacy/{}.md", name))).unwrap();
+        let expected = DateTime::parse("2022-01-02T02:00:00Z").unwrap();
+
+        let metadata = parse("unix-timestamp").metadata;
+        assert_eq!(metadata.last_modified, expected);
+        assert_eq!(
+            metadata.attachment_list[0].created,
+            DateTime::from_timestamp(1_641_000_000)
+        );
+
+        let metadata = parse("local-offset").metadata;
+        assert_eq!(metadata.last_modified, expected);
+        assert_eq!(
+            metadata.attachment_list[0].created,
+            DateTime::parse("2022-01-01T07:30:00Z").unwrap()
+        );
+
+        // The start of the day where it is read
+        let metadata = parse("date-only").metadata;
+        assert_eq!(
+            metadata.last_modified.to_local().naive_local(),
+            NaiveDate::from_ymd(2022, 1, 31).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn foreign_fixtures() {
+        let parse =
+            |name| NoteData::parse_tolerant(&fixture(&format!("notes/foreign/{}.md", name)));
+
+        // Tags and aliases, without a title or dates
+        let (note_data, fallbacks) = parse("obsidian");
+        assert_eq!(fallbacks, [ParseFallback::MissingLastModified]);
+        assert_eq!(note_data.metadata.title, "");
+        assert_eq!(note_data.metadata.tag_list, ["meeting", "work"]);
+        assert_eq!(extra_keys(&note_data.metadata), ["aliases", "cssclass"]);
+        assert!(note_data.content.starts_with("# Standup"));
+
+        // The date of a post is not when it was last modified
+        let (note_data, fallbacks) = parse("jekyll");
+        assert_eq!(fallbacks, [ParseFallback::MissingLastModified]);
+        assert_eq!(note_data.metadata.title, "Welcome to Jekyll!");
+        assert_eq!(
+            extra_keys(&note_data.metadata),
+            ["layout", "date", "categories"]
+        );
+        assert_eq!(
+            note_data.metadata.extra_fields.0.get(&"date".into()),
+            Some(&"2022-01-02 10:00:00 +0800".into())
+        );
+
+        // Only `modified` is read, as other apps also have it
+        let (note_data, fallbacks) = parse("hugo");
+        assert!(fallbacks.is_empty(), "{:?}", fallbacks);
+        assert_eq!(
+            note_data.metadata.last_modified,
+            DateTime::parse("2022-01-02T10:00:00+08:00").unwrap()
+        );
+        assert_eq!(note_data.metadata.tag_list, ["hugo", "draft"]);
+        assert_eq!(
+            extra_keys(&note_data.metadata),
+            ["date", "lastmod", "draft", "weight"]
+        );
+    }
+}
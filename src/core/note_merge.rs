@@ -0,0 +1,153 @@
+use super::NoteData;
+
+const HEADING_PREFIX: &str = "## ";
+
+/// What merging a source note into a target note changes on the target
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteMerge {
+    /// Appended to the end of the content of the target. This is the content of the source
+    /// under a heading with its title, or without a heading if it has no title.
+    pub appended_content: String,
+    /// The tags of the target followed by the tags that only the source has
+    pub tag_names: Vec<String>,
+}
+
+impl NoteMerge {
+    pub fn new(target: &NoteData, source: &NoteData) -> Self {
+        Self {
+            appended_content: appended_content(
+                &target.content,
+                &source.metadata.title,
+                &source.content,
+            ),
+            tag_names: union(&target.metadata.tag_list, &source.metadata.tag_list),
+        }
+    }
+}
+
+fn appended_content(target_content: &str, source_title: &str, source_content: &str) -> String {
+    let title = source_title.trim();
+    let content = source_content.trim_start_matches('\n').trim_end();
+
+    let mut appended = String::new();
+
+    if !title.is_empty() {
+        appended.push_str(HEADING_PREFIX);
+        appended.push_str(title);
+        appended.push('\n');
+    }
+
+    if !content.is_empty() {
+        if !appended.is_empty() {
+            appended.push('\n');
+        }
+        appended.push_str(content);
+        appended.push('\n');
+    }
+
+    if appended.is_empty() || target_content.trim().is_empty() {
+        return appended;
+    }
+
+    // Separate from the target content with a blank line
+    let n_trailing_newlines = target_content.len() - target_content.trim_end_matches('\n').len();
+    let separator = "\n".repeat(2_usize.saturating_sub(n_trailing_newlines));
+
+    separator + &appended
+}
+
+fn union(names: &[String], other_names: &[String]) -> Vec<String> {
+    let mut union = names.to_vec();
+
+    for name in other_names {
+        if !union.contains(name) {
+            union.push(name.clone());
+        }
+    }
+
+    union
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::MetadataData;
+
+    fn note_data(title: &str, tag_list: &[&str], content: &str) -> NoteData {
+        NoteData {
+            metadata: MetadataData {
+                title: title.to_string(),
+                tag_list: tag_list.iter().map(|name| name.to_string()).collect(),
+                ..Default::default()
+            },
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn appended_under_heading() {
+        let merge = NoteMerge::new(
+            &note_data("Target", &[], "Target content"),
+            &note_data("Source", &[], "Source content\n"),
+        );
+        assert_eq!(merge.appended_content, "\n\n## Source\n\nSource content\n");
+    }
+
+    #[test]
+    fn appended_after_trailing_newlines() {
+        let source = note_data("Source", &[], "Source content");
+
+        let merge = NoteMerge::new(&note_data("Target", &[], "Target content\n"), &source);
+        assert_eq!(merge.appended_content, "\n## Source\n\nSource content\n");
+
+        let merge = NoteMerge::new(&note_data("Target", &[], "Target content\n\n\n"), &source);
+        assert_eq!(merge.appended_content, "## Source\n\nSource content\n");
+    }
+
+    #[test]
+    fn appended_to_empty_target() {
+        let merge = NoteMerge::new(
+            &note_data("Target", &[], "\n  \n"),
+            &note_data("Source", &[], "\n\nSource content\n\n"),
+        );
+        assert_eq!(merge.appended_content, "## Source\n\nSource content\n");
+    }
+
+    #[test]
+    fn appended_empty_source() {
+        let target = note_data("Target", &[], "Target content");
+
+        let merge = NoteMerge::new(&target, &note_data("Source", &[], "\n \n"));
+        assert_eq!(merge.appended_content, "\n\n## Source\n");
+
+        let merge = NoteMerge::new(&target, &note_data("", &[], "Source content"));
+        assert_eq!(merge.appended_content, "\n\nSource content\n");
+
+        let merge = NoteMerge::new(&target, &note_data(" ", &[], ""));
+        assert_eq!(merge.appended_content, "");
+    }
+
+    #[test]
+    fn tags_union() {
+        let merge = NoteMerge::new(
+            &note_data("Target", &["Work", "Ideas"], ""),
+            &note_data("Source", &["Ideas", "Home", "Work"], ""),
+        );
+        assert_eq!(merge.tag_names, ["Work", "Ideas", "Home"]);
+    }
+
+    #[test]
+    fn tags_union_empty() {
+        let merge = NoteMerge::new(
+            &note_data("Target", &[], ""),
+            &note_data("Source", &["Home"], ""),
+        );
+        assert_eq!(merge.tag_names, ["Home"]);
+
+        let merge = NoteMerge::new(
+            &note_data("Target", &["Work"], ""),
+            &note_data("Source", &[], ""),
+        );
+        assert_eq!(merge.tag_names, ["Work"]);
+    }
+}
@@ -0,0 +1,9 @@
+/// How many commits the local branch has that the branch of the remote doesn't, and the other
+/// way around
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AheadBehind {
+    /// Commits that are not pushed yet
+    pub n_ahead: usize,
+    /// Commits that are not pulled yet
+    pub n_behind: usize,
+}
@@ -0,0 +1,199 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use super::RepositoryError;
+
+/// Time to wait before syncing automatically after the first rejection of the credentials,
+/// doubled on each one after it
+const FIRST_AUTH_BACKOFF: Duration = Duration::from_secs(60);
+/// Rejections in a row after which the notes are no longer synced automatically, until a new
+/// credential is entered or the user syncs
+const MAX_AUTH_FAILURES: u32 = 4;
+
+/// The rejections of the credentials by the remote since it last accepted them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AuthFailures {
+    n_failures: u32,
+    last_failure: Option<Instant>,
+}
+
+impl AuthFailures {
+    pub fn record_failure(&mut self, now: Instant) {
+        self.n_failures = self.n_failures.saturating_add(1);
+        self.last_failure = Some(now);
+    }
+
+    pub fn len(&self) -> u32 {
+        self.n_failures
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_failures == 0
+    }
+
+    /// Whether the notes may be synced automatically at `now`, which they are not for a while
+    /// after each rejection, and no longer at all after too many of them
+    pub fn allows_auto_sync(&self, now: Instant) -> bool {
+        let last_failure = match self.last_failure {
+            Some(last_failure) => last_failure,
+            None => return true,
+        };
+
+        if self.n_failures >= MAX_AUTH_FAILURES {
+            return false;
+        }
+
+        now.saturating_duration_since(last_failure) >= self.backoff()
+    }
+
+    fn backoff(&self) -> Duration {
+        FIRST_AUTH_BACKOFF * 2_u32.pow(self.n_failures.saturating_sub(1))
+    }
+}
+
+/// Run `operation`, then once more if it was rejected by the remote and `recover` succeeds,
+/// such as when the user entered a new credential
+pub async fn retry_once_on_auth_failure<T, F, R>(
+    operation: impl Fn() -> F,
+    recover: impl FnOnce(&anyhow::Error) -> R,
+) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+    R: Future<Output = bool>,
+{
+    match operation().await {
+        Err(err) if RepositoryError::is_authentication(&err) => {
+            log::warn!("Remote rejected the credentials: {:?}", err);
+
+            if !recover(&err).await {
+                return Err(err);
+            }
+
+            log::info!("Retrying with the new credential...");
+            operation().await
+        }
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use gtk::glib;
+
+    use std::{cell::RefCell, collections::VecDeque};
+
+    fn auth_error() -> anyhow::Error {
+        RepositoryError::Authentication("denied".into()).into()
+    }
+
+    /// Gives the results in order, as a remote that answers each sync differently
+    struct ScriptedRemote {
+        results: RefCell<VecDeque<anyhow::Result<u32>>>,
+        n_calls: RefCell<u32>,
+    }
+
+    impl ScriptedRemote {
+        fn new(results: impl IntoIterator<Item = anyhow::Result<u32>>) -> Self {
+            Self {
+                results: RefCell::new(results.into_iter().collect()),
+                n_calls: RefCell::new(0),
+            }
+        }
+
+        async fn sync(&self) -> anyhow::Result<u32> {
+            *self.n_calls.borrow_mut() += 1;
+            self.results.borrow_mut().pop_front().unwrap()
+        }
+    }
+
+    #[test]
+    fn retry_once_after_recovering() {
+        let remote = ScriptedRemote::new([Err(auth_error()), Ok(1)]);
+        let n_recovered = RefCell::new(0);
+
+        let res = glib::MainContext::default().block_on(retry_once_on_auth_failure(
+            || remote.sync(),
+            |err| {
+                assert!(RepositoryError::is_authentication(err));
+                *n_recovered.borrow_mut() += 1;
+                async { true }
+            },
+        ));
+
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(*remote.n_calls.borrow(), 2);
+        assert_eq!(*n_recovered.borrow(), 1);
+    }
+
+    #[test]
+    fn retry_only_once() {
+        let remote = ScriptedRemote::new([Err(auth_error()), Err(auth_error()), Ok(1)]);
+
+        let res = glib::MainContext::default().block_on(retry_once_on_auth_failure(
+            || remote.sync(),
+            |_| async { true },
+        ));
+
+        assert!(RepositoryError::is_authentication(&res.unwrap_err()));
+        assert_eq!(*remote.n_calls.borrow(), 2);
+    }
+
+    #[test]
+    fn no_retry_without_recovering() {
+        let remote = ScriptedRemote::new([Err(auth_error()), Ok(1)]);
+
+        let res = glib::MainContext::default().block_on(retry_once_on_auth_failure(
+            || remote.sync(),
+            |_| async { false },
+        ));
+
+        assert!(res.is_err());
+        assert_eq!(*remote.n_calls.borrow(), 1);
+    }
+
+    #[test]
+    fn no_retry_on_other_errors() {
+        let remote = ScriptedRemote::new([Err(anyhow::anyhow!("offline")), Ok(1)]);
+
+        let res = glib::MainContext::default().block_on(retry_once_on_auth_failure(
+            || remote.sync(),
+            |_| async { unreachable!() },
+        ));
+
+        assert!(res.is_err());
+        assert_eq!(*remote.n_calls.borrow(), 1);
+    }
+
+    #[test]
+    fn auth_backoff() {
+        let start = Instant::now();
+        let mut failures = AuthFailures::default();
+        assert!(failures.is_empty());
+        assert!(failures.allows_auto_sync(start));
+
+        failures.record_failure(start);
+        assert_eq!(failures.len(), 1);
+        assert!(!failures.allows_auto_sync(start + Duration::from_secs(59)));
+        assert!(failures.allows_auto_sync(start + Duration::from_secs(60)));
+
+        let now = start + Duration::from_secs(60);
+        failures.record_failure(now);
+        assert!(!failures.allows_auto_sync(now + Duration::from_secs(119)));
+        assert!(failures.allows_auto_sync(now + Duration::from_secs(120)));
+
+        failures.record_failure(now);
+        assert!(!failures.allows_auto_sync(now + Duration::from_secs(239)));
+        assert!(failures.allows_auto_sync(now + Duration::from_secs(240)));
+
+        // Stopped until reset
+        failures.record_failure(now);
+        assert!(!failures.allows_auto_sync(now + Duration::from_secs(60 * 60 * 24)));
+
+        failures = AuthFailures::default();
+        assert!(failures.allows_auto_sync(now));
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+/// How a file differs from the last commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    /// Including untracked files
+    New,
+    Modified,
+    Deleted,
+}
+
+/// A file that is not committed yet, whether its changes are staged or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// Relative to the repository
+    pub path: PathBuf,
+    pub change: FileChange,
+}
+
+/// Add the files changed by a merge, `merged_files`, to the ones changed by the merges before
+/// it, so each file is listed once with how it changed since before the first merge
+pub fn merge_changed_files(
+    changed_files: &mut Vec<(PathBuf, git2::Delta)>,
+    merged_files: Vec<(PathBuf, git2::Delta)>,
+) {
+    for (path, delta) in merged_files {
+        let index = match changed_files.iter().position(|(other, _)| *other == path) {
+            Some(index) => index,
+            None => {
+                changed_files.push((path, delta));
+                continue;
+            }
+        };
+
+        match (changed_files[index].1, delta) {
+            // It didn't exist before the first merge, so it is as if it never changed
+            (git2::Delta::Added, git2::Delta::Deleted) => {
+                changed_files.remove(index);
+            }
+            (git2::Delta::Added, _) => {}
+            (git2::Delta::Deleted, git2::Delta::Added) => {
+                changed_files[index].1 = git2::Delta::Modified;
+            }
+            (_, delta) => changed_files[index].1 = delta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_changed_files_combined() {
+        let mut changed_files = vec![
+            (PathBuf::from("added.md"), git2::Delta::Added),
+            (PathBuf::from("added-then-deleted.md"), git2::Delta::Added),
+            (PathBuf::from("deleted.md"), git2::Delta::Deleted),
+            (PathBuf::from("modified.md"), git2::Delta::Modified),
+        ];
+
+        merge_changed_files(
+            &mut changed_files,
+            vec![
+                (PathBuf::from("added.md"), git2::Delta::Modified),
+                (PathBuf::from("added-then-deleted.md"), git2::Delta::Deleted),
+                (PathBuf::from("deleted.md"), git2::Delta::Added),
+                (PathBuf::from("modified.md"), git2::Delta::Deleted),
+                (PathBuf::from("new.md"), git2::Delta::Added),
+            ],
+        );
+
+        assert_eq!(
+            changed_files,
+            [
+                (PathBuf::from("added.md"), git2::Delta::Added),
+                (PathBuf::from("deleted.md"), git2::Delta::Modified),
+                (PathBuf::from("modified.md"), git2::Delta::Deleted),
+                (PathBuf::from("new.md"), git2::Delta::Added),
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use gettextrs::{gettext, ngettext};
+
+/// Units of the sizes shown, each 1000 times the one before, like GLib does
+const SIZE_UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+
+/// Cloning a repository was stopped before it was done
+#[derive(Debug, thiserror::Error)]
+#[error("Cloning was cancelled")]
+pub struct CloneCancelled;
+
+/// How far cloning a repository is, reported as each object is received and each file is
+/// checked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneProgress {
+    Receiving {
+        received_objects: usize,
+        total_objects: usize,
+        received_bytes: usize,
+    },
+    Resolving {
+        indexed_deltas: usize,
+        total_deltas: usize,
+    },
+    CheckingOut {
+        completed_files: usize,
+        total_files: usize,
+    },
+}
+
+impl CloneProgress {
+    /// The progress of a transfer, which resolves the deltas once every object is received
+    pub fn of_transfer(
+        received_objects: usize,
+        total_objects: usize,
+        indexed_deltas: usize,
+        total_deltas: usize,
+        received_bytes: usize,
+    ) -> Self {
+        if total_objects > 0 && received_objects == total_objects && total_deltas > 0 {
+            Self::Resolving {
+                indexed_deltas,
+                total_deltas,
+            }
+        } else {
+            Self::Receiving {
+                received_objects,
+                total_objects,
+                received_bytes,
+            }
+        }
+    }
+
+    /// How much of the current step is done, from 0 to 1, or `None` while it is not known
+    /// how much there is to do
+    pub fn fraction(&self) -> Option<f64> {
+        let (done, total) = self.counts();
+
+        if total == 0 {
+            return None;
+        }
+
+        Some((done as f64 / total as f64).clamp(0.0, 1.0))
+    }
+
+    /// Like "Receiving objects: 52% (520/1000), 1.2 MB"
+    pub fn message(&self) -> String {
+        let (done, total) = self.counts();
+        let percent = percent(done, total);
+
+        match *self {
+            Self::Receiving { received_bytes, .. } => {
+                gettext!(
+                    "Receiving objects: {}% ({}/{}), {}",
+                    percent,
+                    done,
+                    total,
+                    format_bytes(received_bytes as u64)
+                )
+            }
+            Self::Resolving { .. } => {
+                gettext!("Resolving deltas: {}% ({}/{})", percent, done, total)
+            }
+            Self::CheckingOut { .. } => {
+                gettext!("Checking out files: {}% ({}/{})", percent, done, total)
+            }
+        }
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        match *self {
+            Self::Receiving {
+                received_objects,
+                total_objects,
+                ..
+            } => (received_objects, total_objects),
+            Self::Resolving {
+                indexed_deltas,
+                total_deltas,
+            } => (indexed_deltas, total_deltas),
+            Self::CheckingOut {
+                completed_files,
+                total_files,
+            } => (completed_files, total_files),
+        }
+    }
+}
+
+/// `done` out of `total` in whole percent, rounded down so it is only 100 once all is done
+pub fn percent(done: usize, total: usize) -> u32 {
+    if total == 0 {
+        return 0;
+    }
+
+    (done.min(total) as u128 * 100 / total as u128) as u32
+}
+
+/// `bytes` in the largest unit it has at least one of, like "1.2 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1000 {
+        return ngettext!("{} byte", "{} bytes", bytes as u32, bytes);
+    }
+
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = SIZE_UNITS[0];
+
+    for next_unit in &SIZE_UNITS[1..] {
+        // Compared as it is shown, so 999.95 kB is shown as 1.0 MB rather than 1000.0 kB
+        if (size * 10.0).round() < 10_000.0 {
+            break;
+        }
+
+        size /= 1000.0;
+        unit = next_unit;
+    }
+
+    format!("{:.1} {}", size, unit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transfer_steps() {
+        assert_eq!(
+            CloneProgress::of_transfer(5, 10, 0, 4, 2048),
+            CloneProgress::Receiving {
+                received_objects: 5,
+                total_objects: 10,
+                received_bytes: 2048
+            }
+        );
+        assert_eq!(
+            CloneProgress::of_transfer(10, 10, 1, 4, 4096),
+            CloneProgress::Resolving {
+                indexed_deltas: 1,
+                total_deltas: 4
+            }
+        );
+        // Nothing to resolve
+        assert!(matches!(
+            CloneProgress::of_transfer(10, 10, 0, 0, 4096),
+            CloneProgress::Receiving { .. }
+        ));
+        // Not started yet
+        assert!(matches!(
+            CloneProgress::of_transfer(0, 0, 0, 0, 0),
+            CloneProgress::Receiving { .. }
+        ));
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(percent(0, 10), 0);
+        assert_eq!(percent(1, 3), 33);
+        assert_eq!(percent(2, 3), 66);
+        assert_eq!(percent(999, 1000), 99);
+        assert_eq!(percent(1000, 1000), 100);
+        assert_eq!(percent(5, 0), 0);
+        assert_eq!(percent(12, 10), 100);
+        assert_eq!(percent(usize::MAX, usize::MAX), 100);
+    }
+
+    #[test]
+    fn fractions() {
+        let progress = CloneProgress::CheckingOut {
+            completed_files: 1,
+            total_files: 4,
+        };
+        assert_eq!(progress.fraction(), Some(0.25));
+
+        let progress = CloneProgress::of_transfer(0, 0, 0, 0, 0);
+        assert_eq!(progress.fraction(), None);
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(0), "0 bytes");
+        assert_eq!(format_bytes(1), "1 byte");
+        assert_eq!(format_bytes(999), "999 bytes");
+        assert_eq!(format_bytes(1000), "1.0 kB");
+        assert_eq!(format_bytes(1234), "1.2 kB");
+        assert_eq!(format_bytes(999_949), "999.9 kB");
+        assert_eq!(format_bytes(999_950), "1.0 MB");
+        assert_eq!(format_bytes(1_250_000), "1.2 MB");
+        assert_eq!(format_bytes(3_500_000_000), "3.5 GB");
+        assert_eq!(format_bytes(u64::MAX), "18446.7 PB");
+    }
+
+    #[test]
+    fn messages() {
+        let progress = CloneProgress::of_transfer(520, 1000, 0, 10, 1_234_567);
+        assert_eq!(
+            progress.message(),
+            "Receiving objects: 52% (520/1000), 1.2 MB"
+        );
+
+        let progress = CloneProgress::of_transfer(1000, 1000, 10, 10, 2_000_000);
+        assert_eq!(progress.message(), "Resolving deltas: 100% (10/10)");
+
+        let progress = CloneProgress::CheckingOut {
+            completed_files: 3,
+            total_files: 40,
+        };
+        assert_eq!(progress.message(), "Checking out files: 7% (3/40)");
+    }
+}
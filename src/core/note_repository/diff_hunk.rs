@@ -0,0 +1,11 @@
+use crate::core::DiffLine;
+
+/// Lines that changed in a part of a file, with the unchanged lines around them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// Line number, starting from 1, of the first line in the last commit
+    pub old_start: u32,
+    /// Line number, starting from 1, of the first line in the working directory
+    pub new_start: u32,
+    pub lines: Vec<DiffLine>,
+}
@@ -0,0 +1,28 @@
+use std::{fs, io, path::Path};
+
+/// How much space the notes take on the disk, and their history
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// Size of the working tree, without the `.git` directory
+    pub working_tree: u64,
+    /// Size of the `.git` directory
+    pub git_dir: u64,
+}
+
+/// Total size of the files in `path`, without following symlinks
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+use crate::core::DateTime;
+
+/// A commit that changed a file, as shown in its history
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRevision {
+    /// Id of the commit
+    pub id: String,
+    pub time: DateTime,
+    /// First line of the commit message
+    pub summary: String,
+    /// Path of the file in this commit relative to the repository, which is not the
+    /// current one if the file was moved since
+    pub path: PathBuf,
+}
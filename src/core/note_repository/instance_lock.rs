@@ -0,0 +1,195 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// File in the git directory with the pid of the instance that has the repository open
+const PID_FILE_NAME: &str = "noteworthy.pid";
+const INDEX_LOCK_FILE_NAME: &str = "index.lock";
+/// How long the index lock must be left untouched to be taken as stale, as git may be holding
+/// it for a command of the user
+const STALE_INDEX_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Marks a git directory as open in this process, so that its locks are only taken as stale
+/// when no other instance can be holding them. The mark is removed when this is dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    git_dir: PathBuf,
+    /// Whether the pid file is of this process, as another instance may have had it first
+    is_owner: bool,
+}
+
+impl InstanceLock {
+    pub fn acquire(git_dir: &Path) -> Self {
+        let is_owner = if let Some(pid) = running_instance(git_dir) {
+            log::warn!(
+                "Repository at `{}` is already open in process {}",
+                git_dir.display(),
+                pid
+            );
+            false
+        } else {
+            match fs::write(git_dir.join(PID_FILE_NAME), std::process::id().to_string()) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("Failed to write pid file: {:?}", err);
+                    false
+                }
+            }
+        };
+
+        Self {
+            git_dir: git_dir.to_owned(),
+            is_owner,
+        }
+    }
+
+    /// Remove the index lock left by an instance that stopped in the middle of an operation.
+    /// Returns whether there was one to remove, which is never the case while another instance
+    /// is running or when the lock is more recent than [`STALE_INDEX_LOCK_AGE`].
+    pub fn remove_stale_index_lock(&self) -> io::Result<bool> {
+        if let Some(pid) = running_instance(&self.git_dir) {
+            log::warn!("Index is locked, but process {} may be using it", pid);
+            return Ok(false);
+        }
+
+        let path = self.git_dir.join(INDEX_LOCK_FILE_NAME);
+
+        let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+
+        if !is_stale_lock(modified, SystemTime::now()) {
+            log::warn!("Index is locked, but git may still be using it");
+            return Ok(false);
+        }
+
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                log::warn!("Removed stale `{}`", path.display());
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if !self.is_owner {
+            return;
+        }
+
+        if let Err(err) = fs::remove_file(self.git_dir.join(PID_FILE_NAME)) {
+            log::warn!("Failed to remove pid file: {:?}", err);
+        }
+    }
+}
+
+/// Whether `err` is from git failing to take a lock, as its file already exists
+pub fn is_lock_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<git2::Error>()
+        .map_or(false, |err| err.code() == git2::ErrorCode::Locked)
+}
+
+/// Whether a lock last modified at `modified` was left untouched for long enough to be stale.
+/// A lock from the future, like after the clock was changed, is never taken as stale.
+fn is_stale_lock(modified: SystemTime, now: SystemTime) -> bool {
+    matches!(now.duration_since(modified), Ok(age) if age >= STALE_INDEX_LOCK_AGE)
+}
+
+/// Pid of another running instance that has the repository at `git_dir` open
+fn running_instance(git_dir: &Path) -> Option<u32> {
+    let pid_file = fs::read_to_string(git_dir.join(PID_FILE_NAME)).ok()?;
+    other_running_pid(&pid_file, std::process::id(), is_noteworthy_running)
+}
+
+fn other_running_pid(
+    pid_file: &str,
+    own_pid: u32,
+    is_running: impl Fn(u32) -> bool,
+) -> Option<u32> {
+    let pid = pid_file.trim().parse::<u32>().ok()?;
+
+    if pid != own_pid && is_running(pid) {
+        Some(pid)
+    } else {
+        None
+    }
+}
+
+/// Whether the process with `pid` is Noteworthy. Where processes can't be looked up, it is
+/// taken as running, so locks are never removed from under it.
+fn is_noteworthy_running(pid: u32) -> bool {
+    let proc_path = Path::new("/proc");
+
+    if !proc_path.join("self").exists() {
+        return true;
+    }
+
+    fs::read_to_string(proc_path.join(pid.to_string()).join("comm"))
+        .map_or(false, |name| name.trim().starts_with("noteworthy"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn other_running_pids() {
+        let is_running = |pid| pid == 42;
+
+        assert_eq!(other_running_pid("42\n", 7, is_running), Some(42));
+        // Stopped without removing its pid file
+        assert_eq!(other_running_pid("43", 7, is_running), None);
+        assert_eq!(other_running_pid("42", 42, is_running), None);
+        assert_eq!(other_running_pid("", 7, is_running), None);
+        assert_eq!(other_running_pid("not a pid", 7, is_running), None);
+    }
+
+    #[test]
+    fn stale_locks() {
+        let now = SystemTime::now();
+
+        assert!(is_stale_lock(now - STALE_INDEX_LOCK_AGE, now));
+        assert!(is_stale_lock(now - Duration::from_secs(24 * 60 * 60), now));
+        // Like one that git is holding while the user runs a command
+        assert!(!is_stale_lock(now - Duration::from_secs(5), now));
+        assert!(!is_stale_lock(now, now));
+        assert!(!is_stale_lock(now + Duration::from_secs(60), now));
+    }
+
+    #[test]
+    fn pid_file() {
+        let git_dir =
+            std::env::temp_dir().join(format!("noteworthy-instance-lock-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&git_dir);
+        fs::create_dir_all(&git_dir).unwrap();
+        let pid_path = git_dir.join(PID_FILE_NAME);
+
+        let instance_lock = InstanceLock::acquire(&git_dir);
+        assert_eq!(
+            fs::read_to_string(&pid_path).unwrap(),
+            std::process::id().to_string()
+        );
+        assert!(running_instance(&git_dir).is_none());
+
+        let lock_file = fs::File::create(git_dir.join(INDEX_LOCK_FILE_NAME)).unwrap();
+        assert!(!instance_lock.remove_stale_index_lock().unwrap());
+        assert!(git_dir.join(INDEX_LOCK_FILE_NAME).exists());
+
+        lock_file
+            .set_modified(SystemTime::now() - STALE_INDEX_LOCK_AGE)
+            .unwrap();
+        assert!(instance_lock.remove_stale_index_lock().unwrap());
+        assert!(!git_dir.join(INDEX_LOCK_FILE_NAME).exists());
+        assert!(!instance_lock.remove_stale_index_lock().unwrap());
+
+        drop(instance_lock);
+        assert!(!pid_path.exists());
+    }
+}
@@ -1,7 +1,24 @@
+mod ahead_behind;
+mod auth_recovery;
+mod changed_file;
+mod clone_progress;
+mod diff_hunk;
+mod disk_usage;
+mod file_revision;
+mod instance_lock;
+mod operation_queue;
+mod remote_credential;
+mod remote_status;
+mod repo_ops;
 mod repository;
+mod repository_error;
 mod repository_watcher;
+mod status_summary;
 mod sync_state;
 
+use anyhow::Context;
+use futures_channel::oneshot;
+use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
@@ -12,25 +29,95 @@ use once_cell::{sync::Lazy, unsync::OnceCell};
 use regex::Regex;
 
 use std::{
-    cell::Cell,
-    path::PathBuf,
+    cell::{Cell, RefCell},
+    future::Future,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::Instant,
 };
 
-pub use self::sync_state::SyncState;
-use self::{repository::Repository, repository_watcher::RepositoryWatcher};
-use crate::{spawn, spawn_blocking};
+pub use self::{
+    ahead_behind::AheadBehind,
+    auth_recovery::retry_once_on_auth_failure,
+    changed_file::{ChangedFile, FileChange},
+    clone_progress::{CloneCancelled, CloneProgress},
+    diff_hunk::DiffHunk,
+    disk_usage::DiskUsage,
+    file_revision::FileRevision,
+    remote_credential::CredentialKind,
+    remote_status::RemoteStatus,
+    repo_ops::RepoOps,
+    repository_error::RepositoryError,
+    status_summary::StatusSummary,
+    sync_state::SyncState,
+};
+use self::{
+    auth_recovery::AuthFailures,
+    changed_file::merge_changed_files,
+    operation_queue::OperationQueue,
+    repository::{device_branch_name, Repository},
+    repository_watcher::RepositoryWatcher,
+};
+use super::{
+    keyring::{self, Secret},
+    DateTime, Exclusions, Journal, JournalEvent,
+};
+use crate::{
+    model::{ErrorReport, ErrorSource},
+    spawn, spawn_blocking, Application,
+};
 
 const DEFAULT_REMOTE_NAME: &str = "origin";
 const DEFAULT_AUTHOR_NAME: &str = "NoteworthyApp";
 const DEFAULT_AUTHOR_EMAIL: &str = "app@noteworthy.io";
+/// How many times to merge the remote and push again when it moved while syncing
+const MAX_PUSH_RETRIES: u32 = 3;
 
 static RE_VALIDATE_URL: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(git@[\w\.]+)(:(//)?)([\w\.@:/\-~]+)(\.git)(/)?").unwrap());
+/// `https://host/path`, where the `.git` at the end is optional as most hosts take it both ways
+static RE_VALIDATE_HTTPS_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^https://([\w\-]+@)?[\w\-]+(\.[\w\-]+)*(:\d+)?(/[\w\.@\-~]+)+/?$").unwrap()
+});
+
+/// `message` with a line for each of the `renames` of files, from their old to their new
+/// path, after a blank line
+fn message_with_renames(message: &str, renames: &[(impl AsRef<Path>, impl AsRef<Path>)]) -> String {
+    if renames.is_empty() {
+        return message.to_string();
+    }
+
+    let lines = renames
+        .iter()
+        .map(|(old_path, new_path)| {
+            format!(
+                "Move '{}' to '{}'",
+                old_path.as_ref().display(),
+                new_path.as_ref().display()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}\n\n{}", message, lines.join("\n"))
+}
+
+/// Sender of the progress of an operation on another thread, which calls `progress` with it
+/// on the main thread
+fn progress_sender<T: 'static>(progress: impl Fn(T) + 'static) -> glib::Sender<T> {
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    receiver.attach(None, move |fraction| {
+        progress(fraction);
+        glib::Continue(true)
+    });
+    sender
+}
 
 struct SyncOptions {
     is_skip_pull: bool,
+    is_skip_commit: bool,
     is_skip_push: bool,
+    /// Changed files that are left out of the commit, relative to the repository
+    excluded_paths: Vec<PathBuf>,
 }
 
 mod imp {
@@ -40,8 +127,16 @@ mod imp {
     pub struct NoteRepository {
         pub base_path: OnceCell<gio::File>,
         pub sync_state: Cell<SyncState>,
-        pub repository: OnceCell<Arc<Mutex<Repository>>>,
+        pub auth_failures: Cell<AuthFailures>,
+        pub repository: OnceCell<Arc<Mutex<dyn RepoOps>>>,
+        /// Operations waiting for the repository to be opened
+        pub open_waiters: RefCell<Vec<oneshot::Sender<()>>>,
+        pub is_open_failed: Cell<bool>,
+        /// Set on the repository once it is open
+        pub exclusions: RefCell<Exclusions>,
         pub watcher: OnceCell<RepositoryWatcher>,
+        pub operation_queue: OperationQueue,
+        pub journal: RefCell<Option<Journal>>,
     }
 
     #[glib::object_subclass]
@@ -69,6 +164,22 @@ mod imp {
                         SyncState::default() as i32,
                         glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
+                    glib::ParamSpecBoolean::new(
+                        "busy",
+                        "Busy",
+                        "Whether a git operation is running or queued",
+                        false,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "queued-operations",
+                        "Queued Operations",
+                        "Number of git operations running or queued",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -98,6 +209,8 @@ mod imp {
             match pspec.name() {
                 "base-path" => obj.base_path().to_value(),
                 "sync-state" => obj.sync_state().to_value(),
+                "busy" => obj.is_busy().to_value(),
+                "queued-operations" => obj.queued_operations().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -109,28 +222,93 @@ glib::wrapper! {
 }
 
 impl NoteRepository {
-    pub async fn init(base_path: &gio::File) -> anyhow::Result<Self> {
-        let repository_path = base_path.path().unwrap();
-        let repository = spawn_blocking!(move || Repository::init(&repository_path)).await?;
-        Ok(Self::new(base_path, repository))
+    /// For the notes at `base_path`, whose repository is only opened by
+    /// [`Self::open_or_init`], so the notes can be shown before. The git operations wait
+    /// until it is open.
+    pub fn unopened(base_path: &gio::File) -> Self {
+        glib::Object::new(&[("base-path", &base_path)]).expect("Failed to create NoteRepository.")
     }
 
-    pub async fn clone(remote_url: String, base_path: &gio::File) -> anyhow::Result<Self> {
+    /// Clone `remote_url` into `base_path`, calling `progress` as it goes. Fails with
+    /// [`CloneCancelled`] when `cancellable` is cancelled, leaving nothing of the clone behind.
+    pub async fn clone(
+        remote_url: String,
+        base_path: &gio::File,
+        progress: impl Fn(CloneProgress) + 'static,
+        cancellable: &gio::Cancellable,
+    ) -> anyhow::Result<Self> {
         let repository_path = base_path.path().unwrap();
-        let repository =
-            spawn_blocking!(move || Repository::clone(&repository_path, &remote_url)).await?;
+        let sender = progress_sender(progress);
+        let cancellable = cancellable.clone();
+
+        let repository = spawn_blocking!(move || {
+            Repository::clone_with_progress(
+                &repository_path,
+                &remote_url,
+                |progress| {
+                    // The receiver is only dropped along with the main context
+                    let _ = sender.send(progress);
+                },
+                || cancellable.is_cancelled(),
+            )
+        })
+        .await?;
         Ok(Self::new(base_path, repository))
     }
 
-    pub async fn open(base_path: &gio::File) -> anyhow::Result<Self> {
-        let repository_path = base_path.path().unwrap();
-        let repository = spawn_blocking!(move || Repository::open(&repository_path)).await?;
-        Ok(Self::new(base_path, repository))
+    /// Open the repository of [`Self::unopened`] as a queued operation, finishing the merge
+    /// that was interrupted the last time it was open, if any, or create it when there is
+    /// none yet. Does nothing if it is already open.
+    ///
+    /// The files changed by finishing the merge are reloaded like the ones changed by other
+    /// apps.
+    pub async fn open_or_init(&self) -> anyhow::Result<()> {
+        let imp = self.imp();
+
+        if imp.repository.get().is_some() {
+            return Ok(());
+        }
+
+        let repository_path = self.base_path().path().unwrap();
+        let exclusions = imp.exclusions.borrow().clone();
+
+        let res = self
+            .run_queued(spawn_blocking!(move || -> anyhow::Result<_> {
+                let mut repository = match Repository::open(&repository_path) {
+                    Ok(repository) => {
+                        // Left for the next sync to fail on, so it shows up in the error log
+                        if let Err(err) = repository
+                            .recover_interrupted_merge(DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)
+                        {
+                            log::error!("Failed to recover interrupted merge: {:?}", err);
+                        }
+                        repository
+                    }
+                    Err(err) => {
+                        log::info!("Creating a repository, as none could be opened: {:?}", err);
+                        Repository::init(&repository_path)?
+                    }
+                };
+
+                repository.set_exclusions(exclusions);
+                Ok(repository)
+            }))
+            .await;
+
+        match res {
+            Ok(repository) => self.set_repository(repository),
+            Err(_) => imp.is_open_failed.set(true),
+        }
+
+        for sender in imp.open_waiters.take() {
+            let _ = sender.send(());
+        }
+
+        res.map(|_| ())
     }
 
-    fn new(base_path: &gio::File, repository: Repository) -> Self {
-        let obj = glib::Object::new::<Self>(&[("base-path", &base_path)])
-            .expect("Failed to create NoteRepository.");
+    fn new(base_path: &gio::File, repository: impl RepoOps + 'static) -> Self {
+        let obj = Self::unopened(base_path);
         obj.set_repository(repository);
         obj
     }
@@ -140,13 +318,148 @@ impl NoteRepository {
             return false;
         }
 
-        RE_VALIDATE_URL.is_match(remote_url)
+        RE_VALIDATE_URL.is_match(remote_url) || RE_VALIDATE_HTTPS_URL.is_match(remote_url)
+    }
+
+    /// URL of the remote the notes are synced with, or `None` if they are only on this device
+    pub async fn remote_url(&self) -> anyhow::Result<Option<String>> {
+        let repo = self.repository().await?;
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.remote_url(DEFAULT_REMOTE_NAME)
+        })
+        .await
+    }
+
+    /// Whether the remote at `remote_url` can be synced with, without changing anything
+    pub async fn check_remote(remote_url: &str) -> RemoteStatus {
+        let remote_url = remote_url.to_string();
+        spawn_blocking!(move || Repository::check_remote(&remote_url)).await
+    }
+
+    /// Sync with the remote at `remote_url` from now on. The commits that are not pushed yet
+    /// are kept, and compared with the ones of the new remote once it is fetched.
+    pub async fn set_remote_url(&self, remote_url: &str) -> anyhow::Result<AheadBehind> {
+        let repo = self.repository().await?;
+        let remote_url = remote_url.to_string();
+
+        self.run_queued(spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.set_remote_url(DEFAULT_REMOTE_NAME, &remote_url)?;
+            repo.fetch(DEFAULT_REMOTE_NAME)?;
+            repo.ahead_behind(DEFAULT_REMOTE_NAME)
+        }))
+        .await
+    }
+
+    /// Fetch the default branch of the remote, leaving the working tree as it is, then count
+    /// the commits that are not pushed or not pulled yet
+    pub async fn fetch_ahead_behind(&self) -> anyhow::Result<AheadBehind> {
+        let repo = self.repository().await?;
+
+        self.run_queued(spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.fetch_default_branch(DEFAULT_REMOTE_NAME)?;
+            repo.ahead_behind(DEFAULT_REMOTE_NAME)
+        }))
+        .await
+    }
+
+    /// Like [`Self::fetch_ahead_behind`], against the remote as it was last fetched, like
+    /// right after syncing
+    pub async fn ahead_behind(&self) -> anyhow::Result<AheadBehind> {
+        let repo = self.repository().await?;
+
+        self.run_queued(spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.ahead_behind(DEFAULT_REMOTE_NAME)
+        }))
+        .await
     }
 
     pub fn sync_state(&self) -> SyncState {
         self.imp().sync_state.get()
     }
 
+    /// Whether the notes may be synced with the remote without the user asking to. They are
+    /// not for a while after the remote rejected the credentials, and no longer at all after
+    /// it did too many times, until [`Self::reset_auth_failures`].
+    pub fn allows_auto_sync(&self) -> bool {
+        self.imp()
+            .auth_failures
+            .get()
+            .allows_auto_sync(Instant::now())
+    }
+
+    /// Number of times in a row the remote rejected the credentials
+    pub fn n_auth_failures(&self) -> u32 {
+        self.imp().auth_failures.get().len()
+    }
+
+    /// Try the remote again, as the user entered a new credential or asked to sync
+    pub fn reset_auth_failures(&self) {
+        self.imp().auth_failures.take();
+
+        if self.sync_state() == SyncState::AuthRequired {
+            self.set_sync_state(SyncState::Idle);
+        }
+    }
+
+    /// Store `secret` in the keyring, so it is given to the remote at `remote_url` when it
+    /// rejects the other credentials
+    pub async fn store_remote_secret(remote_url: &str, secret: &str) -> anyhow::Result<()> {
+        let remote_url = remote_url.to_string();
+        let secret = secret.to_string();
+        spawn_blocking!(move || keyring::store(Secret::RemoteCredential(&remote_url), &secret))
+            .await
+            .context("Failed to store the credential in the keyring")
+    }
+
+    /// Whether a git operation is running or waiting for another one to finish.
+    ///
+    /// Actions that would conflict with it, like deleting files, should wait until this is
+    /// false. Edits that only touch the working directory are always fine.
+    pub fn is_busy(&self) -> bool {
+        self.queued_operations() > 0
+    }
+
+    pub fn queued_operations(&self) -> u32 {
+        self.imp().operation_queue.n_operations()
+    }
+
+    /// Where the syncs and the git operations are recorded
+    pub fn set_journal(&self, journal: &Journal) {
+        self.imp().journal.replace(Some(journal.clone()));
+    }
+
+    /// Leave the files matched by `exclusions` out of the changes and the commits
+    pub async fn set_exclusions(&self, exclusions: Exclusions) {
+        self.imp().exclusions.replace(exclusions.clone());
+
+        // Otherwise they are set once it is open
+        let repo = match self.imp().repository.get() {
+            Some(repo) => Arc::clone(repo),
+            None => return,
+        };
+
+        spawn_blocking!(move || {
+            repo.lock().unwrap().set_exclusions(exclusions);
+        })
+        .await;
+    }
+
+    pub fn connect_busy_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("busy"), move |obj, _| f(obj))
+    }
+
     pub fn connect_remote_changed<F>(&self, f: F) -> glib::SignalHandlerId
     where
         F: Fn(&RepositoryWatcher) + 'static,
@@ -163,37 +476,147 @@ impl NoteRepository {
         watcher.connect_remote_changed(f)
     }
 
-    pub async fn sync(&self) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+    /// Pull, commit the changes in the working directory with `commit_message`, then push.
+    /// The files at `excluded_paths`, relative to the repository, are left uncommitted.
+    ///
+    /// The changes are committed on the branch of this device (`device/<hostname>`), which is
+    /// merged with the default branch of the remote before being pushed to it.
+    pub async fn sync(
+        &self,
+        commit_message: &str,
+        excluded_paths: &[PathBuf],
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
         let sync_opts = SyncOptions {
             is_skip_pull: false,
+            is_skip_commit: false,
             is_skip_push: false,
+            excluded_paths: excluded_paths.to_vec(),
         };
 
-        let changed_files = self.sync_full(sync_opts).await?.unwrap();
+        let changed_files = self.sync_full(sync_opts, commit_message).await?.unwrap();
         Ok(changed_files)
     }
 
-    pub async fn sync_offline(&self) -> anyhow::Result<()> {
+    /// Commit the changes in the working directory with `commit_message`, except the ones of
+    /// the files at `excluded_paths`
+    pub async fn sync_offline(
+        &self,
+        commit_message: &str,
+        excluded_paths: &[PathBuf],
+    ) -> anyhow::Result<()> {
         let sync_opts = SyncOptions {
             is_skip_pull: true,
+            is_skip_commit: false,
             is_skip_push: true,
+            excluded_paths: excluded_paths.to_vec(),
         };
 
-        match self.sync_full(sync_opts).await {
+        match self.sync_full(sync_opts, commit_message).await {
             Ok(_) => Ok(()),
             Err(err) => Err(err),
         }
     }
 
+    /// Pull the changes of the remote without committing or pushing anything, for
+    /// notebooks that are only read on this device
+    pub async fn pull_only(&self) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        let sync_opts = SyncOptions {
+            is_skip_pull: false,
+            is_skip_commit: true,
+            is_skip_push: true,
+            excluded_paths: Vec::new(),
+        };
+
+        let changed_files = self.sync_full(sync_opts, "").await?.unwrap();
+        Ok(changed_files)
+    }
+
+    /// Commit the files at `paths` only, relative to the repository, with `commit_message`.
+    /// Unlike [`Self::sync_offline`], the other changes in the working directory are left
+    /// uncommitted for the next sync.
+    pub async fn commit_paths(
+        &self,
+        commit_message: &str,
+        paths: &[PathBuf],
+    ) -> anyhow::Result<()> {
+        let repo = self.repository().await?;
+        let message = commit_message.to_string();
+        let paths = paths.to_vec();
+
+        let res = self
+            .run_queued(async move {
+                spawn_blocking!(move || {
+                    let repo = repo.lock().unwrap();
+
+                    repo.commit_paths(&paths, &message, DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)
+                })
+                .await
+            })
+            .await;
+
+        if let Err(ref err) = res {
+            Application::default()
+                .error_log()
+                .push(ErrorReport::from_error(
+                    ErrorSource::Sync,
+                    &gettext("Failed to sync notes"),
+                    err,
+                ));
+        }
+
+        res
+    }
+
     async fn sync_full(
         &self,
         sync_opts: SyncOptions,
+        commit_message: &str,
+    ) -> anyhow::Result<Option<Vec<(PathBuf, git2::Delta)>>> {
+        let is_remote_used = !sync_opts.is_skip_pull || !sync_opts.is_skip_push;
+
+        let res = self
+            .run_queued(self.sync_full_inner(sync_opts, commit_message))
+            .await;
+
+        match res {
+            Ok(_) if is_remote_used => self.reset_auth_failures(),
+            Ok(_) => {}
+            Err(ref err) => {
+                if RepositoryError::is_authentication(err) {
+                    let mut auth_failures = self.imp().auth_failures.get();
+                    auth_failures.record_failure(Instant::now());
+                    self.imp().auth_failures.set(auth_failures);
+                }
+
+                // Otherwise it stays at the step that failed
+                self.set_sync_state(self.idle_sync_state());
+
+                Application::default()
+                    .error_log()
+                    .push(ErrorReport::from_error(
+                        ErrorSource::Sync,
+                        &gettext("Failed to sync notes"),
+                        err,
+                    ));
+            }
+        }
+
+        res
+    }
+
+    async fn sync_full_inner(
+        &self,
+        sync_opts: SyncOptions,
+        commit_message: &str,
     ) -> anyhow::Result<Option<Vec<(PathBuf, git2::Delta)>>> {
         self.set_sync_state(SyncState::Syncing);
+        self.journal().record(JournalEvent::SyncStarted);
 
-        let changed_files = if sync_opts.is_skip_pull {
+        let mut changed_files = if sync_opts.is_skip_pull {
             None
         } else {
+            self.checkout_device_branch().await?;
+
             log::info!("Sync: Repo pulling changes...");
             self.set_sync_state(SyncState::Pulling);
             let changed_files = self.pull().await?;
@@ -201,49 +624,254 @@ impl NoteRepository {
             Some(changed_files)
         };
 
-        if self.is_file_changed_in_workdir().await? {
-            log::info!("Sync: Found changes, adding all...");
-            self.add_all().await?;
-            log::info!("Sync: Added all files");
+        let is_changed = if sync_opts.is_skip_commit {
+            false
+        } else if sync_opts.excluded_paths.is_empty() {
+            let is_changed = self.is_file_changed_in_workdir().await?;
+            if is_changed {
+                log::info!("Sync: Found changes, adding all...");
+                self.add_all().await?;
+                log::info!("Sync: Added all files");
+            }
+            is_changed
+        } else {
+            log::info!(
+                "Sync: Adding all files except {} excluded...",
+                sync_opts.excluded_paths.len()
+            );
+            let is_changed = self.add_all_except(&sync_opts.excluded_paths).await?;
+            log::info!("Sync: Added files; is_changed `{}`", is_changed);
+            is_changed
+        };
 
+        if is_changed {
             log::info!("Sync: Creating commit...");
-            self.commit().await?;
+            self.commit(commit_message).await?;
             log::info!("Sync: Created commit");
-
-            if !sync_opts.is_skip_push {
-                log::info!("Sync: Repo pushing changes...");
-                self.set_sync_state(SyncState::Pushing);
-                self.push().await?;
-                log::info!("Sync: Pushed chanes to remote");
-            }
         } else {
             log::info!("Sync: There is no changed files in directory");
-            log::info!("Sync: Skipped pushing and commit");
+            log::info!("Sync: Skipped commit");
+        }
+
+        // Push even without new changes, since the last push may have failed
+        if !sync_opts.is_skip_push {
+            log::info!("Sync: Repo pushing changes...");
+            self.set_sync_state(SyncState::Pushing);
+            // The remote may have moved since it was pulled, so it is merged again
+            let merged_files = self.push().await?;
+            merge_changed_files(changed_files.get_or_insert_with(Vec::new), merged_files);
+            log::info!("Sync: Pushed chanes to remote");
         }
 
-        self.set_sync_state(SyncState::Idle);
+        self.set_sync_state(self.idle_sync_state());
+        self.journal().record(JournalEvent::SyncFinished {
+            pushed: !sync_opts.is_skip_push,
+            pulled: changed_files.as_ref().map_or(0, Vec::len),
+        });
 
         Ok(changed_files)
     }
 
+    /// Changes in the working tree that are not committed yet
+    pub async fn status_summary(&self) -> anyhow::Result<StatusSummary> {
+        let repo = self.repository().await?;
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.status_summary()
+        })
+        .await
+    }
+
+    /// Files that are not committed yet, relative to the repository
+    pub async fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>> {
+        let repo = self.repository().await?;
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.changed_files()
+        })
+        .await
+    }
+
+    /// Changes of the file at `path`, relative to the repository, that are not committed yet
+    pub async fn diff_workdir_to_head(&self, path: &Path) -> anyhow::Result<Vec<DiffHunk>> {
+        let repo = self.repository().await?;
+        let path = path.to_path_buf();
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.diff_workdir_to_head(&path)
+        })
+        .await
+    }
+
+    /// Commits that changed `file`, newest first
+    pub async fn file_history(&self, file: &gio::File) -> anyhow::Result<Vec<FileRevision>> {
+        let repo = self.repository().await?;
+        let path = self.relative_path(file)?;
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.file_history(&path)
+        })
+        .await
+    }
+
+    /// Contents of the file as they were in `revision`, where it may have had another path
+    pub async fn file_content_at(&self, revision: &FileRevision) -> anyhow::Result<Vec<u8>> {
+        let repo = self.repository().await?;
+        let path = revision.path.clone();
+        let revision_id = revision.id.clone();
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.file_content_at(&revision_id, &path)
+        })
+        .await
+    }
+
+    /// How much space the notes and their history take on the disk
+    pub async fn disk_usage(&self) -> anyhow::Result<DiskUsage> {
+        let repo = self.repository().await?;
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.disk_usage()
+        })
+        .await
+    }
+
+    /// Remove the objects of the history that can't be reached anymore, like the ones of the
+    /// notes that were deleted before being committed, and compress the others. `progress` is
+    /// called with the fraction that is done. Returns how many bytes were reclaimed.
+    pub async fn compact(&self, progress: impl Fn(f64) + 'static) -> anyhow::Result<u64> {
+        let repo = self.repository().await?;
+        let sender = progress_sender(progress);
+
+        self.run_queued(spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            repo.compact(&mut |fraction| {
+                // The receiver is only dropped along with the main context
+                let _ = sender.send(fraction);
+            })
+        }))
+        .await
+    }
+
+    /// Forget the commits made before `cutoff`, keeping the notes as they are now, then
+    /// compact the repository so the notes that were only in them are removed from the disk.
+    /// When there is a remote, its history is replaced too, so the other devices have to
+    /// clone it again. Returns how many bytes were reclaimed.
+    pub async fn forget_history_before(
+        &self,
+        cutoff: DateTime,
+        progress: impl Fn(f64) + 'static,
+    ) -> anyhow::Result<u64> {
+        let repo = self.repository().await?;
+        let sender = progress_sender(progress);
+
+        self.run_queued(spawn_blocking!(move || -> anyhow::Result<u64> {
+            let repo = repo.lock().unwrap();
+
+            let size_before = repo.disk_usage()?.git_dir;
+
+            let n_forgotten =
+                repo.forget_history_before(cutoff, DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)?;
+            log::info!("Forgot {} commits", n_forgotten);
+
+            if !repo.remotes()?.is_empty() {
+                log::info!("Replacing the history of the remote...");
+                repo.force_push(
+                    DEFAULT_REMOTE_NAME,
+                    &repo.default_branch(DEFAULT_REMOTE_NAME),
+                )?;
+            }
+
+            repo.compact(&mut |fraction| {
+                let _ = sender.send(fraction);
+            })?;
+
+            Ok(size_before.saturating_sub(repo.disk_usage()?.git_dir))
+        }))
+        .await
+    }
+
+    /// Wait until the git operations queued so far are done
+    pub async fn wait_for_queued_operations(&self) {
+        self.run_queued(async {}).await;
+    }
+
+    /// Run `operation` once the git operations queued before it are done, so they never
+    /// touch the repository at the same time
+    async fn run_queued<T>(&self, operation: impl Future<Output = T>) -> T {
+        let mut turn = self.imp().operation_queue.enqueue();
+        self.notify_operation_queue();
+
+        turn.wait().await;
+        let output = operation.await;
+
+        drop(turn);
+        self.notify_operation_queue();
+
+        output
+    }
+
+    fn notify_operation_queue(&self) {
+        self.notify("queued-operations");
+        self.notify("busy");
+    }
+
+    /// Move to the branch of this device if the repository already has commits
+    async fn checkout_device_branch(&self) -> anyhow::Result<()> {
+        let repo = self.repository().await?;
+        let branch_name = device_branch_name(&glib::host_name());
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            if repo.current_branch()?.is_none() {
+                log::info!("Sync: HEAD is not on a branch, skipping device branch checkout");
+                return Ok(());
+            }
+
+            repo.switch_to_branch(&branch_name)
+        })
+        .await
+    }
+
     // FIXME (CRITICAL) handle conflicts gracefully
     async fn pull(&self) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
-        let repo = self.repository();
+        let repo = self.repository().await?;
+        let journal_sender = self.journal().sender();
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
 
-            repo.pull(
+            let changed_files = repo.pull(
                 DEFAULT_REMOTE_NAME,
+                &repo.default_branch(DEFAULT_REMOTE_NAME),
                 DEFAULT_AUTHOR_NAME,
                 DEFAULT_AUTHOR_EMAIL,
-            )
+            )?;
+            journal_sender.send(JournalEvent::Pulled {
+                files: changed_files.len(),
+            });
+
+            Ok(changed_files)
         })
         .await
     }
 
     async fn remotes(&self) -> anyhow::Result<Vec<String>> {
-        let repo = self.repository();
+        let repo = self.repository().await?;
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
@@ -258,7 +886,7 @@ impl NoteRepository {
     }
 
     async fn is_file_changed_in_workdir(&self) -> anyhow::Result<bool> {
-        let repo = self.repository();
+        let repo = self.repository().await?;
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
@@ -269,55 +897,193 @@ impl NoteRepository {
     }
 
     async fn add_all(&self) -> anyhow::Result<()> {
-        let repo = self.repository();
+        let repo = self.repository().await?;
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
 
-            repo.add(&["."])
+            repo.add_all()
         })
         .await
     }
 
-    async fn commit(&self) -> anyhow::Result<()> {
-        let repo = self.repository();
+    /// Stage the changed files except the ones at `excluded_paths`, which are unstaged in case
+    /// an earlier sync staged them. Returns whether there is anything to commit.
+    async fn add_all_except(&self, excluded_paths: &[PathBuf]) -> anyhow::Result<bool> {
+        let repo = self.repository().await?;
+        let excluded_paths = excluded_paths.to_vec();
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
 
-            repo.commit("Sync commit", DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)
+            let paths = repo
+                .changed_files()?
+                .into_iter()
+                .map(|changed_file| changed_file.path)
+                .filter(|path| !excluded_paths.contains(path))
+                .collect::<Vec<_>>();
+
+            repo.unstage(&excluded_paths)?;
+
+            if paths.is_empty() {
+                return Ok(false);
+            }
+
+            repo.add(&paths)?;
+            Ok(true)
         })
         .await
     }
 
-    async fn push(&self) -> anyhow::Result<()> {
-        let repo = self.repository();
+    async fn commit(&self, message: &str) -> anyhow::Result<()> {
+        let repo = self.repository().await?;
+        let message = message.to_string();
 
         spawn_blocking!(move || {
             let repo = repo.lock().unwrap();
 
-            repo.push(DEFAULT_REMOTE_NAME)
+            // The changes only know about the notes, not that their files were moved
+            let renames = repo.staged_renames()?;
+            let message = message_with_renames(&message, &renames);
+
+            repo.commit(&message, DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)
         })
         .await
     }
 
-    fn repository(&self) -> Arc<Mutex<Repository>> {
-        Arc::clone(self.imp().repository.get().unwrap())
+    /// Returns the files changed by merging the remote, when it moved since it was pulled
+    async fn push(&self) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        let repo = self.repository().await?;
+        let journal_sender = self.journal().sender();
+
+        spawn_blocking!(move || {
+            let repo = repo.lock().unwrap();
+
+            let merged_files = repo.push_with_retry(
+                DEFAULT_REMOTE_NAME,
+                &repo.default_branch(DEFAULT_REMOTE_NAME),
+                DEFAULT_AUTHOR_NAME,
+                DEFAULT_AUTHOR_EMAIL,
+                MAX_PUSH_RETRIES,
+            )?;
+            journal_sender.send(JournalEvent::Pushed);
+
+            Ok(merged_files)
+        })
+        .await
+    }
+
+    /// The repository, once [`Self::open_or_init`] opened it
+    async fn repository(&self) -> anyhow::Result<Arc<Mutex<dyn RepoOps>>> {
+        let imp = self.imp();
+
+        if imp.repository.get().is_none() && !imp.is_open_failed.get() {
+            let (sender, receiver) = oneshot::channel();
+            imp.open_waiters.borrow_mut().push(sender);
+            let _ = receiver.await;
+        }
+
+        imp.repository
+            .get()
+            .map(Arc::clone)
+            .context("The repository could not be opened")
+    }
+
+    #[cfg(test)]
+    pub fn is_open(&self) -> bool {
+        self.imp().repository.get().is_some()
     }
 
-    fn set_repository(&self, repository: Repository) {
+    fn set_repository(&self, repository: impl RepoOps + 'static) {
         self.imp()
             .repository
             .set(Arc::new(Mutex::new(repository)))
             .unwrap();
     }
 
+    fn journal(&self) -> Journal {
+        self.imp()
+            .journal
+            .borrow_mut()
+            .get_or_insert_with(Journal::new)
+            .clone()
+    }
+
     fn base_path(&self) -> gio::File {
         self.imp().base_path.get().unwrap().clone()
     }
 
+    fn relative_path(&self, file: &gio::File) -> anyhow::Result<PathBuf> {
+        self.base_path()
+            .relative_path(file)
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not in the repository", file.uri()))
+    }
+
+    /// The state once nothing runs, which shows that the remote rejected the credentials
+    /// until it accepts them again
+    fn idle_sync_state(&self) -> SyncState {
+        if self.imp().auth_failures.get().is_empty() {
+            SyncState::Idle
+        } else {
+            SyncState::AuthRequired
+        }
+    }
+
     fn set_sync_state(&self, sync_state: SyncState) {
         self.imp().sync_state.set(sync_state);
         self.notify("sync-state");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn message_with_renames() {
+        let no_renames: &[(&str, &str)] = &[];
+        assert_eq!(
+            super::message_with_renames("Edit 'A'", no_renames),
+            "Edit 'A'"
+        );
+
+        assert_eq!(
+            super::message_with_renames("Edit 'A'", &[("a.md", "b.md"), ("c.md", "d.md")]),
+            "Edit 'A'\n\nMove 'a.md' to 'b.md'\nMove 'c.md' to 'd.md'"
+        );
+    }
+
+    #[test]
+    fn validate_remote_url() {
+        assert!(NoteRepository::validate_remote_url(
+            "git@github.com:SeaDve/test.git"
+        ));
+        assert!(NoteRepository::validate_remote_url(
+            "git@gitlab.gnome.org:World/noteworthy.git/"
+        ));
+        assert!(NoteRepository::validate_remote_url(
+            "https://github.com/SeaDve/test.git"
+        ));
+        assert!(NoteRepository::validate_remote_url(
+            "https://github.com/SeaDve/test"
+        ));
+        assert!(NoteRepository::validate_remote_url(
+            "https://git.example.com:8443/notes/"
+        ));
+        assert!(NoteRepository::validate_remote_url(
+            "https://user@example.com/notes.git"
+        ));
+
+        assert!(!NoteRepository::validate_remote_url(""));
+        assert!(!NoteRepository::validate_remote_url("https://github.com"));
+        assert!(!NoteRepository::validate_remote_url(
+            "http://github.com/SeaDve/test.git"
+        ));
+        assert!(!NoteRepository::validate_remote_url(
+            "github.com/SeaDve/test.git"
+        ));
+        assert!(!NoteRepository::validate_remote_url(
+            "https://github.com/Sea Dve/test.git"
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use futures_channel::oneshot::{self, Receiver, Sender};
+
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+};
+
+/// Runs async operations one at a time, in the order they were queued
+#[derive(Debug, Default)]
+pub struct OperationQueue {
+    /// Resolved when the last queued operation is done
+    last_done: RefCell<Option<Receiver<()>>>,
+    n_operations: Rc<Cell<u32>>,
+}
+
+impl OperationQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of operations that are running or waiting to run
+    pub fn n_operations(&self) -> u32 {
+        self.n_operations.get()
+    }
+
+    /// Take the next place in the queue. The operation can run once [`Turn::wait`] returns,
+    /// and the next one runs once the `Turn` is dropped.
+    pub fn enqueue(&self) -> Turn {
+        let (done_sender, done_receiver) = oneshot::channel();
+        let previous_done = self.last_done.replace(Some(done_receiver));
+
+        self.n_operations.set(self.n_operations.get() + 1);
+
+        Turn {
+            previous_done,
+            done_sender: Some(done_sender),
+            n_operations: Rc::clone(&self.n_operations),
+        }
+    }
+
+    /// Run `operation` after the operations queued before it are done
+    pub async fn run<T>(&self, operation: impl Future<Output = T>) -> T {
+        let mut turn = self.enqueue();
+        turn.wait().await;
+        operation.await
+    }
+}
+
+/// Place of an operation in an [`OperationQueue`]
+#[derive(Debug)]
+pub struct Turn {
+    previous_done: Option<Receiver<()>>,
+    done_sender: Option<Sender<()>>,
+    n_operations: Rc<Cell<u32>>,
+}
+
+impl Turn {
+    /// Wait until the operations queued before this are done
+    pub async fn wait(&mut self) {
+        if let Some(previous_done) = self.previous_done.take() {
+            // The sender is also dropped when the previous operation is cancelled, which
+            // still means it is done
+            let _ = previous_done.await;
+        }
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.n_operations.set(self.n_operations.get() - 1);
+
+        if let Some(done_sender) = self.done_sender.take() {
+            let _ = done_sender.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gtk::glib;
+
+    fn iterate_pending(ctx: &glib::MainContext) {
+        while ctx.pending() {
+            ctx.iteration(false);
+        }
+    }
+
+    #[test]
+    fn run_in_order() {
+        let ctx = glib::MainContext::new();
+        let _guard = ctx.acquire().unwrap();
+
+        let queue = Rc::new(OperationQueue::new());
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let (gate_sender, gate_receiver) = oneshot::channel::<()>();
+
+        let queue_clone = Rc::clone(&queue);
+        let order_clone = Rc::clone(&order);
+        ctx.spawn_local(async move {
+            queue_clone
+                .run(async {
+                    let _ = gate_receiver.await;
+                    order_clone.borrow_mut().push(1);
+                })
+                .await;
+        });
+
+        for i in 2..=3 {
+            let queue_clone = Rc::clone(&queue);
+            let order_clone = Rc::clone(&order);
+            ctx.spawn_local(async move {
+                queue_clone
+                    .run(async { order_clone.borrow_mut().push(i) })
+                    .await;
+            });
+        }
+
+        iterate_pending(&ctx);
+        // The first operation is still waiting, so the others must not have started
+        assert!(order.borrow().is_empty());
+        assert_eq!(queue.n_operations(), 3);
+
+        gate_sender.send(()).unwrap();
+
+        while queue.n_operations() > 0 {
+            ctx.iteration(true);
+        }
+
+        assert_eq!(*order.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn run_returns_output() {
+        let ctx = glib::MainContext::new();
+        let queue = OperationQueue::new();
+
+        assert_eq!(ctx.block_on(queue.run(async { 42 })), 42);
+        assert_eq!(queue.n_operations(), 0);
+    }
+
+    #[test]
+    fn cancelled_turn() {
+        let ctx = glib::MainContext::new();
+        let queue = OperationQueue::new();
+
+        let cancelled_turn = queue.enqueue();
+        let mut turn = queue.enqueue();
+        assert_eq!(queue.n_operations(), 2);
+
+        drop(cancelled_turn);
+        assert_eq!(queue.n_operations(), 1);
+
+        // Must not wait forever for the cancelled operation
+        ctx.block_on(turn.wait());
+        drop(turn);
+        assert_eq!(queue.n_operations(), 0);
+    }
+}
@@ -0,0 +1,83 @@
+use gettextrs::gettext;
+
+use std::path::PathBuf;
+
+/// Keys tried for the passphrase entered by the user, in the order `ssh` tries them
+const SSH_KEY_NAMES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// The secret asked to the user when the remote rejects the credentials, which depends on
+/// how the remote is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialKind {
+    /// Of the SSH key, for `git@host:path` URLs
+    SshPassphrase,
+    /// Personal access token or password, for `https://` URLs
+    HttpsToken,
+}
+
+impl CredentialKind {
+    pub fn for_url(remote_url: &str) -> Self {
+        if remote_url.starts_with("https://") || remote_url.starts_with("http://") {
+            Self::HttpsToken
+        } else {
+            Self::SshPassphrase
+        }
+    }
+
+    /// What the user is asked, naming the remote at `remote_url` that rejected the
+    /// credentials
+    pub fn prompt(&self, remote_url: &str) -> String {
+        match self {
+            Self::SshPassphrase => gettext!(
+                "{} rejected the credentials. Enter the passphrase of your SSH key to sync with it.",
+                remote_url
+            ),
+            Self::HttpsToken => gettext!(
+                "{} rejected the credentials. Enter an access token or the password of your account to sync with it.",
+                remote_url
+            ),
+        }
+    }
+
+    pub fn entry_placeholder(&self) -> String {
+        match self {
+            Self::SshPassphrase => gettext("Passphrase"),
+            Self::HttpsToken => gettext("Access Token"),
+        }
+    }
+}
+
+/// The SSH key the passphrase entered by the user is for, the first one that exists
+pub fn default_ssh_key() -> Option<PathBuf> {
+    let ssh_dir = PathBuf::from(std::env::var_os("HOME")?).join(".ssh");
+
+    SSH_KEY_NAMES
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kind_for_url() {
+        assert_eq!(
+            CredentialKind::for_url("git@github.com:SeaDve/test.git"),
+            CredentialKind::SshPassphrase
+        );
+        assert_eq!(
+            CredentialKind::for_url("ssh://git@example.com/notes.git"),
+            CredentialKind::SshPassphrase
+        );
+        assert_eq!(
+            CredentialKind::for_url("https://github.com/SeaDve/test"),
+            CredentialKind::HttpsToken
+        );
+        assert_eq!(
+            CredentialKind::for_url("https://user@example.com/notes.git"),
+            CredentialKind::HttpsToken
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use super::RepositoryError;
+
+/// Whether a remote can be synced with, from checking it like `git ls-remote` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteStatus {
+    Reachable,
+    /// The remote rejected the credentials, or none is available
+    AuthFailed(String),
+    /// Something answered at the URL, but there is no repository there
+    NotARepository(String),
+    /// Nothing could be reached at the URL, like when offline or on a typo in the host
+    Unreachable(String),
+}
+
+impl RemoteStatus {
+    /// Map the error of connecting to the remote or listing its references
+    pub fn from_error(err: git2::Error) -> Self {
+        let message = err.message().to_string();
+        let lowercase_message = message.to_lowercase();
+
+        // Checked first, as hosts also answer this over SSH, which would count as an
+        // authentication error
+        if err.code() == git2::ErrorCode::NotFound
+            || err.class() == git2::ErrorClass::Repository
+            || lowercase_message.contains("not found")
+            || lowercase_message.contains("status code: 404")
+        {
+            return Self::NotARepository(message);
+        }
+
+        match RepositoryError::from(err) {
+            RepositoryError::Authentication(message) => Self::AuthFailed(message),
+            _ => Self::Unreachable(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_error() {
+        let err = git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Callback,
+            "no credentials",
+        );
+        assert_eq!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::AuthFailed("no credentials".to_string())
+        );
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Ssh,
+            "Failed to authenticate SSH session",
+        );
+        assert!(matches!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::AuthFailed(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::NotFound,
+            git2::ErrorClass::Repository,
+            "could not find repository at '/tmp/notes'",
+        );
+        assert!(matches!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::NotARepository(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Ssh,
+            "ERROR: Repository not found.",
+        );
+        assert!(matches!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::NotARepository(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Http,
+            "unexpected http status code: 404",
+        );
+        assert!(matches!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::NotARepository(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Net,
+            "failed to resolve address for gitlab.example: Name or service not known",
+        );
+        assert!(matches!(
+            RemoteStatus::from_error(err),
+            RemoteStatus::Unreachable(_)
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    AheadBehind, ChangedFile, DiffHunk, DiskUsage, FileRevision, Repository, StatusSummary,
+};
+use crate::core::{DateTime, Exclusions};
+
+/// Blocking git operations that [`NoteRepository`](super::NoteRepository) runs on its
+/// repository, so it can be backed by something other than a local git repository.
+pub trait RepoOps: Debug + Send {
+    fn remotes(&self) -> anyhow::Result<Vec<String>>;
+
+    fn remote_url(&self, name: &str) -> anyhow::Result<Option<String>>;
+
+    /// Point the remote to `url`, keeping the local commits
+    fn set_remote_url(&self, name: &str, url: &str) -> anyhow::Result<()>;
+
+    /// Fetch the branches of the remote, and which of them is its default
+    fn fetch(&self, remote_name: &str) -> anyhow::Result<()>;
+
+    /// Fetch the default branch of the remote only, without changing the working tree
+    fn fetch_default_branch(&self, remote_name: &str) -> anyhow::Result<()>;
+
+    fn ahead_behind(&self, remote_name: &str) -> anyhow::Result<AheadBehind>;
+
+    fn is_file_changed_in_workdir(&self) -> anyhow::Result<bool>;
+
+    fn status_summary(&self) -> anyhow::Result<StatusSummary>;
+
+    fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>>;
+
+    /// Changes of the file at `path` since the last commit, whether they are staged or not
+    fn diff_workdir_to_head(&self, path: &Path) -> anyhow::Result<Vec<DiffHunk>>;
+
+    /// Files that are neither counted as changed nor staged, relative to the repository
+    fn set_exclusions(&mut self, exclusions: Exclusions);
+
+    /// Stage every change in the working directory
+    fn add_all(&self) -> anyhow::Result<()>;
+
+    /// Stage the changes of the files at `paths` only, relative to the repository
+    fn add(&self, paths: &[PathBuf]) -> anyhow::Result<()>;
+
+    /// Leave the files at `paths` out of the next commit, keeping their changes
+    fn unstage(&self, paths: &[PathBuf]) -> anyhow::Result<()>;
+
+    /// Staged files that were moved, from their old to their new path
+    fn staged_renames(&self) -> anyhow::Result<Vec<(PathBuf, PathBuf)>>;
+
+    fn commit(&self, message: &str, author_name: &str, author_email: &str) -> anyhow::Result<()>;
+
+    /// Commit the files at `paths` only, leaving the other changes uncommitted
+    fn commit_paths(
+        &self,
+        paths: &[PathBuf],
+        message: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<()>;
+
+    fn current_branch(&self) -> anyhow::Result<Option<String>>;
+
+    fn switch_to_branch(&self, name: &str) -> anyhow::Result<()>;
+
+    fn default_branch(&self, remote_name: &str) -> String;
+
+    fn pull(
+        &self,
+        remote_name: &str,
+        branch_name: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>>;
+
+    /// Returns the files changed by merging the remote before pushing again
+    fn push_with_retry(
+        &self,
+        remote_name: &str,
+        remote_branch: &str,
+        author_name: &str,
+        author_email: &str,
+        max_retries: u32,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>>;
+
+    /// Commits that changed the file at `path`, newest first
+    fn file_history(&self, path: &Path) -> anyhow::Result<Vec<FileRevision>>;
+
+    fn file_content_at(&self, revision_id: &str, path: &Path) -> anyhow::Result<Vec<u8>>;
+
+    fn disk_usage(&self) -> anyhow::Result<DiskUsage>;
+
+    /// Remove the objects that can't be reached and compress the others, calling `progress`
+    /// with the fraction that is done. Returns how many bytes were reclaimed.
+    fn compact(&self, progress: &mut dyn FnMut(f64)) -> anyhow::Result<u64>;
+
+    /// Replace the commits before `cutoff` with a single one. Returns how many were forgotten.
+    fn forget_history_before(
+        &self,
+        cutoff: DateTime,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<usize>;
+
+    /// Push the current branch to `remote_branch`, replacing the history of the remote
+    fn force_push(&self, remote_name: &str, remote_branch: &str) -> anyhow::Result<()>;
+}
+
+impl RepoOps for Repository {
+    fn remotes(&self) -> anyhow::Result<Vec<String>> {
+        Repository::remotes(self)
+    }
+
+    fn remote_url(&self, name: &str) -> anyhow::Result<Option<String>> {
+        Repository::remote_url(self, name)
+    }
+
+    fn set_remote_url(&self, name: &str, url: &str) -> anyhow::Result<()> {
+        Repository::set_remote_url(self, name, url)
+    }
+
+    fn fetch(&self, remote_name: &str) -> anyhow::Result<()> {
+        Repository::fetch(self, remote_name)?;
+        Repository::update_remote_head(self, remote_name)
+    }
+
+    fn fetch_default_branch(&self, remote_name: &str) -> anyhow::Result<()> {
+        Repository::fetch_default_branch(self, remote_name)
+    }
+
+    fn ahead_behind(&self, remote_name: &str) -> anyhow::Result<AheadBehind> {
+        Repository::ahead_behind(self, remote_name)
+    }
+
+    fn is_file_changed_in_workdir(&self) -> anyhow::Result<bool> {
+        Repository::is_file_changed_in_workdir(self)
+    }
+
+    fn status_summary(&self) -> anyhow::Result<StatusSummary> {
+        Repository::status_summary(self)
+    }
+
+    fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>> {
+        Repository::changed_files(self)
+    }
+
+    fn diff_workdir_to_head(&self, path: &Path) -> anyhow::Result<Vec<DiffHunk>> {
+        Repository::diff_workdir_to_head(self, path)
+    }
+
+    fn set_exclusions(&mut self, exclusions: Exclusions) {
+        Repository::set_exclusions(self, exclusions);
+    }
+
+    fn add_all(&self) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| Repository::add(self, &["."]))
+    }
+
+    fn add(&self, paths: &[PathBuf]) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| Repository::add(self, paths))
+    }
+
+    fn unstage(&self, paths: &[PathBuf]) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| Repository::unstage(self, paths))
+    }
+
+    fn staged_renames(&self) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+        Repository::staged_renames(self)
+    }
+
+    fn commit(&self, message: &str, author_name: &str, author_email: &str) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| Repository::commit(self, message, author_name, author_email))
+    }
+
+    fn commit_paths(
+        &self,
+        paths: &[PathBuf],
+        message: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| {
+            Repository::commit_paths(self, paths, message, author_name, author_email)
+        })
+    }
+
+    fn current_branch(&self) -> anyhow::Result<Option<String>> {
+        Repository::current_branch(self)
+    }
+
+    fn switch_to_branch(&self, name: &str) -> anyhow::Result<()> {
+        self.retry_if_stale_lock(|| Repository::switch_to_branch(self, name))
+    }
+
+    fn default_branch(&self, remote_name: &str) -> String {
+        Repository::default_branch(self, remote_name)
+    }
+
+    fn pull(
+        &self,
+        remote_name: &str,
+        branch_name: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        self.retry_if_stale_lock(|| {
+            Repository::pull(self, remote_name, branch_name, author_name, author_email)
+        })
+    }
+
+    fn push_with_retry(
+        &self,
+        remote_name: &str,
+        remote_branch: &str,
+        author_name: &str,
+        author_email: &str,
+        max_retries: u32,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        self.retry_if_stale_lock(|| {
+            Repository::push_with_retry(
+                self,
+                remote_name,
+                remote_branch,
+                author_name,
+                author_email,
+                max_retries,
+            )
+        })
+    }
+
+    fn file_history(&self, path: &Path) -> anyhow::Result<Vec<FileRevision>> {
+        Repository::file_history(self, path)
+    }
+
+    fn file_content_at(&self, revision_id: &str, path: &Path) -> anyhow::Result<Vec<u8>> {
+        Repository::file_content_at(self, revision_id, path)
+    }
+    fn disk_usage(&self) -> anyhow::Result<DiskUsage> {
+        Repository::disk_usage(self)
+    }
+
+    fn compact(&self, progress: &mut dyn FnMut(f64)) -> anyhow::Result<u64> {
+        Repository::compact(self, progress)
+    }
+
+    fn forget_history_before(
+        &self,
+        cutoff: DateTime,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<usize> {
+        Repository::forget_history_before(self, cutoff, author_name, author_email)
+    }
+
+    fn force_push(&self, remote_name: &str, remote_branch: &str) -> anyhow::Result<()> {
+        Repository::force_push(self, remote_name, remote_branch)
+    }
+}
@@ -1,12 +1,43 @@
 use std::{
+    cell::RefCell,
     fs::{self, File},
     io::Write,
     path::{Path, PathBuf},
 };
 
+use super::{
+    changed_file::merge_changed_files,
+    disk_usage::dir_size,
+    instance_lock::{is_lock_error, InstanceLock},
+    remote_credential, AheadBehind, ChangedFile, CloneCancelled, CloneProgress, DiffHunk,
+    DiskUsage, FileChange, FileRevision, RemoteStatus, RepositoryError, StatusSummary,
+};
+use crate::core::{
+    keyring::{self, Secret},
+    DataFile, DateTime, DiffLine, Exclusions, LineChange, DATA_FILE_NAME,
+};
+
+/// Used when the remote doesn't tell which branch is its default
+pub const DEFAULT_MAIN_BRANCH: &str = "main";
+const DEVICE_BRANCH_PREFIX: &str = "device/";
+const MERGE_COMMIT_MESSAGE: &str = "Custom merge commit";
+const FORGET_HISTORY_COMMIT_MESSAGE: &str = "Forget older history";
+
+/// What was done with a merge that was interrupted before it was committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeRecovery {
+    /// There was no merge in progress
+    None,
+    Completed,
+    Aborted,
+}
+
 pub struct Repository {
     inner: git2::Repository,
     base_path: PathBuf,
+    instance_lock: InstanceLock,
+    /// Files that are neither counted as changed nor staged
+    exclusions: Exclusions,
 }
 
 impl std::fmt::Debug for Repository {
@@ -24,46 +55,115 @@ impl Repository {
 
         let repo = git2::Repository::init_opts(base_path.as_ref(), &init_options)?;
 
-        Ok(Self {
-            inner: repo,
-            base_path: base_path.as_ref().to_owned(),
-        })
+        Ok(Self::new(repo, base_path.as_ref()))
     }
 
     pub fn clone(base_path: impl AsRef<Path>, remote_url: &str) -> anyhow::Result<Self> {
+        Self::clone_with_progress(base_path, remote_url, |_| {}, || false)
+    }
+
+    /// Clone, calling `progress` as objects are received and files are checked out.
+    ///
+    /// Fails with [`CloneCancelled`] once `is_cancelled` returns true, after removing what
+    /// was cloned. The transfer is stopped right away, the checkout only once it is done.
+    pub fn clone_with_progress(
+        base_path: impl AsRef<Path>,
+        remote_url: &str,
+        progress: impl Fn(CloneProgress),
+        is_cancelled: impl Fn() -> bool,
+    ) -> anyhow::Result<Self> {
+        let base_path = base_path.as_ref();
+
+        // Cloning fails on a folder with files, which must then be left as they are
+        let is_new_folder = fs::read_dir(base_path).map_or(true, |mut dir| dir.next().is_none());
+
         let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|_, username_from_url, _| Self::credentials_cb(username_from_url));
-        callbacks.transfer_progress(|ref progress| Self::transfer_progress_cb(progress));
+        callbacks.credentials(Self::credentials_cb());
+        callbacks.transfer_progress(|transfer| {
+            Self::transfer_progress_cb(&transfer);
+            progress(CloneProgress::of_transfer(
+                transfer.received_objects(),
+                transfer.total_objects(),
+                transfer.indexed_deltas(),
+                transfer.total_deltas(),
+                transfer.received_bytes(),
+            ));
+            !is_cancelled()
+        });
 
         let mut fetch_options = git2::FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
 
+        let mut checkout_builder = git2::build::CheckoutBuilder::new();
+        checkout_builder.progress(|_, completed_files, total_files| {
+            progress(CloneProgress::CheckingOut {
+                completed_files,
+                total_files,
+            });
+        });
+
         let mut repo_builder = git2::build::RepoBuilder::new();
-        repo_builder.fetch_options(fetch_options);
+        repo_builder
+            .fetch_options(fetch_options)
+            .with_checkout(checkout_builder);
 
         log::info!("Cloning from `{}` ...", remote_url);
-        let repo = repo_builder.clone(remote_url, base_path.as_ref())?;
+        let res = repo_builder.clone(remote_url, base_path);
+
+        if is_cancelled() {
+            drop(res);
+
+            if is_new_folder {
+                if let Err(err) = fs::remove_dir_all(base_path) {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        log::error!(
+                            "Failed to remove cancelled clone at `{}`: {:?}",
+                            base_path.display(),
+                            err
+                        );
+                    }
+                }
+            }
 
-        Ok(Self {
-            inner: repo,
-            base_path: base_path.as_ref().to_owned(),
-        })
+            log::info!("Cancelled cloning from `{}`", remote_url);
+            return Err(CloneCancelled.into());
+        }
+
+        let repo = res.map_err(RepositoryError::from)?;
+
+        Ok(Self::new(repo, base_path))
     }
 
     pub fn open(base_path: &Path) -> anyhow::Result<Self> {
         log::info!("Opening repo from `{}`", base_path.display());
         let repo = git2::Repository::open(base_path)?;
 
-        Ok(Self {
+        Ok(Self::new(repo, base_path))
+    }
+
+    fn new(repo: git2::Repository, base_path: &Path) -> Self {
+        let instance_lock = InstanceLock::acquire(repo.path());
+
+        Self {
             inner: repo,
             base_path: base_path.to_owned(),
-        })
+            instance_lock,
+            exclusions: Exclusions::default(),
+        }
     }
 
     pub fn base_path(&self) -> &Path {
         self.base_path.as_path()
     }
 
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclusions.is_excluded(path, false)
+    }
+
     pub fn remotes(&self) -> anyhow::Result<Vec<String>> {
         let repo = self.inner();
         let remotes = repo.remotes()?;
@@ -76,6 +176,138 @@ impl Repository {
             .collect())
     }
 
+    /// URL of the remote named `name` as set in the git config, or `None` if there is no such
+    /// remote
+    pub fn remote_url(&self, name: &str) -> anyhow::Result<Option<String>> {
+        match self.inner().find_remote(name) {
+            Ok(remote) => Ok(remote.url().map(str::to_string)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Point the remote named `name` to `url`, adding it if there is none yet.
+    ///
+    /// Local branches and commits are kept. The remote-tracking branches are removed, as they
+    /// are of the old remote, until the next fetch brings the ones of the new one.
+    pub fn set_remote_url(&self, name: &str, url: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        match self.remote_url(name)? {
+            Some(old_url) if old_url == url => return Ok(()),
+            Some(_) => repo.remote_set_url(name, url)?,
+            None => {
+                repo.remote(name, url)?;
+            }
+        }
+
+        for reference in repo.references_glob(&format!("refs/remotes/{}/*", name))? {
+            reference?.delete()?;
+        }
+
+        log::info!("Set URL of remote `{}` to `{}`", name, url);
+
+        Ok(())
+    }
+
+    /// Whether the repository at `remote_url` can be read, by listing its references without
+    /// fetching anything
+    pub fn check_remote(remote_url: &str) -> RemoteStatus {
+        let res = git2::Remote::create_detached(remote_url).and_then(|mut remote| {
+            let connection = remote.connect_auth(
+                git2::Direction::Fetch,
+                Some(Self::remote_callbacks()),
+                None,
+            )?;
+            Ok(connection.list()?.len())
+        });
+
+        match res {
+            Ok(n_references) => {
+                log::info!(
+                    "Remote `{}` is reachable with {} references",
+                    remote_url,
+                    n_references
+                );
+                RemoteStatus::Reachable
+            }
+            Err(err) => {
+                log::warn!("Failed to check remote `{}`: {:?}", remote_url, err);
+                RemoteStatus::from_error(err)
+            }
+        }
+    }
+
+    /// Point `refs/remotes/<name>/HEAD` to the default branch of the remote, which is where
+    /// [`Repository::default_branch`] reads it from. Nothing is changed if the remote has no
+    /// branches yet.
+    pub fn update_remote_head(&self, name: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let mut remote = repo.find_remote(name)?;
+        let default_branch = {
+            let connection = remote
+                .connect_auth(git2::Direction::Fetch, Some(Self::remote_callbacks()), None)
+                .map_err(RepositoryError::from)?;
+
+            match connection.default_branch() {
+                Ok(default_branch) => default_branch.as_str().map(str::to_string),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let branch_name = match default_branch
+            .as_deref()
+            .and_then(|reference| reference.strip_prefix("refs/heads/"))
+        {
+            Some(branch_name) => branch_name,
+            None => {
+                log::info!("Remote `{}` has no default branch", name);
+                return Ok(());
+            }
+        };
+
+        repo.reference_symbolic(
+            &format!("refs/remotes/{}/HEAD", name),
+            &format!("refs/remotes/{}/{}", name, branch_name),
+            true,
+            "Update default branch of remote",
+        )?;
+
+        log::info!("Default branch of remote `{}` is `{}`", name, branch_name);
+
+        Ok(())
+    }
+
+    /// Commits of HEAD that are not on the default branch of the remote named `remote_name`,
+    /// and the other way around. Every commit counts when either side has none.
+    pub fn ahead_behind(&self, remote_name: &str) -> anyhow::Result<AheadBehind> {
+        let repo = self.inner();
+
+        let local_id = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?.id()),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let remote_branch = format!("{}/{}", remote_name, self.default_branch(remote_name));
+        let remote_id = match repo.find_branch(&remote_branch, git2::BranchType::Remote) {
+            Ok(branch) => Some(branch.get().peel_to_commit()?.id()),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let (n_ahead, n_behind) = match (local_id, remote_id) {
+            (Some(local_id), Some(remote_id)) => repo.graph_ahead_behind(local_id, remote_id)?,
+            (Some(local_id), None) => (self.n_commits(local_id)?, 0),
+            (None, Some(remote_id)) => (0, self.n_commits(remote_id)?),
+            (None, None) => (0, 0),
+        };
+
+        Ok(AheadBehind { n_ahead, n_behind })
+    }
+
     pub fn diff_tree_to_tree(
         &self,
         old_tree: &git2::Tree,
@@ -109,24 +341,218 @@ impl Repository {
     pub fn is_file_changed_in_workdir(&self) -> anyhow::Result<bool> {
         let repo = self.inner();
 
+        // Recursing, so the files of untracked directories can be excluded one by one
         let mut diff_options = git2::DiffOptions::new();
-        diff_options.include_untracked(true);
+        diff_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true);
 
         let diff = repo.diff_index_to_workdir(None, Some(&mut diff_options))?;
-        let diff_stats = diff.stats()?;
-        Ok(diff_stats.files_changed() > 0)
+        let is_changed = diff.deltas().any(|delta| {
+            [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+                .any(|path| !self.is_excluded(path))
+        });
+        Ok(is_changed)
+    }
+
+    /// Files changed since the last commit, whether they are staged or not. A file that is
+    /// both staged and changed again counts once.
+    pub fn status_summary(&self) -> anyhow::Result<StatusSummary> {
+        Ok(StatusSummary::from_changed_files(&self.changed_files()?))
+    }
+
+    /// Files that differ from the last commit, whether their changes are staged or not,
+    /// with their paths relative to the repository
+    pub fn changed_files(&self) -> anyhow::Result<Vec<ChangedFile>> {
+        let repo = self.inner();
+
+        let mut status_options = git2::StatusOptions::new();
+        status_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+
+        let mut changed_files = Vec::new();
+
+        for entry in repo.statuses(Some(&mut status_options))?.iter() {
+            let status = entry.status();
+
+            let change = if status.intersects(git2::Status::INDEX_NEW | git2::Status::WT_NEW) {
+                FileChange::New
+            } else if status.intersects(git2::Status::INDEX_DELETED | git2::Status::WT_DELETED) {
+                FileChange::Deleted
+            } else if status.intersects(
+                git2::Status::INDEX_MODIFIED
+                    | git2::Status::WT_MODIFIED
+                    | git2::Status::INDEX_RENAMED
+                    | git2::Status::WT_RENAMED
+                    | git2::Status::INDEX_TYPECHANGE
+                    | git2::Status::WT_TYPECHANGE,
+            ) {
+                FileChange::Modified
+            } else {
+                continue;
+            };
+
+            match entry.path() {
+                Some(path) if self.is_excluded(Path::new(path)) => {}
+                Some(path) => changed_files.push(ChangedFile {
+                    path: PathBuf::from(path),
+                    change,
+                }),
+                None => log::warn!("Skipped changed file with a path that is not UTF-8"),
+            }
+        }
+
+        Ok(changed_files)
+    }
+
+    /// Lines of the file at `path`, relative to the repository, that differ between the last
+    /// commit and the working directory, with the lines around them. The file may be new or
+    /// deleted.
+    pub fn diff_workdir_to_head(&self, path: &Path) -> anyhow::Result<Vec<DiffHunk>> {
+        let repo = self.inner();
+
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(err) => {
+                log::info!("Diffing against nothing, HEAD has no commit: {:?}", err);
+                None
+            }
+        };
+
+        let mut diff_options = git2::DiffOptions::new();
+        diff_options
+            .pathspec(path)
+            .disable_pathspec_match(true)
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+
+        let diff = repo.diff_tree_to_workdir(head_tree.as_ref(), Some(&mut diff_options))?;
+
+        // Both callbacks add to the hunks, the hunk callback a new one and the line callback
+        // the lines of the last one
+        let hunks = RefCell::new(Vec::<DiffHunk>::new());
+        diff.foreach(
+            &mut |_, _| true,
+            None,
+            Some(&mut |_, hunk| {
+                hunks.borrow_mut().push(DiffHunk {
+                    old_start: hunk.old_start(),
+                    new_start: hunk.new_start(),
+                    lines: Vec::new(),
+                });
+                true
+            }),
+            Some(&mut |_, _, line| {
+                let change = match line.origin_value() {
+                    git2::DiffLineType::Context => LineChange::Unchanged,
+                    git2::DiffLineType::Addition => LineChange::Added,
+                    git2::DiffLineType::Deletion => LineChange::Removed,
+                    // Like the markers of a missing line break at the end of the file
+                    _ => return true,
+                };
+                let text = String::from_utf8_lossy(line.content());
+
+                if let Some(hunk) = hunks.borrow_mut().last_mut() {
+                    hunk.lines.push(DiffLine {
+                        change,
+                        text: text.strip_suffix('\n').unwrap_or(&text).to_string(),
+                    });
+                }
+                true
+            }),
+        )?;
+
+        Ok(hunks.into_inner())
     }
 
-    pub fn is_same(&self, spec_a: &str, spec_b: &str) -> anyhow::Result<bool> {
+    /// Whether `spec_a` is the same as `spec_b` or already contains it in its history
+    pub fn contains(&self, spec_a: &str, spec_b: &str) -> anyhow::Result<bool> {
         let repo = self.inner();
 
         let object_a_id = repo.revparse_single(spec_a)?.id();
         let object_b_id = repo.revparse_single(spec_b)?.id();
 
-        log::info!("Revparse spec_a: `{}` <-> `{}`", spec_a, object_a_id);
-        log::info!("Revparse spec_a: `{}` <-> `{}`", spec_b, object_b_id);
+        Ok(object_a_id == object_b_id || repo.graph_descendant_of(object_a_id, object_b_id)?)
+    }
+
+    /// Name of the branch HEAD points to, or `None` if HEAD is detached or has no commits yet.
+    pub fn current_branch(&self) -> anyhow::Result<Option<String>> {
+        let repo = self.inner();
+
+        match repo.head() {
+            Ok(head) if head.is_branch() => Ok(head.shorthand().map(str::to_string)),
+            Ok(_) => Ok(None),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Create a local branch named `name` pointing to the commit of HEAD
+    pub fn create_branch(&self, name: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let head_commit = repo.head()?.peel_to_commit()?;
+        repo.branch(name, &head_commit, false)?;
+
+        log::info!("Created branch `{}` at `{}`", name, head_commit.id());
+
+        Ok(())
+    }
+
+    /// Checkout the local branch named `name`, keeping the changes in the working directory
+    pub fn checkout(&self, name: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let ref_name = format!("refs/heads/{}", name);
+        let object = repo.revparse_single(&ref_name)?;
+
+        let mut checkout_builder = git2::build::CheckoutBuilder::new();
+        checkout_builder.safe();
+
+        repo.checkout_tree(&object, Some(&mut checkout_builder))?;
+        repo.set_head(&ref_name)?;
+
+        log::info!("Checked out branch `{}`", name);
+
+        Ok(())
+    }
+
+    /// Checkout the local branch named `name`, creating it first from HEAD if it doesn't exist
+    pub fn switch_to_branch(&self, name: &str) -> anyhow::Result<()> {
+        if self.current_branch()?.as_deref() == Some(name) {
+            return Ok(());
+        }
+
+        if self
+            .inner()
+            .find_branch(name, git2::BranchType::Local)
+            .is_err()
+        {
+            self.create_branch(name)?;
+        }
+
+        self.checkout(name)
+    }
 
-        Ok(object_a_id == object_b_id)
+    /// Default branch of the remote (e.g. `main`), based on `refs/remotes/<remote>/HEAD`
+    pub fn default_branch(&self, remote_name: &str) -> String {
+        let prefix = format!("refs/remotes/{}/", remote_name);
+
+        self.inner()
+            .find_reference(&format!("{}HEAD", prefix))
+            .ok()
+            .and_then(|reference| {
+                reference
+                    .symbolic_target()
+                    .and_then(|target| target.strip_prefix(&prefix))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| DEFAULT_MAIN_BRANCH.to_string())
     }
 
     pub fn fetch(&self, remote_name: &str) -> anyhow::Result<()> {
@@ -134,37 +560,94 @@ impl Repository {
 
         let mut remote = repo.find_remote(remote_name)?;
 
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|_, username_from_url, _| Self::credentials_cb(username_from_url));
-        callbacks.transfer_progress(|ref progress| Self::transfer_progress_cb(progress));
-
         let mut fetch_options = git2::FetchOptions::new();
-        fetch_options.remote_callbacks(callbacks);
+        fetch_options.remote_callbacks(Self::remote_callbacks());
 
         log::info!("Fetching from `{}`...", remote_name);
-        remote.fetch::<&str>(&[], Some(&mut fetch_options), None)?;
+        remote
+            .fetch::<&str>(&[], Some(&mut fetch_options), None)
+            .map_err(RepositoryError::from)?;
+
+        Ok(())
+    }
+
+    /// Fetch only the default branch of the remote named `remote_name`, without its tags, which
+    /// is enough to compare it with HEAD. The working tree and the local branches are left as
+    /// they are.
+    pub fn fetch_default_branch(&self, remote_name: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let mut remote = repo.find_remote(remote_name)?;
+        let branch_name = self.default_branch(remote_name);
+        let refspec = format!(
+            "+refs/heads/{0}:refs/remotes/{1}/{0}",
+            branch_name, remote_name
+        );
+
+        let mut fetch_options = git2::FetchOptions::new();
+        fetch_options.remote_callbacks(Self::remote_callbacks());
+        fetch_options.download_tags(git2::AutotagOption::None);
+
+        log::info!("Fetching `{}` from `{}`...", branch_name, remote_name);
+        remote
+            .fetch(&[refspec.as_str()], Some(&mut fetch_options), None)
+            .map_err(RepositoryError::from)?;
 
         Ok(())
     }
 
+    /// Stage the files at `paths`, including the ones that were deleted from the working
+    /// directory, but not the excluded ones
     pub fn add(&self, paths: &[impl AsRef<Path>]) -> anyhow::Result<()> {
         let repo = self.inner();
 
         let mut index = repo.index()?;
 
+        // A non-zero return skips the path
         index.add_all(
             paths.iter().map(|p| p.as_ref()),
             git2::IndexAddOption::DEFAULT,
             Some(&mut |path: &Path, _: &[u8]| {
+                if self.is_excluded(path) {
+                    log::info!("Skipped excluded `{}`", path.display());
+                    return 1;
+                }
+
                 log::info!("Added match `{}`", path.display());
                 0
             }),
         )?;
+        index.update_all(
+            paths.iter().map(|p| p.as_ref()),
+            Some(&mut |path: &Path, _: &[u8]| i32::from(self.is_excluded(path))),
+        )?;
         index.write()?;
 
         Ok(())
     }
 
+    /// Reset the files at `paths` in the index to how they are in the last commit, so the
+    /// changes made to them are left out of the next one. They are kept in the working
+    /// directory.
+    pub fn unstage(&self, paths: &[impl AsRef<Path>]) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let head_commit = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(err) => {
+                log::info!("Unstaging from empty index, HEAD has no commit: {:?}", err);
+                None
+            }
+        };
+
+        repo.reset_default(
+            head_commit.as_ref().map(|commit| commit.as_object()),
+            paths.iter().map(|p| p.as_ref()),
+        )?;
+
+        Ok(())
+    }
+
     pub fn remove(&self, paths: &[impl AsRef<Path>]) -> anyhow::Result<()> {
         let repo = self.inner();
 
@@ -232,7 +715,11 @@ impl Repository {
 
                 let current_conflict_path = std::str::from_utf8(&their.path).unwrap();
                 log::info!("Pull: Conflict on file `{}`", current_conflict_path);
-                self.resolve_conflict(&our)?;
+                if current_conflict_path == DATA_FILE_NAME {
+                    self.resolve_data_file_conflict(&our, &their)?;
+                } else {
+                    self.resolve_conflict(&our)?;
+                }
                 log::info!("Resolved conflict on file `{}`", current_conflict_path);
 
                 let path = std::str::from_utf8(&our.path).unwrap();
@@ -252,20 +739,114 @@ impl Repository {
             let origin_head_commit = repo.find_commit(annotated_commit.id())?;
 
             let parents = [&head_commit, &origin_head_commit];
-            let message = "Custom merge commit";
             repo.commit(
                 Some("HEAD"),
                 &signature,
                 &signature,
-                message,
+                MERGE_COMMIT_MESSAGE,
                 &tree,
                 &parents,
             )?;
+            repo.cleanup_state()?;
         }
 
         Ok(())
     }
 
+    /// Finish a merge that was interrupted, like when the app was killed while pulling. It is
+    /// committed when it has no conflicts left, otherwise the files it changed are put back as
+    /// they were before it, keeping the other changes in the working directory.
+    pub fn recover_interrupted_merge(
+        &self,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<MergeRecovery> {
+        let repo = self.inner();
+
+        if repo.state() != git2::RepositoryState::Merge {
+            return Ok(MergeRecovery::None);
+        }
+
+        let mut index = repo.index()?;
+        let head_commit = repo.head()?.peel_to_commit()?;
+
+        if !index.has_conflicts() {
+            let merge_head = fs::read_to_string(repo.path().join("MERGE_HEAD"))?;
+            let mut parents = vec![head_commit];
+            for line in merge_head.lines().filter(|line| !line.trim().is_empty()) {
+                parents.push(repo.find_commit(git2::Oid::from_str(line.trim())?)?);
+            }
+
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let signature = git2::Signature::now(author_name, author_email)?;
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                MERGE_COMMIT_MESSAGE,
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )?;
+            repo.cleanup_state()?;
+
+            log::warn!("Completed interrupted merge");
+            return Ok(MergeRecovery::Completed);
+        }
+
+        // The files that the merge changed, which have its changes or conflict markers
+        let mut merged_paths = index
+            .conflicts()?
+            .filter_map(|conflict| {
+                let conflict = conflict.ok()?;
+                let entry = conflict.our.or(conflict.their).or(conflict.ancestor)?;
+                Some(String::from_utf8_lossy(&entry.path).to_string())
+            })
+            .collect::<Vec<_>>();
+        let diff = repo.diff_tree_to_index(Some(&head_commit.tree()?), Some(&index), None)?;
+        merged_paths.extend(diff.deltas().filter_map(|delta| {
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())?;
+            Some(path.to_string_lossy().to_string())
+        }));
+
+        repo.reset(head_commit.as_object(), git2::ResetType::Mixed, None)?;
+
+        let mut checkout_builder = git2::build::CheckoutBuilder::new();
+        checkout_builder.force().remove_untracked(true);
+        for path in &merged_paths {
+            checkout_builder.path(path);
+        }
+        repo.checkout_head(Some(&mut checkout_builder))?;
+        repo.cleanup_state()?;
+
+        log::warn!(
+            "Aborted interrupted merge, restoring {} files",
+            merged_paths.len()
+        );
+        Ok(MergeRecovery::Aborted)
+    }
+
+    /// Run `operation`, then run it once more if it failed on an index lock that was left by
+    /// an instance that stopped in the middle of an operation, after removing it
+    pub fn retry_if_stale_lock<T>(
+        &self,
+        operation: impl Fn() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        match operation() {
+            Err(err) if is_lock_error(&err) => {
+                if !self.instance_lock.remove_stale_index_lock()? {
+                    return Err(err);
+                }
+
+                log::info!("Retrying after removing stale index lock");
+                operation()
+            }
+            res => res,
+        }
+    }
+
     pub fn commit(
         &self,
         message: &str,
@@ -302,72 +883,561 @@ impl Repository {
         Ok(())
     }
 
-    pub fn push(&self, remote_name: &str) -> anyhow::Result<()> {
+    /// Commit the files at `paths` only, relative to the repository, as they are in the
+    /// working directory. The other changes stay as they are, staged or not.
+    pub fn commit_paths(
+        &self,
+        paths: &[impl AsRef<Path>],
+        message: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<()> {
         let repo = self.inner();
 
-        let mut remote = repo.find_remote(remote_name)?;
-        let ref_head = repo.head()?;
-        let ref_head_name = ref_head
-            .name()
-            .ok_or_else(|| anyhow::anyhow!("Ref head name not found"))?;
-
-        let head_type = ref_head.kind();
+        let parent_commit = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(err) => {
+                log::info!("Committing on empty HEAD: {:?}", err);
+                None
+            }
+        };
+        let base_tree = match parent_commit {
+            Some(ref commit) => commit.tree()?,
+            None => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+        };
 
-        anyhow::ensure!(
-            head_type == Some(git2::ReferenceType::Direct),
-            "Head is not a direct reference"
-        );
+        let mut index = repo.index()?;
+        let mut tree_update = git2::build::TreeUpdateBuilder::new();
+        for path in paths {
+            let path = path.as_ref();
+
+            if self.base_path().join(path).exists() {
+                let blob_id = repo.blob_path(&self.base_path().join(path))?;
+                tree_update.upsert(path, blob_id, git2::FileMode::Blob);
+                index.add_path(path)?;
+            } else {
+                tree_update.remove(path);
+                if index.get_path(path, 0).is_some() {
+                    index.remove_path(path)?;
+                }
+            }
+        }
+        index.write()?;
 
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(|_, username_from_url, _| Self::credentials_cb(username_from_url));
-        callbacks.transfer_progress(|ref progress| Self::transfer_progress_cb(progress));
+        let tree_id = tree_update.create_updated(repo, &base_tree)?;
+        let tree = repo.find_tree(tree_id)?;
 
-        let mut push_options = git2::PushOptions::new();
-        push_options.remote_callbacks(callbacks);
+        let signature = git2::Signature::now(author_name, author_email)?;
 
-        log::info!("Pushing to `{}` ...", remote_name);
-        remote.push(&[ref_head_name], Some(&mut push_options))?;
+        log::info!("Creating commit of {} files...", paths.len());
+        let parents = parent_commit.iter().collect::<Vec<_>>();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?;
 
         Ok(())
     }
 
-    pub fn pull(
-        &self,
-        remote_name: &str,
-        author_name: &str,
-        author_email: &str,
-    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+    /// Files that are staged as moved since the last commit, from their old to their new
+    /// path relative to the repository. Git only stores a deletion and an addition, so they
+    /// are paired by the similarity of their contents, like `git status` does.
+    pub fn staged_renames(&self) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
         let repo = self.inner();
 
-        self.fetch(remote_name)?;
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(err) => {
+                log::info!("No renames, HEAD has no commit: {:?}", err);
+                None
+            }
+        };
 
-        let head = repo.find_reference("HEAD")?;
-        let old_tree = head.peel_to_tree()?;
+        let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+        diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
 
-        let fetch_head = repo.find_reference("FETCH_HEAD")?;
-        let new_tree = fetch_head.peel_to_tree()?;
+        Ok(Self::renames(&diff))
+    }
 
-        let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
+    /// Commits of the current branch that changed the file at `path`, relative to the
+    /// repository, newest first. Merges are only included when they changed it compared to
+    /// every parent. Renames are followed, so the commits from before the file was moved to
+    /// `path` are included too.
+    pub fn file_history(&self, path: &Path) -> anyhow::Result<Vec<FileRevision>> {
+        let repo = self.inner();
 
-        // TODO better way to get this?
-        let head = repo.head()?;
-        let branch_name = head
-            .name()
-            .ok_or_else(|| anyhow::anyhow!("Ref head name not found"))?;
-        let source_branch = format!("{}/{}", remote_name, branch_name);
+        let mut revwalk = repo.revwalk()?;
+        if let Err(err) = revwalk.push_head() {
+            log::info!("No history, HEAD has no commit: {:?}", err);
+            return Ok(Vec::new());
+        }
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        let mut revisions = Vec::new();
+        // Where the file was in the commits walked so far
+        let mut path = path.to_path_buf();
+
+        for commit_id in revwalk {
+            let commit = repo.find_commit(commit_id?)?;
+
+            // Deleted in this commit, or not created yet
+            let blob_id = match Self::blob_id_at(&commit, &path)? {
+                Some(blob_id) => blob_id,
+                None => continue,
+            };
+
+            let mut is_changed = true;
+            let mut is_in_parent = false;
+            for parent in commit.parents() {
+                let parent_blob_id = Self::blob_id_at(&parent, &path)?;
+                is_in_parent |= parent_blob_id.is_some();
+
+                if parent_blob_id == Some(blob_id) {
+                    is_changed = false;
+                    break;
+                }
+            }
 
-        self.merge(
-            &source_branch,
-            Some(fetch_commit),
-            author_name,
-            author_email,
-        )?;
+            if is_changed {
+                revisions.push(FileRevision {
+                    id: commit.id().to_string(),
+                    time: DateTime::from_timestamp(commit.time().seconds()),
+                    summary: commit.summary().unwrap_or_default().to_string(),
+                    path: path.clone(),
+                });
+            }
 
-        let changed_files = self.diff_tree_to_tree(&old_tree, &new_tree)?;
-        Ok(changed_files)
+            if !is_in_parent {
+                if let Some(previous_path) = self.renamed_from(&commit, &path)? {
+                    log::info!(
+                        "History: `{}` was renamed from `{}`",
+                        path.display(),
+                        previous_path.display()
+                    );
+                    path = previous_path;
+                }
+            }
+        }
+
+        Ok(revisions)
     }
 
-    fn perform_fastforward(&self, target_oid: git2::Oid) -> anyhow::Result<()> {
+    /// Contents of the file at `path`, relative to the repository, in the commit with
+    /// `revision_id`
+    pub fn file_content_at(&self, revision_id: &str, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let repo = self.inner();
+
+        let commit = repo.find_commit(git2::Oid::from_str(revision_id)?)?;
+        let entry = commit.tree()?.get_path(path)?;
+        let blob = repo.find_blob(entry.id())?;
+
+        Ok(blob.content().to_vec())
+    }
+
+    /// Push with an explicit `refspec` (e.g. `refs/heads/device/laptop:refs/heads/main`).
+    ///
+    /// Fails with [`RepositoryError::NonFastForward`] if the remote has commits that are
+    /// not yet merged locally.
+    pub fn push(&self, remote_name: &str, refspec: &str) -> anyhow::Result<()> {
+        let repo = self.inner();
+
+        let mut remote = repo.find_remote(remote_name)?;
+
+        let rejected_status = RefCell::new(None);
+
+        {
+            let mut callbacks = Self::remote_callbacks();
+            callbacks.push_update_reference(|ref_name, status| {
+                if let Some(status) = status {
+                    log::warn!("Remote rejected update of `{}`: {}", ref_name, status);
+                    rejected_status.replace(Some(status.to_string()));
+                }
+                Ok(())
+            });
+
+            let mut push_options = git2::PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+
+            log::info!("Pushing `{}` to `{}` ...", refspec, remote_name);
+            remote
+                .push(&[refspec], Some(&mut push_options))
+                .map_err(RepositoryError::from)?;
+        }
+
+        if let Some(status) = rejected_status.into_inner() {
+            return Err(RepositoryError::for_rejected_push(&status).into());
+        }
+
+        Ok(())
+    }
+
+    /// Push the current branch to `remote_branch` on the remote. When the remote has moved,
+    /// fetch and merge it then try again, at most `max_retries` times.
+    ///
+    /// Returns the files changed by the merges, like [`Self::pull`], with each file listed
+    /// once.
+    pub fn push_with_retry(
+        &self,
+        remote_name: &str,
+        remote_branch: &str,
+        author_name: &str,
+        author_email: &str,
+        max_retries: u32,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        let ref_head = self.inner().head()?;
+
+        anyhow::ensure!(ref_head.is_branch(), "Head is not a branch");
+
+        let ref_head_name = ref_head
+            .name()
+            .ok_or_else(|| anyhow::anyhow!("Ref head name not found"))?;
+        let refspec = format!("{}:refs/heads/{}", ref_head_name, remote_branch);
+
+        let mut n_retries = 0;
+        let mut changed_files = Vec::new();
+
+        loop {
+            match self.push(remote_name, &refspec) {
+                Ok(()) => return Ok(changed_files),
+                Err(err) => {
+                    let is_non_fast_forward = matches!(
+                        err.downcast_ref::<RepositoryError>(),
+                        Some(RepositoryError::NonFastForward)
+                    );
+
+                    if !is_non_fast_forward || n_retries >= max_retries {
+                        return Err(err);
+                    }
+
+                    n_retries += 1;
+                    log::info!(
+                        "Remote moved, merging it before pushing again ({}/{})",
+                        n_retries,
+                        max_retries
+                    );
+
+                    let merged_files =
+                        self.pull(remote_name, remote_branch, author_name, author_email)?;
+                    merge_changed_files(&mut changed_files, merged_files);
+                }
+            }
+        }
+    }
+
+    /// Size of the working tree and of the `.git` directory
+    pub fn disk_usage(&self) -> anyhow::Result<DiskUsage> {
+        let mut working_tree = 0;
+
+        for entry in fs::read_dir(self.base_path())? {
+            let entry = entry?;
+
+            if entry.file_name() == ".git" {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            working_tree += if metadata.is_dir() {
+                dir_size(&entry.path())?
+            } else {
+                metadata.len()
+            };
+        }
+
+        Ok(DiskUsage {
+            working_tree,
+            git_dir: dir_size(self.inner().path())?,
+        })
+    }
+
+    /// Pack the objects that can still be reached into a single pack, then remove the other
+    /// packs and the loose objects, like `git gc --aggressive --prune=now`. `progress` is
+    /// called with the fraction of the objects that are compressed.
+    ///
+    /// Objects are kept if they can be reached from a reference, its reflog, or the index.
+    /// Returns how many bytes were reclaimed.
+    pub fn compact(&self, mut progress: impl FnMut(f64)) -> anyhow::Result<u64> {
+        let repo = self.inner();
+
+        anyhow::ensure!(
+            repo.state() == git2::RepositoryState::Clean,
+            "Repository is in the middle of an operation"
+        );
+
+        let size_before = dir_size(repo.path())?;
+
+        let mut packbuilder = repo.packbuilder()?;
+        let mut revwalk = repo.revwalk()?;
+
+        for reference in repo.references()? {
+            let reference = reference?;
+
+            // Symbolic references like `HEAD` are reached through the ones they point to
+            if let Some(target) = reference.target() {
+                match repo.find_commit(target) {
+                    Ok(_) => revwalk.push(target)?,
+                    Err(_) => packbuilder.insert_recursive(target, None)?,
+                }
+            }
+
+            let name = match reference.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            if let Ok(reflog) = repo.reflog(name) {
+                for entry in reflog.iter() {
+                    if repo.find_commit(entry.id_new()).is_ok() {
+                        revwalk.push(entry.id_new())?;
+                    }
+                }
+            }
+        }
+
+        // Detached, so no reference points to it
+        if let Ok(head) = repo.head() {
+            if let Ok(head_commit) = head.peel_to_commit() {
+                revwalk.push(head_commit.id())?;
+            }
+        }
+
+        packbuilder.insert_walk(&mut revwalk)?;
+
+        // Staged but not committed yet
+        let odb = repo.odb()?;
+        for entry in repo.index()?.iter() {
+            if odb.exists(entry.id) {
+                packbuilder.insert_object(entry.id, None)?;
+            }
+        }
+
+        if packbuilder.object_count() == 0 {
+            log::info!("No objects to pack");
+            return Ok(0);
+        }
+
+        packbuilder.set_progress_callback(|stage, current, total| {
+            if stage == git2::PackBuilderStage::Deltafication && total > 0 {
+                progress(current as f64 / total as f64);
+            }
+            true
+        })?;
+
+        log::info!("Packing {} objects...", packbuilder.object_count());
+
+        let pack_dir = repo.path().join("objects").join("pack");
+        let mut packwriter = odb.packwriter()?;
+        // The trailer of a pack is its checksum, which it is named after
+        let mut trailer = Vec::new();
+        packbuilder.foreach(|chunk| {
+            trailer.extend_from_slice(chunk);
+            let trailer_start = trailer.len().saturating_sub(20);
+            trailer.drain(..trailer_start);
+
+            packwriter.write_all(chunk).is_ok()
+        })?;
+        packwriter.commit()?;
+        drop(packbuilder);
+
+        let pack_name = format!("pack-{}", git2::Oid::from_bytes(&trailer)?);
+        anyhow::ensure!(
+            pack_dir.join(&pack_name).with_extension("pack").exists(),
+            "Written pack `{}` not found",
+            pack_name
+        );
+
+        for entry in fs::read_dir(&pack_dir)? {
+            let path = entry?.path();
+
+            if path.file_stem().and_then(|stem| stem.to_str()) != Some(&pack_name) {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        for entry in fs::read_dir(repo.path().join("objects"))? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+
+            // Loose objects are in directories named after the first 2 digits of their id
+            if file_name.len() == 2 && file_name.chars().all(|c| c.is_ascii_hexdigit()) {
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+
+        odb.refresh()?;
+
+        let size_after = dir_size(repo.path())?;
+        log::info!(
+            "Compacted repository from {} to {} bytes",
+            size_before,
+            size_after
+        );
+
+        Ok(size_before.saturating_sub(size_after))
+    }
+
+    /// Forget the commits of the current branch made before `cutoff`, replacing them with a
+    /// single commit of the notes as they were then. The later commits are made again on top
+    /// of it, with the same notes and messages, and the other local branches are moved to the
+    /// new history. The forgotten commits stay on the disk until the repository is compacted.
+    ///
+    /// Returns how many commits were forgotten.
+    pub fn forget_history_before(
+        &self,
+        cutoff: DateTime,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<usize> {
+        let repo = self.inner();
+
+        let head = repo.head()?;
+        anyhow::ensure!(head.is_branch(), "Head is not a branch");
+        let head_name = head
+            .name()
+            .ok_or_else(|| anyhow::anyhow!("Ref head name not found"))?
+            .to_string();
+
+        let cutoff = cutoff.to_local().timestamp();
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head.peel_to_commit()?.id())?;
+        revwalk.simplify_first_parent()?;
+
+        // Merged branches were already merged into the first parents, which have all of
+        // their notes
+        let mut kept_commits = Vec::new();
+        let mut base_commit = None;
+        let mut n_forgotten = 0;
+        for commit_id in revwalk {
+            let commit = repo.find_commit(commit_id?)?;
+
+            if base_commit.is_some() {
+                n_forgotten += 1;
+            } else if commit.time().seconds() < cutoff {
+                base_commit = Some(commit);
+                n_forgotten += 1;
+            } else {
+                kept_commits.push(commit);
+            }
+        }
+
+        let base_commit = match base_commit {
+            Some(base_commit) => base_commit,
+            None => {
+                log::info!("No commits before the cutoff to forget");
+                return Ok(0);
+            }
+        };
+
+        let signature = git2::Signature::now(author_name, author_email)?;
+        let mut new_head_id = repo.commit(
+            None,
+            &signature,
+            &signature,
+            FORGET_HISTORY_COMMIT_MESSAGE,
+            &base_commit.tree()?,
+            &[],
+        )?;
+
+        for commit in kept_commits.iter().rev() {
+            let parent = repo.find_commit(new_head_id)?;
+            new_head_id = repo.commit(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                commit.message().unwrap_or_default(),
+                &commit.tree()?,
+                &[&parent],
+            )?;
+        }
+
+        let mut reference_names = Vec::new();
+        for reference in repo.references()? {
+            if let Some(name) = reference?.name() {
+                reference_names.push(name.to_string());
+            }
+        }
+
+        for name in &reference_names {
+            if name.starts_with("refs/heads/") {
+                repo.reference(name, new_head_id, true, "Forget history")?;
+            }
+        }
+
+        // So the forgotten commits can't be reached from them anymore
+        for name in reference_names.iter().map(String::as_str).chain(["HEAD"]) {
+            repo.reflog_delete(name)?;
+        }
+
+        log::info!(
+            "Forgot {} commits of `{}`, kept {}",
+            n_forgotten,
+            head_name,
+            kept_commits.len()
+        );
+
+        Ok(n_forgotten)
+    }
+
+    /// Push the current branch to `remote_branch` on the remote, replacing the history there
+    pub fn force_push(&self, remote_name: &str, remote_branch: &str) -> anyhow::Result<()> {
+        let ref_head = self.inner().head()?;
+
+        anyhow::ensure!(ref_head.is_branch(), "Head is not a branch");
+
+        let ref_head_name = ref_head
+            .name()
+            .ok_or_else(|| anyhow::anyhow!("Ref head name not found"))?;
+
+        self.push(
+            remote_name,
+            &format!("+{}:refs/heads/{}", ref_head_name, remote_branch),
+        )
+    }
+
+    /// Fetch and merge `branch_name` of the remote into the current branch. Returns the files
+    /// that changed in the working directory.
+    pub fn pull(
+        &self,
+        remote_name: &str,
+        branch_name: &str,
+        author_name: &str,
+        author_email: &str,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        let repo = self.inner();
+
+        self.fetch(remote_name)?;
+
+        let old_tree = repo.head()?.peel_to_tree()?;
+
+        let source_branch = format!("{}/{}", remote_name, branch_name);
+        let remote_ref = match repo.find_branch(&source_branch, git2::BranchType::Remote) {
+            Ok(branch) => branch.into_reference(),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                log::info!("Branch `{}` not found, nothing to merge", source_branch);
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let fetch_commit = repo.reference_to_annotated_commit(&remote_ref)?;
+
+        self.merge(
+            &source_branch,
+            Some(fetch_commit),
+            author_name,
+            author_email,
+        )?;
+
+        let new_tree = repo.head()?.peel_to_tree()?;
+
+        let changed_files = self.diff_tree_to_tree(&old_tree, &new_tree)?;
+        Ok(changed_files)
+    }
+
+    fn perform_fastforward(&self, target_oid: git2::Oid) -> anyhow::Result<()> {
         let repo = self.inner();
 
         let mut target_ref = repo.head()?;
@@ -391,22 +1461,176 @@ impl Repository {
             .base_path()
             .join(std::str::from_utf8(file_path).unwrap());
 
-        let mut file = File::open(file_full_path)?;
+        let mut file = File::create(file_full_path)?;
         file.write_all(file_data)?;
 
         Ok(())
     }
 
+    /// Write the union of both sides of the data file, so no tag created on either device is
+    /// lost. Takes ours like other files when either side can't be read.
+    fn resolve_data_file_conflict(
+        &self,
+        our: &git2::IndexEntry,
+        their: &git2::IndexEntry,
+    ) -> anyhow::Result<()> {
+        let odb = self.inner().odb()?;
+
+        let merged = DataFile::parse(odb.read(our.id)?.data()).and_then(|our_data| {
+            let their_data = DataFile::parse(odb.read(their.id)?.data())?;
+            our_data.union(&their_data).to_canonical_bytes()
+        });
+
+        match merged {
+            Ok(bytes) => {
+                fs::write(self.base_path().join(DATA_FILE_NAME), bytes)?;
+                log::info!("Merged both sides of the data file");
+                Ok(())
+            }
+            Err(err) => {
+                log::warn!("Failed to merge data file, taking ours: {:?}", err);
+                self.resolve_conflict(our)
+            }
+        }
+    }
+
     const fn inner(&self) -> &git2::Repository {
         &self.inner
     }
 
-    fn credentials_cb(username_from_url: Option<&str>) -> Result<git2::Cred, git2::Error> {
-        log::info!(
-            "Credential callback with username `{}`",
-            username_from_url.unwrap()
-        );
-        git2::Cred::ssh_key_from_agent(username_from_url.unwrap())
+    fn blob_id_at(commit: &git2::Commit, path: &Path) -> anyhow::Result<Option<git2::Oid>> {
+        match commit.tree()?.get_path(path) {
+            Ok(entry) => Ok(Some(entry.id())),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Where the file at `path` was before `commit` moved it there, compared to its first
+    /// parent
+    fn renamed_from(&self, commit: &git2::Commit, path: &Path) -> anyhow::Result<Option<PathBuf>> {
+        let parent = match commit.parents().next() {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+
+        let mut diff =
+            self.inner()
+                .diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?;
+        diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+
+        Ok(Self::renames(&diff)
+            .into_iter()
+            .find(|(_, new_path)| new_path == path)
+            .map(|(old_path, _)| old_path))
+    }
+
+    /// Old and new paths of the files that `diff` found as renamed
+    fn renames(diff: &git2::Diff) -> Vec<(PathBuf, PathBuf)> {
+        diff.deltas()
+            .filter(|delta| delta.status() == git2::Delta::Renamed)
+            .filter_map(|delta| {
+                let old_path = delta.old_file().path()?.to_path_buf();
+                let new_path = delta.new_file().path()?.to_path_buf();
+                Some((old_path, new_path))
+            })
+            .collect()
+    }
+
+    fn n_commits(&self, id: git2::Oid) -> anyhow::Result<usize> {
+        let mut revwalk = self.inner().revwalk()?;
+        revwalk.push(id)?;
+        Ok(revwalk.count())
+    }
+
+    fn remote_callbacks<'a>() -> git2::RemoteCallbacks<'a> {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(Self::credentials_cb());
+        callbacks.transfer_progress(|ref progress| Self::transfer_progress_cb(progress));
+        callbacks
+    }
+
+    /// Gives the credentials of the SSH agent or the git credential helpers first, then the
+    /// secret the user entered in the keyring, then fails once both were rejected instead of
+    /// being asked again forever
+    fn credentials_cb(
+    ) -> impl FnMut(&str, Option<&str>, git2::CredentialType) -> Result<git2::Cred, git2::Error>
+    {
+        let mut n_tried = 0;
+
+        move |url, username_from_url, allowed_types| {
+            log::info!(
+                "Credential callback for `{}` with username `{}`; n_tried `{}`",
+                url,
+                username_from_url.unwrap_or_default(),
+                n_tried
+            );
+
+            let mut res = Err(git2::Error::from_str("The credentials were rejected"));
+
+            while res.is_err() && n_tried < 2 {
+                res = if n_tried == 0 {
+                    Self::default_credential(url, username_from_url, allowed_types)
+                } else {
+                    Self::stored_credential(url, username_from_url, allowed_types)
+                };
+                n_tried += 1;
+            }
+
+            // So it is reported as an authentication error instead of a generic one
+            res.map_err(|err| {
+                git2::Error::new(
+                    git2::ErrorCode::Auth,
+                    git2::ErrorClass::Callback,
+                    err.message(),
+                )
+            })
+        }
+    }
+
+    /// Keys from the SSH agent for SSH remotes, and the credential helpers of the git config
+    /// for HTTPS ones
+    fn default_credential(
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed_types: git2::CredentialType,
+    ) -> Result<git2::Cred, git2::Error> {
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            git2::Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+        } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            git2::Config::open_default()
+                .and_then(|config| git2::Cred::credential_helper(&config, url, username_from_url))
+        } else {
+            git2::Cred::default()
+        }
+    }
+
+    /// The passphrase of the default SSH key, or the token for HTTPS, that the user entered
+    /// when the remote rejected the other credentials
+    fn stored_credential(
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed_types: git2::CredentialType,
+    ) -> Result<git2::Cred, git2::Error> {
+        let secret = keyring::lookup(Secret::RemoteCredential(url))
+            .map_err(|err| git2::Error::from_str(&format!("Failed to read the keyring: {}", err)))?
+            .ok_or_else(|| git2::Error::from_str("No credential was entered for the remote"))?;
+
+        if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+            let key_path = remote_credential::default_ssh_key()
+                .ok_or_else(|| git2::Error::from_str("No SSH key found"))?;
+            git2::Cred::ssh_key(
+                username_from_url.unwrap_or("git"),
+                None,
+                &key_path,
+                Some(&secret),
+            )
+        } else if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            // Hosts take a token with any user name
+            git2::Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &secret)
+        } else {
+            Err(git2::Error::from_str("No supported credential type"))
+        }
     }
 
     fn transfer_progress_cb(progress: &git2::Progress) -> bool {
@@ -428,3 +1652,1054 @@ impl Repository {
         true
     }
 }
+
+/// Branch where the device with `host_name` commits its changes before merging them
+pub fn device_branch_name(host_name: &str) -> String {
+    let host_name: String = host_name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let host_name = host_name.trim_matches('-');
+
+    if host_name.is_empty() {
+        format!("{}unknown", DEVICE_BRANCH_PREFIX)
+    } else {
+        format!("{}{}", DEVICE_BRANCH_PREFIX, host_name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::Cell,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+    use crate::core::RepoOps;
+
+    const REMOTE_NAME: &str = "origin";
+    const AUTHOR_NAME: &str = "Test";
+    const AUTHOR_EMAIL: &str = "test@noteworthy.io";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "noteworthy-repository-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn write_and_commit(repo: &Repository, file_name: &str, contents: &str) {
+        fs::write(repo.base_path().join(file_name), contents).unwrap();
+        repo.add(&["."]).unwrap();
+        repo.commit(&format!("Edit {}", file_name), AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+    }
+
+    fn push_main(
+        repo: &Repository,
+        max_retries: u32,
+    ) -> anyhow::Result<Vec<(PathBuf, git2::Delta)>> {
+        repo.push_with_retry(
+            REMOTE_NAME,
+            DEFAULT_MAIN_BRANCH,
+            AUTHOR_NAME,
+            AUTHOR_EMAIL,
+            max_retries,
+        )
+    }
+
+    /// Bare remote with a single commit on `main`, and two clones of it
+    fn setup_remote_and_clones(name: &str) -> (Repository, Repository) {
+        let dir = temp_dir(name);
+
+        let remote_path = dir.join("remote.git");
+        let remote = git2::Repository::init_bare(&remote_path).unwrap();
+        remote.set_head("refs/heads/main").unwrap();
+        let remote_url = remote_path.to_str().unwrap();
+
+        let seed = Repository::init(dir.join("seed")).unwrap();
+        seed.inner().remote(REMOTE_NAME, remote_url).unwrap();
+        write_and_commit(&seed, "seed.md", "Seed");
+        push_main(&seed, 0).unwrap();
+
+        let repo_a = Repository::clone(dir.join("a"), remote_url).unwrap();
+        let repo_b = Repository::clone(dir.join("b"), remote_url).unwrap();
+        (repo_a, repo_b)
+    }
+
+    #[test]
+    fn clone_with_progress() {
+        let (repo, _) = setup_remote_and_clones("clone-with-progress");
+        write_and_commit(&repo, "other.md", "Other");
+        push_main(&repo, 0).unwrap();
+
+        let dir = repo.base_path().parent().unwrap();
+        // Local paths are copied without reporting the transfer, unlike URLs
+        let remote_url = format!("file://{}", dir.join("remote.git").display());
+
+        let reported = RefCell::new(Vec::new());
+        let clone_path = dir.join("with-progress");
+        let clone = Repository::clone_with_progress(
+            &clone_path,
+            &remote_url,
+            |progress| reported.borrow_mut().push(progress),
+            || false,
+        )
+        .unwrap();
+        assert!(clone.base_path().join("other.md").exists());
+
+        let reported = reported.take();
+        assert!(reported
+            .iter()
+            .any(|progress| matches!(progress, CloneProgress::Receiving { .. })));
+        assert!(matches!(
+            reported.last(),
+            Some(CloneProgress::CheckingOut {
+                completed_files,
+                total_files
+            }) if completed_files == total_files && *total_files > 0
+        ));
+    }
+
+    #[test]
+    fn clone_cancelled() {
+        let (repo, _) = setup_remote_and_clones("clone-cancelled");
+        let dir = repo.base_path().parent().unwrap();
+        let remote_url = format!("file://{}", dir.join("remote.git").display());
+
+        // Cancelled while receiving the objects
+        let n_reported = Cell::new(0);
+        let clone_path = dir.join("cancelled");
+        let err = Repository::clone_with_progress(
+            &clone_path,
+            &remote_url,
+            |_| n_reported.set(n_reported.get() + 1),
+            || n_reported.get() > 0,
+        )
+        .unwrap_err();
+        assert!(err.is::<CloneCancelled>());
+        assert_eq!(n_reported.get(), 1);
+        assert!(!clone_path.exists());
+
+        // Into an empty folder that was already there
+        fs::create_dir_all(&clone_path).unwrap();
+        let err =
+            Repository::clone_with_progress(&clone_path, &remote_url, |_| {}, || true).unwrap_err();
+        assert!(err.is::<CloneCancelled>());
+        assert!(!clone_path.exists());
+
+        // Files that were there before are kept
+        fs::create_dir_all(&clone_path).unwrap();
+        fs::write(clone_path.join("mine.md"), "Mine").unwrap();
+        let err =
+            Repository::clone_with_progress(&clone_path, &remote_url, |_| {}, || true).unwrap_err();
+        assert!(err.is::<CloneCancelled>());
+        assert!(clone_path.join("mine.md").exists());
+
+        // A clone that can be done afterwards
+        let clone_path = dir.join("after-cancelled");
+        Repository::clone_with_progress(&clone_path, &remote_url, |_| {}, || false).unwrap();
+        assert!(clone_path.join("seed.md").exists());
+    }
+
+    #[test]
+    fn device_branch_name() {
+        assert_eq!(super::device_branch_name("laptop"), "device/laptop");
+        assert_eq!(
+            super::device_branch_name("My Laptop.local"),
+            "device/my-laptop-local"
+        );
+        assert_eq!(super::device_branch_name("  "), "device/unknown");
+    }
+
+    #[test]
+    fn branches() {
+        let (repo, _) = setup_remote_and_clones("branches");
+        assert_eq!(repo.default_branch(REMOTE_NAME), DEFAULT_MAIN_BRANCH);
+        assert_eq!(repo.current_branch().unwrap().as_deref(), Some("main"));
+
+        repo.create_branch("device/a").unwrap();
+        assert_eq!(repo.current_branch().unwrap().as_deref(), Some("main"));
+
+        repo.checkout("device/a").unwrap();
+        assert_eq!(repo.current_branch().unwrap().as_deref(), Some("device/a"));
+
+        repo.switch_to_branch("device/b").unwrap();
+        assert_eq!(repo.current_branch().unwrap().as_deref(), Some("device/b"));
+        assert!(repo.base_path().join("seed.md").exists());
+    }
+
+    #[test]
+    fn remote_url() {
+        let (repo, _) = setup_remote_and_clones("remote-url");
+        let remote_url = repo.remote_url(REMOTE_NAME).unwrap().unwrap();
+        assert!(remote_url.ends_with("remote.git"));
+        assert_eq!(repo.remote_url("other").unwrap(), None);
+
+        let repo = Repository::init(temp_dir("remote-url-offline")).unwrap();
+        assert_eq!(repo.remote_url(REMOTE_NAME).unwrap(), None);
+
+        // Added when there is none yet
+        repo.set_remote_url(REMOTE_NAME, &remote_url).unwrap();
+        assert_eq!(
+            repo.remote_url(REMOTE_NAME).unwrap().as_deref(),
+            Some(remote_url.as_str())
+        );
+    }
+
+    #[test]
+    fn set_remote_url_keeps_local_commits() {
+        let (repo_a, repo_b) = setup_remote_and_clones("set-remote-url");
+        let dir = repo_a.base_path().parent().unwrap().to_owned();
+
+        // The new remote has a commit that the old one doesn't
+        let new_remote_path = dir.join("new-remote.git");
+        git2::Repository::init_bare(&new_remote_path).unwrap();
+        let new_remote_url = new_remote_path.to_str().unwrap();
+        repo_b.set_remote_url(REMOTE_NAME, new_remote_url).unwrap();
+        write_and_commit(&repo_b, "b.md", "B");
+        push_main(&repo_b, 0).unwrap();
+
+        // Not pushed yet
+        write_and_commit(&repo_a, "a.md", "A");
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 1,
+                n_behind: 0
+            }
+        );
+
+        repo_a.set_remote_url(REMOTE_NAME, new_remote_url).unwrap();
+        assert_eq!(
+            repo_a.remote_url(REMOTE_NAME).unwrap().as_deref(),
+            Some(new_remote_url)
+        );
+        let head_commit = repo_a.inner().head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head_commit.summary(), Some("Edit a.md"));
+        assert!(repo_a.base_path().join("a.md").exists());
+
+        // The branches of the old remote are gone, so every commit is ahead until fetching
+        assert!(repo_a
+            .inner()
+            .find_branch("origin/main", git2::BranchType::Remote)
+            .is_err());
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 2,
+                n_behind: 0
+            }
+        );
+
+        repo_a.fetch(REMOTE_NAME).unwrap();
+        repo_a.update_remote_head(REMOTE_NAME).unwrap();
+        assert_eq!(repo_a.default_branch(REMOTE_NAME), DEFAULT_MAIN_BRANCH);
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 1,
+                n_behind: 1
+            }
+        );
+
+        repo_a
+            .pull(REMOTE_NAME, DEFAULT_MAIN_BRANCH, AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        push_main(&repo_a, 0).unwrap();
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind::default()
+        );
+    }
+
+    #[test]
+    fn fetch_default_branch() {
+        let (repo_a, repo_b) = setup_remote_and_clones("fetch-default-branch");
+        write_and_commit(&repo_b, "b.md", "B");
+        write_and_commit(&repo_b, "b.md", "B again");
+        push_main(&repo_b, 0).unwrap();
+        write_and_commit(&repo_a, "a.md", "A");
+
+        // Only known once fetched
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 1,
+                n_behind: 0
+            }
+        );
+
+        repo_a.fetch_default_branch(REMOTE_NAME).unwrap();
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 1,
+                n_behind: 2
+            }
+        );
+
+        // Nothing is merged
+        assert!(!repo_a.base_path().join("b.md").exists());
+        assert!(!repo_a.is_file_changed_in_workdir().unwrap());
+        let head_commit = repo_a.inner().head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head_commit.summary(), Some("Edit a.md"));
+
+        // With the merge commit, which is not pushed either
+        repo_a
+            .pull(REMOTE_NAME, DEFAULT_MAIN_BRANCH, AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert_eq!(
+            repo_a.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 2,
+                n_behind: 0
+            }
+        );
+
+        push_main(&repo_a, 0).unwrap();
+        repo_b.fetch_default_branch(REMOTE_NAME).unwrap();
+        assert_eq!(
+            repo_b.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind {
+                n_ahead: 0,
+                n_behind: 2
+            }
+        );
+    }
+
+    #[test]
+    fn update_remote_head_of_empty_remote() {
+        let dir = temp_dir("empty-remote-head");
+        let remote_path = dir.join("remote.git");
+        git2::Repository::init_bare(&remote_path).unwrap();
+
+        let repo = Repository::init(dir.join("local")).unwrap();
+        repo.set_remote_url(REMOTE_NAME, remote_path.to_str().unwrap())
+            .unwrap();
+        repo.update_remote_head(REMOTE_NAME).unwrap();
+        assert_eq!(repo.default_branch(REMOTE_NAME), DEFAULT_MAIN_BRANCH);
+        assert_eq!(
+            repo.ahead_behind(REMOTE_NAME).unwrap(),
+            AheadBehind::default()
+        );
+    }
+
+    #[test]
+    fn check_remote() {
+        let (repo, _) = setup_remote_and_clones("check-remote");
+        let remote_url = repo.remote_url(REMOTE_NAME).unwrap().unwrap();
+        assert_eq!(
+            Repository::check_remote(&remote_url),
+            RemoteStatus::Reachable
+        );
+
+        let not_a_repo = temp_dir("check-remote-not-a-repo");
+        assert!(matches!(
+            Repository::check_remote(not_a_repo.to_str().unwrap()),
+            RemoteStatus::NotARepository(_)
+        ));
+    }
+
+    #[test]
+    fn add_stages_deleted_files() {
+        let repo = Repository::init(temp_dir("add-deleted")).unwrap();
+        write_and_commit(&repo, "a.md", "A");
+        write_and_commit(&repo, "b.md", "B");
+
+        fs::remove_file(repo.base_path().join("a.md")).unwrap();
+        assert!(repo.is_file_changed_in_workdir().unwrap());
+
+        repo.add(&["."]).unwrap();
+        repo.commit("Delete a.md", AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert!(!repo.is_file_changed_in_workdir().unwrap());
+
+        let tree = repo.inner().head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("a.md").is_none());
+        assert!(tree.get_name("b.md").is_some());
+    }
+
+    #[test]
+    fn push_non_fast_forward() {
+        let (repo_a, repo_b) = setup_remote_and_clones("push-non-fast-forward");
+
+        write_and_commit(&repo_a, "a.md", "A");
+        push_main(&repo_a, 0).unwrap();
+
+        write_and_commit(&repo_b, "b.md", "B");
+        let err = repo_b
+            .push(REMOTE_NAME, "refs/heads/main:refs/heads/main")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RepositoryError>(),
+            Some(RepositoryError::NonFastForward)
+        ));
+
+        let err = push_main(&repo_b, 0).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RepositoryError>(),
+            Some(RepositoryError::NonFastForward)
+        ));
+    }
+
+    #[test]
+    fn push_with_retry_merges_remote() {
+        let (repo_a, repo_b) = setup_remote_and_clones("push-with-retry");
+
+        repo_a.switch_to_branch("device/a").unwrap();
+        write_and_commit(&repo_a, "a.md", "A");
+        push_main(&repo_a, 0).unwrap();
+
+        repo_b.switch_to_branch("device/b").unwrap();
+        write_and_commit(&repo_b, "b.md", "B");
+        let merged_files = push_main(&repo_b, 3).unwrap();
+        assert_eq!(
+            merged_files,
+            [(repo_b.base_path().join("a.md"), git2::Delta::Added)]
+        );
+        assert!(repo_b.base_path().join("a.md").exists());
+        assert!(repo_b.contains("HEAD", "origin/main").unwrap());
+
+        let changed_files = repo_a
+            .pull(REMOTE_NAME, DEFAULT_MAIN_BRANCH, AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert_eq!(changed_files.len(), 1);
+        assert_eq!(changed_files[0].0, repo_a.base_path().join("b.md"));
+        assert!(repo_a.base_path().join("b.md").exists());
+        assert!(repo_a.contains("HEAD", "origin/main").unwrap());
+        assert!(repo_a.contains("origin/main", "HEAD").unwrap());
+    }
+
+    #[test]
+    fn pull_unions_conflicting_data_file() {
+        let (repo_a, repo_b) = setup_remote_and_clones("data-file-conflict");
+
+        write_and_commit(
+            &repo_a,
+            DATA_FILE_NAME,
+            "---\nversion: 1\ntag_list:\n  - Ideas\n  - Work\n",
+        );
+        push_main(&repo_a, 0).unwrap();
+
+        write_and_commit(
+            &repo_b,
+            DATA_FILE_NAME,
+            "---\ntag_list:\n  - Work\n  - Home\n",
+        );
+        repo_b
+            .pull(REMOTE_NAME, DEFAULT_MAIN_BRANCH, AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+
+        // Both sides are kept, in the canonical form
+        let text = fs::read_to_string(repo_b.base_path().join(DATA_FILE_NAME)).unwrap();
+        assert!(text.starts_with("---\nversion: 2\ntag_list:\n  - Home\n  - Ideas\n  - Work\n"));
+        assert!(!repo_b.inner().index().unwrap().has_conflicts());
+        assert!(!repo_b.is_file_changed_in_workdir().unwrap());
+        assert!(repo_b.contains("HEAD", "origin/main").unwrap());
+    }
+
+    /// Leave `repo` in the middle of merging `origin/main`, like when the app is killed while
+    /// pulling
+    fn interrupt_merge(repo: &Repository) {
+        repo.fetch(REMOTE_NAME).unwrap();
+
+        let repo = repo.inner();
+        let remote_ref = repo
+            .find_branch("origin/main", git2::BranchType::Remote)
+            .unwrap();
+        let annotated_commit = repo
+            .reference_to_annotated_commit(remote_ref.get())
+            .unwrap();
+        repo.merge(&[&annotated_commit], None, None).unwrap();
+        assert_eq!(repo.state(), git2::RepositoryState::Merge);
+    }
+
+    #[test]
+    fn stale_index_lock() {
+        let repo = Repository::init(temp_dir("stale-index-lock")).unwrap();
+        write_and_commit(&repo, "a.md", "A");
+
+        let lock_path = repo.inner().path().join("index.lock");
+        let lock_file = fs::File::create(&lock_path).unwrap();
+        fs::write(repo.base_path().join("a.md"), "A, edited").unwrap();
+        assert!(repo.add(&["."]).is_err());
+
+        // Git may be holding it for the user
+        assert!(RepoOps::add_all(&repo).is_err());
+        assert!(lock_path.exists());
+
+        lock_file
+            .set_modified(SystemTime::now() - Duration::from_secs(60 * 60))
+            .unwrap();
+        RepoOps::add_all(&repo).unwrap();
+        RepoOps::commit(&repo, "Edit a.md", AUTHOR_NAME, AUTHOR_EMAIL).unwrap();
+        assert!(!lock_path.exists());
+        assert!(!repo.is_file_changed_in_workdir().unwrap());
+
+        let history = repo.file_history(Path::new("a.md")).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            repo.file_content_at(&history[0].id, Path::new("a.md"))
+                .unwrap(),
+            b"A, edited"
+        );
+    }
+
+    #[test]
+    fn complete_interrupted_merge() {
+        let (repo_a, repo_b) = setup_remote_and_clones("complete-merge");
+
+        write_and_commit(&repo_a, "a.md", "A");
+        push_main(&repo_a, 0).unwrap();
+
+        write_and_commit(&repo_b, "b.md", "B");
+        interrupt_merge(&repo_b);
+
+        let base_path = repo_b.base_path().to_owned();
+        drop(repo_b);
+        let repo_b = Repository::open(&base_path).unwrap();
+        assert_eq!(
+            repo_b
+                .recover_interrupted_merge(AUTHOR_NAME, AUTHOR_EMAIL)
+                .unwrap(),
+            MergeRecovery::Completed
+        );
+
+        assert_eq!(repo_b.inner().state(), git2::RepositoryState::Clean);
+        let head_commit = repo_b.inner().head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head_commit.parent_count(), 2);
+        assert!(repo_b.contains("HEAD", "origin/main").unwrap());
+        assert_eq!(fs::read_to_string(base_path.join("a.md")).unwrap(), "A");
+        assert_eq!(fs::read_to_string(base_path.join("b.md")).unwrap(), "B");
+        assert!(!repo_b.is_file_changed_in_workdir().unwrap());
+
+        // Nothing left to recover
+        assert_eq!(
+            repo_b
+                .recover_interrupted_merge(AUTHOR_NAME, AUTHOR_EMAIL)
+                .unwrap(),
+            MergeRecovery::None
+        );
+    }
+
+    #[test]
+    fn abort_interrupted_merge() {
+        let (repo_a, repo_b) = setup_remote_and_clones("abort-merge");
+
+        write_and_commit(&repo_a, "note.md", "From A");
+        write_and_commit(&repo_a, "other.md", "Other");
+        push_main(&repo_a, 0).unwrap();
+
+        write_and_commit(&repo_b, "note.md", "From B");
+        interrupt_merge(&repo_b);
+        assert!(repo_b.inner().index().unwrap().has_conflicts());
+
+        // Edited after the merge started, so it is not part of it
+        let base_path = repo_b.base_path().to_owned();
+        fs::write(base_path.join("seed.md"), "Seed, edited").unwrap();
+
+        assert_eq!(
+            repo_b
+                .recover_interrupted_merge(AUTHOR_NAME, AUTHOR_EMAIL)
+                .unwrap(),
+            MergeRecovery::Aborted
+        );
+
+        assert_eq!(repo_b.inner().state(), git2::RepositoryState::Clean);
+        assert!(!repo_b.inner().index().unwrap().has_conflicts());
+        assert_eq!(
+            fs::read_to_string(base_path.join("note.md")).unwrap(),
+            "From B"
+        );
+        assert!(!base_path.join("other.md").exists());
+        assert_eq!(
+            fs::read_to_string(base_path.join("seed.md")).unwrap(),
+            "Seed, edited"
+        );
+        assert_eq!(repo_b.status_summary().unwrap().n_changes(), 1);
+
+        // It can be pulled again, with the conflict resolved this time
+        repo_b
+            .pull(REMOTE_NAME, DEFAULT_MAIN_BRANCH, AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert!(repo_b.contains("HEAD", "origin/main").unwrap());
+        assert!(base_path.join("other.md").exists());
+    }
+
+    #[test]
+    fn status_summary() {
+        let repo = Repository::init(temp_dir("status-summary")).unwrap();
+        assert_eq!(repo.status_summary().unwrap(), StatusSummary::default());
+
+        write_and_commit(&repo, "staged.md", "Staged");
+        write_and_commit(&repo, "unstaged.md", "Unstaged");
+        write_and_commit(&repo, "deleted.md", "Deleted");
+        assert_eq!(repo.status_summary().unwrap().n_changes(), 0);
+
+        let base_path = repo.base_path();
+        fs::write(base_path.join("staged.md"), "Staged, edited").unwrap();
+        repo.add(&["staged.md"]).unwrap();
+        fs::write(base_path.join("unstaged.md"), "Unstaged, edited").unwrap();
+        fs::remove_file(base_path.join("deleted.md")).unwrap();
+        fs::write(base_path.join("untracked.md"), "Untracked").unwrap();
+        fs::create_dir(base_path.join("folder")).unwrap();
+        fs::write(base_path.join("folder/untracked.md"), "Untracked").unwrap();
+
+        assert_eq!(
+            repo.status_summary().unwrap(),
+            StatusSummary {
+                n_modified: 2,
+                n_new: 2,
+                n_deleted: 1,
+            }
+        );
+
+        // Staged, then edited again
+        fs::write(base_path.join("staged.md"), "Staged, edited twice").unwrap();
+        assert_eq!(repo.status_summary().unwrap().n_modified, 2);
+
+        repo.add(&["."]).unwrap();
+        assert_eq!(repo.status_summary().unwrap().n_changes(), 5);
+
+        repo.commit("Commit all", AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert_eq!(repo.status_summary().unwrap(), StatusSummary::default());
+    }
+
+    #[test]
+    fn changed_files() {
+        let repo = Repository::init(temp_dir("changed-files")).unwrap();
+        assert!(repo.changed_files().unwrap().is_empty());
+
+        write_and_commit(&repo, "modified.md", "Modified");
+        write_and_commit(&repo, "deleted.md", "Deleted");
+
+        let base_path = repo.base_path();
+        fs::write(base_path.join("modified.md"), "Modified, edited").unwrap();
+        fs::remove_file(base_path.join("deleted.md")).unwrap();
+        fs::create_dir(base_path.join("folder")).unwrap();
+        fs::write(base_path.join("folder/new.md"), "New").unwrap();
+
+        let mut changed_files = repo.changed_files().unwrap();
+        changed_files.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            changed_files,
+            [
+                ChangedFile {
+                    path: PathBuf::from("deleted.md"),
+                    change: FileChange::Deleted,
+                },
+                ChangedFile {
+                    path: PathBuf::from("folder/new.md"),
+                    change: FileChange::New,
+                },
+                ChangedFile {
+                    path: PathBuf::from("modified.md"),
+                    change: FileChange::Modified,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_workdir_to_head() {
+        let repo = Repository::init(temp_dir("diff-workdir")).unwrap();
+        let base_path = repo.base_path();
+
+        // Everything is added before the first commit
+        fs::write(base_path.join("a.md"), "Milk\nEggs\n").unwrap();
+        let hunks = repo.diff_workdir_to_head(Path::new("a.md")).unwrap();
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(
+            hunks[0].lines,
+            [
+                line(LineChange::Added, "Milk"),
+                line(LineChange::Added, "Eggs")
+            ]
+        );
+
+        write_and_commit(&repo, "a.md", "Milk\nEggs\nBread\n");
+        write_and_commit(&repo, "b.md", "Butter\n");
+        assert!(repo
+            .diff_workdir_to_head(Path::new("a.md"))
+            .unwrap()
+            .is_empty());
+
+        // Staged changes are included too, and other files are not
+        fs::write(base_path.join("a.md"), "Milk\nBread\nButter").unwrap();
+        repo.add(&["a.md"]).unwrap();
+        fs::write(base_path.join("b.md"), "Cheese\n").unwrap();
+
+        let hunks = repo.diff_workdir_to_head(Path::new("a.md")).unwrap();
+        assert_eq!(
+            hunks,
+            [DiffHunk {
+                old_start: 1,
+                new_start: 1,
+                lines: vec![
+                    line(LineChange::Unchanged, "Milk"),
+                    line(LineChange::Removed, "Eggs"),
+                    line(LineChange::Unchanged, "Bread"),
+                    line(LineChange::Added, "Butter"),
+                ],
+            }]
+        );
+
+        fs::remove_file(base_path.join("b.md")).unwrap();
+        let hunks = repo.diff_workdir_to_head(Path::new("b.md")).unwrap();
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].lines, [line(LineChange::Removed, "Butter")]);
+    }
+
+    #[test]
+    fn add_and_unstage_some_files() {
+        let repo = Repository::init(temp_dir("add-some")).unwrap();
+        write_and_commit(&repo, "a.md", "A");
+        write_and_commit(&repo, "b.md", "B");
+        write_and_commit(&repo, "c.md", "C");
+
+        let base_path = repo.base_path();
+        fs::write(base_path.join("a.md"), "A, edited").unwrap();
+        fs::write(base_path.join("b.md"), "B, edited").unwrap();
+        fs::remove_file(base_path.join("c.md")).unwrap();
+        fs::write(base_path.join("d.md"), "D").unwrap();
+
+        // Staged in an earlier sync, but left out of this one
+        repo.add(&["b.md"]).unwrap();
+        repo.unstage(&["b.md"]).unwrap();
+
+        repo.add(&["a.md", "c.md", "d.md"]).unwrap();
+        repo.commit("Commit some", AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+
+        let changed_files = repo.changed_files().unwrap();
+        assert_eq!(
+            changed_files,
+            [ChangedFile {
+                path: PathBuf::from("b.md"),
+                change: FileChange::Modified,
+            }]
+        );
+        assert_eq!(
+            fs::read_to_string(base_path.join("b.md")).unwrap(),
+            "B, edited"
+        );
+
+        let tree = repo.inner().head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("c.md").is_none());
+        assert!(tree.get_name("d.md").is_some());
+    }
+
+    #[test]
+    fn commit_some_paths() {
+        let repo = Repository::init(temp_dir("commit-paths")).unwrap();
+        write_and_commit(&repo, "a.md", "A");
+        write_and_commit(&repo, "b.md", "B");
+
+        let base_path = repo.base_path();
+        fs::write(base_path.join("a.md"), "A, edited").unwrap();
+        fs::write(base_path.join("b.md"), "B, edited").unwrap();
+        fs::write(base_path.join("c.md"), "C").unwrap();
+
+        // Staged by an earlier sync that failed to commit
+        repo.add(&["b.md"]).unwrap();
+
+        repo.commit_paths(&["a.md", "c.md"], "Commit some", AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+
+        let changed_files = repo.changed_files().unwrap();
+        assert_eq!(
+            changed_files,
+            [ChangedFile {
+                path: PathBuf::from("b.md"),
+                change: FileChange::Modified,
+            }]
+        );
+
+        let tree = repo.inner().head().unwrap().peel_to_tree().unwrap();
+        let blob_content = |name: &str| {
+            let entry = tree.get_name(name).unwrap();
+            let blob = repo.inner().find_blob(entry.id()).unwrap();
+            String::from_utf8(blob.content().to_vec()).unwrap()
+        };
+        assert_eq!(blob_content("a.md"), "A, edited");
+        assert_eq!(blob_content("b.md"), "B");
+        assert_eq!(blob_content("c.md"), "C");
+    }
+
+    #[test]
+    fn add_leaves_out_excluded_files() {
+        let mut repo = Repository::init(temp_dir("add-excluded")).unwrap();
+        write_and_commit(&repo, "a.md", "A");
+
+        let (exclusions, _) = Exclusions::parse("draft.md\narchive-raw/\n*~\n");
+        repo.set_exclusions(exclusions);
+
+        let base_path = repo.base_path().to_owned();
+        fs::write(base_path.join("draft.md"), "Draft").unwrap();
+        fs::write(base_path.join("a.md~"), "A").unwrap();
+        fs::create_dir_all(base_path.join("archive-raw/2021")).unwrap();
+        fs::write(base_path.join("archive-raw/2021/Old.md"), "Old").unwrap();
+        assert!(!repo.is_file_changed_in_workdir().unwrap());
+        assert!(repo.changed_files().unwrap().is_empty());
+
+        fs::write(base_path.join("b.md"), "B").unwrap();
+        assert!(repo.is_file_changed_in_workdir().unwrap());
+
+        repo.add(&["."]).unwrap();
+        repo.commit("Add b.md", AUTHOR_NAME, AUTHOR_EMAIL).unwrap();
+        assert!(!repo.is_file_changed_in_workdir().unwrap());
+
+        let tree = repo.inner().head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_name("b.md").is_some());
+        assert!(tree.get_name("draft.md").is_none());
+        assert!(tree.get_name("a.md~").is_none());
+        assert!(tree.get_name("archive-raw").is_none());
+    }
+
+    fn line(change: LineChange, text: &str) -> DiffLine {
+        DiffLine {
+            change,
+            text: text.to_string(),
+        }
+    }
+
+    /// Like [`write_and_commit`], as if it was committed `seconds` after the Unix epoch
+    fn write_and_commit_at(repo: &Repository, file_name: &str, contents: &str, seconds: i64) {
+        fs::write(repo.base_path().join(file_name), contents).unwrap();
+        repo.add(&["."]).unwrap();
+
+        let repo = repo.inner();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let signature =
+            git2::Signature::new(AUTHOR_NAME, AUTHOR_EMAIL, &git2::Time::new(seconds, 0)).unwrap();
+        let parent = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &format!("Edit {}", file_name),
+            &tree,
+            &[&parent],
+        )
+        .unwrap();
+    }
+
+    fn head_summaries(repo: &Repository) -> Vec<String> {
+        let mut revwalk = repo.inner().revwalk().unwrap();
+        revwalk.push_head().unwrap();
+        revwalk
+            .map(|commit_id| {
+                let commit = repo.inner().find_commit(commit_id.unwrap()).unwrap();
+                commit.summary().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compact() {
+        let repo = Repository::init(temp_dir("compact")).unwrap();
+        // Large enough for the deltas between the versions to take less than the loose objects
+        let contents = (0..2000)
+            .map(|i| format!("Line {}\n", i * 7919 % 10007))
+            .collect::<String>();
+        for i in 0..10 {
+            write_and_commit(&repo, "note.md", &format!("{}{}", contents, i));
+        }
+
+        fs::write(repo.base_path().join("staged.md"), "Staged").unwrap();
+        repo.add(&["."]).unwrap();
+        let odb = repo.inner().odb().unwrap();
+        let staged_id = odb.write(git2::ObjectType::Blob, b"Staged").unwrap();
+        let dangling_id = odb
+            .write(git2::ObjectType::Blob, b"Never committed")
+            .unwrap();
+        drop(odb);
+
+        let usage_before = repo.disk_usage().unwrap();
+        let mut fractions = Vec::new();
+        let reclaimed = repo.compact(|fraction| fractions.push(fraction)).unwrap();
+        assert!(reclaimed > 0);
+        assert!(!fractions.is_empty());
+
+        let usage_after = repo.disk_usage().unwrap();
+        assert_eq!(usage_after.git_dir, usage_before.git_dir - reclaimed);
+        assert_eq!(usage_after.working_tree, usage_before.working_tree);
+
+        let odb = repo.inner().odb().unwrap();
+        assert!(!odb.exists(dangling_id));
+        assert!(odb.exists(staged_id));
+
+        let pack_dir = repo.inner().path().join("objects").join("pack");
+        assert_eq!(fs::read_dir(pack_dir).unwrap().count(), 2);
+
+        // Every version of the note is still in the history
+        let mut revwalk = repo.inner().revwalk().unwrap();
+        revwalk.push_head().unwrap();
+        let mut n_commits = 0;
+        for (i, commit_id) in revwalk.enumerate() {
+            let content = repo
+                .file_content_at(&commit_id.unwrap().to_string(), Path::new("note.md"))
+                .unwrap();
+            assert_eq!(content, format!("{}{}", contents, 9 - i).into_bytes());
+            n_commits += 1;
+        }
+        assert_eq!(n_commits, 10);
+
+        // Already compact
+        repo.compact(|_| ()).unwrap();
+        assert_eq!(head_summaries(&repo).len(), 10);
+    }
+
+    #[test]
+    fn forget_history_before() {
+        let (repo, other) = setup_remote_and_clones("forget-history");
+
+        write_and_commit_at(&repo, "deleted.md", "Deleted note", 1_000);
+        write_and_commit_at(&repo, "note.md", "Before", 2_000);
+        fs::remove_file(repo.base_path().join("deleted.md")).unwrap();
+        write_and_commit_at(&repo, "note.md", "Before again", 3_000);
+        write_and_commit(&repo, "note.md", "After");
+        write_and_commit(&repo, "new.md", "New");
+
+        let deleted_id = git2::Oid::hash_object(git2::ObjectType::Blob, b"Deleted note").unwrap();
+        let head_tree_id = repo.inner().head().unwrap().peel_to_tree().unwrap().id();
+
+        // The seed commit is newer than the cutoff, but older than the commits on top of it
+        let n_forgotten = repo
+            .forget_history_before(DateTime::from_timestamp(10_000), AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert_eq!(n_forgotten, 4);
+        assert_eq!(
+            head_summaries(&repo),
+            ["Edit new.md", "Edit note.md", FORGET_HISTORY_COMMIT_MESSAGE]
+        );
+
+        let head = repo.inner().head().unwrap();
+        assert_eq!(head.peel_to_tree().unwrap().id(), head_tree_id);
+        assert_eq!(
+            fs::read_to_string(repo.base_path().join("note.md")).unwrap(),
+            "After"
+        );
+        assert!(repo.base_path().join("new.md").exists());
+        assert!(!repo.base_path().join("deleted.md").exists());
+
+        // Nothing left before the cutoff
+        assert_eq!(
+            repo.forget_history_before(DateTime::from_timestamp(10_000), AUTHOR_NAME, AUTHOR_EMAIL)
+                .unwrap(),
+            0
+        );
+
+        repo.force_push(REMOTE_NAME, DEFAULT_MAIN_BRANCH).unwrap();
+        other.fetch(REMOTE_NAME).unwrap();
+        let remote_main = other
+            .inner()
+            .find_reference("refs/remotes/origin/main")
+            .unwrap();
+        assert_eq!(remote_main.target(), head.target());
+
+        assert!(repo.inner().odb().unwrap().exists(deleted_id));
+        assert!(repo.compact(|_| ()).unwrap() > 0);
+        assert!(!repo.inner().odb().unwrap().exists(deleted_id));
+        assert_eq!(
+            repo.file_content_at(&head.target().unwrap().to_string(), Path::new("note.md"))
+                .unwrap(),
+            b"After"
+        );
+    }
+
+    #[test]
+    fn file_history() {
+        let repo = Repository::init(temp_dir("file-history")).unwrap();
+        assert!(repo.file_history(Path::new("note.md")).unwrap().is_empty());
+
+        write_and_commit(&repo, "note.md", "First");
+        write_and_commit(&repo, "other.md", "Other");
+        write_and_commit(&repo, "note.md", "Second");
+
+        let history = repo.file_history(Path::new("note.md")).unwrap();
+        let summaries = history
+            .iter()
+            .map(|revision| revision.summary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(summaries, ["Edit note.md", "Edit note.md"]);
+
+        let contents = history
+            .iter()
+            .map(|revision| {
+                repo.file_content_at(&revision.id, Path::new("note.md"))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(contents, [b"Second".to_vec(), b"First".to_vec()]);
+
+        // Not in the commit where it didn't exist yet
+        let other_history = repo.file_history(Path::new("other.md")).unwrap();
+        assert_eq!(other_history.len(), 1);
+        assert!(repo
+            .file_content_at(&history[1].id, Path::new("other.md"))
+            .is_err());
+    }
+
+    #[test]
+    fn file_history_follows_renames() {
+        let repo = Repository::init(temp_dir("file-history-follows-renames")).unwrap();
+        let contents = "A note long enough to be found similar after it is moved\n";
+
+        write_and_commit(&repo, "note.md", contents);
+        write_and_commit(&repo, "note.md", &contents.repeat(2));
+
+        let base_path = repo.base_path();
+        fs::rename(base_path.join("note.md"), base_path.join("renamed.md")).unwrap();
+        repo.add(&["."]).unwrap();
+        assert_eq!(
+            repo.staged_renames().unwrap(),
+            [(PathBuf::from("note.md"), PathBuf::from("renamed.md"))]
+        );
+        repo.commit("Rename note.md", AUTHOR_NAME, AUTHOR_EMAIL)
+            .unwrap();
+        assert!(repo.staged_renames().unwrap().is_empty());
+
+        let history = repo.file_history(Path::new("renamed.md")).unwrap();
+        let summaries = history
+            .iter()
+            .map(|revision| revision.summary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summaries,
+            ["Rename note.md", "Edit note.md", "Edit note.md"]
+        );
+
+        let paths = history
+            .iter()
+            .map(|revision| revision.path.as_path())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                Path::new("renamed.md"),
+                Path::new("note.md"),
+                Path::new("note.md")
+            ]
+        );
+
+        for revision in &history {
+            assert!(repo.file_content_at(&revision.id, &revision.path).is_ok());
+        }
+    }
+}
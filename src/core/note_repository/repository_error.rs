@@ -0,0 +1,173 @@
+/// Parts of the messages of the HTTP errors that mean the remote refused the credentials, as
+/// they are not reported with a code of their own
+const HTTP_AUTH_MESSAGES: [&str; 4] = [
+    "status code: 401",
+    "status code: 403",
+    "authentication replays",
+    "authentication required",
+];
+
+/// Errors of [`Repository`](super::Repository) operations that need specific handling
+#[derive(Debug, thiserror::Error)]
+pub enum RepositoryError {
+    /// The remote has commits that are not on the local branch, and they have to be pulled first
+    #[error("Remote rejected non-fast-forward push")]
+    NonFastForward,
+    /// The remote rejected the given credentials or none is available
+    #[error("Failed to authenticate with the remote: {0}")]
+    Authentication(String),
+    #[error(transparent)]
+    Other(git2::Error),
+}
+
+impl From<git2::Error> for RepositoryError {
+    fn from(err: git2::Error) -> Self {
+        match err.code() {
+            git2::ErrorCode::NotFastForward => Self::NonFastForward,
+            git2::ErrorCode::Auth | git2::ErrorCode::Certificate => {
+                Self::Authentication(err.message().to_string())
+            }
+            _ if err.class() == git2::ErrorClass::Ssh => {
+                Self::Authentication(err.message().to_string())
+            }
+            _ if matches!(err.class(), git2::ErrorClass::Http | git2::ErrorClass::Net)
+                && is_http_auth_message(err.message()) =>
+            {
+                Self::Authentication(err.message().to_string())
+            }
+            _ => Self::Other(err),
+        }
+    }
+}
+
+impl RepositoryError {
+    /// Map the status reported by the remote on a rejected reference update
+    pub fn for_rejected_push(status: &str) -> Self {
+        let status = status.to_lowercase();
+
+        if status.contains("fast-forward") || status.contains("fastforward") {
+            Self::NonFastForward
+        } else {
+            Self::Other(git2::Error::from_str(&status))
+        }
+    }
+
+    /// Whether `err`, or what caused it, is the remote rejecting the credentials
+    pub fn is_authentication(err: &anyhow::Error) -> bool {
+        err.chain()
+            .any(|cause| matches!(cause.downcast_ref::<Self>(), Some(Self::Authentication(_))))
+    }
+}
+
+fn is_http_auth_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    HTTP_AUTH_MESSAGES
+        .iter()
+        .any(|auth_message| message.contains(auth_message))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_git2_error() {
+        let err = git2::Error::new(
+            git2::ErrorCode::NotFastForward,
+            git2::ErrorClass::Reference,
+            "rejected",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::NonFastForward
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Net,
+            "bad credentials",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Authentication(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Ssh,
+            "no key",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Authentication(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Http,
+            "unexpected http status code: 401",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Authentication(_)
+        ));
+
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Net,
+            "too many redirects or authentication replays",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Authentication(_)
+        ));
+
+        // Not about the credentials
+        let err = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Http,
+            "unexpected http status code: 404",
+        );
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Other(_)
+        ));
+
+        let err = git2::Error::from_str("other");
+        assert!(matches!(
+            RepositoryError::from(err),
+            RepositoryError::Other(_)
+        ));
+    }
+
+    #[test]
+    fn is_authentication() {
+        let err = anyhow::Error::from(RepositoryError::Authentication("denied".into()));
+        assert!(RepositoryError::is_authentication(&err));
+        assert!(RepositoryError::is_authentication(
+            &err.context("Failed to fetch")
+        ));
+
+        let err = anyhow::Error::from(RepositoryError::NonFastForward);
+        assert!(!RepositoryError::is_authentication(&err));
+        assert!(!RepositoryError::is_authentication(&anyhow::anyhow!(
+            "authentication"
+        )));
+    }
+
+    #[test]
+    fn for_rejected_push() {
+        assert!(matches!(
+            RepositoryError::for_rejected_push("cannot push non-fastforwardable reference"),
+            RepositoryError::NonFastForward
+        ));
+        assert!(matches!(
+            RepositoryError::for_rejected_push("rejected: non-fast-forward"),
+            RepositoryError::NonFastForward
+        ));
+        assert!(matches!(
+            RepositoryError::for_rejected_push("hook declined"),
+            RepositoryError::Other(_)
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use anyhow::Context;
+use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
@@ -9,6 +11,10 @@ use once_cell::unsync::OnceCell;
 use std::{thread, time::Duration};
 
 use super::Repository;
+use crate::{
+    model::{ErrorReport, ErrorSource},
+    Application,
+};
 
 const DEFAULT_SLEEP_TIME_SECS: u64 = 3;
 
@@ -126,42 +132,56 @@ impl RepositoryWatcher {
 
     fn setup(&self) {
         let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT_IDLE);
+        let error_sender = Application::default().error_log().sender();
 
         let base_path = self.base_path().path().unwrap();
         let remote_name = self.remote_name();
+        let error_message = gettext("Failed to check for remote changes");
 
         // FIXME join and end the thread properly when `self` is dropped
         thread::spawn(move || match Repository::open(&base_path) {
             Ok(repo) => {
                 log::info!("Starting watcher thread...");
 
+                // The same error is reported once until it is fixed, instead of every check
+                let mut last_error_detail = None;
+
                 loop {
-                    repo.fetch(&remote_name).unwrap_or_else(|err| {
-                        log::error!("Failed to fetch to origin: {:?}", err);
-                    });
-                    if let Ok(is_same) = repo.is_same("HEAD", "FETCH_HEAD") {
-                        sender.send(is_same).unwrap_or_else(|err| {
-                            log::error!("Failed to send message to channel: {:?}", err);
-                        });
-                    } else {
-                        log::error!("Failed to compare HEAD from FETCH_HEAD");
+                    match is_up_to_date(&repo, &remote_name) {
+                        Ok(is_up_to_date) => {
+                            last_error_detail = None;
+                            sender.send(is_up_to_date).unwrap_or_else(|err| {
+                                log::error!("Failed to send message to channel: {:?}", err);
+                            });
+                        }
+                        Err(err) => {
+                            let detail = format!("{:?}", err);
+                            if last_error_detail.as_ref() != Some(&detail) {
+                                error_sender.send(ErrorReport::new(
+                                    ErrorSource::Sync,
+                                    &error_message,
+                                    &detail,
+                                ));
+                                last_error_detail = Some(detail);
+                            }
+                        }
                     }
                     thread::sleep(Duration::from_secs(DEFAULT_SLEEP_TIME_SECS));
                 }
             }
             Err(err) => {
-                log::error!(
-                    "Failed to open repo with path `{}`: {:?}",
-                    base_path.display(),
-                    err
-                );
+                error_sender.send(ErrorReport::from_error(
+                    ErrorSource::Sync,
+                    &gettext!("Failed to open repository at “{}”", base_path.display()),
+                    &err,
+                ));
             }
         });
 
         receiver.attach(
             None,
-            clone!(@weak self as obj => @default-return Continue(false), move |is_same| {
-                if !is_same {
+            clone!(@weak self as obj => @default-return Continue(false), move |is_up_to_date| {
+                if !is_up_to_date {
                     obj.emit_by_name::<()>("remote-changed", &[]);
                 }
                 Continue(true)
@@ -169,3 +189,14 @@ impl RepositoryWatcher {
         );
     }
 }
+
+/// Fetch `remote_name`, then check whether HEAD contains its default branch
+fn is_up_to_date(repo: &Repository, remote_name: &str) -> anyhow::Result<bool> {
+    repo.fetch(remote_name)
+        .with_context(|| format!("Failed to fetch from `{}`", remote_name))?;
+
+    // HEAD may be ahead of the remote since changes are merged on the device branch
+    let remote_branch = format!("{}/{}", remote_name, repo.default_branch(remote_name));
+    repo.contains("HEAD", &remote_branch)
+        .with_context(|| format!("Failed to compare HEAD from `{}`", remote_branch))
+}
@@ -0,0 +1,30 @@
+use super::{ChangedFile, FileChange};
+
+/// Number of files in the working tree or index that are not committed yet
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatusSummary {
+    pub n_modified: u32,
+    /// Including untracked files
+    pub n_new: u32,
+    pub n_deleted: u32,
+}
+
+impl StatusSummary {
+    pub fn from_changed_files(changed_files: &[ChangedFile]) -> Self {
+        let mut summary = Self::default();
+
+        for changed_file in changed_files {
+            match changed_file.change {
+                FileChange::New => summary.n_new += 1,
+                FileChange::Modified => summary.n_modified += 1,
+                FileChange::Deleted => summary.n_deleted += 1,
+            }
+        }
+
+        summary
+    }
+
+    pub fn n_changes(self) -> u32 {
+        self.n_modified + self.n_new + self.n_deleted
+    }
+}
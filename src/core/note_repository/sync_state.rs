@@ -7,6 +7,15 @@ pub enum SyncState {
     Pulling,
     Pushing,
     Idle,
+    /// Nothing runs, and the remote rejected the credentials the last time
+    AuthRequired,
+}
+
+impl SyncState {
+    /// Whether a sync runs right now
+    pub fn is_syncing(self) -> bool {
+        matches!(self, Self::Syncing | Self::Pulling | Self::Pushing)
+    }
 }
 
 impl Default for SyncState {
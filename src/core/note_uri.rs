@@ -0,0 +1,263 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::vault_import::{destination_of, percent_decode};
+
+/// Scheme of the links to notes, which open them in the app from anywhere
+const NOTE_URI_SCHEME: &str = "noteworthy";
+
+const NOTE_URI_PREFIX: &str = "noteworthy://note/";
+
+/// A Markdown link to a note URI, like the one copied with it, or a bare note URI
+static RE_NOTE_URI: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"\[(?:[^\[\]\\\n]|\\.)*\]\(<?noteworthy://note/([^\s()<>]+)>?\)",
+        r"|noteworthy://note/([^\s()<>\[\]]+)",
+    ))
+    .unwrap()
+});
+
+/// The URI of the note with `id`, like `noteworthy://note/Note%202022`
+pub fn note_uri(id: &str) -> String {
+    let mut uri = String::from(NOTE_URI_PREFIX);
+
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+/// Inverse of [`note_uri`], or `None` if `uri` is not the URI of a note. A heading after
+/// `#` is left out, see [`note_uri_heading`].
+pub fn note_id_from_uri(uri: &str) -> Option<String> {
+    let (scheme, rest) = uri.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case(NOTE_URI_SCHEME) {
+        return None;
+    }
+
+    let rest = rest.split_once('#').map_or(rest, |(rest, _)| rest);
+    let encoded_id = rest.strip_prefix("note/")?.trim_end_matches('/');
+    let id = percent_decode(encoded_id);
+
+    if id.is_empty() || id.contains('/') {
+        return None;
+    }
+
+    Some(id)
+}
+
+/// The heading to scroll to in the note at `uri`, like `Packing` in
+/// `noteworthy://note/Trip#Packing`, or `None` if it links to the whole note
+pub fn note_uri_heading(uri: &str) -> Option<String> {
+    note_id_from_uri(uri)?;
+
+    let (_, fragment) = uri.split_once('#')?;
+    let heading = percent_decode(fragment);
+    let heading = heading.trim();
+
+    if heading.is_empty() {
+        return None;
+    }
+
+    Some(heading.to_string())
+}
+
+/// A Markdown link to the note at `uri` labelled with `title`, for apps that can't paste
+/// the note URI on its own
+pub fn note_uri_markdown_link(title: &str, uri: &str) -> String {
+    format!("[{}]({})", escape_label(title), uri)
+}
+
+/// How links to notes are written when they are pasted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteLinkStyle {
+    /// `[[Title]]`, which follows the note by its title
+    Wiki,
+    /// `[Title](File.md)`, relative to the note, which other Markdown apps can follow
+    Markdown,
+}
+
+/// `text` with the note URIs in it, bare or in a Markdown link, turned into links to the
+/// notes in `style`. `note_of` gives the title and the file name of the note with an id, or
+/// `None` when there is no such note, whose URIs are kept as they are and returned.
+pub fn convert_note_uris(
+    text: &str,
+    style: NoteLinkStyle,
+    note_of: impl Fn(&str) -> Option<(String, String)>,
+) -> (String, Vec<String>) {
+    let mut unknown_ids = Vec::new();
+
+    let converted = RE_NOTE_URI.replace_all(text, |captures: &Captures| {
+        let encoded_id = captures.get(1).or_else(|| captures.get(2)).unwrap();
+        let id = note_id_from_uri(&format!("{}{}", NOTE_URI_PREFIX, encoded_id.as_str()));
+
+        match id.as_deref().and_then(&note_of) {
+            Some((title, file_name)) => note_link(&title, &file_name, style),
+            None => {
+                unknown_ids.extend(id);
+                captures[0].to_string()
+            }
+        }
+    });
+
+    (converted.into_owned(), unknown_ids)
+}
+
+/// A link to the note with `title` in `file_name`. Titles that can't be in a wiki link, like
+/// empty ones, are linked with Markdown instead.
+fn note_link(title: &str, file_name: &str, style: NoteLinkStyle) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let is_wiki_title = !title.is_empty() && !title.contains(|c| matches!(c, '[' | ']' | '|'));
+
+    if style == NoteLinkStyle::Wiki && is_wiki_title {
+        format!("[[{}]]", title)
+    } else {
+        let label = if title.is_empty() { file_name } else { &title };
+        format!("[{}]({})", escape_label(label), destination_of(file_name))
+    }
+}
+
+fn escape_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn note_of(id: &str) -> Option<(String, String)> {
+        match id {
+            "Groceries" => Some(("Groceries".to_string(), "Groceries.md".to_string())),
+            "Note 2022" => Some(("Trip to Rome".to_string(), "Note 2022.md".to_string())),
+            "Untitled" => Some((String::new(), "Untitled.md".to_string())),
+            "Bracketed" => Some(("[Draft] Plan".to_string(), "Bracketed.md".to_string())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn uri_round_trip() {
+        assert_eq!(note_uri("Groceries"), "noteworthy://note/Groceries");
+        assert_eq!(note_uri("Note 2022"), "noteworthy://note/Note%202022");
+        assert_eq!(note_uri("Café #1"), "noteworthy://note/Caf%C3%A9%20%231");
+
+        for id in [
+            "Groceries",
+            "Note 2022",
+            "Café #1",
+            "50% (draft)",
+            "a.b-c_d~e",
+        ] {
+            assert_eq!(note_id_from_uri(&note_uri(id)).as_deref(), Some(id));
+        }
+    }
+
+    #[test]
+    fn id_from_uri() {
+        assert_eq!(
+            note_id_from_uri("NOTEWORTHY://note/Groceries/").as_deref(),
+            Some("Groceries")
+        );
+        assert_eq!(
+            note_id_from_uri("noteworthy://note/Note 2022").as_deref(),
+            Some("Note 2022")
+        );
+
+        assert_eq!(note_id_from_uri("noteworthy://note/"), None);
+        assert_eq!(note_id_from_uri("noteworthy://note/a/b"), None);
+        assert_eq!(note_id_from_uri("noteworthy://tag/work"), None);
+        assert_eq!(note_id_from_uri("https://note/Groceries"), None);
+        assert_eq!(note_id_from_uri("/home/user/Groceries.md"), None);
+    }
+
+    #[test]
+    fn heading_from_uri() {
+        let uri = "noteworthy://note/Trip%202022#Packing%20list";
+        assert_eq!(note_id_from_uri(uri).as_deref(), Some("Trip 2022"));
+        assert_eq!(note_uri_heading(uri).as_deref(), Some("Packing list"));
+
+        // An encoded `#` is part of the id
+        let uri = "noteworthy://note/C%23%20notes#Setup";
+        assert_eq!(note_id_from_uri(uri).as_deref(), Some("C# notes"));
+        assert_eq!(note_uri_heading(uri).as_deref(), Some("Setup"));
+
+        assert_eq!(
+            note_id_from_uri("noteworthy://note/Groceries/#").as_deref(),
+            Some("Groceries")
+        );
+        assert_eq!(note_uri_heading("noteworthy://note/Groceries/#"), None);
+        assert_eq!(note_uri_heading("noteworthy://note/Groceries"), None);
+        assert_eq!(note_uri_heading("noteworthy://note/#Packing"), None);
+        assert_eq!(note_uri_heading("https://example.com/Trip#Packing"), None);
+    }
+
+    #[test]
+    fn markdown_link() {
+        assert_eq!(
+            note_uri_markdown_link("[Draft] Plan", "noteworthy://note/Bracketed"),
+            r"[\[Draft\] Plan](noteworthy://note/Bracketed)"
+        );
+    }
+
+    #[test]
+    fn convert_wiki() {
+        let (text, unknown_ids) = convert_note_uris(
+            "See noteworthy://note/Groceries and [Trip to Rome](noteworthy://note/Note%202022).",
+            NoteLinkStyle::Wiki,
+            note_of,
+        );
+        assert_eq!(text, "See [[Groceries]] and [[Trip to Rome]].");
+        assert!(unknown_ids.is_empty());
+
+        // Titles that would break the wiki link
+        let (text, _) = convert_note_uris(
+            "noteworthy://note/Untitled noteworthy://note/Bracketed",
+            NoteLinkStyle::Wiki,
+            note_of,
+        );
+        assert_eq!(
+            text,
+            r"[Untitled.md](Untitled.md) [\[Draft\] Plan](Bracketed.md)"
+        );
+    }
+
+    #[test]
+    fn convert_markdown() {
+        let copied = note_uri_markdown_link("Trip to Rome", &note_uri("Note 2022"));
+        let (text, unknown_ids) = convert_note_uris(&copied, NoteLinkStyle::Markdown, note_of);
+        assert_eq!(text, "[Trip to Rome](<Note 2022.md>)");
+        assert!(unknown_ids.is_empty());
+
+        let (text, _) = convert_note_uris(
+            "noteworthy://note/Groceries",
+            NoteLinkStyle::Markdown,
+            note_of,
+        );
+        assert_eq!(text, "[Groceries](Groceries.md)");
+    }
+
+    #[test]
+    fn convert_unknown() {
+        let text = "[Old](noteworthy://note/Deleted) and noteworthy://note/Groceries";
+        let (converted, unknown_ids) = convert_note_uris(text, NoteLinkStyle::Wiki, note_of);
+        assert_eq!(
+            converted,
+            "[Old](noteworthy://note/Deleted) and [[Groceries]]"
+        );
+        assert_eq!(unknown_ids, ["Deleted"]);
+
+        let text = "No links, just https://example.com/note/Groceries";
+        assert_eq!(
+            convert_note_uris(text, NoteLinkStyle::Wiki, note_of),
+            (text.to_string(), Vec::new())
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{DateTime, ExtraFields, FrontMatterProfile, NoteLinkStyle};
+
+/// Version of the notebook settings written by this version of the app
+pub const NOTEBOOK_SETTINGS_VERSION: u32 = 5;
+
+const DEFAULT_NOTE_FILE_NAME: &str = "Note";
+
+/// Plain data of the settings that are shared by every device the notebook is synced to, as
+/// stored in the data file. Settings of only this device are in `gio::Settings` instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotebookSettingsData {
+    /// Version of the format the settings were written in. Files from before the settings
+    /// were added have none, which is read as `0`.
+    pub version: u32,
+    /// When the settings were last changed, or `None` when they never were
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime>,
+    /// Name of the files of new notes, before the time that makes it unique
+    pub note_file_name: String,
+    /// Content of the notes created with the new note button
+    pub note_template: String,
+    /// How the front matter of the notes is written when they are saved
+    pub front_matter_profile: FrontMatterProfile,
+    /// Words to write in the notebook each day, or `0` when there is no goal
+    pub daily_word_goal: u32,
+    /// Whether the title of each note is the text of its first level 1 heading
+    pub derive_title_from_heading: bool,
+    /// Whether pasted links to notes are written as Markdown links to their files, instead
+    /// of as wiki links to their titles
+    pub markdown_note_links: bool,
+    /// Settings added by newer versions of the app, kept so saving doesn't lose them
+    #[serde(flatten)]
+    pub extra_fields: ExtraFields,
+}
+
+impl Default for NotebookSettingsData {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            modified: None,
+            note_file_name: DEFAULT_NOTE_FILE_NAME.to_string(),
+            note_template: String::new(),
+            front_matter_profile: FrontMatterProfile::default(),
+            daily_word_goal: 0,
+            derive_title_from_heading: false,
+            markdown_note_links: false,
+            extra_fields: ExtraFields::default(),
+        }
+    }
+}
+
+impl NotebookSettingsData {
+    /// Read the settings from the value of their section, falling back to the defaults when
+    /// it is not valid, so one bad setting does not fail loading the rest of the data file
+    pub fn deserialize_or_default<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        Ok(serde_yaml::from_value(value).unwrap_or_else(|err| {
+            log::warn!("Falling back to default notebook settings: {:?}", err);
+            Self::default()
+        }))
+    }
+
+    /// Whether these settings were changed after `other`. When the settings are changed on
+    /// two devices before syncing, the ones changed last are kept.
+    pub fn is_newer_than(&self, other: &Self) -> bool {
+        self.modified > other.modified
+    }
+
+    /// The file name for new notes, made safe to be used as one
+    pub fn note_file_stem(&self) -> String {
+        let file_stem = self
+            .note_file_name
+            .trim()
+            .replace(|c| c == '/' || c == '\0', "-");
+
+        if file_stem.is_empty() || file_stem.starts_with('.') {
+            DEFAULT_NOTE_FILE_NAME.to_string()
+        } else {
+            file_stem
+        }
+    }
+
+    /// How pasted links to notes are written
+    pub fn note_link_style(&self) -> NoteLinkStyle {
+        if self.markdown_note_links {
+            NoteLinkStyle::Markdown
+        } else {
+            NoteLinkStyle::Wiki
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    struct Section {
+        #[serde(deserialize_with = "NotebookSettingsData::deserialize_or_default")]
+        settings: NotebookSettingsData,
+    }
+
+    fn modified_at(text: &str) -> NotebookSettingsData {
+        NotebookSettingsData {
+            modified: Some(serde_yaml::from_str(text).unwrap()),
+            ..NotebookSettingsData::default()
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let section: Section = serde_yaml::from_str(
+            "settings:\n  version: 1\n  note_file_name: Journal\n  note_template: \"# Today\"\n",
+        )
+        .unwrap();
+
+        let settings = section.settings;
+        assert_eq!(settings.version, 1);
+        assert_eq!(settings.modified, None);
+        assert_eq!(settings.note_file_name, "Journal");
+        assert_eq!(settings.note_template, "# Today");
+        assert_eq!(
+            settings.front_matter_profile,
+            FrontMatterProfile::Noteworthy
+        );
+
+        let section: Section = serde_yaml::from_str(
+            "settings:\n  version: 2\n  front_matter_profile: standard_markdown\n",
+        )
+        .unwrap();
+        assert_eq!(
+            section.settings.front_matter_profile,
+            FrontMatterProfile::StandardMarkdown
+        );
+        assert_eq!(section.settings.daily_word_goal, 0);
+
+        let section: Section =
+            serde_yaml::from_str("settings:\n  version: 3\n  daily_word_goal: 1000\n").unwrap();
+        assert_eq!(section.settings.daily_word_goal, 1000);
+        assert!(!section.settings.derive_title_from_heading);
+
+        let section: Section =
+            serde_yaml::from_str("settings:\n  version: 4\n  derive_title_from_heading: true\n")
+                .unwrap();
+        assert!(section.settings.derive_title_from_heading);
+        assert_eq!(section.settings.note_link_style(), NoteLinkStyle::Wiki);
+
+        let section: Section =
+            serde_yaml::from_str("settings:\n  version: 5\n  markdown_note_links: true\n").unwrap();
+        assert_eq!(section.settings.note_link_style(), NoteLinkStyle::Markdown);
+
+        // A profile of a newer version doesn't reset the other settings
+        let section: Section = serde_yaml::from_str(
+            "settings:\n  note_file_name: Journal\n  front_matter_profile: unknown\n",
+        )
+        .unwrap();
+        assert_eq!(section.settings.note_file_name, "Journal");
+        assert_eq!(
+            section.settings.front_matter_profile,
+            FrontMatterProfile::Noteworthy
+        );
+    }
+
+    #[test]
+    fn deserialize_without_section() {
+        let section: Section = serde_yaml::from_str("other: 1\n").unwrap();
+        assert_eq!(section.settings, NotebookSettingsData::default());
+        assert_eq!(section.settings.version, 0);
+    }
+
+    #[test]
+    fn deserialize_invalid_section() {
+        let section: Section =
+            serde_yaml::from_str("settings:\n  note_file_name:\n    - Not a string\n").unwrap();
+        assert_eq!(section.settings, NotebookSettingsData::default());
+
+        let section: Section = serde_yaml::from_str("settings: 3\n").unwrap();
+        assert_eq!(section.settings, NotebookSettingsData::default());
+    }
+
+    #[test]
+    fn keeps_settings_of_newer_versions() {
+        let text = "version: 5\nnote_file_name: Note\nnote_template: ''\nnew_setting: true\n";
+
+        let settings: NotebookSettingsData = serde_yaml::from_str(text).unwrap();
+        assert_eq!(settings.version, 5);
+
+        let serialized = serde_yaml::to_string(&settings).unwrap();
+        assert!(serialized.contains("\nnew_setting: true\n"));
+        assert!(!serialized.contains("extra_fields"));
+    }
+
+    #[test]
+    fn newer() {
+        let older = modified_at("\"2022-01-01T10:00:00+08:00\"");
+        let newer = modified_at("\"2022-01-02T10:00:00+08:00\"");
+        let never_modified = NotebookSettingsData::default();
+
+        assert!(newer.is_newer_than(&older));
+        assert!(!older.is_newer_than(&newer));
+        assert!(!older.is_newer_than(&older));
+        assert!(older.is_newer_than(&never_modified));
+        assert!(!never_modified.is_newer_than(&older));
+    }
+
+    #[test]
+    fn note_file_stem() {
+        let with_name = |name: &str| NotebookSettingsData {
+            note_file_name: name.to_string(),
+            ..NotebookSettingsData::default()
+        };
+
+        assert_eq!(with_name("Journal").note_file_stem(), "Journal");
+        assert_eq!(with_name("  Log ").note_file_stem(), "Log");
+        assert_eq!(with_name("a/b").note_file_stem(), "a-b");
+        assert_eq!(with_name("").note_file_stem(), "Note");
+        assert_eq!(with_name(".hidden").note_file_stem(), "Note");
+    }
+}
@@ -0,0 +1,114 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use gtk::glib;
+
+use super::keyring::{self, Secret};
+
+/// Argon2 hash of a PIN with a random salt, so the PIN itself is never stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinHash {
+    /// In the PHC string format, which also holds the salt and the parameters it was hashed
+    /// with
+    text: String,
+}
+
+impl PinHash {
+    /// Hash `pin` with a new random salt from the OS
+    pub fn new(pin: &str) -> Self {
+        let salt = SaltString::generate(&mut OsRng);
+        let text = Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)
+            .expect("Failed to hash PIN")
+            .to_string();
+
+        Self { text }
+    }
+
+    /// Parse from the text it is stored as in the keyring
+    pub fn parse(text: &str) -> Option<Self> {
+        let hash = PasswordHash::new(text).ok()?;
+
+        if hash.salt.is_none() || hash.hash.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            text: text.to_string(),
+        })
+    }
+
+    /// The hash of the PIN asked to unlock the notes, which is kept in the keyring, or `None`
+    /// if no PIN is set.
+    ///
+    /// This blocks until the keyring responds, which may need to be unlocked first.
+    pub fn lookup_lock_pin() -> Result<Option<Self>, glib::Error> {
+        let text = keyring::lookup(Secret::LockPinHash)?;
+
+        // Anything that could write something else there could as well remove it
+        Ok(text.and_then(|text| Self::parse(&text)))
+    }
+
+    /// Make it the hash of the lock PIN, replacing the one before
+    ///
+    /// This blocks until the keyring responds, which may need to be unlocked first.
+    pub fn store_as_lock_pin(&self) -> Result<(), glib::Error> {
+        keyring::store(Secret::LockPinHash, &self.text)
+    }
+
+    /// Remove the lock PIN, so the notes are unlocked without one
+    ///
+    /// This blocks until the keyring responds, which may need to be unlocked first.
+    pub fn clear_lock_pin() -> Result<(), glib::Error> {
+        keyring::clear(Secret::LockPinHash)
+    }
+
+    pub fn verify(&self, pin: &str) -> bool {
+        // The comparison takes the same time however much of it matched
+        match PasswordHash::new(&self.text) {
+            Ok(hash) => Argon2::default()
+                .verify_password(pin.as_bytes(), &hash)
+                .is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify() {
+        let pin_hash = PinHash::new("1234");
+        assert!(pin_hash.verify("1234"));
+        assert!(!pin_hash.verify("4321"));
+        assert!(!pin_hash.verify(""));
+    }
+
+    #[test]
+    fn salted() {
+        let pin_hash_a = PinHash::new("1234");
+        let pin_hash_b = PinHash::new("1234");
+        assert_ne!(pin_hash_a, pin_hash_b);
+        assert!(!pin_hash_a.text.contains("1234"));
+        assert!(pin_hash_a.text.starts_with("$argon2id$"));
+    }
+
+    #[test]
+    fn text_then_parse() {
+        let pin_hash = PinHash::new("1234");
+        let parsed = PinHash::parse(&pin_hash.text).unwrap();
+        assert_eq!(parsed, pin_hash);
+        assert!(parsed.verify("1234"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(PinHash::parse(""), None);
+        assert_eq!(PinHash::parse("no separator"), None);
+        assert_eq!(PinHash::parse("salt$hash"), None);
+        assert_eq!(PinHash::parse("$argon2id$v=19$m=4096,t=3,p=1"), None);
+    }
+}
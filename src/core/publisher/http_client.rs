@@ -0,0 +1,56 @@
+use std::fmt::Debug;
+
+use super::PublishError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpMethod {
+    Post,
+    Patch,
+}
+
+impl HttpMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Post => "POST",
+            Self::Patch => "PATCH",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpBody {
+    Json(String),
+    /// An `application/x-www-form-urlencoded` body with a single field
+    Form {
+        field: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: HttpBody,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u32,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Blocking HTTP requests that notes are published with, so publishing can be backed by
+/// something other than a real HTTP client.
+pub trait HttpClient: Debug + Send {
+    /// Send the request and wait for its response. This only fails if no response was
+    /// received, whatever its status is.
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, PublishError>;
+}
@@ -0,0 +1,474 @@
+//! Publishing notes to a GitHub Gist or a paste service
+
+mod http_client;
+mod publish_error;
+mod ureq_client;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+pub use self::{
+    http_client::{HttpBody, HttpClient, HttpMethod, HttpRequest, HttpResponse},
+    publish_error::PublishError,
+    ureq_client::UreqClient,
+};
+use super::keyring::{self, Secret};
+
+const GIST_API_URL: &str = "https://api.github.com/gists";
+/// Name of the file in the gist. It stays the same when the title changes so updating the
+/// gist replaces its content instead of adding another file.
+const GIST_FILE_NAME: &str = "note.md";
+const USER_AGENT: &str = "Noteworthy";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublishTarget {
+    /// A secret gist of the GitHub account of the token
+    Gist { token: String },
+    /// A service that responds with the URL of the paste when its content is posted in a
+    /// form field
+    Paste { url: String, field: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Published {
+    pub url: String,
+    /// Id of the gist it was published to, to update it the next time
+    pub gist_id: Option<String>,
+}
+
+/// Body of the requests creating or updating a gist
+#[derive(Debug, Serialize)]
+struct GistBody<'a> {
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public: Option<bool>,
+    files: BTreeMap<&'a str, GistFile<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct GistFile<'a> {
+    content: &'a str,
+}
+
+/// The members of the gist in the responses that are used
+#[derive(Debug, Deserialize)]
+struct GistResponse {
+    id: String,
+    html_url: String,
+}
+
+/// The markdown a note is published as, with its title as the top heading
+pub fn markdown(title: &str, content: &str) -> String {
+    let title = title.trim();
+    let content = content.trim_start_matches('\n');
+
+    if title.is_empty() {
+        content.to_string()
+    } else {
+        format!("# {}\n\n{}", title, content)
+    }
+}
+
+/// Look up the GitHub token for publishing gists in the keyring.
+///
+/// This blocks until the keyring responds, which may need to be unlocked first.
+pub fn lookup_gist_token() -> Result<String, PublishError> {
+    keyring::lookup(Secret::GistToken)
+        .map_err(|err| PublishError::Keyring(err.to_string()))?
+        .ok_or(PublishError::MissingToken)
+}
+
+/// Upload `markdown` to `target`. When publishing to a gist, the gist with `gist_id` is
+/// updated if given and still exists, otherwise a new one is created.
+pub fn publish(
+    client: &dyn HttpClient,
+    target: &PublishTarget,
+    title: &str,
+    markdown: &str,
+    gist_id: Option<&str>,
+) -> Result<Published, PublishError> {
+    match target {
+        PublishTarget::Gist { token } => {
+            match gist_id {
+                Some(gist_id) if is_gist_id(gist_id) => {
+                    match update_gist(client, token, gist_id, title, markdown) {
+                        Err(PublishError::Status(404)) => {
+                            log::info!("Gist `{}` no longer exists, creating another", gist_id);
+                        }
+                        res => return res,
+                    }
+                }
+                Some(gist_id) => {
+                    log::warn!("`{}` is not a gist id, creating another gist", gist_id);
+                }
+                None => {}
+            }
+
+            create_gist(client, token, title, markdown)
+        }
+        PublishTarget::Paste { url, field } => publish_paste(client, url, field, markdown),
+    }
+}
+
+/// Whether `gist_id` looks like the id of a gist, which is hexadecimal. It comes from the
+/// front matter, so anything else could make the request with the token go to another URL.
+fn is_gist_id(gist_id: &str) -> bool {
+    !gist_id.is_empty()
+        && gist_id
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn create_gist(
+    client: &dyn HttpClient,
+    token: &str,
+    title: &str,
+    markdown: &str,
+) -> Result<Published, PublishError> {
+    let body = GistBody {
+        description: title,
+        public: Some(false),
+        files: gist_files(markdown),
+    };
+    let request = gist_request(HttpMethod::Post, GIST_API_URL.to_string(), token, &body);
+
+    gist_published(&client.send(&request)?)
+}
+
+fn update_gist(
+    client: &dyn HttpClient,
+    token: &str,
+    gist_id: &str,
+    title: &str,
+    markdown: &str,
+) -> Result<Published, PublishError> {
+    // Whether it stays public is left as it is
+    let body = GistBody {
+        description: title,
+        public: None,
+        files: gist_files(markdown),
+    };
+    let url = format!("{}/{}", GIST_API_URL, gist_id);
+    let request = gist_request(HttpMethod::Patch, url, token, &body);
+
+    gist_published(&client.send(&request)?)
+}
+
+fn gist_files(markdown: &str) -> BTreeMap<&'static str, GistFile<'_>> {
+    BTreeMap::from([(GIST_FILE_NAME, GistFile { content: markdown })])
+}
+
+fn gist_request(method: HttpMethod, url: String, token: &str, body: &GistBody<'_>) -> HttpRequest {
+    HttpRequest {
+        method,
+        url,
+        headers: vec![
+            (
+                "Accept".to_string(),
+                "application/vnd.github+json".to_string(),
+            ),
+            ("Authorization".to_string(), format!("token {}", token)),
+            ("User-Agent".to_string(), USER_AGENT.to_string()),
+        ],
+        body: HttpBody::Json(serde_json::to_string(body).expect("Failed to serialize gist")),
+    }
+}
+
+fn gist_published(response: &HttpResponse) -> Result<Published, PublishError> {
+    check_status(response)?;
+
+    let gist: GistResponse = serde_json::from_str(&response.body)
+        .map_err(|err| PublishError::InvalidResponse(err.to_string()))?;
+
+    Ok(Published {
+        url: gist.html_url,
+        gist_id: Some(gist.id),
+    })
+}
+
+fn publish_paste(
+    client: &dyn HttpClient,
+    url: &str,
+    field: &str,
+    markdown: &str,
+) -> Result<Published, PublishError> {
+    if url.trim().is_empty() {
+        return Err(PublishError::MissingPasteUrl);
+    }
+
+    let request = HttpRequest {
+        method: HttpMethod::Post,
+        url: url.trim().to_string(),
+        headers: vec![("User-Agent".to_string(), USER_AGENT.to_string())],
+        body: HttpBody::Form {
+            field: field.to_string(),
+            value: markdown.to_string(),
+        },
+    };
+
+    let response = client.send(&request)?;
+    check_status(&response)?;
+
+    let paste_url = response.body.trim();
+    if !paste_url.starts_with("https://") && !paste_url.starts_with("http://") {
+        return Err(PublishError::InvalidResponse(format!(
+            "Expected a URL but got `{}`",
+            paste_url
+        )));
+    }
+
+    Ok(Published {
+        url: paste_url.to_string(),
+        gist_id: None,
+    })
+}
+
+fn check_status(response: &HttpResponse) -> Result<(), PublishError> {
+    match response.status {
+        _ if response.is_success() => Ok(()),
+        401 => Err(PublishError::Unauthorized),
+        status => Err(PublishError::Status(status)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{cell::RefCell, collections::VecDeque};
+
+    #[derive(Debug, Default)]
+    struct MockClient {
+        responses: RefCell<VecDeque<Result<HttpResponse, PublishError>>>,
+        requests: RefCell<Vec<HttpRequest>>,
+    }
+
+    impl MockClient {
+        fn respond(&self, status: u32, body: &str) {
+            self.responses.borrow_mut().push_back(Ok(HttpResponse {
+                status,
+                body: body.to_string(),
+            }));
+        }
+
+        fn fail(&self, err: PublishError) {
+            self.responses.borrow_mut().push_back(Err(err));
+        }
+
+        fn requests(&self) -> Vec<HttpRequest> {
+            self.requests.borrow().clone()
+        }
+    }
+
+    impl HttpClient for MockClient {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, PublishError> {
+            self.requests.borrow_mut().push(request.clone());
+            self.responses
+                .borrow_mut()
+                .pop_front()
+                .expect("Unexpected request")
+        }
+    }
+
+    const GIST_RESPONSE: &str =
+        r#"{"id":"aa5a","html_url":"https://gist.github.com/aa5a","public":false}"#;
+
+    fn gist_target() -> PublishTarget {
+        PublishTarget::Gist {
+            token: "secret".to_string(),
+        }
+    }
+
+    fn paste_target() -> PublishTarget {
+        PublishTarget::Paste {
+            url: "https://paste.example.org/".to_string(),
+            field: "content".to_string(),
+        }
+    }
+
+    fn json_body(request: &HttpRequest) -> &str {
+        match request.body {
+            HttpBody::Json(ref json) => json,
+            HttpBody::Form { .. } => panic!("Expected a JSON body"),
+        }
+    }
+
+    #[test]
+    fn markdown_with_title() {
+        assert_eq!(markdown("Title", "Content\n"), "# Title\n\nContent\n");
+        assert_eq!(markdown(" Title ", "\n\nContent"), "# Title\n\nContent");
+        assert_eq!(markdown("", "Content"), "Content");
+        assert_eq!(markdown("Title", ""), "# Title\n\n");
+    }
+
+    #[test]
+    fn gist_created() {
+        let client = MockClient::default();
+        client.respond(201, GIST_RESPONSE);
+
+        let published = publish(&client, &gist_target(), "Title", "# Title\n", None).unwrap();
+        assert_eq!(published.url, "https://gist.github.com/aa5a");
+        assert_eq!(published.gist_id.as_deref(), Some("aa5a"));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, HttpMethod::Post);
+        assert_eq!(requests[0].url, "https://api.github.com/gists");
+        assert!(requests[0]
+            .headers
+            .contains(&("Authorization".to_string(), "token secret".to_string())));
+        assert_eq!(
+            json_body(&requests[0]),
+            r##"{"description":"Title","public":false,"files":{"note.md":{"content":"# Title\n"}}}"##
+        );
+    }
+
+    #[test]
+    fn gist_updated() {
+        let client = MockClient::default();
+        client.respond(200, GIST_RESPONSE);
+
+        let published = publish(&client, &gist_target(), "Title", "Content", Some("aa5a")).unwrap();
+        assert_eq!(published.gist_id.as_deref(), Some("aa5a"));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, HttpMethod::Patch);
+        assert_eq!(requests[0].url, "https://api.github.com/gists/aa5a");
+        assert!(!json_body(&requests[0]).contains("public"));
+    }
+
+    #[test]
+    fn gist_created_if_deleted() {
+        let client = MockClient::default();
+        client.respond(404, r#"{"message":"Not Found"}"#);
+        client.respond(201, GIST_RESPONSE);
+
+        let published =
+            publish(&client, &gist_target(), "Title", "Content", Some("de1e7ed")).unwrap();
+        assert_eq!(published.gist_id.as_deref(), Some("aa5a"));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, HttpMethod::Patch);
+        assert_eq!(requests[1].method, HttpMethod::Post);
+    }
+
+    #[test]
+    fn gist_created_if_invalid_id() {
+        for gist_id in ["../../user/repos", "aa5a?page=2", "AA5A", ""] {
+            let client = MockClient::default();
+            client.respond(201, GIST_RESPONSE);
+
+            let published =
+                publish(&client, &gist_target(), "Title", "Content", Some(gist_id)).unwrap();
+            assert_eq!(published.gist_id.as_deref(), Some("aa5a"));
+
+            let requests = client.requests();
+            assert_eq!(requests.len(), 1);
+            assert_eq!(requests[0].method, HttpMethod::Post);
+            assert_eq!(requests[0].url, "https://api.github.com/gists");
+        }
+    }
+
+    #[test]
+    fn gist_unauthorized() {
+        let client = MockClient::default();
+        client.respond(401, r#"{"message":"Bad credentials"}"#);
+
+        assert_eq!(
+            publish(&client, &gist_target(), "Title", "Content", Some("aa5a")),
+            Err(PublishError::Unauthorized)
+        );
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn gist_network_error() {
+        let client = MockClient::default();
+        client.fail(PublishError::Network("Could not resolve host".to_string()));
+
+        assert_eq!(
+            publish(&client, &gist_target(), "Title", "Content", None),
+            Err(PublishError::Network("Could not resolve host".to_string()))
+        );
+    }
+
+    #[test]
+    fn gist_invalid_response() {
+        let client = MockClient::default();
+        client.respond(201, r#"{"id":"aa5a"}"#);
+        assert!(matches!(
+            publish(&client, &gist_target(), "Title", "Content", None),
+            Err(PublishError::InvalidResponse(_))
+        ));
+
+        client.respond(500, "Internal error");
+        assert_eq!(
+            publish(&client, &gist_target(), "Title", "Content", None),
+            Err(PublishError::Status(500))
+        );
+    }
+
+    #[test]
+    fn gist_json_escaped() {
+        let client = MockClient::default();
+        client.respond(201, GIST_RESPONSE);
+
+        publish(&client, &gist_target(), "\"Quoted\"", "a\\b\n\tc", None).unwrap();
+
+        assert_eq!(
+            json_body(&client.requests()[0]),
+            r#"{"description":"\"Quoted\"","public":false,"files":{"note.md":{"content":"a\\b\n\tc"}}}"#
+        );
+    }
+
+    #[test]
+    fn paste_published() {
+        let client = MockClient::default();
+        client.respond(200, "https://paste.example.org/abc\n");
+
+        let published = publish(&client, &paste_target(), "Title", "Content", None).unwrap();
+        assert_eq!(published.url, "https://paste.example.org/abc");
+        assert_eq!(published.gist_id, None);
+
+        let requests = client.requests();
+        assert_eq!(requests[0].method, HttpMethod::Post);
+        assert_eq!(requests[0].url, "https://paste.example.org/");
+        assert_eq!(
+            requests[0].body,
+            HttpBody::Form {
+                field: "content".to_string(),
+                value: "Content".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn paste_errors() {
+        let client = MockClient::default();
+
+        let target = PublishTarget::Paste {
+            url: " ".to_string(),
+            field: "content".to_string(),
+        };
+        assert_eq!(
+            publish(&client, &target, "Title", "Content", None),
+            Err(PublishError::MissingPasteUrl)
+        );
+        assert!(client.requests().is_empty());
+
+        client.respond(200, "<html>Not a URL</html>");
+        assert!(matches!(
+            publish(&client, &paste_target(), "Title", "Content", None),
+            Err(PublishError::InvalidResponse(_))
+        ));
+
+        client.respond(401, "");
+        assert_eq!(
+            publish(&client, &paste_target(), "Title", "Content", None),
+            Err(PublishError::Unauthorized)
+        );
+    }
+}
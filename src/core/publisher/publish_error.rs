@@ -0,0 +1,23 @@
+/// Errors of publishing a note that are shown differently to the user
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PublishError {
+    /// No GitHub token is stored in the keyring
+    #[error("No GitHub token found in the keyring")]
+    MissingToken,
+    /// The keyring couldn't be reached or unlocked
+    #[error("Failed to read the keyring: {0}")]
+    Keyring(String),
+    /// Publishing to a paste service but no URL is set
+    #[error("No paste service URL is set")]
+    MissingPasteUrl,
+    /// The service rejected the token or requires one
+    #[error("Service rejected the credentials")]
+    Unauthorized,
+    /// No response was received from the service
+    #[error("Failed to reach the service: {0}")]
+    Network(String),
+    #[error("Service responded with status {0}")]
+    Status(u32),
+    #[error("Invalid response from the service: {0}")]
+    InvalidResponse(String),
+}
@@ -0,0 +1,62 @@
+use std::{sync::Arc, time::Duration};
+
+use super::{HttpBody, HttpClient, HttpRequest, HttpResponse, PublishError};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// [`HttpClient`] backed by `ureq`, with the TLS of the system
+///
+/// It blocks until the response is received, so it must be used off the main thread.
+#[derive(Debug)]
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl UreqClient {
+    pub fn new() -> Result<Self, PublishError> {
+        let tls_connector = native_tls::TlsConnector::new()
+            .map_err(|err| PublishError::Network(format!("Failed to set up TLS: {}", err)))?;
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout(TIMEOUT)
+            .tls_connector(Arc::new(tls_connector))
+            .build();
+
+        Ok(Self { agent })
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, PublishError> {
+        let mut ureq_request = self.agent.request(request.method.as_str(), &request.url);
+
+        for (name, value) in &request.headers {
+            ureq_request = ureq_request.set(name, value);
+        }
+
+        let res = match request.body {
+            HttpBody::Json(ref json) => ureq_request
+                .set("Content-Type", "application/json")
+                .send_string(json),
+            HttpBody::Form {
+                ref field,
+                ref value,
+            } => ureq_request.send_form(&[(field.as_str(), value.as_str())]),
+        };
+
+        // Statuses of errors are responses too, which the caller checks
+        let response = match res {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(transport)) => {
+                return Err(PublishError::Network(transport.to_string()))
+            }
+        };
+
+        let status = response.status().into();
+        let body = response
+            .into_string()
+            .map_err(|err| PublishError::Network(err.to_string()))?;
+
+        Ok(HttpResponse { status, body })
+    }
+}
@@ -0,0 +1,288 @@
+use indexmap::IndexMap;
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::SystemTime,
+};
+
+use crate::utils::safe_path::resolve_within;
+
+/// Larger notes are only kept in their own file, so the recovery copies stay cheap to write
+const MAX_RECOVERY_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug)]
+enum RecoveryJob {
+    Write { file_name: String, contents: String },
+    Remove { file_name: String },
+}
+
+impl RecoveryJob {
+    fn file_name(&self) -> &str {
+        match self {
+            Self::Write { file_name, .. } | Self::Remove { file_name } => file_name,
+        }
+    }
+}
+
+/// Writes copies of the notes that are not saved yet into a folder, from a thread of its own
+/// so typing never waits for them.
+///
+/// Jobs are run in the order they are queued. The ones queued while a previous one is run are
+/// merged, so only the last copy of each note is written.
+#[derive(Debug, Clone)]
+pub struct RecoveryWriter {
+    sender: mpsc::Sender<RecoveryJob>,
+}
+
+impl RecoveryWriter {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let (sender, receiver) = mpsc::channel::<RecoveryJob>();
+
+        // Ends once every sender is dropped
+        thread::spawn(move || {
+            while let Ok(job) = receiver.recv() {
+                let mut jobs = IndexMap::new();
+                for job in std::iter::once(job).chain(receiver.try_iter()) {
+                    // Moved to the end, where the last one of the note was queued
+                    jobs.shift_remove(job.file_name());
+                    jobs.insert(job.file_name().to_string(), job);
+                }
+
+                for job in jobs.into_values() {
+                    if let Err(err) = run_job(&dir, &job) {
+                        log::warn!(
+                            "Failed to update recovery copy `{}`: {:?}",
+                            job.file_name(),
+                            err
+                        );
+                    }
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Queue writing `contents` as the copy of the note stored in `file_name`. Returns false
+    /// when it is too large to be kept.
+    pub fn write(&self, file_name: &str, contents: String) -> bool {
+        if contents.len() > MAX_RECOVERY_BYTES {
+            self.remove(file_name);
+            return false;
+        }
+
+        self.send(RecoveryJob::Write {
+            file_name: file_name.to_string(),
+            contents,
+        });
+        true
+    }
+
+    /// Queue removing the copy of the note stored in `file_name`, once it is saved
+    pub fn remove(&self, file_name: &str) {
+        self.send(RecoveryJob::Remove {
+            file_name: file_name.to_string(),
+        });
+    }
+
+    fn send(&self, job: RecoveryJob) {
+        if let Err(err) = self.sender.send(job) {
+            log::warn!("Failed to queue recovery job: {:?}", err.0);
+        }
+    }
+}
+
+fn run_job(dir: &Path, job: &RecoveryJob) -> anyhow::Result<()> {
+    match job {
+        RecoveryJob::Write {
+            file_name,
+            contents,
+        } => {
+            fs::create_dir_all(dir)?;
+            let path = resolve_within(dir, file_name)?;
+
+            // Renamed once written, so a crash while writing never leaves half a copy
+            let mut partial_file_name = OsString::from(".");
+            partial_file_name.push(path.file_name().unwrap_or_default());
+            partial_file_name.push(".partial");
+            let partial_path = path.with_file_name(partial_file_name);
+
+            fs::write(&partial_path, contents)?;
+            fs::rename(&partial_path, &path)?;
+            Ok(())
+        }
+        RecoveryJob::Remove { file_name } => {
+            match fs::remove_file(resolve_within(dir, file_name)?) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// A copy written by [`RecoveryWriter`] that was still there on startup
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredNote {
+    /// Name of the file of the note it is a copy of
+    pub file_name: String,
+    pub contents: String,
+    pub modified: SystemTime,
+}
+
+/// The recovered notes, by how they compare to the notes they are copies of
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    /// Newer than their note, so they have changes that were never saved
+    pub unsaved: Vec<RecoveredNote>,
+    /// Their note no longer exists
+    pub orphaned: Vec<RecoveredNote>,
+    /// Their note was saved after them, so they can be removed
+    pub outdated: Vec<RecoveredNote>,
+}
+
+/// Read the copies in `dir`, which may not exist when nothing was ever written
+pub fn read_recovered_notes(dir: &Path) -> io::Result<Vec<RecoveredNote>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut recovered_notes = Vec::new();
+
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        // Left by a crash while writing
+        if file_name.starts_with('.') {
+            fs::remove_file(entry.path())?;
+            continue;
+        }
+
+        recovered_notes.push(RecoveredNote {
+            file_name,
+            contents: fs::read_to_string(entry.path())?,
+            modified: entry.metadata()?.modified()?,
+        });
+    }
+
+    recovered_notes.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    Ok(recovered_notes)
+}
+
+/// Compare each of `recovered_notes` with when its note was last written, as given by
+/// `note_modified`, which is `None` when the note no longer exists
+pub fn reconcile(
+    recovered_notes: Vec<RecoveredNote>,
+    note_modified: impl Fn(&str) -> Option<SystemTime>,
+) -> Reconciliation {
+    let mut reconciliation = Reconciliation::default();
+
+    for recovered_note in recovered_notes {
+        match note_modified(&recovered_note.file_name) {
+            None => reconciliation.orphaned.push(recovered_note),
+            Some(modified) if modified >= recovered_note.modified => {
+                reconciliation.outdated.push(recovered_note);
+            }
+            Some(_) => reconciliation.unsaved.push(recovered_note),
+        }
+    }
+
+    reconciliation
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn recovered_note(file_name: &str, modified: SystemTime) -> RecoveredNote {
+        RecoveredNote {
+            file_name: file_name.to_string(),
+            contents: format!("Contents of {}", file_name),
+            modified,
+        }
+    }
+
+    #[test]
+    fn reconcile_newer_older_and_missing() {
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(60 * 60);
+
+        let newer = recovered_note("Newer.md", now);
+        let older = recovered_note("Older.md", now - hour);
+        let missing = recovered_note("Missing.md", now);
+
+        let reconciliation = reconcile(
+            vec![newer.clone(), older.clone(), missing.clone()],
+            |file_name| match file_name {
+                "Newer.md" => Some(now - hour),
+                "Older.md" => Some(now),
+                _ => None,
+            },
+        );
+
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                unsaved: vec![newer],
+                orphaned: vec![missing],
+                outdated: vec![older],
+            }
+        );
+    }
+
+    #[test]
+    fn write_then_remove() {
+        let dir =
+            std::env::temp_dir().join(format!("noteworthy-recovery-write-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let writer = RecoveryWriter::new(&dir);
+        assert!(writer.write("A.md", "First".into()));
+        assert!(writer.write("A.md", "Second".into()));
+        assert!(writer.write("B.md", "Other".into()));
+        writer.remove("B.md");
+        assert!(!writer.write("C.md", "C".repeat(MAX_RECOVERY_BYTES + 1)));
+
+        // Jobs are run in order, so this one is the last done
+        writer.write("Done.md", String::new());
+        while !dir.join("Done.md").exists() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let recovered_notes = read_recovered_notes(&dir).unwrap();
+        let file_names = recovered_notes
+            .iter()
+            .map(|recovered_note| recovered_note.file_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(file_names, ["A.md", "Done.md"]);
+        assert_eq!(recovered_notes[0].contents, "Second");
+
+        assert!(read_recovered_notes(&dir.join("Missing"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn unsafe_file_name() {
+        let dir =
+            std::env::temp_dir().join(format!("noteworthy-recovery-unsafe-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let job = RecoveryJob::Write {
+            file_name: "../Escaped.md".to_string(),
+            contents: "Escaped".to_string(),
+        };
+        assert!(run_job(&dir.join("recovery"), &job).is_err());
+        assert!(!dir.join("Escaped.md").exists());
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{Local, LocalResult, NaiveDateTime, TimeZone, Utc};
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, hash::Hash};
+
+/// When to remind about a note. It is stored in UTC, so it stays at the same instant when
+/// the timezone or DST offset changes.
+#[derive(
+    Debug, Clone, Copy, glib::Boxed, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[boxed_type(name = "NwtyReminder")]
+#[serde(transparent)]
+pub struct Reminder(chrono::DateTime<Utc>);
+
+impl Reminder {
+    pub const fn new(datetime: chrono::DateTime<Utc>) -> Self {
+        Self(datetime)
+    }
+
+    /// Remind at the wall clock time `naive` in the local timezone
+    pub fn from_local(naive: NaiveDateTime) -> Self {
+        Self::from_naive(naive, &Local)
+    }
+
+    /// Remind at the wall clock time `naive` in `tz`.
+    ///
+    /// If the clock is turned back and `naive` happens twice, the first one is used. If it
+    /// skips over `naive`, the time an hour later is used instead.
+    pub fn from_naive<Tz: TimeZone>(naive: NaiveDateTime, tz: &Tz) -> Self {
+        let datetime = match tz.from_local_datetime(&naive) {
+            LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => datetime,
+            LocalResult::None => tz
+                .from_local_datetime(&(naive + chrono::Duration::hours(1)))
+                .earliest()
+                .unwrap_or_else(|| tz.from_utc_datetime(&naive)),
+        };
+
+        Self(datetime.with_timezone(&Utc))
+    }
+
+    pub fn to_local(self) -> chrono::DateTime<Local> {
+        self.0.with_timezone(&Local)
+    }
+
+    pub fn is_due(self, now: chrono::DateTime<Utc>) -> bool {
+        self.0 <= now
+    }
+
+    pub fn display(self) -> String {
+        self.to_local().format("%b %d, %H∶%M").to_string() // Sep 03, 08∶10
+    }
+}
+
+/// Keeps track of the reminders that were already notified, so each is only notified once
+/// per run of the app
+#[derive(Debug)]
+pub struct DueReminders<K> {
+    notified: HashMap<K, Reminder>,
+}
+
+impl<K> Default for DueReminders<K> {
+    fn default() -> Self {
+        Self {
+            notified: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> DueReminders<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the keys of the `reminders` that are due at `now` and were not returned before.
+    ///
+    /// Reminders that passed while the app was closed are due on the first call. A key is
+    /// returned again if its reminder is changed to another time.
+    pub fn take_due(
+        &mut self,
+        reminders: impl IntoIterator<Item = (K, Reminder)>,
+        now: chrono::DateTime<Utc>,
+    ) -> Vec<K> {
+        let mut due = Vec::new();
+        let mut notified = HashMap::new();
+
+        for (key, reminder) in reminders {
+            if !reminder.is_due(now) {
+                continue;
+            }
+
+            if self.notified.get(&key) != Some(&reminder) {
+                due.push(key.clone());
+            }
+
+            notified.insert(key, reminder);
+        }
+
+        // Forget the ones that were removed, so they are notified again if set back
+        self.notified = notified;
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDate};
+
+    fn utc(hour: u32, min: u32) -> chrono::DateTime<Utc> {
+        Utc.ymd(2022, 3, 27).and_hms(hour, min, 0)
+    }
+
+    #[test]
+    fn from_naive() {
+        let naive = NaiveDate::from_ymd(2022, 3, 27).and_hms(9, 30, 0);
+        let tz = FixedOffset::east(8 * 3600);
+
+        assert_eq!(Reminder::from_naive(naive, &tz), Reminder::new(utc(1, 30)));
+    }
+
+    #[test]
+    fn is_due() {
+        let reminder = Reminder::new(utc(10, 0));
+        assert!(!reminder.is_due(utc(9, 59)));
+        assert!(reminder.is_due(utc(10, 0)));
+        assert!(reminder.is_due(utc(10, 1)));
+    }
+
+    #[test]
+    fn is_due_in_other_timezone() {
+        // Same instant as 10:00 UTC
+        let now = FixedOffset::west(5 * 3600)
+            .ymd(2022, 3, 27)
+            .and_hms(5, 0, 0)
+            .with_timezone(&Utc);
+        assert!(Reminder::new(utc(10, 0)).is_due(now));
+        assert!(!Reminder::new(utc(10, 1)).is_due(now));
+    }
+
+    #[test]
+    fn serialize_then_deserialize() {
+        let reminder = Reminder::new(utc(10, 0));
+        let text = serde_yaml::to_string(&reminder).unwrap();
+        assert!(text.contains("2022-03-27T10:00:00Z"));
+        assert_eq!(serde_yaml::from_str::<Reminder>(&text).unwrap(), reminder);
+    }
+
+    #[test]
+    fn take_due() {
+        let mut due_reminders = DueReminders::new();
+        let reminders = [
+            ("a", Reminder::new(utc(10, 0))),
+            ("b", Reminder::new(utc(11, 0))),
+        ];
+
+        assert!(due_reminders.take_due(reminders, utc(9, 0)).is_empty());
+        assert_eq!(due_reminders.take_due(reminders, utc(10, 0)), vec!["a"]);
+        // Already notified
+        assert!(due_reminders.take_due(reminders, utc(10, 30)).is_empty());
+        assert_eq!(due_reminders.take_due(reminders, utc(11, 30)), vec!["b"]);
+    }
+
+    #[test]
+    fn take_due_missed_while_closed() {
+        let mut due_reminders = DueReminders::new();
+        let reminders = [
+            ("a", Reminder::new(utc(1, 0))),
+            ("b", Reminder::new(utc(2, 0))),
+            ("c", Reminder::new(utc(23, 0))),
+        ];
+
+        let mut due = due_reminders.take_due(reminders, utc(12, 0));
+        due.sort_unstable();
+        assert_eq!(due, vec!["a", "b"]);
+        assert!(due_reminders.take_due(reminders, utc(12, 1)).is_empty());
+    }
+
+    #[test]
+    fn take_due_changed_reminder() {
+        let mut due_reminders = DueReminders::new();
+
+        assert_eq!(
+            due_reminders.take_due([("a", Reminder::new(utc(10, 0)))], utc(10, 0)),
+            vec!["a"]
+        );
+        assert_eq!(
+            due_reminders.take_due([("a", Reminder::new(utc(10, 5)))], utc(10, 5)),
+            vec!["a"]
+        );
+
+        // Removed then set back to the same time
+        assert!(due_reminders.take_due([], utc(10, 6)).is_empty());
+        assert_eq!(
+            due_reminders.take_due([("a", Reminder::new(utc(10, 5)))], utc(10, 7)),
+            vec!["a"]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+use gettextrs::gettext;
+use gtk::glib;
+
+/// Days from the start to the end, both included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl DateRange {
+    /// Range between `a` and `b`, in whichever order they are given
+    pub fn new(a: NaiveDate, b: NaiveDate) -> Self {
+        if a <= b {
+            Self { start: a, end: b }
+        } else {
+            Self { start: b, end: a }
+        }
+    }
+
+    pub const fn start(self) -> NaiveDate {
+        self.start
+    }
+
+    pub const fn end(self) -> NaiveDate {
+        self.end
+    }
+
+    pub fn contains(self, day: NaiveDate) -> bool {
+        self.start <= day && day <= self.end
+    }
+}
+
+/// Which notes are shown in the review, by the day they were last edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "NwtyReviewPeriod")]
+pub enum ReviewPeriod {
+    ThisWeek,
+    ThisMonth,
+    Custom(DateRange),
+}
+
+impl Default for ReviewPeriod {
+    fn default() -> Self {
+        Self::ThisWeek
+    }
+}
+
+impl ReviewPeriod {
+    /// Days in the period when it is `today`. Weeks start on Monday.
+    pub fn range(self, today: NaiveDate) -> DateRange {
+        match self {
+            Self::ThisWeek => {
+                let days_since_monday = today.weekday().num_days_from_monday();
+                let start = today - Duration::days(i64::from(days_since_monday));
+                DateRange::new(start, start + Duration::days(6))
+            }
+            Self::ThisMonth => {
+                let start = NaiveDate::from_ymd(today.year(), today.month(), 1);
+                let next_month_start = if today.month() == 12 {
+                    NaiveDate::from_ymd(today.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(today.year(), today.month() + 1, 1)
+                };
+                DateRange::new(start, next_month_start.pred())
+            }
+            Self::Custom(range) => range,
+        }
+    }
+
+    pub fn display_name(self) -> String {
+        match self {
+            Self::ThisWeek => gettext("This Week"),
+            Self::ThisMonth => gettext("This Month"),
+            Self::Custom(range) => {
+                // Sep 03 – Sep 10
+                format!(
+                    "{} – {}",
+                    range.start().format("%b %d"),
+                    range.end().format("%b %d")
+                )
+            }
+        }
+    }
+}
+
+/// Day of `time` on the wall clock in `tz`, which is what notes are grouped by in the review
+pub fn day_in<Tz: TimeZone, Tz2: TimeZone>(time: &chrono::DateTime<Tz>, tz: &Tz2) -> NaiveDate {
+    time.with_timezone(tz).naive_local().date()
+}
+
+/// Title of the group of notes last edited on `day`
+pub fn day_title(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        gettext("Today")
+    } else if day == today.pred() {
+        gettext("Yesterday")
+    } else if day.year() == today.year() {
+        day.format("%A, %B %d").to_string() // Saturday, September 03
+    } else {
+        day.format("%A, %B %d %Y").to_string() // Saturday, September 03 1920
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    #[test]
+    fn day_in_timezone() {
+        let time = Utc.ymd(2022, 3, 1).and_hms(23, 30, 0);
+
+        assert_eq!(day_in(&time, &Utc), date(2022, 3, 1));
+        assert_eq!(
+            day_in(&time, &FixedOffset::east(2 * 3600)),
+            date(2022, 3, 2)
+        );
+        assert_eq!(
+            day_in(&time, &FixedOffset::west(5 * 3600)),
+            date(2022, 3, 1)
+        );
+    }
+
+    #[test]
+    fn day_in_around_midnight() {
+        let tz = FixedOffset::east(2 * 3600);
+
+        // 23:59:59 and 00:00:00 on the wall clock in `tz`
+        let before_midnight = Utc.ymd(2022, 3, 1).and_hms(21, 59, 59);
+        let at_midnight = Utc.ymd(2022, 3, 1).and_hms(22, 0, 0);
+
+        assert_eq!(day_in(&before_midnight, &tz), date(2022, 3, 1));
+        assert_eq!(day_in(&at_midnight, &tz), date(2022, 3, 2));
+
+        // The day does not depend on the timezone the time was stored in
+        let stored = at_midnight.with_timezone(&FixedOffset::west(8 * 3600));
+        assert_eq!(day_in(&stored, &tz), date(2022, 3, 2));
+    }
+
+    #[test]
+    fn this_week() {
+        let week = DateRange::new(date(2022, 2, 28), date(2022, 3, 6));
+
+        // Monday, Wednesday, and Sunday
+        assert_eq!(ReviewPeriod::ThisWeek.range(date(2022, 2, 28)), week);
+        assert_eq!(ReviewPeriod::ThisWeek.range(date(2022, 3, 2)), week);
+        assert_eq!(ReviewPeriod::ThisWeek.range(date(2022, 3, 6)), week);
+
+        assert_ne!(ReviewPeriod::ThisWeek.range(date(2022, 3, 7)), week);
+    }
+
+    #[test]
+    fn this_month() {
+        assert_eq!(
+            ReviewPeriod::ThisMonth.range(date(2022, 2, 14)),
+            DateRange::new(date(2022, 2, 1), date(2022, 2, 28))
+        );
+        assert_eq!(
+            ReviewPeriod::ThisMonth.range(date(2024, 2, 1)),
+            DateRange::new(date(2024, 2, 1), date(2024, 2, 29))
+        );
+        assert_eq!(
+            ReviewPeriod::ThisMonth.range(date(2022, 12, 31)),
+            DateRange::new(date(2022, 12, 1), date(2022, 12, 31))
+        );
+    }
+
+    #[test]
+    fn custom() {
+        let range = DateRange::new(date(2022, 3, 10), date(2022, 3, 1));
+        assert_eq!(range.start(), date(2022, 3, 1));
+        assert_eq!(range.end(), date(2022, 3, 10));
+
+        assert_eq!(ReviewPeriod::Custom(range).range(date(2000, 1, 1)), range);
+    }
+
+    #[test]
+    fn range_contains() {
+        let range = DateRange::new(date(2022, 3, 1), date(2022, 3, 10));
+
+        assert!(!range.contains(date(2022, 2, 28)));
+        assert!(range.contains(date(2022, 3, 1)));
+        assert!(range.contains(date(2022, 3, 10)));
+        assert!(!range.contains(date(2022, 3, 11)));
+    }
+
+    #[test]
+    fn titles() {
+        let today = date(2022, 3, 1);
+
+        assert_eq!(day_title(today, today), "Today");
+        assert_eq!(day_title(date(2022, 2, 28), today), "Yesterday");
+        assert_eq!(day_title(date(2022, 2, 26), today), "Saturday, February 26");
+        assert_eq!(
+            day_title(date(2021, 12, 31), today),
+            "Friday, December 31 2021"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use indexmap::IndexMap;
+
+use std::sync::Arc;
+
+/// Enough for many versions of a usual note, or a few of a large one
+const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Contents of the versions of a file that were looked at recently, so going back and forth
+/// between them doesn't read them from the repository again.
+///
+/// The least recently used versions are dropped once all of them take more than `max_bytes`,
+/// except the last one added, which is kept even when it is larger than that by itself.
+#[derive(Debug)]
+pub struct RevisionCache {
+    max_bytes: usize,
+    n_bytes: usize,
+    /// From the least to the most recently used
+    entries: IndexMap<String, Arc<str>>,
+}
+
+impl RevisionCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            n_bytes: 0,
+            entries: IndexMap::new(),
+        }
+    }
+
+    /// The contents at `revision_id`, marking them as the most recently used
+    pub fn get(&mut self, revision_id: &str) -> Option<Arc<str>> {
+        let content = self.entries.shift_remove(revision_id)?;
+        self.entries
+            .insert(revision_id.to_string(), Arc::clone(&content));
+        Some(content)
+    }
+
+    pub fn insert(&mut self, revision_id: String, content: Arc<str>) {
+        self.n_bytes += content.len();
+
+        if let Some(previous) = self.entries.shift_remove(&revision_id) {
+            self.n_bytes -= previous.len();
+        }
+        self.entries.insert(revision_id, content);
+
+        while self.n_bytes > self.max_bytes && self.entries.len() > 1 {
+            if let Some((_, evicted)) = self.entries.shift_remove_index(0) {
+                self.n_bytes -= evicted.len();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.n_bytes = 0;
+    }
+}
+
+impl Default for RevisionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn content(text: &str) -> Arc<str> {
+        Arc::from(text)
+    }
+
+    #[test]
+    fn get_and_insert() {
+        let mut cache = RevisionCache::new(100);
+        assert_eq!(cache.get("a"), None);
+
+        cache.insert("a".into(), content("A"));
+        cache.insert("b".into(), content("B"));
+        assert_eq!(cache.get("a"), Some(content("A")));
+        assert_eq!(cache.get("b"), Some(content("B")));
+
+        cache.insert("a".into(), content("New A"));
+        assert_eq!(cache.get("a"), Some(content("New A")));
+        assert_eq!(cache.n_bytes, 6);
+
+        cache.clear();
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.n_bytes, 0);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = RevisionCache::new(10);
+        cache.insert("a".into(), content("aaaa"));
+        cache.insert("b".into(), content("bbbb"));
+
+        // Used last, so `b` goes first
+        cache.get("a");
+        cache.insert("c".into(), content("cccc"));
+
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(content("aaaa")));
+        assert_eq!(cache.get("c"), Some(content("cccc")));
+        assert_eq!(cache.n_bytes, 8);
+    }
+
+    #[test]
+    fn keeps_last_even_when_too_large() {
+        let mut cache = RevisionCache::new(4);
+        cache.insert("a".into(), content("aa"));
+        cache.insert("big".into(), content("0123456789"));
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("big"), Some(content("0123456789")));
+        assert_eq!(cache.n_bytes, 10);
+    }
+}
@@ -0,0 +1,169 @@
+use futures_util::stream::{self, StreamExt};
+
+use std::future::Future;
+
+/// How many files are written at once, so a lot of them don't all wait on the disk together
+pub const MAX_WRITES_IN_FLIGHT: usize = 4;
+
+/// Notes that could not be saved, named by their titles
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to save {}", .titles.join(", "))]
+pub struct NotesNotSaved {
+    pub titles: Vec<String>,
+}
+
+/// Run `write` on each of `items`, with at most `max_in_flight` of them at once. `progress` is
+/// called with the number of finished items after each of them.
+///
+/// Returns the items that failed with their errors, in the order they finished.
+pub async fn write_batch<T, F, Fut>(
+    items: Vec<T>,
+    max_in_flight: usize,
+    write: F,
+    mut progress: impl FnMut(usize),
+) -> Vec<(T, anyhow::Error)>
+where
+    F: Fn(&T) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let write = &write;
+    let mut results = stream::iter(items)
+        .map(|item| async move {
+            let res = write(&item).await;
+            (item, res)
+        })
+        .buffer_unordered(max_in_flight.max(1));
+
+    let mut n_finished = 0;
+    let mut failed = Vec::new();
+
+    while let Some((item, res)) = results.next().await {
+        n_finished += 1;
+        progress(n_finished);
+
+        if let Err(err) = res {
+            failed.push((item, err));
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use gtk::glib;
+
+    use std::{
+        cell::{Cell, RefCell},
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    /// Pending for `n_polls` polls, like a write that takes a while
+    struct SlowWrite {
+        n_polls: usize,
+    }
+
+    impl Future for SlowWrite {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.n_polls == 0 {
+                return Poll::Ready(());
+            }
+
+            self.n_polls -= 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn limited_writes_in_flight() {
+        let n_in_flight = Cell::new(0);
+        let max_seen_in_flight = Cell::new(0);
+        let written = RefCell::new(Vec::new());
+        let progress = RefCell::new(Vec::new());
+
+        let failed = glib::MainContext::new().block_on(write_batch(
+            (0..10).collect(),
+            4,
+            |item: &usize| {
+                let item = *item;
+                let (n_in_flight, max_seen_in_flight, written) =
+                    (&n_in_flight, &max_seen_in_flight, &written);
+                async move {
+                    n_in_flight.set(n_in_flight.get() + 1);
+                    max_seen_in_flight.set(max_seen_in_flight.get().max(n_in_flight.get()));
+
+                    // Later items take less time, so they can finish first
+                    SlowWrite { n_polls: 20 - item }.await;
+
+                    n_in_flight.set(n_in_flight.get() - 1);
+                    written.borrow_mut().push(item);
+                    Ok(())
+                }
+            },
+            |n_finished| progress.borrow_mut().push(n_finished),
+        ));
+
+        assert!(failed.is_empty());
+        assert_eq!(max_seen_in_flight.get(), 4);
+        assert_eq!(n_in_flight.get(), 0);
+
+        let mut written = written.take();
+        assert_ne!(written, (0..10).collect::<Vec<_>>());
+        written.sort_unstable();
+        assert_eq!(written, (0..10).collect::<Vec<_>>());
+
+        assert_eq!(progress.take(), (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn failed_writes() {
+        let failed = glib::MainContext::new().block_on(write_batch(
+            vec!["a", "b", "c", "d", "e"],
+            2,
+            |item: &&str| {
+                let item = *item;
+                async move {
+                    SlowWrite { n_polls: 3 }.await;
+                    anyhow::ensure!(item != "b" && item != "d", "Disk is full");
+                    Ok(())
+                }
+            },
+            |_| {},
+        ));
+
+        let mut failed_items = failed.iter().map(|(item, _)| *item).collect::<Vec<_>>();
+        failed_items.sort_unstable();
+        assert_eq!(failed_items, ["b", "d"]);
+        assert!(failed
+            .iter()
+            .all(|(_, err)| err.to_string() == "Disk is full"));
+    }
+
+    #[test]
+    fn no_writes() {
+        let n_progress = Cell::new(0);
+        let failed = glib::MainContext::new().block_on(write_batch(
+            Vec::<()>::new(),
+            MAX_WRITES_IN_FLIGHT,
+            |_| async { Ok(()) },
+            |_| n_progress.set(n_progress.get() + 1),
+        ));
+
+        assert!(failed.is_empty());
+        assert_eq!(n_progress.get(), 0);
+    }
+
+    #[test]
+    fn not_saved_titles() {
+        let err = NotesNotSaved {
+            titles: vec!["Groceries".into(), "Ideas".into()],
+        };
+        assert_eq!(err.to_string(), "Failed to save Groceries, Ideas");
+    }
+}
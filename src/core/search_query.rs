@@ -0,0 +1,898 @@
+use chrono::{Duration, NaiveDate};
+use gettextrs::gettext;
+
+use std::fmt;
+
+/// A note as it is matched by a [`SearchQuery`]
+pub trait SearchTarget {
+    fn is_pinned(&self) -> bool;
+
+    /// Whether the note has the tag named `name`, compared like
+    /// [`is_same_tag_name`](super::is_same_tag_name)
+    fn has_tag(&self, name: &str) -> bool;
+
+    fn is_untagged(&self) -> bool;
+
+    /// Day the note was last edited in the local timezone
+    fn last_modified_day(&self) -> NaiveDate;
+
+    /// Title in lowercase
+    fn title(&self) -> &str;
+
+    /// Whether the content has `text`, which is in lowercase. Only called when some text is
+    /// not in the title, but may be called more than once.
+    fn content_contains(&self, text: &str) -> bool;
+}
+
+/// Day a `before:` or `after:` term compares to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Day {
+    Date(NaiveDate),
+    DaysAgo(u32),
+}
+
+impl Day {
+    /// Parse `2022-01-31`, `today`, `yesterday`, or a number of days or weeks ago like `7d`
+    /// and `2w`
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+
+        match text.as_str() {
+            "today" => return Some(Self::DaysAgo(0)),
+            "yesterday" => return Some(Self::DaysAgo(1)),
+            _ => (),
+        }
+
+        if let Some(days) = text.strip_suffix('d') {
+            return days.parse().ok().map(Self::DaysAgo);
+        }
+
+        if let Some(weeks) = text.strip_suffix('w') {
+            return weeks
+                .parse::<u32>()
+                .ok()
+                .and_then(|weeks| weeks.checked_mul(7))
+                .map(Self::DaysAgo);
+        }
+
+        NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .ok()
+            .map(Self::Date)
+    }
+
+    fn resolve(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Date(date) => date,
+            Self::DaysAgo(days) => today
+                .checked_sub_signed(Duration::days(i64::from(days)))
+                .unwrap_or(chrono::naive::MIN_DATE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TermKind {
+    /// In lowercase, matched on the title or the content
+    Text(String),
+    Tag(String),
+    Pinned,
+    Untagged,
+    /// Last edited before the day, not including it
+    Before(Day),
+    /// Last edited after the day, not including it
+    After(Day),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    kind: TermKind,
+    is_negated: bool,
+}
+
+impl Term {
+    fn matches(&self, target: &impl SearchTarget, today: NaiveDate) -> bool {
+        let is_match = match self.kind {
+            TermKind::Text(ref text) => {
+                target.title().contains(text.as_str()) || target.content_contains(text)
+            }
+            TermKind::Tag(ref name) => target.has_tag(name),
+            TermKind::Pinned => target.is_pinned(),
+            TermKind::Untagged => target.is_untagged(),
+            TermKind::Before(day) => target.last_modified_day() < day.resolve(today),
+            TermKind::After(day) => target.last_modified_day() > day.resolve(today),
+        };
+
+        is_match != self.is_negated
+    }
+}
+
+/// What is wrong with the text of a [`SearchQuery`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryErrorKind {
+    UnclosedQuote,
+    /// Nothing after the `-` that excludes a term
+    NothingToExclude,
+    /// A keyword, like `OR`, that is not between two terms
+    MisplacedKeyword(String),
+    /// A filter, like `tag:`, with nothing after it
+    MissingValue(String),
+    UnknownFilter(String),
+    /// A value of `is:` other than `pinned` and `untagged`
+    UnknownState(String),
+    InvalidDate(String),
+}
+
+/// Error parsing a [`SearchQuery`], with where in the text it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    kind: QueryErrorKind,
+    position: usize,
+}
+
+impl QueryError {
+    const fn new(kind: QueryErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+
+    pub const fn kind(&self) -> &QueryErrorKind {
+        &self.kind
+    }
+
+    /// Offset in characters of the part of the text the error is about
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            QueryErrorKind::UnclosedQuote => gettext("A quote is not closed"),
+            QueryErrorKind::NothingToExclude => {
+                gettext("Nothing to exclude after “-”, remove the space after it")
+            }
+            QueryErrorKind::MisplacedKeyword(ref keyword) => {
+                gettext!("“{}” must be between two terms", keyword)
+            }
+            QueryErrorKind::MissingValue(ref name) => gettext!("Nothing after “{}:”", name),
+            QueryErrorKind::UnknownFilter(ref name) => gettext!(
+                "Unknown filter “{}:”, put the term in quotes to search for it",
+                name
+            ),
+            QueryErrorKind::UnknownState(ref state) => gettext!(
+                "Unknown “is:{}”, expected “is:pinned” or “is:untagged”",
+                state
+            ),
+            QueryErrorKind::InvalidDate(ref date) => gettext!(
+                "Invalid date “{}”, expected one like “2022-01-31”, “today”, “7d”, or “2w”",
+                date
+            ),
+        };
+
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug)]
+enum Token {
+    Or(usize),
+    And(usize),
+    Term {
+        position: usize,
+        is_negated: bool,
+        /// In lowercase, like `tag` for `tag:work`
+        filter: Option<String>,
+        value: String,
+        is_quoted: bool,
+    },
+}
+
+/// Split `text` in keywords and terms
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let position = i;
+
+        let is_negated = chars[i] == '-';
+        if is_negated {
+            i += 1;
+            if chars.get(i).map_or(true, |c| c.is_whitespace()) {
+                return Err(QueryError::new(QueryErrorKind::NothingToExclude, position));
+            }
+        }
+
+        let name_end = (i..chars.len())
+            .find(|&j| !chars[j].is_alphabetic())
+            .unwrap_or(chars.len());
+        let filter = if name_end > i && chars.get(name_end) == Some(&':') {
+            let name = chars[i..name_end].iter().collect::<String>().to_lowercase();
+            i = name_end + 1;
+            Some(name)
+        } else {
+            None
+        };
+
+        let is_quoted = chars.get(i) == Some(&'"');
+        let value = if is_quoted {
+            let quote_position = i;
+            let end = (i + 1..chars.len())
+                .find(|&j| chars[j] == '"')
+                .ok_or_else(|| QueryError::new(QueryErrorKind::UnclosedQuote, quote_position))?;
+            let value = chars[i + 1..end].iter().collect::<String>();
+            i = end + 1;
+            value
+        } else {
+            let end = (i..chars.len())
+                .find(|&j| chars[j].is_whitespace())
+                .unwrap_or(chars.len());
+            let value = chars[i..end].iter().collect::<String>();
+            i = end;
+            value
+        };
+
+        let is_plain = !is_negated && filter.is_none() && !is_quoted;
+        let token = match value.as_str() {
+            "OR" if is_plain => Token::Or(position),
+            "AND" if is_plain => Token::And(position),
+            _ => Token::Term {
+                position,
+                is_negated,
+                filter,
+                value,
+                is_quoted,
+            },
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn filter_term(name: &str, value: &str, position: usize) -> Result<TermKind, QueryError> {
+    if !matches!(name, "tag" | "is" | "before" | "after") {
+        return Err(QueryError::new(
+            QueryErrorKind::UnknownFilter(name.to_string()),
+            position,
+        ));
+    }
+
+    if value.is_empty() {
+        return Err(QueryError::new(
+            QueryErrorKind::MissingValue(name.to_string()),
+            position,
+        ));
+    }
+
+    match name {
+        "tag" => Ok(TermKind::Tag(value.to_string())),
+        "is" => match value.to_lowercase().as_str() {
+            "pinned" => Ok(TermKind::Pinned),
+            "untagged" => Ok(TermKind::Untagged),
+            _ => Err(QueryError::new(
+                QueryErrorKind::UnknownState(value.to_string()),
+                position,
+            )),
+        },
+        _ => {
+            let day = Day::parse(value).ok_or_else(|| {
+                QueryError::new(QueryErrorKind::InvalidDate(value.to_string()), position)
+            })?;
+
+            if name == "before" {
+                Ok(TermKind::Before(day))
+            } else {
+                Ok(TermKind::After(day))
+            }
+        }
+    }
+}
+
+/// Add the `words` in a row to `group` as one term
+fn end_words(words: &mut Vec<String>, group: &mut Vec<Term>) {
+    if !words.is_empty() {
+        group.push(Term {
+            kind: TermKind::Text(words.join(" ").to_lowercase()),
+            is_negated: false,
+        });
+        words.clear();
+    }
+}
+
+/// Which notes a saved search or the search bar of the sidebar shows, parsed from text like
+/// `tag:work is:pinned after:7d`.
+///
+/// The terms are:
+/// - `tag:name`, notes with the tag, in any case
+/// - `is:pinned` and `is:untagged`
+/// - `before:day` and `after:day`, notes last edited before or after the day, not including
+///   it. The day is like `2022-01-31`, `today`, `yesterday`, or a number of days or weeks ago
+///   like `7d` and `2w`, so `after:7d` is the last seven days.
+/// - Any other words, matched as written on the title or the content, in any case. Words in
+///   quotes are matched as written even when they look like a filter.
+///
+/// A term is excluded with a `-` before it. Every term has to match, and `OR` matches either
+/// the terms before it or the ones after it. `AND` can be written between terms, but it is
+/// the same as not writing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Any of the groups has to match, with every term of it
+    groups: Vec<Vec<Term>>,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            groups: vec![Vec::new()],
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let mut groups = vec![Vec::new()];
+        // Consecutive words are matched together, as they were before there were filters
+        let mut words = Vec::new();
+        // Keyword that has yet to be followed by a term
+        let mut pending_keyword: Option<(&str, usize)> = None;
+
+        for token in tokenize(text)? {
+            let group = groups.last_mut().unwrap();
+
+            match token {
+                Token::Or(position) | Token::And(position) => {
+                    end_words(&mut words, group);
+
+                    let keyword = if matches!(token, Token::Or(_)) {
+                        "OR"
+                    } else {
+                        "AND"
+                    };
+                    let misplaced_keyword = match pending_keyword {
+                        Some(pending_keyword) => Some(pending_keyword),
+                        None if group.is_empty() => Some((keyword, position)),
+                        None => None,
+                    };
+
+                    if let Some((keyword, position)) = misplaced_keyword {
+                        return Err(QueryError::new(
+                            QueryErrorKind::MisplacedKeyword(keyword.to_string()),
+                            position,
+                        ));
+                    }
+
+                    if keyword == "OR" {
+                        groups.push(Vec::new());
+                    }
+                    pending_keyword = Some((keyword, position));
+                }
+                Token::Term {
+                    position,
+                    is_negated,
+                    filter,
+                    value,
+                    is_quoted,
+                } => {
+                    pending_keyword = None;
+
+                    if !is_negated && !is_quoted && filter.is_none() {
+                        words.push(value);
+                        continue;
+                    }
+
+                    end_words(&mut words, group);
+
+                    let kind = match filter {
+                        Some(ref name) => filter_term(name, &value, position)?,
+                        None => TermKind::Text(value.to_lowercase()),
+                    };
+                    group.push(Term { kind, is_negated });
+                }
+            }
+        }
+
+        end_words(&mut words, groups.last_mut().unwrap());
+
+        if let Some((keyword, position)) = pending_keyword {
+            return Err(QueryError::new(
+                QueryErrorKind::MisplacedKeyword(keyword.to_string()),
+                position,
+            ));
+        }
+
+        Ok(Self { groups })
+    }
+
+    /// Query that matches `text` as written, without filters
+    pub fn with_text(text: &str) -> Self {
+        let text = text.trim().to_lowercase();
+
+        if text.is_empty() {
+            return Self::default();
+        }
+
+        Self {
+            groups: vec![vec![Term {
+                kind: TermKind::Text(text),
+                is_negated: false,
+            }]],
+        }
+    }
+
+    /// Whether it matches every note
+    pub fn is_empty(&self) -> bool {
+        self.groups.iter().any(Vec::is_empty)
+    }
+
+    /// The text it matches, when it only matches some text and not filters. It is empty
+    /// when it matches every note.
+    pub fn plain_text(&self) -> Option<&str> {
+        match self.groups.as_slice() {
+            [group] => match group.as_slice() {
+                [] => Some(""),
+                [Term {
+                    kind: TermKind::Text(text),
+                    is_negated: false,
+                }] => Some(text),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The texts it matches on the title or the content, including the excluded ones
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.groups
+            .iter()
+            .flatten()
+            .filter_map(|term| match term.kind {
+                TermKind::Text(ref text) => Some(text.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Whether `target` matches, with days like `7d` counted from `today`
+    pub fn matches(&self, target: &impl SearchTarget, today: NaiveDate) -> bool {
+        self.groups
+            .iter()
+            .any(|group| group.iter().all(|term| term.matches(target, today)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::is_same_tag_name;
+
+    use std::cell::Cell;
+
+    struct TestNote {
+        title: String,
+        content: String,
+        tags: Vec<String>,
+        is_pinned: bool,
+        last_modified_day: NaiveDate,
+        n_content_reads: Cell<u32>,
+    }
+
+    impl TestNote {
+        fn new(title: &str, content: &str, tags: &[&str]) -> Self {
+            Self {
+                title: title.to_lowercase(),
+                content: content.to_lowercase(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                is_pinned: false,
+                last_modified_day: day(15),
+                n_content_reads: Cell::new(0),
+            }
+        }
+    }
+
+    impl SearchTarget for TestNote {
+        fn is_pinned(&self) -> bool {
+            self.is_pinned
+        }
+
+        fn has_tag(&self, name: &str) -> bool {
+            self.tags.iter().any(|tag| is_same_tag_name(tag, name))
+        }
+
+        fn is_untagged(&self) -> bool {
+            self.tags.is_empty()
+        }
+
+        fn last_modified_day(&self) -> NaiveDate {
+            self.last_modified_day
+        }
+
+        fn title(&self) -> &str {
+            &self.title
+        }
+
+        fn content_contains(&self, text: &str) -> bool {
+            self.n_content_reads.set(self.n_content_reads.get() + 1);
+            self.content.contains(text)
+        }
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2022, 3, day)
+    }
+
+    fn today() -> NaiveDate {
+        day(20)
+    }
+
+    fn matches(query: &str, note: &TestNote) -> bool {
+        SearchQuery::parse(query).unwrap().matches(note, today())
+    }
+
+    fn error(query: &str) -> QueryError {
+        SearchQuery::parse(query).unwrap_err()
+    }
+
+    fn text(text: &str) -> Term {
+        Term {
+            kind: TermKind::Text(text.to_string()),
+            is_negated: false,
+        }
+    }
+
+    fn term(kind: TermKind) -> Term {
+        Term {
+            kind,
+            is_negated: false,
+        }
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_eq!(SearchQuery::parse("").unwrap(), SearchQuery::default());
+        assert_eq!(SearchQuery::parse("  \t ").unwrap(), SearchQuery::default());
+        assert!(SearchQuery::default().is_empty());
+    }
+
+    #[test]
+    fn parse_filters() {
+        let query = SearchQuery::parse("tag:Work is:pinned IS:Untagged").unwrap();
+        assert_eq!(
+            query.groups,
+            [[
+                term(TermKind::Tag("Work".to_string())),
+                term(TermKind::Pinned),
+                term(TermKind::Untagged),
+            ]]
+        );
+    }
+
+    #[test]
+    fn parse_dates() {
+        let query =
+            SearchQuery::parse("after:2022-01-31 before:today after:yesterday after:7d before:2W")
+                .unwrap();
+        assert_eq!(
+            query.groups,
+            [[
+                term(TermKind::After(Day::Date(NaiveDate::from_ymd(2022, 1, 31)))),
+                term(TermKind::Before(Day::DaysAgo(0))),
+                term(TermKind::After(Day::DaysAgo(1))),
+                term(TermKind::After(Day::DaysAgo(7))),
+                term(TermKind::Before(Day::DaysAgo(14))),
+            ]]
+        );
+    }
+
+    #[test]
+    fn parse_words() {
+        // Consecutive words are one term
+        let query = SearchQuery::parse("  Meeting  Notes tag:work Monday").unwrap();
+        assert_eq!(
+            query.groups,
+            [[
+                text("meeting notes"),
+                term(TermKind::Tag("work".to_string())),
+                text("monday"),
+            ]]
+        );
+
+        // Unless they are separated with AND
+        let query = SearchQuery::parse("meeting AND notes").unwrap();
+        assert_eq!(query.groups, [[text("meeting"), text("notes")]]);
+
+        // Keywords are only in uppercase
+        let query = SearchQuery::parse("this or that and more").unwrap();
+        assert_eq!(query.groups, [[text("this or that and more")]]);
+
+        // Words that are not filters
+        let query = SearchQuery::parse("10:30 :x x1:y").unwrap();
+        assert_eq!(query.groups, [[text("10:30 :x x1:y")]]);
+    }
+
+    #[test]
+    fn parse_quotes() {
+        let query = SearchQuery::parse(r#""tag:work"  "Two  Spaces" tag:"Side Project""#).unwrap();
+        assert_eq!(
+            query.groups,
+            [[
+                text("tag:work"),
+                text("two  spaces"),
+                term(TermKind::Tag("Side Project".to_string())),
+            ]]
+        );
+
+        let query = SearchQuery::parse(r#""OR" AND"#);
+        assert_eq!(
+            query.unwrap_err().kind(),
+            &QueryErrorKind::MisplacedKeyword("AND".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_negated() {
+        let query = SearchQuery::parse(r#"-tag:work -is:pinned -draft -"to do""#).unwrap();
+        let negated = |kind| Term {
+            kind,
+            is_negated: true,
+        };
+        assert_eq!(
+            query.groups,
+            [[
+                negated(TermKind::Tag("work".to_string())),
+                negated(TermKind::Pinned),
+                negated(TermKind::Text("draft".to_string())),
+                negated(TermKind::Text("to do".to_string())),
+            ]]
+        );
+
+        // Only at the start of a term
+        let query = SearchQuery::parse("well-known").unwrap();
+        assert_eq!(query.groups, [[text("well-known")]]);
+    }
+
+    #[test]
+    fn parse_or() {
+        let query = SearchQuery::parse("is:pinned OR tag:work AND tag:urgent OR todo").unwrap();
+        assert_eq!(
+            query.groups,
+            [
+                vec![term(TermKind::Pinned)],
+                vec![
+                    term(TermKind::Tag("work".to_string())),
+                    term(TermKind::Tag("urgent".to_string())),
+                ],
+                vec![text("todo")],
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_quote() {
+        let err = error(r#"tag:work "to do"#);
+        assert_eq!(err.kind(), &QueryErrorKind::UnclosedQuote);
+        assert_eq!(err.position(), 9);
+
+        assert_eq!(error(r#"tag:"work"#).position(), 4);
+    }
+
+    #[test]
+    fn nothing_to_exclude() {
+        let err = error("work - draft");
+        assert_eq!(err.kind(), &QueryErrorKind::NothingToExclude);
+        assert_eq!(err.position(), 5);
+
+        assert_eq!(error("work -").kind(), &QueryErrorKind::NothingToExclude);
+    }
+
+    #[test]
+    fn misplaced_keyword() {
+        let misplaced = |keyword: &str| QueryErrorKind::MisplacedKeyword(keyword.to_string());
+
+        for (query, keyword, position) in [
+            ("OR work", "OR", 0),
+            ("work OR", "OR", 5),
+            ("work AND", "AND", 5),
+            ("AND work", "AND", 0),
+            ("work OR AND home", "OR", 5),
+            ("work AND OR home", "AND", 5),
+            ("work OR OR home", "OR", 5),
+        ] {
+            let err = error(query);
+            assert_eq!(err.kind(), &misplaced(keyword), "{}", query);
+            assert_eq!(err.position(), position, "{}", query);
+        }
+    }
+
+    #[test]
+    fn invalid_filters() {
+        let err = error("work http://example.com");
+        assert_eq!(
+            err.kind(),
+            &QueryErrorKind::UnknownFilter("http".to_string())
+        );
+        assert_eq!(err.position(), 5);
+
+        assert_eq!(
+            error("tag:").kind(),
+            &QueryErrorKind::MissingValue("tag".to_string())
+        );
+        assert_eq!(
+            error(r#"tag:"""#).kind(),
+            &QueryErrorKind::MissingValue("tag".to_string())
+        );
+        assert_eq!(
+            error("is:starred").kind(),
+            &QueryErrorKind::UnknownState("starred".to_string())
+        );
+
+        for date in [
+            "2022-02-30",
+            "31-01-2022",
+            "d",
+            "-1d",
+            "soon",
+            "99999999999w",
+        ] {
+            assert_eq!(
+                error(&format!("after:{}", date)).kind(),
+                &QueryErrorKind::InvalidDate(date.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn error_message() {
+        assert_eq!(
+            error("is:starred").to_string(),
+            "Unknown “is:starred”, expected “is:pinned” or “is:untagged”"
+        );
+        assert_eq!(error("- work").to_string(), error("-").to_string());
+    }
+
+    #[test]
+    fn matches_text() {
+        let note = TestNote::new("Groceries", "Eggs, Milk and Bread", &[]);
+
+        assert!(matches("", &note));
+        assert!(matches("GROC", &note));
+        assert!(matches("milk and", &note));
+        assert!(matches("bread AND eggs", &note));
+        assert!(!matches("bread eggs", &note));
+        assert!(!matches("groceries -milk", &note));
+        assert!(matches(r#""and bread""#, &note));
+    }
+
+    #[test]
+    fn matches_title_without_content() {
+        let note = TestNote::new("Groceries", "Eggs", &[]);
+
+        assert!(matches("groceries", &note));
+        assert_eq!(note.n_content_reads.get(), 0);
+
+        assert!(matches("eggs", &note));
+        assert_eq!(note.n_content_reads.get(), 1);
+    }
+
+    #[test]
+    fn matches_tags() {
+        let tagged = TestNote::new("A", "", &["Work", "Café"]);
+        let untagged = TestNote::new("B", "", &[]);
+
+        assert!(matches("tag:work", &tagged));
+        assert!(matches("tag:CAFE\u{301}", &tagged));
+        assert!(!matches("tag:wor", &tagged));
+        assert!(!matches("tag:work", &untagged));
+        assert!(matches("-tag:home", &tagged));
+
+        assert!(!matches("is:untagged", &tagged));
+        assert!(matches("is:untagged", &untagged));
+        assert!(matches("-is:untagged", &tagged));
+    }
+
+    #[test]
+    fn matches_pinned() {
+        let mut note = TestNote::new("A", "", &["Work"]);
+
+        assert!(!matches("is:pinned tag:work", &note));
+        note.is_pinned = true;
+        assert!(matches("is:pinned tag:work", &note));
+        assert!(!matches("-is:pinned", &note));
+    }
+
+    #[test]
+    fn matches_dates() {
+        let mut note = TestNote::new("A", "", &[]);
+
+        note.last_modified_day = day(15);
+        assert!(matches("after:2022-03-14", &note));
+        assert!(!matches("after:2022-03-15", &note));
+        assert!(matches("before:2022-03-16", &note));
+        assert!(!matches("before:2022-03-15", &note));
+
+        // Today is the 20th, so the last seven days are from the 14th
+        note.last_modified_day = day(14);
+        assert!(matches("after:7d", &note));
+        note.last_modified_day = day(13);
+        assert!(!matches("after:7d", &note));
+        assert!(matches("before:6d", &note));
+        assert!(!matches("before:1w", &note));
+
+        note.last_modified_day = today();
+        assert!(matches("after:yesterday", &note));
+        assert!(!matches("before:today", &note));
+        assert!(matches("after:2022-03-01 before:2022-04-01", &note));
+    }
+
+    #[test]
+    fn matches_distant_days() {
+        let note = TestNote::new("A", "", &[]);
+        assert!(matches("after:4000000000d", &note));
+    }
+
+    #[test]
+    fn matches_or() {
+        let mut work = TestNote::new("Report", "", &["Work"]);
+        let home = TestNote::new("Chores", "", &["Home"]);
+
+        let query = "tag:work is:pinned OR tag:home";
+        assert!(!matches(query, &work));
+        assert!(matches(query, &home));
+        work.is_pinned = true;
+        assert!(matches(query, &work));
+    }
+
+    #[test]
+    fn with_text() {
+        let query = SearchQuery::with_text(" Tag:Work ");
+        assert_eq!(query.plain_text(), Some("tag:work"));
+        assert_eq!(query, SearchQuery::parse(r#""tag:work""#).unwrap());
+
+        assert_eq!(SearchQuery::with_text(" "), SearchQuery::default());
+    }
+
+    #[test]
+    fn plain_text() {
+        let plain_text = |query| {
+            SearchQuery::parse(query)
+                .unwrap()
+                .plain_text()
+                .map(String::from)
+        };
+
+        assert_eq!(plain_text(""), Some(String::new()));
+        assert_eq!(
+            plain_text("Groceries List"),
+            Some("groceries list".to_string())
+        );
+        assert_eq!(plain_text("-milk"), None);
+        assert_eq!(plain_text("tag:work"), None);
+        assert_eq!(plain_text("a OR b"), None);
+        assert_eq!(plain_text("a AND b"), None);
+    }
+
+    #[test]
+    fn texts() {
+        let texts = |query| {
+            SearchQuery::parse(query)
+                .unwrap()
+                .texts()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(texts("").is_empty());
+        assert_eq!(texts("Milk Eggs"), ["milk eggs"]);
+        assert_eq!(
+            texts("milk tag:work -bread OR \"is:done\" is:pinned"),
+            ["milk", "bread", "is:done"]
+        );
+    }
+}
@@ -0,0 +1,726 @@
+use gettextrs::gettext;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    is_code_fence, markdown_to_html,
+    vault_import::{is_external, percent_decode, unique_file_name},
+    AttachmentData, UnresolvedLink,
+};
+use crate::utils::safe_path::resolve_within;
+
+const PAGE_TEMPLATE: &str = include_str!("../../../data/resources/site/page.html");
+const INDEX_TEMPLATE: &str = include_str!("../../../data/resources/site/index.html");
+const STYLESHEET: &str = include_str!("../../../data/resources/site/style.css");
+
+const INDEX_PATH: &str = "index.html";
+const STYLESHEET_PATH: &str = "style.css";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// `[[Title]]`, `[[Title#Heading]]`, and `[[Title|Label]]`
+static RE_WIKI_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\[\[([^\[\]|#\n]+)(?:#[^\[\]|\n]*)?(?:\|([^\[\]\n]*))?\]\]").unwrap()
+});
+/// `![Label](path)` and `[Label](<path with spaces>)`
+static RE_MARKDOWN_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(!?)\[([^\[\]\n]*)\]\((?:<([^<>\n]+)>|([^()\s]+))\)").unwrap());
+static RE_PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{([a-z_]+)\}\}").unwrap());
+
+/// Writing the website was stopped before it was done
+#[derive(Debug, thiserror::Error)]
+#[error("Website export was cancelled")]
+pub struct SiteExportCancelled;
+
+/// A note to put on the website
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteNote {
+    pub title: String,
+    /// Name of the file of the note, so Markdown links to it can be followed
+    pub file_name: String,
+    pub tag_names: Vec<String>,
+    pub content: String,
+    pub attachments: Vec<AttachmentData>,
+}
+
+/// A page of the website, at `path` relative to its folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitePage {
+    pub path: String,
+    pub html: String,
+}
+
+/// A file copied from `source` to `path` in the folder of the website
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteAttachment {
+    pub source: PathBuf,
+    pub path: String,
+}
+
+/// Everything to write to the folder of the website
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Site {
+    /// The index first, then a page for each note
+    pub pages: Vec<SitePage>,
+    pub attachments: Vec<SiteAttachment>,
+    /// Links to notes that are not on the website, which are kept as text
+    pub invalid_links: Vec<UnresolvedLink>,
+}
+
+/// Render `notes` as the pages of a website named `site_title`, with an index of the notes
+/// grouped by tag
+pub fn build_site(site_title: &str, notes: &[SiteNote]) -> Site {
+    SiteBuilder::new(notes).build(site_title)
+}
+
+/// Write `site` to `output_dir`, calling `on_progress` with the done fraction after each
+/// file, and stopping with [`SiteExportCancelled`] once `is_cancelled` returns true
+pub fn write_site(
+    site: &Site,
+    output_dir: &Path,
+    mut on_progress: impl FnMut(f64),
+    is_cancelled: impl Fn() -> bool,
+) -> anyhow::Result<()> {
+    let n_files = site.pages.len() + site.attachments.len() + 1;
+    let mut n_written = 0;
+    let mut file_written = || {
+        n_written += 1;
+        on_progress(n_written as f64 / n_files as f64);
+    };
+
+    fs::create_dir_all(output_dir)?;
+    fs::write(resolve_within(output_dir, STYLESHEET_PATH)?, STYLESHEET)?;
+    file_written();
+
+    for page in &site.pages {
+        if is_cancelled() {
+            return Err(SiteExportCancelled.into());
+        }
+
+        fs::write(resolve_within(output_dir, &page.path)?, &page.html)?;
+        file_written();
+    }
+
+    if !site.attachments.is_empty() {
+        fs::create_dir_all(output_dir.join(ATTACHMENTS_DIR))?;
+    }
+
+    for attachment in &site.attachments {
+        if is_cancelled() {
+            return Err(SiteExportCancelled.into());
+        }
+
+        fs::copy(
+            &attachment.source,
+            resolve_within(output_dir, &attachment.path)?,
+        )?;
+        file_written();
+    }
+
+    Ok(())
+}
+
+struct SiteBuilder<'a> {
+    notes: &'a [SiteNote],
+    /// Path of the page of each note, in the same order
+    page_paths: Vec<String>,
+    /// Lowercase titles and file names to the index of their note
+    note_indices: HashMap<String, usize>,
+    attachment_paths: HashMap<PathBuf, String>,
+    attachments: Vec<SiteAttachment>,
+    invalid_links: Vec<UnresolvedLink>,
+}
+
+impl<'a> SiteBuilder<'a> {
+    fn new(notes: &'a [SiteNote]) -> Self {
+        let mut used_slugs = HashSet::from(["index".to_string()]);
+        let page_paths = notes
+            .iter()
+            .map(|note| {
+                let slug = unique_slug(&slug(&note.title), &used_slugs);
+                used_slugs.insert(slug.clone());
+                format!("{}.html", slug)
+            })
+            .collect();
+
+        let mut note_indices = HashMap::new();
+        for (index, note) in notes.iter().enumerate() {
+            // The first note with a title is linked when several have it
+            note_indices
+                .entry(note.title.to_lowercase())
+                .or_insert(index);
+            note_indices
+                .entry(note.file_name.to_lowercase())
+                .or_insert(index);
+        }
+
+        Self {
+            notes,
+            page_paths,
+            note_indices,
+            attachment_paths: HashMap::new(),
+            attachments: Vec::new(),
+            invalid_links: Vec::new(),
+        }
+    }
+
+    fn build(mut self, site_title: &str) -> Site {
+        let mut pages = vec![SitePage {
+            path: INDEX_PATH.to_string(),
+            html: self.index_html(site_title),
+        }];
+
+        for (index, note) in self.notes.iter().enumerate() {
+            pages.push(SitePage {
+                path: self.page_paths[index].clone(),
+                html: self.page_html(site_title, note),
+            });
+        }
+
+        self.invalid_links.sort();
+        self.invalid_links.dedup();
+
+        Site {
+            pages,
+            attachments: self.attachments,
+            invalid_links: self.invalid_links,
+        }
+    }
+
+    fn index_html(&self, site_title: &str) -> String {
+        // By lowercase name, with the name as it is first written
+        let mut tag_sections: BTreeMap<String, (&str, Vec<usize>)> = BTreeMap::new();
+        let mut untagged = Vec::new();
+
+        for (index, note) in self.notes.iter().enumerate() {
+            if note.tag_names.is_empty() {
+                untagged.push(index);
+            }
+
+            for tag_name in &note.tag_names {
+                tag_sections
+                    .entry(tag_name.to_lowercase())
+                    .or_insert_with(|| (tag_name, Vec::new()))
+                    .1
+                    .push(index);
+            }
+        }
+
+        let mut sections = tag_sections
+            .into_iter()
+            .map(|(_, (tag_name, indices))| {
+                self.index_section(&tag_anchor(tag_name), tag_name, indices)
+            })
+            .collect::<Vec<_>>();
+
+        if !untagged.is_empty() {
+            sections.push(self.index_section("untagged", &gettext("Untagged"), untagged));
+        }
+
+        render(
+            INDEX_TEMPLATE,
+            &[
+                ("site_title", &escape(site_title)),
+                ("sections", &sections.join("")),
+            ],
+        )
+    }
+
+    fn index_section(&self, id: &str, heading: &str, mut indices: Vec<usize>) -> String {
+        indices.sort_by_cached_key(|index| display_title(&self.notes[*index]).to_lowercase());
+
+        let mut html = format!(
+            "<section id=\"{}\">\n<h2>{}</h2>\n<ul>\n",
+            id,
+            escape(heading)
+        );
+        for index in indices {
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                self.page_paths[index],
+                escape(&display_title(&self.notes[index]))
+            ));
+        }
+        html.push_str("</ul>\n</section>\n");
+        html
+    }
+
+    fn page_html(&mut self, site_title: &str, note: &SiteNote) -> String {
+        let attachment_links = note
+            .attachments
+            .iter()
+            .map(|attachment| (attachment, self.attachment_path(&attachment.file)))
+            .collect::<Vec<_>>();
+
+        let content = self.rewrite_links(note, &attachment_links);
+
+        let tags = if note.tag_names.is_empty() {
+            String::new()
+        } else {
+            let items = note
+                .tag_names
+                .iter()
+                .map(|tag_name| {
+                    format!(
+                        "<li><a href=\"{}#{}\">{}</a></li>\n",
+                        INDEX_PATH,
+                        tag_anchor(tag_name),
+                        escape(tag_name)
+                    )
+                })
+                .collect::<String>();
+            format!("<ul class=\"tags\">\n{}</ul>", items)
+        };
+
+        let attachments = if attachment_links.is_empty() {
+            String::new()
+        } else {
+            let items = attachment_links
+                .iter()
+                .map(|(attachment, path)| {
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        escape(&encode_path(path)),
+                        escape(&attachment_title(attachment))
+                    )
+                })
+                .collect::<String>();
+            format!(
+                "<section class=\"attachments\">\n<h2>{}</h2>\n<ul>\n{}</ul>\n</section>",
+                escape(&gettext("Attachments")),
+                items
+            )
+        };
+
+        render(
+            PAGE_TEMPLATE,
+            &[
+                ("site_title", &escape(site_title)),
+                ("title", &escape(&display_title(note))),
+                ("tags", &tags),
+                ("content", markdown_to_html(&content).trim_end()),
+                ("attachments", &attachments),
+            ],
+        )
+    }
+
+    /// Where `source` is copied to, which is only copied once even if several notes have it
+    fn attachment_path(&mut self, source: &Path) -> String {
+        if let Some(path) = self.attachment_paths.get(source) {
+            return path.clone();
+        }
+
+        let used_file_names = self
+            .attachments
+            .iter()
+            .map(|attachment| attachment.path[ATTACHMENTS_DIR.len() + 1..].to_string())
+            .collect::<HashSet<_>>();
+        let path = format!(
+            "{}/{}",
+            ATTACHMENTS_DIR,
+            unique_file_name(source, &used_file_names)
+        );
+
+        self.attachment_paths
+            .insert(source.to_path_buf(), path.clone());
+        self.attachments.push(SiteAttachment {
+            source: source.to_path_buf(),
+            path: path.clone(),
+        });
+        path
+    }
+
+    /// The content of `note` with the links to other notes and to its attachments pointing
+    /// at their place on the website
+    fn rewrite_links(
+        &mut self,
+        note: &SiteNote,
+        attachments: &[(&AttachmentData, String)],
+    ) -> String {
+        let mut lines = Vec::new();
+        let mut is_in_code_block = false;
+
+        for line in note.content.split('\n') {
+            if is_code_fence(line) {
+                is_in_code_block = !is_in_code_block;
+            }
+
+            if is_in_code_block || is_code_fence(line) {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let line = RE_WIKI_LINK.replace_all(line, |captures: &Captures| {
+                let target = captures[1].trim();
+                let label = captures
+                    .get(2)
+                    .map(|label| label.as_str().trim())
+                    .filter(|label| !label.is_empty())
+                    .unwrap_or(target);
+
+                if let Some(page_path) = self.page_of(target) {
+                    return format!("[{}]({})", label, page_path);
+                }
+
+                self.push_invalid_link(note, target);
+                label.to_string()
+            });
+
+            let line = RE_MARKDOWN_LINK.replace_all(&line, |captures: &Captures| {
+                let destination = captures
+                    .get(3)
+                    .or_else(|| captures.get(4))
+                    .unwrap()
+                    .as_str();
+
+                if is_external(destination) {
+                    return captures[0].to_string();
+                }
+
+                let decoded = percent_decode(destination.split('#').next().unwrap_or_default());
+                let file_name = Path::new(&decoded)
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+
+                if file_name.to_lowercase().ends_with(".md") {
+                    if let Some(page_path) = self.page_of(&file_name) {
+                        return format!("{}[{}]({})", &captures[1], &captures[2], page_path);
+                    }
+
+                    self.push_invalid_link(note, &decoded);
+                    return captures[2].to_string();
+                }
+
+                let attachment_path = attachments.iter().find_map(|(attachment, path)| {
+                    let is_match = attachment
+                        .file
+                        .file_name()
+                        .map_or(false, |name| name.to_string_lossy() == file_name);
+                    is_match.then_some(path)
+                });
+
+                attachment_path.map_or_else(
+                    || captures[0].to_string(),
+                    |path| format!("{}[{}]({})", &captures[1], &captures[2], encode_path(path)),
+                )
+            });
+
+            lines.push(line.into_owned());
+        }
+
+        lines.join("\n")
+    }
+
+    fn page_of(&self, title_or_file_name: &str) -> Option<&str> {
+        let index = self.note_indices.get(&title_or_file_name.to_lowercase())?;
+        Some(&self.page_paths[*index])
+    }
+
+    fn push_invalid_link(&mut self, note: &SiteNote, target: &str) {
+        self.invalid_links.push(UnresolvedLink {
+            note_title: display_title(note),
+            target: target.to_string(),
+        });
+    }
+}
+
+/// Replace each `{{name}}` in `template` with its value in `values`, all at once so a value
+/// containing a placeholder is kept as is
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    RE_PLACEHOLDER
+        .replace_all(template, |captures: &Captures| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &captures[1])
+                .map_or_else(|| captures[0].to_string(), |(_, value)| value.to_string())
+        })
+        .into_owned()
+}
+
+fn display_title(note: &SiteNote) -> String {
+    if note.title.is_empty() {
+        gettext("Untitled Note")
+    } else {
+        note.title.clone()
+    }
+}
+
+fn attachment_title(attachment: &AttachmentData) -> String {
+    if attachment.title.is_empty() {
+        attachment
+            .file
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string())
+    } else {
+        attachment.title.clone()
+    }
+}
+
+fn tag_anchor(tag_name: &str) -> String {
+    format!("tag-{}", slug(tag_name))
+}
+
+/// `title` in lowercase, with dashes in place of anything but letters and numbers
+fn slug(title: &str) -> String {
+    let slug = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug
+    }
+}
+
+fn unique_slug(slug: &str, used_slugs: &HashSet<String>) -> String {
+    if !used_slugs.contains(slug) {
+        return slug.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", slug, n))
+        .find(|slug| !used_slugs.contains(slug))
+        .unwrap()
+}
+
+/// Escape the characters of `path` that would change the meaning of a URL
+fn encode_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        core::DateTime, test_harness::snapshot_settings, utils::safe_path::UnsafePathError,
+    };
+
+    fn note(title: &str, tag_names: &[&str], content: &str) -> SiteNote {
+        SiteNote {
+            title: title.to_string(),
+            file_name: format!("{}.md", title),
+            tag_names: tag_names.iter().map(|name| name.to_string()).collect(),
+            content: content.to_string(),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn notes() -> Vec<SiteNote> {
+        let mut getting_started = note(
+            "Getting Started",
+            &["Docs"],
+            "Read [[Install|the install guide]] first.\n\nSee [[Missing]], [the FAQ](FAQ.md), and ![the map](Map%20Large.png).\n\n```\n[[Install]]\n```",
+        );
+        getting_started.attachments.push(AttachmentData {
+            file: PathBuf::from("/notes/Map Large.png"),
+            created: DateTime::default(),
+            title: String::new(),
+        });
+
+        vec![
+            getting_started,
+            note(
+                "Install",
+                &["docs", "Guides"],
+                "Back to [start](Getting%20Started.md#intro).",
+            ),
+            note("Scratch <draft>", &[], "[[install]] & more"),
+        ]
+    }
+
+    #[test]
+    fn index_snapshot() {
+        let site = build_site("Handbook", &notes());
+
+        assert_eq!(site.pages[0].path, "index.html");
+        let html = &site.pages[0].html;
+        snapshot_settings().bind(|| insta::assert_snapshot!("site-index", html));
+    }
+
+    #[test]
+    fn page_snapshot() {
+        let site = build_site("Handbook", &notes());
+
+        assert_eq!(site.pages[1].path, "getting-started.html");
+        let html = &site.pages[1].html;
+        snapshot_settings().bind(|| insta::assert_snapshot!("site-getting-started", html));
+    }
+
+    #[test]
+    fn links() {
+        let site = build_site("Handbook", &notes());
+
+        let install = &site.pages[2];
+        assert_eq!(install.path, "install.html");
+        assert!(install
+            .html
+            .contains("<a href=\"getting-started.html\">start</a>"));
+
+        let scratch = &site.pages[3];
+        assert_eq!(scratch.path, "scratch-draft.html");
+        assert!(scratch
+            .html
+            .contains("<a href=\"install.html\">install</a> &amp; more"));
+
+        assert_eq!(
+            site.invalid_links,
+            vec![
+                UnresolvedLink {
+                    note_title: "Getting Started".to_string(),
+                    target: "FAQ.md".to_string(),
+                },
+                UnresolvedLink {
+                    note_title: "Getting Started".to_string(),
+                    target: "Missing".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            site.attachments,
+            vec![SiteAttachment {
+                source: PathBuf::from("/notes/Map Large.png"),
+                path: "attachments/Map Large.png".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unique_pages() {
+        let site = build_site(
+            "Handbook",
+            &[
+                note("Index", &[], ""),
+                note("Notes", &[], ""),
+                note("notes", &[], ""),
+                note("", &[], ""),
+            ],
+        );
+
+        let paths = site
+            .pages
+            .iter()
+            .map(|page| page.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "index.html",
+                "index-2.html",
+                "notes.html",
+                "notes-2.html",
+                "untitled.html"
+            ]
+        );
+    }
+
+    #[test]
+    fn emoji_titles() {
+        let site = build_site(
+            "Handbook",
+            &[
+                note("🎉 Launch Party 🎉", &["📌 Todo"], ""),
+                note("🎉🎉", &[], ""),
+                note("Café ☕", &[], ""),
+            ],
+        );
+
+        let paths = site
+            .pages
+            .iter()
+            .map(|page| page.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "index.html",
+                "launch-party.html",
+                "untitled.html",
+                "café.html"
+            ]
+        );
+        assert_eq!(tag_anchor("📌 Todo"), "tag-todo");
+    }
+
+    #[test]
+    fn render_once() {
+        assert_eq!(
+            render(
+                "<h1>{{title}}</h1>{{content}}{{unknown}}",
+                &[("title", "{{content}}"), ("content", "Text")]
+            ),
+            "<h1>{{content}}</h1>Text{{unknown}}"
+        );
+    }
+
+    #[test]
+    fn write_and_cancel() {
+        let dir =
+            std::env::temp_dir().join(format!("noteworthy-site-export-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let attachment_path = dir.join("Map Large.png");
+        fs::write(&attachment_path, "Image").unwrap();
+        let mut notes = notes();
+        notes[0].attachments[0].file = attachment_path;
+        let site = build_site("Handbook", &notes);
+
+        let output_dir = dir.join("site");
+        let mut progress = Vec::new();
+        write_site(
+            &site,
+            &output_dir,
+            |fraction| progress.push(fraction),
+            || false,
+        )
+        .unwrap();
+        assert_eq!(progress.len(), 6);
+        assert_eq!(progress.last(), Some(&1.0));
+        assert!(output_dir.join("style.css").exists());
+        assert!(output_dir.join("scratch-draft.html").exists());
+        assert_eq!(
+            fs::read_to_string(output_dir.join("attachments/Map Large.png")).unwrap(),
+            "Image"
+        );
+
+        let cancelled_dir = dir.join("cancelled");
+        let err = write_site(&site, &cancelled_dir, |_| {}, || true).unwrap_err();
+        assert!(err.is::<SiteExportCancelled>());
+        assert!(!cancelled_dir.join("index.html").exists());
+    }
+
+    #[test]
+    fn write_unsafe_path() {
+        let dir = std::env::temp_dir().join(format!(
+            "noteworthy-site-export-unsafe-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut site = build_site("Handbook", &notes());
+        site.pages[1].path = "../escaped.html".to_string();
+
+        let err = write_site(&site, &dir.join("site"), |_| {}, || false).unwrap_err();
+        assert!(err.is::<UnsafePathError>());
+        assert!(!dir.join("escaped.html").exists());
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::{Duration, Instant};
+
+use super::format_latency;
+
+/// Moments of a cold start, to tell what the window waits for before it is usable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupMilestone {
+    /// The main window is shown, even if it has no notes yet
+    WindowMapped,
+    /// The notes are loaded and shown in the sidebar
+    NotesVisible,
+    /// The repository is open, so the notes can be synced
+    RepositoryReady,
+}
+
+impl StartupMilestone {
+    const ALL: [Self; 3] = [
+        Self::WindowMapped,
+        Self::NotesVisible,
+        Self::RepositoryReady,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WindowMapped => "window mapped",
+            Self::NotesVisible => "notes visible",
+            Self::RepositoryReady => "repository ready",
+        }
+    }
+}
+
+/// When each [`StartupMilestone`] was first reached since the app started
+#[derive(Debug)]
+pub struct StartupTimeline {
+    start: Instant,
+    /// In the order they were reached
+    reached: Vec<(StartupMilestone, Duration)>,
+}
+
+impl StartupTimeline {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            start,
+            reached: Vec::new(),
+        }
+    }
+
+    /// Record that `milestone` is reached `now`, returning how long it took since the start.
+    /// Only the first time is of the cold start, so it returns `None` for the next ones.
+    pub fn reach(&mut self, milestone: StartupMilestone, now: Instant) -> Option<Duration> {
+        if self
+            .reached
+            .iter()
+            .any(|(reached, _)| *reached == milestone)
+        {
+            return None;
+        }
+
+        let elapsed = now.saturating_duration_since(self.start);
+        self.reached.push((milestone, elapsed));
+        Some(elapsed)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        StartupMilestone::ALL
+            .iter()
+            .all(|milestone| self.reached.iter().any(|(reached, _)| reached == milestone))
+    }
+
+    /// The reached milestones in the order they were reached, like
+    /// `window mapped 120.00 ms, notes visible 180.00 ms`
+    pub fn to_line(&self) -> String {
+        self.reached
+            .iter()
+            .map(|(milestone, elapsed)| {
+                format!("{} {}", milestone.name(), format_latency(*elapsed))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn reached_once() {
+        let start = Instant::now();
+        let mut timeline = StartupTimeline::new(start);
+
+        assert_eq!(
+            timeline.reach(StartupMilestone::WindowMapped, start + millis(120)),
+            Some(millis(120))
+        );
+        assert_eq!(
+            timeline.reach(StartupMilestone::WindowMapped, start + millis(500)),
+            None
+        );
+        assert_eq!(timeline.to_line(), "window mapped 120.00 ms");
+    }
+
+    #[test]
+    fn complete_in_any_order() {
+        let start = Instant::now();
+        let mut timeline = StartupTimeline::new(start);
+        assert!(!timeline.is_complete());
+        assert_eq!(timeline.to_line(), "");
+
+        // Like when started in the background, with the window shown later
+        timeline.reach(StartupMilestone::NotesVisible, start + millis(80));
+        timeline.reach(StartupMilestone::RepositoryReady, start + millis(200));
+        assert!(!timeline.is_complete());
+        timeline.reach(StartupMilestone::WindowMapped, start + millis(3000));
+        assert!(timeline.is_complete());
+
+        assert_eq!(
+            timeline.to_line(),
+            "notes visible 80.00 ms, repository ready 200.00 ms, window mapped 3000.00 ms"
+        );
+    }
+
+    #[test]
+    fn reached_before_start() {
+        let start = Instant::now();
+        let mut timeline = StartupTimeline::new(start + millis(10));
+
+        assert_eq!(
+            timeline.reach(StartupMilestone::WindowMapped, start),
+            Some(Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,624 @@
+mod zip;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use self::zip::ZipWriter;
+use super::vault_import::unique_file_name;
+
+/// Extension of the files that bundles are written to
+pub const BUNDLE_EXTENSION: &str = "nwtybundle";
+
+/// Version of the bundle format written by this version of the app
+pub const BUNDLE_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.yml";
+const NOTES_DIR: &str = "notes";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Describes the notes of a bundle, so they can be imported without reading their front matter
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleManifest {
+    /// Version of the format the bundle was written in
+    pub version: u32,
+    /// Names of the tags of the notes, which are created when missing
+    pub tags: Vec<String>,
+    pub notes: Vec<BundleNoteEntry>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleNoteEntry {
+    /// File stem of the note, which tells whether it is already in a notebook
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub attachments: Vec<BundleAttachment>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleAttachment {
+    /// File of the attachment, as it is in the front matter of the note
+    pub file: PathBuf,
+    /// Name of the attachment in the attachments folder of the bundle
+    pub name: String,
+}
+
+/// A note to put in a bundle
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleNote {
+    /// File stem of the note
+    pub id: String,
+    pub title: String,
+    pub tag_names: Vec<String>,
+    /// Contents of the file of the note, with its front matter
+    pub contents: String,
+    /// Files of its attachments, as they are in its front matter
+    pub attachments: Vec<PathBuf>,
+}
+
+/// What was put in a bundle by [`write_bundle`]
+#[derive(Debug, Default, PartialEq)]
+pub struct WrittenBundle {
+    pub n_notes: usize,
+    pub n_attachments: usize,
+    /// Attachments whose file no longer exists, which are left out
+    pub missing_attachments: Vec<PathBuf>,
+}
+
+/// Write `notes` and their attachments as a bundle to `writer`, calling `on_progress` with the
+/// done fraction after each file
+pub fn write_bundle(
+    writer: impl io::Write,
+    notes: &[BundleNote],
+    mut on_progress: impl FnMut(f64),
+) -> anyhow::Result<WrittenBundle> {
+    let mut manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        ..BundleManifest::default()
+    };
+    let mut written_bundle = WrittenBundle::default();
+
+    // Attachments shared by notes are only added once
+    let mut names_of_files: HashMap<&Path, String> = HashMap::new();
+    let mut used_names = HashSet::new();
+    let mut attachment_files = Vec::new();
+
+    for note in notes {
+        for tag_name in &note.tag_names {
+            if !manifest.tags.contains(tag_name) {
+                manifest.tags.push(tag_name.clone());
+            }
+        }
+
+        let mut attachments = Vec::new();
+
+        for file in &note.attachments {
+            if !file.is_file() {
+                log::warn!("Left out missing attachment `{}`", file.display());
+                written_bundle.missing_attachments.push(file.clone());
+                continue;
+            }
+
+            let name = names_of_files.entry(file).or_insert_with(|| {
+                let name = unique_file_name(file, &used_names);
+                used_names.insert(name.clone());
+                attachment_files.push((file, name.clone()));
+                name
+            });
+            attachments.push(BundleAttachment {
+                file: file.clone(),
+                name: name.clone(),
+            });
+        }
+
+        manifest.notes.push(BundleNoteEntry {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            tags: note.tag_names.clone(),
+            attachments,
+        });
+    }
+
+    let n_files = notes.len() + attachment_files.len() + 1;
+    let mut n_written = 0;
+    let mut file_written = || {
+        n_written += 1;
+        on_progress(n_written as f64 / n_files as f64);
+    };
+
+    let mut zip_writer = ZipWriter::new(writer);
+    zip_writer.add(MANIFEST_PATH, serde_yaml::to_string(&manifest)?.as_bytes())?;
+    file_written();
+
+    for note in notes {
+        zip_writer.add(&note_path(&note.id), note.contents.as_bytes())?;
+        file_written();
+    }
+
+    for (file, name) in &attachment_files {
+        zip_writer.add(&attachment_path(name), &fs::read(file)?)?;
+        file_written();
+    }
+
+    zip_writer.finish()?;
+
+    written_bundle.n_notes = notes.len();
+    written_bundle.n_attachments = attachment_files.len();
+    Ok(written_bundle)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("Not a bundle: {0}")]
+    Archive(#[from] io::Error),
+    #[error("Bundle has no `{0}`")]
+    MissingFile(String),
+    #[error("Bundle manifest is invalid: {0}")]
+    Manifest(#[from] serde_yaml::Error),
+    #[error("Bundle version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("Bundle has unsafe path `{0}`")]
+    UnsafePath(String),
+    #[error("Note `{0}` of the bundle is not text")]
+    InvalidNote(String),
+}
+
+/// The notes and attachments of a bundle, checked to only have files that stay in the folders
+/// they are imported to
+#[derive(Debug)]
+pub struct TagBundle {
+    pub manifest: BundleManifest,
+    /// Contents of the notes, in the order of the manifest
+    pub notes: Vec<String>,
+    attachments: HashMap<String, Vec<u8>>,
+}
+
+impl TagBundle {
+    pub fn read(bytes: &[u8]) -> Result<Self, BundleError> {
+        let entries = zip::read_entries(bytes)?;
+
+        if let Some(entry) = entries.iter().find(|entry| !is_safe_path(&entry.name)) {
+            return Err(BundleError::UnsafePath(entry.name.clone()));
+        }
+
+        let mut files = entries
+            .into_iter()
+            .map(|entry| (entry.name, entry.data))
+            .collect::<HashMap<_, _>>();
+
+        let manifest: BundleManifest = serde_yaml::from_slice(
+            files
+                .get(MANIFEST_PATH)
+                .ok_or_else(|| BundleError::MissingFile(MANIFEST_PATH.to_string()))?,
+        )?;
+
+        // Only versions that are known, as newer ones may mean something else
+        if manifest.version == 0 || manifest.version > BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(manifest.version));
+        }
+
+        let mut notes = Vec::new();
+        let mut attachments = HashMap::new();
+
+        for entry in &manifest.notes {
+            if !is_safe_name(&entry.id) {
+                return Err(BundleError::UnsafePath(entry.id.clone()));
+            }
+
+            let path = note_path(&entry.id);
+            let data = files.remove(&path).ok_or(BundleError::MissingFile(path))?;
+            let contents = String::from_utf8(data.to_vec())
+                .map_err(|_| BundleError::InvalidNote(entry.id.clone()))?;
+            notes.push(contents);
+
+            for attachment in &entry.attachments {
+                if !is_safe_name(&attachment.name) {
+                    return Err(BundleError::UnsafePath(attachment.name.clone()));
+                }
+
+                if attachments.contains_key(&attachment.name) {
+                    continue;
+                }
+
+                let path = attachment_path(&attachment.name);
+                let data = files.remove(&path).ok_or(BundleError::MissingFile(path))?;
+                attachments.insert(attachment.name.clone(), data.to_vec());
+            }
+        }
+
+        Ok(Self {
+            manifest,
+            notes,
+            attachments,
+        })
+    }
+
+    /// Data of the attachment with `name` in the bundle
+    pub fn attachment(&self, name: &str) -> Option<&[u8]> {
+        self.attachments.get(name).map(Vec::as_slice)
+    }
+}
+
+/// What to do with the notes of a bundle that are already in the notebook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    Skip,
+    /// Import them as new notes next to the ones already there
+    Duplicate,
+}
+
+/// The files to create when importing a bundle, made by [`plan_import`]
+#[derive(Debug, Default, PartialEq)]
+pub struct BundleImport {
+    pub notes: Vec<ImportedNote>,
+    /// Ids of the notes that are skipped, as they are already in the notebook
+    pub skipped: Vec<String>,
+    /// Names of the attachments in the bundle, with the file names they are copied as
+    pub attachments: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportedNote {
+    /// Index of the note in the bundle
+    pub index: usize,
+    pub file_name: String,
+    /// Files of its attachments in its front matter, with the file names they are copied as.
+    /// Other files in its front matter are not in the bundle.
+    pub attachment_files: HashMap<PathBuf, String>,
+}
+
+/// Choose the file names of the notes and attachments of `bundle`, so they don't replace any
+/// of `existing_file_names`
+pub fn plan_import(
+    bundle: &TagBundle,
+    existing_file_names: &HashSet<String>,
+    policy: CollisionPolicy,
+) -> BundleImport {
+    let mut used_file_names = existing_file_names.clone();
+    let mut bundle_import = BundleImport::default();
+    let mut copied_names: HashMap<&str, String> = HashMap::new();
+
+    for (index, entry) in bundle.manifest.notes.iter().enumerate() {
+        let file_name = format!("{}.md", entry.id);
+
+        let file_name = if used_file_names.contains(&file_name) {
+            match policy {
+                CollisionPolicy::Skip => {
+                    bundle_import.skipped.push(entry.id.clone());
+                    continue;
+                }
+                CollisionPolicy::Duplicate => {
+                    unique_file_name(Path::new(&file_name), &used_file_names)
+                }
+            }
+        } else {
+            file_name
+        };
+        used_file_names.insert(file_name.clone());
+
+        let mut attachment_files = HashMap::new();
+
+        for attachment in &entry.attachments {
+            let copied_name = copied_names.entry(&attachment.name).or_insert_with(|| {
+                let copied_name = unique_file_name(Path::new(&attachment.name), &used_file_names);
+                used_file_names.insert(copied_name.clone());
+                bundle_import
+                    .attachments
+                    .push((attachment.name.clone(), copied_name.clone()));
+                copied_name
+            });
+            attachment_files.insert(attachment.file.clone(), copied_name.clone());
+        }
+
+        bundle_import.notes.push(ImportedNote {
+            index,
+            file_name,
+            attachment_files,
+        });
+    }
+
+    bundle_import
+}
+
+fn note_path(id: &str) -> String {
+    format!("{}/{}.md", NOTES_DIR, id)
+}
+
+fn attachment_path(name: &str) -> String {
+    format!("{}/{}", ATTACHMENTS_DIR, name)
+}
+
+/// Whether `path` is relative and stays in the folder it is in, on any system
+fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with('/')
+        && !path.contains(['\\', ':', '\0'])
+        && path
+            .split('/')
+            .all(|component| !component.is_empty() && component != "." && component != "..")
+}
+
+/// Whether `name` can be used as a file name without going into another folder or hiding it
+fn is_safe_name(name: &str) -> bool {
+    is_safe_path(name) && !name.contains('/') && !name.starts_with('.')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn note(id: &str, tag_names: &[&str], attachments: &[&Path]) -> BundleNote {
+        BundleNote {
+            id: id.to_string(),
+            title: format!("Title of {}", id),
+            tag_names: tag_names.iter().map(|name| name.to_string()).collect(),
+            contents: format!("---\ntitle: Title of {}\n---\nContent of {}", id, id),
+            attachments: attachments.iter().map(|file| file.to_path_buf()).collect(),
+        }
+    }
+
+    fn bundle_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip_writer = ZipWriter::new(Vec::new());
+        for (name, data) in files {
+            zip_writer.add(name, data).unwrap();
+        }
+        zip_writer.finish().unwrap()
+    }
+
+    fn manifest_with(version: u32, ids: &[&str], attachment_names: &[&str]) -> Vec<u8> {
+        let manifest = BundleManifest {
+            version,
+            tags: vec!["Onboarding".into()],
+            notes: ids
+                .iter()
+                .map(|id| BundleNoteEntry {
+                    id: id.to_string(),
+                    title: id.to_string(),
+                    tags: vec!["Onboarding".into()],
+                    attachments: attachment_names
+                        .iter()
+                        .map(|name| BundleAttachment {
+                            file: PathBuf::from(format!("/home/user/Notes/{}", name)),
+                            name: name.to_string(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        };
+        serde_yaml::to_string(&manifest).unwrap().into_bytes()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("noteworthy-bundle-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_then_read() {
+        let dir = temp_dir("write");
+        let picture = dir.join("picture.png");
+        fs::write(&picture, [1, 2, 3]).unwrap();
+        let other_picture = dir.join("other").join("picture.png");
+        fs::create_dir_all(other_picture.parent().unwrap()).unwrap();
+        fs::write(&other_picture, [4, 5]).unwrap();
+        let missing = dir.join("missing.ogg");
+
+        let notes = [
+            note("Note-1", &["Onboarding", "Work"], &[&picture, &missing]),
+            note("Note-2", &["Onboarding"], &[&picture, &other_picture]),
+        ];
+
+        let mut fractions = Vec::new();
+        let mut bytes = Vec::new();
+        let written_bundle =
+            write_bundle(&mut bytes, &notes, |fraction| fractions.push(fraction)).unwrap();
+        assert_eq!(
+            written_bundle,
+            WrittenBundle {
+                n_notes: 2,
+                n_attachments: 2,
+                missing_attachments: vec![missing.clone()],
+            }
+        );
+        assert_eq!(fractions.len(), 5);
+        assert_eq!(fractions.last(), Some(&1.0));
+
+        let bundle = TagBundle::read(&bytes).unwrap();
+        assert_eq!(bundle.manifest.version, BUNDLE_VERSION);
+        assert_eq!(bundle.manifest.tags, ["Onboarding", "Work"]);
+        assert_eq!(
+            bundle.notes,
+            [notes[0].contents.as_str(), &notes[1].contents]
+        );
+
+        let entry = &bundle.manifest.notes[1];
+        assert_eq!(entry.id, "Note-2");
+        assert_eq!(entry.title, "Title of Note-2");
+        assert_eq!(
+            entry.attachments,
+            [
+                BundleAttachment {
+                    file: picture,
+                    name: "picture.png".into(),
+                },
+                BundleAttachment {
+                    file: other_picture,
+                    name: "picture-2.png".into(),
+                },
+            ]
+        );
+        assert_eq!(bundle.attachment("picture.png"), Some(&[1, 2, 3][..]));
+        assert_eq!(bundle.attachment("picture-2.png"), Some(&[4, 5][..]));
+        assert_eq!(bundle.attachment("missing.ogg"), None);
+    }
+
+    #[test]
+    fn versions() {
+        let read_version = |version| {
+            TagBundle::read(&bundle_bytes(&[
+                (MANIFEST_PATH, &manifest_with(version, &["A"], &[])),
+                ("notes/A.md", b"---\ntitle: A\n---\n"),
+            ]))
+        };
+
+        assert!(read_version(BUNDLE_VERSION).is_ok());
+        assert!(matches!(
+            read_version(BUNDLE_VERSION + 1),
+            Err(BundleError::UnsupportedVersion(version)) if version == BUNDLE_VERSION + 1
+        ));
+        assert!(matches!(
+            read_version(0),
+            Err(BundleError::UnsupportedVersion(0))
+        ));
+
+        // Fields added by newer versions of the same format are ignored
+        let manifest = b"version: 1\nnotes: []\nauthor: Someone\n";
+        assert!(TagBundle::read(&bundle_bytes(&[(MANIFEST_PATH, manifest)])).is_ok());
+    }
+
+    #[test]
+    fn invalid_bundles() {
+        assert!(matches!(
+            TagBundle::read(b"Not a bundle"),
+            Err(BundleError::Archive(_))
+        ));
+        assert!(matches!(
+            TagBundle::read(&bundle_bytes(&[("notes/A.md", b"")])),
+            Err(BundleError::MissingFile(path)) if path == MANIFEST_PATH
+        ));
+        assert!(matches!(
+            TagBundle::read(&bundle_bytes(&[(MANIFEST_PATH, b"notes: {")])),
+            Err(BundleError::Manifest(_))
+        ));
+        assert!(matches!(
+            TagBundle::read(&bundle_bytes(&[(MANIFEST_PATH, &manifest_with(1, &["A"], &[]))])),
+            Err(BundleError::MissingFile(path)) if path == "notes/A.md"
+        ));
+        assert!(matches!(
+            TagBundle::read(&bundle_bytes(&[
+                (MANIFEST_PATH, &manifest_with(1, &["A"], &["a.png"])),
+                ("notes/A.md", b""),
+            ])),
+            Err(BundleError::MissingFile(path)) if path == "attachments/a.png"
+        ));
+        assert!(matches!(
+            TagBundle::read(&bundle_bytes(&[
+                (MANIFEST_PATH, &manifest_with(1, &["A"], &[])),
+                ("notes/A.md", &[0xff, 0xfe]),
+            ])),
+            Err(BundleError::InvalidNote(id)) if id == "A"
+        ));
+    }
+
+    #[test]
+    fn malicious_bundles() {
+        let manifest = manifest_with(1, &["A"], &[]);
+
+        for path in [
+            "/etc/passwd",
+            "../outside.md",
+            "notes/../../outside.md",
+            "C:\\Windows\\evil.md",
+            "C:/evil.md",
+            "notes//A.md",
+            "notes/./A.md",
+        ] {
+            let bytes = bundle_bytes(&[
+                (MANIFEST_PATH, &manifest),
+                ("notes/A.md", b""),
+                (path, b"Evil"),
+            ]);
+            assert!(
+                matches!(TagBundle::read(&bytes), Err(BundleError::UnsafePath(unsafe_path)) if unsafe_path == path),
+                "`{}` was not rejected",
+                path
+            );
+        }
+
+        // Paths in the manifest are checked too, as they become file names
+        for id in ["../A", "/tmp/A", "sub/A", ".hidden", ""] {
+            let bytes = bundle_bytes(&[(MANIFEST_PATH, &manifest_with(1, &[id], &[]))]);
+            assert!(
+                matches!(TagBundle::read(&bytes), Err(BundleError::UnsafePath(_))),
+                "`{}` was not rejected",
+                id
+            );
+        }
+
+        let bytes = bundle_bytes(&[
+            (MANIFEST_PATH, &manifest_with(1, &["A"], &["/etc/passwd"])),
+            ("notes/A.md", b""),
+        ]);
+        assert!(matches!(
+            TagBundle::read(&bytes),
+            Err(BundleError::UnsafePath(_))
+        ));
+    }
+
+    #[test]
+    fn plan_with_collisions() {
+        let bytes = bundle_bytes(&[
+            (
+                MANIFEST_PATH,
+                &manifest_with(1, &["New", "Existing"], &["picture.png"]),
+            ),
+            ("notes/New.md", b""),
+            ("notes/Existing.md", b""),
+            ("attachments/picture.png", b"Picture"),
+        ]);
+        let bundle = TagBundle::read(&bytes).unwrap();
+        let existing_file_names =
+            HashSet::from(["Existing.md".to_string(), "picture.png".to_string()]);
+        let picture_file = PathBuf::from("/home/user/Notes/picture.png");
+
+        let skipped = plan_import(&bundle, &existing_file_names, CollisionPolicy::Skip);
+        assert_eq!(
+            skipped,
+            BundleImport {
+                notes: vec![ImportedNote {
+                    index: 0,
+                    file_name: "New.md".into(),
+                    attachment_files: HashMap::from([(
+                        picture_file.clone(),
+                        "picture-2.png".into()
+                    )]),
+                }],
+                skipped: vec!["Existing".into()],
+                attachments: vec![("picture.png".into(), "picture-2.png".into())],
+            }
+        );
+
+        let duplicated = plan_import(&bundle, &existing_file_names, CollisionPolicy::Duplicate);
+        assert!(duplicated.skipped.is_empty());
+        assert_eq!(duplicated.notes.len(), 2);
+        assert_eq!(duplicated.notes[0].file_name, "New.md");
+        assert_eq!(duplicated.notes[1].file_name, "Existing-2.md");
+        assert_eq!(duplicated.notes[1].index, 1);
+        // Shared by both, so copied once
+        assert_eq!(duplicated.attachments.len(), 1);
+        assert_eq!(
+            duplicated.notes[1].attachment_files[&picture_file],
+            "picture-2.png"
+        );
+
+        // Nothing is in the way
+        let fresh = plan_import(&bundle, &HashSet::new(), CollisionPolicy::Skip);
+        assert_eq!(fresh.notes.len(), 2);
+        assert_eq!(fresh.notes[1].file_name, "Existing.md");
+        assert_eq!(
+            fresh.attachments,
+            [("picture.png".to_string(), "picture.png".to_string())]
+        );
+    }
+}
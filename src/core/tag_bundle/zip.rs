@@ -0,0 +1,318 @@
+//! Just enough of the ZIP format for bundles. Files are stored without compression, since
+//! notes are small and attachments like pictures and recordings are already compressed.
+
+use std::io::{self, Write};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_LEN: usize = 22;
+
+const VERSION: u16 = 20;
+/// The names are UTF-8
+const FLAGS: u16 = 1 << 11;
+const STORED: u16 = 0;
+/// 1980-01-01, the first day ZIP can tell
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Debug)]
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes the files added to it as a ZIP archive
+#[derive(Debug)]
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u32,
+    entries: Vec<CentralEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let size = to_u32(data.len())?;
+        let name_len = to_u16(name.len())?;
+        let crc = crc32(data);
+
+        let mut header = Vec::with_capacity(LOCAL_HEADER_LEN + name.len());
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, STORED);
+        put_u16(&mut header, 0);
+        put_u16(&mut header, DOS_DATE);
+        put_u32(&mut header, crc);
+        put_u32(&mut header, size);
+        put_u32(&mut header, size);
+        put_u16(&mut header, name_len);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)?;
+
+        self.entries.push(CentralEntry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset: self.offset,
+        });
+        self.offset = self
+            .offset
+            .checked_add(to_u32(header.len())?)
+            .and_then(|offset| offset.checked_add(size))
+            .ok_or_else(|| too_large("Archive"))?;
+
+        Ok(())
+    }
+
+    /// Write the list of the files at the end, which is where readers start from
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut directory = Vec::new();
+
+        for entry in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, FLAGS);
+            put_u16(&mut directory, STORED);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, DOS_DATE);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, entry.size);
+            put_u32(&mut directory, entry.size);
+            put_u16(&mut directory, to_u16(entry.name.len())?);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u32(&mut directory, 0);
+            put_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let n_entries = to_u16(self.entries.len())?;
+        let directory_size = to_u32(directory.len())?;
+        put_u32(&mut directory, END_SIGNATURE);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, 0);
+        put_u16(&mut directory, n_entries);
+        put_u16(&mut directory, n_entries);
+        put_u32(&mut directory, directory_size);
+        put_u32(&mut directory, self.offset);
+        put_u16(&mut directory, 0);
+
+        self.writer.write_all(&directory)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// A file of an archive read with [`read_entries`]
+#[derive(Debug, PartialEq)]
+pub struct ZipEntry<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+}
+
+/// The files of the ZIP archive in `bytes`, which have to be stored without compression
+pub fn read_entries(bytes: &[u8]) -> io::Result<Vec<ZipEntry<'_>>> {
+    let end = find_end(bytes).ok_or_else(|| invalid("No end of central directory"))?;
+    let n_entries = read_u16(bytes, end + 10)?;
+    let mut position = read_u32(bytes, end + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(n_entries as usize);
+
+    for _ in 0..n_entries {
+        if read_u32(bytes, position)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("Invalid central directory header"));
+        }
+
+        let method = read_u16(bytes, position + 10)?;
+        let crc = read_u32(bytes, position + 16)?;
+        let compressed_size = read_u32(bytes, position + 20)?;
+        let size = read_u32(bytes, position + 24)?;
+        let name_len = read_u16(bytes, position + 28)? as usize;
+        let extra_len = read_u16(bytes, position + 30)? as usize;
+        let comment_len = read_u16(bytes, position + 32)? as usize;
+        let offset = read_u32(bytes, position + 42)? as usize;
+        let name = read_str(bytes, position + CENTRAL_HEADER_LEN, name_len)?;
+
+        if method != STORED || compressed_size != size {
+            return Err(invalid("Compressed files are not supported"));
+        }
+
+        if read_u32(bytes, offset)? != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid("Invalid local file header"));
+        }
+
+        let local_name_len = read_u16(bytes, offset + 26)? as usize;
+        let local_extra_len = read_u16(bytes, offset + 28)? as usize;
+        let data_start = offset + LOCAL_HEADER_LEN + local_name_len + local_extra_len;
+        let data = bytes
+            .get(data_start..data_start + size as usize)
+            .ok_or_else(|| invalid("File data is cut off"))?;
+
+        if crc32(data) != crc {
+            return Err(invalid("File data is corrupted"));
+        }
+
+        entries.push(ZipEntry { name, data });
+        position += CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+/// Position of the end of central directory record, which is followed by a comment of at most
+/// `u16::MAX` bytes
+fn find_end(bytes: &[u8]) -> Option<usize> {
+    let last = bytes.len().checked_sub(END_LEN)?;
+    let first = last.saturating_sub(u16::MAX as usize);
+
+    (first..=last)
+        .rev()
+        .find(|position| read_u32(bytes, *position).ok() == Some(END_SIGNATURE))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_u16(bytes: &[u8], position: usize) -> io::Result<u16> {
+    bytes
+        .get(position..position + 2)
+        .map(|slice| u16::from_le_bytes([slice[0], slice[1]]))
+        .ok_or_else(|| invalid("Archive is cut off"))
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> io::Result<u32> {
+    bytes
+        .get(position..position + 4)
+        .map(|slice| u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+        .ok_or_else(|| invalid("Archive is cut off"))
+}
+
+fn read_str(bytes: &[u8], position: usize, len: usize) -> io::Result<String> {
+    let slice = bytes
+        .get(position..position + len)
+        .ok_or_else(|| invalid("Archive is cut off"))?;
+    String::from_utf8(slice.to_vec()).map_err(|_| invalid("File name is not UTF-8"))
+}
+
+fn to_u16(len: usize) -> io::Result<u16> {
+    u16::try_from(len).map_err(|_| too_large("Name or number of files"))
+}
+
+fn to_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| too_large("File"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is too large for a bundle", what),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn write_then_read() {
+        let mut writer = ZipWriter::new(Vec::new());
+        writer.add("manifest.yml", b"version: 1\n").unwrap();
+        writer.add("notes/Ünïcode.md", b"").unwrap();
+        writer.add("attachments/a.bin", &[0, 1, 2, 255]).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let entries = read_entries(&bytes).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ZipEntry {
+                    name: "manifest.yml".into(),
+                    data: b"version: 1\n"
+                },
+                ZipEntry {
+                    name: "notes/Ünïcode.md".into(),
+                    data: b""
+                },
+                ZipEntry {
+                    name: "attachments/a.bin".into(),
+                    data: &[0, 1, 2, 255]
+                },
+            ]
+        );
+
+        assert!(read_entries(&ZipWriter::new(Vec::new()).finish().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_archives() {
+        assert!(read_entries(b"").is_err());
+        assert!(read_entries(b"not a zip file at all, just some text").is_err());
+
+        let mut writer = ZipWriter::new(Vec::new());
+        writer.add("a.md", b"Some text").unwrap();
+        let bytes = writer.finish().unwrap();
+
+        // Cut before the end of central directory
+        assert!(read_entries(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut corrupted = bytes.clone();
+        corrupted[LOCAL_HEADER_LEN + "a.md".len()] ^= 1;
+        assert!(read_entries(&corrupted).is_err());
+
+        // Marked as deflated
+        let mut compressed = bytes;
+        let central = compressed.len() - END_LEN - CENTRAL_HEADER_LEN - "a.md".len();
+        compressed[central + 10] = 8;
+        assert!(read_entries(&compressed).is_err());
+    }
+}
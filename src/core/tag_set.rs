@@ -0,0 +1,369 @@
+use indexmap::IndexMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// Ordered set of tag names, keeping the invariants of the tags of a notebook: names are
+/// unique and never empty.
+///
+/// Names are unique regardless of their case and of how their characters are composed, so
+/// "Work" and "work", or "Café" typed on different devices, are the same tag. Each keeps the
+/// case it was first given.
+///
+/// Each name can have a `value` attached, which is how the GObject lists store their `Tag`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSet<T = ()> {
+    /// The names and values by the key of their name
+    tags: IndexMap<String, (String, T)>,
+}
+
+impl<T> Default for TagSet<T> {
+    fn default() -> Self {
+        Self {
+            tags: IndexMap::new(),
+        }
+    }
+}
+
+impl<T> TagSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a tag at the end, returning its position
+    pub fn insert(&mut self, name: &str, value: T) -> anyhow::Result<usize> {
+        anyhow::ensure!(!name.is_empty(), "Tag name cannot be empty");
+        anyhow::ensure!(!self.contains(name), "Cannot append existing tag name");
+
+        let (position, _) = self
+            .tags
+            .insert_full(tag_key(name), (normalize_tag_name(name), value));
+        Ok(position)
+    }
+
+    /// Remove the tag, returning its previous position and its value
+    pub fn remove(&mut self, name: &str) -> anyhow::Result<(usize, T)> {
+        self.tags
+            .shift_remove_full(&tag_key(name))
+            .map(|(position, _, (_, value))| (position, value))
+            .ok_or_else(|| anyhow::anyhow!("Cannot remove tag that does not exist"))
+    }
+
+    /// Rename the tag while keeping its position, which is returned. The new name can differ
+    /// from the old one only in case.
+    pub fn rename(&mut self, name: &str, new_name: &str) -> anyhow::Result<usize> {
+        let key = tag_key(name);
+        let new_key = tag_key(new_name);
+
+        anyhow::ensure!(
+            key == new_key || !self.tags.contains_key(&new_key),
+            "Cannot rename a tag to a name that already exist"
+        );
+        anyhow::ensure!(!new_name.is_empty(), "Tag name cannot be empty");
+
+        let (position, _, (_, value)) = self
+            .tags
+            .swap_remove_full(&key)
+            .ok_or_else(|| anyhow::anyhow!("Cannot rename tag that does not exist"))?;
+        let (new_position, _) = self
+            .tags
+            .insert_full(new_key, (normalize_tag_name(new_name), value));
+
+        if position != new_position {
+            // The last tag took the place of the removed one, so move it back to the end
+            let (last_key, last_entry) = self.tags.swap_remove_index(position).unwrap();
+            self.tags.insert(last_key, last_entry);
+        }
+
+        Ok(position)
+    }
+
+    /// Move the tag at `from` so it ends up at `to`, shifting the tags in between
+    pub fn move_index(&mut self, from: usize, to: usize) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            from < self.len() && to < self.len(),
+            "Cannot move tag out of bounds"
+        );
+
+        let (key, entry) = self.tags.shift_remove_index(from).unwrap();
+        let tail = self.tags.split_off(to);
+        self.tags.insert(key, entry);
+        self.tags.extend(tail);
+
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tags.contains_key(&tag_key(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.tags.get(&tag_key(name)).map(|(_, value)| value)
+    }
+
+    pub fn get_index(&self, position: usize) -> Option<(&str, &T)> {
+        self.tags
+            .get_index(position)
+            .map(|(_, (name, value))| (name.as_str(), value))
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.tags.get_index_of(&tag_key(name))
+    }
+
+    /// Whether a tag can be appended or renamed to `name`
+    pub fn is_valid_name(&self, name: &str) -> bool {
+        !self.contains(name) && !name.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tags.values().map(|(name, _)| name.as_str())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.tags.values().map(|(_, value)| value)
+    }
+}
+
+/// `name` with its characters composed the same way however it was typed or synced, as
+/// macOS, for one, stores "é" as "e" followed by an accent
+pub fn normalize_tag_name(name: &str) -> String {
+    name.nfc().collect()
+}
+
+/// Whether `a` and `b` name the same tag
+pub fn is_same_tag_name(a: &str, b: &str) -> bool {
+    tag_key(a) == tag_key(b)
+}
+
+fn tag_key(name: &str) -> String {
+    name.to_lowercase().nfc().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tag_set(names: &[&str]) -> TagSet {
+        let mut tag_set = TagSet::new();
+        for name in names {
+            tag_set.insert(name, ()).unwrap();
+        }
+        tag_set
+    }
+
+    #[test]
+    fn insert() {
+        let mut tag_set = TagSet::new();
+        assert_eq!(tag_set.insert("A", ()).unwrap(), 0);
+        assert_eq!(tag_set.insert("B", ()).unwrap(), 1);
+        assert_eq!(tag_set.len(), 2);
+        assert!(tag_set.contains("A"));
+        assert!(tag_set.contains("B"));
+    }
+
+    #[test]
+    fn insert_other_case() {
+        let mut tag_set = tag_set(&["Work"]);
+        assert!(tag_set.insert("work", ()).is_err());
+        assert!(tag_set.insert("WORK", ()).is_err());
+        assert!(tag_set.contains("wOrK"));
+        assert_eq!(tag_set.index_of("work"), Some(0));
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["Work"]);
+    }
+
+    #[test]
+    fn insert_other_normalization() {
+        let composed = "Caf\u{e9}";
+        let decomposed = "Cafe\u{301}";
+        assert_ne!(composed, decomposed);
+
+        let mut cafe = tag_set(&[decomposed]);
+        assert!(cafe.insert(composed, ()).is_err());
+        assert!(cafe.contains(composed));
+        assert!(cafe.contains("CAFE\u{301}"));
+        assert!(cafe.contains("caf\u{e9}"));
+        // Stored composed, like on most filesystems
+        assert_eq!(cafe.names().collect::<Vec<_>>(), vec![composed]);
+
+        // Hangul syllable and its jamo
+        let mut hangul = tag_set(&["\u{ac00}"]);
+        assert!(hangul.insert("\u{1100}\u{1161}", ()).is_err());
+
+        // Ångström sign and A with ring above
+        let mut angstrom = tag_set(&["\u{212b}"]);
+        assert!(angstrom.insert("\u{c5}", ()).is_err());
+        assert!(angstrom.insert("\u{e5}", ()).is_err());
+        assert!(angstrom.insert("A", ()).is_ok());
+    }
+
+    #[test]
+    fn insert_invalid() {
+        let mut tag_set = tag_set(&["A"]);
+        assert!(tag_set.insert("A", ()).is_err());
+        assert!(tag_set.insert("", ()).is_err());
+        assert_eq!(tag_set.len(), 1);
+    }
+
+    #[test]
+    fn remove() {
+        let mut tag_set = tag_set(&["A", "B", "C"]);
+        assert_eq!(tag_set.remove("B").unwrap().0, 1);
+        assert!(tag_set.remove("B").is_err());
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["A", "C"]);
+    }
+
+    #[test]
+    fn rename_keeps_position() {
+        let mut tag_set = tag_set(&["A", "B", "C"]);
+        assert_eq!(tag_set.rename("A", "D").unwrap(), 0);
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["D", "B", "C"]);
+
+        assert_eq!(tag_set.rename("C", "E").unwrap(), 2);
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["D", "B", "E"]);
+
+        assert_eq!(tag_set.rename("B", "B2").unwrap(), 1);
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["D", "B2", "E"]);
+    }
+
+    #[test]
+    fn rename_case() {
+        let mut tag_set = tag_set(&["work", "B"]);
+        assert_eq!(tag_set.rename("work", "Work").unwrap(), 0);
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["Work", "B"]);
+
+        assert!(tag_set.rename("Work", "b").is_err());
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["Work", "B"]);
+    }
+
+    #[test]
+    fn rename_invalid() {
+        let mut tag_set = tag_set(&["A", "B"]);
+        assert!(tag_set.rename("A", "B").is_err());
+        assert!(tag_set.rename("A", "").is_err());
+        assert!(tag_set.rename("C", "D").is_err());
+        assert_eq!(tag_set.names().collect::<Vec<_>>(), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn move_index() {
+        let mut tag_set = tag_set(&["A", "B", "C", "D"]);
+        tag_set.move_index(0, 2).unwrap();
+        assert_eq!(
+            tag_set.names().collect::<Vec<_>>(),
+            vec!["B", "C", "A", "D"]
+        );
+
+        tag_set.move_index(3, 0).unwrap();
+        assert_eq!(
+            tag_set.names().collect::<Vec<_>>(),
+            vec!["D", "B", "C", "A"]
+        );
+
+        tag_set.move_index(1, 1).unwrap();
+        assert_eq!(
+            tag_set.names().collect::<Vec<_>>(),
+            vec!["D", "B", "C", "A"]
+        );
+
+        assert!(tag_set.move_index(4, 0).is_err());
+        assert!(tag_set.move_index(0, 4).is_err());
+        assert_eq!(tag_set.index_of("A"), Some(3));
+    }
+
+    #[test]
+    fn interleaved_operations() {
+        // Random sequences of operations, checked against a plain list of names
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize % bound.max(1)
+        };
+
+        for _ in 0..50 {
+            let mut tag_set = TagSet::new();
+            let mut expected: Vec<String> = Vec::new();
+
+            for i in 0..100 {
+                let len = expected.len();
+                match next(4) {
+                    0 => {
+                        let name = format!("T{}", next(20));
+                        let is_valid = tag_set.is_valid_name(&name);
+                        assert_eq!(tag_set.insert(&name, i).is_ok(), is_valid);
+                        if is_valid {
+                            expected.push(name);
+                        }
+                    }
+                    1 if len > 0 => {
+                        let name = expected.remove(next(len));
+                        tag_set.remove(&name).unwrap();
+                    }
+                    2 if len > 0 => {
+                        let position = next(len);
+                        let new_name = format!("T{}", next(20));
+                        // Including to the name it already has
+                        let is_valid =
+                            tag_set.is_valid_name(&new_name) || new_name == expected[position];
+                        let res = tag_set.rename(&expected[position], &new_name);
+                        assert_eq!(res.is_ok(), is_valid);
+                        if let Ok(new_position) = res {
+                            assert_eq!(new_position, position);
+                            expected[position] = new_name;
+                        }
+                    }
+                    3 if len > 0 => {
+                        let (from, to) = (next(len), next(len));
+                        tag_set.move_index(from, to).unwrap();
+                        let name = expected.remove(from);
+                        expected.insert(to, name);
+                    }
+                    _ => {}
+                }
+
+                assert_eq!(tag_set.names().collect::<Vec<_>>(), expected);
+                for (position, name) in expected.iter().enumerate() {
+                    assert_eq!(tag_set.index_of(name), Some(position));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn values() {
+        let mut tag_set = TagSet::new();
+        tag_set.insert("A", 1).unwrap();
+        tag_set.insert("B", 2).unwrap();
+        tag_set.rename("A", "C").unwrap();
+        assert_eq!(tag_set.get("C"), Some(&1));
+        assert_eq!(tag_set.get("A"), None);
+        assert_eq!(tag_set.get_index(1), Some(("B", &2)));
+        assert_eq!(tag_set.index_of("C"), Some(0));
+        assert_eq!(tag_set.values().collect::<Vec<_>>(), vec![&1, &2]);
+    }
+
+    #[test]
+    fn is_valid_name() {
+        let tag_set = tag_set(&["A"]);
+        assert!(!tag_set.is_valid_name("A"));
+        assert!(!tag_set.is_valid_name(""));
+        assert!(tag_set.is_valid_name("B"));
+        assert!(!tag_set.is_valid_name("a"));
+    }
+
+    #[test]
+    fn same_tag_names() {
+        assert!(is_same_tag_name("Work", "work"));
+        assert!(is_same_tag_name("Caf\u{e9}", "CAFE\u{301}"));
+        assert!(!is_same_tag_name("Cafe", "Caf\u{e9}"));
+        assert!(!is_same_tag_name("Work", "Works"));
+    }
+}
@@ -0,0 +1,184 @@
+/// How much of the start of a note is scanned for tags, so long notes don't take long
+pub const MAX_SCANNED_BYTES: usize = 20 * 1024;
+
+/// The start of `body` that is scanned for tags, which is at most [`MAX_SCANNED_BYTES`] and
+/// never splits a character
+pub fn scanned_part(body: &str) -> &str {
+    if body.len() <= MAX_SCANNED_BYTES {
+        return body;
+    }
+
+    let mut end = MAX_SCANNED_BYTES;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    &body[..end]
+}
+
+/// The names of `tag_names` that appear as words in `body`, from the one that appears the
+/// most. Case is ignored, and a name only matches whole words, so `art` is not found in
+/// `start`. In scripts written without spaces, like Chinese and Japanese, any position is a
+/// word boundary. Names that appear as often keep their order in `tag_names`.
+pub fn suggest_tags(body: &str, tag_names: &[String]) -> Vec<String> {
+    let body = scanned_part(body).to_lowercase();
+
+    let mut suggestions = tag_names
+        .iter()
+        .filter_map(|name| {
+            let count = count_words(&body, &name.trim().to_lowercase());
+            (count > 0).then_some((name, count))
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    suggestions
+        .into_iter()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Number of times `word` appears in `text` with a word boundary on both of its sides
+fn count_words(text: &str, word: &str) -> usize {
+    if !word.chars().any(char::is_alphanumeric) {
+        return 0;
+    }
+
+    let mut count = 0;
+    let mut start = 0;
+
+    while let Some(offset) = text[start..].find(word) {
+        let match_start = start + offset;
+        let match_end = match_start + word.len();
+
+        let before = text[..match_start].chars().next_back();
+        let after = text[match_end..].chars().next();
+        let first = word.chars().next();
+        let last = word.chars().next_back();
+
+        if !is_joined(before, first) && !is_joined(last, after) {
+            count += 1;
+            start = match_end;
+        } else {
+            // Step one character, so a match that starts inside this one is still found
+            start = match_start + first.map_or(1, char::len_utf8);
+        }
+    }
+
+    count
+}
+
+/// Whether the characters `a` then `b` are in the same word
+fn is_joined(a: Option<char>, b: Option<char>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            is_word_char(a) && is_word_char(b) && !is_unspaced(a) && !is_unspaced(b)
+        }
+        _ => false,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `c` is of a script that is written without spaces between words
+fn is_unspaced(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0E00}'..='\u{0EFF}' // Thai and Lao
+            | '\u{1000}'..='\u{109F}' // Myanmar
+            | '\u{1780}'..='\u{17FF}' // Khmer
+            | '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+            | '\u{3400}'..='\u{4DBF}' // CJK Unified Ideographs Extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+            | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+            | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+            | '\u{20000}'..='\u{2FA1F}' // CJK Unified Ideographs Extension B and later
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn whole_words_only() {
+        let tag_names = names(&["art", "cat", "work"]);
+
+        assert!(suggest_tags("Start the concatenation", &tag_names).is_empty());
+        assert_eq!(
+            suggest_tags("Art class, then a cat.", &tag_names),
+            ["art", "cat"]
+        );
+        assert_eq!(suggest_tags("(work)", &tag_names), ["work"]);
+        assert!(suggest_tags("homework and work_items", &tag_names).is_empty());
+    }
+
+    #[test]
+    fn ignores_case() {
+        let tag_names = names(&["Rust", "ÉTÉ"]);
+        assert_eq!(
+            suggest_tags("RUST is fun. Learning rust in été", &tag_names),
+            ["Rust", "ÉTÉ"]
+        );
+    }
+
+    #[test]
+    fn ranked_by_frequency() {
+        let tag_names = names(&["meeting", "budget", "travel", "unused"]);
+        let body = "Budget meeting. The travel budget was the main budget topic. Next meeting soon";
+
+        assert_eq!(
+            suggest_tags(body, &tag_names),
+            ["budget", "meeting", "travel"]
+        );
+    }
+
+    #[test]
+    fn multiple_words_and_symbols() {
+        let tag_names = names(&["machine learning", "C++", "work/meeting", "!!!", ""]);
+        let body = "Notes on machine learning in C++ for work/meeting. Machine learningish!!!";
+
+        assert_eq!(
+            suggest_tags(body, &tag_names),
+            ["machine learning", "C++", "work/meeting"]
+        );
+    }
+
+    #[test]
+    fn overlapping_candidates() {
+        let tag_names = names(&["aa"]);
+        assert_eq!(count_words("aaa aa", "aa"), 1);
+        assert_eq!(suggest_tags("aaa aa", &tag_names), ["aa"]);
+    }
+
+    #[test]
+    fn unspaced_scripts() {
+        let tag_names = names(&["東京", "タワー", "旅行", "tokyo"]);
+
+        // Words are not separated by spaces, so they match anywhere
+        assert_eq!(
+            suggest_tags("東京タワーに行った。東京旅行", &tag_names),
+            ["東京", "タワー", "旅行"]
+        );
+
+        // Latin words next to them still match
+        assert_eq!(suggest_tags("Tokyo東京", &tag_names), ["東京", "tokyo"]);
+        assert!(suggest_tags("Tokyoite", &tag_names).is_empty());
+    }
+
+    #[test]
+    fn scanned_part_limit() {
+        assert_eq!(scanned_part("short"), "short");
+
+        let long = format!("{}é", "a".repeat(MAX_SCANNED_BYTES - 1));
+        assert_eq!(scanned_part(&long).len(), MAX_SCANNED_BYTES - 1);
+
+        let body = format!("{} late", "a ".repeat(MAX_SCANNED_BYTES));
+        assert!(suggest_tags(&body, &names(&["late"])).is_empty());
+    }
+}
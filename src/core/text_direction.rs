@@ -0,0 +1,44 @@
+use gtk::{pango, TextDirection};
+
+/// Direction of `text` from its first strong character, like a letter, or `None` when it has
+/// none, like when it is only digits, punctuation, and markdown syntax
+pub fn base_direction(text: &str) -> Option<TextDirection> {
+    match pango::find_base_dir(text) {
+        pango::Direction::Ltr | pango::Direction::WeakLtr => Some(TextDirection::Ltr),
+        pango::Direction::Rtl | pango::Direction::WeakRtl => Some(TextDirection::Rtl),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strong_characters() {
+        assert_eq!(base_direction("Groceries"), Some(TextDirection::Ltr));
+        assert_eq!(base_direction("קניות"), Some(TextDirection::Rtl));
+        assert_eq!(base_direction("مشتريات"), Some(TextDirection::Rtl));
+    }
+
+    #[test]
+    fn mixed_directions() {
+        assert_eq!(base_direction("שלום world"), Some(TextDirection::Rtl));
+        assert_eq!(base_direction("Hello שלום"), Some(TextDirection::Ltr));
+        assert_eq!(base_direction("مرحبا GNOME 42"), Some(TextDirection::Rtl));
+    }
+
+    #[test]
+    fn neutral_characters_are_skipped() {
+        assert_eq!(base_direction("# 2022 - قائمة"), Some(TextDirection::Rtl));
+        assert_eq!(base_direction("1. **עברית**"), Some(TextDirection::Rtl));
+        assert_eq!(base_direction("\n\n- Milk"), Some(TextDirection::Ltr));
+    }
+
+    #[test]
+    fn no_strong_characters() {
+        assert_eq!(base_direction(""), None);
+        assert_eq!(base_direction("2022-04-01"), None);
+        assert_eq!(base_direction("# 🎉 ---"), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::ops::Range;
+
+use super::is_code_fence;
+
+/// The text of the first level 1 ATX heading of `content`, like `# Title`, outside of code
+/// blocks. The heading may be empty, like while it is being written.
+pub fn first_heading(content: &str) -> Option<&str> {
+    first_heading_line(content).map(|(_, text)| text)
+}
+
+/// `content` with its first level 1 heading replaced by one of `title`, or with one added at
+/// its top when it has none
+pub fn with_heading(content: &str, title: &str) -> String {
+    let heading = format!(
+        "# {}",
+        title.split_whitespace().collect::<Vec<_>>().join(" ")
+    );
+
+    match first_heading_line(content) {
+        Some((range, _)) => {
+            let mut new_content = String::with_capacity(content.len() + heading.len());
+            new_content.push_str(&content[..range.start]);
+            new_content.push_str(&heading);
+            new_content.push_str(&content[range.end..]);
+            new_content
+        }
+        None if content.is_empty() => format!("{}\n", heading),
+        None => format!("{}\n\n{}", heading, content),
+    }
+}
+
+/// The range of the line of the first heading of `content` of any level with `text` as text,
+/// matched case-insensitively, without its line ending. This is where a link to a section of
+/// a note goes.
+pub fn heading_line(content: &str, text: &str) -> Option<Range<usize>> {
+    let text = text.trim().to_lowercase();
+
+    heading_lines(content)
+        .find(|(_, _, heading)| heading.to_lowercase() == text)
+        .map(|(range, _, _)| range)
+}
+
+/// The range of the line of the first level 1 heading of `content`, without its line
+/// ending, and the text of the heading
+fn first_heading_line(content: &str) -> Option<(Range<usize>, &str)> {
+    heading_lines(content)
+        .find(|(_, level, _)| *level == 1)
+        .map(|(range, _, text)| (range, text))
+}
+
+/// The range of the line of each ATX heading of `content` outside of code blocks, without its
+/// line ending, with the level and the text of the heading
+fn heading_lines(content: &str) -> impl Iterator<Item = (Range<usize>, usize, &str)> {
+    let mut is_in_code_block = false;
+    let mut line_start = 0;
+
+    content.split('\n').filter_map(move |line| {
+        let range = line_start..line_start + line.trim_end_matches('\r').len();
+        line_start += line.len() + 1;
+
+        if is_code_fence(line) {
+            is_in_code_block = !is_in_code_block;
+            return None;
+        }
+
+        if is_in_code_block {
+            return None;
+        }
+
+        let (level, text) = heading_text(&content[range.clone()])?;
+        Some((range, level, text))
+    })
+}
+
+/// The level and the text of `line` if it is an ATX heading
+fn heading_text(line: &str) -> Option<(usize, &str)> {
+    // Indented by four spaces or more, it is a code block
+    let rest = line.trim_start_matches(' ');
+    if line.len() - rest.len() > 3 {
+        return None;
+    }
+
+    // Not `#tag`, nor more than the six levels there are
+    let without_hashes = rest.trim_start_matches('#');
+    let level = rest.len() - without_hashes.len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = without_hashes;
+    if !rest.is_empty() && !rest.starts_with(|c| c == ' ' || c == '\t') {
+        return None;
+    }
+
+    // The closing hashes are only part of the text when they are right after it, like in `C#`
+    let text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    if without_closing.is_empty() {
+        Some((level, ""))
+    } else if without_closing.ends_with(|c| c == ' ' || c == '\t') {
+        Some((level, without_closing.trim_end()))
+    } else {
+        Some((level, text))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn heading() {
+        assert_eq!(first_heading("# Groceries\n\nMilk"), Some("Groceries"));
+        assert_eq!(first_heading("Intro\n\n  #   Spaced  \n"), Some("Spaced"));
+        assert_eq!(first_heading("# Closed ##\n"), Some("Closed"));
+        assert_eq!(first_heading("# Learning C#\n"), Some("Learning C#"));
+        assert_eq!(first_heading("# Windows\r\nText"), Some("Windows"));
+        assert_eq!(first_heading("#\nText"), Some(""));
+    }
+
+    #[test]
+    fn no_heading() {
+        assert_eq!(first_heading(""), None);
+        assert_eq!(first_heading("Just text\n"), None);
+        assert_eq!(first_heading("## Section\n### Subsection\n"), None);
+        assert_eq!(first_heading("#tag and text\n"), None);
+        assert_eq!(first_heading("    # Indented code\n"), None);
+        assert_eq!(first_heading("Title\n=====\n"), None);
+    }
+
+    #[test]
+    fn multiple_headings() {
+        let content = "## Section\n# First\n\n# Second\n";
+        assert_eq!(first_heading(content), Some("First"));
+        assert_eq!(
+            with_heading(content, "Renamed"),
+            "## Section\n# Renamed\n\n# Second\n"
+        );
+    }
+
+    #[test]
+    fn heading_in_code_block() {
+        let content = "```sh\n# Comment\n```\n\n# Real\n";
+        assert_eq!(first_heading(content), Some("Real"));
+
+        assert_eq!(first_heading("~~~\n# Comment\n~~~\n"), None);
+        // Not closed, so the rest of the note is code
+        assert_eq!(first_heading("```\n# Comment\n"), None);
+
+        assert_eq!(
+            with_heading("```\n# Comment\n```\n", "Script"),
+            "# Script\n\n```\n# Comment\n```\n"
+        );
+    }
+
+    #[test]
+    fn section_heading() {
+        let content = "# Trip\n\n```\n## Packing\n```\n\n## Packing ##\nSocks\n### Food\r\n";
+        assert_eq!(heading_line(content, "Packing"), Some(28..41));
+        assert_eq!(&content[28..41], "## Packing ##");
+        assert_eq!(heading_line(content, " food "), Some(48..56));
+        assert_eq!(heading_line(content, "trip"), Some(0..6));
+
+        assert_eq!(heading_line(content, "Socks"), None);
+        assert_eq!(heading_line("####### Seven\n", "Seven"), None);
+        assert_eq!(heading_line("#Packing\n", "Packing"), None);
+    }
+
+    #[test]
+    fn update_heading() {
+        assert_eq!(with_heading("# Old\nText", "New"), "# New\nText");
+        assert_eq!(with_heading("# Old\r\nText", "New"), "# New\r\nText");
+        assert_eq!(with_heading("Intro\n# Old", "New"), "Intro\n# New");
+        assert_eq!(with_heading("# Old", "Two\nLines "), "# Two Lines");
+    }
+
+    #[test]
+    fn insert_heading() {
+        assert_eq!(with_heading("", "New"), "# New\n");
+        assert_eq!(with_heading("Text\n", "New"), "# New\n\nText\n");
+        assert_eq!(with_heading("## Section\n", "New"), "# New\n\n## Section\n");
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use super::{AttachmentFile, AttachmentReferences, FileStamp};
+
+/// The attachments of `files` that are used, and only by the notes for which `is_trashed`
+/// is true, so they can be deleted with them when the trash is emptied
+pub fn trash_only_attachments<'a>(
+    files: &'a [AttachmentFile],
+    references: &AttachmentReferences,
+    is_trashed: impl Fn(usize) -> bool,
+) -> Vec<&'a AttachmentFile> {
+    files
+        .iter()
+        .filter(|file| {
+            let indices = references.referencing(&file.file_name());
+            !indices.is_empty() && indices.iter().all(|index| is_trashed(*index))
+        })
+        .collect()
+}
+
+/// Bytes freed by emptying the trash, which are the note files named `trashed_file_names`,
+/// with their size from `note_stamps`, and the `attachments` only they use. Notes without a
+/// file yet take no space.
+pub fn trash_size(
+    note_stamps: &HashMap<String, FileStamp>,
+    trashed_file_names: &[String],
+    attachments: &[&AttachmentFile],
+) -> u64 {
+    let notes_size = trashed_file_names
+        .iter()
+        .filter_map(|file_name| note_stamps.get(file_name))
+        .map(|stamp| stamp.size)
+        .sum::<u64>();
+    let attachments_size = attachments
+        .iter()
+        .map(|attachment| attachment.size)
+        .sum::<u64>();
+
+    notes_size + attachments_size
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{path::PathBuf, time::Duration};
+
+    fn attachment(file_name: &str, size: u64) -> AttachmentFile {
+        AttachmentFile {
+            path: PathBuf::from("/notes").join(file_name),
+            size,
+        }
+    }
+
+    fn stamp(size: u64) -> FileStamp {
+        FileStamp {
+            modified: Duration::from_secs(1_650_000_000),
+            size,
+        }
+    }
+
+    /// Scanned notes, each with its attachments and content
+    fn references(notes: &[(&[&str], &str)]) -> AttachmentReferences {
+        let notes = notes
+            .iter()
+            .map(|(attachments, content)| {
+                (
+                    attachments
+                        .iter()
+                        .map(|file_name| PathBuf::from("/notes").join(file_name))
+                        .collect(),
+                    content.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        AttachmentReferences::scan(&notes)
+    }
+
+    #[test]
+    fn attachments_only_used_in_trash() {
+        let files = [
+            attachment("Linked.png", 1),
+            attachment("Orphan.txt", 2),
+            attachment("Scan.png", 3),
+            attachment("Shared.pdf", 4),
+        ];
+        // The first note is trashed
+        let references = references(&[
+            (&["Scan.png", "Shared.pdf"], "![Linked](Linked.png)"),
+            (&[], "[Shared](Shared.pdf)"),
+        ]);
+
+        let attachments = trash_only_attachments(&files, &references, |index| index == 0);
+        assert_eq!(attachments, [&files[0], &files[2]]);
+
+        // Orphans are not in the trash, as no note there uses them
+        let attachments = trash_only_attachments(&files, &references, |_| true);
+        assert_eq!(attachments, [&files[0], &files[2], &files[3]]);
+
+        assert!(trash_only_attachments(&files, &references, |_| false).is_empty());
+    }
+
+    #[test]
+    fn size() {
+        let note_stamps = HashMap::from([
+            ("Trashed.md".to_string(), stamp(100)),
+            ("Also Trashed.md".to_string(), stamp(20)),
+            ("Kept.md".to_string(), stamp(5000)),
+        ]);
+        let files = [attachment("Scan.png", 3000), attachment("Other.png", 7)];
+
+        let trashed_file_names = ["Trashed.md", "Also Trashed.md", "Not Saved Yet.md"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(trash_size(&note_stamps, &trashed_file_names, &[]), 120);
+        assert_eq!(
+            trash_size(&note_stamps, &trashed_file_names, &[&files[0]]),
+            3120
+        );
+
+        assert_eq!(trash_size(&note_stamps, &[], &[]), 0);
+        assert_eq!(trash_size(&HashMap::new(), &trashed_file_names, &[]), 0);
+    }
+}
@@ -0,0 +1,944 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{is_code_fence, DateTime, ExtraFields, MetadataData};
+
+/// Folders of a vault that don't have notes, like the settings of Obsidian
+const SKIPPED_FOLDERS: &[&str] = &[".obsidian", ".trash", ".git"];
+
+/// Fields of the front matter that Noteworthy uses itself, so they can't be kept as is
+const RESERVED_FIELDS: &[&str] = &[
+    "title",
+    "tag_list",
+    "attachment_list",
+    "last_modified",
+    "is_pinned",
+    "is_trashed",
+    "color",
+    "reminder",
+    "gist_id",
+    "language",
+    "created",
+];
+const TAG_FIELDS: &[&str] = &["tags", "tag"];
+
+/// `#tag` after whitespace or at the start of the line, so `[[Note#Heading]]` and links with
+/// fragments are not tags
+static RE_HASHTAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)#([\p{L}\p{N}_/-]+)").unwrap());
+/// `![[File]]` and `[[File|Label]]`, with the `!` captured
+static RE_WIKI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(!?)\[\[([^\[\]|\n]+)(?:\|([^\[\]\n]*))?\]\]").unwrap());
+/// `![Label](path)` and `[Label](<path with spaces>)`
+static RE_MARKDOWN_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(!?)\[([^\[\]\n]*)\]\((?:<([^<>\n]+)>|([^()\s]+))\)").unwrap());
+
+/// How a vault is converted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VaultImportOptions {
+    /// Keep the `#hashtags` in the content of the notes, instead of only making them tags
+    pub keep_hashtags: bool,
+    /// Tag the notes with the path of the folder they are in
+    pub folders_as_tags: bool,
+}
+
+/// A file of a vault, at its path relative to the vault
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultFile {
+    pub path: PathBuf,
+    /// Contents of the Markdown files, `None` for the other files
+    pub text: Option<String>,
+}
+
+/// A note of the vault, converted to the data of a Noteworthy note
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultNote {
+    pub source: PathBuf,
+    pub metadata: MetadataData,
+    pub content: String,
+    /// Names of the files copied into the notebook that the note links to
+    pub attachments: Vec<String>,
+}
+
+/// A file of the vault that notes link to, and the name it is copied into the notebook as
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultAttachment {
+    pub source: PathBuf,
+    pub file_name: String,
+}
+
+/// A link to a note or file that is not in the vault
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnresolvedLink {
+    /// Title of the note with the link
+    pub note_title: String,
+    pub target: String,
+}
+
+/// Everything to create in the notebook to import a vault
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VaultImport {
+    pub notes: Vec<VaultNote>,
+    pub attachments: Vec<VaultAttachment>,
+    /// Every tag of the converted notes, sorted
+    pub tags: Vec<String>,
+    pub unresolved_links: Vec<UnresolvedLink>,
+}
+
+/// List the files of the vault at `root`, reading the Markdown ones, skipping hidden files and
+/// the settings of Obsidian
+pub fn read_vault(root: &Path) -> io::Result<Vec<VaultFile>> {
+    let mut files = Vec::new();
+    read_vault_folder(root, Path::new(""), &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn read_vault_folder(root: &Path, folder: &Path, files: &mut Vec<VaultFile>) -> io::Result<()> {
+    for entry in fs::read_dir(root.join(folder))? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let path = folder.join(&file_name);
+
+        let is_hidden = file_name.to_string_lossy().starts_with('.');
+        if is_hidden || SKIPPED_FOLDERS.iter().any(|skipped| file_name == *skipped) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            read_vault_folder(root, &path, files)?;
+            continue;
+        }
+
+        let text = if is_markdown(&path) {
+            match String::from_utf8(fs::read(entry.path())?) {
+                Ok(text) => Some(text),
+                Err(err) => {
+                    log::warn!("Skipping `{}`, it is not UTF-8: {:?}", path.display(), err);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        files.push(VaultFile { path, text });
+    }
+
+    Ok(())
+}
+
+/// Convert the notes of a vault. Files the notes link to are attachments, named so they don't
+/// take the name of another attachment nor of one of `existing_file_names`.
+pub fn convert_vault(
+    files: &[VaultFile],
+    existing_file_names: &HashSet<String>,
+    options: VaultImportOptions,
+) -> VaultImport {
+    let mut converter = Converter {
+        files,
+        used_file_names: existing_file_names.clone(),
+        vault_import: VaultImport::default(),
+    };
+
+    let mut tags = BTreeSet::new();
+
+    for file in files {
+        if let Some(ref text) = file.text {
+            let note = converter.convert_note(&file.path, text, options);
+            tags.extend(note.metadata.tag_list.iter().cloned());
+            converter.vault_import.notes.push(note);
+        }
+    }
+
+    let mut vault_import = converter.vault_import;
+    vault_import.tags = tags.into_iter().collect();
+    vault_import.unresolved_links.sort();
+    vault_import.unresolved_links.dedup();
+    vault_import
+}
+
+struct Converter<'a> {
+    files: &'a [VaultFile],
+    used_file_names: HashSet<String>,
+    vault_import: VaultImport,
+}
+
+impl Converter<'_> {
+    fn convert_note(&mut self, path: &Path, text: &str, options: VaultImportOptions) -> VaultNote {
+        let (front_matter, body) = split_front_matter(text);
+        let mut metadata = front_matter
+            .map(|front_matter| metadata_from_front_matter(path, front_matter))
+            .unwrap_or_default();
+
+        if metadata.title.is_empty() {
+            metadata.title = file_stem(path);
+        }
+
+        let (content, hashtags) = take_hashtags(body, options.keep_hashtags);
+        metadata.tag_list.extend(hashtags);
+
+        if options.folders_as_tags {
+            if let Some(folder_tag) = folder_tag(path) {
+                metadata.tag_list.push(folder_tag);
+            }
+        }
+
+        let mut seen_tags = HashSet::new();
+        metadata
+            .tag_list
+            .retain(|tag| !tag.is_empty() && seen_tags.insert(tag.clone()));
+
+        let folder = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut attachments = Vec::new();
+        let content = self.rewrite_links(&metadata.title, folder, &content, &mut attachments);
+
+        VaultNote {
+            source: path.to_path_buf(),
+            metadata,
+            content,
+            attachments,
+        }
+    }
+
+    /// Point the links to files of the vault to where they are copied in the notebook. Wiki
+    /// links to notes are kept, as the notes keep their names as titles.
+    fn rewrite_links(
+        &mut self,
+        note_title: &str,
+        folder: &Path,
+        content: &str,
+        attachments: &mut Vec<String>,
+    ) -> String {
+        let mut lines = Vec::new();
+        let mut is_in_code_block = false;
+
+        for line in content.split('\n') {
+            if is_code_fence(line) {
+                is_in_code_block = !is_in_code_block;
+            }
+
+            if is_in_code_block || is_code_fence(line) {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let line = RE_WIKI_LINK.replace_all(line, |captures: &Captures| {
+                let is_embed = !captures[1].is_empty();
+                let target = captures[2].trim();
+                let label = captures.get(3).map(|label| label.as_str().trim());
+
+                self.rewrite_wiki_link(note_title, folder, target, label, is_embed, attachments)
+                    .unwrap_or_else(|| captures[0].to_string())
+            });
+
+            let line = RE_MARKDOWN_LINK.replace_all(&line, |captures: &Captures| {
+                let destination = captures
+                    .get(3)
+                    .or_else(|| captures.get(4))
+                    .map_or("", |destination| destination.as_str());
+
+                if is_external(destination) {
+                    return captures[0].to_string();
+                }
+
+                let target = percent_decode(destination);
+                if let Some(file_name) = self.attach(folder, &target, attachments) {
+                    return format!(
+                        "{}[{}]({})",
+                        &captures[1],
+                        &captures[2],
+                        destination_of(&file_name)
+                    );
+                }
+
+                if self.find_note(folder, &target).is_none() {
+                    self.unresolved(note_title, &target);
+                }
+                captures[0].to_string()
+            });
+
+            lines.push(line.into_owned());
+        }
+
+        lines.join("\n")
+    }
+
+    /// The Markdown link to the attachment that `target` links to, or `None` to keep the
+    /// wiki link as is
+    fn rewrite_wiki_link(
+        &mut self,
+        note_title: &str,
+        folder: &Path,
+        target: &str,
+        label: Option<&str>,
+        is_embed: bool,
+        attachments: &mut Vec<String>,
+    ) -> Option<String> {
+        // Links to a heading or block of a note
+        let target_path = target.split('#').next().unwrap_or_default().trim();
+
+        if target_path.is_empty() || self.find_note(folder, target_path).is_some() {
+            return None;
+        }
+
+        let file_name = match self.attach(folder, target_path, attachments) {
+            Some(file_name) => file_name,
+            None => {
+                self.unresolved(note_title, target_path);
+                return None;
+            }
+        };
+
+        // Like `![[photo.png|300]]`, which sets the width of an image rather than a label
+        let is_size = label.map_or(false, |label| {
+            label.chars().all(|c| c.is_ascii_digit() || c == 'x')
+        });
+        let label = label.filter(|_| !is_size).unwrap_or(target_path);
+        Some(format!(
+            "{}[{}]({})",
+            if is_embed { "!" } else { "" },
+            label,
+            destination_of(&file_name)
+        ))
+    }
+
+    /// Copy the file of the vault that `target` links to as an attachment, returning its name
+    /// in the notebook
+    fn attach(
+        &mut self,
+        folder: &Path,
+        target: &str,
+        attachments: &mut Vec<String>,
+    ) -> Option<String> {
+        let source = self
+            .find_file(folder, target)
+            .filter(|file| file.text.is_none())?
+            .path
+            .clone();
+
+        let file_name = match self
+            .vault_import
+            .attachments
+            .iter()
+            .find(|attachment| attachment.source == source)
+        {
+            Some(attachment) => attachment.file_name.clone(),
+            None => {
+                let file_name = unique_file_name(&source, &self.used_file_names);
+                self.used_file_names.insert(file_name.clone());
+                self.vault_import.attachments.push(VaultAttachment {
+                    source,
+                    file_name: file_name.clone(),
+                });
+                file_name
+            }
+        };
+
+        if !attachments.contains(&file_name) {
+            attachments.push(file_name.clone());
+        }
+
+        Some(file_name)
+    }
+
+    /// The note that `target` links to, with or without its `.md` extension
+    fn find_note(&self, folder: &Path, target: &str) -> Option<&VaultFile> {
+        let target = if is_markdown(Path::new(target)) {
+            target.to_string()
+        } else {
+            format!("{}.md", target)
+        };
+
+        self.find_file(folder, &target)
+            .filter(|file| file.text.is_some())
+    }
+
+    /// Like Obsidian, `target` is relative to the folder of the note or to the vault, or is
+    /// only the name of a file anywhere in the vault
+    fn find_file(&self, folder: &Path, target: &str) -> Option<&VaultFile> {
+        let candidates = [
+            normalize(&folder.join(target)),
+            normalize(Path::new(target)),
+        ];
+
+        if let Some(file) = candidates
+            .iter()
+            .flatten()
+            .find_map(|candidate| self.files.iter().find(|file| &file.path == candidate))
+        {
+            return Some(file);
+        }
+
+        let file_name = Path::new(target).file_name()?;
+        self.files
+            .iter()
+            .find(|file| file.path.file_name() == Some(file_name))
+    }
+
+    fn unresolved(&mut self, note_title: &str, target: &str) {
+        self.vault_import.unresolved_links.push(UnresolvedLink {
+            note_title: note_title.to_string(),
+            target: target.to_string(),
+        });
+    }
+}
+
+/// The YAML of the front matter, if there is one, and the content after it
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let rest = match text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => return (None, text),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    // Never closed, so it is only a horizontal rule
+    (None, text)
+}
+
+fn metadata_from_front_matter(path: &Path, front_matter: &str) -> MetadataData {
+    let mut fields = match serde_yaml::from_str::<serde_yaml::Mapping>(front_matter) {
+        Ok(fields) => fields,
+        Err(err) => {
+            log::warn!(
+                "Ignoring invalid front matter of `{}`: {:?}",
+                path.display(),
+                err
+            );
+            return MetadataData::default();
+        }
+    };
+
+    let mut metadata = MetadataData::default();
+
+    for field in TAG_FIELDS {
+        if let Some(value) = fields.remove(&serde_yaml::Value::from(*field)) {
+            metadata.tag_list.extend(tags_from_value(&value));
+        }
+    }
+
+    for field in RESERVED_FIELDS {
+        if let Some(value) = fields.remove(&serde_yaml::Value::from(*field)) {
+            match (*field, value) {
+                ("title", serde_yaml::Value::String(title)) => metadata.title = title,
+                ("created", value) => {
+                    metadata.created = created_from_value(&value);
+
+                    if metadata.created.is_none() {
+                        log::info!(
+                            "Dropping invalid created date of `{}` with value `{:?}`",
+                            path.display(),
+                            value
+                        );
+                    }
+                }
+                (field, value) => log::info!(
+                    "Dropping field `{}` of `{}` with value `{:?}`",
+                    field,
+                    path.display(),
+                    value
+                ),
+            }
+        }
+    }
+
+    metadata.extra_fields = ExtraFields(fields);
+    metadata
+}
+
+/// A date with a time like `2022-01-31T14:30:00+01:00`, or only a date like `2022-01-31`,
+/// which is taken as the start of that day
+fn created_from_value(value: &serde_yaml::Value) -> Option<DateTime> {
+    serde_yaml::from_value(value.clone()).ok()
+}
+
+/// Tags written as a list, or as a string separated by commas or spaces
+fn tags_from_value(value: &serde_yaml::Value) -> Vec<String> {
+    let names: Vec<&str> = match value {
+        serde_yaml::Value::Sequence(items) => {
+            items.iter().filter_map(|item| item.as_str()).collect()
+        }
+        serde_yaml::Value::String(text) => text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    names
+        .into_iter()
+        .map(|name| name.trim().trim_start_matches('#').to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The `#hashtags` of `content` outside of code, and the content with them removed unless
+/// `keep` is set
+fn take_hashtags(content: &str, keep: bool) -> (String, Vec<String>) {
+    let mut tags = Vec::new();
+    let mut lines = Vec::new();
+    let mut is_in_code_block = false;
+
+    for line in content.split('\n') {
+        if is_code_fence(line) {
+            is_in_code_block = !is_in_code_block;
+        }
+
+        if is_in_code_block || is_code_fence(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        // Only what is between backticks is inline code
+        let mut new_line = String::new();
+        for (index, segment) in line.split('`').enumerate() {
+            if index > 0 {
+                new_line.push('`');
+            }
+
+            if index % 2 == 1 {
+                new_line.push_str(segment);
+                continue;
+            }
+
+            let segment = RE_HASHTAG.replace_all(segment, |captures: &Captures| {
+                let name = captures[2].trim_end_matches('/');
+
+                // Like `#1`, which is more likely a number than a tag
+                if name.is_empty() || name.chars().all(|c| c.is_numeric()) {
+                    return captures[0].to_string();
+                }
+
+                tags.push(name.to_string());
+
+                if keep {
+                    captures[0].to_string()
+                } else {
+                    captures[1].to_string()
+                }
+            });
+            new_line.push_str(&segment);
+        }
+
+        if !keep && new_line != line {
+            new_line.truncate(new_line.trim_end().len());
+        }
+
+        lines.push(new_line);
+    }
+
+    (lines.join("\n"), tags)
+}
+
+fn folder_tag(path: &Path) -> Option<String> {
+    let names = path
+        .parent()?
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join("/"))
+    }
+}
+
+/// `path` without `.` and `..`, or `None` if it goes out of the vault
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// The file name of `source`, with a number added when it is already used
+pub(super) fn unique_file_name(source: &Path, used_file_names: &HashSet<String>) -> String {
+    let file_name = source.file_name().map_or_else(
+        || "Attachment".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+
+    if !used_file_names.contains(&file_name) {
+        return file_name;
+    }
+
+    let stem = source
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+    let extension = source
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| format!("{}-{}{}", stem, n, extension))
+        .find(|file_name| !used_file_names.contains(file_name))
+        .unwrap()
+}
+
+/// The destination of a Markdown link to `file_name`, which has to be between `<>` when it
+/// has spaces
+pub(super) fn destination_of(file_name: &str) -> String {
+    if file_name.contains(char::is_whitespace) {
+        format!("<{}>", file_name)
+    } else {
+        file_name.to_string()
+    }
+}
+
+pub(super) fn is_external(destination: &str) -> bool {
+    destination.contains("://")
+        || destination.starts_with('#')
+        || destination.starts_with("mailto:")
+}
+
+/// Decode the `%20`-like escapes of a link destination, keeping invalid ones as is
+pub(super) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("md"))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn note(path: &str, text: &str) -> VaultFile {
+        VaultFile {
+            path: PathBuf::from(path),
+            text: Some(text.to_string()),
+        }
+    }
+
+    fn other(path: &str) -> VaultFile {
+        VaultFile {
+            path: PathBuf::from(path),
+            text: None,
+        }
+    }
+
+    fn convert(files: &[VaultFile], options: VaultImportOptions) -> VaultImport {
+        convert_vault(files, &HashSet::new(), options)
+    }
+
+    #[test]
+    fn front_matter_tags() {
+        let vault_import = convert(
+            &[
+                note(
+                    "List.md",
+                    "---\ntags: [shopping, '#home']\naliases: [Groceries]\n---\nMilk\n",
+                ),
+                note("Other.md", "---\ntags: work, ideas later\n---\n"),
+            ],
+            VaultImportOptions::default(),
+        );
+
+        let list = &vault_import.notes[0];
+        assert_eq!(list.metadata.title, "List");
+        assert_eq!(list.metadata.tag_list, ["shopping", "home"]);
+        assert_eq!(list.content, "Milk\n");
+        assert_eq!(
+            serde_yaml::to_string(&list.metadata.extra_fields).unwrap(),
+            "---\naliases:\n  - Groceries\n"
+        );
+
+        assert_eq!(
+            vault_import.notes[1].metadata.tag_list,
+            ["work", "ideas", "later"]
+        );
+        assert_eq!(
+            vault_import.tags,
+            ["home", "ideas", "later", "shopping", "work"]
+        );
+    }
+
+    #[test]
+    fn reserved_front_matter_fields() {
+        let vault_import = convert(
+            &[note(
+                "Note.md",
+                "---\ntitle: Better title\nis_pinned: yes\ncreated: 2022-01-31\n---\nText",
+            )],
+            VaultImportOptions::default(),
+        );
+
+        let metadata = &vault_import.notes[0].metadata;
+        assert_eq!(metadata.title, "Better title");
+        assert!(!metadata.is_pinned);
+        assert!(metadata.extra_fields.0.is_empty());
+
+        let created = metadata.created.unwrap().to_local();
+        assert_eq!(created.date().naive_local().to_string(), "2022-01-31");
+        assert_eq!(created.time(), chrono::NaiveTime::from_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn invalid_or_unclosed_front_matter() {
+        let vault_import = convert(
+            &[
+                note("Invalid.md", "---\n: [\n---\nText"),
+                note("Rule.md", "---\nText after a rule"),
+            ],
+            VaultImportOptions::default(),
+        );
+
+        assert_eq!(vault_import.notes[0].content, "Text");
+        assert_eq!(vault_import.notes[1].content, "---\nText after a rule");
+    }
+
+    #[test]
+    fn hashtags_stripped() {
+        let vault_import = convert(
+            &[note(
+                "Note.md",
+                "# Heading\nBuy milk #shopping #home/kitchen\n#1 of [[Other#Part]] `#code`\n```\n#not-a-tag\n```\nhttps://example.com/#anchor",
+            )],
+            VaultImportOptions::default(),
+        );
+
+        let note = &vault_import.notes[0];
+        assert_eq!(note.metadata.tag_list, ["shopping", "home/kitchen"]);
+        assert_eq!(
+            note.content,
+            "# Heading\nBuy milk\n#1 of [[Other#Part]] `#code`\n```\n#not-a-tag\n```\nhttps://example.com/#anchor"
+        );
+    }
+
+    #[test]
+    fn hashtags_kept() {
+        let options = VaultImportOptions {
+            keep_hashtags: true,
+            ..VaultImportOptions::default()
+        };
+        let vault_import = convert(
+            &[note(
+                "Note.md",
+                "---\ntags: [home]\n---\nBuy milk #shopping #home",
+            )],
+            options,
+        );
+
+        let note = &vault_import.notes[0];
+        assert_eq!(note.metadata.tag_list, ["home", "shopping"]);
+        assert_eq!(note.content, "Buy milk #shopping #home");
+    }
+
+    #[test]
+    fn folders_as_tags() {
+        let files = [
+            note("Work/Projects/Plan.md", "Plan"),
+            note("Root.md", "Root"),
+        ];
+
+        let vault_import = convert(&files, VaultImportOptions::default());
+        assert!(vault_import.notes[0].metadata.tag_list.is_empty());
+
+        let options = VaultImportOptions {
+            folders_as_tags: true,
+            ..VaultImportOptions::default()
+        };
+        let vault_import = convert(&files, options);
+        assert_eq!(vault_import.notes[0].metadata.tag_list, ["Work/Projects"]);
+        assert!(vault_import.notes[1].metadata.tag_list.is_empty());
+    }
+
+    #[test]
+    fn wiki_links_preserved() {
+        let vault_import = convert(
+            &[
+                note(
+                    "Daily/Today.md",
+                    "See [[Plan]], [[Plan#Goals|goals]] and [[Missing]]",
+                ),
+                note("Work/Plan.md", "![[Today]]"),
+            ],
+            VaultImportOptions::default(),
+        );
+
+        assert_eq!(
+            vault_import.notes[0].content,
+            "See [[Plan]], [[Plan#Goals|goals]] and [[Missing]]"
+        );
+        assert_eq!(vault_import.notes[1].content, "![[Today]]");
+        assert_eq!(
+            vault_import.unresolved_links,
+            [UnresolvedLink {
+                note_title: "Today".into(),
+                target: "Missing".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn attachments_copied_and_rewritten() {
+        let vault_import = convert(
+            &[
+                note(
+                    "Notes/Trip.md",
+                    "![[photo.png]] ![[scan.pdf|Scan]] ![[photo.png|300]]\n![Map](../assets/Map%20Large.jpg) [Doc](missing.pdf) [Site](https://example.com/a.png)",
+                ),
+                note("Other.md", "![](Notes/photo.png)"),
+                other("Notes/photo.png"),
+                other("assets/Map Large.jpg"),
+                other("assets/scan.pdf"),
+            ],
+            VaultImportOptions::default(),
+        );
+
+        let trip = &vault_import.notes[0];
+        assert_eq!(
+            trip.content,
+            "![photo.png](photo.png) ![Scan](scan.pdf) ![photo.png](photo.png)\n![Map](<Map Large.jpg>) [Doc](missing.pdf) [Site](https://example.com/a.png)"
+        );
+        assert_eq!(trip.attachments, ["photo.png", "scan.pdf", "Map Large.jpg"]);
+
+        // Each file is copied once, even when linked from many notes
+        let other = &vault_import.notes[1];
+        assert_eq!(other.content, "![](photo.png)");
+        assert_eq!(
+            vault_import.attachments,
+            [
+                VaultAttachment {
+                    source: "Notes/photo.png".into(),
+                    file_name: "photo.png".into()
+                },
+                VaultAttachment {
+                    source: "assets/scan.pdf".into(),
+                    file_name: "scan.pdf".into()
+                },
+                VaultAttachment {
+                    source: "assets/Map Large.jpg".into(),
+                    file_name: "Map Large.jpg".into()
+                },
+            ]
+        );
+
+        assert_eq!(
+            vault_import.unresolved_links,
+            [UnresolvedLink {
+                note_title: "Trip".into(),
+                target: "missing.pdf".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn attachment_names_are_unique() {
+        let existing_file_names = HashSet::from(["photo.png".to_string()]);
+        let vault_import = convert_vault(
+            &[
+                note("A.md", "![[a/photo.png]] ![[b/photo.png]]"),
+                other("a/photo.png"),
+                other("b/photo.png"),
+            ],
+            &existing_file_names,
+            VaultImportOptions::default(),
+        );
+
+        assert_eq!(
+            vault_import.notes[0].content,
+            "![a/photo.png](photo-2.png) ![b/photo.png](photo-3.png)"
+        );
+    }
+
+    #[test]
+    fn links_in_code_kept() {
+        let vault_import = convert(
+            &[
+                note("Note.md", "```\n![[photo.png]]\n```"),
+                other("photo.png"),
+            ],
+            VaultImportOptions::default(),
+        );
+
+        assert_eq!(vault_import.notes[0].content, "```\n![[photo.png]]\n```");
+        assert!(vault_import.attachments.is_empty());
+    }
+
+    #[test]
+    fn percent_decode_escapes() {
+        assert_eq!(percent_decode("Map%20Large.jpg"), "Map Large.jpg");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%C3%A9"), "%zzé");
+    }
+
+    #[test]
+    fn read_vault_files() {
+        let root =
+            std::env::temp_dir().join(format!("noteworthy-vault-import-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".obsidian")).unwrap();
+        fs::create_dir_all(root.join("Folder")).unwrap();
+        fs::write(root.join(".obsidian/app.json"), "{}").unwrap();
+        fs::write(root.join(".hidden.md"), "Hidden").unwrap();
+        fs::write(root.join("Folder/Note.md"), "Text").unwrap();
+        fs::write(root.join("Folder/photo.png"), [0xff, 0xd8]).unwrap();
+        fs::write(root.join("Broken.md"), [0xff, 0xfe]).unwrap();
+
+        assert_eq!(
+            read_vault(&root).unwrap(),
+            [note("Folder/Note.md", "Text"), other("Folder/photo.png"),]
+        );
+    }
+}
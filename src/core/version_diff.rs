@@ -0,0 +1,99 @@
+use similar::{ChangeTag, TextDiff};
+
+use std::time::Duration;
+
+/// Past it, the diff is not the smallest possible, so very different versions of a large file
+/// don't take long to compare
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How a line differs between a past version of a file and its current version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Unchanged,
+    /// Only in the past version, as it was removed since
+    Removed,
+    /// Only in the current version, as it was added since
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub change: LineChange,
+    /// Without the line break
+    pub text: String,
+}
+
+/// The lines of both `version` and `current`, in order, telling which of the two has them
+pub fn diff_lines(version: &str, current: &str) -> Vec<DiffLine> {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(version, current);
+
+    diff.iter_all_changes()
+        .map(|change| {
+            let change_kind = match change.tag() {
+                ChangeTag::Equal => LineChange::Unchanged,
+                ChangeTag::Delete => LineChange::Removed,
+                ChangeTag::Insert => LineChange::Added,
+            };
+            let text = change.value();
+
+            DiffLine {
+                change: change_kind,
+                text: text.strip_suffix('\n').unwrap_or(text).to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line(change: LineChange, text: &str) -> DiffLine {
+        DiffLine {
+            change,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn added_and_removed_lines() {
+        let lines = diff_lines("Milk\nEggs\nBread\n", "Milk\nBread\nButter\n");
+
+        assert_eq!(
+            lines,
+            [
+                line(LineChange::Unchanged, "Milk"),
+                line(LineChange::Removed, "Eggs"),
+                line(LineChange::Unchanged, "Bread"),
+                line(LineChange::Added, "Butter"),
+            ]
+        );
+    }
+
+    #[test]
+    fn last_line_without_break() {
+        let lines = diff_lines("Milk", "Milk\nEggs");
+
+        assert_eq!(
+            lines,
+            [
+                line(LineChange::Removed, "Milk"),
+                line(LineChange::Added, "Milk"),
+                line(LineChange::Added, "Eggs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_versions() {
+        let lines = diff_lines("Milk\nEggs", "Milk\nEggs");
+        assert!(lines
+            .iter()
+            .all(|line| line.change == LineChange::Unchanged));
+        assert_eq!(lines.len(), 2);
+
+        assert!(diff_lines("", "").is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::is_code_fence;
+
+static RE_WIKI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[\[([^\[\]|\n]+)(\|[^\[\]\n]*)?\]\]").unwrap());
+
+/// Titles of the notes linked with `[[Title]]` or `[[Title|Label]]` in `text`, in the order
+/// they appear. What looks like a link in code is left out, as it is only shown as is.
+pub fn wiki_link_targets(text: &str) -> Vec<&str> {
+    text_parts(text)
+        .into_iter()
+        .filter(|(_, is_code)| !is_code)
+        .flat_map(|(part, _)| RE_WIKI_LINK.captures_iter(part))
+        .filter_map(|captures| captures.get(1))
+        .map(|target| target.as_str().trim())
+        .filter(|target| !target.is_empty())
+        .collect()
+}
+
+/// `text` with the links to `from_title` going to `to_title` instead, keeping their labels,
+/// and how many there were, or `None` when it has none. Titles are matched
+/// case-insensitively. Only the links are changed, not the title written in the text or in
+/// code.
+pub fn retarget_wiki_links(
+    text: &str,
+    from_title: &str,
+    to_title: &str,
+) -> Option<(String, usize)> {
+    let from_title = from_title.trim().to_lowercase();
+    let mut n_retargeted = 0;
+    let mut retargeted = String::with_capacity(text.len());
+
+    for (part, is_code) in text_parts(text) {
+        if is_code {
+            retargeted.push_str(part);
+            continue;
+        }
+
+        let part = RE_WIKI_LINK.replace_all(part, |captures: &Captures| {
+            if captures[1].trim().to_lowercase() != from_title {
+                return captures[0].to_string();
+            }
+
+            n_retargeted += 1;
+            let label = captures.get(2).map_or("", |label| label.as_str());
+            format!("[[{}{}]]", to_title, label)
+        });
+        retargeted.push_str(&part);
+    }
+
+    (n_retargeted > 0).then(|| (retargeted, n_retargeted))
+}
+
+/// `text` split in parts, with whether each is code, either a fenced code block or a code
+/// span like `` `code` ``. Put together, they are `text`.
+fn text_parts(text: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut is_in_code_block = false;
+
+    for line in text.split_inclusive('\n') {
+        if is_code_fence(line) {
+            is_in_code_block = !is_in_code_block;
+            parts.push((line, true));
+        } else if is_in_code_block {
+            parts.push((line, true));
+        } else {
+            push_line_parts(line, &mut parts);
+        }
+    }
+
+    parts
+}
+
+/// Push the parts of `line`, which is outside of code blocks, to `parts`
+fn push_line_parts<'a>(line: &'a str, parts: &mut Vec<(&'a str, bool)>) {
+    let mut text_start = 0;
+    let mut index = 0;
+
+    while let Some(offset) = line[index..].find('`') {
+        let span_start = index + offset;
+        let n_ticks = backtick_run_len(&line[span_start..]);
+        let code_start = span_start + n_ticks;
+
+        match closing_run(&line[code_start..], n_ticks) {
+            Some(code_len) => {
+                let span_end = code_start + code_len + n_ticks;
+                parts.push((&line[text_start..span_start], false));
+                parts.push((&line[span_start..span_end], true));
+                text_start = span_end;
+                index = span_end;
+            }
+            // Backticks that are never closed are text
+            None => index = code_start,
+        }
+    }
+
+    parts.push((&line[text_start..], false));
+}
+
+/// Where the first run of exactly `n_ticks` backticks in `text` starts, which closes a code
+/// span opened with as many
+fn closing_run(text: &str, n_ticks: usize) -> Option<usize> {
+    let mut index = 0;
+
+    while let Some(offset) = text[index..].find('`') {
+        let run_start = index + offset;
+        let run_len = backtick_run_len(&text[run_start..]);
+
+        if run_len == n_ticks {
+            return Some(run_start);
+        }
+
+        index = run_start + run_len;
+    }
+
+    None
+}
+
+fn backtick_run_len(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::{fixtures, snapshot_settings};
+
+    use std::fmt::Write;
+
+    #[test]
+    fn targets() {
+        assert_eq!(
+            wiki_link_targets("See [[Groceries]] and [[ Recipes ]]."),
+            vec!["Groceries", "Recipes"]
+        );
+    }
+
+    #[test]
+    fn targets_with_label() {
+        assert_eq!(
+            wiki_link_targets("[[Groceries|the list]] then [[Recipes|]]"),
+            vec!["Groceries", "Recipes"]
+        );
+    }
+
+    #[test]
+    fn targets_invalid() {
+        assert!(wiki_link_targets("[Groceries] [[]] [[ ]] [[Multi\nline]] [[|Label]]").is_empty());
+        assert!(wiki_link_targets("[[Unclosed").is_empty());
+    }
+
+    #[test]
+    fn targets_in_code() {
+        assert_eq!(
+            wiki_link_targets("`[[Code]]` [[Groceries]]\n```\n[[Block]]\n```\n``[[Code]]``"),
+            vec!["Groceries"]
+        );
+        assert_eq!(
+            wiki_link_targets("`` [[Groceries]] ` [[Recipes]]"),
+            vec!["Groceries", "Recipes"]
+        );
+    }
+
+    #[test]
+    fn retarget() {
+        assert_eq!(
+            retarget_wiki_links(
+                "[[groceries]], [[Groceries|the list]], [[Groceries Old]]",
+                " Groceries ",
+                "Shopping"
+            )
+            .unwrap(),
+            (
+                "[[Shopping]], [[Shopping|the list]], [[Groceries Old]]".to_string(),
+                2
+            )
+        );
+        assert_eq!(
+            retarget_wiki_links("[[Recipes]]", "Groceries", "Shopping"),
+            None
+        );
+    }
+
+    #[test]
+    fn retarget_only_links() {
+        assert_eq!(
+            retarget_wiki_links("Groceries `[[Groceries]]`", "Groceries", "Shopping"),
+            None
+        );
+    }
+
+    #[test]
+    fn retarget_fixtures() {
+        let mut snapshot = String::new();
+
+        for (name, text) in fixtures("links") {
+            let (retargeted, n_links) =
+                retarget_wiki_links(&text, "Rome", "Roma").unwrap_or((text, 0));
+            writeln!(
+                snapshot,
+                "=== {} ({} links) ===\n{}",
+                name, n_links, retargeted
+            )
+            .unwrap();
+        }
+
+        snapshot_settings().bind(|| insta::assert_snapshot!("links", snapshot));
+    }
+
+    #[test]
+    fn targets_nested_brackets() {
+        assert_eq!(wiki_link_targets("[[[Groceries]]]"), vec!["Groceries"]);
+    }
+}
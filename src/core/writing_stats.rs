@@ -0,0 +1,292 @@
+use chrono::{Duration, NaiveDate, TimeZone};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use super::review_period::day_in;
+
+/// Days of history kept, counted back from the last day words were written
+const MAX_HISTORY_DAYS: i64 = 365;
+
+/// Number of words in `text`, which are the runs between whitespace with at least one letter
+/// or digit, so the markers of lists and headings are not counted
+pub fn count_words(text: &str) -> u32 {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count() as u32
+}
+
+/// Counts the words added by an edit of the buffer, from the text of the lines it touches
+/// before and after it
+#[derive(Debug, Default)]
+pub struct EditWordCount {
+    words_before: Option<u32>,
+}
+
+impl EditWordCount {
+    /// Called before the edit, with the lines it will change
+    pub fn begin(&mut self, lines: &str) {
+        self.words_before = Some(count_words(lines));
+    }
+
+    /// Called after the edit, with the lines it changed, returning the words it added. An edit
+    /// that removes words adds none, so deleting doesn't take back what was written that day.
+    pub fn end(&mut self, lines: &str) -> u32 {
+        match self.words_before.take() {
+            Some(words_before) => count_words(lines).saturating_sub(words_before),
+            None => 0,
+        }
+    }
+}
+
+/// Words written on each day, on the wall clock of where they were written
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DailyWordCounts(BTreeMap<NaiveDate, u32>);
+
+impl DailyWordCounts {
+    /// Read the counts from the value of their section, falling back to none when it is not
+    /// valid, so bad counts do not fail loading the rest of the data file
+    pub fn deserialize_or_default<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        Ok(serde_yaml::from_value(value).unwrap_or_else(|err| {
+            log::warn!("Discarding invalid daily word counts: {:?}", err);
+            Self::default()
+        }))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Count `n_words` for the day of `time` in `tz`
+    pub fn add<Tz: TimeZone, Tz2: TimeZone>(
+        &mut self,
+        time: &chrono::DateTime<Tz>,
+        tz: &Tz2,
+        n_words: u32,
+    ) {
+        if n_words == 0 {
+            return;
+        }
+
+        let count = self.0.entry(day_in(time, tz)).or_default();
+        *count = count.saturating_add(n_words);
+        self.truncate();
+    }
+
+    pub fn on(&self, day: NaiveDate) -> u32 {
+        self.0.get(&day).copied().unwrap_or(0)
+    }
+
+    /// Words of each of the `n_days` days up to `today`, starting with the oldest
+    pub fn last_days(&self, today: NaiveDate, n_days: u32) -> Vec<u32> {
+        (0..i64::from(n_days))
+            .rev()
+            .map(|days_ago| self.on(today - Duration::days(days_ago)))
+            .collect()
+    }
+
+    /// Combine the counts of two devices. Of the days counted on both, the larger count is
+    /// kept, so syncing the same counts again doesn't add them up.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+
+        for (day, &count) in &other.0 {
+            let union_count = union.0.entry(*day).or_default();
+            *union_count = (*union_count).max(count);
+        }
+
+        union.truncate();
+        union
+    }
+
+    /// A `date,words` header, then a line for each day with words, oldest first
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("date,words\n");
+
+        for (day, count) in &self.0 {
+            writeln!(csv, "{},{}", day.format("%Y-%m-%d"), count).unwrap();
+        }
+
+        csv
+    }
+
+    /// Forget the days too long before the last one
+    fn truncate(&mut self) {
+        let last_day = match self.0.keys().next_back() {
+            Some(last_day) => *last_day,
+            None => return,
+        };
+
+        let first_kept = last_day - Duration::days(MAX_HISTORY_DAYS - 1);
+        self.0 = self.0.split_off(&first_kept);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    /// Words added by replacing `before` with `after` on the lines of an edit
+    fn words_added(before: &str, after: &str) -> u32 {
+        let mut edit = EditWordCount::default();
+        edit.begin(before);
+        edit.end(after)
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(count_words(""), 0);
+        assert_eq!(count_words("  \n\t"), 0);
+        assert_eq!(count_words("Hello world"), 2);
+        assert_eq!(count_words("# Title\n\n- one\n- two, three"), 4);
+        assert_eq!(count_words("well-known 2022 ---"), 2);
+        assert_eq!(count_words("héllo wörld"), 2);
+    }
+
+    #[test]
+    fn typing() {
+        // Only the first letter of a word adds it
+        assert_eq!(words_added("", "H"), 1);
+        assert_eq!(words_added("H", "He"), 0);
+        assert_eq!(words_added("Hello", "Hello "), 0);
+        assert_eq!(words_added("Hello ", "Hello w"), 1);
+
+        // Splitting a word makes one more
+        assert_eq!(words_added("Helloworld", "Hello world"), 1);
+
+        // Pasting
+        assert_eq!(words_added("Start ", "Start of a line\nand another"), 5);
+    }
+
+    #[test]
+    fn deleting_adds_none() {
+        assert_eq!(words_added("Hello world", "Hello "), 0);
+        assert_eq!(words_added("Hello world", ""), 0);
+        assert_eq!(words_added("Hello world", "Helloworld"), 0);
+
+        // Replacing a selection with fewer words
+        assert_eq!(words_added("one two three", "one four"), 0);
+    }
+
+    #[test]
+    fn edit_without_begin() {
+        let mut edit = EditWordCount::default();
+        assert_eq!(edit.end("Hello world"), 0);
+
+        edit.begin("Hello");
+        assert_eq!(edit.end("Hello world"), 1);
+        // Each end is for the last begin
+        assert_eq!(edit.end("Hello world again"), 0);
+    }
+
+    #[test]
+    fn add_on_local_day() {
+        let tz = FixedOffset::east(2 * 3600);
+        let mut counts = DailyWordCounts::default();
+
+        // 23:59:59 and 00:00:00 on the wall clock in `tz`
+        counts.add(&Utc.ymd(2022, 3, 1).and_hms(21, 59, 59), &tz, 3);
+        counts.add(&Utc.ymd(2022, 3, 1).and_hms(22, 0, 0), &tz, 5);
+        counts.add(&Utc.ymd(2022, 3, 2).and_hms(10, 0, 0), &tz, 7);
+
+        assert_eq!(counts.on(date(2022, 3, 1)), 3);
+        assert_eq!(counts.on(date(2022, 3, 2)), 12);
+        assert_eq!(counts.on(date(2022, 3, 3)), 0);
+
+        // The same instant is on another day west of UTC
+        counts.add(
+            &Utc.ymd(2022, 3, 1).and_hms(2, 0, 0),
+            &FixedOffset::west(5 * 3600),
+            1,
+        );
+        assert_eq!(counts.on(date(2022, 2, 28)), 1);
+    }
+
+    #[test]
+    fn add_nothing() {
+        let mut counts = DailyWordCounts::default();
+        counts.add(&Utc.ymd(2022, 3, 1).and_hms(12, 0, 0), &Utc, 0);
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn capped_history() {
+        let mut counts = DailyWordCounts::default();
+        let start = Utc.ymd(2021, 1, 1).and_hms(12, 0, 0);
+
+        for days in 0..400 {
+            counts.add(&(start + Duration::days(days)), &Utc, 1);
+        }
+
+        let last_day = date(2021, 1, 1) + Duration::days(399);
+        assert_eq!(counts.0.len(), 365);
+        assert_eq!(counts.on(last_day), 1);
+        assert_eq!(counts.on(last_day - Duration::days(364)), 1);
+        assert_eq!(counts.on(last_day - Duration::days(365)), 0);
+    }
+
+    #[test]
+    fn last_days() {
+        let mut counts = DailyWordCounts::default();
+        counts.add(&Utc.ymd(2022, 3, 1).and_hms(12, 0, 0), &Utc, 4);
+        counts.add(&Utc.ymd(2022, 3, 3).and_hms(12, 0, 0), &Utc, 2);
+
+        assert_eq!(counts.last_days(date(2022, 3, 3), 4), [0, 4, 0, 2]);
+        assert_eq!(counts.last_days(date(2022, 3, 4), 2), [2, 0]);
+        assert!(counts.last_days(date(2022, 3, 4), 0).is_empty());
+    }
+
+    #[test]
+    fn union() {
+        let mut a = DailyWordCounts::default();
+        a.add(&Utc.ymd(2022, 3, 1).and_hms(12, 0, 0), &Utc, 4);
+        a.add(&Utc.ymd(2022, 3, 2).and_hms(12, 0, 0), &Utc, 2);
+
+        let mut b = DailyWordCounts::default();
+        b.add(&Utc.ymd(2022, 3, 2).and_hms(12, 0, 0), &Utc, 5);
+        b.add(&Utc.ymd(2022, 3, 3).and_hms(12, 0, 0), &Utc, 1);
+
+        let union = a.union(&b);
+        assert_eq!(union.last_days(date(2022, 3, 3), 3), [4, 5, 1]);
+        assert_eq!(union, b.union(&a));
+
+        // Syncing again changes nothing
+        assert_eq!(union.union(&b), union);
+    }
+
+    #[test]
+    fn csv() {
+        let mut counts = DailyWordCounts::default();
+        assert_eq!(counts.to_csv(), "date,words\n");
+
+        counts.add(&Utc.ymd(2022, 3, 10).and_hms(12, 0, 0), &Utc, 420);
+        counts.add(&Utc.ymd(2022, 3, 9).and_hms(12, 0, 0), &Utc, 12);
+        assert_eq!(
+            counts.to_csv(),
+            "date,words\n2022-03-09,12\n2022-03-10,420\n"
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let mut counts = DailyWordCounts::default();
+        counts.add(&Utc.ymd(2022, 3, 10).and_hms(12, 0, 0), &Utc, 420);
+
+        let text = serde_yaml::to_string(&counts).unwrap();
+        assert!(text.contains("2022-03-10: 420"));
+        assert_eq!(
+            serde_yaml::from_str::<DailyWordCounts>(&text).unwrap(),
+            counts
+        );
+    }
+}
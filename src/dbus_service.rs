@@ -0,0 +1,418 @@
+//! The DBus interface of the app, for scripts and launchers to create, find and open notes
+
+use anyhow::Context;
+use gtk::{gio, glib, prelude::*};
+
+use std::fmt;
+
+use crate::Application;
+
+/// Name of the interface, which is exported at the object path of the app
+pub const INTERFACE_NAME: &str = "io.github.seadve.Noteworthy";
+
+const INTERFACE_RESOURCE: &str = "/io/github/seadve/Noteworthy/dbus-interface.xml";
+
+/// Longest the snippet of a found note is, in characters
+const SNIPPET_LENGTH: usize = 80;
+
+/// A note found by `SearchNotes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundNote {
+    pub id: String,
+    pub title: String,
+    pub snippet: String,
+    /// When the note was last edited, in RFC 3339
+    pub modified: String,
+}
+
+/// Why a method failed, which is returned as a DBus error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodError {
+    /// The notes are not loaded in the main window yet
+    NoSession,
+    Locked,
+    /// The notebook is only read on this device
+    ReadOnly,
+    NoteNotFound(String),
+    InvalidArgs(String),
+    UnknownMethod(String),
+}
+
+impl MethodError {
+    pub const fn dbus_name(&self) -> &'static str {
+        match self {
+            Self::NoSession => "io.github.seadve.Noteworthy.Error.NoSession",
+            Self::Locked => "io.github.seadve.Noteworthy.Error.Locked",
+            Self::ReadOnly => "io.github.seadve.Noteworthy.Error.ReadOnly",
+            Self::NoteNotFound(_) => "io.github.seadve.Noteworthy.Error.NoteNotFound",
+            Self::InvalidArgs(_) => "org.freedesktop.DBus.Error.InvalidArgs",
+            Self::UnknownMethod(_) => "org.freedesktop.DBus.Error.UnknownMethod",
+        }
+    }
+}
+
+impl fmt::Display for MethodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSession => f.write_str("The notes are not loaded"),
+            Self::Locked => f.write_str("The notes are locked"),
+            Self::ReadOnly => f.write_str("The notebook is read-only"),
+            Self::NoteNotFound(id) => write!(f, "No note with id `{}`", id),
+            Self::InvalidArgs(message) => f.write_str(message),
+            Self::UnknownMethod(name) => write!(f, "Unknown method `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for MethodError {}
+
+/// What the methods of the interface are run on, which is the session of the app
+pub trait NotesBackend {
+    /// Returns the id of the created note
+    fn create_note(&self, title: &str, body: &str, tags: &[String]) -> Result<String, MethodError>;
+
+    fn search_notes(&self, query: &str) -> Result<Vec<FoundNote>, MethodError>;
+
+    /// Present the window with the note selected
+    fn open_note(&self, id: &str) -> Result<(), MethodError>;
+}
+
+/// A call of a method of the interface, with its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodCall {
+    CreateNote {
+        title: String,
+        body: String,
+        tags: Vec<String>,
+    },
+    SearchNotes {
+        query: String,
+    },
+    OpenNote {
+        id: String,
+    },
+}
+
+impl MethodCall {
+    pub fn parse(method_name: &str, parameters: &glib::Variant) -> Result<Self, MethodError> {
+        let invalid_args = || {
+            MethodError::InvalidArgs(format!(
+                "Invalid arguments of type `{}` for `{}`",
+                parameters.type_(),
+                method_name
+            ))
+        };
+
+        match method_name {
+            "CreateNote" => {
+                let (title, body, tags) = parameters.get().ok_or_else(invalid_args)?;
+                Ok(Self::CreateNote { title, body, tags })
+            }
+            "SearchNotes" => {
+                let (query,) = parameters.get().ok_or_else(invalid_args)?;
+                Ok(Self::SearchNotes { query })
+            }
+            "OpenNote" => {
+                let (id,) = parameters.get().ok_or_else(invalid_args)?;
+                Ok(Self::OpenNote { id })
+            }
+            _ => Err(MethodError::UnknownMethod(method_name.to_string())),
+        }
+    }
+
+    /// Run the call on `backend`, returning the values it replies with
+    pub fn dispatch(self, backend: &impl NotesBackend) -> Result<glib::Variant, MethodError> {
+        match self {
+            Self::CreateNote { title, body, tags } => {
+                let id = backend.create_note(&title, &body, &tags)?;
+                Ok((id,).to_variant())
+            }
+            Self::SearchNotes { query } => {
+                let notes = backend
+                    .search_notes(&query)?
+                    .into_iter()
+                    .map(|note| (note.id, note.title, note.snippet, note.modified))
+                    .collect::<Vec<_>>();
+                Ok((notes,).to_variant())
+            }
+            Self::OpenNote { id } => {
+                backend.open_note(&id)?;
+                Ok(().to_variant())
+            }
+        }
+    }
+}
+
+/// Reply to the call of `method_name` with what `backend` returns, or with the DBus error of
+/// why it failed
+pub fn handle_method_call(
+    backend: &impl NotesBackend,
+    method_name: &str,
+    parameters: &glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    match MethodCall::parse(method_name, parameters).and_then(|call| call.dispatch(backend)) {
+        Ok(reply) => invocation.return_value(Some(&reply)),
+        Err(err) => {
+            log::warn!("Failed to handle DBus call to `{}`: {}", method_name, err);
+            invocation.return_dbus_error(err.dbus_name(), &err.to_string());
+        }
+    }
+}
+
+/// Export the interface on `connection` at `object_path`, with the methods run on the app in
+/// the main context
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> anyhow::Result<gio::RegistrationId> {
+    let xml = gio::resources_lookup_data(INTERFACE_RESOURCE, gio::ResourceLookupFlags::NONE)?;
+    let node_info = gio::DBusNodeInfo::for_xml(std::str::from_utf8(&xml)?)?;
+    let interface_info = node_info
+        .lookup_interface(INTERFACE_NAME)
+        .with_context(|| {
+            format!(
+                "No interface `{}` in `{}`",
+                INTERFACE_NAME, INTERFACE_RESOURCE
+            )
+        })?;
+
+    let registration_id = connection.register_object(
+        object_path,
+        &interface_info,
+        |_, _, _, _, method_name, parameters, invocation| {
+            // Called in the context the object is registered in, which is the main one
+            handle_method_call(
+                &Application::default(),
+                method_name,
+                &parameters,
+                invocation,
+            );
+        },
+        |_, _, _, _, _| unreachable!("The interface has no properties"),
+        |_, _, _, _, _, _| unreachable!("The interface has no properties"),
+    )?;
+
+    Ok(registration_id)
+}
+
+/// A line of `content` to show what a note found with `query` is about: the first one with
+/// `query` in it, or else the first one that is not blank
+pub fn snippet(content: &str, query: &str) -> String {
+    let query = query.trim().to_lowercase();
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let line = lines
+        .clone()
+        .find(|line| !query.is_empty() && line.to_lowercase().contains(&query))
+        .or_else(|| lines.next())
+        .unwrap_or_default();
+
+    if line.chars().count() <= SNIPPET_LENGTH {
+        return line.to_string();
+    }
+
+    let mut snippet = line.chars().take(SNIPPET_LENGTH - 1).collect::<String>();
+    snippet.truncate(snippet.trim_end().len());
+    snippet.push('…');
+    snippet
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    /// Records the calls, and fails them while `error` is set
+    #[derive(Debug, Default)]
+    struct FakeBackend {
+        calls: RefCell<Vec<MethodCall>>,
+        error: Option<MethodError>,
+    }
+
+    impl FakeBackend {
+        fn failing(error: MethodError) -> Self {
+            Self {
+                error: Some(error),
+                ..Self::default()
+            }
+        }
+
+        fn record(&self, call: MethodCall) -> Result<(), MethodError> {
+            self.calls.borrow_mut().push(call);
+
+            match self.error {
+                Some(ref error) => Err(error.clone()),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl NotesBackend for FakeBackend {
+        fn create_note(
+            &self,
+            title: &str,
+            body: &str,
+            tags: &[String],
+        ) -> Result<String, MethodError> {
+            self.record(MethodCall::CreateNote {
+                title: title.to_string(),
+                body: body.to_string(),
+                tags: tags.to_vec(),
+            })?;
+            Ok("new-note".to_string())
+        }
+
+        fn search_notes(&self, query: &str) -> Result<Vec<FoundNote>, MethodError> {
+            self.record(MethodCall::SearchNotes {
+                query: query.to_string(),
+            })?;
+            Ok(vec![FoundNote {
+                id: "groceries".to_string(),
+                title: "Groceries".to_string(),
+                snippet: "Eggs".to_string(),
+                modified: "2022-03-01T10:00:00+08:00".to_string(),
+            }])
+        }
+
+        fn open_note(&self, id: &str) -> Result<(), MethodError> {
+            self.record(MethodCall::OpenNote { id: id.to_string() })
+        }
+    }
+
+    fn call(
+        backend: &FakeBackend,
+        method_name: &str,
+        parameters: glib::Variant,
+    ) -> Result<glib::Variant, MethodError> {
+        MethodCall::parse(method_name, &parameters).and_then(|call| call.dispatch(backend))
+    }
+
+    #[test]
+    fn create_note() {
+        let backend = FakeBackend::default();
+        let parameters = ("Groceries", "Eggs", vec!["Home".to_string()]).to_variant();
+
+        let reply = call(&backend, "CreateNote", parameters).unwrap();
+        assert_eq!(reply.type_().as_str(), "(s)");
+        assert_eq!(reply.get::<(String,)>().unwrap().0, "new-note");
+        assert_eq!(
+            *backend.calls.borrow(),
+            [MethodCall::CreateNote {
+                title: "Groceries".to_string(),
+                body: "Eggs".to_string(),
+                tags: vec!["Home".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn search_notes() {
+        let backend = FakeBackend::default();
+
+        let reply = call(&backend, "SearchNotes", ("eggs",).to_variant()).unwrap();
+        assert_eq!(reply.type_().as_str(), "(a(ssss))");
+
+        let (notes,) = reply
+            .get::<(Vec<(String, String, String, String)>,)>()
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].0, "groceries");
+        assert_eq!(notes[0].3, "2022-03-01T10:00:00+08:00");
+    }
+
+    #[test]
+    fn open_note() {
+        let backend = FakeBackend::default();
+
+        let reply = call(&backend, "OpenNote", ("groceries",).to_variant()).unwrap();
+        assert_eq!(reply.type_().as_str(), "()");
+        assert_eq!(
+            *backend.calls.borrow(),
+            [MethodCall::OpenNote {
+                id: "groceries".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_args() {
+        let backend = FakeBackend::default();
+
+        let err = call(&backend, "CreateNote", ("Groceries",).to_variant()).unwrap_err();
+        assert!(matches!(err, MethodError::InvalidArgs(_)));
+        assert_eq!(err.dbus_name(), "org.freedesktop.DBus.Error.InvalidArgs");
+
+        let err = call(&backend, "OpenNote", (1_u32,).to_variant()).unwrap_err();
+        assert!(matches!(err, MethodError::InvalidArgs(_)));
+
+        assert!(backend.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn unknown_method() {
+        let backend = FakeBackend::default();
+
+        let err = call(&backend, "DeleteNote", ("groceries",).to_variant()).unwrap_err();
+        assert_eq!(err, MethodError::UnknownMethod("DeleteNote".to_string()));
+        assert!(backend.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn backend_errors() {
+        let backend = FakeBackend::failing(MethodError::NoSession);
+        let err = call(&backend, "SearchNotes", ("eggs",).to_variant()).unwrap_err();
+        assert_eq!(err, MethodError::NoSession);
+        assert_eq!(
+            err.dbus_name(),
+            "io.github.seadve.Noteworthy.Error.NoSession"
+        );
+
+        let backend = FakeBackend::failing(MethodError::NoteNotFound("gone".to_string()));
+        let err = call(&backend, "OpenNote", ("gone",).to_variant()).unwrap_err();
+        assert_eq!(err.to_string(), "No note with id `gone`");
+    }
+
+    #[test]
+    fn interface_has_methods() {
+        let node_info =
+            gio::DBusNodeInfo::for_xml(include_str!("../data/resources/dbus-interface.xml"))
+                .unwrap();
+        let interface_info = node_info.lookup_interface(INTERFACE_NAME).unwrap();
+
+        for method_name in ["CreateNote", "SearchNotes", "OpenNote"] {
+            assert!(interface_info.lookup_method(method_name).is_some());
+        }
+    }
+
+    #[test]
+    fn snippet_of_match() {
+        let content = "First line\n\n  Buy eggs and milk  \nMore eggs";
+        assert_eq!(snippet(content, "EGGS"), "Buy eggs and milk");
+        assert_eq!(snippet(content, "bread"), "First line");
+        assert_eq!(snippet(content, ""), "First line");
+        assert_eq!(snippet("\n \n", "eggs"), "");
+    }
+
+    #[test]
+    fn snippet_truncated() {
+        let content = "word ".repeat(40);
+        let snippet = snippet(&content, "");
+        assert_eq!(snippet.chars().count(), SNIPPET_LENGTH);
+        assert!(snippet.ends_with("word…"));
+    }
+
+    #[test]
+    fn snippet_with_emoji() {
+        assert_eq!(snippet("🎉 Party 🎉\nBring 🍕", "🍕"), "Bring 🍕");
+
+        let content = "👩‍👩‍👧".repeat(40);
+        let snippet = snippet(&content, "");
+        assert_eq!(snippet.chars().count(), SNIPPET_LENGTH);
+        assert!(snippet.ends_with('…'));
+    }
+}
@@ -0,0 +1,249 @@
+use adw::subclass::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::RefCell;
+
+use crate::{
+    config::{PROFILE, VERSION},
+    core::{diagnostics_report, format_latency, Journal},
+    utils::EditProfiler,
+    Application,
+};
+
+/// How often the latencies of the edit handlers are shown again, in seconds
+const LATENCY_REFRESH_INTERVAL_SECS: u32 = 1;
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/debug-window.ui")]
+    pub struct DebugWindow {
+        #[template_child]
+        pub latency_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub text_view: TemplateChild<gtk::TextView>,
+
+        pub journal: OnceCell<Journal>,
+        pub recorded_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub latency_source_id: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DebugWindow {
+        const NAME: &'static str = "NwtyDebugWindow";
+        type Type = super::DebugWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("debug-window.copy-diagnostics", None, move |obj, _, _| {
+                obj.clipboard().set_text(&obj.diagnostics());
+                log::info!("Copied diagnostics to the clipboard");
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for DebugWindow {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "journal",
+                    "Journal",
+                    "Journal whose events are shown",
+                    Journal::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "journal" => {
+                    let journal = value.get().unwrap();
+                    self.journal.set(journal).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "journal" => obj.journal().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.load_entries();
+            obj.setup_signals();
+            obj.update_latency_label();
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            if let Some(handler_id) = self.recorded_handler_id.take() {
+                obj.journal().disconnect(handler_id);
+            }
+
+            if let Some(source_id) = self.latency_source_id.take() {
+                source_id.remove();
+            }
+        }
+    }
+
+    impl WidgetImpl for DebugWindow {}
+    impl WindowImpl for DebugWindow {}
+    impl AdwWindowImpl for DebugWindow {}
+}
+
+glib::wrapper! {
+    /// Live view of the events of the [`Journal`], to help diagnosing sync and save issues
+    pub struct DebugWindow(ObjectSubclass<imp::DebugWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl DebugWindow {
+    pub fn new(journal: &Journal) -> Self {
+        glib::Object::new(&[("journal", journal)]).expect("Failed to create DebugWindow.")
+    }
+
+    fn journal(&self) -> &Journal {
+        self.imp().journal.get().unwrap()
+    }
+
+    /// Version, settings without the secret ones, and the recent events, for a bug report
+    fn diagnostics(&self) -> String {
+        let settings = Application::default().settings();
+        let settings_snapshot = settings
+            .settings_schema()
+            .map(|schema| {
+                schema
+                    .list_keys()
+                    .iter()
+                    .map(|key| {
+                        (
+                            key.to_string(),
+                            settings.value(key).print(false).to_string(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        diagnostics_report(
+            &format!("{} ({})", VERSION, PROFILE),
+            &settings_snapshot,
+            &self.journal().entries(),
+        )
+    }
+
+    fn load_entries(&self) {
+        let lines = self
+            .journal()
+            .entries()
+            .iter()
+            .map(|entry| entry.to_line() + "\n")
+            .collect::<String>();
+
+        self.imp().text_view.buffer().set_text(&lines);
+    }
+
+    fn append_last_entry(&self) {
+        let entry = match self.journal().last_entry() {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let imp = self.imp();
+
+        // Only follow new events when already at the end, so older ones can be read
+        let adjustment = imp.scrolled_window.vadjustment();
+        let is_at_end = adjustment.value() + adjustment.page_size() >= adjustment.upper();
+
+        let buffer = imp.text_view.buffer();
+        buffer.insert(&mut buffer.end_iter(), &(entry.to_line() + "\n"));
+
+        if is_at_end {
+            buffer.place_cursor(&buffer.end_iter());
+            imp.text_view
+                .scroll_to_mark(&buffer.get_insert(), 0.0, false, 0.0, 1.0);
+        }
+    }
+
+    /// Show the 50th and 95th percentiles of how long each edit handler took, while the
+    /// edits are profiled
+    fn update_latency_label(&self) {
+        let profiler = EditProfiler::default();
+        let imp = self.imp();
+        imp.latency_label.set_visible(profiler.is_enabled());
+
+        if !profiler.is_enabled() {
+            return;
+        }
+
+        let histograms = profiler.histograms();
+        let text = if histograms.is_empty() {
+            "No edits measured yet".to_string()
+        } else {
+            histograms
+                .iter()
+                .map(|(handler, histogram)| {
+                    format!(
+                        "{}: p50 {}, p95 {}, max {} over {} edits",
+                        handler,
+                        format_latency(histogram.percentile(0.5).unwrap_or_default()),
+                        format_latency(histogram.percentile(0.95).unwrap_or_default()),
+                        format_latency(histogram.max()),
+                        histogram.len()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        imp.latency_label.set_label(&text);
+    }
+
+    fn setup_signals(&self) {
+        let handler_id = self
+            .journal()
+            .connect_recorded(clone!(@weak self as obj => move |_| {
+                obj.append_last_entry();
+            }));
+        self.imp().recorded_handler_id.replace(Some(handler_id));
+
+        // Polled, as the edit handlers would get slower if each of them updated this
+        let source_id = glib::timeout_add_seconds_local(
+            LATENCY_REFRESH_INTERVAL_SECS,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                obj.update_latency_label();
+                glib::Continue(true)
+            }),
+        );
+        self.imp().latency_source_id.replace(Some(source_id));
+    }
+}
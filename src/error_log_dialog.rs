@@ -0,0 +1,207 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use crate::model::{ErrorEntry, ErrorLog};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/error-log-dialog.ui")]
+    pub struct ErrorLogDialog {
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub list_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+
+        pub error_log: OnceCell<ErrorLog>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ErrorLogDialog {
+        const NAME: &'static str = "NwtyErrorLogDialog";
+        type Type = super::ErrorLogDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("error-log-dialog.copy", None, move |obj, _, _| {
+                obj.clipboard().set_text(&obj.error_log().to_report_text());
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ErrorLogDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "error-log",
+                    "Error Log",
+                    "Errors shown in the dialog",
+                    ErrorLog::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "error-log" => {
+                    let error_log = value.get().unwrap();
+                    self.error_log.set(error_log).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "error-log" => obj.error_log().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_box();
+            obj.setup_signals();
+            obj.update_stack();
+        }
+    }
+
+    impl WidgetImpl for ErrorLogDialog {
+        fn map(&self, widget: &Self::Type) {
+            self.parent_map(widget);
+
+            widget.error_log().mark_seen();
+        }
+    }
+
+    impl WindowImpl for ErrorLogDialog {}
+    impl AdwWindowImpl for ErrorLogDialog {}
+}
+
+glib::wrapper! {
+    /// Lists the errors of the [`ErrorLog`] and marks them as seen once shown
+    pub struct ErrorLogDialog(ObjectSubclass<imp::ErrorLogDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl ErrorLogDialog {
+    pub fn new(error_log: &ErrorLog) -> Self {
+        glib::Object::new(&[("error-log", error_log)]).expect("Failed to create ErrorLogDialog.")
+    }
+
+    fn error_log(&self) -> ErrorLog {
+        self.imp().error_log.get().unwrap().clone()
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+        let has_errors = self.error_log().n_items() > 0;
+
+        if has_errors {
+            imp.stack.set_visible_child(&imp.list_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        }
+
+        self.action_set_enabled("error-log-dialog.copy", has_errors);
+    }
+
+    fn setup_signals(&self) {
+        self.error_log().connect_items_changed(
+            clone!(@weak self as obj => move |error_log, _, _, _| {
+                obj.update_stack();
+
+                // New errors are seen right away while the dialog is open
+                if obj.is_mapped() {
+                    error_log.mark_seen();
+                }
+            }),
+        );
+    }
+
+    fn setup_list_box(&self) {
+        self.imp()
+            .list_box
+            .bind_model(Some(&self.error_log()), |item| {
+                let entry = item.downcast_ref::<ErrorEntry>().unwrap();
+                entry_row(entry).upcast()
+            });
+    }
+}
+
+fn entry_row(entry: &ErrorEntry) -> gtk::ListBoxRow {
+    let message_label = gtk::Label::builder()
+        .label(&entry.message())
+        .xalign(0.0)
+        .wrap(true)
+        .wrap_mode(gtk::pango::WrapMode::WordChar)
+        .css_classes(vec!["heading".into()])
+        .build();
+
+    let info_label = gtk::Label::builder()
+        // Translators: The first is where the error is from, the second is when it happened
+        .label(&gettext!(
+            "{} · {}",
+            entry.source().display_name(),
+            entry.timestamp().format("%b %d, %H∶%M") // Sep 03, 08∶10
+        ))
+        .xalign(0.0)
+        .css_classes(vec!["caption".into(), "dim-label".into()])
+        .build();
+
+    let detail_label = gtk::Label::builder()
+        .label(&entry.detail())
+        .xalign(0.0)
+        .wrap(true)
+        .wrap_mode(gtk::pango::WrapMode::WordChar)
+        .selectable(true)
+        .css_classes(vec!["monospace".into(), "caption".into()])
+        .build();
+
+    let vbox = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    vbox.append(&message_label);
+    vbox.append(&info_label);
+    vbox.append(&detail_label);
+
+    gtk::ListBoxRow::builder()
+        .activatable(false)
+        .child(&vbox)
+        .build()
+}
@@ -18,11 +18,20 @@
 #![warn(clippy::doc_markdown)]
 
 mod application;
+mod command_line;
 mod config;
 mod core;
+mod dbus_service;
+mod debug_window;
+mod error_log_dialog;
 mod model;
+mod preferences_window;
+mod preparing_page;
+mod quick_capture;
 mod session;
 mod setup;
+#[cfg(test)]
+mod test_harness;
 mod utils;
 mod widgets;
 mod window;
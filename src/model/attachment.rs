@@ -1,20 +1,17 @@
 use gtk::{gio, glib, prelude::*, subclass::prelude::*};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{cell::RefCell, path::PathBuf};
+use std::cell::RefCell;
 
 use once_cell::unsync::OnceCell;
 
-use crate::core::{DateTime, FileType};
+use crate::core::{AttachmentData, DateTime, FileType};
 
 mod imp {
     use super::*;
     use once_cell::sync::Lazy;
 
-    #[derive(Debug, Serialize, Deserialize)]
-    #[serde(default)]
+    #[derive(Debug)]
     pub struct AttachmentInner {
-        #[serde(serialize_with = "serialize_file")]
-        #[serde(deserialize_with = "deserialize_file")]
         pub file: gio::File,
         pub created: DateTime,
         pub title: String,
@@ -150,6 +147,22 @@ impl Attachment {
         self.connect_notify_local(Some("title"), move |obj, _| f(obj))
     }
 
+    pub fn from_data(data: &AttachmentData) -> Self {
+        let attachment = Self::new(&gio::File::for_path(&data.file), &data.created);
+        attachment.set_title(&data.title);
+        attachment
+    }
+
+    pub fn to_data(&self) -> AttachmentData {
+        let inner = self.imp().inner.borrow();
+
+        AttachmentData {
+            file: inner.file.path().unwrap(),
+            created: inner.created,
+            title: inner.title.clone(),
+        }
+    }
+
     pub async fn delete(&self) {
         let file = self.file();
 
@@ -171,36 +184,13 @@ impl Default for Attachment {
 // It is helpful for caching the duration of an audio or save the peaks to show visualization later
 impl Serialize for Attachment {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.imp().inner.serialize(serializer)
+        self.to_data().serialize(serializer)
     }
 }
 
 impl<'de> Deserialize<'de> for Attachment {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let inner = imp::AttachmentInner::deserialize(deserializer)?;
-
-        let attachment = Self::new(&inner.file, &inner.created);
-        attachment.set_title(&inner.title);
-
-        Ok(attachment)
+        let data = AttachmentData::deserialize(deserializer)?;
+        Ok(Self::from_data(&data))
     }
 }
-
-pub fn serialize_file<S>(file: &gio::File, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    // TODO Use relative path instead of absolute path
-    // This is to lessen redundancy
-    // I think this should be handled somewhere in Note or AttachmentList
-    file.path().unwrap().serialize(s)
-}
-
-pub fn deserialize_file<'de, D>(deserializer: D) -> Result<gio::File, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let path = PathBuf::deserialize(deserializer)?;
-
-    Ok(gio::File::for_path(path))
-}
@@ -10,6 +10,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cell::RefCell;
 
 use super::Attachment;
+use crate::core::AttachmentData;
 
 mod imp {
     use super::*;
@@ -89,6 +90,19 @@ impl AttachmentList {
         self.imp().list.borrow().is_empty()
     }
 
+    pub fn from_data(data: &[AttachmentData]) -> Self {
+        data.iter().map(Attachment::from_data).collect()
+    }
+
+    pub fn to_data(&self) -> Vec<AttachmentData> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .map(Attachment::to_data)
+            .collect()
+    }
+
     fn get_index_of(&self, attachment: &Attachment) -> Option<usize> {
         self.imp().list.borrow().get_index_of(attachment)
     }
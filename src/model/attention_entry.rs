@@ -0,0 +1,49 @@
+use gtk::{glib, subclass::prelude::*};
+use once_cell::unsync::OnceCell;
+
+use super::Note;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct AttentionEntry {
+        pub note: OnceCell<Note>,
+        pub linked_title: OnceCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttentionEntry {
+        const NAME: &'static str = "NwtyAttentionEntry";
+        type Type = super::AttentionEntry;
+    }
+
+    impl ObjectImpl for AttentionEntry {}
+}
+
+glib::wrapper! {
+    /// A note in the [`AttentionList`](super::AttentionList)
+    pub struct AttentionEntry(ObjectSubclass<imp::AttentionEntry>);
+}
+
+impl AttentionEntry {
+    pub fn new(note: &Note, linked_title: &str) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create AttentionEntry.");
+        obj.imp().note.set(note.clone()).unwrap();
+        obj.imp()
+            .linked_title
+            .set(linked_title.to_string())
+            .unwrap();
+        obj
+    }
+
+    pub fn note(&self) -> Note {
+        self.imp().note.get().unwrap().clone()
+    }
+
+    /// The title the note links to, which was shared by several notes when one of them was
+    /// renamed, so which of them the links go to can't be told
+    pub fn linked_title(&self) -> String {
+        self.imp().linked_title.get().unwrap().clone()
+    }
+}
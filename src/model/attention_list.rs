@@ -0,0 +1,164 @@
+use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+
+use std::cell::RefCell;
+
+use super::{AttentionEntry, Note};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct AttentionList {
+        pub list: RefCell<Vec<AttentionEntry>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttentionList {
+        const NAME: &'static str = "NwtyAttentionList";
+        type Type = super::AttentionList;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for AttentionList {}
+
+    impl ListModelImpl for AttentionList {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            AttentionEntry::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.list.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.list
+                .borrow()
+                .get(position as usize)
+                .map(|entry| entry.upcast_ref::<glib::Object>())
+                .cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Notes whose links were not updated when the note they link to was renamed, as the
+    /// title they link to was shared by several notes. The user has to check where they
+    /// should go.
+    pub struct AttentionList(ObjectSubclass<imp::AttentionList>)
+        @implements gio::ListModel;
+}
+
+impl AttentionList {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create AttentionList.")
+    }
+
+    /// Add `note`, which links to `linked_title`, or replace its entry if it is already in
+    /// the list, so it stays where it is with the latest title
+    pub fn add(&self, note: &Note, linked_title: &str) {
+        let position = self.position(note);
+        let entry = AttentionEntry::new(note, linked_title);
+
+        match position {
+            Some(position) => {
+                self.imp().list.borrow_mut()[position] = entry;
+                self.items_changed(position as u32, 1, 1);
+            }
+            None => {
+                self.imp().list.borrow_mut().push(entry);
+                self.items_changed(self.n_items() - 1, 0, 1);
+            }
+        }
+    }
+
+    /// Remove the entry of `note`, like once its links are checked. Returns whether it had
+    /// one.
+    pub fn remove(&self, note: &Note) -> bool {
+        let position = match self.position(note) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        self.imp().list.borrow_mut().remove(position);
+        self.items_changed(position as u32, 1, 0);
+        true
+    }
+
+    /// Remove all the entries, like before the notes are loaded again
+    pub fn clear(&self) {
+        let n_removed = self.n_items();
+
+        if n_removed == 0 {
+            return;
+        }
+
+        self.imp().list.borrow_mut().clear();
+        self.items_changed(0, n_removed, 0);
+    }
+
+    fn position(&self, note: &Note) -> Option<usize> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .position(|entry| entry.note() == *note)
+    }
+}
+
+impl Default for AttentionList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::run_on_gtk_thread;
+
+    fn entry(attention_list: &AttentionList, position: u32) -> AttentionEntry {
+        attention_list.item(position).unwrap().downcast().unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn add() {
+        run_on_gtk_thread(|| {
+            let attention_list = AttentionList::new();
+            let note_1 = Note::new("/home/user");
+            let note_2 = Note::new("/home/user");
+
+            attention_list.add(&note_1, "Rome");
+            attention_list.add(&note_2, "Rome");
+            assert_eq!(attention_list.n_items(), 2);
+            assert_eq!(entry(&attention_list, 0).note(), note_1);
+            assert_eq!(entry(&attention_list, 1).note(), note_2);
+
+            // Its links to another shared title
+            attention_list.add(&note_1, "Paris");
+            assert_eq!(attention_list.n_items(), 2);
+            assert_eq!(entry(&attention_list, 0).note(), note_1);
+            assert_eq!(entry(&attention_list, 0).linked_title(), "Paris");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn remove() {
+        run_on_gtk_thread(|| {
+            let attention_list = AttentionList::new();
+            let note_1 = Note::new("/home/user");
+            let note_2 = Note::new("/home/user");
+            attention_list.add(&note_1, "Rome");
+            attention_list.add(&note_2, "Rome");
+
+            assert!(attention_list.remove(&note_1));
+            assert!(!attention_list.remove(&note_1));
+            assert_eq!(attention_list.n_items(), 1);
+            assert_eq!(entry(&attention_list, 0).note(), note_2);
+
+            attention_list.clear();
+            assert_eq!(attention_list.n_items(), 0);
+        });
+    }
+}
@@ -0,0 +1,112 @@
+use gettextrs::gettext;
+use gtk::{glib, subclass::prelude::*};
+use once_cell::unsync::OnceCell;
+
+/// Part of the app an error is from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "NwtyErrorSource")]
+pub enum ErrorSource {
+    /// Committing, pulling, pushing and watching the remote
+    Sync,
+    /// Loading and saving notes and the data file
+    Notes,
+}
+
+impl ErrorSource {
+    pub fn display_name(self) -> String {
+        match self {
+            Self::Sync => gettext("Sync"),
+            Self::Notes => gettext("Notes"),
+        }
+    }
+}
+
+/// Plain data of an error, so it can be sent from any thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub source: ErrorSource,
+    /// What failed, shown to the user
+    pub message: String,
+    /// Why it failed, like the chain of causes, which is mostly useful in bug reports
+    pub detail: String,
+}
+
+impl ErrorReport {
+    pub fn new(source: ErrorSource, message: &str, detail: &str) -> Self {
+        Self {
+            timestamp: chrono::Local::now(),
+            source,
+            message: message.to_string(),
+            detail: detail.to_string(),
+        }
+    }
+
+    /// Report of `err`, with its causes as the detail
+    pub fn from_error(source: ErrorSource, message: &str, err: &anyhow::Error) -> Self {
+        Self::new(source, message, &format!("{:?}", err))
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct ErrorEntry {
+        pub report: OnceCell<ErrorReport>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ErrorEntry {
+        const NAME: &'static str = "NwtyErrorEntry";
+        type Type = super::ErrorEntry;
+    }
+
+    impl ObjectImpl for ErrorEntry {}
+}
+
+glib::wrapper! {
+    /// An error in the [`ErrorLog`](super::ErrorLog)
+    pub struct ErrorEntry(ObjectSubclass<imp::ErrorEntry>);
+}
+
+impl ErrorEntry {
+    pub fn new(report: ErrorReport) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create ErrorEntry.");
+        obj.imp().report.set(report).unwrap();
+        obj
+    }
+
+    pub fn timestamp(&self) -> chrono::DateTime<chrono::Local> {
+        self.report().timestamp
+    }
+
+    pub fn source(&self) -> ErrorSource {
+        self.report().source
+    }
+
+    pub fn message(&self) -> String {
+        self.report().message.clone()
+    }
+
+    pub fn detail(&self) -> String {
+        self.report().detail.clone()
+    }
+
+    /// The entry as text to paste in a bug report
+    pub fn to_report_text(&self) -> String {
+        let report = self.report();
+
+        format!(
+            "[{}] {:?}: {}\n{}",
+            report.timestamp.to_rfc3339(),
+            report.source,
+            report.message,
+            report.detail
+        )
+    }
+
+    fn report(&self) -> &ErrorReport {
+        self.imp().report.get().unwrap()
+    }
+}
@@ -0,0 +1,277 @@
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+};
+
+use super::{ErrorEntry, ErrorReport};
+
+/// How many entries are kept, the oldest are removed first
+const MAX_ENTRIES: usize = 100;
+
+/// Sends errors to an [`ErrorLog`] from any thread
+#[derive(Debug, Clone)]
+pub struct ErrorSender(glib::Sender<ErrorReport>);
+
+impl ErrorSender {
+    pub fn send(&self, report: ErrorReport) {
+        if let Err(err) = self.0.send(report) {
+            // The log is gone, so this is the only place the error is still seen
+            log::error!("Failed to send error to the log: {:?}", err.0);
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct ErrorLog {
+        pub list: RefCell<VecDeque<ErrorEntry>>,
+        pub n_unseen: Cell<usize>,
+        pub sender: OnceCell<ErrorSender>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ErrorLog {
+        const NAME: &'static str = "NwtyErrorLog";
+        type Type = super::ErrorLog;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for ErrorLog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecBoolean::new(
+                    "has-unseen",
+                    "Has Unseen",
+                    "Whether there are errors that were not seen yet",
+                    false,
+                    glib::ParamFlags::READABLE,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "has-unseen" => obj.has_unseen().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+
+    impl ListModelImpl for ErrorLog {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            ErrorEntry::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.list.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.list
+                .borrow()
+                .get(position as usize)
+                .map(|entry| entry.upcast_ref::<glib::Object>())
+                .cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Errors the user should know about, like failed syncs and saves, newest last
+    pub struct ErrorLog(ObjectSubclass<imp::ErrorLog>)
+        @implements gio::ListModel;
+}
+
+impl ErrorLog {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ErrorLog.")
+    }
+
+    /// Sender to push errors from other threads. They are added to the log in the default main
+    /// context, so this must be first called on the main thread.
+    pub fn sender(&self) -> ErrorSender {
+        self.imp()
+            .sender
+            .get_or_init(|| {
+                let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+                receiver.attach(
+                    None,
+                    clone!(@weak self as obj => @default-return Continue(false), move |report| {
+                        obj.push(report);
+                        Continue(true)
+                    }),
+                );
+
+                ErrorSender(sender)
+            })
+            .clone()
+    }
+
+    /// Add an error to the log, which is also logged. This must be called on the main thread,
+    /// use [`ErrorLog::sender`] on others.
+    pub fn push(&self, report: ErrorReport) {
+        log::error!("{:?}: {}: {}", report.source, report.message, report.detail);
+
+        let imp = self.imp();
+
+        let n_removed = {
+            let mut list = imp.list.borrow_mut();
+            let n_removed = (list.len() + 1).saturating_sub(MAX_ENTRIES);
+            list.drain(..n_removed);
+            n_removed
+        };
+
+        if n_removed > 0 {
+            self.items_changed(0, n_removed as u32, 0);
+        }
+
+        imp.list.borrow_mut().push_back(ErrorEntry::new(report));
+        self.items_changed(self.n_items() - 1, 0, 1);
+
+        imp.n_unseen
+            .set((imp.n_unseen.get() + 1).min(self.n_items() as usize));
+        self.notify("has-unseen");
+    }
+
+    /// Whether errors were pushed since [`ErrorLog::mark_seen`] was last called
+    pub fn has_unseen(&self) -> bool {
+        self.imp().n_unseen.get() > 0
+    }
+
+    pub fn n_unseen(&self) -> usize {
+        self.imp().n_unseen.get()
+    }
+
+    /// Call once the errors are shown to the user
+    pub fn mark_seen(&self) {
+        if !self.has_unseen() {
+            return;
+        }
+
+        self.imp().n_unseen.set(0);
+        self.notify("has-unseen");
+    }
+
+    pub fn connect_has_unseen_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("has-unseen"), move |obj, _| f(obj))
+    }
+
+    /// All the entries as text to paste in a bug report, oldest first
+    pub fn to_report_text(&self) -> String {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .map(ErrorEntry::to_report_text)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::ErrorSource;
+
+    fn report(message: &str) -> ErrorReport {
+        ErrorReport::new(ErrorSource::Sync, message, "Detail")
+    }
+
+    #[test]
+    fn push() {
+        let error_log = ErrorLog::new();
+        assert_eq!(error_log.n_items(), 0);
+        assert!(!error_log.has_unseen());
+
+        error_log.push(report("A"));
+        error_log.push(report("B"));
+        assert_eq!(error_log.n_items(), 2);
+        assert_eq!(error_log.n_unseen(), 2);
+
+        let last = error_log.item(1).unwrap().downcast::<ErrorEntry>().unwrap();
+        assert_eq!(last.message(), "B");
+        assert_eq!(last.source(), ErrorSource::Sync);
+        assert_eq!(last.detail(), "Detail");
+    }
+
+    #[test]
+    fn mark_seen_clears_unseen() {
+        let error_log = ErrorLog::new();
+        error_log.push(report("A"));
+        assert!(error_log.has_unseen());
+
+        error_log.mark_seen();
+        assert!(!error_log.has_unseen());
+        assert_eq!(error_log.n_items(), 1);
+
+        error_log.push(report("B"));
+        assert!(error_log.has_unseen());
+        assert_eq!(error_log.n_unseen(), 1);
+    }
+
+    #[test]
+    fn max_entries() {
+        let error_log = ErrorLog::new();
+
+        for i in 0..MAX_ENTRIES + 5 {
+            error_log.push(report(&i.to_string()));
+        }
+
+        assert_eq!(error_log.n_items() as usize, MAX_ENTRIES);
+        assert_eq!(error_log.n_unseen(), MAX_ENTRIES);
+        let first = error_log.item(0).unwrap().downcast::<ErrorEntry>().unwrap();
+        assert_eq!(first.message(), "5");
+    }
+
+    #[test]
+    fn send_from_other_thread() {
+        let error_log = ErrorLog::new();
+        let sender = error_log.sender();
+
+        std::thread::spawn(move || sender.send(report("From thread")))
+            .join()
+            .unwrap();
+
+        let ctx = glib::MainContext::default();
+        while ctx.pending() {
+            ctx.iteration(false);
+        }
+
+        assert_eq!(error_log.n_items(), 1);
+        assert!(error_log.has_unseen());
+    }
+
+    #[test]
+    fn report_text() {
+        let error_log = ErrorLog::new();
+        error_log.push(report("A"));
+        error_log.push(report("B"));
+
+        let text = error_log.to_report_text();
+        assert!(text.contains("Sync: A\nDetail"));
+        assert!(text.contains("\n\n"));
+        assert!(text.find("A\n").unwrap() < text.find("B\n").unwrap());
+    }
+}
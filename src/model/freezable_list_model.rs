@@ -0,0 +1,359 @@
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct FreezableListModel {
+        pub model: OnceCell<gio::ListModel>,
+        /// The items as they are shown, which is only behind the model while frozen
+        pub items: RefCell<Vec<glib::Object>>,
+        pub is_frozen: Cell<bool>,
+        /// Whether the model was reordered while frozen
+        pub has_pending: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FreezableListModel {
+        const NAME: &'static str = "NwtyFreezableListModel";
+        type Type = super::FreezableListModel;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for FreezableListModel {}
+
+    impl ListModelImpl for FreezableListModel {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            glib::Object::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.items.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.items.borrow().get(position as usize).cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// The items of a model, in an order that can be frozen so they don't move while the
+    /// user works on one of them. Items that are added or removed are still shown right away.
+    pub struct FreezableListModel(ObjectSubclass<imp::FreezableListModel>)
+        @implements gio::ListModel;
+}
+
+impl FreezableListModel {
+    pub fn new(model: &impl IsA<gio::ListModel>) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create FreezableListModel.");
+        let imp = obj.imp();
+
+        let model = model.upcast_ref::<gio::ListModel>();
+        imp.items.replace(items(model));
+
+        model.connect_items_changed(clone!(@weak obj => move |model, position, removed, added| {
+            obj.handle_items_changed(model, position, removed, added);
+        }));
+
+        imp.model.set(model.clone()).unwrap();
+
+        obj
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.imp().is_frozen.get()
+    }
+
+    /// Keep the order of the items until unfrozen, when they are moved to where the model has
+    /// them
+    pub fn set_is_frozen(&self, is_frozen: bool) {
+        self.imp().is_frozen.set(is_frozen);
+
+        if !is_frozen {
+            self.flush();
+        }
+    }
+
+    /// Move the items to where the model has them, even while frozen
+    pub fn flush(&self) {
+        let imp = self.imp();
+
+        if !imp.has_pending.get() {
+            return;
+        }
+
+        let model = imp.model.get().unwrap();
+        self.sync_with(model);
+    }
+
+    fn handle_items_changed(
+        &self,
+        model: &gio::ListModel,
+        position: u32,
+        removed: u32,
+        added: u32,
+    ) {
+        let imp = self.imp();
+
+        if !imp.is_frozen.get() {
+            let added_items = (position..position + added)
+                .filter_map(|index| model.item(index))
+                .collect::<Vec<_>>();
+            imp.items.borrow_mut().splice(
+                position as usize..(position + removed) as usize,
+                added_items,
+            );
+            self.items_changed(position, removed, added);
+            return;
+        }
+
+        if removed == added && self.has_items(model, position, added) {
+            // Only reordered, so it can wait
+            imp.has_pending.set(true);
+        } else {
+            self.sync_with(model);
+        }
+    }
+
+    /// Whether the `n_items` from `position` of the `model` are already shown. Each item is in
+    /// the model only once, so if as many are removed as they are added, they are the same items.
+    fn has_items(&self, model: &gio::ListModel, position: u32, n_items: u32) -> bool {
+        let items = self.imp().items.borrow();
+        let shown = items.iter().collect::<HashSet<_>>();
+
+        (position..position + n_items)
+            .filter_map(|index| model.item(index))
+            .all(|item| shown.contains(&item))
+    }
+
+    fn sync_with(&self, model: &gio::ListModel) {
+        let imp = self.imp();
+        imp.has_pending.set(false);
+
+        let new_items = items(model);
+        let change = changed_range(&imp.items.borrow(), &new_items);
+        imp.items.replace(new_items);
+
+        if let Some((position, removed, added)) = change {
+            self.items_changed(position, removed, added);
+        }
+    }
+}
+
+fn items(model: &gio::ListModel) -> Vec<glib::Object> {
+    (0..model.n_items())
+        .filter_map(|index| model.item(index))
+        .collect()
+}
+
+/// The position, number of removed and number of added items that turn `old` into `new`,
+/// leaving out the items at their start and end that are the same
+fn changed_range<T: PartialEq>(old: &[T], new: &[T]) -> Option<(u32, u32, u32)> {
+    let n_prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let n_suffix = old[n_prefix..]
+        .iter()
+        .rev()
+        .zip(new[n_prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let removed = old.len() - n_prefix - n_suffix;
+    let added = new.len() - n_prefix - n_suffix;
+
+    if removed == 0 && added == 0 {
+        return None;
+    }
+
+    Some((n_prefix as u32, removed as u32, added as u32))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{collections::HashMap, rc::Rc};
+
+    /// A copy of the items of `model` that is only updated through its items-changed signal,
+    /// so it is different if a change was not told
+    fn mirror(model: &FreezableListModel) -> Rc<RefCell<Vec<glib::Object>>> {
+        let mirror = Rc::new(RefCell::new(items(model.upcast_ref())));
+        model.connect_items_changed(
+            clone!(@strong mirror => move |model, position, removed, added| {
+                let added_items = (position..position + added)
+                    .map(|index| model.item(index).unwrap())
+                    .collect::<Vec<_>>();
+                mirror.borrow_mut().splice(
+                    position as usize..(position + removed) as usize,
+                    added_items,
+                );
+            }),
+        );
+        mirror
+    }
+
+    fn strings(items: &[glib::Object]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| {
+                item.downcast_ref::<gtk::StringObject>()
+                    .unwrap()
+                    .string()
+                    .into()
+            })
+            .collect()
+    }
+
+    /// A store of strings sorted by their rank, which can be changed like the last modified
+    /// time of a note
+    struct RankedStore {
+        store: gio::ListStore,
+        ranks: Rc<RefCell<HashMap<String, i32>>>,
+        sort_model: gtk::SortListModel,
+    }
+
+    impl RankedStore {
+        fn new(ranked: &[(&str, i32)]) -> Self {
+            let store = gio::ListStore::new(gtk::StringObject::static_type());
+            let ranks = Rc::new(RefCell::new(HashMap::new()));
+
+            for (string, rank) in ranked {
+                store.append(&gtk::StringObject::new(string));
+                ranks.borrow_mut().insert(string.to_string(), *rank);
+            }
+
+            let sorter = gtk::CustomSorter::new(clone!(@strong ranks => move |a, b| {
+                let ranks = ranks.borrow();
+                let rank = |obj: &glib::Object| {
+                    ranks[&obj.downcast_ref::<gtk::StringObject>().unwrap().string().to_string()]
+                };
+                rank(a).cmp(&rank(b)).into()
+            }));
+            let sort_model = gtk::SortListModel::new(Some(&store), Some(&sorter));
+
+            Self {
+                store,
+                ranks,
+                sort_model,
+            }
+        }
+
+        /// Change the rank of `string`, telling the sort model like the note list does
+        fn set_rank(&self, string: &str, rank: i32) {
+            self.ranks.borrow_mut().insert(string.to_string(), rank);
+
+            let position = (0..self.store.n_items())
+                .find(|index| {
+                    let item = self.store.item(*index).unwrap();
+                    item.downcast_ref::<gtk::StringObject>()
+                        .unwrap()
+                        .string()
+                        .as_str()
+                        == string
+                })
+                .unwrap();
+            let item = self.store.item(position).unwrap();
+            self.store.splice(position, 1, &[item]);
+        }
+
+        fn sorted(&self) -> Vec<String> {
+            strings(&items(self.sort_model.upcast_ref()))
+        }
+    }
+
+    #[test]
+    fn changed_range_of_items() {
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(changed_range(&[1, 2, 3], &[3, 1, 2]), Some((0, 3, 3)));
+        assert_eq!(changed_range(&[1, 2, 3, 4], &[1, 3, 2, 4]), Some((1, 2, 2)));
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 3]), Some((1, 1, 0)));
+        assert_eq!(changed_range(&[1, 3], &[1, 2, 3]), Some((1, 0, 1)));
+        assert_eq!(changed_range(&[1, 1, 1], &[1, 1]), Some((2, 1, 0)));
+        assert_eq!(changed_range::<i32>(&[], &[1]), Some((0, 0, 1)));
+    }
+
+    #[test]
+    fn follows_model_while_not_frozen() {
+        let store = RankedStore::new(&[("a", 1), ("b", 2), ("c", 3)]);
+        let model = FreezableListModel::new(&store.sort_model);
+        let mirror = mirror(&model);
+
+        store.set_rank("c", 0);
+        assert_eq!(strings(&mirror.borrow()), ["c", "a", "b"]);
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+    }
+
+    #[test]
+    fn keeps_order_while_frozen() {
+        let store = RankedStore::new(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+        let model = FreezableListModel::new(&store.sort_model);
+        let mirror = mirror(&model);
+
+        model.set_is_frozen(true);
+        store.set_rank("c", 0);
+        store.set_rank("d", -1);
+        store.set_rank("a", 5);
+        assert_eq!(strings(&mirror.borrow()), ["a", "b", "c", "d"]);
+        assert_eq!(strings(&items(model.upcast_ref())), ["a", "b", "c", "d"]);
+
+        model.set_is_frozen(false);
+        assert_eq!(strings(&mirror.borrow()), ["d", "c", "b", "a"]);
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+    }
+
+    #[test]
+    fn flush_while_frozen() {
+        let store = RankedStore::new(&[("a", 1), ("b", 2), ("c", 3)]);
+        let model = FreezableListModel::new(&store.sort_model);
+        let mirror = mirror(&model);
+
+        model.set_is_frozen(true);
+        store.set_rank("b", 0);
+        model.flush();
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+        assert!(model.is_frozen());
+
+        // Still frozen for the next changes
+        store.set_rank("c", -1);
+        assert_eq!(strings(&mirror.borrow()), ["b", "a", "c"]);
+
+        model.set_is_frozen(false);
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+    }
+
+    #[test]
+    fn added_and_removed_while_frozen() {
+        let store = RankedStore::new(&[("a", 1), ("b", 2), ("c", 3)]);
+        let model = FreezableListModel::new(&store.sort_model);
+        let mirror = mirror(&model);
+
+        model.set_is_frozen(true);
+        store.set_rank("c", 0);
+
+        // Shown right away, with the changes held until then
+        store.ranks.borrow_mut().insert("e".into(), 10);
+        store.store.append(&gtk::StringObject::new("e"));
+        assert_eq!(strings(&mirror.borrow()), ["c", "a", "b", "e"]);
+
+        store.set_rank("e", -1);
+        assert_eq!(strings(&mirror.borrow()), ["c", "a", "b", "e"]);
+
+        store.store.remove(0);
+        assert_eq!(strings(&mirror.borrow()), ["e", "c", "b"]);
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+
+        model.set_is_frozen(false);
+        assert_eq!(strings(&mirror.borrow()), store.sorted());
+    }
+}
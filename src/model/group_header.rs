@@ -0,0 +1,71 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use once_cell::unsync::OnceCell;
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct GroupHeader {
+        pub title: OnceCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GroupHeader {
+        const NAME: &'static str = "NwtyGroupHeader";
+        type Type = super::GroupHeader;
+    }
+
+    impl ObjectImpl for GroupHeader {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecString::new(
+                    "title",
+                    "Title",
+                    "Title of the group",
+                    None,
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "title" => {
+                    let title = value.get().unwrap();
+                    self.title.set(title).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "title" => obj.title().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Header put before each group of items in a [`GroupedListModel`](super::GroupedListModel)
+    pub struct GroupHeader(ObjectSubclass<imp::GroupHeader>);
+}
+
+impl GroupHeader {
+    pub fn new(title: &str) -> Self {
+        glib::Object::new(&[("title", &title)]).expect("Failed to create GroupHeader.")
+    }
+
+    pub fn title(&self) -> String {
+        self.imp().title.get().unwrap().clone()
+    }
+}
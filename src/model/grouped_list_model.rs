@@ -0,0 +1,290 @@
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{cell::RefCell, collections::HashMap};
+
+use super::GroupHeader;
+
+type GroupFunc = Box<dyn Fn(&glib::Object) -> String>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct GroupedListModel {
+        pub model: OnceCell<gio::ListModel>,
+        pub group_func: RefCell<Option<GroupFunc>>,
+        /// Items of the model with the headers in between
+        pub items: RefCell<Vec<glib::Object>>,
+    }
+
+    impl std::fmt::Debug for GroupedListModel {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("GroupedListModel")
+                .field("model", &self.model)
+                .field("is_grouped", &self.group_func.borrow().is_some())
+                .field("n_items", &self.items.borrow().len())
+                .finish()
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GroupedListModel {
+        const NAME: &'static str = "NwtyGroupedListModel";
+        type Type = super::GroupedListModel;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for GroupedListModel {}
+
+    impl ListModelImpl for GroupedListModel {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            glib::Object::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.items.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.items.borrow().get(position as usize).cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Items of a sorted model with a [`GroupHeader`] before each group of them
+    pub struct GroupedListModel(ObjectSubclass<imp::GroupedListModel>)
+        @implements gio::ListModel;
+}
+
+impl GroupedListModel {
+    pub fn new(model: &impl IsA<gio::ListModel>) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create GroupedListModel.");
+
+        let model = model.upcast_ref::<gio::ListModel>();
+        model.connect_items_changed(clone!(@weak obj => move |_, position, removed, added| {
+            obj.handle_items_changed(position, removed, added);
+        }));
+        obj.imp().model.set(model.clone()).unwrap();
+        obj.handle_items_changed(0, 0, model.n_items());
+
+        obj
+    }
+
+    /// Put a header before each run of items that `group_func` gives the same title. The
+    /// model has to be sorted so that the items of a group are next to each other.
+    pub fn set_group_func(&self, group_func: impl Fn(&glib::Object) -> String + 'static) {
+        self.imp().group_func.replace(Some(Box::new(group_func)));
+        self.regroup();
+    }
+
+    /// Show the items without headers
+    pub fn unset_group_func(&self) {
+        if self.imp().group_func.replace(None).is_some() {
+            self.regroup();
+        }
+    }
+
+    fn model(&self) -> &gio::ListModel {
+        self.imp().model.get().unwrap()
+    }
+
+    fn handle_items_changed(&self, position: u32, removed: u32, added: u32) {
+        let imp = self.imp();
+
+        if imp.group_func.borrow().is_some() {
+            // A change in one group can merge or split the groups around it
+            self.regroup();
+            return;
+        }
+
+        let model = self.model();
+        let start = position as usize;
+        imp.items.borrow_mut().splice(
+            start..start + removed as usize,
+            (position..position + added).map(|position| model.item(position).unwrap()),
+        );
+
+        self.items_changed(position, removed, added);
+    }
+
+    /// Rebuild the items from the model, but only emit the range that changed
+    fn regroup(&self) {
+        let imp = self.imp();
+        let model = self.model();
+
+        let old_items = imp.items.borrow().clone();
+
+        // Reuse the headers, so the rows of the groups that did not change are kept
+        let mut old_headers: HashMap<String, GroupHeader> = old_items
+            .iter()
+            .filter_map(|item| item.downcast_ref::<GroupHeader>())
+            .map(|header| (header.title(), header.clone()))
+            .collect();
+
+        let mut new_items = Vec::with_capacity(old_items.len());
+
+        {
+            let group_func = imp.group_func.borrow();
+            let mut last_title = None;
+
+            for position in 0..model.n_items() {
+                let item = model.item(position).unwrap();
+
+                if let Some(ref group_func) = *group_func {
+                    let title = group_func(&item);
+
+                    if last_title.as_ref() != Some(&title) {
+                        let header = old_headers
+                            .remove(&title)
+                            .unwrap_or_else(|| GroupHeader::new(&title));
+                        new_items.push(header.upcast());
+                        last_title = Some(title);
+                    }
+                }
+
+                new_items.push(item);
+            }
+        }
+
+        let (position, removed, added) = changed_range(&old_items, &new_items);
+        imp.items.replace(new_items);
+
+        if removed > 0 || added > 0 {
+            self.items_changed(position, removed, added);
+        }
+    }
+}
+
+/// Smallest range of `old` that has to be replaced to get `new`, as the position, the number
+/// of removed items, and the number of added items
+fn changed_range<T: PartialEq>(old: &[T], new: &[T]) -> (u32, u32, u32) {
+    let n_prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let n_suffix = old[n_prefix..]
+        .iter()
+        .rev()
+        .zip(new[n_prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    (
+        n_prefix as u32,
+        (old.len() - n_prefix - n_suffix) as u32,
+        (new.len() - n_prefix - n_suffix) as u32,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{cell::Cell, rc::Rc};
+
+    /// Titles of the headers and strings of the other items
+    fn titles(model: &GroupedListModel) -> Vec<String> {
+        (0..model.n_items())
+            .map(|position| {
+                let item = model.item(position).unwrap();
+                if let Some(header) = item.downcast_ref::<GroupHeader>() {
+                    format!("# {}", header.title())
+                } else {
+                    item.downcast::<gtk::StringObject>()
+                        .unwrap()
+                        .string()
+                        .into()
+                }
+            })
+            .collect()
+    }
+
+    fn group_by_first_char(item: &glib::Object) -> String {
+        let string = item.downcast_ref::<gtk::StringObject>().unwrap().string();
+        string.chars().next().unwrap().to_string()
+    }
+
+    #[test]
+    fn changed_range_of_slices() {
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2, 3]), (3, 0, 0));
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 4, 3]), (1, 1, 1));
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2, 4, 3]), (2, 0, 1));
+        assert_eq!(changed_range(&[1, 2, 3], &[3]), (0, 2, 0));
+        assert_eq!(changed_range(&[1, 1], &[1, 1, 1]), (2, 0, 1));
+        assert_eq!(changed_range::<i32>(&[], &[]), (0, 0, 0));
+    }
+
+    #[test]
+    fn without_group_func() {
+        let list = gtk::StringList::new(&["a1", "b1"]);
+        let model = GroupedListModel::new(&list);
+        assert_eq!(titles(&model), vec!["a1", "b1"]);
+
+        list.append("b2");
+        list.remove(0);
+        assert_eq!(titles(&model), vec!["b1", "b2"]);
+    }
+
+    #[test]
+    fn group() {
+        let list = gtk::StringList::new(&["a1", "a2", "b1"]);
+        let model = GroupedListModel::new(&list);
+
+        model.set_group_func(group_by_first_char);
+        assert_eq!(titles(&model), vec!["# a", "a1", "a2", "# b", "b1"]);
+
+        model.unset_group_func();
+        assert_eq!(titles(&model), vec!["a1", "a2", "b1"]);
+    }
+
+    #[test]
+    fn regroup_on_items_changed() {
+        let list = gtk::StringList::new(&["a1", "b1"]);
+        let model = GroupedListModel::new(&list);
+        model.set_group_func(group_by_first_char);
+
+        list.append("c1");
+        assert_eq!(titles(&model), vec!["# a", "a1", "# b", "b1", "# c", "c1"]);
+
+        // The groups around the removed one are not merged since they differ
+        list.remove(1);
+        assert_eq!(titles(&model), vec!["# a", "a1", "# c", "c1"]);
+
+        list.splice(1, 0, &["a2"]);
+        assert_eq!(titles(&model), vec!["# a", "a1", "a2", "# c", "c1"]);
+    }
+
+    #[test]
+    fn emits_only_changed_range() {
+        let list = gtk::StringList::new(&["a1", "b1", "c1"]);
+        let model = GroupedListModel::new(&list);
+        model.set_group_func(group_by_first_char);
+
+        let first_header = model.item(0).unwrap();
+        let last_change = Rc::new(Cell::new(None));
+        model.connect_items_changed(
+            clone!(@strong last_change => move |_, position, removed, added| {
+                last_change.set(Some((position, removed, added)));
+            }),
+        );
+
+        // Adding "b2" only inserts it after "b1"
+        list.splice(2, 0, &["b2"]);
+        assert_eq!(last_change.get(), Some((4, 0, 1)));
+
+        // Removing the only item of the "c" group also removes its header
+        list.remove(3);
+        assert_eq!(last_change.get(), Some((5, 2, 0)));
+
+        assert_eq!(model.item(0).unwrap(), first_header);
+    }
+}
@@ -1,15 +1,53 @@
 mod attachment;
 mod attachment_list;
+mod attention_entry;
+mod attention_list;
+mod error_entry;
+mod error_log;
+mod freezable_list_model;
+mod group_header;
+mod grouped_list_model;
 mod note;
 mod note_id;
+mod note_index;
 mod note_list;
 mod note_metadata;
+mod note_problem;
 mod note_tag_list;
+mod notebook_settings;
+mod paged_list_model;
+mod problem_entry;
+mod problem_list;
+mod save_state;
+mod saved_search;
+mod saved_search_list;
 mod tag;
 mod tag_list;
 
 pub use self::{
-    attachment::Attachment, attachment_list::AttachmentList, note::Note, note_id::NoteId,
-    note_list::NoteList, note_metadata::NoteMetadata, note_tag_list::NoteTagList, tag::Tag,
+    attachment::Attachment,
+    attachment_list::AttachmentList,
+    attention_entry::AttentionEntry,
+    attention_list::AttentionList,
+    error_entry::{ErrorEntry, ErrorReport, ErrorSource},
+    error_log::{ErrorLog, ErrorSender},
+    freezable_list_model::FreezableListModel,
+    group_header::GroupHeader,
+    grouped_list_model::GroupedListModel,
+    note::Note,
+    note_id::NoteId,
+    note_index::{NoteFlags, NoteIndex},
+    note_list::NoteList,
+    note_metadata::NoteMetadata,
+    note_problem::{NoteFileEvent, NoteProblem},
+    note_tag_list::NoteTagList,
+    notebook_settings::NotebookSettings,
+    paged_list_model::PagedListModel,
+    problem_entry::{LoadProblem, LoadProblemKind, ProblemEntry},
+    problem_list::ProblemList,
+    save_state::SaveState,
+    saved_search::SavedSearch,
+    saved_search_list::SavedSearchList,
+    tag::Tag,
     tag_list::TagList,
 };
@@ -1,4 +1,4 @@
-use gray_matter::{engine::YAML, Matter};
+use anyhow::Context;
 use gtk::{
     gio,
     glib::{self, clone},
@@ -7,10 +7,34 @@ use gtk::{
 };
 use once_cell::unsync::OnceCell;
 
-use std::{cell::Cell, path::Path};
+use std::{
+    cell::{Cell, RefCell},
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::Hasher,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use super::{NoteFileEvent, NoteId, NoteIndex, NoteMetadata, NoteProblem, SaveState};
+use crate::{
+    core::{
+        body_offset, content_at, DateTime, FrontMatterProfile, IndexEntry, NoteData, ParseFallback,
+        DEFAULT_LANGUAGE, PLAIN_TEXT_LANGUAGE,
+    },
+    utils::{self, EditProfiler},
+};
+
+/// Notes with at least this many bytes of content are edited in large file mode, where
+/// highlighting is turned off and changes are handled lazily to keep typing responsive
+const LARGE_NOTE_SIZE: usize = 1024 * 1024;
 
-use super::{NoteId, NoteMetadata};
-use crate::utils;
+/// How long a large note has to stay unchanged before its size is checked again
+const SIZE_CHECK_DELAY: Duration = Duration::from_millis(500);
+
+/// Number of characters of the content written to the file at a time
+const WRITE_CHUNK_CHARS: i32 = 64 * 1024;
 
 mod imp {
     use super::*;
@@ -19,11 +43,33 @@ mod imp {
 
     #[derive(Debug, Default)]
     pub struct Note {
-        pub file: OnceCell<gio::File>,
+        pub file: RefCell<Option<gio::File>>,
         pub metadata: OnceCell<NoteMetadata>,
         pub buffer: OnceCell<gtk_source::Buffer>,
         pub is_saved: Cell<bool>,
-        pub id: OnceCell<NoteId>,
+        pub save_state: Cell<SaveState>,
+        pub save_error: RefCell<Option<String>>,
+        pub problem: RefCell<NoteProblem>,
+        /// Hash of the contents of the file as they were last loaded or saved, to tell the
+        /// changes made to it elsewhere from the own ones
+        pub saved_contents_hash: Cell<Option<u64>>,
+        pub is_large: Cell<bool>,
+        pub size_check_source_id: RefCell<Option<glib::SourceId>>,
+        /// Pending emission of `content-changed`, so it is emitted once for all the changes
+        /// made before the main loop is idle
+        pub content_changed_source_id: RefCell<Option<glib::SourceId>>,
+        /// Whether the content was edited since the last modified date was updated
+        pub is_last_modified_pending: Cell<bool>,
+        pub saved_title: RefCell<String>,
+        pub id: RefCell<Option<NoteId>>,
+        pub index: RefCell<Option<Rc<NoteIndex>>>,
+        pub lowercase_content: RefCell<Option<Rc<str>>>,
+        /// Where the content starts in the file, as it was last loaded or saved
+        pub body_offset: Cell<usize>,
+        /// Whether the content is still only in the file, as the note was made from an index
+        pub is_content_pending: Cell<bool>,
+        pub is_loading_content: Cell<bool>,
+        pub front_matter_profile: Cell<FrontMatterProfile>,
     }
 
     #[glib::object_subclass]
@@ -35,7 +81,10 @@ mod imp {
     impl ObjectImpl for Note {
         fn signals() -> &'static [Signal] {
             static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-                vec![Signal::builder("metadata-changed", &[], <()>::static_type().into()).build()]
+                vec![
+                    Signal::builder("metadata-changed", &[], <()>::static_type().into()).build(),
+                    Signal::builder("content-changed", &[], <()>::static_type().into()).build(),
+                ]
             });
             SIGNALS.as_ref()
         }
@@ -48,7 +97,7 @@ mod imp {
                         "File",
                         "File where Self is stored",
                         gio::File::static_type(),
-                        glib::ParamFlags::WRITABLE | glib::ParamFlags::CONSTRUCT_ONLY,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
                     ),
                     glib::ParamSpecObject::new(
                         "metadata",
@@ -71,6 +120,35 @@ mod imp {
                         false,
                         glib::ParamFlags::READABLE,
                     ),
+                    glib::ParamSpecEnum::new(
+                        "save-state",
+                        "Save State",
+                        "Whether the changes are written to file",
+                        SaveState::static_type(),
+                        SaveState::default() as i32,
+                        glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "save-error",
+                        "Save Error",
+                        "Why the last save failed",
+                        None,
+                        glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoxed::new(
+                        "problem",
+                        "Problem",
+                        "What is wrong with the file",
+                        NoteProblem::static_type(),
+                        glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-large",
+                        "Is Large",
+                        "Whether Self is edited in large file mode",
+                        false,
+                        glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -86,7 +164,7 @@ mod imp {
             match pspec.name() {
                 "file" => {
                     let file = value.get().unwrap();
-                    self.file.set(file).unwrap();
+                    self.file.replace(Some(file));
                 }
                 "metadata" => {
                     let metadata = value.get().unwrap();
@@ -106,6 +184,10 @@ mod imp {
                 "metadata" => obj.metadata().to_value(),
                 "buffer" => obj.metadata().to_value(),
                 "is-saved" => obj.is_saved().to_value(),
+                "save-state" => obj.save_state().to_value(),
+                "save-error" => obj.save_error().to_value(),
+                "problem" => obj.problem().to_value(),
+                "is-large" => obj.is_large().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -128,29 +210,77 @@ impl Note {
     ///
     /// This doesn't create an actual file unless `save` is called.
     pub fn new(base_path: impl AsRef<Path>) -> Self {
-        let full_path = utils::generate_unique_path(base_path.as_ref(), "Note", Some("md"));
+        Self::with_file_stem(base_path, "Note")
+    }
+
+    /// Create a new note like [`Note::new`], but with a name starting with `file_stem`
+    pub fn with_file_stem(base_path: impl AsRef<Path>, file_stem: &str) -> Self {
+        let full_path = utils::generate_unique_path(base_path.as_ref(), file_stem, Some("md"));
+        Self::with_file(&gio::File::for_path(full_path))
+    }
+
+    /// Create a new empty note stored in `file`, which must not exist yet
+    pub fn with_file(file: &gio::File) -> Self {
+        let metadata = NoteMetadata::default();
+        metadata.set_created(Some(DateTime::now()));
 
         glib::Object::new(&[
-            ("file", &gio::File::for_path(full_path)),
-            ("metadata", &NoteMetadata::default()),
+            ("file", file),
+            ("metadata", &metadata),
             ("buffer", &Self::default_buffer()),
         ])
         .expect("Failed to create Note.")
     }
 
+    /// Create a note stored in `file` from its `entry` in the index, which only reads the
+    /// content from the file once the buffer is used
+    pub fn with_index_entry(file: &gio::File, entry: &IndexEntry) -> Self {
+        let metadata = NoteMetadata::from_data(&entry.metadata);
+
+        // Guessed from the size of the file until the content is loaded
+        let is_large = entry.stamp.size as usize >= LARGE_NOTE_SIZE;
+        let buffer = Self::default_buffer();
+        set_buffer_language(&buffer, metadata.language().as_deref(), is_large);
+
+        let note: Self = glib::Object::new(&[
+            ("file", &file),
+            ("metadata", &metadata),
+            ("buffer", &buffer),
+        ])
+        .expect("Failed to create Note.");
+
+        let imp = note.imp();
+        imp.is_large.set(is_large);
+        imp.saved_title.replace(note.metadata().title());
+        imp.body_offset.set(entry.body_offset);
+        imp.is_content_pending.set(true);
+
+        note
+    }
+
     /// Load a note from file
     pub async fn load(file: &gio::File) -> anyhow::Result<Self> {
-        let (metadata, content) = Self::load_metadata_and_content(file).await?;
+        let (metadata, content, body_offset, contents_hash) =
+            Self::load_metadata_and_content(file).await?;
 
+        // Decide before setting the text, so large content is never highlighted
+        let is_large = content.len() >= LARGE_NOTE_SIZE;
         let buffer = Self::default_buffer();
+        set_buffer_language(&buffer, metadata.language().as_deref(), is_large);
         buffer.set_text(&content);
 
-        Ok(glib::Object::new(&[
+        let note: Self = glib::Object::new(&[
             ("file", &file),
             ("metadata", &metadata),
             ("buffer", &buffer),
         ])
-        .expect("Failed to create Note."))
+        .expect("Failed to create Note.");
+        note.imp().is_large.set(is_large);
+        note.imp().saved_title.replace(note.metadata().title());
+        note.imp().body_offset.set(body_offset);
+        note.imp().saved_contents_hash.set(Some(contents_hash));
+
+        Ok(note)
     }
 
     /// Save the metadata and content of note to file
@@ -160,47 +290,232 @@ impl Note {
             return Ok(());
         }
 
-        // FIXME replace with non hacky implementation
-        let mut bytes = serde_yaml::to_vec(&self.metadata())?;
+        self.flush_content_changed();
+        self.flush_size_check();
+        self.set_save_state(SaveState::Saving);
 
-        let delimiter = "---\n";
-        bytes.append(&mut delimiter.into());
+        let (body_offset, contents_hash) = match self.write().await {
+            Ok(written) => written,
+            Err(err) => {
+                self.set_save_failed(&err);
+                return Err(err);
+            }
+        };
 
-        let buffer = self.buffer();
-        let (start_iter, end_iter) = buffer.bounds();
-        let buffer_text = buffer.text(&start_iter, &end_iter, true).to_string();
-        bytes.append(&mut buffer_text.into_bytes());
+        self.imp().body_offset.set(body_offset);
+        self.imp().saved_contents_hash.set(Some(contents_hash));
+        self.set_is_saved(true);
+        self.imp().saved_title.replace(self.metadata().title());
+        self.report_file_event(NoteFileEvent::Saved);
+
+        log::info!("Saved `{}`", self);
+
+        Ok(())
+    }
+
+    /// The metadata and content as they are now in a note file, to be written later with
+    /// [`Note::save_contents`] while the note can still be changed
+    pub fn contents(&self) -> anyhow::Result<glib::Bytes> {
+        self.read_pending_content()
+            .context("Failed to read the content of the note")?;
+        self.flush_content_changed();
+        self.flush_size_check();
+
+        let mut bytes = self
+            .metadata()
+            .to_data()
+            .serialize(self.front_matter_profile())?
+            .into_bytes();
+        for chunk in content_chunks(self.buffer()) {
+            bytes.extend_from_slice(chunk.as_bytes());
+        }
+
+        Ok(glib::Bytes::from_owned(bytes))
+    }
+
+    /// Replace the file with `contents` taken with [`Note::contents`]
+    pub async fn save_contents(&self, contents: glib::Bytes) -> anyhow::Result<()> {
+        self.set_save_state(SaveState::Saving);
 
-        self.file()
-            .replace_contents_future(bytes, None, false, gio::FileCreateFlags::NONE)
+        let body_offset = std::str::from_utf8(&contents).map_or(0, |text| body_offset(text, &[]));
+        let contents_hash = contents_hash([&*contents]);
+
+        if let Err((_, err)) = self
+            .file()
+            .replace_contents_future(contents, None, false, gio::FileCreateFlags::NONE)
             .await
-            .map_err(|err| err.1)?;
+        {
+            let err = anyhow::Error::from(err);
+            self.set_save_failed(&err);
+            return Err(err);
+        }
 
+        self.imp().body_offset.set(body_offset);
+        self.imp().saved_contents_hash.set(Some(contents_hash));
         self.set_is_saved(true);
+        self.imp().saved_title.replace(self.metadata().title());
+        self.report_file_event(NoteFileEvent::Saved);
 
         log::info!("Saved `{}`", self);
 
         Ok(())
     }
 
+    /// Write the metadata and content into `stream` as the contents of a note file with the
+    /// front matter in [`Note::front_matter_profile`], which can be parsed back with
+    /// [`NoteData::parse`]
+    ///
+    /// The content is written in chunks, so it is never put together with the front matter
+    /// into one large allocation. Returns where the content starts.
+    pub async fn serialize(&self, stream: &impl IsA<gio::OutputStream>) -> anyhow::Result<usize> {
+        let (body_offset, _) = self.serialize_with_hash(stream).await?;
+        Ok(body_offset)
+    }
+
+    /// How the front matter is written when the note is saved, which is the one of the
+    /// notebook settings
+    pub fn set_front_matter_profile(&self, profile: FrontMatterProfile) {
+        self.imp().front_matter_profile.set(profile);
+    }
+
+    pub fn front_matter_profile(&self) -> FrontMatterProfile {
+        self.imp().front_matter_profile.get()
+    }
+
     pub fn metadata(&self) -> &NoteMetadata {
         self.imp().metadata.get().unwrap()
     }
 
     pub fn buffer(&self) -> &gtk_source::Buffer {
+        // Only read here when it is used before it was loaded in the background
+        if let Err(err) = self.read_pending_content() {
+            log::error!("Failed to load content of `{}`: {:?}", self, err);
+        }
+
         self.imp().buffer.get().unwrap()
     }
 
-    pub fn id(&self) -> &NoteId {
+    /// Where the content starts in the file, as it was last loaded or saved
+    pub fn body_offset(&self) -> usize {
+        self.imp().body_offset.get()
+    }
+
+    pub fn id(&self) -> NoteId {
         self.imp()
             .id
-            .get_or_init(|| NoteId::for_path(&self.file().path().unwrap()))
+            .borrow_mut()
+            .get_or_insert_with(|| NoteId::for_path(&self.file().path().unwrap()))
+            .clone()
     }
 
     pub fn is_saved(&self) -> bool {
         self.imp().is_saved.get()
     }
 
+    /// Whether the changes are saved, being saved, or failed to. It only changes in the main
+    /// context, as the writes on worker threads are awaited there.
+    pub fn save_state(&self) -> SaveState {
+        self.imp().save_state.get()
+    }
+
+    /// Why the last save failed, while [`Note::save_state`] is [`SaveState::Error`]
+    pub fn save_error(&self) -> Option<String> {
+        self.imp().save_error.borrow().clone()
+    }
+
+    /// Mark the changes as not saved because of `err`, until they are saved or changed again
+    pub fn set_save_failed(&self, err: &anyhow::Error) {
+        let message = format!("{:#}", err);
+        self.imp().save_error.replace(Some(message.clone()));
+        self.notify("save-error");
+        self.set_save_state(SaveState::Error);
+        self.report_file_event(NoteFileEvent::SaveFailed(message));
+    }
+
+    /// What is wrong with the file, which stays until it is solved even if the changes are
+    /// saved or changed again
+    pub fn problem(&self) -> NoteProblem {
+        self.imp().problem.borrow().clone()
+    }
+
+    /// Update the problem with what happened to the file, as told by the file monitor
+    pub fn report_file_event(&self, event: NoteFileEvent) {
+        let problem = self.problem().after(event);
+
+        if problem == self.problem() {
+            return;
+        }
+
+        log::info!("Problem of `{}` is now {:?}", self, problem);
+
+        self.imp().problem.replace(problem);
+        self.notify("problem");
+    }
+
+    /// Tell whether the file was changed elsewhere, now that it has `contents`
+    ///
+    /// Nothing is told while the note is being saved, as the file may already have what is
+    /// being written.
+    pub fn check_file_contents(&self, contents: &[u8]) {
+        let imp = self.imp();
+
+        if imp.is_content_pending.get() || self.save_state() == SaveState::Saving {
+            return;
+        }
+
+        if let Some(saved_contents_hash) = imp.saved_contents_hash.get() {
+            self.report_file_event(NoteFileEvent::Changed {
+                is_different: contents_hash([contents]) != saved_contents_hash,
+            });
+        }
+    }
+
+    /// Whether the content is at least [`LARGE_NOTE_SIZE`], which turns off highlighting and
+    /// only updates the last modified date once the changes stop
+    pub fn is_large(&self) -> bool {
+        self.imp().is_large.get()
+    }
+
+    /// What filters check on the metadata, which is kept until the metadata changes
+    pub fn index(&self) -> Rc<NoteIndex> {
+        let imp = self.imp();
+
+        if let Some(ref index) = *imp.index.borrow() {
+            return Rc::clone(index);
+        }
+
+        let index = Rc::new(NoteIndex::new(self.metadata()));
+        imp.index.replace(Some(Rc::clone(&index)));
+        index
+    }
+
+    /// The content in lowercase, which is kept until the buffer changes
+    pub fn lowercase_content(&self) -> Rc<str> {
+        let imp = self.imp();
+
+        if let Some(ref content) = *imp.lowercase_content.borrow() {
+            return Rc::clone(content);
+        }
+
+        let buffer = self.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let content: Rc<str> = Rc::from(buffer.text(&start_iter, &end_iter, true).to_lowercase());
+        imp.lowercase_content.replace(Some(Rc::clone(&content)));
+        content
+    }
+
+    /// The selected text of the content, or all of it when nothing is selected
+    pub fn selected_content(&self) -> String {
+        let buffer = self.buffer();
+        let (start_iter, end_iter) = buffer.selection_bounds().unwrap_or_else(|| buffer.bounds());
+        buffer.text(&start_iter, &end_iter, true).to_string()
+    }
+
+    /// The title of the note as it was when last loaded or saved to file
+    pub fn saved_title(&self) -> String {
+        self.imp().saved_title.borrow().clone()
+    }
+
     pub fn connect_is_saved_notify<F>(&self, f: F) -> glib::SignalHandlerId
     where
         F: Fn(&Self) + 'static,
@@ -208,6 +523,20 @@ impl Note {
         self.connect_notify_local(Some("is-saved"), move |obj, _| f(obj))
     }
 
+    pub fn connect_problem_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("problem"), move |obj, _| f(obj))
+    }
+
+    pub fn connect_is_large_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("is-large"), move |obj, _| f(obj))
+    }
+
     pub fn connect_metadata_changed<F>(&self, f: F) -> glib::SignalHandlerId
     where
         F: Fn(&Self) + 'static,
@@ -219,61 +548,429 @@ impl Note {
         })
     }
 
+    /// Called once after the content was changed, however many changes were made since the
+    /// main loop was last idle. Things that don't have to follow every keystroke, like the
+    /// subtitle in the sidebar, should use this rather than the changed signal of the buffer.
+    pub fn connect_content_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("content-changed", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    /// Emit the pending `content-changed` now instead of waiting for the main loop, so
+    /// everything that depends on the content, like the last modified date, is up to date
+    pub fn flush_content_changed(&self) {
+        if let Some(source_id) = self.imp().content_changed_source_id.take() {
+            source_id.remove();
+            self.emit_content_changed();
+        }
+    }
+
     /// Load file then update metadata and content based on the new file content
     pub async fn update(&self) -> anyhow::Result<()> {
-        let (metadata, content) = Self::load_metadata_and_content(self.file()).await?;
+        let (metadata, content, _, contents_hash) =
+            Self::load_metadata_and_content(&self.file()).await?;
 
         self.metadata().update(&metadata);
         self.buffer().set_text(&content);
 
+        self.imp().saved_contents_hash.set(Some(contents_hash));
+        self.report_file_event(NoteFileEvent::Reloaded);
+
+        Ok(())
+    }
+
+    /// Load the file again after it was changed elsewhere, like while the app was not
+    /// running, which keeps the note saved
+    pub async fn reload(&self) -> anyhow::Result<()> {
+        let (metadata, content, body_offset, contents_hash) =
+            Self::load_metadata_and_content(&self.file()).await?;
+
+        let imp = self.imp();
+        imp.is_content_pending.set(false);
+
+        self.metadata().update(&metadata);
+        self.set_loaded_content(&content);
+
+        imp.body_offset.set(body_offset);
+        imp.saved_contents_hash.set(Some(contents_hash));
+        imp.saved_title.replace(self.metadata().title());
+        self.set_is_saved(true);
+        self.report_file_event(NoteFileEvent::Reloaded);
+
+        Ok(())
+    }
+
+    /// Load the content left in the file by [`Note::with_index_entry`] without blocking, if it
+    /// is not loaded yet
+    ///
+    /// It stays left in the file if it can't be read, so the note is never saved without it.
+    pub async fn load_pending_content(&self) -> anyhow::Result<()> {
+        if !self.imp().is_content_pending.get() {
+            return Ok(());
+        }
+
+        let (contents, _) = self.file().load_contents_future().await?;
+
+        // It may have been read while the file was loaded
+        if self.imp().is_content_pending.get() {
+            self.set_pending_content(std::str::from_utf8(&contents)?);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Note::load_pending_content`], blocking until the file is read
+    fn read_pending_content(&self) -> anyhow::Result<()> {
+        if !self.imp().is_content_pending.get() {
+            return Ok(());
+        }
+
+        let text = fs::read_to_string(self.file().path().unwrap())?;
+        self.set_pending_content(&text);
+
         Ok(())
     }
 
+    /// Set the content from `text`, the contents of the file that were left pending
+    fn set_pending_content(&self, text: &str) {
+        let imp = self.imp();
+        imp.is_content_pending.set(false);
+
+        let content = match content_at(text, self.body_offset()) {
+            Some(content) => content.to_string(),
+            None => {
+                log::warn!("`{}` changed since it was indexed", self);
+                NoteData::parse_tolerant(text).0.content
+            }
+        };
+
+        imp.saved_contents_hash
+            .set(Some(contents_hash([text.as_bytes()])));
+        self.set_loaded_content(&content);
+    }
+
+    /// Set the content to `content` from the file, which is not a change of the note
+    fn set_loaded_content(&self, content: &str) {
+        let imp = self.imp();
+        let buffer = imp.buffer.get().unwrap();
+
+        // Decide before setting the text, so large content is never highlighted
+        self.set_is_large(content.len() >= LARGE_NOTE_SIZE);
+
+        imp.is_loading_content.set(true);
+        buffer.begin_irreversible_action();
+        buffer.set_text(content);
+        buffer.end_irreversible_action();
+        imp.is_loading_content.set(false);
+    }
+
     fn set_is_saved(&self, is_saved: bool) {
         self.imp().is_saved.set(is_saved);
         self.notify("is-saved");
+
+        self.set_save_state(if is_saved {
+            SaveState::Saved
+        } else {
+            SaveState::Modified
+        });
+    }
+
+    fn set_save_state(&self, save_state: SaveState) {
+        if save_state == self.save_state() {
+            return;
+        }
+
+        let imp = self.imp();
+        imp.save_state.set(save_state);
+
+        if save_state != SaveState::Error && imp.save_error.take().is_some() {
+            self.notify("save-error");
+        }
+
+        self.notify("save-state");
+    }
+
+    /// Like [`Note::serialize`], also returning the hash of what was written
+    async fn serialize_with_hash(
+        &self,
+        stream: &impl IsA<gio::OutputStream>,
+    ) -> anyhow::Result<(usize, u64)> {
+        self.load_pending_content()
+            .await
+            .context("Failed to read the content of the note")?;
+
+        let stream = stream.upcast_ref::<gio::OutputStream>();
+        let front_matter = self
+            .metadata()
+            .to_data()
+            .serialize(self.front_matter_profile())?;
+
+        // Take all the chunks first, since the buffer can change while they are written
+        let chunks = content_chunks(self.buffer());
+
+        let body_offset = front_matter.len();
+        let contents_hash = contents_hash(
+            std::iter::once(front_matter.as_bytes()).chain(chunks.iter().map(|c| c.as_bytes())),
+        );
+
+        write_all(stream, front_matter.into_bytes()).await?;
+        for chunk in chunks {
+            write_all(stream, chunk.into_bytes()).await?;
+        }
+
+        Ok((body_offset, contents_hash))
+    }
+
+    /// Replace the file with what [`Note::serialize`] writes, returning where the content
+    /// starts and the hash of the contents
+    async fn write(&self) -> anyhow::Result<(usize, u64)> {
+        let stream = self
+            .file()
+            .replace_future(
+                None,
+                false,
+                gio::FileCreateFlags::NONE,
+                glib::PRIORITY_DEFAULT,
+            )
+            .await?;
+
+        let written = match self.serialize_with_hash(&stream).await {
+            Ok(written) => written,
+            Err(err) => {
+                // A cancelled close keeps the old file instead of replacing it with a partial one
+                let cancellable = gio::Cancellable::new();
+                cancellable.cancel();
+                let _ = stream.close(Some(&cancellable));
+                return Err(err);
+            }
+        };
+
+        stream.close_future(glib::PRIORITY_DEFAULT).await?;
+
+        Ok(written)
+    }
+
+    fn set_is_large(&self, is_large: bool) {
+        if is_large == self.is_large() {
+            return;
+        }
+
+        self.imp().is_large.set(is_large);
+        self.update_buffer_language();
+
+        self.notify("is-large");
+
+        if is_large {
+            log::info!("Switched `{}` to large file mode", self);
+        }
+    }
+
+    fn update_buffer_language(&self) {
+        set_buffer_language(
+            self.buffer(),
+            self.metadata().language().as_deref(),
+            self.is_large(),
+        );
+    }
+
+    /// Update the large file mode from the exact size of the content, and the last modified
+    /// date that was held back while the large note was being changed
+    fn check_size(&self) {
+        let buffer = self.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let size = buffer.text(&start_iter, &end_iter, true).len();
+
+        if self.is_large() {
+            self.metadata().update_last_modified();
+        }
+
+        self.set_is_large(size >= LARGE_NOTE_SIZE);
     }
 
-    fn file(&self) -> &gio::File {
-        self.imp().file.get().unwrap()
+    /// Do the pending size check now instead of waiting for it, so the last modified date is up
+    /// to date
+    fn flush_size_check(&self) {
+        if let Some(source_id) = self.imp().size_check_source_id.take() {
+            source_id.remove();
+            self.check_size();
+        }
     }
 
-    async fn load_metadata_and_content(file: &gio::File) -> anyhow::Result<(NoteMetadata, String)> {
+    fn queue_content_changed(&self) {
+        let imp = self.imp();
+
+        if imp.content_changed_source_id.borrow().is_some() {
+            return;
+        }
+
+        let source_id = glib::idle_add_local_once(clone!(@weak self as obj => move || {
+            obj.imp().content_changed_source_id.replace(None);
+            obj.emit_content_changed();
+        }));
+        imp.content_changed_source_id.replace(Some(source_id));
+    }
+
+    fn emit_content_changed(&self) {
+        EditProfiler::default().measure("note-content-changed", || {
+            if self.imp().is_last_modified_pending.take() {
+                self.metadata().update_last_modified();
+            }
+
+            self.emit_by_name::<()>("content-changed", &[]);
+        });
+    }
+
+    /// Only what has to follow every keystroke is done here, the rest waits for
+    /// `content-changed`
+    fn handle_buffer_changed(&self, buffer: &gtk_source::Buffer) {
+        let imp = self.imp();
+        imp.lowercase_content.replace(None);
+        self.queue_content_changed();
+
+        if imp.is_loading_content.get() {
+            return;
+        }
+
+        self.set_is_saved(false);
+
+        if self.is_large() {
+            self.queue_size_check();
+            return;
+        }
+
+        imp.is_last_modified_pending.set(true);
+
+        // Every character is at least one byte, so only a change to a buffer with
+        // enough characters can make it large. It is switched right away when it
+        // surely is, so pasting a large text is not highlighted in between.
+        let n_chars = buffer.char_count() as usize;
+        if n_chars >= LARGE_NOTE_SIZE {
+            self.set_is_large(true);
+        } else if n_chars * 4 >= LARGE_NOTE_SIZE {
+            self.queue_size_check();
+        }
+    }
+
+    /// Check the size once the changes stop, so it is not done on every keystroke
+    fn queue_size_check(&self) {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.size_check_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            SIZE_CHECK_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().size_check_source_id.replace(None);
+                obj.check_size();
+            }),
+        );
+        imp.size_check_source_id.replace(Some(source_id));
+    }
+
+    pub fn file(&self) -> gio::File {
+        self.imp().file.borrow().clone().unwrap()
+    }
+
+    /// Point the note to `file` after its file was moved there, which also changes its id.
+    /// Use [`NoteList::move_note`](super::NoteList::move_note) for a note in a list.
+    pub fn set_file(&self, file: &gio::File) {
+        let imp = self.imp();
+        imp.file.replace(Some(file.clone()));
+        imp.id.replace(None);
+        self.notify("file");
+    }
+
+    /// The metadata and content of `file`, where the content starts in it, and the hash of
+    /// the contents of the file
+    async fn load_metadata_and_content(
+        file: &gio::File,
+    ) -> anyhow::Result<(NoteMetadata, String, usize, u64)> {
         let (file_content, _) = file.load_contents_future().await?;
+        let contents_hash = contents_hash([&*file_content]);
         let file_content = std::str::from_utf8(&file_content)?;
 
-        let parsed_entity = Matter::<YAML>::new().parse(file_content);
-        let pod = parsed_entity
-            .data
-            .ok_or_else(|| anyhow::anyhow!("ParsedEntity.data not found in `{}`", file_content))?;
+        // Notes from other apps may have front matter that Noteworthy doesn't write itself
+        let (mut note_data, fallbacks) = NoteData::parse_tolerant(file_content);
+
+        for fallback in &fallbacks {
+            log::warn!("Front matter of `{}`: {}", file.uri(), fallback);
+        }
+
+        if fallbacks.iter().any(ParseFallback::is_last_modified) {
+            note_data.metadata.last_modified = Self::file_modified(file).await;
+        }
+
+        Ok((
+            NoteMetadata::from_data(&note_data.metadata),
+            note_data.content,
+            body_offset(file_content, &fallbacks),
+            contents_hash,
+        ))
+    }
 
-        Ok((pod.deserialize()?, parsed_entity.content))
+    /// When `file` was last modified, or now if it can't be queried
+    async fn file_modified(file: &gio::File) -> DateTime {
+        match file
+            .query_info_future(
+                &gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+                gio::FileQueryInfoFlags::NONE,
+                glib::PRIORITY_DEFAULT,
+            )
+            .await
+        {
+            Ok(info) => DateTime::from_timestamp(
+                info.attribute_uint64(&gio::FILE_ATTRIBUTE_TIME_MODIFIED) as i64,
+            ),
+            Err(err) => {
+                log::warn!(
+                    "Failed to query modification time of `{}`: {:?}",
+                    file.uri(),
+                    err
+                );
+                DateTime::now()
+            }
+        }
     }
 
     fn default_buffer() -> gtk_source::Buffer {
         // FIXME not following AdwStyleManager::is-dark
-        gtk_source::Buffer::builder()
+        let buffer = gtk_source::Buffer::builder()
             .highlight_matching_brackets(false)
-            .language(
-                &gtk_source::LanguageManager::default()
-                    .language("markdown")
-                    .unwrap(),
-            )
-            .build()
+            .build();
+        buffer.set_language(source_language(None).as_ref());
+        buffer
     }
 
     fn setup_signals(&self) {
         self.buffer()
-            .connect_changed(clone!(@weak self as obj => move |_| {
-                obj.metadata().update_last_modified();
-                obj.set_is_saved(false);
+            .connect_changed(clone!(@weak self as obj => move |buffer| {
+                EditProfiler::default().measure("note-buffer-changed", || {
+                    obj.handle_buffer_changed(buffer);
+                });
             }));
 
         let metadata = self.metadata();
 
+        metadata.connect_notify_local(
+            Some("language"),
+            clone!(@weak self as obj => move |_, _| {
+                obj.update_buffer_language();
+            }),
+        );
+
         metadata.connect_notify_local(
             None,
             clone!(@weak self as obj => move |_, _| {
+                // Invalidate before emitting, so handlers get the new index
+                obj.imp().index.replace(None);
                 obj.emit_by_name::<()>("metadata-changed", &[]);
                 obj.set_is_saved(false);
             }),
@@ -284,6 +981,7 @@ impl Note {
         metadata
             .tag_list()
             .connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+                obj.imp().index.replace(None);
                 obj.emit_by_name::<()>("metadata-changed", &[]);
                 obj.set_is_saved(false);
             }));
@@ -297,6 +995,66 @@ impl Note {
     }
 }
 
+/// The language to highlight a note as from the `language` of its metadata, or `None` for
+/// plain text. Unknown languages, like ones of a newer GtkSourceView, fall back to Markdown.
+fn source_language(language: Option<&str>) -> Option<gtk_source::Language> {
+    let id = match language {
+        Some(PLAIN_TEXT_LANGUAGE) => return None,
+        Some(id) => id,
+        None => DEFAULT_LANGUAGE,
+    };
+
+    let language_manager = gtk_source::LanguageManager::default();
+    language_manager.language(id).or_else(|| {
+        log::warn!("Unknown language `{}`, highlighting as Markdown", id);
+        language_manager.language(DEFAULT_LANGUAGE)
+    })
+}
+
+/// Highlight `buffer` as `language`, or turn off the highlighting that is too slow for large
+/// content
+fn set_buffer_language(buffer: &gtk_source::Buffer, language: Option<&str>, is_large: bool) {
+    if is_large {
+        buffer.set_language(None);
+        buffer.set_highlight_matching_brackets(false);
+    } else {
+        buffer.set_language(source_language(language).as_ref());
+    }
+}
+
+/// The text of `buffer` in pieces of at most [`WRITE_CHUNK_CHARS`] characters
+fn content_chunks(buffer: &gtk_source::Buffer) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut start_iter = buffer.start_iter();
+
+    while !start_iter.is_end() {
+        let mut end_iter = start_iter.clone();
+        end_iter.forward_chars(WRITE_CHUNK_CHARS);
+        chunks.push(buffer.slice(&start_iter, &end_iter, true).into());
+        start_iter = end_iter;
+    }
+
+    chunks
+}
+
+/// Hash of the contents of a note file, which is the same however they are split in `pieces`
+fn contents_hash<'a>(pieces: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for piece in pieces {
+        hasher.write(piece);
+    }
+
+    hasher.finish()
+}
+
+async fn write_all(stream: &gio::OutputStream, bytes: Vec<u8>) -> Result<(), glib::Error> {
+    match stream.write_all_future(bytes, glib::PRIORITY_DEFAULT).await {
+        Ok((_, _, None)) => Ok(()),
+        Ok((_, _, Some(err))) | Err((_, err)) => Err(err),
+    }
+}
+
 impl std::fmt::Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -307,3 +1065,675 @@ impl std::fmt::Display for Note {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Instant;
+
+    use crate::{core::FileStamp, test_harness::run_on_gtk_thread};
+
+    /// Size of the note that the large file mode is measured with
+    const GENERATED_NOTE_SIZE: usize = 5 * 1024 * 1024;
+
+    /// Time it takes to draw a frame at 60 fps. Unoptimized builds are several times slower,
+    /// so they are given more time.
+    fn frame_budget() -> Duration {
+        if cfg!(debug_assertions) {
+            Duration::from_millis(64)
+        } else {
+            Duration::from_millis(16)
+        }
+    }
+
+    /// Markdown that is at least `size` bytes, like a pasted log with some formatting
+    fn generated_content(size: usize) -> String {
+        let mut content = String::with_capacity(size + 128);
+        let mut i = 0;
+
+        while content.len() < size {
+            content.push_str(&format!(
+                "**{}** [info] ({}) Request to <https://example.com/{}> took `{}ms`\n",
+                i,
+                i % 8,
+                i,
+                i % 1000
+            ));
+            i += 1;
+        }
+
+        content
+    }
+
+    fn note_with_content(content: &str) -> Note {
+        let note = Note::new("/home/user");
+        note.metadata().set_title("Log");
+        note.buffer().set_text(content);
+        note
+    }
+
+    fn serialized(note: &Note) -> Vec<u8> {
+        let stream = gio::MemoryOutputStream::new_resizable();
+
+        glib::MainContext::new()
+            .block_on(async {
+                note.serialize(&stream).await?;
+                stream.close_future(glib::PRIORITY_DEFAULT).await?;
+                Ok::<_, anyhow::Error>(())
+            })
+            .unwrap();
+
+        stream.steal_as_bytes().to_vec()
+    }
+
+    /// How the note was serialized before it was written in chunks
+    fn serialized_as_whole(note: &Note) -> Vec<u8> {
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+
+        NoteData {
+            metadata: note.metadata().to_data(),
+            content: buffer.text(&start_iter, &end_iter, true).to_string(),
+        }
+        .serialize(note.front_matter_profile())
+        .unwrap()
+        .into_bytes()
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn serialize() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("# Heading\n\nSome content");
+
+            let bytes = serialized(&note);
+            assert_eq!(bytes, serialized_as_whole(&note));
+
+            let note_data = NoteData::parse(std::str::from_utf8(&bytes).unwrap()).unwrap();
+            assert_eq!(note_data.metadata.title, "Log");
+            assert_eq!(note_data.content, "# Heading\n\nSome content");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn serialize_standard_markdown() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("# Heading\n\nSome content");
+            note.set_front_matter_profile(FrontMatterProfile::StandardMarkdown);
+
+            let bytes = serialized(&note);
+            assert_eq!(bytes, serialized_as_whole(&note));
+
+            let text = std::str::from_utf8(&bytes).unwrap();
+            assert!(text.starts_with("---\ntitle: Log\ncreated: "));
+            assert!(!text.contains("last_modified"));
+
+            let note_data = NoteData::parse(text).unwrap();
+            assert_eq!(note_data.metadata, note.metadata().to_data());
+            assert_eq!(note_data.content, "# Heading\n\nSome content");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn serialize_empty() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("");
+            assert_eq!(serialized(&note), serialized_as_whole(&note));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn contents() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("# Heading\n\nSome content");
+            let expected = serialized_as_whole(&note);
+            let contents = note.contents().unwrap();
+
+            // Taken before the change, so that is what would be written
+            note.buffer().set_text("Changed");
+            assert_eq!(contents.to_vec(), expected);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn save_state() {
+        run_on_gtk_thread(|| {
+            let dir = std::env::temp_dir()
+                .join(format!("noteworthy-note-save-state-{}", std::process::id()));
+            // The folder is not created yet, so writing fails like on a full disk
+            let note = Note::with_file(&gio::File::for_path(dir.join("Note.md")));
+            assert_eq!(note.save_state(), SaveState::Saved);
+
+            let states = Rc::new(RefCell::new(Vec::new()));
+            note.connect_notify_local(
+                Some("save-state"),
+                clone!(@strong states => move |note, _| {
+                    states.borrow_mut().push(note.save_state());
+                }),
+            );
+            let n_metadata_changed = Rc::new(Cell::new(0));
+            note.connect_metadata_changed(clone!(@strong n_metadata_changed => move |_| {
+                n_metadata_changed.set(n_metadata_changed.get() + 1);
+            }));
+
+            note.buffer().set_text("Content");
+            note.metadata().set_title("Title");
+            assert_eq!(note.save_state(), SaveState::Modified);
+            assert_eq!(note.save_error(), None);
+
+            let ctx = glib::MainContext::new();
+            // The last modified date of the change is only updated once the changes are done
+            note.flush_content_changed();
+            let n_metadata_changed_before = n_metadata_changed.get();
+
+            let contents = note.contents().unwrap();
+            assert!(ctx.block_on(note.save_contents(contents)).is_err());
+            assert_eq!(note.save_state(), SaveState::Error);
+            assert!(note.save_error().is_some());
+            assert!(!note.is_saved());
+
+            std::fs::create_dir_all(&dir).unwrap();
+            let contents = note.contents().unwrap();
+            let res = ctx.block_on(note.save_contents(contents));
+            std::fs::remove_dir_all(&dir).unwrap();
+            res.unwrap();
+
+            assert_eq!(note.save_state(), SaveState::Saved);
+            assert_eq!(note.save_error(), None);
+            assert!(note.is_saved());
+            assert_eq!(
+                states.take(),
+                [
+                    SaveState::Modified,
+                    SaveState::Saving,
+                    SaveState::Error,
+                    SaveState::Saving,
+                    SaveState::Saved,
+                ]
+            );
+            // Saving doesn't change the metadata, so the note list is not sorted again
+            assert_eq!(n_metadata_changed.get(), n_metadata_changed_before);
+
+            // A change after a failed save clears the error
+            note.set_save_failed(&anyhow::anyhow!("Disk is full"));
+            assert_eq!(note.save_error().as_deref(), Some("Disk is full"));
+            note.buffer().set_text("Changed");
+            assert_eq!(note.save_state(), SaveState::Modified);
+            assert_eq!(note.save_error(), None);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn content_changed_coalesced() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("");
+            let ctx = glib::MainContext::default();
+            while ctx.iteration(false) {}
+
+            let n_content_changed = Rc::new(Cell::new(0));
+            note.connect_content_changed(clone!(@strong n_content_changed => move |_| {
+                n_content_changed.set(n_content_changed.get() + 1);
+            }));
+            let n_metadata_changed = Rc::new(Cell::new(0));
+            note.connect_metadata_changed(clone!(@strong n_metadata_changed => move |_| {
+                n_metadata_changed.set(n_metadata_changed.get() + 1);
+            }));
+            let last_modified = note.metadata().last_modified();
+
+            let profiler = EditProfiler::default();
+            profiler.set_is_enabled(true);
+
+            let buffer = note.buffer();
+            for _ in 0..1000 {
+                buffer.insert(&mut buffer.end_iter(), "a");
+            }
+            assert!(!note.is_saved());
+            assert_eq!(n_content_changed.get(), 0);
+            assert_eq!(n_metadata_changed.get(), 0);
+
+            while ctx.iteration(false) {}
+            assert_eq!(n_content_changed.get(), 1);
+            assert_eq!(n_metadata_changed.get(), 1);
+            assert_ne!(note.metadata().last_modified(), last_modified);
+
+            let n_measured = |handler| {
+                profiler
+                    .histograms()
+                    .into_iter()
+                    .find(|(name, _)| *name == handler)
+                    .map_or(0, |(_, histogram)| histogram.len())
+            };
+            assert_eq!(n_measured("note-buffer-changed"), 1000);
+            assert_eq!(n_measured("note-content-changed"), 1);
+            profiler.set_is_enabled(false);
+
+            // Nothing is left pending once flushed
+            buffer.insert(&mut buffer.end_iter(), "b");
+            note.flush_content_changed();
+            assert_eq!(n_content_changed.get(), 2);
+            while ctx.iteration(false) {}
+            assert_eq!(n_content_changed.get(), 2);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn selected_content() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("# Heading\n\nSome content");
+            assert_eq!(note.selected_content(), "# Heading\n\nSome content");
+
+            let buffer = note.buffer();
+            buffer.select_range(&buffer.iter_at_offset(11), &buffer.iter_at_offset(15));
+            assert_eq!(note.selected_content(), "Some");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn large_file_mode() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("Small");
+            assert!(!note.is_large());
+            assert!(note.buffer().language().is_some());
+
+            // Switched as soon as the large text is pasted
+            note.buffer().set_text(&generated_content(LARGE_NOTE_SIZE));
+            assert!(note.is_large());
+            assert!(note.buffer().language().is_none());
+
+            // Kept until the size is checked once the changes stop
+            note.buffer().set_text("Small again");
+            assert!(note.is_large());
+
+            note.check_size();
+            assert!(!note.is_large());
+            assert!(note.buffer().language().is_some());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn language_from_front_matter() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir().join(format!(
+                "noteworthy-note-language-{}.md",
+                std::process::id()
+            ));
+            std::fs::write(
+                &path,
+                "---\ntitle: Config\nlanguage: yaml\n---\nkey: value\n",
+            )
+            .unwrap();
+
+            let note = glib::MainContext::new()
+                .block_on(Note::load(&gio::File::for_path(&path)))
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(note.metadata().language().as_deref(), Some("yaml"));
+            assert_eq!(note.buffer().language().unwrap().id(), "yaml");
+            assert!(note.is_saved());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn load_from_other_app() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir().join(format!(
+                "noteworthy-note-other-app-{}.md",
+                std::process::id()
+            ));
+            std::fs::write(&path, "---\ntitle: 123\nmodified: null\n---\nContent\n").unwrap();
+            let modified = std::fs::metadata(&path)
+                .unwrap()
+                .modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap();
+
+            let note = glib::MainContext::new()
+                .block_on(Note::load(&gio::File::for_path(&path)))
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(note.metadata().title(), "123");
+            assert_eq!(
+                note.metadata().last_modified(),
+                DateTime::from_timestamp(modified.as_secs() as i64)
+            );
+            let (start_iter, end_iter) = note.buffer().bounds();
+            assert_eq!(note.buffer().text(&start_iter, &end_iter, true), "Content");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn content_from_index_entry() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir().join(format!(
+                "noteworthy-note-index-entry-{}.md",
+                std::process::id()
+            ));
+            let text = "---\ntitle: Indexed\nis_pinned: true\n---\n# Heading\n\nContent\n";
+            std::fs::write(&path, text).unwrap();
+
+            let entry = IndexEntry {
+                stamp: FileStamp::for_metadata(&std::fs::metadata(&path).unwrap()).unwrap(),
+                body_offset: body_offset(text, &[]),
+                metadata: NoteData::parse(text).unwrap().metadata,
+            };
+            let note = Note::with_index_entry(&gio::File::for_path(&path), &entry);
+            assert_eq!(note.metadata().title(), "Indexed");
+            assert!(note.metadata().is_pinned());
+
+            // Read once it is used
+            let (start_iter, end_iter) = note.buffer().bounds();
+            let content = note.buffer().text(&start_iter, &end_iter, true);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(content, "# Heading\n\nContent");
+            assert!(note.is_saved());
+            assert!(!note.buffer().can_undo());
+            assert_eq!(note.saved_title(), "Indexed");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn unreadable_index_entry_content() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir().join(format!(
+                "noteworthy-note-unreadable-{}.md",
+                std::process::id()
+            ));
+            let text = "---\ntitle: Indexed\n---\n# Heading\n\nContent\n";
+            std::fs::write(&path, text).unwrap();
+
+            let entry = IndexEntry {
+                stamp: FileStamp::for_metadata(&std::fs::metadata(&path).unwrap()).unwrap(),
+                body_offset: body_offset(text, &[]),
+                metadata: NoteData::parse(text).unwrap().metadata,
+            };
+            let note = Note::with_index_entry(&gio::File::for_path(&path), &entry);
+
+            // Like when the disk is not mounted anymore
+            let moved_path = path.with_extension("moved");
+            std::fs::rename(&path, &moved_path).unwrap();
+            assert_eq!(note.buffer().char_count(), 0);
+
+            // A change of the metadata doesn't write the empty buffer over the content
+            let ctx = glib::MainContext::new();
+            note.metadata().set_is_pinned(true);
+            assert!(note.contents().is_err());
+            assert!(ctx.block_on(note.save()).is_err());
+            assert_eq!(note.save_state(), SaveState::Error);
+
+            std::fs::rename(&moved_path, &path).unwrap();
+            let res = ctx.block_on(note.save());
+            let saved = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            res.unwrap();
+
+            let note_data = NoteData::parse(&saved).unwrap();
+            assert!(note_data.metadata.is_pinned);
+            assert_eq!(note_data.content, "# Heading\n\nContent");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn reload() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir()
+                .join(format!("noteworthy-note-reload-{}.md", std::process::id()));
+            std::fs::write(&path, "---\ntitle: Before\n---\nBefore").unwrap();
+
+            let ctx = glib::MainContext::new();
+            let note = ctx
+                .block_on(Note::load(&gio::File::for_path(&path)))
+                .unwrap();
+
+            std::fs::write(&path, "---\ntitle: After\n---\nAfter").unwrap();
+            let res = ctx.block_on(note.reload());
+            std::fs::remove_file(&path).unwrap();
+            res.unwrap();
+
+            assert_eq!(note.metadata().title(), "After");
+            assert_eq!(note.saved_title(), "After");
+            let (start_iter, end_iter) = note.buffer().bounds();
+            assert_eq!(note.buffer().text(&start_iter, &end_iter, true), "After");
+            assert!(note.is_saved());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn problem_from_saves() {
+        run_on_gtk_thread(|| {
+            let dir = std::env::temp_dir()
+                .join(format!("noteworthy-note-problem-{}", std::process::id()));
+            // The folder is not created yet, so writing fails like on a full disk
+            let note = Note::with_file(&gio::File::for_path(dir.join("Note.md")));
+            assert_eq!(note.problem(), NoteProblem::None);
+
+            let problems = Rc::new(RefCell::new(Vec::new()));
+            note.connect_problem_notify(clone!(@strong problems => move |note| {
+                problems.borrow_mut().push(note.problem());
+            }));
+
+            let ctx = glib::MainContext::new();
+            note.buffer().set_text("Content");
+            assert!(ctx.block_on(note.save()).is_err());
+            assert!(matches!(note.problem(), NoteProblem::SaveFailed(_)));
+
+            // Still failing, as the changes are not saved yet
+            note.buffer().set_text("Changed");
+            assert!(matches!(note.problem(), NoteProblem::SaveFailed(_)));
+
+            std::fs::create_dir_all(&dir).unwrap();
+            let res = ctx.block_on(note.save());
+            std::fs::remove_dir_all(&dir).unwrap();
+            res.unwrap();
+
+            assert_eq!(note.problem(), NoteProblem::None);
+            let problems = problems.take();
+            assert_eq!(problems.len(), 2);
+            assert_eq!(problems[1], NoteProblem::None);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn problem_from_file_events() {
+        run_on_gtk_thread(|| {
+            let path = std::env::temp_dir()
+                .join(format!("noteworthy-note-problem-{}.md", std::process::id()));
+            let saved = "---\ntitle: Groceries\n---\nMilk";
+            std::fs::write(&path, saved).unwrap();
+
+            let ctx = glib::MainContext::new();
+            let note = ctx
+                .block_on(Note::load(&gio::File::for_path(&path)))
+                .unwrap();
+
+            // Like after its own save
+            note.check_file_contents(saved.as_bytes());
+            assert_eq!(note.problem(), NoteProblem::None);
+
+            // Changed by another app, even while there are changes that are not saved
+            note.buffer().set_text("Milk\nEggs");
+            note.check_file_contents(b"---\ntitle: Groceries\n---\nMilk\nBread");
+            assert_eq!(note.problem(), NoteProblem::ExternallyModified);
+
+            note.report_file_event(NoteFileEvent::Removed);
+            assert_eq!(note.problem(), NoteProblem::FileMissing);
+            note.check_file_contents(saved.as_bytes());
+            assert_eq!(note.problem(), NoteProblem::FileMissing);
+            note.report_file_event(NoteFileEvent::Created);
+            assert_eq!(note.problem(), NoteProblem::None);
+
+            std::fs::write(&path, "---\ntitle: Groceries\n---\nMilk\nBread").unwrap();
+            note.check_file_contents(&std::fs::read(&path).unwrap());
+            assert_eq!(note.problem(), NoteProblem::ExternallyModified);
+
+            let res = ctx.block_on(note.reload());
+            std::fs::remove_file(&path).unwrap();
+            res.unwrap();
+
+            assert_eq!(note.problem(), NoteProblem::None);
+            // The reloaded contents are the saved ones now
+            note.check_file_contents(b"---\ntitle: Groceries\n---\nMilk\nBread");
+            assert_eq!(note.problem(), NoteProblem::None);
+        });
+    }
+
+    #[test]
+    fn contents_hash_pieces() {
+        assert_eq!(
+            contents_hash([&b"---\n"[..], b"title: A\n---\n", b"", b"Content"]),
+            contents_hash([&b"---\ntitle: A\n---\nContent"[..]])
+        );
+        assert_ne!(
+            contents_hash([&b"Content"[..]]),
+            contents_hash([&b"Contents"[..]])
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn language() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("SELECT 1;");
+            assert_eq!(note.buffer().language().unwrap().id(), DEFAULT_LANGUAGE);
+
+            note.set_is_saved(true);
+            let last_modified = note.metadata().last_modified();
+
+            note.metadata().set_language(Some("sql"));
+            assert_eq!(note.buffer().language().unwrap().id(), "sql");
+            assert!(!note.is_saved());
+            assert_eq!(note.metadata().last_modified(), last_modified);
+
+            note.metadata().set_language(Some(PLAIN_TEXT_LANGUAGE));
+            assert!(note.buffer().language().is_none());
+
+            note.metadata().set_language(Some("not-a-language"));
+            assert_eq!(note.buffer().language().unwrap().id(), DEFAULT_LANGUAGE);
+
+            note.metadata().set_language(None);
+            assert_eq!(note.buffer().language().unwrap().id(), DEFAULT_LANGUAGE);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn language_kept_in_large_file_mode() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content("Small");
+            note.metadata().set_language(Some("yaml"));
+
+            note.buffer().set_text(&generated_content(LARGE_NOTE_SIZE));
+            assert!(note.buffer().language().is_none());
+
+            // Only highlighted once it is small again
+            note.metadata().set_language(Some("sql"));
+            assert!(note.buffer().language().is_none());
+
+            note.buffer().set_text("Small again");
+            note.check_size();
+            assert_eq!(note.buffer().language().unwrap().id(), "sql");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn large_file_mode_by_bytes() {
+        run_on_gtk_thread(|| {
+            // Fewer characters than the threshold, but more bytes
+            let content = "ü".repeat(LARGE_NOTE_SIZE / 2 + 1);
+            assert!(content.chars().count() < LARGE_NOTE_SIZE);
+
+            let note = note_with_content(&content);
+            assert!(!note.is_large());
+
+            note.check_size();
+            assert!(note.is_large());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn large_note_last_modified_held_back() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content(&generated_content(GENERATED_NOTE_SIZE));
+            let last_modified = note.metadata().last_modified();
+
+            note.buffer().insert_at_cursor("a");
+            assert_eq!(note.metadata().last_modified(), last_modified);
+            assert!(!note.is_saved());
+
+            note.check_size();
+            assert_ne!(note.metadata().last_modified(), last_modified);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn typing_in_large_note_speed() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content(&generated_content(GENERATED_NOTE_SIZE));
+            assert!(note.is_large());
+
+            let buffer = note.buffer();
+            buffer.place_cursor(&buffer.iter_at_offset(buffer.char_count() / 2));
+
+            for _ in 0..100 {
+                let start = Instant::now();
+                buffer.insert_at_cursor("a");
+                let elapsed = start.elapsed();
+
+                assert!(
+                    elapsed < frame_budget(),
+                    "Typing took {:?} in a note of {} bytes",
+                    elapsed,
+                    GENERATED_NOTE_SIZE
+                );
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn serialize_large_note_speed() {
+        run_on_gtk_thread(|| {
+            let note = note_with_content(&generated_content(GENERATED_NOTE_SIZE));
+
+            let start = Instant::now();
+            let as_whole = serialized_as_whole(&note);
+            let as_whole_elapsed = start.elapsed();
+
+            let start = Instant::now();
+            let in_chunks = serialized(&note);
+            let in_chunks_elapsed = start.elapsed();
+
+            assert_eq!(in_chunks, as_whole);
+
+            // Writing in chunks also writes to the stream, so it only has to be about as fast
+            assert!(
+                in_chunks_elapsed < as_whole_elapsed * 2 + frame_budget(),
+                "Serializing in chunks took {:?}, but as a whole {:?} for {} bytes",
+                in_chunks_elapsed,
+                as_whole_elapsed,
+                GENERATED_NOTE_SIZE
+            );
+        });
+    }
+}
@@ -1,5 +1,7 @@
 use std::{ffi::OsStr, path::Path};
 
+use crate::core::{note_id_from_uri, note_uri};
+
 // TODO optimize this (Reduce size of id in generating unique file name in utils.rs)
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct NoteId {
@@ -12,12 +14,41 @@ impl std::fmt::Debug for NoteId {
     }
 }
 
+impl std::fmt::Display for NoteId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.id.to_string_lossy())
+    }
+}
+
 impl NoteId {
     pub fn for_path(path: impl AsRef<Path>) -> Self {
         Self {
             id: Box::from(path.as_ref().file_stem().unwrap()),
         }
     }
+
+    /// Inverse of the [`Display`](std::fmt::Display) of an id, like one given by a script.
+    /// `None` if it can't be the id of a note.
+    pub fn parse(id: &str) -> Option<Self> {
+        if id.is_empty() || id.contains('/') || id == "." || id == ".." {
+            return None;
+        }
+
+        Some(Self {
+            id: Box::from(OsStr::new(id)),
+        })
+    }
+
+    /// The `noteworthy://note/` URI that opens the note from anywhere
+    pub fn uri(&self) -> String {
+        note_uri(&self.to_string())
+    }
+
+    /// Inverse of [`NoteId::uri`], like one opened from another app. `None` if it is not the
+    /// URI of a note.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        Self::parse(&note_id_from_uri(uri)?)
+    }
 }
 
 #[cfg(test)]
@@ -42,4 +73,26 @@ mod test {
         assert_eq!(hash_map.get(&id_1), Some(&1));
         assert_eq!(hash_map.get(&NoteId::for_path("Path2")), Some(&2));
     }
+
+    #[test]
+    fn parse() {
+        let id = NoteId::for_path("/notes/Note 0.md");
+        assert_eq!(id.to_string(), "Note 0");
+        assert_eq!(NoteId::parse(&id.to_string()), Some(id));
+
+        assert_eq!(NoteId::parse(""), None);
+        assert_eq!(NoteId::parse(".."), None);
+        assert_eq!(NoteId::parse("notes/Note 0"), None);
+    }
+
+    #[test]
+    fn uri() {
+        let id = NoteId::for_path("/notes/Note 0.md");
+        assert_eq!(id.uri(), "noteworthy://note/Note%200");
+        assert_eq!(NoteId::from_uri(&id.uri()), Some(id));
+
+        assert_eq!(NoteId::from_uri("noteworthy://note/.."), None);
+        assert_eq!(NoteId::from_uri("noteworthy://note/notes%2FNote%200"), None);
+        assert_eq!(NoteId::from_uri("file:///notes/Note%200.md"), None);
+    }
 }
@@ -0,0 +1,110 @@
+use chrono::{Local, NaiveDate};
+use gtk::prelude::*;
+
+use std::collections::HashSet;
+
+use super::{NoteMetadata, Tag};
+use crate::core;
+
+/// Boolean states of a note that filters check often
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoteFlags(u8);
+
+impl NoteFlags {
+    pub const TRASHED: Self = Self(1 << 0);
+    pub const PINNED: Self = Self(1 << 1);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn set(&mut self, other: Self, value: bool) {
+        if value {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+}
+
+/// What filters check on the metadata of a note, cached on the [`Note`](super::Note) so a
+/// filter does not go through its properties for every note each time it changes
+#[derive(Debug)]
+pub struct NoteIndex {
+    pub flags: NoteFlags,
+    pub tags: HashSet<Tag>,
+    /// Title in lowercase
+    pub title: String,
+    /// Day the note was last edited in the local timezone
+    pub last_modified_day: NaiveDate,
+}
+
+impl NoteIndex {
+    pub fn new(metadata: &NoteMetadata) -> Self {
+        let mut flags = NoteFlags::empty();
+        flags.set(NoteFlags::TRASHED, metadata.is_trashed());
+        flags.set(NoteFlags::PINNED, metadata.is_pinned());
+
+        let tag_list = metadata.tag_list();
+        let tags = (0..tag_list.n_items())
+            .filter_map(|position| tag_list.item(position))
+            .map(|item| item.downcast::<Tag>().unwrap())
+            .collect();
+
+        Self {
+            flags,
+            tags,
+            title: metadata.title().to_lowercase(),
+            last_modified_day: core::day_in(&metadata.last_modified().to_local(), &Local),
+        }
+    }
+
+    pub const fn is_trashed(&self) -> bool {
+        self.flags.contains(NoteFlags::TRASHED)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        let mut flags = NoteFlags::empty();
+        assert!(!flags.contains(NoteFlags::TRASHED));
+
+        flags.set(NoteFlags::TRASHED, true);
+        flags.set(NoteFlags::PINNED, true);
+        assert!(flags.contains(NoteFlags::TRASHED));
+        assert!(flags.contains(NoteFlags::PINNED));
+
+        flags.set(NoteFlags::TRASHED, false);
+        assert!(!flags.contains(NoteFlags::TRASHED));
+        assert!(flags.contains(NoteFlags::PINNED));
+    }
+
+    #[test]
+    fn new() {
+        let metadata = NoteMetadata::new();
+        metadata.set_title("A Title");
+        metadata.set_is_trashed(true);
+
+        let tag = Tag::new("A");
+        metadata.tag_list().append(tag.clone()).unwrap();
+
+        let index = NoteIndex::new(&metadata);
+        assert_eq!(index.title, "a title");
+        assert!(index.is_trashed());
+        assert!(!index.flags.contains(NoteFlags::PINNED));
+        assert!(index.tags.contains(&tag));
+        assert!(!index.tags.contains(&Tag::new("A")));
+        assert_eq!(
+            index.last_modified_day,
+            metadata.last_modified().to_local().date().naive_local()
+        );
+    }
+}
@@ -6,16 +6,18 @@ use gtk::{
 };
 use indexmap::IndexMap;
 
-use std::{
-    cell::{Cell, RefCell},
-    collections::HashSet,
-};
+use std::{cell::RefCell, collections::HashSet, path::Path};
 
-use super::{Note, NoteId, Tag};
-use crate::core::FileType;
+use super::{Note, NoteId};
+use crate::{
+    core::{load_progress, Exclusions, FileType, MetadataIndex, LOAD_BATCH_SIZE},
+    utils::or_cancelled,
+};
 
 mod imp {
     use super::*;
+    use glib::subclass::Signal;
+    use once_cell::sync::Lazy;
 
     #[derive(Debug, Default)]
     pub struct NoteList {
@@ -30,7 +32,19 @@ mod imp {
         type Interfaces = (gio::ListModel,);
     }
 
-    impl ObjectImpl for NoteList {}
+    impl ObjectImpl for NoteList {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder(
+                    "note-content-changed",
+                    &[Note::static_type().into()],
+                    <()>::static_type().into(),
+                )
+                .build()]
+            });
+            SIGNALS.as_ref()
+        }
+    }
 
     impl ListModelImpl for NoteList {
         fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
@@ -61,17 +75,32 @@ impl NoteList {
         glib::Object::new(&[]).expect("Failed to create NoteList.")
     }
 
-    /// Try load notes on `directory` with file type of markdown
-    pub async fn load_from_dir(directory: &gio::File) -> anyhow::Result<Self> {
-        let file_infos = directory
-            .enumerate_children_future(
+    /// Try load notes on `directory` with file type of markdown, except the ones matched by
+    /// `exclusions`, appending them in batches of [`LOAD_BATCH_SIZE`], so the first ones can be
+    /// shown before the rest are read. `on_progress` is called with the fraction of the notes
+    /// that are loaded. Once `cancellable` is cancelled, it fails without appending the rest.
+    /// The files that fail to load are passed to `on_failed` and skipped.
+    pub async fn load_from_dir(
+        &self,
+        directory: &gio::File,
+        exclusions: &Exclusions,
+        cancellable: &gio::Cancellable,
+        on_progress: impl Fn(f64),
+        on_failed: impl Fn(&gio::File, anyhow::Error),
+    ) -> anyhow::Result<()> {
+        on_progress(0.0);
+
+        let file_infos = or_cancelled(
+            cancellable,
+            directory.enumerate_children_future(
                 &gio::FILE_ATTRIBUTE_STANDARD_NAME,
                 gio::FileQueryInfoFlags::NONE,
                 glib::PRIORITY_HIGH_IDLE,
-            )
-            .await?;
+            ),
+        )
+        .await??;
 
-        let mut notes = Vec::new();
+        let mut files = Vec::new();
 
         for file_info in file_infos {
             let file_info = match file_info {
@@ -95,23 +124,64 @@ impl NoteList {
                 continue;
             }
 
+            if exclusions.is_excluded(&file_info.name(), false) {
+                log::info!("`{}` is excluded, skipping...", file.uri());
+                continue;
+            }
+
+            files.push(file);
+        }
+
+        let mut batch = Vec::with_capacity(LOAD_BATCH_SIZE);
+
+        for (index, file) in files.iter().enumerate() {
             log::info!("Loading `{}`", file.uri());
 
             // TODO consider using GtkSourceFile here
             // So we could use GtkSourceFileLoader and GtkSourceFileSaver to handle
             // saving and loading, and perhaps reduce allocations on serializing into buffer and
             // deserializiations.
-            let note = Note::load(&file).await?;
-            notes.push(note);
-        }
+            match Note::load(file).await {
+                Ok(note) => batch.push(note),
+                Err(err) => on_failed(file, err),
+            }
 
-        let note_list = NoteList::new();
+            // The notes loaded so far are dropped with the batch
+            cancellable.set_error_if_cancelled()?;
 
-        if !note_list.append_many(notes) {
-            log::warn!("Failed to append all notes");
+            let n_processed = index + 1;
+
+            if batch.len() == LOAD_BATCH_SIZE || n_processed == files.len() {
+                if !self.append_many(std::mem::take(&mut batch)) {
+                    log::warn!("Failed to append all notes");
+                }
+
+                on_progress(load_progress(n_processed, files.len()));
+            }
         }
 
-        Ok(note_list)
+        on_progress(1.0);
+
+        Ok(())
+    }
+
+    /// Append the notes in `index` of the notes in `directory`, except the ones matched by
+    /// `exclusions`, without reading their files. Their content is read once it is used.
+    pub fn load_from_index(
+        &self,
+        directory: &gio::File,
+        exclusions: &Exclusions,
+        index: &MetadataIndex,
+    ) {
+        let notes = index
+            .entries()
+            .filter(|(file_name, _)| !exclusions.is_excluded(Path::new(file_name), false))
+            .map(|(file_name, entry)| Note::with_index_entry(&directory.child(file_name), entry))
+            .collect();
+
+        if !self.append_many(notes) {
+            log::warn!("Failed to append all indexed notes");
+        }
     }
 
     /// If an equivalent [`Note`] already exists in the list, it returns false leaving the original
@@ -135,6 +205,7 @@ impl NoteList {
     /// This is more efficient than [`NoteList::append`] since it emits `items-changed` only once
     pub fn append_many(&self, notes: Vec<Note>) -> bool {
         let initial_notes_len = notes.len();
+        let n_items_before = self.n_items();
 
         let mut n_appended = 0;
 
@@ -147,7 +218,7 @@ impl NoteList {
         }
 
         if n_appended > 0 {
-            self.items_changed(self.n_items() - 1, 0, n_appended);
+            self.items_changed(n_items_before, 0, n_appended);
         }
 
         n_appended as usize == initial_notes_len
@@ -158,7 +229,7 @@ impl NoteList {
 
         imp.unsaved_notes.borrow_mut().remove(
             &self
-                .get(note_id)
+                .find_by_id(note_id)
                 .expect("Trying to remove a note that doesn't exist"),
         );
 
@@ -169,10 +240,66 @@ impl NoteList {
         }
     }
 
-    pub fn get(&self, note_id: &NoteId) -> Option<Note> {
+    /// Remove all notes where `f` returns false, emitting `items-changed` only once
+    pub fn retain(&self, mut f: impl FnMut(&Note) -> bool) {
+        let imp = self.imp();
+
+        // Call `f` on a snapshot, so it can access the list
+        let removed_notes = self.iter().filter(|note| !f(note)).collect::<Vec<_>>();
+
+        if removed_notes.is_empty() {
+            return;
+        }
+
+        let n_items_before = self.n_items();
+
+        {
+            let mut list = imp.list.borrow_mut();
+            let mut unsaved_notes = imp.unsaved_notes.borrow_mut();
+
+            for note in &removed_notes {
+                list.shift_remove(&note.id());
+                unsaved_notes.remove(note);
+            }
+        }
+
+        self.items_changed(0, n_items_before, self.n_items());
+    }
+
+    /// Point `note` to `file` after its file was moved there, keeping its position in the
+    /// list. Returns false when `note` is not in the list or another note is at `file`.
+    pub fn move_note(&self, note: &Note, file: &gio::File) -> bool {
+        let old_id = note.id();
+        let new_id = NoteId::for_path(&file.path().unwrap());
+
+        let position = match replace_key(&mut self.imp().list.borrow_mut(), &old_id, new_id) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        note.set_file(file);
+        self.items_changed(position as u32, 1, 1);
+
+        true
+    }
+
+    pub fn find_by_id(&self, note_id: &NoteId) -> Option<Note> {
         self.imp().list.borrow().get(note_id).cloned()
     }
 
+    /// Get the first note titled exactly `title`
+    pub fn find_by_title(&self, title: &str) -> Option<Note> {
+        self.iter().find(|note| note.metadata().title() == title)
+    }
+
+    pub fn len(&self) -> usize {
+        self.imp().list.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn get_index_of(&self, note_id: &NoteId) -> Option<usize> {
         self.imp().list.borrow().get_index_of(note_id)
     }
@@ -182,33 +309,58 @@ impl NoteList {
         self.imp().unsaved_notes.take()
     }
 
-    /// Remove tag on `TagList` of all `Note`s
-    pub fn remove_tag_on_all(&self, tag: &Tag) {
-        for note in self.iter() {
-            let note_tag_list = note.metadata().tag_list();
+    /// Mark `notes` taken with [`NoteList::take_unsaved_notes`] as unsaved again, as when they
+    /// failed to be saved
+    pub fn restore_unsaved_notes(&self, notes: impl IntoIterator<Item = Note>) {
+        self.imp().unsaved_notes.borrow_mut().extend(notes);
+    }
 
-            if let Err(err) = note_tag_list.remove(tag) {
-                log::warn!(
-                    "Failed to remove tag with name `{}` on `{}`: {:?}",
-                    tag.name(),
-                    note,
-                    err
-                );
-            }
-        }
+    pub fn unsaved_notes(&self) -> Vec<Note> {
+        self.imp().unsaved_notes.borrow().iter().cloned().collect()
+    }
+
+    /// Called once the content of a note of the list changed, like with
+    /// [`Note::connect_content_changed`]
+    pub fn connect_note_content_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, &Note) + 'static,
+    {
+        self.connect_local("note-content-changed", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            let note = values[1].get::<Note>().unwrap();
+            f(&obj, &note);
+            None
+        })
     }
 
+    /// Iterate over the notes as they are now. Changes to the list while iterating are not
+    /// reflected, so it is fine to call this while appending or removing notes.
     pub fn iter(&self) -> Iter {
-        Iter::new(self.clone())
+        let notes = self
+            .imp()
+            .list
+            .borrow()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        Iter {
+            notes: notes.into_iter(),
+        }
     }
 
     fn append_inner(&self, note: Note) -> bool {
         note.connect_metadata_changed(clone!(@weak self as obj => move |note| {
-            if let Some(position) = obj.get_index_of(note.id()) {
+            if let Some(position) = obj.get_index_of(&note.id()) {
                 obj.items_changed(position as u32, 1, 1);
             }
         }));
 
+        note.connect_content_changed(clone!(@weak self as obj => move |note| {
+            if obj.get_index_of(&note.id()).is_some() {
+                obj.emit_by_name::<()>("note-content-changed", &[note]);
+            }
+        }));
+
         note.connect_is_saved_notify(clone!(@weak self as obj => move |note| {
             let mut unsaved_notes = obj.imp().unsaved_notes.borrow_mut();
 
@@ -224,64 +376,270 @@ impl NoteList {
         self.imp()
             .list
             .borrow_mut()
-            .insert(note.id().clone(), note)
+            .insert(note.id(), note)
             .is_none()
     }
 }
 
-pub struct Iter {
-    model: NoteList,
-    i: Cell<u32>,
+/// Change the key of the value at `old_key` in `map` to `new_key`, keeping its position.
+/// Returns the position, or `None` when there is no value at `old_key` or there is one at
+/// `new_key` already.
+fn replace_key<V>(
+    map: &mut IndexMap<NoteId, V>,
+    old_key: &NoteId,
+    new_key: NoteId,
+) -> Option<usize> {
+    let position = match map.get_index_of(old_key) {
+        Some(position) if !map.contains_key(&new_key) => position,
+        _ => return None,
+    };
+
+    // Keys can't be changed in place, so it is rebuilt with the new key at the same position
+    *map = std::mem::take(map)
+        .into_iter()
+        .map(|(key, value)| {
+            if key == *old_key {
+                (new_key.clone(), value)
+            } else {
+                (key, value)
+            }
+        })
+        .collect();
+
+    Some(position)
 }
 
-impl Iter {
-    const fn new(model: NoteList) -> Self {
-        Self {
-            model,
-            i: Cell::new(0),
+impl std::iter::FromIterator<Note> for NoteList {
+    fn from_iter<I: IntoIterator<Item = Note>>(iter: I) -> Self {
+        let note_list = Self::new();
+
+        if !note_list.append_many(iter.into_iter().collect()) {
+            log::warn!("Some notes already exist, skipping them");
         }
+
+        note_list
     }
 }
 
+impl Default for NoteList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over the notes of a [`NoteList`] created by [`NoteList::iter`]
+#[derive(Debug)]
+pub struct Iter {
+    notes: std::vec::IntoIter<Note>,
+}
+
 impl Iterator for Iter {
     type Item = Note;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let index = self.i.get();
+        self.notes.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.notes.size_hint()
+    }
+}
 
-        let item = self.model.item(index);
-        self.i.set(index + 1);
-        item.map(|x| x.downcast::<Note>().unwrap())
+impl DoubleEndedIterator for Iter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.notes.next_back()
     }
 }
 
+impl ExactSizeIterator for Iter {}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test_harness::run_on_gtk_thread;
+
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn iter_snapshot() {
+        run_on_gtk_thread(|| {
+            let note_list = (0..3)
+                .map(|_| Note::new("/home/user"))
+                .collect::<NoteList>();
+            assert_eq!(note_list.len(), 3);
+
+            let mut n_iterated = 0;
+            for note in note_list.iter() {
+                // Appending while iterating must neither panic nor be iterated over
+                note_list.append(Note::new("/home/user"));
+                assert!(note_list.find_by_id(&note.id()).is_some());
+                n_iterated += 1;
+            }
+
+            assert_eq!(n_iterated, 3);
+            assert_eq!(note_list.len(), 6);
+            assert_eq!(note_list.iter().len(), 6);
+        });
+    }
 
     #[test]
-    fn remove_tag_on_all() {
-        // Gtk has to be initialized when a note is constructed since
-        // GtkSourceView requires it.
-        gtk::init().unwrap();
+    #[ignore = "needs a display"]
+    fn iter_order() {
+        run_on_gtk_thread(|| {
+            let notes = (0..3).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+            let note_list = notes.iter().cloned().collect::<NoteList>();
+
+            assert_eq!(note_list.iter().collect::<Vec<_>>(), notes);
+            assert_eq!(
+                note_list.iter().rev().collect::<Vec<_>>(),
+                notes.into_iter().rev().collect::<Vec<_>>()
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn move_note() {
+        run_on_gtk_thread(|| {
+            let notes = (0..3).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+            let note_list = notes.iter().cloned().collect::<NoteList>();
+            let moved = &notes[1];
+
+            let new_file = gio::File::for_path("/home/user/moved.md");
+            assert!(note_list.move_note(moved, &new_file));
+            assert_eq!(moved.file().path(), new_file.path());
+            assert_eq!(note_list.find_by_id(&moved.id()).as_ref(), Some(moved));
+            assert_eq!(note_list.len(), 3);
+            assert_eq!(note_list.iter().collect::<Vec<_>>(), notes);
+
+            // Not onto another note
+            assert!(!note_list.move_note(&notes[0], &new_file));
+            assert_ne!(notes[0].file().path(), new_file.path());
+        });
+    }
 
+    #[test]
+    fn replace_keys() {
+        let id = |name: &str| NoteId::for_path(format!("/home/user/{}.md", name));
+        let mut map = ["A", "B", "C"]
+            .into_iter()
+            .map(|name| (id(name), name))
+            .collect::<IndexMap<_, _>>();
+
+        assert_eq!(replace_key(&mut map, &id("B"), id("Moved")), Some(1));
+        assert_eq!(
+            map.iter()
+                .map(|(key, value)| (key.clone(), *value))
+                .collect::<Vec<_>>(),
+            [(id("A"), "A"), (id("Moved"), "B"), (id("C"), "C")]
+        );
+
+        // Not onto another value, nor from one that is not there
+        assert_eq!(replace_key(&mut map, &id("A"), id("C")), None);
+        assert_eq!(replace_key(&mut map, &id("B"), id("Other")), None);
+        assert_eq!(map.get(&id("A")), Some(&"A"));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn is_empty() {
         let note_list = NoteList::new();
-        let tag = Tag::new("A");
-
-        let note_1 = Note::new("/home/user");
-        let note_1_tag_list = note_1.metadata().tag_list();
-        note_1_tag_list.append(tag.clone()).unwrap();
-        assert!(note_1_tag_list.contains(&tag));
-        note_list.append(note_1);
-
-        let note_2 = Note::new("/home/user");
-        let note_2_tag_list = note_2.metadata().tag_list();
-        note_2_tag_list.append(tag.clone()).unwrap();
-        assert!(note_2_tag_list.contains(&tag));
-        note_list.append(note_2);
-
-        note_list.remove_tag_on_all(&tag);
-        assert!(!note_1_tag_list.contains(&tag));
-        assert!(!note_2_tag_list.contains(&tag));
+        assert!(note_list.is_empty());
+        assert_eq!(note_list.len(), 0);
+        assert_eq!(note_list.iter().next(), None);
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn find() {
+        run_on_gtk_thread(|| {
+            let note_1 = Note::new("/home/user");
+            note_1.metadata().set_title("Groceries");
+            let note_2 = Note::new("/home/user");
+            note_2.metadata().set_title("Chores");
+
+            let note_list = [note_1.clone(), note_2.clone()]
+                .into_iter()
+                .collect::<NoteList>();
+
+            assert_eq!(note_list.find_by_id(&note_2.id()), Some(note_2));
+            assert_eq!(note_list.find_by_title("Groceries"), Some(note_1));
+            assert_eq!(note_list.find_by_title("groceries"), None);
+            assert_eq!(
+                note_list.find_by_id(&NoteId::for_path("/home/user/Other.md")),
+                None
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn from_iter_skips_duplicates() {
+        run_on_gtk_thread(|| {
+            let note = Note::new("/home/user");
+            let note_list = [note.clone(), note].into_iter().collect::<NoteList>();
+            assert_eq!(note_list.len(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn retain() {
+        run_on_gtk_thread(|| {
+            let notes = (0..4).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+            notes[1].metadata().set_is_trashed(true);
+            notes[3].metadata().set_is_trashed(true);
+            let note_list = notes.iter().cloned().collect::<NoteList>();
+
+            let n_items_changed = Rc::new(Cell::new(0));
+            note_list.connect_items_changed(
+                clone!(@strong n_items_changed => move |_, position, removed, added| {
+                    assert_eq!((position, removed, added), (0, 4, 2));
+                    n_items_changed.set(n_items_changed.get() + 1);
+                }),
+            );
+
+            note_list.retain(|note| !note.metadata().is_trashed());
+            assert_eq!(n_items_changed.get(), 1);
+            assert_eq!(
+                note_list.iter().collect::<Vec<_>>(),
+                vec![notes[0].clone(), notes[2].clone()]
+            );
+
+            // Nothing removed, so nothing is emitted
+            note_list.retain(|_| true);
+            assert_eq!(n_items_changed.get(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn note_content_changed() {
+        run_on_gtk_thread(|| {
+            let note = Note::new("/home/user");
+            let note_list = NoteList::new();
+            note_list.append(note.clone());
+
+            let changed_notes = Rc::new(RefCell::new(Vec::new()));
+            note_list.connect_note_content_changed(
+                clone!(@strong changed_notes => move |_, note| {
+                    changed_notes.borrow_mut().push(note.clone());
+                }),
+            );
+
+            note.buffer().set_text("Milk");
+            note.flush_content_changed();
+            assert_eq!(*changed_notes.borrow(), [note.clone()]);
+
+            // Not in the list anymore
+            note_list.remove(&note.id());
+            note.buffer().set_text("Eggs");
+            note.flush_content_changed();
+            assert_eq!(changed_notes.borrow().len(), 1);
+        });
     }
 }
@@ -4,16 +4,19 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cell::RefCell;
 
 use crate::{
-    core::DateTime,
-    model::{AttachmentList, NoteTagList},
+    core::{
+        is_same_tag_name, DateTime, ExtraFields, MetadataData, NoteColor, NoteSortKey, Reminder,
+        PLAIN_TEXT_LANGUAGE,
+    },
+    model::{AttachmentList, NoteTagList, Tag},
+    Application,
 };
 
 mod imp {
     use super::*;
     use once_cell::sync::Lazy;
 
-    #[derive(Debug, Default, Serialize, Deserialize)]
-    #[serde(default)]
+    #[derive(Debug, Default)]
     pub struct NoteMetadataInner {
         pub title: String,
         pub tag_list: NoteTagList,
@@ -21,6 +24,12 @@ mod imp {
         pub last_modified: DateTime,
         pub is_pinned: bool,
         pub is_trashed: bool,
+        pub color: NoteColor,
+        pub reminder: Option<Reminder>,
+        pub gist_id: Option<String>,
+        pub language: Option<String>,
+        pub created: Option<DateTime>,
+        pub extra_fields: ExtraFields,
     }
 
     #[derive(Debug, Default)]
@@ -80,6 +89,49 @@ mod imp {
                         false,
                         glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
+                    glib::ParamSpecEnum::new(
+                        "color",
+                        "Color",
+                        "Color label of the note",
+                        NoteColor::static_type(),
+                        NoteColor::default() as i32,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoxed::new(
+                        "reminder",
+                        "Reminder",
+                        "When to remind about the note",
+                        Reminder::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "gist-id",
+                        "Gist Id",
+                        "Id of the gist the note was published to",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "language",
+                        "Language",
+                        "Id of the language the content is highlighted as",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoxed::new(
+                        "created",
+                        "Created",
+                        "When the note was created",
+                        DateTime::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoxed::new(
+                        "extra-fields",
+                        "Extra Fields",
+                        "Unknown fields of the front matter of the note",
+                        ExtraFields::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -117,6 +169,30 @@ mod imp {
                     let is_trashed = value.get().unwrap();
                     obj.set_is_trashed(is_trashed);
                 }
+                "color" => {
+                    let color = value.get().unwrap();
+                    obj.set_color(color);
+                }
+                "reminder" => {
+                    let reminder = value.get().unwrap();
+                    obj.set_reminder(reminder);
+                }
+                "gist-id" => {
+                    let gist_id: Option<String> = value.get().unwrap();
+                    obj.set_gist_id(gist_id.as_deref());
+                }
+                "language" => {
+                    let language: Option<String> = value.get().unwrap();
+                    obj.set_language(language.as_deref());
+                }
+                "created" => {
+                    let created = value.get().unwrap();
+                    obj.set_created(created);
+                }
+                "extra-fields" => {
+                    let extra_fields = value.get().unwrap();
+                    obj.set_extra_fields(extra_fields);
+                }
                 _ => unimplemented!(),
             }
         }
@@ -129,6 +205,12 @@ mod imp {
                 "last-modified" => obj.last_modified().to_value(),
                 "is-pinned" => obj.is_pinned().to_value(),
                 "is-trashed" => obj.is_trashed().to_value(),
+                "color" => obj.color().to_value(),
+                "reminder" => obj.reminder().to_value(),
+                "gist-id" => obj.gist_id().to_value(),
+                "language" => obj.language().to_value(),
+                "created" => obj.created().to_value(),
+                "extra-fields" => obj.extra_fields().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -224,10 +306,139 @@ impl NoteMetadata {
         self.imp().inner.borrow().is_trashed
     }
 
+    pub fn set_color(&self, color: NoteColor) {
+        if color == self.color() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().color = color;
+        self.notify("color");
+    }
+
+    pub fn color(&self) -> NoteColor {
+        self.imp().inner.borrow().color
+    }
+
+    pub fn set_reminder(&self, reminder: Option<Reminder>) {
+        if reminder == self.reminder() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().reminder = reminder;
+        self.notify("reminder");
+    }
+
+    pub fn reminder(&self) -> Option<Reminder> {
+        self.imp().inner.borrow().reminder
+    }
+
+    pub fn set_gist_id(&self, gist_id: Option<&str>) {
+        if gist_id == self.gist_id().as_deref() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().gist_id = gist_id.map(str::to_string);
+        self.notify("gist-id");
+    }
+
+    /// Id of the gist the note was last published to, so publishing it again updates it
+    pub fn gist_id(&self) -> Option<String> {
+        self.imp().inner.borrow().gist_id.clone()
+    }
+
+    pub fn set_language(&self, language: Option<&str>) {
+        if language == self.language().as_deref() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().language = language.map(str::to_string);
+        self.notify("language");
+    }
+
+    /// Id of the language the content is highlighted as, which is Markdown if it is `None`,
+    /// or [`PLAIN_TEXT_LANGUAGE`] for no highlighting
+    pub fn language(&self) -> Option<String> {
+        self.imp().inner.borrow().language.clone()
+    }
+
+    pub fn set_created(&self, created: Option<DateTime>) {
+        if created == self.created() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().created = created;
+        self.notify("created");
+    }
+
+    /// When the note was created, or `None` for notes created before it was recorded
+    pub fn created(&self) -> Option<DateTime> {
+        self.imp().inner.borrow().created
+    }
+
+    pub fn set_extra_fields(&self, extra_fields: ExtraFields) {
+        if extra_fields == self.extra_fields() {
+            return;
+        }
+
+        self.imp().inner.borrow_mut().extra_fields = extra_fields;
+        self.notify("extra-fields");
+    }
+
+    pub fn extra_fields(&self) -> ExtraFields {
+        self.imp().inner.borrow().extra_fields.clone()
+    }
+
     pub fn update_last_modified(&self) {
         self.set_last_modified(&DateTime::now());
     }
 
+    pub fn sort_key(&self) -> NoteSortKey {
+        let inner = self.imp().inner.borrow();
+
+        NoteSortKey {
+            is_pinned: inner.is_pinned,
+            last_modified: inner.last_modified,
+        }
+    }
+
+    pub fn from_data(data: &MetadataData) -> Self {
+        let metadata = Self::new();
+        metadata.imp().inner.replace(imp::NoteMetadataInner {
+            title: data.title.clone(),
+            tag_list: NoteTagList::from_names(&data.tag_list),
+            attachment_list: AttachmentList::from_data(&data.attachment_list),
+            last_modified: data.last_modified,
+            is_pinned: data.is_pinned,
+            is_trashed: data.is_trashed,
+            color: data.color,
+            reminder: data.reminder,
+            gist_id: data.gist_id.clone(),
+            language: data.language.clone(),
+            created: data.created,
+            extra_fields: data.extra_fields.clone(),
+        });
+        metadata
+    }
+
+    pub fn to_data(&self) -> MetadataData {
+        let inner = self.imp().inner.borrow();
+
+        MetadataData {
+            title: inner.title.clone(),
+            tag_list: inner.tag_list.names(),
+            attachment_list: inner.attachment_list.to_data(),
+            last_modified: inner.last_modified,
+            is_pinned: inner.is_pinned,
+            is_trashed: inner.is_trashed,
+            color: inner.color,
+            reminder: inner.reminder,
+            gist_id: inner.gist_id.clone(),
+            language: inner.language.clone(),
+            created: inner.created,
+            extra_fields: inner.extra_fields.clone(),
+        }
+    }
+
     pub fn update(&self, other: &Self) {
         self.set_title(&other.title());
         self.set_tag_list(other.tag_list());
@@ -235,23 +446,119 @@ impl NoteMetadata {
         self.set_last_modified(&other.last_modified());
         self.set_is_pinned(other.is_pinned());
         self.set_is_trashed(other.is_trashed());
+        self.set_color(other.color());
+        self.set_reminder(other.reminder());
+        self.set_gist_id(other.gist_id().as_deref());
+        self.set_language(other.language().as_deref());
+        self.set_created(other.created());
+        self.set_extra_fields(other.extra_fields());
+    }
+
+    /// Write the fields of `data` that are different from `previous`, which is what it was
+    /// edited from. Fields changed elsewhere since then, like pinning the note, are kept.
+    ///
+    /// Unlike [`NoteMetadata::update`], the tags are changed in the current tag list, and new
+    /// tag names are added to the session's tags.
+    pub fn apply_data(&self, data: &MetadataData, previous: &MetadataData) {
+        if data.title != previous.title {
+            self.set_title(&data.title);
+        }
+
+        if data.tag_list != previous.tag_list {
+            self.apply_tag_names(&data.tag_list);
+        }
+
+        if data.attachment_list != previous.attachment_list {
+            self.set_attachment_list(AttachmentList::from_data(&data.attachment_list));
+        }
+
+        // After the title, as changing it also updates this
+        if data.last_modified != previous.last_modified {
+            self.set_last_modified(&data.last_modified);
+        }
+
+        if data.is_pinned != previous.is_pinned {
+            self.set_is_pinned(data.is_pinned);
+        }
+
+        if data.is_trashed != previous.is_trashed {
+            self.set_is_trashed(data.is_trashed);
+        }
+
+        if data.color != previous.color {
+            self.set_color(data.color);
+        }
+
+        if data.reminder != previous.reminder {
+            self.set_reminder(data.reminder);
+        }
+
+        if data.gist_id != previous.gist_id {
+            self.set_gist_id(data.gist_id.as_deref());
+        }
+
+        if data.language != previous.language {
+            self.set_language(data.language.as_deref());
+        }
+
+        if data.created != previous.created {
+            self.set_created(data.created);
+        }
+
+        if data.extra_fields != previous.extra_fields {
+            self.set_extra_fields(data.extra_fields.clone());
+        }
+    }
+
+    fn apply_tag_names(&self, names: &[String]) {
+        let tag_list = self.tag_list();
+        let session_tag_list = Application::default().session().note_manager().tag_list();
+
+        let tags = (0..tag_list.n_items())
+            .filter_map(|position| tag_list.item(position))
+            .map(|item| item.downcast::<Tag>().unwrap())
+            .collect::<Vec<_>>();
+
+        for tag in tags {
+            if !names.iter().any(|name| is_same_tag_name(name, &tag.name())) {
+                if let Err(err) = tag_list.remove(&tag) {
+                    log::warn!("Error removing a tag, skipping: {:?}", err);
+                }
+            }
+        }
+
+        for name in names {
+            let tag = match session_tag_list.get_with_name(name) {
+                Some(tag) => tag,
+                None => {
+                    let tag = Tag::new(name);
+                    if let Err(err) = session_tag_list.append(tag.clone()) {
+                        log::warn!("Error creating tag `{}`, skipping: {:?}", name, err);
+                        continue;
+                    }
+                    tag
+                }
+            };
+
+            if !tag_list.contains(&tag) {
+                if let Err(err) = tag_list.append(tag) {
+                    log::warn!("Error appending a tag, skipping: {:?}", err);
+                }
+            }
+        }
     }
 }
 
 impl Serialize for NoteMetadata {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.imp().inner.serialize(serializer)
+        self.to_data().serialize(serializer)
     }
 }
 
 impl<'de> Deserialize<'de> for NoteMetadata {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let inner = imp::NoteMetadataInner::deserialize(deserializer)?;
-
-        let metadata = Self::new();
-        metadata.imp().inner.replace(inner);
-
-        Ok(metadata)
+        let data = MetadataData::deserialize(deserializer)?;
+        Ok(Self::from_data(&data))
     }
 }
 
@@ -264,7 +571,7 @@ impl Default for NoteMetadata {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::model::{Attachment, Tag};
+    use crate::model::Attachment;
     use gtk::gio;
 
     #[test]
@@ -358,6 +665,109 @@ mod test {
         assert!(metadata.is_trashed());
     }
 
+    #[test]
+    fn color() {
+        let metadata = NoteMetadata::new();
+        assert_eq!(metadata.color(), NoteColor::None);
+        metadata.set_color(NoteColor::Red);
+        assert_eq!(metadata.color(), NoteColor::Red);
+        assert_eq!(metadata.to_data().color, NoteColor::Red);
+    }
+
+    #[test]
+    fn color_does_not_affect_sort_key() {
+        let metadata = NoteMetadata::new();
+        let sort_key = metadata.sort_key();
+        metadata.set_color(NoteColor::Green);
+        assert_eq!(metadata.sort_key(), sort_key);
+    }
+
+    #[test]
+    fn reminder() {
+        let metadata = NoteMetadata::new();
+        assert_eq!(metadata.reminder(), None);
+
+        let reminder = Reminder::new(chrono::Utc::now());
+        metadata.set_reminder(Some(reminder));
+        assert_eq!(metadata.reminder(), Some(reminder));
+        assert_eq!(metadata.to_data().reminder, Some(reminder));
+
+        metadata.set_reminder(None);
+        assert_eq!(metadata.reminder(), None);
+    }
+
+    #[test]
+    fn gist_id() {
+        let metadata = NoteMetadata::new();
+        assert_eq!(metadata.gist_id(), None);
+
+        metadata.set_gist_id(Some("aa5a315d61ae9438b18d"));
+        assert_eq!(metadata.gist_id().as_deref(), Some("aa5a315d61ae9438b18d"));
+        assert_eq!(
+            metadata.to_data().gist_id.as_deref(),
+            Some("aa5a315d61ae9438b18d")
+        );
+
+        metadata.set_gist_id(None);
+        assert_eq!(metadata.gist_id(), None);
+    }
+
+    #[test]
+    fn language() {
+        let metadata = NoteMetadata::new();
+        assert_eq!(metadata.language(), None);
+
+        let last_modified = metadata.last_modified();
+        metadata.set_language(Some("yaml"));
+        assert_eq!(metadata.language().as_deref(), Some("yaml"));
+        assert_eq!(metadata.to_data().language.as_deref(), Some("yaml"));
+        assert_eq!(metadata.last_modified(), last_modified);
+
+        metadata.set_language(Some(PLAIN_TEXT_LANGUAGE));
+        let data = metadata.to_data();
+        assert_eq!(
+            NoteMetadata::from_data(&data).language().as_deref(),
+            Some(PLAIN_TEXT_LANGUAGE)
+        );
+
+        metadata.set_language(None);
+        assert_eq!(metadata.language(), None);
+    }
+
+    #[test]
+    fn extra_fields() {
+        let metadata = NoteMetadata::new();
+        assert!(metadata.extra_fields().0.is_empty());
+
+        let mut mapping = serde_yaml::Mapping::new();
+        mapping.insert("author".into(), "Someone".into());
+        metadata.set_extra_fields(ExtraFields(mapping.clone()));
+        assert_eq!(metadata.extra_fields().0, mapping);
+
+        let data = metadata.to_data();
+        assert_eq!(data.extra_fields.0, mapping);
+        assert_eq!(NoteMetadata::from_data(&data).extra_fields().0, mapping);
+    }
+
+    #[test]
+    fn apply_data_keeps_other_changes() {
+        let metadata = NoteMetadata::new();
+        metadata.set_title("Title");
+        let previous = metadata.to_data();
+
+        // Changed after the data was edited
+        metadata.set_is_pinned(true);
+
+        let mut data = previous.clone();
+        data.title = "New Title".to_string();
+        data.color = NoteColor::Green;
+        metadata.apply_data(&data, &previous);
+
+        assert_eq!(metadata.title(), "New Title");
+        assert_eq!(metadata.color(), NoteColor::Green);
+        assert!(metadata.is_pinned());
+    }
+
     #[test]
     fn update() {
         let metadata = NoteMetadata::new();
@@ -386,6 +796,11 @@ mod test {
         other_metadata.set_last_modified(&DateTime::now());
         other_metadata.set_is_pinned(true);
         other_metadata.set_is_trashed(true);
+        other_metadata.set_color(NoteColor::Blue);
+        other_metadata.set_reminder(Some(Reminder::new(chrono::Utc::now())));
+        other_metadata.set_gist_id(Some("aa5a315d61ae9438b18d"));
+        other_metadata.set_language(Some("yaml"));
+        other_metadata.set_created(Some(DateTime::now()));
 
         metadata.update(&other_metadata);
         assert_eq!(metadata.title(), other_metadata.title());
@@ -396,5 +811,10 @@ mod test {
         assert_eq!(metadata.last_modified(), other_metadata.last_modified());
         assert_eq!(metadata.is_pinned(), other_metadata.is_pinned());
         assert_eq!(metadata.is_trashed(), other_metadata.is_trashed());
+        assert_eq!(metadata.color(), other_metadata.color());
+        assert_eq!(metadata.reminder(), other_metadata.reminder());
+        assert_eq!(metadata.gist_id(), other_metadata.gist_id());
+        assert_eq!(metadata.language(), other_metadata.language());
+        assert_eq!(metadata.created(), other_metadata.created());
     }
 }
@@ -0,0 +1,207 @@
+use gtk::glib;
+
+/// What is wrong with the file of a note, which the user is told about while it is shown
+#[derive(Debug, Clone, PartialEq, Eq, glib::Boxed)]
+#[boxed_type(name = "NwtyNoteProblem")]
+pub enum NoteProblem {
+    None,
+    /// The file was deleted or moved out of the notes, so the note is only in memory
+    FileMissing,
+    /// The last save failed for the given reason
+    SaveFailed(String),
+    /// The file was changed elsewhere since it was last loaded or saved
+    ExternallyModified,
+}
+
+impl Default for NoteProblem {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// What happened to the file of a note, reported by the file monitor or the save pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoteFileEvent {
+    /// The file was deleted or moved out of the notes
+    Removed,
+    /// The file is there again, like after it was restored or moved back
+    Created,
+    /// The file was changed elsewhere, `is_different` telling whether it is not what was last
+    /// loaded or saved anymore
+    Changed {
+        is_different: bool,
+    },
+    Saved,
+    SaveFailed(String),
+    /// The file was loaded again, which drops the changes that were not saved
+    Reloaded,
+}
+
+impl NoteProblem {
+    pub fn is_none(&self) -> bool {
+        *self == Self::None
+    }
+
+    /// Whether the user has to decide what happens to the file before the note is saved over
+    /// it, as saving would recreate it or drop the changes made elsewhere
+    pub fn needs_decision(&self) -> bool {
+        matches!(self, Self::FileMissing | Self::ExternallyModified)
+    }
+
+    /// The problem once `event` happened
+    ///
+    /// A missing file is only solved by the file being there again, as nothing else can be
+    /// done about the note before that.
+    pub fn after(&self, event: NoteFileEvent) -> Self {
+        match (self, event) {
+            (_, NoteFileEvent::Removed) => Self::FileMissing,
+            (_, NoteFileEvent::Saved | NoteFileEvent::Reloaded) => Self::None,
+            (Self::FileMissing, NoteFileEvent::Created) => Self::None,
+            (Self::FileMissing, _) => Self::FileMissing,
+            (_, NoteFileEvent::SaveFailed(message)) => Self::SaveFailed(message),
+            (Self::None, NoteFileEvent::Changed { is_different: true }) => Self::ExternallyModified,
+            (
+                Self::ExternallyModified,
+                NoteFileEvent::Changed {
+                    is_different: false,
+                },
+            ) => Self::None,
+            (problem, _) => problem.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn after_all(events: impl IntoIterator<Item = NoteFileEvent>) -> NoteProblem {
+        events
+            .into_iter()
+            .fold(NoteProblem::None, |problem, event| problem.after(event))
+    }
+
+    #[test]
+    fn file_missing() {
+        assert_eq!(
+            after_all([NoteFileEvent::Removed]),
+            NoteProblem::FileMissing
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Removed,
+                NoteFileEvent::SaveFailed("Permission denied".into()),
+                NoteFileEvent::Changed { is_different: true },
+            ]),
+            NoteProblem::FileMissing
+        );
+        assert_eq!(
+            after_all([NoteFileEvent::Removed, NoteFileEvent::Created]),
+            NoteProblem::None
+        );
+        // Recreated from the buffer
+        assert_eq!(
+            after_all([NoteFileEvent::Removed, NoteFileEvent::Saved]),
+            NoteProblem::None
+        );
+    }
+
+    #[test]
+    fn save_failed() {
+        assert_eq!(
+            after_all([NoteFileEvent::SaveFailed("Disk is full".into())]),
+            NoteProblem::SaveFailed("Disk is full".into())
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::SaveFailed("Disk is full".into()),
+                NoteFileEvent::SaveFailed("Permission denied".into()),
+            ]),
+            NoteProblem::SaveFailed("Permission denied".into())
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::SaveFailed("Disk is full".into()),
+                NoteFileEvent::Changed { is_different: true },
+            ]),
+            NoteProblem::SaveFailed("Disk is full".into())
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::SaveFailed("Disk is full".into()),
+                NoteFileEvent::Saved,
+            ]),
+            NoteProblem::None
+        );
+        assert_eq!(
+            after_all([NoteFileEvent::Removed, NoteFileEvent::SaveFailed("".into())]),
+            NoteProblem::FileMissing
+        );
+    }
+
+    #[test]
+    fn externally_modified() {
+        assert_eq!(
+            after_all([NoteFileEvent::Changed {
+                is_different: false
+            }]),
+            NoteProblem::None
+        );
+        assert_eq!(
+            after_all([NoteFileEvent::Changed { is_different: true }]),
+            NoteProblem::ExternallyModified
+        );
+        // Changed back to what was saved
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Changed { is_different: true },
+                NoteFileEvent::Changed {
+                    is_different: false
+                },
+            ]),
+            NoteProblem::None
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Changed { is_different: true },
+                NoteFileEvent::Reloaded,
+            ]),
+            NoteProblem::None
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Changed { is_different: true },
+                NoteFileEvent::SaveFailed("Disk is full".into()),
+            ]),
+            NoteProblem::SaveFailed("Disk is full".into())
+        );
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Changed { is_different: true },
+                NoteFileEvent::Removed,
+            ]),
+            NoteProblem::FileMissing
+        );
+    }
+
+    #[test]
+    fn needs_decision() {
+        assert!(!NoteProblem::None.needs_decision());
+        assert!(NoteProblem::FileMissing.needs_decision());
+        // Saving again is the way to solve it
+        assert!(!NoteProblem::SaveFailed("Disk is full".into()).needs_decision());
+        assert!(NoteProblem::ExternallyModified.needs_decision());
+    }
+
+    #[test]
+    fn created_without_problem() {
+        assert_eq!(after_all([NoteFileEvent::Created]), NoteProblem::None);
+        assert_eq!(
+            after_all([
+                NoteFileEvent::Changed { is_different: true },
+                NoteFileEvent::Created,
+            ]),
+            NoteProblem::ExternallyModified
+        );
+    }
+}
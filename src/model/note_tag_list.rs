@@ -94,6 +94,31 @@ impl NoteTagList {
         self.imp().list.borrow().is_empty()
     }
 
+    /// Create a list from tag names, reusing the tags of the session's `TagList`
+    pub fn from_names(names: &[String]) -> Self {
+        let app = Application::default();
+        let tag_list = app.session().note_manager().tag_list();
+
+        let new_tag_list = Self::new();
+
+        for name in names {
+            let tag = tag_list.get_with_name(name).unwrap_or_else(|| {
+                log::error!("Tag with name `{}` not found, Creating new instead", name);
+                Tag::new(name)
+            });
+
+            if let Err(err) = new_tag_list.append(tag) {
+                log::warn!("Error appending a tag, skipping: {:?}", err);
+            }
+        }
+
+        new_tag_list
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.imp().list.borrow().iter().map(Tag::name).collect()
+    }
+
     fn get_index_of(&self, tag: &Tag) -> Option<usize> {
         self.imp().list.borrow().get_index_of(tag)
     }
@@ -109,20 +134,7 @@ impl Serialize for NoteTagList {
 impl<'de> Deserialize<'de> for NoteTagList {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let tag_name_list: Vec<String> = Vec::deserialize(deserializer)?;
-
-        let app = Application::default();
-        let tag_list = app.main_window().session().note_manager().tag_list();
-
-        let new_tag_list = Self::new();
-        for name in tag_name_list {
-            let tag = tag_list.get_with_name(&name).unwrap_or_else(|| {
-                log::error!("Tag with name `{}` not found, Creating new instead", &name);
-                Tag::new(&name)
-            });
-            new_tag_list.append(tag).unwrap();
-        }
-
-        Ok(new_tag_list)
+        Ok(Self::from_names(&tag_name_list))
     }
 }
 
@@ -0,0 +1,396 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use std::cell::RefCell;
+
+use crate::core::{
+    DateTime, FrontMatterProfile, NoteLinkStyle, NotebookSettingsData, NOTEBOOK_SETTINGS_VERSION,
+};
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct NotebookSettings {
+        pub data: RefCell<NotebookSettingsData>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NotebookSettings {
+        const NAME: &'static str = "NwtyNotebookSettings";
+        type Type = super::NotebookSettings;
+    }
+
+    impl ObjectImpl for NotebookSettings {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecString::new(
+                        "note-file-name",
+                        "Note File Name",
+                        "Name of the files of new notes",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "note-template",
+                        "Note Template",
+                        "Content of new notes",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecEnum::new(
+                        "front-matter-profile",
+                        "Front Matter Profile",
+                        "How the front matter of the notes is written",
+                        FrontMatterProfile::static_type(),
+                        FrontMatterProfile::default() as i32,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "daily-word-goal",
+                        "Daily Word Goal",
+                        "Words to write each day, or 0 for no goal",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "derive-title-from-heading",
+                        "Derive Title From Heading",
+                        "Whether the title of each note is its first heading",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "markdown-note-links",
+                        "Markdown Note Links",
+                        "Whether pasted links to notes are Markdown links",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "note-file-name" => {
+                    let note_file_name = value.get().unwrap();
+                    obj.set_note_file_name(note_file_name);
+                }
+                "note-template" => {
+                    let note_template = value.get().unwrap();
+                    obj.set_note_template(note_template);
+                }
+                "front-matter-profile" => {
+                    let front_matter_profile = value.get().unwrap();
+                    obj.set_front_matter_profile(front_matter_profile);
+                }
+                "daily-word-goal" => {
+                    let daily_word_goal = value.get().unwrap();
+                    obj.set_daily_word_goal(daily_word_goal);
+                }
+                "derive-title-from-heading" => {
+                    let derive_title_from_heading = value.get().unwrap();
+                    obj.set_derive_title_from_heading(derive_title_from_heading);
+                }
+                "markdown-note-links" => {
+                    let markdown_note_links = value.get().unwrap();
+                    obj.set_markdown_note_links(markdown_note_links);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "note-file-name" => obj.note_file_name().to_value(),
+                "note-template" => obj.note_template().to_value(),
+                "front-matter-profile" => obj.front_matter_profile().to_value(),
+                "daily-word-goal" => obj.daily_word_goal().to_value(),
+                "derive-title-from-heading" => obj.derive_title_from_heading().to_value(),
+                "markdown-note-links" => obj.markdown_note_links().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Settings that are stored with the notes, so they are the same on every device the
+    /// notebook is synced to
+    pub struct NotebookSettings(ObjectSubclass<imp::NotebookSettings>);
+}
+
+impl NotebookSettings {
+    pub fn from_data(data: &NotebookSettingsData) -> Self {
+        let settings: Self = glib::Object::new(&[]).expect("Failed to create NotebookSettings.");
+        settings.imp().data.replace(data.clone());
+        settings
+    }
+
+    pub fn to_data(&self) -> NotebookSettingsData {
+        let mut data = self.imp().data.borrow().clone();
+        // Keep the version of newer apps, so they know their settings may be outdated
+        data.version = data.version.max(NOTEBOOK_SETTINGS_VERSION);
+        data
+    }
+
+    /// Replace the settings with `data`, like when they were changed on another device
+    pub fn update(&self, data: &NotebookSettingsData) {
+        let old_data = self.imp().data.replace(data.clone());
+
+        if old_data.note_file_name != data.note_file_name {
+            self.notify("note-file-name");
+        }
+
+        if old_data.note_template != data.note_template {
+            self.notify("note-template");
+        }
+
+        if old_data.front_matter_profile != data.front_matter_profile {
+            self.notify("front-matter-profile");
+        }
+
+        if old_data.daily_word_goal != data.daily_word_goal {
+            self.notify("daily-word-goal");
+        }
+
+        if old_data.derive_title_from_heading != data.derive_title_from_heading {
+            self.notify("derive-title-from-heading");
+        }
+
+        if old_data.markdown_note_links != data.markdown_note_links {
+            self.notify("markdown-note-links");
+        }
+    }
+
+    pub fn set_note_file_name(&self, note_file_name: &str) {
+        if note_file_name == self.note_file_name() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().note_file_name = note_file_name.to_string();
+        self.notify("note-file-name");
+
+        self.update_modified();
+    }
+
+    pub fn note_file_name(&self) -> String {
+        self.imp().data.borrow().note_file_name.clone()
+    }
+
+    /// Name for the file of a new note, before the time that makes it unique
+    pub fn note_file_stem(&self) -> String {
+        self.imp().data.borrow().note_file_stem()
+    }
+
+    pub fn set_note_template(&self, note_template: &str) {
+        if note_template == self.note_template() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().note_template = note_template.to_string();
+        self.notify("note-template");
+
+        self.update_modified();
+    }
+
+    pub fn note_template(&self) -> String {
+        self.imp().data.borrow().note_template.clone()
+    }
+
+    pub fn set_front_matter_profile(&self, front_matter_profile: FrontMatterProfile) {
+        if front_matter_profile == self.front_matter_profile() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().front_matter_profile = front_matter_profile;
+        self.notify("front-matter-profile");
+
+        self.update_modified();
+    }
+
+    /// How the front matter of the notes is written when they are saved. Notes are read in
+    /// either profile, so the ones saved before it was changed keep working.
+    pub fn front_matter_profile(&self) -> FrontMatterProfile {
+        self.imp().data.borrow().front_matter_profile
+    }
+
+    pub fn connect_front_matter_profile_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("front-matter-profile"), move |obj, _| f(obj))
+    }
+
+    pub fn set_daily_word_goal(&self, daily_word_goal: u32) {
+        if daily_word_goal == self.daily_word_goal() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().daily_word_goal = daily_word_goal;
+        self.notify("daily-word-goal");
+
+        self.update_modified();
+    }
+
+    /// Words to write in the notebook each day, or `0` when there is no goal
+    pub fn daily_word_goal(&self) -> u32 {
+        self.imp().data.borrow().daily_word_goal
+    }
+
+    pub fn set_derive_title_from_heading(&self, derive_title_from_heading: bool) {
+        if derive_title_from_heading == self.derive_title_from_heading() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().derive_title_from_heading = derive_title_from_heading;
+        self.notify("derive-title-from-heading");
+
+        self.update_modified();
+    }
+
+    /// Whether the title of each note follows its first level 1 heading, instead of being
+    /// edited on its own
+    pub fn derive_title_from_heading(&self) -> bool {
+        self.imp().data.borrow().derive_title_from_heading
+    }
+
+    pub fn connect_derive_title_from_heading_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("derive-title-from-heading"), move |obj, _| f(obj))
+    }
+
+    pub fn set_markdown_note_links(&self, markdown_note_links: bool) {
+        if markdown_note_links == self.markdown_note_links() {
+            return;
+        }
+
+        self.imp().data.borrow_mut().markdown_note_links = markdown_note_links;
+        self.notify("markdown-note-links");
+
+        self.update_modified();
+    }
+
+    pub fn markdown_note_links(&self) -> bool {
+        self.imp().data.borrow().markdown_note_links
+    }
+
+    /// How pasted links to notes are written, as wiki links to their titles or as Markdown
+    /// links to their files
+    pub fn note_link_style(&self) -> NoteLinkStyle {
+        self.imp().data.borrow().note_link_style()
+    }
+
+    fn update_modified(&self) {
+        self.imp().data.borrow_mut().modified = Some(DateTime::now());
+    }
+}
+
+impl Default for NotebookSettings {
+    fn default() -> Self {
+        Self::from_data(&NotebookSettingsData::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn set_marks_modified() {
+        let settings = NotebookSettings::default();
+        assert_eq!(settings.to_data().modified, None);
+
+        settings.set_note_file_name("Journal");
+        assert_eq!(settings.note_file_name(), "Journal");
+
+        let modified = settings.to_data().modified;
+        assert!(modified.is_some());
+
+        // Setting the same value is not a change
+        settings.set_note_file_name("Journal");
+        assert_eq!(settings.to_data().modified, modified);
+
+        settings.set_note_template("# Today");
+        assert!(settings.to_data().modified > modified);
+
+        let modified = settings.to_data().modified;
+        settings.set_front_matter_profile(FrontMatterProfile::StandardMarkdown);
+        assert_eq!(
+            settings.to_data().front_matter_profile,
+            FrontMatterProfile::StandardMarkdown
+        );
+        assert!(settings.to_data().modified > modified);
+
+        let modified = settings.to_data().modified;
+        settings.set_daily_word_goal(1000);
+        assert_eq!(settings.to_data().daily_word_goal, 1000);
+        assert!(settings.to_data().modified > modified);
+
+        let modified = settings.to_data().modified;
+        settings.set_derive_title_from_heading(true);
+        assert!(settings.to_data().derive_title_from_heading);
+        assert!(settings.to_data().modified > modified);
+
+        let modified = settings.to_data().modified;
+        settings.set_markdown_note_links(true);
+        assert_eq!(settings.note_link_style(), NoteLinkStyle::Markdown);
+        assert!(settings.to_data().modified > modified);
+    }
+
+    #[test]
+    fn update() {
+        let settings = NotebookSettings::default();
+
+        let n_notifies = Rc::new(Cell::new(0));
+        settings.connect_notify_local(
+            None,
+            glib::clone!(@strong n_notifies => move |_, _| {
+                n_notifies.set(n_notifies.get() + 1);
+            }),
+        );
+
+        let data = NotebookSettingsData {
+            note_template: "# Today".to_string(),
+            modified: Some(DateTime::now()),
+            ..NotebookSettingsData::default()
+        };
+        settings.update(&data);
+
+        assert_eq!(settings.note_template(), "# Today");
+        assert_eq!(n_notifies.get(), 1);
+
+        // The time of the other change is kept
+        assert_eq!(settings.to_data().modified, data.modified);
+    }
+
+    #[test]
+    fn version() {
+        let settings = NotebookSettings::from_data(&NotebookSettingsData::default());
+        assert_eq!(settings.to_data().version, NOTEBOOK_SETTINGS_VERSION);
+
+        let newer = NotebookSettingsData {
+            version: NOTEBOOK_SETTINGS_VERSION + 1,
+            ..NotebookSettingsData::default()
+        };
+        assert_eq!(
+            NotebookSettings::from_data(&newer).to_data().version,
+            NOTEBOOK_SETTINGS_VERSION + 1
+        );
+    }
+}
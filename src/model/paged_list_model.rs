@@ -0,0 +1,264 @@
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::Cell;
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct PagedListModel {
+        pub model: OnceCell<gio::ListModel>,
+        pub slice_model: OnceCell<gtk::SliceListModel>,
+        pub page_size: Cell<Option<u32>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PagedListModel {
+        const NAME: &'static str = "NwtyPagedListModel";
+        type Type = super::PagedListModel;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for PagedListModel {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecUInt::new(
+                    "n-total",
+                    "Number of Total",
+                    "Number of items of the model, including the ones not shown yet",
+                    0,
+                    u32::MAX,
+                    0,
+                    glib::ParamFlags::READABLE,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "n-total" => obj.n_total().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+
+    impl ListModelImpl for PagedListModel {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            glib::Object::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.slice_model.get().unwrap().n_items()
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.slice_model.get().unwrap().item(position)
+        }
+    }
+}
+
+glib::wrapper! {
+    /// The first pages of the items of a model, so long lists don't have to create a row for
+    /// each item. All the items are shown until a page size is set.
+    pub struct PagedListModel(ObjectSubclass<imp::PagedListModel>)
+        @implements gio::ListModel;
+}
+
+impl PagedListModel {
+    pub fn new(model: &impl IsA<gio::ListModel>) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create PagedListModel.");
+        let imp = obj.imp();
+
+        let model = model.upcast_ref::<gio::ListModel>();
+        let slice_model = gtk::SliceListModel::new(Some(model), 0, u32::MAX);
+        slice_model.connect_items_changed(clone!(@weak obj => move |_, position, removed, added| {
+            obj.items_changed(position, removed, added);
+        }));
+
+        // Connected after the slice model, so it is up to date when notified
+        model.connect_items_changed(clone!(@weak obj => move |_, _, removed, added| {
+            if removed != added {
+                obj.notify("n-total");
+            }
+        }));
+
+        imp.model.set(model.clone()).unwrap();
+        imp.slice_model.set(slice_model).unwrap();
+
+        obj
+    }
+
+    /// Show only the first `page_size` items, with one more page for each call of
+    /// [`PagedListModel::load_more`], or all of them when `None`
+    pub fn set_page_size(&self, page_size: Option<u32>) {
+        self.imp().page_size.set(page_size);
+        self.slice_model().set_size(page_size.unwrap_or(u32::MAX));
+    }
+
+    /// Show one more page, if there are items left to show
+    pub fn load_more(&self) {
+        let page_size = match self.imp().page_size.get() {
+            Some(page_size) => page_size,
+            None => return,
+        };
+
+        if self.has_more() {
+            let slice_model = self.slice_model();
+            slice_model.set_size(slice_model.size().saturating_add(page_size));
+        }
+    }
+
+    /// Number of items of the model, including the ones not shown yet
+    pub fn n_total(&self) -> u32 {
+        self.imp().model.get().unwrap().n_items()
+    }
+
+    /// Whether some items are not shown yet
+    pub fn has_more(&self) -> bool {
+        self.n_items() < self.n_total()
+    }
+
+    pub fn connect_n_total_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("n-total"), move |obj, _| f(obj))
+    }
+
+    fn slice_model(&self) -> &gtk::SliceListModel {
+        self.imp().slice_model.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        model::{Note, NoteList},
+        test_harness::run_on_gtk_thread,
+    };
+
+    use std::rc::Rc;
+
+    fn string_list(n_items: u32) -> gtk::StringList {
+        let strings = (0..n_items).map(|i| i.to_string()).collect::<Vec<_>>();
+        gtk::StringList::new(&strings.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn without_page_size() {
+        let model = PagedListModel::new(&string_list(250));
+        assert_eq!(model.n_items(), 250);
+        assert!(!model.has_more());
+
+        model.load_more();
+        assert_eq!(model.n_items(), 250);
+    }
+
+    #[test]
+    fn load_more() {
+        let model = PagedListModel::new(&string_list(250));
+        model.set_page_size(Some(100));
+        assert_eq!(model.n_items(), 100);
+        assert_eq!(model.n_total(), 250);
+        assert!(model.has_more());
+
+        model.load_more();
+        assert_eq!(model.n_items(), 200);
+
+        model.load_more();
+        assert_eq!(model.n_items(), 250);
+        assert!(!model.has_more());
+
+        // Back to the first page
+        model.set_page_size(Some(100));
+        assert_eq!(model.n_items(), 100);
+
+        model.set_page_size(None);
+        assert_eq!(model.n_items(), 250);
+    }
+
+    #[test]
+    fn items_changed_within_page() {
+        let list = string_list(150);
+        let model = PagedListModel::new(&list);
+        model.set_page_size(Some(100));
+
+        let last_change = Rc::new(Cell::new(None));
+        model.connect_items_changed(
+            clone!(@strong last_change => move |_, position, removed, added| {
+                last_change.set(Some((position, removed, added)));
+            }),
+        );
+
+        // The next item moves into the page
+        list.remove(3);
+        assert_eq!(last_change.get(), Some((3, 1, 1)));
+        assert_eq!(model.n_items(), 100);
+        assert_eq!(model.n_total(), 149);
+
+        // Past the page, so nothing is shown to change
+        last_change.set(None);
+        list.remove(120);
+        assert_eq!(last_change.get(), None);
+        assert_eq!(model.n_total(), 148);
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn count_as_notes_are_restored() {
+        run_on_gtk_thread(|| {
+            let notes = (0..250)
+                .map(|_| {
+                    let note = Note::new("/home/user");
+                    note.metadata().set_is_trashed(true);
+                    note
+                })
+                .collect::<Vec<_>>();
+            let note_list = notes.iter().cloned().collect::<NoteList>();
+
+            let filter = gtk::CustomFilter::new(|obj| {
+                obj.downcast_ref::<Note>().unwrap().metadata().is_trashed()
+            });
+            let trash = gtk::FilterListModel::new(Some(&note_list), Some(&filter));
+
+            let model = PagedListModel::new(&trash);
+            model.set_page_size(Some(100));
+            model.load_more();
+
+            let n_total_notifies = Rc::new(Cell::new(0));
+            model.connect_n_total_notify(clone!(@strong n_total_notifies => move |_| {
+                n_total_notifies.set(n_total_notifies.get() + 1);
+            }));
+
+            // Restoring shown notes moves the next ones into the shown pages
+            for note in &notes[..60] {
+                note.metadata().set_is_trashed(false);
+            }
+            assert_eq!(model.n_total(), 190);
+            assert_eq!(model.n_items(), 190);
+            assert!(!model.has_more());
+            assert_eq!(n_total_notifies.get(), 60);
+
+            // Restoring a note that is not shown is still counted
+            model.set_page_size(Some(100));
+            notes[200].metadata().set_is_trashed(false);
+            assert_eq!(model.n_total(), 189);
+            assert_eq!(model.n_items(), 100);
+            assert!(model.has_more());
+
+            notes[0].metadata().set_is_trashed(true);
+            assert_eq!(model.n_total(), 190);
+            assert_eq!(model.n_items(), 100);
+        });
+    }
+}
@@ -0,0 +1,140 @@
+use gettextrs::gettext;
+use gtk::{glib, subclass::prelude::*};
+use once_cell::unsync::OnceCell;
+
+use std::path::{Path, PathBuf};
+
+/// Why the file of a note could not be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "NwtyLoadProblemKind")]
+pub enum LoadProblemKind {
+    /// The file could not be read, like when it is not readable by the user
+    Unreadable,
+    /// The file is not UTF-8 text, like when it is an image named like a note
+    NotText,
+}
+
+impl LoadProblemKind {
+    /// The kind of the error of [`Note::load`](super::Note::load)
+    pub fn for_error(err: &anyhow::Error) -> Self {
+        if err.is::<std::str::Utf8Error>() {
+            Self::NotText
+        } else {
+            Self::Unreadable
+        }
+    }
+
+    pub fn display_name(self) -> String {
+        match self {
+            Self::Unreadable => gettext("Could not be read"),
+            Self::NotText => gettext("Not a text file"),
+        }
+    }
+}
+
+/// Plain data of a file that failed to load
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadProblem {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub path: PathBuf,
+    pub kind: LoadProblemKind,
+    /// Why it failed, like the chain of causes
+    pub message: String,
+}
+
+impl LoadProblem {
+    /// Problem of the file at `path` failing to load with `err`
+    pub fn from_error(path: &Path, err: &anyhow::Error) -> Self {
+        Self {
+            timestamp: chrono::Local::now(),
+            path: path.to_path_buf(),
+            kind: LoadProblemKind::for_error(err),
+            message: format!("{:#}", err),
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct ProblemEntry {
+        pub problem: OnceCell<LoadProblem>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProblemEntry {
+        const NAME: &'static str = "NwtyProblemEntry";
+        type Type = super::ProblemEntry;
+    }
+
+    impl ObjectImpl for ProblemEntry {}
+}
+
+glib::wrapper! {
+    /// A file in the [`ProblemList`](super::ProblemList)
+    pub struct ProblemEntry(ObjectSubclass<imp::ProblemEntry>);
+}
+
+impl ProblemEntry {
+    pub fn new(problem: LoadProblem) -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create ProblemEntry.");
+        obj.imp().problem.set(problem).unwrap();
+        obj
+    }
+
+    pub fn timestamp(&self) -> chrono::DateTime<chrono::Local> {
+        self.problem().timestamp
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.problem().path.clone()
+    }
+
+    pub fn kind(&self) -> LoadProblemKind {
+        self.problem().kind
+    }
+
+    pub fn message(&self) -> String {
+        self.problem().message.clone()
+    }
+
+    fn problem(&self) -> &LoadProblem {
+        self.imp().problem.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gtk::gio;
+
+    #[test]
+    fn kind_for_error() {
+        let not_text = std::str::from_utf8(&[0xff, 0xfe]).unwrap_err();
+        assert_eq!(
+            LoadProblemKind::for_error(&anyhow::Error::new(not_text)),
+            LoadProblemKind::NotText
+        );
+
+        let unreadable = glib::Error::new(gio::IOErrorEnum::PermissionDenied, "Permission denied");
+        assert_eq!(
+            LoadProblemKind::for_error(&anyhow::Error::new(unreadable)),
+            LoadProblemKind::Unreadable
+        );
+        assert_eq!(
+            LoadProblemKind::for_error(&anyhow::anyhow!("Anything else")),
+            LoadProblemKind::Unreadable
+        );
+    }
+
+    #[test]
+    fn from_error() {
+        let err = anyhow::anyhow!("Permission denied").context("Failed to read");
+        let problem = LoadProblem::from_error(Path::new("/notes/Rome.md"), &err);
+
+        assert_eq!(problem.path, Path::new("/notes/Rome.md"));
+        assert_eq!(problem.kind, LoadProblemKind::Unreadable);
+        assert_eq!(problem.message, "Failed to read: Permission denied");
+    }
+}
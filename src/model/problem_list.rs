@@ -0,0 +1,255 @@
+use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+
+use std::{cell::RefCell, path::Path};
+
+use super::{LoadProblem, ProblemEntry};
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct ProblemList {
+        pub list: RefCell<Vec<ProblemEntry>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProblemList {
+        const NAME: &'static str = "NwtyProblemList";
+        type Type = super::ProblemList;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for ProblemList {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecUInt::new(
+                    "n-problems",
+                    "N Problems",
+                    "Number of files that failed to load",
+                    0,
+                    u32::MAX,
+                    0,
+                    glib::ParamFlags::READABLE,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "n-problems" => obj.n_items().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+
+    impl ListModelImpl for ProblemList {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            ProblemEntry::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.list.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.list
+                .borrow()
+                .get(position as usize)
+                .map(|entry| entry.upcast_ref::<glib::Object>())
+                .cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Files in the notes directory that failed to load as notes, at most one per file
+    pub struct ProblemList(ObjectSubclass<imp::ProblemList>)
+        @implements gio::ListModel;
+}
+
+impl ProblemList {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ProblemList.")
+    }
+
+    /// Add the file of `problem`, or replace its entry if it already failed before, so it
+    /// stays where it is with the latest error
+    pub fn report(&self, problem: LoadProblem) {
+        log::warn!(
+            "Failed to load `{}`: {}",
+            problem.path.display(),
+            problem.message
+        );
+
+        let position = self.position(&problem.path);
+        let entry = ProblemEntry::new(problem);
+
+        match position {
+            Some(position) => {
+                self.imp().list.borrow_mut()[position] = entry;
+                self.items_changed(position as u32, 1, 1);
+            }
+            None => {
+                self.imp().list.borrow_mut().push(entry);
+                self.items_changed(self.n_items() - 1, 0, 1);
+                self.notify("n-problems");
+            }
+        }
+    }
+
+    /// Remove the entry of the file at `path`, like once it loads or is deleted. Returns
+    /// whether it had one.
+    pub fn resolve(&self, path: &Path) -> bool {
+        let position = match self.position(path) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        self.imp().list.borrow_mut().remove(position);
+        self.items_changed(position as u32, 1, 0);
+        self.notify("n-problems");
+        true
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.position(path).is_some()
+    }
+
+    /// Remove all the entries, like before the notes are loaded again
+    pub fn clear(&self) {
+        let n_removed = self.n_items();
+
+        if n_removed == 0 {
+            return;
+        }
+
+        self.imp().list.borrow_mut().clear();
+        self.items_changed(0, n_removed, 0);
+        self.notify("n-problems");
+    }
+
+    pub fn n_problems(&self) -> u32 {
+        self.n_items()
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .position(|entry| entry.path() == path)
+    }
+}
+
+impl Default for ProblemList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::LoadProblemKind;
+
+    use std::{cell::RefCell, rc::Rc};
+
+    fn not_text(path: &str) -> LoadProblem {
+        let err = std::str::from_utf8(&[0xff, 0xfe]).unwrap_err();
+        LoadProblem::from_error(Path::new(path), &err.into())
+    }
+
+    fn unreadable(path: &str) -> LoadProblem {
+        LoadProblem::from_error(Path::new(path), &anyhow::anyhow!("Permission denied"))
+    }
+
+    fn entry(problem_list: &ProblemList, position: u32) -> ProblemEntry {
+        problem_list.item(position).unwrap().downcast().unwrap()
+    }
+
+    #[test]
+    fn report() {
+        let problem_list = ProblemList::new();
+        assert_eq!(problem_list.n_problems(), 0);
+
+        problem_list.report(not_text("/notes/Rome.md"));
+        problem_list.report(unreadable("/notes/Paris.md"));
+        assert_eq!(problem_list.n_problems(), 2);
+        assert!(problem_list.contains(Path::new("/notes/Rome.md")));
+        assert!(!problem_list.contains(Path::new("/notes/Berlin.md")));
+
+        let first = entry(&problem_list, 0);
+        assert_eq!(first.path(), Path::new("/notes/Rome.md"));
+        assert_eq!(first.kind(), LoadProblemKind::NotText);
+        let last = entry(&problem_list, 1);
+        assert_eq!(last.path(), Path::new("/notes/Paris.md"));
+        assert_eq!(last.kind(), LoadProblemKind::Unreadable);
+        assert_eq!(last.message(), "Permission denied");
+    }
+
+    #[test]
+    fn repeated_failures_are_deduplicated() {
+        let problem_list = ProblemList::new();
+        problem_list.report(not_text("/notes/Rome.md"));
+        problem_list.report(not_text("/notes/Paris.md"));
+
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        problem_list.connect_items_changed({
+            let changes = Rc::clone(&changes);
+            move |_, position, removed, added| {
+                changes.borrow_mut().push((position, removed, added));
+            }
+        });
+
+        // Failing again, for another reason
+        problem_list.report(unreadable("/notes/Rome.md"));
+        assert_eq!(problem_list.n_problems(), 2);
+        assert_eq!(changes.take(), [(0, 1, 1)]);
+
+        let first = entry(&problem_list, 0);
+        assert_eq!(first.path(), Path::new("/notes/Rome.md"));
+        assert_eq!(first.kind(), LoadProblemKind::Unreadable);
+    }
+
+    #[test]
+    fn resolve() {
+        let problem_list = ProblemList::new();
+        problem_list.report(not_text("/notes/Rome.md"));
+        problem_list.report(not_text("/notes/Paris.md"));
+
+        let n_problems = Rc::new(RefCell::new(Vec::new()));
+        problem_list.connect_notify_local(Some("n-problems"), {
+            let n_problems = Rc::clone(&n_problems);
+            move |problem_list, _| n_problems.borrow_mut().push(problem_list.n_problems())
+        });
+
+        assert!(problem_list.resolve(Path::new("/notes/Rome.md")));
+        assert!(!problem_list.contains(Path::new("/notes/Rome.md")));
+        assert_eq!(entry(&problem_list, 0).path(), Path::new("/notes/Paris.md"));
+
+        // Already resolved
+        assert!(!problem_list.resolve(Path::new("/notes/Rome.md")));
+        assert_eq!(problem_list.n_problems(), 1);
+
+        // Failing again after it loaded once
+        problem_list.report(not_text("/notes/Rome.md"));
+        assert_eq!(problem_list.n_problems(), 2);
+        assert_eq!(entry(&problem_list, 1).path(), Path::new("/notes/Rome.md"));
+
+        assert_eq!(n_problems.take(), [1, 2]);
+    }
+
+    #[test]
+    fn clear() {
+        let problem_list = ProblemList::new();
+        problem_list.clear();
+
+        problem_list.report(not_text("/notes/Rome.md"));
+        problem_list.report(unreadable("/notes/Paris.md"));
+        problem_list.clear();
+        assert_eq!(problem_list.n_problems(), 0);
+        assert!(!problem_list.contains(Path::new("/notes/Rome.md")));
+    }
+}
@@ -0,0 +1,20 @@
+use gtk::glib;
+
+/// Whether the changes to a note are written to its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "NwtyNoteSaveState")]
+pub enum SaveState {
+    Saved,
+    /// Changed since it was last saved
+    Modified,
+    /// Being written to its file
+    Saving,
+    /// The last save failed, and the changes are still not written
+    Error,
+}
+
+impl Default for SaveState {
+    fn default() -> Self {
+        Self::Saved
+    }
+}
@@ -0,0 +1,158 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use std::cell::RefCell;
+
+use crate::core::{QueryError, SavedSearchData, SearchQuery};
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct SavedSearch {
+        pub name: RefCell<String>,
+        pub query: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SavedSearch {
+        const NAME: &'static str = "NwtySavedSearch";
+        type Type = super::SavedSearch;
+    }
+
+    impl ObjectImpl for SavedSearch {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecString::new(
+                        "name",
+                        "Name",
+                        "Name of the saved search",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "query",
+                        "Query",
+                        "Text of the query of the saved search",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "name" => {
+                    let name = value.get().unwrap();
+                    obj.set_name(name);
+                }
+                "query" => {
+                    let query = value.get().unwrap();
+                    obj.set_query(query);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "name" => obj.name().to_value(),
+                "query" => obj.query().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SavedSearch(ObjectSubclass<imp::SavedSearch>);
+}
+
+impl SavedSearch {
+    pub fn new(name: &str, query: &str) -> Self {
+        glib::Object::new(&[("name", &name.to_string()), ("query", &query.to_string())])
+            .expect("Failed to create SavedSearch.")
+    }
+
+    pub fn from_data(data: &SavedSearchData) -> Self {
+        Self::new(&data.name, &data.query)
+    }
+
+    pub fn to_data(&self) -> SavedSearchData {
+        SavedSearchData {
+            name: self.name(),
+            query: self.query(),
+        }
+    }
+
+    /// Must not be called directly if it is in a `SavedSearchList`.
+    /// Use `SavedSearchList::edit` instead, which keeps the list sorted by name.
+    pub(super) fn set_name(&self, name: &str) {
+        if name == self.name() {
+            return;
+        }
+
+        self.imp().name.replace(name.to_string());
+        self.notify("name");
+    }
+
+    pub fn name(&self) -> String {
+        self.imp().name.borrow().clone()
+    }
+
+    pub(super) fn set_query(&self, query: &str) {
+        if query == self.query() {
+            return;
+        }
+
+        self.imp().query.replace(query.to_string());
+        self.notify("query");
+    }
+
+    pub fn query(&self) -> String {
+        self.imp().query.borrow().clone()
+    }
+
+    /// The query, parsed. It is stored as written, so it may be invalid, like when it was
+    /// edited in the data file by hand.
+    pub fn parse_query(&self) -> Result<SearchQuery, QueryError> {
+        SearchQuery::parse(&self.imp().query.borrow())
+    }
+
+    pub fn connect_query_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("query"), move |obj, _| f(obj))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn data() {
+        let saved_search = SavedSearch::new("Work", "tag:work is:pinned");
+        assert_eq!(saved_search.name(), "Work");
+        assert_eq!(
+            SavedSearch::from_data(&saved_search.to_data()).query(),
+            "tag:work is:pinned"
+        );
+    }
+
+    #[test]
+    fn parse_query() {
+        assert!(SavedSearch::new("Work", "tag:work").parse_query().is_ok());
+        assert!(SavedSearch::new("Broken", "tag:").parse_query().is_err());
+    }
+}
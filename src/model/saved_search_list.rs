@@ -0,0 +1,333 @@
+use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+
+use std::cell::RefCell;
+
+use super::SavedSearch;
+use crate::core::SavedSearchData;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct SavedSearchList {
+        /// Sorted by name
+        pub list: RefCell<Vec<SavedSearch>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SavedSearchList {
+        const NAME: &'static str = "NwtySavedSearchList";
+        type Type = super::SavedSearchList;
+        type Interfaces = (gio::ListModel,);
+    }
+
+    impl ObjectImpl for SavedSearchList {}
+
+    impl ListModelImpl for SavedSearchList {
+        fn item_type(&self, _list_model: &Self::Type) -> glib::Type {
+            SavedSearch::static_type()
+        }
+
+        fn n_items(&self, _list_model: &Self::Type) -> u32 {
+            self.list.borrow().len() as u32
+        }
+
+        fn item(&self, _list_model: &Self::Type, position: u32) -> Option<glib::Object> {
+            self.list
+                .borrow()
+                .get(position as usize)
+                .map(|s| s.upcast_ref::<glib::Object>())
+                .cloned()
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SavedSearchList(ObjectSubclass<imp::SavedSearchList>)
+        @implements gio::ListModel;
+}
+
+impl SavedSearchList {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create SavedSearchList.")
+    }
+
+    /// Add `saved_search` where it goes by name. Its name must not be used by another one.
+    pub fn append(&self, saved_search: SavedSearch) -> anyhow::Result<()> {
+        let name = saved_search.name();
+        anyhow::ensure!(!name.trim().is_empty(), "Saved search name cannot be empty");
+        anyhow::ensure!(
+            !self.contains_with_name(&name),
+            "Saved search `{}` already exists",
+            name
+        );
+
+        let position = self.position_for(&name);
+        self.imp().list.borrow_mut().insert(position, saved_search);
+        self.items_changed(position as u32, 0, 1);
+
+        Ok(())
+    }
+
+    pub fn remove(&self, saved_search: &SavedSearch) -> anyhow::Result<()> {
+        let position = self
+            .get_index_of(saved_search)
+            .ok_or_else(|| anyhow::anyhow!("Cannot remove saved search that does not exist"))?;
+
+        self.imp().list.borrow_mut().remove(position);
+        self.items_changed(position as u32, 1, 0);
+
+        Ok(())
+    }
+
+    /// Change the name and the query of `saved_search`, moving it to where it goes by its
+    /// new name
+    pub fn edit(&self, saved_search: &SavedSearch, name: &str, query: &str) -> anyhow::Result<()> {
+        let old_position = self
+            .get_index_of(saved_search)
+            .ok_or_else(|| anyhow::anyhow!("Cannot edit saved search that does not exist"))?;
+
+        anyhow::ensure!(!name.trim().is_empty(), "Saved search name cannot be empty");
+        anyhow::ensure!(
+            self.get_with_name(name)
+                .map_or(true, |other| &other == saved_search),
+            "Saved search `{}` already exists",
+            name
+        );
+
+        let saved_search = self.imp().list.borrow_mut().remove(old_position);
+        saved_search.set_name(name);
+        saved_search.set_query(query);
+
+        let new_position = self.position_for(name);
+        self.imp()
+            .list
+            .borrow_mut()
+            .insert(new_position, saved_search);
+
+        // Every saved search between both positions shifted by one
+        let position = old_position.min(new_position) as u32;
+        let n_changed = (old_position.max(new_position) + 1) as u32 - position;
+        self.items_changed(position, n_changed, n_changed);
+
+        Ok(())
+    }
+
+    pub fn contains_with_name(&self, name: &str) -> bool {
+        self.get_with_name(name).is_some()
+    }
+
+    pub fn get_with_name(&self, name: &str) -> Option<SavedSearch> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .find(|saved_search| saved_search.name() == name)
+            .cloned()
+    }
+
+    /// The saved searches as they are now, sorted by name
+    pub fn snapshot(&self) -> Vec<SavedSearch> {
+        self.imp().list.borrow().clone()
+    }
+
+    pub fn from_data(data: &[SavedSearchData]) -> Self {
+        data.iter().map(SavedSearch::from_data).collect()
+    }
+
+    pub fn to_data(&self) -> Vec<SavedSearchData> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .map(SavedSearch::to_data)
+            .collect()
+    }
+
+    /// Take the saved searches synced from another device, editing the query of the ones this
+    /// list has under the same name and appending the others
+    pub fn merge_synced(&self, synced_searches: &[SavedSearchData]) {
+        for synced in synced_searches {
+            match self.get_with_name(&synced.name) {
+                Some(saved_search) if saved_search.query() != synced.query => {
+                    log::info!("Sync: Found edited saved search `{}`", synced.name);
+                    if let Err(err) = self.edit(&saved_search, &synced.name, &synced.query) {
+                        log::warn!(
+                            "Failed to edit synced saved search `{}`: {:?}",
+                            synced.name,
+                            err
+                        );
+                    }
+                }
+                Some(_) => (),
+                None => {
+                    log::info!(
+                        "Sync: Found added saved search `{}`; appending...",
+                        synced.name
+                    );
+                    if let Err(err) = self.append(SavedSearch::from_data(synced)) {
+                        log::warn!(
+                            "Failed to append synced saved search `{}`: {:?}",
+                            synced.name,
+                            err
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_index_of(&self, saved_search: &SavedSearch) -> Option<usize> {
+        self.imp()
+            .list
+            .borrow()
+            .iter()
+            .position(|other| other == saved_search)
+    }
+
+    /// Where a saved search named `name` goes to keep the list sorted
+    fn position_for(&self, name: &str) -> usize {
+        self.imp()
+            .list
+            .borrow()
+            .partition_point(|saved_search| saved_search.name().as_str() < name)
+    }
+}
+
+impl std::iter::FromIterator<SavedSearch> for SavedSearchList {
+    fn from_iter<I: IntoIterator<Item = SavedSearch>>(iter: I) -> Self {
+        let saved_search_list = Self::new();
+
+        for saved_search in iter {
+            if let Err(err) = saved_search_list.append(saved_search) {
+                log::warn!("Error appending a saved search, skipping: {:?}", err);
+            }
+        }
+
+        saved_search_list
+    }
+}
+
+impl Default for SavedSearchList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(saved_search_list: &SavedSearchList) -> Vec<String> {
+        saved_search_list
+            .snapshot()
+            .iter()
+            .map(SavedSearch::name)
+            .collect()
+    }
+
+    #[test]
+    fn append_sorted_by_name() {
+        let saved_search_list = SavedSearchList::new();
+        saved_search_list
+            .append(SavedSearch::new("Work", "tag:work"))
+            .unwrap();
+        saved_search_list
+            .append(SavedSearch::new("Pinned", "is:pinned"))
+            .unwrap();
+        saved_search_list
+            .append(SavedSearch::new("Recent", "after:7d"))
+            .unwrap();
+        assert_eq!(names(&saved_search_list), ["Pinned", "Recent", "Work"]);
+        assert_eq!(saved_search_list.n_items(), 3);
+
+        assert!(saved_search_list
+            .append(SavedSearch::new("Work", "is:untagged"))
+            .is_err());
+        assert!(saved_search_list
+            .append(SavedSearch::new(" ", "is:untagged"))
+            .is_err());
+    }
+
+    #[test]
+    fn edit() {
+        let saved_search_list = ["A", "B", "C"]
+            .into_iter()
+            .map(|name| SavedSearch::new(name, "is:pinned"))
+            .collect::<SavedSearchList>();
+        let saved_search = saved_search_list.get_with_name("A").unwrap();
+
+        saved_search_list
+            .edit(&saved_search, "D", "tag:work")
+            .unwrap();
+        assert_eq!(names(&saved_search_list), ["B", "C", "D"]);
+        assert_eq!(saved_search.query(), "tag:work");
+
+        // Keeping its own name is fine, but not taking another's
+        assert!(saved_search_list
+            .edit(&saved_search, "D", "is:pinned")
+            .is_ok());
+        assert!(saved_search_list
+            .edit(&saved_search, "B", "is:pinned")
+            .is_err());
+        assert_eq!(names(&saved_search_list), ["B", "C", "D"]);
+    }
+
+    #[test]
+    fn remove() {
+        let saved_search = SavedSearch::new("A", "is:pinned");
+        let saved_search_list = SavedSearchList::new();
+        saved_search_list.append(saved_search.clone()).unwrap();
+
+        saved_search_list.remove(&saved_search).unwrap();
+        assert_eq!(saved_search_list.n_items(), 0);
+        assert!(saved_search_list.remove(&saved_search).is_err());
+    }
+
+    #[test]
+    fn data() {
+        let saved_search_list = SavedSearchList::from_data(&[
+            SavedSearchData {
+                name: "Work".to_string(),
+                query: "tag:work".to_string(),
+            },
+            SavedSearchData {
+                name: "Pinned".to_string(),
+                query: "is:pinned".to_string(),
+            },
+        ]);
+        assert_eq!(names(&saved_search_list), ["Pinned", "Work"]);
+        assert_eq!(saved_search_list.to_data()[1].query, "tag:work");
+    }
+
+    #[test]
+    fn merge_synced() {
+        let saved_search_list = SavedSearchList::new();
+        saved_search_list
+            .append(SavedSearch::new("Local", "is:pinned"))
+            .unwrap();
+        saved_search_list
+            .append(SavedSearch::new("Work", "tag:work"))
+            .unwrap();
+
+        saved_search_list.merge_synced(&[
+            SavedSearchData {
+                name: "Synced".to_string(),
+                query: "is:untagged".to_string(),
+            },
+            SavedSearchData {
+                name: "Work".to_string(),
+                query: "tag:work after:7d".to_string(),
+            },
+        ]);
+        assert_eq!(names(&saved_search_list), ["Local", "Synced", "Work"]);
+        assert_eq!(
+            saved_search_list.get_with_name("Work").unwrap().query(),
+            "tag:work after:7d"
+        );
+        assert_eq!(
+            saved_search_list.get_with_name("Local").unwrap().query(),
+            "is:pinned"
+        );
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::cell::RefCell;
 
+use crate::core::normalize_tag_name;
+
 mod imp {
     use super::*;
     use once_cell::sync::Lazy;
@@ -69,7 +71,7 @@ impl Tag {
     /// Must not be called directly if a tag is in a `TagList` or `NoteTagList`.
     /// Use `TagList::rename_tag` instead as it contains sanity checks and other handling.
     pub(super) fn set_name(&self, name: &str) {
-        self.imp().name.replace(name.to_string());
+        self.imp().name.replace(normalize_tag_name(name));
         self.notify("name");
     }
 
@@ -111,6 +113,12 @@ mod test {
         assert_eq!(tag.name(), "New name");
     }
 
+    #[test]
+    fn name_normalized() {
+        let tag = Tag::new("Cafe\u{301}");
+        assert_eq!(tag.name(), "Caf\u{e9}");
+    }
+
     #[test]
     fn serialize() {
         let tag = Tag::new("A tag");
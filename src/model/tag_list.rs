@@ -4,19 +4,19 @@ use gtk::{
     prelude::*,
     subclass::prelude::*,
 };
-use indexmap::IndexMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::cell::RefCell;
 
 use super::Tag;
+use crate::core::TagSet;
 
 mod imp {
     use super::*;
 
     #[derive(Debug, Default)]
     pub struct TagList {
-        pub list: RefCell<IndexMap<String, Tag>>,
+        pub list: RefCell<TagSet<Tag>>,
     }
 
     #[glib::object_subclass]
@@ -71,47 +71,42 @@ impl TagList {
             }
         }));
 
-        let is_name_appended = self.imp().list.borrow_mut().insert(tag_name, tag).is_none();
+        let position = self.imp().list.borrow_mut().insert(&tag_name, tag)?;
 
-        anyhow::ensure!(is_name_appended, "Cannot append existing tag name");
-
-        self.items_changed(self.n_items() - 1, 0, 1);
+        self.items_changed(position as u32, 0, 1);
 
         Ok(())
     }
 
     pub fn remove(&self, tag: &Tag) -> anyhow::Result<()> {
-        let tag_name = tag.name();
+        let (position, _) = self.imp().list.borrow_mut().remove(&tag.name())?;
 
-        let removed = self.imp().list.borrow_mut().shift_remove_full(&tag_name);
+        self.items_changed(position as u32, 1, 0);
 
-        if let Some((position, _, _)) = removed {
-            self.items_changed(position as u32, 1, 0);
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Cannot remove tag that does not exist"))
-        }
+        Ok(())
     }
 
     pub fn rename_tag(&self, tag: &Tag, new_name: &str) -> anyhow::Result<()> {
-        anyhow::ensure!(
-            !self.contains_with_name(new_name),
-            "Cannot rename a tag to a name that already exist"
-        );
-        anyhow::ensure!(!new_name.is_empty(), "Tag name cannot be empty");
+        self.imp().list.borrow_mut().rename(&tag.name(), new_name)?;
 
-        let previous_name = tag.name();
+        tag.set_name(new_name);
 
-        {
-            let mut list = self.imp().list.borrow_mut();
-            // Put new name at the end
-            assert!(list.insert(new_name.to_string(), tag.clone()).is_none());
-            // Remove the old name at the list and replace it with name from the end
-            assert!(list.swap_remove(&previous_name).is_some());
-            // Might replace this in the future with https://github.com/rust-lang/rust/issues/44286
-        }
+        Ok(())
+    }
 
-        tag.set_name(new_name);
+    /// Move the tag at `from` so it ends up at `to`, as when reordered by the user
+    pub fn move_item(&self, from: u32, to: u32) -> anyhow::Result<()> {
+        self.imp()
+            .list
+            .borrow_mut()
+            .move_index(from as usize, to as usize)?;
+
+        // Every tag between both positions shifted by one
+        let position = from.min(to);
+        let n_changed = from.max(to) - position + 1;
+        if from != to {
+            self.items_changed(position, n_changed, n_changed);
+        }
 
         Ok(())
     }
@@ -121,7 +116,19 @@ impl TagList {
     }
 
     pub fn contains_with_name(&self, name: &str) -> bool {
-        self.imp().list.borrow().contains_key(name)
+        self.imp().list.borrow().contains(name)
+    }
+
+    /// Append a tag for each of the names synced from another device that this list lacks
+    pub fn merge_synced_names(&self, names: &[String]) {
+        for name in names {
+            if !self.contains_with_name(name) {
+                log::info!("Sync: Found added tag `{}`; appending...", name);
+                if let Err(err) = self.append(Tag::new(name)) {
+                    log::warn!("Failed to append synced tag `{}`: {:?}", name, err);
+                }
+            }
+        }
     }
 
     pub fn get_with_name(&self, name: &str) -> Option<Tag> {
@@ -129,11 +136,34 @@ impl TagList {
     }
 
     pub fn is_valid_name(&self, name: &str) -> bool {
-        !self.contains_with_name(name) && !name.is_empty()
+        self.imp().list.borrow().is_valid_name(name)
+    }
+
+    /// Iterate over the tags as they are now. Changes to the list while iterating are not
+    /// reflected, so it is fine to call this while appending or removing tags.
+    pub fn iter(&self) -> Iter {
+        let tags = self
+            .imp()
+            .list
+            .borrow()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        Iter {
+            tags: tags.into_iter(),
+        }
     }
 
-    fn get_index_of(&self, tag: &Tag) -> Option<usize> {
-        self.imp().list.borrow().get_index_of(&tag.name())
+    pub fn len(&self) -> usize {
+        self.imp().list.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.imp().list.borrow().is_empty()
+    }
+
+    pub fn get_index_of(&self, tag: &Tag) -> Option<usize> {
+        self.imp().list.borrow().index_of(&tag.name())
     }
 }
 
@@ -142,6 +172,16 @@ impl std::iter::FromIterator<Tag> for TagList {
         let tag_list = Self::new();
 
         for tag in iter {
+            // Like tags added on devices that differ in case or how they compose characters
+            if let Some(existing_tag) = tag_list.get_with_name(&tag.name()) {
+                log::info!(
+                    "Merging tag `{}` into `{}`, as they have the same name",
+                    tag.name(),
+                    existing_tag.name()
+                );
+                continue;
+            }
+
             if let Err(err) = tag_list.append(tag) {
                 log::warn!("Error appending a tag, skipping: {:?}", err);
             }
@@ -151,9 +191,35 @@ impl std::iter::FromIterator<Tag> for TagList {
     }
 }
 
+/// Iterator over the tags of a [`TagList`] created by [`TagList::iter`]
+#[derive(Debug)]
+pub struct Iter {
+    tags: std::vec::IntoIter<Tag>,
+}
+
+impl Iterator for Iter {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tags.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.tags.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.tags.next_back()
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
 impl Serialize for TagList {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.collect_seq(self.imp().list.borrow().keys())
+        serializer.collect_seq(self.imp().list.borrow().names())
     }
 }
 
@@ -177,6 +243,10 @@ impl Default for TagList {
 mod test {
     use super::*;
 
+    use std::rc::Rc;
+
+    use crate::test_harness::tag_list;
+
     #[test]
     fn append() {
         let tag_list = TagList::new();
@@ -191,25 +261,51 @@ mod test {
     fn multiple_append() {
         let tag_list = TagList::new();
         assert!(!tag_list.contains_with_name("A"));
-        assert!(!tag_list.contains_with_name("a"));
         assert!(!tag_list.contains_with_name("B"));
-        assert!(!tag_list.contains_with_name("b"));
+        assert!(!tag_list.contains_with_name("C"));
+        assert!(!tag_list.contains_with_name("D"));
 
         assert!(tag_list.append(Tag::new("A")).is_ok());
         assert_eq!(tag_list.n_items(), 1);
         assert!(tag_list.contains_with_name("A"));
 
-        assert!(tag_list.append(Tag::new("a")).is_ok());
+        assert!(tag_list.append(Tag::new("B")).is_ok());
         assert_eq!(tag_list.n_items(), 2);
-        assert!(tag_list.contains_with_name("a"));
+        assert!(tag_list.contains_with_name("B"));
 
-        assert!(tag_list.append(Tag::new("B")).is_ok());
+        assert!(tag_list.append(Tag::new("C")).is_ok());
         assert_eq!(tag_list.n_items(), 3);
-        assert!(tag_list.contains_with_name("B"));
+        assert!(tag_list.contains_with_name("C"));
 
-        assert!(tag_list.append(Tag::new("b")).is_ok());
+        assert!(tag_list.append(Tag::new("D")).is_ok());
         assert_eq!(tag_list.n_items(), 4);
-        assert!(tag_list.contains_with_name("b"));
+        assert!(tag_list.contains_with_name("D"));
+    }
+
+    #[test]
+    fn append_other_case() {
+        let tag_list = TagList::new();
+        assert!(tag_list.append(Tag::new("Work")).is_ok());
+
+        assert!(tag_list.append(Tag::new("work")).is_err());
+        assert!(tag_list.append(Tag::new("WORK")).is_err());
+        assert_eq!(tag_list.n_items(), 1);
+        assert!(tag_list.contains_with_name("work"));
+        assert_eq!(
+            tag_list.get_with_name("work").map(|tag| tag.name()),
+            Some("Work".into())
+        );
+    }
+
+    #[test]
+    fn append_other_normalization() {
+        let tag_list = TagList::new();
+        assert!(tag_list.append(Tag::new("Cafe\u{301}")).is_ok());
+
+        assert!(tag_list.append(Tag::new("Caf\u{e9}")).is_err());
+        assert!(tag_list.append(Tag::new("caf\u{e9}")).is_err());
+        assert_eq!(tag_list.n_items(), 1);
+        assert_eq!(names(&tag_list), vec!["Caf\u{e9}"]);
     }
 
     #[test]
@@ -239,36 +335,36 @@ mod test {
     fn multiple_remove() {
         let tag_list = TagList::new();
         let tag_a = Tag::new("A");
-        let tag_a2 = Tag::new("a");
+        let tag_c = Tag::new("C");
         let tag_b = Tag::new("B");
-        let tag_b2 = Tag::new("b");
+        let tag_d = Tag::new("D");
         assert!(!tag_list.contains_with_name("A"));
-        assert!(!tag_list.contains_with_name("a"));
+        assert!(!tag_list.contains_with_name("C"));
         assert!(!tag_list.contains_with_name("B"));
-        assert!(!tag_list.contains_with_name("b"));
+        assert!(!tag_list.contains_with_name("D"));
 
         assert!(tag_list.append(tag_a.clone()).is_ok());
         assert!(tag_list.contains_with_name("A"));
-        assert!(tag_list.append(tag_a2.clone()).is_ok());
-        assert!(tag_list.contains_with_name("a"));
+        assert!(tag_list.append(tag_c.clone()).is_ok());
+        assert!(tag_list.contains_with_name("C"));
         assert!(tag_list.append(tag_b.clone()).is_ok());
         assert!(tag_list.contains_with_name("B"));
-        assert!(tag_list.append(tag_b2.clone()).is_ok());
-        assert!(tag_list.contains_with_name("b"));
+        assert!(tag_list.append(tag_d.clone()).is_ok());
+        assert!(tag_list.contains_with_name("D"));
         assert_eq!(tag_list.n_items(), 4);
 
         assert!(tag_list.remove(&tag_a).is_ok());
         assert_eq!(tag_list.n_items(), 3);
         assert!(!tag_list.contains_with_name("A"));
-        assert!(tag_list.remove(&tag_a2).is_ok());
+        assert!(tag_list.remove(&tag_c).is_ok());
         assert_eq!(tag_list.n_items(), 2);
-        assert!(!tag_list.contains_with_name("a"));
+        assert!(!tag_list.contains_with_name("C"));
         assert!(tag_list.remove(&tag_b).is_ok());
         assert_eq!(tag_list.n_items(), 1);
         assert!(!tag_list.contains_with_name("B"));
-        assert!(tag_list.remove(&tag_b2).is_ok());
+        assert!(tag_list.remove(&tag_d).is_ok());
         assert_eq!(tag_list.n_items(), 0);
-        assert!(!tag_list.contains_with_name("b"));
+        assert!(!tag_list.contains_with_name("D"));
     }
 
     #[test]
@@ -313,6 +409,21 @@ mod test {
         assert_eq!(tag_list.n_items(), 1);
     }
 
+    #[test]
+    fn rename_case() {
+        let tag_list = TagList::new();
+        let tag = Tag::new("work");
+        assert!(tag_list.append(tag.clone()).is_ok());
+        assert!(tag_list.append(Tag::new("Home")).is_ok());
+
+        assert!(tag_list.rename_tag(&tag, "Work").is_ok());
+        assert_eq!(tag.name(), "Work");
+        assert_eq!(names(&tag_list), vec!["Work", "Home"]);
+
+        assert!(tag_list.rename_tag(&tag, "home").is_err());
+        assert_eq!(tag.name(), "Work");
+    }
+
     #[test]
     fn rename_tag_empty() {
         let tag_list = TagList::new();
@@ -441,25 +552,151 @@ mod test {
         assert_eq!(tag_list.n_items(), 2);
     }
 
+    /// Keeps a copy of the tags of `tag_list` up to date only through `items-changed`
+    fn mirror(tag_list: &TagList) -> Rc<RefCell<Vec<Tag>>> {
+        let mirror = Rc::new(RefCell::new(tag_list.iter().collect::<Vec<_>>()));
+        tag_list.connect_items_changed(
+            clone!(@strong mirror => move |tag_list, position, removed, added| {
+                let added = (position..position + added)
+                    .map(|i| tag_list.item(i).unwrap().downcast::<Tag>().unwrap())
+                    .collect::<Vec<_>>();
+                mirror.borrow_mut().splice(
+                    position as usize..(position + removed) as usize,
+                    added,
+                );
+            }),
+        );
+        mirror
+    }
+
+    fn names(tag_list: &TagList) -> Vec<String> {
+        tag_list.iter().map(|tag| tag.name()).collect()
+    }
+
+    #[test]
+    fn move_item() {
+        let tag_list = ["A", "B", "C", "D"]
+            .into_iter()
+            .map(Tag::new)
+            .collect::<TagList>();
+        let mirror = mirror(&tag_list);
+
+        assert!(tag_list.move_item(0, 2).is_ok());
+        assert_eq!(names(&tag_list), vec!["B", "C", "A", "D"]);
+
+        assert!(tag_list.move_item(3, 1).is_ok());
+        assert_eq!(names(&tag_list), vec!["B", "D", "C", "A"]);
+
+        assert!(tag_list.move_item(4, 0).is_err());
+        assert_eq!(*mirror.borrow(), tag_list.iter().collect::<Vec<_>>());
+
+        let tag = tag_list.get_with_name("A").unwrap();
+        assert_eq!(tag_list.get_index_of(&tag), Some(3));
+    }
+
+    #[test]
+    fn interleaved_move_and_rename() {
+        let tag_list = ["A", "B", "C", "D", "E"]
+            .into_iter()
+            .map(Tag::new)
+            .collect::<TagList>();
+        let mirror = mirror(&tag_list);
+
+        let mut seed = 0x9e37_79b9_u32;
+        let mut next = |bound: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % bound
+        };
+
+        for i in 0..200 {
+            let n_items = tag_list.n_items();
+            let tag = tag_list
+                .item(next(n_items))
+                .unwrap()
+                .downcast::<Tag>()
+                .unwrap();
+
+            if next(2) == 0 {
+                tag_list.move_item(next(n_items), next(n_items)).unwrap();
+            } else {
+                let new_name = format!("{}{}", &tag.name()[..1], i);
+                tag_list.rename_tag(&tag, &new_name).unwrap();
+            }
+
+            // Both the names and the objects stay where the list model says they are
+            assert_eq!(*mirror.borrow(), tag_list.iter().collect::<Vec<_>>());
+            for (position, tag) in tag_list.iter().enumerate() {
+                assert_eq!(tag_list.get_with_name(&tag.name()), Some(tag.clone()));
+                assert_eq!(tag_list.get_index_of(&tag), Some(position));
+            }
+        }
+
+        assert_eq!(tag_list.len(), 5);
+    }
+
+    #[test]
+    fn invariants_through_changes() {
+        let tag_list = tag_list(&["A", "B", "C"]);
+        let mirror = mirror(&tag_list);
+
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |bound: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % bound
+        };
+
+        for i in 0..300 {
+            let n_items = tag_list.n_items();
+            let random_tag =
+                |position: u32| tag_list.item(position).unwrap().downcast::<Tag>().unwrap();
+
+            match next(4) {
+                1 if n_items > 1 => tag_list.remove(&random_tag(next(n_items))).unwrap(),
+                2 if n_items > 0 => {
+                    let tag = random_tag(next(n_items));
+                    tag_list.rename_tag(&tag, &format!("R{}", i)).unwrap();
+                }
+                3 if n_items > 0 => tag_list.move_item(next(n_items), next(n_items)).unwrap(),
+                _ => tag_list.append(Tag::new(&format!("N{}", i))).unwrap(),
+            }
+
+            assert_eq!(tag_list.n_items() as usize, tag_list.len());
+            assert_eq!(*mirror.borrow(), tag_list.iter().collect::<Vec<_>>());
+
+            let names = names(&tag_list);
+            for (position, tag) in tag_list.iter().enumerate() {
+                assert_eq!(tag_list.get_index_of(&tag), Some(position));
+                assert_eq!(tag_list.get_with_name(&tag.name()), Some(tag.clone()));
+                // No other tag can take its name, in any case
+                assert_eq!(names.iter().filter(|name| **name == tag.name()).count(), 1);
+                assert!(!tag_list.is_valid_name(&tag.name().to_lowercase()));
+            }
+        }
+    }
+
     #[test]
     fn contains() {
         let tag_list = TagList::new();
         let tag_a = Tag::new("A");
-        let tag_a2 = Tag::new("a");
+        let tag_c = Tag::new("C");
         let tag_b = Tag::new("B");
-        let tag_b2 = Tag::new("b");
+        let tag_d = Tag::new("D");
 
         assert!(tag_list.append(tag_a.clone()).is_ok());
-        assert!(tag_list.append(tag_a2.clone()).is_ok());
+        assert!(tag_list.append(tag_c.clone()).is_ok());
         assert!(tag_list.append(tag_b.clone()).is_ok());
-        assert!(tag_list.append(tag_b2.clone()).is_ok());
+        assert!(tag_list.append(tag_d.clone()).is_ok());
 
         assert!(tag_list.contains(&tag_a));
-        assert!(tag_list.contains(&tag_a2));
+        assert!(tag_list.contains(&tag_c));
         assert!(tag_list.contains(&tag_b));
-        assert!(tag_list.contains(&tag_b2));
-        assert!(!tag_list.contains(&Tag::new("C")));
-        assert!(!tag_list.contains(&Tag::new("c")));
+        assert!(tag_list.contains(&tag_d));
+        assert!(!tag_list.contains(&Tag::new("E")));
+        assert!(!tag_list.contains(&Tag::new("F")));
     }
 
     #[test]
@@ -467,37 +704,37 @@ mod test {
         let tag_list = TagList::new();
 
         assert!(tag_list.append(Tag::new("A")).is_ok());
-        assert!(tag_list.append(Tag::new("a")).is_ok());
+        assert!(tag_list.append(Tag::new("C")).is_ok());
         assert!(tag_list.append(Tag::new("B")).is_ok());
-        assert!(tag_list.append(Tag::new("b")).is_ok());
+        assert!(tag_list.append(Tag::new("D")).is_ok());
 
         assert!(tag_list.contains_with_name("A"));
-        assert!(tag_list.contains_with_name("a"));
+        assert!(tag_list.contains_with_name("C"));
         assert!(tag_list.contains_with_name("B"));
-        assert!(tag_list.contains_with_name("b"));
-        assert!(!tag_list.contains_with_name("C"));
-        assert!(!tag_list.contains_with_name("c"));
+        assert!(tag_list.contains_with_name("D"));
+        assert!(tag_list.contains_with_name("a"));
+        assert!(!tag_list.contains_with_name("E"));
     }
 
     #[test]
     fn get_with_name() {
         let tag_list = TagList::new();
         let tag_a = Tag::new("A");
-        let tag_a2 = Tag::new("a");
+        let tag_c = Tag::new("C");
         let tag_b = Tag::new("B");
-        let tag_b2 = Tag::new("b");
+        let tag_d = Tag::new("D");
 
         assert!(tag_list.append(tag_a.clone()).is_ok());
-        assert!(tag_list.append(tag_a2.clone()).is_ok());
+        assert!(tag_list.append(tag_c.clone()).is_ok());
         assert!(tag_list.append(tag_b.clone()).is_ok());
-        assert!(tag_list.append(tag_b2.clone()).is_ok());
+        assert!(tag_list.append(tag_d.clone()).is_ok());
 
         assert_eq!(tag_list.get_with_name("A"), Some(tag_a));
-        assert_eq!(tag_list.get_with_name("a"), Some(tag_a2));
+        assert_eq!(tag_list.get_with_name("C"), Some(tag_c));
         assert_eq!(tag_list.get_with_name("B"), Some(tag_b));
-        assert_eq!(tag_list.get_with_name("b"), Some(tag_b2));
-        assert!(tag_list.get_with_name("C").is_none());
-        assert!(tag_list.get_with_name("c").is_none());
+        assert_eq!(tag_list.get_with_name("D"), Some(tag_d));
+        assert_eq!(tag_list.get_with_name("a"), Some(tag_a));
+        assert!(tag_list.get_with_name("E").is_none());
     }
 
     #[test]
@@ -540,6 +777,16 @@ mod test {
         assert!(tag_list.is_valid_name("A"));
     }
 
+    #[test]
+    fn is_valid_name_other_case() {
+        let tag_list = TagList::new();
+        assert!(tag_list.append(Tag::new("Caf\u{e9}")).is_ok());
+
+        assert!(!tag_list.is_valid_name("CAF\u{c9}"));
+        assert!(!tag_list.is_valid_name("cafe\u{301}"));
+        assert!(tag_list.is_valid_name("Cafe"));
+    }
+
     #[test]
     fn is_valid_name_empty() {
         let tag_list = TagList::new();
@@ -566,4 +813,59 @@ mod test {
         assert!(tag_list.contains_with_name("C"));
         assert_eq!(tag_list.n_items(), 3);
     }
+
+    #[test]
+    fn deserialize_same_names() {
+        let tag_list: TagList =
+            serde_yaml::from_str("- Work\n- Cafe\u{301}\n- work\n- Caf\u{e9}\n- WORK\n").unwrap();
+        assert_eq!(names(&tag_list), vec!["Work", "Caf\u{e9}"]);
+    }
+
+    #[test]
+    fn iter_snapshot() {
+        let tag_list = ["A", "B"].into_iter().map(Tag::new).collect::<TagList>();
+        assert_eq!(tag_list.len(), 2);
+
+        let mut names = Vec::new();
+        for tag in tag_list.iter() {
+            // Appending while iterating must neither panic nor be iterated over
+            tag_list
+                .append(Tag::new(&format!("{}2", tag.name())))
+                .unwrap();
+            names.push(tag.name());
+        }
+
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(
+            tag_list.iter().map(|tag| tag.name()).collect::<Vec<_>>(),
+            vec!["A", "B", "A2", "B2"]
+        );
+        assert_eq!(tag_list.iter().len(), 4);
+    }
+
+    #[test]
+    fn is_empty() {
+        let tag_list = TagList::new();
+        assert!(tag_list.is_empty());
+        assert_eq!(tag_list.len(), 0);
+
+        tag_list.append(Tag::new("A")).unwrap();
+        assert!(!tag_list.is_empty());
+        assert_eq!(
+            tag_list.iter().next().map(|tag| tag.name()),
+            Some("A".into())
+        );
+    }
+
+    #[test]
+    fn merge_synced_names() {
+        let tag_list = TagList::new();
+        tag_list.append(Tag::new("Local")).unwrap();
+
+        tag_list.merge_synced_names(&["Synced".to_string(), "Local".to_string()]);
+        assert_eq!(
+            tag_list.iter().map(|tag| tag.name()).collect::<Vec<_>>(),
+            vec!["Local", "Synced"]
+        );
+    }
 }
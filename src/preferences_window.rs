@@ -0,0 +1,676 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{
+    gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::RefCell;
+
+use crate::{
+    core::{AheadBehind, FrontMatterProfile, NoteRepository, RemoteStatus},
+    model::NotebookSettings,
+    session::Session,
+    spawn,
+    utils::{palette::PALETTE_CHOICES, ROW_DETAIL_CHOICES, WRAP_MODE_CHOICES},
+    Application,
+};
+
+/// What is shown after the remote is tested
+fn remote_status_message(status: &RemoteStatus) -> String {
+    match status {
+        RemoteStatus::Reachable => gettext("The remote can be synced with"),
+        RemoteStatus::AuthFailed(_) => {
+            gettext("Could not sign in to the remote. Check your SSH key or credentials.")
+        }
+        RemoteStatus::NotARepository(_) => gettext("There is no git repository at this URL"),
+        RemoteStatus::Unreachable(message) => {
+            gettext!("Could not reach the remote: {}", message)
+        }
+    }
+}
+
+/// What is shown after the remote is changed, comparing the notes with the new remote
+fn ahead_behind_message(ahead_behind: AheadBehind) -> String {
+    let AheadBehind { n_ahead, n_behind } = ahead_behind;
+
+    let mut parts = Vec::new();
+    if n_ahead > 0 {
+        parts.push(ngettext!(
+            "{} commit to push",
+            "{} commits to push",
+            n_ahead as u32,
+            n_ahead
+        ));
+    }
+    if n_behind > 0 {
+        parts.push(ngettext!(
+            "{} commit to pull",
+            "{} commits to pull",
+            n_behind as u32,
+            n_behind
+        ));
+    }
+
+    if parts.is_empty() {
+        gettext("Up to date with the remote")
+    } else {
+        parts.join(", ")
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/preferences-window.ui")]
+    pub struct PreferencesWindow {
+        #[template_child]
+        pub lock_timeout_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub row_detail_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub freeze_order_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub wrap_mode_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub right_margin_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub right_margin_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub line_numbers_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub heading_marks_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub change_bar_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub limit_width_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub max_width_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub palette_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub read_only_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub commit_message_template_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub commit_message_max_changes_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub run_in_background_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub autostart_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub delete_immediately_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub note_file_name_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub note_template_text_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub front_matter_profile_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub rewrite_front_matter_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub daily_word_goal_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub derive_title_from_heading_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub markdown_note_links_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub remote_url_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub remote_status_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub test_remote_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub apply_remote_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub maintenance_button: TemplateChild<gtk::Button>,
+
+        pub notebook_settings: OnceCell<NotebookSettings>,
+        pub repository: OnceCell<NoteRepository>,
+        /// The remote the notes are synced with now, which the entry is compared with
+        pub remote_url: RefCell<Option<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PreferencesWindow {
+        const NAME: &'static str = "NwtyPreferencesWindow";
+        type Type = super::PreferencesWindow;
+        type ParentType = adw::PreferencesWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PreferencesWindow {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecObject::new(
+                        "notebook-settings",
+                        "Notebook Settings",
+                        "Settings of the notebook that are edited",
+                        NotebookSettings::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "repository",
+                        "Repository",
+                        "Repository of the notebook whose remote is edited",
+                        NoteRepository::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "notebook-settings" => {
+                    let notebook_settings = value.get().unwrap();
+                    self.notebook_settings.set(notebook_settings).unwrap();
+                }
+                "repository" => {
+                    let repository = value.get().unwrap();
+                    self.repository.set(repository).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "notebook-settings" => obj.notebook_settings().to_value(),
+                "repository" => obj.repository().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_device_settings();
+            obj.setup_notebook_settings();
+            obj.setup_repository();
+        }
+    }
+
+    impl WidgetImpl for PreferencesWindow {}
+    impl WindowImpl for PreferencesWindow {}
+    impl AdwWindowImpl for PreferencesWindow {}
+    impl PreferencesWindowImpl for PreferencesWindow {}
+}
+
+glib::wrapper! {
+    /// Edits the settings of this device and the ones shared by the devices of the notebook,
+    /// each on their own page
+    pub struct PreferencesWindow(ObjectSubclass<imp::PreferencesWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window, adw::PreferencesWindow,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl PreferencesWindow {
+    pub fn new(notebook_settings: &NotebookSettings, repository: &NoteRepository) -> Self {
+        glib::Object::new(&[
+            ("notebook-settings", notebook_settings),
+            ("repository", repository),
+        ])
+        .expect("Failed to create PreferencesWindow.")
+    }
+
+    fn notebook_settings(&self) -> &NotebookSettings {
+        self.imp().notebook_settings.get().unwrap()
+    }
+
+    fn repository(&self) -> &NoteRepository {
+        self.imp().repository.get().unwrap()
+    }
+
+    /// The URL in the entry, if it is one that can be synced with
+    fn entered_remote_url(&self) -> Option<String> {
+        let remote_url = self.imp().remote_url_entry.text();
+        let remote_url = remote_url.trim();
+
+        NoteRepository::validate_remote_url(remote_url).then(|| remote_url.to_string())
+    }
+
+    fn update_remote_buttons(&self) {
+        let imp = self.imp();
+        let remote_url = self.entered_remote_url();
+        let is_changed = remote_url.is_some() && remote_url != *imp.remote_url.borrow();
+
+        imp.test_remote_button.set_sensitive(remote_url.is_some());
+        imp.apply_remote_button.set_sensitive(is_changed);
+
+        if remote_url.is_none() && !imp.remote_url_entry.text().is_empty() {
+            imp.remote_url_entry.add_css_class("error");
+        } else {
+            imp.remote_url_entry.remove_css_class("error");
+        }
+    }
+
+    fn set_remote_status(&self, message: &str) {
+        self.imp().remote_status_row.set_subtitle(message);
+    }
+
+    async fn load_remote_url(&self) {
+        let imp = self.imp();
+
+        match self.repository().remote_url().await {
+            Ok(remote_url) => {
+                imp.remote_url_entry
+                    .set_text(remote_url.as_deref().unwrap_or_default());
+                if remote_url.is_none() {
+                    self.set_remote_status(&gettext("The notes are only on this device"));
+                }
+                imp.remote_url.replace(remote_url);
+            }
+            Err(err) => {
+                log::error!("Failed to get remote url: {:?}", err);
+            }
+        }
+
+        self.update_remote_buttons();
+    }
+
+    async fn test_remote(&self) {
+        let remote_url = match self.entered_remote_url() {
+            Some(remote_url) => remote_url,
+            None => return,
+        };
+
+        let imp = self.imp();
+        imp.test_remote_button.set_sensitive(false);
+        self.set_remote_status(&gettext("Connecting…"));
+
+        let status = NoteRepository::check_remote(&remote_url).await;
+
+        // The result is of a URL that is no longer in the entry
+        if self.entered_remote_url().as_ref() == Some(&remote_url) {
+            if let RemoteStatus::Reachable = status {
+                log::info!("Remote `{}` is reachable", remote_url);
+            } else {
+                log::warn!("Remote `{}` failed the test: {:?}", remote_url, status);
+            }
+            self.set_remote_status(&remote_status_message(&status));
+        }
+
+        self.update_remote_buttons();
+    }
+
+    async fn apply_remote(&self) {
+        let remote_url = match self.entered_remote_url() {
+            Some(remote_url) => remote_url,
+            None => return,
+        };
+
+        let imp = self.imp();
+        imp.remote_url_entry.set_sensitive(false);
+        imp.test_remote_button.set_sensitive(false);
+        imp.apply_remote_button.set_sensitive(false);
+        self.set_remote_status(&gettext("Fetching from the new remote…"));
+
+        match self.repository().set_remote_url(&remote_url).await {
+            Ok(ahead_behind) => {
+                imp.remote_url.replace(Some(remote_url));
+                self.set_remote_status(&ahead_behind_message(ahead_behind));
+            }
+            Err(err) => {
+                log::error!("Failed to set remote url: {:?}", err);
+                self.set_remote_status(&gettext!("Failed to change the remote: {}", err));
+
+                // It may have been changed before the fetch failed
+                if let Ok(current) = self.repository().remote_url().await {
+                    imp.remote_url.replace(current);
+                }
+            }
+        }
+
+        imp.remote_url_entry.set_sensitive(true);
+        self.update_remote_buttons();
+    }
+
+    fn setup_device_settings(&self) {
+        let imp = self.imp();
+        let settings = Application::default().settings();
+
+        settings
+            .bind("lock-timeout", &imp.lock_timeout_spin_button.get(), "value")
+            .build();
+        settings
+            .bind("sidebar-row-detail", &imp.row_detail_row.get(), "selected")
+            .mapping(|variant, _| {
+                let choice = variant.str()?;
+                let position = ROW_DETAIL_CHOICES.iter().position(|c| *c == choice)?;
+                Some((position as u32).to_value())
+            })
+            .set_mapping(|value, _| {
+                let position = value.get::<u32>().ok()?;
+                ROW_DETAIL_CHOICES
+                    .get(position as usize)
+                    .map(|choice| choice.to_variant())
+            })
+            .build();
+        settings
+            .bind(
+                "freeze-order-while-editing",
+                &imp.freeze_order_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind("wrap-mode", &imp.wrap_mode_row.get(), "selected")
+            .mapping(|variant, _| {
+                let choice = variant.str()?;
+                let position = WRAP_MODE_CHOICES.iter().position(|c| *c == choice)?;
+                Some((position as u32).to_value())
+            })
+            .set_mapping(|value, _| {
+                let position = value.get::<u32>().ok()?;
+                WRAP_MODE_CHOICES
+                    .get(position as usize)
+                    .map(|choice| choice.to_variant())
+            })
+            .build();
+        settings
+            .bind(
+                "show-right-margin",
+                &imp.right_margin_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind(
+                "right-margin-position",
+                &imp.right_margin_spin_button.get(),
+                "value",
+            )
+            .build();
+        settings
+            .bind(
+                "show-line-numbers",
+                &imp.line_numbers_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind(
+                "show-heading-marks",
+                &imp.heading_marks_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind("show-change-bar", &imp.change_bar_switch.get(), "active")
+            .build();
+        settings
+            .bind(
+                "limit-content-width",
+                &imp.limit_width_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind(
+                "max-content-width-chars",
+                &imp.max_width_spin_button.get(),
+                "value",
+            )
+            .build();
+        settings
+            .bind("color-palette", &imp.palette_row.get(), "selected")
+            .mapping(|variant, _| {
+                let choice = variant.str()?;
+                let position = PALETTE_CHOICES.iter().position(|c| *c == choice)?;
+                Some((position as u32).to_value())
+            })
+            .set_mapping(|value, _| {
+                let position = value.get::<u32>().ok()?;
+                PALETTE_CHOICES
+                    .get(position as usize)
+                    .map(|choice| choice.to_variant())
+            })
+            .build();
+        settings
+            .bind("is-read-only", &imp.read_only_switch.get(), "active")
+            .build();
+        settings
+            .bind(
+                "commit-message-template",
+                &imp.commit_message_template_entry.get(),
+                "text",
+            )
+            .build();
+        settings
+            .bind(
+                "commit-message-max-changes",
+                &imp.commit_message_max_changes_spin_button.get(),
+                "value",
+            )
+            .build();
+        // Turned off again if the desktop does not allow it
+        settings
+            .bind(
+                "run-in-background",
+                &imp.run_in_background_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind("autostart", &imp.autostart_switch.get(), "active")
+            .build();
+        settings
+            .bind(
+                "delete-immediately",
+                &imp.delete_immediately_switch.get(),
+                "active",
+            )
+            .build();
+    }
+
+    fn setup_notebook_settings(&self) {
+        let imp = self.imp();
+        let notebook_settings = self.notebook_settings();
+
+        notebook_settings
+            .bind_property("note-file-name", &imp.note_file_name_entry.get(), "text")
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+        notebook_settings
+            .bind_property(
+                "note-template",
+                &imp.note_template_text_view.buffer(),
+                "text",
+            )
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+        notebook_settings
+            .bind_property(
+                "front-matter-profile",
+                &imp.front_matter_profile_row.get(),
+                "selected",
+            )
+            .transform_to(|_, value| {
+                let profile: FrontMatterProfile = value.get().unwrap();
+                let position = FrontMatterProfile::ALL.iter().position(|p| *p == profile)?;
+                Some((position as u32).to_value())
+            })
+            .transform_from(|_, value| {
+                let position = value.get::<u32>().ok()?;
+                FrontMatterProfile::ALL
+                    .get(position as usize)
+                    .map(|profile| profile.to_value())
+            })
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+
+        notebook_settings
+            .bind_property(
+                "daily-word-goal",
+                &imp.daily_word_goal_spin_button.get(),
+                "value",
+            )
+            .transform_to(|_, value| {
+                let goal = value.get::<u32>().ok()?;
+                Some(f64::from(goal).to_value())
+            })
+            .transform_from(|_, value| {
+                let goal = value.get::<f64>().ok()?;
+                Some((goal.round() as u32).to_value())
+            })
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+
+        notebook_settings
+            .bind_property(
+                "markdown-note-links",
+                &imp.markdown_note_links_switch.get(),
+                "active",
+            )
+            .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
+            .build();
+
+        // Only taken back from the switch when it is toggled, so titles are not derived again
+        // when the setting is changed on another device while this is open
+        let switch = imp.derive_title_from_heading_switch.get();
+        notebook_settings
+            .bind_property("derive-title-from-heading", &switch, "active")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        switch.connect_active_notify(clone!(@weak self as obj => move |switch| {
+            let notebook_settings = obj.notebook_settings();
+            let is_active = switch.is_active();
+
+            if is_active == notebook_settings.derive_title_from_heading() {
+                return;
+            }
+
+            notebook_settings.set_derive_title_from_heading(is_active);
+
+            if is_active {
+                let main_window = Application::default().main_window();
+                main_window.run_with_session(clone!(@weak obj => move |session| {
+                    spawn!(clone!(@weak obj, @weak session => async move {
+                        obj.derive_titles_from_headings(&session).await;
+                    }));
+                }));
+            }
+        }));
+
+        imp.rewrite_front_matter_button
+            .connect_clicked(clone!(@weak self as obj => move |_| {
+                let main_window = Application::default().main_window();
+                main_window.run_with_session(clone!(@weak obj => move |session| {
+                    spawn!(clone!(@weak obj, @weak session => async move {
+                        obj.rewrite_front_matter(&session).await;
+                    }));
+                }));
+            }));
+    }
+
+    async fn rewrite_front_matter(&self, session: &Session) {
+        let button = self.imp().rewrite_front_matter_button.get();
+        button.set_sensitive(false);
+
+        let message = match session.rewrite_front_matter().await {
+            Ok(n_notes) => ngettext!(
+                "Converted {} note",
+                "Converted {} notes",
+                n_notes as u32,
+                n_notes
+            ),
+            Err(err) => {
+                log::error!("Failed to rewrite front matter: {:?}", err);
+                gettext("Failed to convert some of the notes")
+            }
+        };
+        self.add_toast(&adw::Toast::new(&message));
+
+        button.set_sensitive(true);
+    }
+
+    async fn derive_titles_from_headings(&self, session: &Session) {
+        let message = match session.derive_titles_from_headings().await {
+            Ok(0) => gettext("No titles had to be changed"),
+            Ok(n_changed) => ngettext!(
+                "Changed {} title to its first heading",
+                "Changed {} titles to their first heading",
+                n_changed as u32,
+                n_changed
+            ),
+            Err(err) => {
+                log::error!("Failed to derive titles from headings: {:?}", err);
+                gettext("Failed to change the titles of some of the notes")
+            }
+        };
+        self.add_toast(&adw::Toast::new(&message));
+    }
+
+    fn setup_repository(&self) {
+        let imp = self.imp();
+
+        imp.remote_url_entry
+            .connect_changed(clone!(@weak self as obj => move |_| {
+                obj.set_remote_status("");
+                obj.update_remote_buttons();
+            }));
+
+        imp.test_remote_button
+            .connect_clicked(clone!(@weak self as obj => move |_| {
+                spawn!(clone!(@weak obj => async move {
+                    obj.test_remote().await;
+                }));
+            }));
+
+        imp.apply_remote_button
+            .connect_clicked(clone!(@weak self as obj => move |_| {
+                spawn!(clone!(@weak obj => async move {
+                    obj.apply_remote().await;
+                }));
+            }));
+
+        imp.maintenance_button
+            .connect_clicked(clone!(@weak self as obj => move |_| {
+                let main_window = Application::default().main_window();
+                main_window.run_with_session(clone!(@weak obj => move |session| {
+                    session.show_maintenance_dialog(&obj);
+                }));
+            }));
+
+        self.update_remote_buttons();
+        spawn!(clone!(@weak self as obj => async move {
+            obj.load_remote_url().await;
+        }));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ahead_behind_messages() {
+        let message = |n_ahead, n_behind| ahead_behind_message(AheadBehind { n_ahead, n_behind });
+
+        assert_eq!(message(0, 0), "Up to date with the remote");
+        assert_eq!(message(1, 0), "1 commit to push");
+        assert_eq!(message(0, 3), "3 commits to pull");
+        assert_eq!(message(2, 1), "2 commits to push, 1 commit to pull");
+    }
+}
@@ -0,0 +1,159 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::{cell::RefCell, time::Duration};
+
+use crate::core::CloneProgress;
+
+/// How often the progress bar moves while it is not known how much there is to do
+const PULSE_INTERVAL: Duration = Duration::from_millis(100);
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/preparing-page.ui")]
+    pub struct PreparingPage {
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub progress_label: TemplateChild<gtk::Label>,
+
+        /// Cancels the clone in progress, if any
+        pub cancellable: RefCell<Option<gio::Cancellable>>,
+        pub pulse_source_id: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PreparingPage {
+        const NAME: &'static str = "NwtyPreparingPage";
+        type Type = super::PreparingPage;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("preparing-page.cancel", None, move |obj, _, _| {
+                obj.cancel();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PreparingPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.action_set_enabled("preparing-page.cancel", false);
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            obj.stop_pulsing();
+        }
+    }
+
+    impl WidgetImpl for PreparingPage {}
+    impl BinImpl for PreparingPage {}
+}
+
+glib::wrapper! {
+    /// Shown while the notebook is cloned and its notes are loaded for the first time
+    pub struct PreparingPage(ObjectSubclass<imp::PreparingPage>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl PreparingPage {
+    /// Show that cloning starts, returning what cancels it when the user asks to
+    pub fn start_cloning(&self) -> gio::Cancellable {
+        let imp = self.imp();
+
+        let cancellable = gio::Cancellable::new();
+        imp.cancellable.replace(Some(cancellable.clone()));
+        self.action_set_enabled("preparing-page.cancel", true);
+
+        imp.progress_label.set_label(&gettext("Connecting…"));
+        self.start_pulsing();
+
+        cancellable
+    }
+
+    pub fn set_progress(&self, progress: &CloneProgress) {
+        let imp = self.imp();
+
+        imp.progress_label.set_label(&progress.message());
+
+        match progress.fraction() {
+            Some(fraction) => {
+                self.stop_pulsing();
+                imp.progress_bar.set_fraction(fraction);
+            }
+            None => self.start_pulsing(),
+        }
+    }
+
+    /// Show that the notes are loaded, which can't be cancelled
+    pub fn set_loading(&self) {
+        self.imp().cancellable.replace(None);
+        self.action_set_enabled("preparing-page.cancel", false);
+
+        self.imp()
+            .progress_label
+            .set_label(&gettext("Loading notes…"));
+        self.start_pulsing();
+    }
+
+    /// Stop cloning, if it was not done yet
+    pub fn cancel(&self) {
+        if let Some(cancellable) = self.imp().cancellable.take() {
+            log::info!("Cancelling clone...");
+            cancellable.cancel();
+        }
+
+        self.action_set_enabled("preparing-page.cancel", false);
+    }
+
+    /// Back to how it was before cloning, once it is done or failed
+    pub fn reset(&self) {
+        let imp = self.imp();
+
+        imp.cancellable.replace(None);
+        self.action_set_enabled("preparing-page.cancel", false);
+        self.stop_pulsing();
+        imp.progress_bar.set_fraction(0.0);
+        imp.progress_label.set_label("");
+    }
+
+    fn start_pulsing(&self) {
+        let imp = self.imp();
+
+        if imp.pulse_source_id.borrow().is_some() {
+            return;
+        }
+
+        imp.progress_bar.pulse();
+        let source_id = glib::timeout_add_local(
+            PULSE_INTERVAL,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                obj.imp().progress_bar.pulse();
+                glib::Continue(true)
+            }),
+        );
+        imp.pulse_source_id.replace(Some(source_id));
+    }
+
+    fn stop_pulsing(&self) {
+        if let Some(source_id) = self.imp().pulse_source_id.take() {
+            source_id.remove();
+        }
+    }
+}
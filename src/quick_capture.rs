@@ -0,0 +1,185 @@
+use adw::subclass::prelude::*;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+/// Note written in the [`QuickCapture`] dialog
+#[derive(Debug, Default, PartialEq)]
+pub struct Capture {
+    pub title: String,
+    pub content: String,
+    pub tag_names: Vec<String>,
+}
+
+impl Capture {
+    /// The first non-empty line of `text` becomes the title and the rest becomes the content.
+    /// `tags_text` is a comma-separated list of tag names.
+    pub fn parse(text: &str, tags_text: &str) -> Self {
+        let text = text.trim_start();
+        let (first_line, rest) = text.split_once('\n').unwrap_or((text, ""));
+
+        let title = first_line.trim_start_matches('#').trim().to_string();
+        let content = rest.trim_start_matches('\n').trim_end().to_string();
+
+        let mut tag_names: Vec<String> = Vec::new();
+        for name in tags_text.split(',').map(str::trim) {
+            if !name.is_empty() && !tag_names.iter().any(|other| other == name) {
+                tag_names.push(name.to_string());
+            }
+        }
+
+        Self {
+            title,
+            content,
+            tag_names,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty() && self.content.is_empty()
+    }
+}
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/quick-capture.ui")]
+    pub struct QuickCapture {
+        #[template_child]
+        pub text_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub tags_entry: TemplateChild<gtk::Entry>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickCapture {
+        const NAME: &'static str = "NwtyQuickCapture";
+        type Type = super::QuickCapture;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("quick-capture.save", None, move |obj, _, _| {
+                obj.emit_by_name::<()>("captured", &[]);
+                obj.close();
+            });
+
+            klass.install_action("quick-capture.cancel", None, move |obj, _, _| {
+                obj.close();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for QuickCapture {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("captured", &[], <()>::static_type().into()).build()]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.action_set_enabled("quick-capture.save", false);
+
+            self.text_view
+                .buffer()
+                .connect_changed(clone!(@weak obj => move |_| {
+                    obj.update_save_action();
+                }));
+        }
+    }
+
+    impl WidgetImpl for QuickCapture {}
+    impl WindowImpl for QuickCapture {}
+    impl AdwWindowImpl for QuickCapture {}
+}
+
+glib::wrapper! {
+    pub struct QuickCapture(ObjectSubclass<imp::QuickCapture>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl QuickCapture {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create QuickCapture.")
+    }
+
+    pub fn capture(&self) -> Capture {
+        let imp = self.imp();
+        let buffer = imp.text_view.buffer();
+        let (start, end) = buffer.bounds();
+
+        Capture::parse(&buffer.text(&start, &end, false), &imp.tags_entry.text())
+    }
+
+    /// Emitted when the written note is confirmed. Use [`QuickCapture::capture`] to get it.
+    pub fn connect_captured<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("captured", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    fn update_save_action(&self) {
+        self.action_set_enabled("quick-capture.save", !self.capture().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let capture = Capture::parse("Groceries\nMilk\nEggs\n", "Home, Errands");
+        assert_eq!(capture.title, "Groceries");
+        assert_eq!(capture.content, "Milk\nEggs");
+        assert_eq!(capture.tag_names, vec!["Home", "Errands"]);
+    }
+
+    #[test]
+    fn parse_heading_title() {
+        let capture = Capture::parse("\n\n# Idea\n\nSomething", "");
+        assert_eq!(capture.title, "Idea");
+        assert_eq!(capture.content, "Something");
+        assert!(capture.tag_names.is_empty());
+    }
+
+    #[test]
+    fn parse_single_line() {
+        let capture = Capture::parse("Call back", "");
+        assert_eq!(capture.title, "Call back");
+        assert_eq!(capture.content, "");
+    }
+
+    #[test]
+    fn parse_tags() {
+        let capture = Capture::parse("A", " A ,, B,A ,");
+        assert_eq!(capture.tag_names, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn is_empty() {
+        assert!(Capture::parse("  \n\n ", "A").is_empty());
+        assert!(!Capture::parse("A", "").is_empty());
+    }
+}
@@ -0,0 +1,526 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use indexmap::IndexMap;
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+};
+
+use super::{note_manager::AttachmentUsage, Session};
+use crate::{
+    core::{markdown_reference, FileType},
+    spawn, spawn_blocking,
+};
+
+/// Thumbnails kept in memory, which are dropped from the least recently shown
+const MAX_THUMBNAILS: usize = 128;
+/// Width and height the thumbnails are scaled to fit in
+const THUMBNAIL_SIZE: i32 = 48;
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/attachments-dialog.ui")]
+    pub struct AttachmentsDialog {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub loading_page: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub attachments_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub attachments_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub orphans_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub orphans_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub no_orphans_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub back_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub clean_up_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub delete_orphans_button: TemplateChild<gtk::Button>,
+
+        pub session: OnceCell<Session>,
+        /// Increased on each listing, so the results of an older one are dropped
+        pub load_generation: Cell<u32>,
+        /// Whether only the orphans are listed, to choose which ones to delete
+        pub is_cleaning_up: Cell<bool>,
+        pub orphan_check_buttons: RefCell<Vec<(gtk::CheckButton, gio::File)>>,
+        /// From the least to the most recently shown
+        pub thumbnails: RefCell<IndexMap<PathBuf, gdk::Texture>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttachmentsDialog {
+        const NAME: &'static str = "NwtyAttachmentsDialog";
+        type Type = super::AttachmentsDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AttachmentsDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the attachments are from",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.clean_up_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.set_is_cleaning_up(true);
+                }));
+
+            self.back_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.set_is_cleaning_up(false);
+                }));
+
+            self.delete_orphans_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.delete_selected_orphans();
+                }));
+
+            self.clean_up_button
+                .set_visible(!obj.session().note_manager().is_read_only());
+
+            obj.load();
+        }
+    }
+
+    impl WidgetImpl for AttachmentsDialog {}
+    impl WindowImpl for AttachmentsDialog {}
+    impl AdwWindowImpl for AttachmentsDialog {}
+}
+
+glib::wrapper! {
+    /// Shows the attachments of the notes and the notes using them, letting the user delete
+    /// the ones that no note uses
+    pub struct AttachmentsDialog(ObjectSubclass<imp::AttachmentsDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl AttachmentsDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create AttachmentsDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.session().note_manager().is_read_only()
+    }
+
+    fn set_is_cleaning_up(&self, is_cleaning_up: bool) {
+        let imp = self.imp();
+        imp.is_cleaning_up.set(is_cleaning_up);
+
+        imp.back_button.set_visible(is_cleaning_up);
+        imp.clean_up_button.set_visible(!is_cleaning_up);
+        imp.delete_orphans_button.set_visible(is_cleaning_up);
+
+        self.load();
+    }
+
+    fn load(&self) {
+        let imp = self.imp();
+        let generation = imp.load_generation.get().wrapping_add(1);
+        imp.load_generation.set(generation);
+
+        imp.stack.set_visible_child(&imp.loading_page.get());
+        imp.delete_orphans_button.set_sensitive(false);
+
+        spawn!(clone!(@weak self as obj => async move {
+            let usages = obj
+                .session()
+                .note_manager()
+                .attachment_usages()
+                .await
+                .unwrap_or_else(|err| {
+                    log::error!("Failed to list attachments: {:?}", err);
+                    Vec::new()
+                });
+
+            if obj.imp().load_generation.get() != generation {
+                return;
+            }
+
+            if obj.imp().is_cleaning_up.get() {
+                let orphans = usages
+                    .into_iter()
+                    .filter(AttachmentUsage::is_orphan)
+                    .collect::<Vec<_>>();
+                obj.show_orphans(&orphans);
+            } else {
+                obj.show_attachments(&usages);
+            }
+        }));
+    }
+
+    fn show_attachments(&self, usages: &[AttachmentUsage]) {
+        let imp = self.imp();
+
+        clear_list_box(&imp.attachments_list);
+
+        for usage in usages {
+            imp.attachments_list
+                .append(&self.build_attachment_row(usage));
+        }
+
+        if usages.is_empty() {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.attachments_page.get());
+        }
+    }
+
+    fn show_orphans(&self, orphans: &[AttachmentUsage]) {
+        let imp = self.imp();
+
+        clear_list_box(&imp.orphans_list);
+
+        let mut orphan_check_buttons = Vec::new();
+        for orphan in orphans {
+            let check_button = gtk::CheckButton::builder()
+                .valign(gtk::Align::Center)
+                .active(true)
+                .build();
+            check_button.connect_toggled(clone!(@weak self as obj => move |_| {
+                obj.update_delete_orphans_button();
+            }));
+
+            let row = adw::ActionRow::builder()
+                .title(&file_name(&orphan.file))
+                .subtitle(&glib::format_size(orphan.size))
+                .activatable_widget(&check_button)
+                .build();
+            row.add_prefix(&check_button);
+            row.add_suffix(&self.build_thumbnail(&orphan.file));
+            imp.orphans_list.append(&row);
+
+            orphan_check_buttons.push((check_button, orphan.file.clone()));
+        }
+        imp.orphan_check_buttons.replace(orphan_check_buttons);
+        self.update_delete_orphans_button();
+
+        if orphans.is_empty() {
+            imp.stack.set_visible_child(&imp.no_orphans_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.orphans_page.get());
+        }
+    }
+
+    fn build_attachment_row(&self, usage: &AttachmentUsage) -> adw::ActionRow {
+        let usage_text = if usage.is_orphan() {
+            gettext("Not used by any note")
+        } else {
+            let titles = usage
+                .notes
+                .iter()
+                .map(|note| {
+                    let title = note.metadata().title();
+                    if title.is_empty() {
+                        gettext("Untitled Note")
+                    } else {
+                        title
+                    }
+                })
+                .collect::<Vec<_>>();
+            gettext!("Used by {}", titles.join(", "))
+        };
+
+        let row = adw::ActionRow::builder()
+            .title(&file_name(&usage.file))
+            .subtitle(&format!(
+                "{} · {}",
+                glib::format_size(usage.size),
+                usage_text
+            ))
+            .build();
+        row.add_prefix(&self.build_thumbnail(&usage.file));
+
+        let file = &usage.file;
+
+        let open_button = row_button("external-link-symbolic", &gettext("Open"));
+        open_button.connect_clicked(clone!(@weak self as obj, @strong file => move |_| {
+            obj.open(&file);
+        }));
+        row.add_suffix(&open_button);
+
+        let copy_button = row_button("edit-copy-symbolic", &gettext("Copy Link"));
+        copy_button.connect_clicked(clone!(@weak self as obj, @strong file => move |_| {
+            obj.copy_reference(&file);
+        }));
+        row.add_suffix(&copy_button);
+
+        if !self.is_read_only() {
+            let delete_button = row_button("user-trash-symbolic", &gettext("Delete"));
+            let n_notes = usage.notes.len();
+            delete_button.connect_clicked(clone!(@weak self as obj, @strong file => move |_| {
+                obj.show_delete_dialog(&file, n_notes);
+            }));
+            row.add_suffix(&delete_button);
+        }
+
+        row
+    }
+
+    /// An icon for `file`, replaced by a thumbnail of it once loaded if it is a picture
+    fn build_thumbnail(&self, file: &gio::File) -> gtk::Image {
+        let image = gtk::Image::builder()
+            .icon_name("mail-attachment-symbolic")
+            .pixel_size(THUMBNAIL_SIZE)
+            .css_classes(vec!["attachments-dialog-thumbnail".into()])
+            .build();
+
+        let path = match file.path() {
+            Some(path) => path,
+            None => return image,
+        };
+
+        if let Some(texture) = self.cached_thumbnail(&path) {
+            image.set_from_paintable(Some(&texture));
+            return image;
+        }
+
+        spawn!(clone!(@weak self as obj, @weak image => async move {
+            let thumbnail_path = path.clone();
+            let texture = spawn_blocking!(move || load_thumbnail(&thumbnail_path)).await;
+
+            if let Some(texture) = texture {
+                image.set_from_paintable(Some(&texture));
+                obj.cache_thumbnail(path, texture);
+            }
+        }));
+
+        image
+    }
+
+    fn cached_thumbnail(&self, path: &Path) -> Option<gdk::Texture> {
+        let mut thumbnails = self.imp().thumbnails.borrow_mut();
+        let texture = thumbnails.shift_remove(path)?;
+        thumbnails.insert(path.to_path_buf(), texture.clone());
+        Some(texture)
+    }
+
+    fn cache_thumbnail(&self, path: PathBuf, texture: gdk::Texture) {
+        let mut thumbnails = self.imp().thumbnails.borrow_mut();
+        thumbnails.shift_remove(&path);
+        thumbnails.insert(path, texture);
+
+        while thumbnails.len() > MAX_THUMBNAILS {
+            thumbnails.shift_remove_index(0);
+        }
+    }
+
+    fn update_delete_orphans_button(&self) {
+        let imp = self.imp();
+        let has_selected = imp
+            .orphan_check_buttons
+            .borrow()
+            .iter()
+            .any(|(check_button, _)| check_button.is_active());
+        imp.delete_orphans_button.set_sensitive(has_selected);
+    }
+
+    fn open(&self, file: &gio::File) {
+        let file_uri = file.uri();
+        let res = gio::AppInfo::launch_default_for_uri(&file_uri, gio::AppLaunchContext::NONE);
+
+        if let Err(err) = res {
+            log::error!("Failed to open file at uri `{}`: {:?}", file_uri, err);
+            self.show_toast(&gettext("Failed to open attachment"));
+        }
+    }
+
+    fn copy_reference(&self, file: &gio::File) {
+        let is_picture = FileType::for_file(file) == FileType::Bitmap;
+        let reference = markdown_reference(&file_name(file), is_picture);
+        self.clipboard().set_text(&reference);
+
+        self.show_toast(&gettext("Copied link to attachment"));
+    }
+
+    fn show_delete_dialog(&self, file: &gio::File, n_notes: usize) {
+        let secondary_text = if n_notes == 0 {
+            gettext("No note uses it.")
+        } else {
+            ngettext!(
+                "It is used by {} note, where it will no longer be shown.",
+                "It is used by {} notes, where it will no longer be shown.",
+                n_notes as u32,
+                n_notes
+            )
+        };
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Delete Attachment?"))
+            .secondary_text(&secondary_text)
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .transient_for(self)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Delete"), gtk::ResponseType::Accept)
+            .add_css_class("destructive-action");
+
+        dialog.connect_response(
+            clone!(@weak self as obj, @strong file => move |dialog, response| {
+                dialog.destroy();
+
+                if response == gtk::ResponseType::Accept {
+                    obj.delete(vec![file.clone()]);
+                }
+            }),
+        );
+        dialog.present();
+    }
+
+    fn delete_selected_orphans(&self) {
+        let files = self
+            .imp()
+            .orphan_check_buttons
+            .borrow()
+            .iter()
+            .filter(|(check_button, _)| check_button.is_active())
+            .map(|(_, file)| file.clone())
+            .collect::<Vec<_>>();
+
+        if !files.is_empty() {
+            self.delete(files);
+        }
+    }
+
+    fn delete(&self, files: Vec<gio::File>) {
+        let imp = self.imp();
+        imp.stack.set_visible_child(&imp.loading_page.get());
+
+        spawn!(clone!(@weak self as obj => async move {
+            let session = obj.session();
+
+            match session.delete_attachments(&files).await {
+                Ok(()) => {
+                    obj.show_toast(&ngettext!(
+                        "Deleted {} attachment",
+                        "Deleted {} attachments",
+                        files.len() as u32,
+                        files.len()
+                    ));
+                }
+                Err(err) => {
+                    if !session.report_read_only(&err) {
+                        log::error!("Failed to delete attachments: {:?}", err);
+                        obj.show_toast(&gettext("Failed to delete some attachments"));
+                    }
+                }
+            }
+
+            obj.load();
+        }));
+    }
+
+    fn show_toast(&self, title: &str) {
+        self.imp().toast_overlay.add_toast(&adw::Toast::new(title));
+    }
+}
+
+fn row_button(icon_name: &str, tooltip_text: &str) -> gtk::Button {
+    gtk::Button::builder()
+        .icon_name(icon_name)
+        .tooltip_text(tooltip_text)
+        .valign(gtk::Align::Center)
+        .css_classes(vec!["flat".into()])
+        .build()
+}
+
+fn clear_list_box(list_box: &gtk::ListBox) {
+    while let Some(child) = list_box.first_child() {
+        list_box.remove(&child);
+    }
+}
+
+fn file_name(file: &gio::File) -> String {
+    file.basename()
+        .map(|name| name.display().to_string())
+        .unwrap_or_default()
+}
+
+/// A thumbnail of the picture at `path`, read and scaled down in one go so large pictures are
+/// never fully loaded, or `None` if it is not a picture
+fn load_thumbnail(path: &Path) -> Option<gdk::Texture> {
+    if FileType::for_file(&gio::File::for_path(path)) != FileType::Bitmap {
+        return None;
+    }
+
+    match gtk::gdk_pixbuf::Pixbuf::from_file_at_scale(path, THUMBNAIL_SIZE, THUMBNAIL_SIZE, true) {
+        Ok(pixbuf) => Some(gdk::Texture::for_pixbuf(&pixbuf)),
+        Err(err) => {
+            log::warn!(
+                "Failed to load thumbnail of `{}`: {:?}",
+                path.display(),
+                err
+            );
+            None
+        }
+    }
+}
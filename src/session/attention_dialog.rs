@@ -0,0 +1,185 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use super::Session;
+use crate::model::{AttentionEntry, AttentionList, Note};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/attention-dialog.ui")]
+    pub struct AttentionDialog {
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub list_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+
+        pub session: OnceCell<Session>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AttentionDialog {
+        const NAME: &'static str = "NwtyAttentionDialog";
+        type Type = super::AttentionDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AttentionDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session of the notes to check",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_box();
+            obj.update_stack();
+        }
+    }
+
+    impl WidgetImpl for AttentionDialog {}
+    impl WindowImpl for AttentionDialog {}
+    impl AdwWindowImpl for AttentionDialog {}
+}
+
+glib::wrapper! {
+    /// Lists the notes whose links were left as they are when the note they link to was
+    /// renamed, letting the user go check each of them
+    pub struct AttentionDialog(ObjectSubclass<imp::AttentionDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl AttentionDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create AttentionDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn attention_list(&self) -> AttentionList {
+        self.session().note_manager().attention_list()
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+
+        if self.attention_list().n_items() > 0 {
+            imp.stack.set_visible_child(&imp.list_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        }
+    }
+
+    fn setup_list_box(&self) {
+        let attention_list = self.attention_list();
+
+        self.imp().list_box.bind_model(
+            Some(&attention_list),
+            clone!(@weak self as obj => @default-panic, move |item| {
+                let entry = item.downcast_ref::<AttentionEntry>().unwrap();
+                obj.build_row(entry).upcast()
+            }),
+        );
+
+        attention_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            obj.update_stack();
+        }));
+    }
+
+    fn build_row(&self, entry: &AttentionEntry) -> adw::ActionRow {
+        let note = entry.note();
+
+        let row = adw::ActionRow::builder()
+            .title(&note.metadata().title())
+            // Translators: The title of the notes the note links to
+            .subtitle(&gettext!(
+                "Links to “{}”, which more than one note is titled",
+                entry.linked_title()
+            ))
+            .build();
+
+        let show_button = row_button("go-next-symbolic", &gettext("Show Note"));
+        show_button.connect_clicked(clone!(@weak self as obj, @weak note => move |_| {
+            obj.show_note(&note);
+        }));
+        row.add_suffix(&show_button);
+
+        let checked_button = row_button("object-select-symbolic", &gettext("Mark as Checked"));
+        checked_button.connect_clicked(clone!(@weak self as obj, @weak note => move |_| {
+            obj.attention_list().remove(&note);
+        }));
+        row.add_suffix(&checked_button);
+
+        row
+    }
+
+    fn show_note(&self, note: &Note) {
+        self.session().set_selected_note(Some(note.clone()));
+        self.close();
+    }
+}
+
+fn row_button(icon_name: &str, tooltip_text: &str) -> gtk::Button {
+    gtk::Button::builder()
+        .icon_name(icon_name)
+        .tooltip_text(tooltip_text)
+        .valign(gtk::Align::Center)
+        .css_classes(vec!["flat".into()])
+        .build()
+}
@@ -0,0 +1,127 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use std::{cell::RefCell, ops::Range};
+
+use crate::model::{Note, Tag};
+
+#[derive(Debug, Clone, glib::Boxed)]
+#[boxed_type(name = "NwtyCommandPaletteItemKind")]
+pub enum ItemKind {
+    /// Select the note
+    Note(Note),
+    /// Select the note and the match in its content at the byte range
+    NoteMatch(Note, Range<usize>),
+    /// Show the notes with the tag
+    Tag(Tag),
+    /// Activate the action with the detailed name
+    Action(String),
+}
+
+impl Default for ItemKind {
+    fn default() -> Self {
+        Self::Action(String::new())
+    }
+}
+
+impl ItemKind {
+    pub const fn icon_name(&self) -> &'static str {
+        match self {
+            Self::Note(_) => "text-x-generic-symbolic",
+            Self::NoteMatch(..) => "edit-find-symbolic",
+            Self::Tag(_) => "tag-symbolic",
+            Self::Action(_) => "system-run-symbolic",
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct Item {
+        pub kind: RefCell<ItemKind>,
+        pub title: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Item {
+        const NAME: &'static str = "NwtyCommandPaletteItem";
+        type Type = super::Item;
+    }
+
+    impl ObjectImpl for Item {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecBoxed::new(
+                        "kind",
+                        "Kind",
+                        "What activating this item does",
+                        ItemKind::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "title",
+                        "Title",
+                        "Title of this item",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "icon-name",
+                        "Icon Name",
+                        "Icon for the kind of this item",
+                        None,
+                        glib::ParamFlags::READABLE,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "kind" => {
+                    let kind = value.get().unwrap();
+                    self.kind.replace(kind);
+                }
+                "title" => {
+                    let title: Option<String> = value.get().unwrap();
+                    self.title.replace(title.unwrap_or_default());
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "kind" => self.kind.borrow().to_value(),
+                "title" => self.title.borrow().to_value(),
+                "icon-name" => self.kind.borrow().icon_name().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct Item(ObjectSubclass<imp::Item>);
+}
+
+impl Item {
+    pub fn new(kind: &ItemKind, title: &str) -> Self {
+        glib::Object::new(&[("kind", kind), ("title", &title)])
+            .expect("Failed to create CommandPaletteItem.")
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        self.imp().kind.borrow().clone()
+    }
+}
@@ -0,0 +1,424 @@
+mod item;
+
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use self::item::{Item, ItemKind};
+use super::Session;
+use crate::utils::fuzzy;
+
+/// Keep the list short, so it can be rebuilt on every keystroke
+const MAX_RESULTS: usize = 50;
+/// Added to the score of the most recently opened note, and less for the older ones
+const RECENT_NOTE_BONUS: i64 = 64;
+
+const TAG_PREFIX: char = '#';
+const ACTION_PREFIX: char = '>';
+
+#[derive(Debug, PartialEq)]
+enum Query<'a> {
+    Notes(&'a str),
+    Tags(&'a str),
+    Actions(&'a str),
+}
+
+impl<'a> Query<'a> {
+    fn parse(text: &'a str) -> Self {
+        let text = text.trim_start();
+
+        if let Some(tag_name) = text.strip_prefix(TAG_PREFIX) {
+            Self::Tags(tag_name.trim())
+        } else if let Some(action_name) = text.strip_prefix(ACTION_PREFIX) {
+            Self::Actions(action_name.trim())
+        } else {
+            Self::Notes(text.trim())
+        }
+    }
+}
+
+/// Actions that can be run from the palette, with their detailed name and their title
+fn actions() -> Vec<(&'static str, String)> {
+    vec![
+        ("session.create-note", gettext("New Note")),
+        ("session.sync", gettext("Sync Notes")),
+        ("session.edit-tags", gettext("Edit Tags")),
+        ("session.show-graph", gettext("Show Note Graph")),
+        ("session.merge-selected-note", gettext("Merge Note Into…")),
+        ("app.quick-capture", gettext("Quick Capture")),
+        ("app.lock", gettext("Lock Notes")),
+        ("session.set-lock-pin", gettext("Set Lock PIN")),
+        ("win.toggle-fullscreen", gettext("Toggle Fullscreen")),
+        ("win.show-help-overlay", gettext("Keyboard Shortcuts")),
+        ("app.about", gettext("About Noteworthy")),
+        ("app.quit", gettext("Quit")),
+    ]
+}
+
+/// Bonus of the note at `index` in the list of `n_recent` recently opened notes, which
+/// starts with the most recent one
+fn recency_bonus(index: Option<usize>, n_recent: usize) -> i64 {
+    index.map_or(0, |index| {
+        RECENT_NOTE_BONUS * (n_recent - index) as i64 / n_recent as i64
+    })
+}
+
+/// Keep the candidates matching `query`, best match first. These are the value to use on
+/// activation, the title to match, and a bonus to add to its score.
+fn rank<T>(
+    query: &str,
+    candidates: impl IntoIterator<Item = (T, String, i64)>,
+) -> Vec<(T, String)> {
+    let mut matches = candidates
+        .into_iter()
+        .filter_map(|(value, title, bonus)| {
+            fuzzy::score(query, &title).map(|score| (score + bonus, value, title))
+        })
+        .collect::<Vec<_>>();
+
+    // Stable, so candidates with the same score stay in their original order
+    matches.sort_by(|(score_a, ..), (score_b, ..)| score_b.cmp(score_a));
+    matches.truncate(MAX_RESULTS);
+
+    matches
+        .into_iter()
+        .map(|(_, value, title)| (value, title))
+        .collect()
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/command-palette.ui")]
+    pub struct CommandPalette {
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub list_view: TemplateChild<gtk::ListView>,
+
+        pub session: OnceCell<Session>,
+        pub results: OnceCell<gio::ListStore>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CommandPalette {
+        const NAME: &'static str = "NwtyCommandPalette";
+        type Type = super::CommandPalette;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CommandPalette {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the results are from",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_view();
+            obj.setup_signals();
+            obj.update_results();
+        }
+    }
+
+    impl WidgetImpl for CommandPalette {}
+    impl WindowImpl for CommandPalette {}
+    impl AdwWindowImpl for CommandPalette {}
+}
+
+glib::wrapper! {
+    pub struct CommandPalette(ObjectSubclass<imp::CommandPalette>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl CommandPalette {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create CommandPalette.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn results(&self) -> &gio::ListStore {
+        self.imp().results.get().unwrap()
+    }
+
+    fn update_results(&self) {
+        let session = self.session();
+        let note_manager = session.note_manager();
+        let text = self.imp().search_entry.text();
+
+        let results = match Query::parse(&text) {
+            Query::Notes(query) => {
+                let recent_notes = session.recent_notes();
+                let candidates = note_manager
+                    .note_list()
+                    .iter()
+                    .filter(|note| !note.metadata().is_trashed())
+                    .map(|note| {
+                        let index = recent_notes.iter().position(|recent| recent == &note);
+                        let bonus = recency_bonus(index, recent_notes.len());
+                        let title = note.metadata().title();
+                        (ItemKind::Note(note), title, bonus)
+                    });
+                let mut results = rank(query, candidates);
+
+                // Then the notes with the words in their content but not in their title
+                if let Some(matches) = note_manager.search_contents(query) {
+                    let content_results = matches
+                        .into_iter()
+                        .filter(|(note, _)| {
+                            !note.metadata().is_trashed()
+                                && !results.iter().any(|(kind, _)| {
+                                    matches!(kind, ItemKind::Note(other) if other == note)
+                                })
+                        })
+                        .filter_map(|(note, index_match)| {
+                            let range = index_match.offsets.first()?.clone();
+                            let title = note.metadata().title();
+                            Some((ItemKind::NoteMatch(note, range), title))
+                        })
+                        .take(MAX_RESULTS.saturating_sub(results.len()))
+                        .collect::<Vec<_>>();
+                    results.extend(content_results);
+                }
+
+                results
+            }
+            Query::Tags(query) => {
+                let candidates = note_manager.tag_list().iter().map(|tag| {
+                    let name = tag.name();
+                    (ItemKind::Tag(tag), name, 0)
+                });
+                rank(query, candidates)
+            }
+            Query::Actions(query) => {
+                let candidates = actions()
+                    .into_iter()
+                    .map(|(name, title)| (ItemKind::Action(name.to_string()), title, 0));
+                rank(query, candidates)
+            }
+        };
+
+        let items = results
+            .into_iter()
+            .map(|(kind, title)| {
+                let title = if title.is_empty() {
+                    gettext("Untitled Note")
+                } else {
+                    title
+                };
+                Item::new(&kind, &title).upcast()
+            })
+            .collect::<Vec<glib::Object>>();
+
+        let results = self.results();
+        results.splice(0, results.n_items(), &items);
+    }
+
+    fn activate_item(&self, item: &Item) {
+        let session = self.session();
+
+        match item.kind() {
+            ItemKind::Note(note) => session.set_selected_note(Some(note)),
+            ItemKind::NoteMatch(note, range) => session.select_note_match(note, range),
+            ItemKind::Tag(tag) => session.select_tag(&tag),
+            ItemKind::Action(name) => {
+                if let Err(err) = session.activate_action(&name, None) {
+                    log::error!("Failed to activate action `{}`: {:?}", name, err);
+                }
+            }
+        }
+
+        self.close();
+    }
+
+    fn activate_selected(&self) {
+        let selection_model = self
+            .imp()
+            .list_view
+            .model()
+            .and_then(|model| model.downcast::<gtk::SingleSelection>().ok());
+        let selected_item = selection_model
+            .and_then(|model| model.selected_item())
+            .and_then(|item| item.downcast::<Item>().ok());
+
+        if let Some(item) = selected_item {
+            self.activate_item(&item);
+        }
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        imp.search_entry
+            .connect_search_changed(clone!(@weak self as obj => move |_| {
+                obj.update_results();
+            }));
+
+        imp.search_entry
+            .connect_activate(clone!(@weak self as obj => move |_| {
+                obj.activate_selected();
+            }));
+
+        imp.search_entry
+            .connect_stop_search(clone!(@weak self as obj => move |_| {
+                obj.close();
+            }));
+
+        imp.list_view
+            .connect_activate(clone!(@weak self as obj => move |list_view, position| {
+                let item = list_view
+                    .model()
+                    .and_then(|model| model.item(position))
+                    .and_then(|item| item.downcast::<Item>().ok());
+
+                if let Some(item) = item {
+                    obj.activate_item(&item);
+                }
+            }));
+    }
+
+    fn setup_list_view(&self) {
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(|_, list_item| {
+            let icon = gtk::Image::new();
+            let label = gtk::Label::builder()
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+
+            let row = gtk::Box::builder().spacing(12).build();
+            row.append(&icon);
+            row.append(&label);
+
+            let item_expression = list_item.property_expression("item");
+            item_expression.chain_property::<Item>("icon-name").bind(
+                &icon,
+                "icon-name",
+                glib::Object::NONE,
+            );
+            item_expression.chain_property::<Item>("title").bind(
+                &label,
+                "label",
+                glib::Object::NONE,
+            );
+
+            list_item.set_child(Some(&row));
+        });
+
+        let results = gio::ListStore::new(Item::static_type());
+        let selection_model = gtk::SingleSelection::new(Some(&results));
+        self.imp().results.set(results).unwrap();
+
+        let imp = self.imp();
+        imp.list_view.set_factory(Some(&factory));
+        imp.list_view.set_model(Some(&selection_model));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query_parse() {
+        assert_eq!(Query::parse("groceries"), Query::Notes("groceries"));
+        assert_eq!(Query::parse(" #work "), Query::Tags("work"));
+        assert_eq!(Query::parse(">sync"), Query::Actions("sync"));
+        assert_eq!(Query::parse("> "), Query::Actions(""));
+        assert_eq!(Query::parse(""), Query::Notes(""));
+    }
+
+    #[test]
+    fn recency_bonus_order() {
+        assert_eq!(recency_bonus(None, 3), 0);
+        assert_eq!(recency_bonus(Some(0), 3), RECENT_NOTE_BONUS);
+        assert!(recency_bonus(Some(0), 3) > recency_bonus(Some(1), 3));
+        assert!(recency_bonus(Some(2), 3) > 0);
+    }
+
+    #[test]
+    fn rank_best_first() {
+        let candidates = vec![
+            ("a", "Annotations".to_string(), 0),
+            ("b", "Notes".to_string(), 0),
+            ("c", "Groceries".to_string(), 0),
+        ];
+        let ranked = rank("not", candidates)
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn rank_bonus() {
+        let candidates = vec![
+            ("a", "Notes".to_string(), 0),
+            ("b", "Notes".to_string(), RECENT_NOTE_BONUS),
+        ];
+        let ranked = rank("", candidates)
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn rank_cap() {
+        let candidates = (0..MAX_RESULTS * 2).map(|i| (i, format!("Note {}", i), 0));
+        assert_eq!(rank("note", candidates).len(), MAX_RESULTS);
+    }
+}
@@ -10,7 +10,11 @@ use once_cell::unsync::OnceCell;
 
 use std::{fs, path::PathBuf};
 
-use crate::{session::Session, spawn, spawn_blocking, utils};
+use crate::{
+    session::Session,
+    spawn, spawn_blocking,
+    utils::{self, safe_path::resolve_within},
+};
 
 const MAX_BYTES_FILE_SIZE: u64 = 20_000_000;
 
@@ -121,7 +125,11 @@ impl FileImporterButton {
         for source_path in files {
             let notes_dir = Session::default().directory();
             let destination_path =
-                utils::generate_unique_path(notes_dir, "OtherFile", source_path.extension());
+                utils::generate_unique_path(&notes_dir, "OtherFile", source_path.extension());
+            let destination_path = resolve_within(
+                &notes_dir,
+                &destination_path.file_name().unwrap().to_string_lossy(),
+            )?;
             let destination_file = gio::File::for_path(&destination_path);
 
             log::info!(
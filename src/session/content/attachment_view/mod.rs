@@ -12,6 +12,8 @@ use gtk::{
     subclass::prelude::*,
 };
 
+use std::cell::Cell;
+
 use self::{
     audio_recorder_button::AudioRecorderButton, audio_row::AudioRow, camera_button::CameraButton,
     file_importer_button::FileImporterButton, other_row::OtherRow, picture_row::PictureRow,
@@ -41,8 +43,11 @@ mod imp {
         pub camera_button: TemplateChild<CameraButton>,
         #[template_child]
         pub file_importer_button: TemplateChild<FileImporterButton>,
+        #[template_child]
+        pub action_bar: TemplateChild<gtk::ActionBar>,
 
         pub audio_player_handler: AudioPlayerHandler,
+        pub can_delete: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -63,13 +68,22 @@ mod imp {
     impl ObjectImpl for AttachmentView {
         fn properties() -> &'static [glib::ParamSpec] {
             static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-                vec![glib::ParamSpecObject::new(
-                    "attachment-list",
-                    "Attachment List",
-                    "List containing the attachments",
-                    AttachmentList::static_type(),
-                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
-                )]
+                vec![
+                    glib::ParamSpecObject::new(
+                        "attachment-list",
+                        "Attachment List",
+                        "List containing the attachments",
+                        AttachmentList::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "can-delete",
+                        "Can Delete",
+                        "Whether attachments can be deleted",
+                        true,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
             });
             PROPERTIES.as_ref()
         }
@@ -86,6 +100,18 @@ mod imp {
                     let attachment_list: Option<AttachmentList> = value.get().unwrap();
                     obj.set_attachment_list(attachment_list.as_ref());
                 }
+                "can-delete" => {
+                    let can_delete = value.get().unwrap();
+                    obj.set_can_delete(can_delete);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "attachment-list" => obj.attachment_list().to_value(),
+                "can-delete" => obj.can_delete().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -93,6 +119,8 @@ mod imp {
         fn constructed(&self, obj: &Self::Type) {
             self.parent_constructed(obj);
 
+            self.can_delete.set(true);
+
             obj.setup_list_view();
             obj.setup_signals();
         }
@@ -122,6 +150,27 @@ impl AttachmentView {
         self.notify("attachment-list");
     }
 
+    /// Whether attachments can be deleted, which is not the case while the repository is busy
+    /// since it could be rewriting the same files
+    pub fn can_delete(&self) -> bool {
+        self.imp().can_delete.get()
+    }
+
+    pub fn set_can_delete(&self, can_delete: bool) {
+        if can_delete == self.can_delete() {
+            return;
+        }
+
+        self.imp().can_delete.set(can_delete);
+        self.notify("can-delete");
+    }
+
+    /// Hide the buttons that add attachments, as the notes of a read-only notebook can't be
+    /// changed
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        self.imp().action_bar.set_visible(!is_read_only);
+    }
+
     fn attachment_list(&self) -> Option<AttachmentList> {
         self.imp()
             .selection
@@ -142,6 +191,9 @@ impl AttachmentView {
 
         factory.connect_setup(clone!(@weak self as obj => move |_, list_item| {
             let attachment_row = Row::new();
+            obj.bind_property("can-delete", &attachment_row, "can-delete")
+                .flags(glib::BindingFlags::SYNC_CREATE)
+                .build();
 
             attachment_row.connect_on_delete(move |attachment_row| {
                 let attachment = attachment_row.attachment().unwrap();
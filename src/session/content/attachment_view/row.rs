@@ -1,7 +1,7 @@
 use adw::prelude::*;
 use gtk::{glib, subclass::prelude::*};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use super::{AudioRow, OtherRow, PictureRow};
 use crate::{core::FileType, model::Attachment};
@@ -19,6 +19,7 @@ mod imp {
         pub content: TemplateChild<adw::Bin>,
 
         pub attachment: RefCell<Option<Attachment>>,
+        pub can_delete: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -50,13 +51,22 @@ mod imp {
 
         fn properties() -> &'static [glib::ParamSpec] {
             static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-                vec![glib::ParamSpecObject::new(
-                    "attachment",
-                    "attachment",
-                    "The attachment represented by this row",
-                    Attachment::static_type(),
-                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
-                )]
+                vec![
+                    glib::ParamSpecObject::new(
+                        "attachment",
+                        "attachment",
+                        "The attachment represented by this row",
+                        Attachment::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "can-delete",
+                        "Can Delete",
+                        "Whether the attachment can be deleted",
+                        true,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
             });
             PROPERTIES.as_ref()
         }
@@ -73,6 +83,10 @@ mod imp {
                     let attachment = value.get().unwrap();
                     obj.set_attachment(attachment);
                 }
+                "can-delete" => {
+                    let can_delete = value.get().unwrap();
+                    obj.set_can_delete(can_delete);
+                }
                 _ => unimplemented!(),
             }
         }
@@ -80,10 +94,17 @@ mod imp {
         fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
             match pspec.name() {
                 "attachment" => obj.attachment().to_value(),
+                "can-delete" => obj.can_delete().to_value(),
                 _ => unimplemented!(),
             }
         }
 
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_can_delete(true);
+        }
+
         fn dispose(&self, obj: &Self::Type) {
             while let Some(child) = obj.first_child() {
                 child.unparent();
@@ -123,6 +144,16 @@ impl Row {
         self.notify("attachment");
     }
 
+    pub fn can_delete(&self) -> bool {
+        self.imp().can_delete.get()
+    }
+
+    pub fn set_can_delete(&self, can_delete: bool) {
+        self.imp().can_delete.set(can_delete);
+        self.action_set_enabled("row.delete-attachment", can_delete);
+        self.notify("can-delete");
+    }
+
     pub fn inner_row<T: IsA<gtk::Widget>>(&self) -> Option<T> {
         self.imp()
             .content
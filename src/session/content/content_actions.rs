@@ -0,0 +1,418 @@
+use gtk::{gio, glib, prelude::*, subclass::prelude::*};
+
+use std::cell::Cell;
+
+/// What an action of a note needs to be enabled, besides a note being shown
+#[derive(Debug, Clone, Copy)]
+struct Requirements {
+    /// Whether the note has to be trashed or not, or `None` if either is fine
+    is_trashed: Option<bool>,
+    /// Whether the action removes files, which would conflict with a git operation that may
+    /// be merging them
+    removes_files: bool,
+    /// Whether the action changes the notes, which can't be done while the notebook is
+    /// read-only
+    changes_notes: bool,
+}
+
+const ANY_NOTE: Requirements = Requirements {
+    is_trashed: None,
+    removes_files: false,
+    changes_notes: false,
+};
+const NOT_TRASHED: Requirements = Requirements {
+    is_trashed: Some(false),
+    removes_files: false,
+    changes_notes: true,
+};
+const TRASHED: Requirements = Requirements {
+    is_trashed: Some(true),
+    removes_files: false,
+    changes_notes: true,
+};
+
+/// The actions in the `note` group, in the order of the menu of the note, then the ones of
+/// the banner telling what is wrong with its file
+const ACTIONS: &[(&str, Requirements)] = &[
+    (
+        "edit-tags",
+        Requirements {
+            changes_notes: true,
+            ..ANY_NOTE
+        },
+    ),
+    (
+        "properties",
+        Requirements {
+            changes_notes: true,
+            ..ANY_NOTE
+        },
+    ),
+    ("history", ANY_NOTE),
+    ("duplicate", NOT_TRASHED),
+    (
+        "merge",
+        Requirements {
+            removes_files: true,
+            ..NOT_TRASHED
+        },
+    ),
+    ("export", ANY_NOTE),
+    ("print", ANY_NOTE),
+    (
+        "publish",
+        Requirements {
+            changes_notes: false,
+            ..NOT_TRASHED
+        },
+    ),
+    ("copy-markdown", ANY_NOTE),
+    ("copy-html", ANY_NOTE),
+    ("copy-path", ANY_NOTE),
+    ("show-in-folder", ANY_NOTE),
+    ("trash", NOT_TRASHED),
+    ("restore", TRASHED),
+    (
+        "delete",
+        Requirements {
+            removes_files: true,
+            ..TRASHED
+        },
+    ),
+    (
+        "recreate-file",
+        Requirements {
+            changes_notes: true,
+            ..ANY_NOTE
+        },
+    ),
+    (
+        "discard",
+        Requirements {
+            changes_notes: true,
+            ..ANY_NOTE
+        },
+    ),
+    (
+        "retry-save",
+        Requirements {
+            changes_notes: true,
+            ..ANY_NOTE
+        },
+    ),
+    ("show-external-changes", ANY_NOTE),
+];
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct ContentActions {
+        pub action_group: gio::SimpleActionGroup,
+        pub has_note: Cell<bool>,
+        pub is_trashed: Cell<bool>,
+        pub is_repository_busy: Cell<bool>,
+        pub is_publishing: Cell<bool>,
+        pub is_read_only: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ContentActions {
+        const NAME: &'static str = "NwtyContentActions";
+        type Type = super::ContentActions;
+    }
+
+    impl ObjectImpl for ContentActions {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecBoolean::new(
+                        "has-note",
+                        "Has Note",
+                        "Whether a note is shown",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-trashed",
+                        "Is Trashed",
+                        "Whether the shown note is in the trash",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-repository-busy",
+                        "Is Repository Busy",
+                        "Whether a git operation is running",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-publishing",
+                        "Is Publishing",
+                        "Whether the shown note is being published",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-read-only",
+                        "Is Read Only",
+                        "Whether the notebook is read-only",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            let cell = match pspec.name() {
+                "has-note" => &self.has_note,
+                "is-trashed" => &self.is_trashed,
+                "is-repository-busy" => &self.is_repository_busy,
+                "is-publishing" => &self.is_publishing,
+                "is-read-only" => &self.is_read_only,
+                _ => unimplemented!(),
+            };
+
+            let value = value.get().unwrap();
+            if cell.get() != value {
+                cell.set(value);
+                obj.notify_by_pspec(pspec);
+                obj.update_enabled();
+            }
+        }
+
+        fn property(&self, _obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "has-note" => self.has_note.get().to_value(),
+                "is-trashed" => self.is_trashed.get().to_value(),
+                "is-repository-busy" => self.is_repository_busy.get().to_value(),
+                "is-publishing" => self.is_publishing.get().to_value(),
+                "is-read-only" => self.is_read_only.get().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            for (name, _) in ACTIONS {
+                self.action_group
+                    .add_action(&gio::SimpleAction::new(name, None));
+            }
+
+            obj.update_enabled();
+        }
+    }
+}
+
+glib::wrapper! {
+    /// The actions of the shown note, in the `note` group. Each one is only enabled when it
+    /// can be used on the note, like restoring only notes in the trash.
+    pub struct ContentActions(ObjectSubclass<imp::ContentActions>);
+}
+
+impl ContentActions {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ContentActions.")
+    }
+
+    pub fn action_group(&self) -> &gio::SimpleActionGroup {
+        &self.imp().action_group
+    }
+
+    /// Call `f` when the action with `name` is activated
+    pub fn connect_activate<F>(&self, name: &str, f: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.action(name).connect_activate(move |_, _| f());
+    }
+
+    pub fn set_has_note(&self, has_note: bool) {
+        self.set_property("has-note", has_note);
+    }
+
+    pub fn set_is_repository_busy(&self, is_repository_busy: bool) {
+        self.set_property("is-repository-busy", is_repository_busy);
+    }
+
+    pub fn set_is_publishing(&self, is_publishing: bool) {
+        self.set_property("is-publishing", is_publishing);
+    }
+
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        self.set_property("is-read-only", is_read_only);
+    }
+
+    fn action(&self, name: &str) -> gio::SimpleAction {
+        self.action_group()
+            .lookup_action(name)
+            .and_then(|action| action.downcast().ok())
+            .unwrap_or_else(|| panic!("Note action `{}` does not exist", name))
+    }
+
+    fn update_enabled(&self) {
+        let imp = self.imp();
+
+        for (name, requirements) in ACTIONS {
+            let is_enabled = imp.has_note.get()
+                && requirements
+                    .is_trashed
+                    .map_or(true, |is_trashed| is_trashed == imp.is_trashed.get())
+                && !(requirements.removes_files && imp.is_repository_busy.get())
+                && !(requirements.changes_notes && imp.is_read_only.get())
+                && !(*name == "publish" && imp.is_publishing.get());
+
+            self.action(name).set_enabled(is_enabled);
+        }
+    }
+}
+
+impl Default for ContentActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enabled_actions(actions: &ContentActions) -> Vec<&'static str> {
+        ACTIONS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| actions.action_group().is_action_enabled(name))
+            .collect()
+    }
+
+    #[test]
+    fn without_note() {
+        let actions = ContentActions::new();
+        assert!(enabled_actions(&actions).is_empty());
+
+        actions.set_property("is-trashed", true);
+        assert!(enabled_actions(&actions).is_empty());
+    }
+
+    #[test]
+    fn note_not_trashed() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+
+        let enabled = enabled_actions(&actions);
+        assert!(enabled.contains(&"trash"));
+        assert!(enabled.contains(&"merge"));
+        assert!(enabled.contains(&"publish"));
+        assert!(!enabled.contains(&"restore"));
+        assert!(!enabled.contains(&"delete"));
+        assert_eq!(enabled.len(), ACTIONS.len() - 2);
+    }
+
+    #[test]
+    fn note_trashed() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+        actions.set_property("is-trashed", true);
+
+        let enabled = enabled_actions(&actions);
+        assert!(enabled.contains(&"restore"));
+        assert!(enabled.contains(&"delete"));
+        assert!(enabled.contains(&"export"));
+        assert!(!enabled.contains(&"trash"));
+        assert!(!enabled.contains(&"duplicate"));
+        assert!(!enabled.contains(&"merge"));
+        assert!(!enabled.contains(&"publish"));
+    }
+
+    #[test]
+    fn repository_busy() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+        actions.set_is_repository_busy(true);
+
+        let enabled = enabled_actions(&actions);
+        assert!(!enabled.contains(&"merge"));
+        assert!(enabled.contains(&"duplicate"));
+        assert!(enabled.contains(&"trash"));
+
+        actions.set_property("is-trashed", true);
+        let enabled = enabled_actions(&actions);
+        assert!(!enabled.contains(&"delete"));
+        assert!(enabled.contains(&"restore"));
+
+        actions.set_is_repository_busy(false);
+        assert!(actions.action_group().is_action_enabled("delete"));
+    }
+
+    #[test]
+    fn publishing() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+
+        actions.set_is_publishing(true);
+        assert!(!actions.action_group().is_action_enabled("publish"));
+        assert!(actions.action_group().is_action_enabled("copy-html"));
+
+        actions.set_is_publishing(false);
+        assert!(actions.action_group().is_action_enabled("publish"));
+    }
+
+    #[test]
+    fn read_only() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+        actions.set_is_read_only(true);
+
+        let enabled = enabled_actions(&actions);
+        assert!(!enabled.contains(&"edit-tags"));
+        assert!(!enabled.contains(&"duplicate"));
+        assert!(!enabled.contains(&"merge"));
+        assert!(!enabled.contains(&"trash"));
+        assert!(enabled.contains(&"history"));
+        assert!(enabled.contains(&"publish"));
+        assert!(enabled.contains(&"copy-path"));
+        assert!(!enabled.contains(&"recreate-file"));
+        assert!(!enabled.contains(&"retry-save"));
+        assert!(enabled.contains(&"show-external-changes"));
+
+        actions.set_property("is-trashed", true);
+        let enabled = enabled_actions(&actions);
+        assert!(!enabled.contains(&"restore"));
+        assert!(!enabled.contains(&"delete"));
+        assert!(enabled.contains(&"export"));
+
+        actions.set_is_read_only(false);
+        assert!(actions.action_group().is_action_enabled("delete"));
+    }
+
+    #[test]
+    fn activate() {
+        let actions = ContentActions::new();
+        actions.set_has_note(true);
+
+        let n_activated = std::rc::Rc::new(Cell::new(0));
+        actions.connect_activate("print", {
+            let n_activated = n_activated.clone();
+            move || n_activated.set(n_activated.get() + 1)
+        });
+
+        actions.action_group().activate_action("print", None);
+        assert_eq!(n_activated.get(), 1);
+
+        // Disabled actions are not activated
+        actions.set_has_note(false);
+        actions.action_group().activate_action("print", None);
+        assert_eq!(n_activated.get(), 1);
+    }
+}
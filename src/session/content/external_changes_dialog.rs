@@ -0,0 +1,131 @@
+use gettextrs::gettext;
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+};
+
+use crate::{
+    core::{diff_lines, DiffLine, LineChange, NoteData},
+    model::Note,
+    session::Session,
+    spawn, spawn_blocking,
+    utils::palette::Palette,
+};
+
+const FILE_ONLY_TAG: &str = "file-only";
+const NOTE_ONLY_TAG: &str = "note-only";
+
+/// Show how the file of `note` differs from it since it was changed elsewhere, then load the
+/// file or write the note over it
+pub fn show(parent: &impl IsA<gtk::Widget>, note: &Note) {
+    let parent = parent.as_ref().clone();
+
+    spawn!(clone!(@weak parent, @weak note => async move {
+        let file_contents = match note.file().load_contents_future().await {
+            Ok((contents, _)) => contents,
+            Err(err) => {
+                log::error!("Failed to load changed file of `{}`: {:?}", note, err);
+                return;
+            }
+        };
+        let file_content = NoteData::parse_tolerant(&String::from_utf8_lossy(&file_contents))
+            .0
+            .content;
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let note_content = buffer.text(&start_iter, &end_iter, true).to_string();
+
+        let lines = spawn_blocking!(move || diff_lines(&note_content, &file_content)).await;
+        present(&parent, &note, &lines);
+    }));
+}
+
+fn present(parent: &gtk::Widget, note: &Note, lines: &[DiffLine]) {
+    let text_view = gtk::TextView::builder()
+        .editable(false)
+        .cursor_visible(false)
+        .monospace(true)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .build();
+    fill_buffer(&text_view.buffer(), lines);
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_height(240)
+        .max_content_height(480)
+        .propagate_natural_height(true)
+        .child(&text_view)
+        .build();
+
+    let dialog = gtk::MessageDialog::builder()
+        .text(&gettext("Note Changed Elsewhere"))
+        .secondary_text(&gettext(
+            "The lines only in the file are shown in green, and the ones only in the note in red. Loading the file drops the changes to the note that are not saved.",
+        ))
+        .message_type(gtk::MessageType::Question)
+        .modal(true)
+        .build();
+    dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+    dialog.add_button(&gettext("_Keep Note"), gtk::ResponseType::Reject);
+    dialog
+        .add_button(&gettext("_Load File"), gtk::ResponseType::Accept)
+        .add_css_class("suggested-action");
+    dialog
+        .message_area()
+        .downcast::<gtk::Box>()
+        .unwrap()
+        .append(&scrolled_window);
+    dialog.set_transient_for(
+        parent
+            .root()
+            .map(|w| w.downcast::<gtk::Window>().unwrap())
+            .as_ref(),
+    );
+
+    dialog.connect_response(clone!(@weak note => move |dialog, response| {
+        dialog.destroy();
+
+        match response {
+            gtk::ResponseType::Accept => {
+                spawn!(clone!(@weak note => async move {
+                    if let Err(err) = note.reload().await {
+                        log::error!("Failed to reload `{}`: {:?}", note, err);
+                    }
+                }));
+            }
+            gtk::ResponseType::Reject => Session::default().write_note_file(&note),
+            _ => (),
+        }
+    }));
+    dialog.present();
+}
+
+/// Fill `buffer` with `lines`, highlighting the ones only in the file or the note
+fn fill_buffer(buffer: &gtk::TextBuffer, lines: &[DiffLine]) {
+    Palette::current().set_up_diff_tags(buffer, FILE_ONLY_TAG, NOTE_ONLY_TAG);
+
+    let text = lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    buffer.set_text(&text);
+
+    for (index, line) in lines.iter().enumerate() {
+        let tag_name = match line.change {
+            LineChange::Unchanged => continue,
+            LineChange::Added => FILE_ONLY_TAG,
+            LineChange::Removed => NOTE_ONLY_TAG,
+        };
+
+        let start_iter = buffer.iter_at_line(index as i32);
+        let end_iter = buffer
+            .iter_at_line(index as i32 + 1)
+            .unwrap_or_else(|| buffer.end_iter());
+
+        if let Some(start_iter) = start_iter {
+            buffer.apply_tag_by_name(tag_name, &start_iter, &end_iter);
+        }
+    }
+}
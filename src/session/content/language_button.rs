@@ -0,0 +1,343 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    glib::{self, clone, closure},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::RefCell;
+
+use crate::{
+    core::{DEFAULT_LANGUAGE, PLAIN_TEXT_LANGUAGE},
+    model::{Note, NoteMetadata},
+    utils::fuzzy,
+};
+
+/// A language that can be chosen, with the id that is stored for it and its name
+#[derive(Debug, Clone, PartialEq)]
+struct LanguageChoice {
+    /// `None` for the default language, so choosing it doesn't add a field to the front matter
+    language: Option<String>,
+    name: String,
+}
+
+impl LanguageChoice {
+    fn is_current(&self, language: Option<&str>) -> bool {
+        let language = language.filter(|language| *language != DEFAULT_LANGUAGE);
+        self.language.as_deref() == language
+    }
+}
+
+/// The choices for `languages`, which are ids with names. Plain text comes first, then the
+/// languages sorted by their names.
+fn language_choices(languages: impl IntoIterator<Item = (String, String)>) -> Vec<LanguageChoice> {
+    let mut choices = languages
+        .into_iter()
+        .map(|(id, name)| LanguageChoice {
+            language: Some(id).filter(|id| id != DEFAULT_LANGUAGE),
+            name,
+        })
+        .collect::<Vec<_>>();
+    choices.sort_by_cached_key(|choice| choice.name.to_lowercase());
+
+    choices.insert(
+        0,
+        LanguageChoice {
+            language: Some(PLAIN_TEXT_LANGUAGE.to_string()),
+            name: gettext("Plain Text"),
+        },
+    );
+
+    choices
+}
+
+/// Name of `language` as stored in the metadata of a note
+fn language_name(language: Option<&str>) -> String {
+    let id = match language {
+        Some(PLAIN_TEXT_LANGUAGE) => return gettext("Plain Text"),
+        Some(id) => id,
+        None => DEFAULT_LANGUAGE,
+    };
+
+    gtk_source::LanguageManager::default()
+        .language(id)
+        .map_or_else(|| id.to_string(), |language| language.name().into())
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/content-language-button.ui")]
+    pub struct LanguageButton {
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+
+        pub note: RefCell<Option<Note>>,
+        /// The choices in the order of the rows, with the icons that mark the current one
+        pub choices: RefCell<Vec<(LanguageChoice, gtk::Image)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LanguageButton {
+        const NAME: &'static str = "NwtyContentLanguageButton";
+        type Type = super::LanguageButton;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for LanguageButton {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "note",
+                    "Note",
+                    "Note to set the language of",
+                    Note::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "note" => {
+                    let note = value.get().unwrap();
+                    obj.set_note(note);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "note" => obj.note().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_box();
+            obj.setup_signals();
+            obj.setup_expressions();
+        }
+    }
+
+    impl WidgetImpl for LanguageButton {}
+    impl BinImpl for LanguageButton {}
+}
+
+glib::wrapper! {
+    pub struct LanguageButton(ObjectSubclass<imp::LanguageButton>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl LanguageButton {
+    pub fn note(&self) -> Option<Note> {
+        self.imp().note.borrow().clone()
+    }
+
+    pub fn set_note(&self, note: Option<Note>) {
+        if self.note() == note {
+            return;
+        }
+
+        self.imp().note.replace(note);
+        self.notify("note");
+    }
+
+    fn choose(&self, index: usize) {
+        let imp = self.imp();
+
+        let choice = match imp.choices.borrow().get(index) {
+            Some((choice, _)) => choice.clone(),
+            None => return,
+        };
+
+        if let Some(note) = self.note() {
+            note.metadata().set_language(choice.language.as_deref());
+        }
+
+        imp.popover.popdown();
+    }
+
+    /// Choose the first language that matches the search
+    fn choose_first_shown(&self) {
+        let list_box = self.imp().list_box.get();
+
+        let mut index = 0;
+        while let Some(row) = list_box.row_at_index(index) {
+            if row.is_child_visible() {
+                self.choose(index as usize);
+                return;
+            }
+            index += 1;
+        }
+    }
+
+    /// Start from the whole list with the current language marked
+    fn reset_popover(&self) {
+        let imp = self.imp();
+        let language = self.note().and_then(|note| note.metadata().language());
+
+        for (choice, check_icon) in imp.choices.borrow().iter() {
+            check_icon.set_visible(choice.is_current(language.as_deref()));
+        }
+
+        imp.search_entry.set_text("");
+        imp.search_entry.grab_focus();
+    }
+
+    fn setup_list_box(&self) {
+        let imp = self.imp();
+
+        let language_manager = gtk_source::LanguageManager::default();
+        let languages = language_manager
+            .language_ids()
+            .into_iter()
+            .filter_map(|id| language_manager.language(&id))
+            .filter(|language| !language.is_hidden())
+            .map(|language| (language.id().into(), language.name().into()));
+
+        let choices = language_choices(languages)
+            .into_iter()
+            .map(|choice| {
+                let label = gtk::Label::builder()
+                    .label(&choice.name)
+                    .xalign(0.0)
+                    .hexpand(true)
+                    .build();
+                let check_icon = gtk::Image::from_icon_name("object-select-symbolic");
+
+                let row_box = gtk::Box::builder().spacing(12).build();
+                row_box.append(&label);
+                row_box.append(&check_icon);
+                imp.list_box.append(&row_box);
+
+                (choice, check_icon)
+            })
+            .collect();
+        imp.choices.replace(choices);
+
+        imp.list_box.set_filter_func(
+            clone!(@weak self as obj => @default-return true, move |row| {
+                let query = obj.imp().search_entry.text();
+                let choices = obj.imp().choices.borrow();
+
+                choices.get(row.index() as usize).map_or(true, |(choice, _)| {
+                    fuzzy::score(&query, &choice.name).is_some()
+                        || choice
+                            .language
+                            .as_deref()
+                            .map_or(false, |id| fuzzy::score(&query, id).is_some())
+                })
+            }),
+        );
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        imp.popover
+            .connect_show(clone!(@weak self as obj => move |_| {
+                obj.reset_popover();
+            }));
+
+        imp.search_entry
+            .connect_search_changed(clone!(@weak self as obj => move |_| {
+                obj.imp().list_box.invalidate_filter();
+            }));
+
+        imp.search_entry
+            .connect_activate(clone!(@weak self as obj => move |_| {
+                obj.choose_first_shown();
+            }));
+
+        imp.list_box
+            .connect_row_activated(clone!(@weak self as obj => move |_, row| {
+                obj.choose(row.index() as usize);
+            }));
+    }
+
+    fn setup_expressions(&self) {
+        Self::this_expression("note")
+            .chain_property::<Note>("metadata")
+            .chain_property::<NoteMetadata>("language")
+            .chain_closure::<String>(closure!(|_: Self, language: Option<String>| {
+                gettext!("Language: {}", language_name(language.as_deref()))
+            }))
+            .bind(&self.imp().menu_button.get(), "tooltip-text", Some(self));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn choices_order() {
+        let choices = language_choices([
+            ("yaml".to_string(), "YAML".to_string()),
+            ("markdown".to_string(), "Markdown".to_string()),
+            ("c".to_string(), "C".to_string()),
+            ("sql".to_string(), "SQL".to_string()),
+        ]);
+
+        let languages = choices
+            .iter()
+            .map(|choice| choice.language.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            languages,
+            [
+                Some(PLAIN_TEXT_LANGUAGE),
+                Some("c"),
+                None,
+                Some("sql"),
+                Some("yaml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn choice_is_current() {
+        let choices = language_choices([
+            ("markdown".to_string(), "Markdown".to_string()),
+            ("yaml".to_string(), "YAML".to_string()),
+        ]);
+        let (plain_text, markdown, yaml) = (&choices[0], &choices[1], &choices[2]);
+
+        assert!(markdown.is_current(None));
+        assert!(markdown.is_current(Some("markdown")));
+        assert!(!markdown.is_current(Some("yaml")));
+        assert!(yaml.is_current(Some("yaml")));
+        assert!(!yaml.is_current(None));
+        assert!(plain_text.is_current(Some(PLAIN_TEXT_LANGUAGE)));
+        assert!(!plain_text.is_current(None));
+    }
+}
@@ -1,12 +1,43 @@
 mod attachment_view;
+mod content_actions;
+mod external_changes_dialog;
+mod language_button;
+mod properties_panel;
+mod reminder_button;
 mod view;
-
-use gtk::{glib, prelude::*, subclass::prelude::*};
-
-use std::cell::{Cell, RefCell};
-
-use self::{attachment_view::AttachmentView, view::View};
-use crate::model::Note;
+mod word_goal_button;
+
+use gettextrs::{gettext, ngettext};
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+};
+
+use self::{
+    attachment_view::AttachmentView, content_actions::ContentActions,
+    language_button::LanguageButton, properties_panel::PropertiesPanel,
+    reminder_button::ReminderButton, view::View, word_goal_button::WordGoalButton,
+};
+use crate::{
+    core::{
+        embed_images, markdown_to_html,
+        publisher::{self, PublishError, PublishTarget, Published, UreqClient},
+        ImageEmbedding, NoteColor, MAX_COPIED_IMAGE_SIZE,
+    },
+    model::{ErrorReport, ErrorSource, Note, NoteMetadata, NoteProblem},
+    session::{tour, NoteManager, Session},
+    spawn, spawn_blocking,
+    utils::{self, print_layout, Ticker},
+    widgets::ColorDot,
+    window::Window,
+    Application,
+};
 
 mod imp {
     use super::*;
@@ -16,25 +47,64 @@ mod imp {
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/content.ui")]
     pub struct Content {
+        #[template_child]
+        pub window_title: TemplateChild<adw::WindowTitle>,
+        #[template_child]
+        pub back_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub view_flap: TemplateChild<adw::Flap>,
         #[template_child]
+        pub view: TemplateChild<View>,
+        #[template_child]
         pub attachment_view: TemplateChild<AttachmentView>,
         #[template_child]
         pub no_selected_view: TemplateChild<adw::StatusPage>,
         #[template_child]
         pub edit_tags_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub share_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub note_menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub properties_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub history_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
         pub is_pinned_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub is_trashed_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
+        pub delete_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub view_flap_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub reminder_button: TemplateChild<ReminderButton>,
+        #[template_child]
+        pub language_button: TemplateChild<LanguageButton>,
+        #[template_child]
+        pub word_goal_button: TemplateChild<WordGoalButton>,
+        #[template_child]
+        pub color_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub color_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub color_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub problem_banner: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub problem_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub problem_secondary_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub problem_button: TemplateChild<gtk::Button>,
 
         pub compact: Cell<bool>,
+        pub view_title: RefCell<String>,
+        pub is_read_only: Cell<bool>,
         pub note: RefCell<Option<Note>>,
+        pub actions: ContentActions,
 
         pub bindings: RefCell<Vec<glib::Binding>>,
     }
@@ -47,6 +117,10 @@ mod imp {
 
         fn class_init(klass: &mut Self::Class) {
             View::static_type();
+            PropertiesPanel::static_type();
+            ReminderButton::static_type();
+            LanguageButton::static_type();
+            WordGoalButton::static_type();
             Self::bind_template(klass);
         }
 
@@ -66,6 +140,13 @@ mod imp {
                         false,
                         glib::ParamFlags::READWRITE,
                     ),
+                    glib::ParamSpecString::new(
+                        "view-title",
+                        "View Title",
+                        "Name of the view the note is shown from, like `#work` for a tag",
+                        None,
+                        glib::ParamFlags::READWRITE,
+                    ),
                     glib::ParamSpecObject::new(
                         "note",
                         "Note",
@@ -73,6 +154,13 @@ mod imp {
                         Note::static_type(),
                         glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
+                    glib::ParamSpecBoolean::new(
+                        "is-editing",
+                        "Is Editing",
+                        "Whether the text of the note has the focus",
+                        false,
+                        glib::ParamFlags::READABLE,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -89,6 +177,12 @@ mod imp {
                 "compact" => {
                     let compact = value.get().unwrap();
                     self.compact.set(compact);
+                    obj.update_window_title();
+                }
+                "view-title" => {
+                    let view_title = value.get().unwrap();
+                    self.view_title.replace(view_title);
+                    obj.update_window_title();
                 }
                 "note" => {
                     let note = value.get().unwrap();
@@ -101,7 +195,9 @@ mod imp {
         fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
             match pspec.name() {
                 "compact" => self.compact.get().to_value(),
+                "view-title" => self.view_title.borrow().to_value(),
                 "note" => obj.note().to_value(),
+                "is-editing" => obj.is_editing().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -109,6 +205,28 @@ mod imp {
         fn constructed(&self, obj: &Self::Type) {
             self.parent_constructed(obj);
 
+            Application::default()
+                .settings()
+                .bind(
+                    "is-attachment-view-revealed",
+                    &*self.view_flap,
+                    "reveal-flap",
+                )
+                .build();
+
+            tour::register_anchor(&self.is_pinned_button.get(), tour::PIN_ANCHOR);
+
+            self.view.connect_notify_local(
+                Some("is-editing"),
+                clone!(@weak obj => move |_, _| {
+                    obj.notify("is-editing");
+                }),
+            );
+
+            obj.setup_actions();
+            obj.setup_color_box();
+            obj.setup_window_title();
+            obj.setup_problem_banner();
             obj.update_buttons_visibility();
             obj.update_stack();
         }
@@ -137,6 +255,83 @@ impl Content {
         self.imp().note.borrow().clone()
     }
 
+    /// Whether the text of the note has the focus
+    pub fn is_editing(&self) -> bool {
+        self.imp().view.is_editing()
+    }
+
+    /// Show the words written today in the notes of `note_manager` against its daily goal
+    pub fn bind_word_goal(&self, note_manager: &NoteManager) {
+        let word_goal_button = self.imp().word_goal_button.get();
+
+        note_manager
+            .bind_property("words-today", &word_goal_button, "words-today")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager
+            .notebook_settings()
+            .bind_property("daily-word-goal", &word_goal_button, "goal")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+    }
+
+    /// Take the titles of the notes from their first heading while the notebook settings of
+    /// `note_manager` say so
+    pub fn bind_derives_title(&self, note_manager: &NoteManager) {
+        note_manager
+            .notebook_settings()
+            .bind_property(
+                "derive-title-from-heading",
+                &self.imp().view.get(),
+                "derives-title",
+            )
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+    }
+
+    /// Put the cursor in the title of the note, like after it was created
+    pub fn focus_title(&self) {
+        self.imp().view.focus_title();
+    }
+
+    /// Scroll to the text at the byte `range` of the content of the note, like a match of a
+    /// search, highlighting it for a moment
+    pub fn scroll_to_content_range(&self, range: Range<usize>) {
+        self.imp().view.scroll_to_content_range(range);
+    }
+
+    /// Scroll to the first heading of the note with `text`, highlighting it for a moment
+    pub fn scroll_to_heading(&self, text: &str) {
+        self.imp().view.scroll_to_heading(text);
+    }
+
+    pub fn set_can_delete_attachments(&self, can_delete: bool) {
+        self.imp().attachment_view.set_can_delete(can_delete);
+    }
+
+    /// Disable the actions of the note that remove files while a git operation is running
+    pub fn set_is_repository_busy(&self, is_busy: bool) {
+        self.imp().actions.set_is_repository_busy(is_busy);
+    }
+
+    /// Hide what changes the note while the notebook is read-only, and only let its text be
+    /// read
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        let imp = self.imp();
+
+        imp.is_read_only.set(is_read_only);
+        imp.actions.set_is_read_only(is_read_only);
+        imp.view.set_is_read_only(is_read_only);
+        imp.attachment_view.set_is_read_only(is_read_only);
+
+        self.update_buttons_visibility();
+    }
+
+    /// Only show the title of the note, as it is edited where the note was opened first
+    pub fn set_follows_title(&self, follows_title: bool) {
+        self.imp().view.set_follows_title(follows_title);
+    }
+
     pub fn set_note(&self, note: Option<Note>) {
         if self.note() == note {
             return;
@@ -163,8 +358,21 @@ impl Content {
                 .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
                 .build();
             bindings.push(is_trashed);
+
+            let is_deletable = note_metadata
+                .bind_property("is-trashed", &imp.delete_button.get(), "visible")
+                .flags(glib::BindingFlags::SYNC_CREATE)
+                .build();
+            bindings.push(is_deletable);
+
+            let is_trashed_action = note_metadata
+                .bind_property("is-trashed", &imp.actions, "is-trashed")
+                .flags(glib::BindingFlags::SYNC_CREATE)
+                .build();
+            bindings.push(is_trashed_action);
         }
 
+        imp.actions.set_has_note(note.is_some());
         imp.note.replace(note);
         self.notify("note");
 
@@ -172,6 +380,144 @@ impl Content {
         self.update_stack();
     }
 
+    fn setup_window_title(&self) {
+        let note_expression = Self::this_expression("note");
+        let metadata_expression = note_expression.chain_property::<Note>("metadata");
+
+        note_expression.chain_property::<Note>("is-saved").watch(
+            Some(self),
+            clone!(@weak self as obj => move || obj.update_window_title()),
+        );
+        metadata_expression
+            .chain_property::<NoteMetadata>("title")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_window_title()),
+            );
+        metadata_expression
+            .chain_property::<NoteMetadata>("last-modified")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_window_title()),
+            );
+
+        // The relative time goes stale even when nothing changes
+        Ticker::default().connect_tick(clone!(@weak self as obj => move |_| {
+            obj.update_window_title();
+        }));
+
+        self.update_window_title();
+    }
+
+    /// Show the title of the note, and when the sidebar is folded away, the view it is
+    /// shown from
+    fn update_window_title(&self) {
+        let imp = self.imp();
+        let window_title = imp.window_title.get();
+        let view_title = imp.view_title.borrow().clone();
+        let is_compact = imp.compact.get();
+
+        imp.back_button
+            .set_tooltip_text(Some(&gettext!("Back to {}", view_title)));
+
+        let note = match self.note() {
+            Some(note) => note,
+            None => {
+                window_title.set_title("");
+                window_title.set_subtitle("");
+                return;
+            }
+        };
+
+        let note_metadata = note.metadata();
+        let title = match note_metadata.title() {
+            title if title.is_empty() => gettext("Untitled Note"),
+            title => title,
+        };
+        window_title.set_title(&title);
+
+        let status = if note.is_saved() {
+            gettext!(
+                "Edited {}",
+                note_metadata.last_modified().relative_display()
+            )
+        } else {
+            gettext("Unsaved changes")
+        };
+
+        if is_compact {
+            // #work · Edited 5 minutes ago
+            window_title.set_subtitle(&gettext!("{} · {}", view_title, status));
+            // #work ‹ Meeting notes
+            imp.back_button
+                .set_tooltip_text(Some(&gettext!("{} ‹ {}", view_title, title)));
+        } else {
+            window_title.set_subtitle(&status);
+        }
+    }
+
+    fn setup_problem_banner(&self) {
+        Self::this_expression("note")
+            .chain_property::<Note>("problem")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_problem_banner()),
+            );
+
+        self.update_problem_banner();
+    }
+
+    /// Tell what is wrong with the file of the note, with the actions that solve it
+    fn update_problem_banner(&self) {
+        let imp = self.imp();
+        let problem = self.note().map(|note| note.problem()).unwrap_or_default();
+
+        // The label and the buttons are kept while it is hidden, so they don't go blank
+        let (message, button, secondary_button) = match problem {
+            NoteProblem::None => {
+                imp.problem_banner.set_reveal_child(false);
+                return;
+            }
+            NoteProblem::FileMissing => (
+                gettext("The file of this note was deleted elsewhere"),
+                (gettext("_Recreate File"), "note.recreate-file"),
+                Some((gettext("_Discard Note"), "note.discard")),
+            ),
+            NoteProblem::SaveFailed(_) => (
+                gettext("This note failed to save"),
+                (gettext("_Retry"), "note.retry-save"),
+                None,
+            ),
+            NoteProblem::ExternallyModified => (
+                gettext("The file of this note was changed elsewhere"),
+                (gettext("_Show Changes"), "note.show-external-changes"),
+                None,
+            ),
+        };
+
+        imp.problem_label.set_label(&message);
+
+        let (label, action_name) = button;
+        imp.problem_button.set_label(&label);
+        imp.problem_button.set_action_name(Some(action_name));
+        imp.problem_button.set_tooltip_text(match problem {
+            NoteProblem::SaveFailed(ref message) => Some(message.as_str()),
+            _ => None,
+        });
+
+        match secondary_button {
+            Some((label, action_name)) => {
+                imp.problem_secondary_button.set_label(&label);
+                imp.problem_secondary_button
+                    .set_action_name(Some(action_name));
+                imp.problem_secondary_button.set_visible(true);
+            }
+            None => imp.problem_secondary_button.set_visible(false),
+        }
+
+        imp.problem_banner.set_reveal_child(true);
+    }
+
     fn update_stack(&self) {
         let imp = self.imp();
 
@@ -185,10 +531,293 @@ impl Content {
     fn update_buttons_visibility(&self) {
         let imp = self.imp();
         let has_note = self.note().is_some();
+        let can_change_note = has_note && !imp.is_read_only.get();
 
-        imp.is_pinned_button.set_visible(has_note);
-        imp.is_trashed_button.set_visible(has_note);
-        imp.edit_tags_button.set_visible(has_note);
+        imp.is_pinned_button.set_visible(can_change_note);
+        imp.is_trashed_button.set_visible(can_change_note);
+        imp.edit_tags_button.set_visible(can_change_note);
         imp.view_flap_button.set_visible(has_note);
+        imp.color_button.set_visible(can_change_note);
+        imp.reminder_button.set_visible(can_change_note);
+        imp.language_button.set_visible(can_change_note);
+        imp.share_button.set_visible(has_note);
+        imp.note_menu_button.set_visible(has_note);
+        imp.properties_button.set_visible(can_change_note);
+        imp.history_button.set_visible(has_note);
+
+        // Otherwise only shown while the note is trashed
+        if !has_note {
+            imp.delete_button.set_visible(false);
+        }
+    }
+
+    /// Copy the selected text of the note, or all of it when nothing is selected, as is
+    fn copy_as_markdown(&self) {
+        if let Some(note) = self.note() {
+            self.clipboard().set_text(&note.selected_content());
+            self.add_toast(&adw::Toast::new(&gettext("Copied as Markdown")));
+        }
+    }
+
+    /// Like [`Content::copy_as_markdown`], but rendered as HTML for the apps that paste
+    /// formatting, and kept as Markdown for the rest. Small images are put in the HTML, and
+    /// larger ones are linked to.
+    fn copy_as_html(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let markdown = note.selected_content();
+        let notes_dir = Session::default().directory();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let (exported, markdown) = spawn_blocking!(move || {
+                let embedding = ImageEmbedding::DataUriUpTo {
+                    max_size: MAX_COPIED_IMAGE_SIZE,
+                };
+                let exported = embed_images(&markdown_to_html(&markdown), &notes_dir, &embedding);
+                (exported, markdown)
+            })
+            .await;
+            let provider = utils::formatted_text_provider(&exported.html, &markdown);
+
+            if let Err(err) = obj.clipboard().set_content(Some(&provider)) {
+                log::error!("Failed to copy `{}` as HTML: {:?}", note, err);
+                return;
+            }
+
+            let n_missing = exported.missing_images.len();
+            obj.add_toast(&adw::Toast::new(&if n_missing == 0 {
+                gettext("Copied as HTML")
+            } else {
+                ngettext!(
+                    "Copied as HTML, but {} image is missing",
+                    "Copied as HTML, but {} images are missing",
+                    n_missing as u32,
+                    n_missing
+                )
+            }));
+        }));
+    }
+
+    /// Upload the note to the target in the settings, then copy the link to it
+    fn publish(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let settings = Application::default().settings();
+        let target_name = settings.string("publish-target").to_string();
+        let paste_url = settings.string("publish-paste-url").to_string();
+        let paste_field = settings.string("publish-paste-field").to_string();
+
+        let metadata = note.metadata();
+        let title = metadata.title();
+        let gist_id = metadata.gist_id();
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let markdown = publisher::markdown(&title, &buffer.text(&start_iter, &end_iter, true));
+
+        self.imp().actions.set_is_publishing(true);
+
+        spawn!(clone!(@weak self as obj => async move {
+            let res = spawn_blocking!(move || -> Result<Published, PublishError> {
+                let target = if target_name == "paste" {
+                    PublishTarget::Paste {
+                        url: paste_url,
+                        field: paste_field,
+                    }
+                } else {
+                    PublishTarget::Gist {
+                        token: publisher::lookup_gist_token()?,
+                    }
+                };
+
+                let client = UreqClient::new()?;
+                publisher::publish(&client, &target, &title, &markdown, gist_id.as_deref())
+            })
+            .await;
+
+            obj.imp().actions.set_is_publishing(false);
+
+            match res {
+                Ok(published) => {
+                    log::info!("Published `{}` to {}", note, published.url);
+
+                    // The gist is recreated if it was deleted, so the id may have changed
+                    if published.gist_id.is_some() {
+                        note.metadata().set_gist_id(published.gist_id.as_deref());
+                    }
+
+                    obj.clipboard().set_text(&published.url);
+
+                    let toast = adw::Toast::new(&gettext("Link copied to clipboard"));
+                    toast.set_button_label(Some(&gettext("_Open")));
+                    toast.set_action_name(Some("app.open-uri"));
+                    toast.set_action_target_value(Some(&published.url.to_variant()));
+                    obj.add_toast(&toast);
+                }
+                Err(err) => {
+                    log::error!("Failed to publish `{}`: {:?}", note, err);
+                    obj.add_toast(&adw::Toast::new(&publish_error_message(&err)));
+                }
+            }
+        }));
+    }
+
+    /// Show the print dialog for the note, with its title as the heading
+    fn print(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let markdown = publisher::markdown(
+            &note.metadata().title(),
+            &buffer.text(&start_iter, &end_iter, true),
+        );
+        let parent = self
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = print_layout::print(&markdown, parent.as_ref()).await {
+                log::error!("Failed to print `{}`: {:?}", note, err);
+                Application::default().error_log().push(ErrorReport::from_error(
+                    ErrorSource::Notes,
+                    &gettext("Failed to print the note"),
+                    &err,
+                ));
+            }
+        }));
+    }
+
+    fn add_toast(&self, toast: &adw::Toast) {
+        if let Some(window) = self.root().and_then(|root| root.downcast::<Window>().ok()) {
+            window.add_toast(toast);
+        }
+    }
+
+    fn setup_actions(&self) {
+        let imp = self.imp();
+        let actions = &imp.actions;
+
+        self.insert_action_group("note", Some(actions.action_group()));
+
+        let with_note = |f: fn(&Session, &Note)| {
+            clone!(@weak self as obj => move || {
+                if let Some(note) = obj.note() {
+                    f(&Session::default(), &note);
+                }
+            })
+        };
+
+        actions.connect_activate(
+            "edit-tags",
+            clone!(@weak self as obj => move || {
+                if let Err(err) = obj.activate_action("session.edit-selected-note-tags", None) {
+                    log::error!("Failed to edit tags: {:?}", err);
+                }
+            }),
+        );
+        actions.connect_activate(
+            "properties",
+            clone!(@weak self as obj => move || {
+                let button = &obj.imp().properties_button;
+                button.set_active(!button.is_active());
+            }),
+        );
+        actions.connect_activate(
+            "history",
+            clone!(@weak self as obj => move || {
+                let button = &obj.imp().history_button;
+                button.set_active(!button.is_active());
+            }),
+        );
+        actions.connect_activate("duplicate", with_note(Session::duplicate_note));
+        actions.connect_activate("merge", with_note(Session::show_merge_note_dialog));
+        actions.connect_activate(
+            "export",
+            with_note(|session, note| session.show_export_notes_dialog(&[note.clone()])),
+        );
+        actions.connect_activate("print", clone!(@weak self as obj => move || obj.print()));
+        actions.connect_activate(
+            "publish",
+            clone!(@weak self as obj => move || obj.publish()),
+        );
+        actions.connect_activate(
+            "copy-markdown",
+            clone!(@weak self as obj => move || obj.copy_as_markdown()),
+        );
+        actions.connect_activate(
+            "copy-html",
+            clone!(@weak self as obj => move || obj.copy_as_html()),
+        );
+        actions.connect_activate("copy-path", with_note(Session::copy_note_path));
+        actions.connect_activate("show-in-folder", with_note(Session::show_note_in_folder));
+        actions.connect_activate(
+            "trash",
+            with_note(|_, note| note.metadata().set_is_trashed(true)),
+        );
+        actions.connect_activate(
+            "restore",
+            with_note(|_, note| note.metadata().set_is_trashed(false)),
+        );
+        actions.connect_activate("delete", with_note(Session::show_delete_note_dialog));
+        actions.connect_activate("recreate-file", with_note(Session::write_note_file));
+        actions.connect_activate("discard", with_note(Session::show_discard_note_dialog));
+        actions.connect_activate("retry-save", with_note(Session::write_note_file));
+        actions.connect_activate(
+            "show-external-changes",
+            clone!(@weak self as obj => move || {
+                if let Some(note) = obj.note() {
+                    external_changes_dialog::show(&obj, &note);
+                }
+            }),
+        );
+    }
+
+    fn setup_color_box(&self) {
+        let imp = self.imp();
+
+        for color in NoteColor::ALL {
+            let button = gtk::Button::builder()
+                .tooltip_text(&color.display_name())
+                .build();
+            button.add_css_class("flat");
+            button.add_css_class("circular");
+
+            if color.is_none() {
+                button.set_icon_name("edit-clear-symbolic");
+            } else {
+                button.set_child(Some(&ColorDot::new(color)));
+            }
+
+            button.connect_clicked(clone!(@weak self as obj => move |_| {
+                if let Some(note) = obj.note() {
+                    note.metadata().set_color(color);
+                }
+                obj.imp().color_popover.popdown();
+            }));
+
+            imp.color_box.append(&button);
+        }
+    }
+}
+
+fn publish_error_message(err: &PublishError) -> String {
+    match err {
+        PublishError::MissingToken => gettext("No GitHub token found in the keyring"),
+        PublishError::Keyring(_) => gettext("Failed to read the GitHub token from the keyring"),
+        PublishError::MissingPasteUrl => gettext("No paste service URL is set"),
+        PublishError::Unauthorized => gettext("Publishing was denied, check your token"),
+        PublishError::Network(_) => gettext("Failed to publish, check your connection"),
+        PublishError::Status(_) | PublishError::InvalidResponse(_) => {
+            gettext("Failed to publish, the service had an error")
+        }
     }
 }
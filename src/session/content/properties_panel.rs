@@ -0,0 +1,439 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::RefCell;
+
+use crate::{
+    core::{
+        front_matter::{self, Property, PropertyError, PropertyValue},
+        MetadataData,
+    },
+    model::Note,
+};
+
+/// Widget a property is edited with
+#[derive(Debug)]
+enum Editor {
+    Text(gtk::Entry),
+    Boolean(gtk::Switch),
+    Date(gtk::Entry),
+    List(gtk::Entry),
+    Yaml(gtk::TextView),
+}
+
+#[derive(Debug)]
+pub struct PropertyRow {
+    key: String,
+    editor: Editor,
+    error_label: gtk::Label,
+}
+
+impl PropertyRow {
+    fn property(&self) -> Property {
+        let value = match self.editor {
+            Editor::Text(ref entry) => PropertyValue::Text(entry.text().to_string()),
+            Editor::Boolean(ref switch) => PropertyValue::Boolean(switch.is_active()),
+            Editor::Date(ref entry) => PropertyValue::Date(entry.text().to_string()),
+            Editor::List(ref entry) => {
+                PropertyValue::List(front_matter::list_from_text(&entry.text()))
+            }
+            Editor::Yaml(ref text_view) => PropertyValue::Yaml(text_view_text(text_view)),
+        };
+
+        Property {
+            key: self.key.clone(),
+            value,
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/content-properties-panel.ui")]
+    pub struct PropertiesPanel {
+        #[template_child]
+        pub raw_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub raw_view: TemplateChild<gtk::TextView>,
+
+        pub note: RefCell<Option<Note>>,
+        /// What the shown properties were loaded from, so only what was edited is applied
+        pub loaded_data: RefCell<Option<MetadataData>>,
+        pub rows: RefCell<Vec<PropertyRow>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PropertiesPanel {
+        const NAME: &'static str = "NwtyContentPropertiesPanel";
+        type Type = super::PropertiesPanel;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("properties-panel.apply", None, move |obj, _, _| {
+                obj.apply();
+            });
+
+            klass.install_action("properties-panel.reset", None, move |obj, _, _| {
+                obj.load();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PropertiesPanel {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "note",
+                    "Note",
+                    "Note to edit the front matter of",
+                    Note::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "note" => {
+                    let note = value.get().unwrap();
+                    obj.set_note(note);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "note" => obj.note().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            // Load again every time it is revealed, so it is not out of date
+            obj.connect_map(|obj| {
+                obj.load();
+            });
+
+            self.raw_button
+                .connect_toggled(clone!(@weak obj => move |button| {
+                    obj.set_is_raw(button.is_active());
+                }));
+        }
+    }
+
+    impl WidgetImpl for PropertiesPanel {}
+    impl BinImpl for PropertiesPanel {}
+}
+
+glib::wrapper! {
+    pub struct PropertiesPanel(ObjectSubclass<imp::PropertiesPanel>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl PropertiesPanel {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create PropertiesPanel.")
+    }
+
+    pub fn note(&self) -> Option<Note> {
+        self.imp().note.borrow().clone()
+    }
+
+    pub fn set_note(&self, note: Option<Note>) {
+        if self.note() == note {
+            return;
+        }
+
+        self.imp().note.replace(note);
+        self.load();
+
+        self.notify("note");
+    }
+
+    /// Show the front matter of the note, discarding the edits
+    fn load(&self) {
+        let imp = self.imp();
+
+        let data = self.note().map(|note| note.metadata().to_data());
+
+        match data {
+            Some(ref data) => {
+                self.set_rows(data);
+                imp.raw_view.buffer().set_text(&front_matter::to_yaml(data));
+            }
+            None => {
+                self.set_rows(&MetadataData::default());
+                imp.raw_view.buffer().set_text("");
+            }
+        }
+
+        imp.loaded_data.replace(data);
+        self.show_errors(&[]);
+    }
+
+    /// Write the edits into the metadata of the note, if they are valid
+    fn apply(&self) {
+        let (note, loaded_data) = match (self.note(), self.imp().loaded_data.borrow().clone()) {
+            (Some(note), Some(loaded_data)) => (note, loaded_data),
+            _ => return,
+        };
+
+        let res = if self.is_raw() {
+            front_matter::from_yaml(&text_view_text(&self.imp().raw_view)).map_err(|err| vec![err])
+        } else {
+            front_matter::apply(&self.properties())
+        };
+
+        match res {
+            Ok(data) => {
+                // Changing the metadata is what marks the note as unsaved
+                note.metadata().apply_data(&data, &loaded_data);
+                self.load();
+            }
+            Err(errors) => {
+                log::info!("Invalid properties for `{}`: {:?}", note, errors);
+                self.show_errors(&errors);
+            }
+        }
+    }
+
+    fn is_raw(&self) -> bool {
+        self.imp().stack.visible_child_name().as_deref() == Some("raw")
+    }
+
+    /// Switch between editing the rows and the raw YAML, carrying the edits over. If they are
+    /// not valid, the errors are shown and it stays as is.
+    fn set_is_raw(&self, is_raw: bool) {
+        let imp = self.imp();
+
+        if is_raw == self.is_raw() {
+            return;
+        }
+
+        let res = if is_raw {
+            front_matter::apply(&self.properties()).map(|data| {
+                imp.raw_view
+                    .buffer()
+                    .set_text(&front_matter::to_yaml(&data));
+            })
+        } else {
+            front_matter::from_yaml(&text_view_text(&imp.raw_view))
+                .map(|data| self.set_rows(&data))
+                .map_err(|err| vec![err])
+        };
+
+        match res {
+            Ok(()) => {
+                imp.stack
+                    .set_visible_child_name(if is_raw { "raw" } else { "rows" });
+                self.show_errors(&[]);
+            }
+            Err(errors) => {
+                self.show_errors(&errors);
+                imp.raw_button.set_active(!is_raw);
+            }
+        }
+    }
+
+    fn properties(&self) -> Vec<Property> {
+        self.imp()
+            .rows
+            .borrow()
+            .iter()
+            .map(PropertyRow::property)
+            .collect()
+    }
+
+    fn set_rows(&self, data: &MetadataData) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.list_box.first_child() {
+            imp.list_box.remove(&child);
+        }
+
+        let rows = front_matter::properties(data)
+            .into_iter()
+            .map(|property| {
+                let (row, property_row) = self.create_row(property);
+                imp.list_box.append(&row);
+                property_row
+            })
+            .collect();
+
+        imp.rows.replace(rows);
+    }
+
+    fn create_row(&self, property: Property) -> (gtk::ListBoxRow, PropertyRow) {
+        let key_label = gtk::Label::builder()
+            .label(&property.key)
+            .xalign(0.0)
+            .width_chars(14)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        key_label.add_css_class("dim-label");
+
+        let activate_entry = clone!(@weak self as obj => move |_: &gtk::Entry| {
+            obj.apply();
+        });
+
+        let (editor, editor_widget) = match property.value {
+            PropertyValue::Text(ref text) => {
+                let entry = gtk::Entry::builder().text(text).hexpand(true).build();
+                entry.connect_activate(activate_entry);
+                (Editor::Text(entry.clone()), entry.upcast::<gtk::Widget>())
+            }
+            PropertyValue::Boolean(is_active) => {
+                let switch = gtk::Switch::builder()
+                    .active(is_active)
+                    .hexpand(true)
+                    .halign(gtk::Align::End)
+                    .valign(gtk::Align::Center)
+                    .build();
+                (Editor::Boolean(switch.clone()), switch.upcast())
+            }
+            PropertyValue::Date(ref text) => {
+                let entry = gtk::Entry::builder()
+                    .text(text)
+                    .hexpand(true)
+                    .placeholder_text("2022-01-31T14:30:00+01:00")
+                    .build();
+                entry.connect_activate(activate_entry);
+                (Editor::Date(entry.clone()), entry.upcast())
+            }
+            PropertyValue::List(ref items) => {
+                let entry = gtk::Entry::builder()
+                    .text(&front_matter::list_to_text(items))
+                    .hexpand(true)
+                    .placeholder_text(&gettext("Separated by commas"))
+                    .build();
+                entry.connect_activate(activate_entry);
+                (Editor::List(entry.clone()), entry.upcast())
+            }
+            PropertyValue::Yaml(ref yaml) => {
+                let text_view = gtk::TextView::builder()
+                    .monospace(true)
+                    .hexpand(true)
+                    .top_margin(6)
+                    .bottom_margin(6)
+                    .left_margin(6)
+                    .right_margin(6)
+                    .build();
+                text_view.buffer().set_text(yaml);
+                text_view.add_css_class("card");
+                (Editor::Yaml(text_view.clone()), text_view.upcast())
+            }
+        };
+
+        let editor_box = gtk::Box::builder().spacing(12).build();
+        editor_box.append(&key_label);
+        editor_box.append(&editor_widget);
+
+        let error_label = gtk::Label::builder()
+            .xalign(0.0)
+            .wrap(true)
+            .wrap_mode(gtk::pango::WrapMode::WordChar)
+            .visible(false)
+            .build();
+        error_label.add_css_class("error");
+        error_label.add_css_class("caption");
+
+        let row_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        row_box.append(&editor_box);
+        row_box.append(&error_label);
+
+        let row = gtk::ListBoxRow::builder()
+            .activatable(false)
+            .child(&row_box)
+            .build();
+
+        let property_row = PropertyRow {
+            key: property.key,
+            editor,
+            error_label,
+        };
+
+        (row, property_row)
+    }
+
+    /// Show each error under its row, and the ones about no row in particular above them all
+    fn show_errors(&self, errors: &[PropertyError]) {
+        let imp = self.imp();
+
+        for row in imp.rows.borrow().iter() {
+            let messages = errors
+                .iter()
+                .filter(|error| error.key.as_deref() == Some(row.key.as_str()))
+                .map(|error| error.message.as_str())
+                .collect::<Vec<_>>();
+
+            row.error_label.set_label(&messages.join("\n"));
+            row.error_label.set_visible(!messages.is_empty());
+        }
+
+        let shown_keys = imp
+            .rows
+            .borrow()
+            .iter()
+            .map(|row| row.key.clone())
+            .collect::<Vec<_>>();
+        let other_messages = errors
+            .iter()
+            .filter(|error| {
+                // Rows of the keys are not shown in raw mode
+                self.is_raw()
+                    || error
+                        .key
+                        .as_ref()
+                        .map_or(true, |key| !shown_keys.contains(key))
+            })
+            .map(|error| error.message.as_str())
+            .collect::<Vec<_>>();
+
+        imp.error_label.set_label(&other_messages.join("\n"));
+        imp.error_label.set_visible(!other_messages.is_empty());
+    }
+}
+
+fn text_view_text(text_view: &gtk::TextView) -> String {
+    let buffer = text_view.buffer();
+    let (start_iter, end_iter) = buffer.bounds();
+    buffer.text(&start_iter, &end_iter, true).to_string()
+}
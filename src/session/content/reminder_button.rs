@@ -0,0 +1,212 @@
+use adw::subclass::prelude::*;
+use chrono::{Datelike, Local, NaiveDateTime, Timelike};
+use gettextrs::gettext;
+use gtk::{
+    glib::{self, clone, closure},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::RefCell;
+
+use crate::{
+    core::Reminder,
+    model::{Note, NoteMetadata},
+};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/content-reminder-button.ui")]
+    pub struct ReminderButton {
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub calendar: TemplateChild<gtk::Calendar>,
+        #[template_child]
+        pub hour_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub minute_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub remove_button: TemplateChild<gtk::Button>,
+
+        pub note: RefCell<Option<Note>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReminderButton {
+        const NAME: &'static str = "NwtyContentReminderButton";
+        type Type = super::ReminderButton;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("reminder-button.set", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    note.metadata()
+                        .set_reminder(Some(Reminder::from_local(obj.picked_datetime())));
+                }
+                obj.imp().popover.popdown();
+            });
+
+            klass.install_action("reminder-button.remove", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    note.metadata().set_reminder(None);
+                }
+                obj.imp().popover.popdown();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ReminderButton {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "note",
+                    "Note",
+                    "Note to set the reminder of",
+                    Note::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "note" => {
+                    let note = value.get().unwrap();
+                    obj.set_note(note);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "note" => obj.note().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.popover.connect_show(clone!(@weak obj => move |_| {
+                obj.load_picker();
+            }));
+
+            obj.setup_expressions();
+        }
+    }
+
+    impl WidgetImpl for ReminderButton {}
+    impl BinImpl for ReminderButton {}
+}
+
+glib::wrapper! {
+    pub struct ReminderButton(ObjectSubclass<imp::ReminderButton>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl ReminderButton {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ReminderButton.")
+    }
+
+    pub fn note(&self) -> Option<Note> {
+        self.imp().note.borrow().clone()
+    }
+
+    pub fn set_note(&self, note: Option<Note>) {
+        if self.note() == note {
+            return;
+        }
+
+        self.imp().note.replace(note);
+        self.notify("note");
+    }
+
+    /// Show the current reminder in the picker, or the start of the next hour if there is none
+    fn load_picker(&self) {
+        let imp = self.imp();
+
+        let datetime = self
+            .note()
+            .and_then(|note| note.metadata().reminder())
+            .map_or_else(
+                || {
+                    let next_hour = (Local::now() + chrono::Duration::hours(1)).naive_local();
+                    next_hour.date().and_hms(next_hour.hour(), 0, 0)
+                },
+                |reminder| reminder.to_local().naive_local(),
+            );
+
+        match glib::DateTime::from_local(
+            datetime.year(),
+            datetime.month() as i32,
+            datetime.day() as i32,
+            0,
+            0,
+            0.0,
+        ) {
+            Ok(date) => imp.calendar.select_day(&date),
+            Err(err) => log::warn!("Failed to select day on calendar: {:?}", err),
+        }
+
+        imp.hour_spin_button.set_value(f64::from(datetime.hour()));
+        imp.minute_spin_button
+            .set_value(f64::from(datetime.minute()));
+    }
+
+    fn picked_datetime(&self) -> NaiveDateTime {
+        let imp = self.imp();
+        let date = imp.calendar.date();
+
+        chrono::NaiveDate::from_ymd(date.year(), date.month() as u32, date.day_of_month() as u32)
+            .and_hms(
+                imp.hour_spin_button.value_as_int() as u32,
+                imp.minute_spin_button.value_as_int() as u32,
+                0,
+            )
+    }
+
+    fn setup_expressions(&self) {
+        let imp = self.imp();
+
+        let reminder_expression = Self::this_expression("note")
+            .chain_property::<Note>("metadata")
+            .chain_property::<NoteMetadata>("reminder");
+
+        reminder_expression
+            .chain_closure::<String>(closure!(|_: Self, reminder: Option<Reminder>| {
+                reminder.map_or_else(
+                    || gettext("Reminder"),
+                    |reminder| gettext!("Reminder on {}", reminder.display()),
+                )
+            }))
+            .bind(&imp.menu_button.get(), "tooltip-text", Some(self));
+
+        reminder_expression
+            .chain_closure::<bool>(closure!(|_: Self, reminder: Option<Reminder>| {
+                reminder.is_some()
+            }))
+            .bind(&imp.remove_button.get(), "visible", Some(self));
+    }
+}
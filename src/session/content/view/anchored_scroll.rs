@@ -0,0 +1,227 @@
+use std::{ops::Range, time::Duration};
+
+use crate::core::heading_line;
+
+/// How long what is scrolled to stays highlighted
+pub const HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+/// How long the highlight takes to fade out at the end
+const HIGHLIGHT_FADE_DURATION: Duration = Duration::from_millis(500);
+
+/// Where the text of a note is scrolled to, like a match of a search or a section of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrollTarget {
+    /// `highlight_len` characters from the character at `offset`
+    Offset { offset: i32, highlight_len: i32 },
+    /// The first heading with this text, of any level
+    Heading(String),
+}
+
+impl ScrollTarget {
+    /// The characters of `text` to scroll to and highlight, or `None` if it has no such
+    /// heading
+    pub fn range_in(&self, text: &str) -> Option<Range<i32>> {
+        match self {
+            Self::Offset {
+                offset,
+                highlight_len,
+            } => Some(clamped_range(
+                *offset,
+                *highlight_len,
+                text.chars().count() as i32,
+            )),
+            Self::Heading(heading) => {
+                let range = heading_line(text, heading)?;
+                // The buffer counts in characters
+                let start = text[..range.start].chars().count() as i32;
+                Some(start..start + text[range].chars().count() as i32)
+            }
+        }
+    }
+}
+
+/// `highlight_len` characters from `offset`, kept in the `n_chars` of the text, as it may have
+/// changed since the offset was found
+fn clamped_range(offset: i32, highlight_len: i32, n_chars: i32) -> Range<i32> {
+    let start = offset.clamp(0, n_chars);
+    let end = start.saturating_add(highlight_len.max(0)).min(n_chars);
+    start..end
+}
+
+/// How opaque the highlight is after `elapsed`, from 1 to 0 as it fades out
+pub fn highlight_opacity(elapsed: Duration) -> f32 {
+    let remaining = HIGHLIGHT_DURATION.saturating_sub(elapsed);
+    (remaining.as_secs_f32() / HIGHLIGHT_FADE_DURATION.as_secs_f32()).min(1.0)
+}
+
+/// Whether a view can be scrolled, which is once it is shown and allocated
+pub fn is_allocated(is_mapped: bool, height: i32) -> bool {
+    is_mapped && height > 0
+}
+
+/// The scroll asked for before the view could do it, done once the view is allocated. Only
+/// the last one is kept, as it is where the view ends up anyway.
+#[derive(Debug, Default)]
+pub struct PendingScroll {
+    target: Option<ScrollTarget>,
+    is_ready: bool,
+}
+
+impl PendingScroll {
+    pub const fn is_ready(&self) -> bool {
+        self.is_ready
+    }
+
+    /// Ask to scroll to `target`, which is given back if it can be done right away
+    pub fn push(&mut self, target: ScrollTarget) -> Option<ScrollTarget> {
+        if self.is_ready {
+            return Some(target);
+        }
+
+        self.target = Some(target);
+        None
+    }
+
+    /// Wait for the view again, like when it shows another note, dropping the scroll asked
+    /// for in the previous one
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Mark as ready and take the scroll asked for meanwhile
+    pub fn set_ready(&mut self) -> Option<ScrollTarget> {
+        self.is_ready = true;
+        self.target.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn offset(offset: i32, highlight_len: i32) -> ScrollTarget {
+        ScrollTarget::Offset {
+            offset,
+            highlight_len,
+        }
+    }
+
+    #[test]
+    fn offset_clamping() {
+        assert_eq!(clamped_range(2, 3, 10), 2..5);
+        assert_eq!(clamped_range(8, 5, 10), 8..10);
+        assert_eq!(clamped_range(12, 3, 10), 10..10);
+        assert_eq!(clamped_range(-4, 3, 10), 0..3);
+        assert_eq!(clamped_range(2, -1, 10), 2..2);
+        assert_eq!(clamped_range(2, i32::MAX, 10), 2..10);
+        assert_eq!(clamped_range(0, 3, 0), 0..0);
+
+        // In characters, not bytes
+        assert_eq!(offset(1, 10).range_in("Café"), Some(1..4));
+    }
+
+    #[test]
+    fn heading_range() {
+        let text = "# Tripé\n\n## Packing\nSocks";
+        assert_eq!(
+            ScrollTarget::Heading("packing".to_string()).range_in(text),
+            Some(9..19)
+        );
+        assert_eq!(
+            ScrollTarget::Heading("Tripé".to_string()).range_in(text),
+            Some(0..7)
+        );
+        assert_eq!(
+            ScrollTarget::Heading("Socks".to_string()).range_in(text),
+            None
+        );
+    }
+
+    #[test]
+    fn highlight_fades() {
+        assert_eq!(highlight_opacity(Duration::ZERO), 1.0);
+        assert_eq!(highlight_opacity(Duration::from_millis(1500)), 1.0);
+        assert!((highlight_opacity(Duration::from_millis(1750)) - 0.5).abs() < 1e-6);
+        assert_eq!(highlight_opacity(HIGHLIGHT_DURATION), 0.0);
+        assert_eq!(highlight_opacity(Duration::from_secs(3)), 0.0);
+    }
+
+    /// A view whose allocation comes a few frames after it is asked to scroll, like one
+    /// that was just given a note while being hidden
+    struct DeferredAllocation {
+        pending_scroll: PendingScroll,
+        is_mapped: bool,
+        height: i32,
+        scrolled_to: Vec<ScrollTarget>,
+    }
+
+    impl DeferredAllocation {
+        fn new() -> Self {
+            Self {
+                pending_scroll: PendingScroll::default(),
+                is_mapped: false,
+                height: 0,
+                scrolled_to: Vec::new(),
+            }
+        }
+
+        fn scroll_to(&mut self, target: ScrollTarget) {
+            if let Some(target) = self.pending_scroll.push(target) {
+                self.scrolled_to.push(target);
+            }
+        }
+
+        /// Like the tick callback of the view, returning whether to keep waiting
+        fn tick(&mut self) -> bool {
+            if !is_allocated(self.is_mapped, self.height) {
+                return true;
+            }
+
+            if let Some(target) = self.pending_scroll.set_ready() {
+                self.scrolled_to.push(target);
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn queued_until_allocated() {
+        let mut view = DeferredAllocation::new();
+        view.scroll_to(offset(1, 2));
+        view.scroll_to(offset(5, 2));
+
+        assert!(view.tick());
+        view.is_mapped = true;
+        assert!(view.tick());
+        assert!(view.scrolled_to.is_empty());
+        assert!(!view.pending_scroll.is_ready());
+
+        // Only to where it was asked last
+        view.height = 400;
+        assert!(!view.tick());
+        assert_eq!(view.scrolled_to, [offset(5, 2)]);
+        assert!(view.pending_scroll.is_ready());
+
+        // Right away once allocated
+        view.scroll_to(offset(7, 1));
+        assert_eq!(view.scrolled_to, [offset(5, 2), offset(7, 1)]);
+    }
+
+    #[test]
+    fn reset_drops_pending() {
+        let mut view = DeferredAllocation::new();
+        view.scroll_to(offset(1, 2));
+        view.pending_scroll.reset();
+
+        view.is_mapped = true;
+        view.height = 400;
+        assert!(!view.tick());
+        assert!(view.scrolled_to.is_empty());
+
+        // Waiting again for the next note
+        view.pending_scroll.reset();
+        view.scroll_to(offset(3, 1));
+        assert!(view.scrolled_to.is_empty());
+        assert!(!view.tick());
+        assert_eq!(view.scrolled_to, [offset(3, 1)]);
+    }
+}
@@ -0,0 +1,147 @@
+use gtk::{glib, graphene, prelude::*, subclass::prelude::*};
+use gtk_source::{prelude::*, subclass::prelude::*};
+
+use std::cell::{Cell, RefCell};
+
+use crate::{
+    core::{changed_lines, ChangeMark},
+    utils::palette::Palette,
+};
+
+const BAR_WIDTH: f32 = 3.0;
+/// Space between the bar and the text
+const BAR_SPACING: i32 = 3;
+/// Lines that were removed are marked by a thin line above the line after them
+const REMOVED_MARK_HEIGHT: f32 = 2.0;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct ChangeRenderer {
+        /// The text of the buffer as it was last saved, which the lines are compared with
+        pub saved_text: RefCell<Option<String>>,
+        /// Mark of each line, by its number
+        pub marks: RefCell<Vec<Option<ChangeMark>>>,
+        /// Taken once per draw, as it is the same for all the lines
+        pub palette: Cell<Option<Palette>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ChangeRenderer {
+        const NAME: &'static str = "NwtyContentViewChangeRenderer";
+        type Type = super::ChangeRenderer;
+        type ParentType = gtk_source::GutterRenderer;
+    }
+
+    impl ObjectImpl for ChangeRenderer {}
+
+    impl WidgetImpl for ChangeRenderer {
+        fn measure(
+            &self,
+            obj: &Self::Type,
+            orientation: gtk::Orientation,
+            for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            if orientation != gtk::Orientation::Horizontal {
+                return self.parent_measure(obj, orientation, for_size);
+            }
+
+            let width = BAR_WIDTH as i32 + BAR_SPACING;
+            (width, width, -1, -1)
+        }
+    }
+
+    impl GutterRendererImpl for ChangeRenderer {
+        fn change_buffer(&self, obj: &Self::Type, old_buffer: Option<&gtk_source::Buffer>) {
+            // The saved text of the previous buffer is dropped with it, like when another note
+            // is shown or the note is closed
+            obj.set_saved_text(None);
+
+            self.parent_change_buffer(obj, old_buffer);
+        }
+
+        fn begin(&self, obj: &Self::Type, lines: &gtk_source::GutterLines) {
+            self.palette.set(Some(Palette::current()));
+
+            self.parent_begin(obj, lines);
+        }
+
+        fn snapshot_line(
+            &self,
+            obj: &Self::Type,
+            snapshot: &gtk::Snapshot,
+            lines: &gtk_source::GutterLines,
+            line: u32,
+        ) {
+            obj.snapshot_mark(snapshot, lines, line);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Column of the gutter marking the lines changed since the note was last saved
+    pub struct ChangeRenderer(ObjectSubclass<imp::ChangeRenderer>)
+        @extends gtk::Widget, gtk_source::GutterRenderer;
+}
+
+impl ChangeRenderer {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ChangeRenderer.")
+    }
+
+    pub fn has_saved_text(&self) -> bool {
+        self.imp().saved_text.borrow().is_some()
+    }
+
+    /// Compare the lines with `saved_text` from now on, which clears the marks as the buffer
+    /// has this text when it is just saved. `None` drops it, so no line is marked.
+    pub fn set_saved_text(&self, saved_text: Option<String>) {
+        let imp = self.imp();
+        imp.saved_text.replace(saved_text);
+        imp.marks.borrow_mut().clear();
+        self.queue_draw();
+    }
+
+    /// Mark the lines of `text`, which is the text of the buffer, that differ from the saved
+    /// text. Nothing is marked without one.
+    pub fn update(&self, text: &str) {
+        let imp = self.imp();
+
+        let marks = match *imp.saved_text.borrow() {
+            Some(ref saved_text) => changed_lines(saved_text, text),
+            None => return,
+        };
+
+        imp.marks.replace(marks);
+        self.queue_draw();
+    }
+
+    fn snapshot_mark(&self, snapshot: &gtk::Snapshot, lines: &gtk_source::GutterLines, line: u32) {
+        let imp = self.imp();
+
+        let mark = match imp.marks.borrow().get(line as usize) {
+            Some(Some(mark)) => *mark,
+            _ => return,
+        };
+
+        let (y, height) = lines.line_yrange(line, gtk_source::GutterRendererAlignmentMode::Cell);
+        let rect = match mark {
+            ChangeMark::Added | ChangeMark::Modified => {
+                graphene::Rect::new(0.0, y as f32, BAR_WIDTH, height as f32)
+            }
+            ChangeMark::Removed => {
+                graphene::Rect::new(0.0, y as f32, self.width() as f32, REMOVED_MARK_HEIGHT)
+            }
+        };
+
+        let palette = imp.palette.get().unwrap_or_else(Palette::current);
+        snapshot.append_color(&palette.change_mark_color(mark).to_rgba(1.0), &rect);
+    }
+}
+
+impl Default for ChangeRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+use gtk::{gdk, glib, graphene, prelude::*, subclass::prelude::*};
+use gtk_source::{prelude::*, subclass::prelude::*};
+
+use std::cell::RefCell;
+
+use crate::core::heading_levels;
+
+/// The glyph that is the widest, which the column is sized for
+const WIDEST_GLYPH: &str = "H6";
+/// Opacity of the glyphs, so they don't draw more attention than the text
+const GLYPH_ALPHA: f32 = 0.55;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct HeadingRenderer {
+        /// Level of each line that is a heading, by its number
+        pub levels: RefCell<Vec<Option<usize>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HeadingRenderer {
+        const NAME: &'static str = "NwtyContentViewHeadingRenderer";
+        type Type = super::HeadingRenderer;
+        type ParentType = gtk_source::GutterRenderer;
+    }
+
+    impl ObjectImpl for HeadingRenderer {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_xpad(6);
+            obj.set_xalign(1.0);
+            obj.set_yalign(0.5);
+        }
+    }
+
+    impl WidgetImpl for HeadingRenderer {
+        fn measure(
+            &self,
+            obj: &Self::Type,
+            orientation: gtk::Orientation,
+            for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            if orientation != gtk::Orientation::Horizontal {
+                return self.parent_measure(obj, orientation, for_size);
+            }
+
+            let (glyph_width, _) = obj.create_pango_layout(Some(WIDEST_GLYPH)).pixel_size();
+            let width = glyph_width + 2 * obj.xpad();
+            (width, width, -1, -1)
+        }
+    }
+
+    impl GutterRendererImpl for HeadingRenderer {
+        fn change_buffer(&self, obj: &Self::Type, old_buffer: Option<&gtk_source::Buffer>) {
+            // They are of the lines of the previous buffer
+            self.levels.borrow_mut().clear();
+
+            self.parent_change_buffer(obj, old_buffer);
+        }
+
+        fn snapshot_line(
+            &self,
+            obj: &Self::Type,
+            snapshot: &gtk::Snapshot,
+            _lines: &gtk_source::GutterLines,
+            line: u32,
+        ) {
+            obj.snapshot_glyph(snapshot, line);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Column of the gutter showing the level of the headings next to them
+    pub struct HeadingRenderer(ObjectSubclass<imp::HeadingRenderer>)
+        @extends gtk::Widget, gtk_source::GutterRenderer;
+}
+
+impl HeadingRenderer {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create HeadingRenderer.")
+    }
+
+    /// Find the headings of `text`, which is the text of the buffer
+    pub fn update(&self, text: &str) {
+        self.imp().levels.replace(heading_levels(text));
+        self.queue_draw();
+    }
+
+    fn snapshot_glyph(&self, snapshot: &gtk::Snapshot, line: u32) {
+        let level = match self.imp().levels.borrow().get(line as usize) {
+            Some(Some(level)) => *level,
+            _ => return,
+        };
+
+        let layout = self.create_pango_layout(Some(&heading_glyph(level)));
+        let (width, height) = layout.pixel_size();
+        let (x, y) = self.align_cell(line, width as f32, height as f32);
+
+        let color = self.style_context().color();
+        let color = gdk::RGBA::new(
+            color.red(),
+            color.green(),
+            color.blue(),
+            color.alpha() * GLYPH_ALPHA,
+        );
+
+        snapshot.save();
+        snapshot.translate(&graphene::Point::new(x, y));
+        snapshot.append_layout(&layout, &color);
+        snapshot.restore();
+    }
+}
+
+impl Default for HeadingRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `§` for the top level, like the title of a section, and `H2` to `H6` for the others
+fn heading_glyph(level: usize) -> String {
+    if level == 1 {
+        "§".to_string()
+    } else {
+        format!("H{}", level)
+    }
+}
@@ -0,0 +1,398 @@
+use gettextrs::gettext;
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::{Cell, RefCell};
+
+use crate::{
+    core::{find_links, is_code_fence},
+    utils::{palette, EditProfiler},
+};
+
+const LINK_TAG_NAME: &str = "link";
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct Linkifier {
+        pub view: OnceCell<gtk::TextView>,
+        pub buffer: RefCell<Option<(gtk::TextBuffer, Vec<glib::SignalHandlerId>)>>,
+        /// Whether the lines about to be edited have a code fence
+        pub is_editing_fence: Cell<bool>,
+        /// The link that was right-clicked, for the actions of the context menu
+        pub context_link: RefCell<Option<String>>,
+        pub context_menu: OnceCell<gio::Menu>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Linkifier {
+        const NAME: &'static str = "NwtyContentViewLinkifier";
+        type Type = super::Linkifier;
+    }
+
+    impl ObjectImpl for Linkifier {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "view",
+                    "View",
+                    "Text view whose buffer has its links detected",
+                    gtk::TextView::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "view" => {
+                    let view = value.get().unwrap();
+                    self.view.set(view).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "view" => obj.view().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_actions();
+            obj.setup_controllers();
+
+            obj.view()
+                .connect_buffer_notify(clone!(@weak obj => move |view| {
+                    obj.set_buffer(Some(view.buffer()));
+                }));
+            obj.set_buffer(Some(obj.view().buffer()));
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            obj.set_buffer(None);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Underlines the links in the buffer of a text view as it is edited, except in code
+    /// blocks, and opens them on Ctrl+click or middle-click
+    pub struct Linkifier(ObjectSubclass<imp::Linkifier>);
+}
+
+impl Linkifier {
+    pub fn new(view: &impl IsA<gtk::TextView>) -> Self {
+        glib::Object::new(&[("view", view.upcast_ref::<gtk::TextView>())])
+            .expect("Failed to create Linkifier.")
+    }
+
+    fn view(&self) -> gtk::TextView {
+        self.imp().view.get().unwrap().clone()
+    }
+
+    fn set_buffer(&self, buffer: Option<gtk::TextBuffer>) {
+        let imp = self.imp();
+
+        if let Some((old_buffer, handler_ids)) = imp.buffer.take() {
+            for handler_id in handler_ids {
+                old_buffer.disconnect(handler_id);
+            }
+        }
+
+        let buffer = match buffer {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        // Code fences change whether the lines after them are in a code block, so these
+        // are updated too when one is edited
+        let insert_text_handler_id =
+            buffer.connect_insert_text(clone!(@weak self as obj => move |buffer, location, _| {
+                let line = location.line();
+                obj.imp().is_editing_fence.set(has_code_fence(buffer, line, line));
+            }));
+
+        // Ran after the default handler, so the buffer already has the text
+        let after_insert_text_handler_id = buffer.connect_local(
+            "insert-text",
+            true,
+            clone!(@weak self as obj => @default-return None, move |values| {
+                let buffer = values[0].get::<gtk::TextBuffer>().unwrap();
+                // Moved to the end of the inserted text by the default handler
+                let location = values[1].get::<gtk::TextIter>().unwrap();
+                let text = values[2].get::<String>().unwrap();
+
+                let end_line = location.line();
+                let start_line = end_line - text.matches('\n').count() as i32;
+                EditProfiler::default().measure("linkifier", || {
+                    obj.update_edited_lines(&buffer, start_line, end_line);
+                });
+                None
+            }),
+        );
+
+        let delete_range_handler_id =
+            buffer.connect_delete_range(clone!(@weak self as obj => move |buffer, start, end| {
+                let is_editing_fence = has_code_fence(buffer, start.line(), end.line());
+                obj.imp().is_editing_fence.set(is_editing_fence);
+            }));
+
+        let after_delete_range_handler_id = buffer.connect_local(
+            "delete-range",
+            true,
+            clone!(@weak self as obj => @default-return None, move |values| {
+                let buffer = values[0].get::<gtk::TextBuffer>().unwrap();
+                // Both ends are at where the range was after the default handler
+                let line = values[1].get::<gtk::TextIter>().unwrap().line();
+                EditProfiler::default().measure("linkifier", || {
+                    obj.update_edited_lines(&buffer, line, line);
+                });
+                None
+            }),
+        );
+
+        update_lines(&buffer, 0, None);
+
+        imp.buffer.replace(Some((
+            buffer,
+            vec![
+                insert_text_handler_id,
+                after_insert_text_handler_id,
+                delete_range_handler_id,
+                after_delete_range_handler_id,
+            ],
+        )));
+    }
+
+    fn update_edited_lines(&self, buffer: &gtk::TextBuffer, start_line: i32, end_line: i32) {
+        let was_editing_fence = self.imp().is_editing_fence.take();
+
+        if was_editing_fence || has_code_fence(buffer, start_line, end_line) {
+            update_lines(buffer, start_line, None);
+        } else {
+            update_lines(buffer, start_line, Some(end_line));
+        }
+    }
+
+    /// The URI of the link at the given point in the view
+    fn link_at(&self, x: f64, y: f64) -> Option<String> {
+        let view = self.view();
+        let buffer = view.buffer();
+        let tag = buffer.tag_table().lookup(LINK_TAG_NAME)?;
+
+        let (buffer_x, buffer_y) =
+            view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+        let iter = view.iter_at_location(buffer_x, buffer_y)?;
+
+        if !iter.has_tag(&tag) {
+            return None;
+        }
+
+        let (line_start, line_end) = line_bounds(&buffer, iter.line())?;
+        let text = buffer.text(&line_start, &line_end, true);
+        let index = text
+            .char_indices()
+            .nth(iter.line_offset() as usize)
+            .map_or(text.len(), |(index, _)| index);
+
+        find_links(&text)
+            .into_iter()
+            .find(|link| link.range.contains(&index))
+            .map(|link| link.uri)
+    }
+
+    fn setup_actions(&self) {
+        let action_group = gio::SimpleActionGroup::new();
+
+        let action_open = gio::SimpleAction::new("open", None);
+        action_open.connect_activate(clone!(@weak self as obj => move |_, _| {
+            if let Some(ref uri) = *obj.imp().context_link.borrow() {
+                open_link(uri);
+            }
+        }));
+        action_group.add_action(&action_open);
+
+        let action_copy = gio::SimpleAction::new("copy", None);
+        action_copy.connect_activate(clone!(@weak self as obj => move |_, _| {
+            if let Some(ref uri) = *obj.imp().context_link.borrow() {
+                // Emails are more useful to paste without the scheme
+                obj.view().clipboard().set_text(uri.trim_start_matches("mailto:"));
+            }
+        }));
+        action_group.add_action(&action_copy);
+
+        self.view().insert_action_group("link", Some(&action_group));
+
+        let context_menu = gio::Menu::new();
+        context_menu.append(Some(&gettext("_Open Link")), Some("link.open"));
+        context_menu.append(Some(&gettext("_Copy Link")), Some("link.copy"));
+        self.imp().context_menu.set(context_menu).unwrap();
+    }
+
+    fn setup_controllers(&self) {
+        let view = self.view();
+
+        // Before the view handles it, so clicking a link does not also paste or move the cursor
+        let gesture_click = gtk::GestureClick::new();
+        gesture_click.set_button(0);
+        gesture_click.set_propagation_phase(gtk::PropagationPhase::Capture);
+        gesture_click.connect_pressed(clone!(@weak self as obj => move |gesture, _, x, y| {
+            let link = obj.link_at(x, y);
+
+            match gesture.current_button() {
+                gdk::BUTTON_SECONDARY => {
+                    // Only shown in the context menu of the view when a link was clicked
+                    let view = obj.view();
+                    if link.is_some() {
+                        view.set_extra_menu(obj.imp().context_menu.get());
+                    } else {
+                        view.set_extra_menu(None::<&gio::MenuModel>);
+                    }
+                    obj.imp().context_link.replace(link);
+                }
+                gdk::BUTTON_PRIMARY | gdk::BUTTON_MIDDLE => {
+                    let is_primary = gesture.current_button() == gdk::BUTTON_PRIMARY;
+                    let has_control = gesture
+                        .current_event_state()
+                        .contains(gdk::ModifierType::CONTROL_MASK);
+
+                    if let Some(uri) = link.filter(|_| !is_primary || has_control) {
+                        gesture.set_state(gtk::EventSequenceState::Claimed);
+                        open_link(&uri);
+                    }
+                }
+                _ => {}
+            }
+        }));
+        view.add_controller(&gesture_click);
+
+        let motion_controller = gtk::EventControllerMotion::new();
+        motion_controller.connect_motion(clone!(@weak self as obj => move |_, x, y| {
+            let cursor_name = if obj.link_at(x, y).is_some() {
+                "pointer"
+            } else {
+                "text"
+            };
+            obj.view().set_cursor_from_name(Some(cursor_name));
+        }));
+        view.add_controller(&motion_controller);
+    }
+}
+
+fn open_link(uri: &str) {
+    if let Err(err) = gio::AppInfo::launch_default_for_uri(uri, gio::AppLaunchContext::NONE) {
+        log::error!("Failed to open link `{}`: {:?}", uri, err);
+    }
+}
+
+fn link_tag(buffer: &gtk::TextBuffer) -> gtk::TextTag {
+    let tag_table = buffer.tag_table();
+
+    tag_table.lookup(LINK_TAG_NAME).unwrap_or_else(|| {
+        let tag = gtk::TextTag::builder()
+            .name(LINK_TAG_NAME)
+            .foreground_rgba(&palette::LINK.to_rgba(1.0))
+            .underline(gtk::pango::Underline::Single)
+            .build();
+        tag_table.add(&tag);
+        tag
+    })
+}
+
+/// Detect the links from `start_line` to `end_line`, or until the end if `None`
+fn update_lines(buffer: &gtk::TextBuffer, start_line: i32, end_line: Option<i32>) {
+    let tag = link_tag(buffer);
+    let last_line = buffer.line_count() - 1;
+    let end_line = end_line.map_or(last_line, |end_line| end_line.min(last_line));
+    let start_line = start_line.max(0);
+
+    let mut is_in_code_block = is_in_code_block(buffer, start_line);
+
+    for line in start_line..=end_line {
+        let (line_start, line_end) = match line_bounds(buffer, line) {
+            Some(bounds) => bounds,
+            None => break,
+        };
+
+        buffer.remove_tag(&tag, &line_start, &line_end);
+
+        let text = buffer.text(&line_start, &line_end, true);
+
+        if is_code_fence(&text) {
+            is_in_code_block = !is_in_code_block;
+            continue;
+        }
+
+        if is_in_code_block {
+            continue;
+        }
+
+        for link in find_links(&text) {
+            let start_offset =
+                line_start.offset() + text[..link.range.start].chars().count() as i32;
+            let end_offset = start_offset + text[link.range].chars().count() as i32;
+            buffer.apply_tag(
+                &tag,
+                &buffer.iter_at_offset(start_offset),
+                &buffer.iter_at_offset(end_offset),
+            );
+        }
+    }
+}
+
+/// The start and end of `line`, not including its line break
+fn line_bounds(buffer: &gtk::TextBuffer, line: i32) -> Option<(gtk::TextIter, gtk::TextIter)> {
+    let line_start = buffer.iter_at_line(line)?;
+
+    let mut line_end = line_start.clone();
+    // Moves to the end of the next line if already at the end
+    if !line_end.ends_line() {
+        line_end.forward_to_line_end();
+    }
+
+    Some((line_start, line_end))
+}
+
+/// Whether a line from `start_line` to `end_line` is a code fence
+fn has_code_fence(buffer: &gtk::TextBuffer, start_line: i32, end_line: i32) -> bool {
+    (start_line..=end_line).any(|line| {
+        line_bounds(buffer, line).map_or(false, |(line_start, line_end)| {
+            is_code_fence(&buffer.text(&line_start, &line_end, true))
+        })
+    })
+}
+
+/// Whether `line` is in a fenced code block, from the code fences before it
+fn is_in_code_block(buffer: &gtk::TextBuffer, line: i32) -> bool {
+    let line_start = match buffer.iter_at_line(line) {
+        Some(line_start) => line_start,
+        None => return false,
+    };
+
+    let n_fences = buffer
+        .text(&buffer.start_iter(), &line_start, true)
+        .lines()
+        .filter(|line| is_code_fence(line))
+        .count();
+
+    n_fences % 2 == 1
+}
@@ -1,22 +1,87 @@
+mod anchored_scroll;
+mod change_renderer;
+mod heading_renderer;
+mod linkifier;
+mod special_character_dialog;
 mod tag_bar;
+mod word_counter;
 
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::{
-    glib::{self, closure},
+    gdk, gio,
+    glib::{self, clone, closure},
     prelude::*,
     subclass::prelude::*,
 };
 use gtk_source::prelude::*;
+use once_cell::unsync::OnceCell;
 
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use self::tag_bar::TagBar;
+use self::{
+    anchored_scroll::{highlight_opacity, is_allocated, PendingScroll, ScrollTarget},
+    change_renderer::ChangeRenderer,
+    heading_renderer::HeadingRenderer,
+    linkifier::Linkifier,
+    tag_bar::TagBar,
+    word_counter::WordCounter,
+};
 use crate::{
-    core::DateTime,
-    model::{Note, NoteMetadata},
+    core::{
+        base_direction, content_at, convert_note_uris, diff_lines, first_heading, with_heading,
+        DateTime, DiffLine, FileRevision, LineChange, NoteData, RevisionCache,
+    },
+    model::{Note, NoteId, NoteMetadata},
+    session::Session,
+    spawn, spawn_blocking,
+    utils::{
+        html2md,
+        markdown_table::{self, Direction, Table},
+        palette::{self, Palette},
+        wrap_mode_from_setting,
+    },
+    widgets::WidthClamp,
+    window::Window,
+    Application,
 };
 
+const HISTORY_ADDED_TAG: &str = "history-added";
+const HISTORY_REMOVED_TAG: &str = "history-removed";
+const SCROLL_HIGHLIGHT_TAG: &str = "scroll-highlight";
+
+/// How far from the top of the view what is scrolled to ends up, as a fraction of its height
+const SCROLL_YALIGN: f64 = 1.0 / 3.0;
+/// How often the highlight of what is scrolled to is faded
+const SCROLL_HIGHLIGHT_STEP: Duration = Duration::from_millis(50);
+const SCROLL_HIGHLIGHT_ALPHA: f32 = 0.35;
+
+/// How long the history slider has to stay on a version that is not loaded yet before it is
+/// read, so dragging across many versions doesn't read every one of them
+const HISTORY_SCRUB_DELAY: Duration = Duration::from_millis(150);
+
+/// How long the text has to stay unchanged before the title is taken from its first heading
+const TITLE_FROM_HEADING_DELAY: Duration = Duration::from_millis(500);
+
+/// How long the text has to stay unchanged before the marks of the gutter are updated, so the
+/// lines are not compared on every keystroke
+const GUTTER_UPDATE_DELAY: Duration = Duration::from_millis(300);
+/// Where the columns are in the gutter, after the line numbers at -30
+const HEADING_RENDERER_POSITION: i32 = -10;
+const CHANGE_RENDERER_POSITION: i32 = 0;
+
+/// Pasted HTML larger than this is pasted as plain text instead of being converted
+const MAX_PASTED_HTML_SIZE: usize = 4 * 1024 * 1024;
+/// Pasted HTML larger than this is converted off the main thread, so the window doesn't freeze
+const BLOCKING_PASTED_HTML_SIZE: usize = 64 * 1024;
+/// How much of the pasted HTML is read at once
+const PASTED_HTML_CHUNK_SIZE: usize = 64 * 1024;
+
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
@@ -33,10 +98,67 @@ mod imp {
         pub tag_bar: TemplateChild<TagBar>,
         #[template_child]
         pub source_view: TemplateChild<gtk_source::View>,
+        #[template_child]
+        pub trash_banner: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub large_file_banner: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub heading_banner: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub heading_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub heading_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub history_bar: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub history_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub history_scale: TemplateChild<gtk::Scale>,
+        #[template_child]
+        pub text_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub history_view: TemplateChild<gtk_source::View>,
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub width_clamp: TemplateChild<WidthClamp>,
+
+        pub linkifier: OnceCell<Linkifier>,
+        pub word_counter: OnceCell<WordCounter>,
+        pub heading_renderer: OnceCell<HeadingRenderer>,
+        pub change_renderer: OnceCell<ChangeRenderer>,
+        pub gutter_update_source_id: RefCell<Option<glib::SourceId>>,
+        pub is_saved_handler_id: RefCell<Option<glib::SignalHandlerId>>,
 
-        pub bindings: RefCell<Vec<glib::Binding>>,
+        pub is_showing_history: Cell<bool>,
+        /// Versions of the note, from the oldest to the newest like the slider
+        pub history: RefCell<Vec<FileRevision>>,
+        pub shown_revision: Cell<Option<usize>>,
+        /// Content of the shown version, without its front matter
+        pub shown_content: RefCell<Option<Arc<str>>>,
+        /// Increased on each load, so the results of the previous ones are dropped
+        pub history_request: Cell<u64>,
+        pub revision_cache: RefCell<RevisionCache>,
 
         pub note: RefCell<Option<Note>>,
+        pub is_editing: Cell<bool>,
+        pub is_read_only: Cell<bool>,
+
+        pub derives_title: Cell<bool>,
+        pub buffer_changed_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub title_from_heading_source_id: RefCell<Option<glib::SourceId>>,
+        /// The title when the cursor was put in it, to know whether it was edited
+        pub title_before_editing: RefCell<Option<String>>,
+        /// Whether updating the heading to the title was offered for the shown note, so it
+        /// is offered only once
+        pub is_heading_offered: Cell<bool>,
+        pub follows_title: Cell<bool>,
+        pub title_binding: RefCell<Option<glib::Binding>>,
+
+        /// Where to scroll to once the view is allocated for the shown note
+        pub pending_scroll: RefCell<PendingScroll>,
+        pub allocation_tick_id: RefCell<Option<gtk::TickCallbackId>>,
+        pub scroll_highlight_source_id: RefCell<Option<glib::SourceId>>,
     }
 
     #[glib::object_subclass]
@@ -46,7 +168,71 @@ mod imp {
         type ParentType = adw::Bin;
 
         fn class_init(klass: &mut Self::Class) {
+            WidthClamp::static_type();
             Self::bind_template(klass);
+
+            klass.install_action("view.restore-note", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                if let Some(note) = obj.note() {
+                    note.metadata().set_is_trashed(false);
+                }
+            });
+
+            klass.install_action("view.restore-version", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                obj.restore_shown_version();
+            });
+
+            klass.install_action("view.close-history", None, move |obj, _, _| {
+                obj.set_is_showing_history(false);
+            });
+
+            klass.install_action("view.update-heading", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                obj.update_heading();
+            });
+
+            klass.install_action("view.dismiss-heading-banner", None, move |obj, _, _| {
+                obj.imp().heading_banner.set_reveal_child(false);
+            });
+
+            klass.install_action("view.insert-table", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                obj.show_insert_table_dialog();
+            });
+
+            klass.install_action("view.reformat-table", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                obj.reformat_table();
+            });
+
+            klass.install_action("view.insert-special-character", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                special_character_dialog::show(
+                    obj,
+                    clone!(@weak obj => move |character| {
+                        obj.insert_at_cursor(character);
+                    }),
+                );
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -57,13 +243,50 @@ mod imp {
     impl ObjectImpl for View {
         fn properties() -> &'static [glib::ParamSpec] {
             static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-                vec![glib::ParamSpecObject::new(
-                    "note",
-                    "Note",
-                    "Current note in the view",
-                    Note::static_type(),
-                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
-                )]
+                vec![
+                    glib::ParamSpecObject::new(
+                        "note",
+                        "Note",
+                        "Current note in the view",
+                        Note::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-showing-history",
+                        "Is Showing History",
+                        "Whether past versions of the note are shown instead of the note",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-editing",
+                        "Is Editing",
+                        "Whether the text of the note has the focus",
+                        false,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-read-only",
+                        "Is Read Only",
+                        "Whether the note can't be edited because the notebook is read-only",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "derives-title",
+                        "Derives Title",
+                        "Whether the title of the note is taken from its first heading",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "follows-title",
+                        "Follows Title",
+                        "Whether the title of the note is edited in another view of it",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
             });
             PROPERTIES.as_ref()
         }
@@ -80,6 +303,22 @@ mod imp {
                     let note = value.get().unwrap();
                     obj.set_note(note);
                 }
+                "is-showing-history" => {
+                    let is_showing_history = value.get().unwrap();
+                    obj.set_is_showing_history(is_showing_history);
+                }
+                "is-read-only" => {
+                    let is_read_only = value.get().unwrap();
+                    obj.set_is_read_only(is_read_only);
+                }
+                "derives-title" => {
+                    let derives_title = value.get().unwrap();
+                    obj.set_derives_title(derives_title);
+                }
+                "follows-title" => {
+                    let follows_title = value.get().unwrap();
+                    obj.set_follows_title(follows_title);
+                }
                 _ => unimplemented!(),
             }
         }
@@ -87,6 +326,11 @@ mod imp {
         fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
             match pspec.name() {
                 "note" => obj.note().to_value(),
+                "is-showing-history" => obj.is_showing_history().to_value(),
+                "is-editing" => obj.is_editing().to_value(),
+                "is-read-only" => obj.is_read_only().to_value(),
+                "derives-title" => obj.derives_title().to_value(),
+                "follows-title" => obj.follows_title().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -103,7 +347,18 @@ mod imp {
                 .unwrap();
             title_label_buffer.set_style_scheme(None);
 
+            self.linkifier
+                .set(Linkifier::new(&self.source_view.get()))
+                .unwrap();
+
+            obj.setup_history_view();
             obj.setup_expressions();
+            obj.setup_focus_controller();
+            obj.setup_table_key_controller();
+            obj.setup_paste();
+            obj.setup_word_counter();
+            obj.setup_layout_settings();
+            obj.setup_gutter();
         }
     }
 
@@ -125,34 +380,1394 @@ impl View {
         self.imp().note.borrow().clone()
     }
 
+    /// Put the cursor in the title, like in a note that was just created, or in the text when
+    /// the title is taken from it. It is done once the view is shown, as it may be on the page
+    /// the leaflet is moving to.
+    pub fn focus_title(&self) {
+        glib::idle_add_local_once(clone!(@weak self as obj => move || {
+            let imp = obj.imp();
+
+            if obj.derives_title() {
+                imp.source_view.grab_focus();
+            } else {
+                imp.title_label.grab_focus();
+            }
+        }));
+    }
+
+    /// Scroll to `highlight_len` characters from the character at `offset` of the text, like a
+    /// match of a search, about a third down the view. They are highlighted for a moment and the
+    /// cursor is put at their start. It is done once the view is allocated, as it may be on the
+    /// page the leaflet is moving to.
+    pub fn scroll_to_offset(&self, offset: i32, highlight_len: i32) {
+        self.scroll_to(ScrollTarget::Offset {
+            offset,
+            highlight_len,
+        });
+    }
+
+    /// Scroll to the first heading with `text`, of any level, like [`Self::scroll_to_offset`]
+    pub fn scroll_to_heading(&self, text: &str) {
+        self.scroll_to(ScrollTarget::Heading(text.to_string()));
+    }
+
+    /// Scroll to the text at the byte `range` of the content, like a match of a search. Nothing
+    /// is done if the content changed so the range is no longer in it.
+    pub fn scroll_to_content_range(&self, range: Range<usize>) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+        let buffer = note.buffer();
+        let (start, end) = buffer.bounds();
+        let content = buffer.text(&start, &end, true);
+
+        if range.end > content.len()
+            || !content.is_char_boundary(range.start)
+            || !content.is_char_boundary(range.end)
+        {
+            return;
+        }
+
+        // The buffer counts in characters
+        let offset = content[..range.start].chars().count();
+        let highlight_len = content[range].chars().count();
+        self.scroll_to_offset(offset as i32, highlight_len as i32);
+    }
+
+    fn scroll_to(&self, target: ScrollTarget) {
+        let target = self.imp().pending_scroll.borrow_mut().push(target);
+
+        if let Some(target) = target {
+            self.scroll_now(&target);
+        }
+    }
+
+    fn scroll_now(&self, target: &ScrollTarget) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+        let buffer = note.buffer();
+        let (start, end) = buffer.bounds();
+
+        let range = match target.range_in(&buffer.text(&start, &end, true)) {
+            Some(range) => range,
+            None => {
+                log::warn!("Cannot find where to scroll to in `{}`: {:?}", note, target);
+                return;
+            }
+        };
+
+        let start = buffer.iter_at_offset(range.start);
+        let end = buffer.iter_at_offset(range.end);
+        buffer.place_cursor(&start);
+
+        let source_view = self.imp().source_view.get();
+        source_view.scroll_to_mark(&buffer.get_insert(), 0.0, true, 0.0, SCROLL_YALIGN);
+        source_view.grab_focus();
+
+        self.highlight_scrolled_to(&buffer, &start, &end);
+    }
+
+    /// Wait for the view to be allocated before scrolling to where it was asked for the
+    /// shown note, as scrolling before it lands nowhere
+    fn wait_for_allocation(&self) {
+        let imp = self.imp();
+
+        if let Some(tick_id) = imp.allocation_tick_id.take() {
+            tick_id.remove();
+        }
+
+        let tick_id = imp.source_view.add_tick_callback(
+            clone!(@weak self as obj => @default-return glib::Continue(false), move |source_view, _| {
+                if !is_allocated(source_view.is_mapped(), source_view.height()) {
+                    return glib::Continue(true);
+                }
+
+                let imp = obj.imp();
+                imp.allocation_tick_id.take();
+
+                let target = imp.pending_scroll.borrow_mut().set_ready();
+                if let Some(target) = target {
+                    obj.scroll_now(&target);
+                }
+
+                glib::Continue(false)
+            }),
+        );
+        imp.allocation_tick_id.replace(Some(tick_id));
+    }
+
+    /// Highlight from `start` to `end` for a moment, fading it out before it is removed
+    fn highlight_scrolled_to(
+        &self,
+        buffer: &gtk_source::Buffer,
+        start: &gtk::TextIter,
+        end: &gtk::TextIter,
+    ) {
+        self.clear_scroll_highlight();
+
+        if start == end {
+            return;
+        }
+
+        let tag_table = buffer.tag_table();
+        let tag = tag_table.lookup(SCROLL_HIGHLIGHT_TAG).unwrap_or_else(|| {
+            let tag = gtk::TextTag::new(Some(SCROLL_HIGHLIGHT_TAG));
+            tag_table.add(&tag);
+            tag
+        });
+        tag.set_background_rgba(Some(&scroll_highlight_color(1.0)));
+        buffer.apply_tag(&tag, start, end);
+
+        let started = Instant::now();
+        let source_id = glib::timeout_add_local(
+            SCROLL_HIGHLIGHT_STEP,
+            clone!(@weak self as obj, @weak buffer, @weak tag => @default-return glib::Continue(false), move || {
+                let opacity = highlight_opacity(started.elapsed());
+
+                if opacity > 0.0 {
+                    tag.set_background_rgba(Some(&scroll_highlight_color(opacity)));
+                    return glib::Continue(true);
+                }
+
+                // Done, so only forgotten instead of removed
+                obj.imp().scroll_highlight_source_id.take();
+                let (start, end) = buffer.bounds();
+                buffer.remove_tag(&tag, &start, &end);
+                glib::Continue(false)
+            }),
+        );
+        self.imp()
+            .scroll_highlight_source_id
+            .replace(Some(source_id));
+    }
+
+    fn clear_scroll_highlight(&self) {
+        if let Some(source_id) = self.imp().scroll_highlight_source_id.take() {
+            source_id.remove();
+        }
+
+        if let Some(note) = self.note() {
+            let buffer = note.buffer();
+
+            if let Some(tag) = buffer.tag_table().lookup(SCROLL_HIGHLIGHT_TAG) {
+                let (start, end) = buffer.bounds();
+                buffer.remove_tag(&tag, &start, &end);
+            }
+        }
+    }
+
     pub fn set_note(&self, note: Option<Note>) {
         let imp = self.imp();
 
-        for binding in imp.bindings.borrow_mut().drain(..) {
-            binding.unbind();
+        // The history and cached versions are of the previous note
+        self.set_is_showing_history(false);
+        imp.revision_cache.borrow_mut().clear();
+
+        // A pending title is of the previous note, so it is taken before leaving it
+        if let Some(source_id) = imp.title_from_heading_source_id.take() {
+            source_id.remove();
+            self.update_title_from_heading();
+        }
+
+        if let Some(handler_id) = imp.buffer_changed_handler_id.take() {
+            if let Some(old_note) = self.note() {
+                old_note.buffer().disconnect(handler_id);
+            }
         }
 
+        if let Some(source_id) = imp.gutter_update_source_id.take() {
+            source_id.remove();
+        }
+
+        if let Some(handler_id) = imp.is_saved_handler_id.take() {
+            if let Some(old_note) = self.note() {
+                old_note.disconnect(handler_id);
+            }
+        }
+
+        // Where to scroll to was asked for the previous note
+        self.clear_scroll_highlight();
+        imp.pending_scroll.borrow_mut().reset();
+
+        imp.heading_banner.set_reveal_child(false);
+        imp.is_heading_offered.set(false);
+        imp.title_before_editing.replace(None);
+
+        self.bind_title(note.as_ref());
+
         if let Some(ref note) = note {
             imp.source_view.grab_focus();
 
-            let mut bindings = imp.bindings.borrow_mut();
+            let handler_id = note
+                .buffer()
+                .connect_changed(clone!(@weak self as obj => move |_| {
+                    obj.queue_title_from_heading();
+                    obj.update_text_direction();
+                    obj.queue_gutter_update();
+                }));
+            imp.buffer_changed_handler_id.replace(Some(handler_id));
 
-            let title_binding = note
-                .metadata()
-                .bind_property("title", &imp.title_label.get().buffer(), "text")
-                .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
-                .build();
-            bindings.push(title_binding);
+            // The saved text is taken again, which clears the change bar
+            let handler_id =
+                note.connect_is_saved_notify(clone!(@weak self as obj => move |note| {
+                    if note.is_saved() {
+                        obj.reset_saved_text();
+                    }
+                }));
+            imp.is_saved_handler_id.replace(Some(handler_id));
         }
 
         imp.source_view
             .set_buffer(note.as_ref().map(|note| note.buffer()));
 
+        let has_note = note.is_some();
         imp.note.replace(note);
+        self.update_text_direction();
+        self.reset_saved_text();
+        self.update_gutter();
+
+        if has_note {
+            self.wait_for_allocation();
+        }
+
         self.notify("note");
     }
 
+    /// Make the lines without a direction of their own, like empty ones at the top, follow the
+    /// first line that has one, so a note in a right-to-left language is written from the
+    /// right. The other lines already take the direction of their text.
+    fn update_text_direction(&self) {
+        let direction = self.note().and_then(|note| {
+            let buffer = note.buffer();
+
+            // Only the lines until the first one with a direction are read
+            (0..buffer.line_count()).find_map(|line| {
+                let start = buffer.iter_at_line(line)?;
+                let mut end = start.clone();
+                if !end.ends_line() {
+                    end.forward_to_line_end();
+                }
+                base_direction(&buffer.text(&start, &end, true))
+            })
+        });
+
+        self.imp()
+            .source_view
+            .set_direction(direction.unwrap_or(gtk::TextDirection::None));
+    }
+
+    /// Whether the text of the note has the focus
+    pub fn is_editing(&self) -> bool {
+        self.imp().is_editing.get()
+    }
+
+    fn set_is_editing(&self, is_editing: bool) {
+        if is_editing == self.is_editing() {
+            return;
+        }
+
+        self.imp().is_editing.set(is_editing);
+        self.notify("is-editing");
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.imp().is_read_only.get()
+    }
+
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        if is_read_only == self.is_read_only() {
+            return;
+        }
+
+        self.imp().is_read_only.set(is_read_only);
+        self.notify("is-read-only");
+    }
+
+    /// Whether the title of the note is taken from its first heading, so it can't be edited
+    /// on its own
+    pub fn derives_title(&self) -> bool {
+        self.imp().derives_title.get()
+    }
+
+    pub fn set_derives_title(&self, derives_title: bool) {
+        if derives_title == self.derives_title() {
+            return;
+        }
+
+        let imp = self.imp();
+        imp.derives_title.set(derives_title);
+
+        let tooltip = derives_title.then(|| gettext("The title is the first heading of the note"));
+        imp.title_label.set_tooltip_text(tooltip.as_deref());
+        imp.heading_banner.set_reveal_child(false);
+
+        self.notify("derives-title");
+    }
+
+    /// Whether the title of the note is edited in another view of it, so this one only shows
+    /// it
+    pub fn follows_title(&self) -> bool {
+        self.imp().follows_title.get()
+    }
+
+    pub fn set_follows_title(&self, follows_title: bool) {
+        if follows_title == self.follows_title() {
+            return;
+        }
+
+        self.imp().follows_title.set(follows_title);
+        self.bind_title(self.note().as_ref());
+
+        self.notify("follows-title");
+    }
+
+    /// Show the title of `note`, and edit it unless another view of the note does, as
+    /// editing it from both would make their bindings feed each other
+    fn bind_title(&self, note: Option<&Note>) {
+        let imp = self.imp();
+
+        if let Some(binding) = imp.title_binding.take() {
+            binding.unbind();
+        }
+
+        let note = match note {
+            Some(note) => note,
+            None => return,
+        };
+
+        let flags = if self.follows_title() {
+            glib::BindingFlags::SYNC_CREATE
+        } else {
+            glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL
+        };
+        let binding = note
+            .metadata()
+            .bind_property("title", &imp.title_label.get().buffer(), "text")
+            .flags(flags)
+            .build();
+        imp.title_binding.replace(Some(binding));
+    }
+
+    /// Take the title from the first heading once the text stops changing, so it is not
+    /// done on every keystroke
+    fn queue_title_from_heading(&self) {
+        if !self.derives_title() {
+            return;
+        }
+
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.title_from_heading_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            TITLE_FROM_HEADING_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().title_from_heading_source_id.replace(None);
+                obj.update_title_from_heading();
+            }),
+        );
+        imp.title_from_heading_source_id.replace(Some(source_id));
+    }
+
+    /// Set the title of the note to its first heading. Without one, or while it is empty,
+    /// the title is kept.
+    fn update_title_from_heading(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        // Like typing in the title, which can't be done in these notes
+        if !self.derives_title() || !self.imp().source_view.is_editable() {
+            return;
+        }
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let content = buffer.text(&start_iter, &end_iter, true);
+
+        if let Some(heading) = first_heading(&content).filter(|heading| !heading.is_empty()) {
+            note.metadata().set_title(heading);
+        }
+    }
+
+    /// Offer to update the first heading of the note to its title, after the title was
+    /// changed from `old_title`, when the heading is not the title already
+    fn offer_heading_update(&self, old_title: &str) {
+        let imp = self.imp();
+
+        if self.derives_title() || imp.is_heading_offered.get() || !imp.title_label.is_editable() {
+            return;
+        }
+
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let title = note.metadata().title();
+        if title == old_title || title.trim().is_empty() {
+            return;
+        }
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let content = buffer.text(&start_iter, &end_iter, true);
+
+        let (message, button_label) = match first_heading(&content) {
+            Some(heading) if heading == title.trim() => return,
+            Some(_) => (
+                gettext("The first heading of this note is not its title"),
+                gettext("_Update Heading"),
+            ),
+            None => (
+                gettext("This note has no heading with its title"),
+                gettext("_Add Heading"),
+            ),
+        };
+
+        imp.heading_label.set_label(&message);
+        imp.heading_button.set_label(&button_label);
+        imp.heading_banner.set_reveal_child(true);
+        imp.is_heading_offered.set(true);
+    }
+
+    /// Make the links to the note follow it, after its title was changed from `old_title`
+    fn update_links(&self, old_title: &str) {
+        if !self.imp().title_label.is_editable() {
+            return;
+        }
+
+        if let Some(note) = self.note() {
+            Session::default().update_links(&note, old_title);
+        }
+    }
+
+    /// Make the first heading of the note its title, adding one at the top if it has none, as
+    /// an edit that can be undone
+    fn update_heading(&self) {
+        self.imp().heading_banner.set_reveal_child(false);
+
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let content = buffer.text(&start_iter, &end_iter, true);
+        let new_content = with_heading(&content, &note.metadata().title());
+
+        if new_content != content.as_str() {
+            buffer.begin_user_action();
+            buffer.set_text(&new_content);
+            buffer.end_user_action();
+        }
+    }
+
+    pub fn is_showing_history(&self) -> bool {
+        self.imp().is_showing_history.get()
+    }
+
+    /// Show the past versions of the note in place of the note, which is left as is
+    pub fn set_is_showing_history(&self, is_showing_history: bool) {
+        if is_showing_history == self.is_showing_history() {
+            return;
+        }
+
+        let imp = self.imp();
+
+        imp.is_showing_history.set(is_showing_history);
+        imp.history_bar.set_reveal_child(is_showing_history);
+
+        if is_showing_history {
+            imp.text_stack.set_visible_child(&imp.history_view.get());
+            self.load_history();
+        } else {
+            imp.text_stack.set_visible_child(&imp.source_view.get());
+            self.next_history_request();
+            imp.history.borrow_mut().clear();
+            imp.shown_revision.set(None);
+            imp.shown_content.replace(None);
+            imp.history_view.buffer().set_text("");
+        }
+
+        self.notify("is-showing-history");
+    }
+
+    fn load_history(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let imp = self.imp();
+        imp.history_label.set_label(&gettext("Loading versions…"));
+        imp.history_scale.set_sensitive(false);
+        self.action_set_enabled("view.restore-version", false);
+
+        let request = self.next_history_request();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let repository = Session::default().note_manager().repository();
+            let res = repository.file_history(&note.file()).await;
+
+            if obj.imp().history_request.get() != request {
+                return;
+            }
+
+            match res {
+                Ok(mut history) => {
+                    history.reverse();
+                    obj.set_history(history);
+                }
+                Err(err) => {
+                    log::error!("Failed to load history of `{}`: {:?}", note, err);
+                    obj.imp()
+                        .history_label
+                        .set_label(&gettext("Failed to load the versions of this note"));
+                }
+            }
+        }));
+    }
+
+    fn set_history(&self, history: Vec<FileRevision>) {
+        let imp = self.imp();
+        let scale = &imp.history_scale;
+
+        scale.clear_marks();
+
+        if history.is_empty() {
+            imp.history_label
+                .set_label(&gettext("This note has no saved versions yet"));
+            imp.history.replace(history);
+            return;
+        }
+
+        let last_index = history.len() - 1;
+        for index in 0..=last_index {
+            scale.add_mark(index as f64, gtk::PositionType::Bottom, None);
+        }
+
+        imp.history.replace(history);
+        imp.shown_revision.set(None);
+
+        scale.set_range(0.0, last_index as f64);
+        scale.set_value(last_index as f64);
+        scale.set_sensitive(last_index > 0);
+
+        // Not notified when the value was already there
+        self.show_revision(last_index);
+    }
+
+    /// Show the version at `index` in the history, compared to the current content
+    fn show_revision(&self, index: usize) {
+        let imp = self.imp();
+
+        if imp.shown_revision.get() == Some(index) {
+            return;
+        }
+
+        let (note, revision) = match (self.note(), imp.history.borrow().get(index)) {
+            (Some(note), Some(revision)) => (note, revision.clone()),
+            _ => return,
+        };
+        let n_revisions = imp.history.borrow().len();
+
+        imp.shown_revision.set(Some(index));
+        imp.history_label.set_label(&gettext!(
+            "Version {} of {}, saved {}",
+            index + 1,
+            n_revisions,
+            revision.time.fuzzy_display()
+        ));
+        self.action_set_enabled("view.restore-version", false);
+
+        let request = self.next_history_request();
+        let cached_content = imp.revision_cache.borrow_mut().get(&revision.id);
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let current_content = buffer.text(&start_iter, &end_iter, true).to_string();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let content = match cached_content {
+                Some(content) => content,
+                None => {
+                    glib::timeout_future(HISTORY_SCRUB_DELAY).await;
+
+                    if obj.imp().history_request.get() != request {
+                        return;
+                    }
+
+                    match revision_content(&revision).await {
+                        Ok(content) => {
+                            obj.imp()
+                                .revision_cache
+                                .borrow_mut()
+                                .insert(revision.id.clone(), Arc::clone(&content));
+                            content
+                        }
+                        Err(err) => {
+                            log::error!(
+                                "Failed to load `{}` at `{}`: {:?}",
+                                note,
+                                revision.id,
+                                err
+                            );
+
+                            if obj.imp().history_request.get() == request {
+                                obj.imp()
+                                    .history_label
+                                    .set_label(&gettext("Failed to load this version"));
+                            }
+                            return;
+                        }
+                    }
+                }
+            };
+
+            if obj.imp().history_request.get() != request {
+                return;
+            }
+
+            let lines = {
+                let content = Arc::clone(&content);
+                spawn_blocking!(move || diff_lines(&content, &current_content)).await
+            };
+
+            if obj.imp().history_request.get() != request {
+                return;
+            }
+
+            obj.show_diff(&lines);
+            obj.imp().shown_content.replace(Some(content));
+            obj.action_set_enabled("view.restore-version", true);
+        }));
+    }
+
+    /// Fill the history view with `lines`, highlighting the ones that differ
+    fn show_diff(&self, lines: &[DiffLine]) {
+        let buffer = self.imp().history_view.buffer();
+        // The palette may have changed since the last version was shown
+        Palette::current().set_up_diff_tags(&buffer, HISTORY_ADDED_TAG, HISTORY_REMOVED_TAG);
+
+        let text = lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        buffer.set_text(&text);
+
+        for (index, line) in lines.iter().enumerate() {
+            let tag_name = match line.change {
+                LineChange::Unchanged => continue,
+                LineChange::Added => HISTORY_ADDED_TAG,
+                LineChange::Removed => HISTORY_REMOVED_TAG,
+            };
+
+            let start_iter = buffer.iter_at_line(index as i32);
+            let end_iter = buffer
+                .iter_at_line(index as i32 + 1)
+                .unwrap_or_else(|| buffer.end_iter());
+
+            if let Some(start_iter) = start_iter {
+                buffer.apply_tag_by_name(tag_name, &start_iter, &end_iter);
+            }
+        }
+    }
+
+    /// Replace the content of the note with the shown version, as an edit that can be undone
+    fn restore_shown_version(&self) {
+        let imp = self.imp();
+
+        let (note, content) = match (self.note(), imp.shown_content.borrow().clone()) {
+            (Some(note), Some(content)) => (note, content),
+            _ => return,
+        };
+
+        let buffer = note.buffer();
+        buffer.begin_user_action();
+        buffer.set_text(&content);
+        buffer.end_user_action();
+
+        log::info!("Restored a previous version of `{}`", note);
+
+        self.set_is_showing_history(false);
+    }
+
+    fn show_insert_table_dialog(&self) {
+        let columns_spin_button = gtk::SpinButton::with_range(1.0, 20.0, 1.0);
+        columns_spin_button.set_value(2.0);
+        let rows_spin_button = gtk::SpinButton::with_range(1.0, 100.0, 1.0);
+        rows_spin_button.set_value(2.0);
+
+        let grid = gtk::Grid::builder()
+            .row_spacing(6)
+            .column_spacing(12)
+            .halign(gtk::Align::Center)
+            .build();
+        for (row, (label, spin_button)) in [
+            (gettext("_Columns"), &columns_spin_button),
+            (gettext("_Rows"), &rows_spin_button),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let label = gtk::Label::builder()
+                .label(&label)
+                .use_underline(true)
+                .mnemonic_widget(spin_button)
+                .xalign(0.0)
+                .build();
+            grid.attach(&label, 0, row as i32, 1, 1);
+            grid.attach(spin_button, 1, row as i32, 1, 1);
+        }
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Insert Table"))
+            .secondary_text(&gettext("The rows are the ones below the header."))
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Insert"), gtk::ResponseType::Accept)
+            .add_css_class("suggested-action");
+        dialog.set_default_response(Some(gtk::ResponseType::Accept));
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&grid);
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        dialog.connect_response(clone!(@weak self as obj => move |dialog, response| {
+            dialog.destroy();
+
+            if response == gtk::ResponseType::Accept {
+                obj.insert_table(
+                    columns_spin_button.value_as_int() as usize,
+                    rows_spin_button.value_as_int() as usize,
+                );
+            }
+        }));
+        dialog.present();
+    }
+
+    /// Replace the selection with `text`, as a single edit to undo
+    fn insert_at_cursor(&self, text: &str) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let buffer = note.buffer();
+        buffer.begin_user_action();
+        buffer.delete_selection(true, true);
+        buffer.insert_at_cursor(text);
+        buffer.end_user_action();
+
+        self.imp().source_view.grab_focus();
+    }
+
+    /// Insert an empty table at the cursor, on lines of its own, with the cursor in its first cell
+    fn insert_table(&self, n_columns: usize, n_rows: usize) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let lines = Table::empty(n_columns, n_rows).to_lines();
+        let cell_offset = markdown_table::split_row(&lines[0])
+            .first()
+            .map_or(0, |cell| cell.range.start);
+
+        let buffer = note.buffer();
+        let mut iter = buffer.iter_at_mark(&buffer.get_insert());
+
+        // A table has to be separated from a paragraph by a blank line
+        let mut text = lines.join("\n");
+        let mut header_line = iter.line();
+        if !iter.starts_line() {
+            text.insert_str(0, "\n\n");
+            header_line += 2;
+        }
+        if !iter.ends_line() {
+            text.push_str("\n\n");
+        }
+
+        buffer.begin_user_action();
+        buffer.insert(&mut iter, &text);
+        buffer.end_user_action();
+
+        if let Some(iter) = buffer.iter_at_line_offset(header_line, cell_offset as i32) {
+            buffer.place_cursor(&iter);
+        }
+        self.imp().source_view.grab_focus();
+    }
+
+    /// Align the columns of the table at the cursor, as one edit that can be undone, with the
+    /// cursor kept at the same place in its cell
+    fn reformat_table(&self) {
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        let (first_line, lines, cursor) = match self.table_at_cursor() {
+            Some(table) => table,
+            None => return,
+        };
+        let lines = lines.iter().map(String::as_str).collect::<Vec<_>>();
+        let (new_lines, (line, offset)) = match markdown_table::reformat(&lines, cursor) {
+            Some(reformatted) => reformatted,
+            None => return,
+        };
+
+        let buffer = note.buffer();
+
+        if new_lines != lines {
+            let start = buffer.iter_at_line(first_line);
+            let end = buffer.iter_at_line(first_line + lines.len() as i32 - 1);
+
+            if let (Some(mut start), Some(mut end)) = (start, end) {
+                if !end.ends_line() {
+                    end.forward_to_line_end();
+                }
+
+                buffer.begin_user_action();
+                buffer.delete(&mut start, &mut end);
+                buffer.insert(&mut start, &new_lines.join("\n"));
+                buffer.end_user_action();
+            }
+        }
+
+        if let Some(iter) = buffer.iter_at_line_offset(first_line + line as i32, offset as i32) {
+            buffer.place_cursor(&iter);
+        }
+    }
+
+    /// Lines of the table the cursor is in, with the number of the first one in the buffer, and
+    /// the line and the offset of the cursor in them
+    fn table_at_cursor(&self) -> Option<(i32, Vec<String>, (usize, usize))> {
+        let buffer = self.note()?.buffer().clone();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+
+        let row_text = |line: i32| {
+            if line < 0 || line >= buffer.line_count() {
+                return None;
+            }
+
+            let start = buffer.iter_at_line(line)?;
+            let mut end = start.clone();
+            if !end.ends_line() {
+                end.forward_to_line_end();
+            }
+
+            Some(buffer.text(&start, &end, true).to_string())
+                .filter(|text| markdown_table::is_row(text))
+        };
+
+        // Only the lines around the cursor that can be rows are read, not the whole note
+        let mut first_line = cursor.line();
+        let mut lines = vec![row_text(first_line)?];
+        while let Some(text) = row_text(first_line - 1) {
+            lines.insert(0, text);
+            first_line -= 1;
+        }
+        while let Some(text) = row_text(first_line + lines.len() as i32) {
+            lines.push(text);
+        }
+
+        let cursor_line = (cursor.line() - first_line) as usize;
+        let range = markdown_table::table_range(
+            &lines.iter().map(String::as_str).collect::<Vec<_>>(),
+            cursor_line,
+        )?;
+
+        Some((
+            first_line + range.start as i32,
+            lines[range.clone()].to_vec(),
+            (cursor_line - range.start, cursor.line_offset() as usize),
+        ))
+    }
+
+    fn next_history_request(&self) -> u64 {
+        let imp = self.imp();
+        let request = imp.history_request.get() + 1;
+        imp.history_request.set(request);
+        request
+    }
+
+    fn setup_focus_controller(&self) {
+        let imp = self.imp();
+
+        let focus_controller = gtk::EventControllerFocus::new();
+        focus_controller.connect_enter(clone!(@weak self as obj => move |_| {
+            obj.set_is_editing(true);
+        }));
+        focus_controller.connect_leave(clone!(@weak self as obj => move |_| {
+            obj.set_is_editing(false);
+        }));
+        imp.source_view.add_controller(&focus_controller);
+
+        // The heading is offered to be updated, and the links to follow the note, once the
+        // title is done being edited rather than on each keystroke
+        let title_focus_controller = gtk::EventControllerFocus::new();
+        title_focus_controller.connect_enter(clone!(@weak self as obj => move |_| {
+            let title = obj.note().map(|note| note.metadata().title());
+            obj.imp().title_before_editing.replace(title);
+        }));
+        title_focus_controller.connect_leave(clone!(@weak self as obj => move |_| {
+            if let Some(old_title) = obj.imp().title_before_editing.take() {
+                obj.offer_heading_update(&old_title);
+                obj.update_links(&old_title);
+            }
+        }));
+        imp.title_label.add_controller(&title_focus_controller);
+    }
+
+    fn setup_table_key_controller(&self) {
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(
+            clone!(@weak self as obj => @default-return gtk::Inhibit(false), move |_, key, _, modifier| {
+                obj.handle_source_view_key_pressed(key, modifier)
+            }),
+        );
+        self.imp().source_view.add_controller(&key_controller);
+    }
+
+    /// Move between the cells of a table with Tab and Shift+Tab, instead of indenting
+    fn handle_source_view_key_pressed(
+        &self,
+        key: gdk::Key,
+        modifier: gdk::ModifierType,
+    ) -> gtk::Inhibit {
+        if modifier.intersects(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK) {
+            return gtk::Inhibit(false);
+        }
+
+        let is_shift_tab = key == gdk::Key::ISO_Left_Tab
+            || (key == gdk::Key::Tab && modifier.contains(gdk::ModifierType::SHIFT_MASK));
+        let direction = if is_shift_tab {
+            Direction::Backward
+        } else if key == gdk::Key::Tab {
+            Direction::Forward
+        } else {
+            return gtk::Inhibit(false);
+        };
+
+        let (first_line, lines, cursor) = match self.table_at_cursor() {
+            Some(table) => table,
+            None => return gtk::Inhibit(false),
+        };
+        let lines = lines.iter().map(String::as_str).collect::<Vec<_>>();
+
+        // Past the first or the last cell, the cursor stays where it is
+        if let Some((line, range)) = markdown_table::neighbor_cell(&lines, cursor, direction) {
+            let buffer = self.note().unwrap().buffer().clone();
+            let line = first_line + line as i32;
+            let start = buffer.iter_at_line_offset(line, range.start as i32);
+            let end = buffer.iter_at_line_offset(line, range.end as i32);
+
+            if let (Some(start), Some(end)) = (start, end) {
+                buffer.select_range(&start, &end);
+                self.imp()
+                    .source_view
+                    .scroll_mark_onscreen(&buffer.get_insert());
+            }
+        }
+
+        gtk::Inhibit(true)
+    }
+
+    /// Paste rich text, like from a browser or an office suite, as Markdown. Shift+Ctrl+V pastes
+    /// it as plain text instead.
+    fn setup_paste(&self) {
+        let imp = self.imp();
+
+        imp.source_view
+            .connect_paste_clipboard(clone!(@weak self as obj => move |source_view| {
+                let clipboard = source_view.clipboard();
+
+                // A link copied from the list of notes
+                if !obj.is_read_only() && clipboard.formats().contain_mime_type("text/uri-list") {
+                    source_view.stop_signal_emission("paste-clipboard");
+                    spawn!(clone!(@weak obj => async move {
+                        obj.paste_note_links(&clipboard).await;
+                    }));
+                    return;
+                }
+
+                // What is copied from the notes is already Markdown in its plain text
+                if obj.is_read_only()
+                    || clipboard.is_local()
+                    || !clipboard.formats().contain_mime_type("text/html")
+                {
+                    return;
+                }
+
+                source_view.stop_signal_emission("paste-clipboard");
+                spawn!(clone!(@weak obj => async move {
+                    obj.paste_html(&clipboard).await;
+                }));
+            }));
+
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(
+            clone!(@weak self as obj => @default-return gtk::Inhibit(false), move |_, key, _, modifier| {
+                let is_plain_paste = matches!(key, gdk::Key::V | gdk::Key::v)
+                    && modifier.contains(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK);
+
+                if !is_plain_paste || obj.is_read_only() {
+                    return gtk::Inhibit(false);
+                }
+
+                spawn!(clone!(@weak obj => async move {
+                    obj.paste_plain_text(&obj.clipboard()).await;
+                }));
+                gtk::Inhibit(true)
+            }),
+        );
+        imp.source_view.add_controller(&key_controller);
+    }
+
+    /// Insert the HTML in `clipboard` converted to Markdown, or its plain text if the HTML
+    /// can't be read or is too large
+    async fn paste_html(&self, clipboard: &gdk::Clipboard) {
+        let html = match read_clipboard_html(clipboard).await {
+            Ok(Some(html)) => html,
+            Ok(None) => {
+                log::info!("Pasted HTML is too large to convert, pasting plain text");
+                self.paste_plain_text(clipboard).await;
+                return;
+            }
+            Err(err) => {
+                log::warn!("Failed to read pasted HTML: {:?}", err);
+                self.paste_plain_text(clipboard).await;
+                return;
+            }
+        };
+
+        let markdown = if html.len() > BLOCKING_PASTED_HTML_SIZE {
+            spawn_blocking!(move || html2md::html_to_markdown(&html)).await
+        } else {
+            html2md::html_to_markdown(&html)
+        };
+
+        self.insert_at_cursor(&markdown);
+    }
+
+    /// Insert the text in `clipboard` with the note URIs in it turned into links to the notes,
+    /// in the style of the notebook settings
+    async fn paste_note_links(&self, clipboard: &gdk::Clipboard) {
+        let text = match clipboard.read_text_future().await {
+            Ok(Some(text)) => text,
+            Ok(None) => return,
+            Err(err) => {
+                log::warn!("Failed to read pasted text: {:?}", err);
+                return;
+            }
+        };
+
+        let note_manager = Session::default().note_manager();
+        let note_list = note_manager.note_list();
+        let (text, unknown_ids) = convert_note_uris(
+            &text,
+            note_manager.notebook_settings().note_link_style(),
+            |id| {
+                let note = NoteId::parse(id).and_then(|id| note_list.find_by_id(&id))?;
+                let file_name = note.file().basename()?.to_string_lossy().to_string();
+                Some((note.metadata().title(), file_name))
+            },
+        );
+
+        if !unknown_ids.is_empty() {
+            log::info!("Pasted links to unknown notes `{:?}`", unknown_ids);
+            self.add_toast(&adw::Toast::new(&gettext(
+                "The linked note no longer exists",
+            )));
+        }
+
+        self.insert_at_cursor(&text);
+    }
+
+    async fn paste_plain_text(&self, clipboard: &gdk::Clipboard) {
+        match clipboard.read_text_future().await {
+            Ok(Some(text)) => self.insert_at_cursor(&text),
+            Ok(None) => {}
+            Err(err) => log::warn!("Failed to read pasted text: {:?}", err),
+        }
+    }
+
+    fn add_toast(&self, toast: &adw::Toast) {
+        if let Some(window) = self.root().and_then(|root| root.downcast::<Window>().ok()) {
+            window.add_toast(toast);
+        }
+    }
+
+    /// Count the words written in the notes, for the daily word goal
+    fn setup_word_counter(&self) {
+        let imp = self.imp();
+
+        let word_counter = WordCounter::new(&imp.source_view.get());
+        word_counter.connect_words_written(|_, n_words| {
+            Session::default()
+                .note_manager()
+                .record_words_written(n_words);
+        });
+        imp.word_counter.set(word_counter).unwrap();
+    }
+
+    /// Follow the settings of how the text is laid out, for this note and the next ones
+    fn setup_layout_settings(&self) {
+        let imp = self.imp();
+        let settings = Application::default().settings();
+
+        for view in [imp.source_view.get(), imp.history_view.get()] {
+            settings
+                .bind("show-right-margin", &view, "show-right-margin")
+                .flags(gio::SettingsBindFlags::GET)
+                .build();
+            settings
+                .bind("right-margin-position", &view, "right-margin-position")
+                .flags(gio::SettingsBindFlags::GET)
+                .build();
+        }
+
+        for key in [
+            "wrap-mode",
+            "limit-content-width",
+            "max-content-width-chars",
+        ] {
+            settings.connect_changed(
+                Some(key),
+                clone!(@weak self as obj => move |_, _| {
+                    obj.update_layout();
+                }),
+            );
+        }
+
+        self.update_layout();
+    }
+
+    fn update_layout(&self) {
+        let imp = self.imp();
+        let settings = Application::default().settings();
+
+        let wrap_mode = wrap_mode_from_setting(&settings.string("wrap-mode"));
+        imp.source_view.set_wrap_mode(wrap_mode);
+        imp.history_view.set_wrap_mode(wrap_mode);
+
+        // Lines that are not wrapped have to be scrolled to instead
+        imp.scrolled_window
+            .set_hscrollbar_policy(if wrap_mode == gtk::WrapMode::None {
+                gtk::PolicyType::Automatic
+            } else {
+                gtk::PolicyType::Never
+            });
+
+        let max_width_chars = if settings.boolean("limit-content-width") {
+            settings.uint("max-content-width-chars")
+        } else {
+            0
+        };
+        imp.width_clamp.set_max_width_chars(max_width_chars);
+    }
+
+    /// Add the columns of the gutter, each shown while its setting is on
+    fn setup_gutter(&self) {
+        let imp = self.imp();
+        let settings = Application::default().settings();
+
+        settings
+            .bind(
+                "show-line-numbers",
+                &imp.source_view.get(),
+                "show-line-numbers",
+            )
+            .flags(gio::SettingsBindFlags::GET)
+            .build();
+
+        let gutter = imp.source_view.gutter(gtk::TextWindowType::Left);
+
+        let heading_renderer = HeadingRenderer::new();
+        gutter.insert(&heading_renderer, HEADING_RENDERER_POSITION);
+        settings
+            .bind("show-heading-marks", &heading_renderer, "visible")
+            .flags(gio::SettingsBindFlags::GET)
+            .build();
+        heading_renderer.connect_visible_notify(clone!(@weak self as obj => move |_| {
+            obj.update_gutter();
+        }));
+        imp.heading_renderer.set(heading_renderer).unwrap();
+
+        let change_renderer = ChangeRenderer::new();
+        gutter.insert(&change_renderer, CHANGE_RENDERER_POSITION);
+        settings
+            .bind("show-change-bar", &change_renderer, "visible")
+            .flags(gio::SettingsBindFlags::GET)
+            .build();
+        change_renderer.connect_visible_notify(clone!(@weak self as obj => move |_| {
+            obj.reset_saved_text();
+            obj.update_gutter();
+        }));
+        imp.change_renderer.set(change_renderer).unwrap();
+    }
+
+    /// Update the marks of the gutter once the text stops changing
+    fn queue_gutter_update(&self) {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.gutter_update_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            GUTTER_UPDATE_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().gutter_update_source_id.replace(None);
+                obj.update_gutter();
+            }),
+        );
+        imp.gutter_update_source_id.replace(Some(source_id));
+    }
+
+    /// Find the headings and the changed lines of the shown note, for the columns that are
+    /// shown
+    fn update_gutter(&self) {
+        let imp = self.imp();
+        let heading_renderer = imp.heading_renderer.get().unwrap();
+        let change_renderer = imp.change_renderer.get().unwrap();
+
+        let note = match self.note() {
+            Some(note) => note,
+            None => return,
+        };
+
+        if !heading_renderer.is_visible() && !change_renderer.has_saved_text() {
+            return;
+        }
+
+        // Comparing the lines of large notes would slow down typing in them
+        if note.is_large() {
+            change_renderer.set_saved_text(None);
+        }
+
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        let text = buffer.text(&start_iter, &end_iter, true);
+
+        if heading_renderer.is_visible() {
+            heading_renderer.update(&text);
+        }
+
+        change_renderer.update(&text);
+    }
+
+    /// Take the text of the shown note as it was last saved, which the change bar compares
+    /// the lines with. It is only kept while the change bar is shown, and not for large notes.
+    fn reset_saved_text(&self) {
+        let change_renderer = self.imp().change_renderer.get().unwrap();
+        change_renderer.set_saved_text(None);
+
+        let note = match self.note() {
+            Some(note) if change_renderer.is_visible() && !note.is_large() => note,
+            _ => return,
+        };
+
+        if note.is_saved() {
+            let buffer = note.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+            change_renderer.set_saved_text(Some(buffer.text(&start_iter, &end_iter, true).into()));
+            return;
+        }
+
+        // The changes are not saved yet, so the saved text is the one in the file
+        spawn!(clone!(@weak self as obj, @weak note => async move {
+            let saved_text = saved_content(&note).await;
+
+            // Another note may be shown since, the change bar hidden, or the note saved
+            let change_renderer = obj.imp().change_renderer.get().unwrap();
+            if obj.note().as_ref() != Some(&note)
+                || !change_renderer.is_visible()
+                || change_renderer.has_saved_text()
+            {
+                return;
+            }
+
+            change_renderer.set_saved_text(Some(saved_text));
+            obj.update_gutter();
+        }));
+    }
+
+    fn setup_history_view(&self) {
+        let imp = self.imp();
+
+        let buffer = gtk_source::Buffer::new(None);
+        buffer.set_style_scheme(None);
+        buffer.set_highlight_matching_brackets(false);
+        imp.history_view.set_buffer(Some(&buffer));
+
+        imp.history_scale
+            .connect_value_changed(clone!(@weak self as obj => move |scale| {
+                if obj.is_showing_history() && !obj.imp().history.borrow().is_empty() {
+                    obj.show_revision(scale.value().round() as usize);
+                }
+            }));
+    }
+
     fn setup_expressions(&self) {
+        let imp = self.imp();
+
+        // Trashed notes and the notes of a read-only notebook can't be edited, so opening
+        // one does not bump its last modified date. The buffer can still be changed
+        // programmatically.
+        let is_trashed_expression = Self::this_expression("note")
+            .chain_property::<Note>("metadata")
+            .chain_property::<NoteMetadata>("is-trashed");
+        let is_editable_expression = gtk::ClosureExpression::new::<bool, _, _>(
+            &[
+                is_trashed_expression.clone().upcast(),
+                Self::this_expression("is-read-only").upcast(),
+            ],
+            closure!(|_: Self, is_trashed: bool, is_read_only: bool| {
+                !is_trashed && !is_read_only
+            }),
+        );
+        is_editable_expression.bind(&imp.source_view.get(), "editable", Some(self));
+
+        // A title taken from the first heading is changed by changing the heading
+        gtk::ClosureExpression::new::<bool, _, _>(
+            &[
+                is_editable_expression.upcast(),
+                Self::this_expression("derives-title").upcast(),
+                Self::this_expression("follows-title").upcast(),
+            ],
+            closure!(
+                |_: Self, is_editable: bool, derives_title: bool, follows_title: bool| {
+                    is_editable && !derives_title && !follows_title
+                }
+            ),
+        )
+        .bind(&imp.title_label.get(), "editable", Some(self));
+        is_trashed_expression.bind(&imp.trash_banner.get(), "reveal-child", Some(self));
+
+        Self::this_expression("note")
+            .chain_property::<Note>("is-large")
+            .bind(&imp.large_file_banner.get(), "reveal-child", Some(self));
+
         Self::this_expression("note")
             .chain_property::<Note>("metadata")
             .chain_property::<NoteMetadata>("last-modified")
@@ -162,3 +1777,74 @@ impl View {
             .bind(&self.imp().last_modified_label.get(), "label", Some(self));
     }
 }
+
+/// The color of the highlight of what is scrolled to, at `opacity` as it fades out
+fn scroll_highlight_color(opacity: f32) -> gdk::RGBA {
+    palette::HIGHLIGHT.to_rgba(SCROLL_HIGHLIGHT_ALPHA * opacity)
+}
+
+/// The content of `note` as it is in its file, which is empty if it was never saved
+async fn saved_content(note: &Note) -> String {
+    let file = note.file();
+
+    match file.load_contents_future().await {
+        Ok((bytes, _)) => std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| content_at(text, note.body_offset()))
+            .unwrap_or_default()
+            .to_string(),
+        Err(err) => {
+            log::debug!(
+                "Failed to read saved content of `{}`: {:?}",
+                file.uri(),
+                err
+            );
+            String::new()
+        }
+    }
+}
+
+/// Content of the note as it was in `revision`, without the front matter
+/// The HTML in `clipboard`, or `None` if it is larger than `MAX_PASTED_HTML_SIZE`
+async fn read_clipboard_html(clipboard: &gdk::Clipboard) -> anyhow::Result<Option<String>> {
+    let (stream, _) = clipboard
+        .read_future(&["text/html"], glib::PRIORITY_DEFAULT)
+        .await?;
+
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = stream
+            .read_bytes_future(PASTED_HTML_CHUNK_SIZE, glib::PRIORITY_DEFAULT)
+            .await?;
+
+        if chunk.is_empty() {
+            break;
+        }
+
+        bytes.extend_from_slice(&chunk);
+
+        if bytes.len() > MAX_PASTED_HTML_SIZE {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(html2md::decode_clipboard_html(&bytes)))
+}
+
+async fn revision_content(revision: &FileRevision) -> anyhow::Result<Arc<str>> {
+    let repository = Session::default().note_manager().repository();
+    let bytes = repository.file_content_at(revision).await?;
+
+    spawn_blocking!(move || -> anyhow::Result<Arc<str>> {
+        let text = String::from_utf8(bytes)?;
+        let content = match NoteData::parse(&text) {
+            Ok(note_data) => note_data.content,
+            Err(err) => {
+                log::info!("Showing version without front matter: {:?}", err);
+                text
+            }
+        };
+        Ok(Arc::from(content))
+    })
+    .await
+}
@@ -0,0 +1,202 @@
+use gettextrs::gettext;
+use gtk::{glib::clone, prelude::*};
+
+use std::rc::Rc;
+
+use crate::{utils::RecentList, Application};
+
+/// How many of the last inserted characters are offered above the others
+const MAX_RECENT_CHARACTERS: usize = 8;
+
+/// Shown in place of the characters that would look blank on a button
+const BLANK_PLACEHOLDER: &str = "⍽";
+
+/// Characters that are hard to type or that Markdown could take as syntax
+const CHARACTERS: &[&str] = &[
+    "—", "–", "…", "\u{a0}", "→", "←", "↑", "↓", "↔", "⇒", "•", "·", "×", "÷", "±", "≠", "≤", "≥",
+    "°", "©", "®", "™", "§", "¶", "“", "”", "‘", "’", "«", "»", "✓", "✗",
+];
+
+/// Let the user pick one of the special characters, then call `on_chosen` with it
+pub fn show(parent: &impl IsA<gtk::Widget>, on_chosen: impl Fn(&str) + 'static) {
+    let settings = Application::default().settings();
+    let saved = settings.strv("recent-special-characters");
+    let recent_characters = recent_characters(saved.iter().map(|s| s.as_str()));
+
+    let dialog = gtk::MessageDialog::builder()
+        .text(&gettext("Insert Special Character"))
+        .secondary_text(&gettext(
+            "Characters like dashes, arrows, and non-breaking spaces that are hard to type.",
+        ))
+        .modal(true)
+        .build();
+    dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+    dialog.set_transient_for(
+        parent
+            .root()
+            .map(|w| w.downcast::<gtk::Window>().unwrap())
+            .as_ref(),
+    );
+
+    let on_chosen = Rc::new(on_chosen);
+    let flow_box = |characters: &[String]| {
+        let flow_box = gtk::FlowBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .max_children_per_line(8)
+            .min_children_per_line(8)
+            .homogeneous(true)
+            .build();
+
+        for character in characters {
+            let label = if character.trim().is_empty() {
+                BLANK_PLACEHOLDER
+            } else {
+                character.as_str()
+            };
+            let button = gtk::Button::builder()
+                .label(label)
+                .tooltip_text(&character_name(character))
+                .build();
+            let character = character.clone();
+            button.add_css_class("flat");
+            button.connect_clicked(
+                clone!(@weak dialog, @strong on_chosen, @strong character, @strong recent_characters => move |_| {
+                    dialog.destroy();
+                    save_recent_character(recent_characters.clone(), &character);
+                    on_chosen(&character);
+                }),
+            );
+            flow_box.insert(&button, -1);
+        }
+
+        flow_box
+    };
+
+    let message_area = dialog.message_area().downcast::<gtk::Box>().unwrap();
+
+    if !recent_characters.is_empty() {
+        let recent_label = gtk::Label::builder()
+            .label(&gettext("Recently Used"))
+            .xalign(0.0)
+            .build();
+        recent_label.add_css_class("heading");
+        message_area.append(&recent_label);
+        message_area.append(&flow_box(recent_characters.items()));
+
+        let all_label = gtk::Label::builder()
+            .label(&gettext("All Characters"))
+            .xalign(0.0)
+            .build();
+        all_label.add_css_class("heading");
+        message_area.append(&all_label);
+    }
+
+    let all_characters = CHARACTERS
+        .iter()
+        .map(|character| character.to_string())
+        .collect::<Vec<_>>();
+    message_area.append(&flow_box(&all_characters));
+
+    dialog.connect_response(|dialog, _| dialog.destroy());
+    dialog.present();
+}
+
+/// The characters inserted last, from the ones that were saved. The ones that are not offered
+/// anymore are dropped.
+fn recent_characters<'a>(saved: impl IntoIterator<Item = &'a str>) -> RecentList<String> {
+    RecentList::with_items(
+        MAX_RECENT_CHARACTERS,
+        saved
+            .into_iter()
+            .filter(|character| CHARACTERS.contains(character))
+            .map(str::to_string),
+    )
+}
+
+fn save_recent_character(mut recent_characters: RecentList<String>, character: &str) {
+    recent_characters.push(character.to_string());
+    let characters = recent_characters
+        .items()
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    if let Err(err) = Application::default()
+        .settings()
+        .set_strv("recent-special-characters", &characters)
+    {
+        log::warn!("Failed to save recent special characters: {:?}", err);
+    }
+}
+
+/// What `character` is, for the tooltip of its button
+fn character_name(character: &str) -> String {
+    match character {
+        "—" => gettext("Em Dash"),
+        "–" => gettext("En Dash"),
+        "…" => gettext("Ellipsis"),
+        "\u{a0}" => gettext("Non-Breaking Space"),
+        "→" => gettext("Rightwards Arrow"),
+        "←" => gettext("Leftwards Arrow"),
+        "↑" => gettext("Upwards Arrow"),
+        "↓" => gettext("Downwards Arrow"),
+        "↔" => gettext("Left Right Arrow"),
+        "⇒" => gettext("Rightwards Double Arrow"),
+        "•" => gettext("Bullet"),
+        "·" => gettext("Middle Dot"),
+        "×" => gettext("Multiplication Sign"),
+        "÷" => gettext("Division Sign"),
+        "±" => gettext("Plus-Minus Sign"),
+        "≠" => gettext("Not Equal To"),
+        "≤" => gettext("Less-Than or Equal To"),
+        "≥" => gettext("Greater-Than or Equal To"),
+        "°" => gettext("Degree Sign"),
+        "©" => gettext("Copyright Sign"),
+        "®" => gettext("Registered Sign"),
+        "™" => gettext("Trade Mark Sign"),
+        "§" => gettext("Section Sign"),
+        "¶" => gettext("Pilcrow Sign"),
+        "“" => gettext("Left Double Quotation Mark"),
+        "”" => gettext("Right Double Quotation Mark"),
+        "‘" => gettext("Left Single Quotation Mark"),
+        "’" => gettext("Right Single Quotation Mark"),
+        "«" => gettext("Left-Pointing Double Angle Quotation Mark"),
+        "»" => gettext("Right-Pointing Double Angle Quotation Mark"),
+        "✓" => gettext("Check Mark"),
+        "✗" => gettext("Ballot X"),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recent_characters_saved() {
+        let recent = recent_characters(["→", "—", "→", "\u{a0}"]);
+        assert_eq!(recent.items(), ["→", "—", "\u{a0}"]);
+    }
+
+    #[test]
+    fn recent_characters_not_offered() {
+        // Edited by hand, or offered by an older version
+        let recent = recent_characters(["", "a", "🎉", "→ ←", "…"]);
+        assert_eq!(recent.items(), ["…"]);
+    }
+
+    #[test]
+    fn recent_characters_bounded() {
+        let recent = recent_characters(CHARACTERS.iter().copied());
+        assert_eq!(recent.items().len(), MAX_RECENT_CHARACTERS);
+        assert_eq!(recent.items()[0], CHARACTERS[0]);
+    }
+
+    #[test]
+    fn characters_unique() {
+        for (index, character) in CHARACTERS.iter().enumerate() {
+            assert_eq!(character.chars().count(), 1, "{:?}", character);
+            assert!(!CHARACTERS[..index].contains(character), "{:?}", character);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::{Cell, RefCell};
+
+use crate::{core::EditWordCount, utils::EditProfiler};
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct WordCounter {
+        pub view: OnceCell<gtk::TextView>,
+        pub buffer: RefCell<Option<(gtk::TextBuffer, Vec<glib::SignalHandlerId>)>>,
+        /// Nesting of the user actions the buffer is in, as only the edits of the user count
+        pub n_user_actions: Cell<u32>,
+        pub edit: RefCell<EditWordCount>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WordCounter {
+        const NAME: &'static str = "NwtyContentViewWordCounter";
+        type Type = super::WordCounter;
+    }
+
+    impl ObjectImpl for WordCounter {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder(
+                    "words-written",
+                    &[u32::static_type().into()],
+                    <()>::static_type().into(),
+                )
+                .build()]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "view",
+                    "View",
+                    "Text view whose buffer has its added words counted",
+                    gtk::TextView::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "view" => {
+                    let view = value.get().unwrap();
+                    self.view.set(view).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "view" => obj.view().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.view()
+                .connect_buffer_notify(clone!(@weak obj => move |view| {
+                    obj.set_buffer(Some(view.buffer()));
+                }));
+            obj.set_buffer(Some(obj.view().buffer()));
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            obj.set_buffer(None);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Counts the words the user adds to the buffer of a text view, but not the ones of text
+    /// set by the app, like when a note is loaded or reloaded after a sync
+    pub struct WordCounter(ObjectSubclass<imp::WordCounter>);
+}
+
+impl WordCounter {
+    pub fn new(view: &impl IsA<gtk::TextView>) -> Self {
+        glib::Object::new(&[("view", view.upcast_ref::<gtk::TextView>())])
+            .expect("Failed to create WordCounter.")
+    }
+
+    pub fn connect_words_written<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, u32) + 'static,
+    {
+        self.connect_local("words-written", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            let n_words = values[1].get::<u32>().unwrap();
+            f(&obj, n_words);
+            None
+        })
+    }
+
+    fn view(&self) -> gtk::TextView {
+        self.imp().view.get().unwrap().clone()
+    }
+
+    fn set_buffer(&self, buffer: Option<gtk::TextBuffer>) {
+        let imp = self.imp();
+
+        if let Some((old_buffer, handler_ids)) = imp.buffer.take() {
+            for handler_id in handler_ids {
+                old_buffer.disconnect(handler_id);
+            }
+        }
+        imp.n_user_actions.set(0);
+
+        let buffer = match buffer {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        let begin_user_action_handler_id =
+            buffer.connect_begin_user_action(clone!(@weak self as obj => move |_| {
+                let imp = obj.imp();
+                imp.n_user_actions.set(imp.n_user_actions.get() + 1);
+            }));
+
+        let end_user_action_handler_id =
+            buffer.connect_end_user_action(clone!(@weak self as obj => move |_| {
+                let imp = obj.imp();
+                imp.n_user_actions.set(imp.n_user_actions.get().saturating_sub(1));
+            }));
+
+        let insert_text_handler_id =
+            buffer.connect_insert_text(clone!(@weak self as obj => move |buffer, location, _| {
+                let line = location.line();
+                obj.begin_edit(buffer, line, line);
+            }));
+
+        // Ran after the default handler, so the buffer already has the text
+        let after_insert_text_handler_id = buffer.connect_local(
+            "insert-text",
+            true,
+            clone!(@weak self as obj => @default-return None, move |values| {
+                let buffer = values[0].get::<gtk::TextBuffer>().unwrap();
+                // Moved to the end of the inserted text by the default handler
+                let location = values[1].get::<gtk::TextIter>().unwrap();
+                let text = values[2].get::<String>().unwrap();
+
+                let end_line = location.line();
+                let start_line = end_line - text.matches('\n').count() as i32;
+                obj.end_edit(&buffer, start_line, end_line);
+                None
+            }),
+        );
+
+        let delete_range_handler_id =
+            buffer.connect_delete_range(clone!(@weak self as obj => move |buffer, start, end| {
+                obj.begin_edit(buffer, start.line(), end.line());
+            }));
+
+        let after_delete_range_handler_id = buffer.connect_local(
+            "delete-range",
+            true,
+            clone!(@weak self as obj => @default-return None, move |values| {
+                let buffer = values[0].get::<gtk::TextBuffer>().unwrap();
+                // Both ends are at where the range was after the default handler
+                let line = values[1].get::<gtk::TextIter>().unwrap().line();
+                obj.end_edit(&buffer, line, line);
+                None
+            }),
+        );
+
+        imp.buffer.replace(Some((
+            buffer,
+            vec![
+                begin_user_action_handler_id,
+                end_user_action_handler_id,
+                insert_text_handler_id,
+                after_insert_text_handler_id,
+                delete_range_handler_id,
+                after_delete_range_handler_id,
+            ],
+        )));
+    }
+
+    fn begin_edit(&self, buffer: &gtk::TextBuffer, start_line: i32, end_line: i32) {
+        let imp = self.imp();
+
+        if imp.n_user_actions.get() == 0 {
+            return;
+        }
+
+        EditProfiler::default().measure("word-counter", || {
+            imp.edit
+                .borrow_mut()
+                .begin(&lines_text(buffer, start_line, end_line));
+        });
+    }
+
+    fn end_edit(&self, buffer: &gtk::TextBuffer, start_line: i32, end_line: i32) {
+        let n_words = EditProfiler::default().measure("word-counter", || {
+            self.imp()
+                .edit
+                .borrow_mut()
+                .end(&lines_text(buffer, start_line, end_line))
+        });
+
+        if n_words > 0 {
+            self.emit_by_name::<()>("words-written", &[&n_words]);
+        }
+    }
+}
+
+/// Text of the lines from `start_line` to `end_line`, both included
+fn lines_text(buffer: &gtk::TextBuffer, start_line: i32, end_line: i32) -> glib::GString {
+    let start = buffer
+        .iter_at_line(start_line.max(0))
+        .unwrap_or_else(|| buffer.start_iter());
+    let mut end = buffer
+        .iter_at_line(end_line)
+        .unwrap_or_else(|| buffer.end_iter());
+    // Moves to the end of the next line if already at the end
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+
+    buffer.text(&start, &end, true)
+}
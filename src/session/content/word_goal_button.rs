@@ -0,0 +1,255 @@
+use adw::subclass::prelude::*;
+use chrono::Local;
+use gettextrs::gettext;
+use gtk::{
+    cairo,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::{Cell, RefCell};
+
+use crate::session::Session;
+
+/// Days shown in the history
+const HISTORY_DAYS: u32 = 30;
+const SPARKLINE_LINE_WIDTH: f64 = 2.0;
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/content-word-goal-button.ui")]
+    pub struct WordGoalButton {
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub sparkline: TemplateChild<gtk::DrawingArea>,
+
+        pub words_today: Cell<u32>,
+        pub goal: Cell<u32>,
+        /// Words of each of the last days, starting with the oldest
+        pub history: RefCell<Vec<u32>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WordGoalButton {
+        const NAME: &'static str = "NwtyContentWordGoalButton";
+        type Type = super::WordGoalButton;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for WordGoalButton {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecUInt::new(
+                        "words-today",
+                        "Words Today",
+                        "Number of words written today",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "goal",
+                        "Goal",
+                        "Words to write each day, or 0 for no goal",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "words-today" => {
+                    let words_today = value.get().unwrap();
+                    obj.set_words_today(words_today);
+                }
+                "goal" => {
+                    let goal = value.get().unwrap();
+                    obj.set_goal(goal);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "words-today" => obj.words_today().to_value(),
+                "goal" => obj.goal().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.popover.connect_show(clone!(@weak obj => move |_| {
+                obj.load_history();
+            }));
+
+            self.sparkline
+                .set_draw_func(clone!(@weak obj => move |_, cr, width, height| {
+                    if let Err(err) = obj.draw_sparkline(cr, f64::from(width), f64::from(height)) {
+                        log::error!("Failed to draw word count history: {:?}", err);
+                    }
+                }));
+
+            obj.update_label();
+        }
+    }
+
+    impl WidgetImpl for WordGoalButton {}
+    impl BinImpl for WordGoalButton {}
+}
+
+glib::wrapper! {
+    /// Shows the words written today against the daily word goal, with the words of the last
+    /// days in its popover. It is hidden while there is no goal.
+    pub struct WordGoalButton(ObjectSubclass<imp::WordGoalButton>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl WordGoalButton {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create WordGoalButton.")
+    }
+
+    pub fn words_today(&self) -> u32 {
+        self.imp().words_today.get()
+    }
+
+    pub fn set_words_today(&self, words_today: u32) {
+        if words_today == self.words_today() {
+            return;
+        }
+
+        self.imp().words_today.set(words_today);
+        self.update_label();
+        self.notify("words-today");
+    }
+
+    pub fn goal(&self) -> u32 {
+        self.imp().goal.get()
+    }
+
+    pub fn set_goal(&self, goal: u32) {
+        if goal == self.goal() {
+            return;
+        }
+
+        self.imp().goal.set(goal);
+        self.update_label();
+        self.imp().sparkline.queue_draw();
+        self.notify("goal");
+    }
+
+    fn update_label(&self) {
+        let imp = self.imp();
+        let (words_today, goal) = (self.words_today(), self.goal());
+
+        self.set_visible(goal > 0);
+
+        // 420 / 1000 today
+        imp.menu_button
+            .set_label(&gettext!("{} / {} today", words_today, goal));
+
+        if words_today >= goal {
+            imp.menu_button.add_css_class("success");
+        } else {
+            imp.menu_button.remove_css_class("success");
+        }
+    }
+
+    /// Take the words of the last days, which may have changed since the popover was shown
+    fn load_history(&self) {
+        let today = Local::today().naive_local();
+        let history = Session::default()
+            .note_manager()
+            .daily_word_counts()
+            .last_days(today, HISTORY_DAYS);
+
+        self.imp().history.replace(history);
+        self.imp().sparkline.queue_draw();
+    }
+
+    /// A line through the words of each day, over a fainter line at the goal
+    fn draw_sparkline(
+        &self,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        let history = self.imp().history.borrow();
+
+        if history.len() < 2 {
+            return Ok(());
+        }
+
+        let goal = self.goal();
+        let max_words = history.iter().copied().max().unwrap_or(0).max(goal).max(1);
+
+        // Inset by the line width, so the line isn't cut at the top and the bottom
+        let inner_height = height - 2.0 * SPARKLINE_LINE_WIDTH;
+        let y = |words: u32| {
+            SPARKLINE_LINE_WIDTH + inner_height * (1.0 - f64::from(words) / f64::from(max_words))
+        };
+        let x_step = width / (history.len() - 1) as f64;
+
+        let style_context = self.style_context();
+        let color = style_context.color();
+        let accent_color = style_context.lookup_color("accent_color").unwrap_or(color);
+        let set_source = |alpha: f64, color: gtk::gdk::RGBA| {
+            cr.set_source_rgba(
+                f64::from(color.red()),
+                f64::from(color.green()),
+                f64::from(color.blue()),
+                alpha * f64::from(color.alpha()),
+            );
+        };
+
+        cr.set_line_width(1.0);
+        set_source(0.3, color);
+        cr.set_dash(&[4.0, 4.0], 0.0);
+        cr.move_to(0.0, y(goal));
+        cr.line_to(width, y(goal));
+        cr.stroke()?;
+
+        cr.set_line_width(SPARKLINE_LINE_WIDTH);
+        set_source(1.0, accent_color);
+        cr.set_dash(&[], 0.0);
+        cr.set_line_join(cairo::LineJoin::Round);
+        for (index, &words) in history.iter().enumerate() {
+            cr.line_to(index as f64 * x_step, y(words));
+        }
+        cr.stroke()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use gettextrs::{gettext, ngettext};
+use gtk::{glib::clone, prelude::*};
+
+use super::Session;
+use crate::{core::DELETED_TAG_RETENTION_DAYS, model::Tag};
+
+/// How many titles of the notes with the tag are listed before the others are only counted
+const MAX_LISTED_TITLES: usize = 10;
+
+/// Ask before deleting `tag`, telling which notes it will be removed from
+pub fn show(parent: &impl IsA<gtk::Widget>, tag: &Tag) {
+    let session = Session::default();
+
+    let mut titles = session
+        .note_manager()
+        .notes_with_tag(tag)
+        .iter()
+        .map(|note| note.metadata().title())
+        .collect::<Vec<_>>();
+    titles.sort_by_key(|title| title.to_lowercase());
+
+    let secondary_text = if titles.is_empty() {
+        gettext("No note has this tag.")
+    } else {
+        ngettext!(
+            "The notes are kept, and the tag can be restored on them for {} day.",
+            "The notes are kept, and the tag can be restored on them for {} days.",
+            DELETED_TAG_RETENTION_DAYS as u32,
+            DELETED_TAG_RETENTION_DAYS
+        )
+    };
+
+    let dialog = gtk::MessageDialog::builder()
+        .text(&confirmation_text(&tag.name(), titles.len()))
+        .secondary_text(&secondary_text)
+        .message_type(gtk::MessageType::Question)
+        .modal(true)
+        .build();
+    dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+    dialog
+        .add_button(&gettext("_Delete"), gtk::ResponseType::Accept)
+        .add_css_class("destructive-action");
+    dialog.set_transient_for(
+        parent
+            .root()
+            .map(|w| w.downcast::<gtk::Window>().unwrap())
+            .as_ref(),
+    );
+
+    if !titles.is_empty() {
+        let titles_label = gtk::Label::builder()
+            .label(&listed_titles(&titles))
+            .wrap(true)
+            .xalign(0.0)
+            .selectable(true)
+            .build();
+        let expander = gtk::Expander::builder()
+            .label(&gettext("_Affected Notes"))
+            .use_underline(true)
+            .child(&titles_label)
+            .build();
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&expander);
+    }
+
+    dialog.connect_response(clone!(@weak session, @weak tag => move |dialog, response| {
+        dialog.destroy();
+
+        if response != gtk::ResponseType::Accept {
+            return;
+        }
+
+        session.delete_tag(&tag);
+    }));
+    dialog.present();
+}
+
+/// The question asked before deleting the tag named `tag_name`, which `n_notes` notes have
+fn confirmation_text(tag_name: &str, n_notes: usize) -> String {
+    if n_notes == 0 {
+        gettext!("Delete “{}”?", tag_name)
+    } else {
+        ngettext!(
+            "Remove “{}” from {} note?",
+            "Remove “{}” from {} notes?",
+            n_notes as u32,
+            tag_name,
+            n_notes
+        )
+    }
+}
+
+/// The first of `titles`, one per line, followed by how many are not listed
+fn listed_titles(titles: &[String]) -> String {
+    let mut lines = titles
+        .iter()
+        .take(MAX_LISTED_TITLES)
+        .map(|title| {
+            if title.is_empty() {
+                gettext("Untitled Note")
+            } else {
+                title.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let n_more = titles.len().saturating_sub(MAX_LISTED_TITLES);
+    if n_more > 0 {
+        lines.push(ngettext!(
+            "and {} more",
+            "and {} more",
+            n_more as u32,
+            n_more
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn confirmation_texts() {
+        assert_eq!(confirmation_text("work", 0), "Delete “work”?");
+        assert_eq!(confirmation_text("work", 1), "Remove “work” from 1 note?");
+        assert_eq!(
+            confirmation_text("work", 23),
+            "Remove “work” from 23 notes?"
+        );
+    }
+
+    #[test]
+    fn listed_titles_all() {
+        let titles = ["Groceries".to_string(), String::new()];
+        assert_eq!(listed_titles(&titles), "Groceries\nUntitled Note");
+        assert_eq!(listed_titles(&[]), "");
+    }
+
+    #[test]
+    fn listed_titles_more() {
+        let titles = (1..=23).map(|n| format!("Note {}", n)).collect::<Vec<_>>();
+        let listed = listed_titles(&titles);
+        let lines = listed.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), MAX_LISTED_TITLES + 1);
+        assert_eq!(lines[0], "Note 1");
+        assert_eq!(lines[MAX_LISTED_TITLES - 1], "Note 10");
+        assert_eq!(lines[MAX_LISTED_TITLES], "and 13 more");
+
+        let titles = (1..=11).map(|n| format!("Note {}", n)).collect::<Vec<_>>();
+        assert!(listed_titles(&titles).ends_with("Note 10\nand 1 more"));
+    }
+}
@@ -0,0 +1,225 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::Cell;
+
+use super::Session;
+use crate::{model::Note, spawn};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/duplicates-dialog.ui")]
+    pub struct DuplicatesDialog {
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub loading_page: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub groups_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub groups_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub near_switch: TemplateChild<gtk::Switch>,
+
+        pub session: OnceCell<Session>,
+        /// Increased on each search, so the results of an older one are dropped
+        pub search_generation: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for DuplicatesDialog {
+        const NAME: &'static str = "NwtyDuplicatesDialog";
+        type Type = super::DuplicatesDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for DuplicatesDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the notes are from",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.near_switch
+                .connect_active_notify(clone!(@weak obj => move |_| {
+                    obj.search();
+                }));
+
+            obj.search();
+        }
+    }
+
+    impl WidgetImpl for DuplicatesDialog {}
+    impl WindowImpl for DuplicatesDialog {}
+    impl AdwWindowImpl for DuplicatesDialog {}
+}
+
+glib::wrapper! {
+    /// Shows the groups of duplicate notes, letting the user keep one note of each
+    pub struct DuplicatesDialog(ObjectSubclass<imp::DuplicatesDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl DuplicatesDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create DuplicatesDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn search(&self) {
+        let imp = self.imp();
+        let generation = imp.search_generation.get().wrapping_add(1);
+        imp.search_generation.set(generation);
+
+        imp.stack.set_visible_child(&imp.loading_page.get());
+
+        let is_near = imp.near_switch.is_active();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let groups = obj.session().note_manager().find_duplicates(is_near).await;
+
+            if obj.imp().search_generation.get() == generation {
+                obj.show_groups(&groups);
+            }
+        }));
+    }
+
+    fn show_groups(&self, groups: &[Vec<Note>]) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.groups_box.first_child() {
+            imp.groups_box.remove(&child);
+        }
+
+        for group in groups {
+            imp.groups_box.append(&self.build_group(group));
+        }
+
+        if groups.is_empty() {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.groups_page.get());
+        }
+    }
+
+    fn build_group(&self, group: &[Note]) -> gtk::Widget {
+        let list_box = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(vec!["boxed-list".into()])
+            .build();
+
+        let mut first_check_button: Option<gtk::CheckButton> = None;
+        let mut check_buttons = Vec::new();
+
+        for note in group {
+            let metadata = note.metadata();
+            let title = metadata.title();
+
+            let check_button = gtk::CheckButton::builder()
+                .valign(gtk::Align::Center)
+                .active(first_check_button.is_none())
+                .build();
+            check_button.set_group(first_check_button.as_ref());
+
+            let row = adw::ActionRow::builder()
+                .title(&if title.is_empty() {
+                    gettext("Untitled Note")
+                } else {
+                    title
+                })
+                .subtitle(&metadata.last_modified().fuzzy_display())
+                .activatable_widget(&check_button)
+                .build();
+            row.add_prefix(&check_button);
+            list_box.append(&row);
+
+            first_check_button.get_or_insert_with(|| check_button.clone());
+            check_buttons.push(check_button);
+        }
+
+        let keep_button = gtk::Button::builder()
+            .label(&gettext("_Keep Selected"))
+            .use_underline(true)
+            .halign(gtk::Align::End)
+            .tooltip_text(&gettext("Move the other notes to the Trash"))
+            .build();
+
+        let group = group.to_vec();
+        keep_button.connect_clicked(clone!(@weak self as obj => move |_| {
+            let kept = check_buttons
+                .iter()
+                .position(|check_button| check_button.is_active())
+                .map(|index| &group[index]);
+
+            if let Some(kept) = kept {
+                obj.session().keep_duplicate(kept, &group);
+                obj.search();
+            }
+        }));
+
+        let group_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        group_box.append(&list_box);
+        group_box.append(&keep_button);
+        group_box.upcast()
+    }
+}
@@ -0,0 +1,362 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::cell::RefCell;
+
+use super::Session;
+use crate::{
+    core::{
+        images_dir_name, move_item, sorted_by_date, CombinedFormat, CombinedNote, ImageEmbedding,
+    },
+    model::Note,
+};
+
+/// Formats in the order of the choices of the format row
+const FORMAT_CHOICES: [CombinedFormat; 3] = [
+    CombinedFormat::Markdown,
+    CombinedFormat::Html,
+    CombinedFormat::Pdf,
+];
+
+/// Position of "Manual" in the choices of the order row
+const MANUAL_ORDER_POSITION: u32 = 1;
+/// Position of "Copy Alongside" in the choices of the images row
+const COPY_IMAGES_POSITION: u32 = 1;
+
+fn combined_note(note: &Note) -> CombinedNote {
+    let metadata = note.metadata();
+    let buffer = note.buffer();
+    let (start_iter, end_iter) = buffer.bounds();
+    let title = metadata.title();
+
+    CombinedNote {
+        title: if title.is_empty() {
+            gettext("Untitled Note")
+        } else {
+            title
+        },
+        last_modified: metadata.last_modified(),
+        tag_names: metadata.tag_list().names(),
+        content: buffer.text(&start_iter, &end_iter, true).to_string(),
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/export-notes-dialog.ui")]
+    pub struct ExportNotesDialog {
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub format_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub images_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub order_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub metadata_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub notes_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+
+        pub session: OnceCell<Session>,
+        /// The notes as they were when the dialog was opened, in the order dragged to
+        pub manual_order: RefCell<Vec<CombinedNote>>,
+        pub export_chooser: OnceCell<gtk::FileChooserNative>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ExportNotesDialog {
+        const NAME: &'static str = "NwtyExportNotesDialog";
+        type Type = super::ExportNotesDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ExportNotesDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the notes are from",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_signals();
+        }
+    }
+
+    impl WidgetImpl for ExportNotesDialog {}
+    impl WindowImpl for ExportNotesDialog {}
+    impl AdwWindowImpl for ExportNotesDialog {}
+}
+
+glib::wrapper! {
+    /// Lets the user choose how notes are exported as one document
+    pub struct ExportNotesDialog(ObjectSubclass<imp::ExportNotesDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl ExportNotesDialog {
+    pub fn new(session: &Session, notes: &[Note]) -> Self {
+        let obj: Self = glib::Object::new(&[("session", session)])
+            .expect("Failed to create ExportNotesDialog.");
+
+        let notes = notes.iter().map(combined_note).collect::<Vec<_>>();
+        obj.imp().manual_order.replace(sorted_by_date(&notes));
+        obj.update_list_box();
+
+        obj
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn format(&self) -> CombinedFormat {
+        FORMAT_CHOICES
+            .get(self.imp().format_row.selected() as usize)
+            .copied()
+            .unwrap_or(CombinedFormat::Markdown)
+    }
+
+    /// How the images are kept in the HTML written to `file`
+    fn image_embedding(&self, file: &gio::File) -> ImageEmbedding {
+        if self.imp().images_row.selected() != COPY_IMAGES_POSITION {
+            return ImageEmbedding::DataUri;
+        }
+
+        let file_name = file
+            .basename()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ImageEmbedding::CopyAlongside {
+            dir_name: images_dir_name(&file_name),
+        }
+    }
+
+    fn is_manual_order(&self) -> bool {
+        self.imp().order_row.selected() == MANUAL_ORDER_POSITION
+    }
+
+    /// The notes in the order they are exported in
+    fn notes(&self) -> Vec<CombinedNote> {
+        let manual_order = self.imp().manual_order.borrow();
+
+        if self.is_manual_order() {
+            manual_order.clone()
+        } else {
+            sorted_by_date(&manual_order)
+        }
+    }
+
+    /// Move the note at `from` to `to`, as shown in the list
+    fn move_note(&self, from: usize, to: usize) {
+        if !self.is_manual_order() || from == to {
+            return;
+        }
+
+        move_item(&mut self.imp().manual_order.borrow_mut(), from, to);
+        self.update_list_box();
+    }
+
+    fn update_list_box(&self) {
+        let imp = self.imp();
+        let is_manual_order = self.is_manual_order();
+
+        while let Some(row) = imp.list_box.row_at_index(0) {
+            imp.list_box.remove(&row);
+        }
+
+        for (index, note) in self.notes().iter().enumerate() {
+            let row = self.note_row(index, note, is_manual_order);
+            imp.list_box.append(&row);
+        }
+
+        imp.notes_group.set_description(Some(&if is_manual_order {
+            gettext("Drag the notes to the order they are exported in")
+        } else {
+            gettext("From the least to the most recently modified")
+        }));
+    }
+
+    fn note_row(
+        &self,
+        index: usize,
+        note: &CombinedNote,
+        is_manual_order: bool,
+    ) -> gtk::ListBoxRow {
+        let title_label = gtk::Label::builder()
+            .label(&note.title)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        let date_label = gtk::Label::new(Some(&note.last_modified.format_relative()));
+        date_label.add_css_class("dim-label");
+        date_label.add_css_class("caption");
+
+        let row_box = gtk::Box::builder().spacing(12).build();
+        row_box.add_css_class("export-notes-dialog-row");
+
+        let row = gtk::ListBoxRow::builder()
+            .child(&row_box)
+            .activatable(false)
+            .build();
+
+        if is_manual_order {
+            let drag_handle = gtk::Image::from_icon_name("list-drag-handle-symbolic");
+            drag_handle.set_tooltip_text(Some(&gettext("Drag to Reorder")));
+            drag_handle.add_css_class("dim-label");
+            drag_handle.set_cursor_from_name(Some("grab"));
+            row_box.append(&drag_handle);
+
+            self.setup_drag_and_drop(&row, &drag_handle, index);
+        }
+
+        row_box.append(&title_label);
+        row_box.append(&date_label);
+
+        row
+    }
+
+    /// Notes are reordered by dragging their handle onto the row of another note
+    fn setup_drag_and_drop(&self, row: &gtk::ListBoxRow, drag_handle: &gtk::Image, index: usize) {
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        drag_source.set_content(Some(&gdk::ContentProvider::for_value(
+            &(index as u32).to_value(),
+        )));
+        drag_source.connect_drag_begin(clone!(@weak row => move |drag_source, _| {
+            let paintable = gtk::WidgetPaintable::new(Some(&row));
+            drag_source.set_icon(Some(&paintable), 0, 0);
+        }));
+        drag_handle.add_controller(&drag_source);
+
+        let drop_target = gtk::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+        drop_target.connect_drop(
+            clone!(@weak self as obj => @default-return false, move |_, value, _, _| {
+                match value.get::<u32>() {
+                    Ok(from) => {
+                        obj.move_note(from as usize, index);
+                        true
+                    }
+                    Err(err) => {
+                        log::warn!("Dropped value is not a note position: {:?}", err);
+                        false
+                    }
+                }
+            }),
+        );
+        row.add_controller(&drop_target);
+    }
+
+    fn export_chooser(&self) -> &gtk::FileChooserNative {
+        self.imp().export_chooser.get_or_init(|| {
+            let chooser = gtk::FileChooserNative::builder()
+                .accept_label(&gettext("Export"))
+                .cancel_label(&gettext("Cancel"))
+                .title(&gettext("Export Notes"))
+                .action(gtk::FileChooserAction::Save)
+                .modal(true)
+                .build();
+
+            chooser.set_transient_for(Some(self));
+
+            chooser.connect_response(clone!(@weak self as obj => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(file) = chooser.file() {
+                        obj.export(&file);
+                    }
+                }
+            }));
+
+            chooser
+        })
+    }
+
+    fn export(&self, file: &gio::File) {
+        let imp = self.imp();
+        self.session().export_combined_notes(
+            file,
+            self.notes(),
+            self.format(),
+            imp.metadata_switch.is_active(),
+            self.image_embedding(file),
+        );
+        self.close();
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        imp.format_row
+            .connect_selected_notify(clone!(@weak self as obj => move |_| {
+                let is_html = obj.format() == CombinedFormat::Html;
+                obj.imp().images_row.set_visible(is_html);
+            }));
+
+        imp.order_row
+            .connect_selected_notify(clone!(@weak self as obj => move |_| {
+                obj.update_list_box();
+            }));
+
+        imp.export_button
+            .connect_clicked(clone!(@weak self as obj => move |_| {
+                let chooser = obj.export_chooser();
+                let file_name = format!("{}.{}", gettext("Notes"), obj.format().extension());
+                chooser.set_current_name(&file_name);
+                chooser.show();
+            }));
+    }
+}
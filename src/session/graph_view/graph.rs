@@ -0,0 +1,146 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{model::NoteId, session::note_manager::LinkIndex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub note_id: NoteId,
+    pub title: String,
+}
+
+/// Notes as nodes and the links between them as edges. An edge is the pair of the indices of
+/// the nodes it connects, the lower first, so links in both directions are a single edge.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Graph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    /// Links to notes that are not in `nodes` are left out
+    pub fn new(nodes: Vec<GraphNode>, link_index: &LinkIndex) -> Self {
+        let indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (&node.note_id, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+
+        for (index, node) in nodes.iter().enumerate() {
+            for target in link_index.links_from(&node.note_id) {
+                if let Some(&target_index) = indices.get(target) {
+                    let edge = (index.min(target_index), index.max(target_index));
+                    if seen.insert(edge) {
+                        edges.push(edge);
+                    }
+                }
+            }
+        }
+
+        Self { nodes, edges }
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn position(&self, note_id: &NoteId) -> Option<usize> {
+        self.nodes.iter().position(|node| &node.note_id == note_id)
+    }
+
+    /// The same graph without the nodes that have no edges
+    pub fn without_orphans(&self) -> Self {
+        let mut is_linked = vec![false; self.nodes.len()];
+        for &(a, b) in &self.edges {
+            is_linked[a] = true;
+            is_linked[b] = true;
+        }
+
+        let mut new_indices = vec![None; self.nodes.len()];
+        let mut nodes = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if is_linked[index] {
+                new_indices[index] = Some(nodes.len());
+                nodes.push(node.clone());
+            }
+        }
+
+        // Both ends of an edge are linked, so they always have a new index
+        let edges = self
+            .edges
+            .iter()
+            .filter_map(|&(a, b)| Some((new_indices[a]?, new_indices[b]?)))
+            .collect();
+
+        Self { nodes, edges }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(name: &str) -> GraphNode {
+        GraphNode {
+            note_id: NoteId::for_path(name),
+            title: name.to_string(),
+        }
+    }
+
+    fn link_index() -> LinkIndex {
+        LinkIndex::new([
+            (NoteId::for_path("a"), "a", "[[b]] [[c]]"),
+            (NoteId::for_path("b"), "b", "[[a]]"),
+            (NoteId::for_path("c"), "c", "[[trashed]]"),
+            (NoteId::for_path("d"), "d", ""),
+            (NoteId::for_path("trashed"), "trashed", "[[d]]"),
+        ])
+    }
+
+    #[test]
+    fn new() {
+        let graph = Graph::new(
+            vec![node("a"), node("b"), node("c"), node("d")],
+            &link_index(),
+        );
+
+        assert_eq!(graph.n_nodes(), 4);
+        // a -> b and b -> a are the same edge, and the trashed note is not a node
+        assert_eq!(graph.edges(), [(0, 1), (0, 2)]);
+        assert_eq!(graph.position(&NoteId::for_path("c")), Some(2));
+        assert_eq!(graph.position(&NoteId::for_path("trashed")), None);
+    }
+
+    #[test]
+    fn new_empty() {
+        let graph = Graph::new(Vec::new(), &link_index());
+        assert_eq!(graph.n_nodes(), 0);
+        assert!(graph.edges().is_empty());
+    }
+
+    #[test]
+    fn without_orphans() {
+        let graph = Graph::new(vec![node("d"), node("c"), node("a")], &link_index());
+        assert_eq!(graph.edges(), [(1, 2)]);
+
+        let graph = graph.without_orphans();
+        assert_eq!(graph.nodes(), [node("c"), node("a")]);
+        assert_eq!(graph.edges(), [(0, 1)]);
+    }
+
+    #[test]
+    fn without_orphans_no_edges() {
+        let graph = Graph::new(vec![node("d")], &link_index()).without_orphans();
+        assert_eq!(graph, Graph::default());
+    }
+}
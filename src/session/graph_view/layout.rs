@@ -0,0 +1,219 @@
+// Based on the force-directed placement of Fruchterman and Reingold
+// See https://doi.org/10.1002/spe.4380211102
+
+use crate::core::Point;
+
+/// Distance that linked nodes settle around, in layout units
+const IDEAL_DISTANCE: f64 = 1.0;
+/// Pulls every node toward the origin, so unlinked parts of the graph don't drift apart
+const GRAVITY: f64 = 0.05;
+/// How much the maximum step shrinks after each iteration
+const COOLING: f64 = 0.95;
+/// The layout is settled once the maximum step is below this
+const MIN_TEMPERATURE: f64 = 0.005;
+/// Nodes closer than this are treated as this far apart, to avoid dividing by zero
+const MIN_DISTANCE: f64 = 0.01;
+
+/// Angle between consecutive nodes in the initial spiral, so they start spread out
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+
+/// Positions of the nodes of a graph, moved a bit closer to a balanced layout on each
+/// [`ForceLayout::step`]. This doesn't depend on Gtk, so it can run on another thread.
+#[derive(Debug, Clone)]
+pub struct ForceLayout {
+    positions: Vec<Point>,
+    edges: Vec<(usize, usize)>,
+    temperature: f64,
+}
+
+impl ForceLayout {
+    /// Start with the `n_nodes` nodes placed on a spiral. `edges` are pairs of node indices.
+    pub fn new(n_nodes: usize, edges: Vec<(usize, usize)>) -> Self {
+        let positions = (0..n_nodes)
+            .map(|index| {
+                let radius = IDEAL_DISTANCE * (index as f64 + 0.5).sqrt();
+                let angle = index as f64 * GOLDEN_ANGLE;
+                Point::new(radius * angle.cos(), radius * angle.sin())
+            })
+            .collect();
+
+        Self {
+            positions,
+            edges,
+            temperature: IDEAL_DISTANCE * (n_nodes as f64).sqrt().max(1.0) / 2.0,
+        }
+    }
+
+    pub fn positions(&self) -> &[Point] {
+        &self.positions
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.temperature < MIN_TEMPERATURE
+    }
+
+    /// Move the nodes by one iteration. Linked nodes attract each other and all nodes repel
+    /// each other.
+    pub fn step(&mut self) {
+        if self.is_settled() {
+            return;
+        }
+
+        let n_nodes = self.positions.len();
+        let mut displacements = vec![Point::ZERO; n_nodes];
+
+        for a in 0..n_nodes {
+            for b in a + 1..n_nodes {
+                let (dx, dy, distance) = self.delta(a, b);
+                let force = IDEAL_DISTANCE * IDEAL_DISTANCE / distance;
+                apply_force(
+                    &mut displacements,
+                    a,
+                    b,
+                    dx / distance * force,
+                    dy / distance * force,
+                );
+            }
+        }
+
+        for &(a, b) in &self.edges {
+            let (dx, dy, distance) = self.delta(a, b);
+            let force = -distance * distance / IDEAL_DISTANCE;
+            apply_force(
+                &mut displacements,
+                a,
+                b,
+                dx / distance * force,
+                dy / distance * force,
+            );
+        }
+
+        for (position, displacement) in self.positions.iter_mut().zip(&displacements) {
+            let dx = displacement.x - position.x * GRAVITY;
+            let dy = displacement.y - position.y * GRAVITY;
+            let length = dx.hypot(dy).max(MIN_DISTANCE);
+            let step = length.min(self.temperature);
+
+            position.x += dx / length * step;
+            position.y += dy / length * step;
+        }
+
+        self.temperature *= COOLING;
+    }
+
+    /// Offset from node `b` to node `a`, and the distance between them
+    fn delta(&self, a: usize, b: usize) -> (f64, f64, f64) {
+        let a = self.positions[a];
+        let b = self.positions[b];
+        let (dx, dy) = (a.x - b.x, a.y - b.y);
+        (dx, dy, dx.hypot(dy).max(MIN_DISTANCE))
+    }
+}
+
+/// Push node `a` by (`x`, `y`) and node `b` by the opposite
+fn apply_force(displacements: &mut [Point], a: usize, b: usize, x: f64, y: f64) {
+    displacements[a].x += x;
+    displacements[a].y += y;
+    displacements[b].x -= x;
+    displacements[b].y -= y;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAX_STEPS: usize = 500;
+
+    fn settle(layout: &mut ForceLayout) -> usize {
+        let mut n_steps = 0;
+        while !layout.is_settled() {
+            assert!(n_steps < MAX_STEPS, "Layout did not settle");
+            layout.step();
+            n_steps += 1;
+        }
+        n_steps
+    }
+
+    fn distance(layout: &ForceLayout, a: usize, b: usize) -> f64 {
+        layout.delta(a, b).2
+    }
+
+    #[test]
+    fn new_spread_out() {
+        let layout = ForceLayout::new(100, Vec::new());
+        assert_eq!(layout.positions().len(), 100);
+
+        for a in 0..100 {
+            for b in a + 1..100 {
+                assert!(distance(&layout, a, b) > MIN_DISTANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let mut layout = ForceLayout::new(0, Vec::new());
+        settle(&mut layout);
+        assert!(layout.positions().is_empty());
+    }
+
+    #[test]
+    fn single_node_stays_near_origin() {
+        let mut layout = ForceLayout::new(1, Vec::new());
+        settle(&mut layout);
+
+        let position = layout.positions()[0];
+        assert!(position.x.hypot(position.y) < IDEAL_DISTANCE);
+    }
+
+    #[test]
+    fn settles() {
+        let edges = (1..50).map(|index| (index / 2, index)).collect();
+        let mut layout = ForceLayout::new(50, edges);
+        settle(&mut layout);
+
+        // Stepping a settled layout does nothing
+        let positions = layout.positions().to_vec();
+        layout.step();
+        assert_eq!(layout.positions(), positions);
+
+        assert!(layout
+            .positions()
+            .iter()
+            .all(|position| position.x.is_finite() && position.y.is_finite()));
+    }
+
+    #[test]
+    fn linked_nodes_are_closer() {
+        // Two triangles, linked by nothing
+        let edges = vec![(0, 1), (1, 2), (0, 2), (3, 4), (4, 5), (3, 5)];
+        let mut layout = ForceLayout::new(6, edges);
+        settle(&mut layout);
+
+        let max_linked = [(0, 1), (1, 2), (0, 2), (3, 4), (4, 5), (3, 5)]
+            .iter()
+            .map(|&(a, b)| distance(&layout, a, b))
+            .fold(0.0, f64::max);
+        let min_unlinked = (0..3)
+            .flat_map(|a| (3..6).map(move |b| (a, b)))
+            .map(|(a, b)| distance(&layout, a, b))
+            .fold(f64::INFINITY, f64::min);
+
+        assert!(
+            max_linked < min_unlinked,
+            "{} is not less than {}",
+            max_linked,
+            min_unlinked
+        );
+    }
+
+    #[test]
+    fn deterministic() {
+        let edges = vec![(0, 1), (1, 2)];
+        let mut layout_a = ForceLayout::new(3, edges.clone());
+        let mut layout_b = ForceLayout::new(3, edges);
+        settle(&mut layout_a);
+        settle(&mut layout_b);
+        assert_eq!(layout_a.positions(), layout_b.positions());
+    }
+}
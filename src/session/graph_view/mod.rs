@@ -0,0 +1,457 @@
+mod graph;
+mod layout;
+mod viewport;
+
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    cairo, gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    thread,
+    time::Duration,
+};
+
+use self::{
+    graph::{Graph, GraphNode},
+    layout::ForceLayout,
+    viewport::Viewport,
+};
+use super::Session;
+use crate::core::Point;
+
+/// Above this, nodes are drawn as dots without labels until zoomed in
+const MAX_DETAILED_NODES: usize = 1000;
+const DETAILED_ZOOM: f64 = 4.0;
+
+const MIN_ZOOM: f64 = 1.0;
+const MAX_ZOOM: f64 = 32.0;
+const ZOOM_STEP: f64 = 1.2;
+
+const NODE_RADIUS: f64 = 5.0;
+const CURRENT_NODE_RADIUS: f64 = 8.0;
+const DOT_SIZE: f64 = 2.0;
+const LABEL_FONT_SIZE: f64 = 12.0;
+/// How far from a node a click still opens it, in pixels
+const CLICK_RADIUS: f64 = 12.0;
+
+/// Layout iterations done between each update of the drawn positions
+const STEPS_PER_UPDATE: usize = 4;
+const UPDATE_INTERVAL: Duration = Duration::from_millis(16);
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/graph-view.ui")]
+    pub struct GraphView {
+        #[template_child]
+        pub hide_orphans_button: TemplateChild<gtk::ToggleButton>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub empty_view: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub drawing_area: TemplateChild<gtk::DrawingArea>,
+
+        pub session: OnceCell<Session>,
+        /// Every note, including those without links
+        pub full_graph: RefCell<Graph>,
+        /// The graph that is shown, and the current positions of its nodes
+        pub graph: RefCell<Graph>,
+        pub positions: RefCell<Vec<Point>>,
+        /// Increased when the layout is restarted, so updates from the previous one are ignored
+        pub layout_generation: Cell<u32>,
+
+        pub zoom: Cell<f64>,
+        pub pan: Cell<Point>,
+        pub drag_anchor: Cell<Option<Point>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GraphView {
+        const NAME: &'static str = "NwtyGraphView";
+        type Type = super::GraphView;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for GraphView {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the notes are from",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.zoom.set(MIN_ZOOM);
+
+            self.drawing_area
+                .set_draw_func(clone!(@weak obj => move |_, cr, width, height| {
+                    if let Err(err) = obj.draw(cr, f64::from(width), f64::from(height)) {
+                        log::error!("Failed to draw graph: {:?}", err);
+                    }
+                }));
+
+            obj.setup_signals();
+            obj.setup_gestures();
+            obj.load_graph();
+        }
+
+        fn dispose(&self, _obj: &Self::Type) {
+            // Stops the running layout on its next update
+            self.layout_generation.set(self.layout_generation.get() + 1);
+        }
+    }
+
+    impl WidgetImpl for GraphView {}
+    impl WindowImpl for GraphView {}
+    impl AdwWindowImpl for GraphView {}
+}
+
+glib::wrapper! {
+    pub struct GraphView(ObjectSubclass<imp::GraphView>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl GraphView {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create GraphView.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn load_graph(&self) {
+        let note_manager = self.session().note_manager().clone();
+
+        let nodes = note_manager
+            .note_list()
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+            .map(|note| {
+                let title = note.metadata().title();
+                GraphNode {
+                    note_id: note.id(),
+                    title: if title.is_empty() {
+                        gettext("Untitled Note")
+                    } else {
+                        title
+                    },
+                }
+            })
+            .collect();
+
+        self.imp()
+            .full_graph
+            .replace(Graph::new(nodes, &note_manager.link_index()));
+        self.update_graph();
+    }
+
+    /// Show the full graph, or only the linked notes, and lay it out again
+    fn update_graph(&self) {
+        let imp = self.imp();
+
+        let graph = if imp.hide_orphans_button.is_active() {
+            imp.full_graph.borrow().without_orphans()
+        } else {
+            imp.full_graph.borrow().clone()
+        };
+
+        if graph.n_nodes() == 0 {
+            imp.stack.set_visible_child(&imp.empty_view.get());
+        } else {
+            imp.stack.set_visible_child(&imp.drawing_area.get());
+        }
+
+        imp.zoom.set(MIN_ZOOM);
+        imp.pan.set(Point::ZERO);
+
+        let layout = ForceLayout::new(graph.n_nodes(), graph.edges().to_vec());
+        imp.positions.replace(layout.positions().to_vec());
+        imp.graph.replace(graph);
+        imp.drawing_area.queue_draw();
+
+        self.start_layout(layout);
+    }
+
+    /// Iterate `layout` on the thread pool, and draw the positions as they come in
+    fn start_layout(&self, mut layout: ForceLayout) {
+        let imp = self.imp();
+
+        let generation = imp.layout_generation.get() + 1;
+        imp.layout_generation.set(generation);
+
+        if layout.positions().is_empty() {
+            return;
+        }
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT_IDLE);
+
+        let res = crate::THREAD_POOL.push(move || {
+            while !layout.is_settled() {
+                for _ in 0..STEPS_PER_UPDATE {
+                    layout.step();
+                }
+
+                // The receiver is dropped once the layout is restarted or the view is closed
+                if sender.send(layout.positions().to_vec()).is_err() {
+                    break;
+                }
+
+                thread::sleep(UPDATE_INTERVAL);
+            }
+        });
+
+        if let Err(err) = res {
+            log::error!("Failed to start graph layout: {:?}", err);
+            return;
+        }
+
+        receiver.attach(
+            None,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move |positions| {
+                let imp = obj.imp();
+
+                if imp.layout_generation.get() != generation {
+                    return glib::Continue(false);
+                }
+
+                imp.positions.replace(positions);
+                imp.drawing_area.queue_draw();
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    fn viewport(&self, positions: &[Point]) -> Viewport {
+        let imp = self.imp();
+        let drawing_area = imp.drawing_area.get();
+
+        Viewport::fit(
+            positions,
+            f64::from(drawing_area.width()),
+            f64::from(drawing_area.height()),
+            imp.zoom.get(),
+            imp.pan.get(),
+        )
+    }
+
+    fn set_zoom(&self, zoom: f64) {
+        let imp = self.imp();
+        imp.zoom.set(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+        imp.drawing_area.queue_draw();
+    }
+
+    /// The node at (`x`, `y`) in the drawing area
+    fn node_at(&self, x: f64, y: f64) -> Option<GraphNode> {
+        let imp = self.imp();
+        let positions = imp.positions.borrow();
+
+        self.viewport(&positions)
+            .position_at(&positions, x, y, CLICK_RADIUS)
+            .and_then(|index| imp.graph.borrow().nodes().get(index).cloned())
+    }
+
+    fn open_node_at(&self, x: f64, y: f64) {
+        let session = self.session();
+
+        if let Some(note) = self
+            .node_at(x, y)
+            .and_then(|node| session.note_manager().note_list().find_by_id(&node.note_id))
+        {
+            session.set_selected_note(Some(note));
+            self.close();
+        }
+    }
+
+    fn draw(&self, cr: &cairo::Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+        let imp = self.imp();
+        let graph = imp.graph.borrow();
+        let positions = imp.positions.borrow();
+
+        if positions.len() != graph.n_nodes() {
+            return Ok(());
+        }
+
+        let viewport = self.viewport(&positions);
+        let points = positions
+            .iter()
+            .map(|&position| viewport.to_widget(position))
+            .collect::<Vec<_>>();
+
+        let style_context = self.style_context();
+        let color = style_context.color();
+        let accent_color = style_context.lookup_color("accent_color").unwrap_or(color);
+        let set_source = |alpha: f64, color: gtk::gdk::RGBA| {
+            cr.set_source_rgba(
+                f64::from(color.red()),
+                f64::from(color.green()),
+                f64::from(color.blue()),
+                alpha * f64::from(color.alpha()),
+            );
+        };
+
+        // Edges are drawn with a single stroke, so they stay cheap even with many nodes
+        set_source(0.3, color);
+        cr.set_line_width(1.0);
+        for &(a, b) in graph.edges() {
+            cr.move_to(points[a].x, points[a].y);
+            cr.line_to(points[b].x, points[b].y);
+        }
+        cr.stroke()?;
+
+        let is_in_view = |point: &Point| {
+            (-CURRENT_NODE_RADIUS..width + CURRENT_NODE_RADIUS).contains(&point.x)
+                && (-CURRENT_NODE_RADIUS..height + CURRENT_NODE_RADIUS).contains(&point.y)
+        };
+        let is_detailed = graph.n_nodes() <= MAX_DETAILED_NODES || imp.zoom.get() >= DETAILED_ZOOM;
+
+        set_source(1.0, color);
+        if is_detailed {
+            for point in points.iter().filter(|point| is_in_view(point)) {
+                cr.new_sub_path();
+                cr.arc(point.x, point.y, NODE_RADIUS, 0.0, std::f64::consts::TAU);
+            }
+            cr.fill()?;
+
+            cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+            cr.set_font_size(LABEL_FONT_SIZE);
+            set_source(0.8, color);
+            for (node, point) in graph.nodes().iter().zip(&points) {
+                if is_in_view(point) {
+                    cr.move_to(
+                        point.x + CURRENT_NODE_RADIUS + 2.0,
+                        point.y + LABEL_FONT_SIZE / 3.0,
+                    );
+                    cr.show_text(&node.title)?;
+                }
+            }
+        } else {
+            for point in points.iter().filter(|point| is_in_view(point)) {
+                cr.rectangle(
+                    point.x - DOT_SIZE / 2.0,
+                    point.y - DOT_SIZE / 2.0,
+                    DOT_SIZE,
+                    DOT_SIZE,
+                );
+            }
+            cr.fill()?;
+        }
+
+        let current_index = self
+            .session()
+            .selected_note()
+            .and_then(|note| graph.position(&note.id()));
+        if let Some(point) = current_index.map(|index| points[index]) {
+            set_source(1.0, accent_color);
+            cr.new_sub_path();
+            cr.arc(
+                point.x,
+                point.y,
+                CURRENT_NODE_RADIUS,
+                0.0,
+                std::f64::consts::TAU,
+            );
+            cr.fill()?;
+        }
+
+        Ok(())
+    }
+
+    fn setup_signals(&self) {
+        self.imp()
+            .hide_orphans_button
+            .connect_toggled(clone!(@weak self as obj => move |_| {
+                obj.update_graph();
+            }));
+    }
+
+    fn setup_gestures(&self) {
+        let drawing_area = self.imp().drawing_area.get();
+
+        let gesture_click = gtk::GestureClick::new();
+        gesture_click.connect_released(clone!(@weak self as obj => move |_, _, x, y| {
+            obj.open_node_at(x, y);
+        }));
+        drawing_area.add_controller(&gesture_click);
+
+        let gesture_drag = gtk::GestureDrag::new();
+        gesture_drag.connect_drag_begin(clone!(@weak self as obj => move |_, _, _| {
+            let imp = obj.imp();
+            imp.drag_anchor.set(Some(imp.pan.get()));
+        }));
+        gesture_drag.connect_drag_update(
+            clone!(@weak self as obj => move |_, offset_x, offset_y| {
+                let imp = obj.imp();
+                if let Some(drag_anchor) = imp.drag_anchor.get() {
+                    imp.pan.set(Point::new(drag_anchor.x + offset_x, drag_anchor.y + offset_y));
+                    imp.drawing_area.queue_draw();
+                }
+            }),
+        );
+        gesture_drag.connect_drag_end(clone!(@weak self as obj => move |_, _, _| {
+            obj.imp().drag_anchor.set(None);
+        }));
+        drawing_area.add_controller(&gesture_drag);
+
+        let scroll_controller =
+            gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        scroll_controller.connect_scroll(
+            clone!(@weak self as obj => @default-panic, move |_, _delta_x, delta_y| {
+                obj.set_zoom(obj.imp().zoom.get() * ZOOM_STEP.powf(-delta_y));
+                gtk::Inhibit(true)
+            }),
+        );
+        drawing_area.add_controller(&scroll_controller);
+    }
+}
@@ -0,0 +1,148 @@
+use std::cmp::Ordering;
+
+use crate::core::Point;
+
+/// Space kept around the graph when it is fitted, in pixels
+const MARGIN: f64 = 24.0;
+
+/// Maps layout positions to widget coordinates. The graph is fitted to the widget at zoom 1,
+/// then scaled by the zoom around the center and moved by the pan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    scale: f64,
+    offset: Point,
+}
+
+impl Viewport {
+    pub fn fit(positions: &[Point], width: f64, height: f64, zoom: f64, pan: Point) -> Self {
+        let (min, max) = positions.iter().fold(
+            (
+                Point::new(f64::INFINITY, f64::INFINITY),
+                Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(min, max), position| {
+                (
+                    Point::new(min.x.min(position.x), min.y.min(position.y)),
+                    Point::new(max.x.max(position.x), max.y.max(position.y)),
+                )
+            },
+        );
+
+        let (center, fit_scale) = if positions.is_empty() {
+            (Point::ZERO, 1.0)
+        } else {
+            let center = Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+            let graph_width = (max.x - min.x).max(f64::EPSILON);
+            let graph_height = (max.y - min.y).max(f64::EPSILON);
+            let fit_scale = ((width - 2.0 * MARGIN) / graph_width)
+                .min((height - 2.0 * MARGIN) / graph_height)
+                .max(f64::EPSILON);
+            // A single node or a few close ones shouldn't be blown up
+            (center, fit_scale.min(width.min(height)))
+        };
+
+        let scale = fit_scale * zoom;
+
+        Self {
+            scale,
+            offset: Point::new(
+                width / 2.0 + pan.x - center.x * scale,
+                height / 2.0 + pan.y - center.y * scale,
+            ),
+        }
+    }
+
+    pub fn to_widget(&self, position: Point) -> Point {
+        Point::new(
+            position.x * self.scale + self.offset.x,
+            position.y * self.scale + self.offset.y,
+        )
+    }
+
+    /// Index of the position closest to (`x`, `y`) in widget coordinates, if it is within
+    /// `radius` pixels
+    pub fn position_at(&self, positions: &[Point], x: f64, y: f64, radius: f64) -> Option<usize> {
+        positions
+            .iter()
+            .map(|&position| {
+                let point = self.to_widget(position);
+                (point.x - x).hypot(point.y - y)
+            })
+            .enumerate()
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn positions() -> Vec<Point> {
+        vec![
+            Point::new(-1.0, -1.0),
+            Point::new(1.0, 1.0),
+            Point::new(3.0, -1.0),
+        ]
+    }
+
+    #[test]
+    fn fit() {
+        let viewport = Viewport::fit(&positions(), 448.0, 248.0, 1.0, Point::ZERO);
+
+        // Limited by the height, which is 200 without the margins for a graph 2 high
+        assert_eq!(viewport.scale, 100.0);
+        assert_eq!(
+            viewport.to_widget(Point::new(1.0, 0.0)),
+            Point::new(224.0, 124.0)
+        );
+        assert_eq!(
+            viewport.to_widget(Point::new(1.0, -1.0)),
+            Point::new(224.0, 24.0)
+        );
+    }
+
+    #[test]
+    fn fit_zoom_and_pan() {
+        let viewport = Viewport::fit(&positions(), 448.0, 248.0, 2.0, Point::new(10.0, -5.0));
+
+        assert_eq!(viewport.scale, 200.0);
+        // The center stays in place when zoomed, then is moved by the pan
+        assert_eq!(
+            viewport.to_widget(Point::new(1.0, 0.0)),
+            Point::new(234.0, 119.0)
+        );
+        assert_eq!(
+            viewport.to_widget(Point::new(2.0, 0.0)),
+            Point::new(434.0, 119.0)
+        );
+    }
+
+    #[test]
+    fn fit_empty_or_single() {
+        let viewport = Viewport::fit(&[], 100.0, 100.0, 1.0, Point::ZERO);
+        assert_eq!(viewport.to_widget(Point::ZERO), Point::new(50.0, 50.0));
+
+        let viewport = Viewport::fit(&[Point::new(5.0, 5.0)], 100.0, 100.0, 1.0, Point::ZERO);
+        assert_eq!(
+            viewport.to_widget(Point::new(5.0, 5.0)),
+            Point::new(50.0, 50.0)
+        );
+        assert!(viewport.scale.is_finite());
+    }
+
+    #[test]
+    fn position_at() {
+        let positions = positions();
+        let viewport = Viewport::fit(&positions, 448.0, 248.0, 1.0, Point::ZERO);
+
+        assert_eq!(viewport.position_at(&positions, 224.0, 224.0, 8.0), Some(1));
+        assert_eq!(
+            viewport.position_at(&positions, 230.0, 218.0, 10.0),
+            Some(1)
+        );
+        assert_eq!(viewport.position_at(&positions, 224.0, 124.0, 8.0), None);
+        assert_eq!(viewport.position_at(&[], 0.0, 0.0, 8.0), None);
+    }
+}
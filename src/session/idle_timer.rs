@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+/// Tells whether there was no activity for longer than the timeout
+#[derive(Debug)]
+pub struct IdleTimer {
+    last_activity: Instant,
+}
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl IdleTimer {
+    pub fn new(now: Instant) -> Self {
+        Self { last_activity: now }
+    }
+
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Never idle when `timeout` is `None`
+    pub fn is_idle(&self, timeout: Option<Duration>, now: Instant) -> bool {
+        timeout.map_or(false, |timeout| {
+            now.saturating_duration_since(self.last_activity) >= timeout
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(60));
+
+    #[test]
+    fn is_idle() {
+        let start = Instant::now();
+        let idle_timer = IdleTimer::new(start);
+
+        assert!(!idle_timer.is_idle(TIMEOUT, start));
+        assert!(!idle_timer.is_idle(TIMEOUT, start + Duration::from_secs(59)));
+        assert!(idle_timer.is_idle(TIMEOUT, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn record_activity() {
+        let start = Instant::now();
+        let mut idle_timer = IdleTimer::new(start);
+
+        idle_timer.record_activity(start + Duration::from_secs(30));
+        assert!(!idle_timer.is_idle(TIMEOUT, start + Duration::from_secs(60)));
+        assert!(idle_timer.is_idle(TIMEOUT, start + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn no_timeout() {
+        let start = Instant::now();
+        let idle_timer = IdleTimer::new(start);
+        assert!(!idle_timer.is_idle(None, start + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn activity_after_now() {
+        let start = Instant::now();
+        let idle_timer = IdleTimer::new(start + Duration::from_secs(10));
+        assert!(!idle_timer.is_idle(TIMEOUT, start));
+    }
+}
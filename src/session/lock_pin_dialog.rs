@@ -0,0 +1,136 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use crate::{core::PinHash, spawn, spawn_blocking, Application};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/lock-pin-dialog.ui")]
+    pub struct LockPinDialog {
+        #[template_child]
+        pub pin_entry: TemplateChild<gtk::PasswordEntry>,
+        #[template_child]
+        pub confirm_entry: TemplateChild<gtk::PasswordEntry>,
+        #[template_child]
+        pub remove_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LockPinDialog {
+        const NAME: &'static str = "NwtyLockPinDialog";
+        type Type = super::LockPinDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+
+            klass.install_action("lock-pin-dialog.set", None, move |obj, _, _| {
+                obj.set_pin();
+            });
+
+            klass.install_action("lock-pin-dialog.remove", None, move |obj, _, _| {
+                obj.remove_pin();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for LockPinDialog {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            // Shown once the keyring says there is a PIN to remove
+            self.remove_button.set_visible(false);
+            spawn!(clone!(@weak obj => async move {
+                match spawn_blocking!(PinHash::lookup_lock_pin).await {
+                    Ok(pin_hash) => obj.imp().remove_button.set_visible(pin_hash.is_some()),
+                    Err(err) => log::error!("Failed to look up lock PIN: {:?}", err),
+                }
+            }));
+
+            self.pin_entry
+                .connect_changed(clone!(@weak obj => move |_| obj.update_set_action()));
+            self.confirm_entry
+                .connect_changed(clone!(@weak obj => move |_| obj.update_set_action()));
+            self.confirm_entry
+                .connect_activate(clone!(@weak obj => move |_| obj.set_pin()));
+
+            obj.update_set_action();
+        }
+    }
+
+    impl WidgetImpl for LockPinDialog {}
+    impl WindowImpl for LockPinDialog {}
+    impl AdwWindowImpl for LockPinDialog {}
+}
+
+glib::wrapper! {
+    pub struct LockPinDialog(ObjectSubclass<imp::LockPinDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl LockPinDialog {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create LockPinDialog.")
+    }
+
+    fn is_pin_valid(&self) -> bool {
+        let imp = self.imp();
+        let pin = imp.pin_entry.text();
+        !pin.is_empty() && pin == imp.confirm_entry.text()
+    }
+
+    fn update_set_action(&self) {
+        self.action_set_enabled("lock-pin-dialog.set", self.is_pin_valid());
+    }
+
+    fn set_pin(&self) {
+        if !self.is_pin_valid() {
+            return;
+        }
+
+        let pin = self.imp().pin_entry.text().to_string();
+        self.set_sensitive(false);
+
+        // Hashing is slow on purpose, so don't block the UI on it
+        spawn!(clone!(@weak self as obj => async move {
+            let res = spawn_blocking!(move || PinHash::new(&pin).store_as_lock_pin()).await;
+            obj.close_with_result(res);
+        }));
+    }
+
+    fn remove_pin(&self) {
+        self.set_sensitive(false);
+
+        spawn!(clone!(@weak self as obj => async move {
+            let res = spawn_blocking!(PinHash::clear_lock_pin).await;
+            obj.close_with_result(res);
+        }));
+    }
+
+    fn close_with_result(&self, res: Result<(), glib::Error>) {
+        if let Err(err) = res {
+            log::error!("Failed to save lock PIN: {:?}", err);
+            Application::default()
+                .main_window()
+                .add_toast(&adw::Toast::new(&gettext(
+                    "Failed to save the PIN in the keyring",
+                )));
+        }
+
+        self.close();
+    }
+}
@@ -0,0 +1,372 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{
+    gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use super::Session;
+use crate::{
+    core::{DateTime, DiskUsage},
+    spawn,
+};
+
+/// Seconds in the months of the spin button, which are all taken as 30 days long
+const SECONDS_PER_MONTH: i64 = 30 * 24 * 60 * 60;
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/maintenance-dialog.ui")]
+    pub struct MaintenanceDialog {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub progress_bar: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
+        pub notes_size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub history_size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub empty_trash_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub compact_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub months_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub forget_history_button: TemplateChild<gtk::Button>,
+
+        pub session: OnceCell<Session>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MaintenanceDialog {
+        const NAME: &'static str = "NwtyMaintenanceDialog";
+        type Type = super::MaintenanceDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MaintenanceDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session whose notebook is maintained",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            self.empty_trash_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.show_empty_trash_dialog();
+                }));
+
+            self.compact_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    spawn!(clone!(@weak obj => async move {
+                        obj.compact().await;
+                    }));
+                }));
+
+            self.forget_history_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.show_forget_history_dialog();
+                }));
+
+            obj.session().note_manager().connect_n_trashed_notes_notify(
+                clone!(@weak obj => move |_| {
+                    obj.update_empty_trash_button();
+                }),
+            );
+            obj.update_empty_trash_button();
+
+            spawn!(clone!(@weak obj => async move {
+                obj.update_disk_usage().await;
+            }));
+        }
+    }
+
+    impl WidgetImpl for MaintenanceDialog {}
+    impl WindowImpl for MaintenanceDialog {}
+    impl AdwWindowImpl for MaintenanceDialog {}
+}
+
+glib::wrapper! {
+    /// Shows how much space the notes and their history take, with ways to free some of it
+    pub struct MaintenanceDialog(ObjectSubclass<imp::MaintenanceDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl MaintenanceDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create MaintenanceDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    /// Sizes of the notes and their history, computed off the main thread
+    async fn disk_usage(&self) -> Option<DiskUsage> {
+        let repository = self.session().note_manager().repository();
+
+        match repository.disk_usage().await {
+            Ok(disk_usage) => Some(disk_usage),
+            Err(err) => {
+                log::error!("Failed to get disk usage: {:?}", err);
+                None
+            }
+        }
+    }
+
+    async fn update_disk_usage(&self) {
+        let imp = self.imp();
+
+        match self.disk_usage().await {
+            Some(disk_usage) => {
+                imp.notes_size_row
+                    .set_subtitle(&glib::format_size(disk_usage.working_tree));
+                imp.history_size_row
+                    .set_subtitle(&glib::format_size(disk_usage.git_dir));
+            }
+            None => {
+                let message = gettext("Failed to calculate");
+                imp.notes_size_row.set_subtitle(&message);
+                imp.history_size_row.set_subtitle(&message);
+            }
+        }
+    }
+
+    fn update_empty_trash_button(&self) {
+        let imp = self.imp();
+        let n_trashed_notes = self.session().note_manager().n_trashed_notes();
+
+        imp.empty_trash_button
+            .set_sensitive(n_trashed_notes > 0 && !imp.progress_bar.is_visible());
+    }
+
+    /// Disable the operations while one runs, showing its progress
+    fn set_is_busy(&self, is_busy: bool) {
+        let imp = self.imp();
+
+        imp.progress_bar.set_fraction(0.0);
+        imp.progress_bar.set_visible(is_busy);
+        imp.compact_button.set_sensitive(!is_busy);
+        imp.forget_history_button.set_sensitive(!is_busy);
+        imp.months_spin_button.set_sensitive(!is_busy);
+        self.update_empty_trash_button();
+    }
+
+    fn show_toast(&self, title: &str) {
+        self.imp().toast_overlay.add_toast(&adw::Toast::new(title));
+    }
+
+    fn show_reclaimed(&self, reclaimed: u64) {
+        self.show_toast(&gettext!("Freed {}", glib::format_size(reclaimed)));
+    }
+
+    fn show_empty_trash_dialog(&self) {
+        let n_trashed_notes = self.session().note_manager().n_trashed_notes();
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Empty Trash?"))
+            .secondary_text(&ngettext!(
+                "The {} note in the trash will be deleted permanently.",
+                "The {} notes in the trash will be deleted permanently.",
+                n_trashed_notes,
+                n_trashed_notes
+            ))
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .transient_for(self)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Empty Trash"), gtk::ResponseType::Accept)
+            .add_css_class("destructive-action");
+
+        dialog.connect_response(clone!(@weak self as obj => move |dialog, response| {
+            dialog.destroy();
+
+            if response == gtk::ResponseType::Accept {
+                spawn!(clone!(@weak obj => async move {
+                    obj.empty_trash().await;
+                }));
+            }
+        }));
+        dialog.present();
+    }
+
+    async fn empty_trash(&self) {
+        self.set_is_busy(true);
+        self.imp().progress_bar.pulse();
+
+        let disk_usage_before = self.disk_usage().await;
+
+        match self.session().empty_trash().await {
+            Ok(n_deleted) => {
+                let disk_usage_after = self.disk_usage().await;
+                let reclaimed = disk_usage_before
+                    .zip(disk_usage_after)
+                    .map_or(0, |(before, after)| {
+                        before.working_tree.saturating_sub(after.working_tree)
+                    });
+
+                self.show_toast(&ngettext!(
+                    "Deleted {} note, freeing {}",
+                    "Deleted {} notes, freeing {}",
+                    n_deleted as u32,
+                    n_deleted,
+                    glib::format_size(reclaimed)
+                ));
+            }
+            Err(err) => {
+                log::error!("Failed to empty trash: {:?}", err);
+                self.show_toast(&gettext("Failed to delete some of the notes in the trash"));
+            }
+        }
+
+        self.set_is_busy(false);
+        self.update_disk_usage().await;
+    }
+
+    async fn compact(&self) {
+        self.set_is_busy(true);
+
+        let repository = self.session().note_manager().repository();
+        let progress_bar = self.imp().progress_bar.get();
+        let res = repository
+            .compact(move |fraction| progress_bar.set_fraction(fraction))
+            .await;
+
+        match res {
+            Ok(reclaimed) => self.show_reclaimed(reclaimed),
+            Err(err) => {
+                log::error!("Failed to compact repository: {:?}", err);
+                self.show_toast(&gettext("Failed to compact the history"));
+            }
+        }
+
+        self.set_is_busy(false);
+        self.update_disk_usage().await;
+    }
+
+    /// The start of the history that is kept, from the months in the spin button
+    fn history_cutoff(&self) -> DateTime {
+        let n_months = self.imp().months_spin_button.value_as_int() as i64;
+        let now = DateTime::now().to_local().timestamp();
+
+        DateTime::from_timestamp(now - n_months * SECONDS_PER_MONTH)
+    }
+
+    /// Ask before forgetting the history, which has to be confirmed by typing a phrase as it
+    /// also replaces the history of the remote
+    fn show_forget_history_dialog(&self) {
+        let cutoff = self.history_cutoff();
+        let confirmation = gettext("forget history");
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Forget Older History?"))
+            .secondary_text(&gettext!(
+                "The changes made before {} can't be restored anymore. If the notes are synced, the history of the remote is replaced too, and the other devices have to set up the notebook again.\n\nType “{}” to confirm.",
+                cutoff.fuzzy_display(),
+                confirmation
+            ))
+            .message_type(gtk::MessageType::Warning)
+            .modal(true)
+            .transient_for(self)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Forget"), gtk::ResponseType::Accept)
+            .add_css_class("destructive-action");
+        dialog.set_response_sensitive(gtk::ResponseType::Accept, false);
+
+        let entry = gtk::Entry::new();
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            let is_confirmed = entry.text().trim() == confirmation;
+            dialog.set_response_sensitive(gtk::ResponseType::Accept, is_confirmed);
+        }));
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&entry);
+
+        dialog.connect_response(clone!(@weak self as obj => move |dialog, response| {
+            dialog.destroy();
+
+            if response == gtk::ResponseType::Accept {
+                spawn!(clone!(@weak obj => async move {
+                    obj.forget_history(cutoff).await;
+                }));
+            }
+        }));
+        dialog.present();
+    }
+
+    async fn forget_history(&self, cutoff: DateTime) {
+        self.set_is_busy(true);
+
+        let repository = self.session().note_manager().repository();
+        let progress_bar = self.imp().progress_bar.get();
+        let res = repository
+            .forget_history_before(cutoff, move |fraction| progress_bar.set_fraction(fraction))
+            .await;
+
+        match res {
+            Ok(reclaimed) => self.show_reclaimed(reclaimed),
+            Err(err) => {
+                log::error!("Failed to forget history: {:?}", err);
+                self.show_toast(&gettext("Failed to forget the older history"));
+            }
+        }
+
+        self.set_is_busy(false);
+        self.update_disk_usage().await;
+    }
+}
@@ -0,0 +1,254 @@
+use adw::subclass::prelude::*;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone, closure},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use super::Session;
+use crate::{
+    model::{Note, NoteMetadata},
+    utils::fuzzy,
+};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/merge-note-dialog.ui")]
+    pub struct MergeNoteDialog {
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub list_view_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub list_view: TemplateChild<gtk::ListView>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+
+        pub session: OnceCell<Session>,
+        pub note: OnceCell<Note>,
+        pub filter: OnceCell<gtk::CustomFilter>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MergeNoteDialog {
+        const NAME: &'static str = "NwtyMergeNoteDialog";
+        type Type = super::MergeNoteDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MergeNoteDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecObject::new(
+                        "session",
+                        "Session",
+                        "Session where the notes are from",
+                        Session::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "note",
+                        "Note",
+                        "Note to merge into the chosen note",
+                        Note::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                "note" => {
+                    let note = value.get().unwrap();
+                    self.note.set(note).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                "note" => obj.note().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_view();
+            obj.setup_signals();
+            obj.update_stack();
+        }
+    }
+
+    impl WidgetImpl for MergeNoteDialog {}
+    impl WindowImpl for MergeNoteDialog {}
+    impl AdwWindowImpl for MergeNoteDialog {}
+}
+
+glib::wrapper! {
+    /// Lets the user choose the note to merge a note into
+    pub struct MergeNoteDialog(ObjectSubclass<imp::MergeNoteDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl MergeNoteDialog {
+    pub fn new(session: &Session, note: &Note) -> Self {
+        glib::Object::new(&[("session", session), ("note", note)])
+            .expect("Failed to create MergeNoteDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn note(&self) -> Note {
+        self.imp().note.get().unwrap().clone()
+    }
+
+    fn filter(&self) -> &gtk::CustomFilter {
+        self.imp().filter.get().unwrap()
+    }
+
+    fn is_candidate(&self, note: &Note) -> bool {
+        if note == &self.note() || note.metadata().is_trashed() {
+            return false;
+        }
+
+        let query = self.imp().search_entry.text();
+        fuzzy::score(query.trim(), &note.metadata().title()).is_some()
+    }
+
+    fn merge_into(&self, target: &Note) {
+        self.session().merge_notes(target, &self.note());
+        self.close();
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+        let has_candidates = imp
+            .list_view
+            .model()
+            .map_or(false, |model| model.n_items() > 0);
+
+        if has_candidates {
+            imp.stack.set_visible_child(&imp.list_view_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        }
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        imp.search_entry
+            .connect_search_changed(clone!(@weak self as obj => move |_| {
+                obj.filter().changed(gtk::FilterChange::Different);
+                obj.update_stack();
+            }));
+
+        imp.search_entry
+            .connect_activate(clone!(@weak self as obj => move |_| {
+                let first_note = obj
+                    .imp()
+                    .list_view
+                    .model()
+                    .and_then(|model| model.item(0))
+                    .and_then(|item| item.downcast::<Note>().ok());
+
+                if let Some(note) = first_note {
+                    obj.merge_into(&note);
+                }
+            }));
+
+        imp.search_entry
+            .connect_stop_search(clone!(@weak self as obj => move |_| {
+                obj.close();
+            }));
+
+        imp.list_view
+            .connect_activate(clone!(@weak self as obj => move |list_view, position| {
+                let note = list_view
+                    .model()
+                    .and_then(|model| model.item(position))
+                    .and_then(|item| item.downcast::<Note>().ok());
+
+                if let Some(note) = note {
+                    obj.merge_into(&note);
+                }
+            }));
+    }
+
+    fn setup_list_view(&self) {
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(|_, list_item| {
+            let label = gtk::Label::builder()
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+
+            list_item
+                .property_expression("item")
+                .chain_property::<Note>("metadata")
+                .chain_property::<NoteMetadata>("title")
+                .chain_closure::<String>(closure!(|_: Option<glib::Object>, title: String| {
+                    if title.is_empty() {
+                        gettext("Untitled Note")
+                    } else {
+                        title
+                    }
+                }))
+                .bind(&label, "label", glib::Object::NONE);
+
+            list_item.set_child(Some(&label));
+        });
+
+        let filter = gtk::CustomFilter::new(
+            clone!(@weak self as obj => @default-return false, move |item| {
+                obj.is_candidate(item.downcast_ref::<Note>().unwrap())
+            }),
+        );
+
+        let note_list = self.session().note_manager().note_list();
+        let filter_model = gtk::FilterListModel::new(Some(&note_list), Some(&filter));
+        let selection_model = gtk::SingleSelection::new(Some(&filter_model));
+
+        let imp = self.imp();
+        imp.filter.set(filter).unwrap();
+        imp.list_view.set_factory(Some(&factory));
+        imp.list_view.set_model(Some(&selection_model));
+    }
+}
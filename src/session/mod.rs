@@ -1,13 +1,34 @@
+mod attachments_dialog;
+mod attention_dialog;
+mod command_palette;
 mod content;
+mod delete_tag_dialog;
+mod duplicates_dialog;
+mod export_notes_dialog;
+mod graph_view;
+mod idle_timer;
+mod lock_pin_dialog;
+mod maintenance_dialog;
+mod merge_note_dialog;
 mod note_manager;
 mod note_tag_dialog;
+mod open_notes;
 mod picture_viewer;
+mod problems_dialog;
+mod review_changes_dialog;
 mod sidebar;
 mod tag_editor;
+mod tour;
 
 use adw::subclass::prelude::*;
+use futures_channel::mpsc;
+use futures_util::{
+    future::{self, Either},
+    StreamExt,
+};
+use gettextrs::{gettext, ngettext};
 use gtk::{
-    gio,
+    gdk, gio,
     glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
@@ -16,19 +37,96 @@ use once_cell::unsync::OnceCell;
 
 use std::{
     cell::{Cell, RefCell},
-    path::PathBuf,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
 };
 
 use self::{
-    content::Content, note_manager::NoteManager, note_tag_dialog::NoteTagDialog,
-    picture_viewer::PictureViewer, sidebar::Sidebar, tag_editor::TagEditor,
+    attachments_dialog::AttachmentsDialog,
+    attention_dialog::AttentionDialog,
+    command_palette::CommandPalette,
+    content::Content,
+    duplicates_dialog::DuplicatesDialog,
+    export_notes_dialog::ExportNotesDialog,
+    graph_view::GraphView,
+    idle_timer::IdleTimer,
+    lock_pin_dialog::LockPinDialog,
+    maintenance_dialog::MaintenanceDialog,
+    merge_note_dialog::MergeNoteDialog,
+    note_manager::{
+        LinkUpdate, MergedNotes, NoteManager, SiteExportReport, TagBundleImportReport,
+        UpdatedLinks, VaultImportReport,
+    },
+    note_tag_dialog::NoteTagDialog,
+    open_notes::OpenNotes,
+    picture_viewer::PictureViewer,
+    problems_dialog::ProblemsDialog,
+    review_changes_dialog::ReviewChangesDialog,
+    sidebar::{NoteQuery, NoteScope, Sidebar},
+    tag_editor::TagEditor,
+    tour::TourController,
 };
+
+pub use self::note_manager::ReadOnly;
 use crate::{
-    core::FileType,
-    model::{Attachment, Note},
-    spawn, Application,
+    core::{
+        combine_notes, embed_images, html_document, markdown_to_html, note_uri_markdown_link,
+        retry_once_on_auth_failure, CollisionPolicy, CombinedFormat, CombinedNote, CredentialKind,
+        DeletedTagData, DeletionMode, FileType, ImageEmbedding, NoteData, NoteRepository,
+        NotesNotSaved, PinHash, SiteExportCancelled, StartupMilestone, SyncState, TagBundle,
+        UnresolvedLink, VaultImportOptions, WrittenBundle, BUNDLE_EXTENSION,
+    },
+    model::{Attachment, ErrorReport, ErrorSource, Note, NoteId, Tag, TagList},
+    spawn, spawn_blocking,
+    utils::{
+        file_manager::{self, DesktopFileManager},
+        print_layout,
+        safe_path::resolve_within,
+        RecentList,
+    },
+    window::Window,
+    Application,
 };
 
+const MAX_RECENT_NOTES: usize = 20;
+/// Number of tags shown above the notes to quickly switch between them
+const MAX_RECENT_TAGS: usize = 5;
+const LOCK_CHECK_INTERVAL_SECS: u32 = 15;
+/// How long saving before closing can take before its progress is shown
+const SAVING_DIALOG_DELAY: Duration = Duration::from_millis(400);
+
+/// Actions that show or change the notes, so they are disabled while locked or loading
+const LOCKED_ACTIONS: &[&str] = &[
+    "session.navigate-back",
+    "session.create-note",
+    "session.show-command-palette",
+    "session.search-notes",
+    "session.show-graph",
+    "session.edit-tags",
+    "session.edit-selected-note-tags",
+    "session.edit-multi-selected-note-tags",
+    "session.export-multi-selected-notes",
+    "session.merge-selected-note",
+    "session.delete-selected-note",
+    "session.set-lock-pin",
+    "session.start-tour",
+    "session.export-site",
+    "session.find-duplicates",
+    "session.manage-attachments",
+    "session.show-problems",
+    "session.show-links-to-check",
+    "session.import-tag-bundle",
+    "session.export-writing-stats",
+    "session.empty-trash",
+];
+
+/// Actions that delete note files, so they are also disabled while a git operation may be
+/// merging them
+const REPOSITORY_BUSY_ACTIONS: &[&str] = &["session.delete-selected-note", "session.empty-trash"];
+
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
@@ -47,10 +145,42 @@ mod imp {
         pub content: TemplateChild<Content>,
         #[template_child]
         pub picture_viewer: TemplateChild<PictureViewer>,
+        #[template_child]
+        pub lock_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub unlock_entry: TemplateChild<gtk::PasswordEntry>,
 
         pub note_manager: OnceCell<NoteManager>,
         pub selected_note: RefCell<Option<Note>>,
+        pub recent_notes: RefCell<Vec<Note>>,
+        /// The note shown by each content, like the one of each window
+        pub open_notes: RefCell<OpenNotes<Content>>,
+        /// Whether the user is removing notes, so the notes shown that are closed with them
+        /// are not told about
+        pub is_removing_notes: Cell<bool>,
+        /// Names of the tags whose notes were shown, set once the tags are loaded
+        pub recent_tags: RefCell<Option<RecentList<String>>>,
         pub is_syncing: Cell<bool>,
+        /// Whether the user is asked for a new credential, so it is asked once at a time
+        pub is_asking_credential: Cell<bool>,
+        pub is_locked: Cell<bool>,
+        pub is_loading: Cell<bool>,
+        pub idle_timer: RefCell<IdleTimer>,
+        /// Whether the last copy to the clipboard was from the content of a note
+        pub is_clipboard_from_note: Cell<bool>,
+        /// The last merge while its toast is shown, so it can still be undone
+        pub merged_notes: RefCell<Option<(adw::Toast, MergedNotes)>>,
+        /// The links last updated after a rename while its toast is shown, so it can still be
+        /// undone
+        pub updated_links: RefCell<Option<(adw::Toast, UpdatedLinks)>>,
+        /// The last deleted tag while its toast is shown, so it can still be restored
+        pub deleted_tag: RefCell<Option<(adw::Toast, DeletedTagData)>>,
+        pub tour: OnceCell<TourController>,
+        pub export_site_chooser: OnceCell<gtk::FileChooserNative>,
+        pub import_tag_bundle_chooser: OnceCell<gtk::FileChooserNative>,
+        pub export_writing_stats_chooser: OnceCell<gtk::FileChooserNative>,
+        /// Kept until it is answered, as it is made for the tag being exported
+        pub export_tag_bundle_chooser: RefCell<Option<gtk::FileChooserNative>>,
     }
 
     #[glib::object_subclass]
@@ -67,6 +197,9 @@ mod imp {
             });
 
             klass.install_action("session.sync", None, move |obj, _, _| {
+                // Asked by the user, so try the remote even if it rejected the credentials
+                obj.note_manager().repository().reset_auth_failures();
+
                 spawn!(clone!(@weak obj => async move {
                     if let Err(err) = obj.sync().await {
                         log::error!("Failed to sync: {:?}", err);
@@ -74,12 +207,96 @@ mod imp {
                 }));
             });
 
+            klass.install_action("session.review-changes", None, move |obj, _, _| {
+                obj.show_review_changes_dialog();
+            });
+
             klass.install_action("session.create-note", None, move |obj, _, _| {
-                let note_manager = obj.note_manager();
-                note_manager.create_note();
+                obj.create_note_in_view();
+            });
+
+            klass.install_action("session.show-command-palette", None, move |obj, _, _| {
+                let command_palette = CommandPalette::new(obj);
+                command_palette.set_modal(true);
+                command_palette.set_transient_for(
+                    obj.root()
+                        .map(|w| w.downcast::<gtk::Window>().unwrap())
+                        .as_ref(),
+                );
+                command_palette.present();
+            });
+
+            klass.install_action("session.search-notes", None, move |obj, _, _| {
+                obj.imp().sidebar.start_search();
+            });
+
+            klass.install_action("session.show-graph", None, move |obj, _, _| {
+                let graph_view = GraphView::new(obj);
+                graph_view.set_modal(true);
+                graph_view.set_transient_for(
+                    obj.root()
+                        .map(|w| w.downcast::<gtk::Window>().unwrap())
+                        .as_ref(),
+                );
+                graph_view.present();
+            });
+
+            klass.install_action("session.start-tour", None, move |obj, _, _| {
+                obj.start_tour();
+            });
+
+            klass.install_action("session.export-site", None, move |obj, _, _| {
+                obj.export_site_chooser().show();
+            });
+
+            klass.install_action("session.export-writing-stats", None, move |obj, _, _| {
+                obj.export_writing_stats_chooser().show();
+            });
+
+            klass.install_action("session.find-duplicates", None, move |obj, _, _| {
+                obj.show_duplicates_dialog();
+            });
+
+            klass.install_action("session.manage-attachments", None, move |obj, _, _| {
+                obj.show_attachments_dialog();
+            });
+
+            klass.install_action("session.show-problems", None, move |obj, _, _| {
+                obj.show_problems_dialog();
+            });
+
+            klass.install_action("session.show-links-to-check", None, move |obj, _, _| {
+                obj.show_attention_dialog();
+            });
+
+            klass.install_action("session.import-tag-bundle", None, move |obj, _, _| {
+                if !obj.can_change_notes() {
+                    return;
+                }
+
+                obj.import_tag_bundle_chooser().show();
+            });
+
+            klass.install_action("session.unlock", None, move |obj, _, _| {
+                obj.unlock();
+            });
+
+            klass.install_action("session.set-lock-pin", None, move |obj, _, _| {
+                let lock_pin_dialog = LockPinDialog::new();
+                lock_pin_dialog.set_modal(true);
+                lock_pin_dialog.set_transient_for(
+                    obj.root()
+                        .map(|w| w.downcast::<gtk::Window>().unwrap())
+                        .as_ref(),
+                );
+                lock_pin_dialog.present();
             });
 
             klass.install_action("session.edit-tags", None, move |obj, _, _| {
+                if !obj.can_change_notes() {
+                    return;
+                }
+
                 let tag_list = obj.note_manager().tag_list();
                 let note_list = obj.note_manager().note_list();
 
@@ -94,12 +311,17 @@ mod imp {
             });
 
             klass.install_action("session.edit-selected-note-tags", None, move |obj, _, _| {
+                if !obj.can_change_notes() {
+                    return;
+                }
+
                 let imp = obj.imp();
                 let tag_list = imp.note_manager.get().unwrap().tag_list();
-                let selected_note_tag_list =
-                    imp.sidebar.selected_note().unwrap().metadata().tag_list();
+                let selected_note = imp.sidebar.selected_note().unwrap();
+                let selected_note_tag_list = selected_note.metadata().tag_list();
 
                 let note_tag_dialog = NoteTagDialog::new(&tag_list, vec![selected_note_tag_list]);
+                note_tag_dialog.suggest_tags_for(&selected_note);
                 note_tag_dialog.set_modal(true);
                 note_tag_dialog.set_transient_for(
                     obj.root()
@@ -109,10 +331,30 @@ mod imp {
                 note_tag_dialog.present();
             });
 
+            klass.install_action("session.merge-selected-note", None, move |obj, _, _| {
+                if let Some(note) = obj.selected_note() {
+                    obj.show_merge_note_dialog(&note);
+                }
+            });
+
+            klass.install_action("session.delete-selected-note", None, move |obj, _, _| {
+                if let Some(note) = obj.selected_note() {
+                    obj.show_delete_note_dialog(&note);
+                }
+            });
+
+            klass.install_action("session.empty-trash", None, move |obj, _, _| {
+                obj.show_empty_trash_dialog();
+            });
+
             klass.install_action(
                 "session.edit-multi-selected-note-tags",
                 None,
                 move |obj, _, _| {
+                    if !obj.can_change_notes() {
+                        return;
+                    }
+
                     let imp = obj.imp();
                     let tag_list = imp.note_manager.get().unwrap().tag_list();
                     let other_tag_lists = imp
@@ -132,6 +374,15 @@ mod imp {
                     note_tag_dialog.present();
                 },
             );
+
+            klass.install_action(
+                "session.export-multi-selected-notes",
+                None,
+                move |obj, _, _| {
+                    let notes = obj.imp().sidebar.selected_notes();
+                    obj.show_export_notes_dialog(&notes);
+                },
+            );
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -164,6 +415,13 @@ mod imp {
                         false,
                         glib::ParamFlags::READWRITE,
                     ),
+                    glib::ParamSpecBoolean::new(
+                        "is-locked",
+                        "Is Locked",
+                        "Whether the notes are hidden until unlocked",
+                        false,
+                        glib::ParamFlags::READABLE,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -198,6 +456,7 @@ mod imp {
                 "note-manager" => obj.note_manager().to_value(),
                 "selected-note" => obj.selected_note().to_value(),
                 "is-syncing" => self.is_syncing.get().to_value(),
+                "is-locked" => obj.is_locked().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -206,7 +465,9 @@ mod imp {
             self.parent_constructed(obj);
 
             obj.setup_signals();
+            obj.track_open_note(&self.content.get());
             obj.setup_picture_viewer();
+            obj.setup_lock();
         }
     }
 
@@ -220,13 +481,27 @@ glib::wrapper! {
 }
 
 impl Session {
-    pub async fn new(directory: &gio::File) -> Self {
-        let note_manager = NoteManager::for_directory(directory, false).await;
+    pub fn new(directory: &gio::File) -> Self {
+        let note_manager = NoteManager::for_directory(directory, false);
+        glib::Object::new(&[("note-manager", &note_manager)]).expect("Failed to create Session.")
+    }
+
+    /// For the notes of `repository` that was cloned into `directory`
+    pub fn for_clone(directory: &gio::File, repository: &NoteRepository) -> Self {
+        let note_manager = NoteManager::with_repository(directory, repository, false);
         glib::Object::new(&[("note-manager", &note_manager)]).expect("Failed to create Session.")
     }
 
-    pub async fn new_offline(directory: &gio::File) -> Self {
-        let note_manager = NoteManager::for_directory(directory, true).await;
+    pub fn new_offline(directory: &gio::File) -> Self {
+        let note_manager = NoteManager::for_directory(directory, true);
+        glib::Object::new(&[("note-manager", &note_manager)]).expect("Failed to create Session.")
+    }
+
+    /// For the notes already set up in `directory`, offline or not depending on how they were
+    /// set up
+    pub fn for_existing(directory: &gio::File) -> Self {
+        let is_offline_mode = Application::default().settings().boolean("is-offline-mode");
+        let note_manager = NoteManager::for_directory(directory, is_offline_mode);
         glib::Object::new(&[("note-manager", &note_manager)]).expect("Failed to create Session.")
     }
 
@@ -234,6 +509,21 @@ impl Session {
         self.note_manager().directory().path().unwrap()
     }
 
+    /// Notes that are not in the trash with `query` in their title or content, like the ones
+    /// the sidebar finds, with the most recently edited first
+    pub fn search_notes(&self, query: &str) -> Vec<Note> {
+        let query = NoteQuery::new(NoteScope::All, query);
+
+        let mut notes = self
+            .note_manager()
+            .note_list()
+            .iter()
+            .filter(|note| query.matches(&note.index(), || note.lowercase_content()))
+            .collect::<Vec<_>>();
+        notes.sort_by_key(|note| std::cmp::Reverse(note.metadata().last_modified()));
+        notes
+    }
+
     pub fn selected_note(&self) -> Option<Note> {
         self.imp().selected_note.borrow().clone()
     }
@@ -256,38 +546,492 @@ impl Session {
 
         let imp = self.imp();
 
-        if selected_note.is_some() {
+        if let Some(ref note) = selected_note {
             imp.leaflet.navigate(adw::NavigationDirection::Forward);
+
+            let mut recent_notes = imp.recent_notes.borrow_mut();
+            recent_notes.retain(|recent_note| recent_note != note);
+            recent_notes.insert(0, note.clone());
+            recent_notes.truncate(MAX_RECENT_NOTES);
         }
 
         imp.selected_note.replace(selected_note);
         self.notify("selected-note");
     }
 
+    /// Notes that were selected, starting with the most recent
+    pub fn recent_notes(&self) -> Vec<Note> {
+        self.imp().recent_notes.borrow().clone()
+    }
+
+    /// Select `note` and scroll to the text at the byte `range` of its content, like a match
+    /// of a search
+    pub fn select_note_match(&self, note: Note, range: Range<usize>) {
+        self.set_selected_note(Some(note));
+        self.imp().content.scroll_to_content_range(range);
+    }
+
+    /// Select `note` and scroll to its first heading with `text`, like a link to a section
+    pub fn select_note_heading(&self, note: Note, text: &str) {
+        self.set_selected_note(Some(note));
+        self.imp().content.scroll_to_heading(text);
+    }
+
+    /// Show the notes tagged with `tag` in the sidebar
+    pub fn select_tag(&self, tag: &Tag) {
+        self.imp().sidebar.select_tag(tag);
+    }
+
+    /// Create a note among the notes shown in the sidebar, tagged with their tag, then select
+    /// it and put the cursor in its title. From the trash, it is created among all the notes.
+    pub fn create_note_in_view(&self) {
+        if !self.can_change_notes() {
+            return;
+        }
+
+        let imp = self.imp();
+        let tag = imp.sidebar.prepare_new_note();
+
+        match self.note_manager().create_note_with_tag(tag.as_ref()) {
+            Ok(note) => {
+                // Also shows the content when the leaflet is folded
+                self.set_selected_note(Some(note));
+                imp.sidebar.scroll_to_selected_note();
+                imp.content.focus_title();
+            }
+            Err(err) => {
+                log::error!("Failed to create note: {:?}", err);
+                self.report_read_only(&err);
+            }
+        }
+    }
+
+    /// Create a note titled `title` and select it
+    pub fn create_note_with_title(&self, title: &str) {
+        if !self.can_change_notes() {
+            return;
+        }
+
+        match self.note_manager().create_note() {
+            Ok(note) => {
+                note.metadata().set_title(title);
+                self.set_selected_note(Some(note));
+            }
+            Err(err) => log::error!("Failed to create note `{}`: {:?}", title, err),
+        }
+    }
+
+    /// Select the note stored in `file`. If it is outside the notes directory, it is imported
+    /// first.
+    pub async fn open_file(&self, file: &gio::File) -> anyhow::Result<()> {
+        let note_manager = self.note_manager();
+
+        let note = match note_manager.note_for_file(file) {
+            Some(note) => note,
+            None if file.has_parent(Some(&note_manager.directory())) => {
+                anyhow::bail!("`{}` is not a loaded note", file.uri());
+            }
+            None => note_manager.import_file(file).await?,
+        };
+
+        self.set_selected_note(Some(note));
+
+        Ok(())
+    }
+
     pub fn note_manager(&self) -> &NoteManager {
         self.imp().note_manager.get().unwrap()
     }
 
     pub async fn load(&self) -> anyhow::Result<()> {
         let note_manager = self.note_manager();
-        note_manager.load().await?;
-
         let imp = self.imp();
-        imp.sidebar.set_note_list(&note_manager.note_list());
-        imp.sidebar.set_tag_list(&note_manager.tag_list());
+
+        self.set_loading(true);
+        note_manager
+            .bind_property("load-progress", &imp.sidebar.get(), "load-progress")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+
+        // The note list is set before its notes are loaded, so they are shown as they arrive
+        let note_list_handler_id = note_manager.connect_notify_local(
+            Some("note-list"),
+            clone!(@weak self as obj => move |note_manager, _| {
+                obj.imp().sidebar.set_note_list(&note_manager.note_list());
+            }),
+        );
+        let res = note_manager.load().await;
+        note_manager.disconnect(note_list_handler_id);
+        self.set_loading(false);
+        res?;
+
+        imp.sidebar.set_lists(
+            &note_manager.tag_list(),
+            &note_manager.saved_search_list(),
+            &note_manager.problem_list(),
+        );
+        imp.sidebar.bind_search_index(note_manager);
+        imp.content.bind_word_goal(note_manager);
+        imp.content.bind_derives_title(note_manager);
+        note_manager.note_list().connect_items_changed(
+            clone!(@weak self as obj => move |_, _, n_removed, _| {
+                if n_removed > 0 {
+                    obj.close_removed_notes();
+                }
+            }),
+        );
+        self.load_recent_tags(&note_manager.tag_list());
+        note_manager
+            .bind_property("n-trashed-notes", &imp.sidebar.get(), "n-trashed-notes")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager
+            .bind_property("trash-size", &imp.sidebar.get(), "trash-size")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager.connect_n_trashed_notes_notify(|note_manager| {
+            spawn!(clone!(@weak note_manager => async move {
+                note_manager.refresh_trash_size().await;
+            }));
+        });
+        spawn!(clone!(@weak note_manager => async move {
+            note_manager.refresh_trash_size().await;
+        }));
+        note_manager
+            .bind_property("pending-changes", &imp.sidebar.get(), "pending-changes")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager
+            .bind_property("ahead", &imp.sidebar.get(), "ahead")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager
+            .bind_property("behind", &imp.sidebar.get(), "behind")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        note_manager
+            .repository()
+            .bind_property("sync-state", &imp.sidebar.get(), "is-auth-required")
+            .transform_to(|_, value| {
+                let sync_state: SyncState = value.get().unwrap();
+                Some((sync_state == SyncState::AuthRequired).to_value())
+            })
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+
+        Application::default().reach_startup_milestone(StartupMilestone::NotesVisible);
+
+        // Only once the notes are shown, so they don't wait for git
+        spawn!(clone!(@weak note_manager => async move {
+            match note_manager.connect_repository().await {
+                Ok(()) => {
+                    Application::default()
+                        .reach_startup_milestone(StartupMilestone::RepositoryReady);
+                }
+                Err(err) => {
+                    log::error!("Failed to open the repository: {:?}", err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Sync,
+                        &gettext("Failed to open the history of the notes"),
+                        &err,
+                    ));
+                }
+            }
+        }));
 
         Ok(())
     }
 
+    /// Restore the tags whose notes were shown, without the ones that were deleted since
+    fn load_recent_tags(&self, tag_list: &TagList) {
+        let imp = self.imp();
+
+        let names = Application::default().settings().strv("recent-tags");
+        let mut recent_tags =
+            RecentList::with_items(MAX_RECENT_TAGS, names.iter().map(|name| name.to_string()));
+        let is_reconciled = recent_tags.retain(|name| tag_list.contains_with_name(name));
+        imp.recent_tags.replace(Some(recent_tags));
+
+        if is_reconciled {
+            self.save_recent_tags();
+        }
+        self.update_recent_tags();
+
+        tag_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            obj.update_recent_tags();
+        }));
+        imp.sidebar
+            .connect_selected_tag_notify(clone!(@weak self as obj => move |sidebar| {
+                if let Some(tag) = sidebar.selected_tag() {
+                    obj.push_recent_tag(&tag);
+                }
+            }));
+    }
+
+    fn push_recent_tag(&self, tag: &Tag) {
+        if let Some(ref mut recent_tags) = *self.imp().recent_tags.borrow_mut() {
+            if recent_tags.items().first() == Some(&tag.name()) {
+                return;
+            }
+            recent_tags.push(tag.name());
+        }
+
+        self.save_recent_tags();
+        self.update_recent_tags();
+    }
+
+    fn save_recent_tags(&self) {
+        let names = match *self.imp().recent_tags.borrow() {
+            Some(ref recent_tags) => recent_tags.items().to_vec(),
+            None => return,
+        };
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+        if let Err(err) = Application::default()
+            .settings()
+            .set_strv("recent-tags", &names)
+        {
+            log::warn!("Failed to save recent tags: {:?}", err);
+        }
+    }
+
+    /// Show the recent tags that exist in the sidebar, so it has none when there are no tags
+    fn update_recent_tags(&self) {
+        let imp = self.imp();
+        let tag_list = self.note_manager().tag_list();
+
+        let tags = match *imp.recent_tags.borrow() {
+            Some(ref recent_tags) => recent_tags
+                .items()
+                .iter()
+                .filter_map(|name| tag_list.get_with_name(name))
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+
+        imp.sidebar.set_recent_tags(&tags);
+    }
+
     pub async fn sync(&self) -> anyhow::Result<()> {
-        self.note_manager().sync().await?;
+        self.sync_excluding(&[]).await
+    }
+
+    /// Sync, leaving the files at `excluded_paths`, relative to the notes directory, uncommitted.
+    ///
+    /// When the remote rejects the credentials, the user is asked for a new one, then it is
+    /// synced once more with it.
+    pub async fn sync_excluding(&self, excluded_paths: &[PathBuf]) -> anyhow::Result<()> {
+        let note_manager = self.note_manager();
+        retry_once_on_auth_failure(
+            || note_manager.sync_excluding(excluded_paths),
+            |_| self.ask_remote_credential(),
+        )
+        .await?;
         log::info!("Session synced");
         Ok(())
     }
 
+    /// Ask the user for a new credential for the remote and store it in the keyring. Returns
+    /// whether one was stored, so syncing can be tried again.
+    ///
+    /// It is only asked on the first rejection in a row, so the syncs that are done
+    /// automatically don't ask again and again. Syncing from the sync button asks again.
+    async fn ask_remote_credential(&self) -> bool {
+        let imp = self.imp();
+        let repository = self.note_manager().repository();
+
+        if imp.is_asking_credential.get() || repository.n_auth_failures() > 1 {
+            return false;
+        }
+
+        let remote_url = match repository.remote_url().await {
+            Ok(Some(remote_url)) => remote_url,
+            Ok(None) => return false,
+            Err(err) => {
+                log::error!("Failed to get remote url: {:?}", err);
+                return false;
+            }
+        };
+
+        imp.is_asking_credential.set(true);
+        let secret = self.show_remote_credential_dialog(&remote_url).await;
+        imp.is_asking_credential.set(false);
+
+        let secret = match secret {
+            Some(secret) => secret,
+            None => return false,
+        };
+
+        if let Err(err) = NoteRepository::store_remote_secret(&remote_url, &secret).await {
+            log::error!("Failed to store remote credential: {:?}", err);
+            self.add_toast(&adw::Toast::new(&gettext(
+                "Failed to store the credential in the keyring",
+            )));
+            return false;
+        }
+
+        repository.reset_auth_failures();
+        true
+    }
+
+    /// The secret entered by the user for the remote at `remote_url`, or `None` if cancelled
+    async fn show_remote_credential_dialog(&self, remote_url: &str) -> Option<String> {
+        let kind = CredentialKind::for_url(remote_url);
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Authentication Required"))
+            .secondary_text(&kind.prompt(remote_url))
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Retry"), gtk::ResponseType::Accept)
+            .add_css_class("suggested-action");
+        dialog.set_default_response(gtk::ResponseType::Accept);
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        let entry = gtk::PasswordEntry::builder()
+            .placeholder_text(&kind.entry_placeholder())
+            .show_peek_icon(true)
+            .activates_default(true)
+            .build();
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&entry);
+        dialog.set_response_sensitive(gtk::ResponseType::Accept, false);
+        entry.connect_changed(clone!(@weak dialog => move |entry| {
+            dialog.set_response_sensitive(gtk::ResponseType::Accept, !entry.text().is_empty());
+        }));
+
+        let response = dialog.run_future().await;
+        let secret = entry.text().to_string();
+        dialog.destroy();
+
+        if response != gtk::ResponseType::Accept || secret.is_empty() {
+            return None;
+        }
+
+        Some(secret)
+    }
+
+    /// Save and sync the notes before the window is closed, showing the progress if it takes
+    /// a while. Returns whether the window can be closed, which is not the case when some notes
+    /// failed to be saved and the user chose to keep it open.
+    pub async fn save_before_close(&self) -> bool {
+        // Nothing can be saved, and the changes of the remote can be pulled next time
+        if self.note_manager().is_read_only() {
+            return true;
+        }
+
+        let (dialog, progress_bar) = self.progress_dialog(
+            &gettext("Saving…"),
+            &gettext("The notes are saved before closing"),
+            gtk::ButtonsType::None,
+        );
+        dialog.add_button(&gettext("_Quit Anyway"), gtk::ResponseType::Close);
+
+        let (response_sender, mut responses) = mpsc::unbounded();
+        dialog.connect_response(move |_, response| {
+            let _ = response_sender.unbounded_send(response);
+        });
+
+        let show_source_id = Rc::new(RefCell::new(None));
+        show_source_id.replace(Some(glib::timeout_add_local_once(
+            SAVING_DIALOG_DELAY,
+            clone!(@weak dialog, @weak show_source_id => move || {
+                show_source_id.replace(None);
+                dialog.present();
+            }),
+        )));
+
+        let mut save = Box::pin(async {
+            self.note_manager()
+                .save_all_notes_with_progress(move |fraction| progress_bar.set_fraction(fraction))
+                .await?;
+
+            if let Err(err) = self.sync().await {
+                log::error!("Failed to sync session: {:?}", err);
+            }
+
+            Ok::<_, anyhow::Error>(())
+        });
+
+        let res = loop {
+            match future::select(save, responses.next()).await {
+                Either::Left((res, _)) => break Some(res),
+                Either::Right((Some(gtk::ResponseType::Close), _)) => break None,
+                Either::Right((Some(_), unfinished_save)) => save = unfinished_save,
+                // Closed without choosing to quit, so it still has to finish
+                Either::Right((None, unfinished_save)) => break Some(unfinished_save.await),
+            }
+        };
+
+        if let Some(source_id) = show_source_id.take() {
+            source_id.remove();
+        }
+        dialog.destroy();
+
+        match res {
+            Some(Ok(())) => true,
+            Some(Err(err)) => self.show_save_before_close_error(&err).await,
+            None => {
+                log::warn!("Quitting before the notes are saved");
+                true
+            }
+        }
+    }
+
+    /// Let the user choose whether to close even if the notes failed to be saved
+    async fn show_save_before_close_error(&self, err: &anyhow::Error) -> bool {
+        log::error!("Failed to save notes before closing: {:?}", err);
+
+        let secondary_text = match err.downcast_ref::<NotesNotSaved>() {
+            Some(not_saved) => not_saved
+                .titles
+                .iter()
+                .map(|title| format!("• {}", title))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => err.to_string(),
+        };
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Some Notes Could Not Be Saved"))
+            .secondary_text(&secondary_text)
+            .message_type(gtk::MessageType::Error)
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Keep Open"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Quit Anyway"), gtk::ResponseType::Close)
+            .add_css_class("destructive-action");
+        dialog.set_default_response(gtk::ResponseType::Cancel);
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        let response = dialog.run_future().await;
+        dialog.destroy();
+
+        response == gtk::ResponseType::Close
+    }
+
     pub fn show_attachment(&self, attachment: Attachment) {
         let imp = self.imp();
 
+        if self.is_locked() {
+            return;
+        }
+
         match attachment.file_type() {
             FileType::Bitmap => {
                 imp.picture_viewer.set_attachment(Some(attachment));
@@ -299,33 +1043,1838 @@ impl Session {
         }
     }
 
-    fn set_note_manager(&self, note_manager: NoteManager) {
-        self.imp().note_manager.set(note_manager).unwrap();
+    /// Let the user choose a note to merge `note` into
+    pub fn show_merge_note_dialog(&self, note: &Note) {
+        if self.is_locked() || !self.can_change_notes() {
+            return;
+        }
+
+        let merge_note_dialog = MergeNoteDialog::new(self, note);
+        merge_note_dialog.set_modal(true);
+        merge_note_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        merge_note_dialog.present();
     }
 
-    fn setup_signals(&self) {
-        self.note_manager()
-            .bind_property("is-syncing", self, "is-syncing")
-            .flags(glib::BindingFlags::SYNC_CREATE)
-            .build();
+    /// Let the user choose how `notes` are exported as one document
+    pub fn show_export_notes_dialog(&self, notes: &[Note]) {
+        if self.is_locked() || notes.is_empty() {
+            return;
+        }
 
-        self.imp().leaflet.connect_child_transition_running_notify(
-            clone!(@weak self as obj => move |leaflet| {
-                // Only deselect the note when the content is fully hidden
-                let is_sidebar_visible = leaflet.visible_child().unwrap() == obj.imp().sidebar.get();
-                if !leaflet.is_child_transition_running() && is_sidebar_visible  {
-                    obj.set_selected_note(None);
-                }
-            }),
+        let export_notes_dialog = ExportNotesDialog::new(self, notes);
+        export_notes_dialog.set_modal(true);
+        export_notes_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
         );
+        export_notes_dialog.present();
     }
 
-    fn setup_picture_viewer(&self) {
-        self.connect_root_notify(|obj| {
-            if let Some(window) = obj
-                .root()
-                .and_then(|root| root.downcast::<gtk::Window>().ok())
-            {
+    /// Write `notes` in their order to `file` as one document, showing the progress until it
+    /// is done
+    pub fn export_combined_notes(
+        &self,
+        file: &gio::File,
+        notes: Vec<CombinedNote>,
+        format: CombinedFormat,
+        include_metadata: bool,
+        image_embedding: ImageEmbedding,
+    ) {
+        let (dialog, progress_bar) = self.progress_dialog(
+            &gettext("Exporting Notes"),
+            &file.parse_name(),
+            gtk::ButtonsType::None,
+        );
+        dialog.present();
+
+        // Laying out the pages takes about as long as putting the notes together
+        let combine_share = match format {
+            CombinedFormat::Markdown | CombinedFormat::Html => 1.0,
+            CombinedFormat::Pdf => 0.5,
+        };
+        let notes_dir = self.directory();
+
+        spawn!(clone!(@weak self as obj, @strong file => async move {
+            let res = async {
+                let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+                receiver.attach(
+                    None,
+                    clone!(@weak progress_bar => @default-return glib::Continue(false), move |fraction: f64| {
+                        progress_bar.set_fraction(fraction * combine_share);
+                        glib::Continue(true)
+                    }),
+                );
+
+                let n_notes = notes.len();
+                let document = spawn_blocking!(move || {
+                    combine_notes(&notes, include_metadata, |n_done| {
+                        // The receiver is only dropped along with the main context
+                        let _ = sender.send(n_done as f64 / n_notes as f64);
+                    })
+                })
+                .await;
+
+                let missing_images = match format {
+                    CombinedFormat::Markdown => {
+                        file.replace_contents_future(
+                            document.into_bytes(),
+                            None,
+                            false,
+                            gio::FileCreateFlags::NONE,
+                        )
+                        .await
+                        .map_err(|(_, err)| err)?;
+                        Vec::new()
+                    }
+                    CombinedFormat::Html => {
+                        let title = file.basename().map_or_else(String::new, |name| {
+                            name.file_stem().unwrap_or_default().to_string_lossy().to_string()
+                        });
+                        let output_dir = file.parent().and_then(|parent| parent.path());
+
+                        let exported = spawn_blocking!(move || {
+                            let body = markdown_to_html(&document);
+                            let exported = embed_images(&body, &notes_dir, &image_embedding);
+                            copy_exported_images(&exported.copies, output_dir.as_deref())?;
+                            Ok::<_, anyhow::Error>(exported)
+                        })
+                        .await?;
+
+                        file.replace_contents_future(
+                            html_document(&title, &exported.html).into_bytes(),
+                            None,
+                            false,
+                            gio::FileCreateFlags::NONE,
+                        )
+                        .await
+                        .map_err(|(_, err)| err)?;
+                        exported.missing_images
+                    }
+                    CombinedFormat::Pdf => {
+                        let (document, images) = spawn_blocking!(move || {
+                            let images = print_layout::load_images(&document, &notes_dir);
+                            (document, images)
+                        })
+                        .await;
+                        let missing_images = images
+                            .iter()
+                            .filter_map(|image| image.missing_source().map(str::to_string))
+                            .collect::<Vec<_>>();
+
+                        print_layout::export_pdf(
+                            &document,
+                            images,
+                            &file,
+                            clone!(@weak progress_bar => move |fraction| {
+                                progress_bar.set_fraction(
+                                    combine_share + fraction * (1.0 - combine_share),
+                                );
+                            }),
+                        )
+                        .await?;
+                        missing_images
+                    }
+                };
+
+                Ok::<_, anyhow::Error>(missing_images)
+            }
+            .await;
+            dialog.destroy();
+
+            match res {
+                Ok(missing_images) if !missing_images.is_empty() => {
+                    log::warn!(
+                        "Exported notes to `{}` with {} missing images",
+                        file.uri(),
+                        missing_images.len()
+                    );
+                    obj.show_report(
+                        &gettext("Notes Exported"),
+                        &missing_image_lines(&missing_images),
+                    );
+                }
+                Ok(_) => {
+                    log::info!("Exported notes to `{}`", file.uri());
+                    obj.add_toast(&adw::Toast::new(&gettext("Notes exported")));
+                }
+                Err(err) => {
+                    log::error!("Failed to export notes to `{}`: {:?}", file.uri(), err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to export notes"),
+                        &err,
+                    ));
+                }
+            }
+        }));
+    }
+
+    /// Create a copy of `note` with its content and tags, and select it
+    pub fn duplicate_note(&self, note: &Note) {
+        if !self.can_change_notes() {
+            return;
+        }
+
+        let metadata = note.metadata();
+        let title = metadata.title();
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+
+        let res = self.note_manager().create_note_with_content(
+            &if title.is_empty() {
+                title
+            } else {
+                gettext!("{} (Copy)", title)
+            },
+            &buffer.text(&start_iter, &end_iter, true),
+            &metadata.tag_list().names(),
+        );
+
+        match res {
+            Ok(copy) => self.set_selected_note(Some(copy)),
+            Err(err) => log::error!("Failed to duplicate `{}`: {:?}", note, err),
+        }
+    }
+
+    /// Let the user choose which of the duplicate notes to keep
+    pub fn show_duplicates_dialog(&self) {
+        if self.is_locked() || !self.can_change_notes() {
+            return;
+        }
+
+        let duplicates_dialog = DuplicatesDialog::new(self);
+        duplicates_dialog.set_modal(true);
+        duplicates_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        duplicates_dialog.present();
+    }
+
+    /// Let the user browse the attachments of the notes and delete the ones no note uses
+    pub fn show_attachments_dialog(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let attachments_dialog = AttachmentsDialog::new(self);
+        attachments_dialog.set_modal(true);
+        attachments_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        attachments_dialog.present();
+    }
+
+    /// Let the user fix, retry, or exclude the files that failed to load as notes
+    pub fn show_problems_dialog(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let problems_dialog = ProblemsDialog::new(self);
+        problems_dialog.set_modal(true);
+        problems_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        problems_dialog.present();
+    }
+
+    /// Let the user check the notes whose links were not updated when a note was renamed
+    pub fn show_attention_dialog(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let attention_dialog = AttentionDialog::new(self);
+        attention_dialog.set_modal(true);
+        attention_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        attention_dialog.present();
+    }
+
+    /// Let the user review the changes that are not synced yet before syncing them
+    pub fn show_review_changes_dialog(&self) {
+        if self.is_locked() || !self.can_change_notes() {
+            return;
+        }
+
+        let review_changes_dialog = ReviewChangesDialog::new(self);
+        review_changes_dialog.set_modal(true);
+        review_changes_dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        review_changes_dialog.present();
+    }
+
+    /// Let the user see how much space the notes take and free some of it
+    pub fn show_maintenance_dialog(&self, transient_for: &impl IsA<gtk::Window>) {
+        if self.is_locked() {
+            return;
+        }
+
+        let maintenance_dialog = MaintenanceDialog::new(self);
+        maintenance_dialog.set_modal(true);
+        maintenance_dialog.set_transient_for(Some(transient_for));
+        maintenance_dialog.present();
+    }
+
+    /// Delete the notes in the trash permanently, the way the preferences say, and commit it.
+    /// Returns how many were deleted.
+    pub async fn empty_trash(&self) -> anyhow::Result<usize> {
+        let imp = self.imp();
+
+        imp.is_removing_notes.set(true);
+        let res = self.note_manager().empty_trash(deletion_mode()).await;
+        imp.is_removing_notes.set(false);
+
+        if let Err(err) = self.sync().await {
+            log::error!("Failed to sync emptied trash: {:?}", err);
+        }
+
+        res
+    }
+
+    /// Ask before emptying the trash, telling how many notes are in it and how much space
+    /// deleting them frees
+    pub fn show_empty_trash_dialog(&self) {
+        // Deleting files would conflict with a git operation that may be merging them
+        if self.is_locked()
+            || self.note_manager().repository().is_busy()
+            || !self.can_change_notes()
+        {
+            return;
+        }
+
+        spawn!(clone!(@weak self as obj => async move {
+            let note_manager = obj.note_manager();
+            note_manager.refresh_trash_size().await;
+
+            let n_trashed_notes = note_manager.n_trashed_notes();
+            if n_trashed_notes == 0 {
+                return;
+            }
+            let trash_size = note_manager.trash_size();
+
+            let dialog = gtk::MessageDialog::builder()
+                .text(&gettext("Empty Trash?"))
+                .secondary_text(&ngettext!(
+                    "The {} note in the trash will be deleted permanently, freeing {}.",
+                    "The {} notes in the trash will be deleted permanently, freeing {}.",
+                    n_trashed_notes,
+                    n_trashed_notes,
+                    glib::format_size(trash_size)
+                ))
+                .message_type(gtk::MessageType::Question)
+                .modal(true)
+                .build();
+            dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+            dialog
+                .add_button(&gettext("_Empty Trash"), gtk::ResponseType::Accept)
+                .add_css_class("destructive-action");
+            dialog.set_transient_for(
+                obj.root()
+                    .map(|w| w.downcast::<gtk::Window>().unwrap())
+                    .as_ref(),
+            );
+
+            dialog.connect_response(clone!(@weak obj => move |dialog, response| {
+                dialog.destroy();
+
+                if response != gtk::ResponseType::Accept {
+                    return;
+                }
+
+                spawn!(clone!(@weak obj => async move {
+                    match obj.empty_trash().await {
+                        Ok(n_deleted) => {
+                            obj.add_toast(&adw::Toast::new(&ngettext!(
+                                "Deleted {} note, freeing {}",
+                                "Deleted {} notes, freeing {}",
+                                n_deleted as u32,
+                                n_deleted,
+                                glib::format_size(trash_size)
+                            )));
+                        }
+                        Err(err) => {
+                            log::error!("Failed to empty trash: {:?}", err);
+                            if !obj.report_read_only(&err) {
+                                obj.add_toast(&adw::Toast::new(&gettext(
+                                    "Failed to delete some of the notes in the trash",
+                                )));
+                            }
+                        }
+                    }
+                }));
+            }));
+            dialog.present();
+        }));
+    }
+
+    /// Delete the attachment `files`, the way the preferences say, and commit it as one change
+    pub async fn delete_attachments(&self, files: &[gio::File]) -> anyhow::Result<()> {
+        let res = self
+            .note_manager()
+            .delete_attachments(files, deletion_mode())
+            .await;
+
+        if let Err(err) = self.sync().await {
+            log::error!("Failed to sync deleted attachments: {:?}", err);
+        }
+
+        res
+    }
+
+    /// Write every note again in the front matter profile of the notebook settings, and
+    /// commit it as one change. Returns how many notes were written.
+    pub async fn rewrite_front_matter(&self) -> anyhow::Result<usize> {
+        let n_notes = self.note_manager().rewrite_front_matter().await?;
+
+        if let Err(err) = self.sync().await {
+            log::error!("Failed to sync rewritten front matter: {:?}", err);
+        }
+
+        Ok(n_notes)
+    }
+
+    /// Take the title of every note from its first heading and commit it. Returns how many
+    /// titles were changed.
+    pub async fn derive_titles_from_headings(&self) -> anyhow::Result<usize> {
+        let n_changed = self.note_manager().derive_titles_from_headings().await?;
+
+        if n_changed > 0 {
+            if let Err(err) = self.sync().await {
+                log::error!("Failed to sync titles taken from headings: {:?}", err);
+            }
+        }
+
+        Ok(n_changed)
+    }
+
+    /// Ask before deleting `note` permanently, telling whether it can be restored from the
+    /// Trash of the computer
+    pub fn show_delete_note_dialog(&self, note: &Note) {
+        if self.is_locked()
+            || self.note_manager().repository().is_busy()
+            || !self.can_change_notes()
+        {
+            return;
+        }
+
+        let is_delete_immediately = Application::default()
+            .settings()
+            .boolean("delete-immediately");
+
+        // Files are only deleted when trashing fails if the dialog said so
+        let (mode, secondary_text) = if is_delete_immediately {
+            (
+                DeletionMode::Delete,
+                gettext("The note and its attachments will be deleted right away and can't be restored."),
+            )
+        } else {
+            (
+                DeletionMode::TrashOrDelete,
+                gettext("The note and its attachments will be moved to the Trash of this computer. Where there is no Trash, they will be deleted right away instead."),
+            )
+        };
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Delete Note Permanently?"))
+            .secondary_text(&secondary_text)
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Delete"), gtk::ResponseType::Accept)
+            .add_css_class("destructive-action");
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        dialog.connect_response(
+            clone!(@weak self as obj, @weak note => move |dialog, response| {
+                dialog.destroy();
+
+                if response == gtk::ResponseType::Accept {
+                    obj.delete_note(&note, mode);
+                }
+            }),
+        );
+        dialog.present();
+    }
+
+    /// Delete `note` permanently and commit it
+    fn delete_note(&self, note: &Note, mode: DeletionMode) {
+        spawn!(clone!(@weak self as obj, @weak note => async move {
+            obj.imp().is_removing_notes.set(true);
+            let res = obj.note_manager().delete_note(&note, mode).await;
+            obj.imp().is_removing_notes.set(false);
+
+            if let Err(err) = res {
+                log::error!("Failed to delete `{}`: {:?}", note, err);
+                if obj.report_read_only(&err) {
+                    return;
+                }
+                Application::default().error_log().push(ErrorReport::from_error(
+                    ErrorSource::Notes,
+                    &gettext("Failed to delete note"),
+                    &err,
+                ));
+                return;
+            }
+
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync deleted note: {:?}", err);
+            }
+        }));
+    }
+
+    /// Write `note` to its file as it is now, like to recreate the file after it was deleted
+    /// elsewhere or to retry a save that failed
+    pub fn write_note_file(&self, note: &Note) {
+        if !self.can_change_notes() {
+            return;
+        }
+
+        spawn!(clone!(@weak self as obj, @weak note => async move {
+            if let Err(err) = obj.note_manager().write_note_file(&note).await {
+                log::error!("Failed to write `{}`: {:?}", note, err);
+                if obj.report_read_only(&err) {
+                    return;
+                }
+                Application::default().error_log().push(ErrorReport::from_error(
+                    ErrorSource::Notes,
+                    &gettext("Failed to save note"),
+                    &err,
+                ));
+            }
+        }));
+    }
+
+    /// Ask before dropping `note`, whose file was deleted elsewhere, as its text would be lost
+    pub fn show_discard_note_dialog(&self, note: &Note) {
+        if self.is_locked() || !self.can_change_notes() {
+            return;
+        }
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Discard Note?"))
+            .secondary_text(&gettext(
+                "The file of the note was deleted elsewhere, so its text will be lost.",
+            ))
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog
+            .add_button(&gettext("_Discard"), gtk::ResponseType::Accept)
+            .add_css_class("destructive-action");
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        dialog.connect_response(
+            clone!(@weak self as obj, @weak note => move |dialog, response| {
+                dialog.destroy();
+
+                if response != gtk::ResponseType::Accept {
+                    return;
+                }
+
+                obj.imp().is_removing_notes.set(true);
+                let res = obj.note_manager().discard_note(&note);
+                obj.imp().is_removing_notes.set(false);
+
+                if let Err(err) = res {
+                    log::error!("Failed to discard `{}`: {:?}", note, err);
+                    obj.report_read_only(&err);
+                }
+            }),
+        );
+        dialog.present();
+    }
+
+    /// Open the folder containing the file of `note`, with the file highlighted where the file
+    /// manager can
+    pub fn show_note_in_folder(&self, note: &Note) {
+        if self.is_locked() {
+            return;
+        }
+
+        spawn!(clone!(@weak self as obj, @weak note => async move {
+            let res = file_manager::show_in_folder(&DesktopFileManager, &note.file()).await;
+
+            if let Err(err) = res {
+                log::error!("Failed to show `{}` in its folder: {:?}", note, err);
+                obj.add_toast(&adw::Toast::new(&gettext("Failed to open containing folder")));
+            }
+        }));
+    }
+
+    /// Copy the path of the file of `note`, as it is on the computer even when sandboxed
+    pub fn copy_note_path(&self, note: &Note) {
+        if self.is_locked() {
+            return;
+        }
+
+        spawn!(clone!(@weak self as obj, @weak note => async move {
+            let path = match file_manager::host_path(&note.file()).await {
+                Some(path) => path,
+                None => {
+                    log::warn!("`{}` has no path to copy", note);
+                    return;
+                }
+            };
+
+            obj.clipboard().set_text(&path.display().to_string());
+            obj.add_toast(&adw::Toast::new(&gettext("Copied file path")));
+        }));
+    }
+
+    /// Copy the URI of `note`, which is pasted in other notes as a link to it, along with a
+    /// Markdown link to it for other apps
+    pub fn copy_note_link(&self, note: &Note) {
+        if self.is_locked() {
+            return;
+        }
+
+        let uri = note.id().uri();
+        let markdown_link = note_uri_markdown_link(&note.metadata().title(), &uri);
+        let provider = gdk::ContentProvider::new_union(&[
+            gdk::ContentProvider::for_bytes(
+                "text/uri-list",
+                &glib::Bytes::from(format!("{}\r\n", uri).as_bytes()),
+            ),
+            gdk::ContentProvider::for_value(&markdown_link.to_value()),
+        ]);
+
+        if let Err(err) = self.clipboard().set_content(Some(&provider)) {
+            log::error!("Failed to copy link to `{}`: {:?}", note, err);
+            return;
+        }
+
+        self.add_toast(&adw::Toast::new(&gettext("Copied link to note")));
+    }
+
+    /// Import the Obsidian vault at `folder` and commit it, then show what was imported
+    pub fn import_vault(&self, folder: &gio::File, options: VaultImportOptions) {
+        if !self.can_change_notes() {
+            return;
+        }
+
+        spawn!(clone!(@weak self as obj, @strong folder => async move {
+            match obj.note_manager().import_vault(&folder, options).await {
+                Ok(report) => obj.show_vault_import_report(&report),
+                Err(err) => {
+                    log::error!("Failed to import vault `{}`: {:?}", folder.uri(), err);
+                    if obj.report_read_only(&err) {
+                        return;
+                    }
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to import vault"),
+                        &err,
+                    ));
+                }
+            }
+
+            // Even when it failed, the notes imported before are kept
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync imported vault: {:?}", err);
+            }
+        }));
+    }
+
+    fn show_vault_import_report(&self, report: &VaultImportReport) {
+        let mut lines = vec![
+            ngettext!(
+                "{} note imported",
+                "{} notes imported",
+                report.n_notes as u32,
+                report.n_notes
+            ),
+            ngettext!(
+                "{} attachment copied",
+                "{} attachments copied",
+                report.n_attachments as u32,
+                report.n_attachments
+            ),
+        ];
+
+        if !report.created_tags.is_empty() {
+            lines.push(ngettext!(
+                "{} tag created: {}",
+                "{} tags created: {}",
+                report.created_tags.len() as u32,
+                report.created_tags.len(),
+                report.created_tags.join(", ")
+            ));
+        }
+
+        lines.extend(unresolved_link_lines(&report.unresolved_links));
+
+        self.show_report(&gettext("Vault Imported"), &lines);
+    }
+
+    fn export_site_chooser(&self) -> &gtk::FileChooserNative {
+        self.imp().export_site_chooser.get_or_init(|| {
+            let chooser = gtk::FileChooserNative::builder()
+                .accept_label(&gettext("Export"))
+                .cancel_label(&gettext("Cancel"))
+                .title(&gettext("Select Website Folder"))
+                .action(gtk::FileChooserAction::SelectFolder)
+                .modal(true)
+                .build();
+
+            chooser.set_transient_for(
+                self.root()
+                    .map(|w| w.downcast::<gtk::Window>().unwrap())
+                    .as_ref(),
+            );
+
+            chooser.connect_response(clone!(@weak self as obj => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(folder) = chooser.file() {
+                        obj.export_site(&folder, obj.imp().sidebar.selected_tag());
+                    }
+                }
+            }));
+
+            chooser
+        })
+    }
+
+    fn export_writing_stats_chooser(&self) -> &gtk::FileChooserNative {
+        self.imp().export_writing_stats_chooser.get_or_init(|| {
+            let chooser = gtk::FileChooserNative::builder()
+                .accept_label(&gettext("Export"))
+                .cancel_label(&gettext("Cancel"))
+                .title(&gettext("Export Writing Stats"))
+                .action(gtk::FileChooserAction::Save)
+                .modal(true)
+                .build();
+
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some(&gettext("CSV Files")));
+            filter.add_mime_type("text/csv");
+            filter.add_pattern("*.csv");
+            chooser.add_filter(&filter);
+            chooser.set_current_name("writing-stats.csv");
+
+            chooser.set_transient_for(
+                self.root()
+                    .map(|w| w.downcast::<gtk::Window>().unwrap())
+                    .as_ref(),
+            );
+
+            chooser.connect_response(clone!(@weak self as obj => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(file) = chooser.file() {
+                        obj.export_writing_stats(&file);
+                    }
+                }
+            }));
+
+            chooser
+        })
+    }
+
+    /// Write the words written on each day to `file`, as CSV
+    fn export_writing_stats(&self, file: &gio::File) {
+        let csv = self.note_manager().daily_word_counts().to_csv();
+
+        spawn!(clone!(@weak self as obj, @strong file => async move {
+            let res = file
+                .replace_contents_future(csv.into_bytes(), None, false, gio::FileCreateFlags::NONE)
+                .await;
+
+            match res {
+                Ok(_) => obj.add_toast(&adw::Toast::new(&gettext("Writing stats exported"))),
+                Err((_, err)) => {
+                    log::error!("Failed to export writing stats to `{}`: {:?}", file.uri(), err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to export writing stats"),
+                        &anyhow::Error::from(err),
+                    ));
+                }
+            }
+        }));
+    }
+
+    /// Write the notes, or only those with `tag`, as a website in `folder`, showing the
+    /// progress until it is done or cancelled
+    fn export_site(&self, folder: &gio::File, tag: Option<Tag>) {
+        let (dialog, progress_bar) = self.progress_dialog(
+            &gettext("Exporting Website"),
+            &folder.parse_name(),
+            gtk::ButtonsType::Cancel,
+        );
+
+        let cancellable = gio::Cancellable::new();
+        dialog.connect_response(clone!(@weak cancellable => move |_, _| {
+            cancellable.cancel();
+        }));
+        dialog.present();
+
+        spawn!(clone!(@weak self as obj, @strong folder => async move {
+            let res = obj
+                .note_manager()
+                .export_site(
+                    &folder,
+                    tag.as_ref(),
+                    move |fraction| progress_bar.set_fraction(fraction),
+                    &cancellable,
+                )
+                .await;
+            dialog.destroy();
+
+            match res {
+                Ok(report) => obj.show_site_export_report(&report),
+                Err(err) if err.is::<SiteExportCancelled>() => {
+                    log::info!("Cancelled exporting website to `{}`", folder.uri());
+                }
+                Err(err) => {
+                    log::error!("Failed to export website to `{}`: {:?}", folder.uri(), err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to export website"),
+                        &err,
+                    ));
+                }
+            }
+        }));
+    }
+
+    fn show_site_export_report(&self, report: &SiteExportReport) {
+        let mut lines = vec![
+            ngettext!(
+                "{} page written",
+                "{} pages written",
+                report.n_pages as u32,
+                report.n_pages
+            ),
+            ngettext!(
+                "{} attachment copied",
+                "{} attachments copied",
+                report.n_attachments as u32,
+                report.n_attachments
+            ),
+        ];
+        lines.extend(unresolved_link_lines(&report.invalid_links));
+
+        self.show_report(&gettext("Website Exported"), &lines);
+    }
+
+    /// Export the notes with `tag` as a bundle, to a file chosen by the user
+    pub fn export_tag_bundle(&self, tag: &Tag) {
+        if self.is_locked() {
+            return;
+        }
+
+        let chooser = gtk::FileChooserNative::builder()
+            .accept_label(&gettext("Export"))
+            .cancel_label(&gettext("Cancel"))
+            .title(&gettext("Export Tag"))
+            .action(gtk::FileChooserAction::Save)
+            .modal(true)
+            .build();
+        chooser.add_filter(&tag_bundle_filter());
+        chooser.set_current_name(&format!("{}.{}", tag.name(), BUNDLE_EXTENSION));
+        chooser.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        chooser.connect_response(
+            clone!(@weak self as obj, @weak tag => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(file) = chooser.file() {
+                        obj.write_tag_bundle(&tag, &file);
+                    }
+                }
+
+                obj.imp().export_tag_bundle_chooser.replace(None);
+            }),
+        );
+        chooser.show();
+
+        self.imp().export_tag_bundle_chooser.replace(Some(chooser));
+    }
+
+    fn write_tag_bundle(&self, tag: &Tag, file: &gio::File) {
+        let (dialog, progress_bar) = self.progress_dialog(
+            &gettext("Exporting Tag"),
+            &file.parse_name(),
+            gtk::ButtonsType::None,
+        );
+        dialog.present();
+
+        spawn!(
+            clone!(@weak self as obj, @strong tag, @strong file => async move {
+                let res = obj
+                    .note_manager()
+                    .export_tag_bundle(&tag, &file, move |fraction| progress_bar.set_fraction(fraction))
+                    .await;
+                dialog.destroy();
+
+                match res {
+                    Ok(written_bundle) => obj.show_tag_bundle_export_report(&written_bundle),
+                    Err(err) => {
+                        log::error!("Failed to export `{}` to `{}`: {:?}", tag.name(), file.uri(), err);
+                        Application::default().error_log().push(ErrorReport::from_error(
+                            ErrorSource::Notes,
+                            &gettext("Failed to export tag"),
+                            &err,
+                        ));
+                    }
+                }
+            })
+        );
+    }
+
+    fn show_tag_bundle_export_report(&self, written_bundle: &WrittenBundle) {
+        let mut lines = vec![
+            ngettext!(
+                "{} note exported",
+                "{} notes exported",
+                written_bundle.n_notes as u32,
+                written_bundle.n_notes
+            ),
+            ngettext!(
+                "{} attachment copied",
+                "{} attachments copied",
+                written_bundle.n_attachments as u32,
+                written_bundle.n_attachments
+            ),
+        ];
+
+        let n_missing = written_bundle.missing_attachments.len();
+        if n_missing > 0 {
+            lines.push(ngettext!(
+                "{} attachment left out, as its file is missing",
+                "{} attachments left out, as their files are missing",
+                n_missing as u32,
+                n_missing
+            ));
+        }
+
+        self.show_report(&gettext("Tag Exported"), &lines);
+    }
+
+    fn import_tag_bundle_chooser(&self) -> &gtk::FileChooserNative {
+        self.imp().import_tag_bundle_chooser.get_or_init(|| {
+            let chooser = gtk::FileChooserNative::builder()
+                .accept_label(&gettext("Import"))
+                .cancel_label(&gettext("Cancel"))
+                .title(&gettext("Select Tag Bundle"))
+                .action(gtk::FileChooserAction::Open)
+                .modal(true)
+                .build();
+            chooser.add_filter(&tag_bundle_filter());
+
+            chooser.set_transient_for(
+                self.root()
+                    .map(|w| w.downcast::<gtk::Window>().unwrap())
+                    .as_ref(),
+            );
+
+            chooser.connect_response(clone!(@weak self as obj => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(file) = chooser.file() {
+                        obj.read_tag_bundle(&file);
+                    }
+                }
+            }));
+
+            chooser
+        })
+    }
+
+    /// Read the bundle in `file`, and import it once the user chose what to do with the notes
+    /// that are already in the notebook
+    fn read_tag_bundle(&self, file: &gio::File) {
+        spawn!(clone!(@weak self as obj, @strong file => async move {
+            let bundle = match obj.note_manager().read_tag_bundle(&file).await {
+                Ok(bundle) => bundle,
+                Err(err) => {
+                    log::error!("Failed to read tag bundle `{}`: {:?}", file.uri(), err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to read tag bundle"),
+                        &err,
+                    ));
+                    return;
+                }
+            };
+
+            let n_existing = obj.note_manager().n_existing_bundle_notes(&bundle);
+
+            if n_existing == 0 {
+                obj.import_tag_bundle(bundle, CollisionPolicy::Skip);
+            } else {
+                obj.show_collision_policy_dialog(bundle, n_existing);
+            }
+        }));
+    }
+
+    fn show_collision_policy_dialog(&self, bundle: TagBundle, n_existing: usize) {
+        let dialog = gtk::MessageDialog::builder()
+            .text(&ngettext(
+                "Note Already Exists",
+                "Notes Already Exist",
+                n_existing as u32,
+            ))
+            .secondary_text(&ngettext!(
+                "{} note of the bundle is already in this notebook. It can be skipped, or imported as a copy next to the one already there.",
+                "{} notes of the bundle are already in this notebook. They can be skipped, or imported as copies next to the ones already there.",
+                n_existing as u32,
+                n_existing
+            ))
+            .message_type(gtk::MessageType::Question)
+            .modal(true)
+            .build();
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog.add_button(&gettext("_Duplicate"), gtk::ResponseType::No);
+        dialog
+            .add_button(&gettext("_Skip"), gtk::ResponseType::Yes)
+            .add_css_class("suggested-action");
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        // Only answered once, so the bundle is only taken once
+        let bundle = RefCell::new(Some(bundle));
+        dialog.connect_response(clone!(@weak self as obj => move |dialog, response| {
+            dialog.destroy();
+
+            let policy = match response {
+                gtk::ResponseType::Yes => CollisionPolicy::Skip,
+                gtk::ResponseType::No => CollisionPolicy::Duplicate,
+                _ => return,
+            };
+
+            if let Some(bundle) = bundle.take() {
+                obj.import_tag_bundle(bundle, policy);
+            }
+        }));
+        dialog.present();
+    }
+
+    /// Import `bundle` and commit it, then show what was imported
+    fn import_tag_bundle(&self, bundle: TagBundle, policy: CollisionPolicy) {
+        let (dialog, progress_bar) = self.progress_dialog(
+            &gettext("Importing Tag Bundle"),
+            &bundle.manifest.tags.join(", "),
+            gtk::ButtonsType::None,
+        );
+        dialog.present();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let res = obj
+                .note_manager()
+                .import_tag_bundle(bundle, policy, move |fraction| {
+                    progress_bar.set_fraction(fraction);
+                })
+                .await;
+            dialog.destroy();
+
+            match res {
+                Ok(report) => obj.show_tag_bundle_import_report(&report),
+                Err(err) => {
+                    log::error!("Failed to import tag bundle: {:?}", err);
+                    Application::default().error_log().push(ErrorReport::from_error(
+                        ErrorSource::Notes,
+                        &gettext("Failed to import tag bundle"),
+                        &err,
+                    ));
+                }
+            }
+
+            // Even when it failed, the notes imported before are kept
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync imported tag bundle: {:?}", err);
+            }
+        }));
+    }
+
+    fn show_tag_bundle_import_report(&self, report: &TagBundleImportReport) {
+        let mut lines = vec![
+            ngettext!(
+                "{} note imported",
+                "{} notes imported",
+                report.n_notes as u32,
+                report.n_notes
+            ),
+            ngettext!(
+                "{} attachment copied",
+                "{} attachments copied",
+                report.n_attachments as u32,
+                report.n_attachments
+            ),
+        ];
+
+        if !report.skipped_notes.is_empty() {
+            lines.push(ngettext!(
+                "{} note skipped, as it is already in this notebook",
+                "{} notes skipped, as they are already in this notebook",
+                report.skipped_notes.len() as u32,
+                report.skipped_notes.len()
+            ));
+        }
+
+        if !report.created_tags.is_empty() {
+            lines.push(ngettext!(
+                "{} tag created: {}",
+                "{} tags created: {}",
+                report.created_tags.len() as u32,
+                report.created_tags.len(),
+                report.created_tags.join(", ")
+            ));
+        }
+
+        self.show_report(&gettext("Tag Bundle Imported"), &lines);
+    }
+
+    /// A modal dialog with a progress bar below `secondary_text`, which has to be presented
+    fn progress_dialog(
+        &self,
+        title: &str,
+        secondary_text: &str,
+        buttons: gtk::ButtonsType,
+    ) -> (gtk::MessageDialog, gtk::ProgressBar) {
+        let progress_bar = gtk::ProgressBar::new();
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(title)
+            .secondary_text(secondary_text)
+            .buttons(buttons)
+            .modal(true)
+            .build();
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&progress_bar);
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+
+        (dialog, progress_bar)
+    }
+
+    fn show_report(&self, title: &str, lines: &[String]) {
+        let dialog = gtk::MessageDialog::builder()
+            .text(title)
+            .secondary_text(&lines.join("\n"))
+            .buttons(gtk::ButtonsType::Ok)
+            .message_type(gtk::MessageType::Info)
+            .modal(true)
+            .build();
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        dialog.connect_response(|dialog, _| dialog.destroy());
+        dialog.present();
+    }
+
+    /// Keep track of the note shown in `content`, so what is done to the note reaches it
+    fn track_open_note(&self, content: &Content) {
+        content.connect_notify_local(
+            Some("note"),
+            clone!(@weak self as obj => move |content, _| {
+                obj.imp()
+                    .open_notes
+                    .borrow_mut()
+                    .set_note(content, content.note());
+
+                // It is no longer among the viewers once it shows nothing
+                if content.note().is_none() {
+                    content.set_follows_title(false);
+                }
+                obj.update_title_owners();
+            }),
+        );
+    }
+
+    /// Let only one of the contents showing a note edit its title
+    fn update_title_owners(&self) {
+        let owners = {
+            let open_notes = self.imp().open_notes.borrow();
+            open_notes
+                .viewers()
+                .into_iter()
+                .map(|content| {
+                    let owns_title = open_notes.owns_title(&content);
+                    (content, owns_title)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (content, owns_title) in owners {
+            content.set_follows_title(!owns_title);
+        }
+    }
+
+    /// Close the notes that are no longer in the note list wherever they are shown, like after
+    /// they were deleted by a sync. They are told about unless the user removed them.
+    fn close_removed_notes(&self) {
+        let imp = self.imp();
+        let note_list = self.note_manager().note_list();
+
+        let closed = imp
+            .open_notes
+            .borrow_mut()
+            .retain_notes(|note| note_list.find_by_id(&note.id()).as_ref() == Some(note));
+
+        let mut closed_notes = Vec::new();
+        for (content, note) in closed {
+            content.set_note(None);
+
+            if !closed_notes.contains(&note) {
+                closed_notes.push(note);
+            }
+        }
+
+        if imp.is_removing_notes.get() {
+            return;
+        }
+
+        for note in closed_notes {
+            log::info!("Closed `{}`, which was removed elsewhere", note);
+            self.add_toast(&adw::Toast::new(&gettext!(
+                "“{}” was deleted elsewhere",
+                note.metadata().title()
+            )));
+        }
+    }
+
+    fn add_toast(&self, toast: &adw::Toast) {
+        if let Some(window) = self.root().and_then(|root| root.downcast::<Window>().ok()) {
+            window.add_toast(toast);
+        }
+    }
+
+    /// Whether the notes can be changed, which is not the case while the notebook is
+    /// read-only. A toast explains it when they can't.
+    pub fn can_change_notes(&self) -> bool {
+        if !self.note_manager().is_read_only() {
+            return true;
+        }
+
+        self.add_toast(&adw::Toast::new(&read_only_message()));
+        false
+    }
+
+    /// Show a toast explaining the read-only mode if that is why `err` happened. Returns
+    /// whether it was.
+    pub fn report_read_only(&self, err: &anyhow::Error) -> bool {
+        if err.downcast_ref::<ReadOnly>().is_none() {
+            return false;
+        }
+
+        self.add_toast(&adw::Toast::new(&read_only_message()));
+        true
+    }
+
+    /// Merge `source` into `target` and commit it, with a toast to undo it
+    pub fn merge_notes(&self, target: &Note, source: &Note) {
+        let merged_notes = self.note_manager().merge_notes(target, source);
+        self.set_selected_note(Some(merged_notes.target().clone()));
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync merged notes: {:?}", err);
+            }
+        }));
+
+        let toast = adw::Toast::new(&gettext("Notes merged"));
+        toast.set_button_label(Some(&gettext("_Undo")));
+        // Toasts are outside of the session, so its own actions can't be used
+        toast.set_action_name(Some("app.undo-merge"));
+        toast.connect_dismissed(clone!(@weak self as obj => move |toast| {
+            let mut merged_notes = obj.imp().merged_notes.borrow_mut();
+
+            // It can no longer be undone, unless another merge already took its place
+            if merged_notes.as_ref().map_or(false, |(other, _)| other == toast) {
+                merged_notes.take();
+            }
+        }));
+
+        if let Some((previous_toast, _)) = self
+            .imp()
+            .merged_notes
+            .replace(Some((toast.clone(), merged_notes)))
+        {
+            previous_toast.dismiss();
+        }
+
+        self.add_toast(&toast);
+    }
+
+    /// Keep `kept` of the `duplicates`, trashing the others, and commit it
+    pub fn keep_duplicate(&self, kept: &Note, duplicates: &[Note]) {
+        let n_trashed = self.note_manager().keep_duplicate(kept, duplicates);
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync trashed duplicates: {:?}", err);
+            }
+        }));
+
+        let toast = adw::Toast::new(&ngettext!(
+            "Moved {} duplicate to the Trash",
+            "Moved {} duplicates to the Trash",
+            n_trashed as u32,
+            n_trashed
+        ));
+        self.add_toast(&toast);
+    }
+
+    /// Make the links to `note` follow it after it was renamed from `old_title`, with a toast
+    /// to undo both
+    pub fn update_links(&self, note: &Note, old_title: &str) {
+        let updated_links = match self.note_manager().update_links(note, old_title) {
+            LinkUpdate::Unlinked => return,
+            LinkUpdate::Updated(updated_links) => updated_links,
+            LinkUpdate::Ambiguous(n_notes) => {
+                let toast = adw::Toast::new(&ngettext!(
+                    "Links in {} note were kept, as other notes had the same title",
+                    "Links in {} notes were kept, as other notes had the same title",
+                    n_notes as u32,
+                    n_notes
+                ));
+                toast.set_button_label(Some(&gettext("_Show")));
+                // Toasts are outside of the session, so its own actions can't be used
+                toast.set_action_name(Some("app.show-links-to-check"));
+                self.add_toast(&toast);
+                return;
+            }
+        };
+
+        let n_links = updated_links.n_links();
+        let toast = adw::Toast::new(&ngettext!(
+            "Updated {} link",
+            "Updated {} links",
+            n_links as u32,
+            n_links
+        ));
+        toast.set_button_label(Some(&gettext("_Undo")));
+        toast.set_action_name(Some("app.undo-link-update"));
+        toast.connect_dismissed(clone!(@weak self as obj => move |toast| {
+            let mut updated_links = obj.imp().updated_links.borrow_mut();
+
+            // It can no longer be undone, unless another rename already took its place
+            if updated_links.as_ref().map_or(false, |(other, _)| other == toast) {
+                updated_links.take();
+            }
+        }));
+
+        if let Some((previous_toast, _)) = self
+            .imp()
+            .updated_links
+            .replace(Some((toast.clone(), updated_links)))
+        {
+            previous_toast.dismiss();
+        }
+
+        self.add_toast(&toast);
+    }
+
+    /// Undo the last rename and its updated links if its toast is still shown
+    pub fn undo_link_update(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        if let Some((_, updated_links)) = self.imp().updated_links.take() {
+            updated_links.undo();
+        }
+    }
+
+    /// Undo the last merge if its toast is still shown
+    pub fn undo_merge(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let merged_notes = match self.imp().merged_notes.take() {
+            Some((_, merged_notes)) => merged_notes,
+            None => return,
+        };
+
+        merged_notes.undo();
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync unmerged notes: {:?}", err);
+            }
+        }));
+    }
+
+    /// Delete `tag`, removing it from its notes, with a toast to restore it on them
+    pub fn delete_tag(&self, tag: &Tag) {
+        let deleted_tag = match self.note_manager().delete_tag(tag) {
+            Ok(deleted_tag) => deleted_tag,
+            Err(err) => {
+                if !self.report_read_only(&err) {
+                    log::error!("Failed to delete tag `{}`: {:?}", tag.name(), err);
+                }
+                return;
+            }
+        };
+
+        let toast = adw::Toast::new(&gettext!("Deleted “{}”", deleted_tag.name));
+        toast.set_button_label(Some(&gettext("_Undo")));
+        // Toasts are outside of the session, so its own actions can't be used
+        toast.set_action_name(Some("app.undo-delete-tag"));
+        toast.connect_dismissed(clone!(@weak self as obj => move |toast| {
+            let mut deleted_tag = obj.imp().deleted_tag.borrow_mut();
+
+            // It is still in the recently deleted tags of the tag editor
+            if deleted_tag.as_ref().map_or(false, |(other, _)| other == toast) {
+                deleted_tag.take();
+            }
+        }));
+
+        if let Some((previous_toast, _)) = self
+            .imp()
+            .deleted_tag
+            .replace(Some((toast.clone(), deleted_tag)))
+        {
+            previous_toast.dismiss();
+        }
+
+        self.add_toast(&toast);
+    }
+
+    /// Restore the last deleted tag while its toast is shown
+    pub fn undo_delete_tag(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        if let Some((_, deleted_tag)) = self.imp().deleted_tag.take() {
+            self.restore_deleted_tag(&deleted_tag);
+        }
+    }
+
+    /// Restore `deleted_tag` on the notes it was removed from that still exist
+    pub fn restore_deleted_tag(&self, deleted_tag: &DeletedTagData) {
+        if let Err(err) = self.note_manager().restore_deleted_tag(deleted_tag) {
+            if !self.report_read_only(&err) {
+                log::error!("Failed to restore tag `{}`: {:?}", deleted_tag.name, err);
+            }
+        }
+    }
+
+    /// Offer to restore or discard the changes that were not saved before the app last
+    /// stopped, if there are any
+    pub fn show_recovered_notes(&self) {
+        let note_manager = self.note_manager();
+        let recovered_notes = note_manager.recovered_notes();
+
+        // Kept for when the notebook is writable again, as they can't be restored until then
+        if recovered_notes.is_empty() || note_manager.is_read_only() {
+            return;
+        }
+
+        let list_box = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(vec!["boxed-list".into()])
+            .build();
+
+        let dialog = gtk::MessageDialog::builder()
+            .text(&gettext("Restore Unsaved Changes?"))
+            .secondary_text(&gettext(
+                "Noteworthy stopped before these changes were saved.",
+            ))
+            .message_type(gtk::MessageType::Question)
+            .buttons(gtk::ButtonsType::Close)
+            .modal(true)
+            .build();
+        dialog
+            .message_area()
+            .downcast::<gtk::Box>()
+            .unwrap()
+            .append(&list_box);
+        dialog.set_transient_for(
+            self.root()
+                .map(|w| w.downcast::<gtk::Window>().unwrap())
+                .as_ref(),
+        );
+        dialog.connect_response(|dialog, _| dialog.destroy());
+
+        for recovered_note in recovered_notes {
+            let title = NoteData::parse(&recovered_note.contents)
+                .map(|note_data| note_data.metadata.title)
+                .unwrap_or_default();
+            let note_id = NoteId::for_path(&recovered_note.file_name);
+            let subtitle = if note_manager.note_list().find_by_id(&note_id).is_some() {
+                String::new()
+            } else {
+                gettext("Restored as a new note, as it was deleted")
+            };
+
+            let row = adw::ActionRow::builder()
+                .title(&if title.is_empty() {
+                    gettext("Untitled Note")
+                } else {
+                    title
+                })
+                .subtitle(&subtitle)
+                .build();
+
+            let discard_button = gtk::Button::builder()
+                .label(&gettext("_Discard"))
+                .use_underline(true)
+                .valign(gtk::Align::Center)
+                .build();
+            let restore_button = gtk::Button::builder()
+                .label(&gettext("_Restore"))
+                .use_underline(true)
+                .valign(gtk::Align::Center)
+                .css_classes(vec!["suggested-action".into()])
+                .build();
+            row.add_suffix(&discard_button);
+            row.add_suffix(&restore_button);
+            list_box.append(&row);
+
+            // The dialog is closed once every change is restored or discarded
+            let remove_row = clone!(@weak dialog, @weak list_box, @weak row => move || {
+                list_box.remove(&row);
+
+                if list_box.first_child().is_none() {
+                    dialog.destroy();
+                }
+            });
+
+            discard_button.connect_clicked(
+                clone!(@weak self as obj, @strong recovered_note, @strong remove_row => move |_| {
+                    obj.note_manager().discard_recovered_note(&recovered_note);
+                    remove_row();
+                }),
+            );
+            restore_button.connect_clicked(
+                clone!(@weak self as obj, @strong recovered_note => move |_| {
+                    match obj.note_manager().restore_recovered_note(&recovered_note) {
+                        Ok(note) => obj.set_selected_note(Some(note)),
+                        Err(err) => {
+                            log::error!(
+                                "Failed to restore `{}`: {:?}",
+                                recovered_note.file_name,
+                                err
+                            );
+                            Application::default().error_log().push(ErrorReport::from_error(
+                                ErrorSource::Notes,
+                                &gettext("Failed to restore unsaved changes"),
+                                &err,
+                            ));
+                        }
+                    }
+                    remove_row();
+                }),
+            );
+        }
+
+        dialog.present();
+    }
+
+    /// Point out the main parts of the window, one at a time
+    pub fn start_tour(&self) {
+        self.imp().tour.get_or_init(TourController::new).start();
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.imp().is_locked.get()
+    }
+
+    /// Hide the notes until unlocked, with the PIN if one is set
+    pub fn lock(&self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let imp = self.imp();
+
+        self.clear_note_clipboard();
+
+        imp.lock_page.set_description(None);
+        imp.unlock_entry.set_visible(false);
+        imp.unlock_entry.set_text("");
+        imp.unlock_entry.remove_css_class("error");
+        imp.stack.set_visible_child(&imp.lock_page.get());
+
+        self.set_locked(true);
+
+        // The PIN is asked once the keyring says there is one. It is asked too if the keyring
+        // can't be read, as unlocking checks it again anyway.
+        spawn!(clone!(@weak self as obj => async move {
+            let has_pin = match spawn_blocking!(PinHash::lookup_lock_pin).await {
+                Ok(pin_hash) => pin_hash.is_some(),
+                Err(err) => {
+                    log::error!("Failed to look up lock PIN: {:?}", err);
+                    true
+                }
+            };
+
+            if !has_pin || !obj.is_locked() {
+                return;
+            }
+
+            let imp = obj.imp();
+            imp.lock_page
+                .set_description(Some(&gettext("Enter your PIN to unlock.")));
+            imp.unlock_entry.set_visible(true);
+            imp.unlock_entry.grab_focus();
+        }));
+
+        // Save the changes now, so nothing waits on the session to be unlocked
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.sync().await {
+                log::error!("Failed to sync session on lock: {:?}", err);
+            }
+        }));
+
+        log::info!("Session locked");
+    }
+
+    fn unlock(&self) {
+        if !self.is_locked() {
+            return;
+        }
+
+        let pin = self.imp().unlock_entry.text().to_string();
+        self.action_set_enabled("session.unlock", false);
+
+        // The PIN is looked up again, so it is never unlocked on what was known when it was
+        // locked. Hashing is slow on purpose, so don't block the UI on it.
+        spawn!(clone!(@weak self as obj => async move {
+            let res = spawn_blocking!(move || {
+                PinHash::lookup_lock_pin().map(|pin_hash| match pin_hash {
+                    Some(pin_hash) => pin_hash.verify(&pin),
+                    None => true,
+                })
+            })
+            .await;
+            obj.action_set_enabled("session.unlock", true);
+
+            match res {
+                Ok(true) => obj.finish_unlock(),
+                Ok(false) => {
+                    let unlock_entry = obj.imp().unlock_entry.get();
+                    unlock_entry.add_css_class("error");
+                    unlock_entry.grab_focus();
+                }
+                Err(err) => {
+                    log::error!("Failed to look up lock PIN: {:?}", err);
+                    obj.add_toast(&adw::Toast::new(&gettext(
+                        "Failed to read the PIN from the keyring",
+                    )));
+                }
+            }
+        }));
+    }
+
+    fn finish_unlock(&self) {
+        let imp = self.imp();
+
+        imp.unlock_entry.set_text("");
+        imp.unlock_entry.remove_css_class("error");
+        imp.stack.set_visible_child(&imp.leaflet.get());
+        imp.idle_timer.borrow_mut().record_activity(Instant::now());
+
+        self.set_locked(false);
+
+        log::info!("Session unlocked");
+    }
+
+    fn set_locked(&self, is_locked: bool) {
+        self.imp().is_locked.set(is_locked);
+        self.update_locked_actions();
+        self.notify("is-locked");
+    }
+
+    /// The notes can't be used until they are loaded, like while locked
+    fn set_loading(&self, is_loading: bool) {
+        self.imp().is_loading.set(is_loading);
+        self.imp().sidebar.set_is_loading(is_loading);
+        self.update_locked_actions();
+    }
+
+    fn update_locked_actions(&self) {
+        let imp = self.imp();
+        let is_enabled = !imp.is_locked.get() && !imp.is_loading.get();
+        let is_busy = self.note_manager().repository().is_busy();
+
+        for action_name in LOCKED_ACTIONS {
+            let is_busy_action = REPOSITORY_BUSY_ACTIONS.contains(action_name);
+            self.action_set_enabled(action_name, is_enabled && !(is_busy && is_busy_action));
+        }
+    }
+
+    /// Clear the clipboard if it still has what was copied from a note, so it cannot be
+    /// pasted while locked
+    fn clear_note_clipboard(&self) {
+        let clipboard = self.clipboard();
+
+        if self.imp().is_clipboard_from_note.get() && clipboard.is_local() {
+            if let Err(err) = clipboard.set_content(None::<&gdk::ContentProvider>) {
+                log::warn!("Failed to clear clipboard: {:?}", err);
+            }
+        }
+
+        self.imp().is_clipboard_from_note.set(false);
+    }
+
+    fn set_note_manager(&self, note_manager: NoteManager) {
+        note_manager.set_journal(&Application::default().journal());
+        self.imp().note_manager.set(note_manager).unwrap();
+    }
+
+    /// Disable the actions that would conflict with a git operation while one is running, like
+    /// deleting files it may be merging. Edits are still allowed as they are only committed
+    /// on the next sync.
+    fn update_repository_busy(&self) {
+        let is_busy = self.note_manager().repository().is_busy();
+        let is_read_only = self.note_manager().is_read_only();
+        self.imp()
+            .content
+            .set_can_delete_attachments(!is_busy && !is_read_only);
+        self.imp().content.set_is_repository_busy(is_busy);
+        self.update_locked_actions();
+    }
+
+    /// Hide what changes the notes while the notebook is read-only, and make them only
+    /// readable
+    fn update_read_only(&self) {
+        let imp = self.imp();
+        let is_read_only = self.note_manager().is_read_only();
+
+        imp.sidebar.set_is_read_only(is_read_only);
+        imp.content.set_is_read_only(is_read_only);
+        self.update_repository_busy();
+
+        log::info!("Notebook is_read_only `{}`", is_read_only);
+    }
+
+    fn setup_signals(&self) {
+        self.note_manager()
+            .bind_property("is-syncing", self, "is-syncing")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+
+        Application::default()
+            .settings()
+            .bind("is-read-only", self.note_manager(), "is-read-only")
+            .flags(gio::SettingsBindFlags::GET)
+            .build();
+        self.note_manager()
+            .connect_is_read_only_notify(clone!(@weak self as obj => move |_| {
+                obj.update_read_only();
+            }));
+        self.update_read_only();
+
+        self.note_manager().repository().connect_busy_notify(
+            clone!(@weak self as obj => move |_| {
+                obj.update_repository_busy();
+            }),
+        );
+        self.update_repository_busy();
+
+        self.imp().leaflet.connect_child_transition_running_notify(
+            clone!(@weak self as obj => move |leaflet| {
+                // Only deselect the note when the content is fully hidden
+                let is_sidebar_visible = leaflet.visible_child().unwrap() == obj.imp().sidebar.get();
+                if !leaflet.is_child_transition_running() && is_sidebar_visible  {
+                    obj.set_selected_note(None);
+                }
+            }),
+        );
+    }
+
+    fn setup_lock(&self) {
+        let imp = self.imp();
+
+        self.clipboard()
+            .connect_changed(clone!(@weak self as obj => move |clipboard| {
+                // Copying is done with the keyboard or a context menu, so the focused widget
+                // is the one that copied
+                let is_from_note = clipboard.is_local()
+                    && obj
+                        .root()
+                        .and_then(|root| root.focus())
+                        .map_or(false, |focus| focus.is_ancestor(&obj.imp().content.get()));
+                obj.imp().is_clipboard_from_note.set(is_from_note);
+            }));
+
+        imp.unlock_entry
+            .connect_activate(clone!(@weak self as obj => move |_| {
+                obj.unlock();
+            }));
+
+        // Seen before it reaches the focused widget, so any input counts as activity
+        let event_controller = gtk::EventControllerLegacy::new();
+        event_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        event_controller.connect_event(
+            clone!(@weak self as obj => @default-return gtk::Inhibit(false), move |_, _| {
+                obj.imp().idle_timer.borrow_mut().record_activity(Instant::now());
+                gtk::Inhibit(false)
+            }),
+        );
+        self.add_controller(&event_controller);
+
+        glib::timeout_add_seconds_local(
+            LOCK_CHECK_INTERVAL_SECS,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                let is_idle = obj
+                    .imp()
+                    .idle_timer
+                    .borrow()
+                    .is_idle(lock_timeout(), Instant::now());
+
+                if is_idle && !obj.is_locked() {
+                    log::info!("Locking session after being idle");
+                    obj.lock();
+                }
+
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    fn setup_picture_viewer(&self) {
+        self.connect_root_notify(|obj| {
+            if let Some(window) = obj
+                .root()
+                .and_then(|root| root.downcast::<gtk::Window>().ok())
+            {
                 window
                     .bind_property(
                         "fullscreened",
@@ -346,8 +2895,133 @@ impl Session {
     }
 }
 
+/// Time without activity before the session is locked, or `None` if it is never locked
+fn read_only_message() -> String {
+    gettext("The notebook is read-only, so the notes can't be changed. It can be made writable in the preferences.")
+}
+
+fn lock_timeout() -> Option<Duration> {
+    let minutes = Application::default().settings().uint("lock-timeout");
+    (minutes > 0).then(|| Duration::from_secs(u64::from(minutes) * 60))
+}
+
+/// How deleted files are removed, which is right away when the preferences say so, as the
+/// user was told that they may be deleted
+fn deletion_mode() -> DeletionMode {
+    let is_delete_immediately = Application::default()
+        .settings()
+        .boolean("delete-immediately");
+
+    if is_delete_immediately {
+        DeletionMode::Delete
+    } else {
+        DeletionMode::TrashOrDelete
+    }
+}
+
+fn tag_bundle_filter() -> gtk::FileFilter {
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some(&gettext("Tag Bundles")));
+    filter.add_pattern(&format!("*.{}", BUNDLE_EXTENSION));
+    filter
+}
+
+/// Lines of a report listing the first of `links`, with nothing when there are none
+fn unresolved_link_lines(links: &[UnresolvedLink]) -> Vec<String> {
+    const MAX_SHOWN_LINKS: usize = 10;
+
+    let n_links = links.len();
+    if n_links == 0 {
+        return Vec::new();
+    }
+
+    let mut lines = vec![
+        String::new(),
+        ngettext!(
+            "{} link could not be found:",
+            "{} links could not be found:",
+            n_links as u32,
+            n_links
+        ),
+    ];
+    lines.extend(
+        links
+            .iter()
+            .take(MAX_SHOWN_LINKS)
+            .map(|link| format!("• {} → {}", link.note_title, link.target)),
+    );
+
+    if n_links > MAX_SHOWN_LINKS {
+        let n_more = n_links - MAX_SHOWN_LINKS;
+        lines.push(ngettext!(
+            "and {} more",
+            "and {} more",
+            n_more as u32,
+            n_more
+        ));
+    }
+
+    lines
+}
+
+/// Lines of the report of an export, listing the images that were left out as their file
+/// is missing
+fn missing_image_lines(sources: &[String]) -> Vec<String> {
+    const MAX_SHOWN_IMAGES: usize = 10;
+
+    let n_images = sources.len();
+    let mut lines = vec![ngettext!(
+        "{} image could not be found, so a box was put in its place:",
+        "{} images could not be found, so boxes were put in their place:",
+        n_images as u32,
+        n_images
+    )];
+    lines.extend(
+        sources
+            .iter()
+            .take(MAX_SHOWN_IMAGES)
+            .map(|source| format!("• {}", source)),
+    );
+
+    if n_images > MAX_SHOWN_IMAGES {
+        let n_more = n_images - MAX_SHOWN_IMAGES;
+        lines.push(ngettext!(
+            "and {} more",
+            "and {} more",
+            n_more as u32,
+            n_more
+        ));
+    }
+
+    lines
+}
+
+/// Copy the images of exported HTML to their paths relative to `output_dir`, the folder of
+/// the HTML file
+fn copy_exported_images(
+    copies: &[(PathBuf, String)],
+    output_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    if copies.is_empty() {
+        return Ok(());
+    }
+
+    let output_dir =
+        output_dir.ok_or_else(|| anyhow::anyhow!("Images can only be copied to a local folder"))?;
+
+    for (source, path) in copies {
+        let destination = resolve_within(output_dir, path)?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, destination)?;
+    }
+
+    Ok(())
+}
+
 impl Default for Session {
     fn default() -> Self {
-        Application::default().main_window().session().clone()
+        Application::default().session()
     }
 }
@@ -0,0 +1,358 @@
+use indexmap::IndexMap;
+
+use crate::model::NoteId;
+
+/// Used when there are changes in the working directory that are not recorded in the journal
+/// (e.g. attachments or the data file).
+pub const DEFAULT_COMMIT_MESSAGE: &str = "Sync commit";
+
+pub const CHANGES_PLACEHOLDER: &str = "{changes}";
+pub const COUNT_PLACEHOLDER: &str = "{count}";
+
+const UNTITLED_NOTE: &str = "Untitled";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    Edited,
+    Created,
+    Renamed { previous_title: String },
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub title: String,
+}
+
+/// Records what happened to the notes since the last commit, so it can be summarized
+/// into a meaningful commit message.
+#[derive(Debug, Default, Clone)]
+pub struct ChangeJournal {
+    changes: IndexMap<NoteId, Change>,
+}
+
+impl ChangeJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_created(&mut self, note_id: &NoteId, title: &str) {
+        self.changes.insert(
+            note_id.clone(),
+            Change {
+                kind: ChangeKind::Created,
+                title: title.to_string(),
+            },
+        );
+    }
+
+    pub fn record_edited(&mut self, note_id: &NoteId, title: &str) {
+        if let Some(change) = self.changes.get_mut(note_id) {
+            // Created, renamed, and deleted notes already imply that it is edited
+            change.title = title.to_string();
+            return;
+        }
+
+        self.changes.insert(
+            note_id.clone(),
+            Change {
+                kind: ChangeKind::Edited,
+                title: title.to_string(),
+            },
+        );
+    }
+
+    pub fn record_renamed(&mut self, note_id: &NoteId, previous_title: &str, title: &str) {
+        if let Some(change) = self.changes.get_mut(note_id) {
+            match change.kind {
+                ChangeKind::Edited => {
+                    change.kind = ChangeKind::Renamed {
+                        previous_title: previous_title.to_string(),
+                    };
+                }
+                // Keep the oldest previous title, and a new note is still a new note
+                ChangeKind::Renamed { .. } | ChangeKind::Created | ChangeKind::Deleted => (),
+            }
+            change.title = title.to_string();
+            return;
+        }
+
+        self.changes.insert(
+            note_id.clone(),
+            Change {
+                kind: ChangeKind::Renamed {
+                    previous_title: previous_title.to_string(),
+                },
+                title: title.to_string(),
+            },
+        );
+    }
+
+    pub fn record_deleted(&mut self, note_id: &NoteId, title: &str) {
+        if let Some(change) = self.changes.get(note_id) {
+            // It was never committed, so there is nothing to tell
+            if change.kind == ChangeKind::Created {
+                self.changes.shift_remove(note_id);
+                return;
+            }
+        }
+
+        self.changes.insert(
+            note_id.clone(),
+            Change {
+                kind: ChangeKind::Deleted,
+                title: title.to_string(),
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Keep only the changes of the notes for which `f` returns true
+    pub fn retain(&mut self, mut f: impl FnMut(&NoteId) -> bool) {
+        self.changes.retain(|note_id, _| f(note_id));
+    }
+
+    /// Build a commit message from `template`, replacing `{changes}` with the summary of the
+    /// changes and `{count}` with the number of changed notes.
+    ///
+    /// When there are more than `max_changes` changed notes, `{changes}` falls back to just
+    /// the count of the changes. A `max_changes` of 0 means there is no limit.
+    pub fn commit_message(&self, template: &str, max_changes: usize) -> String {
+        if self.is_empty() {
+            return DEFAULT_COMMIT_MESSAGE.to_string();
+        }
+
+        let changes_summary = if max_changes != 0 && self.len() > max_changes {
+            format!("Update {} notes", self.len())
+        } else {
+            self.changes_summary()
+        };
+
+        let message = template
+            .replace(CHANGES_PLACEHOLDER, &changes_summary)
+            .replace(COUNT_PLACEHOLDER, &self.len().to_string());
+
+        let message = message.trim();
+
+        if message.is_empty() {
+            changes_summary
+        } else {
+            message.to_string()
+        }
+    }
+
+    fn changes_summary(&self) -> String {
+        let edited = self.changes_with(|kind| matches!(kind, ChangeKind::Edited));
+        let created = self.changes_with(|kind| matches!(kind, ChangeKind::Created));
+        let renamed = self.changes_with(|kind| matches!(kind, ChangeKind::Renamed { .. }));
+        let deleted = self.changes_with(|kind| matches!(kind, ChangeKind::Deleted));
+
+        let mut segments = Vec::new();
+
+        if let Some(segment) = Self::segment("edit", &edited) {
+            segments.push(segment);
+        }
+
+        if let Some(segment) = Self::segment("create", &created) {
+            segments.push(segment);
+        }
+
+        match renamed.as_slice() {
+            [] => (),
+            [change] => {
+                if let ChangeKind::Renamed { ref previous_title } = change.kind {
+                    segments.push(format!(
+                        "rename '{}' to '{}'",
+                        display_title(previous_title),
+                        display_title(&change.title)
+                    ));
+                }
+            }
+            changes => segments.push(format!("rename {} notes", changes.len())),
+        }
+
+        if let Some(segment) = Self::segment("delete", &deleted) {
+            segments.push(segment);
+        }
+
+        capitalize_first(&segments.join("; "))
+    }
+
+    fn changes_with(&self, predicate: impl Fn(&ChangeKind) -> bool) -> Vec<&Change> {
+        self.changes
+            .values()
+            .filter(|change| predicate(&change.kind))
+            .collect()
+    }
+
+    fn segment(verb: &str, changes: &[&Change]) -> Option<String> {
+        match changes {
+            [] => None,
+            [change] => Some(format!("{} '{}'", verb, display_title(&change.title))),
+            changes => Some(format!("{} {} notes", verb, changes.len())),
+        }
+    }
+}
+
+fn display_title(title: &str) -> &str {
+    let title = title.trim();
+
+    if title.is_empty() {
+        UNTITLED_NOTE
+    } else {
+        title
+    }
+}
+
+fn capitalize_first(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id(name: &str) -> NoteId {
+        NoteId::for_path(format!("/home/user/{}.md", name))
+    }
+
+    #[test]
+    fn empty() {
+        let journal = ChangeJournal::new();
+        assert!(journal.is_empty());
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            DEFAULT_COMMIT_MESSAGE
+        );
+    }
+
+    #[test]
+    fn single_edit() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "Project plan");
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            "Edit 'Project plan'"
+        );
+    }
+
+    #[test]
+    fn mixed_changes() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "Project plan");
+        journal.record_created(&id("b"), "New 1");
+        journal.record_created(&id("c"), "New 2");
+        journal.record_deleted(&id("d"), "Old todo");
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            "Edit 'Project plan'; create 2 notes; delete 'Old todo'"
+        );
+    }
+
+    #[test]
+    fn rename() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "Old");
+        journal.record_renamed(&id("a"), "Old", "Mid");
+        journal.record_renamed(&id("a"), "Mid", "New");
+        assert_eq!(journal.len(), 1);
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            "Rename 'Old' to 'New'"
+        );
+    }
+
+    #[test]
+    fn created_then_edited_is_still_created() {
+        let mut journal = ChangeJournal::new();
+        journal.record_created(&id("a"), "");
+        journal.record_edited(&id("a"), "Title");
+        journal.record_renamed(&id("a"), "", "Other title");
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            "Create 'Other title'"
+        );
+    }
+
+    #[test]
+    fn created_then_deleted_is_nothing() {
+        let mut journal = ChangeJournal::new();
+        journal.record_created(&id("a"), "Title");
+        journal.record_deleted(&id("a"), "Title");
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn untitled() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "  ");
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 5),
+            "Edit 'Untitled'"
+        );
+    }
+
+    #[test]
+    fn over_max_changes() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "A");
+        journal.record_edited(&id("b"), "B");
+        journal.record_created(&id("c"), "C");
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 2),
+            "Update 3 notes"
+        );
+        assert_eq!(
+            journal.commit_message(CHANGES_PLACEHOLDER, 0),
+            "Edit 2 notes; create 'C'"
+        );
+    }
+
+    #[test]
+    fn template() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "A");
+        journal.record_edited(&id("b"), "B");
+        assert_eq!(
+            journal.commit_message("Noteworthy: {changes} ({count})", 5),
+            "Noteworthy: Edit 2 notes (2)"
+        );
+        assert_eq!(journal.commit_message("  ", 5), "Edit 2 notes");
+    }
+
+    #[test]
+    fn clear() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "A");
+        assert!(!journal.is_empty());
+        journal.clear();
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn retain() {
+        let mut journal = ChangeJournal::new();
+        journal.record_edited(&id("a"), "A");
+        journal.record_created(&id("b"), "B");
+        journal.retain(|note_id| *note_id == id("b"));
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal.commit_message("{changes}", 5), "Create 'B'");
+    }
+}
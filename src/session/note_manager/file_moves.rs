@@ -0,0 +1,99 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How long a removed file waits for one with the same contents to appear, for them to be
+/// taken as a move
+const MOVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Hash of the contents of a file, to find the file it was moved from
+pub fn content_hash(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug)]
+struct RemovedFile {
+    path: PathBuf,
+    content_hash: u64,
+    time: Instant,
+}
+
+/// Pairs files that were removed with files that appeared with the same contents soon
+/// after, which is what a move looks like when it is not reported as one
+#[derive(Debug, Default)]
+pub struct FileMoves {
+    removed: Vec<RemovedFile>,
+}
+
+impl FileMoves {
+    pub fn record_removed(&mut self, path: PathBuf, content_hash: u64, now: Instant) {
+        self.forget_expired(now);
+        self.removed.retain(|removed| removed.path != path);
+        self.removed.push(RemovedFile {
+            path,
+            content_hash,
+            time: now,
+        });
+    }
+
+    /// Path of the removed file that a file with `content_hash`, which appeared at `now`,
+    /// was moved from
+    pub fn take_moved_from(&mut self, content_hash: u64, now: Instant) -> Option<PathBuf> {
+        self.forget_expired(now);
+
+        let index = self
+            .removed
+            .iter()
+            .position(|removed| removed.content_hash == content_hash)?;
+        Some(self.removed.remove(index).path)
+    }
+
+    fn forget_expired(&mut self, now: Instant) {
+        self.removed
+            .retain(|removed| now.saturating_duration_since(removed.time) <= MOVE_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paired_by_contents() {
+        let now = Instant::now();
+        let mut file_moves = FileMoves::default();
+
+        file_moves.record_removed("a.md".into(), content_hash(b"A"), now);
+        file_moves.record_removed("b.md".into(), content_hash(b"B"), now);
+
+        assert_eq!(file_moves.take_moved_from(content_hash(b"C"), now), None);
+        assert_eq!(
+            file_moves.take_moved_from(content_hash(b"B"), now),
+            Some("b.md".into())
+        );
+
+        // Only moved once
+        assert_eq!(file_moves.take_moved_from(content_hash(b"B"), now), None);
+        assert_eq!(
+            file_moves.take_moved_from(content_hash(b"A"), now),
+            Some("a.md".into())
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let now = Instant::now();
+        let mut file_moves = FileMoves::default();
+
+        file_moves.record_removed("a.md".into(), content_hash(b"A"), now);
+        assert_eq!(
+            file_moves.take_moved_from(content_hash(b"A"), now + MOVE_TIMEOUT * 2),
+            None
+        );
+    }
+}
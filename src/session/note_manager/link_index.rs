@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use crate::{core::wiki_link_targets, model::NoteId};
+
+/// Which notes each note links to with `[[Title]]`. Titles are matched case-insensitively,
+/// and a link to a title shared by several notes goes to the first of them.
+#[derive(Debug, Default)]
+pub struct LinkIndex {
+    links: HashMap<NoteId, Vec<NoteId>>,
+    /// The note each lowercase title links to
+    ids_by_title: HashMap<String, NoteId>,
+}
+
+impl LinkIndex {
+    /// Build from the id, title, and content of each note
+    pub fn new<S, C>(notes: impl IntoIterator<Item = (NoteId, S, C)>) -> Self
+    where
+        S: AsRef<str>,
+        C: AsRef<str>,
+    {
+        let notes = notes.into_iter().collect::<Vec<_>>();
+
+        let mut ids_by_title = HashMap::new();
+        for (id, title, _) in &notes {
+            ids_by_title
+                .entry(title.as_ref().trim().to_lowercase())
+                .or_insert_with(|| id.clone());
+        }
+
+        let links = notes
+            .iter()
+            .map(|(id, _, content)| {
+                let mut targets: Vec<NoteId> = Vec::new();
+
+                for target_title in wiki_link_targets(content.as_ref()) {
+                    if let Some(target) = ids_by_title.get(&target_title.to_lowercase()) {
+                        if target != id && !targets.contains(target) {
+                            targets.push(target.clone());
+                        }
+                    }
+                }
+
+                (id.clone(), targets)
+            })
+            .collect();
+
+        Self {
+            links,
+            ids_by_title,
+        }
+    }
+
+    /// Notes linked from `note_id`, in the order they are first linked
+    pub fn links_from(&self, note_id: &NoteId) -> &[NoteId] {
+        self.links
+            .get(note_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Notes linking to `note_id`, in no particular order
+    pub fn links_to(&self, note_id: &NoteId) -> Vec<NoteId> {
+        self.links
+            .iter()
+            .filter(|(_, targets)| targets.contains(note_id))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// The note that links to `title` go to
+    pub fn note_with_title(&self, title: &str) -> Option<&NoteId> {
+        self.ids_by_title.get(&title.trim().to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id(name: &str) -> NoteId {
+        NoteId::for_path(name)
+    }
+
+    #[test]
+    fn links() {
+        let link_index = LinkIndex::new([
+            (id("a"), "Groceries", "Buy for [[recipes]] and [[Unknown]]"),
+            (
+                id("b"),
+                "Recipes",
+                "From [[Groceries|the list]], see [[groceries]] again",
+            ),
+            (id("c"), "Orphan", ""),
+        ]);
+
+        assert_eq!(link_index.links_from(&id("a")), [id("b")]);
+        assert_eq!(link_index.links_from(&id("b")), [id("a")]);
+        assert!(link_index.links_from(&id("c")).is_empty());
+        assert!(link_index.links_from(&id("d")).is_empty());
+
+        assert_eq!(link_index.links_to(&id("a")), [id("b")]);
+        assert!(link_index.links_to(&id("c")).is_empty());
+    }
+
+    #[test]
+    fn links_to_many() {
+        let link_index = LinkIndex::new([
+            (id("a"), "Groceries", ""),
+            (id("b"), "Recipes", "[[Groceries]]"),
+            (
+                id("c"),
+                "Menu",
+                "[[Recipes]] with [[groceries|what to buy]]",
+            ),
+        ]);
+
+        let links_to = link_index.links_to(&id("a"));
+        assert_eq!(links_to.len(), 2);
+        assert!(links_to.contains(&id("b")));
+        assert!(links_to.contains(&id("c")));
+    }
+
+    #[test]
+    fn links_to_self() {
+        let link_index = LinkIndex::new([(id("a"), "Groceries", "[[Groceries]]")]);
+        assert!(link_index.links_from(&id("a")).is_empty());
+    }
+
+    #[test]
+    fn links_to_shared_title() {
+        let link_index = LinkIndex::new([
+            (id("a"), "Groceries", ""),
+            (id("b"), "groceries ", ""),
+            (id("c"), "Recipes", "[[Groceries]]"),
+        ]);
+        assert_eq!(link_index.links_from(&id("c")), [id("a")]);
+        assert_eq!(link_index.note_with_title(" GROCERIES"), Some(&id("a")));
+        assert_eq!(link_index.note_with_title("Unknown"), None);
+    }
+}
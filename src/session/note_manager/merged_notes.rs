@@ -0,0 +1,121 @@
+use gtk::prelude::*;
+
+use crate::{
+    core::{NoteData, NoteMerge},
+    model::{Note, Tag, TagList},
+};
+
+/// The changes made by merging a source note into a target note, so they can be undone
+#[derive(Debug)]
+pub struct MergedNotes {
+    target: Note,
+    source: Note,
+    appended_content: String,
+    /// Character offset in the buffer of the target where the content was appended
+    appended_offset: i32,
+    added_tags: Vec<Tag>,
+}
+
+impl MergedNotes {
+    /// Append the content of `source` to `target` under a heading with its title, add the
+    /// tags of `source` to `target`, then trash `source`
+    pub fn merge(tag_list: &TagList, target: &Note, source: &Note) -> Self {
+        let merge = NoteMerge::new(&note_data(target), &note_data(source));
+
+        let buffer = target.buffer();
+        let mut end_iter = buffer.end_iter();
+        let appended_offset = end_iter.offset();
+
+        // A single user action, so the editor also undoes it in one step
+        buffer.begin_user_action();
+        buffer.insert(&mut end_iter, &merge.appended_content);
+        buffer.end_user_action();
+
+        let target_tag_list = target.metadata().tag_list();
+        let mut added_tags = Vec::new();
+
+        for tag in merge
+            .tag_names
+            .iter()
+            .filter_map(|name| tag_list.get_with_name(name))
+        {
+            if target_tag_list.contains(&tag) {
+                continue;
+            }
+
+            match target_tag_list.append(tag.clone()) {
+                Ok(()) => added_tags.push(tag),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to tag `{}` with `{}`: {:?}",
+                        target,
+                        tag.name(),
+                        err
+                    );
+                }
+            }
+        }
+
+        source.metadata().set_is_trashed(true);
+
+        log::info!("Merged `{}` into `{}`", source, target);
+
+        Self {
+            target: target.clone(),
+            source: source.clone(),
+            appended_content: merge.appended_content,
+            appended_offset,
+            added_tags,
+        }
+    }
+
+    pub fn target(&self) -> &Note {
+        &self.target
+    }
+
+    /// Restore the source note and remove what was added to the target. The appended content
+    /// is kept if it was edited since.
+    pub fn undo(self) {
+        let buffer = self.target.buffer();
+        let appended_len = self.appended_content.chars().count() as i32;
+        let mut start_iter = buffer.iter_at_offset(self.appended_offset);
+        let mut end_iter = buffer.iter_at_offset(self.appended_offset + appended_len);
+
+        if buffer.text(&start_iter, &end_iter, true).as_str() == self.appended_content {
+            buffer.begin_user_action();
+            buffer.delete(&mut start_iter, &mut end_iter);
+            buffer.end_user_action();
+        } else {
+            log::warn!(
+                "Content merged into `{}` was edited, keeping it",
+                self.target
+            );
+        }
+
+        let target_tag_list = self.target.metadata().tag_list();
+        for tag in &self.added_tags {
+            if let Err(err) = target_tag_list.remove(tag) {
+                log::warn!(
+                    "Failed to untag `{}` with `{}`: {:?}",
+                    self.target,
+                    tag.name(),
+                    err
+                );
+            }
+        }
+
+        self.source.metadata().set_is_trashed(false);
+
+        log::info!("Unmerged `{}` from `{}`", self.source, self.target);
+    }
+}
+
+fn note_data(note: &Note) -> NoteData {
+    let buffer = note.buffer();
+    let (start_iter, end_iter) = buffer.bounds();
+
+    NoteData {
+        metadata: note.metadata().to_data(),
+        content: buffer.text(&start_iter, &end_iter, true).to_string(),
+    }
+}
@@ -0,0 +1,5536 @@
+mod change_journal;
+mod file_moves;
+mod link_index;
+mod merged_notes;
+mod tag_index;
+mod updated_links;
+
+use chrono::Local;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+pub use self::{
+    link_index::LinkIndex,
+    merged_notes::MergedNotes,
+    updated_links::{LinkUpdate, UpdatedLinks},
+};
+
+use self::{
+    change_journal::ChangeJournal,
+    file_moves::{content_hash, FileMoves},
+    tag_index::TagIndex,
+};
+use crate::{
+    core::{
+        attachment_files, build_site, convert_vault, duplicate_groups, first_heading,
+        near_duplicate_groups, plan_import, query_words, read_recovered_notes, read_vault,
+        reconcile, remove_file, retarget_wiki_links, sorted_deleted_tags, trash_only_attachments,
+        trash_size, write_batch, write_bundle, write_site, AheadBehind, AttachmentData,
+        AttachmentReferences, BundleNote, CollisionPolicy, DailyWordCounts, DataFile, DateTime,
+        DeletedTagData, DeletionMode, DesktopTrash, Exclusions, FileStamp, FileType, IndexEntry,
+        IndexMatch, Journal, JournalEvent, MetadataIndex, NoteData, NoteRepository, NotesNotSaved,
+        RecoveredNote, RecoveryWriter, SearchIndex, SiteNote, SyncState, TagBundle, Trash,
+        UnresolvedLink, VaultImportOptions, WrittenBundle, DATA_FILE_NAME, DEFAULT_EXCLUSIONS,
+        DELETED_TAG_RETENTION_DAYS, EXCLUSIONS_FILE_NAME, MAX_WRITES_IN_FLIGHT,
+        METADATA_INDEX_FILE_NAME, NEAR_DUPLICATE_THRESHOLD, SEARCH_INDEX_FILE_NAME,
+    },
+    model::{
+        Attachment, AttentionList, ErrorReport, ErrorSource, LoadProblem, Note, NoteFileEvent,
+        NoteId, NoteList, NoteMetadata, NotebookSettings, ProblemList, SavedSearchList, Tag,
+        TagList,
+    },
+    spawn, spawn_blocking,
+    utils::{or_cancelled, safe_path::resolve_within},
+    Application,
+};
+
+/// How long the notes directory has to stay unchanged before the pending changes are
+/// counted again, so a sync touching many files only counts them once
+const PENDING_CHANGES_REFRESH_DELAY: Duration = Duration::from_millis(500);
+/// How often copies of the unsaved notes are written, for restoring them after a crash
+const RECOVERY_INTERVAL_SECS: u32 = 5;
+/// How often the remote is fetched to tell how far it is from the notes, when the remote changes
+/// are not synced right away
+const AHEAD_BEHIND_REFRESH_INTERVAL_SECS: u32 = 5 * 60;
+/// How long the notes have to stay unchanged before the changes are searchable in the index,
+/// so it is not updated on every key press
+const SEARCH_INDEX_DELAY: Duration = Duration::from_secs(1);
+
+/// Returned by [`NoteManager::load`] when it was stopped by a newer load or by
+/// [`NoteManager::cancel_load`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Loading the notebook was cancelled")]
+pub struct LoadCancelled;
+
+/// Returned instead of changing the notes while the notebook is read-only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("The notebook is read-only")]
+pub struct ReadOnly;
+
+/// What importing a vault added to the notebook
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VaultImportReport {
+    pub n_notes: usize,
+    pub n_attachments: usize,
+    /// Tags that were not in the notebook before
+    pub created_tags: Vec<String>,
+    pub unresolved_links: Vec<UnresolvedLink>,
+}
+
+/// What exporting the notes as a website wrote
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SiteExportReport {
+    pub n_pages: usize,
+    pub n_attachments: usize,
+    /// Links to notes that are not on the website
+    pub invalid_links: Vec<UnresolvedLink>,
+}
+
+/// What importing a tag bundle added to the notebook
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TagBundleImportReport {
+    pub n_notes: usize,
+    pub n_attachments: usize,
+    /// Ids of the notes that were already in the notebook, so they were not imported
+    pub skipped_notes: Vec<String>,
+    /// Tags that were not in the notebook before
+    pub created_tags: Vec<String>,
+}
+
+/// A file in the notes directory that is not a note, with the notes using it
+#[derive(Debug, Clone)]
+pub struct AttachmentUsage {
+    pub file: gio::File,
+    /// Size in bytes
+    pub size: u64,
+    /// Notes having it in their attachments or linking to it, including the trashed ones
+    pub notes: Vec<Note>,
+}
+
+impl AttachmentUsage {
+    /// Whether no note uses it, so it can be deleted
+    pub fn is_orphan(&self) -> bool {
+        self.notes.is_empty()
+    }
+}
+
+/// Which notes use each attachment, as of when it was built
+#[derive(Debug)]
+struct AttachmentIndex {
+    notes: Vec<Note>,
+    references: AttachmentReferences,
+}
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct NoteManager {
+        pub directory: OnceCell<gio::File>,
+        pub repository: OnceCell<NoteRepository>,
+        pub note_list: RefCell<Option<NoteList>>,
+        pub tag_list: RefCell<Option<TagList>>,
+        pub saved_search_list: RefCell<Option<SavedSearchList>>,
+        pub notebook_settings: RefCell<Option<NotebookSettings>>,
+        pub daily_word_counts: RefCell<DailyWordCounts>,
+        /// Tags that can still be restored, from the oldest deletion
+        pub deleted_tags: RefCell<Vec<DeletedTagData>>,
+        pub is_syncing: Cell<bool>,
+        pub is_offline_mode: Cell<bool>,
+        pub is_read_only: Cell<bool>,
+        pub load_progress: Cell<f64>,
+        /// Cancels the running load, so a newer one can take its place
+        pub load_cancellable: RefCell<Option<gio::Cancellable>>,
+        /// Waited for before loading, like on a slow disk, so a test can start another load
+        #[cfg(test)]
+        pub load_delay: Cell<Duration>,
+        pub change_journal: RefCell<ChangeJournal>,
+        pub trash: RefCell<Option<Rc<dyn Trash>>>,
+        pub trashed_notes: RefCell<Option<gtk::FilterListModel>>,
+        pub tag_index: RefCell<TagIndex>,
+        pub journal: RefCell<Option<Journal>>,
+        /// Notes saved since the last successful push
+        pub unpushed_notes: RefCell<HashSet<NoteId>>,
+        pub n_uncommitted_changes: Cell<u32>,
+        /// Commits that are not pushed and not pulled, as of when the remote was last fetched
+        pub ahead_behind: Cell<AheadBehind>,
+        pub ahead_behind_source_id: RefCell<Option<glib::SourceId>>,
+        pub directory_monitor: OnceCell<gio::FileMonitor>,
+        pub pending_changes_source_id: RefCell<Option<glib::SourceId>>,
+        pub recovery_dir: OnceCell<PathBuf>,
+        pub recovery_writer: OnceCell<RecoveryWriter>,
+        /// Last modified date of the notes when their recovery copy was last written
+        pub recovery_snapshots: RefCell<HashMap<NoteId, DateTime>>,
+        /// Copies left from the last time the app stopped without saving them
+        pub recovered_notes: RefCell<Vec<RecoveredNote>>,
+        /// Note files that were removed, in case they were moved
+        pub file_moves: RefCell<FileMoves>,
+        pub metadata_index_path: OnceCell<PathBuf>,
+        /// Of the content of the notes, or `None` until it is built after they are loaded
+        pub search_index: RefCell<Option<SearchIndex>>,
+        /// Notes changed or added since the search index was last updated
+        pub unindexed_notes: RefCell<HashSet<NoteId>>,
+        pub search_index_source_id: RefCell<Option<glib::SourceId>>,
+        pub search_index_path: OnceCell<PathBuf>,
+        /// Dropped once the notes change, and built again when needed
+        pub attachment_index: RefCell<Option<Rc<AttachmentIndex>>>,
+        /// Increased each time the notes change, so an index of older notes is not kept
+        pub attachment_index_generation: Cell<u32>,
+        pub trash_size: Cell<u64>,
+        /// Generation of the attachment index the trash size was computed with
+        pub trash_size_generation: Cell<Option<u32>>,
+        /// Files in the directory that are not notes, from its exclusions file
+        pub exclusions: RefCell<Exclusions>,
+        /// Note files that failed to load, so the user can fix or exclude them
+        pub problem_list: ProblemList,
+        /// Notes whose links were not updated when the note they link to was renamed
+        pub attention_list: AttentionList,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NoteManager {
+        const NAME: &'static str = "NwtyNoteManager";
+        type Type = super::NoteManager;
+    }
+
+    impl ObjectImpl for NoteManager {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("search-index-changed", &[], <()>::static_type().into())
+                        .build(),
+                    Signal::builder("deleted-tags-changed", &[], <()>::static_type().into())
+                        .build(),
+                ]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecObject::new(
+                        "directory",
+                        "Directory",
+                        "Directory where the notes are stored",
+                        gio::File::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "repository",
+                        "Repository",
+                        "Repository where the notes are stored",
+                        NoteRepository::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "note-list",
+                        "Note List",
+                        "List of notes",
+                        NoteList::static_type(),
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "tag-list",
+                        "Tag List",
+                        "List of tags",
+                        TagList::static_type(),
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "saved-search-list",
+                        "Saved Search List",
+                        "List of saved searches",
+                        SavedSearchList::static_type(),
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecObject::new(
+                        "notebook-settings",
+                        "Notebook Settings",
+                        "Settings shared by the devices the notes are synced to",
+                        NotebookSettings::static_type(),
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "n-trashed-notes",
+                        "Number of Trashed Notes",
+                        "Number of notes in the trash",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt64::new(
+                        "trash-size",
+                        "Trash Size",
+                        "Bytes freed by emptying the trash",
+                        0,
+                        u64::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "pending-changes",
+                        "Pending Changes",
+                        "Number of changes that are not on the remote yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "ahead",
+                        "Ahead",
+                        "Number of commits that are not pushed to the remote yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "behind",
+                        "Behind",
+                        "Number of commits of the remote that are not pulled yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "words-today",
+                        "Words Today",
+                        "Number of words written today",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecDouble::new(
+                        "load-progress",
+                        "Load Progress",
+                        "Fraction of the notes that are loaded",
+                        0.0,
+                        1.0,
+                        0.0,
+                        glib::ParamFlags::READABLE,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-syncing",
+                        "Is Syncing",
+                        "Whether the session is currently syncing",
+                        false,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-offline-mode",
+                        "Is Offline Mode",
+                        "Whether the repo syncs to a remote repo",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-read-only",
+                        "Is Read Only",
+                        "Whether the notes are only read and pulled, without being changed",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "directory" => {
+                    let directory = value.get().unwrap();
+                    self.directory.set(directory).unwrap();
+                }
+                "repository" => {
+                    let repository = value.get().unwrap();
+                    self.repository.set(repository).unwrap();
+                }
+                "note-list" => {
+                    let note_list = value.get().unwrap();
+                    self.note_list.replace(Some(note_list));
+                }
+                "tag-list" => {
+                    let tag_list = value.get().unwrap();
+                    self.tag_list.replace(Some(tag_list));
+                }
+                "saved-search-list" => {
+                    let saved_search_list = value.get().unwrap();
+                    self.saved_search_list.replace(Some(saved_search_list));
+                }
+                "notebook-settings" => {
+                    let notebook_settings = value.get().unwrap();
+                    self.notebook_settings.replace(Some(notebook_settings));
+                }
+                "is-syncing" => {
+                    let is_syncing = value.get().unwrap();
+                    self.is_syncing.set(is_syncing);
+                }
+                "is-offline-mode" => {
+                    let is_offline_mode = value.get().unwrap();
+                    self.is_offline_mode.set(is_offline_mode);
+                }
+                "is-read-only" => {
+                    let is_read_only = value.get().unwrap();
+                    obj.set_is_read_only(is_read_only);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "directory" => obj.directory().to_value(),
+                "repository" => obj.repository().to_value(),
+                "note-list" => obj.note_list().to_value(),
+                "tag-list" => obj.tag_list().to_value(),
+                "saved-search-list" => obj.saved_search_list().to_value(),
+                "notebook-settings" => obj.notebook_settings().to_value(),
+                "n-trashed-notes" => obj.n_trashed_notes().to_value(),
+                "trash-size" => obj.trash_size().to_value(),
+                "pending-changes" => obj.pending_changes().to_value(),
+                "ahead" => obj.ahead().to_value(),
+                "behind" => obj.behind().to_value(),
+                "words-today" => obj.words_today().to_value(),
+                "load-progress" => obj.load_progress().to_value(),
+                "is-syncing" => self.is_syncing.get().to_value(),
+                "is-offline-mode" => self.is_offline_mode.get().to_value(),
+                "is-read-only" => obj.is_read_only().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_bindings();
+            obj.setup_signals();
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct NoteManager(ObjectSubclass<imp::NoteManager>);
+}
+
+impl NoteManager {
+    // TODO add ways to convert offline mode to online mode
+    /// For the notes in `directory`, which can be loaded right away from its files. Its
+    /// repository is only opened by [`Self::connect_repository`].
+    pub fn for_directory(directory: &gio::File, is_offline_mode: bool) -> Self {
+        let repository = NoteRepository::unopened(directory);
+        Self::with_repository(directory, &repository, is_offline_mode)
+    }
+
+    /// For the notes of `repository`, like one that was just cloned into `directory`
+    pub fn with_repository(
+        directory: &gio::File,
+        repository: &NoteRepository,
+        is_offline_mode: bool,
+    ) -> Self {
+        glib::Object::new(&[
+            ("directory", directory),
+            ("repository", repository),
+            ("is-offline-mode", &is_offline_mode),
+        ])
+        .expect("Failed to create NoteManager.")
+    }
+
+    pub fn directory(&self) -> gio::File {
+        self.imp().directory.get().unwrap().clone()
+    }
+
+    pub fn repository(&self) -> NoteRepository {
+        Clone::clone(self.imp().repository.get().unwrap())
+    }
+
+    pub fn note_list(&self) -> NoteList {
+        self.imp()
+            .note_list
+            .borrow()
+            .clone()
+            .expect("Please call `load_notes` first")
+    }
+
+    pub fn problem_list(&self) -> ProblemList {
+        self.imp().problem_list.clone()
+    }
+
+    pub fn attention_list(&self) -> AttentionList {
+        self.imp().attention_list.clone()
+    }
+
+    pub fn tag_list(&self) -> TagList {
+        self.imp()
+            .tag_list
+            .borrow()
+            .clone()
+            .expect("Please call `load_data_file` first")
+    }
+
+    pub fn saved_search_list(&self) -> SavedSearchList {
+        self.imp()
+            .saved_search_list
+            .borrow()
+            .clone()
+            .expect("Please call `load_data_file` first")
+    }
+
+    pub fn notebook_settings(&self) -> NotebookSettings {
+        self.imp()
+            .notebook_settings
+            .borrow()
+            .clone()
+            .expect("Please call `load_data_file` first")
+    }
+
+    pub fn is_offline_mode(&self) -> bool {
+        self.property("is-offline-mode")
+    }
+
+    /// Whether the notes are only read, like when browsing the repository of someone else.
+    /// Changes of the remote are still pulled, but nothing is saved, committed, or pushed.
+    pub fn is_read_only(&self) -> bool {
+        self.imp().is_read_only.get()
+    }
+
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        if is_read_only == self.is_read_only() {
+            return;
+        }
+
+        self.imp().is_read_only.set(is_read_only);
+        self.notify("is-read-only");
+    }
+
+    pub fn connect_is_read_only_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("is-read-only"), move |obj, _| f(obj))
+    }
+
+    /// Number of notes in the trash, kept up to date as notes are trashed and restored
+    pub fn n_trashed_notes(&self) -> u32 {
+        self.imp()
+            .trashed_notes
+            .borrow()
+            .as_ref()
+            .map_or(0, |trashed_notes| trashed_notes.n_items())
+    }
+
+    /// Bytes on disk of the notes in the trash and of the attachments only they use, as of
+    /// the last [`NoteManager::refresh_trash_size`]
+    pub fn trash_size(&self) -> u64 {
+        self.imp().trash_size.get()
+    }
+
+    fn set_trash_size(&self, trash_size: u64) {
+        if trash_size == self.trash_size() {
+            return;
+        }
+
+        self.imp().trash_size.set(trash_size);
+        self.notify("trash-size");
+    }
+
+    pub fn connect_trash_size_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("trash-size"), move |obj, _| f(obj))
+    }
+
+    /// Compute the size of the trash again from the files in the notes directory, off the
+    /// main thread, unless the notes did not change since it was last computed
+    pub async fn refresh_trash_size(&self) {
+        let imp = self.imp();
+
+        let attachment_index = self.attachment_index().await;
+        let generation = imp.attachment_index_generation.get();
+
+        if imp.trash_size_generation.get() == Some(generation) {
+            return;
+        }
+
+        let is_trashed = attachment_index
+            .notes
+            .iter()
+            .map(|note| note.metadata().is_trashed())
+            .collect::<Vec<_>>();
+        let trashed_file_names = attachment_index
+            .notes
+            .iter()
+            .filter(|note| note.metadata().is_trashed())
+            .map(file_name)
+            .collect::<Vec<_>>();
+        let references = attachment_index.references.clone();
+        let notes_path = self.directory().path().unwrap();
+
+        let res = spawn_blocking!(move || -> io::Result<u64> {
+            let note_stamps = note_file_stamps(&notes_path)?;
+            let files = attachment_files(&notes_path)?;
+            let attachments =
+                trash_only_attachments(&files, &references, |index| is_trashed[index]);
+            Ok(trash_size(&note_stamps, &trashed_file_names, &attachments))
+        })
+        .await;
+
+        match res {
+            Ok(size) => {
+                // Only kept while the notes are as they were when it was computed
+                let is_current = imp
+                    .attachment_index
+                    .borrow()
+                    .as_ref()
+                    .map_or(false, |index| Rc::ptr_eq(index, &attachment_index));
+                if is_current {
+                    imp.trash_size_generation.set(Some(generation));
+                }
+
+                self.set_trash_size(size);
+            }
+            Err(err) => log::warn!("Failed to compute the size of the trash: {:?}", err),
+        }
+    }
+
+    /// Number of changes that are not on the remote yet, either saved notes that are not
+    /// pushed or files that are not committed
+    pub fn pending_changes(&self) -> u32 {
+        let imp = self.imp();
+
+        // Saved notes are also uncommitted changes until the next sync, so they are not
+        // added together
+        let n_unpushed_notes = imp.unpushed_notes.borrow().len() as u32;
+        n_unpushed_notes.max(imp.n_uncommitted_changes.get())
+    }
+
+    /// Commits that are not pushed to the remote yet, as of when it was last fetched
+    pub fn ahead(&self) -> u32 {
+        self.imp().ahead_behind.get().n_ahead as u32
+    }
+
+    /// Commits of the remote that are not pulled yet, as of when it was last fetched
+    pub fn behind(&self) -> u32 {
+        self.imp().ahead_behind.get().n_behind as u32
+    }
+
+    fn set_ahead_behind(&self, ahead_behind: AheadBehind) {
+        let old_ahead_behind = self.imp().ahead_behind.replace(ahead_behind);
+
+        if old_ahead_behind.n_ahead != ahead_behind.n_ahead {
+            self.notify("ahead");
+        }
+        if old_ahead_behind.n_behind != ahead_behind.n_behind {
+            self.notify("behind");
+        }
+    }
+
+    /// Count the commits that are not pushed or not pulled again, fetching the remote first
+    /// when `is_fetch`, as otherwise it is compared as it was last fetched, like by a sync
+    async fn refresh_ahead_behind(&self, is_fetch: bool) {
+        if self.is_offline_mode() {
+            return;
+        }
+
+        let repo = self.repository();
+        let res = if is_fetch {
+            repo.fetch_ahead_behind().await
+        } else {
+            repo.ahead_behind().await
+        };
+
+        match res {
+            Ok(ahead_behind) => self.set_ahead_behind(ahead_behind),
+            Err(err) => log::warn!("Failed to compare with the remote: {:?}", err),
+        }
+    }
+
+    /// Fetch the remote from time to time while its changes are not synced right away, like
+    /// after it rejected the credentials, so it still shows how far behind the notes are
+    fn start_ahead_behind_refresh(&self) {
+        let imp = self.imp();
+
+        if self.is_offline_mode() || imp.ahead_behind_source_id.borrow().is_some() {
+            return;
+        }
+
+        let source_id = glib::timeout_add_seconds_local(
+            AHEAD_BEHIND_REFRESH_INTERVAL_SECS,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                if !obj.repository().allows_auto_sync() {
+                    spawn!(async move {
+                        obj.refresh_ahead_behind(true).await;
+                    });
+                }
+                glib::Continue(true)
+            }),
+        );
+        imp.ahead_behind_source_id.replace(Some(source_id));
+    }
+
+    /// Count `n_words` written in the notes now, for the daily word goal
+    pub fn record_words_written(&self, n_words: u32) {
+        if n_words == 0 {
+            return;
+        }
+
+        self.imp()
+            .daily_word_counts
+            .borrow_mut()
+            .add(&Local::now(), &Local, n_words);
+        self.notify("words-today");
+    }
+
+    /// Number of words written today, on this device or the ones synced with it
+    pub fn words_today(&self) -> u32 {
+        let today = Local::today().naive_local();
+        self.imp().daily_word_counts.borrow().on(today)
+    }
+
+    /// Words written on each day, as of the last sync on the other devices
+    pub fn daily_word_counts(&self) -> DailyWordCounts {
+        self.imp().daily_word_counts.borrow().clone()
+    }
+
+    /// Where the changes to the notes and the syncs of the repository are recorded
+    pub fn set_journal(&self, journal: &Journal) {
+        self.imp().journal.replace(Some(journal.clone()));
+        self.repository().set_journal(journal);
+    }
+
+    pub fn connect_n_trashed_notes_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("n-trashed-notes"), move |obj, _| f(obj))
+    }
+
+    /// Fraction of the notes that are loaded, while [`NoteManager::load`] runs
+    pub fn load_progress(&self) -> f64 {
+        self.imp().load_progress.get()
+    }
+
+    fn set_load_progress(&self, load_progress: f64) {
+        if load_progress == self.load_progress() {
+            return;
+        }
+
+        self.imp().load_progress.set(load_progress);
+        self.notify("load-progress");
+    }
+
+    /// Load the notes from `metadata_index` if there is one, or else from their files. When
+    /// `cancellable` is cancelled before the notes are shown, the list is dropped unseen, and
+    /// after, the notes are no longer added to it.
+    async fn load_notes(
+        &self,
+        metadata_index: Option<&MetadataIndex>,
+        cancellable: &gio::Cancellable,
+    ) -> anyhow::Result<()> {
+        cancellable.set_error_if_cancelled()?;
+
+        let note_list = NoteList::new();
+
+        let filter =
+            gtk::CustomFilter::new(|obj| obj.downcast_ref::<Note>().unwrap().index().is_trashed());
+        let trashed_notes = gtk::FilterListModel::new(Some(&note_list), Some(&filter));
+        trashed_notes.connect_items_changed(
+            clone!(@weak self as obj => move |_, _, removed, added| {
+                if removed != added {
+                    obj.notify("n-trashed-notes");
+                }
+            }),
+        );
+        self.imp().trashed_notes.replace(Some(trashed_notes));
+
+        // The tags of the notes of a cancelled load are no longer used
+        self.imp().tag_index.replace(TagIndex::default());
+        // Found again while loading, as they may have been fixed since
+        self.problem_list().clear();
+        // Its notes are replaced by the ones loaded
+        self.attention_list().clear();
+
+        self.setup_front_matter_profile(&note_list);
+        self.setup_tag_index(&note_list);
+        self.setup_attachment_index(&note_list);
+        self.setup_search_index(&note_list);
+
+        // Set before loading, so the notes can be shown as they are loaded
+        self.set_property("note-list", &note_list);
+
+        match metadata_index {
+            Some(metadata_index) => {
+                note_list.load_from_index(
+                    &self.directory(),
+                    &self.imp().exclusions.borrow(),
+                    metadata_index,
+                );
+                self.set_load_progress(1.0);
+            }
+            None => {
+                let exclusions = self.imp().exclusions.borrow().clone();
+                let problem_list = self.problem_list();
+                note_list
+                    .load_from_dir(
+                        &self.directory(),
+                        &exclusions,
+                        cancellable,
+                        |progress| self.set_load_progress(progress),
+                        |file, err| {
+                            problem_list
+                                .report(LoadProblem::from_error(&file.path().unwrap(), &err));
+                        },
+                    )
+                    .await?
+            }
+        }
+        self.notify("n-trashed-notes");
+
+        Ok(())
+    }
+
+    /// The index written when the notes were last saved, or `None` if there is none or it is
+    /// corrupt, in which case the notes are loaded from their files
+    async fn read_metadata_index(&self) -> Option<MetadataIndex> {
+        let file = gio::File::for_path(self.metadata_index_path());
+
+        let contents = match file.load_contents_future().await {
+            Ok((contents, _)) => contents,
+            Err(err) => {
+                log::info!("No metadata index, loading all notes: {:?}", err);
+                return None;
+            }
+        };
+
+        match MetadataIndex::parse(&contents, &self.directory().path().unwrap()) {
+            Ok(metadata_index) => Some(metadata_index),
+            Err(err) => {
+                log::warn!(
+                    "Failed to parse metadata index, loading all notes: {:?}",
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Load the note files that were changed, added, or removed since `metadata_index` was
+    /// written, like by another app or a sync while this one was not running
+    async fn verify_indexed_notes(&self, metadata_index: &MetadataIndex) {
+        let notes_path = self.directory().path().unwrap();
+
+        let stamps = {
+            let notes_path = notes_path.clone();
+            spawn_blocking!(move || note_file_stamps(&notes_path)).await
+        };
+        let stamps = match stamps {
+            Ok(stamps) => stamps,
+            Err(err) => {
+                log::error!("Failed to verify metadata index: {:?}", err);
+                return;
+            }
+        };
+
+        let reconciliation = metadata_index.reconcile(&stamps);
+
+        if reconciliation.is_empty() {
+            log::info!("All indexed notes are up to date");
+            return;
+        }
+
+        log::info!(
+            "Found {} changed, {} added, and {} removed notes since indexed",
+            reconciliation.stale.len(),
+            reconciliation.added.len(),
+            reconciliation.removed.len()
+        );
+
+        let note_list = self.note_list();
+
+        for file_name in &reconciliation.stale {
+            let note = match note_list.find_by_id(&NoteId::for_path(&notes_path.join(file_name))) {
+                Some(note) => note,
+                None => continue,
+            };
+
+            // Changed since it was shown, so the changes are saved over the file instead
+            if !note.is_saved() {
+                continue;
+            }
+
+            if let Err(err) = note.reload().await {
+                log::warn!("Failed to reload `{}`: {:?}", note, err);
+            }
+        }
+
+        let mut added_notes = Vec::with_capacity(reconciliation.added.len());
+        for file_name in &reconciliation.added {
+            if self.is_excluded(&notes_path.join(file_name)) {
+                continue;
+            }
+
+            let path = notes_path.join(file_name);
+            match Note::load(&gio::File::for_path(&path)).await {
+                Ok(note) => {
+                    self.problem_list().resolve(&path);
+                    added_notes.push(note);
+                }
+                Err(err) => self
+                    .problem_list()
+                    .report(LoadProblem::from_error(&path, &err)),
+            }
+        }
+        if !added_notes.is_empty() {
+            note_list.append_many(added_notes);
+        }
+
+        let removed_ids = reconciliation
+            .removed
+            .iter()
+            .map(|file_name| NoteId::for_path(&notes_path.join(file_name)))
+            .collect::<HashSet<_>>();
+        if !removed_ids.is_empty() {
+            note_list.retain(|note| !note.is_saved() || !removed_ids.contains(&note.id()));
+        }
+
+        self.notify("n-trashed-notes");
+        self.write_metadata_index().await;
+    }
+
+    /// Load the content of the notes shown from the metadata index, so it is not read on the
+    /// main thread once it is used, like when the notes are searched
+    async fn load_pending_contents(&self) {
+        for note in self.note_list().iter() {
+            if let Err(err) = note.load_pending_content().await {
+                log::warn!("Failed to load content of `{}`: {:?}", note, err);
+            }
+        }
+    }
+
+    /// Write the metadata of the saved notes, so they are shown right away the next time the
+    /// notes are loaded
+    pub async fn write_metadata_index(&self) {
+        let notes_path = self.directory().path().unwrap();
+        let index_path = self.metadata_index_path().to_path_buf();
+
+        // Notes with unsaved changes are left out, so they are loaded from their files
+        let notes = self
+            .note_list()
+            .iter()
+            .filter(Note::is_saved)
+            .map(|note| {
+                (
+                    file_name(&note),
+                    note.body_offset(),
+                    note.metadata().to_data(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let res = spawn_blocking!(move || -> anyhow::Result<()> {
+            let mut metadata_index = MetadataIndex::new(&notes_path);
+
+            for (file_name, body_offset, metadata) in notes {
+                // New notes that were never saved have no file
+                let stamp = fs::metadata(notes_path.join(&file_name))
+                    .ok()
+                    .and_then(|metadata| FileStamp::for_metadata(&metadata));
+
+                if let Some(stamp) = stamp {
+                    let entry = IndexEntry {
+                        stamp,
+                        body_offset,
+                        metadata,
+                    };
+                    metadata_index.insert(file_name, entry);
+                }
+            }
+
+            if let Some(parent) = index_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // Written next to it first, so it is never left half written
+            let temp_path = index_path.with_extension("tmp");
+            fs::write(&temp_path, metadata_index.serialize()?)?;
+            fs::rename(&temp_path, &index_path)?;
+
+            Ok(())
+        })
+        .await;
+
+        match res {
+            Ok(()) => log::info!("Metadata index written"),
+            Err(err) => log::warn!("Failed to write metadata index: {:?}", err),
+        }
+    }
+
+    /// The notes whose content has every word of `text`, the best match first, or `None`
+    /// while the search index is being built or if `text` has no words
+    pub fn search_contents(&self, text: &str) -> Option<Vec<(Note, IndexMatch)>> {
+        if query_words(text).is_empty() {
+            return None;
+        }
+
+        self.update_search_index();
+
+        let note_list = self.note_list();
+        let search_index = self.imp().search_index.borrow();
+        let matches = search_index
+            .as_ref()?
+            .query(text)
+            .into_iter()
+            .filter_map(|index_match| {
+                let note = note_list.find_by_id(&NoteId::for_path(&index_match.file_name))?;
+                Some((note, index_match))
+            })
+            .collect();
+
+        Some(matches)
+    }
+
+    /// Called when the search index is built or when it is updated with the changes of the
+    /// notes, so the results of [`Self::search_contents`] may differ
+    pub fn connect_search_index_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("search-index-changed", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    /// Build the search index from the one written last time, indexing again the note files
+    /// that changed since, so only those are read
+    async fn build_search_index(&self) {
+        let note_list = self.note_list();
+        let notes_path = self.directory().path().unwrap();
+        let index_path = self.search_index_path().to_path_buf();
+
+        let res = spawn_blocking!(move || -> anyhow::Result<(SearchIndex, usize)> {
+            let mut search_index = match fs::read(&index_path) {
+                Ok(bytes) => SearchIndex::parse(&bytes, &notes_path).unwrap_or_else(|err| {
+                    log::warn!(
+                        "Failed to parse search index, indexing all notes: {:?}",
+                        err
+                    );
+                    SearchIndex::new(&notes_path)
+                }),
+                Err(err) => {
+                    log::info!("No search index, indexing all notes: {:?}", err);
+                    SearchIndex::new(&notes_path)
+                }
+            };
+
+            let stamps = note_file_stamps(&notes_path)?;
+            let reconciliation = search_index.reconcile(&stamps);
+
+            for file_name in &reconciliation.removed {
+                search_index.remove(file_name);
+            }
+
+            let changed = reconciliation
+                .stale
+                .iter()
+                .chain(&reconciliation.added)
+                .collect::<Vec<_>>();
+
+            for file_name in &changed {
+                match fs::read_to_string(notes_path.join(file_name)) {
+                    Ok(text) => {
+                        let content = NoteData::parse_tolerant(&text).0.content;
+                        search_index.insert(file_name, &content, Some(stamps[*file_name]));
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to index note file `{}`: {:?}", file_name, err);
+                        search_index.remove(file_name);
+                    }
+                }
+            }
+
+            search_index.compact();
+
+            Ok((search_index, changed.len()))
+        })
+        .await;
+
+        let (search_index, n_indexed) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                log::error!("Failed to build search index: {:?}", err);
+                return;
+            }
+        };
+
+        // Loaded again meanwhile, so it is built for the newer notes instead
+        if self.note_list() != note_list {
+            return;
+        }
+
+        log::info!("Search index built, with {} note files indexed", n_indexed);
+
+        let imp = self.imp();
+        {
+            // Changed since they were read from their files, or never saved
+            let mut unindexed_notes = imp.unindexed_notes.borrow_mut();
+            for note in note_list.iter() {
+                if !note.is_saved() || !search_index.contains(&file_name(&note)) {
+                    unindexed_notes.insert(note.id());
+                }
+            }
+        }
+        imp.search_index.replace(Some(search_index));
+
+        self.flush_search_index();
+        self.emit_by_name::<()>("search-index-changed", &[]);
+
+        self.write_search_index().await;
+    }
+
+    /// Write the search index, so only the notes that changed are indexed again the next
+    /// time the notes are loaded
+    async fn write_search_index(&self) {
+        self.update_search_index();
+
+        let mut search_index = match *self.imp().search_index.borrow() {
+            Some(ref search_index) => search_index.clone(),
+            None => return,
+        };
+        let notes_path = self.directory().path().unwrap();
+        let index_path = self.search_index_path().to_path_buf();
+
+        // Indexed from their changes, which are now in their files
+        let saved_file_names = self
+            .note_list()
+            .iter()
+            .filter(Note::is_saved)
+            .map(|note| file_name(&note))
+            .collect::<Vec<_>>();
+
+        let res = spawn_blocking!(move || -> anyhow::Result<()> {
+            for file_name in saved_file_names {
+                let stamp = fs::metadata(notes_path.join(&file_name))
+                    .ok()
+                    .and_then(|metadata| FileStamp::for_metadata(&metadata));
+
+                if let Some(stamp) = stamp {
+                    search_index.set_missing_stamp(&file_name, stamp);
+                }
+            }
+
+            if let Some(parent) = index_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // Written next to it first, so it is never left half written
+            let temp_path = index_path.with_extension("tmp");
+            fs::write(&temp_path, search_index.serialize()?)?;
+            fs::rename(&temp_path, &index_path)?;
+
+            Ok(())
+        })
+        .await;
+
+        match res {
+            Ok(()) => log::info!("Search index written"),
+            Err(err) => log::warn!("Failed to write search index: {:?}", err),
+        }
+    }
+
+    /// Update the search index with the notes changed, added, or removed since it was last
+    /// updated, if it is built
+    fn update_search_index(&self) {
+        if self.flush_search_index() {
+            self.emit_by_name::<()>("search-index-changed", &[]);
+        }
+    }
+
+    /// Like [`Self::update_search_index`], without notifying, returning whether it changed
+    fn flush_search_index(&self) -> bool {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.search_index_source_id.take() {
+            source_id.remove();
+        }
+
+        if imp.search_index.borrow().is_none() {
+            return false;
+        }
+
+        // Read before borrowing the index, as reading the content of a note may load it
+        let note_list = self.note_list();
+        let contents = imp
+            .unindexed_notes
+            .take()
+            .into_iter()
+            .filter_map(|note_id| note_list.find_by_id(&note_id))
+            .map(|note| {
+                let buffer = note.buffer();
+                let (start, end) = buffer.bounds();
+                (file_name(&note), buffer.text(&start, &end, true))
+            })
+            .collect::<Vec<_>>();
+
+        let mut search_index = imp.search_index.borrow_mut();
+        let search_index = search_index.as_mut().unwrap();
+
+        for (name, content) in &contents {
+            search_index.insert(name, content, None);
+        }
+
+        let has_removed = search_index.retain(|name| {
+            note_list
+                .find_by_id(&NoteId::for_path(name))
+                .map_or(false, |note| file_name(&note) == name)
+        });
+
+        !contents.is_empty() || has_removed
+    }
+
+    /// Update the search index once the notes stay unchanged for a moment
+    fn queue_search_index_update(&self) {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.search_index_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            SEARCH_INDEX_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().search_index_source_id.replace(None);
+                obj.update_search_index();
+            }),
+        );
+        imp.search_index_source_id.replace(Some(source_id));
+    }
+
+    /// Load the tags, saved searches, and settings, unless `cancellable` is cancelled first
+    async fn load_data_file(&self, cancellable: &gio::Cancellable) -> anyhow::Result<()> {
+        #[cfg(test)]
+        or_cancelled(
+            cancellable,
+            glib::timeout_future(self.imp().load_delay.get()),
+        )
+        .await?;
+
+        let data_file_path = self.data_file_path();
+        let file = gio::File::for_path(&data_file_path);
+
+        let data = match or_cancelled(cancellable, file.load_contents_future()).await? {
+            Ok((file_content, _)) => {
+                log::info!("Data file found at `{}` is loaded successfully", file.uri(),);
+                DataFile::parse(&file_content).unwrap_or_else(|err| {
+                    report_error(&gettext("Failed to load tags"), &err);
+                    DataFile::default()
+                })
+            }
+            Err(err) => {
+                log::warn!(
+                    "Falling back to default data, Failed to load data file: {:?}",
+                    err
+                );
+                DataFile::default()
+            }
+        };
+
+        let tag_list = data
+            .ordered_tag_names()
+            .iter()
+            .map(|name| Tag::new(name))
+            .collect::<TagList>();
+        self.set_property("tag-list", tag_list);
+        self.set_property(
+            "saved-search-list",
+            SavedSearchList::from_data(&data.saved_searches),
+        );
+        self.set_property(
+            "notebook-settings",
+            NotebookSettings::from_data(&data.notebook_settings),
+        );
+        self.imp().daily_word_counts.replace(data.daily_word_counts);
+        self.notify("words-today");
+        self.set_deleted_tags(data.deleted_tags);
+
+        Ok(())
+    }
+
+    /// Read the patterns of the files that are neither loaded as notes nor committed, writing
+    /// the default ones when there is no exclusions file yet. Invalid patterns are reported
+    /// and skipped.
+    async fn load_exclusions(&self) {
+        let file = self.directory().child(EXCLUSIONS_FILE_NAME);
+
+        let text = match file.load_contents_future().await {
+            Ok((contents, _)) => String::from_utf8_lossy(&contents).into_owned(),
+            Err(err) if err.matches(gio::IOErrorEnum::NotFound) => {
+                if !self.is_read_only() {
+                    let res = file
+                        .replace_contents_future(
+                            DEFAULT_EXCLUSIONS.as_bytes(),
+                            None,
+                            false,
+                            gio::FileCreateFlags::NONE,
+                        )
+                        .await;
+
+                    if let Err((_, err)) = res {
+                        log::warn!("Failed to write default exclusions: {:?}", err);
+                    }
+                }
+
+                DEFAULT_EXCLUSIONS.to_string()
+            }
+            Err(err) => {
+                report_error(&gettext("Failed to load exclusions"), &err.into());
+                String::new()
+            }
+        };
+
+        let (exclusions, errors) = Exclusions::parse(&text);
+
+        for err in errors {
+            report_error(&gettext("Invalid pattern in exclusions"), &err.into());
+        }
+
+        self.imp().exclusions.replace(exclusions.clone());
+        self.repository().set_exclusions(exclusions).await;
+    }
+
+    /// Whether `path`, in the notes directory, is left out by its exclusions file
+    fn is_excluded(&self, path: &Path) -> bool {
+        let notes_path = self.directory().path().unwrap();
+
+        match path.strip_prefix(&notes_path) {
+            Ok(relative_path) => self
+                .imp()
+                .exclusions
+                .borrow()
+                .is_excluded(relative_path, false),
+            Err(_) => false,
+        }
+    }
+
+    /// Take the tags, saved searches, word counts, and notebook settings from the data file
+    /// changed by a sync. Tags are only ever added, saved searches are added or take the synced
+    /// query, word counts take the larger of each day, and the notebook settings are only taken
+    /// if they were changed after the ones of this device. Otherwise, the ones of this device
+    /// are saved back on the next sync.
+    async fn reload_data_file(&self) {
+        let file = gio::File::for_path(self.data_file_path());
+
+        let data = match file.load_contents_future().await {
+            Ok((file_content, _)) => match DataFile::parse(&file_content) {
+                Ok(data) => data,
+                Err(err) => {
+                    log::warn!(
+                        "Keeping tags and notebook settings, the synced data file is invalid: {:?}",
+                        err
+                    );
+                    return;
+                }
+            },
+            Err(err) => {
+                log::warn!(
+                    "Keeping tags and notebook settings, failed to load synced data file: {:?}",
+                    err
+                );
+                return;
+            }
+        };
+
+        self.tag_list()
+            .merge_synced_names(&data.ordered_tag_names());
+        self.saved_search_list().merge_synced(&data.saved_searches);
+
+        let daily_word_counts = self.daily_word_counts().union(&data.daily_word_counts);
+        self.imp().daily_word_counts.replace(daily_word_counts);
+        self.notify("words-today");
+
+        if !data.deleted_tags.is_empty() {
+            let deleted_tags = self.imp().deleted_tags.take();
+            self.set_deleted_tags(sorted_deleted_tags(
+                deleted_tags.into_iter().chain(data.deleted_tags),
+            ));
+        }
+
+        let notebook_settings = self.notebook_settings();
+        let current = notebook_settings.to_data();
+        let synced = data.notebook_settings;
+
+        if synced.is_newer_than(&current) {
+            log::info!(
+                "Sync: Using notebook settings changed on another device at {:?}",
+                synced.modified
+            );
+            notebook_settings.update(&synced);
+        } else if synced.modified != current.modified {
+            log::warn!(
+                "Sync: Discarded notebook settings changed on another device at {:?}, which are older than the ones changed at {:?}",
+                synced.modified,
+                current.modified
+            );
+        }
+    }
+
+    pub async fn save_all_notes(&self) -> anyhow::Result<()> {
+        self.save_all_notes_with_progress(|_| {}).await
+    }
+
+    /// Save the notes with unsaved changes, a few at a time, calling `progress` with the
+    /// fraction of them that are done. Fails with [`NotesNotSaved`] if some could not be saved,
+    /// or with [`ReadOnly`] if there are some while the notebook is read-only.
+    pub async fn save_all_notes_with_progress(&self, progress: impl Fn(f64)) -> anyhow::Result<()> {
+        let (held_back_notes, unsaved_notes): (Vec<_>, Vec<_>) = self
+            .note_list()
+            .take_unsaved_notes()
+            .into_iter()
+            .partition(|note| note.problem().needs_decision());
+
+        if !held_back_notes.is_empty() {
+            log::info!(
+                "Not saving {} notes until their file problems are solved",
+                held_back_notes.len()
+            );
+            self.note_list().restore_unsaved_notes(held_back_notes);
+        }
+
+        if unsaved_notes.is_empty() {
+            log::info!("No unsaved notes, skipping save...");
+            return Ok(());
+        }
+
+        if let Err(err) = self.ensure_writable() {
+            // Kept, so they are saved if the notebook is made writable again
+            self.note_list().restore_unsaved_notes(unsaved_notes);
+            return Err(err);
+        }
+
+        let n_notes = unsaved_notes.len();
+
+        // Taken all at once, so each note is saved as it is now even if it has to wait
+        let mut pending_saves = Vec::with_capacity(n_notes);
+        let mut failed_notes = Vec::new();
+
+        for note in unsaved_notes {
+            match note.contents() {
+                Ok(contents) => pending_saves.push((note, contents)),
+                Err(err) => {
+                    note.set_save_failed(&err);
+                    failed_notes.push((note, err));
+                }
+            }
+        }
+
+        let n_failed_before = failed_notes.len();
+        let failed_writes = write_batch(
+            pending_saves,
+            MAX_WRITES_IN_FLIGHT,
+            |(note, contents)| {
+                let note = note.clone();
+                let contents = contents.clone();
+                async move { self.save_note_contents(&note, contents).await }
+            },
+            |n_saved| progress((n_failed_before + n_saved) as f64 / n_notes as f64),
+        )
+        .await;
+        failed_notes.extend(
+            failed_writes
+                .into_iter()
+                .map(|((note, _), err)| (note, err)),
+        );
+
+        self.refresh_pending_changes().await;
+        self.write_metadata_index().await;
+        self.write_search_index().await;
+
+        if failed_notes.is_empty() {
+            return Ok(());
+        }
+
+        let mut titles = Vec::with_capacity(failed_notes.len());
+
+        for (note, err) in &failed_notes {
+            let title = note.metadata().title();
+            let message = if title.is_empty() {
+                gettext("Failed to save an untitled note")
+            } else {
+                gettext!("Failed to save “{}”", title)
+            };
+            report_error(&message, err);
+
+            titles.push(if title.is_empty() {
+                gettext("Untitled Note")
+            } else {
+                title
+            });
+        }
+
+        // Still have to be saved, as they were not changed since
+        self.note_list()
+            .restore_unsaved_notes(failed_notes.into_iter().map(|(note, _)| note));
+
+        Err(NotesNotSaved { titles }.into())
+    }
+
+    /// Write `note` to its file even if it is saved, like to recreate it after it was deleted
+    /// elsewhere or to replace the changes made to it there
+    pub async fn write_note_file(&self, note: &Note) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        let contents = note.contents()?;
+        self.save_note_contents(note, contents).await
+    }
+
+    /// Remove `note`, whose file was deleted elsewhere, from the note list without recreating
+    /// the file. The deletion is committed on the next sync.
+    pub fn discard_note(&self, note: &Note) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        self.journal().record(JournalEvent::NoteDeleted {
+            id: journal_id(note),
+        });
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .record_deleted(&note.id(), &note.metadata().title());
+        self.remove_recovery_copy(note);
+        self.note_list().remove(&note.id());
+
+        log::info!("Discarded `{}`", note);
+
+        Ok(())
+    }
+
+    /// Write `contents` taken from `note`, and keep track of it being changed
+    async fn save_note_contents(&self, note: &Note, contents: glib::Bytes) -> anyhow::Result<()> {
+        let previous_title = note.saved_title();
+        let bytes = contents.len() as u64;
+
+        note.save_contents(contents).await?;
+
+        self.journal().record(JournalEvent::NoteSaved {
+            id: journal_id(note),
+            bytes,
+        });
+        self.remove_recovery_copy(note);
+        self.imp().unpushed_notes.borrow_mut().insert(note.id());
+
+        let title = note.metadata().title();
+        let mut change_journal = self.imp().change_journal.borrow_mut();
+
+        if title != previous_title && !previous_title.is_empty() {
+            change_journal.record_renamed(&note.id(), &previous_title, &title);
+        } else {
+            change_journal.record_edited(&note.id(), &title);
+        }
+
+        Ok(())
+    }
+
+    pub async fn save_data_file(&self) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        let tag_names = self
+            .tag_list()
+            .iter()
+            .map(|tag| tag.name())
+            .collect::<Vec<_>>();
+        self.purge_deleted_tags();
+
+        let data = DataFile {
+            tag_list: tag_names.clone(),
+            tag_order: tag_names,
+            saved_searches: self.saved_search_list().to_data(),
+            deleted_tags: self.imp().deleted_tags.borrow().clone(),
+            daily_word_counts: self.daily_word_counts(),
+            notebook_settings: self.notebook_settings().to_data(),
+            ..DataFile::default()
+        };
+        let data_bytes = data.to_canonical_bytes()?;
+
+        // FIXME consider making backup on all replace_contents
+        let res = gio::File::for_path(self.data_file_path())
+            .replace_contents_future(data_bytes, None, false, gio::FileCreateFlags::NONE)
+            .await;
+
+        if let Err((_, err)) = res {
+            let err = anyhow::Error::from(err);
+            report_error(&gettext("Failed to save tags"), &err);
+            return Err(err);
+        }
+
+        log::info!("Successfully saved data file");
+
+        Ok(())
+    }
+
+    /// Create an empty note, or one with the template from the notebook settings
+    pub fn create_note(&self) -> anyhow::Result<Note> {
+        self.create_note_with_tag(None)
+    }
+
+    /// Like [`NoteManager::create_note`], tagged with `tag` if there is one, like the tag whose
+    /// notes are shown
+    pub fn create_note_with_tag(&self, tag: Option<&Tag>) -> anyhow::Result<Note> {
+        self.ensure_writable()?;
+
+        let new_note = self.new_note();
+
+        log::info!("Created note `{}`", new_note);
+        self.record_note_created(&new_note);
+
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .record_created(&new_note.id(), &new_note.metadata().title());
+
+        self.note_list().append(new_note.clone());
+
+        let note_template = self.notebook_settings().note_template();
+        if !note_template.is_empty() {
+            new_note.buffer().set_text(&note_template);
+
+            if self.notebook_settings().derive_title_from_heading() {
+                if let Some(heading) = first_heading(&note_template).filter(|h| !h.is_empty()) {
+                    new_note.metadata().set_title(heading);
+                }
+            }
+        }
+
+        if let Some(tag) = tag {
+            if let Err(err) = new_note.metadata().tag_list().append(tag.clone()) {
+                log::warn!(
+                    "Failed to tag `{}` with `{}`: {:?}",
+                    new_note,
+                    tag.name(),
+                    err
+                );
+            }
+        }
+
+        Ok(new_note)
+    }
+
+    /// Create a note with `title` and `content`, tagged with `tag_names`. Tags that are not
+    /// in the tag list yet are created.
+    pub fn create_note_with_content(
+        &self,
+        title: &str,
+        content: &str,
+        tag_names: &[String],
+    ) -> anyhow::Result<Note> {
+        self.ensure_writable()?;
+
+        let new_note = self.new_note();
+
+        log::info!("Created note `{}` with title `{}`", new_note, title);
+        self.record_note_created(&new_note);
+
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .record_created(&new_note.id(), title);
+
+        // Append first, so the changes below mark the note as unsaved in the list
+        self.note_list().append(new_note.clone());
+
+        let metadata = new_note.metadata();
+        metadata.set_title(title);
+        new_note.buffer().set_text(content);
+
+        let tag_list = self.tag_list();
+        let note_tag_list = metadata.tag_list();
+
+        for name in tag_names {
+            let tag = tag_list.get_with_name(name).unwrap_or_else(|| {
+                let tag = Tag::new(name);
+                if let Err(err) = tag_list.append(tag.clone()) {
+                    log::warn!("Failed to append tag `{}`: {:?}", name, err);
+                }
+                tag
+            });
+
+            if let Err(err) = note_tag_list.append(tag) {
+                log::warn!("Failed to tag `{}` with `{}`: {:?}", new_note, name, err);
+            }
+        }
+
+        Ok(new_note)
+    }
+
+    /// Remove `note` and its attachments from the notes directory, the way `mode` says, and
+    /// from the note list. The deletion is committed on the next sync.
+    ///
+    /// When the file of the note can't be removed, the note is kept and the error is returned.
+    /// Attachments that can't be removed are only logged, as the note is already gone.
+    pub async fn delete_note(&self, note: &Note, mode: DeletionMode) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        let trash = self.trash();
+        let metadata = note.metadata();
+
+        self.delete_note_file(note, mode).await?;
+
+        for attachment in metadata.attachment_list().to_data() {
+            let file = gio::File::for_path(&attachment.file);
+
+            if let Err(err) = remove_file(trash.as_ref(), &file, mode).await {
+                log::warn!(
+                    "Failed to remove attachment `{}` of `{}`: {:?}",
+                    file.uri(),
+                    note,
+                    err
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the file of `note`, the way `mode` says, and the note from the note list, leaving
+    /// its attachments
+    async fn delete_note_file(&self, note: &Note, mode: DeletionMode) -> anyhow::Result<()> {
+        let removal = remove_file(self.trash().as_ref(), &note.file(), mode).await?;
+        log::info!("Deleted `{}` permanently: {:?}", note, removal);
+        self.journal().record(JournalEvent::NoteDeleted {
+            id: journal_id(note),
+        });
+
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .record_deleted(&note.id(), &note.metadata().title());
+
+        self.note_list().remove(&note.id());
+
+        Ok(())
+    }
+
+    /// Write every note again with the front matter in the profile of the notebook settings,
+    /// like after it was changed, as otherwise only the notes saved since are written in it.
+    /// Returns how many notes were written.
+    pub async fn rewrite_front_matter(&self) -> anyhow::Result<usize> {
+        let note_list = self.note_list();
+        let n_notes = note_list.len();
+
+        log::info!(
+            "Rewriting {} notes as `{}`",
+            n_notes,
+            self.notebook_settings().front_matter_profile().name()
+        );
+
+        note_list.restore_unsaved_notes(note_list.iter());
+        self.save_all_notes().await?;
+
+        Ok(n_notes)
+    }
+
+    /// Take the title of every note from its first level 1 heading, like after that was turned
+    /// on in the notebook settings. Notes without one, or with an empty one, keep their
+    /// title. Returns how many titles were changed.
+    pub async fn derive_titles_from_headings(&self) -> anyhow::Result<usize> {
+        self.ensure_writable()?;
+
+        let mut n_changed = 0;
+
+        for note in self.note_list().iter() {
+            let buffer = note.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+            let content = buffer.text(&start_iter, &end_iter, true);
+
+            let heading = match first_heading(&content) {
+                Some(heading) if !heading.is_empty() => heading,
+                _ => continue,
+            };
+
+            let metadata = note.metadata();
+            if metadata.title() != heading {
+                metadata.set_title(heading);
+                n_changed += 1;
+            }
+        }
+
+        log::info!("Took {} titles from the first heading", n_changed);
+
+        self.save_all_notes().await?;
+
+        Ok(n_changed)
+    }
+
+    /// Delete the notes in the trash permanently, the way `mode` says, with the attachments
+    /// that only they use. The notes that can't be deleted are kept, and fail it once the
+    /// others are deleted. Returns how many were deleted.
+    ///
+    /// A note restored while the others are deleted is kept, and so are its attachments.
+    pub async fn empty_trash(&self, mode: DeletionMode) -> anyhow::Result<usize> {
+        self.ensure_writable()?;
+
+        let attachment_index = self.attachment_index().await;
+        let notes_path = self.directory().path().unwrap();
+        let files = spawn_blocking!(move || attachment_files(&notes_path)).await?;
+        let attachments = trash_only_attachments(&files, &attachment_index.references, |index| {
+            attachment_index.notes[index].metadata().is_trashed()
+        });
+
+        let trashed_notes = self
+            .note_list()
+            .iter()
+            .filter(|note| note.metadata().is_trashed())
+            .collect::<Vec<_>>();
+
+        let mut n_deleted = 0;
+        let mut n_failed = 0;
+        for note in &trashed_notes {
+            if !note.metadata().is_trashed() {
+                log::info!("Keeping `{}`, as it was restored", note);
+                continue;
+            }
+
+            match self.delete_note_file(note, mode).await {
+                Ok(()) => n_deleted += 1,
+                Err(err) => {
+                    log::error!("Failed to delete `{}` from the trash: {:?}", note, err);
+                    n_failed += 1;
+                }
+            }
+        }
+
+        // The notes that were kept still use theirs
+        let note_list = self.note_list();
+        let deleted_attachments = attachments
+            .into_iter()
+            .filter(|file| {
+                attachment_index
+                    .references
+                    .referencing(&file.file_name())
+                    .iter()
+                    .all(|index| {
+                        let note = &attachment_index.notes[*index];
+                        note_list.get_index_of(&note.id()).is_none()
+                    })
+            })
+            .map(|file| gio::File::for_path(&file.path))
+            .collect::<Vec<_>>();
+
+        if !deleted_attachments.is_empty() {
+            if let Err(err) = self.delete_attachments(&deleted_attachments, mode).await {
+                log::warn!("Failed to delete the attachments of the trash: {:?}", err);
+            }
+        }
+
+        anyhow::ensure!(
+            n_failed == 0,
+            "Failed to delete {} of {} notes in the trash",
+            n_failed,
+            n_deleted + n_failed
+        );
+
+        Ok(n_deleted)
+    }
+
+    /// Take `notes` out of the trash. The ones that are not in it, like when they are listed
+    /// twice, are left as they are. Returns how many were restored.
+    pub fn restore_notes(&self, notes: &[Note]) -> usize {
+        let mut n_restored = 0;
+
+        for note in notes {
+            let metadata = note.metadata();
+
+            if metadata.is_trashed() {
+                metadata.set_is_trashed(false);
+                n_restored += 1;
+            }
+        }
+
+        n_restored
+    }
+
+    /// Find the note stored in `file`, which must be inside the notes directory
+    pub fn note_for_file(&self, file: &gio::File) -> Option<Note> {
+        if !file.has_parent(Some(&self.directory())) {
+            return None;
+        }
+
+        let path = file.path()?;
+        self.note_list().find_by_id(&NoteId::for_path(&path))
+    }
+
+    /// Merge `source` into `target` and trash `source`. The returned changes can be undone.
+    pub fn merge_notes(&self, target: &Note, source: &Note) -> MergedNotes {
+        MergedNotes::merge(&self.tag_list(), target, source)
+    }
+
+    /// Groups of notes that are not trashed and have the same content, ignoring whitespace and
+    /// the front matter. With `is_near`, notes with mostly the same content are also grouped,
+    /// which takes much longer.
+    pub async fn find_duplicates(&self, is_near: bool) -> Vec<Vec<Note>> {
+        let notes = self
+            .note_list()
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+            .collect::<Vec<_>>();
+        let contents = notes
+            .iter()
+            .map(|note| {
+                let buffer = note.buffer();
+                let (start_iter, end_iter) = buffer.bounds();
+                buffer.text(&start_iter, &end_iter, true).to_string()
+            })
+            .collect::<Vec<_>>();
+
+        let groups = spawn_blocking!(move || {
+            if is_near {
+                near_duplicate_groups(&contents, NEAR_DUPLICATE_THRESHOLD)
+            } else {
+                duplicate_groups(&contents)
+            }
+        })
+        .await;
+
+        groups
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|index| notes[index].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Trash the `duplicates` other than `kept`, and make the links to them go to `kept`.
+    /// Returns the number of trashed notes.
+    pub fn keep_duplicate(&self, kept: &Note, duplicates: &[Note]) -> usize {
+        let trashed = duplicates
+            .iter()
+            .filter(|duplicate| *duplicate != kept)
+            .collect::<Vec<_>>();
+
+        for duplicate in &trashed {
+            duplicate.metadata().set_is_trashed(true);
+            log::info!("Trashed `{}` as a duplicate of `{}`", duplicate, kept);
+        }
+
+        // Links to the same title already go to `kept`
+        let kept_title = kept.metadata().title();
+        let mut retargeted_titles = trashed
+            .iter()
+            .map(|duplicate| duplicate.metadata().title())
+            .filter(|title| {
+                !title.trim().is_empty()
+                    && title.trim().to_lowercase() != kept_title.trim().to_lowercase()
+            })
+            .collect::<Vec<_>>();
+        retargeted_titles.dedup();
+
+        if retargeted_titles.is_empty() {
+            return trashed.len();
+        }
+
+        for note in self
+            .note_list()
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+        {
+            let buffer = note.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+            let text = buffer.text(&start_iter, &end_iter, true).to_string();
+
+            let mut retargeted: Option<String> = None;
+            for title in &retargeted_titles {
+                let text = retargeted.as_deref().unwrap_or(&text);
+                if let Some((new_text, _)) = retarget_wiki_links(text, title, &kept_title) {
+                    retargeted = Some(new_text);
+                }
+            }
+
+            if let Some(retargeted) = retargeted {
+                buffer.begin_user_action();
+                buffer.set_text(&retargeted);
+                buffer.end_user_action();
+                log::info!(
+                    "Made the links to duplicates in `{}` go to `{}`",
+                    note,
+                    kept
+                );
+            }
+        }
+
+        trashed.len()
+    }
+
+    /// Make the links to `note` follow it after it was renamed from `old_title`, so they go to
+    /// its new title. When other notes are also titled `old_title`, which of them the links
+    /// go to can't be told, so they are left as they are and their notes are added to the
+    /// attention list.
+    pub fn update_links(&self, note: &Note, old_title: &str) -> LinkUpdate {
+        let old_title = old_title.trim();
+        let new_title = note.metadata().title();
+
+        if old_title.is_empty()
+            || new_title.trim().is_empty()
+            || old_title.to_lowercase() == new_title.trim().to_lowercase()
+        {
+            return LinkUpdate::Unlinked;
+        }
+
+        let notes = self
+            .note_list()
+            .iter()
+            .filter(|other| !other.metadata().is_trashed())
+            .collect::<Vec<_>>();
+
+        // As it was before the rename
+        let link_index = LinkIndex::new(notes.iter().map(|other| {
+            let title = if other == note {
+                old_title.to_string()
+            } else {
+                other.metadata().title()
+            };
+            (other.id(), title, other.lowercase_content())
+        }));
+        let linking_ids = link_index
+            .note_with_title(old_title)
+            .map(|target| link_index.links_to(target))
+            .unwrap_or_default();
+        let linking_notes = notes
+            .iter()
+            .filter(|other| linking_ids.contains(&other.id()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if linking_notes.is_empty() {
+            return LinkUpdate::Unlinked;
+        }
+
+        let is_title_shared = notes.iter().any(|other| {
+            other != note
+                && other.metadata().title().trim().to_lowercase() == old_title.to_lowercase()
+        });
+
+        if is_title_shared {
+            let attention_list = self.attention_list();
+            for linking_note in &linking_notes {
+                attention_list.add(linking_note, old_title);
+            }
+
+            log::info!(
+                "Kept the links in {} notes to `{}`, as other notes have its old title",
+                linking_notes.len(),
+                note
+            );
+            return LinkUpdate::Ambiguous(linking_notes.len());
+        }
+
+        UpdatedLinks::update(note, old_title, &linking_notes)
+            .map_or(LinkUpdate::Unlinked, LinkUpdate::Updated)
+    }
+
+    /// The files in the notes directory that are not notes, sorted by name, each with the
+    /// notes using it
+    pub async fn attachment_usages(&self) -> anyhow::Result<Vec<AttachmentUsage>> {
+        let attachment_index = self.attachment_index().await;
+
+        let notes_path = self.directory().path().unwrap();
+        let files = spawn_blocking!(move || attachment_files(&notes_path)).await?;
+
+        let usages = files
+            .into_iter()
+            .map(|file| {
+                let notes = attachment_index
+                    .references
+                    .referencing(&file.file_name())
+                    .iter()
+                    .map(|index| attachment_index.notes[*index].clone())
+                    .collect();
+
+                AttachmentUsage {
+                    file: gio::File::for_path(&file.path),
+                    size: file.size,
+                    notes,
+                }
+            })
+            .collect();
+
+        Ok(usages)
+    }
+
+    /// Remove the attachment `files` from the notes directory, the way `mode` says, and from
+    /// the attachments of the notes having them. Links to them in the content of the notes are
+    /// left as they are. The deletion is committed on the next sync.
+    pub async fn delete_attachments(
+        &self,
+        files: &[gio::File],
+        mode: DeletionMode,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        let trash = self.trash();
+
+        let mut n_failed = 0;
+        for file in files {
+            match remove_file(trash.as_ref(), file, mode).await {
+                Ok(removal) => log::info!("Deleted attachment `{}`: {:?}", file.uri(), removal),
+                Err(err) => {
+                    log::error!("Failed to delete attachment `{}`: {:?}", file.uri(), err);
+                    n_failed += 1;
+                    continue;
+                }
+            }
+
+            for note in self.note_list().iter() {
+                let attachment_list = note.metadata().attachment_list();
+                let attachments = (0..attachment_list.n_items())
+                    .filter_map(|position| attachment_list.item(position))
+                    .map(|item| item.downcast::<Attachment>().unwrap())
+                    .filter(|attachment| attachment.file().equal(file))
+                    .collect::<Vec<_>>();
+
+                for attachment in attachments {
+                    attachment_list.remove(&attachment)?;
+                }
+            }
+        }
+
+        anyhow::ensure!(
+            n_failed == 0,
+            "Failed to delete {} of {} attachments",
+            n_failed,
+            files.len()
+        );
+
+        Ok(())
+    }
+
+    /// Which notes use each attachment, built off the main thread from the content of the
+    /// notes, unless the notes did not change since it was last built
+    async fn attachment_index(&self) -> Rc<AttachmentIndex> {
+        let imp = self.imp();
+
+        // The index is only invalidated once the changes made to the notes are handled
+        for note in self.note_list().unsaved_notes() {
+            note.flush_content_changed();
+        }
+
+        if let Some(attachment_index) = imp.attachment_index.borrow().as_ref() {
+            return Rc::clone(attachment_index);
+        }
+
+        let generation = imp.attachment_index_generation.get();
+
+        let notes = self.note_list().iter().collect::<Vec<_>>();
+        let scanned = notes
+            .iter()
+            .map(|note| {
+                let attachment_files = note
+                    .metadata()
+                    .attachment_list()
+                    .to_data()
+                    .into_iter()
+                    .map(|attachment| attachment.file)
+                    .collect::<Vec<_>>();
+
+                let buffer = note.buffer();
+                let (start_iter, end_iter) = buffer.bounds();
+                let content = buffer.text(&start_iter, &end_iter, true).to_string();
+
+                (attachment_files, content)
+            })
+            .collect::<Vec<_>>();
+
+        let references = spawn_blocking!(move || AttachmentReferences::scan(&scanned)).await;
+        let attachment_index = Rc::new(AttachmentIndex { notes, references });
+
+        // The notes changed while scanning them
+        if imp.attachment_index_generation.get() == generation {
+            imp.attachment_index
+                .replace(Some(Rc::clone(&attachment_index)));
+        }
+
+        attachment_index
+    }
+
+    /// Notes with `tag`, including the trashed ones, in no particular order
+    pub fn notes_with_tag(&self, tag: &Tag) -> Vec<Note> {
+        self.imp().tag_index.borrow().notes_with(tag)
+    }
+
+    /// Delete `tag`, removing it from the notes that have it. It is kept with the notes it was
+    /// removed from for [`DELETED_TAG_RETENTION_DAYS`], so [`Self::restore_deleted_tag`] can
+    /// put it back on them.
+    pub fn delete_tag(&self, tag: &Tag) -> anyhow::Result<DeletedTagData> {
+        self.ensure_writable()?;
+
+        self.tag_list().remove(tag)?;
+
+        let mut note_ids = Vec::new();
+
+        for note in self.notes_with_tag(tag) {
+            if let Err(err) = note.metadata().tag_list().remove(tag) {
+                log::warn!(
+                    "Failed to remove tag `{}` on `{}`: {:?}",
+                    tag.name(),
+                    note,
+                    err
+                );
+                continue;
+            }
+
+            note_ids.push(note.id().to_string());
+        }
+
+        let deleted_tag = DeletedTagData {
+            name: tag.name(),
+            deleted: DateTime::now(),
+            note_ids,
+        };
+
+        let mut deleted_tags = self.deleted_tags();
+        deleted_tags.push(deleted_tag.clone());
+        self.set_deleted_tags(deleted_tags);
+
+        Ok(deleted_tag)
+    }
+
+    /// Create `deleted_tag` again and put it back on the notes it was removed from. Notes that
+    /// were deleted since are skipped, and a tag with its name created since, like one renamed
+    /// to it, is put on them instead, so both end up merged.
+    pub fn restore_deleted_tag(&self, deleted_tag: &DeletedTagData) -> anyhow::Result<Tag> {
+        self.ensure_writable()?;
+
+        let mut deleted_tags = self.deleted_tags();
+        let position = deleted_tags
+            .iter()
+            .position(|other| other == deleted_tag)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Tag `{}` can no longer be restored", deleted_tag.name)
+            })?;
+
+        let tag_list = self.tag_list();
+        let tag = match tag_list.get_with_name(&deleted_tag.name) {
+            Some(tag) => {
+                log::info!(
+                    "Restoring deleted tag `{}` into `{}`, as they have the same name",
+                    deleted_tag.name,
+                    tag.name()
+                );
+                tag
+            }
+            None => {
+                let tag = Tag::new(&deleted_tag.name);
+                tag_list.append(tag.clone())?;
+                tag
+            }
+        };
+
+        let note_list = self.note_list();
+        let notes = deleted_tag
+            .note_ids
+            .iter()
+            .filter_map(|id| NoteId::parse(id))
+            .filter_map(|id| note_list.find_by_id(&id));
+
+        for note in notes {
+            let note_tag_list = note.metadata().tag_list();
+
+            if note_tag_list.contains(&tag) {
+                continue;
+            }
+
+            if let Err(err) = note_tag_list.append(tag.clone()) {
+                log::warn!(
+                    "Failed to restore tag `{}` on `{}`: {:?}",
+                    tag.name(),
+                    note,
+                    err
+                );
+            }
+        }
+
+        deleted_tags.remove(position);
+        self.set_deleted_tags(deleted_tags);
+
+        Ok(tag)
+    }
+
+    /// Tags that can still be restored, from the oldest deletion
+    pub fn deleted_tags(&self) -> Vec<DeletedTagData> {
+        self.imp().deleted_tags.borrow().clone()
+    }
+
+    fn set_deleted_tags(&self, deleted_tags: Vec<DeletedTagData>) {
+        self.imp().deleted_tags.replace(deleted_tags);
+        self.purge_deleted_tags();
+        self.emit_by_name::<()>("deleted-tags-changed", &[]);
+    }
+
+    /// Forget the deleted tags that are too old to be restored
+    fn purge_deleted_tags(&self) {
+        let now = DateTime::now();
+        let mut deleted_tags = self.imp().deleted_tags.borrow_mut();
+        let n_deleted_tags = deleted_tags.len();
+
+        deleted_tags.retain(|deleted_tag| !deleted_tag.is_expired(now));
+
+        let n_purged = n_deleted_tags - deleted_tags.len();
+        if n_purged > 0 {
+            log::info!(
+                "Purged {} deleted tags older than {} days",
+                n_purged,
+                DELETED_TAG_RETENTION_DAYS
+            );
+        }
+    }
+
+    /// Called when a tag is deleted or restored, or when deleted tags are synced or purged
+    pub fn connect_deleted_tags_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("deleted-tags-changed", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    /// Index of the links between the notes that are not trashed, from their current content
+    pub fn link_index(&self) -> LinkIndex {
+        LinkIndex::new(
+            self.note_list()
+                .iter()
+                .filter(|note| !note.metadata().is_trashed())
+                .map(|note| {
+                    let title = note.metadata().title();
+                    (note.id(), title, note.lowercase_content())
+                }),
+        )
+    }
+
+    /// Copy a markdown file from outside the notes directory as a new note.
+    ///
+    /// When the file has no front matter, the whole file becomes the content and the file
+    /// name becomes the title.
+    pub async fn import_file(&self, file: &gio::File) -> anyhow::Result<Note> {
+        self.ensure_writable()?;
+
+        let (file_content, _) = file.load_contents_future().await?;
+        let file_content = std::str::from_utf8(&file_content)?;
+
+        let new_note = self.new_note();
+
+        match NoteData::parse(file_content) {
+            Ok(note_data) => {
+                new_note
+                    .metadata()
+                    .update(&NoteMetadata::from_data(&note_data.metadata));
+                new_note.buffer().set_text(&note_data.content);
+            }
+            Err(err) => {
+                log::info!("Importing `{}` without front matter: {:?}", file.uri(), err);
+
+                let title = file
+                    .path()
+                    .and_then(|path| {
+                        path.file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                    })
+                    .unwrap_or_default();
+                new_note.metadata().set_title(&title);
+                new_note.buffer().set_text(file_content);
+            }
+        }
+
+        new_note.save().await?;
+
+        log::info!("Imported `{}` as `{}`", file.uri(), new_note);
+        self.record_note_created(&new_note);
+
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .record_created(&new_note.id(), &new_note.metadata().title());
+
+        self.note_list().append(new_note.clone());
+
+        Ok(new_note)
+    }
+
+    /// Import the notes of the Obsidian vault at `folder` as new notes, along with their tags
+    /// and the files they link to.
+    pub async fn import_vault(
+        &self,
+        folder: &gio::File,
+        options: VaultImportOptions,
+    ) -> anyhow::Result<VaultImportReport> {
+        self.ensure_writable()?;
+
+        let vault_path = folder
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("Vault `{}` is not a local folder", folder.uri()))?;
+        let notes_path = self.directory().path().unwrap();
+
+        let (files, existing_file_names) = {
+            let vault_path = vault_path.clone();
+            let notes_path = notes_path.clone();
+            spawn_blocking!(move || -> anyhow::Result<_> {
+                let files = read_vault(&vault_path)?;
+                let existing_file_names = fs::read_dir(&notes_path)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect::<HashSet<_>>();
+                Ok((files, existing_file_names))
+            })
+            .await?
+        };
+
+        let vault_import = convert_vault(&files, &existing_file_names, options);
+
+        for attachment in &vault_import.attachments {
+            let source = gio::File::for_path(vault_path.join(&attachment.source));
+            let destination =
+                gio::File::for_path(resolve_within(&notes_path, &attachment.file_name)?);
+
+            log::info!("Copying `{}` to `{}`", source.uri(), destination.uri());
+
+            let (copy, _) = source.copy_future(
+                &destination,
+                gio::FileCopyFlags::NONE,
+                glib::PRIORITY_DEFAULT,
+            );
+            copy.await?;
+        }
+
+        let tag_list = self.tag_list();
+        let mut created_tags = Vec::new();
+
+        for name in &vault_import.tags {
+            if tag_list.contains_with_name(name) {
+                continue;
+            }
+
+            match tag_list.append(Tag::new(name)) {
+                Ok(()) => created_tags.push(name.clone()),
+                Err(err) => log::warn!("Failed to create tag `{}`: {:?}", name, err),
+            }
+        }
+
+        let now = DateTime::now();
+
+        for vault_note in &vault_import.notes {
+            let mut metadata = vault_note.metadata.clone();
+            metadata.last_modified = now;
+            metadata.attachment_list = vault_note
+                .attachments
+                .iter()
+                .map(|file_name| AttachmentData {
+                    file: notes_path.join(file_name),
+                    created: now,
+                    title: String::new(),
+                })
+                .collect();
+
+            let new_note = self.new_note();
+            new_note
+                .metadata()
+                .update(&NoteMetadata::from_data(&metadata));
+            new_note.buffer().set_text(&vault_note.content);
+            new_note.save().await?;
+
+            log::info!(
+                "Imported `{}` as `{}`",
+                vault_note.source.display(),
+                new_note
+            );
+            self.record_note_created(&new_note);
+
+            self.imp()
+                .change_journal
+                .borrow_mut()
+                .record_created(&new_note.id(), &new_note.metadata().title());
+
+            self.note_list().append(new_note);
+        }
+
+        Ok(VaultImportReport {
+            n_notes: vault_import.notes.len(),
+            n_attachments: vault_import.attachments.len(),
+            created_tags,
+            unresolved_links: vault_import.unresolved_links,
+        })
+    }
+
+    /// Write the notes that are not trashed, or only those with `tag`, as a website in
+    /// `folder`, calling `on_progress` with the done fraction as files are written.
+    ///
+    /// Fails with [`SiteExportCancelled`](crate::core::SiteExportCancelled) once `cancellable`
+    /// is cancelled, leaving the files written before.
+    pub async fn export_site(
+        &self,
+        folder: &gio::File,
+        tag: Option<&Tag>,
+        on_progress: impl Fn(f64) + 'static,
+        cancellable: &gio::Cancellable,
+    ) -> anyhow::Result<SiteExportReport> {
+        let output_path = folder
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a local folder", folder.uri()))?;
+
+        let notes = self
+            .note_list()
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+            .filter(|note| tag.map_or(true, |tag| note.metadata().tag_list().contains(tag)))
+            .map(|note| {
+                let buffer = note.buffer();
+                let (start_iter, end_iter) = buffer.bounds();
+                let metadata = note.metadata();
+
+                SiteNote {
+                    title: metadata.title(),
+                    file_name: file_name(&note),
+                    tag_names: metadata.tag_list().names(),
+                    content: buffer.text(&start_iter, &end_iter, true).to_string(),
+                    attachments: metadata.attachment_list().to_data(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let site_title = tag.map_or_else(|| gettext("Notes"), |tag| tag.name());
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        receiver.attach(None, move |fraction| {
+            on_progress(fraction);
+            glib::Continue(true)
+        });
+
+        let cancellable = cancellable.clone();
+        let site = spawn_blocking!(move || -> anyhow::Result<_> {
+            let site = build_site(&site_title, &notes);
+            write_site(
+                &site,
+                &output_path,
+                |fraction| {
+                    // The receiver is only dropped along with the main context
+                    let _ = sender.send(fraction);
+                },
+                || cancellable.is_cancelled(),
+            )?;
+            Ok(site)
+        })
+        .await?;
+
+        log::info!("Exported {} pages to `{}`", site.pages.len(), folder.uri());
+
+        Ok(SiteExportReport {
+            n_pages: site.pages.len(),
+            n_attachments: site.attachments.len(),
+            invalid_links: site.invalid_links,
+        })
+    }
+
+    /// Write the notes with `tag` that are not trashed, along with their attachments, as a
+    /// bundle to `file`, calling `on_progress` with the done fraction as files are written
+    pub async fn export_tag_bundle(
+        &self,
+        tag: &Tag,
+        file: &gio::File,
+        on_progress: impl Fn(f64) + 'static,
+    ) -> anyhow::Result<WrittenBundle> {
+        let path = file
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a local file", file.uri()))?;
+
+        let notes = self
+            .note_list()
+            .iter()
+            .filter(|note| !note.metadata().is_trashed())
+            .filter(|note| note.metadata().tag_list().contains(tag))
+            .map(|note| {
+                let metadata = note.metadata();
+                let file_name = file_name(&note);
+
+                Ok(BundleNote {
+                    id: file_name
+                        .strip_suffix(".md")
+                        .unwrap_or(&file_name)
+                        .to_string(),
+                    title: metadata.title(),
+                    tag_names: metadata.tag_list().names(),
+                    contents: Self::serialized(&note)?,
+                    attachments: metadata
+                        .attachment_list()
+                        .to_data()
+                        .into_iter()
+                        .map(|attachment| attachment.file)
+                        .collect(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        receiver.attach(None, move |fraction| {
+            on_progress(fraction);
+            glib::Continue(true)
+        });
+
+        let written_bundle = spawn_blocking!(move || -> anyhow::Result<_> {
+            let writer = io::BufWriter::new(fs::File::create(&path)?);
+            write_bundle(writer, &notes, |fraction| {
+                // The receiver is only dropped along with the main context
+                let _ = sender.send(fraction);
+            })
+        })
+        .await?;
+
+        log::info!(
+            "Exported {} notes with `{}` to `{}`",
+            written_bundle.n_notes,
+            tag.name(),
+            file.uri()
+        );
+
+        Ok(written_bundle)
+    }
+
+    /// Read and check the bundle in `file`, without importing it yet
+    pub async fn read_tag_bundle(&self, file: &gio::File) -> anyhow::Result<TagBundle> {
+        let path = file
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a local file", file.uri()))?;
+
+        spawn_blocking!(move || -> anyhow::Result<_> { Ok(TagBundle::read(&fs::read(&path)?)?) })
+            .await
+    }
+
+    /// Number of the notes of `bundle` that are already in the notebook
+    pub fn n_existing_bundle_notes(&self, bundle: &TagBundle) -> usize {
+        let note_file_names = self
+            .note_list()
+            .iter()
+            .map(|note| file_name(&note))
+            .collect::<HashSet<_>>();
+
+        plan_import(bundle, &note_file_names, CollisionPolicy::Skip)
+            .skipped
+            .len()
+    }
+
+    /// Import the notes of `bundle` with their tags and attachments, handling the notes that
+    /// are already in the notebook with `policy`. `on_progress` is called with the done
+    /// fraction as files are created.
+    pub async fn import_tag_bundle(
+        &self,
+        bundle: TagBundle,
+        policy: CollisionPolicy,
+        on_progress: impl Fn(f64) + 'static,
+    ) -> anyhow::Result<TagBundleImportReport> {
+        self.ensure_writable()?;
+
+        let notes_path = self.directory().path().unwrap();
+
+        let existing_file_names = {
+            let notes_path = notes_path.clone();
+            spawn_blocking!(move || -> anyhow::Result<_> {
+                Ok(fs::read_dir(&notes_path)?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect::<HashSet<_>>())
+            })
+            .await?
+        };
+
+        let bundle_import = plan_import(&bundle, &existing_file_names, policy);
+        let n_files = bundle_import.attachments.len() + bundle_import.notes.len();
+        let on_progress = Rc::new(on_progress);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        receiver.attach(
+            None,
+            clone!(@strong on_progress => move |fraction| {
+                on_progress(fraction);
+                glib::Continue(true)
+            }),
+        );
+
+        let attachments = bundle_import.attachments.clone();
+        let bundle = {
+            let notes_path = notes_path.clone();
+            spawn_blocking!(move || -> anyhow::Result<_> {
+                for (index, (name, file_name)) in attachments.iter().enumerate() {
+                    // Checked to be there when the bundle was read
+                    let data = bundle.attachment(name).unwrap();
+                    fs::write(resolve_within(&notes_path, file_name)?, data)?;
+
+                    // The receiver is only dropped along with the main context
+                    let _ = sender.send((index + 1) as f64 / n_files as f64);
+                }
+                Ok(bundle)
+            })
+            .await?
+        };
+
+        let tag_list = self.tag_list();
+        let mut created_tags = Vec::new();
+
+        for name in &bundle.manifest.tags {
+            if tag_list.contains_with_name(name) {
+                continue;
+            }
+
+            match tag_list.append(Tag::new(name)) {
+                Ok(()) => created_tags.push(name.clone()),
+                Err(err) => log::warn!("Failed to create tag `{}`: {:?}", name, err),
+            }
+        }
+
+        for (index, imported_note) in bundle_import.notes.iter().enumerate() {
+            let mut note_data = NoteData::parse(&bundle.notes[imported_note.index])?;
+
+            // Other attachments were left out of the bundle, as their file was missing
+            note_data.metadata.attachment_list = note_data
+                .metadata
+                .attachment_list
+                .into_iter()
+                .filter_map(|attachment| {
+                    let file_name = imported_note.attachment_files.get(&attachment.file)?;
+                    Some(AttachmentData {
+                        file: notes_path.join(file_name),
+                        ..attachment
+                    })
+                })
+                .collect();
+            // Publishing it again would replace the gist of whoever shared it
+            note_data.metadata.gist_id = None;
+
+            let new_note = Note::with_file(&gio::File::for_path(resolve_within(
+                &notes_path,
+                &imported_note.file_name,
+            )?));
+            new_note.set_front_matter_profile(self.notebook_settings().front_matter_profile());
+            new_note
+                .metadata()
+                .update(&NoteMetadata::from_data(&note_data.metadata));
+            new_note.buffer().set_text(&note_data.content);
+            new_note.save().await?;
+
+            log::info!("Imported `{}` from a tag bundle", new_note);
+            self.record_note_created(&new_note);
+
+            self.imp()
+                .change_journal
+                .borrow_mut()
+                .record_created(&new_note.id(), &new_note.metadata().title());
+
+            self.note_list().append(new_note);
+
+            on_progress((bundle_import.attachments.len() + index + 1) as f64 / n_files as f64);
+        }
+
+        Ok(TagBundleImportReport {
+            n_notes: bundle_import.notes.len(),
+            n_attachments: bundle_import.attachments.len(),
+            skipped_notes: bundle_import.skipped,
+            created_tags,
+        })
+    }
+
+    /// Load the notebook, cancelling the load that is still running, if there is one. A
+    /// cancelled load fails with [`LoadCancelled`] once the git operations it queued are done,
+    /// without setting the tags, the settings, or the notes that it did not show yet.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let cancellable = gio::Cancellable::new();
+        if let Some(previous_cancellable) = self
+            .imp()
+            .load_cancellable
+            .replace(Some(cancellable.clone()))
+        {
+            log::info!("Cancelling the previous load of the notebook");
+            previous_cancellable.cancel();
+        }
+
+        let res = self.load_with_cancellable(&cancellable).await;
+
+        // Unless a newer load already took its place
+        let imp = self.imp();
+        if imp.load_cancellable.borrow().as_ref() == Some(&cancellable) {
+            imp.load_cancellable.replace(None);
+        }
+
+        match res {
+            Err(_) if cancellable.is_cancelled() => {
+                // So they are not running when the newer load uses the repository
+                self.repository().wait_for_queued_operations().await;
+                log::info!("Loading the notebook was cancelled");
+                Err(LoadCancelled.into())
+            }
+            res => res,
+        }
+    }
+
+    /// Stop the running load, if there is one, like before switching to another notebook
+    pub fn cancel_load(&self) {
+        if let Some(cancellable) = self.imp().load_cancellable.take() {
+            cancellable.cancel();
+        }
+    }
+
+    async fn load_with_cancellable(&self, cancellable: &gio::Cancellable) -> anyhow::Result<()> {
+        self.load_data_file(cancellable).await?;
+        self.load_exclusions().await;
+
+        let metadata_index = or_cancelled(cancellable, self.read_metadata_index()).await?;
+        self.load_notes(metadata_index.as_ref(), cancellable)
+            .await?;
+        cancellable.set_error_if_cancelled()?;
+
+        // Before anything is saved, so the copies are compared with the notes as they were
+        self.load_recovered_notes().await;
+        self.start_recovery_copies();
+
+        self.setup_directory_monitor();
+
+        // In the background, so the notes are shown before their files are read
+        spawn!(clone!(@weak self as obj => async move {
+            match metadata_index {
+                Some(metadata_index) => obj.verify_indexed_notes(&metadata_index).await,
+                None => obj.write_metadata_index().await,
+            }
+
+            obj.build_search_index().await;
+            obj.load_pending_contents().await;
+        }));
+
+        Ok(())
+    }
+
+    /// Open the repository of the notes, or create it if there is none yet, then count the
+    /// changes that are not committed. The remote is then fetched in the background.
+    ///
+    /// This is done once the notes are loaded, so they are shown without waiting for git. The
+    /// git operations asked for before wait until it is done.
+    pub async fn connect_repository(&self) -> anyhow::Result<()> {
+        self.repository().open_or_init().await?;
+        self.refresh_pending_changes().await;
+        self.start_ahead_behind_refresh();
+
+        spawn!(clone!(@weak self as obj => async move {
+            obj.refresh_ahead_behind(true).await;
+        }));
+
+        Ok(())
+    }
+
+    /// Copies of notes with changes that were not saved before the app last stopped, which
+    /// are kept until they are restored or discarded
+    pub fn recovered_notes(&self) -> Vec<RecoveredNote> {
+        self.imp().recovered_notes.borrow().clone()
+    }
+
+    /// Replace the note that `recovered_note` is a copy of with it, or create a new note
+    /// from it when that note no longer exists
+    pub fn restore_recovered_note(&self, recovered_note: &RecoveredNote) -> anyhow::Result<Note> {
+        self.ensure_writable()?;
+
+        let note_data = NoteData::parse(&recovered_note.contents)?;
+        let note_id = NoteId::for_path(&recovered_note.file_name);
+
+        let note = match self.note_list().find_by_id(&note_id) {
+            Some(note) => note,
+            None => {
+                let new_note = self.new_note();
+
+                log::info!("Created note `{}` from a recovery copy", new_note);
+                self.record_note_created(&new_note);
+
+                self.imp()
+                    .change_journal
+                    .borrow_mut()
+                    .record_created(&new_note.id(), &note_data.metadata.title);
+
+                // Append first, so the changes below mark the note as unsaved in the list
+                self.note_list().append(new_note.clone());
+                new_note
+            }
+        };
+
+        note.metadata()
+            .update(&NoteMetadata::from_data(&note_data.metadata));
+        note.buffer().set_text(&note_data.content);
+
+        log::info!("Restored `{}` from its recovery copy", note);
+        self.discard_recovered_note(recovered_note);
+
+        Ok(note)
+    }
+
+    pub fn discard_recovered_note(&self, recovered_note: &RecoveredNote) {
+        self.imp()
+            .recovered_notes
+            .borrow_mut()
+            .retain(|other| other.file_name != recovered_note.file_name);
+        self.recovery_writer().remove(&recovered_note.file_name);
+    }
+
+    async fn load_recovered_notes(&self) {
+        let recovery_dir = self.recovery_dir().to_path_buf();
+        let notes_path = self.directory().path().unwrap();
+
+        let res = spawn_blocking!(move || -> std::io::Result<_> {
+            let reconciliation = reconcile(read_recovered_notes(&recovery_dir)?, |file_name| {
+                fs::metadata(notes_path.join(file_name))
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            });
+
+            for recovered_note in &reconciliation.outdated {
+                fs::remove_file(recovery_dir.join(&recovered_note.file_name))?;
+            }
+
+            Ok(reconciliation)
+        })
+        .await;
+
+        match res {
+            Ok(reconciliation) => {
+                let mut recovered_notes = reconciliation.unsaved;
+                recovered_notes.extend(reconciliation.orphaned);
+
+                if !recovered_notes.is_empty() {
+                    log::info!("Found {} recovery copies", recovered_notes.len());
+                }
+
+                self.imp().recovered_notes.replace(recovered_notes);
+            }
+            Err(err) => log::warn!("Failed to load recovery copies: {:?}", err),
+        }
+    }
+
+    /// Write copies of the unsaved notes every [`RECOVERY_INTERVAL_SECS`]
+    fn start_recovery_copies(&self) {
+        glib::timeout_add_seconds_local(
+            RECOVERY_INTERVAL_SECS,
+            clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                obj.write_recovery_copies();
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    /// Queue writing a copy of each unsaved note changed since its last copy
+    fn write_recovery_copies(&self) {
+        let mut recovery_snapshots = self.imp().recovery_snapshots.borrow_mut();
+
+        for note in self.note_list().unsaved_notes() {
+            let last_modified = note.metadata().last_modified();
+
+            if recovery_snapshots.get(&note.id()) == Some(&last_modified) {
+                continue;
+            }
+
+            let contents = match Self::serialized(&note) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    log::warn!("Failed to serialize `{}` for recovery: {:?}", note, err);
+                    continue;
+                }
+            };
+
+            if !self.recovery_writer().write(&file_name(&note), contents) {
+                log::info!("`{}` is too large for a recovery copy", note);
+            }
+
+            recovery_snapshots.insert(note.id(), last_modified);
+        }
+    }
+
+    fn remove_recovery_copy(&self, note: &Note) {
+        let had_copy = self
+            .imp()
+            .recovery_snapshots
+            .borrow_mut()
+            .remove(&note.id())
+            .is_some();
+
+        if had_copy {
+            self.recovery_writer().remove(&file_name(note));
+        }
+    }
+
+    fn serialized(note: &Note) -> anyhow::Result<String> {
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+
+        NoteData {
+            metadata: note.metadata().to_data(),
+            content: buffer.text(&start_iter, &end_iter, true).to_string(),
+        }
+        .serialize(note.front_matter_profile())
+    }
+
+    fn recovery_dir(&self) -> &Path {
+        self.imp().recovery_dir.get_or_init(|| {
+            let mut path = glib::user_cache_dir();
+            path.push("noteworthy");
+            path.push("recovery");
+            path
+        })
+    }
+
+    /// Outside of the notes like the recovery copies, so it is never committed
+    fn metadata_index_path(&self) -> &Path {
+        self.imp().metadata_index_path.get_or_init(|| {
+            let mut path = glib::user_cache_dir();
+            path.push("noteworthy");
+            path.push(METADATA_INDEX_FILE_NAME);
+            path
+        })
+    }
+
+    /// In the cache like the metadata index, as it can be built again from the notes
+    fn search_index_path(&self) -> &Path {
+        self.imp().search_index_path.get_or_init(|| {
+            let mut path = glib::user_cache_dir();
+            path.push("noteworthy");
+            path.push(SEARCH_INDEX_FILE_NAME);
+            path
+        })
+    }
+
+    fn recovery_writer(&self) -> &RecoveryWriter {
+        self.imp()
+            .recovery_writer
+            .get_or_init(|| RecoveryWriter::new(self.recovery_dir()))
+    }
+
+    pub async fn sync(&self) -> anyhow::Result<()> {
+        self.sync_excluding(&[]).await
+    }
+
+    /// Sync the notes, leaving the changes of the files at `excluded_paths`, relative to the
+    /// directory, uncommitted until a later sync
+    // TODO Application::inhibit while syncing
+    // TODO Better way to handle trying to sync multiple times (maybe refactor to use a thread pool)
+    pub async fn sync_excluding(&self, excluded_paths: &[PathBuf]) -> anyhow::Result<()> {
+        let repo = self.repository();
+
+        if repo.sync_state() == SyncState::Pulling {
+            log::info!("Currently pulling. Returning and skipping session sync...");
+            return Ok(());
+        }
+
+        if self.is_read_only() {
+            return self.pull_read_only().await;
+        }
+
+        self.save_all_notes().await?;
+        self.save_data_file().await?;
+
+        let excluded_note_ids = excluded_paths
+            .iter()
+            .map(NoteId::for_path)
+            .collect::<HashSet<_>>();
+        let commit_message = self.commit_message(|note_id| !excluded_note_ids.contains(note_id));
+
+        let is_offline_mode = self.is_offline_mode();
+        let is_pushed = if is_offline_mode {
+            repo.sync_offline(&commit_message, excluded_paths).await?;
+            true
+        } else if !repo.allows_auto_sync() {
+            log::info!("Remote rejected the credentials, only committing until it is retried");
+            repo.sync_offline(&commit_message, excluded_paths).await?;
+            false
+        } else {
+            let changed_files = repo.sync(&commit_message, excluded_paths).await?;
+            self.handle_changed_files(&changed_files).await?;
+            true
+        };
+
+        // Changes made while syncing are not in the commit, but they will be saved
+        // on the next sync, and recorded again in the journal anyway
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .retain(|note_id| excluded_note_ids.contains(note_id));
+
+        // Pushed, or committed when there is no remote to push to
+        if is_pushed {
+            self.imp()
+                .unpushed_notes
+                .borrow_mut()
+                .retain(|note_id| excluded_note_ids.contains(note_id));
+        }
+        self.refresh_pending_changes().await;
+        self.refresh_ahead_behind(false).await;
+
+        log::info!("Session synced; is_offline_mode `{}`", is_offline_mode);
+
+        Ok(())
+    }
+
+    /// Save `note` and commit its file only, with the data file when it is tagged, as the
+    /// tags may have been created with it. The other changes in the notes directory are left
+    /// uncommitted until the next sync, and nothing is pulled or pushed.
+    pub async fn commit_note(&self, note: &Note) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+
+        self.write_note_file(note).await?;
+
+        let notes_path = self.directory().path().unwrap();
+        let note_path = note.file().path().unwrap();
+        let mut paths = vec![note_path
+            .strip_prefix(&notes_path)
+            .map_err(|_| anyhow::anyhow!("`{}` is not in the notes", note_path.display()))?
+            .to_path_buf()];
+
+        if !note.metadata().tag_list().is_empty() {
+            self.save_data_file().await?;
+            paths.push(PathBuf::from(DATA_FILE_NAME));
+        }
+
+        let note_id = note.id();
+        let commit_message = self.commit_message(|id| *id == note_id);
+        self.repository()
+            .commit_paths(&commit_message, &paths)
+            .await?;
+
+        self.imp()
+            .change_journal
+            .borrow_mut()
+            .retain(|id| *id != note_id);
+        if self.is_offline_mode() {
+            self.imp().unpushed_notes.borrow_mut().remove(&note_id);
+        }
+        self.refresh_pending_changes().await;
+
+        log::info!("Committed `{}`", note);
+
+        Ok(())
+    }
+
+    /// Only pull the changes of the remote, as nothing is committed in a read-only notebook
+    async fn pull_read_only(&self) -> anyhow::Result<()> {
+        if self.is_offline_mode() {
+            log::info!("Read-only without a remote, skipping sync...");
+            return Ok(());
+        }
+
+        if !self.repository().allows_auto_sync() {
+            log::info!("Remote rejected the credentials, skipping pull until it is retried");
+            return Ok(());
+        }
+
+        let changed_files = self.repository().pull_only().await?;
+        self.handle_changed_files(&changed_files).await?;
+        self.refresh_ahead_behind(false).await;
+
+        log::info!("Session pulled; is_read_only `true`");
+
+        Ok(())
+    }
+
+    async fn handle_changed_files(
+        &self,
+        changed_files: &[(PathBuf, git2::Delta)],
+    ) -> anyhow::Result<()> {
+        let note_list = self.note_list();
+        let data_file_path = self.data_file_path();
+        // Removed all at once afterwards, so the list only changes once
+        let mut deleted_note_ids = HashSet::new();
+
+        for (path, delta) in changed_files {
+            if path == &data_file_path {
+                if matches!(delta, git2::Delta::Added | git2::Delta::Modified) {
+                    self.reload_data_file().await;
+                }
+                continue;
+            }
+
+            // Never in the note list, even if they are in the repository
+            if self.is_excluded(path) {
+                log::info!("Sync: Skipped excluded file `{}`", path.display());
+                continue;
+            }
+
+            match delta {
+                git2::Delta::Added => {
+                    log::info!("Sync: Found added files `{}`; appending...", path.display());
+                    self.load_synced_note(path).await;
+                }
+                git2::Delta::Deleted => {
+                    log::info!(
+                        "Sync: Found removed files `{}`; removing...",
+                        path.display()
+                    );
+                    deleted_note_ids.insert(NoteId::for_path(path));
+                }
+                git2::Delta::Modified => {
+                    log::info!(
+                        "Sync: Found modified files `{}`; updating...",
+                        path.display()
+                    );
+                    match note_list.find_by_id(&NoteId::for_path(path)) {
+                        Some(note) => note.update().await?,
+                        // Like when it failed to load before
+                        None => self.load_synced_note(path).await,
+                    }
+                }
+                other => {
+                    log::warn!("Found other delta type: {:?}", other);
+                }
+            }
+        }
+
+        if !deleted_note_ids.is_empty() {
+            note_list.retain(|note| !deleted_note_ids.contains(&note.id()));
+        }
+
+        Ok(())
+    }
+
+    /// Load the note at `path` that was changed by a sync but is not in the note list yet,
+    /// reporting a problem if it can't be loaded
+    async fn load_synced_note(&self, path: &Path) {
+        let file = gio::File::for_path(path);
+
+        if !is_note_file(&file) {
+            return;
+        }
+
+        match Note::load(&file).await {
+            Ok(note) => {
+                self.problem_list().resolve(path);
+                self.note_list().append(note);
+            }
+            Err(err) => self
+                .problem_list()
+                .report(LoadProblem::from_error(path, &err)),
+        }
+    }
+
+    /// Count the files that are not committed again, as they may have changed outside of
+    /// the notes that were saved
+    async fn refresh_pending_changes(&self) {
+        let n_uncommitted_changes = match self.repository().status_summary().await {
+            Ok(summary) => summary.n_changes(),
+            Err(err) => {
+                log::warn!("Failed to get status of repository: {:?}", err);
+                return;
+            }
+        };
+
+        let imp = self.imp();
+        if imp.n_uncommitted_changes.get() != n_uncommitted_changes {
+            imp.n_uncommitted_changes.set(n_uncommitted_changes);
+            self.notify("pending-changes");
+        }
+    }
+
+    fn queue_refresh_pending_changes(&self) {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.pending_changes_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            PENDING_CHANGES_REFRESH_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().pending_changes_source_id.replace(None);
+
+                spawn!(async move {
+                    obj.refresh_pending_changes().await;
+                });
+            }),
+        );
+        imp.pending_changes_source_id.replace(Some(source_id));
+    }
+
+    /// Watch the notes directory for changes made by other apps
+    fn setup_directory_monitor(&self) {
+        let imp = self.imp();
+
+        if imp.directory_monitor.get().is_some() {
+            return;
+        }
+
+        let monitor = match self
+            .directory()
+            .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+        {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                log::warn!("Failed to monitor notes directory: {:?}", err);
+                return;
+            }
+        };
+
+        monitor.connect_changed(
+            clone!(@weak self as obj => move |_, file, other_file, event| {
+                if file.path().map_or(false, |path| obj.is_excluded(&path)) {
+                    return;
+                }
+
+                // The repository itself changes on every sync
+                if !is_hidden(file) && event != gio::FileMonitorEvent::Attributes {
+                    obj.queue_refresh_pending_changes();
+                }
+
+                obj.handle_file_event(file, other_file, event);
+            }),
+        );
+
+        imp.directory_monitor.set(monitor).unwrap();
+    }
+
+    /// Keep the notes pointed to their files when they are moved in the notes directory,
+    /// instead of them being taken as removed and other notes appearing
+    fn handle_file_event(
+        &self,
+        file: &gio::File,
+        other_file: Option<&gio::File>,
+        event: gio::FileMonitorEvent,
+    ) {
+        match event {
+            gio::FileMonitorEvent::Renamed => {
+                if let Some(other_file) = other_file {
+                    self.handle_note_file_renamed(file, other_file);
+
+                    if self.resolve_problem(file) && is_note_file(other_file) {
+                        self.spawn_retry_load(other_file);
+                    }
+                }
+            }
+            gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => {
+                self.record_note_file_removed(file);
+                self.resolve_problem(file);
+
+                if let Some(note) = self.note_at(file) {
+                    note.report_file_event(NoteFileEvent::Removed);
+                }
+            }
+            gio::FileMonitorEvent::Created | gio::FileMonitorEvent::MovedIn => {
+                if let Some(note) = self.note_at(file) {
+                    note.report_file_event(NoteFileEvent::Created);
+                }
+
+                if self.has_problem(file) {
+                    self.spawn_retry_load(file);
+                    return;
+                }
+
+                spawn!(clone!(@weak self as obj, @strong file => async move {
+                    obj.handle_file_created(&file).await;
+                }));
+            }
+            gio::FileMonitorEvent::ChangesDoneHint => {
+                // Maybe it was fixed
+                if self.has_problem(file) {
+                    self.spawn_retry_load(file);
+                    return;
+                }
+
+                spawn!(clone!(@weak self as obj, @strong file => async move {
+                    obj.check_note_file_changed(&file).await;
+                }));
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_note_file_renamed(&self, old_file: &gio::File, new_file: &gio::File) {
+        match self.note_at(old_file) {
+            Some(note) => {
+                if is_note_file(new_file) {
+                    self.move_note(&note, new_file);
+                }
+            }
+            // Like when another app saves a note by moving a new file over the old one
+            None => {
+                if let Some(note) = self.note_at(new_file) {
+                    note.report_file_event(NoteFileEvent::Created);
+
+                    spawn!(clone!(@weak self as obj, @strong new_file => async move {
+                        obj.check_note_file_changed(&new_file).await;
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Tell the note at `file` what its file has now, so it knows if it was changed elsewhere
+    async fn check_note_file_changed(&self, file: &gio::File) {
+        if self.note_at(file).is_none() {
+            return;
+        }
+
+        let contents = match file.load_contents_future().await {
+            Ok((contents, _)) => contents,
+            Err(err) => {
+                log::info!("Failed to load changed file `{}`: {:?}", file.uri(), err);
+                return;
+            }
+        };
+
+        // Looked up again, as it may have been moved or removed while loading
+        if let Some(note) = self.note_at(file) {
+            note.check_file_contents(&contents);
+        }
+    }
+
+    /// Remember the contents of the note at `file`, in case a file with the same contents
+    /// appears
+    fn record_note_file_removed(&self, file: &gio::File) {
+        let note = match self.note_at(file) {
+            Some(note) if note.is_saved() => note,
+            _ => return,
+        };
+
+        match note.contents() {
+            Ok(contents) => self.imp().file_moves.borrow_mut().record_removed(
+                file.path().unwrap(),
+                content_hash(&contents),
+                Instant::now(),
+            ),
+            Err(err) => log::warn!("Failed to get contents of `{}`: {:?}", note, err),
+        }
+    }
+
+    /// Move the note that was removed with the same contents as `file` to it
+    async fn handle_file_created(&self, file: &gio::File) {
+        if !is_note_file(file) {
+            return;
+        }
+
+        if self.note_at(file).is_some() {
+            self.check_note_file_changed(file).await;
+            return;
+        }
+
+        let contents = match file.load_contents_future().await {
+            Ok((contents, _)) => contents,
+            Err(err) => {
+                log::info!("Failed to load created file `{}`: {:?}", file.uri(), err);
+                return;
+            }
+        };
+
+        let moved_from = self
+            .imp()
+            .file_moves
+            .borrow_mut()
+            .take_moved_from(content_hash(&contents), Instant::now());
+
+        if let Some(old_file) = moved_from.map(gio::File::for_path) {
+            if let Some(note) = self.note_at(&old_file) {
+                self.move_note(&note, file);
+            }
+        }
+    }
+
+    /// Load the file of a note that failed to load again, like once it was fixed, adding the
+    /// note to the list. If it fails again, it stays in the problems with the new error.
+    pub async fn retry_load(&self, file: &gio::File) -> anyhow::Result<()> {
+        let path = file.path().unwrap();
+
+        let note = match Note::load(file).await {
+            Ok(note) => note,
+            Err(err) => {
+                self.problem_list()
+                    .report(LoadProblem::from_error(&path, &err));
+                return Err(err);
+            }
+        };
+
+        self.problem_list().resolve(&path);
+
+        // Like when a sync loaded it in the meantime
+        if self.note_at(file).is_none() {
+            self.note_list().append(note);
+        }
+
+        Ok(())
+    }
+
+    fn spawn_retry_load(&self, file: &gio::File) {
+        spawn!(clone!(@weak self as obj, @strong file => async move {
+            // Left in the problems otherwise
+            if obj.retry_load(&file).await.is_ok() {
+                log::info!("Loaded `{}` that failed to load before", file.uri());
+            }
+        }));
+    }
+
+    fn has_problem(&self, file: &gio::File) -> bool {
+        file.path()
+            .map_or(false, |path| self.problem_list().contains(&path))
+    }
+
+    /// Drop the problem of `file`, like once it is deleted. Returns whether it had one.
+    fn resolve_problem(&self, file: &gio::File) -> bool {
+        file.path()
+            .map_or(false, |path| self.problem_list().resolve(&path))
+    }
+
+    /// Add the file at `path`, in the notes directory, to the exclusions file, so it is no
+    /// longer loaded as a note, like one that fails to load
+    pub async fn exclude_file(&self, path: &Path) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            anyhow::bail!("Can't change the exclusions of read-only notes");
+        }
+
+        let notes_path = self.directory().path().unwrap();
+        let relative_path = path
+            .strip_prefix(&notes_path)
+            .map_err(|_| anyhow::anyhow!("`{}` is not in the notes", path.display()))?;
+
+        let file = self.directory().child(EXCLUSIONS_FILE_NAME);
+        let mut text = match file.load_contents_future().await {
+            Ok((contents, _)) => String::from_utf8_lossy(&contents).into_owned(),
+            Err(err) if err.matches(gio::IOErrorEnum::NotFound) => DEFAULT_EXCLUSIONS.to_string(),
+            Err(err) => return Err(err.into()),
+        };
+
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&Exclusions::path_pattern(relative_path));
+        text.push('\n');
+
+        file.replace_contents_future(text.into_bytes(), None, false, gio::FileCreateFlags::NONE)
+            .await
+            .map_err(|(_, err)| err)?;
+
+        self.load_exclusions().await;
+        self.problem_list().resolve(path);
+
+        Ok(())
+    }
+
+    /// The note stored in `file`
+    fn note_at(&self, file: &gio::File) -> Option<Note> {
+        let path = file.path()?;
+        let note = self.note_list().find_by_id(&NoteId::for_path(&path))?;
+        (note.file().path().as_ref() == Some(&path)).then_some(note)
+    }
+
+    fn move_note(&self, note: &Note, file: &gio::File) {
+        let old_id = note.id();
+
+        // Named after the file of the note, so it would be left behind
+        self.remove_recovery_copy(note);
+
+        if !self.note_list().move_note(note, file) {
+            log::warn!("Failed to move `{}` to `{}`", note, file.uri());
+            return;
+        }
+
+        // Its file was reported missing if it was only found by its contents
+        note.report_file_event(NoteFileEvent::Created);
+
+        let mut unpushed_notes = self.imp().unpushed_notes.borrow_mut();
+        if unpushed_notes.remove(&old_id) {
+            unpushed_notes.insert(note.id());
+        }
+
+        log::info!("Moved `{:?}` to `{}`", old_id, note);
+    }
+
+    /// Fail with [`ReadOnly`] if the notes can't be changed
+    fn ensure_writable(&self) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            return Err(ReadOnly.into());
+        }
+
+        Ok(())
+    }
+
+    /// Message of the commit of the changes of the notes for which `is_committed` is true
+    fn commit_message(&self, is_committed: impl FnMut(&NoteId) -> bool) -> String {
+        let settings = Application::default().settings();
+        let template = settings.string("commit-message-template");
+        let max_changes = settings.uint("commit-message-max-changes");
+
+        let mut change_journal = self.imp().change_journal.borrow().clone();
+        change_journal.retain(is_committed);
+        change_journal.commit_message(&template, max_changes as usize)
+    }
+
+    /// A note in the notes directory named with the notebook settings, which is not in the
+    /// note list yet
+    fn new_note(&self) -> Note {
+        let notebook_settings = self.notebook_settings();
+
+        let note = Note::with_file_stem(
+            self.directory().path().unwrap(),
+            &notebook_settings.note_file_stem(),
+        );
+        note.set_front_matter_profile(notebook_settings.front_matter_profile());
+        note
+    }
+
+    /// Keep the tag index up to date with the notes of `note_list`. A change to the tags of a
+    /// note is emitted by the list as a change of the note.
+    fn setup_tag_index(&self, note_list: &NoteList) {
+        note_list.connect_items_changed(
+            clone!(@weak self as obj => move |note_list, position, removed, added| {
+                let mut tag_index = obj.imp().tag_index.borrow_mut();
+
+                if removed > added {
+                    tag_index.retain(|note| note_list.get_index_of(&note.id()).is_some());
+                }
+
+                for position in position..position + added {
+                    if let Some(note) = note_list.item(position) {
+                        let note = note.downcast::<Note>().unwrap();
+                        tag_index.update(&note, &note.index().tags);
+                    }
+                }
+            }),
+        );
+    }
+
+    /// Drop the attachment index when the notes of `note_list` change. An edit of the content
+    /// or the attachments of a note is emitted by the list as a change of the note.
+    fn setup_attachment_index(&self, note_list: &NoteList) {
+        note_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            let imp = obj.imp();
+            imp.attachment_index
+                .replace(None);
+            imp.attachment_index_generation
+                .set(imp.attachment_index_generation.get().wrapping_add(1));
+        }));
+    }
+
+    /// Keep the search index up to date with the notes of `note_list` once it is built. The
+    /// notes that are edited or added are indexed again when the changes stop.
+    fn setup_search_index(&self, note_list: &NoteList) {
+        let imp = self.imp();
+
+        // Built again for the new notes
+        imp.search_index.replace(None);
+        imp.unindexed_notes.borrow_mut().clear();
+        if let Some(source_id) = imp.search_index_source_id.take() {
+            source_id.remove();
+        }
+
+        note_list.connect_note_content_changed(clone!(@weak self as obj => move |_, note| {
+            obj.imp().unindexed_notes.borrow_mut().insert(note.id());
+            obj.queue_search_index_update();
+        }));
+
+        note_list.connect_items_changed(
+            clone!(@weak self as obj => move |note_list, position, removed, added| {
+                let imp = obj.imp();
+                let search_index = imp.search_index.borrow();
+                let search_index = match *search_index {
+                    Some(ref search_index) => search_index,
+                    None => return,
+                };
+
+                let mut unindexed_notes = imp.unindexed_notes.borrow_mut();
+                let mut is_changed = removed > added;
+
+                // Added, or moved to another file
+                for position in position..position + added {
+                    if let Some(note) = note_list.item(position) {
+                        let note = note.downcast::<Note>().unwrap();
+                        if !search_index.contains(&file_name(&note)) {
+                            unindexed_notes.insert(note.id());
+                            is_changed = true;
+                        }
+                    }
+                }
+
+                if is_changed {
+                    obj.queue_search_index_update();
+                }
+            }),
+        );
+    }
+
+    /// Keep the notes of `note_list` writing their front matter in the profile of the
+    /// notebook settings, including the ones added later and when the setting changes
+    fn setup_front_matter_profile(&self, note_list: &NoteList) {
+        note_list.connect_items_changed(
+            clone!(@weak self as obj => move |note_list, position, _, added| {
+                let profile = obj.notebook_settings().front_matter_profile();
+
+                for position in position..position + added {
+                    if let Some(note) = note_list.item(position) {
+                        note.downcast::<Note>().unwrap().set_front_matter_profile(profile);
+                    }
+                }
+            }),
+        );
+
+        self.notebook_settings()
+            .connect_front_matter_profile_notify(
+                clone!(@weak note_list => move |notebook_settings| {
+                    let profile = notebook_settings.front_matter_profile();
+                    log::info!("Writing front matter as `{}` from now on", profile.name());
+
+                    for note in note_list.iter() {
+                        note.set_front_matter_profile(profile);
+                    }
+                }),
+            );
+    }
+
+    fn trash(&self) -> Rc<dyn Trash> {
+        self.imp()
+            .trash
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| Rc::new(DesktopTrash))
+    }
+
+    fn journal(&self) -> Journal {
+        self.imp()
+            .journal
+            .borrow_mut()
+            .get_or_insert_with(Journal::new)
+            .clone()
+    }
+
+    fn record_note_created(&self, note: &Note) {
+        self.journal().record(JournalEvent::NoteCreated {
+            id: journal_id(note),
+        });
+    }
+
+    #[cfg(test)]
+    fn set_trash(&self, trash: impl Trash + 'static) {
+        self.imp().trash.replace(Some(Rc::new(trash)));
+    }
+
+    #[cfg(test)]
+    fn set_load_delay(&self, delay: Duration) {
+        self.imp().load_delay.set(delay);
+    }
+
+    #[cfg(test)]
+    fn set_recovery_dir(&self, path: PathBuf) {
+        self.imp().recovery_dir.set(path).unwrap();
+    }
+
+    #[cfg(test)]
+    fn set_metadata_index_path(&self, path: PathBuf) {
+        self.imp().metadata_index_path.set(path).unwrap();
+    }
+
+    #[cfg(test)]
+    fn set_search_index_path(&self, path: PathBuf) {
+        self.imp().search_index_path.set(path).unwrap();
+    }
+
+    fn data_file_path(&self) -> PathBuf {
+        let mut data_file_path = self.directory().path().unwrap();
+        data_file_path.push(DATA_FILE_NAME);
+        data_file_path
+    }
+
+    fn setup_bindings(&self) {
+        self.repository()
+            .bind_property("sync-state", self, "is-syncing")
+            .transform_to(|_, value| {
+                let sync_state: SyncState = value.get().unwrap();
+                Some(sync_state.is_syncing().to_value())
+            })
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+    }
+
+    fn setup_signals(&self) {
+        if !self.is_offline_mode() {
+            self.repository()
+                .connect_remote_changed(clone!(@weak self as obj => move |_| {
+                    log::info!("New remote changes! Syncing...");
+                    spawn!(async move {
+                        if let Err(err) = obj.sync().await {
+                            log::error!("Failed to sync: {:?}", err);
+                        }
+                    });
+                }));
+        }
+    }
+}
+
+fn is_hidden(file: &gio::File) -> bool {
+    file.basename()
+        .map_or(false, |name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Whether `file` can be a note, which excludes the temporary files that saving writes
+fn is_note_file(file: &gio::File) -> bool {
+    !is_hidden(file) && FileType::for_file(file) == FileType::Markdown
+}
+
+fn file_name(note: &Note) -> String {
+    note.file()
+        .basename()
+        .map(|name| name.display().to_string())
+        .unwrap_or_default()
+}
+
+/// The stamps of the note files in `notes_path` by file name
+fn note_file_stamps(notes_path: &Path) -> io::Result<HashMap<String, FileStamp>> {
+    let mut stamps = HashMap::new();
+
+    for entry in fs::read_dir(notes_path)? {
+        let path = entry?.path();
+
+        let file_name = match path.file_name().and_then(|file_name| file_name.to_str()) {
+            Some(file_name) => file_name.to_string(),
+            None => continue,
+        };
+
+        if FileType::for_file(&gio::File::for_path(&path)) != FileType::Markdown {
+            continue;
+        }
+
+        if let Some(stamp) = FileStamp::for_metadata(&fs::metadata(&path)?) {
+            stamps.insert(file_name, stamp);
+        }
+    }
+
+    Ok(stamps)
+}
+
+/// The file name of `note`, as its title is private and may be in a bug report
+fn journal_id(note: &Note) -> String {
+    file_name(note)
+}
+
+/// Show `err` in the error log of the app, besides logging it
+fn report_error(message: &str, err: &anyhow::Error) {
+    Application::default()
+        .error_log()
+        .push(ErrorReport::from_error(ErrorSource::Notes, message, err));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures_util::future;
+
+    use crate::{
+        core::{DateTime, FrontMatterProfile, MetadataData, SiteExportCancelled, TrashFuture},
+        model::{AttentionEntry, LoadProblemKind, NoteProblem, ProblemEntry, SavedSearch},
+        test_harness::block_on_gtk_thread,
+    };
+
+    /// Like a file system without a Trash
+    #[derive(Debug)]
+    struct UnsupportedTrash;
+
+    impl Trash for UnsupportedTrash {
+        fn trash(&self, _file: &gio::File) -> TrashFuture {
+            Box::pin(async {
+                Err(glib::Error::new(
+                    gio::IOErrorEnum::NotSupported,
+                    "Trashing is not supported",
+                ))
+            })
+        }
+    }
+
+    /// Manager of a new notebook in a temporary directory, where trashing fails
+    async fn new_note_manager(name: &str) -> NoteManager {
+        new_note_manager_with_files(name, &[]).await
+    }
+
+    /// Loaded from a directory with `files`, by their path in it and their contents, with its
+    /// repository open
+    async fn new_note_manager_with_files(name: &str, files: &[(&str, &str)]) -> NoteManager {
+        let note_manager = unloaded_note_manager(name, files).await;
+        note_manager.load().await.unwrap();
+        note_manager.connect_repository().await.unwrap();
+        note_manager.set_trash(UnsupportedTrash);
+        note_manager
+    }
+
+    /// Like [`new_note_manager_with_files`], before it is loaded
+    async fn unloaded_note_manager(name: &str, files: &[(&str, &str)]) -> NoteManager {
+        let path = std::env::temp_dir().join(format!(
+            "noteworthy-note-manager-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        for (file_path, contents) in files {
+            let file_path = path.join(file_path);
+            std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            std::fs::write(file_path, contents).unwrap();
+        }
+
+        // Outside of the notes, so the copies are never committed
+        let recovery_path = path.with_extension("recovery");
+        let _ = std::fs::remove_dir_all(&recovery_path);
+        let metadata_index_path = path.with_extension(METADATA_INDEX_FILE_NAME);
+        let _ = std::fs::remove_file(&metadata_index_path);
+        let search_index_path = path.with_extension(SEARCH_INDEX_FILE_NAME);
+        let _ = std::fs::remove_file(&search_index_path);
+
+        let note_manager = NoteManager::for_directory(&gio::File::for_path(&path), true);
+        note_manager.set_recovery_dir(recovery_path);
+        note_manager.set_metadata_index_path(metadata_index_path);
+        note_manager.set_search_index_path(search_index_path);
+        note_manager
+    }
+
+    /// Like [`new_note_manager`], with a saved note that has an attachment
+    async fn note_manager_with_note(name: &str) -> (NoteManager, Note) {
+        let note_manager = new_note_manager(name).await;
+
+        let attachment_path = note_manager.directory().path().unwrap().join("Camera.png");
+        std::fs::write(&attachment_path, "Image").unwrap();
+
+        let note = note_manager.create_note().unwrap();
+        note.metadata()
+            .attachment_list()
+            .append(Attachment::new(
+                &gio::File::for_path(&attachment_path),
+                &DateTime::now(),
+            ))
+            .unwrap();
+        note.save().await.unwrap();
+
+        (note_manager, note)
+    }
+
+    /// Like [`new_note_manager_with_files`], with `Paris.md` that is not text and fails to
+    /// load
+    async fn note_manager_with_problem(name: &str) -> (NoteManager, gio::File) {
+        let note_manager = unloaded_note_manager(name, &[("Rome.md", "Rome")]).await;
+        let file = note_manager.directory().child("Paris.md");
+        fs::write(file.path().unwrap(), [0xff, 0xfe, 0x00]).unwrap();
+
+        note_manager.load().await.unwrap();
+        note_manager.connect_repository().await.unwrap();
+        (note_manager, file)
+    }
+
+    fn exists(file: &gio::File) -> bool {
+        file.query_exists(gio::Cancellable::NONE)
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn delete_note_falls_back_to_deleting() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("delete-fall-back").await;
+            let attachment_file = note.metadata().attachment_list().to_data()[0].file.clone();
+
+            note_manager
+                .delete_note(&note, DeletionMode::TrashOrDelete)
+                .await
+                .unwrap();
+
+            assert!(!exists(&note.file()));
+            assert!(!attachment_file.exists());
+            assert!(note_manager.note_list().find_by_id(&note.id()).is_none());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn rewrite_front_matter() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("rewrite-front-matter").await;
+            let notes = (0..2)
+                .map(|_| note_manager.create_note().unwrap())
+                .collect::<Vec<_>>();
+            notes[0].metadata().set_is_pinned(true);
+            note_manager.save_all_notes().await.unwrap();
+
+            let text = |note: &Note| std::fs::read_to_string(note.file().path().unwrap()).unwrap();
+            assert!(text(&notes[0]).contains("\nlast_modified: "));
+
+            note_manager
+                .notebook_settings()
+                .set_front_matter_profile(FrontMatterProfile::StandardMarkdown);
+            assert_eq!(
+                notes[1].front_matter_profile(),
+                FrontMatterProfile::StandardMarkdown
+            );
+
+            // Only written in the new profile once they are saved again
+            assert!(text(&notes[1]).contains("\nlast_modified: "));
+
+            let n_rewritten = note_manager.rewrite_front_matter().await.unwrap();
+            assert_eq!(n_rewritten, 2);
+
+            for note in &notes {
+                let text = text(note);
+                assert!(text.contains("\nmodified: "));
+                assert!(!text.contains("last_modified"));
+                assert_eq!(
+                    NoteData::parse(&text).unwrap().metadata,
+                    note.metadata().to_data()
+                );
+            }
+            assert!(text(&notes[0]).contains("\npinned: true\n"));
+
+            let new_note = note_manager.create_note().unwrap();
+            assert_eq!(
+                new_note.front_matter_profile(),
+                FrontMatterProfile::StandardMarkdown
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn derive_titles_from_headings() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("derive-titles-from-headings").await;
+            let contents = [
+                "# Groceries\n\nMilk",
+                "```\n# Comment\n```\n",
+                "# Same\n",
+                "#\nNot yet written",
+            ];
+            let notes = contents
+                .iter()
+                .map(|content| {
+                    let note = note_manager.create_note().unwrap();
+                    note.metadata().set_title("Untitled");
+                    note.buffer().set_text(content);
+                    note
+                })
+                .collect::<Vec<_>>();
+            notes[2].metadata().set_title("Same");
+            note_manager.save_all_notes().await.unwrap();
+
+            let n_changed = note_manager.derive_titles_from_headings().await.unwrap();
+            assert_eq!(n_changed, 1);
+
+            let titles = notes
+                .iter()
+                .map(|note| note.metadata().title())
+                .collect::<Vec<_>>();
+            assert_eq!(titles, ["Groceries", "Untitled", "Same", "Untitled"]);
+
+            let text = std::fs::read_to_string(notes[0].file().path().unwrap()).unwrap();
+            assert_eq!(NoteData::parse(&text).unwrap().metadata.title, "Groceries");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn cancelled_load() {
+        block_on_gtk_thread(|| async {
+            let note_manager = unloaded_note_manager(
+                "cancelled-load",
+                &[("Rome.md", "Rome"), ("Oslo.md", "Oslo")],
+            )
+            .await;
+
+            // Each list that is set, with the number of notes it was told to have
+            let note_lists = Rc::new(RefCell::new(Vec::new()));
+            note_manager.connect_notify_local(
+                Some("note-list"),
+                clone!(@strong note_lists => move |note_manager, _| {
+                    let note_list = note_manager.note_list();
+                    let n_items = Rc::new(Cell::new(0));
+                    note_list.connect_items_changed(
+                        clone!(@strong n_items => move |_, _, removed, added| {
+                            n_items.set(n_items.get() + added - removed);
+                        }),
+                    );
+                    note_lists.borrow_mut().push((note_list, n_items));
+                }),
+            );
+
+            note_manager.set_load_delay(Duration::from_secs(60));
+            let (slow_res, res) = future::join(note_manager.load(), async {
+                glib::timeout_future(Duration::from_millis(50)).await;
+                note_manager.cancel_load();
+
+                note_manager.set_load_delay(Duration::ZERO);
+                note_manager.load().await
+            })
+            .await;
+
+            assert!(slow_res.unwrap_err().is::<LoadCancelled>());
+            res.unwrap();
+
+            let note_lists = note_lists.borrow();
+            assert_eq!(note_lists.len(), 1);
+            let (note_list, n_items) = &note_lists[0];
+            assert_eq!(note_list, &note_manager.note_list());
+            assert_eq!(note_list.len(), 2);
+            assert_eq!(n_items.get(), 2);
+            assert!(note_manager.imp().load_cancellable.borrow().is_none());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn newer_load_cancels() {
+        block_on_gtk_thread(|| async {
+            let note_manager =
+                unloaded_note_manager("newer-load-cancels", &[("Rome.md", "Rome")]).await;
+
+            note_manager.set_load_delay(Duration::from_secs(60));
+            let (slow_res, res) = future::join(note_manager.load(), async {
+                glib::timeout_future(Duration::from_millis(50)).await;
+                note_manager.set_load_delay(Duration::ZERO);
+                note_manager.load().await
+            })
+            .await;
+
+            assert!(slow_res.unwrap_err().is::<LoadCancelled>());
+            res.unwrap();
+            assert_eq!(note_manager.note_list().len(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn load_does_not_wait_for_repository() {
+        block_on_gtk_thread(|| async {
+            let note_manager =
+                unloaded_note_manager("load-does-not-wait-for-repository", &[("Rome.md", "Rome")])
+                    .await;
+
+            note_manager.load().await.unwrap();
+            assert_eq!(note_manager.note_list().len(), 1);
+            assert!(!note_manager.repository().is_open());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn git_operations_wait_for_repository() {
+        block_on_gtk_thread(|| async {
+            let note_manager =
+                unloaded_note_manager("git-operations-wait-for-repository", &[("Rome.md", "Rome")])
+                    .await;
+            note_manager.load().await.unwrap();
+
+            let repository = note_manager.repository();
+            let (changed_files, res) = future::join(repository.changed_files(), async {
+                glib::timeout_future(Duration::from_millis(50)).await;
+                assert!(!repository.is_open());
+                note_manager.connect_repository().await
+            })
+            .await;
+
+            res.unwrap();
+            assert!(repository.is_open());
+            let changed_files = changed_files.unwrap();
+            assert!(changed_files
+                .iter()
+                .any(|changed_file| changed_file.path == Path::new("Rome.md")));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn empty_trash() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("empty-trash").await;
+            let notes = (0..3)
+                .map(|_| note_manager.create_note().unwrap())
+                .collect::<Vec<_>>();
+            notes[0].metadata().set_is_trashed(true);
+            notes[1].metadata().set_is_trashed(true);
+            note_manager.save_all_notes().await.unwrap();
+            assert_eq!(note_manager.n_trashed_notes(), 2);
+
+            let n_deleted = note_manager
+                .empty_trash(DeletionMode::TrashOrDelete)
+                .await
+                .unwrap();
+            assert_eq!(n_deleted, 2);
+            assert_eq!(note_manager.n_trashed_notes(), 0);
+            assert!(!exists(&notes[0].file()));
+            assert!(!exists(&notes[1].file()));
+
+            assert_eq!(note_manager.note_list().len(), 1);
+            assert!(exists(&notes[2].file()));
+
+            // Nothing left to delete
+            let n_deleted = note_manager
+                .empty_trash(DeletionMode::TrashOrDelete)
+                .await
+                .unwrap();
+            assert_eq!(n_deleted, 0);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn empty_trash_with_attachments() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("empty-trash-attachments").await;
+            let notes_path = note_manager.directory().path().unwrap();
+            std::fs::write(notes_path.join("Scan.png"), "Scan").unwrap();
+
+            // Also linked by a note that is kept
+            let linking = note_manager.create_note().unwrap();
+            linking.buffer().set_text("![Scan](Scan.png)");
+            note.buffer().set_text("![Scan](Scan.png)");
+
+            note.metadata().set_is_trashed(true);
+            note_manager.save_all_notes().await.unwrap();
+
+            let n_deleted = note_manager
+                .empty_trash(DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert_eq!(n_deleted, 1);
+            assert!(!exists(&note.file()));
+            assert!(!notes_path.join("Camera.png").exists());
+            assert!(notes_path.join("Scan.png").exists());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn trash_size() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("trash-size").await;
+            let other_note = note_manager.create_note().unwrap();
+            note_manager.save_all_notes().await.unwrap();
+
+            note_manager.refresh_trash_size().await;
+            assert_eq!(note_manager.trash_size(), 0);
+
+            note.metadata().set_is_trashed(true);
+            other_note.metadata().set_is_trashed(true);
+            note_manager.save_all_notes().await.unwrap();
+            note_manager.refresh_trash_size().await;
+
+            let file_size =
+                |file: &gio::File| std::fs::metadata(file.path().unwrap()).unwrap().len();
+            // With the 5 bytes of its attachment
+            let expected = file_size(&note.file()) + file_size(&other_note.file()) + 5;
+            assert_eq!(note_manager.trash_size(), expected);
+
+            // Kept until the notes change
+            std::fs::write(
+                note_manager.directory().path().unwrap().join("Camera.png"),
+                "Larger image",
+            )
+            .unwrap();
+            note_manager.refresh_trash_size().await;
+            assert_eq!(note_manager.trash_size(), expected);
+
+            other_note.metadata().set_is_trashed(false);
+            note_manager.refresh_trash_size().await;
+            assert_eq!(note_manager.trash_size(), file_size(&note.file()) + 12);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn restore_notes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("restore-notes").await;
+            let notes = (0..3)
+                .map(|_| note_manager.create_note().unwrap())
+                .collect::<Vec<_>>();
+            notes[0].metadata().set_is_trashed(true);
+            notes[1].metadata().set_is_trashed(true);
+            assert_eq!(note_manager.n_trashed_notes(), 2);
+
+            // Like a note restored by its row while it is selected with the others
+            assert_eq!(note_manager.restore_notes(&notes[1..2]), 1);
+            let selected = [
+                notes[0].clone(),
+                notes[1].clone(),
+                notes[0].clone(),
+                notes[2].clone(),
+            ];
+            assert_eq!(note_manager.restore_notes(&selected), 1);
+
+            assert_eq!(note_manager.n_trashed_notes(), 0);
+            assert!(notes.iter().all(|note| !note.metadata().is_trashed()));
+            assert_eq!(note_manager.restore_notes(&selected), 0);
+
+            // Nothing is left for emptying the trash
+            note_manager.save_all_notes().await.unwrap();
+            let n_deleted = note_manager
+                .empty_trash(DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert_eq!(n_deleted, 0);
+            assert_eq!(note_manager.note_list().len(), 3);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn create_note_with_tag() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("create-note-with-tag").await;
+            let tag = Tag::new("Work");
+            note_manager.tag_list().append(tag.clone()).unwrap();
+
+            let tagged = note_manager.create_note_with_tag(Some(&tag)).unwrap();
+            assert!(tagged.metadata().tag_list().contains(&tag));
+            assert_eq!(note_manager.notes_with_tag(&tag), [tagged.clone()]);
+
+            let untagged = note_manager.create_note_with_tag(None).unwrap();
+            assert!(untagged.metadata().tag_list().is_empty());
+            assert_eq!(note_manager.note_list().len(), 2);
+
+            // Saved with the tag
+            note_manager.save_all_notes().await.unwrap();
+            let text = std::fs::read_to_string(tagged.file().path().unwrap()).unwrap();
+            assert!(text.contains("Work"));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn load_progress() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("load-progress").await;
+            let notes = (0..3)
+                .map(|_| note_manager.create_note().unwrap())
+                .collect::<Vec<_>>();
+            note_manager.save_all_notes().await.unwrap();
+
+            let directory = note_manager.directory();
+            let reloaded = NoteManager::for_directory(&directory, true);
+            reloaded.set_recovery_dir(note_manager.recovery_dir().to_path_buf());
+            // Without an index, so the notes are loaded from their files
+            reloaded.set_metadata_index_path(
+                note_manager.metadata_index_path().with_extension("missing"),
+            );
+            reloaded.set_search_index_path(note_manager.search_index_path().to_path_buf());
+
+            let progress = Rc::new(RefCell::new(Vec::new()));
+            reloaded.connect_notify_local(
+                Some("load-progress"),
+                clone!(@strong progress => move |obj, _| {
+                    progress.borrow_mut().push(obj.load_progress());
+                }),
+            );
+            reloaded.load().await.unwrap();
+
+            assert_eq!(reloaded.note_list().len(), notes.len());
+            assert_eq!(*progress.borrow(), [1.0]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn load_from_metadata_index() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("load-from-metadata-index").await;
+            let notes = ["Changed", "Removed", "Same"]
+                .into_iter()
+                .map(|title| {
+                    let note = note_manager.create_note().unwrap();
+                    note.metadata().set_title(title);
+                    note
+                })
+                .collect::<Vec<_>>();
+            note_manager.save_all_notes().await.unwrap();
+
+            let notes_path = note_manager.directory().path().unwrap();
+            let index_path = note_manager.metadata_index_path().to_path_buf();
+            let index = MetadataIndex::parse(&fs::read(&index_path).unwrap(), &notes_path).unwrap();
+            assert_eq!(index.entries().count(), notes.len());
+
+            // Changed by another app while this one was not running
+            let changed_path = notes[0].file().path().unwrap();
+            fs::write(
+                &changed_path,
+                "---\ntitle: Changed elsewhere\n---\nNew content",
+            )
+            .unwrap();
+            fs::remove_file(notes[1].file().path().unwrap()).unwrap();
+            fs::write(notes_path.join("added.md"), "---\ntitle: Added\n---\n").unwrap();
+
+            let reloaded = NoteManager::for_directory(&note_manager.directory(), true);
+            reloaded.set_recovery_dir(note_manager.recovery_dir().to_path_buf());
+            reloaded.set_metadata_index_path(index_path);
+            reloaded.set_search_index_path(note_manager.search_index_path().to_path_buf());
+            reloaded.load().await.unwrap();
+
+            let titles = |note_manager: &NoteManager| {
+                let mut titles = note_manager
+                    .note_list()
+                    .iter()
+                    .map(|note| note.metadata().title())
+                    .collect::<Vec<_>>();
+                titles.sort();
+                titles
+            };
+
+            // Shown as indexed until the index is verified
+            assert_eq!(titles(&reloaded), ["Changed", "Removed", "Same"]);
+
+            reloaded.verify_indexed_notes(&index).await;
+            assert_eq!(titles(&reloaded), ["Added", "Changed elsewhere", "Same"]);
+
+            let changed = reloaded.note_list().find_by_id(&notes[0].id()).unwrap();
+            assert_eq!(&*changed.lowercase_content(), "new content");
+            assert!(changed.is_saved());
+
+            // Written again, so it matches the files
+            let index_path = reloaded.metadata_index_path();
+            let index = MetadataIndex::parse(&fs::read(index_path).unwrap(), &notes_path).unwrap();
+            let file_names = index
+                .entries()
+                .map(|(file_name, _)| file_name.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(file_names.len(), 3);
+            assert!(file_names.contains(&"added.md".to_string()));
+            assert!(index
+                .reconcile(&note_file_stamps(&notes_path).unwrap())
+                .is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn search_contents() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("search-contents").await;
+            let groceries = note_manager.create_note().unwrap();
+            groceries.buffer().set_text("Milk and eggs");
+            let journal = note_manager.create_note().unwrap();
+            journal.buffer().set_text("Bought milk, then more milk");
+            note_manager.save_all_notes().await.unwrap();
+
+            note_manager.build_search_index().await;
+
+            let ids = |text: &str| {
+                note_manager
+                    .search_contents(text)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(note, _)| note.id())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(ids("milk"), [journal.id(), groceries.id()]);
+            assert_eq!(ids("EGG"), [groceries.id()]);
+            assert!(note_manager.search_contents(" - ").is_none());
+
+            // Searchable before it is saved
+            journal.buffer().set_text("Bought eggs");
+            journal.flush_content_changed();
+            assert_eq!(ids("eggs").len(), 2);
+
+            note_manager
+                .delete_note(&groceries, DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert_eq!(ids("eggs"), [journal.id()]);
+
+            // Written with the saved notes, so none is indexed again on the next load
+            note_manager.save_all_notes().await.unwrap();
+            let notes_path = note_manager.directory().path().unwrap();
+            let index_path = note_manager.search_index_path();
+            let index = SearchIndex::parse(&fs::read(index_path).unwrap(), &notes_path).unwrap();
+            assert!(index
+                .reconcile(&note_file_stamps(&notes_path).unwrap())
+                .is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn move_note_file() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("move-note-file").await;
+            let note = note_manager.create_note().unwrap();
+            note_manager.save_all_notes().await.unwrap();
+
+            let old_file = note.file();
+            let new_file = note_manager.directory().child("moved.md");
+            fs::rename(old_file.path().unwrap(), new_file.path().unwrap()).unwrap();
+            note_manager.handle_file_event(
+                &old_file,
+                Some(&new_file),
+                gio::FileMonitorEvent::Renamed,
+            );
+
+            let note_list = note_manager.note_list();
+            assert_eq!(note_list.len(), 1);
+            assert_eq!(note.file().path(), new_file.path());
+            assert_eq!(note_list.find_by_id(&note.id()), Some(note));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn move_note_file_by_contents() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("move-note-file-by-contents").await;
+            let note = note_manager.create_note().unwrap();
+            note.buffer()
+                .set_text("Moved without being reported as moved");
+            let other_note = note_manager.create_note().unwrap();
+            note_manager.save_all_notes().await.unwrap();
+
+            let old_file = note.file();
+            let new_file = note_manager.directory().child("moved.md");
+            fs::rename(old_file.path().unwrap(), new_file.path().unwrap()).unwrap();
+            note_manager.handle_file_event(&old_file, None, gio::FileMonitorEvent::Deleted);
+            note_manager.handle_file_created(&new_file).await;
+
+            let note_list = note_manager.note_list();
+            assert_eq!(note_list.len(), 2);
+            assert_eq!(note.file().path(), new_file.path());
+            assert_eq!(note_list.find_by_id(&note.id()), Some(note));
+            assert_ne!(other_note.file().path(), new_file.path());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn note_file_problems() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("note-file-problems").await;
+            let note = note_manager.create_note().unwrap();
+            note.buffer().set_text("Milk");
+            note_manager.save_all_notes().await.unwrap();
+            let file = note.file();
+
+            fs::remove_file(file.path().unwrap()).unwrap();
+            note_manager.handle_file_event(&file, None, gio::FileMonitorEvent::Deleted);
+            assert_eq!(note.problem(), NoteProblem::FileMissing);
+
+            // Not recreated behind the back of the user
+            note.buffer().set_text("Milk\nEggs");
+            note_manager.save_all_notes().await.unwrap();
+            assert!(!exists(&file));
+            assert!(!note.is_saved());
+
+            note_manager.write_note_file(&note).await.unwrap();
+            assert!(exists(&file));
+            assert!(note.is_saved());
+            assert_eq!(note.problem(), NoteProblem::None);
+
+            // Its own save is not a change made elsewhere
+            note_manager.check_note_file_changed(&file).await;
+            assert_eq!(note.problem(), NoteProblem::None);
+
+            let text = fs::read_to_string(file.path().unwrap()).unwrap();
+            fs::write(file.path().unwrap(), text.replace("Eggs", "Bread")).unwrap();
+            note_manager.check_note_file_changed(&file).await;
+            assert_eq!(note.problem(), NoteProblem::ExternallyModified);
+
+            note.buffer().set_text("Milk\nEggs\nButter");
+            note_manager.save_all_notes().await.unwrap();
+            assert!(fs::read_to_string(file.path().unwrap())
+                .unwrap()
+                .contains("Bread"));
+
+            note.reload().await.unwrap();
+            assert_eq!(note.problem(), NoteProblem::None);
+            assert!(note.is_saved());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn load_problems() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, file) = note_manager_with_problem("load-problems").await;
+            let problem_list = note_manager.problem_list();
+
+            // The other notes are still loaded
+            assert_eq!(note_manager.note_list().len(), 1);
+            assert_eq!(problem_list.n_problems(), 1);
+            let entry = problem_list
+                .item(0)
+                .unwrap()
+                .downcast::<ProblemEntry>()
+                .unwrap();
+            assert_eq!(entry.path(), file.path().unwrap());
+            assert_eq!(entry.kind(), LoadProblemKind::NotText);
+
+            // Still broken, so it is reported once
+            assert!(note_manager.retry_load(&file).await.is_err());
+            assert_eq!(problem_list.n_problems(), 1);
+            assert_eq!(note_manager.note_list().len(), 1);
+
+            fs::write(file.path().unwrap(), "Paris").unwrap();
+            note_manager.retry_load(&file).await.unwrap();
+            assert_eq!(problem_list.n_problems(), 0);
+            assert_eq!(note_manager.note_list().len(), 2);
+            assert!(note_manager.note_at(&file).is_some());
+
+            // Found again by the next load
+            fs::write(file.path().unwrap(), [0xff]).unwrap();
+            note_manager.load().await.unwrap();
+            assert_eq!(problem_list.n_problems(), 1);
+            assert_eq!(note_manager.note_list().len(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn problem_file_removed() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, file) = note_manager_with_problem("problem-file-removed").await;
+
+            fs::remove_file(file.path().unwrap()).unwrap();
+            note_manager.handle_file_event(&file, None, gio::FileMonitorEvent::Deleted);
+            assert_eq!(note_manager.problem_list().n_problems(), 0);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn exclude_problem_file() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, file) = note_manager_with_problem("exclude-problem-file").await;
+
+            note_manager
+                .exclude_file(&file.path().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(note_manager.problem_list().n_problems(), 0);
+            assert!(note_manager.is_excluded(&file.path().unwrap()));
+
+            let exclusions_file = note_manager.directory().child(EXCLUSIONS_FILE_NAME);
+            let (contents, _) = exclusions_file.load_contents_future().await.unwrap();
+            let contents = String::from_utf8(contents).unwrap();
+            assert!(contents.starts_with(DEFAULT_EXCLUSIONS));
+            assert!(contents.ends_with("\n/Paris.md\n"));
+
+            // Not loaded anymore
+            note_manager.load().await.unwrap();
+            assert_eq!(note_manager.problem_list().n_problems(), 0);
+            assert_eq!(note_manager.note_list().len(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn discard_note() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("discard-note").await;
+            let note = note_manager.create_note().unwrap();
+            note_manager.save_all_notes().await.unwrap();
+
+            let file = note.file();
+            fs::remove_file(file.path().unwrap()).unwrap();
+            note_manager.handle_file_event(&file, None, gio::FileMonitorEvent::Deleted);
+
+            note_manager.discard_note(&note).unwrap();
+            assert!(note_manager.note_list().find_by_id(&note.id()).is_none());
+            assert!(!exists(&file));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn n_trashed_notes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("n-trashed-notes").await;
+            assert_eq!(note_manager.n_trashed_notes(), 0);
+
+            let notes = (0..3)
+                .map(|_| note_manager.create_note().unwrap())
+                .collect::<Vec<_>>();
+            for note in &notes {
+                note.metadata().set_is_trashed(true);
+            }
+            assert_eq!(note_manager.n_trashed_notes(), 3);
+
+            notes[0].metadata().set_is_trashed(false);
+            assert_eq!(note_manager.n_trashed_notes(), 2);
+
+            note_manager
+                .delete_note(&notes[1], DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert_eq!(note_manager.n_trashed_notes(), 1);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn pending_changes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("pending-changes").await;
+            let n_pending_changes = note_manager.pending_changes();
+
+            note_manager
+                .create_note_with_content("First", "Content", &[])
+                .unwrap();
+            note_manager
+                .create_note_with_content("Second", "Content", &[])
+                .unwrap();
+            note_manager.save_all_notes().await.unwrap();
+            assert_eq!(note_manager.pending_changes(), n_pending_changes + 2);
+
+            // Saving again doesn't add changes that are already pending
+            note_manager.save_all_notes().await.unwrap();
+            assert_eq!(note_manager.pending_changes(), n_pending_changes + 2);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn journal_records_without_titles() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("journal").await;
+            let journal = Journal::new();
+            note_manager.set_journal(&journal);
+
+            let note = note_manager
+                .create_note_with_content("Secret plans", "Content", &[])
+                .unwrap();
+            note_manager.save_all_notes().await.unwrap();
+
+            let file_name = journal_id(&note);
+            assert!(file_name.ends_with(".md"));
+
+            let events = journal
+                .entries()
+                .into_iter()
+                .map(|entry| entry.event)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                events[0],
+                JournalEvent::NoteCreated {
+                    id: file_name.clone()
+                }
+            );
+            assert!(matches!(
+                events[1],
+                JournalEvent::NoteSaved { ref id, bytes } if *id == file_name && bytes > 0
+            ));
+            assert!(journal
+                .entries()
+                .iter()
+                .all(|entry| !entry.to_line().contains("Secret plans")));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn delete_note_error() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("delete-error").await;
+
+            let err = note_manager
+                .delete_note(&note, DeletionMode::Trash)
+                .await
+                .unwrap_err();
+            let err = err.downcast_ref::<glib::Error>().unwrap();
+            assert!(err.matches(gio::IOErrorEnum::NotSupported));
+
+            // Nothing is removed when the note can't be
+            assert!(exists(&note.file()));
+            assert!(note_manager.note_list().find_by_id(&note.id()).is_some());
+            assert!(!note.metadata().attachment_list().is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn read_only_guards() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("read-only").await;
+            let unsaved_note = note_manager.create_note().unwrap();
+            unsaved_note.buffer().set_text("Changed");
+
+            note_manager.set_is_read_only(true);
+
+            let is_read_only = |err: anyhow::Error| err.downcast_ref::<ReadOnly>().is_some();
+            assert!(is_read_only(note_manager.create_note().unwrap_err()));
+            assert!(is_read_only(
+                note_manager
+                    .create_note_with_content("Title", "Content", &[])
+                    .unwrap_err()
+            ));
+            assert!(is_read_only(
+                note_manager.save_all_notes().await.unwrap_err()
+            ));
+            assert!(is_read_only(
+                note_manager.save_data_file().await.unwrap_err()
+            ));
+            assert!(is_read_only(
+                note_manager
+                    .delete_note(&note, DeletionMode::Delete)
+                    .await
+                    .unwrap_err()
+            ));
+
+            // Nothing is changed, and the changes are kept for later
+            assert!(exists(&note.file()));
+            assert_eq!(note_manager.note_list().len(), 2);
+            assert!(note_manager
+                .note_list()
+                .unsaved_notes()
+                .contains(&unsaved_note));
+
+            // Without a remote there is nothing to pull, and nothing is committed
+            note_manager.sync().await.unwrap();
+            assert!(note_manager
+                .note_list()
+                .unsaved_notes()
+                .contains(&unsaved_note));
+
+            note_manager.set_is_read_only(false);
+            note_manager.save_all_notes().await.unwrap();
+            assert!(note_manager.note_list().unsaved_notes().is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn default_exclusions() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("default-exclusions").await;
+            let exclusions_file = note_manager.directory().child(EXCLUSIONS_FILE_NAME);
+
+            let (contents, _) = exclusions_file.load_contents_future().await.unwrap();
+            assert_eq!(contents, DEFAULT_EXCLUSIONS.as_bytes());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn excluded_notes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager_with_files(
+                "excluded-notes",
+                &[
+                    (EXCLUSIONS_FILE_NAME, "Draft *.md\n"),
+                    ("Note.md", "Note"),
+                    ("Draft Ideas.md", "Ideas"),
+                ],
+            )
+            .await;
+            let notes_path = note_manager.directory().path().unwrap();
+
+            let file_names = note_manager
+                .note_list()
+                .iter()
+                .map(|note| file_name(&note))
+                .collect::<Vec<_>>();
+            assert_eq!(file_names, ["Note.md"]);
+
+            note_manager.sync().await.unwrap();
+
+            let repo = git2::Repository::open(&notes_path).unwrap();
+            let tree = repo.head().unwrap().peel_to_tree().unwrap();
+            assert!(tree.get_name("Note.md").is_some());
+            assert!(tree.get_name(EXCLUSIONS_FILE_NAME).is_some());
+            assert!(tree.get_name("Draft Ideas.md").is_none());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn changed_files_of_excluded_notes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager_with_files(
+                "changed-files-of-excluded-notes",
+                &[
+                    (EXCLUSIONS_FILE_NAME, "Draft *.md\n"),
+                    ("Note.md", "Note"),
+                    ("Draft Ideas.md", "Ideas"),
+                ],
+            )
+            .await;
+            let notes_path = note_manager.directory().path().unwrap();
+            fs::write(notes_path.join("Draft Plans.md"), "Plans").unwrap();
+            fs::write(notes_path.join("Pulled.md"), "Pulled").unwrap();
+
+            // Like after a pull that changed them
+            note_manager
+                .handle_changed_files(&[
+                    (notes_path.join("Draft Ideas.md"), git2::Delta::Modified),
+                    (notes_path.join("Draft Plans.md"), git2::Delta::Added),
+                    (notes_path.join("Pulled.md"), git2::Delta::Modified),
+                ])
+                .await
+                .unwrap();
+
+            let mut file_names = note_manager
+                .note_list()
+                .iter()
+                .map(|note| file_name(&note))
+                .collect::<Vec<_>>();
+            file_names.sort();
+            assert_eq!(file_names, ["Note.md", "Pulled.md"]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn tag_index() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("tag-index").await;
+            let tag = Tag::new("work");
+            note_manager.tag_list().append(tag.clone()).unwrap();
+            assert!(note_manager.notes_with_tag(&tag).is_empty());
+
+            note.metadata().tag_list().append(tag.clone()).unwrap();
+            let other_note = note_manager
+                .create_note_with_content("Other", "", &["work".to_string()])
+                .unwrap();
+            assert_eq!(note_manager.notes_with_tag(&tag).len(), 2);
+
+            // Trashed notes keep their tags, so deleting it removes it from them too
+            other_note.metadata().set_is_trashed(true);
+            assert_eq!(note_manager.notes_with_tag(&tag).len(), 2);
+
+            note.metadata().tag_list().remove(&tag).unwrap();
+            assert_eq!(note_manager.notes_with_tag(&tag), [other_note.clone()]);
+
+            note_manager
+                .delete_note(&other_note, DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert!(note_manager.notes_with_tag(&tag).is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn delete_tag() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("delete-tag").await;
+            let tag = Tag::new("work");
+            let other_tag = Tag::new("home");
+            note_manager.tag_list().append(tag.clone()).unwrap();
+            note_manager.tag_list().append(other_tag.clone()).unwrap();
+
+            let note_tag_list = note.metadata().tag_list();
+            note_tag_list.append(tag.clone()).unwrap();
+            note_tag_list.append(other_tag.clone()).unwrap();
+            let other_note = note_manager
+                .create_note_with_content("Other", "", &["work".to_string()])
+                .unwrap();
+            note_manager.create_note().unwrap();
+
+            note_manager.delete_tag(&tag).unwrap();
+            assert!(!note_manager.tag_list().contains(&tag));
+            assert!(!note_tag_list.contains(&tag));
+            assert!(note_tag_list.contains(&other_tag));
+            assert!(!other_note.metadata().tag_list().contains(&tag));
+            assert!(note_manager.notes_with_tag(&tag).is_empty());
+            assert_eq!(note_manager.notes_with_tag(&other_tag), [note.clone()]);
+
+            note_manager.set_is_read_only(true);
+            let err = note_manager.delete_tag(&other_tag).unwrap_err();
+            assert!(err.downcast_ref::<ReadOnly>().is_some());
+            assert!(note_tag_list.contains(&other_tag));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn restore_deleted_tag() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("restore-deleted-tag").await;
+            let tag = Tag::new("work");
+            note_manager.tag_list().append(tag.clone()).unwrap();
+            note.metadata().tag_list().append(tag.clone()).unwrap();
+            let deleted_note = note_manager
+                .create_note_with_content("Deleted", "", &["work".to_string()])
+                .unwrap();
+            let untagged_note = note_manager.create_note().unwrap();
+
+            let deleted_tag = note_manager.delete_tag(&tag).unwrap();
+            assert_eq!(deleted_tag.name, "work");
+            assert_eq!(deleted_tag.note_ids.len(), 2);
+            assert_eq!(note_manager.deleted_tags(), [deleted_tag.clone()]);
+
+            // Another tag was renamed to the name of the deleted one
+            let renamed_tag = Tag::new("home");
+            note_manager.tag_list().append(renamed_tag.clone()).unwrap();
+            untagged_note
+                .metadata()
+                .tag_list()
+                .append(renamed_tag.clone())
+                .unwrap();
+            note_manager
+                .tag_list()
+                .rename_tag(&renamed_tag, "work")
+                .unwrap();
+
+            note_manager
+                .delete_note(&deleted_note, DeletionMode::Delete)
+                .await
+                .unwrap();
+
+            let restored_tag = note_manager.restore_deleted_tag(&deleted_tag).unwrap();
+            assert_eq!(restored_tag, renamed_tag);
+            assert_eq!(note_manager.tag_list().len(), 1);
+            assert!(note.metadata().tag_list().contains(&renamed_tag));
+            assert!(untagged_note.metadata().tag_list().contains(&renamed_tag));
+            assert!(note_manager.deleted_tags().is_empty());
+
+            // Only once
+            assert!(note_manager.restore_deleted_tag(&deleted_tag).is_err());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn purge_deleted_tags() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("purge-deleted-tags").await;
+            let deleted_tag = |name: &str, days_ago: i64| DeletedTagData {
+                name: name.to_string(),
+                deleted: DateTime::from_timestamp(
+                    DateTime::now().to_local().timestamp() - days_ago * 24 * 60 * 60,
+                ),
+                note_ids: Vec::new(),
+            };
+
+            note_manager.set_deleted_tags(vec![
+                deleted_tag("old", DELETED_TAG_RETENTION_DAYS + 1),
+                deleted_tag("recent", DELETED_TAG_RETENTION_DAYS - 1),
+            ]);
+
+            let names = note_manager
+                .deleted_tags()
+                .into_iter()
+                .map(|deleted_tag| deleted_tag.name)
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["recent"]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn import_vault() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("import-vault").await;
+            note_manager.tag_list().append(Tag::new("home")).unwrap();
+
+            let vault_path = note_manager.directory().path().unwrap().join("Vault");
+            std::fs::create_dir_all(vault_path.join("Trips")).unwrap();
+            std::fs::write(
+                vault_path.join("Trips/Rome.md"),
+                "---\ntags: [travel]\n---\n![[map.png]] #home [[Missing]]",
+            )
+            .unwrap();
+            std::fs::write(vault_path.join("Trips/map.png"), "Image").unwrap();
+
+            let options = VaultImportOptions {
+                folders_as_tags: true,
+                ..VaultImportOptions::default()
+            };
+            let report = note_manager
+                .import_vault(&gio::File::for_path(&vault_path), options)
+                .await
+                .unwrap();
+
+            assert_eq!(report.n_notes, 1);
+            assert_eq!(report.n_attachments, 1);
+            assert_eq!(report.created_tags, ["Trips", "travel"]);
+            assert_eq!(report.unresolved_links.len(), 1);
+
+            let note = note_manager.note_list().find_by_title("Rome").unwrap();
+            assert_eq!(note.metadata().title(), "Rome");
+            assert_eq!(
+                note.metadata().tag_list().names(),
+                ["travel", "home", "Trips"]
+            );
+            assert!(note_manager.tag_list().contains_with_name("travel"));
+
+            let attachment_path = note_manager.directory().path().unwrap().join("map.png");
+            assert_eq!(std::fs::read(&attachment_path).unwrap(), b"Image");
+            assert_eq!(
+                note.metadata().attachment_list().to_data()[0].file,
+                attachment_path
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn export_site() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("export-site").await;
+            note.metadata().set_title("Trip");
+            note.buffer().set_text("![Camera](Camera.png) [[Missing]]");
+
+            let trashed_note = note_manager.create_note().unwrap();
+            trashed_note.metadata().set_title("Old");
+            trashed_note.metadata().set_is_trashed(true);
+
+            let output_path = note_manager.directory().path().unwrap().join("Site");
+            let last_progress = Rc::new(Cell::new(0.0));
+            let report = note_manager
+                .export_site(
+                    &gio::File::for_path(&output_path),
+                    None,
+                    clone!(@strong last_progress => move |fraction| last_progress.set(fraction)),
+                    &gio::Cancellable::new(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(report.n_pages, 2);
+            assert_eq!(report.n_attachments, 1);
+            assert_eq!(report.invalid_links.len(), 1);
+            assert!(output_path.join("index.html").exists());
+            assert!(output_path.join("trip.html").exists());
+            assert!(!output_path.join("old.html").exists());
+            assert_eq!(
+                std::fs::read(output_path.join("attachments/Camera.png")).unwrap(),
+                b"Image"
+            );
+
+            let cancellable = gio::Cancellable::new();
+            cancellable.cancel();
+            let err = note_manager
+                .export_site(
+                    &gio::File::for_path(&output_path),
+                    None,
+                    |_| {},
+                    &cancellable,
+                )
+                .await
+                .unwrap_err();
+            assert!(err.is::<SiteExportCancelled>());
+
+            // Pending progress is handled once the main context runs again
+            while glib::MainContext::default().iteration(false) {}
+            assert_eq!(last_progress.get(), 1.0);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn export_and_import_tag_bundle() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("export-tag-bundle").await;
+            let tag = Tag::new("Recipes");
+            note_manager.tag_list().append(tag.clone()).unwrap();
+            note.metadata().tag_list().append(tag.clone()).unwrap();
+            note.metadata().set_title("Soup");
+            note.buffer().set_text("Boil water");
+
+            let other_note = note_manager.create_note().unwrap();
+            other_note.metadata().set_title("Groceries");
+
+            let bundle_file = gio::File::for_path(std::env::temp_dir().join(format!(
+                "noteworthy-tag-bundle-{}.nwtybundle",
+                std::process::id()
+            )));
+            let written_bundle = note_manager
+                .export_tag_bundle(&tag, &bundle_file, |_| {})
+                .await
+                .unwrap();
+            assert_eq!(written_bundle.n_notes, 1);
+            assert_eq!(written_bundle.n_attachments, 1);
+
+            let other_manager = new_note_manager("import-tag-bundle").await;
+            let bundle = other_manager.read_tag_bundle(&bundle_file).await.unwrap();
+            assert_eq!(other_manager.n_existing_bundle_notes(&bundle), 0);
+
+            let last_progress = Rc::new(Cell::new(0.0));
+            let report = other_manager
+                .import_tag_bundle(
+                    bundle,
+                    CollisionPolicy::Skip,
+                    clone!(@strong last_progress => move |fraction| last_progress.set(fraction)),
+                )
+                .await
+                .unwrap();
+            assert_eq!(report.n_notes, 1);
+            assert_eq!(report.n_attachments, 1);
+            assert_eq!(report.created_tags, ["Recipes"]);
+            assert_eq!(last_progress.get(), 1.0);
+
+            let imported_note = other_manager.note_list().find_by_title("Soup").unwrap();
+            assert_eq!(file_name(&imported_note), file_name(&note));
+            assert_eq!(imported_note.metadata().tag_list().names(), ["Recipes"]);
+            assert!(other_manager
+                .note_list()
+                .find_by_title("Groceries")
+                .is_none());
+
+            let attachment_path = other_manager.directory().path().unwrap().join("Camera.png");
+            assert_eq!(std::fs::read(&attachment_path).unwrap(), b"Image");
+            assert_eq!(
+                imported_note.metadata().attachment_list().to_data()[0].file,
+                attachment_path
+            );
+
+            // Importing it again runs into the note it added
+            let bundle = other_manager.read_tag_bundle(&bundle_file).await.unwrap();
+            assert_eq!(other_manager.n_existing_bundle_notes(&bundle), 1);
+            let report = other_manager
+                .import_tag_bundle(bundle, CollisionPolicy::Skip, |_| {})
+                .await
+                .unwrap();
+            assert_eq!(report.n_notes, 0);
+            assert_eq!(report.skipped_notes.len(), 1);
+
+            let bundle = other_manager.read_tag_bundle(&bundle_file).await.unwrap();
+            let report = other_manager
+                .import_tag_bundle(bundle, CollisionPolicy::Duplicate, |_| {})
+                .await
+                .unwrap();
+            assert_eq!(report.n_notes, 1);
+            assert!(report.created_tags.is_empty());
+            assert_eq!(other_manager.note_list().n_items(), 2);
+
+            let _ = std::fs::remove_file(bundle_file.path().unwrap());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn restore_recovered_notes() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("restore-recovered").await;
+            let recovery_dir = note_manager.recovery_dir().to_path_buf();
+            std::fs::create_dir_all(&recovery_dir).unwrap();
+
+            let newer_note = note_manager.create_note().unwrap();
+            newer_note.buffer().set_text("Saved");
+            let older_note = note_manager.create_note().unwrap();
+            older_note.buffer().set_text("Saved");
+            note_manager.save_all_notes().await.unwrap();
+
+            let now = std::time::SystemTime::now();
+            let hour = Duration::from_secs(60 * 60);
+            let write_copy = |file_name: &str, title: &str, modified| {
+                let note_data = NoteData {
+                    metadata: MetadataData {
+                        title: title.into(),
+                        ..MetadataData::default()
+                    },
+                    content: format!("Unsaved {}", title),
+                };
+                let path = recovery_dir.join(file_name);
+                std::fs::write(
+                    &path,
+                    note_data.serialize(FrontMatterProfile::Noteworthy).unwrap(),
+                )
+                .unwrap();
+                std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+            };
+            write_copy(&file_name(&newer_note), "Newer", now + hour);
+            write_copy(&file_name(&older_note), "Older", now - hour);
+            write_copy("Gone.md", "Gone", now);
+
+            note_manager.load_recovered_notes().await;
+
+            // Copies older than their note were saved already
+            let recovered_notes = note_manager.recovered_notes();
+            let file_names = recovered_notes
+                .iter()
+                .map(|recovered_note| recovered_note.file_name.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(file_names, [file_name(&newer_note), "Gone.md".into()]);
+            assert!(!recovery_dir.join(file_name(&older_note)).exists());
+
+            let note = note_manager
+                .restore_recovered_note(&recovered_notes[0])
+                .unwrap();
+            assert_eq!(note, newer_note);
+            assert_eq!(note.metadata().title(), "Newer");
+            assert!(!note.is_saved());
+
+            // A new note is created when its note no longer exists
+            let n_notes = note_manager.note_list().len();
+            let note = note_manager
+                .restore_recovered_note(&recovered_notes[1])
+                .unwrap();
+            assert_eq!(note.metadata().title(), "Gone");
+            assert_eq!(note_manager.note_list().len(), n_notes + 1);
+            assert!(note_manager.recovered_notes().is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn find_then_keep_duplicates() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("find-duplicates").await;
+            let create_note = |title: &str, content: &str| {
+                let note = note_manager.create_note().unwrap();
+                note.metadata().set_title(title);
+                note.buffer().set_text(content);
+                note
+            };
+
+            let original = create_note("Groceries", "- Milk\n- Eggs");
+            let copy = create_note("Groceries (Copy)", "- Milk\n\n- Eggs\n");
+            let linking = create_note("Plans", "Buy [[Groceries (Copy)|milk]]");
+            create_note("Empty", "");
+            create_note("Also Empty", "");
+
+            let groups = note_manager.find_duplicates(false).await;
+            assert_eq!(groups.len(), 1);
+            assert_eq!(groups[0].len(), 2);
+            assert!(groups[0].contains(&original) && groups[0].contains(&copy));
+
+            assert_eq!(note_manager.keep_duplicate(&original, &groups[0]), 1);
+            assert!(copy.metadata().is_trashed());
+            assert!(!original.metadata().is_trashed());
+
+            let buffer = linking.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+            assert_eq!(
+                buffer.text(&start_iter, &end_iter, true),
+                "Buy [[Groceries|milk]]"
+            );
+
+            // Trashed notes are no longer duplicates
+            assert!(note_manager.find_duplicates(true).await.is_empty());
+        });
+    }
+
+    fn text(note: &Note) -> String {
+        let buffer = note.buffer();
+        let (start_iter, end_iter) = buffer.bounds();
+        buffer.text(&start_iter, &end_iter, true).to_string()
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn update_links_then_undo() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("update-links").await;
+            let create_note = |title: &str, content: &str| {
+                let note = note_manager.create_note().unwrap();
+                note.metadata().set_title(title);
+                note.buffer().set_text(content);
+                note
+            };
+
+            let renamed = create_note("Rome", "");
+            let linking = create_note("Trip", "Rome first: [[Rome]], then [[rome|back]]");
+            let in_code = create_note("Syntax", "`[[Rome]]` makes a link");
+            let other = create_note("Paris", "[[Trip]]");
+
+            // Renamed back and forth without leaving the title
+            assert!(matches!(
+                note_manager.update_links(&renamed, "ROME "),
+                LinkUpdate::Unlinked
+            ));
+
+            renamed.metadata().set_title("Roma");
+            let updated_links = match note_manager.update_links(&renamed, "Rome") {
+                LinkUpdate::Updated(updated_links) => updated_links,
+                update => panic!("Expected the links to be updated, got {:?}", update),
+            };
+            assert_eq!(updated_links.n_links(), 2);
+            assert_eq!(text(&linking), "Rome first: [[Roma]], then [[Roma|back]]");
+            assert_eq!(text(&in_code), "`[[Rome]]` makes a link");
+            assert_eq!(text(&other), "[[Trip]]");
+
+            updated_links.undo();
+            assert_eq!(renamed.metadata().title(), "Rome");
+            assert_eq!(text(&linking), "Rome first: [[Rome]], then [[rome|back]]");
+            assert_eq!(note_manager.attention_list().n_items(), 0);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn update_links_of_shared_title() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("update-links-of-shared-title").await;
+            let create_note = |title: &str, content: &str| {
+                let note = note_manager.create_note().unwrap();
+                note.metadata().set_title(title);
+                note.buffer().set_text(content);
+                note
+            };
+
+            let renamed = create_note("Rome", "");
+            create_note("rome", "");
+            let linking = create_note("Trip", "[[Rome]]");
+            create_note("Paris", "");
+
+            renamed.metadata().set_title("Roma");
+            assert!(matches!(
+                note_manager.update_links(&renamed, "Rome"),
+                LinkUpdate::Ambiguous(1)
+            ));
+            assert_eq!(text(&linking), "[[Rome]]");
+
+            let attention_list = note_manager.attention_list();
+            assert_eq!(attention_list.n_items(), 1);
+            let entry = attention_list
+                .item(0)
+                .unwrap()
+                .downcast::<AttentionEntry>()
+                .unwrap();
+            assert_eq!(entry.note(), linking);
+            assert_eq!(entry.linked_title(), "Rome");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn attachment_usages_then_delete() {
+        block_on_gtk_thread(|| async {
+            let (note_manager, note) = note_manager_with_note("attachment-usages").await;
+            let notes_path = note_manager.directory().path().unwrap();
+            std::fs::write(notes_path.join("Scan.png"), "Scan").unwrap();
+            std::fs::write(notes_path.join("Orphan.txt"), "Orphan").unwrap();
+
+            let linking = note_manager.create_note().unwrap();
+            linking.buffer().set_text("![Scan](Scan.png)");
+
+            let usages = note_manager.attachment_usages().await.unwrap();
+            let file_names = usages
+                .iter()
+                .map(|usage| usage.file.basename().unwrap().display().to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(file_names, ["Camera.png", "Orphan.txt", "Scan.png"]);
+            assert_eq!(usages[0].notes, [note.clone()]);
+            assert_eq!(usages[0].size, 5);
+            assert!(usages[1].is_orphan());
+            assert_eq!(usages[2].notes, [linking.clone()]);
+
+            // The index is built again after the link is removed
+            linking.buffer().set_text("No more links");
+            let usages = note_manager.attachment_usages().await.unwrap();
+            assert!(usages[2].is_orphan());
+
+            note_manager
+                .delete_attachments(&[usages[0].file.clone()], DeletionMode::Delete)
+                .await
+                .unwrap();
+            assert!(!notes_path.join("Camera.png").exists());
+            assert!(note.metadata().attachment_list().is_empty());
+
+            let usages = note_manager.attachment_usages().await.unwrap();
+            assert_eq!(usages.len(), 2);
+            assert!(usages.iter().all(|usage| usage.is_orphan()));
+
+            note_manager.set_is_read_only(true);
+            let err = note_manager
+                .delete_attachments(&[usages[0].file.clone()], DeletionMode::Delete)
+                .await
+                .unwrap_err();
+            assert!(err.downcast_ref::<ReadOnly>().is_some());
+            assert!(notes_path.join("Orphan.txt").exists());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn save_then_load_data() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("save-data").await;
+            note_manager.tag_list().append(Tag::new("B")).unwrap();
+            note_manager.tag_list().append(Tag::new("A")).unwrap();
+            note_manager
+                .notebook_settings()
+                .set_note_template("# Today");
+            note_manager.save_data_file().await.unwrap();
+
+            // Tags are written sorted, whatever order they were created in
+            let text = std::fs::read_to_string(note_manager.data_file_path()).unwrap();
+            assert!(text.starts_with("---\nversion: 2\ntag_list:\n  - A\n  - B\n"));
+
+            // While the order they are shown in is kept
+            note_manager.load_data_file().await.unwrap();
+            let names = note_manager
+                .tag_list()
+                .iter()
+                .map(|tag| tag.name())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["B", "A"]);
+            assert_eq!(note_manager.notebook_settings().note_template(), "# Today");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn save_then_load_saved_searches() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("save-saved-searches").await;
+            note_manager
+                .saved_search_list()
+                .append(SavedSearch::new("Work", "tag:work is:pinned"))
+                .unwrap();
+            note_manager.save_data_file().await.unwrap();
+
+            note_manager.load_data_file().await.unwrap();
+            let saved_search = note_manager
+                .saved_search_list()
+                .get_with_name("Work")
+                .unwrap();
+            assert_eq!(saved_search.query(), "tag:work is:pinned");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn words_written() {
+        block_on_gtk_thread(|| async {
+            let note_manager = new_note_manager("words-written").await;
+            note_manager.record_words_written(0);
+            assert!(note_manager.daily_word_counts().is_empty());
+
+            note_manager.record_words_written(3);
+            note_manager.record_words_written(4);
+            assert_eq!(note_manager.words_today(), 7);
+
+            note_manager.save_data_file().await.unwrap();
+            note_manager.load_data_file().await.unwrap();
+            assert_eq!(note_manager.words_today(), 7);
+
+            // Another device wrote more today
+            let today = Local::today().naive_local();
+            std::fs::write(
+                note_manager.data_file_path(),
+                format!(
+                    "---\nversion: 1\ntag_list: []\ndaily_word_counts:\n  {}: 20\n",
+                    today.format("%Y-%m-%d")
+                ),
+            )
+            .unwrap();
+            note_manager.reload_data_file().await;
+            assert_eq!(note_manager.words_today(), 20);
+        });
+    }
+}
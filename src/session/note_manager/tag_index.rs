@@ -0,0 +1,122 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::model::{Note, Tag};
+
+/// Which notes have each tag, kept up to date as the tags of the notes change, so they don't
+/// have to be gone through to know it. The notes can be anything that identifies them, which
+/// is only not a [`Note`] in the tests, as it needs GTK.
+#[derive(Debug)]
+pub struct TagIndex<N = Note> {
+    notes: HashMap<Tag, HashSet<N>>,
+    tags: HashMap<N, HashSet<Tag>>,
+}
+
+impl<N> Default for TagIndex<N> {
+    fn default() -> Self {
+        Self {
+            notes: HashMap::new(),
+            tags: HashMap::new(),
+        }
+    }
+}
+
+impl<N: Clone + Eq + Hash> TagIndex<N> {
+    /// Make `tags` the tags of `note`, which is indexed if it is not yet
+    pub fn update(&mut self, note: &N, tags: &HashSet<Tag>) {
+        let old_tags = self.tags.entry(note.clone()).or_default();
+
+        if old_tags == tags {
+            return;
+        }
+
+        for tag in old_tags.difference(tags) {
+            if let Some(notes) = self.notes.get_mut(tag) {
+                notes.remove(note);
+
+                if notes.is_empty() {
+                    self.notes.remove(tag);
+                }
+            }
+        }
+
+        for tag in tags.difference(old_tags) {
+            self.notes
+                .entry(tag.clone())
+                .or_default()
+                .insert(note.clone());
+        }
+
+        *old_tags = tags.clone();
+    }
+
+    /// Only keep the notes for which `f` returns true, like the ones still in the list
+    pub fn retain(&mut self, mut f: impl FnMut(&N) -> bool) {
+        let removed_notes = self
+            .tags
+            .keys()
+            .filter(|note| !f(note))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for note in removed_notes {
+            self.update(&note, &HashSet::new());
+            self.tags.remove(&note);
+        }
+    }
+
+    /// Notes with `tag`, in no particular order
+    pub fn notes_with(&self, tag: &Tag) -> Vec<N> {
+        self.notes
+            .get(tag)
+            .map(|notes| notes.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags(tags: &[&Tag]) -> HashSet<Tag> {
+        tags.iter().map(|tag| (*tag).clone()).collect()
+    }
+
+    #[test]
+    fn update() {
+        let (a, b) = (Tag::new("A"), Tag::new("B"));
+
+        let mut tag_index = TagIndex::default();
+        tag_index.update(&"Note 1", &tags(&[&a, &b]));
+        tag_index.update(&"Note 2", &tags(&[&a]));
+        assert_eq!(tag_index.notes_with(&a).len(), 2);
+        assert_eq!(tag_index.notes_with(&b), ["Note 1"]);
+
+        tag_index.update(&"Note 1", &tags(&[&a]));
+        assert_eq!(tag_index.notes_with(&a).len(), 2);
+        assert!(tag_index.notes_with(&b).is_empty());
+
+        tag_index.update(&"Note 2", &tags(&[]));
+        assert_eq!(tag_index.notes_with(&a), ["Note 1"]);
+        assert!(tag_index.notes_with(&Tag::new("C")).is_empty());
+    }
+
+    #[test]
+    fn retain() {
+        let tag = Tag::new("A");
+
+        let mut tag_index = TagIndex::default();
+        tag_index.update(&"Note 1", &tags(&[&tag]));
+        tag_index.update(&"Note 2", &tags(&[&tag]));
+
+        tag_index.retain(|note| *note != "Note 2");
+        assert_eq!(tag_index.notes_with(&tag), ["Note 1"]);
+        assert!(!tag_index.tags.contains_key("Note 2"));
+
+        tag_index.retain(|_| false);
+        assert!(tag_index.notes.is_empty());
+        assert!(tag_index.tags.is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use gtk::prelude::*;
+
+use crate::{core::retarget_wiki_links, model::Note};
+
+/// What was done to the links to a note after it was renamed
+#[derive(Debug)]
+pub enum LinkUpdate {
+    /// No note links to its old title
+    Unlinked,
+    Updated(UpdatedLinks),
+    /// Its old title is shared with other notes, so the links to it were left as they are,
+    /// and this many notes with them need attention
+    Ambiguous(usize),
+}
+
+/// The links made to follow a renamed note, so they can be undone with the rename
+#[derive(Debug)]
+pub struct UpdatedLinks {
+    note: Note,
+    old_title: String,
+    /// Each note whose links were changed, with its content before and after
+    linking_notes: Vec<(Note, String, String)>,
+    n_links: usize,
+}
+
+impl UpdatedLinks {
+    /// Make the links to `old_title` in `linking_notes` go to the title of `note` instead,
+    /// as edits that can be undone. Returns `None` when none of them has such links.
+    pub fn update(note: &Note, old_title: &str, linking_notes: &[Note]) -> Option<Self> {
+        let new_title = note.metadata().title();
+        let mut updated = Vec::new();
+        let mut n_links = 0;
+
+        for linking_note in linking_notes {
+            let buffer = linking_note.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+            let text = buffer.text(&start_iter, &end_iter, true).to_string();
+
+            if let Some((new_text, n_retargeted)) =
+                retarget_wiki_links(&text, old_title, new_title.trim())
+            {
+                // A single user action, so the editor also undoes it in one step
+                buffer.begin_user_action();
+                buffer.set_text(&new_text);
+                buffer.end_user_action();
+
+                n_links += n_retargeted;
+                updated.push((linking_note.clone(), text, new_text));
+            }
+        }
+
+        if updated.is_empty() {
+            return None;
+        }
+
+        log::info!(
+            "Updated {} links to `{}` in {} notes",
+            n_links,
+            note,
+            updated.len()
+        );
+
+        Some(Self {
+            note: note.clone(),
+            old_title: old_title.to_string(),
+            linking_notes: updated,
+            n_links,
+        })
+    }
+
+    pub fn n_links(&self) -> usize {
+        self.n_links
+    }
+
+    /// Rename the note back and restore the links. The notes edited since are kept as they
+    /// are.
+    pub fn undo(self) {
+        self.note.metadata().set_title(&self.old_title);
+
+        for (linking_note, old_text, new_text) in &self.linking_notes {
+            let buffer = linking_note.buffer();
+            let (start_iter, end_iter) = buffer.bounds();
+
+            if buffer.text(&start_iter, &end_iter, true).as_str() == new_text {
+                buffer.begin_user_action();
+                buffer.set_text(old_text);
+                buffer.end_user_action();
+            } else {
+                log::warn!("Links in `{}` were edited, keeping them", linking_note);
+            }
+        }
+
+        log::info!("Restored the links to `{}`", self.note);
+    }
+}
@@ -12,7 +12,14 @@ use gtk::{
 use once_cell::unsync::OnceCell;
 
 use self::{note_tag_lists::NoteTagLists, row::Row};
-use crate::model::{NoteTagList, Tag, TagList};
+use crate::{
+    core::{scanned_part, suggest_tags},
+    model::{Note, NoteTagList, Tag, TagList},
+    spawn, spawn_blocking,
+};
+
+/// Most tags that are suggested at a time, so they don't push the list of tags away
+const MAX_SUGGESTIONS: usize = 8;
 
 mod imp {
     use super::*;
@@ -30,6 +37,10 @@ mod imp {
         pub create_tag_button_revealer: TemplateChild<gtk::Revealer>,
         #[template_child]
         pub create_tag_button_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub suggestions_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub suggestions_box: TemplateChild<gtk::FlowBox>,
 
         pub tag_list: OnceCell<TagList>,
         pub other_tag_lists: OnceCell<NoteTagLists>,
@@ -172,6 +183,73 @@ impl NoteTagDialog {
         imp.tag_list.set(tag_list).unwrap();
     }
 
+    /// Suggest the tags that `note` doesn't have yet whose names appear in its content. The
+    /// content is scanned in another thread, and the suggestions are shown when it is done.
+    pub fn suggest_tags_for(&self, note: &Note) {
+        let note_tag_list = note.metadata().tag_list();
+        let tag_names = self
+            .tag_list()
+            .iter()
+            .filter(|tag| !note_tag_list.contains(tag))
+            .map(|tag| tag.name())
+            .collect::<Vec<_>>();
+
+        if tag_names.is_empty() {
+            return;
+        }
+
+        let body = scanned_part(&note.lowercase_content()).to_string();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let suggestions = spawn_blocking!(move || suggest_tags(&body, &tag_names)).await;
+            obj.show_suggestions(&suggestions);
+        }));
+    }
+
+    fn show_suggestions(&self, tag_names: &[String]) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.suggestions_box.first_child() {
+            imp.suggestions_box.remove(&child);
+        }
+
+        let tags = tag_names
+            .iter()
+            .filter_map(|name| self.tag_list().get_with_name(name))
+            .take(MAX_SUGGESTIONS)
+            .collect::<Vec<_>>();
+
+        for tag in &tags {
+            let button = gtk::Button::builder()
+                .label(&tag.name())
+                .tooltip_text(&gettext!("Add “{}”", tag.name()))
+                .build();
+            button.add_css_class("note-tag-dialog-suggestion");
+
+            button.connect_clicked(clone!(@weak self as obj, @weak tag => move |button| {
+                obj.accept_suggestion(&tag, button);
+            }));
+
+            imp.suggestions_box.insert(&button, -1);
+        }
+
+        imp.suggestions_revealer.set_reveal_child(!tags.is_empty());
+    }
+
+    fn accept_suggestion(&self, tag: &Tag, button: &gtk::Button) {
+        let imp = self.imp();
+
+        self.other_tag_lists().append_on_all(tag);
+
+        if let Some(child) = button.parent() {
+            imp.suggestions_box.remove(&child);
+        }
+
+        if imp.suggestions_box.first_child().is_none() {
+            imp.suggestions_revealer.set_reveal_child(false);
+        }
+    }
+
     fn on_create_tag(&self) {
         let imp = self.imp();
         let tag_name = imp.search_entry.text();
@@ -6,7 +6,7 @@ use gtk::{
 
 use std::cell::RefCell;
 
-use super::{NoteTagLists, Tag};
+use super::{NoteTagList, NoteTagLists, Tag};
 
 mod imp {
     use super::*;
@@ -23,6 +23,7 @@ mod imp {
 
         pub other_tag_lists: RefCell<NoteTagLists>,
         pub tag: RefCell<Option<Tag>>,
+        pub tag_list_handler_ids: RefCell<Vec<(NoteTagList, glib::SignalHandlerId)>>,
     }
 
     #[glib::object_subclass]
@@ -98,6 +99,10 @@ mod imp {
         }
 
         fn dispose(&self, obj: &Self::Type) {
+            for (tag_list, handler_id) in self.tag_list_handler_ids.take() {
+                tag_list.disconnect(handler_id);
+            }
+
             while let Some(child) = obj.first_child() {
                 child.unparent();
             }
@@ -152,6 +157,7 @@ impl Row {
             .all(|other| is_first_contains_tag == other.contains(tag));
 
         if is_all_equal {
+            imp.check_button.set_inconsistent(false);
             imp.check_button.set_active(is_first_contains_tag);
         } else {
             imp.check_button.set_inconsistent(true);
@@ -159,15 +165,38 @@ impl Row {
     }
 
     fn setup_signals(&self) {
-        // FIXME This get activated on first launch which makes it try to append an
-        // existing tag
-        self.imp().check_button.connect_active_notify(
-            clone!(@weak self as obj => move |check_button| {
+        let imp = self.imp();
+
+        // The lists can also be changed elsewhere, like when a suggested tag is added
+        for tag_list in self.other_tag_lists().iter() {
+            let handler_id =
+                tag_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+                    if let Some(tag) = obj.tag() {
+                        obj.update_check_button_state(&tag);
+                    }
+                }));
+            imp.tag_list_handler_ids
+                .borrow_mut()
+                .push((tag_list.clone(), handler_id));
+        }
+
+        imp.check_button
+            .connect_active_notify(clone!(@weak self as obj => move |check_button| {
                 let tag = match obj.tag() {
                     Some(tag) => tag,
                     None => return,
                 };
 
+                // Already in sync, like when the state is updated from the lists
+                let is_active = check_button.is_active();
+                if obj
+                    .other_tag_lists()
+                    .iter()
+                    .all(|tag_list| tag_list.contains(&tag) == is_active)
+                {
+                    return;
+                }
+
                 obj.imp().check_button.set_inconsistent(false);
 
                 if check_button.is_active() {
@@ -175,8 +204,7 @@ impl Row {
                 } else {
                     obj.other_tag_lists().remove_on_all(&tag);
                 }
-            }),
-        );
+            }));
 
         // TODO Implement this so clicking the row activates the checkbutton
         // Works well when clicking the row but when you click the button it gets activated
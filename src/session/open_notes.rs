@@ -0,0 +1,161 @@
+use crate::model::Note;
+
+/// Which viewers show which notes, like the content of each window, so what is done to a note
+/// reaches every view of it. The viewers of a note share its buffer, so its text is always the
+/// same in all of them.
+#[derive(Debug)]
+pub struct OpenNotes<V> {
+    /// In the order the notes were opened, so the first viewer of a note comes first
+    entries: Vec<(V, Note)>,
+}
+
+impl<V> Default for OpenNotes<V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<V: PartialEq + Clone> OpenNotes<V> {
+    /// Record that `viewer` shows `note`, or nothing when it is `None`, instead of what it
+    /// showed before
+    pub fn set_note(&mut self, viewer: &V, note: Option<Note>) {
+        if self.note_of(viewer) == note.as_ref() {
+            return;
+        }
+
+        self.entries.retain(|(other, _)| other != viewer);
+
+        if let Some(note) = note {
+            self.entries.push((viewer.clone(), note));
+        }
+    }
+
+    pub fn note_of(&self, viewer: &V) -> Option<&Note> {
+        self.entries
+            .iter()
+            .find(|(other, _)| other == viewer)
+            .map(|(_, note)| note)
+    }
+
+    /// The viewers showing `note`, starting with the one that opened it first
+    pub fn viewers_of(&self, note: &Note) -> Vec<V> {
+        self.entries
+            .iter()
+            .filter(|(_, other)| other == note)
+            .map(|(viewer, _)| viewer.clone())
+            .collect()
+    }
+
+    /// The viewers showing a note
+    pub fn viewers(&self) -> Vec<V> {
+        self.entries
+            .iter()
+            .map(|(viewer, _)| viewer.clone())
+            .collect()
+    }
+
+    /// Whether `viewer` is the one that edits the title of the note it shows, which is the
+    /// first that opened it, so the title bindings of the others don't feed each other. The
+    /// next viewer takes it once it closes the note.
+    pub fn owns_title(&self, viewer: &V) -> bool {
+        match self.note_of(viewer) {
+            Some(note) => self.viewers_of(note).first() == Some(viewer),
+            None => true,
+        }
+    }
+
+    /// Forget the notes where `f` returns false, like the ones that no longer exist, returning
+    /// the viewers that showed them with their note
+    pub fn retain_notes(&mut self, mut f: impl FnMut(&Note) -> bool) -> Vec<(V, Note)> {
+        let (kept, removed) = self.entries.drain(..).partition(|(_, note)| f(note));
+        self.entries = kept;
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::NoteList, test_harness::run_on_gtk_thread};
+
+    const MAIN: &str = "main";
+    const SECONDARY: &str = "secondary";
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn set_note() {
+        run_on_gtk_thread(|| {
+            let note_1 = Note::new("/home/user");
+            let note_2 = Note::new("/home/user");
+            let mut open_notes = OpenNotes::default();
+            assert_eq!(open_notes.note_of(&MAIN), None);
+
+            open_notes.set_note(&MAIN, Some(note_1.clone()));
+            open_notes.set_note(&SECONDARY, Some(note_1.clone()));
+            assert_eq!(open_notes.note_of(&MAIN), Some(&note_1));
+            assert_eq!(open_notes.viewers_of(&note_1), [MAIN, SECONDARY]);
+            assert!(open_notes.viewers_of(&note_2).is_empty());
+
+            // Switching to another note leaves the first one
+            open_notes.set_note(&MAIN, Some(note_2.clone()));
+            assert_eq!(open_notes.viewers_of(&note_1), [SECONDARY]);
+            assert_eq!(open_notes.viewers_of(&note_2), [MAIN]);
+
+            open_notes.set_note(&SECONDARY, None);
+            assert_eq!(open_notes.note_of(&SECONDARY), None);
+            assert!(open_notes.viewers_of(&note_1).is_empty());
+            assert_eq!(open_notes.viewers(), [MAIN]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn title_owner() {
+        run_on_gtk_thread(|| {
+            let note = Note::new("/home/user");
+            let mut open_notes = OpenNotes::default();
+            assert!(open_notes.owns_title(&MAIN));
+
+            open_notes.set_note(&SECONDARY, Some(note.clone()));
+            open_notes.set_note(&MAIN, Some(note.clone()));
+            assert!(open_notes.owns_title(&SECONDARY));
+            assert!(!open_notes.owns_title(&MAIN));
+
+            // Showing it again doesn't take the title from the first viewer
+            open_notes.set_note(&MAIN, Some(note.clone()));
+            assert!(!open_notes.owns_title(&MAIN));
+
+            open_notes.set_note(&SECONDARY, None);
+            assert!(open_notes.owns_title(&SECONDARY));
+            assert!(open_notes.owns_title(&MAIN));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn deleted_while_open_in_two_viewers() {
+        run_on_gtk_thread(|| {
+            let deleted = Note::new("/home/user");
+            let other = Note::new("/home/user");
+            let note_list = NoteList::new();
+            note_list.append(deleted.clone());
+            note_list.append(other.clone());
+
+            let mut open_notes = OpenNotes::default();
+            open_notes.set_note(&MAIN, Some(deleted.clone()));
+            open_notes.set_note(&SECONDARY, Some(deleted.clone()));
+            open_notes.set_note(&"third", Some(other.clone()));
+
+            note_list.remove(&deleted.id());
+            let closed = open_notes.retain_notes(|note| note_list.find_by_id(&note.id()).is_some());
+            assert_eq!(
+                closed,
+                [(MAIN, deleted.clone()), (SECONDARY, deleted.clone())]
+            );
+            assert!(open_notes.viewers_of(&deleted).is_empty());
+            assert_eq!(open_notes.viewers(), ["third"]);
+        });
+    }
+}
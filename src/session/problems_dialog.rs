@@ -0,0 +1,250 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::path::Path;
+
+use super::Session;
+use crate::{
+    model::{ProblemEntry, ProblemList},
+    spawn,
+};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/problems-dialog.ui")]
+    pub struct ProblemsDialog {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub list_page: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+
+        pub session: OnceCell<Session>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProblemsDialog {
+        const NAME: &'static str = "NwtyProblemsDialog";
+        type Type = super::ProblemsDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ProblemsDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session where the files failed to load",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_list_box();
+            obj.update_stack();
+        }
+    }
+
+    impl WidgetImpl for ProblemsDialog {}
+    impl WindowImpl for ProblemsDialog {}
+    impl AdwWindowImpl for ProblemsDialog {}
+}
+
+glib::wrapper! {
+    /// Lists the files that failed to load as notes, letting the user open, retry, or exclude
+    /// each of them
+    pub struct ProblemsDialog(ObjectSubclass<imp::ProblemsDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl ProblemsDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create ProblemsDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    fn problem_list(&self) -> ProblemList {
+        self.session().note_manager().problem_list()
+    }
+
+    fn update_stack(&self) {
+        let imp = self.imp();
+
+        if self.problem_list().n_problems() > 0 {
+            imp.stack.set_visible_child(&imp.list_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        }
+    }
+
+    fn setup_list_box(&self) {
+        let problem_list = self.problem_list();
+
+        self.imp().list_box.bind_model(
+            Some(&problem_list),
+            clone!(@weak self as obj => @default-panic, move |item| {
+                let entry = item.downcast_ref::<ProblemEntry>().unwrap();
+                obj.build_row(entry).upcast()
+            }),
+        );
+
+        problem_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            obj.update_stack();
+        }));
+    }
+
+    fn build_row(&self, entry: &ProblemEntry) -> adw::ActionRow {
+        let path = entry.path();
+
+        let row = adw::ActionRow::builder()
+            .title(&self.display_path(&path))
+            // Translators: The first is why the file failed to load, the second is when
+            .subtitle(&gettext!(
+                "{} · {}",
+                entry.kind().display_name(),
+                entry.timestamp().format("%b %d, %H∶%M") // Sep 03, 08∶10
+            ))
+            .tooltip_text(&entry.message())
+            .build();
+
+        let open_button = row_button("external-link-symbolic", &gettext("Open in Another App"));
+        open_button.connect_clicked(clone!(@weak self as obj, @strong path => move |_| {
+            obj.open(&path);
+        }));
+        row.add_suffix(&open_button);
+
+        let retry_button = row_button("view-refresh-symbolic", &gettext("Try Again"));
+        retry_button.connect_clicked(clone!(@weak self as obj, @strong path => move |_| {
+            obj.retry(&path);
+        }));
+        row.add_suffix(&retry_button);
+
+        if !self.session().note_manager().is_read_only() {
+            let exclude_button = row_button("action-unavailable-symbolic", &gettext("Exclude"));
+            exclude_button.connect_clicked(clone!(@weak self as obj, @strong path => move |_| {
+                obj.exclude(&path);
+            }));
+            row.add_suffix(&exclude_button);
+        }
+
+        row
+    }
+
+    /// `path` relative to the notes, as they are where the user put the file
+    fn display_path(&self, path: &Path) -> String {
+        let notes_path = self.session().note_manager().directory().path().unwrap();
+        path.strip_prefix(&notes_path)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    fn open(&self, path: &Path) {
+        let uri = gio::File::for_path(path).uri();
+
+        spawn!(clone!(@weak self as obj => async move {
+            // Goes through the OpenURI portal when sandboxed
+            let res =
+                gio::AppInfo::launch_default_for_uri_future(&uri, None::<&gio::AppLaunchContext>)
+                    .await;
+
+            if let Err(err) = res {
+                log::error!("Failed to open file at uri `{}`: {:?}", uri, err);
+                obj.show_toast(&gettext("Failed to open the file"));
+            }
+        }));
+    }
+
+    fn retry(&self, path: &Path) {
+        let file = gio::File::for_path(path);
+
+        spawn!(clone!(@weak self as obj => async move {
+            // Its row shows the new error otherwise
+            if obj.session().note_manager().retry_load(&file).await.is_ok() {
+                obj.show_toast(&gettext("Loaded the note"));
+            } else {
+                obj.show_toast(&gettext("The file still can't be loaded"));
+            }
+        }));
+    }
+
+    fn exclude(&self, path: &Path) {
+        let path = path.to_path_buf();
+
+        spawn!(clone!(@weak self as obj => async move {
+            if let Err(err) = obj.session().note_manager().exclude_file(&path).await {
+                log::error!("Failed to exclude `{}`: {:?}", path.display(), err);
+                obj.show_toast(&gettext("Failed to exclude the file"));
+            }
+        }));
+    }
+
+    fn show_toast(&self, title: &str) {
+        self.imp().toast_overlay.add_toast(&adw::Toast::new(title));
+    }
+}
+
+fn row_button(icon_name: &str, tooltip_text: &str) -> gtk::Button {
+    gtk::Button::builder()
+        .icon_name(icon_name)
+        .tooltip_text(tooltip_text)
+        .valign(gtk::Align::Center)
+        .css_classes(vec!["flat".into()])
+        .build()
+}
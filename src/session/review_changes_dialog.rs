@@ -0,0 +1,438 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+    subclass::prelude::*,
+};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+};
+
+use super::Session;
+use crate::{
+    core::{ChangedFile, DiffHunk, FileChange, LineChange},
+    model::{Note, NoteId},
+    spawn,
+};
+
+const DIFF_ADDED_TAG: &str = "diff-added";
+const DIFF_REMOVED_TAG: &str = "diff-removed";
+const DIFF_HUNK_TAG: &str = "diff-hunk";
+
+/// A changed file as listed in the dialog
+#[derive(Debug)]
+struct ReviewedFile {
+    path: PathBuf,
+    change: FileChange,
+    /// The note the file is of, when it is still in the notebook
+    note: Option<Note>,
+    /// Whether the file is included in the sync
+    check_button: gtk::CheckButton,
+}
+
+impl ReviewedFile {
+    fn title(&self) -> String {
+        match self.note {
+            Some(ref note) => note.metadata().title(),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/review-changes-dialog.ui")]
+    pub struct ReviewChangesDialog {
+        #[template_child]
+        pub sync_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub loading_page: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub changes_page: TemplateChild<gtk::Paned>,
+        #[template_child]
+        pub file_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub diff_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+
+        pub session: OnceCell<Session>,
+        /// In the order of the rows of the file list
+        pub files: RefCell<Vec<ReviewedFile>>,
+        /// Increased each time a diff is requested, so an older one that finishes later is
+        /// not shown
+        pub diff_request: Cell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReviewChangesDialog {
+        const NAME: &'static str = "NwtyReviewChangesDialog";
+        type Type = super::ReviewChangesDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ReviewChangesDialog {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecObject::new(
+                    "session",
+                    "Session",
+                    "Session whose changes are reviewed",
+                    Session::static_type(),
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            _obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "session" => {
+                    let session = value.get().unwrap();
+                    self.session.set(session).unwrap();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "session" => obj.session().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_file_list();
+            obj.setup_diff_view();
+
+            self.sync_button
+                .connect_clicked(clone!(@weak obj => move |_| {
+                    obj.sync();
+                }));
+
+            spawn!(clone!(@weak obj => async move {
+                obj.load_changed_files().await;
+            }));
+        }
+    }
+
+    impl WidgetImpl for ReviewChangesDialog {}
+    impl WindowImpl for ReviewChangesDialog {}
+    impl AdwWindowImpl for ReviewChangesDialog {}
+}
+
+glib::wrapper! {
+    /// Lists the changes that are not synced yet with their diffs, letting the user leave
+    /// some of them out of the sync
+    pub struct ReviewChangesDialog(ObjectSubclass<imp::ReviewChangesDialog>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gio::ActionMap, gio::ActionGroup;
+}
+
+impl ReviewChangesDialog {
+    pub fn new(session: &Session) -> Self {
+        glib::Object::new(&[("session", session)]).expect("Failed to create ReviewChangesDialog.")
+    }
+
+    fn session(&self) -> Session {
+        self.imp().session.get().unwrap().clone()
+    }
+
+    /// Save the notes first, so the files on the disk have the latest changes, then list the
+    /// files that are not committed yet
+    async fn load_changed_files(&self) {
+        let note_manager = self.session().note_manager();
+
+        if let Err(err) = note_manager.save_all_notes().await {
+            log::error!("Failed to save notes before reviewing changes: {:?}", err);
+        }
+
+        let changed_files = match note_manager.repository().changed_files().await {
+            Ok(changed_files) => changed_files,
+            Err(err) => {
+                log::error!("Failed to get changed files: {:?}", err);
+                Vec::new()
+            }
+        };
+
+        self.show_changed_files(changed_files);
+    }
+
+    /// The note of the file at `path`, relative to the notes directory
+    fn note_for_path(&self, path: &Path) -> Option<Note> {
+        let note_manager = self.session().note_manager();
+        let full_path = note_manager.directory().path()?.join(path);
+
+        note_manager
+            .note_list()
+            .find_by_id(&NoteId::for_path(path))
+            .filter(|note| note.file().path().as_deref() == Some(full_path.as_path()))
+    }
+
+    /// Show the files of the notes first, by their titles, then the other files by their paths
+    fn show_changed_files(&self, changed_files: Vec<ChangedFile>) {
+        let imp = self.imp();
+
+        let mut files = changed_files
+            .into_iter()
+            .map(|changed_file| ReviewedFile {
+                note: self.note_for_path(&changed_file.path),
+                path: changed_file.path,
+                change: changed_file.change,
+                check_button: gtk::CheckButton::builder()
+                    .active(true)
+                    .valign(gtk::Align::Center)
+                    .tooltip_text(&gettext("Include in This Sync"))
+                    .build(),
+            })
+            .collect::<Vec<_>>();
+        files.sort_by_cached_key(|file| (file.note.is_none(), file.title().to_lowercase()));
+
+        let is_empty = files.is_empty();
+        // Set before the rows are added, as their headers are from it
+        imp.files.replace(files);
+
+        for file in imp.files.borrow().iter() {
+            imp.file_list.append(&file_row(file));
+        }
+
+        if is_empty {
+            imp.stack.set_visible_child(&imp.empty_page.get());
+        } else {
+            imp.stack.set_visible_child(&imp.changes_page.get());
+            imp.file_list
+                .select_row(imp.file_list.row_at_index(0).as_ref());
+        }
+
+        // There may still be commits to pull or push
+        imp.sync_button.set_sensitive(true);
+    }
+
+    fn setup_file_list(&self) {
+        let imp = self.imp();
+
+        imp.file_list
+            .set_header_func(clone!(@weak self as obj => move |row, row_before| {
+                let files = obj.imp().files.borrow();
+                let is_note = |row: &gtk::ListBoxRow| {
+                    files
+                        .get(row.index() as usize)
+                        .map_or(false, |file| file.note.is_some())
+                };
+
+                if row_before.map(is_note) == Some(is_note(row)) {
+                    row.set_header(gtk::Widget::NONE);
+                    return;
+                }
+
+                let title = if is_note(row) {
+                    gettext("Notes")
+                } else {
+                    gettext("Other Files")
+                };
+                let header = gtk::Label::builder()
+                    .label(&title)
+                    .xalign(0.0)
+                    .margin_top(12)
+                    .margin_start(12)
+                    .margin_bottom(6)
+                    .css_classes(vec!["heading".into()])
+                    .build();
+                row.set_header(Some(&header));
+            }));
+
+        imp.file_list
+            .connect_row_selected(clone!(@weak self as obj => move |_, row| {
+                if let Some(row) = row {
+                    obj.show_diff_of(row.index() as usize);
+                }
+            }));
+    }
+
+    fn setup_diff_view(&self) {
+        let buffer = self.imp().diff_view.buffer();
+        buffer.create_tag(
+            Some(DIFF_ADDED_TAG),
+            &[(
+                "paragraph-background-rgba",
+                &gdk::RGBA::new(0.18, 0.76, 0.49, 0.25),
+            )],
+        );
+        buffer.create_tag(
+            Some(DIFF_REMOVED_TAG),
+            &[(
+                "paragraph-background-rgba",
+                &gdk::RGBA::new(0.88, 0.11, 0.14, 0.25),
+            )],
+        );
+        buffer.create_tag(
+            Some(DIFF_HUNK_TAG),
+            &[("weight", &700), ("pixels-above-lines", &12)],
+        );
+    }
+
+    fn next_diff_request(&self) -> u64 {
+        let imp = self.imp();
+        let request = imp.diff_request.get() + 1;
+        imp.diff_request.set(request);
+        request
+    }
+
+    fn show_diff_of(&self, index: usize) {
+        let path = match self.imp().files.borrow().get(index) {
+            Some(file) => file.path.clone(),
+            None => return,
+        };
+        let request = self.next_diff_request();
+
+        spawn!(clone!(@weak self as obj => async move {
+            let repository = obj.session().note_manager().repository();
+            let res = repository.diff_workdir_to_head(&path).await;
+
+            if obj.imp().diff_request.get() != request {
+                return;
+            }
+
+            match res {
+                Ok(hunks) => obj.show_diff(&hunks),
+                Err(err) => {
+                    log::error!("Failed to diff `{}`: {:?}", path.display(), err);
+                    obj.imp()
+                        .diff_view
+                        .buffer()
+                        .set_text(&gettext("Failed to load the changes of this file"));
+                }
+            }
+        }));
+    }
+
+    /// Fill the diff view with the lines of `hunks`, each after a line telling where it is
+    fn show_diff(&self, hunks: &[DiffHunk]) {
+        let buffer = self.imp().diff_view.buffer();
+
+        if hunks.is_empty() {
+            buffer.set_text(&gettext("There are no changed lines to show"));
+            return;
+        }
+
+        let mut lines = Vec::new();
+        for hunk in hunks {
+            lines.push((
+                Some(DIFF_HUNK_TAG),
+                gettext!("Line {}", hunk.new_start.max(1)),
+            ));
+
+            for line in &hunk.lines {
+                let tag_name = match line.change {
+                    LineChange::Unchanged => None,
+                    LineChange::Added => Some(DIFF_ADDED_TAG),
+                    LineChange::Removed => Some(DIFF_REMOVED_TAG),
+                };
+                lines.push((tag_name, line.text.clone()));
+            }
+        }
+
+        let text = lines
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        buffer.set_text(&text);
+
+        for (index, (tag_name, _)) in lines.iter().enumerate() {
+            let tag_name = match tag_name {
+                Some(tag_name) => tag_name,
+                None => continue,
+            };
+
+            let start_iter = buffer.iter_at_line(index as i32);
+            let end_iter = buffer
+                .iter_at_line(index as i32 + 1)
+                .unwrap_or_else(|| buffer.end_iter());
+
+            if let Some(start_iter) = start_iter {
+                buffer.apply_tag_by_name(tag_name, &start_iter, &end_iter);
+            }
+        }
+    }
+
+    /// Sync the changes, except the ones of the files that were unchecked, closing the dialog
+    fn sync(&self) {
+        let excluded_paths = self
+            .imp()
+            .files
+            .borrow()
+            .iter()
+            .filter(|file| !file.check_button.is_active())
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+
+        let session = self.session();
+        spawn!(async move {
+            if let Err(err) = session.sync_excluding(&excluded_paths).await {
+                log::error!("Failed to sync: {:?}", err);
+            }
+        });
+
+        self.close();
+    }
+}
+
+/// Row with the title of `file` and how it changed, with its check button to leave it out of
+/// the sync
+fn file_row(file: &ReviewedFile) -> gtk::ListBoxRow {
+    let title_label = gtk::Label::builder()
+        .label(&file.title())
+        .xalign(0.0)
+        .ellipsize(gtk::pango::EllipsizeMode::End)
+        .build();
+
+    let change = match file.change {
+        FileChange::New => gettext("New"),
+        FileChange::Modified => gettext("Modified"),
+        FileChange::Deleted => gettext("Deleted"),
+    };
+    let change_label = gtk::Label::builder()
+        .label(&change)
+        .xalign(0.0)
+        .css_classes(vec!["caption".into(), "dim-label".into()])
+        .build();
+
+    let labels_box = gtk::Box::new(gtk::Orientation::Vertical, 3);
+    labels_box.set_hexpand(true);
+    labels_box.append(&title_label);
+    labels_box.append(&change_label);
+
+    let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    row_box.append(&file.check_button);
+    row_box.append(&labels_box);
+
+    gtk::ListBoxRow::builder().child(&row_box).build()
+}
@@ -0,0 +1,42 @@
+use adw::subclass::prelude::*;
+use gtk::{glib, subclass::prelude::*};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/sidebar-loading-note-row.ui")]
+    pub struct LoadingNoteRow {}
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LoadingNoteRow {
+        const NAME: &'static str = "NwtySidebarLoadingNoteRow";
+        type Type = super::LoadingNoteRow;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for LoadingNoteRow {}
+    impl WidgetImpl for LoadingNoteRow {}
+    impl BinImpl for LoadingNoteRow {}
+}
+
+glib::wrapper! {
+    /// Placeholder in the shape of a note row, shown while the notes are loaded
+    pub struct LoadingNoteRow(ObjectSubclass<imp::LoadingNoteRow>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl LoadingNoteRow {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create LoadingNoteRow.")
+    }
+}
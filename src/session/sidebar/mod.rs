@@ -1,24 +1,71 @@
+mod loading_note_row;
+mod note_filter;
 mod note_row;
+mod review_period_button;
+mod saved_search_dialog;
 mod selection;
 mod sync_button;
+mod type_ahead;
 mod view_switcher;
 
-use gettextrs::gettext;
+use chrono::Local;
+use gettextrs::{gettext, ngettext};
 use gtk::{
+    gdk, gio,
     glib::{self, clone, closure},
     prelude::*,
     subclass::prelude::*,
 };
 
-use std::cell::{Cell, RefCell};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    time::Instant,
+};
+
+pub use self::note_filter::{NoteQuery, NoteScope};
 
 use self::{
-    note_row::NoteRow,
+    loading_note_row::LoadingNoteRow,
+    note_filter::{ContentMatches, NoteFilter},
+    note_row::{NoteRow, RowDetail},
+    review_period_button::ReviewPeriodButton,
     selection::{Selection, SelectionMode},
     sync_button::SyncButton,
+    type_ahead::TypeAhead,
     view_switcher::{ItemKind, ViewSwitcher},
 };
-use crate::model::{Note, NoteList, TagList};
+use crate::{
+    core::{self, NoteSortKey, SearchQuery},
+    model::{
+        FreezableListModel, GroupHeader, GroupedListModel, Note, NoteList, PagedListModel,
+        ProblemList, SavedSearchList, Tag, TagList,
+    },
+    session::{tour, NoteManager, Session},
+    Application,
+};
+
+/// Number of trashed notes shown at first, and added each time the end of the list is reached
+const TRASH_PAGE_SIZE: u32 = 100;
+/// Number of placeholder rows shown while the notes are loaded
+const N_PLACEHOLDER_ROWS: u32 = 12;
+
+/// Whether the notes replace the placeholders shown while loading, which is once the first
+/// ones are loaded, or once loading is done when there are none
+fn is_notes_shown(is_loading: bool, n_notes: u32) -> bool {
+    !is_loading || n_notes > 0
+}
+
+/// Order of the notes, with the pinned ones first, then the most recently edited
+fn default_order(key_1: NoteSortKey, key_2: NoteSortKey) -> Ordering {
+    key_1.cmp(&key_2)
+}
+
+/// Order of the notes in the review, from the most recently edited, ignoring whether they are
+/// pinned, so the notes of each day are next to each other
+fn review_order(key_1: NoteSortKey, key_2: NoteSortKey) -> Ordering {
+    key_2.last_modified.cmp(&key_1.last_modified)
+}
 
 mod imp {
     use super::*;
@@ -28,15 +75,41 @@ mod imp {
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/sidebar.ui")]
     pub struct Sidebar {
+        #[template_child]
+        pub recent_tags_bar: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub recent_tags_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub review_bar: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub review_period_button: TemplateChild<ReviewPeriodButton>,
+        #[template_child]
+        pub trash_header: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub trash_header_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
         pub list_view: TemplateChild<gtk::ListView>,
         #[template_child]
+        pub trash_footer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub trash_footer_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub search_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
         pub view_switcher: TemplateChild<ViewSwitcher>,
         #[template_child]
+        pub create_note_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub header_bar_stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub main_header_bar: TemplateChild<adw::HeaderBar>,
         #[template_child]
+        pub error_log_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub selection_header_bar: TemplateChild<adw::HeaderBar>,
         #[template_child]
         pub selection_menu_button: TemplateChild<gtk::MenuButton>,
@@ -48,11 +121,34 @@ mod imp {
         pub trash_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub tag_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
 
         pub compact: Cell<bool>,
         pub selection_mode: Cell<SelectionMode>,
         pub selected_note: RefCell<Option<Note>>,
         pub is_syncing: Cell<bool>,
+        pub is_auth_required: Cell<bool>,
+        pub n_trashed_notes: Cell<u32>,
+        pub trash_size: Cell<u64>,
+        pub pending_changes: Cell<u32>,
+        pub ahead: Cell<u32>,
+        pub behind: Cell<u32>,
+        pub is_editing_note: Cell<bool>,
+        pub is_loading: Cell<bool>,
+        pub load_progress: Cell<f64>,
+
+        pub selection_model: RefCell<Option<Selection>>,
+        pub note_filter: RefCell<Option<NoteFilter>>,
+        /// Whose search index the content of the notes is matched on
+        pub note_manager: glib::WeakRef<NoteManager>,
+        pub sorter_model: RefCell<Option<gtk::SortListModel>>,
+        pub freezable_model: RefCell<Option<FreezableListModel>>,
+        pub paged_model: RefCell<Option<PagedListModel>>,
+        pub grouped_model: RefCell<Option<GroupedListModel>>,
+        pub is_review: Cell<bool>,
+        pub recent_tag_chips: RefCell<Vec<(Tag, gtk::ToggleButton)>>,
+        pub type_ahead: RefCell<TypeAhead>,
     }
 
     #[glib::object_subclass]
@@ -62,6 +158,7 @@ mod imp {
         type ParentType = gtk::Widget;
 
         fn class_init(klass: &mut Self::Class) {
+            ReviewPeriodButton::static_type();
             SyncButton::static_type();
             Self::bind_template(klass);
 
@@ -82,6 +179,11 @@ mod imp {
                 let model = obj.selection_model();
                 model.unselect_all();
             });
+
+            klass.install_action("sidebar.save-search", None, move |obj, _, _| {
+                let query = obj.imp().search_entry.text();
+                saved_search_dialog::show(obj, None, query.trim());
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -129,6 +231,88 @@ mod imp {
                         false,
                         glib::ParamFlags::READWRITE,
                     ),
+                    glib::ParamSpecBoolean::new(
+                        "is-auth-required",
+                        "Is Auth Required",
+                        "Whether the remote rejected the credentials",
+                        false,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "n-trashed-notes",
+                        "Number of Trashed Notes",
+                        "Number of notes in the trash, including the ones not shown yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt64::new(
+                        "trash-size",
+                        "Trash Size",
+                        "Bytes freed by emptying the trash",
+                        0,
+                        u64::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "pending-changes",
+                        "Pending Changes",
+                        "Number of changes that are not synced yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "ahead",
+                        "Ahead",
+                        "Number of commits that are not pushed yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "behind",
+                        "Behind",
+                        "Number of commits of the remote that are not pulled yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-editing-note",
+                        "Is Editing Note",
+                        "Whether the text of the selected note has the focus",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-loading",
+                        "Is Loading",
+                        "Whether the notes are being loaded",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecDouble::new(
+                        "load-progress",
+                        "Load Progress",
+                        "Fraction of the notes that are loaded",
+                        0.0,
+                        1.0,
+                        0.0,
+                        glib::ParamFlags::READWRITE,
+                    ),
+                    glib::ParamSpecString::new(
+                        "view-title",
+                        "View Title",
+                        "Name of the view of the shown notes",
+                        None,
+                        glib::ParamFlags::READABLE,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -162,6 +346,45 @@ mod imp {
                     let is_syncing = value.get().unwrap();
                     self.is_syncing.set(is_syncing);
                 }
+                "is-auth-required" => {
+                    let is_auth_required = value.get().unwrap();
+                    self.is_auth_required.set(is_auth_required);
+                }
+                "n-trashed-notes" => {
+                    let n_trashed_notes = value.get().unwrap();
+                    self.n_trashed_notes.set(n_trashed_notes);
+                    obj.update_trash_header();
+                    obj.update_trash_footer();
+                }
+                "trash-size" => {
+                    let trash_size = value.get().unwrap();
+                    self.trash_size.set(trash_size);
+                    obj.update_trash_header();
+                }
+                "pending-changes" => {
+                    let pending_changes = value.get().unwrap();
+                    self.pending_changes.set(pending_changes);
+                }
+                "ahead" => {
+                    let ahead = value.get().unwrap();
+                    self.ahead.set(ahead);
+                }
+                "behind" => {
+                    let behind = value.get().unwrap();
+                    self.behind.set(behind);
+                }
+                "is-editing-note" => {
+                    let is_editing_note = value.get().unwrap();
+                    obj.set_is_editing_note(is_editing_note);
+                }
+                "is-loading" => {
+                    let is_loading = value.get().unwrap();
+                    obj.set_is_loading(is_loading);
+                }
+                "load-progress" => {
+                    let load_progress = value.get().unwrap();
+                    self.load_progress.set(load_progress);
+                }
                 _ => unimplemented!(),
             }
         }
@@ -172,6 +395,16 @@ mod imp {
                 "selection-mode" => obj.selection_mode().to_value(),
                 "selected-note" => obj.selected_note().to_value(),
                 "is-syncing" => self.is_syncing.get().to_value(),
+                "is-auth-required" => self.is_auth_required.get().to_value(),
+                "n-trashed-notes" => self.n_trashed_notes.get().to_value(),
+                "trash-size" => self.trash_size.get().to_value(),
+                "pending-changes" => self.pending_changes.get().to_value(),
+                "ahead" => self.ahead.get().to_value(),
+                "behind" => self.behind.get().to_value(),
+                "is-editing-note" => self.is_editing_note.get().to_value(),
+                "is-loading" => obj.is_loading().to_value(),
+                "load-progress" => self.load_progress.get().to_value(),
+                "view-title" => self.view_switcher.view_title().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -179,8 +412,20 @@ mod imp {
         fn constructed(&self, obj: &Self::Type) {
             self.parent_constructed(obj);
 
+            tour::register_anchor(&self.create_note_button.get(), tour::CREATE_NOTE_ANCHOR);
+            tour::register_anchor(&self.view_switcher.get(), tour::VIEW_SWITCHER_ANCHOR);
+
             obj.setup_list_view();
             obj.setup_signals();
+
+            self.view_switcher
+                .connect_selected_type_notify(clone!(@weak obj => move |_| {
+                    obj.update_recent_tag_chips();
+                }));
+            self.view_switcher
+                .connect_view_title_notify(clone!(@weak obj => move |_| {
+                    obj.notify("view-title");
+                }));
         }
 
         fn dispose(&self, obj: &Self::Type) {
@@ -206,17 +451,46 @@ impl Sidebar {
     pub fn set_note_list(&self, note_list: &NoteList) {
         let imp = self.imp();
 
-        let filter = self.note_filter();
-        let filter_model = gtk::FilterListModel::new(Some(note_list), Some(&filter));
+        let filter = NoteFilter::new(NoteQuery::new(NoteScope::All, ""));
+        let filter_model = gtk::FilterListModel::new(Some(note_list), Some(filter.filter()));
 
-        let sorter = Self::default_note_sorter();
+        let sorter = Self::note_sorter(default_order);
         let sorter_model = gtk::SortListModel::new(Some(&filter_model), Some(&sorter));
 
-        imp.view_switcher.connect_selected_type_notify(move |_| {
-            filter.changed(gtk::FilterChange::Different);
-        });
+        // Frozen while a note is edited, so its row doesn't move as it is changed
+        let freezable_model = FreezableListModel::new(&sorter_model);
 
-        let selection_model = Selection::new(Some(&sorter_model));
+        // Sliced after sorting, so the first pages have the newest notes
+        let paged_model = PagedListModel::new(&freezable_model);
+        paged_model.connect_items_changed(
+            clone!(@weak self as obj => move |_, _, removed, added| {
+                if removed != added {
+                    obj.update_trash_footer();
+                }
+            }),
+        );
+        paged_model.connect_n_total_notify(clone!(@weak self as obj => move |_| {
+            obj.update_trash_footer();
+        }));
+
+        // Only has headers in the review
+        let grouped_model = GroupedListModel::new(&paged_model);
+
+        imp.note_filter.replace(Some(filter));
+        imp.sorter_model.replace(Some(sorter_model));
+        imp.freezable_model.replace(Some(freezable_model));
+        self.update_is_order_frozen();
+        imp.paged_model.replace(Some(paged_model));
+        imp.grouped_model.replace(Some(grouped_model.clone()));
+        imp.is_review.set(false);
+        self.update_view();
+
+        imp.view_switcher
+            .connect_selected_type_notify(clone!(@weak self as obj => move |_| {
+                obj.update_view();
+            }));
+
+        let selection_model = Selection::new(Some(&grouped_model));
         self.bind_property("selected-note", &selection_model, "selected-item")
             .flags(glib::BindingFlags::SYNC_CREATE | glib::BindingFlags::BIDIRECTIONAL)
             .build();
@@ -240,16 +514,77 @@ impl Sidebar {
                     obj.update_selection_menu_button_label(selection_size);
                     obj.update_action_bar_sensitivity(selection_size);
                     obj.update_action_bar(selection_size);
+                } else if removed == added && added > 1 {
+                    // Changed in place when only one item is changed, and moved otherwise
+                    obj.scroll_to_moved_selected_note(model, pos, added);
                 }
                 log::info!("Selection items changed at `{}`; removed `{}`; added `{}`", pos, removed, added);
             }),
         );
 
-        imp.list_view.set_model(Some(&selection_model));
+        // Shown in place of the placeholders once the first notes are loaded
+        selection_model.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            obj.update_list_view_model();
+        }));
+
+        imp.selection_model.replace(Some(selection_model));
+        self.update_list_view_model();
 
         self.set_selection_mode(SelectionMode::Single);
     }
 
+    /// Show placeholder rows in place of the notes while they are loaded
+    pub fn set_is_loading(&self, is_loading: bool) {
+        let imp = self.imp();
+
+        if is_loading == imp.is_loading.get() {
+            return;
+        }
+
+        imp.is_loading.set(is_loading);
+
+        if is_loading {
+            let placeholders = gio::ListStore::new(glib::Object::static_type());
+            for _ in 0..N_PLACEHOLDER_ROWS {
+                placeholders.append(&glib::Object::new::<glib::Object>(&[]).unwrap());
+            }
+            imp.list_view
+                .set_model(Some(&gtk::NoSelection::new(Some(&placeholders))));
+        } else {
+            self.update_list_view_model();
+        }
+
+        self.notify("is-loading");
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.imp().is_loading.get()
+    }
+
+    /// Swap the placeholders for the notes once they can be shown
+    fn update_list_view_model(&self) {
+        let imp = self.imp();
+
+        let selection_model = match imp.selection_model.borrow().clone() {
+            Some(selection_model) => selection_model,
+            None => return,
+        };
+
+        if !is_notes_shown(imp.is_loading.get(), selection_model.n_items()) {
+            return;
+        }
+
+        let is_shown = imp.list_view.model().map_or(false, |model| {
+            model == *selection_model.upcast_ref::<gtk::SelectionModel>()
+        });
+
+        // The selected note is already bound to the selection model, so swapping it in
+        // does not change the selected note
+        if !is_shown {
+            imp.list_view.set_model(Some(&selection_model));
+        }
+    }
+
     pub fn set_selected_note(&self, selected_note: Option<Note>) {
         if self.selected_note() == selected_note {
             return;
@@ -257,14 +592,202 @@ impl Sidebar {
 
         self.imp().selected_note.replace(selected_note);
         self.notify("selected-note");
+
+        // The previous note may have been moved while it was edited
+        if let Some(ref freezable_model) = *self.imp().freezable_model.borrow() {
+            freezable_model.flush();
+        }
     }
 
     pub fn selected_note(&self) -> Option<Note> {
         self.imp().selected_note.borrow().clone()
     }
 
-    pub fn set_tag_list(&self, tag_list: &TagList) {
-        self.imp().view_switcher.set_tag_list(tag_list);
+    /// Whether the text of the selected note has the focus, which keeps the order of the notes
+    /// while the setting is on
+    pub fn set_is_editing_note(&self, is_editing_note: bool) {
+        if is_editing_note == self.imp().is_editing_note.get() {
+            return;
+        }
+
+        self.imp().is_editing_note.set(is_editing_note);
+        self.update_is_order_frozen();
+        self.notify("is-editing-note");
+    }
+
+    /// Hide the buttons that change the notes while the notebook is read-only
+    pub fn set_is_read_only(&self, is_read_only: bool) {
+        let imp = self.imp();
+
+        imp.create_note_button.set_visible(!is_read_only);
+        imp.tag_button.set_visible(!is_read_only);
+        imp.trash_button.set_visible(!is_read_only);
+        imp.pin_button.set_visible(!is_read_only);
+    }
+
+    fn update_is_order_frozen(&self) {
+        let is_frozen = self.imp().is_editing_note.get()
+            && Application::default()
+                .settings()
+                .boolean("freeze-order-while-editing");
+
+        if let Some(ref freezable_model) = *self.imp().freezable_model.borrow() {
+            freezable_model.set_is_frozen(is_frozen);
+        }
+    }
+
+    /// Keep the selected note in view when it was moved from elsewhere, like to the top once
+    /// it is edited
+    fn scroll_to_moved_selected_note(&self, model: &Selection, position: u32, n_moved: u32) {
+        let selected_note = match self.selected_note() {
+            Some(selected_note) => selected_note,
+            None => return,
+        };
+
+        let selected_position = (position..position + n_moved).find(|index| {
+            model.item(*index).map_or(false, |item| {
+                &item == selected_note.upcast_ref::<glib::Object>()
+            })
+        });
+
+        if let Some(selected_position) = selected_position {
+            self.scroll_to_later(selected_position);
+        }
+    }
+
+    /// Scroll to the note at `position` once the list view has the latest changes too
+    fn scroll_to_later(&self, position: u32) {
+        glib::idle_add_local_once(clone!(@weak self as obj => move || {
+            if let Err(err) = obj
+                .imp()
+                .list_view
+                .activate_action("list.scroll-to-item", Some(&position.to_variant()))
+            {
+                log::warn!("Failed to scroll to selected note: {:?}", err);
+            }
+        }));
+    }
+
+    pub fn set_lists(
+        &self,
+        tag_list: &TagList,
+        saved_search_list: &SavedSearchList,
+        problem_list: &ProblemList,
+    ) {
+        self.imp()
+            .view_switcher
+            .set_lists(tag_list, saved_search_list, problem_list);
+
+        // The selected saved search may have been edited
+        saved_search_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+            if matches!(obj.imp().view_switcher.selected_type(), ItemKind::SavedSearch(_)) {
+                obj.update_view();
+            }
+        }));
+    }
+
+    /// Show the search bar and focus it
+    pub fn start_search(&self) {
+        let imp = self.imp();
+        imp.search_bar.set_search_mode(true);
+        imp.search_entry.grab_focus();
+    }
+
+    pub fn select_tag(&self, tag: &Tag) {
+        self.imp().view_switcher.select_tag(tag);
+    }
+
+    /// Show notes among which a new note is shown, like all of them instead of the trash, and
+    /// leave the selection of many notes. Returns the tag it has to have to be shown among
+    /// the notes of the selected tag.
+    pub fn prepare_new_note(&self) -> Option<Tag> {
+        let imp = self.imp();
+
+        self.set_selection_mode(SelectionMode::Single);
+
+        let scope = self.note_scope(&imp.view_switcher.selected_type());
+        if !scope.can_show_new_notes() {
+            imp.view_switcher.select_all_notes();
+        }
+
+        scope.new_note_tag().cloned()
+    }
+
+    /// Scroll to the selected note, like after it was created
+    pub fn scroll_to_selected_note(&self) {
+        let selected_position = match *self.imp().selection_model.borrow() {
+            Some(ref selection_model) => selection_model.selected(),
+            None => return,
+        };
+
+        if selected_position != gtk::INVALID_LIST_POSITION {
+            self.scroll_to_later(selected_position);
+        }
+    }
+
+    /// The tag whose notes are shown, if any
+    pub fn selected_tag(&self) -> Option<Tag> {
+        match self.imp().view_switcher.selected_type() {
+            ItemKind::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    pub fn connect_selected_tag_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.imp().view_switcher.connect_selected_type_notify(
+            clone!(@weak self as obj => move |_| {
+                f(&obj);
+            }),
+        )
+    }
+
+    /// Show a chip for each of `tags` above the notes, to quickly show the notes of one
+    pub fn set_recent_tags(&self, tags: &[Tag]) {
+        let imp = self.imp();
+
+        for (_, chip) in imp.recent_tag_chips.take() {
+            imp.recent_tags_box.remove(&chip);
+        }
+
+        let chips = tags
+            .iter()
+            .map(|tag| {
+                let chip = gtk::ToggleButton::with_label(&tag.name());
+                chip.connect_clicked(clone!(@weak self as obj, @weak tag => move |_| {
+                    obj.toggle_tag_filter(&tag);
+                }));
+                imp.recent_tags_box.append(&chip);
+                (tag.clone(), chip)
+            })
+            .collect::<Vec<_>>();
+
+        imp.recent_tags_bar.set_visible(!chips.is_empty());
+        imp.recent_tag_chips.replace(chips);
+        self.update_recent_tag_chips();
+    }
+
+    /// Show the notes of `tag`, or all the notes if they are already shown
+    fn toggle_tag_filter(&self, tag: &Tag) {
+        if self.selected_tag().as_ref() == Some(tag) {
+            self.imp().view_switcher.select_all_notes();
+        } else {
+            self.select_tag(tag);
+        }
+
+        // Clicking toggled it, even if the view didn't change
+        self.update_recent_tag_chips();
+    }
+
+    /// Highlight the chip of the tag whose notes are shown
+    fn update_recent_tag_chips(&self) {
+        let selected_tag = self.selected_tag();
+
+        for (tag, chip) in self.imp().recent_tag_chips.borrow().iter() {
+            chip.set_active(selected_tag.as_ref() == Some(tag));
+        }
     }
 
     pub fn selection_mode(&self) -> SelectionMode {
@@ -301,11 +824,10 @@ impl Sidebar {
 
     pub fn selection_model(&self) -> Selection {
         self.imp()
-            .list_view
-            .model()
-            .expect("List view model not set")
-            .downcast()
-            .unwrap()
+            .selection_model
+            .borrow()
+            .clone()
+            .expect("Please call `set_note_list` first")
     }
 
     pub fn selected_notes(&self) -> Vec<Note> {
@@ -318,56 +840,260 @@ impl Sidebar {
 
         for position in 0..model.n_items() {
             if model.is_selected(position) {
-                let note_at_position = model.item(position).unwrap().downcast::<Note>().unwrap();
-                selected_notes.push(note_at_position);
+                // Headers of the review can be selected with select all
+                if let Ok(note_at_position) = model.item(position).unwrap().downcast::<Note>() {
+                    selected_notes.push(note_at_position);
+                }
             }
         }
 
         selected_notes
     }
 
-    fn note_filter(&self) -> gtk::BoolFilter {
-        let selected_type_expression = self
-            .imp()
-            .view_switcher
-            .property_expression("selected-type");
-
-        let filter_expression = gtk::ClosureExpression::new::<bool, _, _>(
-            &[selected_type_expression],
-            closure!(|note: Note, selected_type: ItemKind| {
-                let note = note.metadata();
-
-                match selected_type {
-                    ItemKind::AllNotes => !note.is_trashed(),
-                    ItemKind::Trash => note.is_trashed(),
-                    ItemKind::Tag(ref tag) => note.tag_list().contains(tag) && !note.is_trashed(),
-                    ItemKind::Separator | ItemKind::Category | ItemKind::EditTags => {
-                        unreachable!(
-                            "ItemKind of type Separator, Category, or EditTags cannot be selected."
-                        );
-                    }
+    fn note_scope(&self, selected_type: &ItemKind) -> NoteScope {
+        match selected_type {
+            ItemKind::AllNotes => NoteScope::All,
+            ItemKind::Review => {
+                let today = Local::today().naive_local();
+                NoteScope::Review(self.imp().review_period_button.period().range(today))
+            }
+            ItemKind::Trash => NoteScope::Trash,
+            ItemKind::Tag(tag) => NoteScope::Tag(tag.clone()),
+            ItemKind::SavedSearch(saved_search) => match saved_search.parse_query() {
+                Ok(query) => NoteScope::SavedSearch(Some(query)),
+                Err(err) => {
+                    log::warn!(
+                        "Showing no notes, saved search `{}` has an invalid query `{}`: {}",
+                        saved_search.name(),
+                        saved_search.query(),
+                        err
+                    );
+                    NoteScope::SavedSearch(None)
                 }
-            }),
-        );
+            },
+            ItemKind::Separator
+            | ItemKind::Category
+            | ItemKind::EditTags
+            | ItemKind::Problems(_) => {
+                unreachable!(
+                    "ItemKind of type Separator, Category, EditTags, or Problems cannot be selected."
+                );
+            }
+        }
+    }
 
-        gtk::BoolFilter::builder()
-            .expression(&filter_expression)
-            .build()
+    /// Match the content of the notes on the search index of `note_manager` once it is built,
+    /// rather than reading the content of each note as the search is typed
+    pub fn bind_search_index(&self, note_manager: &NoteManager) {
+        self.imp().note_manager.set(Some(note_manager));
+
+        note_manager.connect_search_index_changed(clone!(@weak self as obj => move |_| {
+            if let Some(ref filter) = *obj.imp().note_filter.borrow() {
+                filter.set_content_matches(obj.content_matches(&filter.query()));
+            }
+        }));
     }
 
-    fn default_note_sorter() -> gtk::CustomSorter {
-        gtk::CustomSorter::new(move |obj1, obj2| {
-            let note_1 = obj1.downcast_ref::<Note>().unwrap().metadata();
-            let note_2 = obj2.downcast_ref::<Note>().unwrap().metadata();
-
-            // Sort is pinned first before classifying by last modified
-            if note_1.is_pinned() == note_2.is_pinned() {
-                note_2.last_modified().cmp(&note_1.last_modified()).into()
-            } else if note_1.is_pinned() && !note_2.is_pinned() {
-                gtk::Ordering::Smaller
+    /// The notes whose content has each text of `query` in the search index, or `None` before
+    /// it is bound
+    fn content_matches(&self, query: &NoteQuery) -> Option<ContentMatches> {
+        let note_manager = self.imp().note_manager.upgrade()?;
+        let mut content_matches = ContentMatches::default();
+
+        // Texts the index can't tell about are matched on the content itself
+        for text in query.texts() {
+            if let Some(matches) = note_manager.search_contents(text) {
+                let note_ids = matches.into_iter().map(|(note, _)| note.id()).collect();
+                content_matches.insert(text, note_ids);
+            }
+        }
+
+        Some(content_matches)
+    }
+
+    /// Scroll `note` to the first match of the texts of the search in its content, if any, like
+    /// when it is opened from the results
+    fn scroll_to_search_match(&self, note: Note) {
+        let imp = self.imp();
+
+        let query = match *imp.note_filter.borrow() {
+            Some(ref filter) => filter.query(),
+            None => return,
+        };
+        let note_manager = match imp.note_manager.upgrade() {
+            Some(note_manager) => note_manager,
+            None => return,
+        };
+
+        let note_id = note.id();
+        let first_match = query
+            .texts()
+            .into_iter()
+            .filter_map(|text| note_manager.search_contents(text))
+            .flatten()
+            .filter(|(matched, _)| matched.id() == note_id)
+            .filter_map(|(_, index_match)| index_match.offsets.into_iter().next())
+            .min_by_key(|range| range.start);
+
+        if let Some(range) = first_match {
+            Session::default().select_note_match(note, range);
+        }
+    }
+
+    /// Match the notes on the text of the search bar, and tell when it is not a valid search
+    fn update_search(&self) {
+        let imp = self.imp();
+        let text = imp.search_entry.text();
+
+        if let Some(ref filter) = *imp.note_filter.borrow() {
+            let query = filter.query().with_text(&text);
+            filter.set_query_with_matches(query.clone(), self.content_matches(&query));
+        }
+
+        let search = SearchQuery::parse(&text);
+        match search {
+            Ok(_) => {
+                imp.search_entry.remove_css_class("error");
+                imp.search_entry.set_tooltip_text(None);
+            }
+            Err(ref err) => {
+                imp.search_entry.add_css_class("error");
+                imp.search_entry.set_tooltip_text(Some(&err.to_string()));
+            }
+        }
+
+        let can_save = search.map_or(false, |search| !search.is_empty());
+        self.action_set_enabled("sidebar.save-search", can_save);
+    }
+
+    /// Show the notes of the selected view, with the headers of the days in the review
+    fn update_view(&self) {
+        let imp = self.imp();
+
+        let selected_type = imp.view_switcher.selected_type();
+        let is_review = selected_type == ItemKind::Review;
+        let is_trash = selected_type == ItemKind::Trash;
+
+        if let Some(ref filter) = *imp.note_filter.borrow() {
+            let query = filter.query().with_scope(self.note_scope(&selected_type));
+            filter.set_query_with_matches(query.clone(), self.content_matches(&query));
+        }
+
+        // Only resort when needed, since it goes through all the notes
+        if is_review != imp.is_review.get() {
+            if let Some(ref sorter_model) = *imp.sorter_model.borrow() {
+                let sorter = if is_review {
+                    Self::note_sorter(review_order)
+                } else {
+                    Self::note_sorter(default_order)
+                };
+                sorter_model.set_sorter(Some(&sorter));
+            }
+            imp.is_review.set(is_review);
+        }
+
+        // Everything else is shown at once, as before
+        if let Some(ref paged_model) = *imp.paged_model.borrow() {
+            paged_model.set_page_size(is_trash.then(|| TRASH_PAGE_SIZE));
+        }
+
+        if let Some(ref grouped_model) = *imp.grouped_model.borrow() {
+            if is_review {
+                let today = Local::today().naive_local();
+                grouped_model.set_group_func(move |item| {
+                    let note = item.downcast_ref::<Note>().unwrap();
+                    core::day_title(note.index().last_modified_day, today)
+                });
             } else {
-                gtk::Ordering::Larger
+                grouped_model.unset_group_func();
             }
+        }
+
+        imp.review_bar.set_reveal_child(is_review);
+        self.update_trash_header();
+        self.update_trash_footer();
+        self.update_selection_after_view_change();
+    }
+
+    /// Keep the selected note selected at its new position if it is still shown after the
+    /// notes were filtered and sorted for another view, and unselect it otherwise
+    fn update_selection_after_view_change(&self) {
+        let selection_model = match self.imp().selection_model.borrow().clone() {
+            Some(selection_model) => selection_model,
+            None => return,
+        };
+
+        if selection_model.selection_mode() != SelectionMode::Single
+            || selection_model.unselect_item_if_removed()
+        {
+            return;
+        }
+
+        let selected_position = selection_model.selected();
+        if selected_position != gtk::INVALID_LIST_POSITION {
+            self.scroll_to_later(selected_position);
+        }
+    }
+
+    /// Show how many notes are in the trash and how much space they take, above them in the
+    /// trash
+    fn update_trash_header(&self) {
+        let imp = self.imp();
+
+        let n_trashed_notes = imp.n_trashed_notes.get();
+        let is_trash = imp.view_switcher.selected_type() == ItemKind::Trash;
+        imp.trash_header
+            .set_reveal_child(is_trash && n_trashed_notes > 0);
+
+        // 3 notes, 12.4 kB
+        imp.trash_header_label.set_label(&ngettext!(
+            "{} note, {}",
+            "{} notes, {}",
+            n_trashed_notes,
+            n_trashed_notes,
+            glib::format_size(imp.trash_size.get())
+        ));
+    }
+
+    /// Show how many of the trashed notes are shown, while some are left to load
+    fn update_trash_footer(&self) {
+        let imp = self.imp();
+
+        let paged_model = imp.paged_model.borrow();
+        let paged_model = match *paged_model {
+            Some(ref paged_model) => paged_model,
+            None => return,
+        };
+
+        let is_trash = imp.view_switcher.selected_type() == ItemKind::Trash;
+        imp.trash_footer
+            .set_reveal_child(is_trash && paged_model.has_more());
+
+        imp.trash_footer_label.set_label(&gettext!(
+            "Showing {} of {}",
+            paged_model.n_items(),
+            imp.n_trashed_notes.get()
+        ));
+    }
+
+    /// Load the next page once the list is scrolled near its end
+    fn load_more_if_near_end(&self, adjustment: &gtk::Adjustment) {
+        // About a screen of rows left before the end
+        let is_near_end = adjustment.value() + adjustment.page_size() * 2.0 >= adjustment.upper();
+
+        if is_near_end {
+            if let Some(ref paged_model) = *self.imp().paged_model.borrow() {
+                paged_model.load_more();
+            }
+        }
+    }
+
+    fn note_sorter(order: fn(NoteSortKey, NoteSortKey) -> Ordering) -> gtk::CustomSorter {
+        gtk::CustomSorter::new(move |obj1, obj2| {
+            let key_1 = obj1.downcast_ref::<Note>().unwrap().metadata().sort_key();
+            let key_2 = obj2.downcast_ref::<Note>().unwrap().metadata().sort_key();
+
+            order(key_1, key_2).into()
         })
     }
 
@@ -376,6 +1102,7 @@ impl Sidebar {
 
         let imp = self.imp();
         imp.tag_button.set_sensitive(!is_selection_empty);
+        imp.export_button.set_sensitive(!is_selection_empty);
         imp.trash_button.set_sensitive(!is_selection_empty);
         imp.pin_button.set_sensitive(!is_selection_empty);
     }
@@ -415,11 +1142,42 @@ impl Sidebar {
     fn setup_signals(&self) {
         let imp = self.imp();
 
+        imp.search_bar.connect_entry(&imp.search_entry.get());
+        imp.search_entry
+            .connect_search_changed(clone!(@weak self as obj => move |_| {
+                obj.update_search();
+            }));
+        self.action_set_enabled("sidebar.save-search", false);
+
+        imp.review_period_button
+            .connect_period_notify(clone!(@weak self as obj => move |_| {
+                obj.update_view();
+            }));
+
+        Application::default().settings().connect_changed(
+            Some("freeze-order-while-editing"),
+            clone!(@weak self as obj => move |_, _| {
+                obj.update_is_order_frozen();
+            }),
+        );
+
+        Application::default()
+            .error_log()
+            .bind_property("has-unseen", &imp.error_log_button.get(), "visible")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+
         imp.trash_button
             .connect_clicked(clone!(@weak self as obj => move |button| {
-                let is_active = button.is_active();
-                for note in &obj.selected_notes() {
-                    note.metadata().set_is_trashed(is_active);
+                let selected_notes = obj.selected_notes();
+
+                if button.is_active() {
+                    for note in &selected_notes {
+                        note.metadata().set_is_trashed(true);
+                    }
+                } else {
+                    // Some may already be restored from their row
+                    Session::default().note_manager().restore_notes(&selected_notes);
                 }
             }));
 
@@ -442,9 +1200,23 @@ impl Sidebar {
             obj.bind_property("selection-mode", &note_row, "selection-mode")
                 .flags(glib::BindingFlags::SYNC_CREATE)
                 .build();
+            Application::default()
+                .settings()
+                .bind("sidebar-row-detail", &note_row, "detail")
+                .mapping(|variant, _| {
+                    Some(RowDetail::from_setting(variant.str()?).to_value())
+                })
+                .flags(gio::SettingsBindFlags::GET)
+                .build();
 
-            list_item
-                .property_expression("item")
+            let item_expression = list_item.property_expression("item");
+
+            item_expression
+                .chain_closure::<Option<Note>>(closure!(
+                    |_: Option<glib::Object>, item: Option<glib::Object>| {
+                        item.and_then(|item| item.downcast::<Note>().ok())
+                    }
+                ))
                 .bind(&note_row, "note", glib::Object::NONE);
 
             list_item
@@ -455,20 +1227,362 @@ impl Sidebar {
                 .property_expression("position")
                 .bind(&note_row, "position", glib::Object::NONE);
 
-            list_item.set_child(Some(&note_row));
+            let header_label = gtk::Label::builder()
+                .xalign(0.0)
+                .css_classes(vec!["heading".into(), "sidebar-group-header".into()])
+                .build();
+
+            item_expression
+                .chain_closure::<String>(closure!(
+                    |_: Option<glib::Object>, item: Option<glib::Object>| {
+                        item.and_then(|item| item.downcast::<GroupHeader>().ok())
+                            .map(|header| header.title())
+                            .unwrap_or_default()
+                    }
+                ))
+                .bind(&header_label, "label", glib::Object::NONE);
+
+            let stack = gtk::Stack::builder().vhomogeneous(false).build();
+            stack.add_named(&note_row, Some("note"));
+            stack.add_named(&header_label, Some("header"));
+            stack.add_named(&LoadingNoteRow::new(), Some("loading"));
+
+            list_item.set_child(Some(&stack));
         }));
 
+        factory.connect_bind(|_, list_item| {
+            let child_name = match list_item.item() {
+                Some(item) if item.is::<Note>() => "note",
+                Some(item) if item.is::<GroupHeader>() => "header",
+                // Placeholders shown while the notes are loaded
+                _ => "loading",
+            };
+
+            list_item.set_selectable(child_name == "note");
+            list_item.set_activatable(child_name == "note");
+
+            let stack = list_item.child().unwrap().downcast::<gtk::Stack>().unwrap();
+            stack.set_visible_child_name(child_name);
+        });
+
         imp.list_view.set_factory(Some(&factory));
 
+        imp.scrolled_window.vadjustment().connect_value_changed(
+            clone!(@weak self as obj => move |adjustment| {
+                obj.load_more_if_near_end(adjustment);
+            }),
+        );
+
         imp.list_view
             .get()
-            .connect_activate(move |list_view, index| {
+            .connect_activate(clone!(@weak self as obj => move |list_view, index| {
                 let model: Option<Selection> = list_view.model().and_then(|o| o.downcast().ok());
-                let note: Option<glib::Object> = model.as_ref().and_then(|m| m.item(index));
+                let note: Option<Note> = model
+                    .as_ref()
+                    .and_then(|m| m.item(index))
+                    .and_then(|o| o.downcast().ok());
 
-                if let (Some(model), Some(_)) = (model, note) {
+                if let (Some(model), Some(note)) = (model, note) {
                     model.set_selected(index);
+                    obj.scroll_to_search_match(note);
                 }
-            });
+            }));
+
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.connect_key_pressed(
+            clone!(@weak self as obj => @default-return gtk::Inhibit(false), move |_, key, _, modifier| {
+                obj.handle_list_view_key_pressed(key, modifier)
+            }),
+        );
+        imp.list_view.add_controller(&key_controller);
+    }
+
+    /// Open the context menu of the focused row with the menu key, and find notes by typing
+    fn handle_list_view_key_pressed(
+        &self,
+        key: gdk::Key,
+        modifier: gdk::ModifierType,
+    ) -> gtk::Inhibit {
+        let is_menu_key = key == gdk::Key::Menu
+            || (key == gdk::Key::F10 && modifier.contains(gdk::ModifierType::SHIFT_MASK));
+
+        if is_menu_key {
+            return match self.focused_note_row() {
+                Some(note_row) => {
+                    note_row.popup_context_menu();
+                    gtk::Inhibit(true)
+                }
+                None => gtk::Inhibit(false),
+            };
+        }
+
+        if modifier.intersects(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK) {
+            return gtk::Inhibit(false);
+        }
+
+        let character = match key.to_unicode() {
+            Some(character) if !character.is_control() => character,
+            _ => return gtk::Inhibit(false),
+        };
+
+        // Space still toggles the selection, unless it is in the middle of a title
+        let is_typing = !self.imp().type_ahead.borrow().is_expired(Instant::now());
+        if character == ' ' && !is_typing {
+            return gtk::Inhibit(false);
+        }
+
+        self.find_as_typed(character);
+        gtk::Inhibit(true)
+    }
+
+    /// The row of the note that has the keyboard focus
+    fn focused_note_row(&self) -> Option<NoteRow> {
+        let stack = self
+            .imp()
+            .list_view
+            .focus_child()?
+            .first_child()?
+            .downcast::<gtk::Stack>()
+            .ok()?;
+        stack.visible_child()?.downcast::<NoteRow>().ok()
+    }
+
+    /// Select the next note with a title that starts with what was typed
+    fn find_as_typed(&self, character: char) {
+        let imp = self.imp();
+        let model = self.selection_model();
+
+        let mut type_ahead = imp.type_ahead.borrow_mut();
+        type_ahead.push(character, Instant::now());
+
+        let titles = (0..model.n_items())
+            .map(|position| {
+                model
+                    .item(position)
+                    .and_then(|item| item.downcast::<Note>().ok())
+                    .map(|note| note.metadata().title())
+            })
+            .collect::<Vec<_>>();
+        let start = self
+            .focused_note_row()
+            .map_or(0, |note_row| note_row.position() as usize);
+
+        let position = match type_ahead::find_match(
+            &titles,
+            start,
+            type_ahead.text(),
+            !type_ahead.is_first_key(),
+        ) {
+            Some(position) => position as u32,
+            None => return,
+        };
+
+        if self.selection_mode() == SelectionMode::Single {
+            model.set_selected(position);
+        }
+
+        if let Err(err) = imp
+            .list_view
+            .activate_action("list.scroll-to-item", Some(&position.to_variant()))
+        {
+            log::warn!("Failed to scroll to found note: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::run_on_gtk_thread;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn notes_replace_placeholders() {
+        assert!(!is_notes_shown(true, 0));
+        assert!(is_notes_shown(true, 1));
+
+        // An empty notebook is shown once it is loaded
+        assert!(is_notes_shown(false, 0));
+        assert!(is_notes_shown(false, 3));
+    }
+
+    fn sort_key(is_pinned: bool, last_modified: &str) -> NoteSortKey {
+        NoteSortKey {
+            is_pinned,
+            last_modified: core::DateTime::parse(last_modified).unwrap(),
+        }
+    }
+
+    #[test]
+    fn note_orders() {
+        use Ordering::{Equal, Greater, Less};
+
+        let older = "2022-01-01T10:00:00+00:00";
+        let newer = "2022-01-02T10:00:00+00:00";
+        // Later on the clock, but when `newer` is
+        let east = "2022-01-02T18:00:00+08:00";
+
+        for (key_1, key_2, default, review) in [
+            (sort_key(false, newer), sort_key(false, older), Less, Less),
+            (sort_key(true, newer), sort_key(true, older), Less, Less),
+            (sort_key(true, older), sort_key(false, newer), Less, Greater),
+            (sort_key(true, newer), sort_key(false, newer), Less, Equal),
+            (sort_key(false, newer), sort_key(false, east), Equal, Equal),
+            (sort_key(true, older), sort_key(true, older), Equal, Equal),
+        ] {
+            assert_eq!(
+                default_order(key_1, key_2),
+                default,
+                "{:?} and {:?}",
+                key_1,
+                key_2
+            );
+            assert_eq!(
+                review_order(key_1, key_2),
+                review,
+                "{:?} and {:?}",
+                key_1,
+                key_2
+            );
+
+            // The same the other way around
+            assert_eq!(default_order(key_2, key_1), default.reverse());
+            assert_eq!(review_order(key_2, key_1), review.reverse());
+        }
+    }
+
+    #[test]
+    fn sorted_notes() {
+        let mut notes = vec![
+            ("Old", sort_key(false, "2021-06-01T10:00:00+00:00")),
+            ("Pinned", sort_key(true, "2021-01-01T10:00:00+00:00")),
+            ("Today", sort_key(false, "2022-03-01T09:00:00+00:00")),
+            ("Pinned today", sort_key(true, "2022-03-01T08:00:00+00:00")),
+            ("Yesterday", sort_key(false, "2022-02-28T20:00:00-05:00")),
+        ];
+        let names = |notes: &[(&'static str, NoteSortKey)]| {
+            notes.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+        };
+
+        notes.sort_by(|(_, key_1), (_, key_2)| default_order(*key_1, *key_2));
+        assert_eq!(
+            names(&notes),
+            ["Pinned today", "Pinned", "Today", "Yesterday", "Old"]
+        );
+
+        notes.sort_by(|(_, key_1), (_, key_2)| review_order(*key_1, *key_2));
+        assert_eq!(
+            names(&notes),
+            ["Today", "Pinned today", "Yesterday", "Old", "Pinned"]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn first_notes_do_not_change_selection() {
+        run_on_gtk_thread(|| {
+            let note_list = NoteList::new();
+            let selection_model = Selection::new(Some(&note_list));
+
+            let n_selected_item_changes = Rc::new(Cell::new(0));
+            selection_model.connect_notify_local(
+                Some("selected-item"),
+                clone!(@strong n_selected_item_changes => move |_, _| {
+                    n_selected_item_changes.set(n_selected_item_changes.get() + 1);
+                }),
+            );
+
+            // Still loading with no notes, so the placeholders are kept
+            assert!(!is_notes_shown(true, selection_model.n_items()));
+
+            note_list.append_many((0..3).map(|_| Note::new("/home/user")).collect());
+            assert!(is_notes_shown(true, selection_model.n_items()));
+
+            assert_eq!(selection_model.selected_item(), None);
+            assert_eq!(n_selected_item_changes.get(), 0);
+        });
+    }
+
+    /// Four notes where only the odd ones have `tag`, shown through a filter of all notes
+    fn filtered_selection(tag: &Tag) -> (Vec<Note>, NoteFilter, Selection) {
+        let notes = (0..4).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+        for note in notes.iter().skip(1).step_by(2) {
+            note.metadata().tag_list().append(tag.clone()).unwrap();
+        }
+
+        let note_list = NoteList::new();
+        note_list.append_many(notes.clone());
+
+        let filter = NoteFilter::new(NoteQuery::new(NoteScope::All, ""));
+        let filter_model = gtk::FilterListModel::new(Some(&note_list), Some(filter.filter()));
+        let selection_model = Selection::new(Some(&filter_model));
+
+        (notes, filter, selection_model)
+    }
+
+    fn count_selected_item_changes(selection_model: &Selection) -> Rc<Cell<u32>> {
+        let n_changes = Rc::new(Cell::new(0));
+        selection_model.connect_notify_local(
+            Some("selected-item"),
+            clone!(@strong n_changes => move |_, _| {
+                n_changes.set(n_changes.get() + 1);
+            }),
+        );
+        n_changes
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn selected_note_kept_when_still_shown() {
+        run_on_gtk_thread(|| {
+            let tag = Tag::new("A");
+            let (notes, filter, selection_model) = filtered_selection(&tag);
+            selection_model.set_selected(3);
+            let n_selected_item_changes = count_selected_item_changes(&selection_model);
+
+            filter.set_query(filter.query().with_scope(NoteScope::Tag(tag)));
+            assert!(!selection_model.unselect_item_if_removed());
+
+            assert_eq!(selection_model.selected(), 1);
+            assert_eq!(
+                selection_model.selected_item(),
+                Some(notes[3].clone().upcast::<glib::Object>())
+            );
+            assert_eq!(n_selected_item_changes.get(), 0);
+
+            filter.set_query(filter.query().with_scope(NoteScope::All));
+            assert_eq!(selection_model.selected(), 3);
+            assert_eq!(n_selected_item_changes.get(), 0);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn selected_note_unselected_when_filtered_out() {
+        run_on_gtk_thread(|| {
+            let tag = Tag::new("A");
+            let (notes, filter, selection_model) = filtered_selection(&tag);
+            selection_model.set_selected(2);
+            let n_selected_item_changes = count_selected_item_changes(&selection_model);
+
+            // Kept while the model changes, so it is not unselected then selected again
+            filter.set_query(filter.query().with_scope(NoteScope::Tag(tag)));
+            assert_eq!(selection_model.selected(), gtk::INVALID_LIST_POSITION);
+            assert_eq!(
+                selection_model.selected_item(),
+                Some(notes[2].clone().upcast::<glib::Object>())
+            );
+            assert_eq!(n_selected_item_changes.get(), 0);
+
+            assert!(selection_model.unselect_item_if_removed());
+            assert_eq!(selection_model.selected_item(), None);
+            assert_eq!(n_selected_item_changes.get(), 1);
+
+            // Not selected again once it is shown again
+            filter.set_query(filter.query().with_scope(NoteScope::All));
+            assert_eq!(selection_model.selected(), gtk::INVALID_LIST_POSITION);
+            assert!(!selection_model.unselect_item_if_removed());
+        });
     }
 }
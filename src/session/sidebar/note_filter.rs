@@ -0,0 +1,890 @@
+use chrono::{Local, NaiveDate};
+use gtk::{glib, prelude::*};
+use once_cell::unsync::OnceCell;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    core::{contains_words, is_same_tag_name, DateRange, SearchQuery, SearchTarget},
+    model::{Note, NoteFlags, NoteId, NoteIndex, Tag},
+};
+
+/// Which notes are shown, before matching the text
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoteScope {
+    All,
+    Trash,
+    Tag(Tag),
+    /// Notes last edited within the range, for the review
+    Review(DateRange),
+    /// Notes matching a saved search. `None` when its query is invalid, which matches no notes.
+    SavedSearch(Option<SearchQuery>),
+}
+
+impl NoteScope {
+    /// Tag given to the notes created while the scope is shown, so they are shown in it
+    pub fn new_note_tag(&self) -> Option<&Tag> {
+        match self {
+            Self::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Whether a note created while the scope is shown can be shown in it, which it can't in
+    /// the trash, so the notes of another scope have to be shown instead
+    pub fn can_show_new_notes(&self) -> bool {
+        *self != Self::Trash
+    }
+}
+
+/// What a note has to match to be shown in the sidebar
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteQuery {
+    scope: NoteScope,
+    /// Parsed from the text, or the text as written when it can't be
+    search: SearchQuery,
+    /// Day that relative days, like in `after:7d`, are counted from
+    today: NaiveDate,
+}
+
+impl NoteQuery {
+    /// Query matching `text` as a [`SearchQuery`], or as written while it is not a valid one,
+    /// like while `tag:` is being typed
+    pub fn new(scope: NoteScope, text: &str) -> Self {
+        Self {
+            scope,
+            search: SearchQuery::parse(text).unwrap_or_else(|_| SearchQuery::with_text(text)),
+            today: Local::today().naive_local(),
+        }
+    }
+
+    pub fn with_scope(self, scope: NoteScope) -> Self {
+        Self {
+            scope,
+            today: Local::today().naive_local(),
+            ..self
+        }
+    }
+
+    pub fn with_text(self, text: &str) -> Self {
+        Self::new(self.scope, text)
+    }
+
+    /// Whether the note with `index` matches. `content` is only called when the text has to
+    /// be matched on it.
+    pub fn matches(&self, index: &NoteIndex, content: impl FnOnce() -> Rc<str>) -> bool {
+        self.matches_indexed(index, |_| None, content)
+    }
+
+    /// Like [`NoteQuery::matches`], where `indexed_content_contains` tells whether the
+    /// content has a text when it is known without reading the content
+    pub fn matches_indexed(
+        &self,
+        index: &NoteIndex,
+        indexed_content_contains: impl Fn(&str) -> Option<bool>,
+        content: impl FnOnce() -> Rc<str>,
+    ) -> bool {
+        let target = IndexTarget::new(index, indexed_content_contains, content);
+
+        is_in_scope(&self.scope, index, &target, self.today)
+            && self.search.matches(&target, self.today)
+    }
+
+    /// The texts matched on the title or the content, including the ones of the saved search
+    pub fn texts(&self) -> Vec<&str> {
+        let mut texts = self.search.texts().collect::<Vec<_>>();
+
+        if let NoteScope::SavedSearch(Some(ref search)) = self.scope {
+            texts.extend(search.texts());
+        }
+
+        texts
+    }
+
+    /// How the notes matched by `self` compare to the ones matched by `old`, so the filter
+    /// only checks the notes that may change. `None` if they are the same.
+    pub fn change_from(&self, old: &Self) -> Option<gtk::FilterChange> {
+        // Relative days, like in `after:7d`, now fall on other days
+        if self.today != old.today {
+            return Some(gtk::FilterChange::Different);
+        }
+
+        let scope_change = if self.scope == old.scope {
+            None
+        } else {
+            match (&old.scope, &self.scope) {
+                (NoteScope::All, NoteScope::Tag(_) | NoteScope::SavedSearch(_)) => {
+                    Some(gtk::FilterChange::MoreStrict)
+                }
+                (NoteScope::Tag(_) | NoteScope::SavedSearch(_), NoteScope::All) => {
+                    Some(gtk::FilterChange::LessStrict)
+                }
+                _ => Some(gtk::FilterChange::Different),
+            }
+        };
+
+        // Only text can be compared, as adding a term can also match more notes, like `OR`.
+        // The content may be matched by words, the last one as the start of a word, so only
+        // text typed or erased at the end is known to be stricter or less strict.
+        let text_change = if self.search == old.search {
+            None
+        } else {
+            match (self.search.plain_text(), old.search.plain_text()) {
+                (Some(text), Some(old_text)) if text.starts_with(old_text) => {
+                    Some(gtk::FilterChange::MoreStrict)
+                }
+                (Some(text), Some(old_text)) if old_text.starts_with(text) => {
+                    Some(gtk::FilterChange::LessStrict)
+                }
+                _ => Some(gtk::FilterChange::Different),
+            }
+        };
+
+        match (scope_change, text_change) {
+            (change, None) | (None, change) => change,
+            (Some(scope_change), Some(text_change)) if scope_change == text_change => {
+                Some(scope_change)
+            }
+            _ => Some(gtk::FilterChange::Different),
+        }
+    }
+}
+
+/// Whether the note with `index`, which is matched as `target`, is shown in `scope`, before
+/// matching the text
+fn is_in_scope(
+    scope: &NoteScope,
+    index: &NoteIndex,
+    target: &impl SearchTarget,
+    today: NaiveDate,
+) -> bool {
+    match scope {
+        NoteScope::All => !index.is_trashed(),
+        NoteScope::Trash => index.is_trashed(),
+        NoteScope::Tag(tag) => !index.is_trashed() && index.tags.contains(tag),
+        NoteScope::Review(range) => !index.is_trashed() && range.contains(index.last_modified_day),
+        NoteScope::SavedSearch(search) => {
+            !index.is_trashed()
+                && search
+                    .as_ref()
+                    .map_or(false, |search| search.matches(target, today))
+        }
+    }
+}
+
+/// A [`NoteIndex`] as it is matched by a [`SearchQuery`], which only reads the content once
+/// and only when it has to
+struct IndexTarget<'a, I, F> {
+    index: &'a NoteIndex,
+    indexed_content_contains: I,
+    read_content: Cell<Option<F>>,
+    content: OnceCell<Rc<str>>,
+}
+
+impl<'a, I, F> IndexTarget<'a, I, F>
+where
+    I: Fn(&str) -> Option<bool>,
+    F: FnOnce() -> Rc<str>,
+{
+    fn new(index: &'a NoteIndex, indexed_content_contains: I, read_content: F) -> Self {
+        Self {
+            index,
+            indexed_content_contains,
+            read_content: Cell::new(Some(read_content)),
+            content: OnceCell::new(),
+        }
+    }
+
+    fn content(&self) -> Rc<str> {
+        self.content
+            .get_or_init(|| {
+                self.read_content
+                    .take()
+                    .map_or_else(|| Rc::from(""), |read_content| read_content())
+            })
+            .clone()
+    }
+}
+
+impl<I, F> SearchTarget for IndexTarget<'_, I, F>
+where
+    I: Fn(&str) -> Option<bool>,
+    F: FnOnce() -> Rc<str>,
+{
+    fn is_pinned(&self) -> bool {
+        self.index.flags.contains(NoteFlags::PINNED)
+    }
+
+    fn has_tag(&self, name: &str) -> bool {
+        self.index
+            .tags
+            .iter()
+            .any(|tag| is_same_tag_name(&tag.name(), name))
+    }
+
+    fn is_untagged(&self) -> bool {
+        self.index.tags.is_empty()
+    }
+
+    fn last_modified_day(&self) -> NaiveDate {
+        self.index.last_modified_day
+    }
+
+    fn title(&self) -> &str {
+        &self.index.title
+    }
+
+    fn content_contains(&self, text: &str) -> bool {
+        (self.indexed_content_contains)(text).unwrap_or_else(|| self.content().contains(text))
+    }
+}
+
+/// The notes whose content has each text of a query, as found in the search index, so the
+/// content of the notes is not read to match them
+#[derive(Debug, Default)]
+pub struct ContentMatches {
+    by_text: HashMap<String, HashSet<NoteId>>,
+}
+
+impl ContentMatches {
+    pub fn insert(&mut self, text: &str, note_ids: HashSet<NoteId>) {
+        self.by_text.insert(text.to_string(), note_ids);
+    }
+
+    /// Whether the content of `note` has `text`, or `None` if it was not searched for
+    fn contains(&self, note: &Note, text: &str) -> Option<bool> {
+        let note_ids = self.by_text.get(text)?;
+
+        // The changes that are not saved may not be indexed yet
+        if !note.is_saved() {
+            return Some(contains_words(&note.lowercase_content(), text));
+        }
+
+        Some(note_ids.contains(&note.id()))
+    }
+}
+
+/// Filter of the notes shown in the sidebar
+#[derive(Debug)]
+pub struct NoteFilter {
+    filter: gtk::CustomFilter,
+    query: Rc<RefCell<NoteQuery>>,
+    content_matches: Rc<RefCell<Option<ContentMatches>>>,
+}
+
+impl NoteFilter {
+    pub fn new(query: NoteQuery) -> Self {
+        let query = Rc::new(RefCell::new(query));
+        let content_matches: Rc<RefCell<Option<ContentMatches>>> = Rc::default();
+
+        let filter = gtk::CustomFilter::new(
+            glib::clone!(@strong query, @strong content_matches => move |obj| {
+                let note = obj.downcast_ref::<Note>().unwrap();
+                let content_matches = content_matches.borrow();
+
+                query.borrow().matches_indexed(
+                    &note.index(),
+                    |text| {
+                        content_matches
+                            .as_ref()
+                            .and_then(|content_matches| content_matches.contains(note, text))
+                    },
+                    || note.lowercase_content(),
+                )
+            }),
+        );
+
+        Self {
+            filter,
+            query,
+            content_matches,
+        }
+    }
+
+    pub fn filter(&self) -> &gtk::CustomFilter {
+        &self.filter
+    }
+
+    pub fn query(&self) -> NoteQuery {
+        self.query.borrow().clone()
+    }
+
+    pub fn set_query(&self, query: NoteQuery) {
+        let change = query.change_from(&self.query.borrow());
+        self.query.replace(query);
+
+        if let Some(change) = change {
+            self.filter.changed(change);
+        }
+    }
+
+    /// Like [`NoteFilter::set_query`], with the notes whose content has its texts, or `None`
+    /// when the notes are not indexed yet
+    pub fn set_query_with_matches(
+        &self,
+        query: NoteQuery,
+        content_matches: Option<ContentMatches>,
+    ) {
+        self.content_matches.replace(content_matches);
+        self.set_query(query);
+    }
+
+    /// Match the notes again with `content_matches` of the texts of the query, like once the
+    /// search index changed
+    pub fn set_content_matches(&self, content_matches: Option<ContentMatches>) {
+        self.content_matches.replace(content_matches);
+
+        if !self.query.borrow().texts().is_empty() {
+            self.filter.changed(gtk::FilterChange::Different);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::NoteList,
+        test_harness::{run_on_gtk_thread, NoteIndexBuilder},
+    };
+
+    use std::time::{Duration, Instant};
+
+    const N_NOTES: usize = 10_000;
+
+    /// Time it takes to draw a frame at 60 fps. Unoptimized builds are several times slower,
+    /// so they are given more time.
+    fn frame_budget() -> Duration {
+        if cfg!(debug_assertions) {
+            Duration::from_millis(64)
+        } else {
+            Duration::from_millis(16)
+        }
+    }
+
+    fn index(title: &str, tags: &[&Tag], is_trashed: bool) -> NoteIndex {
+        tags.iter()
+            .fold(NoteIndexBuilder::new(title), |builder, tag| {
+                builder.tag(tag)
+            })
+            .trashed(is_trashed)
+            .build()
+    }
+
+    fn no_content() -> Rc<str> {
+        Rc::from("")
+    }
+
+    /// Indices of notes where every fourth is tagged with `tag` and every tenth is trashed
+    fn synthetic_indices(tag: &Tag) -> Vec<NoteIndex> {
+        (0..N_NOTES)
+            .map(|i| {
+                let builder = NoteIndexBuilder::new(&format!("Note {}", i)).trashed(i % 10 == 0);
+
+                if i % 4 == 0 {
+                    builder.tag(tag).build()
+                } else {
+                    builder.build()
+                }
+            })
+            .collect()
+    }
+
+    /// Which of `indices` are shown after the query changed from `old` to `new`, when only
+    /// the notes that may change are checked again, like a `gtk::FilterListModel` does, and
+    /// how many were checked
+    fn refilter(
+        indices: &[NoteIndex],
+        shown: &[bool],
+        old: &NoteQuery,
+        new: &NoteQuery,
+    ) -> (Vec<bool>, usize) {
+        let change = new.change_from(old);
+        let mut n_checks = 0;
+
+        let new_shown = indices
+            .iter()
+            .zip(shown)
+            .map(|(index, is_shown)| {
+                let is_checked = match change {
+                    None => false,
+                    Some(gtk::FilterChange::MoreStrict) => *is_shown,
+                    Some(gtk::FilterChange::LessStrict) => !*is_shown,
+                    Some(_) => true,
+                };
+
+                if is_checked {
+                    n_checks += 1;
+                    new.matches(index, no_content)
+                } else {
+                    *is_shown
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Checking only some must give what checking all of them would
+        for (index, is_shown) in indices.iter().zip(&new_shown) {
+            assert_eq!(new.matches(index, no_content), *is_shown, "{:?}", index);
+        }
+
+        (new_shown, n_checks)
+    }
+
+    /// Like [`synthetic_indices`], as notes in a list
+    fn synthetic_note_list(tag: &Tag) -> NoteList {
+        let note_list = NoteList::new();
+
+        for i in 0..N_NOTES {
+            let note = Note::new("/home/user");
+            let metadata = note.metadata();
+            metadata.set_title(&format!("Note {}", i));
+            metadata.set_is_trashed(i % 10 == 0);
+
+            if i % 4 == 0 {
+                metadata.tag_list().append(tag.clone()).unwrap();
+            }
+
+            note_list.append(note);
+        }
+
+        note_list
+    }
+
+    #[test]
+    fn matches_scope() {
+        let tag = Tag::new("A");
+        let tagged = index("Tagged", &[&tag], false);
+        let untagged = index("Untagged", &[], false);
+        let trashed = index("Trashed", &[&tag], true);
+
+        let all = NoteQuery::new(NoteScope::All, "");
+        assert!(all.matches(&tagged, no_content));
+        assert!(all.matches(&untagged, no_content));
+        assert!(!all.matches(&trashed, no_content));
+
+        let trash = NoteQuery::new(NoteScope::Trash, "");
+        assert!(!trash.matches(&tagged, no_content));
+        assert!(trash.matches(&trashed, no_content));
+
+        let tag_query = NoteQuery::new(NoteScope::Tag(tag), "");
+        assert!(tag_query.matches(&tagged, no_content));
+        assert!(!tag_query.matches(&untagged, no_content));
+        assert!(!tag_query.matches(&trashed, no_content));
+    }
+
+    #[test]
+    fn scope_of_flags() {
+        let tag = Tag::new("Work");
+        let day = |day| NaiveDate::from_ymd(2022, 3, day);
+
+        let scopes = [
+            NoteScope::All,
+            NoteScope::Trash,
+            NoteScope::Tag(tag.clone()),
+            NoteScope::Review(DateRange::new(day(1), day(7))),
+            NoteScope::SavedSearch(Some(SearchQuery::parse("is:pinned").unwrap())),
+            NoteScope::SavedSearch(None),
+        ];
+
+        // Whether a note that is pinned, trashed, and tagged is in each of `scopes`
+        for ((is_pinned, is_trashed, is_tagged), is_in_scopes) in [
+            (
+                (false, false, false),
+                [true, false, false, true, false, false],
+            ),
+            (
+                (true, false, false),
+                [true, false, false, true, true, false],
+            ),
+            (
+                (false, false, true),
+                [true, false, true, true, false, false],
+            ),
+            ((true, false, true), [true, false, true, true, true, false]),
+            (
+                (false, true, false),
+                [false, true, false, false, false, false],
+            ),
+            (
+                (true, true, true),
+                [false, true, false, false, false, false],
+            ),
+        ] {
+            let mut builder = NoteIndexBuilder::new("Note")
+                .pinned(is_pinned)
+                .trashed(is_trashed)
+                .last_modified_day(day(3));
+            if is_tagged {
+                builder = builder.tag(&tag);
+            }
+            let index = builder.build();
+            let target = IndexTarget::new(&index, |_| None, no_content);
+
+            for (scope, expected) in scopes.iter().zip(is_in_scopes) {
+                assert_eq!(
+                    is_in_scope(scope, &index, &target, day(3)),
+                    expected,
+                    "{:?} with pinned: {}, trashed: {}, tagged: {}",
+                    scope,
+                    is_pinned,
+                    is_trashed,
+                    is_tagged
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scope_of_last_modified_day() {
+        let day = |day| NaiveDate::from_ymd(2022, 3, day);
+        let review = NoteScope::Review(DateRange::new(day(2), day(8)));
+        let edited_in_last_week =
+            NoteScope::SavedSearch(Some(SearchQuery::parse("after:7d").unwrap()));
+
+        // Today is the 10th, so the last seven days are from the 4th
+        for (last_modified_day, in_review, in_last_week) in [
+            (day(1), false, false),
+            (day(2), true, false),
+            (day(3), true, false),
+            (day(4), true, true),
+            (day(8), true, true),
+            (day(10), false, true),
+        ] {
+            let index = NoteIndexBuilder::new("Note")
+                .last_modified_day(last_modified_day)
+                .build();
+            let target = IndexTarget::new(&index, |_| None, no_content);
+
+            assert_eq!(
+                is_in_scope(&review, &index, &target, day(10)),
+                in_review,
+                "{}",
+                last_modified_day
+            );
+            assert_eq!(
+                is_in_scope(&edited_in_last_week, &index, &target, day(10)),
+                in_last_week,
+                "{}",
+                last_modified_day
+            );
+        }
+    }
+
+    #[test]
+    fn new_notes() {
+        let tag = Tag::new("A");
+
+        let new_note = |scope: &NoteScope| {
+            let tags = scope.new_note_tag().into_iter().collect::<Vec<_>>();
+            index("", &tags, false)
+        };
+
+        for scope in [NoteScope::All, NoteScope::Tag(tag.clone())] {
+            assert!(scope.can_show_new_notes());
+            assert!(NoteQuery::new(scope.clone(), "").matches(&new_note(&scope), no_content));
+        }
+        assert_eq!(NoteScope::Tag(tag.clone()).new_note_tag(), Some(&tag));
+        assert_eq!(NoteScope::All.new_note_tag(), None);
+
+        let trash = NoteScope::Trash;
+        assert!(!trash.can_show_new_notes());
+        assert_eq!(trash.new_note_tag(), None);
+        assert!(!NoteQuery::new(trash.clone(), "").matches(&new_note(&trash), no_content));
+    }
+
+    #[test]
+    fn matches_review() {
+        let note = index("Note", &[], false);
+        let trashed = index("Trashed", &[], true);
+
+        let day = |day| NaiveDate::from_ymd(2022, 3, day);
+        let review = |start, end| NoteQuery::new(NoteScope::Review(DateRange::new(start, end)), "");
+
+        assert!(review(day(1), day(1)).matches(&note, no_content));
+        assert!(review(day(1), day(7)).matches(&note, no_content));
+        assert!(!review(day(2), day(7)).matches(&note, no_content));
+        assert!(!review(day(1), day(7)).matches(&trashed, no_content));
+    }
+
+    #[test]
+    fn matches_saved_search() {
+        let tag = Tag::new("Work");
+        let tagged = index("Report", &[&tag], false);
+        let untagged = index("Chores", &[], false);
+        let trashed = index("Old Report", &[&tag], true);
+
+        let saved_search = |query| {
+            NoteQuery::new(
+                NoteScope::SavedSearch(Some(SearchQuery::parse(query).unwrap())),
+                "",
+            )
+        };
+        let work = saved_search("tag:work");
+        assert!(work.matches(&tagged, no_content));
+        assert!(!work.matches(&untagged, no_content));
+        assert!(!work.matches(&trashed, no_content));
+
+        let not_work = saved_search("-tag:WORK report OR is:untagged");
+        assert!(!not_work.matches(&tagged, no_content));
+        assert!(not_work.matches(&untagged, no_content));
+
+        // An invalid query shows nothing
+        let invalid = NoteQuery::new(NoteScope::SavedSearch(None), "");
+        assert!(!invalid.matches(&tagged, no_content));
+        assert!(!invalid.matches(&untagged, no_content));
+
+        // The search text still narrows it down
+        let work_report = NoteQuery::new(work.scope.clone(), "report");
+        assert!(work_report.matches(&tagged, no_content));
+    }
+
+    #[test]
+    fn matches_search_text() {
+        let tag = Tag::new("Work");
+        let note = index("Report", &[&tag], false);
+
+        assert!(NoteQuery::new(NoteScope::All, "tag:work").matches(&note, no_content));
+        assert!(!NoteQuery::new(NoteScope::All, "is:pinned").matches(&note, no_content));
+
+        // Matched as written while it is not a valid search
+        let content = || Rc::from("see tag: below");
+        assert!(NoteQuery::new(NoteScope::All, "tag:").matches(&note, content));
+        assert!(!NoteQuery::new(NoteScope::All, "tag:").matches(&note, no_content));
+    }
+
+    #[test]
+    fn matches_text() {
+        let note = index("Groceries", &[], false);
+
+        assert!(NoteQuery::new(NoteScope::All, "GROC").matches(&note, no_content));
+        assert!(!NoteQuery::new(NoteScope::All, "milk").matches(&note, no_content));
+
+        let content = || Rc::from("eggs, milk");
+        assert!(NoteQuery::new(NoteScope::All, " Milk ").matches(&note, content));
+    }
+
+    #[test]
+    fn matches_indexed_content() {
+        let note = index("Groceries", &[], false);
+        let indexed = |text: &str| Some(text == "milk");
+        let unread_content = || -> Rc<str> { panic!("Content read while indexed") };
+
+        assert!(NoteQuery::new(NoteScope::All, "milk").matches_indexed(
+            &note,
+            indexed,
+            unread_content
+        ));
+        assert!(!NoteQuery::new(NoteScope::All, "bread").matches_indexed(
+            &note,
+            indexed,
+            unread_content
+        ));
+        assert!(NoteQuery::new(NoteScope::All, "-bread").matches_indexed(
+            &note,
+            indexed,
+            unread_content
+        ));
+
+        // Read when the index doesn't tell
+        let content = || Rc::from("eggs, milk");
+        assert!(NoteQuery::new(NoteScope::All, "eggs").matches_indexed(&note, |_| None, content));
+    }
+
+    #[test]
+    fn matches_title_without_content() {
+        let note = index("Groceries", &[], false);
+
+        let is_content_read = Cell::new(false);
+        assert!(
+            NoteQuery::new(NoteScope::All, "groceries").matches(&note, || {
+                is_content_read.set(true);
+                no_content()
+            })
+        );
+        assert!(!is_content_read.get());
+    }
+
+    #[test]
+    fn change_from_scope() {
+        let tag = Tag::new("A");
+        let all = NoteQuery::new(NoteScope::All, "");
+        let trash = NoteQuery::new(NoteScope::Trash, "");
+        let tag_query = NoteQuery::new(NoteScope::Tag(tag), "");
+        let other_tag_query = NoteQuery::new(NoteScope::Tag(Tag::new("B")), "");
+
+        assert_eq!(all.change_from(&all), None);
+        assert_eq!(
+            tag_query.change_from(&all),
+            Some(gtk::FilterChange::MoreStrict)
+        );
+        assert_eq!(
+            all.change_from(&tag_query),
+            Some(gtk::FilterChange::LessStrict)
+        );
+        assert_eq!(trash.change_from(&all), Some(gtk::FilterChange::Different));
+        assert_eq!(
+            other_tag_query.change_from(&tag_query),
+            Some(gtk::FilterChange::Different)
+        );
+    }
+
+    #[test]
+    fn change_from_text() {
+        let query = |text| NoteQuery::new(NoteScope::All, text);
+
+        assert_eq!(query("gro").change_from(&query("gro")), None);
+        assert_eq!(
+            query("groc").change_from(&query("gro")),
+            Some(gtk::FilterChange::MoreStrict)
+        );
+        assert_eq!(
+            query("gro").change_from(&query("groc")),
+            Some(gtk::FilterChange::LessStrict)
+        );
+        assert_eq!(
+            query("milk").change_from(&query("gro")),
+            Some(gtk::FilterChange::Different)
+        );
+        // Not the start of the same words anymore
+        assert_eq!(
+            query("agro").change_from(&query("gro")),
+            Some(gtk::FilterChange::Different)
+        );
+    }
+
+    #[test]
+    fn change_from_search() {
+        let query = |text| NoteQuery::new(NoteScope::All, text);
+        let saved_search = NoteQuery::new(
+            NoteScope::SavedSearch(Some(SearchQuery::parse("is:pinned").unwrap())),
+            "",
+        );
+
+        assert_eq!(
+            saved_search.change_from(&query("")),
+            Some(gtk::FilterChange::MoreStrict)
+        );
+        assert_eq!(
+            query("").change_from(&saved_search),
+            Some(gtk::FilterChange::LessStrict)
+        );
+
+        // Adding a term may match more notes
+        assert_eq!(
+            query("gro OR milk").change_from(&query("gro")),
+            Some(gtk::FilterChange::Different)
+        );
+        assert_eq!(query("tag:a").change_from(&query("tag:a")), None);
+    }
+
+    #[test]
+    fn change_from_scope_and_text() {
+        let tag = Tag::new("A");
+        let all = NoteQuery::new(NoteScope::All, "gro");
+
+        assert_eq!(
+            NoteQuery::new(NoteScope::Tag(tag.clone()), "groc").change_from(&all),
+            Some(gtk::FilterChange::MoreStrict)
+        );
+        assert_eq!(
+            NoteQuery::new(NoteScope::Tag(tag), "g").change_from(&all),
+            Some(gtk::FilterChange::Different)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn set_query_filters_model() {
+        run_on_gtk_thread(|| {
+            let tag = Tag::new("A");
+            let note_list = synthetic_note_list(&tag);
+
+            let note_filter = NoteFilter::new(NoteQuery::new(NoteScope::All, ""));
+            let filter_model =
+                gtk::FilterListModel::new(Some(&note_list), Some(note_filter.filter()));
+            assert_eq!(filter_model.n_items(), 9_000);
+
+            note_filter.set_query(note_filter.query().with_scope(NoteScope::Tag(tag)));
+            // Every twentieth note is both tagged and trashed
+            assert_eq!(filter_model.n_items(), 2_000);
+
+            note_filter.set_query(note_filter.query().with_scope(NoteScope::Trash));
+            assert_eq!(filter_model.n_items(), 1_000);
+
+            note_filter.set_query(NoteQuery::new(NoteScope::All, "note 99"));
+            // "Note 99" and "Note 990" to "Note 9999", without the trashed ones
+            assert_eq!(filter_model.n_items(), 100);
+        });
+    }
+
+    #[test]
+    fn stricter_query_checks_fewer_notes() {
+        let tag = Tag::new("A");
+        let indices = synthetic_indices(&tag);
+
+        let all = NoteQuery::new(NoteScope::All, "");
+        let shown = indices
+            .iter()
+            .map(|index| all.matches(index, no_content))
+            .collect::<Vec<_>>();
+
+        // Only the 9000 shown notes have to be checked again
+        let tagged = NoteQuery::new(NoteScope::Tag(tag), "");
+        let (shown, n_checks) = refilter(&indices, &shown, &all, &tagged);
+        assert_eq!(n_checks, 9_000);
+        assert_eq!(shown.iter().filter(|is_shown| **is_shown).count(), 2_000);
+
+        // Only the 8000 hidden notes have to be checked again
+        let (shown, n_checks) = refilter(&indices, &shown, &tagged, &all);
+        assert_eq!(n_checks, 8_000);
+        assert_eq!(shown.iter().filter(|is_shown| **is_shown).count(), 9_000);
+
+        // Typing more only checks the notes that still match what was typed before
+        let typed = NoteQuery::new(NoteScope::All, "note 9");
+        let (shown, _) = refilter(&indices, &shown, &all, &typed);
+        let (shown, n_checks) = refilter(
+            &indices,
+            &shown,
+            &typed,
+            &NoteQuery::new(NoteScope::All, "note 99"),
+        );
+        assert_eq!(n_checks, 1_000);
+        assert_eq!(shown.iter().filter(|is_shown| **is_shown).count(), 100);
+    }
+
+    #[test]
+    fn view_switch_within_frame_budget() {
+        let tag = Tag::new("A");
+        let indices = synthetic_indices(&tag);
+
+        for scope in [
+            NoteScope::Tag(tag.clone()),
+            NoteScope::All,
+            NoteScope::Trash,
+            NoteScope::Tag(tag),
+            NoteScope::All,
+        ] {
+            let query = NoteQuery::new(scope.clone(), "");
+
+            let start = Instant::now();
+            let n_shown = indices
+                .iter()
+                .filter(|index| query.matches(index, no_content))
+                .count();
+            let elapsed = start.elapsed();
+
+            assert!(n_shown > 0);
+            assert!(
+                elapsed < frame_budget(),
+                "Switching to {:?} took {:?} for {} notes",
+                scope,
+                elapsed,
+                N_NOTES
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use gettextrs::gettext;
 use gtk::{
-    glib::{self, clone, closure},
+    gdk, gio,
+    glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
 };
@@ -7,36 +9,116 @@ use gtk::{
 use std::cell::{Cell, RefCell};
 
 use super::{Note, Selection, SelectionMode, Sidebar};
-use crate::{core::DateTime, model::NoteMetadata};
+use crate::{
+    core::{base_direction, markdown_to_html, NoteRepository},
+    model::{NoteMetadata, NoteTagList, SaveState},
+    session::Session,
+    utils::{self, EditProfiler, Ticker},
+    widgets::{ColorDot, SwipeActionRow},
+};
 
 const MAX_SUBTITLE_LEN: usize = 100;
 const MAX_SUBTITLE_LINE: u32 = 3;
+const MAX_TAG_CHIPS: usize = 3;
+/// Characters of a tag name shown in a chip, so a long one leaves room for the others
+const MAX_TAG_CHIP_LEN: usize = 16;
+
+/// Notes dragged from the sidebar, which are all the selected ones when a selected row is
+/// dragged while selecting
+#[derive(Debug, Clone, glib::Boxed)]
+#[boxed_type(name = "NwtySidebarDraggedNotes")]
+pub struct DraggedNotes(pub Vec<Note>);
+
+/// What is shown under the title of a row, from the `sidebar-row-detail` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[enum_type(name = "SidebarNoteRowDetail")]
+pub enum RowDetail {
+    Date,
+    Preview,
+    Tags,
+    DatePreview,
+}
+
+impl Default for RowDetail {
+    fn default() -> Self {
+        Self::DatePreview
+    }
+}
+
+impl RowDetail {
+    pub fn from_setting(choice: &str) -> Self {
+        match choice {
+            "date" => Self::Date,
+            "preview" => Self::Preview,
+            "tags" => Self::Tags,
+            _ => Self::DatePreview,
+        }
+    }
+
+    /// Page of the detail stack that is shown, the date of both being beside the title
+    fn page_name(self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Preview | Self::DatePreview => "preview",
+            Self::Tags => "tags",
+        }
+    }
+}
 
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
-    use once_cell::sync::Lazy;
+    use once_cell::{sync::Lazy, unsync::OnceCell};
 
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/sidebar-note-row.ui")]
     pub struct NoteRow {
+        #[template_child]
+        pub swipe_action_row: TemplateChild<SwipeActionRow>,
         #[template_child]
         pub title_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub detail_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub date_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub subtitle_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub tag_chip_0: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub tag_chip_1: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub tag_chip_2: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub tag_overflow_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub no_tags_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub save_state_icon: TemplateChild<gtk::Image>,
+        #[template_child]
         pub time_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub check_button_revealer: TemplateChild<gtk::Revealer>,
         #[template_child]
         pub check_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub trash_buttons: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub context_menu: TemplateChild<gio::MenuModel>,
+        #[template_child]
+        pub trash_context_menu: TemplateChild<gio::MenuModel>,
 
         pub selection_mode: Cell<SelectionMode>,
+        pub detail: Cell<RowDetail>,
         pub is_selected: Cell<bool>,
         pub position: Cell<u32>,
         pub note: RefCell<Option<Note>>,
 
-        pub buffer_changed_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub content_changed_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub tag_list_handler: RefCell<Option<(NoteTagList, glib::SignalHandlerId)>>,
+        pub repository_busy_handler: RefCell<Option<(NoteRepository, glib::SignalHandlerId)>>,
+        pub tick_handler_id: RefCell<Option<glib::SignalHandlerId>>,
+        pub context_menu_popover: OnceCell<gtk::PopoverMenu>,
     }
 
     #[glib::object_subclass]
@@ -46,7 +128,73 @@ mod imp {
         type ParentType = gtk::Widget;
 
         fn class_init(klass: &mut Self::Class) {
+            ColorDot::static_type();
+            SwipeActionRow::static_type();
             Self::bind_template(klass);
+
+            klass.install_action("note-row.select", None, move |obj, _, _| {
+                let model = obj.parent_model();
+                model.set_selection_mode(SelectionMode::Multi);
+                model.select_item(obj.position(), true);
+            });
+
+            klass.install_action("note-row.merge-into", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    Session::default().show_merge_note_dialog(&note);
+                }
+            });
+
+            klass.install_action("note-row.restore", None, move |obj, _, _| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                if let Some(note) = obj.note() {
+                    Session::default().note_manager().restore_notes(&[note]);
+                }
+            });
+
+            klass.install_action("note-row.delete-forever", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    Session::default().show_delete_note_dialog(&note);
+                }
+            });
+
+            klass.install_action("note-row.copy-markdown", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    obj.clipboard().set_text(&note.selected_content());
+                }
+            });
+
+            klass.install_action("note-row.copy-link", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    Session::default().copy_note_link(&note);
+                }
+            });
+
+            klass.install_action("note-row.show-in-folder", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    Session::default().show_note_in_folder(&note);
+                }
+            });
+
+            klass.install_action("note-row.copy-path", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    Session::default().copy_note_path(&note);
+                }
+            });
+
+            klass.install_action("note-row.copy-html", None, move |obj, _, _| {
+                if let Some(note) = obj.note() {
+                    let markdown = note.selected_content();
+                    let provider =
+                        utils::formatted_text_provider(&markdown_to_html(&markdown), &markdown);
+
+                    if let Err(err) = obj.clipboard().set_content(Some(&provider)) {
+                        log::error!("Failed to copy `{}` as HTML: {:?}", note, err);
+                    }
+                }
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -66,6 +214,14 @@ mod imp {
                         SelectionMode::default() as i32,
                         glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
+                    glib::ParamSpecEnum::new(
+                        "detail",
+                        "Detail",
+                        "What is shown under the title",
+                        RowDetail::static_type(),
+                        RowDetail::default() as i32,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
                     glib::ParamSpecBoolean::new(
                         "is-selected",
                         "Is Checked",
@@ -106,6 +262,10 @@ mod imp {
                     let selection_mode = value.get().unwrap();
                     obj.set_selection_mode(selection_mode);
                 }
+                "detail" => {
+                    let detail = value.get().unwrap();
+                    obj.set_detail(detail);
+                }
                 "is-selected" => {
                     let is_selected = value.get().unwrap();
                     obj.set_is_selected(is_selected);
@@ -125,6 +285,7 @@ mod imp {
         fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
             match pspec.name() {
                 "selection-mode" => obj.selection_mode().to_value(),
+                "detail" => obj.detail().to_value(),
                 "is-selected" => obj.is_selected().to_value(),
                 "position" => obj.position().to_value(),
                 "note" => obj.note().to_value(),
@@ -143,6 +304,18 @@ mod imp {
             while let Some(child) = obj.first_child() {
                 child.unparent();
             }
+
+            if let Some(handler_id) = self.tick_handler_id.take() {
+                Ticker::default().disconnect(handler_id);
+            }
+
+            if let Some((tag_list, handler_id)) = self.tag_list_handler.take() {
+                tag_list.disconnect(handler_id);
+            }
+
+            if let Some((repository, handler_id)) = self.repository_busy_handler.take() {
+                repository.disconnect(handler_id);
+            }
         }
     }
 
@@ -196,9 +369,29 @@ impl NoteRow {
         }
 
         imp.selection_mode.replace(selection_mode);
+        self.update_trash_actions();
         self.notify("selection-mode");
     }
 
+    pub fn detail(&self) -> RowDetail {
+        self.imp().detail.get()
+    }
+
+    /// Switch what is shown under the title, keeping the other details up to date so the
+    /// row stays as high
+    pub fn set_detail(&self, detail: RowDetail) {
+        if detail == self.detail() {
+            return;
+        }
+
+        let imp = self.imp();
+        imp.detail.set(detail);
+        imp.detail_stack.set_visible_child_name(detail.page_name());
+        imp.time_label.set_visible(detail == RowDetail::DatePreview);
+
+        self.notify("detail");
+    }
+
     pub fn note(&self) -> Option<Note> {
         self.imp().note.borrow().clone()
     }
@@ -206,11 +399,19 @@ impl NoteRow {
     pub fn set_note(&self, note: Option<Note>) {
         let imp = self.imp();
 
+        if let (Some(old_note), Some(handler_id)) =
+            (self.note(), imp.content_changed_handler_id.take())
+        {
+            old_note.disconnect(handler_id);
+        }
+
         if let Some(ref note) = note {
-            imp.buffer_changed_handler_id
-                .replace(Some(note.buffer().connect_changed(
+            imp.content_changed_handler_id
+                .replace(Some(note.connect_content_changed(
                     clone!(@weak self as obj => move |_| {
-                        obj.update_subtitle_label();
+                        EditProfiler::default().measure("note-row-subtitle", || {
+                            obj.update_subtitle_label();
+                        });
                     }),
                 )));
         }
@@ -221,14 +422,28 @@ impl NoteRow {
         self.update_subtitle_label();
     }
 
-    // TODO remove this, maybe just emit a signal from NoteRow and let sidebar handle changing
-    // the selection model
-    fn parent_model(&self) -> Selection {
+    fn sidebar(&self) -> Sidebar {
         self.ancestor(Sidebar::static_type())
             .expect("Cannot find `Sidebar` as `NoteRow` ancestor")
-            .downcast_ref::<Sidebar>()
+            .downcast::<Sidebar>()
             .unwrap()
-            .selection_model()
+    }
+
+    // TODO remove this, maybe just emit a signal from NoteRow and let sidebar handle changing
+    // the selection model
+    fn parent_model(&self) -> Selection {
+        self.sidebar().selection_model()
+    }
+
+    /// The note of the row, or all the selected ones if it is one of them while selecting
+    fn dragged_notes(&self) -> Option<DraggedNotes> {
+        let note = self.note()?;
+
+        if self.selection_mode() == SelectionMode::Multi && self.is_selected() {
+            Some(DraggedNotes(self.sidebar().selected_notes()))
+        } else {
+            Some(DraggedNotes(vec![note]))
+        }
     }
 
     fn update_subtitle_label(&self) {
@@ -238,50 +453,288 @@ impl NoteRow {
         };
 
         let mut iter = note.buffer().start_iter();
-        let mut subtitle = String::from(iter.char());
-
-        let mut line_count = 0;
-        let mut last_non_empty_char_index = 0;
-
-        while iter.forward_char() {
-            if subtitle.len() >= MAX_SUBTITLE_LEN || line_count >= MAX_SUBTITLE_LINE {
-                break;
+        let chars = std::iter::from_fn(move || {
+            if iter.is_end() {
+                return None;
             }
 
             let character = iter.char();
+            iter.forward_char();
+            Some(character)
+        });
+
+        self.imp().subtitle_label.set_label(&subtitle(chars));
+        self.update_direction();
+    }
+
+    /// Mirror the row when its note is in a right-to-left language, so the title is aligned
+    /// and ellipsized on its side, with the date on the other
+    fn update_direction(&self) {
+        let imp = self.imp();
+        let title = self
+            .note()
+            .map(|note| note.metadata().title())
+            .unwrap_or_default();
+        let preview = imp.subtitle_label.label();
+
+        let direction = row_direction(&title, &preview);
+        if let Some(child) = imp.swipe_action_row.child() {
+            set_direction_recursive(&child, direction.unwrap_or(gtk::TextDirection::None));
+        }
+        imp.subtitle_label.set_direction(
+            preview_direction(&preview, direction).unwrap_or(gtk::TextDirection::None),
+        );
+    }
+
+    fn setup_expressions(&self) {
+        let note_expression = Self::this_expression("note");
+        let metadata_expression = note_expression.chain_property::<Note>("metadata");
+
+        metadata_expression
+            .chain_property::<NoteMetadata>("last-modified")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_time_label()),
+            );
+
+        metadata_expression
+            .chain_property::<NoteMetadata>("title")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_direction()),
+            );
+
+        for property_name in ["title", "is-pinned"] {
+            metadata_expression
+                .chain_property::<NoteMetadata>(property_name)
+                .watch(
+                    Some(self),
+                    clone!(@weak self as obj => move || obj.update_accessible_label()),
+                );
+        }
+
+        metadata_expression
+            .chain_property::<NoteMetadata>("tag-list")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_tag_list()),
+            );
+
+        metadata_expression
+            .chain_property::<NoteMetadata>("is-trashed")
+            .watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_trash_actions()),
+            );
+
+        // Only the icon is updated, so the row stays in its place while the note is saved
+        for property_name in ["save-state", "save-error"] {
+            note_expression.chain_property::<Note>(property_name).watch(
+                Some(self),
+                clone!(@weak self as obj => move || obj.update_save_state_icon()),
+            );
+        }
+
+        // Also as time passes, like to yesterday at midnight
+        let handler_id = Ticker::default().connect_tick(clone!(@weak self as obj => move |_| {
+            obj.update_time_label();
+        }));
+        self.imp().tick_handler_id.replace(Some(handler_id));
+    }
+
+    fn update_time_label(&self) {
+        let label = self
+            .note()
+            .map(|note| note.metadata().last_modified().format_relative())
+            .unwrap_or_default();
+        let imp = self.imp();
+        imp.time_label.set_label(&label);
+        imp.date_label.set_label(&label);
+
+        self.update_accessible_label();
+    }
+
+    /// Follow the tags of the note, which are renamed, added, and removed in its tag list
+    fn update_tag_list(&self) {
+        let imp = self.imp();
 
-            if character == '\n' {
-                line_count += 1;
+        if let Some((tag_list, handler_id)) = imp.tag_list_handler.take() {
+            tag_list.disconnect(handler_id);
+        }
+
+        if let Some(note) = self.note() {
+            let tag_list = note.metadata().tag_list();
+            let handler_id =
+                tag_list.connect_items_changed(clone!(@weak self as obj => move |_, _, _, _| {
+                    obj.update_tag_chips();
+                }));
+            imp.tag_list_handler.replace(Some((tag_list, handler_id)));
+        }
+
+        self.update_tag_chips();
+    }
+
+    fn update_tag_chips(&self) {
+        let imp = self.imp();
+
+        let names = self
+            .note()
+            .map(|note| note.metadata().tag_list().names())
+            .unwrap_or_default();
+        let (shown, n_hidden) = tag_chips(&names, MAX_TAG_CHIPS);
+
+        let chips = [
+            imp.tag_chip_0.get(),
+            imp.tag_chip_1.get(),
+            imp.tag_chip_2.get(),
+        ];
+        for (index, chip) in chips.iter().enumerate() {
+            if let Some(name) = shown.get(index) {
+                chip.set_label(&elide_chip_label(name, MAX_TAG_CHIP_LEN));
+                chip.set_tooltip_text(Some(name));
+                chip.set_visible(true);
+            } else {
+                chip.set_visible(false);
             }
+        }
+
+        imp.tag_overflow_label.set_visible(n_hidden > 0);
+        if n_hidden > 0 {
+            imp.tag_overflow_label.set_label(&format!("+{}", n_hidden));
+            imp.tag_overflow_label
+                .set_tooltip_text(Some(&names[shown.len()..].join(", ")));
+        }
 
-            subtitle.push(character);
+        imp.no_tags_label.set_visible(names.is_empty());
+    }
+
+    fn update_save_state_icon(&self) {
+        let icon = self.imp().save_state_icon.get();
+        let note = match self.note() {
+            Some(note) => note,
+            None => {
+                icon.set_visible(false);
+                return;
+            }
+        };
 
-            if !character.is_whitespace() {
-                last_non_empty_char_index = subtitle.len() - 1;
+        let (icon_name, description) = match note.save_state() {
+            SaveState::Saved => {
+                icon.set_visible(false);
+                return;
             }
+            SaveState::Modified => ("document-edit-symbolic", gettext("Unsaved changes")),
+            SaveState::Saving => ("emblem-synchronizing-symbolic", gettext("Saving…")),
+            SaveState::Error => (
+                "dialog-error-symbolic",
+                gettext!("Failed to save: {}", note.save_error().unwrap_or_default()),
+            ),
+        };
+
+        icon.set_icon_name(Some(icon_name));
+        icon.set_tooltip_text(Some(&description));
+        icon.update_property(&[gtk::accessible::Property::Label(&description)]);
+
+        if note.save_state() == SaveState::Error {
+            icon.add_css_class("error");
+        } else {
+            icon.remove_css_class("error");
+        }
+
+        icon.set_visible(true);
+    }
+
+    fn update_accessible_label(&self) {
+        let label = self.note().map_or(String::new(), |note| {
+            let metadata = note.metadata();
+            accessible_label(
+                &metadata.title(),
+                &metadata.last_modified().format_relative(),
+                metadata.is_pinned(),
+            )
+        });
+        self.update_property(&[gtk::accessible::Property::Label(&label)]);
+    }
+
+    /// Offer restoring and deleting a trashed note instead of the usual actions, with buttons
+    /// unless the notes are being selected
+    fn update_trash_actions(&self) {
+        let imp = self.imp();
+        let is_trashed = self
+            .note()
+            .map_or(false, |note| note.metadata().is_trashed());
+
+        imp.trash_buttons
+            .set_visible(is_trashed && self.selection_mode() == SelectionMode::Single);
+
+        if is_trashed && imp.repository_busy_handler.borrow().is_none() {
+            self.setup_repository_busy_handler();
         }
 
-        subtitle.truncate(last_non_empty_char_index + 1);
+        if let Some(popover) = imp.context_menu_popover.get() {
+            let menu_model = if is_trashed {
+                imp.trash_context_menu.get()
+            } else {
+                imp.context_menu.get()
+            };
+            popover.set_menu_model(Some(&menu_model));
+        }
+    }
 
-        // Panicking when subtitle contains `\u{0}`
-        let trimmed_subtitle = subtitle.trim_matches(char::from(0));
+    /// Only offer deleting the note forever while no git operation is running, as it may be
+    /// merging its file
+    fn setup_repository_busy_handler(&self) {
+        let repository = Session::default().note_manager().repository();
+        self.action_set_enabled("note-row.delete-forever", !repository.is_busy());
+
+        let handler_id =
+            repository.connect_busy_notify(clone!(@weak self as obj => move |repository| {
+                obj.action_set_enabled("note-row.delete-forever", !repository.is_busy());
+            }));
+        self.imp()
+            .repository_busy_handler
+            .replace(Some((repository, handler_id)));
+    }
 
-        self.imp().subtitle_label.set_label(trimmed_subtitle);
+    /// Show the context menu under the row, like when opened with the keyboard
+    pub fn popup_context_menu(&self) {
+        self.popup_context_menu_at(gdk::Rectangle::new(0, self.height(), 0, 0));
     }
 
-    fn setup_expressions(&self) {
-        Self::this_expression("note")
-            .chain_property::<Note>("metadata")
-            .chain_property::<NoteMetadata>("last-modified")
-            .chain_closure::<String>(closure!(|_: Self, last_modified: DateTime| {
-                last_modified.fuzzy_display()
-            }))
-            .bind(&self.imp().time_label.get(), "label", Some(self));
+    fn popup_context_menu_at(&self, rectangle: gdk::Rectangle) {
+        let popover = self.imp().context_menu_popover.get().unwrap();
+        popover.set_pointing_to(Some(&rectangle));
+        popover.popup();
     }
 
     fn setup_signals(&self) {
-        self.imp().check_button.connect_active_notify(
-            clone!(@weak self as obj => move |check_button| {
+        let imp = self.imp();
+
+        imp.swipe_action_row
+            .connect_swipe_right_action(clone!(@weak self as obj => move |_| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                if let Some(note) = obj.note() {
+                    let metadata = note.metadata();
+                    metadata.set_is_pinned(!metadata.is_pinned());
+                }
+            }));
+        imp.swipe_action_row
+            .connect_swipe_left_action(clone!(@weak self as obj => move |_| {
+                if !Session::default().can_change_notes() {
+                    return;
+                }
+
+                if let Some(note) = obj.note() {
+                    let metadata = note.metadata();
+                    metadata.set_is_trashed(!metadata.is_trashed());
+                }
+            }));
+
+        imp.check_button
+            .connect_active_notify(clone!(@weak self as obj => move |check_button| {
                 if obj.selection_mode() != SelectionMode::Multi {
                     return;
                 }
@@ -293,16 +746,247 @@ impl NoteRow {
                 } else {
                     model.unselect_item(obj.position());
                 }
-            }),
-        );
+            }));
+
+        let context_menu_popover = gtk::PopoverMenu::from_model(Some(&imp.context_menu.get()));
+        context_menu_popover.set_parent(self);
+        context_menu_popover.set_has_arrow(false);
+        context_menu_popover.set_halign(gtk::Align::Start);
 
         let gesture_click = gtk::GestureClick::new();
         gesture_click.set_button(3);
-        gesture_click.connect_pressed(clone!(@weak self as obj => move |_, _, _, _| {
-            let model = obj.parent_model();
-            model.set_selection_mode(SelectionMode::Multi);
-            model.select_item(obj.position(), true);
+        gesture_click.connect_pressed(clone!(@weak self as obj => move |_, _, x, y| {
+            obj.popup_context_menu_at(gdk::Rectangle::new(x as i32, y as i32, 0, 0));
         }));
         self.add_controller(&gesture_click);
+
+        imp.context_menu_popover.set(context_menu_popover).unwrap();
+        self.update_trash_actions();
+
+        self.setup_drag_source();
+    }
+
+    /// Notes are tagged or trashed by dragging them onto a view of the view switcher
+    fn setup_drag_source(&self) {
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::COPY);
+        drag_source.connect_prepare(
+            clone!(@weak self as obj => @default-return None, move |_, _, _| {
+                obj.dragged_notes()
+                    .map(|notes| gdk::ContentProvider::for_value(&notes.to_value()))
+            }),
+        );
+        drag_source.connect_drag_begin(clone!(@weak self as obj => move |drag_source, _| {
+            let paintable = gtk::WidgetPaintable::new(Some(&obj));
+            drag_source.set_icon(Some(&paintable), 0, 0);
+        }));
+        self.add_controller(&drag_source);
+    }
+}
+
+/// What screen readers announce for a row, with what is only shown as icons
+fn accessible_label(title: &str, last_modified: &str, is_pinned: bool) -> String {
+    let title = if title.is_empty() {
+        gettext("Untitled Note")
+    } else {
+        title.to_string()
+    };
+
+    if is_pinned {
+        gettext!("Note, {}, modified {}, pinned", title, last_modified)
+    } else {
+        gettext!("Note, {}, modified {}", title, last_modified)
+    }
+}
+
+/// Direction of a row, from its title, or from its preview when the title has no letters,
+/// like an empty one. `None` is the direction of the locale.
+fn row_direction(title: &str, preview: &str) -> Option<gtk::TextDirection> {
+    base_direction(title).or_else(|| base_direction(preview))
+}
+
+/// Direction of the preview in a row of `row_direction`. It follows its own text, so it is
+/// ellipsized at its end, even when it is not in the language of the title.
+fn preview_direction(
+    preview: &str,
+    row_direction: Option<gtk::TextDirection>,
+) -> Option<gtk::TextDirection> {
+    base_direction(preview).or(row_direction)
+}
+
+/// Set the direction of `widget` and of all its children, as they don't take the one of
+/// their parent
+fn set_direction_recursive(widget: &gtk::Widget, direction: gtk::TextDirection) {
+    widget.set_direction(direction);
+
+    let mut child = widget.first_child();
+    while let Some(widget) = child {
+        set_direction_recursive(&widget, direction);
+        child = widget.next_sibling();
+    }
+}
+
+/// The names shown as chips, at most `max_chips` of them, and how many more there are
+fn tag_chips(names: &[String], max_chips: usize) -> (&[String], usize) {
+    let n_shown = names.len().min(max_chips);
+    (&names[..n_shown], names.len() - n_shown)
+}
+
+/// `name` cut to at most `max_len` characters, ending with an ellipsis when it was cut
+fn elide_chip_label(name: &str, max_len: usize) -> String {
+    if name.chars().count() <= max_len {
+        return name.to_string();
+    }
+
+    let mut label = name
+        .chars()
+        .take(max_len.saturating_sub(1))
+        .collect::<String>();
+    label.truncate(label.trim_end().len());
+    label.push('…');
+    label
+}
+
+/// The start of the text of a note, at most [`MAX_SUBTITLE_LEN`] characters on
+/// [`MAX_SUBTITLE_LINE`] lines, without the blanks at its end
+fn subtitle(chars: impl IntoIterator<Item = char>) -> String {
+    let mut subtitle = String::new();
+
+    let mut char_count = 0;
+    let mut line_count = 0;
+    let mut non_blank_len = 0;
+
+    for character in chars {
+        if char_count >= MAX_SUBTITLE_LEN || line_count >= MAX_SUBTITLE_LINE {
+            break;
+        }
+
+        if character == '\n' {
+            line_count += 1;
+        }
+
+        subtitle.push(character);
+        char_count += 1;
+
+        if !character.is_whitespace() {
+            non_blank_len = subtitle.len();
+        }
+    }
+
+    subtitle.truncate(non_blank_len);
+
+    // Panicking when subtitle contains `\u{0}`
+    subtitle.trim_matches(char::from(0)).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::ROW_DETAIL_CHOICES;
+
+    #[test]
+    fn accessible_labels() {
+        assert_eq!(
+            accessible_label("Groceries", "Yesterday", false),
+            "Note, Groceries, modified Yesterday"
+        );
+        assert_eq!(
+            accessible_label("Groceries", "12∶45", true),
+            "Note, Groceries, modified 12∶45, pinned"
+        );
+        assert_eq!(
+            accessible_label("", "Tue", false),
+            "Note, Untitled Note, modified Tue"
+        );
+        assert_eq!(
+            accessible_label("🎉 Party", "Tue", false),
+            "Note, 🎉 Party, modified Tue"
+        );
+    }
+
+    #[test]
+    fn row_details_from_setting() {
+        assert_eq!(
+            ROW_DETAIL_CHOICES.map(RowDetail::from_setting),
+            [
+                RowDetail::Date,
+                RowDetail::Preview,
+                RowDetail::Tags,
+                RowDetail::DatePreview
+            ]
+        );
+        assert_eq!(RowDetail::from_setting(""), RowDetail::DatePreview);
+    }
+
+    #[test]
+    fn tag_chip_overflow() {
+        let names = ["Home", "Ideas", "Work", "Books", "Music"]
+            .map(String::from)
+            .to_vec();
+
+        assert_eq!(tag_chips(&names, 3), (&names[..3], 2));
+        assert_eq!(tag_chips(&names[..3], 3), (&names[..3], 0));
+        assert_eq!(tag_chips(&names[..1], 3), (&names[..1], 0));
+        let no_names: &[String] = &[];
+        assert_eq!(tag_chips(no_names, 3), (no_names, 0));
+    }
+
+    #[test]
+    fn chip_labels() {
+        assert_eq!(elide_chip_label("Work", 16), "Work");
+        assert_eq!(elide_chip_label("Exactly sixteen!", 16), "Exactly sixteen!");
+        assert_eq!(
+            elide_chip_label("Seventeen letters", 16),
+            "Seventeen lette…"
+        );
+        // Not with a blank before the ellipsis
+        assert_eq!(elide_chip_label("Some long tag name", 6), "Some…");
+        // Cut by characters, not in the middle of the bytes of one
+        assert_eq!(elide_chip_label("🎉🎉🎉🎉", 3), "🎉🎉…");
+        assert_eq!(elide_chip_label("", 3), "");
+    }
+
+    #[test]
+    fn row_directions() {
+        use gtk::TextDirection::{Ltr, Rtl};
+
+        assert_eq!(row_direction("Groceries", "חלב"), Some(Ltr));
+        assert_eq!(row_direction("קניות", "Milk"), Some(Rtl));
+        assert_eq!(row_direction("2022 مشتريات", ""), Some(Rtl));
+        // A title without letters follows the preview
+        assert_eq!(row_direction("", "חלב ולחם"), Some(Rtl));
+        assert_eq!(row_direction("2022-04-01", "Milk"), Some(Ltr));
+        assert_eq!(row_direction("", "- [ ] 42"), None);
+    }
+
+    #[test]
+    fn preview_directions() {
+        use gtk::TextDirection::{Ltr, Rtl};
+
+        // Ellipsized at the end of its own text, whatever the title is in
+        assert_eq!(preview_direction("Milk and bread", Some(Rtl)), Some(Ltr));
+        assert_eq!(preview_direction("חלב and bread", Some(Ltr)), Some(Rtl));
+        assert_eq!(preview_direction("42", Some(Rtl)), Some(Rtl));
+        assert_eq!(preview_direction("", None), None);
+    }
+
+    #[test]
+    fn subtitles() {
+        assert_eq!(subtitle("Buy eggs\n\n".chars()), "Buy eggs");
+        assert_eq!(subtitle("a\nb\nc\nd\ne".chars()), "a\nb\nc");
+        assert_eq!(subtitle(" \n\t".chars()), "");
+        assert_eq!(subtitle("".chars()), "");
+    }
+
+    #[test]
+    fn subtitles_with_emoji() {
+        assert_eq!(subtitle("🎉 Party 🎉\n".chars()), "🎉 Party 🎉");
+        assert_eq!(subtitle("👩‍👩‍👧 Family".chars()), "👩‍👩‍👧 Family");
+
+        // Cut by characters, not in the middle of the bytes of one
+        let long = "😀".repeat(MAX_SUBTITLE_LEN + 10);
+        assert_eq!(subtitle(long.chars()), "😀".repeat(MAX_SUBTITLE_LEN));
+        let long = format!("a{}", "😀 ".repeat(MAX_SUBTITLE_LEN));
+        assert!(subtitle(long.chars()).ends_with('😀'));
     }
 }
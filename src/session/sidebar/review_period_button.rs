@@ -0,0 +1,160 @@
+use adw::subclass::prelude::*;
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::Cell;
+
+use crate::core::{DateRange, ReviewPeriod};
+
+mod imp {
+    use super::*;
+    use gtk::CompositeTemplate;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/io/github/seadve/Noteworthy/ui/sidebar-review-period-button.ui")]
+    pub struct ReviewPeriodButton {
+        #[template_child]
+        pub menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub week_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub month_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub custom_button: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub start_calendar: TemplateChild<gtk::Calendar>,
+        #[template_child]
+        pub end_calendar: TemplateChild<gtk::Calendar>,
+
+        pub period: Cell<ReviewPeriod>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReviewPeriodButton {
+        const NAME: &'static str = "NwtySidebarReviewPeriodButton";
+        type Type = super::ReviewPeriodButton;
+        type ParentType = adw::Bin;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ReviewPeriodButton {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecBoxed::new(
+                    "period",
+                    "Period",
+                    "Picked period of the review",
+                    ReviewPeriod::static_type(),
+                    glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "period" => obj.period().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_signals();
+            obj.update_label();
+        }
+    }
+
+    impl WidgetImpl for ReviewPeriodButton {}
+    impl BinImpl for ReviewPeriodButton {}
+}
+
+glib::wrapper! {
+    /// Lets the user pick which period of notes are shown in the review
+    pub struct ReviewPeriodButton(ObjectSubclass<imp::ReviewPeriodButton>)
+        @extends gtk::Widget, adw::Bin;
+}
+
+impl ReviewPeriodButton {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ReviewPeriodButton.")
+    }
+
+    pub fn period(&self) -> ReviewPeriod {
+        self.imp().period.get()
+    }
+
+    pub fn connect_period_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("period"), move |obj, _| f(obj))
+    }
+
+    fn set_period(&self, period: ReviewPeriod) {
+        if period == self.period() {
+            return;
+        }
+
+        self.imp().period.set(period);
+        self.update_label();
+        self.notify("period");
+    }
+
+    fn picked_period(&self) -> ReviewPeriod {
+        let imp = self.imp();
+
+        if imp.month_button.is_active() {
+            ReviewPeriod::ThisMonth
+        } else if imp.custom_button.is_active() {
+            ReviewPeriod::Custom(DateRange::new(
+                calendar_day(&imp.start_calendar),
+                calendar_day(&imp.end_calendar),
+            ))
+        } else {
+            ReviewPeriod::ThisWeek
+        }
+    }
+
+    fn update_label(&self) {
+        self.imp()
+            .menu_button
+            .set_label(&self.period().display_name());
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        for button in [&imp.week_button, &imp.month_button, &imp.custom_button] {
+            button.connect_toggled(clone!(@weak self as obj => move |button| {
+                // Only handle the newly active one, since the others are toggled off too
+                if button.is_active() {
+                    obj.set_period(obj.picked_period());
+                }
+            }));
+        }
+
+        for calendar in [&imp.start_calendar, &imp.end_calendar] {
+            calendar.connect_day_selected(clone!(@weak self as obj => move |_| {
+                obj.set_period(obj.picked_period());
+            }));
+        }
+    }
+}
+
+fn calendar_day(calendar: &gtk::Calendar) -> chrono::NaiveDate {
+    let date = calendar.date();
+    chrono::NaiveDate::from_ymd(date.year(), date.month() as u32, date.day_of_month() as u32)
+}
@@ -0,0 +1,153 @@
+use gettextrs::gettext;
+use gtk::{glib::clone, prelude::*};
+
+use crate::{
+    core::SearchQuery,
+    model::{SavedSearch, SavedSearchList},
+    Application,
+};
+
+/// Ask for the name and the query of a saved search, then save a new one, or change
+/// `saved_search` when it is given
+pub fn show(parent: &impl IsA<gtk::Widget>, saved_search: Option<&SavedSearch>, query: &str) {
+    let saved_search_list = Application::default()
+        .session()
+        .note_manager()
+        .saved_search_list();
+
+    let name_entry = gtk::Entry::builder()
+        .text(&saved_search.map(SavedSearch::name).unwrap_or_default())
+        .activates_default(true)
+        .build();
+    let query_entry = gtk::Entry::builder()
+        .text(query)
+        .placeholder_text("tag:work is:pinned")
+        .activates_default(true)
+        .build();
+    let error_label = gtk::Label::builder().wrap(true).xalign(0.0).build();
+    error_label.add_css_class("error");
+    error_label.add_css_class("caption");
+
+    let grid = gtk::Grid::builder()
+        .row_spacing(6)
+        .column_spacing(12)
+        .build();
+    for (row, (label, entry)) in [
+        (gettext("_Name"), &name_entry),
+        (gettext("_Query"), &query_entry),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let label = gtk::Label::builder()
+            .label(&label)
+            .use_underline(true)
+            .mnemonic_widget(entry)
+            .xalign(0.0)
+            .build();
+        entry.set_hexpand(true);
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(entry, 1, row as i32, 1, 1);
+    }
+    grid.attach(&error_label, 1, 2, 1, 1);
+
+    let title = if saved_search.is_some() {
+        gettext("Edit Saved Search")
+    } else {
+        gettext("Save Search")
+    };
+    let dialog = gtk::MessageDialog::builder()
+        .text(&title)
+        .secondary_text(&gettext(
+            "Filter with tag:, is:pinned, is:untagged, before:, and after:, like after:7d for the last week.",
+        ))
+        .modal(true)
+        .build();
+    dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+    dialog
+        .add_button(&gettext("_Save"), gtk::ResponseType::Accept)
+        .add_css_class("suggested-action");
+    dialog.set_default_response(Some(gtk::ResponseType::Accept));
+    dialog
+        .message_area()
+        .downcast::<gtk::Box>()
+        .unwrap()
+        .append(&grid);
+    dialog.set_transient_for(
+        parent
+            .root()
+            .map(|w| w.downcast::<gtk::Window>().unwrap())
+            .as_ref(),
+    );
+
+    let saved_search = saved_search.cloned();
+
+    let update_error = clone!(@weak dialog, @weak name_entry, @weak query_entry, @weak error_label, @strong saved_search, @strong saved_search_list => move || {
+        let error = validate(
+            &saved_search_list,
+            saved_search.as_ref(),
+            &name_entry.text(),
+            &query_entry.text(),
+        )
+        .err();
+
+        error_label.set_label(error.as_deref().unwrap_or_default());
+        error_label.set_visible(error.is_some());
+        dialog.set_response_sensitive(gtk::ResponseType::Accept, error.is_none());
+    });
+    update_error();
+    name_entry.connect_changed(clone!(@strong update_error => move |_| update_error()));
+    query_entry.connect_changed(move |_| update_error());
+
+    dialog.connect_response(move |dialog, response| {
+        dialog.destroy();
+
+        if response != gtk::ResponseType::Accept {
+            return;
+        }
+
+        let name = name_entry.text();
+        let name = name.trim();
+        let query = query_entry.text();
+        let query = query.trim();
+
+        let res = match saved_search {
+            Some(ref saved_search) => saved_search_list.edit(saved_search, name, query),
+            None => saved_search_list.append(SavedSearch::new(name, query)),
+        };
+
+        if let Err(err) = res {
+            log::error!("Failed to save search `{}`: {:?}", name, err);
+        }
+    });
+    dialog.present();
+}
+
+/// Why `name` and `query` can't be saved, if they can't
+fn validate(
+    saved_search_list: &SavedSearchList,
+    saved_search: Option<&SavedSearch>,
+    name: &str,
+    query: &str,
+) -> Result<(), String> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        // Shown before anything is typed, so it is not worded as an error
+        return Err(gettext("Enter a name for the search"));
+    }
+
+    let is_name_taken = saved_search_list
+        .get_with_name(name)
+        .map_or(false, |other| Some(&other) != saved_search);
+    if is_name_taken {
+        return Err(gettext("A saved search with this name already exists"));
+    }
+
+    let query = SearchQuery::parse(query).map_err(|err| err.to_string())?;
+    if query.is_empty() {
+        return Err(gettext("Enter what to search for"));
+    }
+
+    Ok(())
+}
@@ -354,6 +354,18 @@ impl Selection {
         self.notify("selected-item");
     }
 
+    /// Unselect the selected item if it is not in the model anymore. While the model changes,
+    /// like when it is filtered again, the item stays selected even when it is removed, so it
+    /// is selected at its new position if it is added back. Returns whether it was unselected.
+    pub fn unselect_item_if_removed(&self) -> bool {
+        if self.selected_item().is_none() || self.selected() != gtk::INVALID_LIST_POSITION {
+            return false;
+        }
+
+        self.set_selected_item(None);
+        true
+    }
+
     fn multi_selection_model(&self) -> gtk::MultiSelection {
         self.imp()
             .multi_selection_model
@@ -1,8 +1,22 @@
 use adw::subclass::prelude::*;
+use gettextrs::{gettext, ngettext};
 use gtk::{glib, prelude::*, subclass::prelude::*};
 
+use crate::session::tour;
+
 use std::cell::Cell;
 
+/// `↑2 ↓5` for 2 commits that are not pushed and 5 that are not pulled, leaving out the
+/// side that has none, or `None` when both are in sync
+fn divergence_label(ahead: u32, behind: u32) -> Option<String> {
+    match (ahead, behind) {
+        (0, 0) => None,
+        (ahead, 0) => Some(format!("↑{}", ahead)),
+        (0, behind) => Some(format!("↓{}", behind)),
+        (ahead, behind) => Some(format!("↑{} ↓{}", ahead, behind)),
+    }
+}
+
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
@@ -12,9 +26,17 @@ mod imp {
     #[template(resource = "/io/github/seadve/Noteworthy/ui/sync-button.ui")]
     pub struct SyncButton {
         #[template_child]
-        pub inner_button: TemplateChild<gtk::Button>,
+        pub inner_button: TemplateChild<adw::SplitButton>,
+        #[template_child]
+        pub badge: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub divergence_badge: TemplateChild<gtk::Label>,
 
         pub is_spinning: Cell<bool>,
+        pub pending_changes: Cell<u32>,
+        pub is_auth_required: Cell<bool>,
+        pub ahead: Cell<u32>,
+        pub behind: Cell<u32>,
     }
 
     #[glib::object_subclass]
@@ -50,6 +72,40 @@ mod imp {
                         false,
                         glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
+                    glib::ParamSpecUInt::new(
+                        "pending-changes",
+                        "Pending Changes",
+                        "Number of changes that are not synced yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecBoolean::new(
+                        "is-auth-required",
+                        "Is Auth Required",
+                        "Whether the remote rejected the credentials",
+                        false,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "ahead",
+                        "Ahead",
+                        "Number of commits that are not pushed yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "behind",
+                        "Behind",
+                        "Number of commits of the remote that are not pulled yet",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
                 ]
             });
             PROPERTIES.as_ref()
@@ -71,6 +127,22 @@ mod imp {
                     let is_spinning = value.get().unwrap();
                     obj.set_is_spinning(is_spinning);
                 }
+                "pending-changes" => {
+                    let pending_changes = value.get().unwrap();
+                    obj.set_pending_changes(pending_changes);
+                }
+                "is-auth-required" => {
+                    let is_auth_required = value.get().unwrap();
+                    obj.set_is_auth_required(is_auth_required);
+                }
+                "ahead" => {
+                    let ahead = value.get().unwrap();
+                    obj.set_ahead(ahead);
+                }
+                "behind" => {
+                    let behind = value.get().unwrap();
+                    obj.set_behind(behind);
+                }
                 _ => unimplemented!(),
             }
         }
@@ -79,9 +151,19 @@ mod imp {
             match pspec.name() {
                 "action-name" => self.inner_button.action_name().to_value(),
                 "is-spinning" => self.is_spinning.get().to_value(),
+                "pending-changes" => self.pending_changes.get().to_value(),
+                "is-auth-required" => self.is_auth_required.get().to_value(),
+                "ahead" => self.ahead.get().to_value(),
+                "behind" => self.behind.get().to_value(),
                 _ => unimplemented!(),
             }
         }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            tour::register_anchor(obj, tour::SYNC_ANCHOR);
+        }
     }
 
     impl WidgetImpl for SyncButton {}
@@ -110,4 +192,95 @@ impl SyncButton {
         imp.is_spinning.set(is_spinning);
         self.notify("is-spinning");
     }
+
+    pub fn set_pending_changes(&self, pending_changes: u32) {
+        let imp = self.imp();
+
+        imp.badge.set_visible(pending_changes > 0);
+        imp.badge.set_label(&pending_changes.to_string());
+
+        imp.pending_changes.set(pending_changes);
+        self.update_tooltip();
+        self.notify("pending-changes");
+    }
+
+    /// Show that the remote rejected the credentials, so syncing asks for a new one
+    pub fn set_is_auth_required(&self, is_auth_required: bool) {
+        let imp = self.imp();
+
+        // Shown by the icon too, so it doesn't rely on the color alone
+        if is_auth_required {
+            imp.inner_button.add_css_class("error");
+            imp.inner_button.set_icon_name("dialog-password-symbolic");
+        } else {
+            imp.inner_button.remove_css_class("error");
+            imp.inner_button
+                .set_icon_name("emblem-synchronizing-symbolic");
+        }
+
+        imp.is_auth_required.set(is_auth_required);
+        self.update_tooltip();
+        self.notify("is-auth-required");
+    }
+
+    /// Show how many commits are not pushed yet
+    pub fn set_ahead(&self, ahead: u32) {
+        self.imp().ahead.set(ahead);
+        self.update_divergence();
+        self.notify("ahead");
+    }
+
+    /// Show how many commits of the remote are not pulled yet
+    pub fn set_behind(&self, behind: u32) {
+        self.imp().behind.set(behind);
+        self.update_divergence();
+        self.notify("behind");
+    }
+
+    fn update_divergence(&self) {
+        let imp = self.imp();
+        let label = divergence_label(imp.ahead.get(), imp.behind.get());
+
+        imp.divergence_badge.set_visible(label.is_some());
+        imp.divergence_badge
+            .set_label(label.as_deref().unwrap_or_default());
+        self.update_tooltip();
+    }
+
+    fn update_tooltip(&self) {
+        let imp = self.imp();
+        let pending_changes = imp.pending_changes.get();
+
+        let tooltip_text = if imp.is_auth_required.get() {
+            gettext("Authentication required")
+        } else if pending_changes > 0 {
+            ngettext!(
+                "Sync ({} change not synced)",
+                "Sync ({} changes not synced)",
+                pending_changes,
+                pending_changes
+            )
+        } else {
+            gettext("Sync")
+        };
+
+        let tooltip_text = match divergence_label(imp.ahead.get(), imp.behind.get()) {
+            Some(label) => format!("{}\n{}", tooltip_text, label),
+            None => tooltip_text,
+        };
+        imp.inner_button.set_tooltip_text(Some(&tooltip_text));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn divergence() {
+        assert_eq!(divergence_label(0, 0), None);
+        assert_eq!(divergence_label(2, 0).as_deref(), Some("↑2"));
+        assert_eq!(divergence_label(0, 5).as_deref(), Some("↓5"));
+        assert_eq!(divergence_label(2, 5).as_deref(), Some("↑2 ↓5"));
+    }
 }
@@ -0,0 +1,114 @@
+//! Finding a note by typing the start of its title, like in file managers
+
+use std::time::{Duration, Instant};
+
+/// How long after the last key the typed text starts over
+const RESET_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct TypeAhead {
+    text: String,
+    last_key_time: Option<Instant>,
+}
+
+impl TypeAhead {
+    /// Add `character` to the typed text, which starts over if the last key was too long ago
+    pub fn push(&mut self, character: char, now: Instant) {
+        let is_expired = self.last_key_time.map_or(true, |last_key_time| {
+            now.duration_since(last_key_time) > RESET_TIMEOUT
+        });
+
+        if is_expired {
+            self.text.clear();
+        }
+
+        self.text.push(character);
+        self.last_key_time = Some(now);
+    }
+
+    /// Whether the next key starts the typed text over
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.last_key_time.map_or(true, |last_key_time| {
+            now.duration_since(last_key_time) > RESET_TIMEOUT
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether only one key was typed, so the next match is wanted rather than the current one
+    pub fn is_first_key(&self) -> bool {
+        self.text.chars().count() == 1
+    }
+}
+
+/// Position of the first of `titles` from `start` that starts with `prefix`, ignoring case,
+/// wrapping around at the end. Items without a title, like headers, are never matched. Unless
+/// `include_start`, the title at `start` is checked last.
+pub fn find_match(
+    titles: &[Option<String>],
+    start: usize,
+    prefix: &str,
+    include_start: bool,
+) -> Option<usize> {
+    let prefix = prefix.to_lowercase();
+    let offset = if include_start { 0 } else { 1 };
+
+    (0..titles.len())
+        .map(|index| (start + offset + index) % titles.len())
+        .find(|position| {
+            titles[*position]
+                .as_ref()
+                .map_or(false, |title| title.to_lowercase().starts_with(&prefix))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn titles() -> Vec<Option<String>> {
+        vec![
+            None,
+            Some("Groceries".into()),
+            Some("garden".into()),
+            None,
+            Some("Recipes".into()),
+            Some("Grades".into()),
+        ]
+    }
+
+    #[test]
+    fn type_ahead_text() {
+        let start = Instant::now();
+        let mut type_ahead = TypeAhead::default();
+        assert!(type_ahead.is_expired(start));
+
+        type_ahead.push('g', start);
+        assert!(!type_ahead.is_expired(start + Duration::from_millis(500)));
+        assert!(type_ahead.is_first_key());
+        type_ahead.push('r', start + Duration::from_millis(500));
+        assert_eq!(type_ahead.text(), "gr");
+        assert!(!type_ahead.is_first_key());
+
+        type_ahead.push('r', start + Duration::from_secs(2));
+        assert_eq!(type_ahead.text(), "r");
+        assert!(type_ahead.is_first_key());
+    }
+
+    #[test]
+    fn find_matches() {
+        let titles = titles();
+
+        assert_eq!(find_match(&titles, 0, "g", true), Some(1));
+        assert_eq!(find_match(&titles, 1, "G", true), Some(1));
+        assert_eq!(find_match(&titles, 1, "g", false), Some(2));
+        assert_eq!(find_match(&titles, 2, "gr", true), Some(5));
+        // Wraps around
+        assert_eq!(find_match(&titles, 5, "g", false), Some(1));
+        assert_eq!(find_match(&titles, 4, "rec", false), Some(4));
+        assert_eq!(find_match(&titles, 0, "x", true), None);
+        assert_eq!(find_match(&[], 0, "g", true), None);
+    }
+}
@@ -1,6 +1,8 @@
+use gettextrs::gettext;
 use gtk::glib;
 
-use super::Tag;
+use super::{SavedSearch, Tag};
+use crate::model::{Note, ProblemList};
 
 #[derive(Debug, Clone, glib::Boxed, PartialEq)]
 #[boxed_type(name = "NwtySidebarViewSwitcherType")]
@@ -8,9 +10,72 @@ pub enum ItemKind {
     Separator,
     Category,
     AllNotes,
+    Review,
     EditTags,
     Tag(Tag),
+    SavedSearch(SavedSearch),
     Trash,
+    /// Opens the files that failed to load, shown only while there are some
+    Problems(ProblemList),
+}
+
+impl ItemKind {
+    /// Name of the view of this kind, like `All Notes` or `#work`
+    pub fn title(&self) -> String {
+        match self {
+            Self::AllNotes => gettext("All Notes"),
+            Self::Review => gettext("Review"),
+            Self::Tag(tag) => format!("#{}", tag.name()),
+            Self::SavedSearch(saved_search) => saved_search.name(),
+            Self::Trash => gettext("Trash"),
+            // Selecting these shows all the notes instead
+            Self::Separator | Self::Category | Self::EditTags | Self::Problems(_) => {
+                gettext("All Notes")
+            }
+        }
+    }
+
+    /// Whether notes can be dropped on the row of this kind. Dropping them on a review or a
+    /// saved search can't make them part of it, so only tags, the trash, and all notes do.
+    pub fn accepts_notes(&self) -> bool {
+        matches!(self, Self::AllNotes | Self::Tag(_) | Self::Trash)
+    }
+
+    /// Drop `notes` on the row of this kind, which tags them or trashes them. Returns how
+    /// many were changed, not counting the ones that already have the tag or are trashed.
+    pub fn drop_notes(&self, notes: &[Note]) -> usize {
+        let mut n_changed = 0;
+
+        for note in notes {
+            let metadata = note.metadata();
+
+            match self {
+                Self::Tag(tag) => {
+                    let tag_list = metadata.tag_list();
+
+                    if !tag_list.contains(tag) {
+                        tag_list.append(tag.clone()).unwrap();
+                        n_changed += 1;
+                    }
+                }
+                Self::Trash => {
+                    if !metadata.is_trashed() {
+                        metadata.set_is_trashed(true);
+                        n_changed += 1;
+                    }
+                }
+                Self::AllNotes
+                | Self::Review
+                | Self::SavedSearch(_)
+                | Self::Separator
+                | Self::Category
+                | Self::EditTags
+                | Self::Problems(_) => (),
+            }
+        }
+
+        n_changed
+    }
 }
 
 impl Default for ItemKind {
@@ -18,3 +83,87 @@ impl Default for ItemKind {
         Self::AllNotes
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::run_on_gtk_thread;
+
+    #[test]
+    fn title() {
+        assert_eq!(ItemKind::AllNotes.title(), "All Notes");
+        assert_eq!(ItemKind::Review.title(), "Review");
+        assert_eq!(ItemKind::Trash.title(), "Trash");
+        assert_eq!(ItemKind::Tag(Tag::new("work")).title(), "#work");
+        assert_eq!(
+            ItemKind::SavedSearch(SavedSearch::new("Recent", "modified:week")).title(),
+            "Recent"
+        );
+
+        assert_eq!(ItemKind::Separator.title(), "All Notes");
+        assert_eq!(ItemKind::Category.title(), "All Notes");
+        assert_eq!(ItemKind::EditTags.title(), "All Notes");
+        assert_eq!(ItemKind::Problems(ProblemList::new()).title(), "All Notes");
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn drop_notes_on_tag() {
+        run_on_gtk_thread(|| {
+            let tag = Tag::new("work");
+            let notes = (0..2).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+            notes[1].metadata().tag_list().append(tag.clone()).unwrap();
+
+            let kind = ItemKind::Tag(tag.clone());
+            assert!(kind.accepts_notes());
+            assert_eq!(kind.drop_notes(&notes), 1);
+            assert!(notes
+                .iter()
+                .all(|note| note.metadata().tag_list().contains(&tag)));
+
+            // Dropping again leaves the tag once
+            assert_eq!(kind.drop_notes(&notes), 0);
+            assert_eq!(notes[0].metadata().tag_list().names(), ["work"]);
+            assert_eq!(notes[1].metadata().tag_list().names(), ["work"]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn drop_notes_on_trash() {
+        run_on_gtk_thread(|| {
+            let notes = (0..3).map(|_| Note::new("/home/user")).collect::<Vec<_>>();
+            notes[2].metadata().set_is_trashed(true);
+
+            assert!(ItemKind::Trash.accepts_notes());
+            assert_eq!(ItemKind::Trash.drop_notes(&notes), 2);
+            assert!(notes.iter().all(|note| note.metadata().is_trashed()));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn drop_notes_elsewhere() {
+        run_on_gtk_thread(|| {
+            let note = Note::new("/home/user");
+
+            assert!(ItemKind::AllNotes.accepts_notes());
+            assert_eq!(ItemKind::AllNotes.drop_notes(&[note.clone()]), 0);
+            assert!(!note.metadata().is_trashed());
+            assert!(note.metadata().tag_list().is_empty());
+
+            for kind in [
+                ItemKind::Separator,
+                ItemKind::Category,
+                ItemKind::EditTags,
+                ItemKind::Problems(ProblemList::new()),
+                ItemKind::Review,
+                ItemKind::SavedSearch(SavedSearch::new("Recent", "modified:week")),
+            ] {
+                assert!(!kind.accepts_notes());
+                assert_eq!(kind.drop_notes(&[note.clone()]), 0);
+            }
+            assert!(!note.metadata().is_trashed());
+        });
+    }
+}
@@ -1,13 +1,28 @@
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use gettextrs::ngettext;
+use gtk::{
+    gdk, gio,
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
 
 use std::cell::{Cell, RefCell};
 
-use super::{Item, ItemKind, Tag};
+use super::{Item, ItemKind, SavedSearch, Tag};
+use crate::{
+    session::{
+        delete_tag_dialog,
+        sidebar::{note_row::DraggedNotes, saved_search_dialog},
+        Session,
+    },
+    widgets::SwipeActionRow,
+    Application,
+};
 
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
-    use once_cell::sync::Lazy;
+    use once_cell::{sync::Lazy, unsync::OnceCell};
 
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/sidebar-view-switcher-item-row.ui")]
@@ -15,14 +30,28 @@ mod imp {
         #[template_child]
         pub label_child: TemplateChild<gtk::Label>,
         #[template_child]
+        pub tag_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub saved_search_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub separator_child: TemplateChild<gtk::Separator>,
         #[template_child]
         pub category_child: TemplateChild<gtk::Label>,
         #[template_child]
         pub edit_tags_child: TemplateChild<gtk::Button>,
         #[template_child]
+        pub problems_child: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub problems_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub select_icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub tag_context_menu: TemplateChild<gio::MenuModel>,
+        #[template_child]
+        pub saved_search_context_menu: TemplateChild<gio::MenuModel>,
 
+        pub tag_child: OnceCell<SwipeActionRow>,
+        pub context_menu_popover: OnceCell<gtk::PopoverMenu>,
         pub binding: RefCell<Option<glib::Binding>>,
 
         pub item: RefCell<Option<Item>>,
@@ -39,6 +68,22 @@ mod imp {
         fn class_init(klass: &mut Self::Class) {
             Self::bind_template(klass);
             klass.set_css_name("itemrow");
+
+            klass.install_action("item-row.export-tag", None, move |obj, _, _| {
+                if let Some(tag) = obj.tag() {
+                    Application::default().session().export_tag_bundle(&tag);
+                }
+            });
+
+            klass.install_action("item-row.edit-saved-search", None, move |obj, _, _| {
+                if let Some(saved_search) = obj.saved_search() {
+                    saved_search_dialog::show(obj, Some(&saved_search), &saved_search.query());
+                }
+            });
+
+            klass.install_action("item-row.delete-saved-search", None, move |obj, _, _| {
+                obj.delete_saved_search();
+            });
         }
 
         fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
@@ -105,6 +150,22 @@ mod imp {
             }
         }
 
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            let tag_child = SwipeActionRow::new();
+            tag_child.set_hexpand(true);
+            tag_child.set_child(Some(&self.tag_label.get()));
+            tag_child.set_swipe_left_icon_name(Some("user-trash-symbolic"));
+            tag_child.connect_swipe_left_action(clone!(@weak obj => move |_| {
+                obj.delete_tag();
+            }));
+            self.tag_child.set(tag_child).unwrap();
+
+            obj.setup_context_menu();
+            obj.setup_drop_target();
+        }
+
         fn dispose(&self, obj: &Self::Type) {
             while let Some(child) = obj.first_child() {
                 child.unparent();
@@ -133,6 +194,7 @@ impl ItemRow {
         let imp = self.imp();
         imp.select_icon.set_visible(selected);
         imp.selected.set(selected);
+        self.update_state(&[gtk::accessible::State::Selected(Some(selected))]);
         self.notify("selected");
     }
 
@@ -166,7 +228,7 @@ impl ItemRow {
         if let Some(item) = self.item() {
             if let Some(item) = item.downcast_ref::<Item>() {
                 match item.kind() {
-                    ItemKind::AllNotes | ItemKind::Trash => {
+                    ItemKind::AllNotes | ItemKind::Review | ItemKind::Trash => {
                         imp.label_child.set_label(&item.display_name().unwrap());
                         self.insert_before_select_icon(&imp.label_child.get());
                     }
@@ -180,15 +242,38 @@ impl ItemRow {
                     ItemKind::Separator => {
                         self.insert_before_select_icon(&imp.separator_child.get());
                     }
-                    ItemKind::Tag(_) => unreachable!("This is handled by below"),
+                    ItemKind::Problems(problem_list) => {
+                        let binding = problem_list
+                            .bind_property("n-problems", &imp.problems_label.get(), "label")
+                            .transform_to(|_, value| {
+                                let n_problems = value.get::<u32>().unwrap();
+                                let label =
+                                    ngettext!("{} Problem", "{} Problems", n_problems, n_problems);
+                                Some(label.to_value())
+                            })
+                            .flags(glib::BindingFlags::SYNC_CREATE)
+                            .build();
+                        imp.binding.replace(Some(binding));
+                        self.insert_before_select_icon(&imp.problems_child.get());
+                    }
+                    ItemKind::Tag(_) | ItemKind::SavedSearch(_) => {
+                        unreachable!("This is handled by below")
+                    }
                 }
             } else if let Some(tag) = item.downcast_ref::<Tag>() {
                 let binding = tag
-                    .bind_property("name", &imp.label_child.get(), "label")
+                    .bind_property("name", &imp.tag_label.get(), "label")
                     .flags(glib::BindingFlags::SYNC_CREATE)
                     .build();
                 imp.binding.replace(Some(binding));
-                self.insert_before_select_icon(&imp.label_child.get());
+                self.insert_before_select_icon(imp.tag_child.get().unwrap());
+            } else if let Some(saved_search) = item.downcast_ref::<SavedSearch>() {
+                let binding = saved_search
+                    .bind_property("name", &imp.saved_search_label.get(), "label")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                imp.binding.replace(Some(binding));
+                self.insert_before_select_icon(&imp.saved_search_label.get());
             } else {
                 unreachable!("Invalid row item `{:?}`", item);
             }
@@ -198,6 +283,142 @@ impl ItemRow {
         self.notify("list-row");
     }
 
+    /// Kind of the view of this row, where tags and saved searches are items of their own
+    fn kind(&self) -> Option<ItemKind> {
+        let item = self.item()?;
+
+        if let Some(item) = item.downcast_ref::<Item>() {
+            Some(item.kind())
+        } else if let Ok(tag) = item.clone().downcast::<Tag>() {
+            Some(ItemKind::Tag(tag))
+        } else {
+            item.downcast::<SavedSearch>()
+                .ok()
+                .map(ItemKind::SavedSearch)
+        }
+    }
+
+    fn accepts_notes(&self) -> bool {
+        self.kind().map_or(false, |kind| kind.accepts_notes())
+    }
+
+    /// Notes dragged from the sidebar are tagged when dropped on a tag, and trashed when
+    /// dropped on the trash. The rows of the other views refuse them.
+    fn setup_drop_target(&self) {
+        let drop_target = gtk::DropTarget::new(DraggedNotes::static_type(), gdk::DragAction::COPY);
+        drop_target.connect_accept(
+            clone!(@weak self as obj => @default-return false, move |_, drop| {
+                drop.formats().contain_type(DraggedNotes::static_type()) && obj.accepts_notes()
+            }),
+        );
+        drop_target.connect_drop(
+            clone!(@weak self as obj => @default-return false, move |_, value, _, _| {
+                let notes = match value.get::<DraggedNotes>() {
+                    Ok(DraggedNotes(notes)) => notes,
+                    Err(err) => {
+                        log::warn!("Dropped value is not notes: {:?}", err);
+                        return false;
+                    }
+                };
+
+                let kind = match obj.kind() {
+                    Some(kind) if kind.accepts_notes() => kind,
+                    _ => return false,
+                };
+
+                if !Session::default().can_change_notes() {
+                    return false;
+                }
+
+                kind.drop_notes(&notes);
+                true
+            }),
+        );
+        self.add_controller(&drop_target);
+    }
+
+    fn tag(&self) -> Option<Tag> {
+        self.item().and_then(|item| item.downcast::<Tag>().ok())
+    }
+
+    fn delete_tag(&self) {
+        let tag = match self.tag() {
+            Some(tag) => tag,
+            None => return,
+        };
+
+        if !Session::default().can_change_notes() {
+            return;
+        }
+
+        delete_tag_dialog::show(self, &tag);
+    }
+
+    fn saved_search(&self) -> Option<SavedSearch> {
+        self.item()
+            .and_then(|item| item.downcast::<SavedSearch>().ok())
+    }
+
+    fn delete_saved_search(&self) {
+        let saved_search = match self.saved_search() {
+            Some(saved_search) => saved_search,
+            None => return,
+        };
+
+        let saved_search_list = Application::default()
+            .session()
+            .note_manager()
+            .saved_search_list();
+
+        if let Err(err) = saved_search_list.remove(&saved_search) {
+            log::error!(
+                "Failed to delete saved search `{}`: {:?}",
+                saved_search.name(),
+                err
+            );
+        }
+    }
+
+    fn setup_context_menu(&self) {
+        let popover = gtk::PopoverMenu::from_model(gio::MenuModel::NONE);
+        popover.set_parent(self);
+        popover.set_has_arrow(false);
+        popover.set_halign(gtk::Align::Start);
+
+        let gesture_click = gtk::GestureClick::new();
+        gesture_click.set_button(3);
+        gesture_click.connect_pressed(clone!(@weak self as obj => move |_, _, x, y| {
+            obj.popup_context_menu_at(gdk::Rectangle::new(x as i32, y as i32, 0, 0));
+        }));
+        self.add_controller(&gesture_click);
+
+        self.imp().context_menu_popover.set(popover).unwrap();
+    }
+
+    /// Show the context menu under the row, like when opened with the keyboard. Returns
+    /// whether there is one, as only tags and saved searches have a context menu.
+    pub fn popup_context_menu(&self) -> bool {
+        self.popup_context_menu_at(gdk::Rectangle::new(0, self.height(), 0, 0))
+    }
+
+    fn popup_context_menu_at(&self, rectangle: gdk::Rectangle) -> bool {
+        let imp = self.imp();
+
+        let menu_model = if self.tag().is_some() {
+            imp.tag_context_menu.get()
+        } else if self.saved_search().is_some() {
+            imp.saved_search_context_menu.get()
+        } else {
+            return false;
+        };
+
+        let popover = imp.context_menu_popover.get().unwrap();
+        popover.set_menu_model(Some(&menu_model));
+        popover.set_pointing_to(Some(&rectangle));
+        popover.popup();
+        true
+    }
+
     fn insert_before_select_icon(&self, widget: &impl IsA<gtk::Widget>) {
         widget.insert_before(self, Some(&self.imp().select_icon.get()));
     }
@@ -1,12 +1,13 @@
 mod item;
 mod item_kind;
 mod item_row;
+mod view_title;
 
 use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::{
-    gio,
-    glib::{self, closure},
+    gdk, gio,
+    glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
 };
@@ -14,8 +15,11 @@ use gtk::{
 use std::cell::RefCell;
 
 pub use self::item_kind::ItemKind;
-use self::{item::Item, item_row::ItemRow};
-use crate::model::{Tag, TagList};
+use self::{item::Item, item_row::ItemRow, view_title::ViewTitle};
+use crate::model::{ProblemList, SavedSearch, SavedSearchList, Tag, TagList};
+
+/// Number of the items that are always shown, before the ones of the problems
+const N_FIXED_ITEMS: u32 = 9;
 
 mod imp {
     use super::*;
@@ -31,6 +35,7 @@ mod imp {
         pub list_view: TemplateChild<gtk::ListView>,
 
         pub selected_item: RefCell<Option<glib::Object>>,
+        pub view_title: ViewTitle,
     }
 
     #[glib::object_subclass]
@@ -66,12 +71,12 @@ mod imp {
                         ItemKind::static_type(),
                         glib::ParamFlags::READABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
                     ),
-                    glib::ParamSpecObject::new(
-                        "tag-list",
-                        "Tag List",
-                        "The tag list in the view switcher",
-                        TagList::static_type(),
-                        glib::ParamFlags::WRITABLE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    glib::ParamSpecString::new(
+                        "view-title",
+                        "View Title",
+                        "Name of the selected view, like `#work` for a tag",
+                        None,
+                        glib::ParamFlags::READABLE,
                     ),
                 ]
             });
@@ -90,10 +95,6 @@ mod imp {
                     let selected_item = value.get().unwrap();
                     obj.set_selected_item(selected_item);
                 }
-                "tag-list" => {
-                    let tag_list = value.get().unwrap();
-                    obj.set_tag_list(&tag_list);
-                }
                 _ => unimplemented!(),
             }
         }
@@ -102,6 +103,7 @@ mod imp {
             match pspec.name() {
                 "selected-item" => obj.selected_item().to_value(),
                 "selected-type" => obj.selected_type().to_value(),
+                "view-title" => obj.view_title().to_value(),
                 _ => unimplemented!(),
             }
         }
@@ -110,7 +112,8 @@ mod imp {
             self.parent_constructed(obj);
 
             obj.setup_list_view();
-            obj.setup_expressions();
+            obj.setup_view_title();
+            obj.setup_drag_to_open();
         }
     }
 
@@ -128,12 +131,21 @@ impl ViewSwitcher {
         glib::Object::new(&[]).expect("Failed to create ViewSwitcher.")
     }
 
-    pub fn set_tag_list(&self, tag_list: &TagList) {
-        let items: &[glib::Object; 6] = &[
+    pub fn set_lists(
+        &self,
+        tag_list: &TagList,
+        saved_search_list: &SavedSearchList,
+        problem_list: &ProblemList,
+    ) {
+        let items: &[glib::Object; N_FIXED_ITEMS as usize] = &[
             Item::builder(ItemKind::AllNotes)
                 .display_name(&gettext("All Notes"))
                 .build()
                 .upcast(),
+            Item::builder(ItemKind::Review)
+                .display_name(&gettext("Review"))
+                .build()
+                .upcast(),
             Item::builder(ItemKind::Separator).build().upcast(),
             Item::builder(ItemKind::Category)
                 .display_name(&gettext("Tags"))
@@ -142,6 +154,12 @@ impl ViewSwitcher {
                 .upcast(),
             Item::builder(ItemKind::EditTags).build().upcast(),
             Item::builder(ItemKind::Separator).build().upcast(),
+            Item::builder(ItemKind::Category)
+                .display_name(&gettext("Saved Searches"))
+                .model(saved_search_list)
+                .build()
+                .upcast(),
+            Item::builder(ItemKind::Separator).build().upcast(),
             Item::builder(ItemKind::Trash)
                 .display_name(&gettext("Trash"))
                 .build()
@@ -150,6 +168,13 @@ impl ViewSwitcher {
         let item_list = gio::ListStore::new(Item::static_type());
         item_list.splice(0, 0, items);
 
+        update_problems_items(&item_list, problem_list);
+        problem_list.connect_items_changed(
+            clone!(@weak item_list => move |problem_list, _, _, _| {
+                update_problems_items(&item_list, problem_list);
+            }),
+        );
+
         let tree_model = gtk::TreeListModel::new(&item_list, false, true, |item| {
             item.downcast_ref::<Item>().and_then(|item| item.model())
         });
@@ -168,7 +193,61 @@ impl ViewSwitcher {
             .build();
 
         self.imp().list_view.set_model(Some(&selection_model));
-        self.notify("tag-list");
+    }
+
+    pub fn select_tag(&self, tag: &Tag) {
+        let selection_model = self
+            .imp()
+            .list_view
+            .model()
+            .and_then(|model| model.downcast::<gtk::SingleSelection>().ok());
+
+        let selection_model = match selection_model {
+            Some(selection_model) => selection_model,
+            None => {
+                log::warn!("Cannot select tag `{}` without a tag list", tag.name());
+                return;
+            }
+        };
+
+        let position = (0..selection_model.n_items()).find(|position| {
+            selection_model
+                .item(*position)
+                .and_then(|row| row.downcast::<gtk::TreeListRow>().ok())
+                .and_then(|row| row.item())
+                .map_or(false, |item| item.downcast_ref::<Tag>() == Some(tag))
+        });
+
+        if let Some(position) = position {
+            selection_model.set_selected(position);
+        } else {
+            log::warn!("Tag `{}` not found in view switcher", tag.name());
+        }
+    }
+
+    /// Show all the notes, like when going back from a tag
+    pub fn select_all_notes(&self) {
+        let selection_model = self
+            .imp()
+            .list_view
+            .model()
+            .and_then(|model| model.downcast::<gtk::SingleSelection>().ok());
+
+        // All notes is the first item
+        if let Some(selection_model) = selection_model {
+            selection_model.set_selected(0);
+        }
+    }
+
+    pub fn view_title(&self) -> String {
+        self.imp().view_title.title()
+    }
+
+    pub fn connect_view_title_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("view-title"), move |obj, _| f(obj))
     }
 
     pub fn connect_selected_type_notify<F>(&self, f: F) -> glib::SignalHandlerId
@@ -183,10 +262,13 @@ impl ViewSwitcher {
             .map_or(ItemKind::AllNotes, |selected_item| {
                 if let Some(item) = selected_item.downcast_ref::<Item>() {
                     match item.kind() {
-                        ItemKind::Separator | ItemKind::Category | ItemKind::EditTags => {
+                        ItemKind::Separator
+                        | ItemKind::Category
+                        | ItemKind::EditTags
+                        | ItemKind::Problems(_) => {
                             let model: gtk::SingleSelection =
                                 self.imp().list_view.model().unwrap().downcast().unwrap();
-                            // These three get selected when trying to delete an item that was selected.
+                            // These get selected when trying to delete an item that was selected.
                             // Therefore, select the first item, AllNotes, instead. Maybe a GTK bug?
                             model.set_selected(0);
                             ItemKind::AllNotes
@@ -195,6 +277,8 @@ impl ViewSwitcher {
                     }
                 } else if let Some(tag) = selected_item.downcast_ref::<Tag>() {
                     ItemKind::Tag(tag.clone())
+                } else if let Some(saved_search) = selected_item.downcast_ref::<SavedSearch>() {
+                    ItemKind::SavedSearch(saved_search.clone())
                 } else {
                     unreachable!("Invalid selected item `{:?}`", selected_item);
                 }
@@ -205,32 +289,43 @@ impl ViewSwitcher {
         self.imp().selected_item.replace(selected_item);
         self.notify("selected-item");
         self.notify("selected-type");
+
+        self.imp().view_title.set_kind(self.selected_type());
     }
 
     fn selected_item(&self) -> Option<glib::Object> {
         self.imp().selected_item.borrow().clone()
     }
 
-    fn setup_expressions(&self) {
-        Self::this_expression("selected-item")
-            .chain_closure::<String>(closure!(|_: Self, selected_item: Option<glib::Object>| {
-                selected_item.map_or(String::new(), |selected_item| {
-                    if let Some(tag) = selected_item.downcast_ref::<Tag>() {
-                        tag.name()
-                    } else if let Some(item) = selected_item.downcast_ref::<Item>() {
-                        // FIXME The selected item is set to `EditTags` temporarily
-                        // which doesn't have `display_name`, panicking.
-                        // A solution is to handle `Separator`, `Category`, and `EditTags` being
-                        // selected directly from the binding's `transform_to`, instead of handling
-                        // it when `selected_type` is called. When that is changed, replace too
-                        // `unwrap_or_default` to just `unwrap` or `expect`.
-                        item.display_name().unwrap_or_default()
-                    } else {
-                        unreachable!("Invalid selected item `{:?}`", selected_item);
-                    }
-                })
-            }))
-            .bind(&self.imp().menu_button.get(), "label", Some(self));
+    fn setup_view_title(&self) {
+        let imp = self.imp();
+
+        imp.view_title
+            .bind_property("title", &imp.menu_button.get(), "label")
+            .flags(glib::BindingFlags::SYNC_CREATE)
+            .build();
+        imp.view_title
+            .connect_title_notify(clone!(@weak self as obj => move |_| {
+                obj.notify("view-title");
+            }));
+
+        // The label alone does not tell that it switches the view
+        imp.menu_button.connect_label_notify(|menu_button| {
+            let label = gettext!("View: {}", menu_button.label().unwrap_or_default());
+            menu_button.update_property(&[gtk::accessible::Property::Label(&label)]);
+        });
+    }
+
+    /// Open the views while something is dragged over the button, so notes can be dropped
+    /// on one
+    fn setup_drag_to_open(&self) {
+        let menu_button = self.imp().menu_button.get();
+
+        let drop_controller = gtk::DropControllerMotion::new();
+        drop_controller.connect_enter(clone!(@weak menu_button => move |_, _, _| {
+            menu_button.popup();
+        }));
+        menu_button.add_controller(&drop_controller);
     }
 
     fn setup_list_view(&self) {
@@ -262,16 +357,62 @@ impl ViewSwitcher {
 
             if let Some(item) = item {
                 match item.kind() {
-                    ItemKind::Separator | ItemKind::Category | ItemKind::EditTags => {
+                    ItemKind::Separator
+                    | ItemKind::Category
+                    | ItemKind::EditTags
+                    | ItemKind::Problems(_) => {
                         list_item.set_selectable(false);
                     }
-                    ItemKind::AllNotes | ItemKind::Tag(_) | ItemKind::Trash => (),
+                    ItemKind::AllNotes
+                    | ItemKind::Review
+                    | ItemKind::Tag(_)
+                    | ItemKind::SavedSearch(_)
+                    | ItemKind::Trash => (),
                 }
             }
         });
 
-        self.imp().list_view.set_factory(Some(&factory));
+        let list_view = self.imp().list_view.get();
+        list_view.set_factory(Some(&factory));
+
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.connect_key_pressed(
+            clone!(@weak list_view => @default-return gtk::Inhibit(false), move |_, key, _, modifier| {
+                let is_menu_key = key == gdk::Key::Menu
+                    || (key == gdk::Key::F10 && modifier.contains(gdk::ModifierType::SHIFT_MASK));
+
+                if !is_menu_key {
+                    return gtk::Inhibit(false);
+                }
+
+                let focused_row = list_view
+                    .focus_child()
+                    .and_then(|list_item| list_item.first_child())
+                    .and_then(|child| child.downcast::<ItemRow>().ok());
+                gtk::Inhibit(focused_row.map_or(false, |item_row| item_row.popup_context_menu()))
+            }),
+        );
+        list_view.add_controller(&key_controller);
 
         // FIXME popdown this popover when something is clicked
     }
 }
+
+/// Show the item of the problems at the bottom of `item_list` while there are some
+fn update_problems_items(item_list: &gio::ListStore, problem_list: &ProblemList) {
+    let is_shown = item_list.n_items() > N_FIXED_ITEMS;
+    let has_problems = problem_list.n_problems() > 0;
+
+    if has_problems && !is_shown {
+        let items: &[glib::Object; 2] = &[
+            Item::builder(ItemKind::Separator).build().upcast(),
+            Item::builder(ItemKind::Problems(problem_list.clone()))
+                .build()
+                .upcast(),
+        ];
+        item_list.splice(N_FIXED_ITEMS, 0, items);
+    } else if !has_problems && is_shown {
+        let n_removed = item_list.n_items() - N_FIXED_ITEMS;
+        item_list.splice(N_FIXED_ITEMS, n_removed, &[] as &[glib::Object]);
+    }
+}
@@ -0,0 +1,207 @@
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::RefCell;
+
+use super::ItemKind;
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct ViewTitle {
+        pub kind: RefCell<ItemKind>,
+        /// The tag or saved search of the kind, with the handler of its name changing
+        pub named_item: RefCell<Option<(glib::Object, glib::SignalHandlerId)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ViewTitle {
+        const NAME: &'static str = "NwtySidebarViewSwitcherViewTitle";
+        type Type = super::ViewTitle;
+    }
+
+    impl ObjectImpl for ViewTitle {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecBoxed::new(
+                        "kind",
+                        "Kind",
+                        "Kind of the view",
+                        ItemKind::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "title",
+                        "Title",
+                        "Name of the view",
+                        None,
+                        glib::ParamFlags::READABLE,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "kind" => {
+                    let kind = value.get().unwrap();
+                    obj.set_kind(kind);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "kind" => obj.kind().to_value(),
+                "title" => obj.title().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn dispose(&self, _obj: &Self::Type) {
+            if let Some((named_item, handler_id)) = self.named_item.take() {
+                named_item.disconnect(handler_id);
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Title of the view of a kind, which changes with the name of the tag or saved search
+    /// it shows
+    pub struct ViewTitle(ObjectSubclass<imp::ViewTitle>);
+}
+
+impl ViewTitle {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create ViewTitle.")
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        self.imp().kind.borrow().clone()
+    }
+
+    pub fn set_kind(&self, kind: ItemKind) {
+        if kind == self.kind() {
+            return;
+        }
+
+        let imp = self.imp();
+
+        if let Some((named_item, handler_id)) = imp.named_item.take() {
+            named_item.disconnect(handler_id);
+        }
+
+        let named_item = match kind {
+            ItemKind::Tag(ref tag) => Some(tag.clone().upcast::<glib::Object>()),
+            ItemKind::SavedSearch(ref saved_search) => Some(saved_search.clone().upcast()),
+            _ => None,
+        };
+
+        if let Some(named_item) = named_item {
+            let handler_id = named_item.connect_notify_local(
+                Some("name"),
+                clone!(@weak self as obj => move |_, _| {
+                    obj.notify("title");
+                }),
+            );
+            imp.named_item.replace(Some((named_item, handler_id)));
+        }
+
+        imp.kind.replace(kind);
+        self.notify("kind");
+        self.notify("title");
+    }
+
+    pub fn title(&self) -> String {
+        self.imp().kind.borrow().title()
+    }
+
+    pub fn connect_title_notify<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_notify_local(Some("title"), move |obj, _| f(obj))
+    }
+}
+
+impl Default for ViewTitle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{SavedSearch, SavedSearchList, Tag, TagList};
+
+    use std::{cell::Cell, rc::Rc};
+
+    /// Count of the times the title of `view_title` changes
+    fn title_changes(view_title: &ViewTitle) -> Rc<Cell<u32>> {
+        let n_changes = Rc::new(Cell::new(0));
+        view_title.connect_title_notify(clone!(@strong n_changes => move |_| {
+            n_changes.set(n_changes.get() + 1);
+        }));
+        n_changes
+    }
+
+    #[test]
+    fn follows_tag_rename() {
+        let tag_list = TagList::new();
+        let tag = Tag::new("work");
+        tag_list.append(tag.clone()).unwrap();
+
+        let view_title = ViewTitle::new();
+        assert_eq!(view_title.title(), "All Notes");
+
+        let n_changes = title_changes(&view_title);
+        view_title.set_kind(ItemKind::Tag(tag.clone()));
+        assert_eq!(view_title.title(), "#work");
+        assert_eq!(n_changes.get(), 1);
+
+        tag_list.rename_tag(&tag, "job").unwrap();
+        assert_eq!(view_title.title(), "#job");
+        assert_eq!(n_changes.get(), 2);
+
+        // Not shown anymore, so its name doesn't matter
+        view_title.set_kind(ItemKind::Trash);
+        assert_eq!(view_title.title(), "Trash");
+        tag_list.rename_tag(&tag, "work").unwrap();
+        assert_eq!(view_title.title(), "Trash");
+        assert_eq!(n_changes.get(), 3);
+    }
+
+    #[test]
+    fn follows_saved_search_rename() {
+        let saved_search_list = SavedSearchList::new();
+        let saved_search = SavedSearch::new("Work", "tag:work");
+        saved_search_list.append(saved_search.clone()).unwrap();
+
+        let view_title = ViewTitle::new();
+        view_title.set_kind(ItemKind::SavedSearch(saved_search.clone()));
+        assert_eq!(view_title.title(), "Work");
+
+        let n_changes = title_changes(&view_title);
+        saved_search_list
+            .edit(&saved_search, "Pinned Work", "tag:work is:pinned")
+            .unwrap();
+        assert_eq!(view_title.title(), "Pinned Work");
+        assert_eq!(n_changes.get(), 1);
+    }
+}
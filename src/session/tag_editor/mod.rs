@@ -1,6 +1,7 @@
 mod row;
 
-use adw::subclass::prelude::*;
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
 use gtk::{
     gio,
     glib::{self, clone, closure},
@@ -9,8 +10,14 @@ use gtk::{
 };
 use once_cell::unsync::OnceCell;
 
+use std::cell::RefCell;
+
 use self::row::Row;
-use crate::model::{NoteList, Tag, TagList};
+use crate::{
+    core::DeletedTagData,
+    model::{NoteList, Tag, TagList},
+    session::{delete_tag_dialog, Session},
+};
 
 mod imp {
     use super::*;
@@ -26,9 +33,14 @@ mod imp {
         pub search_entry: TemplateChild<gtk::SearchEntry>,
         #[template_child]
         pub create_tag_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub deleted_tags_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub deleted_tags_list_box: TemplateChild<gtk::ListBox>,
 
         pub tag_list: OnceCell<TagList>,
         pub note_list: OnceCell<NoteList>,
+        pub deleted_tags_handler_id: RefCell<Option<glib::SignalHandlerId>>,
     }
 
     #[glib::object_subclass]
@@ -108,6 +120,13 @@ mod imp {
             obj.action_set_enabled("tag-editor.create-tag", false);
 
             obj.setup_signals();
+            obj.setup_deleted_tags();
+        }
+
+        fn dispose(&self, _obj: &Self::Type) {
+            if let Some(handler_id) = self.deleted_tags_handler_id.take() {
+                Session::default().note_manager().disconnect(handler_id);
+            }
         }
     }
 
@@ -165,12 +184,75 @@ impl TagEditor {
         self.imp().note_list.set(note_list).unwrap();
     }
 
+    /// Move `tag` to where `target` is, returning whether it was moved
+    fn move_tag(&self, tag: &Tag, target: &Tag) -> bool {
+        let tag_list = self.tag_list();
+
+        let (from, to) = match (tag_list.get_index_of(tag), tag_list.get_index_of(target)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                log::warn!(
+                    "Cannot move tag `{}` to `{}`, one of them was removed",
+                    tag.name(),
+                    target.name()
+                );
+                return false;
+            }
+        };
+
+        if from == to {
+            return false;
+        }
+
+        if let Err(err) = tag_list.move_item(from as u32, to as u32) {
+            log::error!("Failed to move tag `{}`: {:?}", tag.name(), err);
+            return false;
+        }
+
+        true
+    }
+
+    fn show_delete_tag_dialog(&self, tag: &Tag) {
+        delete_tag_dialog::show(self, tag);
+    }
+
+    /// Show the tags that can still be restored, the last deleted first
+    fn update_deleted_tags(&self) {
+        let imp = self.imp();
+        let list_box = imp.deleted_tags_list_box.get();
+
+        while let Some(child) = list_box.first_child() {
+            list_box.remove(&child);
+        }
+
+        let deleted_tags = Session::default().note_manager().deleted_tags();
+        imp.deleted_tags_revealer
+            .set_reveal_child(!deleted_tags.is_empty());
+
+        for deleted_tag in deleted_tags.iter().rev() {
+            list_box.append(&deleted_tag_row(deleted_tag));
+        }
+    }
+
+    fn setup_deleted_tags(&self) {
+        let handler_id = Session::default()
+            .note_manager()
+            .connect_deleted_tags_changed(clone!(@weak self as obj => move |_| {
+                obj.update_deleted_tags();
+            }));
+        self.imp().deleted_tags_handler_id.replace(Some(handler_id));
+
+        self.update_deleted_tags();
+    }
+
     fn on_create_tag(&self) {
         let imp = self.imp();
         let name = imp.create_tag_entry.text();
 
-        let tag_list = self.tag_list();
-        tag_list.append(Tag::new(&name)).unwrap();
+        if let Err(err) = self.tag_list().append(Tag::new(&name)) {
+            log::error!("Failed to create tag `{}`: {:?}", name, err);
+            return;
+        }
 
         imp.create_tag_entry.set_text("");
     }
@@ -197,3 +279,34 @@ impl TagEditor {
             }));
     }
 }
+
+/// A row of a deleted tag, telling when it was deleted and from how many notes, with a button
+/// to restore it on them
+fn deleted_tag_row(deleted_tag: &DeletedTagData) -> adw::ActionRow {
+    let n_notes = deleted_tag.note_ids.len();
+    let subtitle = ngettext!(
+        "Deleted {}, from {} note",
+        "Deleted {}, from {} notes",
+        n_notes as u32,
+        deleted_tag.deleted.relative_display(),
+        n_notes
+    );
+
+    let row = adw::ActionRow::builder()
+        .title(&deleted_tag.name)
+        .subtitle(&subtitle)
+        .build();
+
+    let restore_button = gtk::Button::builder()
+        .label(&gettext("_Restore"))
+        .use_underline(true)
+        .valign(gtk::Align::Center)
+        .build();
+    let deleted_tag = deleted_tag.clone();
+    restore_button.connect_clicked(move |_| {
+        Session::default().restore_deleted_tag(&deleted_tag);
+    });
+    row.add_suffix(&restore_button);
+
+    row
+}
@@ -1,4 +1,6 @@
+use gettextrs::gettext;
 use gtk::{
+    gdk,
     glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
@@ -17,11 +19,11 @@ mod imp {
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/tag-editor-row.ui")]
     pub struct Row {
+        #[template_child]
+        pub drag_handle: TemplateChild<gtk::Image>,
         #[template_child]
         pub entry: TemplateChild<gtk::Entry>,
 
-        pub binding: RefCell<Option<glib::Binding>>,
-
         pub tag: RefCell<Option<Tag>>,
     }
 
@@ -35,16 +37,9 @@ mod imp {
             Self::bind_template(klass);
 
             klass.install_action("tag-editor-row.delete-tag", None, move |obj, _, _| {
-                let tag_editor = obj.root().unwrap().downcast::<TagEditor>().unwrap();
-                let tag_list = tag_editor.tag_list();
-                let note_list = tag_editor.note_list();
-
-                // TODO add confirmation dialog before deleting tag
-
-                let tag = obj.tag().unwrap();
-
-                tag_list.remove(&tag).unwrap();
-                note_list.remove_tag_on_all(&tag);
+                if let Some(tag) = obj.tag() {
+                    obj.tag_editor().show_delete_tag_dialog(&tag);
+                }
             });
         }
 
@@ -90,6 +85,13 @@ mod imp {
             }
         }
 
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.setup_signals();
+            obj.setup_drag_and_drop();
+        }
+
         fn dispose(&self, obj: &Self::Type) {
             while let Some(child) = obj.first_child() {
                 child.unparent();
@@ -113,31 +115,101 @@ impl Row {
     fn set_tag(&self, tag: Option<Tag>) {
         let imp = self.imp();
 
-        if let Some(binding) = imp.binding.take() {
-            binding.unbind();
-        }
+        // Set first, so changing the text below is not taken as renaming the previous tag
+        imp.tag.replace(tag.clone());
 
         if let Some(ref tag) = tag {
             imp.entry.set_text(&tag.name());
-            imp.entry
-                .connect_text_notify(clone!(@weak tag, @weak self as obj => move |entry| {
-                    let tag_list = obj.root().unwrap().downcast::<TagEditor>().unwrap().tag_list();
-                    let tag = obj.tag().unwrap();
-                    let new_name = entry.text();
-
-                    if new_name != tag.name() && tag_list.rename_tag(&tag, &new_name).is_err() {
-                        entry.add_css_class("error");
-                    } else {
-                        entry.remove_css_class("error");
-                    }
-                }));
         }
 
-        imp.tag.replace(tag);
         self.notify("tag");
     }
 
     fn tag(&self) -> Option<Tag> {
         self.imp().tag.borrow().clone()
     }
+
+    fn tag_editor(&self) -> TagEditor {
+        self.root().unwrap().downcast::<TagEditor>().unwrap()
+    }
+
+    fn on_entry_text_changed(&self) {
+        let tag = match self.tag() {
+            Some(tag) => tag,
+            None => return,
+        };
+
+        let entry = &self.imp().entry;
+        let new_name = entry.text();
+
+        if new_name == tag.name() {
+            entry.remove_css_class("error");
+            entry.set_tooltip_text(None);
+            return;
+        }
+
+        let tag_list = self.tag_editor().tag_list();
+
+        if let Err(err) = tag_list.rename_tag(&tag, &new_name) {
+            log::info!("Failed to rename tag `{}`: {:?}", tag.name(), err);
+
+            let message = if new_name.is_empty() {
+                gettext("Tag name cannot be empty")
+            } else {
+                gettext("A tag with this name already exists")
+            };
+            entry.add_css_class("error");
+            entry.set_tooltip_text(Some(&message));
+        } else {
+            entry.remove_css_class("error");
+            entry.set_tooltip_text(None);
+        }
+    }
+
+    fn setup_signals(&self) {
+        self.imp()
+            .entry
+            .connect_text_notify(clone!(@weak self as obj => move |_| {
+                obj.on_entry_text_changed();
+            }));
+    }
+
+    /// Tags are reordered by dragging their handle onto the row of another tag
+    fn setup_drag_and_drop(&self) {
+        let imp = self.imp();
+
+        imp.drag_handle.set_cursor_from_name(Some("grab"));
+
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        drag_source.connect_prepare(
+            clone!(@weak self as obj => @default-return None, move |_, _, _| {
+                obj.tag().map(|tag| gdk::ContentProvider::for_value(&tag.to_value()))
+            }),
+        );
+        drag_source.connect_drag_begin(clone!(@weak self as obj => move |drag_source, _| {
+            let paintable = gtk::WidgetPaintable::new(Some(&obj));
+            drag_source.set_icon(Some(&paintable), 0, 0);
+        }));
+        imp.drag_handle.add_controller(&drag_source);
+
+        let drop_target = gtk::DropTarget::new(Tag::static_type(), gdk::DragAction::MOVE);
+        drop_target.connect_drop(
+            clone!(@weak self as obj => @default-return false, move |_, value, _, _| {
+                let dropped_tag = match value.get::<Tag>() {
+                    Ok(tag) => tag,
+                    Err(err) => {
+                        log::warn!("Dropped value is not a tag: {:?}", err);
+                        return false;
+                    }
+                };
+
+                match obj.tag() {
+                    Some(tag) => obj.tag_editor().move_tag(&dropped_tag, &tag),
+                    None => false,
+                }
+            }),
+        );
+        self.add_controller(&drop_target);
+    }
 }
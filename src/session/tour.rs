@@ -0,0 +1,289 @@
+use gettextrs::gettext;
+use gtk::{
+    glib::{self, clone},
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use crate::Application;
+
+pub const CREATE_NOTE_ANCHOR: &str = "create-note-button";
+pub const VIEW_SWITCHER_ANCHOR: &str = "view-switcher";
+pub const PIN_ANCHOR: &str = "pin-button";
+pub const SYNC_ANCHOR: &str = "sync-button";
+
+const HIGHLIGHT_CSS_CLASS: &str = "tour-highlight";
+
+thread_local! {
+    static ANCHORS: RefCell<HashMap<&'static str, glib::WeakRef<gtk::Widget>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Let the tour point at `widget` by the `anchor` name in its steps
+pub fn register_anchor(widget: &impl IsA<gtk::Widget>, anchor: &'static str) {
+    ANCHORS.with(|anchors| {
+        anchors
+            .borrow_mut()
+            .insert(anchor, widget.upcast_ref::<gtk::Widget>().downgrade());
+    });
+}
+
+fn anchor_widget(anchor: &str) -> Option<gtk::Widget> {
+    ANCHORS.with(|anchors| anchors.borrow().get(anchor).and_then(|weak| weak.upgrade()))
+}
+
+#[derive(Debug)]
+struct TourStep {
+    anchor: &'static str,
+    title: String,
+    description: String,
+}
+
+fn steps() -> Vec<TourStep> {
+    vec![
+        TourStep {
+            anchor: CREATE_NOTE_ANCHOR,
+            title: gettext("Create Notes"),
+            description: gettext("Start a new note here. It is saved as you write."),
+        },
+        TourStep {
+            anchor: VIEW_SWITCHER_ANCHOR,
+            title: gettext("Switch Views"),
+            description: gettext("Show all notes, the trash, or only the notes with a tag."),
+        },
+        TourStep {
+            anchor: PIN_ANCHOR,
+            title: gettext("Pin Notes"),
+            description: gettext("Keep the notes you need the most at the top of the list."),
+        },
+        TourStep {
+            anchor: SYNC_ANCHOR,
+            title: gettext("Sync"),
+            description: gettext(
+                "Your notes are synced as you go, or right away with this button. The badge counts the changes that are not synced yet.",
+            ),
+        },
+    ]
+}
+
+/// Index of the first step from `start` that can be shown
+fn next_shown_step(
+    anchors: &[&str],
+    start: usize,
+    is_shown: impl Fn(&str) -> bool,
+) -> Option<usize> {
+    anchors
+        .iter()
+        .enumerate()
+        .skip(start)
+        .find(|(_, anchor)| is_shown(anchor))
+        .map(|(index, _)| index)
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct TourController {
+        pub steps: RefCell<Vec<TourStep>>,
+        pub current_step: Cell<Option<usize>>,
+        pub popover: RefCell<Option<gtk::Popover>>,
+        pub highlighted: RefCell<Option<gtk::Widget>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TourController {
+        const NAME: &'static str = "NwtyTourController";
+        type Type = super::TourController;
+    }
+
+    impl ObjectImpl for TourController {
+        fn dispose(&self, obj: &Self::Type) {
+            obj.hide_step();
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Goes through the steps of the tour, pointing at the widgets registered with
+    /// [`register_anchor`]
+    pub struct TourController(ObjectSubclass<imp::TourController>);
+}
+
+impl TourController {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create TourController.")
+    }
+
+    /// Start from the first step, even if the tour was already done
+    pub fn start(&self) {
+        self.hide_step();
+        self.imp().steps.replace(steps());
+        self.show_step_from(0);
+    }
+
+    /// Stop the tour, so it is not started automatically again
+    pub fn finish(&self) {
+        self.hide_step();
+        self.imp().current_step.set(None);
+
+        if let Err(err) = Application::default()
+            .settings()
+            .set_boolean("is-tour-done", true)
+        {
+            log::warn!("Failed to save that the tour is done: {:?}", err);
+        }
+    }
+
+    fn next(&self) {
+        let next_step = self.imp().current_step.get().map_or(0, |index| index + 1);
+        self.hide_step();
+        self.show_step_from(next_step);
+    }
+
+    /// Show the first step from `start` whose widget is shown, or finish if there is none
+    fn show_step_from(&self, start: usize) {
+        let imp = self.imp();
+
+        let anchors = imp
+            .steps
+            .borrow()
+            .iter()
+            .map(|step| step.anchor)
+            .collect::<Vec<_>>();
+        // Hidden widgets, like the pin button in compact mode, are skipped
+        let is_shown =
+            |anchor: &str| anchor_widget(anchor).map_or(false, |widget| widget.is_mapped());
+
+        let (index, widget) = match next_shown_step(&anchors, start, is_shown)
+            .and_then(|index| Some((index, anchor_widget(anchors[index])?)))
+        {
+            Some(shown_step) => shown_step,
+            None => {
+                self.finish();
+                return;
+            }
+        };
+        let is_last = next_shown_step(&anchors, index + 1, is_shown).is_none();
+
+        imp.current_step.set(Some(index));
+
+        let popover = self.build_popover(index, anchors.len(), is_last);
+        popover.set_parent(&widget);
+        widget.add_css_class(HIGHLIGHT_CSS_CLASS);
+
+        imp.highlighted.replace(Some(widget));
+        imp.popover.replace(Some(popover.clone()));
+
+        popover.popup();
+    }
+
+    fn hide_step(&self) {
+        let imp = self.imp();
+
+        // Taken first, so closing it is not handled as skipping the tour
+        if let Some(popover) = imp.popover.take() {
+            popover.popdown();
+            popover.unparent();
+        }
+
+        if let Some(widget) = imp.highlighted.take() {
+            widget.remove_css_class(HIGHLIGHT_CSS_CLASS);
+        }
+    }
+
+    fn build_popover(&self, index: usize, n_steps: usize, is_last: bool) -> gtk::Popover {
+        let steps = self.imp().steps.borrow();
+        let step = &steps[index];
+
+        let title_label = gtk::Label::builder()
+            .label(&step.title)
+            .xalign(0.0)
+            .css_classes(vec!["heading".into()])
+            .build();
+        let description_label = gtk::Label::builder()
+            .label(&step.description)
+            .xalign(0.0)
+            .wrap(true)
+            .max_width_chars(36)
+            .build();
+        let progress_label = gtk::Label::builder()
+            .label(&gettext!("{} of {}", index + 1, n_steps))
+            .hexpand(true)
+            .xalign(0.0)
+            .css_classes(vec!["caption".into(), "dim-label".into()])
+            .build();
+
+        let skip_button = gtk::Button::builder()
+            .label(&gettext("_Skip"))
+            .use_underline(true)
+            .visible(!is_last)
+            .build();
+        skip_button.connect_clicked(clone!(@weak self as obj => move |_| {
+            obj.finish();
+        }));
+
+        let next_button = gtk::Button::builder()
+            .label(&if is_last {
+                gettext("_Done")
+            } else {
+                gettext("_Next")
+            })
+            .use_underline(true)
+            .css_classes(vec!["suggested-action".into()])
+            .build();
+        next_button.connect_clicked(clone!(@weak self as obj => move |_| {
+            obj.next();
+        }));
+
+        let button_box = gtk::Box::builder().spacing(6).build();
+        button_box.append(&progress_label);
+        button_box.append(&skip_button);
+        button_box.append(&next_button);
+
+        let content_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .css_classes(vec!["tour-popover".into()])
+            .build();
+        content_box.append(&title_label);
+        content_box.append(&description_label);
+        content_box.append(&button_box);
+
+        let popover = gtk::Popover::builder()
+            .child(&content_box)
+            .autohide(false)
+            .default_widget(&next_button)
+            .build();
+
+        // Closed with Escape
+        popover.connect_closed(clone!(@weak self as obj => move |popover| {
+            if obj.imp().popover.borrow().as_ref() == Some(popover) {
+                obj.finish();
+            }
+        }));
+
+        popover
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn next_shown_step_skips_hidden() {
+        let anchors = [CREATE_NOTE_ANCHOR, PIN_ANCHOR, SYNC_ANCHOR];
+        let is_shown = |anchor: &str| anchor != PIN_ANCHOR;
+
+        assert_eq!(next_shown_step(&anchors, 0, is_shown), Some(0));
+        assert_eq!(next_shown_step(&anchors, 1, is_shown), Some(2));
+        assert_eq!(next_shown_step(&anchors, 3, is_shown), None);
+        assert_eq!(next_shown_step(&anchors, 0, |_| false), None);
+    }
+}
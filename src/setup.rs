@@ -1,4 +1,5 @@
 use adw::prelude::*;
+use gettextrs::gettext;
 use gtk::{
     gio,
     glib::{self, clone},
@@ -8,13 +9,19 @@ use num_enum::TryFromPrimitive;
 
 use std::{cell::RefCell, convert::TryFrom};
 
-use crate::{core::NoteRepository, session::Session, spawn, utils};
+use crate::{
+    core::{NoteRepository, VaultImportOptions},
+    session::Session,
+    spawn, utils,
+    window::Window,
+    Application,
+};
 
 mod imp {
     use super::*;
     use glib::subclass::Signal;
     use gtk::CompositeTemplate;
-    use once_cell::sync::Lazy;
+    use once_cell::{sync::Lazy, unsync::OnceCell};
 
     #[repr(u8)]
     #[derive(Debug, PartialEq, TryFromPrimitive)]
@@ -38,6 +45,7 @@ mod imp {
         pub provider: Option<GitHost>,
         pub is_automatic: Option<bool>,
         pub clone_url: Option<String>,
+        pub is_read_only: bool,
     }
 
     #[derive(Debug, Default, CompositeTemplate)]
@@ -59,8 +67,20 @@ mod imp {
         // create repo page
         #[template_child]
         pub clone_url_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub is_read_only_switch: TemplateChild<gtk::Switch>,
+
+        // import vault page
+        #[template_child]
+        pub vault_folder_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub keep_hashtags_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub folders_as_tags_switch: TemplateChild<gtk::Switch>,
 
         pub config: RefCell<SetupConfig>,
+        pub vault_folder: RefCell<Option<gio::File>>,
+        pub vault_folder_chooser: OnceCell<gtk::FileChooserNative>,
     }
 
     #[glib::object_subclass]
@@ -92,6 +112,15 @@ mod imp {
                 obj.imp().content.set_visible_child_name("select-provider");
             });
 
+            klass.install_action("setup.setup-vault-import", None, move |obj, _, _| {
+                obj.imp().content.set_visible_child_name("import-vault");
+                obj.update_vault_import_forward();
+            });
+
+            klass.install_action("setup.select-vault-folder", None, move |obj, _, _| {
+                obj.vault_folder_chooser().show();
+            });
+
             // klass.install_action("setup.enter-repo-url", None, move |obj, _, _| {
             //     let imp = obj.imp();
             //     let repo_url = imp.repo_url_entry.text();
@@ -120,12 +149,20 @@ mod imp {
     impl ObjectImpl for Setup {
         fn signals() -> &'static [Signal] {
             static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-                vec![Signal::builder(
-                    "session-setup-done",
-                    &[Session::static_type().into()],
-                    <()>::static_type().into(),
-                )
-                .build()]
+                vec![
+                    Signal::builder(
+                        "session-setup-done",
+                        &[Session::static_type().into()],
+                        <()>::static_type().into(),
+                    )
+                    .build(),
+                    Signal::builder(
+                        "clone-requested",
+                        &[String::static_type().into()],
+                        <()>::static_type().into(),
+                    )
+                    .build(),
+                ]
             });
             SIGNALS.as_ref()
         }
@@ -183,6 +220,19 @@ impl Setup {
         })
     }
 
+    /// Called with the URL of the repository to clone the notes from once it is entered
+    pub fn connect_clone_requested<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, String) + 'static,
+    {
+        self.connect_local("clone-requested", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            let remote_url = values[1].get::<String>().unwrap();
+            f(&obj, remote_url);
+            None
+        })
+    }
+
     async fn setup_offline_session(&self) -> Session {
         let notes_folder = gio::File::for_path(&utils::default_notes_dir());
         if let Err(err) = notes_folder
@@ -193,7 +243,14 @@ impl Setup {
             log::error!("Failed to create note folder: {:?}", err);
         }
 
-        Session::new_offline(&notes_folder).await
+        if let Err(err) = Application::default()
+            .settings()
+            .set_boolean("is-offline-mode", true)
+        {
+            log::error!("Failed to set is-offline-mode setting: {:?}", err);
+        }
+
+        Session::new_offline(&notes_folder)
     }
 
     fn navigate_forward(&self) {
@@ -210,6 +267,9 @@ impl Setup {
             "create-repo" => {
                 self.create_repo();
             }
+            "import-vault" => {
+                self.import_vault();
+            }
             other => unreachable!("Invalid page name `{}`", other),
         }
     }
@@ -219,7 +279,7 @@ impl Setup {
         let visible_page_name = imp.content.visible_child_name().unwrap();
 
         match visible_page_name.as_str() {
-            "select-provider" => {
+            "select-provider" | "import-vault" => {
                 imp.content.set_visible_child_name("welcome");
             }
             "create-repo" => {
@@ -244,14 +304,94 @@ impl Setup {
 
     fn create_repo(&self) {
         let imp = self.imp();
-        let mut config = imp.config.borrow_mut();
-
         let clone_url = imp.clone_url_entry.text();
-        config.clone_url = Some(clone_url.to_string());
 
         if !NoteRepository::validate_remote_url(&clone_url) {
             log::warn!("Invalid remote url `{}`", clone_url);
+            return;
+        }
+
+        {
+            let mut config = imp.config.borrow_mut();
+            config.clone_url = Some(clone_url.to_string());
+
+            config.is_read_only = imp.is_read_only_switch.is_active();
+            if let Err(err) = Application::default()
+                .settings()
+                .set_boolean("is-read-only", config.is_read_only)
+            {
+                log::error!("Failed to set is-read-only setting: {:?}", err);
+            }
         }
+
+        self.emit_by_name::<()>("clone-requested", &[&clone_url.to_string()]);
+    }
+
+    fn update_vault_import_forward(&self) {
+        let has_vault_folder = self.imp().vault_folder.borrow().is_some();
+        self.action_set_enabled("setup.navigate-forward", has_vault_folder);
+    }
+
+    fn vault_folder_chooser(&self) -> &gtk::FileChooserNative {
+        self.imp().vault_folder_chooser.get_or_init(|| {
+            let chooser = gtk::FileChooserNative::builder()
+                .accept_label(&gettext("Select"))
+                .cancel_label(&gettext("Cancel"))
+                .title(&gettext("Select Vault Folder"))
+                .action(gtk::FileChooserAction::SelectFolder)
+                .modal(true)
+                .build();
+
+            chooser.set_transient_for(
+                self.root()
+                    .map(|w| w.downcast::<gtk::Window>().unwrap())
+                    .as_ref(),
+            );
+
+            chooser.connect_response(clone!(@weak self as obj => move |chooser, response| {
+                if response == gtk::ResponseType::Accept {
+                    if let Some(folder) = chooser.file() {
+                        obj.imp().vault_folder_row.set_subtitle(&folder.parse_name());
+                        obj.imp().vault_folder.replace(Some(folder));
+                        obj.update_vault_import_forward();
+                    }
+                }
+            }));
+
+            chooser
+        })
+    }
+
+    /// Store the notes locally, then import the vault into them once the session is loaded
+    fn import_vault(&self) {
+        let imp = self.imp();
+
+        let folder = match imp.vault_folder.borrow().clone() {
+            Some(folder) => folder,
+            None => {
+                log::warn!("No vault folder selected");
+                return;
+            }
+        };
+        let options = VaultImportOptions {
+            keep_hashtags: imp.keep_hashtags_switch.is_active(),
+            folders_as_tags: imp.folders_as_tags_switch.is_active(),
+        };
+
+        // Clicking again while the session is set up would import it twice
+        self.action_set_enabled("setup.navigate-forward", false);
+
+        spawn!(clone!(@weak self as obj => async move {
+            let new_session = obj.setup_offline_session().await;
+
+            if let Some(window) = obj.root().and_then(|root| root.downcast::<Window>().ok()) {
+                window.run_with_session(move |session| session.import_vault(&folder, options));
+            } else {
+                log::error!("Setup is not in a window, not importing vault");
+            }
+
+            obj.emit_by_name::<()>("session-setup-done", &[&new_session]);
+        }));
     }
 }
 
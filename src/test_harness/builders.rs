@@ -0,0 +1,201 @@
+use chrono::NaiveDate;
+use serde_yaml::Value;
+
+use std::path::PathBuf;
+
+use crate::{
+    core::{AttachmentData, DateTime, MetadataData, NoteColor, Reminder},
+    model::{NoteFlags, NoteIndex, Tag, TagList},
+};
+
+/// Last modified date of the built metadata, fixed so what is written doesn't change between
+/// runs of the tests
+const LAST_MODIFIED: &str = "2022-01-02T02:00:00+00:00";
+
+fn date_time(text: &str) -> DateTime {
+    DateTime::parse(text).unwrap_or_else(|| panic!("Invalid date `{}`", text))
+}
+
+/// Builds the [`MetadataData`] of a note, with every field empty or unset unless it is given
+#[derive(Debug, Clone)]
+pub struct MetadataBuilder {
+    data: MetadataData,
+}
+
+impl MetadataBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            data: MetadataData {
+                title: title.to_string(),
+                last_modified: date_time(LAST_MODIFIED),
+                ..MetadataData::default()
+            },
+        }
+    }
+
+    pub fn tags(mut self, names: &[&str]) -> Self {
+        self.data.tag_list = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    pub fn attachment(mut self, file: &str, title: &str, created: &str) -> Self {
+        self.data.attachment_list.push(AttachmentData {
+            file: PathBuf::from(file),
+            created: date_time(created),
+            title: title.to_string(),
+        });
+        self
+    }
+
+    pub fn last_modified(mut self, last_modified: &str) -> Self {
+        self.data.last_modified = date_time(last_modified);
+        self
+    }
+
+    pub fn pinned(mut self) -> Self {
+        self.data.is_pinned = true;
+        self
+    }
+
+    pub fn trashed(mut self) -> Self {
+        self.data.is_trashed = true;
+        self
+    }
+
+    pub fn color(mut self, color: NoteColor) -> Self {
+        self.data.color = color;
+        self
+    }
+
+    pub fn reminder(mut self, reminder: &str) -> Self {
+        let reminder = chrono::DateTime::parse_from_rfc3339(reminder).unwrap();
+        self.data.reminder = Some(Reminder::new(reminder.into()));
+        self
+    }
+
+    pub fn gist_id(mut self, gist_id: &str) -> Self {
+        self.data.gist_id = Some(gist_id.to_string());
+        self
+    }
+
+    pub fn language(mut self, language: &str) -> Self {
+        self.data.language = Some(language.to_string());
+        self
+    }
+
+    pub fn created(mut self, created: &str) -> Self {
+        self.data.created = Some(date_time(created));
+        self
+    }
+
+    /// A field of another app, kept as is
+    pub fn extra_field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.data.extra_fields.0.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> MetadataData {
+        self.data
+    }
+
+    /// Metadata with no field set, with each field set on its own, and with all of them set,
+    /// named as they are in snapshots
+    pub fn permutations() -> Vec<(&'static str, MetadataData)> {
+        let builder = || Self::new("Title");
+
+        let permutations = [
+            ("none", builder()),
+            ("no_title", Self::new("")),
+            ("tags", builder().tags(&["Work", "Home"])),
+            (
+                "attachment",
+                builder().attachment("/home/user/Picture.png", "Picture", "2022-01-01T07:30:00Z"),
+            ),
+            ("pinned", builder().pinned()),
+            ("trashed", builder().trashed()),
+            ("pinned_and_trashed", builder().pinned().trashed()),
+            ("color", builder().color(NoteColor::Purple)),
+            ("reminder", builder().reminder("2022-01-03T01:30:00Z")),
+            ("gist_id", builder().gist_id("aa5a315d61ae9438b18d")),
+            ("language", builder().language("yaml")),
+            ("created", builder().created("2021-12-31T00:00:00Z")),
+            (
+                "extra_fields",
+                builder()
+                    .extra_field("author", "Someone")
+                    .extra_field("draft", true),
+            ),
+            (
+                "all",
+                builder()
+                    .tags(&["Work", "Home"])
+                    .attachment("/home/user/Picture.png", "Picture", "2022-01-01T07:30:00Z")
+                    .last_modified("2022-01-02T10:00:00.5+08:00")
+                    .pinned()
+                    .trashed()
+                    .color(NoteColor::Purple)
+                    .reminder("2022-01-03T01:30:00Z")
+                    .gist_id("aa5a315d61ae9438b18d")
+                    .language("yaml")
+                    .created("2021-12-31T00:00:00Z")
+                    .extra_field("author", "Someone")
+                    .extra_field("draft", true),
+            ),
+        ];
+
+        permutations
+            .into_iter()
+            .map(|(name, builder)| (name, builder.build()))
+            .collect()
+    }
+}
+
+/// Builds the [`NoteIndex`] of a note that filters check, without a
+/// [`Note`](crate::model::Note), whose buffer needs GTK to be initialized
+#[derive(Debug)]
+pub struct NoteIndexBuilder {
+    index: NoteIndex,
+}
+
+impl NoteIndexBuilder {
+    /// Last edited on 2022-03-01, and neither pinned nor trashed
+    pub fn new(title: &str) -> Self {
+        Self {
+            index: NoteIndex {
+                flags: NoteFlags::empty(),
+                tags: Default::default(),
+                title: title.to_lowercase(),
+                last_modified_day: NaiveDate::from_ymd(2022, 3, 1),
+            },
+        }
+    }
+
+    pub fn tag(mut self, tag: &Tag) -> Self {
+        self.index.tags.insert(tag.clone());
+        self
+    }
+
+    pub fn pinned(mut self, is_pinned: bool) -> Self {
+        self.index.flags.set(NoteFlags::PINNED, is_pinned);
+        self
+    }
+
+    pub fn trashed(mut self, is_trashed: bool) -> Self {
+        self.index.flags.set(NoteFlags::TRASHED, is_trashed);
+        self
+    }
+
+    pub fn last_modified_day(mut self, day: NaiveDate) -> Self {
+        self.index.last_modified_day = day;
+        self
+    }
+
+    pub fn build(self) -> NoteIndex {
+        self.index
+    }
+}
+
+/// A [`TagList`] of new tags with `names`, in that order
+pub fn tag_list(names: &[&str]) -> TagList {
+    names.iter().copied().map(Tag::new).collect()
+}
@@ -0,0 +1,63 @@
+use gtk::glib;
+use once_cell::sync::Lazy;
+
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Sender of the jobs run on the thread where GTK is initialized, which is started with the
+/// first of them
+static GTK_THREAD: Lazy<Mutex<mpsc::Sender<Job>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel::<Job>();
+
+    thread::Builder::new()
+        .name("gtk".to_string())
+        .spawn(move || {
+            for job in receiver {
+                job();
+            }
+        })
+        .expect("Failed to spawn the GTK thread");
+
+    Mutex::new(sender)
+});
+
+/// Run `f` on the one thread where GTK is initialized, and wait for it. GTK can only be
+/// initialized on a single thread, while each test runs on its own, so every test that
+/// creates a [`Note`](crate::model::Note) or a widget goes through this. A panic of `f`, like
+/// a failed assertion, fails the calling test.
+pub fn run_on_gtk_thread(f: impl FnOnce() + Send + 'static) {
+    let (result_sender, result_receiver) = mpsc::channel();
+
+    let job = Box::new(move || {
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            gtk::init().expect("Failed to initialize GTK, the tests need a display");
+            f();
+        }));
+        let _ = result_sender.send(res);
+    });
+
+    GTK_THREAD
+        .lock()
+        .unwrap()
+        .send(job)
+        .expect("The GTK thread stopped");
+
+    if let Err(payload) = result_receiver.recv().expect("The GTK thread stopped") {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Like [`run_on_gtk_thread`], running the future made by `f` there to completion in a new
+/// main context
+pub fn block_on_gtk_thread<F>(f: impl FnOnce() -> F + Send + 'static)
+where
+    F: Future<Output = ()>,
+{
+    run_on_gtk_thread(move || glib::MainContext::new().block_on(f()));
+}
@@ -0,0 +1,57 @@
+//! Helpers shared by the tests of every module: the files in `tests/fixtures`, snapshots of
+//! what is written in `tests/snapshots`, and builders of the data that is checked. None of
+//! them needs a display, so the tests that only use them run headless. The ones that need GTK
+//! run on the thread of [`run_on_gtk_thread`] or [`block_on_gtk_thread`].
+
+mod builders;
+mod gtk_thread;
+mod snapshot;
+
+pub use self::{
+    builders::{tag_list, MetadataBuilder, NoteIndexBuilder},
+    gtk_thread::{block_on_gtk_thread, run_on_gtk_thread},
+    snapshot::snapshot_settings,
+};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Folder of the files of the tests, which are described in its `fixtures/README.md`
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// The text of the fixture at `path`, relative to `tests/fixtures`
+pub fn fixture(path: &str) -> String {
+    let path = tests_dir().join("fixtures").join(path);
+    fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Failed to read fixture `{}`: {}", path.display(), err))
+}
+
+/// The names, without their extension, and texts of the fixtures in the folder `dir` of
+/// `tests/fixtures`, sorted by name
+pub fn fixtures(dir: &str) -> Vec<(String, String)> {
+    let dir_path = tests_dir().join("fixtures").join(dir);
+    let entries = fs::read_dir(&dir_path).unwrap_or_else(|err| {
+        panic!(
+            "Failed to read fixtures in `{}`: {}",
+            dir_path.display(),
+            err
+        )
+    });
+
+    let mut fixtures = entries
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fixture(&format!("{}/{}", dir, file_name)))
+        })
+        .collect::<Vec<_>>();
+    fixtures.sort();
+
+    assert!(!fixtures.is_empty(), "No fixtures in `{}`", dir);
+    fixtures
+}
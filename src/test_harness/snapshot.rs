@@ -0,0 +1,11 @@
+use super::tests_dir;
+
+/// Settings of `insta` that keep the snapshots in `tests/snapshots`, with the name given to
+/// `insta::assert_snapshot!` as their file name. The assertion must run in
+/// [`insta::Settings::bind`] for them to be used.
+pub fn snapshot_settings() -> insta::Settings {
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_path(tests_dir().join("snapshots"));
+    settings.set_prepend_module_to_snapshot(false);
+    settings
+}
@@ -0,0 +1,254 @@
+use anyhow::Context;
+use futures_channel::oneshot;
+use gettextrs::gettext;
+use gtk::{
+    gio,
+    glib::{self, VariantTy},
+    prelude::*,
+};
+
+use std::{cell::RefCell, fs, io, path::PathBuf};
+
+use super::file_manager::is_sandboxed;
+use crate::{command_line::BACKGROUND_OPTION, config::APP_ID};
+
+const PORTAL_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const BACKGROUND_INTERFACE: &str = "org.freedesktop.portal.Background";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// Ask if the app may keep running without its windows, and start it on login if
+/// `is_autostart`. Returns whether it is allowed.
+///
+/// In the sandbox, this is asked to the Background portal. Outside of it, nothing stops the app
+/// from running, and it is only started on login with a desktop file in the autostart folder.
+pub async fn request_background(is_autostart: bool) -> anyhow::Result<bool> {
+    if is_sandboxed() {
+        return request_portal(is_autostart).await;
+    }
+
+    set_autostart_file(is_autostart)?;
+    Ok(true)
+}
+
+/// Stop starting the app on login, after it no longer runs in the background
+pub async fn remove_autostart() -> anyhow::Result<()> {
+    if is_sandboxed() {
+        // The autostart file of the portal is outside of the sandbox, so it is asked again
+        // without it
+        request_portal(false).await?;
+        return Ok(());
+    }
+
+    set_autostart_file(false)
+}
+
+async fn request_portal(is_autostart: bool) -> anyhow::Result<bool> {
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+    let unique_name = connection
+        .unique_name()
+        .context("No unique name on the session bus")?;
+
+    // The response is sent to the request object, whose path is known from the token, so it
+    // can be subscribed to before the request is made
+    let token = format!("noteworthy{}", glib::random_int());
+    let handle = request_handle(&unique_name, &token);
+
+    let (response_sender, response_receiver) = oneshot::channel();
+    let response_sender = RefCell::new(Some(response_sender));
+    let subscription_id = connection.signal_subscribe(
+        Some(PORTAL_BUS_NAME),
+        Some(REQUEST_INTERFACE),
+        Some("Response"),
+        Some(&handle),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, parameters| {
+            if let Some(response_sender) = response_sender.take() {
+                let _ = response_sender.send(parameters.clone());
+            }
+        },
+    );
+
+    let options = glib::VariantDict::new(None);
+    options.insert_value("handle_token", &token.to_variant());
+    options.insert_value(
+        "reason",
+        &gettext("Reminders and syncing keep running while the window is closed").to_variant(),
+    );
+    options.insert_value("autostart", &is_autostart.to_variant());
+    options.insert_value(
+        "commandline",
+        &vec!["noteworthy".to_string(), format!("--{}", BACKGROUND_OPTION)].to_variant(),
+    );
+    options.insert_value("dbus-activatable", &false.to_variant());
+
+    let res = connection
+        .call_future(
+            Some(PORTAL_BUS_NAME),
+            PORTAL_OBJECT_PATH,
+            BACKGROUND_INTERFACE,
+            "RequestBackground",
+            Some(&("", options).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await;
+
+    let response = match res {
+        Ok(_) => response_receiver.await,
+        Err(err) => {
+            connection.signal_unsubscribe(subscription_id);
+            return Err(err.into());
+        }
+    };
+    connection.signal_unsubscribe(subscription_id);
+
+    let parameters = response.context("Background portal did not respond")?;
+    let response_code = parameters
+        .child_value(0)
+        .get::<u32>()
+        .context("Invalid response of the Background portal")?;
+    let results = glib::VariantDict::new(Some(&parameters.child_value(1)));
+
+    Ok(is_background_granted(response_code, &results))
+}
+
+/// Path of the object of the portal request made with `token`
+fn request_handle(unique_name: &str, token: &str) -> String {
+    let sender = unique_name.trim_start_matches(':').replace('.', "_");
+    format!("{}/request/{}/{}", PORTAL_OBJECT_PATH, sender, token)
+}
+
+/// Whether the response of `RequestBackground` allows running in the background. Any other
+/// response code than 0 means the request was cancelled or failed.
+fn is_background_granted(response_code: u32, results: &glib::VariantDict) -> bool {
+    response_code == 0
+        && results
+            .lookup_value("background", Some(VariantTy::BOOLEAN))
+            .and_then(|value| value.get::<bool>())
+            .unwrap_or(false)
+}
+
+fn autostart_file_path() -> PathBuf {
+    let mut path = glib::user_config_dir();
+    path.push("autostart");
+    path.push(format!("{}.desktop", APP_ID));
+    path
+}
+
+fn set_autostart_file(is_autostart: bool) -> anyhow::Result<()> {
+    let path = autostart_file_path();
+
+    if !is_autostart {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Failed to remove `{}`", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    let exec = std::env::current_exe().context("Failed to get the path of the app")?;
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, autostart_desktop_entry(&exec.to_string_lossy()))
+        .with_context(|| format!("Failed to write `{}`", path.display()))?;
+    log::info!("Wrote autostart file `{}`", path.display());
+
+    Ok(())
+}
+
+/// Desktop entry that starts `exec` in the background on login
+fn autostart_desktop_entry(exec: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Noteworthy\n\
+         Icon={}\n\
+         Exec={} --{}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        APP_ID,
+        quoted_exec_arg(exec),
+        BACKGROUND_OPTION
+    )
+}
+
+/// `arg` as an argument of the `Exec` key, quoted if it has characters that are reserved there
+fn quoted_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    if !arg.contains(RESERVED) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_handle_of_sender() {
+        assert_eq!(
+            request_handle(":1.42", "noteworthy7"),
+            "/org/freedesktop/portal/desktop/request/1_42/noteworthy7"
+        );
+    }
+
+    #[test]
+    fn background_granted() {
+        let results = glib::VariantDict::new(None);
+        results.insert_value("background", &true.to_variant());
+        results.insert_value("autostart", &true.to_variant());
+        assert!(is_background_granted(0, &results));
+
+        let results = glib::VariantDict::new(None);
+        results.insert_value("background", &false.to_variant());
+        assert!(!is_background_granted(0, &results));
+    }
+
+    #[test]
+    fn background_cancelled() {
+        let results = glib::VariantDict::new(None);
+        results.insert_value("background", &true.to_variant());
+        assert!(!is_background_granted(1, &results));
+        assert!(!is_background_granted(2, &results));
+
+        assert!(!is_background_granted(0, &glib::VariantDict::new(None)));
+    }
+
+    #[test]
+    fn desktop_entry() {
+        let entry = autostart_desktop_entry("/usr/bin/noteworthy");
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("\nExec=/usr/bin/noteworthy --background\n"));
+        assert!(entry.contains(&format!("\nIcon={}\n", APP_ID)));
+    }
+
+    #[test]
+    fn exec_arg_quoting() {
+        assert_eq!(
+            quoted_exec_arg("/usr/bin/noteworthy"),
+            "/usr/bin/noteworthy"
+        );
+        assert_eq!(
+            quoted_exec_arg("/home/user/My Apps/noteworthy"),
+            "\"/home/user/My Apps/noteworthy\""
+        );
+        assert_eq!(quoted_exec_arg("/opt/$app/a\"b"), "\"/opt/\\$app/a\\\"b\"");
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::core::{format_latency, LatencyHistogram};
+
+/// Time a reaction to an edit can take before it is reported, as the whole frame has about
+/// 16 ms for the edit and everything else
+const SLOW_HANDLER_THRESHOLD: Duration = Duration::from_millis(2);
+/// Environment variable that enables the profiling whatever the setting says
+pub const PROFILE_ENV_VAR: &str = "NOTEWORTHY_PROFILE";
+/// Number of measurements of a handler between the logs of its histogram
+const LOG_INTERVAL: u64 = 500;
+
+#[derive(Debug, Default)]
+struct Inner {
+    is_enabled: Cell<bool>,
+    histograms: RefCell<BTreeMap<&'static str, LatencyHistogram>>,
+}
+
+/// Measures how long the reactions to the edits of the notes take, to find what makes typing
+/// slow. It does nothing until enabled with the `profile-editing` setting or
+/// [`PROFILE_ENV_VAR`].
+#[derive(Debug, Clone)]
+pub struct EditProfiler {
+    inner: Rc<Inner>,
+}
+
+thread_local! {
+    static EDIT_PROFILER: EditProfiler = EditProfiler {
+        inner: Rc::default(),
+    };
+}
+
+impl EditProfiler {
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_enabled.get()
+    }
+
+    /// Start or stop measuring. The measurements are dropped when stopped.
+    pub fn set_is_enabled(&self, is_enabled: bool) {
+        if is_enabled == self.is_enabled() {
+            return;
+        }
+
+        self.inner.is_enabled.set(is_enabled);
+
+        if !is_enabled {
+            self.inner.histograms.borrow_mut().clear();
+        }
+
+        log::info!("Edit profiling set to `{}`", is_enabled);
+    }
+
+    /// Run `f`, measuring how long it takes as `handler` while enabled
+    pub fn measure<T>(&self, handler: &'static str, f: impl FnOnce() -> T) -> T {
+        if !self.is_enabled() {
+            return f();
+        }
+
+        let start = Instant::now();
+        let res = f();
+        self.record(handler, start.elapsed());
+        res
+    }
+
+    /// The measurements of each handler, sorted by its name
+    pub fn histograms(&self) -> Vec<(&'static str, LatencyHistogram)> {
+        self.inner
+            .histograms
+            .borrow()
+            .iter()
+            .map(|(handler, histogram)| (*handler, histogram.clone()))
+            .collect()
+    }
+
+    fn record(&self, handler: &'static str, duration: Duration) {
+        if duration > SLOW_HANDLER_THRESHOLD {
+            log::warn!(
+                "Edit handler `{}` took {}, over the budget of {}",
+                handler,
+                format_latency(duration),
+                format_latency(SLOW_HANDLER_THRESHOLD)
+            );
+        }
+
+        let mut histograms = self.inner.histograms.borrow_mut();
+        let histogram = histograms.entry(handler).or_default();
+        histogram.record(duration);
+
+        if histogram.len() % LOG_INTERVAL == 0 {
+            log::info!(
+                "Latency of edit handler `{}` over {} edits: {}",
+                handler,
+                histogram.len(),
+                histogram.to_line()
+            );
+        }
+    }
+}
+
+impl Default for EditProfiler {
+    /// The profiler shared by everything on this thread
+    fn default() -> Self {
+        EDIT_PROFILER.with(Clone::clone)
+    }
+}
+
+/// Whether `value` of [`PROFILE_ENV_VAR`] enables the profiling, which any value but an empty
+/// one or `0` does
+pub fn is_profile_env_value(value: Option<&str>) -> bool {
+    value.map_or(false, |value| !value.is_empty() && value != "0")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn measure_only_while_enabled() {
+        let profiler = EditProfiler::default();
+        assert_eq!(profiler.measure("disabled", || 1), 1);
+        assert!(profiler.histograms().is_empty());
+
+        profiler.set_is_enabled(true);
+        assert_eq!(profiler.measure("b", || 2), 2);
+        profiler.measure("a", || ());
+        profiler.measure("b", || ());
+
+        let histograms = profiler.histograms();
+        assert_eq!(
+            histograms
+                .iter()
+                .map(|(handler, histogram)| (*handler, histogram.len()))
+                .collect::<Vec<_>>(),
+            [("a", 1), ("b", 2)]
+        );
+
+        // Shared on the thread
+        assert_eq!(EditProfiler::default().histograms().len(), 2);
+
+        profiler.set_is_enabled(false);
+        assert!(profiler.histograms().is_empty());
+    }
+
+    #[test]
+    fn profile_env_values() {
+        assert!(!is_profile_env_value(None));
+        assert!(!is_profile_env_value(Some("")));
+        assert!(!is_profile_env_value(Some("0")));
+        assert!(is_profile_env_value(Some("1")));
+        assert!(is_profile_env_value(Some("yes")));
+    }
+}
@@ -0,0 +1,207 @@
+use gtk::{gio, glib, prelude::*};
+
+use std::{
+    fmt::Debug,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+pub type FileManagerFuture = Pin<Box<dyn Future<Output = Result<(), glib::Error>>>>;
+
+const FILE_MANAGER_BUS_NAME: &str = "org.freedesktop.FileManager1";
+const FILE_MANAGER_OBJECT_PATH: &str = "/org/freedesktop/FileManager1";
+
+/// Set by the document portal on the files it exports into the sandbox
+const HOST_PATH_ATTRIBUTE: &str = "xattr::document-portal.host-path";
+
+/// Where files are shown to the user
+pub trait FileManager: Debug {
+    /// Open the folders of `uris` with the files highlighted
+    fn show_items(&self, uris: &[String]) -> FileManagerFuture;
+
+    /// Open the folder at `uri`, without highlighting anything in it
+    fn open_folder(&self, uri: &str) -> FileManagerFuture;
+}
+
+/// The file manager of the desktop, through its `FileManager1` DBus interface when it has one
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DesktopFileManager;
+
+impl FileManager for DesktopFileManager {
+    fn show_items(&self, uris: &[String]) -> FileManagerFuture {
+        let parameters = (uris.to_vec(), "").to_variant();
+
+        Box::pin(async move {
+            let connection = gio::bus_get_future(gio::BusType::Session).await?;
+            connection
+                .call_future(
+                    Some(FILE_MANAGER_BUS_NAME),
+                    FILE_MANAGER_OBJECT_PATH,
+                    FILE_MANAGER_BUS_NAME,
+                    "ShowItems",
+                    Some(&parameters),
+                    None,
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                )
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn open_folder(&self, uri: &str) -> FileManagerFuture {
+        // Goes through the OpenURI portal when sandboxed
+        gio::AppInfo::launch_default_for_uri_future(uri, None::<&gio::AppLaunchContext>)
+    }
+}
+
+/// Whether the app runs in a Flatpak sandbox, where files may be at other paths than outside
+pub fn is_sandboxed() -> bool {
+    Path::new("/.flatpak-info").exists()
+}
+
+/// Path of `file` where the user can find it. In the sandbox, the files the user picked are
+/// exported by the document portal at another path than where they are on the computer.
+pub async fn host_path(file: &gio::File) -> Option<PathBuf> {
+    let path = file.path()?;
+
+    if !is_sandboxed() {
+        return Some(path);
+    }
+
+    match file
+        .query_info_future(
+            HOST_PATH_ATTRIBUTE,
+            gio::FileQueryInfoFlags::NONE,
+            glib::PRIORITY_DEFAULT,
+        )
+        .await
+    {
+        Ok(info) => Some(
+            info.attribute_as_string(HOST_PATH_ATTRIBUTE)
+                .map_or(path, |host_path| PathBuf::from(host_path.as_str())),
+        ),
+        Err(err) => {
+            log::warn!("Failed to query host path of `{}`: {:?}", file.uri(), err);
+            Some(path)
+        }
+    }
+}
+
+/// Open the folder containing `file` with it highlighted. Where the file manager can't, like
+/// when it has no `FileManager1` interface or the sandbox does not allow talking to it, the
+/// folder is only opened.
+pub async fn show_in_folder(
+    file_manager: &dyn FileManager,
+    file: &gio::File,
+) -> Result<(), glib::Error> {
+    let host_file = host_path(file)
+        .await
+        .map_or_else(|| file.clone(), gio::File::for_path);
+
+    match file_manager.show_items(&[host_file.uri().into()]).await {
+        Ok(()) => return Ok(()),
+        Err(err) => log::info!(
+            "Failed to show `{}` in the file manager, opening its folder instead: {:?}",
+            host_file.uri(),
+            err
+        ),
+    }
+
+    let folder = host_file.parent().ok_or_else(|| {
+        glib::Error::new(
+            gio::IOErrorEnum::NotFound,
+            &format!("`{}` is not in a folder", host_file.uri()),
+        )
+    })?;
+    file_manager.open_folder(&folder.uri()).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    /// Records the calls instead of opening anything
+    #[derive(Debug, Default)]
+    struct FakeFileManager {
+        has_show_items: bool,
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl FileManager for FakeFileManager {
+        fn show_items(&self, uris: &[String]) -> FileManagerFuture {
+            self.calls
+                .borrow_mut()
+                .push(format!("show_items {}", uris.join(" ")));
+
+            let has_show_items = self.has_show_items;
+            Box::pin(async move {
+                if has_show_items {
+                    Ok(())
+                } else {
+                    Err(glib::Error::new(
+                        gio::IOErrorEnum::NotSupported,
+                        "No FileManager1",
+                    ))
+                }
+            })
+        }
+
+        fn open_folder(&self, uri: &str) -> FileManagerFuture {
+            self.calls.borrow_mut().push(format!("open_folder {}", uri));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn show_in_folder_highlights_file() {
+        let file_manager = FakeFileManager {
+            has_show_items: true,
+            ..FakeFileManager::default()
+        };
+        let file = gio::File::for_path("/home/user/Notes/Note.md");
+
+        glib::MainContext::new()
+            .block_on(show_in_folder(&file_manager, &file))
+            .unwrap();
+
+        assert_eq!(
+            *file_manager.calls.borrow(),
+            vec!["show_items file:///home/user/Notes/Note.md"]
+        );
+    }
+
+    #[test]
+    fn show_in_folder_falls_back_to_folder() {
+        let file_manager = FakeFileManager::default();
+        let file = gio::File::for_path("/home/user/Notes/Note.md");
+
+        glib::MainContext::new()
+            .block_on(show_in_folder(&file_manager, &file))
+            .unwrap();
+
+        assert_eq!(
+            *file_manager.calls.borrow(),
+            vec![
+                "show_items file:///home/user/Notes/Note.md",
+                "open_folder file:///home/user/Notes"
+            ]
+        );
+    }
+
+    #[test]
+    fn host_path_outside_sandbox() {
+        if is_sandboxed() {
+            return;
+        }
+
+        let file = gio::File::for_path("/home/user/Notes/Note.md");
+        assert_eq!(
+            glib::MainContext::new().block_on(host_path(&file)),
+            Some(PathBuf::from("/home/user/Notes/Note.md"))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Fuzzy matching of a query against a candidate, such as a note title
+
+const MATCH_SCORE: i64 = 16;
+const PREFIX_BONUS: i64 = 32;
+const WORD_BOUNDARY_BONUS: i64 = 24;
+const CONSECUTIVE_BONUS: i64 = 16;
+const GAP_PENALTY: i64 = 1;
+
+/// Score how well `query` matches `candidate`, ignoring case.
+///
+/// Returns `None` when the characters of `query` do not all appear in `candidate` in the
+/// same order. Otherwise, the higher the score, the better the match. Matches at the start of
+/// the candidate, at the start of words, and runs of consecutive characters score higher.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let candidate = candidate.chars().collect::<Vec<_>>();
+
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    // Lowercased like the query, where a character may become several, like "İ". Only the
+    // first of them gets the bonus of its position.
+    let (lowercase_candidate, bonuses): (Vec<_>, Vec<_>) = candidate
+        .iter()
+        .enumerate()
+        .flat_map(|(index, c)| {
+            let bonus = position_bonus(&candidate, index);
+            c.to_lowercase()
+                .enumerate()
+                .map(move |(i, lowercase)| (lowercase, if i == 0 { bonus } else { 0 }))
+        })
+        .unzip();
+
+    if query.len() > lowercase_candidate.len() {
+        return None;
+    }
+
+    // `scores[j]` is the best score of matching the query so far, with its last character
+    // matched on `lowercase_candidate[j]`
+    let mut scores = lowercase_candidate
+        .iter()
+        .enumerate()
+        .map(|(j, c)| (*c == query[0]).then(|| MATCH_SCORE + bonuses[j] - GAP_PENALTY * j as i64))
+        .collect::<Vec<_>>();
+
+    for query_char in &query[1..] {
+        let mut next_scores = vec![None; lowercase_candidate.len()];
+        // Best score with the previous character matched before `j - 1`, minus the gap
+        let mut best_gapped: Option<i64> = None;
+
+        for j in 1..lowercase_candidate.len() {
+            if j >= 2 {
+                best_gapped = max_option(best_gapped, scores[j - 2]).map(|s| s - GAP_PENALTY);
+            }
+
+            if lowercase_candidate[j] != *query_char {
+                continue;
+            }
+
+            let consecutive = scores[j - 1].map(|s| s + CONSECUTIVE_BONUS);
+            next_scores[j] =
+                max_option(consecutive, best_gapped).map(|s| s + MATCH_SCORE + bonuses[j]);
+        }
+
+        scores = next_scores;
+    }
+
+    scores.into_iter().flatten().max()
+}
+
+fn position_bonus(candidate: &[char], index: usize) -> i64 {
+    if index == 0 {
+        return PREFIX_BONUS;
+    }
+
+    let previous = candidate[index - 1];
+    let current = candidate[index];
+
+    if !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase()) {
+        WORD_BOUNDARY_BONUS
+    } else {
+        0
+    }
+}
+
+fn max_option(a: Option<i64>, b: Option<i64>) -> Option<i64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn no_match() {
+        assert_eq!(score("abc", "acb"), None);
+        assert_eq!(score("abcd", "abc"), None);
+        assert_eq!(score("x", ""), None);
+    }
+
+    #[test]
+    fn empty_query() {
+        assert_eq!(score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn ignore_case() {
+        assert!(score("GRO", "groceries").is_some());
+        assert_eq!(score("gro", "Groceries"), score("GRO", "groceries"));
+    }
+
+    #[test]
+    fn ignore_case_of_multiple_chars() {
+        // "İ" is lowercased to "i" and a combining dot
+        assert!(score("İ", "İ").is_some());
+        assert!(score("İstanbul", "İSTANBUL").is_some());
+        assert!(score("i̇st", "İstanbul").is_some());
+        assert_eq!(score("İ", "İ"), score("i̇", "i̇"));
+    }
+
+    #[test]
+    fn prefer_prefix() {
+        assert!(score("not", "Notes") > score("not", "Annotations"));
+    }
+
+    #[test]
+    fn prefer_word_boundary() {
+        assert!(score("sl", "Shopping list") > score("sl", "Islands"));
+        assert!(score("ml", "MeetingLog") > score("ml", "Formal"));
+    }
+
+    #[test]
+    fn prefer_consecutive() {
+        assert!(score("list", "Unlisted") > score("list", "Unlit mist"));
+    }
+
+    #[test]
+    fn best_alignment() {
+        // The "b" at the start of a word is preferred over the earlier one
+        assert!(score("nb", "nob-book") > score("nb", "nob"));
+    }
+}
@@ -0,0 +1,1212 @@
+//! HTML from the clipboard converted to Markdown, for pasting from browsers and word processors
+//!
+//! This covers headings, paragraphs, emphasis, links, images, lists, code, quotes, rules, and
+//! tables, which are aligned like the ones of the editor. Scripts and styles are dropped, and
+//! anything else is kept as its text.
+
+use super::markdown_table::{Alignment, Table};
+
+/// Elements whose content is dropped, as it is not text of the document
+const SKIPPED_ELEMENTS: [&str; 10] = [
+    "script", "style", "head", "title", "noscript", "template", "textarea", "select", "button",
+    "svg",
+];
+/// Elements whose content is not HTML, so it is read as text until their end tag
+const RAW_TEXT_ELEMENTS: [&str; 6] = [
+    "script", "style", "title", "noscript", "template", "textarea",
+];
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+const BLOCK_ELEMENTS: [&str; 33] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "center",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+/// Elements that close an open paragraph, as a paragraph can't contain them
+const PARAGRAPH_CLOSERS: [&str; 16] = [
+    "address",
+    "blockquote",
+    "div",
+    "dl",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+/// Elements nested deeper than this are flattened into their parent, like the spans that some
+/// editors nest for each change of style
+const MAX_DEPTH: usize = 64;
+
+/// `html` converted to Markdown, without a new line at the end
+pub fn html_to_markdown(html: &str) -> String {
+    let root = parse(html);
+    blocks(&root.children).join("\n\n")
+}
+
+/// Text of HTML from the clipboard, which some apps put in UTF-16
+pub fn decode_clipboard_html(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        // Without a byte order mark, but the ASCII of the first tag has a zero after each byte
+        [b'<', 0, ..] => utf16(bytes, u16::from_le_bytes),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Element(Element),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str, attributes: Vec<(String, String)>) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes,
+            children: Vec::new(),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Value of `property` in the `style` attribute, in lowercase
+    fn style(&self, property: &str) -> Option<String> {
+        self.attribute("style")?.split(';').find_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            (name.trim().eq_ignore_ascii_case(property)).then(|| value.trim().to_lowercase())
+        })
+    }
+
+    /// Whether it is not part of the text, like scripts and decorations hidden from screen
+    /// readers such as the anchors next to headings
+    fn is_skipped(&self) -> bool {
+        SKIPPED_ELEMENTS.contains(&self.name.as_str())
+            || self.attribute("aria-hidden") == Some("true")
+    }
+
+    fn is_block(&self) -> bool {
+        BLOCK_ELEMENTS.contains(&self.name.as_str())
+            // Like Google Docs, which puts the whole document in a `<b>`
+            || self.children.iter().any(|child| match child {
+                Node::Element(element) => element.is_block(),
+                Node::Text(_) => false,
+            })
+    }
+}
+
+enum Token {
+    Text(String),
+    StartTag {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    EndTag(String),
+}
+
+fn parse(html: &str) -> Element {
+    let mut stack = vec![Element::new("", Vec::new())];
+    // Start tags that were flattened into their parent, with the depth they were at, so their
+    // end tags don't close an element that is open
+    let mut flattened = Vec::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => stack.last_mut().unwrap().children.push(Node::Text(text)),
+            Token::StartTag { name, attributes } => {
+                close_implied(&mut stack, &name);
+
+                // Spans without a style change nothing, however many are nested
+                let is_plain_span =
+                    matches!(name.as_str(), "span" | "font") && attributes.is_empty();
+                let element = Element::new(&name, attributes);
+
+                if VOID_ELEMENTS.contains(&name.as_str())
+                    || RAW_TEXT_ELEMENTS.contains(&name.as_str())
+                {
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .children
+                        .push(Node::Element(element));
+                } else if is_plain_span || stack.len() > MAX_DEPTH {
+                    flattened.push((name, stack.len()));
+                } else {
+                    stack.push(element);
+                }
+            }
+            Token::EndTag(name) => {
+                if flattened.last() == Some(&(name.clone(), stack.len())) {
+                    flattened.pop();
+                    continue;
+                }
+
+                // Stray end tags are ignored
+                if let Some(index) = stack.iter().rposition(|element| element.name == name) {
+                    if index > 0 {
+                        close_to(&mut stack, index);
+                    }
+                }
+            }
+        }
+    }
+
+    close_to(&mut stack, 1);
+    stack.pop().unwrap()
+}
+
+/// Close the elements that the start tag of `name` ends without their end tags
+fn close_implied(stack: &mut Vec<Element>, name: &str) {
+    let siblings: &[&str] = match name {
+        "li" => &["li"],
+        "dt" | "dd" => &["dt", "dd"],
+        "tr" => &["tr", "td", "th"],
+        "td" | "th" => &["td", "th"],
+        _ if PARAGRAPH_CLOSERS.contains(&name) => &["p"],
+        _ => return,
+    };
+    // Not past the parent of the sibling, so a nested list doesn't close its item
+    let scopes: &[&str] = match name {
+        "li" => &["ul", "ol"],
+        "dt" | "dd" => &["dl"],
+        "tr" | "td" | "th" => &["table"],
+        _ => &["div", "li", "td", "th", "blockquote"],
+    };
+
+    let open_sibling = stack
+        .iter()
+        .enumerate()
+        .skip(1)
+        .rev()
+        .take_while(|(_, element)| !scopes.contains(&element.name.as_str()))
+        .filter(|(_, element)| siblings.contains(&element.name.as_str()))
+        // The outermost, so a new row also closes the cell of the last one
+        .last()
+        .map(|(index, _)| index);
+
+    if let Some(index) = open_sibling {
+        close_to(stack, index);
+    }
+}
+
+/// Close the elements from the last one to the one at `index`, adding each to its parent
+fn close_to(stack: &mut Vec<Element>, index: usize) {
+    while stack.len() > index {
+        let element = stack.pop().unwrap();
+        stack
+            .last_mut()
+            .unwrap()
+            .children
+            .push(Node::Element(element));
+    }
+}
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let tag_start = match rest.find('<') {
+            Some(tag_start) => tag_start,
+            None => {
+                tokens.push(Token::Text(decode_entities(rest)));
+                break;
+            }
+        };
+
+        if tag_start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..tag_start])));
+            rest = &rest[tag_start..];
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some((token, after)) = tag(rest) {
+            rest = after;
+
+            if let Token::StartTag { ref name, .. } = token {
+                if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                    // The content is dropped anyway
+                    let end = find_ignore_ascii_case(rest, &format!("</{}", name));
+                    rest = end.map_or("", |end| &rest[end..]);
+                }
+            }
+
+            tokens.push(token);
+        } else {
+            // Not a tag, like in `a < b`
+            tokens.push(Token::Text("<".to_string()));
+            rest = &rest[1..];
+        }
+    }
+
+    tokens
+}
+
+/// The tag at the start of `text`, and the text after it
+fn tag(text: &str) -> Option<(Token, &str)> {
+    let (is_end, after_bracket) = match text.strip_prefix("</") {
+        Some(after) => (true, after),
+        None => (false, &text[1..]),
+    };
+
+    if !after_bracket.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let name_end = after_bracket
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != ':')
+        .unwrap_or(after_bracket.len());
+    let name = after_bracket[..name_end].to_ascii_lowercase();
+    let mut rest = &after_bracket[name_end..];
+
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break;
+        }
+        if rest.is_empty() {
+            break;
+        }
+
+        let attribute_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let attribute = rest[..attribute_end].to_ascii_lowercase();
+        rest = rest[attribute_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, after) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..].find(quote).map_or(after.len(), |end| end + 1);
+                        (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = after;
+                decode_entities(value)
+            }
+            None => String::new(),
+        };
+
+        attributes.push((attribute, value));
+    }
+
+    let token = if is_end {
+        Token::EndTag(name)
+    } else {
+        Token::StartTag { name, attributes }
+    };
+
+    Some((token, rest))
+}
+
+fn find_ignore_ascii_case(text: &str, pattern: &str) -> Option<usize> {
+    text.as_bytes()
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern.as_bytes()))
+}
+
+/// `text` with its character references, like `&amp;` and `&#8217;`, replaced
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let character = rest[1..]
+            .char_indices()
+            .take(10)
+            .find(|(_, c)| *c == ';')
+            .and_then(|(end, _)| Some((entity(&rest[1..end + 1])?, end + 2)));
+
+        match character {
+            Some((character, len)) => {
+                decoded.push(character);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(|c| c == 'x' || c == 'X') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    let character = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        "middot" => '·',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "times" => '×',
+        "deg" => '°',
+        "euro" => '€',
+        _ => return None,
+    };
+    Some(character)
+}
+
+/// Markdown blocks of `nodes`, where the text between the block elements is a paragraph
+fn blocks(nodes: &[Node]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut inline_nodes = Vec::new();
+
+    for node in nodes {
+        match node {
+            Node::Element(element) if element.is_block() => {
+                blocks.extend(paragraph(&inline_nodes));
+                inline_nodes.clear();
+                blocks.extend(block(element));
+            }
+            node => inline_nodes.push(node),
+        }
+    }
+
+    blocks.extend(paragraph(&inline_nodes));
+    blocks
+}
+
+fn block(element: &Element) -> Vec<String> {
+    match element.name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(element.name.as_bytes()[1] - b'0');
+            let text = single_line(&inline(&element.children, Format::default()));
+
+            if text.is_empty() {
+                return Vec::new();
+            }
+
+            vec![format!("{} {}", "#".repeat(level), text)]
+        }
+        "pre" => vec![code_block(element)],
+        "blockquote" => {
+            let quoted = blocks(&element.children).join("\n\n");
+
+            if quoted.is_empty() {
+                return Vec::new();
+            }
+
+            vec![prefix_lines(&quoted, "> ", ">")]
+        }
+        "ul" => list(element, false),
+        "ol" => list(element, true),
+        "table" => table(element),
+        "hr" => vec!["---".to_string()],
+        _ if element.is_skipped() => Vec::new(),
+        _ => blocks(&element.children),
+    }
+}
+
+fn paragraph(nodes: &[&Node]) -> Option<String> {
+    let text = inline(nodes.iter().copied(), Format::default());
+
+    let mut paragraph = String::new();
+    let mut is_after_empty_line = false;
+
+    for line in text.split('\n') {
+        let line = collapse_spaces(line);
+        let line = line.trim();
+
+        if line.is_empty() {
+            is_after_empty_line = true;
+            continue;
+        }
+
+        if !paragraph.is_empty() {
+            // Two line breaks in a row separate paragraphs
+            paragraph.push_str(if is_after_empty_line { "\n\n" } else { "  \n" });
+        }
+        paragraph.push_str(&escape_line_start(line));
+        is_after_empty_line = false;
+    }
+
+    if paragraph.is_empty() {
+        return None;
+    }
+
+    Some(paragraph)
+}
+
+/// Styles of the text, so they are not added again by the elements inside the ones adding them
+#[derive(Debug, Default, Clone, Copy)]
+struct Format {
+    is_bold: bool,
+    is_italic: bool,
+    is_strikethrough: bool,
+}
+
+fn inline<'a>(nodes: impl IntoIterator<Item = &'a Node>, format: Format) -> String {
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            Node::Text(text) => escape(&collapse_whitespace(text)),
+            Node::Element(element) => inline_element(element, format),
+        })
+        .collect()
+}
+
+fn inline_element(element: &Element, format: Format) -> String {
+    let name = element.name.as_str();
+
+    match name {
+        _ if element.is_skipped() => return String::new(),
+        "br" => return "\n".to_string(),
+        "img" => return image(element),
+        "a" => return link(element, format),
+        "code" | "kbd" | "samp" | "tt" => return code_span(&text_content(element)),
+        _ if is_monospace(element) => return code_span(&text_content(element)),
+        _ => {}
+    }
+
+    let font_weight = element.style("font-weight");
+    let is_bold = match font_weight.as_deref() {
+        Some("bold" | "bolder" | "600" | "700" | "800" | "900") => true,
+        Some(_) => false,
+        None => matches!(name, "b" | "strong"),
+    };
+    let is_italic = match element.style("font-style").as_deref() {
+        Some("italic" | "oblique") => true,
+        Some(_) => false,
+        None => matches!(name, "i" | "em" | "cite" | "dfn" | "var"),
+    };
+    let is_strikethrough = matches!(name, "s" | "del" | "strike")
+        || ["text-decoration", "text-decoration-line"]
+            .iter()
+            .filter_map(|property| element.style(property))
+            .any(|value| value.contains("line-through"));
+
+    let is_bold = is_bold && !format.is_bold;
+    let is_italic = is_italic && !format.is_italic;
+    let is_strikethrough = is_strikethrough && !format.is_strikethrough;
+
+    let mut text = inline(
+        &element.children,
+        Format {
+            is_bold: format.is_bold || is_bold,
+            is_italic: format.is_italic || is_italic,
+            is_strikethrough: format.is_strikethrough || is_strikethrough,
+        },
+    );
+
+    if is_strikethrough {
+        text = wrap(text, "~~");
+    }
+    if is_italic {
+        text = wrap(text, "*");
+    }
+    if is_bold {
+        text = wrap(text, "**");
+    }
+
+    text
+}
+
+/// `text` between `marker`s, like `**bold**`
+fn wrap(text: String, marker: &str) -> String {
+    surround(text, marker, marker)
+}
+
+/// `text` between `before` and `after`, with the spaces around it outside of them, as emphasis
+/// and links can't start or end with a space. It is left as it is when it is only spaces.
+fn surround(text: String, before: &str, after: &str) -> String {
+    let trimmed = text.trim();
+
+    if trimmed.is_empty() {
+        return text;
+    }
+
+    let start = text.len() - text.trim_start().len();
+    let end = text.trim_end().len();
+    format!(
+        "{}{}{}{}{}",
+        &text[..start],
+        before,
+        trimmed,
+        after,
+        &text[end..]
+    )
+}
+
+fn link(element: &Element, format: Format) -> String {
+    let text = inline(&element.children, format);
+
+    let href = match element.attribute("href").map(str::trim) {
+        Some(href) if !href.is_empty() && !is_script_url(href) => href,
+        _ => return text,
+    };
+
+    surround(text, "[", &format!("]({})", escape_url(href)))
+}
+
+fn image(element: &Element) -> String {
+    let alt = escape(&collapse_whitespace(
+        element.attribute("alt").unwrap_or_default(),
+    ));
+
+    match element.attribute("src").map(str::trim) {
+        // Embedded images would be pasted as pages of base64
+        Some(src) if !src.is_empty() && !src.starts_with("data:") && !is_script_url(src) => {
+            format!("![{}]({})", alt.trim(), escape_url(src))
+        }
+        _ => alt,
+    }
+}
+
+fn is_script_url(url: &str) -> bool {
+    let scheme = url.split(':').next().unwrap_or_default();
+    url.contains(':') && ["javascript", "vbscript"].contains(&scheme.trim().to_lowercase().as_str())
+}
+
+/// `url` that can be in the parentheses of a link
+fn escape_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// Like the fonts LibreOffice and Google Docs give to code
+fn is_monospace(element: &Element) -> bool {
+    let font = element
+        .style("font-family")
+        .or_else(|| element.attribute("face").map(str::to_lowercase));
+
+    font.map_or(false, |font| {
+        ["mono", "courier", "consolas"]
+            .iter()
+            .any(|name| font.contains(name))
+    })
+}
+
+fn code_span(text: &str) -> String {
+    let text = collapse_whitespace(text);
+
+    if text.trim().is_empty() {
+        return text;
+    }
+
+    let fence = "`".repeat(longest_run(&text, '`') + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", fence, padding, text, padding, fence)
+}
+
+fn code_block(element: &Element) -> String {
+    let text = text_content(element);
+    // Like browsers, which ignore a line break right after `<pre>`
+    let text = text.strip_prefix('\n').unwrap_or(&text).trim_end();
+
+    let language = std::iter::once(element)
+        .chain(element.children.iter().find_map(|child| match child {
+            Node::Element(element) if element.name == "code" => Some(element),
+            _ => None,
+        }))
+        .filter_map(|element| element.attribute("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .unwrap_or_default();
+
+    let fence = "`".repeat(longest_run(text, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, text, fence)
+}
+
+/// The text of `element` as it is, with its line breaks
+fn text_content(element: &Element) -> String {
+    element
+        .children
+        .iter()
+        .map(|child| match child {
+            Node::Text(text) => text.replace('\u{a0}', " "),
+            Node::Element(element) if element.name == "br" => "\n".to_string(),
+            Node::Element(element) if element.is_skipped() => String::new(),
+            Node::Element(element) => text_content(element),
+        })
+        .collect()
+}
+
+fn list(element: &Element, is_ordered: bool) -> Vec<String> {
+    let mut number = element
+        .attribute("start")
+        .and_then(|start| start.trim().parse::<u32>().ok())
+        .unwrap_or(1);
+    let mut items: Vec<(String, usize)> = Vec::new();
+
+    for child in &element.children {
+        match child {
+            // Nested in the list instead of in an item, like Google Docs does
+            Node::Element(nested) if nested.name == "ul" || nested.name == "ol" => {
+                let nested = list(nested, nested.name == "ol").join("\n");
+                match items.last_mut() {
+                    Some((item, indent)) => {
+                        let indent = " ".repeat(*indent);
+                        item.push('\n');
+                        item.push_str(&prefix_lines(&nested, &indent, ""));
+                    }
+                    None => items.push((nested, 0)),
+                }
+            }
+            Node::Text(text) if text.trim().is_empty() => {}
+            child => {
+                let content = match child {
+                    Node::Element(item) if item.name == "li" => blocks(&item.children),
+                    child => blocks(std::slice::from_ref(child)),
+                }
+                .join("\n");
+
+                let marker = if is_ordered {
+                    format!("{}. ", number)
+                } else {
+                    "- ".to_string()
+                };
+                number += 1;
+
+                let indent = " ".repeat(marker.len());
+                let item = prefix_lines(&content, &indent, "");
+                let item = item.strip_prefix(&indent).unwrap_or(&item);
+                items.push((format!("{}{}", marker, item), marker.len()));
+            }
+        }
+    }
+
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    vec![items
+        .into_iter()
+        .map(|(item, _)| item.trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")]
+}
+
+fn table(element: &Element) -> Vec<String> {
+    let mut rows = Vec::new();
+    collect_rows(element, &mut rows);
+
+    // Only used to lay out its content
+    if let [row] = rows.as_slice() {
+        if let [cell] = row.as_slice() {
+            return blocks(&cell.children);
+        }
+    }
+
+    if rows.is_empty() {
+        return Vec::new();
+    }
+
+    let alignments = rows[0].iter().map(|cell| cell_alignment(cell)).collect();
+    let rows = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| single_line(&blocks(&cell.children).join(" ")).replace('|', "\\|"))
+                .collect()
+        })
+        .collect();
+
+    vec![Table { alignments, rows }.to_lines().join("\n")]
+}
+
+/// Cells of the rows of `element`, including the ones in `<thead>`, `<tbody>`, and `<tfoot>`,
+/// but not the ones of nested tables
+fn collect_rows<'a>(element: &'a Element, rows: &mut Vec<Vec<&'a Element>>) {
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            match child.name.as_str() {
+                "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+                "tr" => rows.push(
+                    child
+                        .children
+                        .iter()
+                        .filter_map(|cell| match cell {
+                            Node::Element(cell) if cell.name == "td" || cell.name == "th" => {
+                                Some(cell)
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn cell_alignment(cell: &Element) -> Alignment {
+    let align = cell
+        .style("text-align")
+        .or_else(|| cell.attribute("align").map(str::to_lowercase));
+
+    match align.as_deref() {
+        Some("left") => Alignment::Left,
+        Some("center") => Alignment::Center,
+        Some("right") => Alignment::Right,
+        _ => Alignment::None,
+    }
+}
+
+/// `text` with the lines after line breaks joined with spaces, for headings and cells
+fn single_line(text: &str) -> String {
+    collapse_spaces(&text.replace('\n', " ")).trim().to_string()
+}
+
+/// `text` with `prefix` before each line, or `empty_prefix` before the empty ones
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                empty_prefix.to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `text` with each run of white space as a single space, as HTML shows it
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut is_after_space = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !is_after_space {
+                collapsed.push(' ');
+            }
+            is_after_space = true;
+        } else {
+            collapsed.push(c);
+            is_after_space = false;
+        }
+    }
+
+    collapsed
+}
+
+/// `text` with the spaces that were around elements, like `a <b> b</b>`, as a single one
+fn collapse_spaces(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if c != ' ' || !collapsed.ends_with(' ') {
+            collapsed.push(c);
+        }
+    }
+
+    collapsed
+}
+
+/// `text` with the characters that would be read as Markdown escaped
+fn escape(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut escaped = String::with_capacity(text.len());
+
+    for (index, &c) in chars.iter().enumerate() {
+        let is_intraword = |index: usize| {
+            index > 0
+                && chars[index - 1].is_alphanumeric()
+                && chars.get(index + 1).map_or(false, |c| c.is_alphanumeric())
+        };
+
+        match c {
+            '\\' | '*' | '`' | '[' | ']' | '<' => escaped.push('\\'),
+            // Not emphasis within a word, like in `snake_case`
+            '_' if !is_intraword(index) => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// `line` that doesn't start a heading, a quote, or a list item
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(|c: char| matches!(c, '#' | '>' | '-' | '+' | '=')) {
+        return format!("\\{}", line);
+    }
+
+    let n_digits = line.chars().take_while(char::is_ascii_digit).count();
+    let after_digits = &line[n_digits..];
+
+    if n_digits > 0
+        && after_digits.starts_with(|c: char| c == '.' || c == ')')
+        && after_digits[1..].chars().next().map_or(true, |c| c == ' ')
+    {
+        return format!("{}\\{}", &line[..n_digits], after_digits);
+    }
+
+    line.to_string()
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Clipboard HTML copied from other apps, as they are named in `fixtures/`, with the
+    /// Markdown it is converted to
+    const FIXTURES: &[(&str, &str, &str)] = &[
+        (
+            "google-docs",
+            include_str!("fixtures/google-docs.html"),
+            include_str!("fixtures/google-docs.md"),
+        ),
+        (
+            "firefox-selection",
+            include_str!("fixtures/firefox-selection.html"),
+            include_str!("fixtures/firefox-selection.md"),
+        ),
+        (
+            "libreoffice",
+            include_str!("fixtures/libreoffice.html"),
+            include_str!("fixtures/libreoffice.md"),
+        ),
+    ];
+
+    #[test]
+    fn fixtures() {
+        for (name, html, markdown) in FIXTURES {
+            assert_eq!(
+                html_to_markdown(html),
+                markdown.trim_end(),
+                "`{}` converted differently",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn headings_and_paragraphs() {
+        assert_eq!(
+            html_to_markdown("<h1>Title</h1><p>First</p><p>Second<br>line</p><h3> Sub </h3>"),
+            "# Title\n\nFirst\n\nSecond  \nline\n\n### Sub"
+        );
+        assert_eq!(html_to_markdown("Just text"), "Just text");
+        assert_eq!(html_to_markdown("<p>a</p>loose<p>b</p>"), "a\n\nloose\n\nb");
+        assert_eq!(html_to_markdown("<p>a<br><br>b</p>"), "a\n\nb");
+        assert_eq!(html_to_markdown("<p> </p><br><p></p>"), "");
+        // Closed by the next one
+        assert_eq!(html_to_markdown("<p>a<p>b"), "a\n\nb");
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(
+            html_to_markdown(
+                "<b>bold</b>, <i>italic</i>, <s>gone</s>, <strong><em>both</em></strong>"
+            ),
+            "**bold**, *italic*, ~~gone~~, ***both***"
+        );
+        // Spaces outside of the markers
+        assert_eq!(html_to_markdown("a<b> b </b>c"), "a **b** c");
+        assert_eq!(html_to_markdown("<b><b>twice</b></b>"), "**twice**");
+        assert_eq!(html_to_markdown("<b> </b>"), "");
+        assert_eq!(
+            html_to_markdown(
+                r#"<span style="font-weight:700">x</span> <span style="font-style: italic">y</span> <b style="font-weight:normal">z</b>"#
+            ),
+            "**x** *y* z"
+        );
+    }
+
+    #[test]
+    fn links_and_images() {
+        assert_eq!(
+            html_to_markdown(
+                r#"See <a href="https://example.com/a_(b)?q=1&amp;r=2">the <b>docs</b></a>."#
+            ),
+            "See [the **docs**](https://example.com/a_%28b%29?q=1&r=2)."
+        );
+        assert_eq!(
+            html_to_markdown(r#"<a href=" https://a.org "> x </a>y"#),
+            "[x](https://a.org) y"
+        );
+        assert_eq!(
+            html_to_markdown(r#"<a href="javascript:alert(1)">click</a>"#),
+            "click"
+        );
+        assert_eq!(html_to_markdown(r#"<a name="top">anchor</a>"#), "anchor");
+        assert_eq!(html_to_markdown(r#"<a href="https://a.org"></a>"#), "");
+        assert_eq!(
+            html_to_markdown(r#"<img src="https://a.org/cat.png" alt="A cat">"#),
+            "![A cat](https://a.org/cat.png)"
+        );
+        assert_eq!(
+            html_to_markdown(r#"<img src="data:image/png;base64,AAAA" alt="Pasted">"#),
+            "Pasted"
+        );
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            html_to_markdown("Run <code>cargo  build</code>"),
+            "Run `cargo build`"
+        );
+        assert_eq!(html_to_markdown("<code>a`b</code>"), "``a`b``");
+        assert_eq!(
+            html_to_markdown("<code>*not* [escaped]</code>"),
+            "`*not* [escaped]`"
+        );
+        assert_eq!(
+            html_to_markdown(
+                "<pre class=\"language-rust\">\nfn main() {\n    let a = 1 &lt; 2;\n}\n</pre>"
+            ),
+            "```rust\nfn main() {\n    let a = 1 < 2;\n}\n```"
+        );
+        assert_eq!(
+            html_to_markdown("<pre><code class=\"lang-sh\"><span>$</span> ls<br>```</code></pre>"),
+            "````sh\n$ ls\n```\n````"
+        );
+    }
+
+    #[test]
+    fn lists_and_quotes() {
+        assert_eq!(
+            html_to_markdown("<ul><li>One<li>Two<ul><li>Nested</li></ul></li></ul>"),
+            "- One\n- Two\n  - Nested"
+        );
+        assert_eq!(
+            html_to_markdown(
+                r#"<ol start="9"><li><p>Nine</p></li><li>Ten<ol><li>A</li></ol></li></ol>"#
+            ),
+            "9. Nine\n10. Ten\n    1. A"
+        );
+        assert_eq!(
+            html_to_markdown("<blockquote><p>Quoted</p><p>Twice</p></blockquote>"),
+            "> Quoted\n>\n> Twice"
+        );
+        assert_eq!(html_to_markdown("<ul><li></li></ul>"), "-");
+        assert_eq!(html_to_markdown("<hr>"), "---");
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            html_to_markdown(
+                "<table><thead><tr><th>Name</th><th align=\"right\">Age</th></tr></thead>\
+                 <tbody><tr><td>Ann</td><td>31</td></tr><tr><td>a|b</td></tr></tbody></table>"
+            ),
+            "| Name | Age |\n| ---- | --: |\n| Ann  |  31 |\n| a\\|b |     |"
+        );
+        // Only for the layout
+        assert_eq!(
+            html_to_markdown("<table><tr><td><p>Inside</p></td></tr></table>"),
+            "Inside"
+        );
+    }
+
+    #[test]
+    fn sanitized() {
+        assert_eq!(
+            html_to_markdown(
+                "<style>p { color: red }</style><script>alert('<p>x</p>')</script><p>Kept</p><SCRIPT>bad()</SCRIPT>"
+            ),
+            "Kept"
+        );
+        assert_eq!(
+            html_to_markdown("<!-- comment --><!DOCTYPE html><p>a</p>"),
+            "a"
+        );
+        assert_eq!(
+            html_to_markdown("<html><head><title>T</title></head><body>b</body></html>"),
+            "b"
+        );
+    }
+
+    #[test]
+    fn nested_spans() {
+        let depth = 10_000;
+        let html = format!(
+            "{}<b>bold</b>{}",
+            "<span>".repeat(depth),
+            "</span>".repeat(depth)
+        );
+        assert_eq!(html_to_markdown(&html), "**bold**");
+
+        // Flattened past the maximum depth
+        let html = format!(
+            "{}deep{}<p>after</p>",
+            r#"<span style="color:red">"#.repeat(depth),
+            "</span>".repeat(depth)
+        );
+        assert_eq!(html_to_markdown(&html), "deep\n\nafter");
+
+        // A span without style is not closed by the end tag of one inside it
+        assert_eq!(
+            html_to_markdown(r#"<span><span style="font-weight:bold">a</span>b</span>"#),
+            "**a**b"
+        );
+    }
+
+    #[test]
+    fn escaped() {
+        assert_eq!(
+            html_to_markdown("2 * 3 [a] `b` &lt;not a tag&gt; snake_case _x_"),
+            "2 \\* 3 \\[a\\] \\`b\\` \\<not a tag> snake_case \\_x\\_"
+        );
+        assert_eq!(
+            html_to_markdown("<p># not a heading</p>"),
+            "\\# not a heading"
+        );
+        assert_eq!(html_to_markdown("<p>1. not a list</p>"), "1\\. not a list");
+        assert_eq!(html_to_markdown("<p>- not an item</p>"), "\\- not an item");
+        assert_eq!(html_to_markdown("<p>2022. A year</p>"), "2022\\. A year");
+        assert_eq!(html_to_markdown("<p>3.14</p>"), "3.14");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &#8217; &#x2014; &nbsp;"),
+            "a & b <c> ’ — \u{a0}"
+        );
+        assert_eq!(
+            decode_entities("AT&T &unknown; & &#xZZ;"),
+            "AT&T &unknown; & &#xZZ;"
+        );
+        assert_eq!(html_to_markdown("a&nbsp;&nbsp;b"), "a b");
+    }
+
+    #[test]
+    fn clipboard_encodings() {
+        assert_eq!(decode_clipboard_html(b"<p>a</p>"), "<p>a</p>");
+        assert_eq!(decode_clipboard_html(b"\xef\xbb\xbf<p>a</p>"), "<p>a</p>");
+
+        let utf16_le = "<p>é</p>"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(decode_clipboard_html(&utf16_le), "<p>é</p>");
+        assert_eq!(
+            decode_clipboard_html(&[&[0xff, 0xfe][..], &utf16_le].concat()),
+            "<p>é</p>"
+        );
+
+        let utf16_be = "<p>é</p>"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decode_clipboard_html(&[&[0xfe, 0xff][..], &utf16_be].concat()),
+            "<p>é</p>"
+        );
+    }
+}
@@ -0,0 +1,699 @@
+//! Pipe tables of Markdown, as in GitHub Flavored Markdown, found and aligned in plain text
+//!
+//! Offsets in lines are in characters, like the ones of text iters.
+
+use std::ops::Range;
+
+/// Width a column is padded to at least, so the separator row has three dashes
+const MIN_COLUMN_WIDTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    /// Alignment of a cell of the separator row, like `:--` or `-:`
+    fn from_separator(cell: &str) -> Self {
+        match (cell.starts_with(':'), cell.len() > 1 && cell.ends_with(':')) {
+            (false, false) => Self::None,
+            (true, false) => Self::Left,
+            (true, true) => Self::Center,
+            (false, true) => Self::Right,
+        }
+    }
+
+    /// Cell of the separator row for a column `width` characters wide
+    fn separator(self, width: usize) -> String {
+        match self {
+            Self::None => "-".repeat(width),
+            Self::Left => format!(":{}", "-".repeat(width - 1)),
+            Self::Center => format!(":{}:", "-".repeat(width - 2)),
+            Self::Right => format!("{}:", "-".repeat(width - 1)),
+        }
+    }
+
+    /// `text` padded with spaces to `width`
+    fn pad(self, text: &str, width: usize) -> String {
+        let padding = width.saturating_sub(display_width(text));
+        let (before, after) = match self {
+            Self::None | Self::Left => (0, padding),
+            Self::Center => (padding / 2, padding - padding / 2),
+            Self::Right => (padding, 0),
+        };
+
+        format!("{}{}{}", " ".repeat(before), text, " ".repeat(after))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A cell of a row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// Text of the cell, without the spaces around it
+    pub text: String,
+    /// Where the text is in the line. When the cell is empty, it is an empty range after the
+    /// first space of the cell, where text would be typed.
+    pub range: Range<usize>,
+    /// Where the whole cell is in the line, between the pipes around it
+    pub bounds: Range<usize>,
+}
+
+/// Offsets of the pipes of `chars` that are not escaped with a backslash
+fn pipe_offsets(chars: &[char]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut is_escaped = false;
+
+    for (offset, c) in chars.iter().enumerate() {
+        if is_escaped {
+            is_escaped = false;
+        } else if *c == '\\' {
+            is_escaped = true;
+        } else if *c == '|' {
+            offsets.push(offset);
+        }
+    }
+
+    offsets
+}
+
+/// Whether `line` can be a row of a table, that is whether it has a pipe that is not escaped
+pub fn is_row(line: &str) -> bool {
+    !pipe_offsets(&line.chars().collect::<Vec<_>>()).is_empty()
+}
+
+/// Whether `line` is the row separating the header from the other rows, like `| --- | :-: |`
+pub fn is_separator_row(line: &str) -> bool {
+    let cells = split_row(line);
+
+    is_row(line)
+        && !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let dashes = cell.text.strip_prefix(':').unwrap_or(&cell.text);
+            let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+/// Cells of `line`, split at the pipes that are not escaped. The pipes at the start and at the
+/// end of the line are optional.
+pub fn split_row(line: &str) -> Vec<Cell> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let pipes = pipe_offsets(&chars);
+
+    let starts = std::iter::once(0).chain(pipes.iter().map(|offset| offset + 1));
+    let ends = pipes.iter().copied().chain(std::iter::once(chars.len()));
+    let mut segments = starts
+        .zip(ends)
+        .map(|(start, end)| start..end)
+        .collect::<Vec<_>>();
+
+    let is_blank =
+        |segment: &Range<usize>| chars[segment.clone()].iter().all(|c| c.is_whitespace());
+    if !pipes.is_empty() {
+        if segments.last().map_or(false, is_blank) {
+            segments.pop();
+        }
+        if segments.first().map_or(false, is_blank) {
+            segments.remove(0);
+        }
+    }
+
+    segments
+        .into_iter()
+        .map(|bounds| {
+            let text_start =
+                (bounds.start..bounds.end).find(|&offset| !chars[offset].is_whitespace());
+            let range = match text_start {
+                Some(start) => {
+                    let end = (start..bounds.end)
+                        .rev()
+                        .find(|&offset| !chars[offset].is_whitespace())
+                        .map_or(start, |offset| offset + 1);
+                    start..end
+                }
+                None => {
+                    let start = (bounds.start + 1).min(bounds.end);
+                    start..start
+                }
+            };
+
+            Cell {
+                text: chars[range.clone()].iter().collect(),
+                range,
+                bounds,
+            }
+        })
+        .collect()
+}
+
+/// Index of the cell of `line` that `offset` is in. Offsets on a pipe are in the cell before it.
+pub fn column_at(line: &str, offset: usize) -> usize {
+    let cells = split_row(line);
+    cells
+        .iter()
+        .position(|cell| offset <= cell.bounds.end)
+        .unwrap_or_else(|| cells.len().saturating_sub(1))
+}
+
+/// Lines of the table that `line` of `lines` is in, from its header row up to its last row,
+/// or `None` when it is not in a table
+pub fn table_range(lines: &[&str], line: usize) -> Option<Range<usize>> {
+    if !lines.get(line).map_or(false, |text| is_row(text)) {
+        return None;
+    }
+
+    let mut start = line;
+    while start > 0 && is_row(lines[start - 1]) {
+        start -= 1;
+    }
+
+    let mut end = line + 1;
+    while end < lines.len() && is_row(lines[end]) {
+        end += 1;
+    }
+
+    // The rows with pipes before the header are not in the table
+    let separator = (start + 1..end).find(|&index| is_separator_row(lines[index]))?;
+    let header = separator - 1;
+    if header <= line {
+        Some(header..end)
+    } else {
+        None
+    }
+}
+
+/// Columns of a table, with the texts of its cells
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    /// Cells of the header and then of the other rows, which may have fewer or more cells than
+    /// there are alignments
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Table with empty cells, with `n_rows` rows below the header
+    pub fn empty(n_columns: usize, n_rows: usize) -> Self {
+        Self {
+            alignments: vec![Alignment::None; n_columns],
+            rows: vec![vec![String::new(); n_columns]; n_rows + 1],
+        }
+    }
+
+    /// Table of `lines`, which are the header row, the separator row, and the other rows
+    pub fn parse(lines: &[&str]) -> Option<Self> {
+        let separator = lines.get(1).filter(|line| is_separator_row(line))?;
+
+        let alignments = split_row(separator)
+            .iter()
+            .map(|cell| Alignment::from_separator(&cell.text))
+            .collect();
+        let rows = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != 1)
+            .map(|(_, line)| split_row(line).into_iter().map(|cell| cell.text).collect())
+            .collect();
+
+        Some(Self { alignments, rows })
+    }
+
+    pub fn n_columns(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.alignments.len()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Lines of the table, with every row having all the columns, each padded to its widest
+    /// cell, and the separator row written with dashes as wide as the columns
+    pub fn to_lines(&self) -> Vec<String> {
+        let n_columns = self.n_columns();
+        let alignment = |column: usize| {
+            self.alignments
+                .get(column)
+                .copied()
+                .unwrap_or(Alignment::None)
+        };
+        let widths = (0..n_columns)
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|text| display_width(text))
+                    .max()
+                    .unwrap_or(0)
+                    .max(MIN_COLUMN_WIDTH)
+            })
+            .collect::<Vec<_>>();
+
+        let format_row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+        let format_cells = |row: &Vec<String>| {
+            let cells = (0..n_columns)
+                .map(|column| {
+                    let text = row.get(column).map_or("", String::as_str);
+                    alignment(column).pad(text, widths[column])
+                })
+                .collect();
+            format_row(cells)
+        };
+
+        let separator = format_row(
+            (0..n_columns)
+                .map(|column| alignment(column).separator(widths[column]))
+                .collect(),
+        );
+
+        let mut lines = self.rows.iter().map(format_cells).collect::<Vec<_>>();
+        lines.insert(1.min(lines.len()), separator);
+        lines
+    }
+}
+
+/// `lines` of a table aligned with [`Table::to_lines`], with where the cursor at `cursor`, a
+/// line of `lines` and an offset in it, goes so it stays at the same place in its cell
+pub fn reformat(lines: &[&str], cursor: (usize, usize)) -> Option<(Vec<String>, (usize, usize))> {
+    let table = Table::parse(lines)?;
+    let new_lines = table.to_lines();
+
+    let (line, offset) = cursor;
+    let column = column_at(lines.get(line)?, offset);
+    let offset_in_text = split_row(lines[line]).get(column).map_or(0, |cell| {
+        offset.clamp(cell.range.start, cell.range.end) - cell.range.start
+    });
+    let new_offset = split_row(&new_lines[line]).get(column).map_or(0, |cell| {
+        cell.range.start + offset_in_text.min(cell.range.len())
+    });
+
+    Some((new_lines, (line, new_offset)))
+}
+
+/// Text of the cell after or before the one at `cursor` in `lines` of a table, as a line of
+/// `lines` and a range in it, skipping the separator row. It is `None` past the last or the
+/// first cell.
+pub fn neighbor_cell(
+    lines: &[&str],
+    cursor: (usize, usize),
+    direction: Direction,
+) -> Option<(usize, Range<usize>)> {
+    let cells = lines
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != 1)
+        .flat_map(|(index, line)| {
+            split_row(line)
+                .into_iter()
+                .map(move |cell| (index, cell.range))
+        })
+        .collect::<Vec<_>>();
+
+    let (line, offset) = cursor;
+    let cell = if line == 1 {
+        // The separator row is between the last cell of the header and the first of the next row
+        match direction {
+            Direction::Forward => cells.iter().find(|(index, _)| *index > 1),
+            Direction::Backward => cells.iter().rev().find(|(index, _)| *index < 1),
+        }
+    } else {
+        let column = column_at(lines.get(line)?, offset);
+        let position = cells.iter().filter(|(index, _)| *index < line).count() + column;
+        match direction {
+            Direction::Forward => cells.get(position + 1),
+            Direction::Backward => position
+                .checked_sub(1)
+                .and_then(|position| cells.get(position)),
+        }
+    };
+
+    cell.cloned()
+}
+
+/// Number of columns `text` takes in a monospace font, with wide characters like CJK ones and
+/// emoji taking two, and combining ones none
+fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    const ZERO_WIDTH: &[(u32, u32)] = &[
+        (0x0300, 0x036F),
+        (0x0483, 0x0489),
+        (0x0591, 0x05BD),
+        (0x0610, 0x061A),
+        (0x064B, 0x065F),
+        (0x0E31, 0x0E31),
+        (0x0E34, 0x0E3A),
+        (0x0E47, 0x0E4E),
+        (0x1AB0, 0x1AFF),
+        (0x1DC0, 0x1DFF),
+        (0x200B, 0x200F),
+        (0x20D0, 0x20FF),
+        (0xFE00, 0xFE0F),
+        (0xFE20, 0xFE2F),
+        (0xE0100, 0xE01EF),
+    ];
+    const WIDE: &[(u32, u32)] = &[
+        (0x1100, 0x115F),
+        (0x231A, 0x231B),
+        (0x2329, 0x232A),
+        (0x23E9, 0x23EC),
+        (0x2614, 0x2615),
+        (0x2648, 0x2653),
+        (0x26A1, 0x26A1),
+        (0x26AA, 0x26AB),
+        (0x26BD, 0x26BE),
+        (0x26C4, 0x26C5),
+        (0x2705, 0x2705),
+        (0x270A, 0x270B),
+        (0x2728, 0x2728),
+        (0x274C, 0x274C),
+        (0x2753, 0x2755),
+        (0x2757, 0x2757),
+        (0x2795, 0x2797),
+        (0x2B1B, 0x2B1C),
+        (0x2B50, 0x2B50),
+        (0x2E80, 0x303E),
+        (0x3041, 0x33FF),
+        (0x3400, 0x4DBF),
+        (0x4E00, 0x9FFF),
+        (0xA000, 0xA4CF),
+        (0xA960, 0xA97F),
+        (0xAC00, 0xD7A3),
+        (0xF900, 0xFAFF),
+        (0xFE30, 0xFE4F),
+        (0xFF00, 0xFF60),
+        (0xFFE0, 0xFFE6),
+        (0x1F004, 0x1F004),
+        (0x1F0CF, 0x1F0CF),
+        (0x1F18E, 0x1F18E),
+        (0x1F191, 0x1F19A),
+        (0x1F200, 0x1F251),
+        (0x1F300, 0x1F64F),
+        (0x1F680, 0x1F6FF),
+        (0x1F900, 0x1F9FF),
+        (0x1FA70, 0x1FAFF),
+        (0x20000, 0x3FFFD),
+    ];
+
+    let is_in = |ranges: &[(u32, u32)]| {
+        ranges
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&(c as u32)))
+    };
+
+    if c == '\u{200D}' || is_in(ZERO_WIDTH) {
+        0
+    } else if is_in(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(line: &str) -> Vec<String> {
+        split_row(line).into_iter().map(|cell| cell.text).collect()
+    }
+
+    fn reformatted(lines: &[&str]) -> Vec<String> {
+        Table::parse(lines).unwrap().to_lines()
+    }
+
+    #[test]
+    fn split_with_and_without_outer_pipes() {
+        assert_eq!(texts("| a | b |"), ["a", "b"]);
+        assert_eq!(texts("a | b"), ["a", "b"]);
+        assert_eq!(texts("| a | b"), ["a", "b"]);
+        assert_eq!(texts("a | b |  "), ["a", "b"]);
+        assert_eq!(texts("  |a|b|"), ["a", "b"]);
+    }
+
+    #[test]
+    fn split_empty_cells() {
+        assert_eq!(texts("| | b |"), ["", "b"]);
+        assert_eq!(texts("| a | |"), ["a", ""]);
+        assert_eq!(texts("|   |"), [""]);
+        assert!(texts("|").is_empty());
+    }
+
+    #[test]
+    fn split_escaped_pipes() {
+        assert_eq!(texts(r"| a \| b | c |"), [r"a \| b", "c"]);
+        assert_eq!(texts(r"| a \\| b |"), [r"a \\", "b"]);
+        assert_eq!(texts(r"| `a \| b` |"), [r"`a \| b`"]);
+        assert!(!is_row(r"not \| a table"));
+    }
+
+    #[test]
+    fn split_ranges() {
+        let cells = split_row("| ab |  |é|");
+        assert_eq!(cells[0].range, 2..4);
+        assert_eq!(cells[0].bounds, 1..5);
+        assert_eq!(cells[1].range, 7..7);
+        assert_eq!(cells[1].bounds, 6..8);
+        assert_eq!(cells[2].range, 9..10);
+    }
+
+    #[test]
+    fn separator_row() {
+        assert!(is_separator_row("| --- | :-: | --: | :-- |"));
+        assert!(is_separator_row("---|---"));
+        assert!(is_separator_row("|-|"));
+        assert!(!is_separator_row("| --- | a |"));
+        assert!(!is_separator_row("| --- | |"));
+        assert!(!is_separator_row("---"));
+        assert!(!is_separator_row("| : |"));
+    }
+
+    #[test]
+    fn column_at_offset() {
+        let line = "| a | bc |";
+        assert_eq!(column_at(line, 0), 0);
+        assert_eq!(column_at(line, 3), 0);
+        assert_eq!(column_at(line, 4), 0);
+        assert_eq!(column_at(line, 5), 1);
+        assert_eq!(column_at(line, 10), 1);
+        assert_eq!(column_at("a | b", 5), 1);
+    }
+
+    #[test]
+    fn range_of_table() {
+        let lines = [
+            "Some text",
+            "| a | b |",
+            "| - | - |",
+            "| 1 | 2 |",
+            "3 | 4",
+            "",
+            "| c |",
+        ];
+        assert_eq!(table_range(&lines, 0), None);
+        assert_eq!(table_range(&lines, 1), Some(1..5));
+        assert_eq!(table_range(&lines, 2), Some(1..5));
+        assert_eq!(table_range(&lines, 4), Some(1..5));
+        assert_eq!(table_range(&lines, 5), None);
+        assert_eq!(table_range(&lines, 6), None);
+        assert_eq!(table_range(&lines, 7), None);
+    }
+
+    #[test]
+    fn range_of_table_after_rows_with_pipes() {
+        let lines = ["a | b", "| c | d |", "|---|---|", "| e | f |"];
+        assert_eq!(table_range(&lines, 0), None);
+        assert_eq!(table_range(&lines, 1), Some(1..4));
+        assert_eq!(table_range(&lines, 3), Some(1..4));
+
+        let lines = ["| a | b |", "| c | d |"];
+        assert_eq!(table_range(&lines, 0), None);
+    }
+
+    #[test]
+    fn parse() {
+        let table = Table::parse(&["| a | b |", "|:--|--:|", "| 1 |"]).unwrap();
+        assert_eq!(table.alignments, [Alignment::Left, Alignment::Right]);
+        assert_eq!(table.rows, [vec!["a", "b"], vec!["1"]]);
+
+        assert_eq!(Table::parse(&["| a | b |", "| 1 | 2 |"]), None);
+        assert_eq!(Table::parse(&["| a |"]), None);
+    }
+
+    #[test]
+    fn align_columns() {
+        assert_eq!(
+            reformatted(&["|a|long header|", "|-|-|", "|longer cell|b|"]),
+            [
+                "| a           | long header |",
+                "| ----------- | ----------- |",
+                "| longer cell | b           |",
+            ]
+        );
+    }
+
+    #[test]
+    fn align_by_separator() {
+        assert_eq!(
+            reformatted(&[
+                "| left | center | right | none |",
+                "| :- | :-: | -: | - |",
+                "| a | b | c | d |",
+            ]),
+            [
+                "| left | center | right | none |",
+                "| :--- | :----: | ----: | ---- |",
+                "| a    |   b    |     c | d    |",
+            ]
+        );
+    }
+
+    #[test]
+    fn align_missing_and_extra_cells() {
+        assert_eq!(
+            reformatted(&["a | b", "--- | ---", "1", "2 | 3 | 4"]),
+            [
+                "| a   | b   |     |",
+                "| --- | --- | --- |",
+                "| 1   |     |     |",
+                "| 2   | 3   | 4   |",
+            ]
+        );
+    }
+
+    #[test]
+    fn align_escaped_pipes() {
+        assert_eq!(
+            reformatted(&[r"| a \| b | c |", "|-|-|", "| d | e |"]),
+            [r"| a \| b | c   |", "| ------ | --- |", "| d      | e   |",]
+        );
+    }
+
+    #[test]
+    fn align_unicode() {
+        assert_eq!(
+            reformatted(&[
+                "| 名前 | café | e\u{301}t\u{e9} |",
+                "|-|-|-|",
+                "| a | b | c |"
+            ]),
+            [
+                "| 名前 | café | e\u{301}té |",
+                "| ---- | ---- | --- |",
+                "| a    | b    | c   |",
+            ]
+        );
+        assert_eq!(display_width("🎉!"), 3);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+    }
+
+    #[test]
+    fn empty_table() {
+        assert_eq!(
+            Table::empty(2, 1).to_lines(),
+            ["|     |     |", "| --- | --- |", "|     |     |"]
+        );
+        assert_eq!(
+            Table::parse(&["|     |     |", "| --- | --- |"]),
+            Some(Table::empty(2, 0))
+        );
+    }
+
+    #[test]
+    fn reformat_keeps_cursor_in_cell() {
+        let lines = ["|a|long header|", "|-|-|", "|longer cell|b|"];
+
+        // In the middle of `header`
+        let (new_lines, cursor) = reformat(&lines, (0, 10)).unwrap();
+        assert_eq!(cursor, (0, 23));
+        assert_eq!(&new_lines[0][23..], "ader |");
+
+        // At the end of `b`
+        let (_, cursor) = reformat(&lines, (2, 14)).unwrap();
+        assert_eq!(cursor, (2, 17));
+
+        // In the spaces before a cell, which are moved to the start of its text
+        let (_, cursor) = reformat(&["|  a  |", "|-|"], (0, 1)).unwrap();
+        assert_eq!(cursor, (0, 2));
+
+        // In a cell that was missing
+        let (_, cursor) = reformat(&["| a | b |", "|-|-|", "| c"], (2, 3)).unwrap();
+        assert_eq!(cursor, (2, 3));
+
+        assert_eq!(reformat(&["| a |"], (0, 0)), None);
+    }
+
+    #[test]
+    fn reformat_keeps_cursor_after_wide_characters() {
+        let lines = ["| 名前 | b |", "|-|-|", "| c | 名前です |"];
+        let (new_lines, cursor) = reformat(&lines, (2, 7)).unwrap();
+        assert_eq!(new_lines[2], "| c    | 名前です |");
+        assert_eq!(cursor, (2, 10));
+    }
+
+    #[test]
+    fn next_cell() {
+        let lines = ["| a | b |", "| - | - |", "| c |  |"];
+
+        assert_eq!(
+            neighbor_cell(&lines, (0, 2), Direction::Forward),
+            Some((0, 6..7))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (0, 6), Direction::Forward),
+            Some((2, 2..3))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (1, 3), Direction::Forward),
+            Some((2, 2..3))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (2, 0), Direction::Forward),
+            Some((2, 6..6))
+        );
+        assert_eq!(neighbor_cell(&lines, (2, 7), Direction::Forward), None);
+    }
+
+    #[test]
+    fn previous_cell() {
+        let lines = ["| a | b |", "| - | - |", "| c |  |"];
+
+        assert_eq!(
+            neighbor_cell(&lines, (2, 7), Direction::Backward),
+            Some((2, 2..3))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (2, 2), Direction::Backward),
+            Some((0, 6..7))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (1, 3), Direction::Backward),
+            Some((0, 6..7))
+        );
+        assert_eq!(neighbor_cell(&lines, (0, 3), Direction::Backward), None);
+    }
+
+    #[test]
+    fn next_cell_without_outer_pipes() {
+        let lines = ["a | b", "--|--", "c | d"];
+        assert_eq!(
+            neighbor_cell(&lines, (0, 0), Direction::Forward),
+            Some((0, 4..5))
+        );
+        assert_eq!(
+            neighbor_cell(&lines, (2, 0), Direction::Forward),
+            Some((2, 4..5))
+        );
+    }
+}
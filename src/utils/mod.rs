@@ -0,0 +1,312 @@
+pub mod background;
+mod edit_profiler;
+pub mod file_manager;
+pub mod fuzzy;
+pub mod html2md;
+pub mod markdown_table;
+pub mod palette;
+pub mod print_layout;
+mod recent_list;
+pub mod safe_path;
+mod ticker;
+
+pub use self::{
+    edit_profiler::{is_profile_env_value, EditProfiler, PROFILE_ENV_VAR},
+    recent_list::RecentList,
+    ticker::Ticker,
+};
+
+use futures_channel::oneshot;
+use futures_util::future::{self, Either};
+use gtk::{gdk, gio, glib, prelude::*};
+
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::core::{safe_file_extension, safe_file_stem, unique_file_name};
+
+// Taken from fractal-next GPLv3
+// See https://gitlab.gnome.org/GNOME/fractal/-/blob/fractal-next/src/utils.rs
+/// Spawns a future in the main context
+#[macro_export]
+macro_rules! spawn {
+    ($future:expr) => {
+        let ctx = glib::MainContext::default();
+        ctx.spawn_local($future);
+    };
+    ($priority:expr, $future:expr) => {
+        let ctx = glib::MainContext::default();
+        ctx.spawn_local_with_priority($priority, $future);
+    };
+}
+
+/// Pushes a function to be executed in the main thread pool
+#[macro_export]
+macro_rules! spawn_blocking {
+    ($function:expr) => {
+        crate::THREAD_POOL.push_future($function).unwrap()
+    };
+}
+
+pub fn default_notes_dir() -> PathBuf {
+    let mut data_dir = glib::user_data_dir();
+    data_dir.push("Notes");
+    data_dir
+}
+
+/// A path in `base_path` for a new file starting with `file_name_prefix`, with only safe
+/// characters in its name and not taken by any file there, even one differing only in case
+pub fn generate_unique_path(
+    base_path: impl AsRef<Path>,
+    file_name_prefix: &str,
+    extension: Option<impl AsRef<OsStr>>,
+) -> PathBuf {
+    let base_path = base_path.as_ref();
+
+    let formatted_time = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S-%f");
+    let file_stem = format!("{}-{}", safe_file_stem(file_name_prefix), formatted_time);
+    let extension = extension
+        .as_ref()
+        .and_then(|extension| extension.as_ref().to_str())
+        .and_then(safe_file_extension);
+
+    let existing_names = fs::read_dir(base_path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_lowercase))
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    base_path.join(unique_file_name(
+        &file_stem,
+        extension.as_deref(),
+        &existing_names,
+    ))
+}
+
+/// Choices of the `sidebar-row-detail` setting, in the order they are shown in the preferences
+pub const ROW_DETAIL_CHOICES: [&str; 4] = ["date", "preview", "tags", "date-preview"];
+
+/// Choices of the `wrap-mode` setting, in the order they are shown in the preferences
+pub const WRAP_MODE_CHOICES: [&str; 3] = ["word", "char", "none"];
+
+/// How the text of notes is wrapped for a choice of the `wrap-mode` setting
+pub fn wrap_mode_from_setting(choice: &str) -> gtk::WrapMode {
+    match choice {
+        "char" => gtk::WrapMode::Char,
+        "none" => gtk::WrapMode::None,
+        _ => gtk::WrapMode::Word,
+    }
+}
+
+/// Clipboard content with both `html` and `plain` text, so apps that support formatting paste
+/// the HTML and the rest paste the plain text
+pub fn formatted_text_provider(html: &str, plain: &str) -> gdk::ContentProvider {
+    gdk::ContentProvider::new_union(&[
+        gdk::ContentProvider::for_bytes("text/html", &glib::Bytes::from(html.as_bytes())),
+        gdk::ContentProvider::for_value(&plain.to_value()),
+    ])
+}
+
+/// Run `future` until it is done, or until `cancellable` is cancelled, which drops it and
+/// fails with [`gio::IOErrorEnum::Cancelled`]. Dropping the futures of gio stops their I/O.
+pub async fn or_cancelled<T>(
+    cancellable: &gio::Cancellable,
+    future: impl Future<Output = T>,
+) -> Result<T, glib::Error> {
+    cancellable.set_error_if_cancelled()?;
+
+    let (cancelled_sender, cancelled_receiver) = oneshot::channel();
+    let cancelled_sender = Mutex::new(Some(cancelled_sender));
+    let handler_id = cancellable.connect_cancelled(move |_| {
+        if let Some(cancelled_sender) = cancelled_sender.lock().unwrap().take() {
+            let _ = cancelled_sender.send(());
+        }
+    });
+
+    let output = future::select(Box::pin(future), cancelled_receiver).await;
+    glib::ObjectExt::disconnect(cancellable, handler_id);
+
+    match output {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(glib::Error::new(
+            gio::IOErrorEnum::Cancelled,
+            "Operation was cancelled",
+        )),
+    }
+}
+
+/// Calls that have to wait until something is ready (e.g., a loaded session) before running
+pub struct DeferredQueue<T> {
+    is_ready: bool,
+    pending: Vec<Box<dyn FnOnce(&T)>>,
+}
+
+impl<T> Default for DeferredQueue<T> {
+    fn default() -> Self {
+        Self {
+            is_ready: false,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for DeferredQueue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeferredQueue")
+            .field("is_ready", &self.is_ready)
+            .field("n_pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl<T> DeferredQueue<T> {
+    pub const fn is_ready(&self) -> bool {
+        self.is_ready
+    }
+
+    /// Queue `f` until [`DeferredQueue::set_ready`] is called. If it is already ready, `f` is
+    /// given back so the caller can run it right away.
+    pub fn push(&mut self, f: Box<dyn FnOnce(&T)>) -> Option<Box<dyn FnOnce(&T)>> {
+        if self.is_ready {
+            return Some(f);
+        }
+
+        self.pending.push(f);
+        None
+    }
+
+    /// Mark as ready and take the queued calls in the order they were pushed
+    ///
+    /// The calls are returned instead of being run here, so they can push to this queue
+    /// without borrowing it twice.
+    pub fn set_ready(&mut self) -> Vec<Box<dyn FnOnce(&T)>> {
+        self.is_ready = true;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::run_on_gtk_thread;
+
+    #[test]
+    fn wrap_modes_from_setting() {
+        assert_eq!(
+            WRAP_MODE_CHOICES.map(wrap_mode_from_setting),
+            [
+                gtk::WrapMode::Word,
+                gtk::WrapMode::Char,
+                gtk::WrapMode::None
+            ]
+        );
+        assert_eq!(wrap_mode_from_setting(""), gtk::WrapMode::Word);
+    }
+
+    fn read_mime_type(provider: &gdk::ContentProvider, mime_type: &str) -> String {
+        let stream = gtk::gio::MemoryOutputStream::new_resizable();
+
+        glib::MainContext::new()
+            .block_on(async {
+                provider
+                    .write_mime_type_future(mime_type, &stream, glib::PRIORITY_DEFAULT)
+                    .await?;
+                stream.close_future(glib::PRIORITY_DEFAULT).await
+            })
+            .unwrap();
+
+        String::from_utf8(stream.steal_as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn formatted_text_provider_formats() {
+        run_on_gtk_thread(|| {
+            let provider = formatted_text_provider("<p><em>Hi</em> &amp; bye</p>\n", "*Hi* & bye");
+
+            let formats = provider.formats();
+            assert!(formats.contain_mime_type("text/html"));
+            assert!(formats.contain_gtype(String::static_type()));
+
+            assert_eq!(
+                read_mime_type(&provider, "text/html"),
+                "<p><em>Hi</em> &amp; bye</p>\n"
+            );
+            assert_eq!(
+                read_mime_type(&provider, "text/plain;charset=utf-8"),
+                "*Hi* & bye"
+            );
+        });
+    }
+
+    #[test]
+    fn or_cancelled_stops_future() {
+        let ctx = glib::MainContext::new();
+
+        let cancellable = gio::Cancellable::new();
+        let output = ctx.block_on(or_cancelled(&cancellable, async { 1 }));
+        assert_eq!(output.unwrap(), 1);
+
+        let res = ctx.block_on(future::join(
+            or_cancelled(&cancellable, future::pending::<()>()),
+            async { cancellable.cancel() },
+        ));
+        assert!(res.0.unwrap_err().matches(gio::IOErrorEnum::Cancelled));
+
+        // Already cancelled, so it doesn't run at all
+        let res = ctx.block_on(or_cancelled::<()>(&cancellable, async { unreachable!() }));
+        assert!(res.unwrap_err().matches(gio::IOErrorEnum::Cancelled));
+    }
+
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn deferred_queue_runs_in_order_when_ready() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let mut queue = DeferredQueue::<u32>::default();
+        assert!(!queue.is_ready());
+
+        for i in 0..3 {
+            let calls = Rc::clone(&calls);
+            let f = queue.push(Box::new(move |value: &u32| {
+                calls.borrow_mut().push(value + i);
+            }));
+            assert!(f.is_none());
+        }
+        assert!(calls.borrow().is_empty());
+
+        for f in queue.set_ready() {
+            f(&10);
+        }
+        assert!(queue.is_ready());
+        assert_eq!(*calls.borrow(), vec![10, 11, 12]);
+
+        // Nothing is left to run
+        assert!(queue.set_ready().is_empty());
+    }
+
+    #[test]
+    fn deferred_queue_gives_back_when_ready() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let mut queue = DeferredQueue::<u32>::default();
+        assert!(queue.set_ready().is_empty());
+
+        let calls_clone = Rc::clone(&calls);
+        let f = queue
+            .push(Box::new(move |value: &u32| {
+                calls_clone.borrow_mut().push(*value);
+            }))
+            .expect("Queue is already ready");
+        f(&5);
+        assert_eq!(*calls.borrow(), vec![5]);
+    }
+}
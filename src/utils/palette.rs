@@ -0,0 +1,392 @@
+use gtk::{gdk, prelude::*};
+
+use std::fmt::Write;
+
+use crate::{
+    core::{ChangeMark, LineChange, NoteColor},
+    Application,
+};
+
+/// Choices of the `color-palette` setting, in the order they are shown in the preferences
+pub const PALETTE_CHOICES: [&str; 3] = ["auto", "standard", "color-blind-safe"];
+
+/// Color of links in the text of notes
+pub const LINK: Rgb = Rgb(0x35_84_e4);
+/// Color of the highlight of what is scrolled to in a note
+pub const HIGHLIGHT: Rgb = Rgb(0xf5_c2_12);
+
+/// Opacity of the background of the lines of a diff
+const DIFF_ALPHA: f32 = 0.25;
+
+/// A color without opacity, as `0xrrggbb`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u32);
+
+impl Rgb {
+    pub fn to_rgba(self, alpha: f32) -> gdk::RGBA {
+        let channel = |shift: u32| ((self.0 >> shift) & 0xff) as f32 / 255.0;
+        gdk::RGBA::new(channel(16), channel(8), channel(0), alpha)
+    }
+
+    fn to_css(self) -> String {
+        format!("#{:06x}", self.0)
+    }
+}
+
+/// Which set of colors is used for what shows a state, like note colors, diffs, and errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteKind {
+    /// The GNOME palette
+    Standard,
+    /// Colors that can be told apart without seeing red and green, with enough contrast to
+    /// the background, and with states also shown by something else than their hue
+    ColorBlindSafe,
+}
+
+impl PaletteKind {
+    /// The palette for a choice of the `color-palette` setting, where `auto` follows the high
+    /// contrast style
+    pub fn from_setting(choice: &str, is_high_contrast: bool) -> Self {
+        match choice {
+            "standard" => Self::Standard,
+            "color-blind-safe" => Self::ColorBlindSafe,
+            _ if is_high_contrast => Self::ColorBlindSafe,
+            _ => Self::Standard,
+        }
+    }
+}
+
+/// How a changed line of a diff is highlighted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffStyle {
+    pub background: gdk::RGBA,
+    pub is_struck_through: bool,
+}
+
+/// The colors of a [`PaletteKind`] on a light or dark background. Every color that shows a
+/// state is taken from here, so switching the palette switches them all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    kind: PaletteKind,
+    is_dark: bool,
+}
+
+impl Palette {
+    pub const fn new(kind: PaletteKind, is_dark: bool) -> Self {
+        Self { kind, is_dark }
+    }
+
+    /// The palette chosen in the settings, for the current style
+    pub fn current() -> Self {
+        let style_manager = adw::StyleManager::default();
+        let choice = Application::default().settings().string("color-palette");
+
+        Self::new(
+            PaletteKind::from_setting(&choice, style_manager.is_high_contrast()),
+            style_manager.is_dark(),
+        )
+    }
+
+    pub fn note_color(self, color: NoteColor) -> Option<Rgb> {
+        let rgb = match (self.kind, color) {
+            (_, NoteColor::None) => return None,
+            (PaletteKind::Standard, NoteColor::Red) => 0xe0_1b_24,
+            (PaletteKind::Standard, NoteColor::Orange) => 0xff_78_00,
+            (PaletteKind::Standard, NoteColor::Yellow) => 0xf6_d3_2d,
+            (PaletteKind::Standard, NoteColor::Green) => 0x33_d1_7a,
+            (PaletteKind::Standard, NoteColor::Blue) => 0x35_84_e4,
+            (PaletteKind::Standard, NoteColor::Purple) => 0x91_41_ac,
+            (PaletteKind::Standard, NoteColor::Pink) => 0xdc_8a_dd,
+            (PaletteKind::Standard, NoteColor::Brown) => 0x98_6a_44,
+            // From the Okabe-Ito palette, with a purple and a brown that stay apart from it
+            (PaletteKind::ColorBlindSafe, NoteColor::Red) => 0xd5_5e_00,
+            (PaletteKind::ColorBlindSafe, NoteColor::Orange) => 0xe6_9f_00,
+            (PaletteKind::ColorBlindSafe, NoteColor::Yellow) => 0xf0_e4_42,
+            (PaletteKind::ColorBlindSafe, NoteColor::Green) => 0x00_9e_73,
+            (PaletteKind::ColorBlindSafe, NoteColor::Blue) => 0x00_72_b2,
+            (PaletteKind::ColorBlindSafe, NoteColor::Purple) => 0x6a_3d_9a,
+            (PaletteKind::ColorBlindSafe, NoteColor::Pink) => 0xcc_79_a7,
+            (PaletteKind::ColorBlindSafe, NoteColor::Brown) => 0x8c_51_0a,
+        };
+
+        Some(Rgb(rgb))
+    }
+
+    /// How a line of a diff with `change` is highlighted, if it is
+    pub fn diff_style(self, change: LineChange) -> Option<DiffStyle> {
+        let (rgb, is_struck_through) = match (self.kind, change) {
+            (_, LineChange::Unchanged) => return None,
+            (PaletteKind::Standard, LineChange::Added) => (0x2e_c2_7d, false),
+            (PaletteKind::Standard, LineChange::Removed) => (0xe0_1c_24, false),
+            (PaletteKind::ColorBlindSafe, LineChange::Added) => (0x00_72_b2, false),
+            (PaletteKind::ColorBlindSafe, LineChange::Removed) => (0xd5_5e_00, true),
+        };
+
+        Some(DiffStyle {
+            background: Rgb(rgb).to_rgba(DIFF_ALPHA),
+            is_struck_through,
+        })
+    }
+
+    /// Color of the change bar next to a line with `mark`. Removed lines are also told apart
+    /// by the shape of their mark.
+    pub fn change_mark_color(self, mark: ChangeMark) -> Rgb {
+        let rgb = match (self.kind, mark) {
+            (PaletteKind::Standard, ChangeMark::Added) => 0x2e_c2_7d,
+            (PaletteKind::Standard, ChangeMark::Modified) => 0x35_84_e4,
+            (PaletteKind::Standard, ChangeMark::Removed) => 0xe0_1c_24,
+            (PaletteKind::ColorBlindSafe, ChangeMark::Added) => 0x00_72_b2,
+            (PaletteKind::ColorBlindSafe, ChangeMark::Modified) => 0xe6_9f_00,
+            (PaletteKind::ColorBlindSafe, ChangeMark::Removed) => 0xd5_5e_00,
+        };
+
+        Rgb(rgb)
+    }
+
+    /// Create or update the tags of `buffer` named `added_tag` and `removed_tag`, which
+    /// highlight the lines of a diff
+    pub fn set_up_diff_tags(self, buffer: &gtk::TextBuffer, added_tag: &str, removed_tag: &str) {
+        let tag_table = buffer.tag_table();
+
+        for (name, change) in [
+            (added_tag, LineChange::Added),
+            (removed_tag, LineChange::Removed),
+        ] {
+            let style = self.diff_style(change).unwrap();
+            let tag = tag_table
+                .lookup(name)
+                .or_else(|| buffer.create_tag(Some(name), &[]))
+                .unwrap();
+            tag.set_paragraph_background_rgba(Some(&style.background));
+            tag.set_strikethrough(style.is_struck_through);
+        }
+    }
+
+    /// Colors of errors, successes, and warnings, which replace the ones of the style, or
+    /// `None` to keep them
+    fn status_colors(self) -> Option<[(&'static str, Rgb); 3]> {
+        match (self.kind, self.is_dark) {
+            (PaletteKind::Standard, _) => None,
+            (PaletteKind::ColorBlindSafe, false) => Some([
+                ("error_color", Rgb(0xb3_3c_00)),
+                ("success_color", Rgb(0x00_5f_96)),
+                ("warning_color", Rgb(0x7a_5a_00)),
+            ]),
+            (PaletteKind::ColorBlindSafe, true) => Some([
+                ("error_color", Rgb(0xff_8f_52)),
+                ("success_color", Rgb(0x56_b4_e9)),
+                ("warning_color", Rgb(0xf0_e4_42)),
+            ]),
+        }
+    }
+
+    /// The stylesheet that applies the palette to the widgets
+    pub fn css(self) -> String {
+        let mut css = String::new();
+
+        for (name, rgb) in self.status_colors().into_iter().flatten() {
+            let _ = writeln!(css, "@define-color {} {};", name, rgb.to_css());
+        }
+
+        for color in NoteColor::ALL {
+            if let Some(rgb) = self.note_color(color) {
+                let _ = writeln!(
+                    css,
+                    "colordot.{} {{ color: {}; }}",
+                    color.name(),
+                    rgb.to_css()
+                );
+            }
+        }
+
+        css
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LIGHT_BACKGROUNDS: [Rgb; 2] = [Rgb(0xff_ff_ff), Rgb(0xfa_fa_fa)];
+    const DARK_BACKGROUNDS: [Rgb; 2] = [Rgb(0x1e_1e_1e), Rgb(0x24_24_24)];
+
+    /// WCAG contrast ratio of text
+    const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+    fn relative_luminance(rgb: Rgb) -> f64 {
+        let channel = |shift: u32| {
+            let value = f64::from((rgb.0 >> shift) & 0xff) / 255.0;
+            if value <= 0.03928 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(16) + 0.7152 * channel(8) + 0.0722 * channel(0)
+    }
+
+    fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+        let (a, b) = (relative_luminance(a), relative_luminance(b));
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// The `@define-color` rules of `css`
+    fn defined_colors(css: &str) -> Vec<(String, Rgb)> {
+        css.lines()
+            .filter_map(|line| {
+                let rule = line.strip_prefix("@define-color ")?.strip_suffix(';')?;
+                let (name, value) = rule.split_once(' ')?;
+                let rgb = u32::from_str_radix(value.strip_prefix('#')?, 16).ok()?;
+                Some((name.to_string(), Rgb(rgb)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kind_from_setting() {
+        assert_eq!(
+            PaletteKind::from_setting("auto", false),
+            PaletteKind::Standard
+        );
+        assert_eq!(
+            PaletteKind::from_setting("auto", true),
+            PaletteKind::ColorBlindSafe
+        );
+        assert_eq!(
+            PaletteKind::from_setting("standard", true),
+            PaletteKind::Standard
+        );
+        assert_eq!(
+            PaletteKind::from_setting("color-blind-safe", false),
+            PaletteKind::ColorBlindSafe
+        );
+        assert_eq!(
+            PaletteKind::from_setting("unknown", false),
+            PaletteKind::Standard
+        );
+        assert_eq!(PALETTE_CHOICES[0], "auto");
+    }
+
+    #[test]
+    fn rgb_to_rgba() {
+        assert_eq!(
+            Rgb(0x35_84_e4).to_rgba(0.5),
+            gdk::RGBA::new(
+                0x35 as f32 / 255.0,
+                0x84 as f32 / 255.0,
+                0xe4 as f32 / 255.0,
+                0.5
+            )
+        );
+        assert_eq!(Rgb(0x05_00_ff).to_css(), "#0500ff");
+    }
+
+    #[test]
+    fn note_colors() {
+        for kind in [PaletteKind::Standard, PaletteKind::ColorBlindSafe] {
+            let palette = Palette::new(kind, false);
+            assert_eq!(palette.note_color(NoteColor::None), None);
+
+            let colors = NoteColor::ALL
+                .into_iter()
+                .filter_map(|color| palette.note_color(color))
+                .collect::<Vec<_>>();
+            assert_eq!(colors.len(), NoteColor::ALL.len() - 1);
+            for (index, color) in colors.iter().enumerate() {
+                assert!(!colors[index + 1..].contains(color));
+            }
+        }
+
+        assert_eq!(
+            Palette::new(PaletteKind::Standard, true).note_color(NoteColor::Blue),
+            Some(Rgb(0x35_84_e4))
+        );
+    }
+
+    #[test]
+    fn palette_switch() {
+        let standard = Palette::new(PaletteKind::Standard, false);
+        let safe = Palette::new(PaletteKind::ColorBlindSafe, false);
+
+        assert_ne!(standard.css(), safe.css());
+        assert_ne!(
+            standard.diff_style(LineChange::Added),
+            safe.diff_style(LineChange::Added)
+        );
+        assert_ne!(
+            standard.note_color(NoteColor::Red),
+            safe.note_color(NoteColor::Red)
+        );
+
+        // The status colors of the style are kept
+        assert!(defined_colors(&standard.css()).is_empty());
+        assert!(standard.css().contains("colordot.red { color: #e01b24; }"));
+        assert!(safe.css().contains("colordot.red { color: #d55e00; }"));
+    }
+
+    #[test]
+    fn diff_styles() {
+        for kind in [PaletteKind::Standard, PaletteKind::ColorBlindSafe] {
+            let palette = Palette::new(kind, false);
+            assert_eq!(palette.diff_style(LineChange::Unchanged), None);
+        }
+
+        // Removed lines are told apart by more than their hue
+        let safe = Palette::new(PaletteKind::ColorBlindSafe, false);
+        let added = safe.diff_style(LineChange::Added).unwrap();
+        let removed = safe.diff_style(LineChange::Removed).unwrap();
+        assert!(!added.is_struck_through);
+        assert!(removed.is_struck_through);
+        assert_eq!(added.background.alpha(), DIFF_ALPHA);
+    }
+
+    #[test]
+    fn change_mark_colors() {
+        for kind in [PaletteKind::Standard, PaletteKind::ColorBlindSafe] {
+            let palette = Palette::new(kind, false);
+            let colors = [ChangeMark::Added, ChangeMark::Modified, ChangeMark::Removed]
+                .map(|mark| palette.change_mark_color(mark));
+
+            for (index, color) in colors.iter().enumerate() {
+                assert!(!colors[index + 1..].contains(color));
+            }
+        }
+
+        // Like the lines of a diff
+        let safe = Palette::new(PaletteKind::ColorBlindSafe, false);
+        assert_eq!(
+            safe.change_mark_color(ChangeMark::Added)
+                .to_rgba(DIFF_ALPHA),
+            safe.diff_style(LineChange::Added).unwrap().background
+        );
+    }
+
+    #[test]
+    fn status_color_contrast() {
+        for (is_dark, backgrounds) in [(false, LIGHT_BACKGROUNDS), (true, DARK_BACKGROUNDS)] {
+            let css = Palette::new(PaletteKind::ColorBlindSafe, is_dark).css();
+            let colors = defined_colors(&css);
+            assert_eq!(colors.len(), 3, "{}", css);
+
+            for (name, rgb) in colors {
+                for background in backgrounds {
+                    let ratio = contrast_ratio(rgb, background);
+                    assert!(
+                        ratio >= MIN_TEXT_CONTRAST,
+                        "`{}` {} has a contrast of {:.2} on {}",
+                        name,
+                        rgb.to_css(),
+                        ratio,
+                        background.to_css()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contrast_ratio_bounds() {
+        assert!((contrast_ratio(Rgb(0), Rgb(0xff_ff_ff)) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(LINK, LINK) - 1.0).abs() < f64::EPSILON);
+    }
+}
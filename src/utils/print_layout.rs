@@ -0,0 +1,279 @@
+use futures_channel::oneshot;
+use gettextrs::gettext;
+use gtk::{
+    cairo,
+    gdk_pixbuf::{Colorspace, Pixbuf},
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
+use gtk_source::prelude::*;
+
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use crate::core::{
+    fitted_size, image_path, image_sources, markdown_to_html, DEFAULT_LANGUAGE, MAX_PDF_IMAGE_SIZE,
+};
+
+/// Pixels of images per inch, like in web pages, to draw them at the size they are shown at
+const IMAGE_DPI: f64 = 96.0;
+/// Height of the box drawn in place of a missing image, in points
+const PLACEHOLDER_HEIGHT: f64 = 48.0;
+/// Size of the text in the box of a missing image, in points
+const PLACEHOLDER_FONT_SIZE: f64 = 11.0;
+
+/// An image of the notes, drawn on a page of its own after their text
+#[derive(Debug)]
+pub enum PrintedImage {
+    /// Decoded at most at [`MAX_PDF_IMAGE_SIZE`], so it is only drawn on the main thread
+    Decoded {
+        pixels: glib::Bytes,
+        has_alpha: bool,
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        /// Size of the image in the note, in pixels
+        shown_size: (f64, f64),
+    },
+    /// Its file doesn't exist or can't be read, so a box with its source is drawn instead
+    Missing { source: String },
+}
+
+impl PrintedImage {
+    pub fn missing_source(&self) -> Option<&str> {
+        match self {
+            Self::Missing { source } => Some(source),
+            Self::Decoded { .. } => None,
+        }
+    }
+
+    fn load(path: &Path, source: &str) -> Self {
+        let missing = || Self::Missing {
+            source: source.to_string(),
+        };
+
+        let (_, width, height) = match Pixbuf::file_info(path) {
+            Some(info) => info,
+            None => {
+                log::warn!("Image `{}` to export can't be read", path.display());
+                return missing();
+            }
+        };
+
+        let max_size = MAX_PDF_IMAGE_SIZE as f64;
+        let (scaled_width, scaled_height) =
+            fitted_size(width as f64, height as f64, max_size, max_size);
+
+        let pixbuf = match Pixbuf::from_file_at_scale(
+            path,
+            (scaled_width.round() as i32).max(1),
+            (scaled_height.round() as i32).max(1),
+            true,
+        ) {
+            Ok(pixbuf) => pixbuf,
+            Err(err) => {
+                log::warn!("Failed to load image `{}`: {:?}", path.display(), err);
+                return missing();
+            }
+        };
+
+        match pixbuf.read_pixel_bytes() {
+            Some(pixels) => Self::Decoded {
+                pixels,
+                has_alpha: pixbuf.has_alpha(),
+                width: pixbuf.width(),
+                height: pixbuf.height(),
+                rowstride: pixbuf.rowstride(),
+                shown_size: (width as f64, height as f64),
+            },
+            None => missing(),
+        }
+    }
+
+    /// Draw the image at the top of the page of `context`, at the size it is shown at, or
+    /// smaller if it doesn't fit
+    fn draw(&self, context: &gtk::PrintContext) -> Result<(), cairo::Error> {
+        let cr = context.cairo_context();
+        let points = context.dpi_x() / 72.0;
+
+        match self {
+            Self::Decoded {
+                pixels,
+                has_alpha,
+                width,
+                height,
+                rowstride,
+                shown_size: (shown_width, shown_height),
+            } => {
+                let pixbuf = Pixbuf::from_bytes(
+                    pixels,
+                    Colorspace::Rgb,
+                    *has_alpha,
+                    8,
+                    *width,
+                    *height,
+                    *rowstride,
+                );
+                let scale = context.dpi_x() / IMAGE_DPI;
+                let (drawn_width, drawn_height) = fitted_size(
+                    shown_width * scale,
+                    shown_height * scale,
+                    context.width(),
+                    context.height(),
+                );
+
+                cr.scale(drawn_width / *width as f64, drawn_height / *height as f64);
+                cr.set_source_pixbuf(&pixbuf, 0.0, 0.0);
+                cr.paint()?;
+            }
+            Self::Missing { source } => {
+                let box_height = PLACEHOLDER_HEIGHT * points;
+                cr.set_source_rgb(0.6, 0.6, 0.6);
+                cr.set_line_width(points);
+                cr.set_dash(&[4.0 * points], 0.0);
+                cr.rectangle(0.0, 0.0, context.width(), box_height);
+                cr.stroke()?;
+
+                let font_size = PLACEHOLDER_FONT_SIZE * points;
+                cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+                cr.set_font_size(font_size);
+                cr.move_to(font_size, (box_height + font_size) / 2.0);
+                cr.show_text(&gettext!("Missing image: {}", source))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The images of the Markdown `text`, from the notes in `notes_dir`, ready to be drawn in
+/// the order they are shown. This reads them, so it is done off the main thread.
+pub fn load_images(text: &str, notes_dir: &Path) -> Vec<PrintedImage> {
+    image_sources(&markdown_to_html(text))
+        .into_iter()
+        .filter_map(|source| {
+            let path = image_path(&source, notes_dir)?;
+
+            if path.is_file() {
+                Some(PrintedImage::load(&path, &source))
+            } else {
+                log::warn!("Image `{}` to export is missing", path.display());
+                Some(PrintedImage::Missing { source })
+            }
+        })
+        .collect()
+}
+
+/// Lay out `text` in pages like it is printed, with the page numbers at the bottom
+fn print_compositor(text: &str) -> gtk_source::PrintCompositor {
+    let buffer = gtk_source::Buffer::new(None);
+    buffer.set_language(
+        gtk_source::LanguageManager::default()
+            .language(DEFAULT_LANGUAGE)
+            .as_ref(),
+    );
+    buffer.set_text(text);
+
+    let compositor = gtk_source::PrintCompositor::builder()
+        .buffer(&buffer)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .highlight_syntax(true)
+        .print_footer(true)
+        .build();
+    compositor.set_footer_format(false, None, Some("%N / %Q"), None);
+    compositor
+}
+
+/// An operation that prints the pages of `compositor`, then a page for each of `images`.
+/// `on_progress` is called with the fraction of the pages laid out, which is done a part at a
+/// time in the main context so it stays responsive.
+fn print_operation(
+    compositor: &gtk_source::PrintCompositor,
+    images: Vec<PrintedImage>,
+    on_progress: impl Fn(f64) + 'static,
+) -> gtk::PrintOperation {
+    let operation = gtk::PrintOperation::new();
+    operation.set_allow_async(true);
+
+    let n_images = images.len() as i32;
+    operation.connect_paginate(clone!(@strong compositor => move |operation, context| {
+        let is_done = compositor.paginate(context);
+        on_progress(compositor.pagination_progress());
+
+        if is_done {
+            operation.set_n_pages(compositor.n_pages() + n_images);
+        }
+
+        is_done
+    }));
+
+    let images = Rc::new(images);
+    operation.connect_draw_page(clone!(@strong compositor => move |_, context, page_nr| {
+        let n_text_pages = compositor.n_pages();
+        if page_nr < n_text_pages {
+            compositor.draw_page(context, page_nr);
+        } else if let Some(image) = images.get((page_nr - n_text_pages) as usize) {
+            if let Err(err) = image.draw(context) {
+                log::warn!("Failed to draw image on page {}: {:?}", page_nr, err);
+            }
+        }
+    }));
+
+    operation
+}
+
+/// Wait for `operation` to be done after it was run with `action`
+async fn run(
+    operation: &gtk::PrintOperation,
+    action: gtk::PrintOperationAction,
+    parent: Option<&gtk::Window>,
+) -> anyhow::Result<gtk::PrintOperationResult> {
+    let (sender, receiver) = oneshot::channel();
+    let sender = RefCell::new(Some(sender));
+    operation.connect_done(move |_, result| {
+        if let Some(sender) = sender.take() {
+            let _ = sender.send(result);
+        }
+    });
+
+    let result = match operation.run(action, parent)? {
+        gtk::PrintOperationResult::InProgress => receiver.await?,
+        result => result,
+    };
+
+    match result {
+        gtk::PrintOperationResult::Error => Err(operation
+            .error()
+            .map_or_else(|| anyhow::anyhow!("Failed to print"), Into::into)),
+        result => Ok(result),
+    }
+}
+
+/// Write the Markdown `text` to `file` as a PDF, with its `images` from [`load_images`] after
+/// it, calling `on_progress` like [`print_operation`]
+pub async fn export_pdf(
+    text: &str,
+    images: Vec<PrintedImage>,
+    file: &gio::File,
+    on_progress: impl Fn(f64) + 'static,
+) -> anyhow::Result<()> {
+    let path = file
+        .path()
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a local file", file.uri()))?;
+
+    let operation = print_operation(&print_compositor(text), images, on_progress);
+    operation.set_export_filename(&path);
+
+    match run(&operation, gtk::PrintOperationAction::Export, None).await? {
+        gtk::PrintOperationResult::Apply => Ok(()),
+        result => anyhow::bail!("PDF export ended with `{:?}`", result),
+    }
+}
+
+/// Show the print dialog for the Markdown `text`, which is laid out like in
+/// [`export_pdf`]. Cancelling the dialog is not an error.
+pub async fn print(text: &str, parent: Option<&gtk::Window>) -> anyhow::Result<()> {
+    let operation = print_operation(&print_compositor(text), Vec::new(), |_| {});
+    run(&operation, gtk::PrintOperationAction::PrintDialog, parent).await?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! A list of the most recently used items, such as the names of tags
+
+/// At most `capacity` items, from the most recently used, without duplicates
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentList<T> {
+    items: Vec<T>,
+    capacity: usize,
+}
+
+impl<T: PartialEq> RecentList<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// A list with `items`, starting with the most recent, like ones that were saved. Duplicates
+    /// and the items over `capacity` are dropped.
+    pub fn with_items(capacity: usize, items: impl IntoIterator<Item = T>) -> Self {
+        let mut list = Self::new(capacity);
+
+        for item in items {
+            if list.items.len() == capacity {
+                break;
+            }
+
+            if !list.items.contains(&item) {
+                list.items.push(item);
+            }
+        }
+
+        list
+    }
+
+    /// Make `item` the most recent, dropping the least recent if it is full
+    pub fn push(&mut self, item: T) {
+        self.items.retain(|other| *other != item);
+        self.items.insert(0, item);
+        self.items.truncate(self.capacity);
+    }
+
+    /// Only keep the items for which `f` returns true, like the tags that still exist. Returns
+    /// whether some were dropped.
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) -> bool {
+        let len = self.items.len();
+        self.items.retain(f);
+        self.items.len() != len
+    }
+
+    /// The items, starting with the most recent
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push() {
+        let mut list = RecentList::new(3);
+        assert!(list.is_empty());
+
+        list.push("a");
+        list.push("b");
+        assert_eq!(list.items(), ["b", "a"]);
+
+        // Used again, so moved to the front instead of added twice
+        list.push("a");
+        assert_eq!(list.items(), ["a", "b"]);
+    }
+
+    #[test]
+    fn bounded() {
+        let mut list = RecentList::new(3);
+        for item in ["a", "b", "c", "d"] {
+            list.push(item);
+        }
+        assert_eq!(list.items(), ["d", "c", "b"]);
+
+        list.push("b");
+        assert_eq!(list.items(), ["b", "d", "c"]);
+    }
+
+    #[test]
+    fn with_items() {
+        let list = RecentList::with_items(3, ["a", "b", "a", "c", "d"]);
+        assert_eq!(list.items(), ["a", "b", "c"]);
+
+        let list = RecentList::<&str>::with_items(0, ["a"]);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn retain() {
+        let mut list = RecentList::with_items(5, ["a", "deleted", "b"]);
+
+        assert!(list.retain(|item| *item != "deleted"));
+        assert_eq!(list.items(), ["a", "b"]);
+
+        assert!(!list.retain(|item| *item != "deleted"));
+        assert_eq!(list.items(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Why a path that comes from the notes, a repository, or an imported file can't be written
+#[derive(Debug, thiserror::Error)]
+pub enum UnsafePathError {
+    #[error("Path `{0}` is empty or has a nul byte")]
+    Invalid(String),
+    #[error("Path `{0}` is absolute")]
+    Absolute(String),
+    #[error("Path `{0}` goes out of its folder")]
+    Traversal(String),
+    #[error("Path `{path}` goes through a symlink to `{target}` out of its folder")]
+    Symlink { path: String, target: PathBuf },
+    #[error("Failed to check path `{path}`: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// The path of `relative` in `base`, checked to stay in it. Both `/` and `\` separate
+/// folders, as paths written on Windows end up in repositories too.
+///
+/// Fails for absolute paths, for `..` that goes out of `base`, and for folders in it that
+/// are symlinks out of it. The symlinks that stay in `base` are resolved, except for the last
+/// component, which is only checked, as writing to the returned path follows it.
+pub fn resolve_within(base: &Path, relative: &str) -> Result<PathBuf, UnsafePathError> {
+    let res = resolve(base, relative);
+
+    if let Err(ref err) = res {
+        log::warn!("Refusing unsafe path in `{}`: {}", base.display(), err);
+    }
+
+    res
+}
+
+fn resolve(base: &Path, relative: &str) -> Result<PathBuf, UnsafePathError> {
+    let components = normalized_components(relative)?;
+    let (last, parents) = components
+        .split_last()
+        .ok_or_else(|| UnsafePathError::Invalid(relative.to_string()))?;
+
+    let io_error = |source| UnsafePathError::Io {
+        path: relative.to_string(),
+        source,
+    };
+
+    let base = match fs::canonicalize(base) {
+        Ok(base) => base,
+        // Then nothing in it exists either, let alone symlinks
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(components
+                .iter()
+                .fold(base.to_path_buf(), |path, component| path.join(component)))
+        }
+        Err(err) => return Err(io_error(err)),
+    };
+
+    let mut path = base.clone();
+
+    for (index, component) in components.iter().enumerate() {
+        let next = path.join(component);
+
+        let metadata = match fs::symlink_metadata(&next) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                // The rest is created, so it can't be a symlink yet
+                return Ok(components[index + 1..]
+                    .iter()
+                    .fold(next, |path, component| path.join(component)));
+            }
+            Err(err) => return Err(io_error(err)),
+        };
+
+        if !metadata.file_type().is_symlink() {
+            path = next;
+            continue;
+        }
+
+        // Following a whole chain of symlinks, where a dangling one goes nowhere safe
+        let target = match fs::canonicalize(&next) {
+            Ok(target) => target,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(UnsafePathError::Symlink {
+                    path: relative.to_string(),
+                    target: fs::read_link(&next).unwrap_or(next),
+                });
+            }
+            Err(err) => return Err(io_error(err)),
+        };
+
+        if !target.starts_with(&base) {
+            return Err(UnsafePathError::Symlink {
+                path: relative.to_string(),
+                target,
+            });
+        }
+
+        path = if index < parents.len() {
+            target
+        } else {
+            path.join(last)
+        };
+    }
+
+    Ok(path)
+}
+
+/// The components of `relative` without `.` and with `..` applied, which must not go out
+/// of the folder it is relative to
+fn normalized_components(relative: &str) -> Result<Vec<&str>, UnsafePathError> {
+    if relative.contains('\0') {
+        return Err(UnsafePathError::Invalid(relative.to_string()));
+    }
+
+    if is_absolute(relative) {
+        return Err(UnsafePathError::Absolute(relative.to_string()));
+    }
+
+    let mut components = Vec::new();
+
+    for component in relative.split(['/', '\\']) {
+        match component {
+            "" | "." => (),
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(UnsafePathError::Traversal(relative.to_string()));
+                }
+            }
+            component => components.push(component),
+        }
+    }
+
+    Ok(components)
+}
+
+/// Whether `path` is absolute here or on Windows, like `/etc`, `\\server\share`, or `C:`
+fn is_absolute(path: &str) -> bool {
+    let mut chars = path.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some('/' | '\\'), _) => true,
+        (Some(drive), Some(':')) => drive.is_ascii_alphabetic(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::os::unix::fs::symlink;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "noteworthy-safe-path-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn relative_paths() {
+        let base = temp_dir("relative");
+        fs::create_dir_all(base.join("Folder")).unwrap();
+
+        for (relative, expected) in [
+            ("Note.md", "Note.md"),
+            ("./Note.md", "Note.md"),
+            ("Folder/Picture.png", "Folder/Picture.png"),
+            ("Folder//./Picture.png", "Folder/Picture.png"),
+            ("Folder/../Note.md", "Note.md"),
+            ("New/Deeper/File.txt", "New/Deeper/File.txt"),
+            ("..Note..md", "..Note..md"),
+            ("Folder/", "Folder"),
+        ] {
+            assert_eq!(
+                resolve_within(&base, relative).unwrap(),
+                base.join(expected),
+                "`{}`",
+                relative
+            );
+        }
+    }
+
+    #[test]
+    fn traversal() {
+        let base = temp_dir("traversal");
+
+        for relative in [
+            "..",
+            "../Note.md",
+            "../../etc/passwd",
+            "Folder/../../Note.md",
+            "./../Note.md",
+            "a/b/../../../Note.md",
+            "a/./../..",
+        ] {
+            assert!(
+                matches!(
+                    resolve_within(&base, relative),
+                    Err(UnsafePathError::Traversal(path)) if path == relative
+                ),
+                "`{}` was not rejected",
+                relative
+            );
+        }
+    }
+
+    #[test]
+    fn absolute() {
+        let base = temp_dir("absolute");
+
+        for relative in [
+            "/etc/passwd",
+            "/",
+            "\\Windows",
+            "\\\\server\\share",
+            "C:",
+            "c:/x",
+        ] {
+            assert!(
+                matches!(
+                    resolve_within(&base, relative),
+                    Err(UnsafePathError::Absolute(_))
+                ),
+                "`{}` was not rejected",
+                relative
+            );
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let base = temp_dir("invalid");
+
+        for relative in ["", ".", "./", "Folder/..", "Note\0.md"] {
+            assert!(
+                matches!(
+                    resolve_within(&base, relative),
+                    Err(UnsafePathError::Invalid(_))
+                ),
+                "`{}` was not rejected",
+                relative
+            );
+        }
+    }
+
+    #[test]
+    fn windows_separators() {
+        let base = temp_dir("windows-separators");
+
+        assert_eq!(
+            resolve_within(&base, "Folder\\Picture.png").unwrap(),
+            base.join("Folder/Picture.png")
+        );
+        assert_eq!(
+            resolve_within(&base, "Folder\\..\\Note.md").unwrap(),
+            base.join("Note.md")
+        );
+
+        for relative in [
+            "..\\Note.md",
+            "Folder\\..\\..\\Note.md",
+            "Folder/..\\../Note.md",
+        ] {
+            assert!(
+                matches!(
+                    resolve_within(&base, relative),
+                    Err(UnsafePathError::Traversal(_))
+                ),
+                "`{}` was not rejected",
+                relative
+            );
+        }
+    }
+
+    #[test]
+    fn symlinks_inside() {
+        let base = temp_dir("symlinks-inside");
+        fs::create_dir_all(base.join("Real")).unwrap();
+        symlink(base.join("Real"), base.join("Link")).unwrap();
+        symlink("Real", base.join("Relative Link")).unwrap();
+        symlink(base.join("Link"), base.join("Chain")).unwrap();
+
+        assert_eq!(
+            resolve_within(&base, "Link/Note.md").unwrap(),
+            base.join("Real/Note.md")
+        );
+        assert_eq!(
+            resolve_within(&base, "Relative Link/Note.md").unwrap(),
+            base.join("Real/Note.md")
+        );
+        assert_eq!(
+            resolve_within(&base, "Chain/Note.md").unwrap(),
+            base.join("Real/Note.md")
+        );
+
+        // The last one is not followed
+        assert_eq!(resolve_within(&base, "Link").unwrap(), base.join("Link"));
+    }
+
+    #[test]
+    fn symlinks_outside() {
+        let base = temp_dir("symlinks-outside");
+        let outside = temp_dir("symlinks-outside-target");
+        fs::create_dir_all(base.join("Folder")).unwrap();
+
+        symlink(&outside, base.join("Escape")).unwrap();
+        symlink("..", base.join("Folder/Up")).unwrap();
+        symlink("../..", base.join("Folder/Up Twice")).unwrap();
+        symlink(base.join("Escape"), base.join("Chain")).unwrap();
+        symlink(outside.join("File.txt"), base.join("File.txt")).unwrap();
+        symlink(base.join("Missing"), base.join("Dangling")).unwrap();
+
+        for relative in [
+            "Escape/Note.md",
+            "Folder/Up Twice/Note.md",
+            "Chain/Note.md",
+            "Chain",
+            "File.txt",
+            "Dangling/Note.md",
+        ] {
+            assert!(
+                matches!(
+                    resolve_within(&base, relative),
+                    Err(UnsafePathError::Symlink { path, .. }) if path == relative
+                ),
+                "`{}` was not rejected",
+                relative
+            );
+        }
+
+        // Up once only goes back to the base
+        assert_eq!(
+            resolve_within(&base, "Folder/Up/Note.md").unwrap(),
+            base.join("Note.md")
+        );
+    }
+
+    #[test]
+    fn missing_base() {
+        let base = temp_dir("missing-base").join("Missing");
+
+        assert_eq!(
+            resolve_within(&base, "Folder/Note.md").unwrap(),
+            base.join("Folder/Note.md")
+        );
+        assert!(resolve_within(&base, "../Note.md").is_err());
+    }
+}
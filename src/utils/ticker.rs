@@ -0,0 +1,102 @@
+use chrono::{Local, Timelike};
+use gtk::{
+    glib::{self, clone, subclass::Signal},
+    prelude::*,
+    subclass::prelude::*,
+};
+use once_cell::sync::Lazy;
+
+use std::time::Duration;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct Ticker;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Ticker {
+        const NAME: &'static str = "NwtyTicker";
+        type Type = super::Ticker;
+    }
+
+    impl ObjectImpl for Ticker {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> =
+                Lazy::new(
+                    || vec![Signal::builder("tick", &[], <()>::static_type().into()).build()],
+                );
+            SIGNALS.as_ref()
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Ticks at the start of every minute, for the times shown relative to now, so they all
+    /// change together and without a timeout each
+    pub struct Ticker(ObjectSubclass<imp::Ticker>);
+}
+
+thread_local! {
+    static TICKER: Ticker = Ticker::new();
+}
+
+impl Ticker {
+    fn new() -> Self {
+        let obj: Self = glib::Object::new(&[]).expect("Failed to create Ticker.");
+        obj.schedule_tick();
+        obj
+    }
+
+    pub fn connect_tick<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("tick", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    fn schedule_tick(&self) {
+        let now = Local::now();
+
+        glib::timeout_add_local_once(
+            until_next_minute(now.second(), now.nanosecond()),
+            clone!(@weak self as obj => move || {
+                obj.emit_by_name::<()>("tick", &[]);
+                obj.schedule_tick();
+            }),
+        );
+    }
+}
+
+impl Default for Ticker {
+    /// The ticker shared by everything on this thread
+    fn default() -> Self {
+        TICKER.with(Clone::clone)
+    }
+}
+
+/// Time from `second` and `nanosecond` of a minute to the start of the next one. The
+/// nanoseconds go past a billion during a leap second.
+fn until_next_minute(second: u32, nanosecond: u32) -> Duration {
+    let elapsed = Duration::from_secs(second.into()) + Duration::from_nanos(nanosecond.into());
+    Duration::from_secs(60).saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn until_next_minutes() {
+        assert_eq!(until_next_minute(0, 0), Duration::from_secs(60));
+        assert_eq!(
+            until_next_minute(59, 500_000_000),
+            Duration::from_millis(500)
+        );
+        assert_eq!(until_next_minute(59, 1_500_000_000), Duration::ZERO);
+    }
+}
@@ -0,0 +1,137 @@
+use gtk::{glib, graphene, gsk, prelude::*, subclass::prelude::*};
+
+use std::cell::Cell;
+
+use crate::core::NoteColor;
+
+const SIZE: i32 = 10;
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct ColorDot {
+        pub color: Cell<NoteColor>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ColorDot {
+        const NAME: &'static str = "NwtyColorDot";
+        type Type = super::ColorDot;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("colordot");
+        }
+    }
+
+    impl ObjectImpl for ColorDot {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![glib::ParamSpecEnum::new(
+                    "color",
+                    "Color",
+                    "Note color shown by the dot",
+                    NoteColor::static_type(),
+                    NoteColor::default() as i32,
+                    glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                )]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "color" => {
+                    let color = value.get().unwrap();
+                    obj.set_color(color);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "color" => obj.color().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_valign(gtk::Align::Center);
+            obj.set_visible(false);
+        }
+    }
+
+    impl WidgetImpl for ColorDot {
+        fn measure(
+            &self,
+            _obj: &Self::Type,
+            _orientation: gtk::Orientation,
+            _for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            (SIZE, SIZE, -1, -1)
+        }
+
+        fn snapshot(&self, obj: &Self::Type, snapshot: &gtk::Snapshot) {
+            obj.on_snapshot(snapshot);
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ColorDot(ObjectSubclass<imp::ColorDot>)
+        @extends gtk::Widget;
+}
+
+impl ColorDot {
+    pub fn new(color: NoteColor) -> Self {
+        glib::Object::new(&[("color", &color)]).expect("Failed to create ColorDot")
+    }
+
+    /// The dot is hidden for [`NoteColor::None`]
+    pub fn set_color(&self, color: NoteColor) {
+        if color == self.color() {
+            return;
+        }
+
+        // Drawn with the color of the palette for the class
+        self.remove_css_class(self.color().name());
+        self.add_css_class(color.name());
+
+        self.imp().color.set(color);
+        self.set_visible(!color.is_none());
+        self.queue_draw();
+        self.notify("color");
+    }
+
+    pub fn color(&self) -> NoteColor {
+        self.imp().color.get()
+    }
+
+    fn on_snapshot(&self, snapshot: &gtk::Snapshot) {
+        if self.color().is_none() {
+            return;
+        }
+
+        let rgba = self.style_context().color();
+
+        let width = self.width() as f32;
+        let height = self.height() as f32;
+        let size = width.min(height);
+        let rect = graphene::Rect::new((width - size) / 2.0, (height - size) / 2.0, size, size);
+
+        snapshot.push_rounded_clip(&gsk::RoundedRect::from_rect(rect, size / 2.0));
+        snapshot.append_color(&rgba, &rect);
+        snapshot.pop();
+    }
+}
@@ -1,9 +1,13 @@
 mod audio_visualizer;
 mod camera;
+mod color_dot;
 mod scrollable_picture;
+mod swipe_action_row;
 mod time_label;
+mod width_clamp;
 
 pub use self::{
-    audio_visualizer::AudioVisualizer, camera::Camera, scrollable_picture::ScrollablePicture,
-    time_label::TimeLabel,
+    audio_visualizer::AudioVisualizer, camera::Camera, color_dot::ColorDot,
+    scrollable_picture::ScrollablePicture, swipe_action_row::SwipeActionRow, time_label::TimeLabel,
+    width_clamp::WidthClamp,
 };
@@ -0,0 +1,562 @@
+use gtk::{
+    glib::{self, clone},
+    graphene, gsk,
+    prelude::*,
+    subclass::prelude::*,
+};
+
+use std::cell::{Cell, RefCell};
+
+/// Distance the pointer has to move before it is decided whether the drag is a swipe
+const INTENT_DISTANCE: f64 = 12.0;
+/// A drag is only a swipe if it moved this many times more horizontally than vertically,
+/// so scrolling the list is not taken over
+const HORIZONTAL_SLOPE: f64 = 2.0;
+/// Fraction of the width the content has to be swiped to activate an action
+const THRESHOLD_FRACTION: f64 = 0.3;
+/// How much the content still follows the pointer past the threshold
+const ELASTICITY: f64 = 0.25;
+/// Time the content takes to slide back, in microseconds
+const RESET_DURATION: f64 = 200_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SwipeIntent {
+    Undecided,
+    Horizontal,
+    Vertical,
+}
+
+impl Default for SwipeIntent {
+    fn default() -> Self {
+        Self::Undecided
+    }
+}
+
+impl SwipeIntent {
+    fn for_offset(offset_x: f64, offset_y: f64) -> Self {
+        if offset_x.hypot(offset_y) < INTENT_DISTANCE {
+            Self::Undecided
+        } else if offset_x.abs() > offset_y.abs() * HORIZONTAL_SLOPE {
+            Self::Horizontal
+        } else {
+            Self::Vertical
+        }
+    }
+}
+
+/// Follow the pointer exactly up to `threshold`, and with resistance past it
+fn elastic_offset(offset: f64, threshold: f64) -> f64 {
+    if offset.abs() <= threshold {
+        offset
+    } else {
+        offset.signum() * (threshold + (offset.abs() - threshold) * ELASTICITY)
+    }
+}
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use once_cell::{sync::Lazy, unsync::OnceCell};
+
+    #[derive(Debug, Default)]
+    pub struct SwipeActionRow {
+        pub child: RefCell<Option<gtk::Widget>>,
+        pub right_background: OnceCell<gtk::Box>,
+        pub right_icon: OnceCell<gtk::Image>,
+        pub left_background: OnceCell<gtk::Box>,
+        pub left_icon: OnceCell<gtk::Image>,
+
+        pub offset: Cell<f64>,
+        pub intent: Cell<SwipeIntent>,
+        pub reset_generation: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SwipeActionRow {
+        const NAME: &'static str = "NwtySwipeActionRow";
+        type Type = super::SwipeActionRow;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("swipeactionrow");
+        }
+    }
+
+    impl ObjectImpl for SwipeActionRow {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("swipe-left-action", &[], <()>::static_type().into()).build(),
+                    Signal::builder("swipe-right-action", &[], <()>::static_type().into()).build(),
+                ]
+            });
+            SIGNALS.as_ref()
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecObject::new(
+                        "child",
+                        "Child",
+                        "Content that is swiped",
+                        gtk::Widget::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "swipe-left-icon-name",
+                        "Swipe Left Icon Name",
+                        "Icon shown when swiping left, or none to not allow it",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecString::new(
+                        "swipe-right-icon-name",
+                        "Swipe Right Icon Name",
+                        "Icon shown when swiping right, or none to not allow it",
+                        None,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "child" => {
+                    let child: Option<gtk::Widget> = value.get().unwrap();
+                    obj.set_child(child.as_ref());
+                }
+                "swipe-left-icon-name" => {
+                    let icon_name: Option<String> = value.get().unwrap();
+                    obj.set_swipe_left_icon_name(icon_name.as_deref());
+                }
+                "swipe-right-icon-name" => {
+                    let icon_name: Option<String> = value.get().unwrap();
+                    obj.set_swipe_right_icon_name(icon_name.as_deref());
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "child" => obj.child().to_value(),
+                "swipe-left-icon-name" => obj.swipe_left_icon_name().to_value(),
+                "swipe-right-icon-name" => obj.swipe_right_icon_name().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_overflow(gtk::Overflow::Hidden);
+
+            let (right_background, right_icon) = obj.create_background("right", gtk::Align::Start);
+            self.right_background.set(right_background).unwrap();
+            self.right_icon.set(right_icon).unwrap();
+
+            let (left_background, left_icon) = obj.create_background("left", gtk::Align::End);
+            self.left_background.set(left_background).unwrap();
+            self.left_icon.set(left_icon).unwrap();
+
+            obj.setup_gesture();
+        }
+
+        fn dispose(&self, obj: &Self::Type) {
+            // Stop a running reset animation
+            self.reset_generation
+                .set(self.reset_generation.get().wrapping_add(1));
+
+            while let Some(child) = obj.first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for SwipeActionRow {
+        fn measure(
+            &self,
+            obj: &Self::Type,
+            orientation: gtk::Orientation,
+            for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            obj.on_measure(orientation, for_size)
+        }
+
+        fn size_allocate(&self, obj: &Self::Type, width: i32, height: i32, baseline: i32) {
+            obj.on_size_allocate(width, height, baseline);
+        }
+
+        fn snapshot(&self, obj: &Self::Type, snapshot: &gtk::Snapshot) {
+            obj.on_snapshot(snapshot);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Wraps a child that can be swiped left or right on touch devices to activate an action.
+    /// The background behind the child shows the icon of the action.
+    pub struct SwipeActionRow(ObjectSubclass<imp::SwipeActionRow>)
+        @extends gtk::Widget;
+}
+
+impl SwipeActionRow {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create SwipeActionRow.")
+    }
+
+    pub fn child(&self) -> Option<gtk::Widget> {
+        self.imp().child.borrow().clone()
+    }
+
+    pub fn set_child(&self, child: Option<&impl IsA<gtk::Widget>>) {
+        let child = child.map(|child| child.as_ref().clone());
+
+        if self.child() == child {
+            return;
+        }
+
+        if let Some(old_child) = self.imp().child.replace(child.clone()) {
+            old_child.unparent();
+        }
+
+        if let Some(ref child) = child {
+            // Always on top of the backgrounds
+            child.insert_before(self, gtk::Widget::NONE);
+        }
+
+        self.notify("child");
+    }
+
+    pub fn swipe_left_icon_name(&self) -> Option<String> {
+        Self::icon_name(self.imp().left_icon.get().unwrap())
+    }
+
+    pub fn set_swipe_left_icon_name(&self, icon_name: Option<&str>) {
+        if self.swipe_left_icon_name().as_deref() == icon_name {
+            return;
+        }
+
+        self.imp().left_icon.get().unwrap().set_icon_name(icon_name);
+        self.notify("swipe-left-icon-name");
+    }
+
+    pub fn swipe_right_icon_name(&self) -> Option<String> {
+        Self::icon_name(self.imp().right_icon.get().unwrap())
+    }
+
+    pub fn set_swipe_right_icon_name(&self, icon_name: Option<&str>) {
+        if self.swipe_right_icon_name().as_deref() == icon_name {
+            return;
+        }
+
+        self.imp()
+            .right_icon
+            .get()
+            .unwrap()
+            .set_icon_name(icon_name);
+        self.notify("swipe-right-icon-name");
+    }
+
+    pub fn connect_swipe_left_action<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("swipe-left-action", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    pub fn connect_swipe_right_action<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self) + 'static,
+    {
+        self.connect_local("swipe-right-action", true, move |values| {
+            let obj = values[0].get::<Self>().unwrap();
+            f(&obj);
+            None
+        })
+    }
+
+    fn icon_name(icon: &gtk::Image) -> Option<String> {
+        icon.icon_name()
+            .map(|icon_name| icon_name.to_string())
+            .filter(|icon_name| !icon_name.is_empty())
+    }
+
+    fn create_background(
+        &self,
+        css_class: &str,
+        icon_halign: gtk::Align,
+    ) -> (gtk::Box, gtk::Image) {
+        let icon = gtk::Image::new();
+        icon.set_hexpand(true);
+        icon.set_halign(icon_halign);
+
+        let background = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        background.add_css_class("swipe-background");
+        background.add_css_class(css_class);
+        background.append(&icon);
+        background.set_visible(false);
+        // Always below the child
+        background.insert_after(self, gtk::Widget::NONE);
+
+        (background, icon)
+    }
+
+    fn threshold(&self) -> f64 {
+        self.width() as f64 * THRESHOLD_FRACTION
+    }
+
+    fn set_offset(&self, offset: f64) {
+        let imp = self.imp();
+        imp.offset.set(offset);
+
+        let threshold = self.threshold();
+        let right_background = imp.right_background.get().unwrap();
+        let left_background = imp.left_background.get().unwrap();
+
+        right_background.set_visible(offset > 0.0);
+        left_background.set_visible(offset < 0.0);
+
+        for background in [right_background, left_background] {
+            if offset.abs() >= threshold {
+                background.add_css_class("armed");
+            } else {
+                background.remove_css_class("armed");
+            }
+        }
+
+        self.queue_allocate();
+    }
+
+    /// Content offset for a drag of `offset_x`, limited to the directions with an action
+    fn offset_for_drag(&self, offset_x: f64) -> f64 {
+        let is_allowed = if offset_x > 0.0 {
+            self.swipe_right_icon_name().is_some()
+        } else {
+            self.swipe_left_icon_name().is_some()
+        };
+
+        if is_allowed {
+            elastic_offset(offset_x, self.threshold())
+        } else {
+            0.0
+        }
+    }
+
+    fn slide_back(&self) {
+        let imp = self.imp();
+
+        let generation = imp.reset_generation.get().wrapping_add(1);
+        imp.reset_generation.set(generation);
+
+        let start_offset = imp.offset.get();
+        let start_time = Cell::new(None);
+
+        self.add_tick_callback(move |obj, frame_clock| {
+            let imp = obj.imp();
+
+            if imp.reset_generation.get() != generation {
+                return glib::Continue(false);
+            }
+
+            let now = frame_clock.frame_time();
+            let start = start_time.get().unwrap_or(now);
+            start_time.set(Some(start));
+
+            let progress = ((now - start) as f64 / RESET_DURATION).min(1.0);
+            let ease_out = 1.0 - (1.0 - progress).powi(3);
+            obj.set_offset(start_offset * (1.0 - ease_out));
+
+            glib::Continue(progress < 1.0)
+        });
+    }
+
+    fn on_drag_end(&self) {
+        let imp = self.imp();
+
+        if imp.intent.replace(SwipeIntent::Undecided) != SwipeIntent::Horizontal {
+            return;
+        }
+
+        let offset = imp.offset.get();
+        let threshold = self.threshold();
+
+        if offset >= threshold {
+            self.emit_by_name::<()>("swipe-right-action", &[]);
+        } else if offset <= -threshold {
+            self.emit_by_name::<()>("swipe-left-action", &[]);
+        }
+
+        self.slide_back();
+    }
+
+    fn setup_gesture(&self) {
+        let gesture_drag = gtk::GestureDrag::new();
+        // Keyboard and pointer users have the context menu instead
+        gesture_drag.set_touch_only(true);
+
+        gesture_drag.connect_drag_begin(clone!(@weak self as obj => move |_, _, _| {
+            let imp = obj.imp();
+            imp.intent.set(SwipeIntent::Undecided);
+            // Take over from the animation of the previous swipe
+            imp.reset_generation.set(imp.reset_generation.get().wrapping_add(1));
+        }));
+        gesture_drag.connect_drag_update(
+            clone!(@weak self as obj => move |gesture, offset_x, offset_y| {
+                let imp = obj.imp();
+
+                match imp.intent.get() {
+                    SwipeIntent::Horizontal => (),
+                    SwipeIntent::Vertical => return,
+                    SwipeIntent::Undecided => {
+                        let intent = SwipeIntent::for_offset(offset_x, offset_y);
+                        imp.intent.set(intent);
+
+                        match intent {
+                            SwipeIntent::Undecided => return,
+                            SwipeIntent::Vertical => {
+                                gesture.set_state(gtk::EventSequenceState::Denied);
+                                return;
+                            }
+                            SwipeIntent::Horizontal => {
+                                gesture.set_state(gtk::EventSequenceState::Claimed);
+                            }
+                        }
+                    }
+                }
+
+                obj.set_offset(obj.offset_for_drag(offset_x));
+            }),
+        );
+        gesture_drag.connect_drag_end(clone!(@weak self as obj => move |_, _, _| {
+            obj.on_drag_end();
+        }));
+        gesture_drag.connect_cancel(clone!(@weak self as obj => move |_, _| {
+            obj.imp().intent.set(SwipeIntent::Undecided);
+            obj.slide_back();
+        }));
+        self.add_controller(&gesture_drag);
+    }
+
+    fn on_measure(&self, orientation: gtk::Orientation, for_size: i32) -> (i32, i32, i32, i32) {
+        let mut minimum = 0;
+        let mut natural = 0;
+
+        let mut child = self.first_child();
+        while let Some(widget) = child {
+            if widget.should_layout() {
+                let (child_minimum, child_natural, _, _) = widget.measure(orientation, for_size);
+                minimum = minimum.max(child_minimum);
+                natural = natural.max(child_natural);
+            }
+            child = widget.next_sibling();
+        }
+
+        (minimum, natural, -1, -1)
+    }
+
+    fn on_size_allocate(&self, width: i32, height: i32, baseline: i32) {
+        let imp = self.imp();
+
+        for background in [
+            imp.right_background.get().unwrap(),
+            imp.left_background.get().unwrap(),
+        ] {
+            if background.should_layout() {
+                background.allocate(width, height, baseline, None);
+            }
+        }
+
+        if let Some(child) = self.child() {
+            let transform = gsk::Transform::new()
+                .translate(&graphene::Point::new(imp.offset.get() as f32, 0.0));
+            child.allocate(width, height, baseline, transform.as_ref());
+        }
+    }
+
+    fn on_snapshot(&self, snapshot: &gtk::Snapshot) {
+        let imp = self.imp();
+        let width = self.width() as f32;
+        let height = self.height() as f32;
+        let offset = imp.offset.get() as f32;
+
+        // Only show the part of the background the child has slid away from, as the child
+        // itself may be transparent
+        let (background, revealed) = if offset > 0.0 {
+            (
+                imp.right_background.get().unwrap(),
+                graphene::Rect::new(0.0, 0.0, offset, height),
+            )
+        } else {
+            (
+                imp.left_background.get().unwrap(),
+                graphene::Rect::new(width + offset, 0.0, -offset, height),
+            )
+        };
+
+        if background.should_layout() {
+            snapshot.push_clip(&revealed);
+            self.snapshot_child(background, snapshot);
+            snapshot.pop();
+        }
+
+        if let Some(child) = self.child() {
+            self.snapshot_child(&child, snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intent_undecided_when_barely_moved() {
+        assert_eq!(SwipeIntent::for_offset(0.0, 0.0), SwipeIntent::Undecided);
+        assert_eq!(SwipeIntent::for_offset(5.0, -5.0), SwipeIntent::Undecided);
+    }
+
+    #[test]
+    fn intent_horizontal() {
+        assert_eq!(SwipeIntent::for_offset(30.0, 2.0), SwipeIntent::Horizontal);
+        assert_eq!(
+            SwipeIntent::for_offset(-30.0, 10.0),
+            SwipeIntent::Horizontal
+        );
+    }
+
+    #[test]
+    fn intent_vertical() {
+        assert_eq!(SwipeIntent::for_offset(0.0, 30.0), SwipeIntent::Vertical);
+        // Diagonal drags are left for scrolling
+        assert_eq!(SwipeIntent::for_offset(20.0, 20.0), SwipeIntent::Vertical);
+        assert_eq!(SwipeIntent::for_offset(-20.0, -15.0), SwipeIntent::Vertical);
+    }
+
+    #[test]
+    fn elastic_offset_below_threshold() {
+        assert_eq!(elastic_offset(0.0, 100.0), 0.0);
+        assert_eq!(elastic_offset(40.0, 100.0), 40.0);
+        assert_eq!(elastic_offset(-100.0, 100.0), -100.0);
+    }
+
+    #[test]
+    fn elastic_offset_past_threshold() {
+        assert_eq!(elastic_offset(200.0, 100.0), 125.0);
+        assert_eq!(elastic_offset(-200.0, 100.0), -125.0);
+        assert!(elastic_offset(300.0, 100.0) > elastic_offset(200.0, 100.0));
+    }
+}
@@ -0,0 +1,239 @@
+use gtk::{glib, graphene, gsk, pango, prelude::*, subclass::prelude::*};
+
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+    use once_cell::sync::Lazy;
+
+    #[derive(Debug, Default)]
+    pub struct WidthClamp {
+        pub child: RefCell<Option<gtk::Widget>>,
+        pub max_width_chars: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WidthClamp {
+        const NAME: &'static str = "NwtyWidthClamp";
+        type Type = super::WidthClamp;
+        type ParentType = gtk::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_css_name("widthclamp");
+        }
+    }
+
+    impl ObjectImpl for WidthClamp {
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+                vec![
+                    glib::ParamSpecObject::new(
+                        "child",
+                        "Child",
+                        "Widget whose width is limited",
+                        gtk::Widget::static_type(),
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                    glib::ParamSpecUInt::new(
+                        "max-width-chars",
+                        "Max Width Chars",
+                        "Maximum width of the child in characters, or 0 to not limit it",
+                        0,
+                        u32::MAX,
+                        0,
+                        glib::ParamFlags::READWRITE | glib::ParamFlags::EXPLICIT_NOTIFY,
+                    ),
+                ]
+            });
+            PROPERTIES.as_ref()
+        }
+
+        fn set_property(
+            &self,
+            obj: &Self::Type,
+            _id: usize,
+            value: &glib::Value,
+            pspec: &glib::ParamSpec,
+        ) {
+            match pspec.name() {
+                "child" => {
+                    let child: Option<gtk::Widget> = value.get().unwrap();
+                    obj.set_child(child.as_ref());
+                }
+                "max-width-chars" => {
+                    let max_width_chars = value.get().unwrap();
+                    obj.set_max_width_chars(max_width_chars);
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, obj: &Self::Type, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            match pspec.name() {
+                "child" => obj.child().to_value(),
+                "max-width-chars" => obj.max_width_chars().to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
+        fn dispose(&self, _obj: &Self::Type) {
+            if let Some(child) = self.child.take() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for WidthClamp {
+        fn measure(
+            &self,
+            obj: &Self::Type,
+            orientation: gtk::Orientation,
+            for_size: i32,
+        ) -> (i32, i32, i32, i32) {
+            let child = match obj.child() {
+                Some(child) => child,
+                None => return (0, 0, -1, -1),
+            };
+
+            if orientation == gtk::Orientation::Horizontal {
+                let (minimum, natural, _, _) = child.measure(orientation, for_size);
+                let natural = obj
+                    .max_width()
+                    .map_or(natural, |max_width| natural.min(max_width).max(minimum));
+                return (minimum, natural, -1, -1);
+            }
+
+            let child_for_size = if for_size < 0 {
+                for_size
+            } else {
+                obj.child_width(&child, for_size)
+            };
+            child.measure(orientation, child_for_size)
+        }
+
+        fn size_allocate(&self, obj: &Self::Type, width: i32, height: i32, baseline: i32) {
+            if let Some(child) = obj.child() {
+                let child_width = obj.child_width(&child, width);
+                let x = (width - child_width) / 2;
+                let transform =
+                    gsk::Transform::new().translate(&graphene::Point::new(x as f32, 0.0));
+                child.allocate(child_width, height, baseline, transform.as_ref());
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Centers its child, making it at most as wide as a number of characters of its font. The
+    /// width follows the font, so it stays the same number of characters when the text is
+    /// scaled.
+    pub struct WidthClamp(ObjectSubclass<imp::WidthClamp>)
+        @extends gtk::Widget;
+}
+
+impl WidthClamp {
+    pub fn child(&self) -> Option<gtk::Widget> {
+        self.imp().child.borrow().clone()
+    }
+
+    pub fn set_child(&self, child: Option<&impl IsA<gtk::Widget>>) {
+        let child = child.map(|child| child.as_ref().clone());
+
+        if self.child() == child {
+            return;
+        }
+
+        if let Some(old_child) = self.imp().child.replace(child.clone()) {
+            old_child.unparent();
+        }
+
+        if let Some(ref child) = child {
+            child.set_parent(self);
+        }
+
+        self.queue_resize();
+        self.notify("child");
+    }
+
+    pub fn max_width_chars(&self) -> u32 {
+        self.imp().max_width_chars.get()
+    }
+
+    pub fn set_max_width_chars(&self, max_width_chars: u32) {
+        if max_width_chars == self.max_width_chars() {
+            return;
+        }
+
+        self.imp().max_width_chars.set(max_width_chars);
+        self.queue_resize();
+        self.notify("max-width-chars");
+    }
+
+    /// Maximum width of the child in pixels, measured with the current font so it follows
+    /// changes to its size
+    fn max_width(&self) -> Option<i32> {
+        let max_width_chars = self.max_width_chars();
+
+        if max_width_chars == 0 {
+            return None;
+        }
+
+        let metrics = self.pango_context().metrics(None, None)?;
+        let mut max_width = chars_width(
+            max_width_chars,
+            metrics.approximate_char_width(),
+            metrics.approximate_digit_width(),
+        );
+
+        // The characters are of the text, which is inside the padding of the child
+        if let Some(child) = self.child() {
+            let padding = child.style_context().padding();
+            max_width += i32::from(padding.left()) + i32::from(padding.right());
+        }
+
+        Some(max_width)
+    }
+
+    fn child_width(&self, child: &gtk::Widget, width: i32) -> i32 {
+        match self.max_width() {
+            Some(max_width) => {
+                let (minimum, _, _, _) = child.measure(gtk::Orientation::Horizontal, -1);
+                width.min(max_width).max(minimum)
+            }
+            None => width,
+        }
+    }
+}
+
+/// Width in pixels of `n_chars` characters, from the widths of a character and a digit of the
+/// font in Pango units. The wider of them is used, like `GtkLabel:width-chars` does.
+fn chars_width(n_chars: u32, char_width: i32, digit_width: i32) -> i32 {
+    let char_width = i64::from(char_width.max(digit_width).max(0));
+    let width = i64::from(n_chars) * char_width;
+    let scale = i64::from(pango::SCALE);
+
+    // Rounded up, so the last character is never cut off
+    ((width + scale - 1) / scale).min(i64::from(i32::MAX)) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chars_widths() {
+        let scale = pango::SCALE;
+
+        assert_eq!(chars_width(80, 8 * scale, 7 * scale), 640);
+        assert_eq!(chars_width(80, 7 * scale, 8 * scale), 640);
+        assert_eq!(chars_width(0, 8 * scale, 8 * scale), 0);
+        // Partial pixels
+        assert_eq!(chars_width(3, 8 * scale + scale / 2, 0), 26);
+        assert_eq!(chars_width(2, 8 * scale + scale / 2, 0), 17);
+        assert_eq!(chars_width(1, 1, 0), 1);
+        // Bigger font, as when the text is scaled
+        assert_eq!(chars_width(80, 16 * scale, 14 * scale), 1280);
+        assert_eq!(chars_width(u32::MAX, i32::MAX, 0), i32::MAX);
+        assert_eq!(chars_width(80, -1, -1), 0);
+    }
+}
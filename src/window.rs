@@ -1,13 +1,32 @@
 use adw::subclass::prelude::*;
+use gettextrs::gettext;
 use gtk::{
-    gio,
+    gdk, gio,
     glib::{self, clone},
     prelude::*,
     subclass::prelude::*,
 };
 use once_cell::unsync::OnceCell;
 
-use crate::{config::PROFILE, session::Session, setup::Setup, spawn, utils, Application};
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use crate::{
+    config::PROFILE,
+    core::{CloneCancelled, NoteRepository, StartupMilestone},
+    model::{Note, NoteMetadata},
+    preparing_page::PreparingPage,
+    session::Session,
+    setup::Setup,
+    spawn,
+    utils::{self, DeferredQueue},
+    Application,
+};
+
+/// How long the window has to stay the same size before it is saved
+const SAVE_WINDOW_STATE_DELAY: Duration = Duration::from_millis(500);
 
 mod imp {
     use super::*;
@@ -16,14 +35,24 @@ mod imp {
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/io/github/seadve/Noteworthy/ui/window.ui")]
     pub struct Window {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
         pub main_stack: TemplateChild<gtk::Stack>,
         #[template_child]
         pub setup: TemplateChild<Setup>,
         #[template_child]
-        pub loading: TemplateChild<gtk::WindowHandle>,
+        pub preparing_page: TemplateChild<PreparingPage>,
 
         pub session: OnceCell<Session>,
+        pub session_queue: RefCell<DeferredQueue<Session>>,
+        pub save_window_state_source_id: RefCell<Option<glib::SourceId>>,
+        pub is_saving_before_close: Cell<bool>,
+        /// Whether the notes are saved, so the window can be closed
+        pub can_close: Cell<bool>,
+        pub is_cloning: Cell<bool>,
+        /// Whether the window was closed while cloning, so it is closed once that is cancelled
+        pub is_closing_after_clone: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -53,7 +82,17 @@ mod imp {
                 obj.add_css_class("devel");
             }
 
-            obj.load_window_size();
+            obj.load_window_state();
+
+            obj.connect_default_width_notify(|obj| obj.queue_save_window_state());
+            obj.connect_default_height_notify(|obj| obj.queue_save_window_state());
+            obj.connect_maximized_notify(|obj| obj.queue_save_window_state());
+            obj.connect_fullscreened_notify(|obj| obj.queue_save_window_state());
+
+            // The first frame, while the notes may still be loading
+            obj.connect_map(|_| {
+                Application::default().reach_startup_milestone(StartupMilestone::WindowMapped);
+            });
 
             self.setup
                 .connect_session_setup_done(clone!(@weak obj => move |_, session| {
@@ -64,12 +103,18 @@ mod imp {
                     });
                 }));
 
+            self.setup
+                .connect_clone_requested(clone!(@weak obj => move |_, remote_url| {
+                    spawn!(async move {
+                        obj.clone_notebook(remote_url).await;
+                    });
+                }));
+
             // If already setup
             if utils::default_notes_dir().exists() {
                 let notes_folder = gio::File::for_path(&utils::default_notes_dir());
                 spawn!(clone!(@weak obj => async move {
-                    // FIXME detect if it is offline mode or online
-                    let existing_session = Session::new_offline(&notes_folder).await;
+                    let existing_session = Session::for_existing(&notes_folder);
                     if let Err(err) = obj.load_session(existing_session).await {
                         log::error!("Failed to load session: {:?}", err);
                     }
@@ -82,18 +127,57 @@ mod imp {
 
     impl WindowImpl for Window {
         fn close_request(&self, obj: &Self::Type) -> gtk::Inhibit {
-            if let Err(err) = obj.save_window_size() {
+            if self.is_saving_before_close.get() {
+                return gtk::Inhibit(true);
+            }
+
+            // Stopped first, so no partly cloned notebook is left to be opened the next time
+            if self.is_cloning.get() {
+                self.is_closing_after_clone.set(true);
+                self.preparing_page.cancel();
+                return gtk::Inhibit(true);
+            }
+
+            if let Some(source_id) = self.save_window_state_source_id.take() {
+                source_id.remove();
+            }
+
+            if let Err(err) = obj.save_window_state() {
                 log::warn!("Failed to save window state: {:?}", &err);
             }
 
+            // The reminders and syncs of the session keep running while it is hidden
+            if Application::default().hides_main_window_on_close() {
+                obj.hide();
+
+                if let Some(session) = self.session.get() {
+                    spawn!(clone!(@weak session => async move {
+                        if let Err(err) = session.note_manager().save_all_notes().await {
+                            log::error!("Failed to save notes before hiding window: {:?}", err);
+                        }
+                    }));
+                }
+
+                return gtk::Inhibit(true);
+            }
+
             // TODO what if app crashed? so maybe implement autosync
-            if let Some(session) = self.session.get() {
-                let ctx = glib::MainContext::default();
-                ctx.block_on(async move {
-                    if let Err(err) = session.sync().await {
-                        log::error!("Failed to sync session: {:?}", err);
+            if let Some(session) = self.session.get().filter(|_| !self.can_close.get()) {
+                self.is_saving_before_close.set(true);
+
+                spawn!(clone!(@weak obj, @weak session => async move {
+                    let can_close = session.save_before_close().await;
+
+                    let imp = obj.imp();
+                    imp.is_saving_before_close.set(false);
+                    imp.can_close.set(can_close);
+
+                    if can_close {
+                        obj.close();
                     }
-                });
+                }));
+
+                return gtk::Inhibit(true);
             }
 
             self.parent_close_request(obj)
@@ -119,6 +203,20 @@ impl Window {
         self.imp().session.get().expect("Call load_session first")
     }
 
+    pub fn has_session(&self) -> bool {
+        self.imp().session.get().is_some()
+    }
+
+    /// Whether the notes of the session are loaded, which is when [`Window::run_with_session`]
+    /// runs right away
+    pub fn is_session_ready(&self) -> bool {
+        self.imp().session_queue.borrow().is_ready()
+    }
+
+    pub fn add_toast(&self, toast: &adw::Toast) {
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
     pub fn add_page(&self, page: &impl IsA<gtk::Widget>) {
         self.imp().main_stack.add_child(page);
     }
@@ -149,8 +247,147 @@ impl Window {
         self.set_visible_page(self.session());
     }
 
-    fn switch_to_loading_page(&self) {
-        self.set_visible_page(&self.imp().loading.get());
+    /// Run `f` once the session is loaded, or right away if it is already loaded
+    pub fn run_with_session(&self, f: impl FnOnce(&Session) + 'static) {
+        let ready_f = self.imp().session_queue.borrow_mut().push(Box::new(f));
+
+        if let Some(f) = ready_f {
+            f(self.session());
+        }
+    }
+
+    fn setup_title(&self, session: &Session) {
+        let note_expression =
+            gtk::ObjectExpression::new(session).chain_property::<Session>("selected-note");
+        let metadata_expression = note_expression.chain_property::<Note>("metadata");
+
+        note_expression.chain_property::<Note>("is-saved").watch(
+            None::<&glib::Object>,
+            clone!(@weak self as obj => move || obj.update_title()),
+        );
+        metadata_expression
+            .chain_property::<NoteMetadata>("title")
+            .watch(
+                None::<&glib::Object>,
+                clone!(@weak self as obj => move || obj.update_title()),
+            );
+        session.note_manager().connect_notify_local(
+            Some("pending-changes"),
+            clone!(@weak self as obj => move |_, _| obj.update_title()),
+        );
+        session.connect_notify_local(
+            Some("is-locked"),
+            clone!(@weak self as obj => move |_, _| obj.update_title()),
+        );
+
+        self.update_title();
+    }
+
+    /// Titled like a document editor after the selected note, or the notebook when there is
+    /// none, and marked while it has unsaved changes. Nothing is named while locked.
+    fn update_title(&self) {
+        let session = self.session();
+        let app_name = glib::application_name().unwrap_or_default();
+
+        let title = if session.is_locked() {
+            app_name.to_string()
+        } else if let Some(note) = session.selected_note() {
+            let note_title = note.metadata().title();
+            let note_title = if note_title.is_empty() {
+                gettext("Untitled Note")
+            } else {
+                note_title
+            };
+            window_title(&note_title, &app_name, !note.is_saved())
+        } else {
+            let note_manager = session.note_manager();
+            let notebook_name = note_manager
+                .directory()
+                .basename()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| app_name.to_string());
+            window_title(
+                &notebook_name,
+                &app_name,
+                note_manager.pending_changes() > 0,
+            )
+        };
+
+        self.set_title(Some(&title));
+    }
+
+    /// Clone the notebook at `remote_url`, showing how far it is until its notes are loaded,
+    /// or going back to the setup when it is cancelled or fails
+    async fn clone_notebook(&self, remote_url: String) {
+        let imp = self.imp();
+        let preparing_page = imp.preparing_page.get();
+
+        let cancellable = preparing_page.start_cloning();
+        self.set_is_preparing(true);
+        imp.is_cloning.set(true);
+
+        let notes_folder = gio::File::for_path(&utils::default_notes_dir());
+        let res = NoteRepository::clone(
+            remote_url,
+            &notes_folder,
+            clone!(@weak preparing_page => move |progress| {
+                preparing_page.set_progress(&progress);
+            }),
+            &cancellable,
+        )
+        .await;
+
+        imp.is_cloning.set(false);
+
+        if imp.is_closing_after_clone.get() {
+            self.close();
+            return;
+        }
+
+        let repository = match res {
+            Ok(repository) => repository,
+            Err(err) => {
+                if err.is::<CloneCancelled>() {
+                    log::info!("Cloning was cancelled, going back to setup");
+                } else {
+                    log::error!("Failed to clone notebook: {:?}", err);
+                    self.add_toast(&adw::Toast::new(&gettext(
+                        "Failed to download the notebook",
+                    )));
+                }
+
+                self.set_is_preparing(false);
+                self.set_visible_page(&imp.setup.get());
+                return;
+            }
+        };
+
+        if let Err(err) = Application::default()
+            .settings()
+            .set_boolean("is-offline-mode", false)
+        {
+            log::error!("Failed to set is-offline-mode setting: {:?}", err);
+        }
+
+        preparing_page.set_loading();
+        let session = Session::for_clone(&notes_folder, &repository);
+        if let Err(err) = self.load_session(session).await {
+            log::error!("Failed to load session: {:?}", err);
+        }
+    }
+
+    /// Show the page preparing the notebook, with the actions that need the notes disabled
+    fn set_is_preparing(&self, is_preparing: bool) {
+        let imp = self.imp();
+
+        if is_preparing {
+            self.set_visible_page(&imp.preparing_page.get());
+        } else {
+            imp.preparing_page.reset();
+        }
+
+        self.action_set_enabled("win.toggle-fullscreen", !is_preparing);
+        Application::default().set_session_actions_enabled(!is_preparing);
     }
 
     async fn load_session(&self, session: Session) -> anyhow::Result<()> {
@@ -159,16 +396,68 @@ impl Window {
         imp.session.set(session).unwrap();
 
         let session = self.session();
+        let is_preparing = imp.main_stack.visible_child().as_ref()
+            == Some(imp.preparing_page.upcast_ref::<gtk::Widget>());
+
+        // Shown while loading, with placeholders in place of the notes, unless the notebook
+        // is being prepared, which shows the loading itself
+        if is_preparing {
+            let res = session.load().await;
+            self.switch_to_session_page();
+            self.set_is_preparing(false);
+            res?;
+        } else {
+            self.switch_to_session_page();
+            session.load().await?;
+        }
+
+        self.setup_title(session);
+
+        let pending = imp.session_queue.borrow_mut().set_ready();
+        for f in pending {
+            f(session);
+        }
+
+        if !session.is_locked() {
+            session.show_recovered_notes();
+        }
+
+        let is_tour_done = Application::default().settings().boolean("is-tour-done");
+        if !is_tour_done && !session.is_locked() {
+            // Once the session page is shown, so the widgets it points at are mapped
+            glib::idle_add_local_once(clone!(@weak session => move || {
+                session.start_tour();
+            }));
+        }
 
-        self.switch_to_loading_page();
-        session.load().await?;
-        self.switch_to_session_page();
         session.sync().await?;
 
         Ok(())
     }
 
-    fn save_window_size(&self) -> Result<(), glib::BoolError> {
+    /// Save the window state once it stops changing, so resizing does not write the
+    /// settings on every frame
+    fn queue_save_window_state(&self) {
+        let imp = self.imp();
+
+        if let Some(source_id) = imp.save_window_state_source_id.take() {
+            source_id.remove();
+        }
+
+        let source_id = glib::timeout_add_local_once(
+            SAVE_WINDOW_STATE_DELAY,
+            clone!(@weak self as obj => move || {
+                obj.imp().save_window_state_source_id.replace(None);
+
+                if let Err(err) = obj.save_window_state() {
+                    log::warn!("Failed to save window state: {:?}", &err);
+                }
+            }),
+        );
+        imp.save_window_state_source_id.replace(Some(source_id));
+    }
+
+    fn save_window_state(&self) -> Result<(), glib::BoolError> {
         let settings = Application::default().settings();
 
         let (width, height) = self.default_size();
@@ -177,22 +466,31 @@ impl Window {
         settings.set_int("window-height", height)?;
 
         settings.set_boolean("is-maximized", self.is_maximized())?;
+        settings.set_boolean("is-fullscreened", self.is_fullscreened())?;
 
         Ok(())
     }
 
-    fn load_window_size(&self) {
+    fn load_window_state(&self) {
         let settings = Application::default().settings();
 
-        let width = settings.int("window-width");
-        let height = settings.int("window-height");
+        let size = (settings.int("window-width"), settings.int("window-height"));
+        let (width, height) = match monitor_size() {
+            Some(monitor_size) => clamp_size(size, monitor_size),
+            None => size,
+        };
         let is_maximized = settings.boolean("is-maximized");
+        let is_fullscreened = settings.boolean("is-fullscreened");
 
         self.set_default_size(width, height);
 
         if is_maximized {
             self.maximize();
         }
+
+        if is_fullscreened {
+            self.fullscreen();
+        }
     }
 
     fn on_toggle_fullscreen(&self) {
@@ -203,3 +501,67 @@ impl Window {
         }
     }
 }
+
+/// Size of the first monitor, where the window is most likely to be opened
+fn monitor_size() -> Option<(i32, i32)> {
+    let monitor = gdk::Display::default()?
+        .monitors()
+        .item(0)?
+        .downcast::<gdk::Monitor>()
+        .ok()?;
+    let geometry = monitor.geometry();
+    Some((geometry.width(), geometry.height()))
+}
+
+fn window_title(title: &str, app_name: &str, is_unsaved: bool) -> String {
+    let title = if title == app_name {
+        title.to_string()
+    } else {
+        format!("{} — {}", title, app_name)
+    };
+
+    if is_unsaved {
+        format!("• {}", title)
+    } else {
+        title
+    }
+}
+
+/// Shrink `size` to fit in `max_size`, so a size remembered from a larger monitor does
+/// not make the window bigger than the screen
+fn clamp_size((width, height): (i32, i32), (max_width, max_height): (i32, i32)) -> (i32, i32) {
+    (width.min(max_width), height.min(max_height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn window_titles() {
+        assert_eq!(
+            window_title("Groceries", "Noteworthy", false),
+            "Groceries — Noteworthy"
+        );
+        assert_eq!(
+            window_title("Groceries", "Noteworthy", true),
+            "• Groceries — Noteworthy"
+        );
+        // Nothing to name the notebook after
+        assert_eq!(
+            window_title("Noteworthy", "Noteworthy", true),
+            "• Noteworthy"
+        );
+    }
+
+    #[test]
+    fn clamp_size_smaller() {
+        assert_eq!(clamp_size((1000, 600), (1920, 1080)), (1000, 600));
+    }
+
+    #[test]
+    fn clamp_size_larger() {
+        assert_eq!(clamp_size((2560, 1440), (1920, 1080)), (1920, 1080));
+        assert_eq!(clamp_size((2560, 600), (1920, 1080)), (1920, 600));
+    }
+}